@@ -0,0 +1,190 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Benchmarks for the hot paths of the simulation. The full tick benchmark needs the original
+//! game data and a display, so it only runs when `CHARIOT_GAME_DIR` and `CHARIOT_BENCH_SCENARIO`
+//! (ideally a crowded four player map) are set, and is skipped otherwise.
+
+extern crate chariot;
+extern crate chariot_dat as dat;
+extern crate chariot_identifier as identifier;
+extern crate chariot_resource as resource;
+extern crate chariot_scn as scn;
+extern crate chariot_slp as slp;
+extern crate chariot_types as types;
+
+#[macro_use]
+extern crate criterion;
+extern crate nalgebra;
+
+use chariot::config::{CONFIG_FILE_NAME, ConfigLoader, ConfigValue};
+use chariot::ecs::resource::{GameSettings, OccupiedTiles, PassabilityProvider, PathFinder, Terrain, Tile};
+use chariot::game::{Game, GameState, ScenarioGameState};
+use chariot::partition::GridPartition;
+use criterion::Criterion;
+use identifier::{TerrainId, UnitTerrainRestrictionId};
+use nalgebra::Vector2;
+
+use std::env;
+use std::io;
+use types::{Fixed, Vector3};
+
+const GRID_ENTITY_COUNT: u32 = 10000;
+const GRID_WORLD_SIZE: i32 = 8000;
+const GRID_CELL_SIZE: i32 = 64;
+const PATH_MAP_SIZE: i32 = 256;
+
+/// Cheap deterministic positions so runs are comparable
+fn scatter(index: u32, size: i32) -> Vector2<i32> {
+    let hash = index.wrapping_mul(2654435761);
+    Vector2::new((hash % size as u32) as i32, ((hash >> 16) % size as u32) as i32)
+}
+
+fn bench_grid_partition(c: &mut Criterion) {
+    let mut grid = GridPartition::new(GRID_CELL_SIZE, GRID_CELL_SIZE);
+    for entity_id in 0..GRID_ENTITY_COUNT {
+        grid.update_entity(entity_id, &scatter(entity_id, GRID_WORLD_SIZE));
+    }
+
+    let mut frame = 0u32;
+    c.bench_function("grid_partition_update_10k", move |b| {
+        b.iter(|| {
+            frame = frame.wrapping_add(1);
+            for entity_id in 0..GRID_ENTITY_COUNT {
+                let position = scatter(entity_id.wrapping_add(frame), GRID_WORLD_SIZE);
+                grid.update_entity(entity_id, &position);
+            }
+        })
+    });
+
+    let mut grid = GridPartition::new(GRID_CELL_SIZE, GRID_CELL_SIZE);
+    for entity_id in 0..GRID_ENTITY_COUNT {
+        grid.update_entity(entity_id, &scatter(entity_id, GRID_WORLD_SIZE));
+    }
+    c.bench_function("grid_partition_query_10k", move |b| {
+        b.iter(|| {
+            let mut found = 0;
+            for index in 0..100 {
+                let start = scatter(index, GRID_WORLD_SIZE);
+                let end = start + Vector2::new(256, 256);
+                found += grid.query(&start, &end).len();
+            }
+            found
+        })
+    });
+}
+
+/// Terrain 0 is impassable; everything else can be walked on
+struct BenchPassability;
+
+impl PassabilityProvider for BenchPassability {
+    fn passable(&self, _restriction_id: UnitTerrainRestrictionId, terrain_id: TerrainId) -> bool {
+        *terrain_id != 0
+    }
+}
+
+/// Rows of walls with a single gap at alternating ends, so paths have to snake across the map
+fn maze_terrain() -> Terrain {
+    let mut tiles = Vec::new();
+    for row in 0..PATH_MAP_SIZE {
+        for col in 0..PATH_MAP_SIZE {
+            let gap = if (row / 16) % 2 == 0 { col >= PATH_MAP_SIZE - 4 } else { col <= 3 };
+            let terrain_id: usize = if row % 16 == 8 && !gap { 0 } else { 1 };
+            tiles.push(Tile::new(terrain_id.into(), 0));
+        }
+    }
+    Terrain::new(PATH_MAP_SIZE,
+                 PATH_MAP_SIZE,
+                 tiles,
+                 dat::EmpiresDbRef::new(dat::EmpiresDb::new()))
+}
+
+fn bench_path_finding(c: &mut Criterion) {
+    let terrain = maze_terrain();
+    let path_finder = PathFinder::new_with(Box::new(BenchPassability));
+    let occupied_tiles = OccupiedTiles::new();
+    let from = Vector3::new(1.into(), 1.into(), 0.into());
+    let to = Vector3::new((PATH_MAP_SIZE - 2).into(), (PATH_MAP_SIZE - 2).into(), 0.into());
+
+    c.bench_function("path_finder_256x256_maze", move |b| {
+        b.iter(|| {
+            path_finder.find_path(&terrain,
+                                  &occupied_tiles,
+                                  &from,
+                                  &to,
+                                  UnitTerrainRestrictionId::GroundWildlife)
+        })
+    });
+}
+
+fn bench_slp_decoding(c: &mut Criterion) {
+    // A unit-sized SLP with a mix of transparency, solid runs, and player colors in every frame
+    let mut encoder = resource::SlpEncoder::new();
+    for frame in 0..50u32 {
+        let (width, height) = (64u32, 64u32);
+        let pixels: Vec<u8> = (0..(width * height))
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let distance = (x as i32 - 32).abs() + (y as i32 - 32).abs();
+                if distance > 28 {
+                    0
+                } else if (x + frame) % 8 == 0 {
+                    16 + (y % 8) as u8
+                } else {
+                    (40 + distance) as u8
+                }
+            })
+            .collect();
+        encoder.add_frame(resource::SlpFrame::from_indexed(width, height, 32, 48, &pixels));
+    }
+    let mut slp_bytes = Vec::new();
+    encoder.write_to(&mut slp_bytes).unwrap();
+
+    c.bench_function("slp_decode_50_frames", move |b| {
+        b.iter(|| slp::SlpFile::read_from(&mut io::Cursor::new(&slp_bytes[..]), 1).unwrap())
+    });
+}
+
+fn bench_full_tick(c: &mut Criterion) {
+    let (game_dir, scenario_file) = match (env::var("CHARIOT_GAME_DIR"), env::var("CHARIOT_BENCH_SCENARIO")) {
+        (Ok(game_dir), Ok(scenario_file)) => (game_dir, scenario_file),
+        _ => {
+            println!("Skipping full_tick: set CHARIOT_GAME_DIR and CHARIOT_BENCH_SCENARIO to run it");
+            return;
+        }
+    };
+
+    let scenario = scn::Scenario::read_from_file(&scenario_file).unwrap();
+    let mut config_loader = ConfigLoader::new(CONFIG_FILE_NAME);
+    config_loader.set_cli_override("data_dir", ConfigValue::String(game_dir));
+    let game = Game::new(config_loader).unwrap();
+    let mut state = ScenarioGameState::new(&game, scenario, GameSettings::new(), None);
+    let time_step = Fixed::from(1) / Fixed::from(60);
+
+    c.bench_function("full_tick", move |b| b.iter(|| state.update(time_step)));
+}
+
+criterion_group!(benches,
+                 bench_grid_partition,
+                 bench_path_finding,
+                 bench_slp_decoding,
+                 bench_full_tick);
+criterion_main!(benches);
@@ -81,6 +81,17 @@ pub enum ResearchEffectValue {
     MultiplyBy(f32),
 }
 
+impl ResearchEffectValue {
+    /// The given value once the effect has been applied to it
+    pub fn apply(&self, value: f32) -> f32 {
+        match *self {
+            ResearchEffectValue::SetTo(amount) => amount,
+            ResearchEffectValue::Add(amount) => value + amount,
+            ResearchEffectValue::MultiplyBy(amount) => value * amount,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ResearchEffect {
     UnitAttribute {
@@ -20,13 +20,15 @@
 // SOFTWARE.
 //
 
+use empires::age::{ResearchEffect, ResearchEffectValue};
+use empires::research::{Research, ResearchCost};
 use empires::resource::ResourceType;
 use empires::unit::{Unit, read_unit};
 use error::*;
 
 use identifier::*;
 use chariot_io_tools::*;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use std::io::prelude::*;
 
@@ -70,14 +72,156 @@ pub struct Civilization {
     icon_set: i8,
 
     units: HashMap<UnitId, Unit>,
+
+    /// Units and research that the civ's tech tree leaves out
+    disabled_units: HashSet<UnitId>,
+    disabled_research: HashSet<ResearchId>,
+
+    /// Research that costs this civ something other than what the research table says
+    research_costs: HashMap<ResearchId, Vec<ResearchCost>>,
 }
 
 impl Civilization {
+    #[inline]
+    pub fn id(&self) -> CivilizationId {
+        self.id
+    }
+
+    #[inline]
+    pub fn name<'a>(&'a self) -> &'a str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Determines which user interface theme to use
+    /// 0 => Egyption interface, 1 => Greek, 2 => Babylonian, 3 => Asiatic, 4 => Roman
+    #[inline]
+    pub fn icon_set(&self) -> i8 {
+        self.icon_set
+    }
+
+    /// All of the units available to this civ, ordered by ID
+    pub fn units<'a>(&'a self) -> Vec<&'a Unit> {
+        let mut units: Vec<&Unit> = self.units.values().collect();
+        units.sort_by_key(|unit| unit.id);
+        units
+    }
+
     /// Retrieve unit data by ID
     #[inline]
     pub fn unit<'a>(&'a self, unit_id: UnitId) -> &'a Unit {
         return &self.units[&unit_id];
     }
+
+    /// Whether unit data exists for the given ID
+    #[inline]
+    pub fn has_unit(&self, unit_id: UnitId) -> bool {
+        self.units.contains_key(&unit_id)
+    }
+
+    #[inline]
+    pub fn unit_mut<'a>(&'a mut self, unit_id: UnitId) -> Option<&'a mut Unit> {
+        self.units.get_mut(&unit_id)
+    }
+
+    /// Adds a unit, replacing any unit with the same ID
+    pub fn add_unit(&mut self, unit: Unit) {
+        self.units.insert(unit.id, unit);
+    }
+
+    /// Effect group that applies the civ's bonuses to its units at the start of a game
+    #[inline]
+    pub fn bonus_effects_id(&self) -> Option<AgeId> {
+        self.starting_values.age_id
+    }
+
+    /// Fraction of a tribute that is lost to the market tax when this civ sends it
+    #[inline]
+    pub fn tribute_penalty(&self) -> f32 {
+        self.starting_values.tribute_penalty
+    }
+
+    /// Whether the civ has the unit and its tech tree lets it be trained or built
+    pub fn unit_available(&self, unit_id: UnitId) -> bool {
+        self.has_unit(unit_id) && !self.disabled_units.contains(&unit_id)
+    }
+
+    /// Whether the civ's tech tree lets it research the tech
+    pub fn research_available(&self, research_id: ResearchId) -> bool {
+        !self.disabled_research.contains(&research_id)
+    }
+
+    /// What the research costs this civ, after its bonuses
+    pub fn research_costs<'a>(&'a self, research: &'a Research) -> &'a [ResearchCost] {
+        match self.research_costs.get(&research.id) {
+            Some(costs) => costs,
+            None => &research.resource_costs,
+        }
+    }
+
+    /// Applies the civ's bonuses from its effect group: unit stat changes, cost discounts,
+    /// starting value changes, and the units and research its tech tree leaves out. Effects
+    /// that only make sense once a game is under way, like upgrades, are skipped.
+    pub fn apply_bonus_effects(&mut self, effects: &[ResearchEffect], research: &[Research]) {
+        for effect in effects {
+            match *effect {
+                ResearchEffect::UnitAttribute { target_unit_id,
+                                                target_unit_class_id,
+                                                ref attribute_id,
+                                                ref effect } => {
+                    for unit in self.units.values_mut() {
+                        let targeted = match (target_unit_id, target_unit_class_id) {
+                            (Some(unit_id), _) => unit.id == unit_id,
+                            (None, Some(class_id)) => unit.is_in_class(class_id),
+                            (None, None) => false,
+                        };
+                        if targeted {
+                            unit.apply_attribute_effect(attribute_id, effect);
+                        }
+                    }
+                }
+                ResearchEffect::CivHeader { target_civ_header_id, ref effect } => {
+                    self.apply_starting_value_effect(target_civ_header_id, effect)
+                }
+                ResearchEffect::SetUnitEnabled { target_unit_id: Some(unit_id), enabled } => {
+                    if enabled {
+                        self.disabled_units.remove(&unit_id);
+                    } else {
+                        self.disabled_units.insert(unit_id);
+                    }
+                }
+                ResearchEffect::ResearchCost { research_id, resource_type, ref effect } => {
+                    let base_costs = match research.get(*research_id as usize) {
+                        Some(entry) => entry.resource_costs.clone(),
+                        None => continue,
+                    };
+                    let costs = self.research_costs.entry(research_id).or_insert(base_costs);
+                    for cost in costs.iter_mut().filter(|cost| cost.resource_type == resource_type) {
+                        cost.amount = effect.apply(cost.amount as f32).round().max(0.) as i16;
+                    }
+                }
+                ResearchEffect::DisableResearch { research_id } => {
+                    self.disabled_research.insert(research_id);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn apply_starting_value_effect(&mut self, index: i16, effect: &ResearchEffectValue) {
+        let values = &mut self.starting_values;
+        match index {
+            10 => values.trade_productivity = effect.apply(values.trade_productivity),
+            36 => values.farm_food_capacity = effect.apply(values.farm_food_capacity),
+            46 => values.tribute_penalty = effect.apply(values.tribute_penalty),
+            47 => values.gold_mine_productivity = effect.apply(values.gold_mine_productivity),
+            _ => (),
+        }
+    }
 }
 
 pub fn read_civs<R: Read + Seek>(stream: &mut R) -> Result<Vec<Civilization>> {
@@ -131,3 +275,67 @@ fn read_civ<R: Read + Seek>(stream: &mut R) -> Result<Civilization> {
     }
     Ok(civ)
 }
+
+#[cfg(test)]
+mod tests {
+    use empires::age::{ResearchEffect, ResearchEffectValue, UnitAttributeId};
+    use empires::unit::Unit;
+    use super::Civilization;
+
+    fn test_civ() -> Civilization {
+        let mut civ = Civilization::default();
+        civ.add_unit(Unit::for_effect_tests(74.into(), 6, 45));
+        civ.add_unit(Unit::for_effect_tests(75.into(), 6, 60));
+        civ.add_unit(Unit::for_effect_tests(83.into(), 4, 25));
+        civ
+    }
+
+    #[test]
+    fn test_class_targeted_bonus() {
+        let mut civ = test_civ();
+        let effects = [ResearchEffect::UnitAttribute {
+                           target_unit_id: None,
+                           target_unit_class_id: Some(6.into()),
+                           attribute_id: UnitAttributeId::HitPoints,
+                           effect: ResearchEffectValue::MultiplyBy(1.2),
+                       }];
+        civ.apply_bonus_effects(&effects, &[]);
+        assert_eq!(54, civ.unit(74.into()).hit_points());
+        assert_eq!(72, civ.unit(75.into()).hit_points());
+        assert_eq!(25, civ.unit(83.into()).hit_points());
+    }
+
+    #[test]
+    fn test_unit_targeted_bonus() {
+        let mut civ = test_civ();
+        let effects = [ResearchEffect::UnitAttribute {
+                           target_unit_id: Some(75.into()),
+                           target_unit_class_id: None,
+                           attribute_id: UnitAttributeId::ArmorStrength,
+                           effect: ResearchEffectValue::Add((3 << 8 | 2) as f32),
+                       }];
+        civ.apply_bonus_effects(&effects, &[]);
+        assert_eq!(&[(4, 0), (3, 3)], civ.unit(75.into()).armors());
+        assert_eq!(&[(3, 1), (4, 0)], civ.unit(74.into()).armors());
+    }
+
+    #[test]
+    fn test_disabled_unit() {
+        let mut civ = test_civ();
+        let effects = [ResearchEffect::SetUnitEnabled {
+                           target_unit_id: Some(75.into()),
+                           enabled: false,
+                       }];
+        civ.apply_bonus_effects(&effects, &[]);
+        assert!(civ.has_unit(75.into()));
+        assert!(!civ.unit_available(75.into()));
+        assert!(civ.unit_available(74.into()));
+
+        let effects = [ResearchEffect::SetUnitEnabled {
+                           target_unit_id: Some(75.into()),
+                           enabled: true,
+                       }];
+        civ.apply_bonus_effects(&effects, &[]);
+        assert!(civ.unit_available(75.into()));
+    }
+}
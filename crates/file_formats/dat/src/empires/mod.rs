@@ -26,6 +26,7 @@ mod resource;
 mod age;
 mod civ;
 mod graphic;
+mod patch;
 mod player_color;
 mod random_map;
 mod research;
@@ -35,22 +36,30 @@ mod terrain_restrictions;
 mod unit;
 
 
-use empires::age::{ResearchEffectGroup, read_ages};
+pub use empires::age::{ResearchEffect, ResearchEffectGroup, ResearchEffectValue, UnitAttributeId};
+use empires::age::read_ages;
 pub use empires::civ::Civilization;
 use empires::civ::read_civs;
 pub use empires::graphic::Graphic;
+pub use empires::patch::{PatchValue, ResearchPatch, UnitPatch};
 use empires::graphic::read_graphics;
-use empires::player_color::{PlayerColor, read_player_colors};
-use empires::random_map::{RandomMap, read_random_maps};
-use empires::research::{Research, read_research};
-use empires::sound::{SoundEffectGroup, read_sound_effect_groups};
+pub use empires::player_color::PlayerColor;
+use empires::player_color::read_player_colors;
+pub use empires::random_map::{BaseZone, MapTerrain, MapUnit, RandomMap};
+use empires::random_map::read_random_maps;
+pub use empires::research::{Research, ResearchCost};
+use empires::research::read_research;
+pub use empires::resource::{ResourceCost, ResourceType};
+pub use empires::sound::{SoundEffect, SoundEffectGroup};
+use empires::sound::read_sound_effect_groups;
 pub use empires::terrain_block::Terrain;
 
 pub use empires::terrain_block::TerrainBlock;
 pub use empires::terrain_block::TerrainBorder;
 use empires::terrain_block::read_terrain_block;
-use empires::terrain_restrictions::{TerrainRestriction, read_terrain_restrictions};
-pub use empires::unit::{InteractionMode, Unit};
+pub use empires::terrain_restrictions::TerrainRestriction;
+use empires::terrain_restrictions::read_terrain_restrictions;
+pub use empires::unit::{InteractionMode, Unit, UnitClass, UnitResourceCost};
 use error::*;
 
 use identifier::*;
@@ -92,24 +101,61 @@ impl EmpiresDb {
         &self.ages[*age_id as usize]
     }
 
+    /// All ages, ordered by ID
+    #[inline]
+    pub fn ages<'a>(&'a self) -> &'a [ResearchEffectGroup] {
+        &self.ages
+    }
+
+    /// Applies each civ's bonus effects to its own units and starting values, so that the civs
+    /// actually play differently. Should be called once, right after loading and before any
+    /// data patches, so that patches have the final say.
+    pub fn apply_civ_bonuses(&mut self) {
+        for civ in &mut self.civilizations {
+            let effects = match civ.bonus_effects_id() {
+                Some(age_id) if (*age_id as usize) < self.ages.len() => &self.ages[*age_id as usize].effects,
+                _ => continue,
+            };
+            civ.apply_bonus_effects(effects, &self.research);
+        }
+    }
+
     /// Retrieve a player color by ID
     #[inline]
     pub fn player_color<'a>(&'a self, player_color_id: PlayerColorId) -> &'a PlayerColor {
         &self.player_colors[*player_color_id as usize]
     }
 
+    /// All player colors, ordered by ID
+    #[inline]
+    pub fn player_colors<'a>(&'a self) -> &'a [PlayerColor] {
+        &self.player_colors
+    }
+
     /// Retrieve a civilization by ID
     #[inline]
     pub fn civilization<'a>(&'a self, civilization_id: CivilizationId) -> &'a Civilization {
         &self.civilizations[(*civilization_id - 1) as usize]
     }
 
+    /// All civilizations, ordered by ID (which starts at 1; Gaia is the first civilization)
+    #[inline]
+    pub fn civilizations<'a>(&'a self) -> &'a [Civilization] {
+        &self.civilizations
+    }
+
     /// Retrieve a graphic by ID
     #[inline]
     pub fn graphic<'a>(&'a self, graphic_id: GraphicId) -> &'a Graphic {
         &self.graphics[*graphic_id as usize]
     }
 
+    /// All graphics, ordered by ID
+    #[inline]
+    pub fn graphics<'a>(&'a self) -> &'a [Graphic] {
+        &self.graphics
+    }
+
     /// Convenience to quickly get unit information
     #[inline]
     pub fn unit<'a>(&'a self, civilization_id: CivilizationId, unit_id: UnitId) -> &'a Unit {
@@ -136,6 +182,12 @@ impl EmpiresDb {
         &self.terrain_restrictions[unit_terrain_restriction_id.as_index()]
     }
 
+    /// All terrain restrictions, ordered by ID
+    #[inline]
+    pub fn all_terrain_restrictions<'a>(&'a self) -> &'a [TerrainRestriction] {
+        &self.terrain_restrictions
+    }
+
     /// Convenience that returns terrain border by ID
     #[inline]
     pub fn terrain_border<'a>(&'a self, terrain_border_id: TerrainBorderId) -> &'a TerrainBorder {
@@ -154,12 +206,30 @@ impl EmpiresDb {
         &self.research[*research_id as usize]
     }
 
+    /// All research, ordered by ID
+    #[inline]
+    pub fn all_research<'a>(&'a self) -> &'a [Research] {
+        &self.research
+    }
+
     /// Retrieve a sound effect group by ID
     #[inline]
     pub fn sound_effect_group<'a>(&'a self, sound_group_id: SoundGroupId) -> &'a SoundEffectGroup {
         &self.sound_effect_groups[*sound_group_id as usize]
     }
 
+    /// All sound effect groups, ordered by ID
+    #[inline]
+    pub fn sound_effect_groups<'a>(&'a self) -> &'a [SoundEffectGroup] {
+        &self.sound_effect_groups
+    }
+
+    /// Random map generation parameters, in the order they appear in the file
+    #[inline]
+    pub fn random_maps<'a>(&'a self) -> &'a [RandomMap] {
+        &self.random_maps
+    }
+
     /// Read all of the game data from the empires.dat file specified
     pub fn read_from_file<P: AsRef<Path>>(file_name: P) -> Result<EmpiresDb> {
         let file = try!(File::open(file_name.as_ref()));
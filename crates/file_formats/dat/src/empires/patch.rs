@@ -0,0 +1,188 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+use empires::EmpiresDb;
+use empires::resource::ResourceType;
+use error::*;
+
+use identifier::*;
+
+/// A value given to a field by a data patch
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchValue {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+}
+
+impl PatchValue {
+    pub fn to_i16(&self, field: &str) -> Result<i16> {
+        self.to_integer(field, i16::min_value() as i64, i16::max_value() as i64).map(|value| value as i16)
+    }
+
+    pub fn to_i8(&self, field: &str) -> Result<i8> {
+        self.to_integer(field, i8::min_value() as i64, i8::max_value() as i64).map(|value| value as i8)
+    }
+
+    fn to_integer(&self, field: &str, min: i64, max: i64) -> Result<i64> {
+        match *self {
+            PatchValue::Integer(value) if value >= min && value <= max => Ok(value),
+            _ => Err(bad_value(field, &format!("a whole number from {} to {}", min, max))),
+        }
+    }
+
+    pub fn to_f32(&self, field: &str) -> Result<f32> {
+        match *self {
+            PatchValue::Integer(value) => Ok(value as f32),
+            PatchValue::Float(value) => Ok(value as f32),
+            _ => Err(bad_value(field, "a number")),
+        }
+    }
+
+    pub fn to_bool(&self, field: &str) -> Result<bool> {
+        match *self {
+            PatchValue::Boolean(value) => Ok(value),
+            _ => Err(bad_value(field, "true or false")),
+        }
+    }
+
+    pub fn to_str<'a>(&'a self, field: &str) -> Result<&'a str> {
+        match *self {
+            PatchValue::String(ref value) => Ok(value),
+            _ => Err(bad_value(field, "a string")),
+        }
+    }
+
+    /// An ID, or None for -1
+    pub fn to_optional_id<T>(&self, field: &str) -> Result<Option<T>>
+        where usize: Into<T>
+    {
+        match *self {
+            PatchValue::Integer(-1) => Ok(None),
+            PatchValue::Integer(value) if value >= 0 && value <= i16::max_value() as i64 => {
+                Ok(Some((value as usize).into()))
+            }
+            _ => Err(bad_value(field, "an ID, or -1 for none")),
+        }
+    }
+}
+
+fn bad_value(field: &str, expected: &str) -> Error {
+    ErrorKind::BadPatch(format!("\"{}\" must be {}", field, expected)).into()
+}
+
+/// Resource named by the suffix of a field like `cost.food`
+pub fn patch_resource_type(field: &str, name: &str) -> Result<ResourceType> {
+    match name {
+        "food" => Ok(ResourceType::Food),
+        "wood" => Ok(ResourceType::Wood),
+        "stone" => Ok(ResourceType::Stone),
+        "gold" => Ok(ResourceType::Gold),
+        _ => {
+            Err(ErrorKind::BadPatch(format!("\"{}\" isn't a resource; use food, wood, stone, or gold", field))
+                .into())
+        }
+    }
+}
+
+/// Changes to fields of a unit, by the same names that `Unit::set_field` takes. A unit ID that
+/// doesn't exist yet adds a new unit, copied from the base unit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnitPatch {
+    pub unit_id: UnitId,
+    /// The civilization to change the unit for, or None for all of them
+    pub civilization_id: Option<CivilizationId>,
+    pub base_unit_id: Option<UnitId>,
+    pub fields: Vec<(String, PatchValue)>,
+}
+
+/// Changes to fields of a research entry, by the same names that `Research::set_field` takes.
+/// New research has to take the next unused ID, and is copied from the base research.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResearchPatch {
+    pub research_id: ResearchId,
+    pub base_research_id: Option<ResearchId>,
+    pub fields: Vec<(String, PatchValue)>,
+}
+
+impl EmpiresDb {
+    pub fn apply_unit_patch(&mut self, patch: &UnitPatch) -> Result<()> {
+        let mut applied = false;
+        for civ in &mut self.civilizations {
+            if patch.civilization_id.map_or(false, |civilization_id| civilization_id != civ.id()) {
+                continue;
+            }
+            if !civ.has_unit(patch.unit_id) {
+                match patch.base_unit_id {
+                    Some(base_unit_id) if civ.has_unit(base_unit_id) => {
+                        let mut unit = civ.unit(base_unit_id).clone();
+                        unit.id = patch.unit_id;
+                        civ.add_unit(unit);
+                    }
+                    _ => continue,
+                }
+            }
+            let unit = civ.unit_mut(patch.unit_id).unwrap();
+            for &(ref field, ref value) in &patch.fields {
+                try!(unit.set_field(field, value));
+            }
+            applied = true;
+        }
+        if applied {
+            Ok(())
+        } else {
+            Err(ErrorKind::BadPatch(format!("unit {} doesn't exist, and there's no base unit to copy",
+                                            *patch.unit_id))
+                .into())
+        }
+    }
+
+    pub fn apply_research_patch(&mut self, patch: &ResearchPatch) -> Result<()> {
+        let index = *patch.research_id as usize;
+        if index >= self.research.len() {
+            let base_index = match patch.base_research_id {
+                Some(base_research_id) if (*base_research_id as usize) < self.research.len() => {
+                    *base_research_id as usize
+                }
+                _ => {
+                    return Err(ErrorKind::BadPatch(format!("research {} doesn't exist, and there's no base \
+                                                            research to copy",
+                                                           index))
+                        .into())
+                }
+            };
+            if index != self.research.len() {
+                return Err(ErrorKind::BadPatch(format!("new research must use the next unused ID, {}",
+                                                       self.research.len()))
+                    .into());
+            }
+            let mut research = self.research[base_index].clone();
+            research.id = patch.research_id;
+            self.research.push(research);
+        }
+        for &(ref field, ref value) in &patch.fields {
+            try!(self.research[index].set_field(field, value));
+        }
+        Ok(())
+    }
+}
@@ -46,47 +46,47 @@ pub struct RandomMapHeader {
 
 #[derive(Default, Debug)]
 pub struct BaseZone {
-    base_terrain_id: TerrainId,
-    space_between_players: i32,
-    start_area_radius: i32,
+    pub base_terrain_id: TerrainId,
+    pub space_between_players: i32,
+    pub start_area_radius: i32,
 }
 
 #[derive(Default, Debug)]
 pub struct MapTerrain {
-    proportion: i32,
-    terrain_id: TerrainId,
-    clump_count: i32,
-    spacing_to_other_terrains: i32,
-    placement_zone: i32,
+    pub proportion: i32,
+    pub terrain_id: TerrainId,
+    pub clump_count: i32,
+    pub spacing_to_other_terrains: i32,
+    pub placement_zone: i32,
 }
 
 #[derive(Default, Debug)]
 pub struct MapUnit {
-    unit_id: UnitId,
-    host_terrain_id: Option<TerrainId>,
-    objects_per_group: i32,
-    fluctuation: i32,
-    groups_per_player: i32,
-    group_radius: i32,
-    own_at_start: i32,
-    set_place_for_all_players: i32,
-    min_distance_to_players: i32,
-    max_distance_to_players: i32,
+    pub unit_id: UnitId,
+    pub host_terrain_id: Option<TerrainId>,
+    pub objects_per_group: i32,
+    pub fluctuation: i32,
+    pub groups_per_player: i32,
+    pub group_radius: i32,
+    pub own_at_start: i32,
+    pub set_place_for_all_players: i32,
+    pub min_distance_to_players: i32,
+    pub max_distance_to_players: i32,
 }
 
 #[derive(Default, Debug)]
 pub struct RandomMap {
-    border_sw: i32,
-    border_nw: i32,
-    border_ne: i32,
-    border_se: i32,
-    border_usage: i32,
-    water_shape: i32,
-    non_base_terrain_id: TerrainId,
-    base_zone_coverage: i32,
-    base_zones: Vec<BaseZone>,
-    terrains: Vec<MapTerrain>,
-    units: Vec<MapUnit>,
+    pub border_sw: i32,
+    pub border_nw: i32,
+    pub border_ne: i32,
+    pub border_se: i32,
+    pub border_usage: i32,
+    pub water_shape: i32,
+    pub non_base_terrain_id: TerrainId,
+    pub base_zone_coverage: i32,
+    pub base_zones: Vec<BaseZone>,
+    pub terrains: Vec<MapTerrain>,
+    pub units: Vec<MapUnit>,
 }
 
 pub fn read_random_maps<R: Read + Seek>(stream: &mut R) -> Result<Vec<RandomMap>> {
@@ -19,6 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use empires::patch::{PatchValue, patch_resource_type};
 use empires::resource::*;
 
 use error::*;
@@ -33,7 +34,7 @@ const RESOURCE_COST_COUNT: usize = 3;
 
 pub type ResearchCost = ResourceCost<i16, u8>;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Research {
     pub id: ResearchId,
     pub required_techs: Vec<i16>,
@@ -61,6 +62,27 @@ pub struct Research {
     pub name: String,
 }
 
+impl Research {
+    /// Changes a field by name, for data patches. The fields are `name`, `time_seconds`,
+    /// `location`, `age`, `icon_id`, `button_id`, and `cost.<resource>`.
+    pub fn set_field(&mut self, field: &str, value: &PatchValue) -> Result<()> {
+        match field {
+            "name" => self.name = try!(value.to_str(field)).to_string(),
+            "time_seconds" => self.time_seconds = try!(value.to_i16(field)),
+            "location" => self.location = try!(value.to_optional_id(field)),
+            "age" => self.age_id = try!(value.to_optional_id(field)),
+            "icon_id" => self.icon_id = try!(value.to_i16(field)),
+            "button_id" => self.button_id = try!(value.to_i8(field)),
+            _ if field.starts_with("cost.") => {
+                let resource_type = try!(patch_resource_type(field, &field["cost.".len()..]));
+                set_resource_cost(&mut self.resource_costs, resource_type, try!(value.to_i16(field)));
+            }
+            _ => return Err(ErrorKind::BadPatch(format!("research has no field \"{}\"", field)).into()),
+        }
+        Ok(())
+    }
+}
+
 pub fn read_research<R: Read + Seek>(stream: &mut R) -> Result<Vec<Research>> {
     let research_count = try!(stream.read_u16()) as usize;
     let mut research = try!(stream.read_array(research_count, |c| read_single_research(c)));
@@ -105,6 +105,21 @@ impl ReadResourceCost for ResourceCost<f32, u8> {
     }
 }
 
+/// Sets how much of a resource something costs, for data patches; a cost of zero removes it
+pub fn set_resource_cost<E: Copy>(costs: &mut Vec<ResourceCost<i16, E>>,
+                                  resource_type: ResourceType,
+                                  amount: i16) {
+    costs.retain(|cost| cost.resource_type != resource_type);
+    if amount != 0 {
+        costs.push(ResourceCost {
+            resource_type: resource_type,
+            amount: amount,
+            enabled: true,
+            phantom: PhantomData,
+        });
+    }
+}
+
 #[macro_export]
 macro_rules! read_resource_costs {
     ($t:ty, $e:ty, $stream:expr, $num:expr) => {
@@ -119,6 +119,11 @@ impl Terrain {
     pub fn terrain_border<'a>(&'a self, terrain_id: TerrainId) -> TerrainBorderId {
         self.terrain_borders[*terrain_id as usize]
     }
+
+    /// The sound heard around the terrain, like the waves on water
+    pub fn sound_group_id(&self) -> Option<SoundGroupId> {
+        self.sound_group_id
+    }
 }
 
 #[derive(Default, Debug)]
@@ -163,6 +168,12 @@ impl TerrainBlock {
         (self.tile_half_width as i32, self.tile_half_height as i32)
     }
 
+    /// Whether there's a terrain with the ID
+    #[inline]
+    pub fn has_terrain(&self, terrain_id: TerrainId) -> bool {
+        (*terrain_id as usize) < self.terrains.len()
+    }
+
     /// Retrieve terrain by ID
     #[inline]
     pub fn terrain<'a>(&'a self, terrain_id: TerrainId) -> &'a Terrain {
@@ -20,6 +20,8 @@
 // SOFTWARE.
 //
 
+use empires::age::{ResearchEffectValue, UnitAttributeId};
+use empires::patch::{PatchValue, patch_resource_type};
 use empires::resource::*;
 use error::*;
 
@@ -29,9 +31,21 @@ use chariot_io_tools::*;
 use std::io::prelude::*;
 
 type UnitResourceStorage = ResourceCost<f32, u8>;
-type UnitResourceCost = ResourceCost<i16, i16>;
+pub type UnitResourceCost = ResourceCost<i16, i16>;
 
-#[derive(Default, Debug)]
+/// The command type of the tasks that gather resources
+const GATHER_COMMAND_TYPE: i16 = 5;
+const BUILD_COMMAND_TYPE: i16 = 101;
+const HEAL_COMMAND_TYPE: i16 = 105;
+const REPAIR_COMMAND_TYPE: i16 = 106;
+
+/// Stored by units that heal themselves over time, as hit points a minute
+const REGENERATION_RESOURCE_TYPE: i16 = 109;
+
+/// Stored by remains like corpses, as the seconds they last before decaying
+const DECAY_TIME_RESOURCE_TYPE: i16 = 12;
+
+#[derive(Default, Debug, Clone)]
 pub struct DamageGraphic {
     graphic_id: GraphicId,
     damage_percent: u8,
@@ -68,6 +82,159 @@ impl InteractionMode {
     }
 }
 
+/// Broad category a unit belongs to; research effects and attack bonuses target these
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum UnitClass {
+    Archer,
+    Artifact,
+    TradeBoat,
+    Building,
+    Civilian,
+    SeaFish,
+    Soldier,
+    BerryBush,
+    StoneMine,
+    PreyAnimal,
+    PredatorAnimal,
+    Other,
+    Cavalry,
+    SiegeWeapon,
+    Terrain,
+    Tree,
+    TreeStump,
+    Healer,
+    Priest,
+    TradeCart,
+    TransportBoat,
+    FishingBoat,
+    WarBoat,
+    Conquistador,
+    WarElephant,
+    Hero,
+    ElephantArcher,
+    Wall,
+    Phalanx,
+    DomesticAnimal,
+    Flag,
+    DeepSeaFish,
+    GoldMine,
+    ShoreFish,
+    Cliff,
+    Petard,
+    CavalryArcher,
+    Doppelganger,
+    Bird,
+    Unknown(i16),
+}
+
+impl Default for UnitClass {
+    fn default() -> UnitClass {
+        UnitClass::Unknown(-1)
+    }
+}
+
+impl UnitClass {
+    pub fn from_i16(val: i16) -> UnitClass {
+        use self::UnitClass::*;
+        match val {
+            0 => Archer,
+            1 => Artifact,
+            2 => TradeBoat,
+            3 => Building,
+            4 => Civilian,
+            5 => SeaFish,
+            6 => Soldier,
+            7 => BerryBush,
+            8 => StoneMine,
+            9 => PreyAnimal,
+            10 => PredatorAnimal,
+            11 => Other,
+            12 => Cavalry,
+            13 => SiegeWeapon,
+            14 => Terrain,
+            15 => Tree,
+            16 => TreeStump,
+            17 => Healer,
+            18 => Priest,
+            19 => TradeCart,
+            20 => TransportBoat,
+            21 => FishingBoat,
+            22 => WarBoat,
+            23 => Conquistador,
+            24 => WarElephant,
+            25 => Hero,
+            26 => ElephantArcher,
+            27 => Wall,
+            28 => Phalanx,
+            29 => DomesticAnimal,
+            30 => Flag,
+            31 => DeepSeaFish,
+            32 => GoldMine,
+            33 => ShoreFish,
+            34 => Cliff,
+            35 => Petard,
+            36 => CavalryArcher,
+            37 => Doppelganger,
+            38 => Bird,
+            _ => Unknown(val),
+        }
+    }
+
+    /// Villagers, which are the only units that build, and gather everything but deep sea fish
+    pub fn is_villager(&self) -> bool {
+        *self == UnitClass::Civilian
+    }
+
+    /// Wild animals, which villagers can hunt
+    pub fn is_animal(&self) -> bool {
+        *self == UnitClass::PreyAnimal || *self == UnitClass::PredatorAnimal
+    }
+
+    /// Fish, which fishing boats gather out at sea, and villagers gather from the shore when
+    /// they're close enough to it
+    pub fn is_fish(&self) -> bool {
+        use self::UnitClass::*;
+        match *self {
+            SeaFish | DeepSeaFish | ShoreFish => true,
+            _ => false,
+        }
+    }
+
+    /// Living units, which priests can heal
+    pub fn is_organic(&self) -> bool {
+        !self.is_repairable() && self.is_unit()
+    }
+
+    /// Buildings, siege weapons and boats, which villagers can repair
+    pub fn is_repairable(&self) -> bool {
+        use self::UnitClass::*;
+        match *self {
+            Building | Wall | SiegeWeapon | TradeBoat | TransportBoat | FishingBoat | WarBoat => true,
+            _ => false,
+        }
+    }
+
+    /// Units as opposed to resources, scenery and the like
+    fn is_unit(&self) -> bool {
+        use self::UnitClass::*;
+        match *self {
+            Artifact | SeaFish | BerryBush | StoneMine | Other | Terrain | Tree | TreeStump | Flag |
+            DeepSeaFish | GoldMine | ShoreFish | Cliff | Bird | Unknown(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Units whose main purpose is fighting
+    pub fn is_military(&self) -> bool {
+        use self::UnitClass::*;
+        match *self {
+            Archer | Soldier | Cavalry | SiegeWeapon | WarBoat | Conquistador | WarElephant | Hero |
+            ElephantArcher | Phalanx | Petard | CavalryArcher => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum UnitType {
     GraphicEffect,
@@ -155,7 +322,7 @@ impl UnitType {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct UnitCommand {
     id: UnitCommandId,
     enabled: bool,
@@ -182,7 +349,7 @@ pub struct UnitCommand {
     resource_deposit_sound_id: Option<SoundGroupId>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct MotionParams {
     pub speed: f32,
     pub walking_graphics: [Option<GraphicId>; 2],
@@ -192,7 +359,7 @@ pub struct MotionParams {
     tracking_unit_density: f32,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct CommandableParams {
     action_when_discovered_id: i16,
     search_radius: f32,
@@ -205,7 +372,7 @@ pub struct CommandableParams {
     commands: Vec<UnitCommand>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct BattleParams {
     default_armor: u8,
     attacks: Vec<(i16, i16)>, // class, amount
@@ -228,7 +395,7 @@ pub struct BattleParams {
     displayed_reload_time: f32,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct BuildingParams {
     construction_graphic_id: GraphicId,
     adjacent_mode: i8,
@@ -241,7 +408,7 @@ pub struct BuildingParams {
     construction_sound: i16,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct ProjectileParams {
     stretch_mode: i8,
     smart_mode: i8,
@@ -250,7 +417,7 @@ pub struct ProjectileParams {
     projectile_arc: f32,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct TrainableParams {
     resource_costs: Vec<UnitResourceCost>,
     train_time: i16,
@@ -259,7 +426,7 @@ pub struct TrainableParams {
     displayed_pierce_armor: i16,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Unit {
     pub id: UnitId,
 
@@ -365,6 +532,416 @@ pub struct Unit {
     building_params: Option<BuildingParams>,
 }
 
+impl Unit {
+    #[inline]
+    pub fn class(&self) -> UnitClass {
+        UnitClass::from_i16(self.class_id)
+    }
+
+    #[inline]
+    pub fn name<'a>(&'a self) -> &'a str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn hit_points(&self) -> i16 {
+        self.hit_points
+    }
+
+    #[inline]
+    pub fn line_of_sight(&self) -> f32 {
+        self.line_of_sight
+    }
+
+    /// How many units can shelter inside the unit; zero for nearly everything in the original
+    /// game data
+    pub fn garrison_capacity(&self) -> usize {
+        if self.garrison_capability > 0 {
+            self.garrison_capability as usize
+        } else {
+            0
+        }
+    }
+
+    /// Attack amounts as (armor class, amount) pairs; empty for units that can't attack
+    pub fn attacks<'a>(&'a self) -> &'a [(i16, i16)] {
+        self.battle_params.as_ref().map(|p| &p.attacks[..]).unwrap_or(&[])
+    }
+
+    /// Armor amounts as (armor class, amount) pairs
+    pub fn armors<'a>(&'a self) -> &'a [(i16, i16)] {
+        self.battle_params.as_ref().map(|p| &p.armors[..]).unwrap_or(&[])
+    }
+
+    pub fn max_range(&self) -> f32 {
+        self.battle_params.as_ref().map(|p| p.max_range).unwrap_or(0.)
+    }
+
+    pub fn reload_time(&self) -> f32 {
+        self.battle_params.as_ref().map(|p| p.reload_time).unwrap_or(0.)
+    }
+
+    /// How close the unit can fire at, for siege that can't hit what's right next to it
+    pub fn min_range(&self) -> f32 {
+        self.battle_params.as_ref().map(|p| p.min_range).unwrap_or(0.)
+    }
+
+    /// Radius, in tiles, of the area that the unit's shots damage; zero for units that only
+    /// hit what they're aimed at
+    pub fn blast_width(&self) -> f32 {
+        self.battle_params.as_ref().map(|p| p.blast_width).unwrap_or(0.)
+    }
+
+    /// The unit that flies from this one to what it shoots, like a catapult's stone
+    pub fn projectile_unit_id(&self) -> Option<UnitId> {
+        self.battle_params.as_ref().and_then(|p| p.projectile_unit_id)
+    }
+
+    pub fn attack_graphic(&self) -> Option<GraphicId> {
+        self.battle_params.as_ref().and_then(|p| p.attack_graphic_id)
+    }
+
+    /// Seconds it takes to train the unit, if it can be trained
+    pub fn train_time(&self) -> Option<i16> {
+        self.trainable_params.as_ref().map(|p| p.train_time)
+    }
+
+    pub fn train_costs<'a>(&'a self) -> &'a [UnitResourceCost] {
+        self.trainable_params.as_ref().map(|p| &p.resource_costs[..]).unwrap_or(&[])
+    }
+
+    /// The building that trains this unit, if it can be trained
+    pub fn train_location_id(&self) -> Option<UnitId> {
+        self.trainable_params.as_ref().and_then(|p| p.train_location_id)
+    }
+
+    /// Where the unit's button goes on the command panel of what trains it, counting from one
+    pub fn button_id(&self) -> Option<i8> {
+        self.trainable_params.as_ref().map(|p| p.button_id)
+    }
+
+    /// Terrains that the tiles under the unit have to be when it's placed; any terrain when empty
+    pub fn placement_terrain_ids(&self) -> Vec<TerrainId> {
+        self.placement_terrain_ids.iter().filter_map(|id| *id).collect()
+    }
+
+    /// Terrains that one of the tiles next to the unit has to be when it's placed, like the
+    /// shore next to a dock; no requirement when empty
+    pub fn placement_side_terrain_ids(&self) -> Vec<TerrainId> {
+        self.placement_side_terrain_ids.iter().filter_map(|id| *id).collect()
+    }
+
+    /// How much of a resource the unit can carry at once
+    pub fn resource_capacity(&self) -> i16 {
+        self.resource_capacity
+    }
+
+    /// The resource the unit holds for gatherers, like the wood in a tree
+    pub fn stored_resource(&self) -> Option<(ResourceType, f32)> {
+        self.resource_storage
+            .iter()
+            .find(|storage| match storage.resource_type {
+                ResourceType::Unknown(_) => false,
+                _ => storage.amount > 0.,
+            })
+            .map(|storage| (storage.resource_type, storage.amount))
+    }
+
+    /// Resource gathered per second when working
+    pub fn work_rate(&self) -> f32 {
+        self.commandable_params.as_ref().map(|p| p.work_rate).unwrap_or(0.)
+    }
+
+    /// The graphic the unit works with while it gathers the resource, if it can. Units with
+    /// several ways of gathering the same resource, like villagers fishing or picking berries
+    /// for food, use the one for the class of unit they gather from when it's given.
+    pub fn gathering_graphic(&self,
+                             resource_type: ResourceType,
+                             target_class: Option<UnitClass>)
+                             -> Option<GraphicId> {
+        self.gather_command(resource_type, target_class).and_then(|command| command.action_graphic_id)
+    }
+
+    /// The graphic the unit walks with while it carries the resource back to a drop site
+    pub fn carrying_graphic(&self,
+                            resource_type: ResourceType,
+                            target_class: Option<UnitClass>)
+                            -> Option<GraphicId> {
+        self.gather_command(resource_type, target_class).and_then(|command| command.carrying_graphic_id)
+    }
+
+    /// The resources the unit can gather, ordered by type
+    pub fn gathered_resources(&self) -> Vec<ResourceType> {
+        let commands = match self.commandable_params {
+            Some(ref params) => &params.commands,
+            None => return Vec::new(),
+        };
+        let mut resource_types: Vec<ResourceType> = commands.iter()
+            .filter(|command| command.enabled && command.type_id == GATHER_COMMAND_TYPE)
+            .flat_map(|command| vec![command.resource_in, command.resource_out])
+            .map(ResourceType::from_i16)
+            .filter(|resource_type| match *resource_type {
+                ResourceType::Unknown(_) => false,
+                _ => true,
+            })
+            .collect();
+        resource_types.sort();
+        resource_types.dedup();
+        resource_types
+    }
+
+    fn gather_command(&self,
+                      resource_type: ResourceType,
+                      target_class: Option<UnitClass>)
+                      -> Option<&UnitCommand> {
+        let commands = match self.commandable_params {
+            Some(ref params) => &params.commands,
+            None => return None,
+        };
+        let mut matching = commands.iter().filter(|command| {
+            command.enabled && command.type_id == GATHER_COMMAND_TYPE &&
+            (ResourceType::from_i16(command.resource_in) == resource_type ||
+             ResourceType::from_i16(command.resource_out) == resource_type)
+        });
+        let first = matching.next();
+        first.into_iter()
+            .chain(matching)
+            .find(|command| Some(UnitClass::from_i16(command.class_id)) == target_class)
+            .or(first)
+    }
+
+    /// Whether the unit can heal other units, like a priest
+    pub fn can_heal(&self) -> bool {
+        self.command_of_type(HEAL_COMMAND_TYPE).is_some()
+    }
+
+    /// Whether the unit can repair buildings, siege weapons and boats, like a villager
+    pub fn can_repair(&self) -> bool {
+        self.command_of_type(REPAIR_COMMAND_TYPE).is_some()
+    }
+
+    pub fn healing_graphic(&self) -> Option<GraphicId> {
+        self.command_of_type(HEAL_COMMAND_TYPE).and_then(|command| command.action_graphic_id)
+    }
+
+    pub fn repairing_graphic(&self) -> Option<GraphicId> {
+        self.command_of_type(REPAIR_COMMAND_TYPE).and_then(|command| command.action_graphic_id)
+    }
+
+    pub fn building_graphic(&self) -> Option<GraphicId> {
+        self.command_of_type(BUILD_COMMAND_TYPE).and_then(|command| command.action_graphic_id)
+    }
+
+    /// What the building looks like while it's still being built
+    pub fn construction_graphic(&self) -> Option<GraphicId> {
+        self.building_params.as_ref().map(|p| p.construction_graphic_id)
+    }
+
+    /// Hit points a minute the unit heals itself by; zero for units that don't
+    pub fn regeneration_rate(&self) -> f32 {
+        self.stored_attribute(REGENERATION_RESOURCE_TYPE)
+    }
+
+    /// What's left behind when the unit dies or decays, like a corpse, a skeleton or debris
+    pub fn dead_unit_id(&self) -> Option<UnitId> {
+        self.dead_unit_id
+    }
+
+    /// Seconds the unit lasts as remains before decaying into its dead unit; zero when the game
+    /// data doesn't say
+    pub fn decay_time(&self) -> f32 {
+        self.stored_attribute(DECAY_TIME_RESOURCE_TYPE)
+    }
+
+    fn stored_attribute(&self, resource_type: i16) -> f32 {
+        self.resource_storage
+            .iter()
+            .find(|storage| storage.resource_type == ResourceType::Unknown(resource_type))
+            .map(|storage| storage.amount)
+            .unwrap_or(0.)
+    }
+
+    fn command_of_type(&self, type_id: i16) -> Option<&UnitCommand> {
+        match self.commandable_params {
+            Some(ref params) => {
+                params.commands.iter().find(|command| command.enabled && command.type_id == type_id)
+            }
+            None => None,
+        }
+    }
+
+    /// The buildings this unit can drop gathered resources off at
+    pub fn drop_sites(&self) -> Vec<UnitId> {
+        match self.commandable_params {
+            Some(ref params) => params.drop_sites.iter().filter_map(|id| optional_id!(*id)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The voice the unit answers with when it's selected
+    pub fn selection_sound_id(&self) -> Option<SoundGroupId> {
+        optional_id!(self.selection_sound)
+    }
+
+    /// The voice the unit answers with when it's sent somewhere or given a task
+    pub fn move_sound_id(&self) -> Option<SoundGroupId> {
+        self.commandable_params.as_ref().and_then(|params| optional_id!(params.move_sound))
+    }
+
+    /// The voice the unit answers with when it's sent to attack
+    pub fn attack_sound_id(&self) -> Option<SoundGroupId> {
+        self.commandable_params.as_ref().and_then(|params| optional_id!(params.attack_sound))
+    }
+
+    /// Whether the unit belongs to the class, for effects that target a whole class of units
+    pub fn is_in_class(&self, class_id: UnitClassId) -> bool {
+        *class_id as i16 == self.class_id
+    }
+
+    /// Changes one of the unit's attributes the way a civ bonus or research effect does.
+    /// Returns false for attributes that aren't supported, or that the unit doesn't have.
+    pub fn apply_attribute_effect(&mut self,
+                                  attribute_id: &UnitAttributeId,
+                                  effect: &ResearchEffectValue)
+                                  -> bool {
+        match *attribute_id {
+            UnitAttributeId::HitPoints => {
+                self.hit_points = effect.apply(self.hit_points as f32).round() as i16
+            }
+            UnitAttributeId::LineOfSight => self.line_of_sight = effect.apply(self.line_of_sight),
+            UnitAttributeId::ResourceCarryCapacity => {
+                self.resource_capacity = effect.apply(self.resource_capacity as f32).round() as i16
+            }
+            UnitAttributeId::Speed => {
+                match self.motion_params {
+                    Some(ref mut params) => params.speed = effect.apply(params.speed),
+                    None => return false,
+                }
+            }
+            UnitAttributeId::WorkRate => {
+                match self.commandable_params {
+                    Some(ref mut params) => params.work_rate = effect.apply(params.work_rate),
+                    None => return false,
+                }
+            }
+            UnitAttributeId::ReloadTime | UnitAttributeId::AttackRange | UnitAttributeId::AttackStrength |
+            UnitAttributeId::ArmorStrength => {
+                let params = match self.battle_params {
+                    Some(ref mut params) => params,
+                    None => return false,
+                };
+                match *attribute_id {
+                    UnitAttributeId::ReloadTime => params.reload_time = effect.apply(params.reload_time),
+                    UnitAttributeId::AttackRange => params.max_range = effect.apply(params.max_range),
+                    UnitAttributeId::AttackStrength => apply_class_amount_effect(&mut params.attacks, effect),
+                    _ => apply_class_amount_effect(&mut params.armors, effect),
+                }
+            }
+            UnitAttributeId::ResourceCost => {
+                let params = match self.trainable_params {
+                    Some(ref mut params) => params,
+                    None => return false,
+                };
+                for cost in params.resource_costs.iter_mut().filter(|cost| cost.enabled && cost.amount > 0) {
+                    cost.amount = effect.apply(cost.amount as f32).round() as i16;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Changes a field by name, for data patches. The fields are `name`, `enabled`,
+    /// `hit_points`, `line_of_sight`, `collision_size_x` and `_y`, `speed`, `max_range`,
+    /// `min_range`, `reload_time`, `accuracy_percent`, `blast_width`, `attack.<armor class>`
+    /// and `armor.<armor class>`, `train_time`, `train_location`, and `cost.<resource>`.
+    /// Fields in a parameter group the unit doesn't have, like the speed of a building, are errors.
+    pub fn set_field(&mut self, field: &str, value: &PatchValue) -> Result<()> {
+        let unit_id = *self.id;
+        let missing = |group: &str| -> Error {
+            let message = format!("unit {} has no {}, so \"{}\" can't be set", unit_id, group, field);
+            ErrorKind::BadPatch(message).into()
+        };
+        let (group, suffix) = match field.find('.') {
+            Some(index) => (&field[..index], Some(&field[index + 1..])),
+            None => (field, None),
+        };
+        match (group, suffix) {
+            ("name", None) => self.name = try!(value.to_str(field)).to_string(),
+            ("enabled", None) => self.enabled = try!(value.to_bool(field)),
+            ("hit_points", None) => self.hit_points = try!(value.to_i16(field)),
+            ("line_of_sight", None) => self.line_of_sight = try!(value.to_f32(field)),
+            ("collision_size_x", None) => self.collision_size_x = try!(value.to_f32(field)),
+            ("collision_size_y", None) => self.collision_size_y = try!(value.to_f32(field)),
+            ("speed", None) => {
+                let params = try!(self.motion_params.as_mut().ok_or(missing("movement")));
+                params.speed = try!(value.to_f32(field));
+            }
+            ("max_range", None) | ("min_range", None) | ("reload_time", None) | ("accuracy_percent", None) |
+            ("blast_width", None) | ("attack", Some(_)) | ("armor", Some(_)) => {
+                let params = try!(self.battle_params.as_mut().ok_or(missing("combat stats")));
+                match (group, suffix) {
+                    ("max_range", _) => params.max_range = try!(value.to_f32(field)),
+                    ("min_range", _) => params.min_range = try!(value.to_f32(field)),
+                    ("reload_time", _) => params.reload_time = try!(value.to_f32(field)),
+                    ("accuracy_percent", _) => params.accuracy_percent = try!(value.to_i16(field)),
+                    ("blast_width", _) => params.blast_width = try!(value.to_f32(field)),
+                    (_, Some(armor_class)) => {
+                        let armor_class: i16 = try!(armor_class.parse().map_err(|_| {
+                            Error::from(ErrorKind::BadPatch(format!("\"{}\" isn't an armor class", field)))
+                        }));
+                        let amount = try!(value.to_i16(field));
+                        let amounts = if group == "attack" {
+                            &mut params.attacks
+                        } else {
+                            &mut params.armors
+                        };
+                        amounts.retain(|&(class, _)| class != armor_class);
+                        amounts.push((armor_class, amount));
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            ("train_time", None) | ("train_location", None) | ("cost", Some(_)) => {
+                let params = try!(self.trainable_params.as_mut().ok_or(missing("training stats")));
+                match suffix {
+                    Some(resource) => {
+                        let resource_type = try!(patch_resource_type(field, resource));
+                        let amount = try!(value.to_i16(field));
+                        set_resource_cost(&mut params.resource_costs, resource_type, amount);
+                    }
+                    None if group == "train_time" => params.train_time = try!(value.to_i16(field)),
+                    None => params.train_location_id = try!(value.to_optional_id(field)),
+                }
+            }
+            _ => return Err(ErrorKind::BadPatch(format!("units have no field \"{}\"", field)).into()),
+        }
+        Ok(())
+    }
+}
+
+/// Attack and armor effects that set or add to an amount pack the armor class into the high
+/// byte of the value and the amount into the low byte; multiplying affects every class.
+fn apply_class_amount_effect(amounts: &mut Vec<(i16, i16)>, effect: &ResearchEffectValue) {
+    let (armor_class, amount) = match *effect {
+        ResearchEffectValue::MultiplyBy(_) => {
+            for entry in amounts.iter_mut() {
+                entry.1 = effect.apply(entry.1 as f32).round() as i16;
+            }
+            return;
+        }
+        ResearchEffectValue::SetTo(value) |
+        ResearchEffectValue::Add(value) => (((value as i32) >> 8) as i16, ((value as i32) & 0xff) as f32),
+    };
+    let current = amounts.iter().find(|&&(class, _)| class == armor_class).map(|&(_, amount)| amount);
+    let updated = match *effect {
+        ResearchEffectValue::SetTo(_) => amount,
+        _ => current.unwrap_or(0) as f32 + amount,
+    };
+    amounts.retain(|&(class, _)| class != armor_class);
+    amounts.push((armor_class, updated.round() as i16));
+}
+
 pub fn read_unit<R: Read + Seek>(stream: &mut R) -> Result<Unit> {
     let mut unit: Unit = Default::default();
 
@@ -617,3 +1194,70 @@ fn read_building_params<R: Read>(stream: &mut R) -> Result<BuildingParams> {
     params.construction_sound = try!(stream.read_i16());
     Ok(params)
 }
+
+#[cfg(test)]
+impl Unit {
+    /// A fighting unit with just enough filled in for the effect tests
+    pub fn for_effect_tests(unit_id: UnitId, class_id: i16, hit_points: i16) -> Unit {
+        Unit {
+            id: unit_id,
+            class_id: class_id,
+            hit_points: hit_points,
+            battle_params: Some(BattleParams {
+                attacks: vec![(4, 5)],
+                armors: vec![(3, 1), (4, 0)],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use empires::age::{ResearchEffectValue, UnitAttributeId};
+    use super::{Unit, apply_class_amount_effect};
+
+    /// The armor class goes in the high byte of an attack or armor effect's value
+    fn class_amount(armor_class: i32, amount: i32) -> f32 {
+        (armor_class << 8 | amount) as f32
+    }
+
+    #[test]
+    fn test_class_amount_add() {
+        let mut amounts = vec![(4, 5), (3, 1)];
+        apply_class_amount_effect(&mut amounts, &ResearchEffectValue::Add(class_amount(4, 2)));
+        assert_eq!(vec![(3, 1), (4, 7)], amounts);
+
+        // Classes the unit didn't have start from nothing
+        apply_class_amount_effect(&mut amounts, &ResearchEffectValue::Add(class_amount(11, 1)));
+        assert_eq!(vec![(3, 1), (4, 7), (11, 1)], amounts);
+    }
+
+    #[test]
+    fn test_class_amount_set_and_multiply() {
+        let mut amounts = vec![(4, 5), (3, 2)];
+        apply_class_amount_effect(&mut amounts, &ResearchEffectValue::SetTo(class_amount(3, 6)));
+        assert_eq!(vec![(4, 5), (3, 6)], amounts);
+
+        // Multiplying goes for every class, and doesn't have a class packed in
+        apply_class_amount_effect(&mut amounts, &ResearchEffectValue::MultiplyBy(1.5));
+        assert_eq!(vec![(4, 8), (3, 9)], amounts);
+    }
+
+    #[test]
+    fn test_attack_and_armor_effects() {
+        let mut unit = Unit::for_effect_tests(74.into(), 6, 45);
+        assert!(unit.apply_attribute_effect(&UnitAttributeId::AttackStrength,
+                                            &ResearchEffectValue::Add(class_amount(4, 1))));
+        assert!(unit.apply_attribute_effect(&UnitAttributeId::ArmorStrength,
+                                            &ResearchEffectValue::Add(class_amount(3, 2))));
+        assert_eq!(&[(4, 6)], unit.attacks());
+        assert_eq!(&[(4, 0), (3, 3)], unit.armors());
+
+        let mut building = Unit::default();
+        assert!(!building.apply_attribute_effect(&UnitAttributeId::AttackStrength,
+                                                 &ResearchEffectValue::Add(class_amount(4, 1))));
+        assert!(building.attacks().is_empty());
+    }
+}
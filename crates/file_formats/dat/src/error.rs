@@ -49,5 +49,9 @@ error_chain! {
             description("invalid unit type")
             display("Invalid unit type: {}", type_id)
         }
+        BadPatch(message: String) {
+            description("bad data patch")
+            display("Bad data patch: {}", message)
+        }
     }
 }
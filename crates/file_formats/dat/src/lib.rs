@@ -33,12 +33,20 @@ mod tileedge;
 mod error;
 
 pub use empires::{EmpiresDb, EmpiresDbRef};
-pub use empires::{InteractionMode, Unit};
+pub use empires::{InteractionMode, Unit, UnitClass, UnitResourceCost};
 pub use empires::Civilization;
 pub use empires::Graphic;
+pub use empires::{PatchValue, ResearchPatch, UnitPatch};
+pub use empires::PlayerColor;
+pub use empires::{BaseZone, MapTerrain, MapUnit, RandomMap};
+pub use empires::{Research, ResearchCost};
+pub use empires::{ResearchEffect, ResearchEffectGroup, ResearchEffectValue, UnitAttributeId};
+pub use empires::{ResourceCost, ResourceType};
+pub use empires::{SoundEffect, SoundEffectGroup};
 pub use empires::Terrain;
 pub use empires::TerrainBlock;
 pub use empires::TerrainBorder;
+pub use empires::TerrainRestriction;
 
 pub use error::{ChainErr, Error, ErrorKind, Result};
 
@@ -0,0 +1,98 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use error::*;
+
+/// Newest data version with the Age of Empires/Rise of Rome layout
+const MAX_AGE_OF_EMPIRES_DATA_VERSION: f32 = 1.15;
+const AGE_OF_KINGS_DATA_VERSION: f32 = 1.18;
+const CONQUERORS_DATA_VERSION: f32 = 1.22;
+
+/// Revision of the compressed scenario data's layout, which is decided by its data version.
+/// Each revision only adds fields to the one before it, so the readers and writers check
+/// whether the scenario's layout is at least the one that a field first appeared in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DataLayout {
+    /// Age of Empires and Rise of Rome (up to 1.15)
+    AgeOfEmpires,
+
+    /// Age of Kings (1.18 to 1.21), which added string table IDs for the player names and
+    /// messages, ore, disabled units and buildings, the starting camera position, and the
+    /// animation frame and garrison of each unit
+    AgeOfKings,
+
+    /// The Conquerors (1.22), which added the scouting message and the AI map type
+    Conquerors,
+}
+
+impl Default for DataLayout {
+    fn default() -> DataLayout {
+        DataLayout::AgeOfEmpires
+    }
+}
+
+impl DataLayout {
+    pub fn from_version(version: f32) -> Result<DataLayout> {
+        if version <= MAX_AGE_OF_EMPIRES_DATA_VERSION {
+            Ok(DataLayout::AgeOfEmpires)
+        } else if version >= AGE_OF_KINGS_DATA_VERSION && version < CONQUERORS_DATA_VERSION {
+            Ok(DataLayout::AgeOfKings)
+        } else if version == CONQUERORS_DATA_VERSION {
+            Ok(DataLayout::Conquerors)
+        } else {
+            Err(ErrorKind::UnsupportedDataVersion(version).into())
+        }
+    }
+
+    /// Data version written for new scenarios with this layout
+    pub fn data_version(&self) -> f32 {
+        match *self {
+            DataLayout::AgeOfEmpires => MAX_AGE_OF_EMPIRES_DATA_VERSION,
+            DataLayout::AgeOfKings => AGE_OF_KINGS_DATA_VERSION,
+            DataLayout::Conquerors => CONQUERORS_DATA_VERSION,
+        }
+    }
+
+    /// Header version written for new scenarios with this layout
+    pub fn header_version(&self) -> &'static str {
+        match *self {
+            DataLayout::AgeOfEmpires => "1.11",
+            DataLayout::AgeOfKings => "1.18",
+            DataLayout::Conquerors => "1.21",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataLayout;
+
+    #[test]
+    fn test_layout_from_version() {
+        assert_eq!(DataLayout::AgeOfEmpires, DataLayout::from_version(1.10).unwrap());
+        assert_eq!(DataLayout::AgeOfEmpires, DataLayout::from_version(1.15).unwrap());
+        assert!(DataLayout::from_version(1.16).is_err());
+        assert_eq!(DataLayout::AgeOfKings, DataLayout::from_version(1.18).unwrap());
+        assert_eq!(DataLayout::AgeOfKings, DataLayout::from_version(1.21).unwrap());
+        assert_eq!(DataLayout::Conquerors, DataLayout::from_version(1.22).unwrap());
+        assert!(DataLayout::from_version(1.30).is_err());
+    }
+}
@@ -35,10 +35,18 @@ error_chain! {
     }
 
     errors {
-        UnrecognizedScenarioVersion {
-            display("unrecognized scenario version")
+        UnrecognizedScenarioVersion(version: String) {
+            display("unrecognized scenario version \"{}\"", version)
             description("unrecognized scenario version")
         }
+        UnsupportedDataVersion(version: f32) {
+            display("unsupported scenario data version {}", version)
+            description("unsupported scenario data version")
+        }
+        BadHeaderLength {
+            display("scenario header length is shorter than its contents")
+            description("bad scenario header length")
+        }
         InstructionsTooLarge {
             display("scenario instructions too large")
             description("scenario instructions too large")
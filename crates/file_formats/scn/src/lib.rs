@@ -29,18 +29,24 @@ extern crate error_chain;
 extern crate chariot_identifier as identifier;
 
 extern crate chariot_io_tools;
+extern crate flate2;
 
+mod data_layout;
 mod error;
 mod scn;
 mod player_data;
 mod player_resources;
 mod player_unit;
 mod map;
+mod write_ext;
 
+pub use data_layout::DataLayout;
 pub use error::ChainErr;
 pub use error::Error;
 pub use error::ErrorKind;
 pub use error::Result;
 pub use map::{Map, MapTile};
+pub use player_resources::PlayerResources;
+pub use player_unit::PlayerUnit;
 
 pub use scn::Scenario;
@@ -20,15 +20,24 @@
 // SOFTWARE.
 //
 
+use data_layout::DataLayout;
 use error::*;
 
 use identifier::TerrainId;
 use chariot_io_tools::*;
 
-use std::io::Read;
+use std::io::{Read, Write};
+use write_ext::WriteExt;
 
 #[derive(Default, Debug)]
 pub struct Map {
+    /// Where player 1's camera starts (Age of Kings onwards)
+    pub camera_x: i32,
+    pub camera_y: i32,
+
+    /// Which map the AI plays it as (The Conquerors onwards)
+    pub ai_map_type: u32,
+
     pub width: u32,
     pub height: u32,
     pub tiles: Vec<MapTile>,
@@ -42,15 +51,38 @@ pub struct MapTile {
 }
 
 impl Map {
-    pub fn read_from_stream<S: Read>(stream: &mut S) -> Result<Map> {
-        let mut map = Map {
-            width: try!(stream.read_u32()),
-            height: try!(stream.read_u32()),
-            tiles: Default::default(),
-        };
+    pub fn read_from_stream<S: Read>(stream: &mut S, layout: DataLayout) -> Result<Map> {
+        let mut map: Map = Default::default();
+        if layout >= DataLayout::AgeOfKings {
+            map.camera_x = try!(stream.read_i32());
+            map.camera_y = try!(stream.read_i32());
+        }
+        if layout >= DataLayout::Conquerors {
+            map.ai_map_type = try!(stream.read_u32());
+        }
+        map.width = try!(stream.read_u32());
+        map.height = try!(stream.read_u32());
         map.tiles = try!(stream.read_array((map.width * map.height) as usize, |s| read_map_tile(s)));
         Ok(map)
     }
+
+    pub fn write_to_stream<W: Write>(&self, stream: &mut W, layout: DataLayout) -> Result<()> {
+        if layout >= DataLayout::AgeOfKings {
+            try!(stream.write_i32(self.camera_x));
+            try!(stream.write_i32(self.camera_y));
+        }
+        if layout >= DataLayout::Conquerors {
+            try!(stream.write_u32(self.ai_map_type));
+        }
+        try!(stream.write_u32(self.width));
+        try!(stream.write_u32(self.height));
+        for tile in &self.tiles {
+            try!(stream.write_i8(*tile.terrain_id as i8));
+            try!(stream.write_u8(tile.elevation));
+            try!(stream.write_u8(tile.unused));
+        }
+        Ok(())
+    }
 }
 
 fn read_map_tile<S: Read>(stream: &mut S) -> Result<MapTile> {
@@ -19,27 +19,35 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use data_layout::DataLayout;
 use error::*;
 use identifier::*;
 
 use chariot_io_tools::*;
 
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
+use write_ext::WriteExt;
 
+/// Fields that are missing from older layouts are left empty (or blank, for strings) rather than
+/// wrapped in options, and are only read and written for the layouts that have them
 #[derive(Default, Debug)]
 pub struct PlayerData {
     version: f32,
+    layout: DataLayout,
     pub player_names: Vec<String>,
+    player_name_string_ids: Vec<u32>,
     pub player_civs: Vec<PlayerCivilization>,
     pub conquest_victory: bool,
     unknown1: Vec<u8>,
     pub original_file_name: String,
 
+    message_string_ids: Vec<u32>,
     pub instructions: String,
     pub hints: String,
     pub victory: String,
     pub loss: String,
     pub history: String,
+    pub scouts: String,
 
     pub pre_game_cinematic_file_name: String,
     pub victory_cinematic_file_name: String,
@@ -60,7 +68,12 @@ pub struct PlayerData {
     pub diplomacy: Diplomacy,
 
     pub allied_victory: Vec<u32>,
+    disabled_research_counts: Vec<u32>,
     pub disabled_research_ids: Vec<Vec<u32>>,
+    disabled_unit_counts: Vec<u32>,
+    pub disabled_unit_ids: Vec<Vec<u32>>,
+    disabled_building_counts: Vec<u32>,
+    pub disabled_building_ids: Vec<Vec<u32>>,
     unused1: u32,
     unused2: u32,
 
@@ -82,6 +95,8 @@ pub struct PlayerStartingResources {
     wood: u32,
     food: u32,
     stone: u32,
+    ore: u32,
+    unused1: u32,
 }
 
 #[derive(Default, Debug)]
@@ -122,17 +137,111 @@ pub struct Diplomacy {
     individual_victory: Vec<Vec<u32>>,
 }
 
+impl PlayerCivilization {
+    /// Whether or not the player slot is in use by the scenario
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.state != 0
+    }
+
+    /// Whether or not the player is meant to be controlled by a human (otherwise, by the AI)
+    #[inline]
+    pub fn is_human(&self) -> bool {
+        self.type_id != 0
+    }
+}
+
+impl Diplomacy {
+    /// Raw stance of the given player index towards the other player index;
+    /// 0 = allied, 1 = neutral, 3 = enemy
+    #[inline]
+    pub fn stance(&self, player_index: usize, other_player_index: usize) -> u32 {
+        self.stances[player_index][other_player_index]
+    }
+
+    #[inline]
+    pub fn set_stance(&mut self, player_index: usize, other_player_index: usize, stance: u32) {
+        self.stances[player_index][other_player_index] = stance;
+    }
+}
+
 const PLAYER_DATA_UNKNOWN_1_LENGTH: usize = 8;
 const THUMBNAIL_UNKNOWN_1_LENGTH: usize = 22;
 const THUMBNAIL_UNKNOWN_2_LENGTH: usize = 16;
 
+/// How many of the instructions, hints, victory, loss, history and scouts messages there are
+fn message_count(layout: DataLayout) -> usize {
+    if layout >= DataLayout::Conquerors { 6 } else { 5 }
+}
+
+/// How many research IDs each player can have disabled
+fn disabled_research_limit(layout: DataLayout) -> usize {
+    if layout >= DataLayout::AgeOfKings { 30 } else { 20 }
+}
+
+const DISABLED_UNIT_LIMIT: usize = 30;
+const DISABLED_BUILDING_LIMIT: usize = 20;
+
 impl PlayerData {
-    // TODO: Implement writing
+    /// Creates empty player data for a new scenario with the given layout
+    pub fn new(layout: DataLayout) -> PlayerData {
+        let aok = layout >= DataLayout::AgeOfKings;
+        PlayerData {
+            version: layout.data_version(),
+            layout: layout,
+            player_names: vec![String::new(); 16],
+            player_name_string_ids: if aok { vec![0; 16] } else { Vec::new() },
+            player_civs: (0..16).map(|_| Default::default()).collect(),
+            unknown1: vec![0u8; PLAYER_DATA_UNKNOWN_1_LENGTH],
+            message_string_ids: if aok { vec![0; message_count(layout)] } else { Vec::new() },
+            preview_thumbnail: PreviewThumbnail {
+                unknown1: vec![0u8; 2],
+                ..Default::default()
+            },
+            ai_names: vec![String::new(); 16],
+            city_names: vec![String::new(); 16],
+            personality_names: vec![String::new(); 16],
+            ai_script_configs: (0..16).map(|_| Default::default()).collect(),
+            ai_types: vec![0u8; 4],
+            player_starting_resources: (0..16).map(|_| Default::default()).collect(),
+            diplomacy: Diplomacy {
+                stances: vec![vec![0; 16]; 16],
+                individual_victory: vec![vec![0; 180]; 16],
+            },
+            allied_victory: vec![0; 16],
+            disabled_research_counts: if aok { vec![0; 16] } else { Vec::new() },
+            disabled_research_ids: vec![vec![0; disabled_research_limit(layout)]; 16],
+            disabled_unit_counts: if aok { vec![0; 16] } else { Vec::new() },
+            disabled_unit_ids: if aok { vec![vec![0; DISABLED_UNIT_LIMIT]; 16] } else { Vec::new() },
+            disabled_building_counts: if aok { vec![0; 16] } else { Vec::new() },
+            disabled_building_ids: if aok { vec![vec![0; DISABLED_BUILDING_LIMIT]; 16] } else { Vec::new() },
+            starting_ages: vec![0; 16],
+            ..Default::default()
+        }
+    }
+
+    /// Version of the scenario data layout
+    #[inline]
+    pub fn version(&self) -> f32 {
+        self.version
+    }
+
+    /// Revision of the data layout, decided by the version
+    #[inline]
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
 
     pub fn read_from_stream<S: Read + Seek>(stream: &mut S) -> Result<PlayerData> {
         let mut data: PlayerData = Default::default();
         data.version = try!(stream.read_f32());
+        data.layout = try!(DataLayout::from_version(data.version));
+        let aok = data.layout >= DataLayout::AgeOfKings;
+
         data.player_names = try!(stream.read_array(16, |s| s.read_sized_str(256)));
+        if aok {
+            data.player_name_string_ids = try!(stream.read_array(16, |s| s.read_u32()));
+        }
         data.player_civs = try!(stream.read_array(16, |s| read_civilization(s)));
         data.conquest_victory = try!(stream.read_u8()) != 0;
 
@@ -140,11 +249,17 @@ impl PlayerData {
         try!(stream.read_exact(&mut data.unknown1));
 
         data.original_file_name = try!(read_pascal_string(stream));
+        if aok {
+            data.message_string_ids = try!(stream.read_array(message_count(data.layout), |s| s.read_u32()));
+        }
         data.instructions = try!(read_pascal_string(stream));
         data.hints = try!(read_pascal_string(stream));
         data.victory = try!(read_pascal_string(stream));
         data.loss = try!(read_pascal_string(stream));
         data.history = try!(read_pascal_string(stream));
+        if data.layout >= DataLayout::Conquerors {
+            data.scouts = try!(read_pascal_string(stream));
+        }
 
         data.pre_game_cinematic_file_name = try!(read_pascal_string(stream));
         data.victory_cinematic_file_name = try!(read_pascal_string(stream));
@@ -159,7 +274,9 @@ impl PlayerData {
         data.ai_script_configs = try!(stream.read_array(16, |s| read_ai_script_config(s)));
         data.ai_types = try!(stream.read_array(4, |s| s.read_u8()));
 
-        data.player_starting_resources = try!(stream.read_array(16, |s| read_player_starting_resources(s)));
+        let layout = data.layout;
+        data.player_starting_resources =
+            try!(stream.read_array(16, |s| read_player_starting_resources(s, layout)));
         try!(stream.read_i32()); // separator (-1)
 
         data.victory_conditions = try!(read_victory_conditions(stream));
@@ -167,7 +284,20 @@ impl PlayerData {
         try!(stream.read_i32()); // separator (-1)
 
         data.allied_victory = try!(stream.read_array(16, |s| s.read_u32()));
-        data.disabled_research_ids = try!(stream.read_array(16, |s| s.read_array(20, |s2| s2.read_u32())));
+        let research_limit = disabled_research_limit(data.layout);
+        if aok {
+            data.disabled_research_counts = try!(stream.read_array(16, |s| s.read_u32()));
+        }
+        data.disabled_research_ids =
+            try!(stream.read_array(16, |s| s.read_array(research_limit, |s2| s2.read_u32())));
+        if aok {
+            data.disabled_unit_counts = try!(stream.read_array(16, |s| s.read_u32()));
+            data.disabled_unit_ids =
+                try!(stream.read_array(16, |s| s.read_array(DISABLED_UNIT_LIMIT, |s2| s2.read_u32())));
+            data.disabled_building_counts = try!(stream.read_array(16, |s| s.read_u32()));
+            data.disabled_building_ids =
+                try!(stream.read_array(16, |s| s.read_array(DISABLED_BUILDING_LIMIT, |s2| s2.read_u32())));
+        }
 
         data.unused1 = try!(stream.read_u32());
         data.unused2 = try!(stream.read_u32());
@@ -178,6 +308,87 @@ impl PlayerData {
 
         Ok(data)
     }
+
+    pub fn write_to_stream<W: Write>(&self, stream: &mut W) -> Result<()> {
+        try!(stream.write_f32(self.version));
+        for name in &self.player_names {
+            try!(stream.write_sized_str(name, 256));
+        }
+        try!(write_u32s(stream, &self.player_name_string_ids));
+        for civ in &self.player_civs {
+            try!(write_civilization(stream, civ));
+        }
+        try!(stream.write_u8(self.conquest_victory as u8));
+        try!(stream.write_all(&self.unknown1));
+
+        try!(stream.write_pascal_string(&self.original_file_name));
+        try!(write_u32s(stream, &self.message_string_ids));
+        for text in &[&self.instructions, &self.hints, &self.victory, &self.loss, &self.history] {
+            try!(stream.write_pascal_string(text));
+        }
+        if self.layout >= DataLayout::Conquerors {
+            try!(stream.write_pascal_string(&self.scouts));
+        }
+        for text in &[&self.pre_game_cinematic_file_name,
+                      &self.victory_cinematic_file_name,
+                      &self.loss_cinematic_file_name,
+                      &self.background_file_name] {
+            try!(stream.write_pascal_string(text));
+        }
+
+        try!(write_preview_thumbnail(stream, &self.preview_thumbnail));
+
+        for names in &[&self.ai_names, &self.city_names, &self.personality_names] {
+            for name in names.iter() {
+                try!(stream.write_pascal_string(name));
+            }
+        }
+        for config in &self.ai_script_configs {
+            try!(write_ai_script_config(stream, config));
+        }
+        try!(stream.write_all(&self.ai_types));
+
+        for resources in &self.player_starting_resources {
+            try!(write_player_starting_resources(stream, resources, self.layout));
+        }
+        try!(stream.write_i32(-1)); // separator
+
+        try!(write_victory_conditions(stream, &self.victory_conditions));
+        try!(write_diplomacy(stream, &self.diplomacy));
+        try!(stream.write_i32(-1)); // separator
+
+        try!(write_u32s(stream, &self.allied_victory));
+        try!(write_u32s(stream, &self.disabled_research_counts));
+        for research_ids in &self.disabled_research_ids {
+            try!(write_u32s(stream, research_ids));
+        }
+        try!(write_u32s(stream, &self.disabled_unit_counts));
+        for unit_ids in &self.disabled_unit_ids {
+            try!(write_u32s(stream, unit_ids));
+        }
+        try!(write_u32s(stream, &self.disabled_building_counts));
+        for building_ids in &self.disabled_building_ids {
+            try!(write_u32s(stream, building_ids));
+        }
+
+        try!(stream.write_u32(self.unused1));
+        try!(stream.write_u32(self.unused2));
+
+        try!(stream.write_u32(self.all_techs as u32));
+        try!(write_u32s(stream, &self.starting_ages));
+        try!(stream.write_i32(-1)); // separator
+
+        Ok(())
+    }
+}
+
+/// Writes each value in turn; the fields that a layout doesn't have are left empty, so they
+/// write nothing
+fn write_u32s<W: Write>(stream: &mut W, values: &[u32]) -> Result<()> {
+    for value in values {
+        try!(stream.write_u32(*value));
+    }
+    Ok(())
 }
 
 fn read_civilization<S: Read + Seek>(stream: &mut S) -> Result<PlayerCivilization> {
@@ -189,6 +400,14 @@ fn read_civilization<S: Read + Seek>(stream: &mut S) -> Result<PlayerCivilizatio
     })
 }
 
+fn write_civilization<W: Write>(stream: &mut W, civ: &PlayerCivilization) -> Result<()> {
+    try!(stream.write_u32(civ.state));
+    try!(stream.write_u32(civ.type_id));
+    try!(stream.write_i32(*civ.civilization_id as i32));
+    try!(stream.write_u32(civ.unknown1));
+    Ok(())
+}
+
 fn read_preview_thumbnail<S: Read + Seek>(stream: &mut S) -> Result<PreviewThumbnail> {
     let mut thumb: PreviewThumbnail = Default::default();
     thumb.included = try!(stream.read_u32()) != 0;
@@ -214,6 +433,19 @@ fn read_preview_thumbnail<S: Read + Seek>(stream: &mut S) -> Result<PreviewThumb
     Ok(thumb)
 }
 
+fn write_preview_thumbnail<W: Write>(stream: &mut W, thumb: &PreviewThumbnail) -> Result<()> {
+    try!(stream.write_u32(thumb.included as u32));
+    try!(stream.write_u32(thumb.width));
+    try!(stream.write_u32(thumb.height));
+    try!(stream.write_all(&thumb.unknown1));
+    if thumb.included {
+        try!(stream.write_u32(thumb.pixel_data_length + 40));
+        try!(stream.write_all(&thumb.unknown2));
+        try!(stream.write_all(&thumb.pixel_data));
+    }
+    Ok(())
+}
+
 fn read_ai_script_config<S: Read>(stream: &mut S) -> Result<AiScriptConfig> {
     let ai_len = try!(stream.read_u32()) as usize;
     let city_len = try!(stream.read_u32()) as usize;
@@ -226,13 +458,46 @@ fn read_ai_script_config<S: Read>(stream: &mut S) -> Result<AiScriptConfig> {
     })
 }
 
-fn read_player_starting_resources<S: Read>(stream: &mut S) -> Result<PlayerStartingResources> {
-    Ok(PlayerStartingResources {
+fn write_ai_script_config<W: Write>(stream: &mut W, config: &AiScriptConfig) -> Result<()> {
+    try!(stream.write_u32(config.ai_file_name.len() as u32));
+    try!(stream.write_u32(config.city_file_name.len() as u32));
+    try!(stream.write_u32(config.personality_file_name.len() as u32));
+    try!(stream.write_all(config.ai_file_name.as_bytes()));
+    try!(stream.write_all(config.city_file_name.as_bytes()));
+    try!(stream.write_all(config.personality_file_name.as_bytes()));
+    Ok(())
+}
+
+fn read_player_starting_resources<S: Read>(stream: &mut S,
+                                           layout: DataLayout)
+                                           -> Result<PlayerStartingResources> {
+    let mut resources = PlayerStartingResources {
         gold: try!(stream.read_u32()),
         wood: try!(stream.read_u32()),
         food: try!(stream.read_u32()),
         stone: try!(stream.read_u32()),
-    })
+        ..Default::default()
+    };
+    if layout >= DataLayout::AgeOfKings {
+        resources.ore = try!(stream.read_u32());
+        resources.unused1 = try!(stream.read_u32());
+    }
+    Ok(resources)
+}
+
+fn write_player_starting_resources<W: Write>(stream: &mut W,
+                                            resources: &PlayerStartingResources,
+                                            layout: DataLayout)
+                                            -> Result<()> {
+    try!(stream.write_u32(resources.gold));
+    try!(stream.write_u32(resources.wood));
+    try!(stream.write_u32(resources.food));
+    try!(stream.write_u32(resources.stone));
+    if layout >= DataLayout::AgeOfKings {
+        try!(stream.write_u32(resources.ore));
+        try!(stream.write_u32(resources.unused1));
+    }
+    Ok(())
 }
 
 fn read_victory_conditions<S: Read>(stream: &mut S) -> Result<VictoryConditions> {
@@ -250,6 +515,20 @@ fn read_victory_conditions<S: Read>(stream: &mut S) -> Result<VictoryConditions>
     })
 }
 
+fn write_victory_conditions<W: Write>(stream: &mut W, conditions: &VictoryConditions) -> Result<()> {
+    try!(stream.write_u32(conditions.conquest_required as u32));
+    try!(stream.write_u32(conditions.unused1));
+    try!(stream.write_u32(conditions.required_relic_count));
+    try!(stream.write_u32(conditions.unused2));
+    try!(stream.write_u32(conditions.required_exploration_percent));
+    try!(stream.write_u32(conditions.unused3));
+    try!(stream.write_u32(conditions.all_conditions_required as u32));
+    try!(stream.write_u32(conditions.victory_mode));
+    try!(stream.write_u32(conditions.score_required));
+    try!(stream.write_u32(conditions.timed_game_time));
+    Ok(())
+}
+
 fn read_diplomacy<S: Read>(stream: &mut S) -> Result<Diplomacy> {
     Ok(Diplomacy {
         stances: try!(stream.read_array(16, |s| s.read_array(16, |s2| s2.read_u32()))),
@@ -257,6 +536,15 @@ fn read_diplomacy<S: Read>(stream: &mut S) -> Result<Diplomacy> {
     })
 }
 
+fn write_diplomacy<W: Write>(stream: &mut W, diplomacy: &Diplomacy) -> Result<()> {
+    for row in diplomacy.stances.iter().chain(diplomacy.individual_victory.iter()) {
+        for value in row {
+            try!(stream.write_u32(*value));
+        }
+    }
+    Ok(())
+}
+
 fn read_pascal_string<S: Read>(stream: &mut S) -> Result<String> {
     let length = try!(stream.read_u16()) as usize;
     Ok(try!(stream.read_sized_str(length)))
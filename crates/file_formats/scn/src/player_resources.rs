@@ -19,12 +19,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use data_layout::DataLayout;
 use error::*;
 use identifier::*;
 
 use chariot_io_tools::*;
 
-use std::io::Read;
+use std::io::{Read, Write};
+use write_ext::WriteExt;
 
 #[derive(Default, Debug)]
 pub struct PlayerResources {
@@ -33,25 +35,48 @@ pub struct PlayerResources {
     pub wood: f32,
     pub gold: f32,
     pub stone: f32,
+
+    /// Age of Kings onwards
+    pub ore: f32,
+    unused1: f32,
 }
 
 impl PlayerResources {
-    // TODO: Implement writing
-
-    pub fn read_from_stream<S: Read>(stream: &mut S) -> Result<Vec<PlayerResources>> {
-        let mut resources = try!(stream.read_array(8, |s| read_single_from_stream(s)));
+    pub fn read_from_stream<S: Read>(stream: &mut S, layout: DataLayout) -> Result<Vec<PlayerResources>> {
+        let mut resources = try!(stream.read_array(8, |s| read_single_from_stream(s, layout)));
         for (index, mut resource) in resources.iter_mut().enumerate() {
             resource.player_id = index.into();
         }
         Ok(resources)
     }
+
+    pub fn write_to_stream<W: Write>(resources: &[PlayerResources],
+                                     stream: &mut W,
+                                     layout: DataLayout)
+                                     -> Result<()> {
+        for resource in resources {
+            try!(stream.write_f32(resource.food));
+            try!(stream.write_f32(resource.wood));
+            try!(stream.write_f32(resource.gold));
+            try!(stream.write_f32(resource.stone));
+            if layout >= DataLayout::AgeOfKings {
+                try!(stream.write_f32(resource.ore));
+                try!(stream.write_f32(resource.unused1));
+            }
+        }
+        Ok(())
+    }
 }
 
-fn read_single_from_stream<S: Read>(stream: &mut S) -> Result<PlayerResources> {
+fn read_single_from_stream<S: Read>(stream: &mut S, layout: DataLayout) -> Result<PlayerResources> {
     let mut data: PlayerResources = Default::default();
     data.food = try!(stream.read_f32());
     data.wood = try!(stream.read_f32());
     data.gold = try!(stream.read_f32());
     data.stone = try!(stream.read_f32());
+    if layout >= DataLayout::AgeOfKings {
+        data.ore = try!(stream.read_f32());
+        data.unused1 = try!(stream.read_f32());
+    }
     Ok(data)
 }
@@ -19,14 +19,16 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use data_layout::DataLayout;
 use error::*;
 use identifier::*;
 
 use chariot_io_tools::*;
 
-use std::io::Read;
+use std::io::{Read, Write};
+use write_ext::WriteExt;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct PlayerUnit {
     pub position_x: f32,
     pub position_y: f32,
@@ -35,12 +37,16 @@ pub struct PlayerUnit {
     pub unit_id: UnitId,
     pub state: u8,
     pub rotation: f32,
+
+    /// Animation frame the unit starts on (Age of Kings onwards)
+    pub frame: u16,
+
+    /// Unit that this one starts garrisoned in (Age of Kings onwards)
+    pub garrisoned_in: Option<SpawnId>,
 }
 
 impl PlayerUnit {
-    // TODO: Implement writing
-
-    pub fn read_from_stream<S: Read>(stream: &mut S) -> Result<PlayerUnit> {
+    pub fn read_from_stream<S: Read>(stream: &mut S, layout: DataLayout) -> Result<PlayerUnit> {
         let mut data: PlayerUnit = Default::default();
         data.position_x = try!(stream.read_f32());
         data.position_y = try!(stream.read_f32());
@@ -49,6 +55,25 @@ impl PlayerUnit {
         data.unit_id = required_id!(try!(stream.read_i16()));
         data.state = try!(stream.read_u8());
         data.rotation = try!(stream.read_f32());
+        if layout >= DataLayout::AgeOfKings {
+            data.frame = try!(stream.read_u16());
+            data.garrisoned_in = optional_id!(try!(stream.read_i32()));
+        }
         Ok(data)
     }
+
+    pub fn write_to_stream<W: Write>(&self, stream: &mut W, layout: DataLayout) -> Result<()> {
+        try!(stream.write_f32(self.position_x));
+        try!(stream.write_f32(self.position_y));
+        try!(stream.write_f32(self.position_z));
+        try!(stream.write_i32(self.spawn_id.map(|id| *id as i32).unwrap_or(-1)));
+        try!(stream.write_i16(*self.unit_id as i16));
+        try!(stream.write_u8(self.state));
+        try!(stream.write_f32(self.rotation));
+        if layout >= DataLayout::AgeOfKings {
+            try!(stream.write_u16(self.frame));
+            try!(stream.write_i32(self.garrisoned_in.map(|id| *id as i32).unwrap_or(-1)));
+        }
+        Ok(())
+    }
 }
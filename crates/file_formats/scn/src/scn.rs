@@ -19,34 +19,80 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use data_layout::DataLayout;
 use error::*;
 
-use identifier::{CivilizationId, PlayerId};
+use identifier::{CivilizationId, PlayerId, SpawnId};
 use chariot_io_tools::*;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
 use map::Map;
 use player_data::PlayerData;
 use player_resources::PlayerResources;
 use player_unit::PlayerUnit;
 use std::fs::File;
+use write_ext::WriteExt;
 
-use std::io;
+use std::io::{self, SeekFrom};
 use std::io::prelude::*;
 use std::path::Path;
 
+/// Header versions written by Age of Empires 1.0 (1.10), by the later patches and Rise of
+/// Rome (1.11), by Age of Kings (1.18) and by The Conquerors (1.21). Rise of Rome's .scx files
+/// share the .scn layout; the data version after the header decides the rest of the layout.
+const SUPPORTED_HEADER_VERSIONS: [&'static str; 4] = ["1.10", "1.11", "1.18", "1.21"];
+
 #[derive(Default, Debug)]
 pub struct Scenario {
     header: ScenarioHeader,
+    next_unit_id: u32,
     pub player_data: PlayerData,
     player_resources: Vec<PlayerResources>,
     player_units: Vec<Vec<PlayerUnit>>,
     pub map: Map,
+
+    /// Everything after the units that isn't parsed yet (triggers and the like);
+    /// kept as is so that it survives being written back out
+    unparsed_data: Vec<u8>,
 }
 
 impl Scenario {
-    /// Retrieves player resources by player ID
+    /// Creates an empty scenario with a map of the given size
+    pub fn new(map_width: u32, map_height: u32) -> Scenario {
+        Scenario::with_layout(map_width, map_height, DataLayout::AgeOfEmpires)
+    }
+
+    /// Creates an empty scenario with a map of the given size, to be written in the given layout
+    pub fn with_layout(map_width: u32, map_height: u32, layout: DataLayout) -> Scenario {
+        Scenario {
+            header: ScenarioHeader {
+                version: layout.header_version().into(),
+                victory_type: Some(0),
+                player_count: Some(0),
+                ..Default::default()
+            },
+            player_data: PlayerData::new(layout),
+            player_resources: (0..8).map(|_| Default::default()).collect(),
+            player_units: (0..9).map(|_| Vec::new()).collect(),
+            map: Map {
+                width: map_width,
+                height: map_height,
+                tiles: (0..map_width * map_height).map(|_| Default::default()).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Retrieves player resources by player ID; the scenario only stores them for 8 players
+    #[inline]
+    pub fn player_resources<'a>(&'a self, player_id: PlayerId) -> Option<&'a PlayerResources> {
+        self.player_resources.get(*player_id as usize)
+    }
+
     #[inline]
-    pub fn player_resources<'a>(&'a self, player_id: PlayerId) -> &'a PlayerResources {
-        &self.player_resources[*player_id as usize]
+    pub fn player_resources_mut<'a>(&'a mut self, player_id: PlayerId) -> Option<&'a mut PlayerResources> {
+        self.player_resources.get_mut(*player_id as usize)
     }
 
     /// Retrieves a list of units by player ID
@@ -55,6 +101,26 @@ impl Scenario {
         &self.player_units[*player_id as usize]
     }
 
+    #[inline]
+    pub fn player_units_mut<'a>(&'a mut self, player_id: PlayerId) -> &'a mut Vec<PlayerUnit> {
+        &mut self.player_units[*player_id as usize]
+    }
+
+    /// Hands out a spawn ID that no other unit in the scenario has
+    pub fn next_spawn_id(&mut self) -> SpawnId {
+        let spawn_id: SpawnId = (self.next_unit_id as usize).into();
+        self.next_unit_id += 1;
+        spawn_id
+    }
+
+    /// Adds a unit for a player, giving it the next unused spawn ID
+    pub fn add_unit(&mut self, player_id: PlayerId, mut unit: PlayerUnit) -> SpawnId {
+        let spawn_id = self.next_spawn_id();
+        unit.spawn_id = Some(spawn_id);
+        self.player_units[*player_id as usize].push(unit);
+        spawn_id
+    }
+
     /// Returns the civilization ID of the given player
     #[inline]
     pub fn player_civilization_id(&self, player_id: PlayerId) -> CivilizationId {
@@ -66,7 +132,23 @@ impl Scenario {
         (0..self.player_units.len()).map(|i| i.into()).collect()
     }
 
-    // TODO: Implement writing
+    /// Version string at the start of the file (such as "1.11")
+    #[inline]
+    pub fn header_version<'a>(&'a self) -> &'a str {
+        &self.header.version
+    }
+
+    /// Version of the compressed scenario data, which determines its layout
+    #[inline]
+    pub fn data_version(&self) -> f32 {
+        self.player_data.version()
+    }
+
+    /// Revision of the compressed scenario data layout, decided by the data version
+    #[inline]
+    pub fn data_layout(&self) -> DataLayout {
+        self.player_data.layout()
+    }
 
     pub fn read_from_file<P: AsRef<Path>>(file_name: P) -> Result<Scenario> {
         Scenario::read_from_stream(try!(File::open(file_name.as_ref())))
@@ -78,24 +160,57 @@ impl Scenario {
 
         let mut stream = io::Cursor::new(try!(stream.read_and_decompress()));
 
-        let _next_unit_id = try!(stream.read_u32()); // not sure what this is for yet
+        scenario.next_unit_id = try!(stream.read_u32()); // not sure what this is for yet
         scenario.player_data = try!(PlayerData::read_from_stream(&mut stream));
-        scenario.map = try!(Map::read_from_stream(&mut stream));
+        let layout = scenario.data_layout();
+        scenario.map = try!(Map::read_from_stream(&mut stream, layout));
 
         let player_unit_group_count = try!(stream.read_u32()) as isize;
-        scenario.player_resources = try!(PlayerResources::read_from_stream(&mut stream));
+        scenario.player_resources = try!(PlayerResources::read_from_stream(&mut stream, layout));
 
         for _player_index in 0..player_unit_group_count {
             let unit_count = try!(stream.read_u32()) as usize;
-            let units = try!(stream.read_array(unit_count, |s| PlayerUnit::read_from_stream(s)));
+            let units = try!(stream.read_array(unit_count, |s| PlayerUnit::read_from_stream(s, layout)));
             scenario.player_units.push(units);
         }
 
         // TODO: Read other player data
         // TODO: Read triggers
+        try!(stream.read_to_end(&mut scenario.unparsed_data));
 
         Ok(scenario)
     }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, file_name: P) -> Result<()> {
+        let mut file = try!(File::create(file_name.as_ref()));
+        self.write_to_stream(&mut file)
+    }
+
+    /// Writes the scenario out in the same format it was read in
+    pub fn write_to_stream<W: Write>(&self, stream: &mut W) -> Result<()> {
+        try!(self.header.write_to_stream(stream));
+
+        let layout = self.data_layout();
+        let mut data = Vec::new();
+        try!(data.write_u32(self.next_unit_id));
+        try!(self.player_data.write_to_stream(&mut data));
+        try!(self.map.write_to_stream(&mut data, layout));
+
+        try!(data.write_u32(self.player_units.len() as u32));
+        try!(PlayerResources::write_to_stream(&self.player_resources, &mut data, layout));
+        for units in &self.player_units {
+            try!(data.write_u32(units.len() as u32));
+            for unit in units {
+                try!(unit.write_to_stream(&mut data, layout));
+            }
+        }
+        try!(data.write_all(&self.unparsed_data));
+
+        let mut encoder = DeflateEncoder::new(stream, Compression::Default);
+        try!(encoder.write_all(&data));
+        try!(encoder.finish());
+        Ok(())
+    }
 }
 
 const REASONABLE_INSTRUCTION_LIMIT: usize = 512 * 1024; // 0.5 mibibytes
@@ -107,21 +222,42 @@ struct ScenarioHeader {
     save_type: i32,
     last_save_time: u32,
     instructions: String,
-    victory_type: u32,
-    player_count: u32,
+    victory_type: Option<u32>,
+    player_count: Option<u32>,
+    unparsed_data: Vec<u8>,
 }
 
 impl ScenarioHeader {
-    // TODO: Implement writing
+    fn write_to_stream<W: Write>(&self, stream: &mut W) -> Result<()> {
+        let mut body = Vec::new();
+        try!(body.write_i32(self.save_type));
+        try!(body.write_u32(self.last_save_time));
+        try!(body.write_long_string(&self.instructions));
+        if let Some(victory_type) = self.victory_type {
+            try!(body.write_u32(victory_type));
+            if let Some(player_count) = self.player_count {
+                try!(body.write_u32(player_count));
+            }
+        }
+        try!(body.write_all(&self.unparsed_data));
+
+        try!(stream.write_sized_str(&self.version, 4));
+        try!(stream.write_u32(body.len() as u32));
+        try!(stream.write_all(&body));
+        Ok(())
+    }
 
     fn read_from_stream<S: Read + Seek>(stream: &mut S) -> Result<ScenarioHeader> {
         let mut header: ScenarioHeader = Default::default();
         header.version = try!(stream.read_sized_str(4));
-        if header.version != "1.11" {
-            return Err(ErrorKind::UnrecognizedScenarioVersion.into());
+        if !SUPPORTED_HEADER_VERSIONS.contains(&&header.version[..]) {
+            return Err(ErrorKind::UnrecognizedScenarioVersion(header.version.clone()).into());
         }
 
         header.length = try!(stream.read_u32());
+        let header_start = try!(stream.seek(SeekFrom::Current(0)));
+        let header_end = header_start + header.length as u64;
+
         header.save_type = try!(stream.read_i32());
         header.last_save_time = try!(stream.read_u32());
         header.instructions = {
@@ -132,8 +268,153 @@ impl ScenarioHeader {
             }
             try!(stream.read_sized_str(length))
         };
-        header.victory_type = try!(stream.read_u32());
-        header.player_count = try!(stream.read_u32());
+
+        // Older revisions don't have all of the trailing fields, and the header length
+        // is the only reliable way to tell which are there
+        let mut position = try!(stream.seek(SeekFrom::Current(0)));
+        if position + 4 <= header_end {
+            header.victory_type = Some(try!(stream.read_u32()));
+            position += 4;
+        }
+        if position + 4 <= header_end {
+            header.player_count = Some(try!(stream.read_u32()));
+            position += 4;
+        }
+        if position > header_end {
+            return Err(ErrorKind::BadHeaderLength.into());
+        }
+        header.unparsed_data = vec![0u8; (header_end - position) as usize];
+        try!(stream.read_exact(&mut header.unparsed_data));
         Ok(header)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use data_layout::DataLayout;
+    use player_unit::PlayerUnit;
+    use super::{Scenario, ScenarioHeader};
+    use std::io::{self, Seek, SeekFrom};
+
+    fn u32_bytes(value: u32) -> Vec<u8> {
+        vec![value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+    }
+
+    fn header_bytes(version: &str, trailing_fields: &[u32]) -> Vec<u8> {
+        let instructions = b"Win";
+        let mut body = Vec::new();
+        body.extend(u32_bytes(2)); // save type
+        body.extend(u32_bytes(12345)); // last save time
+        body.extend(u32_bytes(instructions.len() as u32));
+        body.extend_from_slice(instructions);
+        for field in trailing_fields {
+            body.extend(u32_bytes(*field));
+        }
+
+        let mut bytes = version.as_bytes().to_vec();
+        bytes.extend(u32_bytes(body.len() as u32));
+        bytes.extend(body);
+        bytes.extend_from_slice(b"rest");
+        bytes
+    }
+
+    #[test]
+    fn test_read_header_with_all_fields() {
+        let mut stream = io::Cursor::new(header_bytes("1.11", &[1, 4]));
+        let header = ScenarioHeader::read_from_stream(&mut stream).unwrap();
+        assert_eq!("1.11", header.version);
+        assert_eq!("Win", header.instructions);
+        assert_eq!(Some(1), header.victory_type);
+        assert_eq!(Some(4), header.player_count);
+        assert_eq!(stream.get_ref().len() as u64 - 4,
+                   stream.seek(SeekFrom::Current(0)).unwrap());
+    }
+
+    #[test]
+    fn test_read_header_without_trailing_fields() {
+        let mut stream = io::Cursor::new(header_bytes("1.10", &[]));
+        let header = ScenarioHeader::read_from_stream(&mut stream).unwrap();
+        assert_eq!("1.10", header.version);
+        assert_eq!(None, header.player_count);
+        assert_eq!(stream.get_ref().len() as u64 - 4,
+                   stream.seek(SeekFrom::Current(0)).unwrap());
+    }
+
+    #[test]
+    fn test_read_header_unknown_version() {
+        let mut stream = io::Cursor::new(header_bytes("2.00", &[1, 4]));
+        assert!(ScenarioHeader::read_from_stream(&mut stream).is_err());
+    }
+
+    #[test]
+    fn test_scenario_round_trip() {
+        let mut scenario = Scenario::new(3, 2);
+        scenario.header.instructions = "Build a wonder".into();
+        scenario.player_data.player_names[1] = "Ramses".into();
+        scenario.map.tiles[4].elevation = 2;
+        scenario.unparsed_data = vec![1, 2, 3];
+
+        let mut bytes = Vec::new();
+        scenario.write_to_stream(&mut bytes).unwrap();
+
+        let read_back = Scenario::read_from_stream(io::Cursor::new(&bytes[..])).unwrap();
+        assert_eq!("1.11", read_back.header_version());
+        assert_eq!("Build a wonder", read_back.header.instructions);
+        assert_eq!("Ramses", read_back.player_data.player_names[1]);
+        assert_eq!(2, read_back.map.tiles[4].elevation);
+        assert_eq!(vec![1, 2, 3], read_back.unparsed_data);
+
+        let mut rewritten = Vec::new();
+        read_back.write_to_stream(&mut rewritten).unwrap();
+        assert_eq!(bytes, rewritten);
+    }
+
+    #[test]
+    fn test_scenario_round_trip_with_each_layout() {
+        for &layout in &[DataLayout::AgeOfEmpires, DataLayout::AgeOfKings, DataLayout::Conquerors] {
+            let mut scenario = Scenario::with_layout(3, 2, layout);
+            scenario.player_data.history = "Long ago".into();
+            scenario.player_data.scouts = "Hills to the north".into();
+            scenario.player_data.disabled_research_ids[1][0] = 7;
+            scenario.player_resources[1].ore = 100.0;
+            let barracks = scenario.add_unit(1.into(), Default::default());
+            scenario.add_unit(1.into(),
+                              PlayerUnit {
+                                  frame: 3,
+                                  garrisoned_in: Some(barracks),
+                                  ..Default::default()
+                              });
+
+            let mut bytes = Vec::new();
+            scenario.write_to_stream(&mut bytes).unwrap();
+
+            let read_back = Scenario::read_from_stream(io::Cursor::new(&bytes[..])).unwrap();
+            let aok = layout >= DataLayout::AgeOfKings;
+            assert_eq!(layout, read_back.data_layout());
+            assert_eq!(layout.header_version(), read_back.header_version());
+            assert_eq!("Long ago", read_back.player_data.history);
+            assert_eq!(layout == DataLayout::Conquerors, read_back.player_data.scouts != "");
+            assert_eq!(7, read_back.player_data.disabled_research_ids[1][0]);
+            assert_eq!(aok, read_back.player_resources[1].ore == 100.0);
+
+            let garrisoned = &read_back.player_units(1.into())[1];
+            assert_eq!(aok, garrisoned.frame == 3);
+            assert_eq!(aok, garrisoned.garrisoned_in == Some(barracks));
+
+            let mut rewritten = Vec::new();
+            read_back.write_to_stream(&mut rewritten).unwrap();
+            assert_eq!(bytes, rewritten);
+        }
+    }
+
+    #[test]
+    fn test_add_unit() {
+        let mut scenario = Scenario::new(3, 2);
+        let first = scenario.add_unit(1.into(), PlayerUnit { position_x: 1.5, ..Default::default() });
+        let second = scenario.add_unit(2.into(), Default::default());
+        assert!(first != second);
+        assert_eq!(Some(first), scenario.player_units(1.into())[0].spawn_id);
+        assert_eq!(1.5, scenario.player_units(1.into())[0].position_x);
+        assert_eq!(Some(second), scenario.player_units(2.into())[0].spawn_id);
+    }
+}
@@ -0,0 +1,83 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use error::*;
+
+use std::io::Write;
+
+/// Little-endian counterparts to the reading functions in chariot_io_tools
+pub trait WriteExt: Write {
+    fn write_u8(&mut self, value: u8) -> Result<()> {
+        try!(self.write_all(&[value]));
+        Ok(())
+    }
+
+    fn write_i8(&mut self, value: i8) -> Result<()> {
+        self.write_u8(value as u8)
+    }
+
+    fn write_u16(&mut self, value: u16) -> Result<()> {
+        try!(self.write_all(&[value as u8, (value >> 8) as u8]));
+        Ok(())
+    }
+
+    fn write_i16(&mut self, value: i16) -> Result<()> {
+        self.write_u16(value as u16)
+    }
+
+    fn write_u32(&mut self, value: u32) -> Result<()> {
+        try!(self.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]));
+        Ok(())
+    }
+
+    fn write_i32(&mut self, value: i32) -> Result<()> {
+        self.write_u32(value as u32)
+    }
+
+    fn write_f32(&mut self, value: f32) -> Result<()> {
+        self.write_u32(value.to_bits())
+    }
+
+    /// Writes the string into a fixed size field, padding it out with nulls
+    fn write_sized_str(&mut self, value: &str, size: usize) -> Result<()> {
+        let bytes = value.as_bytes();
+        let length = if bytes.len() < size { bytes.len() } else { size };
+        try!(self.write_all(&bytes[0..length]));
+        try!(self.write_all(&vec![0u8; size - length]));
+        Ok(())
+    }
+
+    /// Writes a string prefixed with its length as a u16
+    fn write_pascal_string(&mut self, value: &str) -> Result<()> {
+        try!(self.write_u16(value.len() as u16));
+        try!(self.write_all(value.as_bytes()));
+        Ok(())
+    }
+
+    /// Writes a string prefixed with its length as a u32
+    fn write_long_string(&mut self, value: &str) -> Result<()> {
+        try!(self.write_u32(value.len() as u32));
+        try!(self.write_all(value.as_bytes()));
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> WriteExt for W {}
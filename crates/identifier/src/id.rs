@@ -53,6 +53,17 @@ macro_rules! create_id_type {
 /// ID for players
 create_id_type!(PlayerId, u8);
 
+impl PlayerId {
+    /// Gaia owns all of the wildlife, resources, and other neutral objects on the map
+    pub fn gaia() -> PlayerId {
+        PlayerId(0)
+    }
+
+    pub fn is_gaia(&self) -> bool {
+        self.0 == 0
+    }
+}
+
 impl Into<PlayerColorId> for PlayerId {
     fn into(self) -> PlayerColorId {
         PlayerColorId(self.0)
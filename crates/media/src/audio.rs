@@ -0,0 +1,320 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use error::*;
+use sdl2;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use std::sync::Arc;
+
+/// The rate sounds are mixed at, if the sound card allows it; the game's sounds are recorded at it
+const DESIRED_RATE: i32 = 22050;
+
+/// Which volume setting a sound plays at, on top of the master volume
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AudioChannel {
+    Sounds,
+    /// The sounds of the map itself, like waves and birds
+    Ambient,
+    Music,
+}
+
+/// A decoded sound, as mono samples at the mixing rate. Cloning it shares the samples.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    samples: Arc<Vec<i16>>,
+    rate: i32,
+}
+
+impl Sound {
+    pub fn duration_nanos(&self) -> u64 {
+        self.samples.len() as u64 * 1000000000 / self.rate.max(1) as u64
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PlaybackId(u64);
+
+struct Playback {
+    id: PlaybackId,
+    samples: Arc<Vec<i16>>,
+    position: usize,
+    channel: AudioChannel,
+    volume: f32,
+    /// From -1 (all the way left) to 1 (all the way right)
+    pan: f32,
+    looping: bool,
+}
+
+impl Playback {
+    fn is_done(&self) -> bool {
+        !self.looping && self.position >= self.samples.len()
+    }
+}
+
+/// Runs on SDL's audio thread, adding up whatever is playing
+struct Mixer {
+    playbacks: Vec<Playback>,
+    output_channels: usize,
+    master_volume: f32,
+    sound_volume: f32,
+    ambient_volume: f32,
+    music_volume: f32,
+}
+
+impl AudioCallback for Mixer {
+    type Channel = i16;
+
+    fn callback(&mut self, out: &mut [i16]) {
+        for sample in out.iter_mut() {
+            *sample = 0;
+        }
+        let output_channels = self.output_channels.max(1);
+        for playback in &mut self.playbacks {
+            let channel_volume = match playback.channel {
+                AudioChannel::Sounds => self.sound_volume,
+                AudioChannel::Ambient => self.ambient_volume,
+                AudioChannel::Music => self.music_volume,
+            };
+            let gain = self.master_volume * channel_volume * playback.volume;
+            let left_gain = gain * (1.0 - playback.pan.max(0.0));
+            let right_gain = gain * (1.0 + playback.pan.min(0.0));
+            for frame in out.chunks_mut(output_channels) {
+                if playback.position >= playback.samples.len() {
+                    if !playback.looping || playback.samples.is_empty() {
+                        break;
+                    }
+                    playback.position = 0;
+                }
+                let sample = playback.samples[playback.position] as f32;
+                playback.position += 1;
+                if frame.len() == 1 {
+                    frame[0] = mix(frame[0], sample * gain);
+                } else {
+                    frame[0] = mix(frame[0], sample * left_gain);
+                    frame[1] = mix(frame[1], sample * right_gain);
+                }
+            }
+        }
+        self.playbacks.retain(|playback| !playback.is_done());
+    }
+}
+
+fn mix(mixed: i16, sample: f32) -> i16 {
+    (mixed as f32 + sample).max(i16::min_value() as f32).min(i16::max_value() as f32) as i16
+}
+
+/// Plays sounds mixed together. Without a sound card, or if SDL can't open one, nothing is heard
+/// but everything else still works.
+pub struct Audio {
+    device: Option<AudioDevice<Mixer>>,
+    rate: i32,
+    next_id: u64,
+}
+
+impl Audio {
+    pub fn new(context: &sdl2::Sdl) -> Audio {
+        let desired = AudioSpecDesired {
+            freq: Some(DESIRED_RATE),
+            channels: Some(2),
+            samples: None,
+        };
+        let mut rate = DESIRED_RATE;
+        let device = context.audio()
+            .and_then(|subsystem| {
+                subsystem.open_playback(None, &desired, |spec| {
+                    rate = spec.freq;
+                    Mixer {
+                        playbacks: Vec::new(),
+                        output_channels: spec.channels as usize,
+                        master_volume: 1.0,
+                        sound_volume: 1.0,
+                        ambient_volume: 1.0,
+                        music_volume: 1.0,
+                    }
+                })
+            })
+            .map_err(|err| warn!("There won't be any sound: {}", err))
+            .ok();
+        if let Some(ref device) = device {
+            device.resume();
+        }
+        Audio {
+            device: device,
+            rate: rate,
+            next_id: 0,
+        }
+    }
+
+    /// Reads a PCM WAV file, like the ones in sounds.drs, into a sound that's ready to be played
+    pub fn decode_wav(&self, bytes: &[u8]) -> Result<Sound> {
+        let (format, data) = try!(read_wav_chunks(bytes));
+        let bytes_per_sample = format.bits_per_sample as usize / 8;
+        let frame_size = bytes_per_sample * format.channels as usize;
+        if frame_size == 0 || (bytes_per_sample != 1 && bytes_per_sample != 2) {
+            return Err(ErrorKind::InvalidWav(format!("{}-bit samples aren't supported",
+                                                     format.bits_per_sample))
+                .into());
+        }
+
+        // Mixed down to mono; 8-bit samples are unsigned, 16-bit ones signed
+        let mono: Vec<i16> = data.chunks(frame_size)
+            .filter(|frame| frame.len() == frame_size)
+            .map(|frame| {
+                let sum: i32 = frame.chunks(bytes_per_sample)
+                    .map(|sample| if bytes_per_sample == 1 {
+                        (sample[0] as i32 - 128) << 8
+                    } else {
+                        sample[0] as i32 | (sample[1] as i8 as i32) << 8
+                    })
+                    .sum();
+                (sum / format.channels as i32) as i16
+            })
+            .collect();
+        Ok(Sound {
+            samples: Arc::new(resample(&mono, format.rate as i32, self.rate)),
+            rate: self.rate,
+        })
+    }
+
+    /// Starts a sound, at a volume from 0 to 1 and a pan from -1 (left) to 1 (right)
+    pub fn play(&mut self,
+                sound: &Sound,
+                channel: AudioChannel,
+                volume: f32,
+                pan: f32,
+                looping: bool)
+                -> PlaybackId {
+        let id = PlaybackId(self.next_id);
+        self.next_id += 1;
+        if let Some(ref mut device) = self.device {
+            device.lock().playbacks.push(Playback {
+                id: id,
+                samples: sound.samples.clone(),
+                position: 0,
+                channel: channel,
+                volume: volume.max(0.0).min(1.0),
+                pan: pan.max(-1.0).min(1.0),
+                looping: looping,
+            });
+        }
+        id
+    }
+
+    pub fn stop(&mut self, id: PlaybackId) {
+        if let Some(ref mut device) = self.device {
+            device.lock().playbacks.retain(|playback| playback.id != id);
+        }
+    }
+
+    /// Changes the volume and pan of a sound that's playing
+    pub fn set_mix(&mut self, id: PlaybackId, volume: f32, pan: f32) {
+        if let Some(ref mut device) = self.device {
+            let mut mixer = device.lock();
+            if let Some(playback) = mixer.playbacks.iter_mut().find(|playback| playback.id == id) {
+                playback.volume = volume.max(0.0).min(1.0);
+                playback.pan = pan.max(-1.0).min(1.0);
+            }
+        }
+    }
+
+    pub fn is_playing(&mut self, id: PlaybackId) -> bool {
+        match self.device {
+            Some(ref mut device) => device.lock().playbacks.iter().any(|playback| playback.id == id),
+            None => false,
+        }
+    }
+
+    /// Sets the master volume and the volume of each channel, each from 0 to 1
+    pub fn set_volumes(&mut self,
+                       master_volume: f32,
+                       sound_volume: f32,
+                       ambient_volume: f32,
+                       music_volume: f32) {
+        if let Some(ref mut device) = self.device {
+            let mut mixer = device.lock();
+            mixer.master_volume = master_volume;
+            mixer.sound_volume = sound_volume;
+            mixer.ambient_volume = ambient_volume;
+            mixer.music_volume = music_volume;
+        }
+    }
+}
+
+struct WavFormat {
+    channels: u16,
+    rate: u32,
+    bits_per_sample: u16,
+}
+
+/// Finds the format and the sample data in a RIFF WAVE file
+fn read_wav_chunks(bytes: &[u8]) -> Result<(WavFormat, &[u8])> {
+    let invalid = |reason: &str| -> Error { ErrorKind::InvalidWav(reason.to_string()).into() };
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF WAVE file"));
+    }
+    let read_u16 = |at: usize| bytes[at] as u16 | (bytes[at + 1] as u16) << 8;
+    let read_u32 = |at: usize| read_u16(at) as u32 | (read_u16(at + 2) as u32) << 16;
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let chunk_size = read_u32(offset + 4) as usize;
+        let start = offset + 8;
+        let end = (start + chunk_size).min(bytes.len());
+        let chunk_id = &bytes[offset..offset + 4];
+        if chunk_id == b"fmt " && end - start >= 16 {
+            // Only uncompressed PCM
+            if read_u16(start) != 1 {
+                return Err(invalid("only PCM is supported"));
+            }
+            format = Some(WavFormat {
+                channels: read_u16(start + 2),
+                rate: read_u32(start + 4),
+                bits_per_sample: read_u16(start + 14),
+            });
+        } else if chunk_id == b"data" {
+            return match format {
+                Some(format) => Ok((format, &bytes[start..end])),
+                None => Err(invalid("the data comes before the format")),
+            };
+        }
+        // Chunks are padded to an even size
+        offset = start + chunk_size + (chunk_size & 1);
+    }
+    Err(invalid("there's no sample data"))
+}
+
+/// Linear interpolation is plenty for the game's short, low rate sounds
+fn resample(samples: &[i16], from_rate: i32, to_rate: i32) -> Vec<i16> {
+    if from_rate == to_rate || from_rate <= 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let count = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    (0..count)
+        .map(|index| {
+            let position = index as f64 * from_rate as f64 / to_rate as f64;
+            let before = position.floor() as usize;
+            let after = (before + 1).min(samples.len() - 1);
+            let fraction = position - before as f64;
+            (samples[before] as f64 * (1.0 - fraction) + samples[after] as f64 * fraction) as i16
+        })
+        .collect()
+}
@@ -0,0 +1,74 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use sdl2::messagebox::{self, ButtonData, ClickedButton, MESSAGEBOX_BUTTON_ESCAPEKEY_DEFAULT,
+                       MESSAGEBOX_BUTTON_RETURNKEY_DEFAULT, MESSAGEBOX_ERROR, MESSAGEBOX_INFORMATION,
+                       MessageBoxFlag};
+
+const ACCEPT_BUTTON_ID: i32 = 1;
+
+/// Shows a blocking error message box. This works before any window has been created,
+/// so it's usable for problems found during startup. Failing to show it isn't fatal, since
+/// the message should have been logged as well.
+pub fn show_error_dialog(title: &str, message: &str) {
+    if let Err(err) = messagebox::show_simple_message_box(MESSAGEBOX_ERROR, title, message, None) {
+        warn!("Failed to show error dialog: {:?}", err);
+    }
+}
+
+/// Shows a blocking error message box with a button to try again, returning whether it was
+/// pressed. Like `show_error_dialog`, this works before any window has been created; if the
+/// box can't be shown, there's nobody to ask, so it returns false.
+pub fn show_retry_dialog(title: &str, message: &str, retry_text: &str) -> bool {
+    show_two_button_dialog(MESSAGEBOX_ERROR, title, message, retry_text, "Quit")
+}
+
+/// Shows a blocking message box asking a question, returning whether the accepting button was
+/// pressed; closing the box or failing to show it counts as declining
+pub fn show_choice_dialog(title: &str, message: &str, accept_text: &str, decline_text: &str) -> bool {
+    show_two_button_dialog(MESSAGEBOX_INFORMATION, title, message, accept_text, decline_text)
+}
+
+fn show_two_button_dialog(flags: MessageBoxFlag,
+                          title: &str,
+                          message: &str,
+                          accept_text: &str,
+                          decline_text: &str)
+                          -> bool {
+    let buttons = [ButtonData {
+                       flags: MESSAGEBOX_BUTTON_ESCAPEKEY_DEFAULT,
+                       button_id: 0,
+                       text: decline_text,
+                   },
+                   ButtonData {
+                       flags: MESSAGEBOX_BUTTON_RETURNKEY_DEFAULT,
+                       button_id: ACCEPT_BUTTON_ID,
+                       text: accept_text,
+                   }];
+    match messagebox::show_message_box(flags, &buttons, title, message, None, None) {
+        Ok(ClickedButton::CustomButton(button)) => button.button_id == ACCEPT_BUTTON_ID,
+        Ok(ClickedButton::CloseButton) => false,
+        Err(err) => {
+            warn!("Failed to show dialog: {:?}", err);
+            false
+        }
+    }
+}
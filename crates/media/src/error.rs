@@ -37,8 +37,13 @@ error_chain! {
         sdl2::video::WindowBuildError, WindowBuildError;
         sdl2::IntegerOrSdlError, IntegerOrSdlError;
         sdl2::render::TextureValueError, TextureValueError;
+        sdl2::render::UpdateTextureError, UpdateTextureError;
     }
 
     errors {
+        InvalidWav(reason: String) {
+            description("invalid WAV file")
+            display("invalid WAV file: {}", reason)
+        }
     }
 }
@@ -0,0 +1,83 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use key::KeyStates;
+use nalgebra::Vector2;
+use sdl2;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Start,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub fn from_sdl(button: sdl2::controller::Button) -> Option<GamepadButton> {
+        use sdl2::controller::Button;
+        Some(match button {
+            Button::A => GamepadButton::A,
+            Button::B => GamepadButton::B,
+            Button::X => GamepadButton::X,
+            Button::Y => GamepadButton::Y,
+            Button::Back => GamepadButton::Back,
+            Button::Start => GamepadButton::Start,
+            Button::LeftShoulder => GamepadButton::LeftShoulder,
+            Button::RightShoulder => GamepadButton::RightShoulder,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+}
+
+/// The first connected gamepad, as of the last update. Sticks range from -1 to 1 on each axis,
+/// with down and right positive like screen coordinates.
+#[derive(Clone, Debug)]
+pub struct GamepadState {
+    pub connected: bool,
+    pub left_stick: Vector2<f32>,
+    pub right_stick: Vector2<f32>,
+    pub buttons: KeyStates<GamepadButton>,
+}
+
+impl GamepadState {
+    pub fn new() -> GamepadState {
+        GamepadState {
+            connected: false,
+            left_stick: Vector2::new(0.0, 0.0),
+            right_stick: Vector2::new(0.0, 0.0),
+            buttons: KeyStates::new(HashMap::new()),
+        }
+    }
+}
@@ -31,10 +31,100 @@ pub enum Key {
     Left,
     Right,
 
-    Space, // Add keys as necessary
+    Space,
+    Comma,
+    Period,
+    Backquote,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    Return,
+    Backspace,
+    Escape,
+    Plus,
+    Minus,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    Q,
+    R,
+    S,
+    T,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Tab,
+    Shift,
+    Ctrl,
+    Home,
+    End,
+    Delete,
+    PageUp,
+    PageDown, // Add keys as necessary
 }
 
+/// Names used for keys in config files
+const KEY_NAMES: [(Key, &'static str); 42] = [(Key::Up, "Up"),
+                                              (Key::Down, "Down"),
+                                              (Key::Left, "Left"),
+                                              (Key::Right, "Right"),
+                                              (Key::Space, "Space"),
+                                              (Key::Comma, "Comma"),
+                                              (Key::Period, "Period"),
+                                              (Key::Backquote, "Backquote"),
+                                              (Key::F3, "F3"),
+                                              (Key::F4, "F4"),
+                                              (Key::F5, "F5"),
+                                              (Key::F6, "F6"),
+                                              (Key::F7, "F7"),
+                                              (Key::Return, "Return"),
+                                              (Key::Backspace, "Backspace"),
+                                              (Key::Escape, "Escape"),
+                                              (Key::Plus, "Plus"),
+                                              (Key::Minus, "Minus"),
+                                              (Key::A, "A"),
+                                              (Key::B, "B"),
+                                              (Key::C, "C"),
+                                              (Key::D, "D"),
+                                              (Key::E, "E"),
+                                              (Key::F, "F"),
+                                              (Key::G, "G"),
+                                              (Key::Q, "Q"),
+                                              (Key::R, "R"),
+                                              (Key::S, "S"),
+                                              (Key::T, "T"),
+                                              (Key::V, "V"),
+                                              (Key::W, "W"),
+                                              (Key::X, "X"),
+                                              (Key::Y, "Y"),
+                                              (Key::Z, "Z"),
+                                              (Key::Tab, "Tab"),
+                                              (Key::Shift, "Shift"),
+                                              (Key::Ctrl, "Ctrl"),
+                                              (Key::Home, "Home"),
+                                              (Key::End, "End"),
+                                              (Key::Delete, "Delete"),
+                                              (Key::PageUp, "PageUp"),
+                                              (Key::PageDown, "PageDown")];
+
 impl Key {
+    /// Looks up a key by its config file name, ignoring case
+    pub fn from_name(name: &str) -> Option<Key> {
+        KEY_NAMES.iter().find(|&&(_, key_name)| key_name.eq_ignore_ascii_case(name)).map(|&(key, _)| key)
+    }
+
+    pub fn name(&self) -> &'static str {
+        KEY_NAMES.iter().find(|&&(key, _)| key == *self).map(|&(_, name)| name).unwrap()
+    }
+
     pub fn from_sdl(scancode: sdl2::keyboard::Scancode) -> Option<Key> {
         sdl2::keyboard::Keycode::from_scancode(scancode).and_then(|keycode| {
             use sdl2::keyboard::Keycode::*;
@@ -44,6 +134,43 @@ impl Key {
                 Left => Key::Left,
                 Right => Key::Right,
                 Space => Key::Space,
+                Comma => Key::Comma,
+                Period => Key::Period,
+                Backquote => Key::Backquote,
+                F3 => Key::F3,
+                F4 => Key::F4,
+                F5 => Key::F5,
+                F6 => Key::F6,
+                F7 => Key::F7,
+                Return => Key::Return,
+                Backspace => Key::Backspace,
+                Escape => Key::Escape,
+                Equals | Plus | KpPlus => Key::Plus,
+                Minus | KpMinus => Key::Minus,
+                A => Key::A,
+                B => Key::B,
+                C => Key::C,
+                D => Key::D,
+                E => Key::E,
+                F => Key::F,
+                G => Key::G,
+                Q => Key::Q,
+                R => Key::R,
+                S => Key::S,
+                T => Key::T,
+                V => Key::V,
+                W => Key::W,
+                X => Key::X,
+                Y => Key::Y,
+                Z => Key::Z,
+                Tab => Key::Tab,
+                LShift | RShift => Key::Shift,
+                LCtrl | RCtrl => Key::Ctrl,
+                Home => Key::Home,
+                End => Key::End,
+                Delete => Key::Delete,
+                PageUp => Key::PageUp,
+                PageDown => Key::PageDown,
                 _ => return None,
             })
         })
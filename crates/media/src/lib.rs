@@ -27,26 +27,40 @@ extern crate error_chain;
 extern crate sdl2;
 extern crate nalgebra;
 
+#[macro_use]
+extern crate tracing;
+
 extern crate chariot_types as types;
 
+mod audio;
+mod cursor;
+mod dialog;
 mod error;
+mod gamepad;
 mod key;
 mod media;
 mod renderer;
 mod texture;
 mod texture_builder;
+mod touch;
+
+pub use audio::{Audio, AudioChannel, PlaybackId, Sound};
+pub use cursor::HardwareCursor;
+pub use dialog::{show_choice_dialog, show_error_dialog, show_retry_dialog};
 
 pub use error::ChainErr;
 pub use error::Error;
 pub use error::ErrorKind;
 pub use error::Result;
 
+pub use gamepad::{GamepadButton, GamepadState};
 pub use key::{Key, KeyState, KeyStates, MouseButton};
 pub use media::{Media, MediaRef};
 
 pub use media::create_media;
 
-pub use renderer::Renderer;
+pub use renderer::{FullscreenMode, Renderer};
 
 pub use texture::Texture;
 pub use texture_builder::TextureBuilder;
+pub use touch::Touch;
@@ -19,11 +19,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use audio::Audio;
+use cursor::{self, HardwareCursor};
 use error::*;
+use gamepad::{GamepadButton, GamepadState};
 use key::{Key, KeyState, KeyStates, MouseButton};
 
 use nalgebra::Vector2;
-use renderer::Renderer;
+use renderer::{FullscreenMode, Renderer};
 
 use sdl2;
 use std::cell::RefCell;
@@ -31,6 +34,10 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::rc::Rc;
+use touch::Touch;
+
+/// Stick positions closer to the middle than this count as centred, since sticks rarely rest at 0
+const STICK_DEAD_ZONE: f32 = 0.2;
 
 pub trait Media {
     fn is_open(&self) -> bool;
@@ -38,54 +45,146 @@ pub trait Media {
 
     fn key_states(&self) -> &KeyStates<Key>;
 
+    /// Text typed since the last update, already translated by the keyboard layout
+    fn text_input<'a>(&'a self) -> &'a str;
+
+    /// Text that's being put together with an input method, such as for Chinese or Japanese,
+    /// and hasn't been typed yet. It can be shown where it'll go, but it comes through
+    /// `text_input` once it's finished, so it shouldn't be taken as typed.
+    fn text_composition<'a>(&'a self) -> &'a str;
+
     fn mouse_position(&self) -> Vector2<i32>;
     fn mouse_button_states<'a>(&'a self) -> &'a KeyStates<MouseButton>;
 
+    fn gamepad<'a>(&'a self) -> &'a GamepadState;
+
+    /// Fingers on the touch screen, in the order they were put down
+    fn touches<'a>(&'a self) -> &'a [Touch];
+
     fn renderer<'a>(&'a mut self) -> &'a mut Renderer;
+    fn audio<'a>(&'a mut self) -> &'a mut Audio;
     fn viewport_size(&self) -> Vector2<u32>;
+
+    fn is_fullscreen(&self) -> bool;
+
+    /// Switches between windowed and fullscreen; Alt+Enter does the same, with the mode last set
+    fn set_fullscreen(&mut self, fullscreen: bool, mode: FullscreenMode);
+
+    /// How many window pixels each drawn pixel covers; 0 picks a whole number from the window's
+    /// height, so that the HUD isn't tiny on big screens
+    fn set_render_scale(&mut self, scale: u32);
+
+    /// Shows the cursor in place of the mouse pointer, or the system's own arrow with None
+    fn set_cursor(&mut self, cursor: Option<&HardwareCursor>);
+
+    /// Hides the mouse pointer, for when a cursor is drawn with the rest of the frame instead
+    fn show_cursor(&mut self, shown: bool);
 }
 
 pub type MediaRef = Rc<RefCell<Box<Media>>>;
 
-pub fn create_media(width: u32, height: u32, title: &str) -> Result<MediaRef> {
-    SdlMedia::new(width, height, title).map(|m| Rc::new(RefCell::new(Box::new(m) as Box<Media>)))
+/// With `vsync`, showing each frame waits for the display to refresh
+pub fn create_media(width: u32, height: u32, vsync: bool, title: &str) -> Result<MediaRef> {
+    SdlMedia::new(width, height, vsync, title).map(|m| Rc::new(RefCell::new(Box::new(m) as Box<Media>)))
 }
 
 struct SdlMedia {
     context: sdl2::Sdl,
     renderer: Renderer,
+    audio: Audio,
     open: bool,
     keys_pressed: HashSet<Key>,
     key_states: KeyStates<Key>,
+    text_input: String,
+    text_composition: String,
     mouse_position: Vector2<i32>,
     mouse_button_states: KeyStates<MouseButton>,
-    initial_width: u32,
-    initial_height: u32,
-    scale_x: f32,
-    scale_y: f32,
+    controller_subsystem: Option<sdl2::GameControllerSubsystem>,
+    controllers: Vec<sdl2::controller::GameController>,
+    gamepad: GamepadState,
+    touches: Vec<Touch>,
+    fullscreen_mode: FullscreenMode,
+    /// The system's arrow, for going back to from a hardware cursor
+    system_cursor: Option<sdl2::mouse::Cursor>,
 }
 
 impl SdlMedia {
-    fn new(width: u32, height: u32, title: &str) -> Result<SdlMedia> {
+    fn new(width: u32, height: u32, vsync: bool, title: &str) -> Result<SdlMedia> {
+        // Touches are handled as gestures of their own rather than as pretend mouse clicks
+        sdl2::hint::set("SDL_TOUCH_MOUSE_EVENTS", "0");
         let mut context = try!(sdl2::init());
-        let renderer = try!(Renderer::new(&mut context, width, height, title));
+        let renderer = try!(Renderer::new(&mut context, width, height, vsync, title));
+        let audio = Audio::new(&context);
+        // Gamepads are optional, so not having the subsystem for them isn't a failure
+        let controller_subsystem = context.game_controller()
+            .map_err(|err| warn!("Gamepads won't work: {}", err))
+            .ok();
 
         Ok(SdlMedia {
             context: context,
             renderer: renderer,
+            audio: audio,
             open: true,
             keys_pressed: HashSet::new(),
             key_states: KeyStates::new(HashMap::new()),
+            text_input: String::new(),
+            text_composition: String::new(),
             mouse_position: Vector2::new(0, 0),
             mouse_button_states: KeyStates::new(HashMap::new()),
-            initial_width: width,
-            initial_height: height,
-            scale_x: 1f32,
-            scale_y: 1f32,
+            controller_subsystem: controller_subsystem,
+            controllers: Vec::new(),
+            gamepad: GamepadState::new(),
+            touches: Vec::new(),
+            fullscreen_mode: FullscreenMode::Borderless,
+            system_cursor: sdl2::mouse::Cursor::from_system(sdl2::mouse::SystemCursor::Arrow)
+                .map_err(|err| warn!("Failed to get the system's cursor: {}", err))
+                .ok(),
         })
     }
 }
 
+impl SdlMedia {
+    /// Touch positions come as fractions of the window's size
+    fn touch_position(&self, x: f32, y: f32) -> Vector2<i32> {
+        let viewport_size = self.renderer.viewport_size();
+        Vector2::new((x * viewport_size.x as f32) as i32, (y * viewport_size.y as f32) as i32)
+    }
+
+    fn update_gamepad(&mut self) {
+        use sdl2::controller::{Axis, Button};
+
+        let (connected, left_stick, right_stick, pressed) = match self.controllers.first() {
+            Some(controller) => {
+                let stick = |x_axis, y_axis| {
+                    Vector2::new(stick_axis(controller.axis(x_axis)), stick_axis(controller.axis(y_axis)))
+                };
+                let buttons = [Button::A,
+                               Button::B,
+                               Button::X,
+                               Button::Y,
+                               Button::Back,
+                               Button::Start,
+                               Button::LeftShoulder,
+                               Button::RightShoulder,
+                               Button::DPadUp,
+                               Button::DPadDown,
+                               Button::DPadLeft,
+                               Button::DPadRight];
+                let pressed: HashSet<GamepadButton> = buttons.iter()
+                    .filter(|&&button| controller.button(button))
+                    .filter_map(|&button| GamepadButton::from_sdl(button))
+                    .collect();
+                (true, stick(Axis::LeftX, Axis::LeftY), stick(Axis::RightX, Axis::RightY), pressed)
+            }
+            None => (false, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), HashSet::new()),
+        };
+        self.gamepad.connected = connected;
+        self.gamepad.left_stick = left_stick;
+        self.gamepad.right_stick = right_stick;
+        self.gamepad.buttons = update_key_states(&self.gamepad.buttons, &pressed);
+    }
+}
+
 impl Media for SdlMedia {
     fn is_open(&self) -> bool {
         self.open
@@ -93,32 +192,81 @@ impl Media for SdlMedia {
 
     fn update(&mut self) {
         use sdl2::event::{Event, WindowEvent};
+        use sdl2::keyboard::{Keycode, LALTMOD, RALTMOD, Scancode};
 
         let mut event_pump = match self.context.event_pump() {
             Ok(pump) => pump,
             Err(err) => {
-                println!("Failed to handle window events: {}", err);
+                warn!("Failed to handle window events: {}", err);
                 self.open = false;
                 return;
             }
         };
 
+        self.text_input.clear();
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => {
                     self.open = false;
                 }
-                Event::Window { win_event: WindowEvent::Resized(data1, data2), .. } => {
-                    self.scale_x = data1 as f32 / self.initial_width as f32;
-                    self.scale_y = data2 as f32 / self.initial_height as f32;
-                    self.renderer.set_scale(self.scale_x, self.scale_y);
+                Event::Window { win_event: WindowEvent::SizeChanged(..), .. } => {
+                    self.renderer.update_scale();
+                }
+                Event::KeyDown { keycode: Some(Keycode::Return), keymod, repeat: false, .. }
+                    if keymod.intersects(LALTMOD | RALTMOD) => {
+                    let fullscreen = !self.renderer.is_fullscreen();
+                    self.renderer.set_fullscreen(fullscreen, self.fullscreen_mode);
+                }
+                Event::TextInput { text, .. } => {
+                    self.text_input.push_str(&text);
+                    self.text_composition.clear();
+                }
+                Event::TextEditing { text, .. } => {
+                    // Sent again as the composition changes, and empty once it's done or dropped
+                    self.text_composition = text;
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Some(ref subsystem) = self.controller_subsystem {
+                        match subsystem.open(which) {
+                            Ok(controller) => {
+                                info!("Gamepad connected: {}", controller.name());
+                                self.controllers.push(controller);
+                            }
+                            Err(err) => warn!("Failed to open gamepad {}: {}", which, err),
+                        }
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.controllers.retain(|controller| controller.instance_id() != which);
+                }
+                Event::FingerDown { finger_id, x, y, .. } => {
+                    let position = self.touch_position(x, y);
+                    self.touches.push(Touch {
+                        id: finger_id,
+                        position: position,
+                    });
+                }
+                Event::FingerMotion { finger_id, x, y, .. } => {
+                    let position = self.touch_position(x, y);
+                    for touch in self.touches.iter_mut().filter(|touch| touch.id == finger_id) {
+                        touch.position = position;
+                    }
+                }
+                Event::FingerUp { finger_id, .. } => {
+                    self.touches.retain(|touch| touch.id != finger_id);
                 }
                 _ => {}
             }
         }
 
-        self.keys_pressed =
-            event_pump.keyboard_state().pressed_scancodes().filter_map(Key::from_sdl).collect();
+        let keyboard_state = event_pump.keyboard_state();
+        self.keys_pressed = keyboard_state.pressed_scancodes().filter_map(Key::from_sdl).collect();
+        // Return is left out while Alt is held, so that Alt+Enter doesn't also open the chat box
+        let alt_held = keyboard_state.is_scancode_pressed(Scancode::LAlt) ||
+                       keyboard_state.is_scancode_pressed(Scancode::RAlt);
+        if alt_held {
+            self.keys_pressed.remove(&Key::Return);
+        }
 
         let new_key_states = update_key_states(&self.key_states, &self.keys_pressed);
         self.key_states = new_key_states;
@@ -128,28 +276,82 @@ impl Media for SdlMedia {
 
         let new_mouse_states = update_key_states(&self.mouse_button_states, &determine_pressed_mouse_buttons(&mouse_state));
         self.mouse_button_states = new_mouse_states;
+
+        self.update_gamepad();
     }
 
     fn key_states(&self) -> &KeyStates<Key> {
         &self.key_states
     }
 
+    fn text_input<'a>(&'a self) -> &'a str {
+        &self.text_input
+    }
+
+    fn text_composition<'a>(&'a self) -> &'a str {
+        &self.text_composition
+    }
+
     fn mouse_position(&self) -> Vector2<i32> {
-        Vector2::new((self.mouse_position.x as f32 / self.scale_x) as i32,
-                     (self.mouse_position.y as f32 / self.scale_y) as i32)
+        let scale = self.renderer.scale() as i32;
+        Vector2::new(self.mouse_position.x / scale, self.mouse_position.y / scale)
     }
 
     fn mouse_button_states<'a>(&'a self) -> &'a KeyStates<MouseButton> {
         &self.mouse_button_states
     }
 
+    fn gamepad<'a>(&'a self) -> &'a GamepadState {
+        &self.gamepad
+    }
+
+    fn touches<'a>(&'a self) -> &'a [Touch] {
+        &self.touches
+    }
+
     fn renderer<'a>(&'a mut self) -> &'a mut Renderer {
         &mut self.renderer
     }
 
+    fn audio<'a>(&'a mut self) -> &'a mut Audio {
+        &mut self.audio
+    }
+
     fn viewport_size(&self) -> Vector2<u32> {
         self.renderer.viewport_size()
     }
+
+    fn is_fullscreen(&self) -> bool {
+        self.renderer.is_fullscreen()
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool, mode: FullscreenMode) {
+        let mode_changed = mode != self.fullscreen_mode;
+        self.fullscreen_mode = mode;
+        if fullscreen != self.renderer.is_fullscreen() || (fullscreen && mode_changed) {
+            self.renderer.set_fullscreen(fullscreen, mode);
+        }
+    }
+
+    fn set_render_scale(&mut self, scale: u32) {
+        self.renderer.set_scale(scale);
+    }
+
+    fn set_cursor(&mut self, cursor: Option<&HardwareCursor>) {
+        match cursor {
+            Some(cursor) => cursor::sdl_cursor(cursor).set(),
+            None => {
+                if let Some(ref system_cursor) = self.system_cursor {
+                    system_cursor.set();
+                }
+            }
+        }
+        self.show_cursor(true);
+    }
+
+    fn show_cursor(&mut self, shown: bool) {
+        self.context.mouse().show_cursor(shown);
+    }
 }
 
 fn update_key_states<K: Eq + Hash + Copy>(key_states: &KeyStates<K>,
@@ -172,6 +374,11 @@ fn update_key_states<K: Eq + Hash + Copy>(key_states: &KeyStates<K>,
     KeyStates::new(new_states)
 }
 
+fn stick_axis(value: i16) -> f32 {
+    let value = value as f32 / 32767.0;
+    if value.abs() < STICK_DEAD_ZONE { 0.0 } else { value.max(-1.0).min(1.0) }
+}
+
 fn determine_pressed_mouse_buttons(mouse_state: &sdl2::mouse::MouseState) -> HashSet<MouseButton> {
     let mut buttons = HashSet::new();
     if mouse_state.left() {
@@ -27,6 +27,22 @@ use sdl2;
 use texture::{self, SdlTexture, Texture};
 use types::{Color, Rect};
 
+/// The window can be made smaller than the size it starts at, down to this
+const MIN_WINDOW_SIZE: (u32, u32) = (640, 480);
+
+/// Height of the original game's 800x600 layout; the automatic render scale is the number of
+/// times it fits in the window, so the HUD stays about as big on screen at higher resolutions
+const REFERENCE_HEIGHT: u32 = 600;
+
+/// How the window covers the screen when it's fullscreen
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FullscreenMode {
+    /// A borderless window as big as the desktop, which is quick to switch in and out of
+    Borderless,
+    /// Switches the display to the window's resolution
+    Exclusive,
+}
+
 // Separate so that it's not exported with the crate
 pub trait SdlRenderer {
     fn create_texture_from_surface(&mut self, surface: sdl2::surface::Surface) -> Result<Texture>;
@@ -36,25 +52,40 @@ pub struct Renderer {
     camera_pos: Vector2<i32>,
     _video: sdl2::VideoSubsystem,
     renderer: sdl2::render::Renderer<'static>,
+    /// The render scale asked for, with 0 meaning automatic
+    requested_scale: u32,
+    /// How many window pixels each drawn pixel covers
+    scale: u32,
 }
 
 impl Renderer {
-    pub fn new(sdl_context: &mut sdl2::Sdl, width: u32, height: u32, title: &str) -> Result<Renderer> {
+    pub fn new(sdl_context: &mut sdl2::Sdl,
+               width: u32,
+               height: u32,
+               vsync: bool,
+               title: &str)
+               -> Result<Renderer> {
         let video = try!(sdl_context.video());
         let mut window = try!(video.window(title, width, height)
             .position_centered()
             .resizable()
             .opengl()
             .build());
-        window.set_minimum_size(width, height).expect("set window min size");
+        window.set_minimum_size(MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1).expect("set window min size");
 
-        let renderer = try!(window.renderer().present_vsync().build());
-        println!("Renderer initialized with {:#?}", renderer.info());
+        let mut builder = window.renderer();
+        if vsync {
+            builder = builder.present_vsync();
+        }
+        let renderer = try!(builder.build());
+        info!("Renderer initialized with {:#?}", renderer.info());
 
         Ok(Renderer {
             camera_pos: Vector2::new(0, 0),
             _video: video,
             renderer: renderer,
+            requested_scale: 1,
+            scale: 1,
         })
     }
 
@@ -64,13 +95,65 @@ impl Renderer {
         self.renderer.clear();
     }
 
+    /// Size of the area that's drawn to, which is the window's size divided by the render scale
     pub fn viewport_size(&self) -> Vector2<u32> {
+        let window_size = self.window_size();
+        Vector2::new(window_size.x / self.scale, window_size.y / self.scale)
+    }
+
+    pub fn window_size(&self) -> Vector2<u32> {
         let size = self.renderer.window().unwrap().size();
         Vector2::new(size.0, size.1)
     }
 
-    pub fn set_scale(&mut self, scale_x: f32, scale_y: f32) {
-        self.renderer.set_scale(scale_x, scale_y).expect("set render scale");
+    #[inline]
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Sets how many window pixels each drawn pixel covers; 0 picks it from the window's height
+    pub fn set_scale(&mut self, scale: u32) {
+        self.requested_scale = scale;
+        self.update_scale();
+    }
+
+    /// Picks the automatic render scale again, for after the window has changed size
+    pub fn update_scale(&mut self) {
+        let scale = if self.requested_scale == 0 {
+            (self.window_size().y / REFERENCE_HEIGHT).max(1)
+        } else {
+            self.requested_scale
+        };
+        if scale != self.scale {
+            self.scale = scale;
+            self.renderer.set_scale(scale as f32, scale as f32).expect("set render scale");
+        }
+    }
+
+    /// How many times a second the display the window is on refreshes, when it's known
+    pub fn refresh_rate(&self) -> Option<u32> {
+        match self.renderer.window().unwrap().display_mode() {
+            Ok(mode) if mode.refresh_rate > 0 => Some(mode.refresh_rate as u32),
+            _ => None,
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        use sdl2::video::FullscreenType;
+        self.renderer.window().unwrap().fullscreen_state() != FullscreenType::Off
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool, mode: FullscreenMode) {
+        use sdl2::video::FullscreenType;
+        let fullscreen_type = match (fullscreen, mode) {
+            (false, _) => FullscreenType::Off,
+            (true, FullscreenMode::Borderless) => FullscreenType::Desktop,
+            (true, FullscreenMode::Exclusive) => FullscreenType::True,
+        };
+        if let Err(err) = self.renderer.window_mut().unwrap().set_fullscreen(fullscreen_type) {
+            warn!("Failed to change to {:?}: {}", fullscreen_type, err);
+        }
+        self.update_scale();
     }
 
     pub fn set_camera_position(&mut self, position: &Vector2<i32>) {
@@ -94,10 +177,46 @@ impl Renderer {
                      flip_horizontal,
                      flip_vertical)
             .unwrap_or_else(|err| {
-                println!("Failed to render texture: {}", err);
+                warn!("Failed to render texture: {}", err);
             });
     }
 
+    /// Creates a blank, see-through texture that can be drawn into with `render_to_texture`
+    pub fn create_target_texture(&mut self, width: u32, height: u32) -> Result<Texture> {
+        let mut sdl_texture = try!(self.renderer
+            .create_texture_target(sdl2::pixels::PixelFormatEnum::ARGB8888, width, height));
+        sdl_texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+        Ok(texture::create_texture(sdl_texture, width, height))
+    }
+
+    /// Draws into the texture instead of the window, which has to have been made with
+    /// `create_target_texture`. It's cleared first, and `draw` renders with the camera at
+    /// `origin`, so that what's at `origin` lands in the texture's top left corner, unscaled.
+    pub fn render_to_texture<F>(&mut self, texture: Texture, origin: Vector2<i32>, draw: F) -> Result<Texture>
+        where F: FnOnce(&mut Renderer)
+    {
+        let (width, height) = (texture.width, texture.height);
+        {
+            let mut target = try!(self.renderer.render_target().ok_or("Render targets aren't supported"));
+            try!(target.set(texture::into_sdl_texture(texture)));
+        }
+        let camera_pos = self.camera_pos;
+        self.camera_pos = origin;
+        try!(self.renderer.set_scale(1., 1.));
+        self.set_render_color(Color::rgba(0, 0, 0, 0));
+        self.renderer.clear();
+
+        draw(self);
+
+        self.camera_pos = camera_pos;
+        let sdl_texture = {
+            let mut target = try!(self.renderer.render_target().ok_or("Render targets aren't supported"));
+            try!(try!(target.reset()).ok_or("Render target went missing"))
+        };
+        try!(self.renderer.set_scale(self.scale as f32, self.scale as f32));
+        Ok(texture::create_texture(sdl_texture, width, height))
+    }
+
     pub fn set_render_color(&mut self, color: Color) {
         self.renderer.set_draw_color(color.into());
     }
@@ -108,6 +227,25 @@ impl Renderer {
         self.renderer.draw_rect(rect.into()).expect("Failed to draw rect");
     }
 
+    pub fn fill_rect(&mut self, mut rect: Rect) {
+        rect.x -= self.camera_pos.x;
+        rect.y -= self.camera_pos.y;
+        self.renderer.fill_rect(rect.into()).expect("Failed to fill rect");
+    }
+
+    /// Multiplies everything drawn so far by the color, over the whole viewport; white leaves
+    /// it as it is, and darker or colored tints darken or color it
+    pub fn modulate_screen(&mut self, color: Color) {
+        let size = self.viewport_size();
+        let blend_mode = self.renderer.blend_mode();
+        self.renderer.set_blend_mode(sdl2::render::BlendMode::Mod);
+        self.set_render_color(color);
+        self.renderer
+            .fill_rect(sdl2::rect::Rect::new(0, 0, size.x, size.y))
+            .expect("Failed to modulate screen");
+        self.renderer.set_blend_mode(blend_mode);
+    }
+
     pub fn render_line(&mut self, mut first: Vector2<i32>, mut second: Vector2<i32>) {
         first.x -= self.camera_pos.x;
         first.y -= self.camera_pos.y;
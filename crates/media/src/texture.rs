@@ -37,13 +37,22 @@ pub fn create_texture(sdl_texture: sdl2::render::Texture, width: u32, height: u3
     }
 }
 
+pub fn into_sdl_texture(texture: Texture) -> sdl2::render::Texture {
+    texture.texture
+}
+
 // Separate so that it's not exported with the crate
 pub trait SdlTexture {
     fn sdl_texture<'a>(&'a self) -> &'a sdl2::render::Texture;
+    fn sdl_texture_mut<'a>(&'a mut self) -> &'a mut sdl2::render::Texture;
 }
 
 impl SdlTexture for Texture {
     fn sdl_texture<'a>(&'a self) -> &'a sdl2::render::Texture {
         &self.texture
     }
+
+    fn sdl_texture_mut<'a>(&'a mut self) -> &'a mut sdl2::render::Texture {
+        &mut self.texture
+    }
 }
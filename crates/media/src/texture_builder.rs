@@ -19,15 +19,18 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use cursor::{self, HardwareCursor};
 use error::*;
+use nalgebra::Vector2;
 use renderer::{Renderer, SdlRenderer};
+use sdl2;
 use sdl2::pixels::PixelFormatEnum;
 
 use sdl2::surface::Surface;
 
 use std::io::{self, Write};
 use std::mem;
-use texture::Texture;
+use texture::{SdlTexture, Texture};
 use types::Rect;
 
 pub struct TextureBuilder<'a> {
@@ -77,11 +80,36 @@ impl<'a> TextureBuilder<'a> {
             }
         };
 
-        let surf_result = Surface::from_data(&mut pixels,
+        self.blit_pixels(&mut pixels, PixelFormatEnum::RGBA8888, src_rect, dst_rect)
+    }
+
+    /// Blits already decoded pixels laid out as R, G, B, A bytes (such as those from a PNG)
+    pub fn blit_rgba(self, rgba_pixels: &mut [u8], src_rect: Rect, dst_rect: Rect) -> Self {
+        // SDL names packed formats by component order within a u32, so byte order RGBA is
+        // ABGR8888 on little endian machines
+        let format = if cfg!(target_endian = "little") {
+            PixelFormatEnum::ABGR8888
+        } else {
+            PixelFormatEnum::RGBA8888
+        };
+        self.blit_pixels(rgba_pixels, format, src_rect, dst_rect)
+    }
+
+    fn blit_pixels(mut self,
+                   pixels: &mut [u8],
+                   format: PixelFormatEnum,
+                   src_rect: Rect,
+                   dst_rect: Rect)
+                   -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+
+        let surf_result = Surface::from_data(pixels,
                                              src_rect.w as u32,
                                              src_rect.h as u32,
                                              4 * (src_rect.w as u32),
-                                             PixelFormatEnum::RGBA8888);
+                                             format);
         if surf_result.is_err() {
             self.error = Some(surf_result.err().unwrap().into());
             return self;
@@ -105,4 +133,37 @@ impl<'a> TextureBuilder<'a> {
 
         renderer.create_texture_from_surface(self.surface)
     }
+
+    /// Draws what was blitted over part of an existing texture, with its top left corner at
+    /// `position`, rather than building a new texture
+    pub fn update(self, texture: &mut Texture, position: Vector2<i32>) -> Result<()> {
+        if self.error.is_some() {
+            return Err(self.error.unwrap());
+        }
+
+        // The renderer may have picked a different pixel format for the texture than the
+        // surface it was built from
+        let sdl_texture = texture.sdl_texture_mut();
+        let surface = try!(self.surface.convert_format(sdl_texture.query().format));
+        let rect = Rect::of(position.x, position.y, surface.width() as i32, surface.height() as i32);
+        let pixels = try!(surface.without_lock().ok_or("texture builder surface needs locking"));
+        try!(sdl_texture.update(Some(rect.into()), pixels, surface.pitch() as usize));
+        Ok(())
+    }
+
+    /// Makes a hardware cursor of what was blitted, `scale` times as big so that it matches the
+    /// size things are drawn at, with the point that clicks at `hot_spot` before scaling
+    pub fn build_cursor(self, hot_spot: Vector2<i32>, scale: u32) -> Result<HardwareCursor> {
+        if self.error.is_some() {
+            return Err(self.error.unwrap());
+        }
+
+        let (width, height) = (self.surface.width() * scale, self.surface.height() * scale);
+        let mut scaled = try!(Surface::new(width, height, PixelFormatEnum::RGBA8888));
+        try!(self.surface.blit_scaled(None, &mut scaled, None));
+        let scale = scale as i32;
+        let hot_spot = hot_spot * scale;
+        let sdl_cursor = try!(sdl2::mouse::Cursor::from_surface(scaled, hot_spot.x, hot_spot.y));
+        Ok(cursor::create_hardware_cursor(sdl_cursor))
+    }
 }
@@ -0,0 +1,417 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs_writer;
+use error::*;
+use game_dir::GameDir;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Every file the game can't start without
+pub const REQUIRED_FILES: [&'static str; 8] = ["language.dll",
+                                                 "data/border.drs",
+                                                 "data/empires.dat",
+                                                 "data/graphics.drs",
+                                                 "data/interfac.drs",
+                                                 "data/sounds.drs",
+                                                 "data/terrain.drs",
+                                                 "data/tileedge.dat"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssetStatus {
+    Ok,
+    Missing,
+    Unreadable(String),
+    Corrupt(String),
+    UnsupportedVersion(String),
+    /// The file doesn't match the size or checksum that the known checksums give for it
+    Modified(String),
+}
+
+impl AssetStatus {
+    pub fn is_ok(&self) -> bool {
+        *self == AssetStatus::Ok
+    }
+}
+
+impl fmt::Display for AssetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::AssetStatus::*;
+        match *self {
+            Ok => write!(f, "ok"),
+            Missing => write!(f, "missing"),
+            Unreadable(ref reason) => write!(f, "unreadable ({})", reason),
+            Corrupt(ref reason) => write!(f, "corrupt ({})", reason),
+            UnsupportedVersion(ref version) => write!(f, "unsupported version \"{}\"", version),
+            Modified(ref reason) => write!(f, "modified ({})", reason),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AssetCheck {
+    pub file_name: &'static str,
+    pub path: Option<PathBuf>,
+    pub status: AssetStatus,
+    pub size: u64,
+    /// Adler-32 of the file contents, only worked out when checksums were asked for
+    pub checksum: Option<u32>,
+}
+
+/// The size and Adler-32 of each required file in an install known to be good, for telling
+/// whether another install's files were changed. The table is one file to a line, as
+/// "file_name size adler32" with the checksum in hex, which is how `AssetReport::checksum_table`
+/// writes it out; blank lines and lines starting with '#' are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct KnownChecksums {
+    files: HashMap<String, (u64, u32)>,
+}
+
+impl KnownChecksums {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KnownChecksums> {
+        let mut text = String::new();
+        try!(try!(File::open(path.as_ref())).read_to_string(&mut text));
+        KnownChecksums::parse(&text).map_err(|message| {
+            ErrorKind::BadChecksumTable(path.as_ref().to_path_buf(), message).into()
+        })
+    }
+
+    pub fn parse(text: &str) -> ::std::result::Result<KnownChecksums, String> {
+        let mut files = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match fields.len() {
+                3 => fields[1].parse().ok().and_then(|size| {
+                    u32::from_str_radix(fields[2], 16).ok().map(|checksum| (size, checksum))
+                }),
+                _ => None,
+            };
+            match parsed {
+                Some(known) => {
+                    files.insert(fields[0].to_string(), known);
+                }
+                None => return Err(format!("line {} isn't \"file_name size adler32\"", index + 1)),
+            }
+        }
+        Ok(KnownChecksums { files: files })
+    }
+
+    /// The known (size, checksum) of the file, if the table has it
+    pub fn get(&self, file_name: &str) -> Option<(u64, u32)> {
+        self.files.get(file_name).cloned()
+    }
+}
+
+/// Outcome of checking the game data directory for everything the game needs
+#[derive(Clone, Debug)]
+pub struct AssetReport {
+    pub checks: Vec<AssetCheck>,
+}
+
+impl AssetReport {
+    /// Checks that all of the required files exist and look like what they claim to be, from
+    /// their sizes and headers alone so that startup doesn't read them through. DRS archives
+    /// have their header and table of contents verified against the file size.
+    pub fn generate(game_dir: &GameDir) -> AssetReport {
+        AssetReport { checks: REQUIRED_FILES.iter().map(|file_name| check_file(game_dir, file_name)).collect() }
+    }
+
+    /// Does the same checks as `generate`, and then reads each file that passed them through to
+    /// work out its checksum, which has to match the known one for files that the table has
+    pub fn generate_with_checksums(game_dir: &GameDir, known: &KnownChecksums) -> AssetReport {
+        let mut report = AssetReport::generate(game_dir);
+        for check in &mut report.checks {
+            if check.status.is_ok() {
+                checksum_file(check, known);
+            }
+        }
+        report
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.status.is_ok())
+    }
+
+    pub fn problems<'a>(&'a self) -> Vec<&'a AssetCheck> {
+        self.checks.iter().filter(|check| !check.status.is_ok()).collect()
+    }
+
+    /// The sizes and checksums of the files, in the format `KnownChecksums` reads
+    pub fn checksum_table(&self) -> String {
+        let mut table = String::new();
+        for check in &self.checks {
+            if let Some(checksum) = check.checksum {
+                table.push_str(&format!("{} {} {:08x}\n", check.file_name, check.size, checksum));
+            }
+        }
+        table
+    }
+}
+
+impl fmt::Display for AssetReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            try!(write!(f, "  {:<20} {}", check.file_name, check.status));
+            if let Some(checksum) = check.checksum {
+                try!(write!(f, " [{} bytes, adler32 {:08x}]", check.size, checksum));
+            }
+            try!(writeln!(f, ""));
+        }
+        let problem_count = self.problems().len();
+        if problem_count > 0 {
+            try!(writeln!(f, "{} of {} required files have problems", problem_count, self.checks.len()));
+        }
+        Ok(())
+    }
+}
+
+fn check_file(game_dir: &GameDir, file_name: &'static str) -> AssetCheck {
+    let mut check = AssetCheck {
+        file_name: file_name,
+        path: None,
+        status: AssetStatus::Missing,
+        size: 0,
+        checksum: None,
+    };
+
+    let path = match game_dir.find_file(file_name) {
+        Ok(path) => path,
+        Err(_) => return check,
+    };
+    check.path = Some(path.clone());
+
+    let opened = File::open(&path).and_then(|file| file.metadata().map(|metadata| (file, metadata.len())));
+    let (file, size) = match opened {
+        Ok(opened) => opened,
+        Err(err) => {
+            check.status = AssetStatus::Unreadable(format!("{}", err));
+            return check;
+        }
+    };
+    check.size = size;
+
+    check.status = if size == 0 {
+        AssetStatus::Corrupt("empty file".into())
+    } else if file_name.ends_with(".drs") {
+        check_drs(&mut BufReader::new(file), size)
+    } else if file_name.ends_with(".dll") {
+        check_dll(&mut BufReader::new(file))
+    } else {
+        AssetStatus::Ok
+    };
+    check
+}
+
+fn checksum_file(check: &mut AssetCheck, known: &KnownChecksums) {
+    let path = match check.path {
+        Some(ref path) => path.clone(),
+        None => return,
+    };
+    let checksum = match File::open(&path).and_then(|file| adler32(&mut BufReader::new(file))) {
+        Ok(checksum) => checksum,
+        Err(err) => {
+            check.status = AssetStatus::Unreadable(format!("{}", err));
+            return;
+        }
+    };
+    check.checksum = Some(checksum);
+
+    if let Some((known_size, known_checksum)) = known.get(check.file_name) {
+        if known_size != check.size {
+            check.status = AssetStatus::Modified(format!("{} bytes instead of {}", check.size, known_size));
+        } else if known_checksum != checksum {
+            check.status = AssetStatus::Modified(format!("adler32 {:08x} instead of {:08x}",
+                                                         checksum,
+                                                         known_checksum));
+        }
+    }
+}
+
+fn check_dll<R: Read>(stream: &mut R) -> AssetStatus {
+    let mut magic = [0u8; 2];
+    match stream.read_exact(&mut magic) {
+        Ok(()) if &magic == b"MZ" => AssetStatus::Ok,
+        _ => AssetStatus::Corrupt("not a Windows DLL".into()),
+    }
+}
+
+fn check_drs<R: Read + Seek>(stream: &mut R, file_size: u64) -> AssetStatus {
+    let copyright = &drs_writer::COPYRIGHT[..drs_writer::COPYRIGHT.len() - 1];
+    let mut header = vec![0u8; copyright.len()];
+    if stream.read_exact(&mut header).is_err() || &header[..] != copyright {
+        return AssetStatus::Corrupt("missing DRS header".into());
+    }
+    match check_drs_tables(stream, file_size) {
+        Ok(status) => status,
+        Err(_) => AssetStatus::Corrupt("truncated table of contents".into()),
+    }
+}
+
+fn check_drs_tables<R: Read + Seek>(stream: &mut R, file_size: u64) -> io::Result<AssetStatus> {
+    let mut version = [0u8; 4];
+    try!(stream.seek(SeekFrom::Start(40)));
+    try!(stream.read_exact(&mut version));
+    if &version != drs_writer::VERSION {
+        return Ok(AssetStatus::UnsupportedVersion(String::from_utf8_lossy(&version).into_owned()));
+    }
+
+    try!(stream.seek(SeekFrom::Start(56)));
+    let table_count = try!(read_u32(stream));
+    try!(stream.seek(SeekFrom::Current(4))); // first file offset
+    let mut tables = Vec::new();
+    for _ in 0..table_count {
+        try!(stream.seek(SeekFrom::Current(4))); // extension
+        let offset = try!(read_u32(stream));
+        let count = try!(read_u32(stream));
+        tables.push((offset, count));
+    }
+
+    for (offset, count) in tables {
+        try!(stream.seek(SeekFrom::Start(offset as u64)));
+        for _ in 0..count {
+            let file_id = try!(read_u32(stream));
+            let contents_offset = try!(read_u32(stream)) as u64;
+            let contents_size = try!(read_u32(stream)) as u64;
+            if contents_offset + contents_size > file_size {
+                return Ok(AssetStatus::Corrupt(format!("resource {} extends past the end of the file", file_id)));
+            }
+        }
+    }
+    Ok(AssetStatus::Ok)
+}
+
+fn read_u32<R: Read>(stream: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    try!(stream.read_exact(&mut bytes));
+    Ok(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24)
+}
+
+fn adler32<R: Read>(stream: &mut R) -> io::Result<u32> {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Deferring the modulo is safe for up to 5552 bytes at a time
+    let mut buffer = [0u8; 5552];
+    loop {
+        let count = try!(stream.read(&mut buffer));
+        if count == 0 {
+            return Ok((b << 16) | a);
+        }
+        for byte in &buffer[..count] {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drs::DrsFileType;
+    use drs_writer::DrsWriter;
+    use std::io::Cursor;
+    use super::{AssetStatus, KnownChecksums, adler32, check_dll, check_drs};
+
+    fn drs_bytes() -> Vec<u8> {
+        let mut writer = DrsWriter::new();
+        writer.insert(DrsFileType::Binary, 50500, vec![1, 2, 3, 4]);
+        let mut bytes = Vec::new();
+        writer.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn check(bytes: &[u8]) -> AssetStatus {
+        check_drs(&mut Cursor::new(bytes), bytes.len() as u64)
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(1, adler32(&mut Cursor::new(&[])).unwrap());
+        assert_eq!(0x11e60398, adler32(&mut Cursor::new(b"Wikipedia")).unwrap());
+
+        // Longer than the chunks the modulo is deferred for
+        let long: Vec<u8> = (0..20000).map(|index| (index % 251) as u8).collect();
+        let mut a = 1u32;
+        let mut b = 0u32;
+        for byte in &long {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!((b << 16) | a, adler32(&mut Cursor::new(&long)).unwrap());
+    }
+
+    #[test]
+    fn test_check_valid_drs() {
+        assert_eq!(AssetStatus::Ok, check(&drs_bytes()));
+    }
+
+    #[test]
+    fn test_check_dll() {
+        assert_eq!(AssetStatus::Ok, check_dll(&mut Cursor::new(b"MZ\x90\x00")));
+        assert_eq!(AssetStatus::Corrupt("not a Windows DLL".into()),
+                   check_dll(&mut Cursor::new(b"M")));
+    }
+
+    #[test]
+    fn test_parse_known_checksums() {
+        let table = "# From the original CD\n\ndata/empires.dat 1234 0badf00d\n";
+        let known = KnownChecksums::parse(table).unwrap();
+        assert_eq!(Some((1234, 0x0badf00d)), known.get("data/empires.dat"));
+        assert_eq!(None, known.get("language.dll"));
+
+        assert_eq!(Err("line 2 isn't \"file_name size adler32\"".into()),
+                   KnownChecksums::parse("language.dll 10 00000001\nlanguage.dll ten 1\n").map(|_| ()));
+    }
+
+    #[test]
+    fn test_check_truncated_drs() {
+        let mut bytes = drs_bytes();
+        let length = bytes.len();
+        bytes.truncate(length - 2);
+        assert_eq!(AssetStatus::Corrupt("resource 50500 extends past the end of the file".into()),
+                   check(&bytes));
+
+        bytes.truncate(60);
+        assert_eq!(AssetStatus::Corrupt("truncated table of contents".into()),
+                   check(&bytes));
+    }
+
+    #[test]
+    fn test_check_drs_version() {
+        let mut bytes = drs_bytes();
+        bytes[40..44].copy_from_slice(b"2.00");
+        assert_eq!(AssetStatus::UnsupportedVersion("2.00".into()), check(&bytes));
+    }
+
+    #[test]
+    fn test_check_not_drs() {
+        assert_eq!(AssetStatus::Corrupt("missing DRS header".into()),
+                   check(b"not an archive"));
+    }
+}
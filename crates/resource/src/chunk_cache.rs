@@ -0,0 +1,174 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use media::{self, Renderer, Texture};
+use nalgebra::Vector2;
+use std::collections::HashMap;
+use super::{RenderCommand, ShapeManager};
+use types::Rect;
+
+/// Which chunk of whatever grid the baking code splits its drawing into, by row and column
+pub type ChunkKey = (i32, i32);
+
+/// One of a chunk's textures, along with what was drawn into it
+struct Layer {
+    texture: Texture,
+    contents: Vec<RenderCommand>,
+}
+
+struct Chunk {
+    /// Where the textures go, in the same coordinates as the commands baked into them
+    bounds: Rect,
+    /// Everything that stays as it was baked
+    fixed: Option<Layer>,
+    /// The shapes drawn in water or lava colors, which get redrawn on their own as the colors cycle
+    cycled: Option<Layer>,
+    /// The color cycle step the cycled layer was drawn at
+    color_cycle_step: u32,
+}
+
+/// Render commands that are drawn once into textures of their own, and from then on take a
+/// single copy each frame, for things like the terrain that hardly ever change. A chunk stays
+/// as it was baked until it's baked again, except that the shapes in it with water or lava
+/// colors are kept on a layer of their own, which is redrawn as the colors cycle without
+/// having to redraw the rest of the chunk. That layer goes on top of the rest.
+pub struct ChunkCache {
+    chunks: HashMap<ChunkKey, Chunk>,
+}
+
+impl ChunkCache {
+    pub fn new() -> ChunkCache {
+        ChunkCache { chunks: HashMap::new() }
+    }
+
+    /// Draws the commands into the chunk's textures, replacing whatever was baked there before.
+    /// The textures cover `bounds`, and anything drawn outside of them is cut off.
+    pub fn bake(&mut self,
+                chunk_key: ChunkKey,
+                bounds: Rect,
+                contents: Vec<RenderCommand>,
+                renderer: &mut Renderer,
+                shape_manager: &mut ShapeManager) {
+        // The old textures can be drawn over again when they're the right size
+        let (old_fixed, old_cycled) = match self.chunks.remove(&chunk_key) {
+            Some(chunk) => {
+                if chunk.bounds.w == bounds.w && chunk.bounds.h == bounds.h {
+                    (chunk.fixed.map(|layer| layer.texture), chunk.cycled.map(|layer| layer.texture))
+                } else {
+                    (None, None)
+                }
+            }
+            None => (None, None),
+        };
+
+        let (cycled, fixed): (Vec<RenderCommand>, Vec<RenderCommand>) =
+            contents.into_iter().partition(|command| cycles_colors(command, renderer, shape_manager));
+        let chunk = Chunk {
+            bounds: bounds,
+            fixed: bake_layer(chunk_key, old_fixed, bounds, fixed, renderer, shape_manager),
+            cycled: bake_layer(chunk_key, old_cycled, bounds, cycled, renderer, shape_manager),
+            color_cycle_step: shape_manager.color_cycle_step(),
+        };
+        self.chunks.insert(chunk_key, chunk);
+    }
+
+    /// Draws the chunk as it was last baked, if it has been
+    pub fn render(&mut self, chunk_key: ChunkKey, renderer: &mut Renderer, shape_manager: &mut ShapeManager) {
+        let chunk = match self.chunks.get_mut(&chunk_key) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        let color_cycle_step = shape_manager.color_cycle_step();
+        if chunk.color_cycle_step != color_cycle_step {
+            if let Some(layer) = chunk.cycled.take() {
+                chunk.cycled = bake_layer(chunk_key,
+                                          Some(layer.texture),
+                                          chunk.bounds,
+                                          layer.contents,
+                                          renderer,
+                                          shape_manager);
+            }
+            chunk.color_cycle_step = color_cycle_step;
+        }
+
+        for layer in chunk.fixed.iter().chain(chunk.cycled.iter()) {
+            renderer.render_texture(&layer.texture, None, chunk.bounds, false, false);
+        }
+    }
+
+    pub fn remove(&mut self, chunk_key: ChunkKey) {
+        self.chunks.remove(&chunk_key);
+    }
+
+    /// Number of chunks that have been baked
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+fn cycles_colors(command: &RenderCommand, renderer: &mut Renderer, shape_manager: &mut ShapeManager) -> bool {
+    match *command {
+        RenderCommand::RenderShape(_, ref params) => {
+            shape_manager.get(&params.shape_key, renderer).map_or(false, |shape| shape.cycles_colors())
+        }
+        _ => false,
+    }
+}
+
+/// Draws the commands into the texture, or a new one when there isn't one to reuse. There's
+/// no layer when there's nothing to draw, or the drawing failed.
+fn bake_layer(chunk_key: ChunkKey,
+              texture: Option<Texture>,
+              bounds: Rect,
+              mut contents: Vec<RenderCommand>,
+              renderer: &mut Renderer,
+              shape_manager: &mut ShapeManager)
+              -> Option<Layer> {
+    if contents.is_empty() {
+        return None;
+    }
+    let texture = texture.map_or_else(|| renderer.create_target_texture(bounds.w as u32, bounds.h as u32),
+                                      |texture| Ok(texture));
+    let origin = Vector2::new(bounds.x, bounds.y);
+    let texture: media::Result<Texture> = texture.and_then(|texture| {
+        renderer.render_to_texture(texture, origin, |renderer| {
+            // Chunks don't have chunks of their own inside them
+            RenderCommand::render_all(renderer, shape_manager, &mut ChunkCache::new(), &mut contents);
+        })
+    });
+    match texture {
+        Ok(texture) => {
+            Some(Layer {
+                texture: texture,
+                contents: contents,
+            })
+        }
+        Err(err) => {
+            warn!("Failed to bake chunk {:?}: {}", chunk_key, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use game_dir::GameDir;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the game tends to be installed, relative to a Windows drive's Program Files
+const PROGRAM_FILES_DIRS: [&'static str; 3] = ["Microsoft Games/Age of Empires",
+                                                 "GOG Galaxy/Games/Age of Empires",
+                                                 "Steam/steamapps/common/Age of Empires"];
+
+/// Where the game tends to be installed, relative to the root of a Windows drive
+const DRIVE_DIRS: [&'static str; 2] = ["GOG Games/Age of Empires", "Games/Age of Empires"];
+
+/// Directories that removable drives, such as the game CD, are mounted under
+const UNIX_MOUNT_DIRS: [&'static str; 3] = ["/media", "/mnt", "/run/media"];
+
+/// A directory that was looked in for the game data and passed over
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedDir {
+    pub dir: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for RejectedDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.dir.display(), self.reason)
+    }
+}
+
+/// Every directory the game data is looked for in, in the order they're tried: the given
+/// search directories (and any drives mounted in them), then the usual install locations
+/// for the platform, then the game CD. Only directories that exist are returned.
+pub fn candidate_dirs(search_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let var = |name: &str| match env::var(name) {
+        Ok(ref value) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    };
+    let mut candidates = Vec::new();
+    for dir in search_dirs {
+        candidates.extend(install_dirs(dir));
+        candidates.extend(volume_dirs(dir));
+    }
+    if cfg!(windows) {
+        candidates.extend(windows_dirs(&var));
+    } else {
+        let home = var("HOME").map(PathBuf::from);
+        let wine_prefix = var("WINEPREFIX")
+            .map(PathBuf::from)
+            .or(home.as_ref().map(|home| home.join(".wine")));
+        if let Some(wine_prefix) = wine_prefix {
+            candidates.extend(wine_dirs(&wine_prefix));
+        }
+        if let Some(ref home) = home {
+            candidates.extend(home_dirs(home));
+        }
+        if cfg!(target_os = "macos") {
+            candidates.extend(volume_dirs(Path::new("/Volumes")));
+        } else {
+            for mount_dir in &UNIX_MOUNT_DIRS {
+                let mount_dir = Path::new(mount_dir);
+                candidates.extend(volume_dirs(mount_dir));
+                // Desktops tend to mount drives in a directory per user
+                if let Some(user) = var("USER") {
+                    candidates.extend(volume_dirs(&mount_dir.join(user)));
+                }
+            }
+        }
+    }
+
+    let mut unique = Vec::new();
+    for candidate in candidates {
+        if candidate.is_dir() && !unique.contains(&candidate) {
+            unique.push(candidate);
+        }
+    }
+    unique
+}
+
+/// Finds the game data, trying the configured directory before searching for it. On failure,
+/// returns every directory that was looked in and why it wasn't used.
+pub fn locate_game_dir(configured_dir: &Path, search_dirs: &[PathBuf]) -> Result<GameDir, Vec<RejectedDir>> {
+    let mut rejected = Vec::new();
+    match GameDir::new(configured_dir) {
+        Ok(game_dir) => return Ok(game_dir),
+        Err(err) => rejected.push(rejection(configured_dir, err)),
+    }
+    for dir in candidate_dirs(search_dirs) {
+        if dir == configured_dir {
+            continue;
+        }
+        match GameDir::new(&dir) {
+            Ok(game_dir) => {
+                info!("Found game data in {:?}", dir);
+                return Ok(game_dir);
+            }
+            Err(err) => rejected.push(rejection(&dir, err)),
+        }
+    }
+    Err(rejected)
+}
+
+fn rejection<E: fmt::Display>(dir: &Path, err: E) -> RejectedDir {
+    // The first line says what's wrong; the rest is advice on where to look
+    let message = format!("{}", err);
+    RejectedDir {
+        dir: dir.to_path_buf(),
+        reason: message.lines().next().unwrap_or("").to_string(),
+    }
+}
+
+/// An install directory holds the data itself, while the CD has it in a `game` directory
+fn install_dirs(dir: &Path) -> Vec<PathBuf> {
+    vec![dir.to_path_buf(), dir.join("game")]
+}
+
+/// The install directories of every drive mounted in a directory
+fn volume_dirs(mount_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(entries) = fs::read_dir(mount_dir) {
+        let mut volumes: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        volumes.sort();
+        for volume in volumes {
+            dirs.extend(install_dirs(&volume));
+        }
+    }
+    dirs
+}
+
+fn windows_dirs(var: &Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for program_files in &["ProgramFiles(x86)", "ProgramFiles"] {
+        if let Some(program_files) = var(program_files) {
+            for dir in &PROGRAM_FILES_DIRS {
+                dirs.push(Path::new(&program_files).join(dir));
+            }
+        }
+    }
+    // The CD can be in any drive
+    for letter in b'C'..b'Z' + 1 {
+        let drive = PathBuf::from(format!("{}:\\", letter as char));
+        for dir in &DRIVE_DIRS {
+            dirs.push(drive.join(dir));
+        }
+        dirs.push(drive.join("game"));
+    }
+    dirs
+}
+
+/// Where Wine installs Windows programs, for players who installed the game with it
+fn wine_dirs(prefix: &Path) -> Vec<PathBuf> {
+    let drive = prefix.join("drive_c");
+    let mut dirs = Vec::new();
+    for program_files in &["Program Files (x86)", "Program Files"] {
+        for dir in &PROGRAM_FILES_DIRS {
+            dirs.push(drive.join(program_files).join(dir));
+        }
+    }
+    for dir in &DRIVE_DIRS {
+        dirs.push(drive.join(dir));
+    }
+    dirs
+}
+
+fn home_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![home.join("GOG Games/Age of Empires"),
+                        home.join(".steam/steam/steamapps/common/Age of Empires"),
+                        home.join(".local/share/Steam/steamapps/common/Age of Empires"),
+                        home.join("Library/Application Support/Steam/steamapps/common/Age of Empires")];
+    // CrossOver keeps a Wine prefix per bottle
+    let bottles_dir = home.join("Library/Application Support/CrossOver/Bottles");
+    if let Ok(entries) = fs::read_dir(&bottles_dir) {
+        let mut bottles: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        bottles.sort();
+        for bottle in bottles {
+            dirs.extend(wine_dirs(&bottle));
+        }
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::{candidate_dirs, locate_game_dir, volume_dirs, windows_dirs, wine_dirs};
+
+    #[test]
+    fn test_install_locations() {
+        let dirs = windows_dirs(&|name: &str| match name {
+            "ProgramFiles(x86)" => Some("C:\\Program Files (x86)".into()),
+            _ => None,
+        });
+        assert_eq!(Path::new("C:\\Program Files (x86)").join("Microsoft Games/Age of Empires"),
+                   dirs[0]);
+        assert!(dirs.contains(&PathBuf::from("D:\\").join("game")));
+        assert!(dirs.contains(&PathBuf::from("E:\\").join("GOG Games/Age of Empires")));
+
+        let dirs = wine_dirs(Path::new("/home/player/.wine"));
+        assert!(dirs.contains(&PathBuf::from("/home/player/.wine/drive_c/Program Files (x86)/\
+                                              Microsoft Games/Age of Empires")));
+        assert!(dirs.contains(&PathBuf::from("/home/player/.wine/drive_c/GOG Games/Age of Empires")));
+    }
+
+    #[test]
+    fn test_search_dirs() {
+        let mount_dir = env::temp_dir().join("chariot_data_locator_test");
+        let _ = fs::remove_dir_all(&mount_dir);
+        fs::create_dir_all(mount_dir.join("AOE/game/data")).unwrap();
+        fs::create_dir_all(mount_dir.join("USB")).unwrap();
+
+        assert_eq!(vec![mount_dir.join("AOE"),
+                        mount_dir.join("AOE/game"),
+                        mount_dir.join("USB"),
+                        mount_dir.join("USB/game")],
+                   volume_dirs(&mount_dir));
+        let candidates = candidate_dirs(&[mount_dir.clone()]);
+        assert_eq!(vec![mount_dir.clone(),
+                        mount_dir.join("AOE"),
+                        mount_dir.join("AOE/game"),
+                        mount_dir.join("USB")],
+                   candidates[..4].to_vec());
+
+        // None of them has the data, so each is reported along with the configured directory
+        let rejected = locate_game_dir(&mount_dir.join("missing"), &[mount_dir.clone()]).unwrap_err();
+        assert_eq!(mount_dir.join("missing"), rejected[0].dir);
+        assert_eq!("Given game data directory doesn't exist", rejected[0].reason);
+        assert_eq!(candidates.len() + 1, rejected.len());
+        fs::remove_dir_all(&mount_dir).unwrap();
+    }
+}
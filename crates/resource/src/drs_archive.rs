@@ -0,0 +1,208 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs::DrsFileType;
+use drs_writer::{HEADER_SIZE, TABLE_ENTRY_SIZE, TABLE_HEADER_SIZE, TABLE_ORDER, table_extension, table_index};
+use error::*;
+use memmap::Mmap;
+
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::Path;
+
+enum ArchiveBytes {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for ArchiveBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            ArchiveBytes::Mapped(ref map) => &map[..],
+            ArchiveBytes::Read(ref bytes) => &bytes[..],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DrsArchiveEntry {
+    pub file_id: u32,
+    offset: u32,
+    size: u32,
+}
+
+struct TableIndex {
+    /// Which DrsFileType the table holds, as a drs_writer table index
+    table_index: usize,
+    entries: Vec<DrsArchiveEntry>,
+}
+
+/// A DRS archive that hands out slices of the archive's bytes rather than copying out every
+/// resource when it's opened. The bytes are memory mapped, so only the pages that get used are
+/// read in, or read into memory in one go when mapping isn't wanted.
+pub struct DrsArchive {
+    bytes: ArchiveBytes,
+    tables: Vec<TableIndex>,
+}
+
+/// One table of a DrsArchive
+#[derive(Copy, Clone)]
+pub struct DrsArchiveTable<'a> {
+    bytes: &'a [u8],
+    index: &'a TableIndex,
+}
+
+impl DrsArchive {
+    pub fn open<P: AsRef<Path>>(file_name: P, memory_map: bool) -> Result<DrsArchive> {
+        let file_name = file_name.as_ref();
+        let mut file = try!(File::open(file_name));
+        let bytes = if memory_map {
+            // The archives are only ever read, by this process and the original game
+            ArchiveBytes::Mapped(try!(unsafe { Mmap::map(&file) }))
+        } else {
+            let mut bytes = Vec::new();
+            try!(file.read_to_end(&mut bytes));
+            ArchiveBytes::Read(bytes)
+        };
+        DrsArchive::from_bytes(bytes)
+            .chain_err(|| ErrorKind::InvalidDrsArchive(file_name.to_path_buf()))
+    }
+
+    fn from_bytes(bytes: ArchiveBytes) -> Result<DrsArchive> {
+        let table_count = try!(read_u32(&bytes, HEADER_SIZE as usize - 8));
+        let mut tables = Vec::new();
+        for table in 0..table_count as usize {
+            let header_offset = HEADER_SIZE as usize + table * TABLE_HEADER_SIZE as usize;
+            let extension =
+                try!(bytes.get(header_offset..header_offset + 4).ok_or(ErrorKind::DrsTruncated));
+            let entries_offset = try!(read_u32(&bytes, header_offset + 4)) as usize;
+            let entry_count = try!(read_u32(&bytes, header_offset + 8)) as usize;
+
+            let mut entries = Vec::with_capacity(entry_count);
+            for entry in 0..entry_count {
+                let entry_offset = entries_offset + entry * TABLE_ENTRY_SIZE as usize;
+                let entry = DrsArchiveEntry {
+                    file_id: try!(read_u32(&bytes, entry_offset)),
+                    offset: try!(read_u32(&bytes, entry_offset + 4)),
+                    size: try!(read_u32(&bytes, entry_offset + 8)),
+                };
+                if entry.offset as usize + entry.size as usize > bytes.len() {
+                    return Err(ErrorKind::DrsTruncated.into());
+                }
+                entries.push(entry);
+            }
+
+            // Tables of types nothing reads are skipped
+            let file_type =
+                TABLE_ORDER.iter().find(|file_type| &table_extension(**file_type)[..] == extension);
+            if let Some(file_type) = file_type {
+                tables.push(TableIndex {
+                    table_index: table_index(*file_type),
+                    entries: entries,
+                });
+            }
+        }
+        Ok(DrsArchive {
+            bytes: bytes,
+            tables: tables,
+        })
+    }
+
+    pub fn is_memory_mapped(&self) -> bool {
+        match self.bytes {
+            ArchiveBytes::Mapped(_) => true,
+            ArchiveBytes::Read(_) => false,
+        }
+    }
+
+    pub fn find_table<'a>(&'a self, file_type: DrsFileType) -> Option<DrsArchiveTable<'a>> {
+        let wanted = table_index(file_type);
+        self.tables.iter().find(|table| table.table_index == wanted).map(|table| {
+            DrsArchiveTable {
+                bytes: &self.bytes,
+                index: table,
+            }
+        })
+    }
+}
+
+impl<'a> DrsArchiveTable<'a> {
+    pub fn entries(&self) -> &'a [DrsArchiveEntry] {
+        &self.index.entries
+    }
+
+    pub fn find_file_contents(&self, file_id: u32) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.index.entries.iter().find(|entry| entry.file_id == file_id).map(|entry| {
+            &bytes[entry.offset as usize..(entry.offset + entry.size) as usize]
+        })
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    let value = try!(bytes.get(offset..offset + 4).ok_or(ErrorKind::DrsTruncated));
+    Ok(value[0] as u32 | (value[1] as u32) << 8 | (value[2] as u32) << 16 | (value[3] as u32) << 24)
+}
+
+#[cfg(test)]
+mod tests {
+    use drs::DrsFileType;
+    use drs_writer::DrsWriter;
+    use super::{ArchiveBytes, DrsArchive};
+
+    fn archive_bytes() -> Vec<u8> {
+        let mut writer = DrsWriter::new();
+        writer.insert(DrsFileType::Slp, 2, vec![1, 2, 3]);
+        writer.insert(DrsFileType::Slp, 1, vec![4]);
+        writer.insert(DrsFileType::Wav, 5, vec![7, 7]);
+        let mut bytes = Vec::new();
+        writer.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_contents_are_slices_of_the_archive() {
+        let bytes = archive_bytes();
+        let archive = DrsArchive::from_bytes(ArchiveBytes::Read(bytes)).unwrap();
+
+        let slps = archive.find_table(DrsFileType::Slp).unwrap();
+        let file_ids: Vec<u32> = slps.entries().iter().map(|entry| entry.file_id).collect();
+        assert_eq!(vec![1, 2], file_ids);
+        assert_eq!(Some(&[1u8, 2, 3][..]), slps.find_file_contents(2));
+        assert_eq!(Some(&[4u8][..]), slps.find_file_contents(1));
+        assert_eq!(None, slps.find_file_contents(3));
+
+        let wavs = archive.find_table(DrsFileType::Wav).unwrap();
+        assert_eq!(Some(&[7u8, 7][..]), wavs.find_file_contents(5));
+        assert!(archive.find_table(DrsFileType::Binary).is_none());
+    }
+
+    #[test]
+    fn test_truncated_archive_is_an_error() {
+        let mut bytes = archive_bytes();
+        let length = bytes.len();
+        bytes.truncate(length - 1);
+        assert!(DrsArchive::from_bytes(ArchiveBytes::Read(bytes)).is_err());
+    }
+}
@@ -21,12 +21,18 @@
 //
 
 
-use drs::DrsFile;
+use drs::DrsFileType;
+use drs_archive::DrsArchive;
+use edition_assets::EditionAssets;
 use error::*;
 use game_dir::GameDir;
+use mod_overrides::ModOverrides;
 use std::cell::RefCell;
 
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
@@ -49,40 +55,158 @@ impl DrsKey {
             Terrain => "data/terrain.drs",
         }
     }
+
+    /// Name of the archive without its directory or extension
+    pub fn name(&self) -> &'static str {
+        use self::DrsKey::*;
+        match *self {
+            Border => "border",
+            Graphics => "graphics",
+            Interfac => "interfac",
+            Sounds => "sounds",
+            Terrain => "terrain",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DrsKey> {
+        use self::DrsKey::*;
+        [Border, Graphics, Interfac, Sounds, Terrain].iter().cloned().find(|key| key.name() == name)
+    }
+}
+
+/// Archives opened at startup. The shape metadata reads the frame count of every SLP in these
+/// before a game starts, graphics.drs included, so deferring them would only move the work.
+/// Opening a memory mapped archive just reads its tables; the SLPs in it are decoded when
+/// they're first drawn. sounds.drs isn't opened until the first sound plays.
+const STARTUP_ARCHIVES: [DrsKey; 4] = [DrsKey::Border, DrsKey::Graphics, DrsKey::Interfac, DrsKey::Terrain];
+
+/// Extension a mod uses to replace resources of the given type
+fn override_extension(file_type: DrsFileType) -> &'static str {
+    match file_type {
+        DrsFileType::Binary => "bin",
+        DrsFileType::Shape => "shp",
+        DrsFileType::Slp => "slp",
+        DrsFileType::Wav => "wav",
+    }
 }
 
 pub struct DrsManager {
     game_dir: GameDir,
-    resources: HashMap<DrsKey, DrsFile>,
+    resources: HashMap<DrsKey, DrsArchive>,
+    mod_overrides: ModOverrides,
+    edition_assets: EditionAssets,
+    memory_map: bool,
 }
 
 pub type DrsManagerRef = Rc<RefCell<DrsManager>>;
 
 impl DrsManager {
     pub fn new(game_dir: &GameDir) -> DrsManagerRef {
-        Rc::new(RefCell::new(DrsManager {
+        Rc::new(RefCell::new(DrsManager::open(game_dir)))
+    }
+
+    /// A manager that isn't shared yet, so that it can be filled in on another thread
+    pub fn open(game_dir: &GameDir) -> DrsManager {
+        DrsManager {
             game_dir: game_dir.clone(),
             resources: HashMap::new(),
-        }))
+            mod_overrides: ModOverrides::new(),
+            edition_assets: EditionAssets::new(),
+            memory_map: true,
+        }
+    }
+
+    /// Whether archives loaded from now on get memory mapped, which is the default, or read
+    /// into memory in one go
+    pub fn set_memory_map(&mut self, memory_map: bool) {
+        self.memory_map = memory_map;
+    }
+
+    pub fn set_mod_overrides(&mut self, mod_overrides: ModOverrides) {
+        self.mod_overrides = mod_overrides;
+    }
+
+    pub fn mod_overrides<'a>(&'a self) -> &'a ModOverrides {
+        &self.mod_overrides
+    }
+
+    pub fn set_edition_assets(&mut self, edition_assets: EditionAssets) {
+        self.edition_assets = edition_assets;
+    }
+
+    pub fn edition_assets<'a>(&'a self) -> &'a EditionAssets {
+        &self.edition_assets
+    }
+
+    /// Finds a loose file for a resource, from the mods first and then a newer edition: a
+    /// replacement PNG or WAV, or the JSON describing the frames of a replacement image, such as
+    /// `mods/<name>/graphics/12.json`
+    pub fn find_override(&self, drs_key: DrsKey, file_id: u32, extension: &str) -> Option<PathBuf> {
+        self.mod_overrides
+            .find(drs_key, file_id, extension)
+            .or_else(|| self.edition_assets.find(drs_key, file_id, extension))
+    }
+
+    /// Reads the raw contents of a resource, preferring a mod's replacement file
+    /// (such as `mods/<name>/sounds/5000.wav`), then a newer edition's version of it, over
+    /// the one in the archive
+    pub fn load_contents(&mut self, drs_key: DrsKey, file_type: DrsFileType, file_id: u32) -> Result<Vec<u8>> {
+        if let Some(override_path) = self.find_override(drs_key, file_id, override_extension(file_type)) {
+            let mut contents = Vec::new();
+            try!(try!(File::open(override_path)).read_to_end(&mut contents));
+            return Ok(contents);
+        }
+
+        try!(self.load(drs_key));
+        let table = try!(self.get(drs_key)
+            .find_table(file_type)
+            .ok_or(ErrorKind::ResourceNotFound(drs_key, file_id)));
+        let contents = try!(table.find_file_contents(file_id)
+            .ok_or(ErrorKind::ResourceNotFound(drs_key, file_id)));
+        Ok(contents.to_vec())
+    }
+
+    /// Whether a resource can be loaded, from a mod, a newer edition, or the archive. Data sets
+    /// like the demo's ship archives with only some of the full game's resources in them.
+    pub fn has_file(&mut self, drs_key: DrsKey, file_type: DrsFileType, file_id: u32) -> bool {
+        if self.find_override(drs_key, file_id, override_extension(file_type)).is_some() {
+            return true;
+        }
+        if self.load(drs_key).is_err() {
+            return false;
+        }
+        self.get(drs_key)
+            .find_table(file_type)
+            .map(|table| table.find_file_contents(file_id).is_some())
+            .unwrap_or(false)
     }
 
-    pub fn get<'a>(&'a self, drs_key: DrsKey) -> &'a DrsFile {
+    /// Retrieves an archive that has already been loaded
+    pub fn get<'a>(&'a self, drs_key: DrsKey) -> &'a DrsArchive {
         self.resources.get(&drs_key).unwrap()
     }
 
+    pub fn is_loaded(&self, drs_key: DrsKey) -> bool {
+        self.resources.contains_key(&drs_key)
+    }
+
+    /// Opens the archives that are needed at startup, which is all of them but sounds.drs
     pub fn preload(&mut self) -> Result<()> {
-        try!(self.preload_drs(DrsKey::Border));
-        try!(self.preload_drs(DrsKey::Graphics));
-        try!(self.preload_drs(DrsKey::Interfac));
-        try!(self.preload_drs(DrsKey::Sounds));
-        try!(self.preload_drs(DrsKey::Terrain));
+        for drs_key in &STARTUP_ARCHIVES {
+            try!(self.load(*drs_key));
+        }
         Ok(())
     }
 
-    fn preload_drs(&mut self, drs_key: DrsKey) -> Result<()> {
+    /// Loads an archive if it hasn't been loaded yet
+    pub fn load(&mut self, drs_key: DrsKey) -> Result<()> {
+        if self.is_loaded(drs_key) {
+            return Ok(());
+        }
+
         let file_name = try!(self.game_dir.find_file(drs_key.path()));
-        println!("Loading {:?}...", file_name);
-        let drs = try!(DrsFile::read_from_file(file_name));
+        info!("Loading {:?}...", file_name);
+        let drs = try!(DrsArchive::open(file_name, self.memory_map));
         self.resources.insert(drs_key, drs);
         Ok(())
     }
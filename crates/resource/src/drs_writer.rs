@@ -0,0 +1,225 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs::DrsFileType;
+use drs_archive::DrsArchive;
+use error::*;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const COPYRIGHT: &'static [u8] = b"Copyright (c) 1997 Ensemble Studios.\x1a";
+const COPYRIGHT_SIZE: usize = 40;
+pub const VERSION: &'static [u8; 4] = b"1.00";
+const ARCHIVE_TYPE: &'static [u8] = b"tribe";
+const ARCHIVE_TYPE_SIZE: usize = 12;
+
+pub const HEADER_SIZE: u32 = 64;
+pub const TABLE_HEADER_SIZE: u32 = 12;
+pub const TABLE_ENTRY_SIZE: u32 = 12;
+
+/// DRS table types, in the order tables are written to the archive
+pub const TABLE_ORDER: [DrsFileType; 4] = [DrsFileType::Binary,
+                                       DrsFileType::Shape,
+                                       DrsFileType::Slp,
+                                       DrsFileType::Wav];
+
+/// Extensions are stored back to front in the table headers
+pub fn table_extension(file_type: DrsFileType) -> &'static [u8; 4] {
+    match file_type {
+        DrsFileType::Binary => b"anib",
+        DrsFileType::Shape => b" phs",
+        DrsFileType::Slp => b" pls",
+        DrsFileType::Wav => b" vaw",
+    }
+}
+
+pub fn table_index(file_type: DrsFileType) -> usize {
+    match file_type {
+        DrsFileType::Binary => 0,
+        DrsFileType::Shape => 1,
+        DrsFileType::Slp => 2,
+        DrsFileType::Wav => 3,
+    }
+}
+
+/// Builds DRS archives, either from scratch or by adding and replacing resources
+/// in an existing archive. Resources are written out sorted by table and file ID.
+pub struct DrsWriter {
+    tables: [BTreeMap<u32, Vec<u8>>; 4],
+}
+
+impl DrsWriter {
+    pub fn new() -> DrsWriter {
+        DrsWriter { tables: [BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new()] }
+    }
+
+    /// Starts with all of the resources of an existing archive
+    pub fn from_drs(drs: &DrsArchive) -> DrsWriter {
+        let mut writer = DrsWriter::new();
+        for file_type in TABLE_ORDER.iter() {
+            if let Some(table) = drs.find_table(*file_type) {
+                for entry in table.entries() {
+                    if let Some(contents) = table.find_file_contents(entry.file_id) {
+                        writer.insert(*file_type, entry.file_id, contents.to_vec());
+                    }
+                }
+            }
+        }
+        writer
+    }
+
+    /// Adds a resource, replacing any existing resource with the same type and ID.
+    /// Returns the contents that were replaced, if any.
+    pub fn insert(&mut self, file_type: DrsFileType, file_id: u32, contents: Vec<u8>) -> Option<Vec<u8>> {
+        self.tables[table_index(file_type)].insert(file_id, contents)
+    }
+
+    pub fn remove(&mut self, file_type: DrsFileType, file_id: u32) -> Option<Vec<u8>> {
+        self.tables[table_index(file_type)].remove(&file_id)
+    }
+
+    pub fn contains(&self, file_type: DrsFileType, file_id: u32) -> bool {
+        self.tables[table_index(file_type)].contains_key(&file_id)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, file_name: P) -> Result<()> {
+        let mut file = try!(File::create(file_name.as_ref()));
+        try!(self.write_to(&mut file));
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, stream: &mut W) -> Result<()> {
+        let tables: Vec<(DrsFileType, &BTreeMap<u32, Vec<u8>>)> = TABLE_ORDER.iter()
+            .map(|file_type| (*file_type, &self.tables[table_index(*file_type)]))
+            .filter(|&(_, files)| !files.is_empty())
+            .collect();
+
+        // Layout: header, table headers, table entries, then the file contents
+        let table_count = tables.len() as u32;
+        let entry_count = tables.iter().map(|&(_, files)| files.len() as u32).sum::<u32>();
+        let first_file_offset = HEADER_SIZE + table_count * TABLE_HEADER_SIZE +
+                                entry_count * TABLE_ENTRY_SIZE;
+
+        try!(write_padded(stream, COPYRIGHT, COPYRIGHT_SIZE));
+        try!(stream.write_all(VERSION));
+        try!(write_padded(stream, ARCHIVE_TYPE, ARCHIVE_TYPE_SIZE));
+        try!(write_u32(stream, table_count));
+        try!(write_u32(stream, first_file_offset));
+
+        let mut table_offset = HEADER_SIZE + table_count * TABLE_HEADER_SIZE;
+        for &(file_type, files) in &tables {
+            try!(stream.write_all(table_extension(file_type)));
+            try!(write_u32(stream, table_offset));
+            try!(write_u32(stream, files.len() as u32));
+            table_offset += files.len() as u32 * TABLE_ENTRY_SIZE;
+        }
+
+        let mut file_offset = first_file_offset as u64;
+        for &(_, files) in &tables {
+            for (file_id, contents) in files.iter() {
+                let file_size = contents.len() as u64;
+                if file_offset + file_size > u32::max_value() as u64 {
+                    return Err(ErrorKind::DrsResourceTooLarge(*file_id).into());
+                }
+                try!(write_u32(stream, *file_id));
+                try!(write_u32(stream, file_offset as u32));
+                try!(write_u32(stream, file_size as u32));
+                file_offset += file_size;
+            }
+        }
+
+        for &(_, files) in &tables {
+            for contents in files.values() {
+                try!(stream.write_all(contents));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_padded<W: Write>(stream: &mut W, value: &[u8], size: usize) -> io::Result<()> {
+    try!(stream.write_all(value));
+    stream.write_all(&vec![0u8; size - value.len()])
+}
+
+fn write_u32<W: Write>(stream: &mut W, value: u32) -> io::Result<()> {
+    stream.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use drs::DrsFileType;
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        bytes[offset] as u32 | (bytes[offset + 1] as u32) << 8 | (bytes[offset + 2] as u32) << 16 |
+        (bytes[offset + 3] as u32) << 24
+    }
+
+    #[test]
+    fn test_drs_writer_layout() {
+        let mut writer = DrsWriter::new();
+        writer.insert(DrsFileType::Wav, 5, vec![7, 7]);
+        writer.insert(DrsFileType::Slp, 2, vec![1, 2, 3]);
+        writer.insert(DrsFileType::Slp, 1, vec![4]);
+
+        let mut bytes = Vec::new();
+        writer.write_to(&mut bytes).unwrap();
+
+        assert_eq!(b"1.00", &bytes[40..44]);
+        assert_eq!(b"tribe", &bytes[44..49]);
+        assert_eq!(2, read_u32(&bytes, 56));
+
+        let first_file_offset = 64 + 2 * 12 + 3 * 12;
+        assert_eq!(first_file_offset, read_u32(&bytes, 60));
+
+        // Table headers, with the SLP table first
+        assert_eq!(b" pls", &bytes[64..68]);
+        assert_eq!(64 + 2 * 12, read_u32(&bytes, 68));
+        assert_eq!(2, read_u32(&bytes, 72));
+        assert_eq!(b" vaw", &bytes[76..80]);
+        assert_eq!(64 + 2 * 12 + 2 * 12, read_u32(&bytes, 80));
+        assert_eq!(1, read_u32(&bytes, 84));
+
+        // Entries are sorted by ID and point at their contents
+        let entry = 64 + 2 * 12;
+        assert_eq!(1, read_u32(&bytes, entry));
+        assert_eq!(first_file_offset, read_u32(&bytes, entry + 4));
+        assert_eq!(1, read_u32(&bytes, entry + 8));
+        assert_eq!(2, read_u32(&bytes, entry + 12));
+        assert_eq!(first_file_offset + 1, read_u32(&bytes, entry + 16));
+
+        assert_eq!(&[4, 1, 2, 3, 7, 7], &bytes[first_file_offset as usize..]);
+    }
+
+    #[test]
+    fn test_drs_writer_replace() {
+        let mut writer = DrsWriter::new();
+        assert_eq!(None, writer.insert(DrsFileType::Binary, 50500, vec![1]));
+        assert_eq!(Some(vec![1]), writer.insert(DrsFileType::Binary, 50500, vec![2]));
+        assert!(writer.contains(DrsFileType::Binary, 50500));
+        assert_eq!(Some(vec![2]), writer.remove(DrsFileType::Binary, 50500));
+        assert!(!writer.contains(DrsFileType::Binary, 50500));
+    }
+}
@@ -0,0 +1,132 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs_manager::DrsKey;
+
+use std::path::{Path, PathBuf};
+
+/// Where re-releases keep their loose resources, relative to the install directory. Each has
+/// a directory per original archive, holding files named by resource ID.
+const ASSET_LAYOUTS: [&'static str; 2] = ["resources/_common/drs", ""];
+
+/// Entries past this aren't used by anything drawn with an original palette
+const PALETTE_SIZE: usize = 256;
+
+/// Resources from one of the newer editions of the game, used in place of the original DRS
+/// archives' contents while leaving mods to take priority over both. Graphics can be the newer
+/// SLP 4.x files as well as the original kind, and palettes can be JASC-PAL files with an
+/// alpha column and more than 256 colors.
+#[derive(Clone, Debug, Default)]
+pub struct EditionAssets {
+    dir: Option<PathBuf>,
+}
+
+impl EditionAssets {
+    pub fn new() -> EditionAssets {
+        Default::default()
+    }
+
+    /// Uses the assets of the edition installed in the given directory, if any of the known
+    /// layouts is found in it
+    pub fn open<P: AsRef<Path>>(install_dir: P) -> EditionAssets {
+        let install_dir = install_dir.as_ref();
+        for layout in &ASSET_LAYOUTS {
+            let dir = install_dir.join(layout);
+            if ["graphics", "interfac", "terrain"].iter().any(|archive| dir.join(archive).is_dir()) {
+                info!("Using edition assets from {:?}", dir);
+                return EditionAssets { dir: Some(dir) };
+            }
+        }
+        warn!("No edition assets found in {:?}; using the original graphics", install_dir);
+        EditionAssets::new()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dir.is_none()
+    }
+
+    /// Finds the edition's version of a resource with the given extension
+    pub fn find(&self, drs_key: DrsKey, file_id: u32, extension: &str) -> Option<PathBuf> {
+        let path = match self.dir {
+            Some(ref dir) => dir.join(drs_key.name()).join(format!("{}.{}", file_id, extension)),
+            None => return None,
+        };
+        if path.is_file() { Some(path) } else { None }
+    }
+}
+
+/// Rewrites a newer edition's JASC-PAL palette the way the original palettes are written, so
+/// that the same reader can load it: any alpha column is dropped (the original palettes are
+/// opaque) and only the first 256 colors are kept
+pub fn normalize_palette(text: &str) -> Option<String> {
+    let mut lines = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+    match (lines.next(), lines.next(), lines.next()) {
+        (Some("JASC-PAL"), Some(_), Some(_)) => {}
+        _ => return None,
+    }
+    let mut colors = Vec::new();
+    for line in lines.take(PALETTE_SIZE) {
+        let components: Vec<&str> = line.split_whitespace().collect();
+        if components.len() < 3 || components[..3].iter().any(|component| component.parse::<u8>().is_err()) {
+            return None;
+        }
+        colors.push(components[..3].join(" "));
+    }
+    Some(format!("JASC-PAL\r\n0100\r\n{}\r\n{}\r\n", colors.len(), colors.join("\r\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use drs_manager::DrsKey;
+    use std::env;
+    use std::fs;
+    use super::{EditionAssets, normalize_palette};
+
+    #[test]
+    fn test_find() {
+        let install_dir = env::temp_dir().join("chariot_edition_assets_test");
+        let graphics_dir = install_dir.join("resources/_common/drs/graphics");
+        fs::create_dir_all(&graphics_dir).unwrap();
+        fs::File::create(graphics_dir.join("12.slp")).unwrap();
+
+        let assets = EditionAssets::open(&install_dir);
+        assert_eq!(Some(graphics_dir.join("12.slp")), assets.find(DrsKey::Graphics, 12, "slp"));
+        assert_eq!(None, assets.find(DrsKey::Graphics, 13, "slp"));
+        assert_eq!(None, assets.find(DrsKey::Terrain, 12, "slp"));
+        assert!(EditionAssets::open(install_dir.join("missing")).is_empty());
+        fs::remove_dir_all(&install_dir).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_palette() {
+        let mut text = "JASC-PAL\n0100\n300\n".to_string();
+        for index in 0..300 {
+            text.push_str(&format!("{} 0 {} 255\n", index % 256, index % 7));
+        }
+        let normalized = normalize_palette(&text).unwrap();
+        let lines: Vec<&str> = normalized.lines().collect();
+        assert_eq!(vec!["JASC-PAL", "0100", "256", "0 0 0", "1 0 1"], lines[..5].to_vec());
+        assert_eq!(3 + 256, lines.len());
+
+        assert_eq!(None, normalize_palette("not a palette"));
+        assert_eq!(None, normalize_palette("JASC-PAL\n0100\n1\n0 0\n"));
+    }
+}
@@ -25,8 +25,11 @@ use drs;
 use drs_manager::DrsKey;
 use media;
 use palette;
+use png;
+use rustc_serialize::json;
 use slp;
 
+use std::io;
 use std::path::PathBuf;
 
 error_chain! {
@@ -42,6 +45,9 @@ error_chain! {
     }
 
     foreign_links {
+        io::Error, IoError;
+        png::DecodingError, PngError;
+        json::ParserError, JsonError;
     }
 
     errors {
@@ -61,6 +67,46 @@ error_chain! {
             description("SLP not found")
             display("{}.slp not found in \"{}\"", slp_id, drs_key.path())
         }
+        FrameNotFound(drs_key: DrsKey, slp_id: u32, frame: usize) {
+            description("SLP frame not found")
+            display("{}.slp in \"{}\" has no frame {}", slp_id, drs_key.path(), frame)
+        }
+        ResourceNotFound(drs_key: DrsKey, file_id: u32) {
+            description("resource not found")
+            display("resource {} not found in \"{}\"", file_id, drs_key.path())
+        }
+        BadSlpV4(message: String) {
+            description("bad SLP 4.x")
+            display("bad SLP 4.x: {}", message)
+        }
+        UnsupportedOverrideImage(file_name: PathBuf) {
+            description("unsupported override image")
+            display("override image {:?} must be an 8-bit RGBA PNG", file_name)
+        }
+        BadOverrideMetadata(file_name: PathBuf, message: String) {
+            description("bad override metadata")
+            display("bad override metadata in {:?}: {}", file_name, message)
+        }
+        BadModManifest(file_name: PathBuf, message: String) {
+            description("bad mod manifest")
+            display("bad mod manifest {:?}: {}", file_name, message)
+        }
+        InvalidDrsArchive(file_name: PathBuf) {
+            description("invalid DRS archive")
+            display("{:?} is not a valid DRS archive", file_name)
+        }
+        DrsTruncated {
+            description("DRS archive is truncated")
+            display("the DRS archive ends before the end of its tables or resources")
+        }
+        DrsResourceTooLarge(file_id: u32) {
+            description("DRS resource too large")
+            display("resource {} is too large to fit in a DRS archive", file_id)
+        }
+        BadChecksumTable(file_name: PathBuf, message: String) {
+            description("bad checksum table")
+            display("bad checksum table {:?}: {}", file_name, message)
+        }
         GameDirInvalid(message: String) {
             description("Game directory is invalid")
             display("{}", message)
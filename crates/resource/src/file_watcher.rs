@@ -0,0 +1,111 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Notices changes to files by polling their modification times. That's plenty for picking up
+/// edits made while developing, without needing each platform's file notification API.
+pub struct FileWatcher {
+    roots: Vec<PathBuf>,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            roots: Vec::new(),
+            modified: HashMap::new(),
+        }
+    }
+
+    /// Watches a file, or every file under a directory, including ones added to it later
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref().to_path_buf();
+        scan(&path, &mut self.modified);
+        self.roots.push(path);
+    }
+
+    /// Files that were added, changed, or removed since the last poll, sorted by path
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut modified = HashMap::new();
+        for root in &self.roots {
+            scan(root, &mut modified);
+        }
+
+        let mut changed: Vec<PathBuf> = modified.iter()
+            .filter(|&(path, time)| self.modified.get(path) != Some(time))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(self.modified.keys().filter(|path| !modified.contains_key(*path)).cloned());
+        changed.sort();
+
+        self.modified = modified;
+        changed
+    }
+}
+
+fn scan(path: &Path, modified: &mut HashMap<PathBuf, SystemTime>) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                scan(&entry.path(), modified);
+            }
+        }
+    } else if let Ok(time) = metadata.modified() {
+        modified.insert(path.to_path_buf(), time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use super::FileWatcher;
+
+    #[test]
+    fn test_added_and_removed_files() {
+        let dir = env::temp_dir().join("chariot_file_watcher_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("graphics")).unwrap();
+        fs::File::create(dir.join("graphics").join("12.png")).unwrap();
+
+        let mut watcher = FileWatcher::new();
+        watcher.watch(&dir);
+        assert!(watcher.poll().is_empty());
+
+        let added = dir.join("graphics").join("13.png");
+        fs::File::create(&added).unwrap();
+        assert_eq!(vec![added.clone()], watcher.poll());
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_file(&added).unwrap();
+        assert_eq!(vec![added], watcher.poll());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -19,6 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use asset_report::AssetReport;
 use error::*;
 
 use std::ascii::AsciiExt;
@@ -44,19 +45,10 @@ impl GameDir {
         }
 
         let game_dir = GameDir { dir: dir.to_path_buf() };
-        for file_name in &["language.dll",
-                           "data/border.drs",
-                           "data/empires.dat",
-                           "data/graphics.drs",
-                           "data/interfac.drs",
-                           "data/sounds.drs",
-                           "data/terrain.drs",
-                           "data/tileedge.dat"] {
-            if let Ok(actual_file_name) = game_dir.find_file(file_name) {
-                println!("Found {:?} at {:?}", file_name, actual_file_name);
-            } else {
-                return Err(error(dir, &format!("Failed to find {}", file_name)));
-            }
+        let report = AssetReport::generate(&game_dir);
+        info!("Game data in {:?}:\n{}", dir, report);
+        if !report.is_ok() {
+            return Err(error(dir, &format!("Some of the game data is missing or damaged:\n{}", report)));
         }
         Ok(game_dir)
     }
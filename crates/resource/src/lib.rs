@@ -24,7 +24,13 @@
 #[macro_use]
 extern crate error_chain;
 
+extern crate memmap;
 extern crate nalgebra;
+extern crate png;
+extern crate rustc_serialize;
+
+#[macro_use]
+extern crate tracing;
 
 extern crate chariot_drs as drs;
 extern crate chariot_slp as slp;
@@ -35,15 +41,49 @@ extern crate chariot_identifier as identifier;
 #[macro_use]
 extern crate chariot_types as types;
 
+mod asset_report;
+mod chunk_cache;
+mod data_locator;
 mod error;
+mod file_watcher;
 mod game_dir;
+mod lru_cache;
+mod mod_manifest;
+mod mod_overrides;
+mod drs_archive;
 mod drs_manager;
+mod drs_writer;
+mod edition_assets;
+mod palette_manager;
+mod player_palette;
 mod shape_manager;
 mod shape_metadata;
+mod slp_decoder;
+mod sound_manager;
+mod slp_encoder;
+mod slp_v4;
 mod render_command;
 
+pub use asset_report::{AssetCheck, AssetReport, AssetStatus, KnownChecksums, REQUIRED_FILES};
+pub use chunk_cache::{ChunkCache, ChunkKey};
+pub use data_locator::{RejectedDir, candidate_dirs, locate_game_dir};
+pub use drs_archive::{DrsArchive, DrsArchiveEntry, DrsArchiveTable};
 pub use drs_manager::{DrsKey, DrsManager, DrsManagerRef};
+pub use drs_writer::DrsWriter;
+pub use edition_assets::{EditionAssets, normalize_palette};
+pub use error::{Error, ErrorKind, Result};
+pub use file_watcher::FileWatcher;
 pub use game_dir::GameDir;
+pub use lru_cache::LruCache;
+pub use mod_manifest::{LoadOrder, LoadOrderProblem, MANIFEST_FILE_NAME, ModConflict, ModManifest, ModPackage};
+pub use mod_overrides::ModOverrides;
+pub use palette_manager::{COLOR_CYCLES, ColorCycle, MAIN_PALETTE_ID, PaletteManager};
+pub use player_palette::{PlayerPalette, remap_player_colors};
 pub use render_command::*;
-pub use shape_manager::{Shape, ShapeKey, ShapeManager, ShapeManagerRef};
+pub use shape_manager::{DEFAULT_TEXTURE_BUDGET, PRELOAD_BUDGET, Shape, ShapeKey, ShapeManager,
+                        ShapeManagerRef};
 pub use shape_metadata::{ShapeMetadata, ShapeMetadataKey, ShapeMetadataStore, ShapeMetadataStoreRef};
+pub use slp_decoder::{DecodedSlps, decode_in_parallel};
+pub use sound_manager::{SoundManager, SoundManagerRef};
+pub use slp_encoder::{SlpEncoder, SlpFrame, SlpPixel};
+pub use slp_v4::{SlpV4Frame, decode_slp_v4, is_slp_v4};
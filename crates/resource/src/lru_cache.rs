@@ -0,0 +1,210 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+struct LruEntry<V> {
+    value: V,
+    cost: usize,
+    last_used: u64,
+}
+
+/// Cache that evicts the least recently used values once the total cost of
+/// everything in it goes over budget
+pub struct LruCache<K: Eq + Hash + Copy, V> {
+    entries: HashMap<K, LruEntry<V>>,
+    usage_order: BTreeMap<u64, K>,
+    next_use: u64,
+    budget: usize,
+    used: usize,
+}
+
+impl<K: Eq + Hash + Copy, V> LruCache<K, V> {
+    pub fn new(budget: usize) -> LruCache<K, V> {
+        LruCache {
+            entries: HashMap::new(),
+            usage_order: BTreeMap::new(),
+            next_use: 0,
+            budget: budget,
+            used: 0,
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict_over_budget(None);
+    }
+
+    /// Total cost of everything currently cached
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Retrieves a value and marks it as the most recently used
+    pub fn get<'a>(&'a mut self, key: &K) -> Option<&'a V> {
+        let next_use = self.next_use;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.usage_order.remove(&entry.last_used);
+                self.usage_order.insert(next_use, *key);
+                entry.last_used = next_use;
+                self.next_use += 1;
+                Some(&entry.value)
+            }
+            None => None,
+        }
+    }
+
+    /// Retrieves a value to change it in place, without counting as a use of it
+    pub fn peek_mut<'a>(&'a mut self, key: &K) -> Option<&'a mut V> {
+        self.entries.get_mut(key).map(|entry| &mut entry.value)
+    }
+
+    /// Adds a value, evicting the least recently used values to stay within budget.
+    /// The new value itself is never evicted, even if it's over budget on its own.
+    pub fn insert(&mut self, key: K, value: V, cost: usize) {
+        self.remove(&key);
+
+        let last_used = self.next_use;
+        self.next_use += 1;
+        self.entries.insert(key,
+                            LruEntry {
+                                value: value,
+                                cost: cost,
+                                last_used: last_used,
+                            });
+        self.usage_order.insert(last_used, key);
+        self.used += cost;
+        self.evict_over_budget(Some(key));
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.usage_order.remove(&entry.last_used);
+                self.used -= entry.cost;
+                Some(entry.value)
+            }
+            None => None,
+        }
+    }
+
+    /// Removes every value whose key matches, and returns how many were removed
+    pub fn remove_where<F: Fn(&K) -> bool>(&mut self, matches: F) -> usize {
+        let keys: Vec<K> = self.entries.keys().filter(|key| matches(key)).cloned().collect();
+        for key in &keys {
+            self.remove(key);
+        }
+        keys.len()
+    }
+
+    fn evict_over_budget(&mut self, keep: Option<K>) {
+        while self.used > self.budget {
+            let oldest = match self.usage_order.iter().next() {
+                Some((_, key)) => *key,
+                None => break,
+            };
+            if Some(oldest) == keep {
+                break;
+            }
+            self.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let mut cache = LruCache::new(10);
+        cache.insert(1, "one", 4);
+        cache.insert(2, "two", 4);
+        assert_eq!(Some(&"one"), cache.get(&1));
+
+        cache.insert(3, "three", 4);
+        assert!(cache.contains_key(&1));
+        assert!(!cache.contains_key(&2));
+        assert!(cache.contains_key(&3));
+        assert_eq!(8, cache.used());
+    }
+
+    #[test]
+    fn test_lru_cache_keeps_oversized_value() {
+        let mut cache = LruCache::new(10);
+        cache.insert(1, "one", 4);
+        cache.insert(2, "two", 20);
+        assert_eq!(1, cache.len());
+        assert!(cache.contains_key(&2));
+
+        cache.set_budget(5);
+        assert_eq!(0, cache.len());
+        assert_eq!(0, cache.used());
+    }
+
+    #[test]
+    fn test_lru_cache_replace() {
+        let mut cache = LruCache::new(10);
+        cache.insert(1, "one", 4);
+        cache.insert(1, "uno", 6);
+        assert_eq!(6, cache.used());
+        assert_eq!(Some("uno"), cache.remove(&1));
+        assert_eq!(0, cache.used());
+    }
+
+    #[test]
+    fn test_lru_cache_remove_where() {
+        let mut cache = LruCache::new(10);
+        cache.insert(1, "one", 1);
+        cache.insert(2, "two", 2);
+        cache.insert(3, "three", 3);
+        assert_eq!(2, cache.remove_where(|key| key % 2 == 1));
+        assert_eq!(1, cache.len());
+        assert_eq!(2, cache.used());
+    }
+
+    #[test]
+    fn test_lru_cache_peek_mut_keeps_usage_order() {
+        let mut cache = LruCache::new(10);
+        cache.insert(1, "one", 4);
+        cache.insert(2, "two", 4);
+        *cache.peek_mut(&1).unwrap() = "uno";
+
+        cache.insert(3, "three", 4);
+        assert!(!cache.contains_key(&1));
+        assert_eq!(Some(&"two"), cache.get(&2));
+        assert!(cache.peek_mut(&1).is_none());
+    }
+}
@@ -0,0 +1,428 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs_manager::DrsKey;
+use error::{ErrorKind, Result};
+use rustc_serialize::json::Json;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file at the top of a mod's directory
+pub const MANIFEST_FILE_NAME: &'static str = "mod.json";
+
+/// Describes a mod. Manifests are optional, so that a directory of replacement files is still
+/// a mod; one looks like this:
+///
+/// ```json
+/// {
+///     "name": "HD Trees",
+///     "version": "1.2.0",
+///     "dependencies": ["hd-base"],
+///     "scripts": ["scripts/forests.rhai"],
+///     "map_scripts": ["maps/woodlands.rhai"]
+/// }
+/// ```
+///
+/// Dependencies are the directory names of other mods, which are loaded first. Script paths are
+/// relative to the mod's directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: String,
+    pub dependencies: Vec<String>,
+    pub scripts: Vec<PathBuf>,
+    pub map_scripts: Vec<PathBuf>,
+}
+
+impl ModManifest {
+    /// The manifest of a mod that doesn't have one
+    pub fn unversioned(name: &str) -> ModManifest {
+        ModManifest {
+            name: name.to_string(),
+            version: "0.0.0".into(),
+            dependencies: Vec::new(),
+            scripts: Vec::new(),
+            map_scripts: Vec::new(),
+        }
+    }
+
+    pub fn parse(file_name: &Path, contents: &str) -> Result<ModManifest> {
+        let bad_manifest = |message: &str| ErrorKind::BadModManifest(file_name.to_path_buf(), message.into());
+        let manifest = try!(Json::from_str(contents));
+        if !manifest.is_object() {
+            return Err(bad_manifest("the manifest must be an object").into());
+        }
+        let string = |name: &str| -> Result<Option<String>> {
+            match manifest.find(name) {
+                Some(value) => {
+                    value.as_string()
+                        .map(|value| Some(value.to_string()))
+                        .ok_or(bad_manifest(&format!("\"{}\" must be a string", name)).into())
+                }
+                None => Ok(None),
+            }
+        };
+        let string_list = |name: &str| -> Result<Vec<String>> {
+            let list = match manifest.find(name) {
+                Some(list) => {
+                    try!(list.as_array().ok_or(bad_manifest(&format!("\"{}\" must be a list", name))))
+                }
+                None => return Ok(Vec::new()),
+            };
+            list.iter()
+                .map(|value| {
+                    value.as_string()
+                        .map(|value| value.to_string())
+                        .ok_or(bad_manifest(&format!("\"{}\" must only contain strings", name)).into())
+                })
+                .collect()
+        };
+
+        let name = try!(try!(string("name")).ok_or(bad_manifest("missing \"name\"")));
+        Ok(ModManifest {
+            name: name,
+            version: try!(string("version")).unwrap_or("0.0.0".into()),
+            dependencies: try!(string_list("dependencies")),
+            scripts: try!(string_list("scripts")).into_iter().map(PathBuf::from).collect(),
+            map_scripts: try!(string_list("map_scripts")).into_iter().map(PathBuf::from).collect(),
+        })
+    }
+
+    /// Reads the manifest in a mod's directory, if it has one
+    pub fn read_from_dir(mod_dir: &Path) -> Result<Option<ModManifest>> {
+        let file_name = mod_dir.join(MANIFEST_FILE_NAME);
+        if !file_name.is_file() {
+            return Ok(None);
+        }
+        let mut contents = String::new();
+        try!(try!(File::open(&file_name)).read_to_string(&mut contents));
+        ModManifest::parse(&file_name, &contents).map(Some)
+    }
+}
+
+/// A mod found in the mods directory
+#[derive(Clone, Debug)]
+pub struct ModPackage {
+    /// The mod's directory name, which is what the config and dependencies refer to it by
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: ModManifest,
+}
+
+impl ModPackage {
+    pub fn script_paths(&self) -> Vec<PathBuf> {
+        self.manifest.scripts.iter().map(|script| self.dir.join(script)).collect()
+    }
+
+    pub fn map_script_paths(&self) -> Vec<PathBuf> {
+        self.manifest.map_scripts.iter().map(|script| self.dir.join(script)).collect()
+    }
+
+    /// The resources the mod replaces, as the archive and resource ID of each
+    fn replaced_resources(&self) -> HashSet<(DrsKey, u32)> {
+        let mut resources = HashSet::new();
+        let archive_dirs = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return resources,
+        };
+        for archive_dir in archive_dirs.filter_map(|entry| entry.ok()) {
+            let drs_key = match archive_dir.file_name().to_str().and_then(DrsKey::from_name) {
+                Some(drs_key) => drs_key,
+                None => continue,
+            };
+            let files = match fs::read_dir(archive_dir.path()) {
+                Ok(files) => files,
+                Err(_) => continue,
+            };
+            for file in files.filter_map(|entry| entry.ok()) {
+                let path = file.path();
+                let file_id = path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok());
+                if let Some(file_id) = file_id {
+                    resources.insert((drs_key, file_id));
+                }
+            }
+        }
+        resources
+    }
+}
+
+/// Why an enabled mod, or one it depends on, couldn't be loaded
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoadOrderProblem {
+    MissingMod(String),
+    BadManifest(String, String),
+    MissingDependency {
+        mod_id: String,
+        dependency: String,
+    },
+    /// The mods that depend on each other, in dependency order with the first repeated at the end
+    DependencyCycle(Vec<String>),
+}
+
+impl fmt::Display for LoadOrderProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadOrderProblem::MissingMod(ref mod_id) => write!(f, "mod \"{}\" doesn't exist", mod_id),
+            LoadOrderProblem::BadManifest(ref mod_id, ref message) => {
+                write!(f, "mod \"{}\" has a bad manifest: {}", mod_id, message)
+            }
+            LoadOrderProblem::MissingDependency { ref mod_id, ref dependency } => {
+                write!(f, "mod \"{}\" depends on \"{}\", which couldn't be loaded", mod_id, dependency)
+            }
+            LoadOrderProblem::DependencyCycle(ref mod_ids) => {
+                write!(f, "mods depend on each other: {}", mod_ids.join(" -> "))
+            }
+        }
+    }
+}
+
+/// A resource that more than one of the loaded mods replaces; the last of them wins
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModConflict {
+    pub drs_key: DrsKey,
+    pub file_id: u32,
+    /// The mods that replace the resource, in load order
+    pub mod_ids: Vec<String>,
+}
+
+impl fmt::Display for ModConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} {} is replaced by {}; using the one from \"{}\"",
+               self.drs_key.name(),
+               self.file_id,
+               self.mod_ids.join(", "),
+               self.mod_ids.last().unwrap())
+    }
+}
+
+/// The order that the enabled mods get loaded in. Mods keep the order they were enabled in,
+/// except that each one's dependencies are loaded before it, even if they weren't enabled.
+/// Mods that can't be loaded are left out, along with the mods that depend on them.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOrder {
+    pub packages: Vec<ModPackage>,
+    pub problems: Vec<LoadOrderProblem>,
+    pub conflicts: Vec<ModConflict>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Visit {
+    InProgress,
+    Loaded,
+    Failed,
+}
+
+impl LoadOrder {
+    pub fn resolve<P: AsRef<Path>, S: AsRef<str>>(mods_dir: P, enabled: &[S]) -> LoadOrder {
+        let mut load_order = LoadOrder::default();
+        let mut visits = HashMap::new();
+        let mut path = Vec::new();
+        for mod_id in enabled {
+            load_order.visit(mods_dir.as_ref(), mod_id.as_ref(), &mut visits, &mut path);
+        }
+        load_order.conflicts = find_conflicts(&load_order.packages);
+        load_order
+    }
+
+    pub fn mod_dirs(&self) -> Vec<PathBuf> {
+        self.packages.iter().map(|package| package.dir.clone()).collect()
+    }
+
+    /// Adds the mod after its dependencies, returning whether it could be loaded
+    fn visit(&mut self,
+             mods_dir: &Path,
+             mod_id: &str,
+             visits: &mut HashMap<String, Visit>,
+             path: &mut Vec<String>)
+             -> bool {
+        match visits.get(mod_id).cloned() {
+            Some(Visit::Loaded) => return true,
+            Some(Visit::Failed) => return false,
+            Some(Visit::InProgress) => {
+                let start = path.iter().position(|id| id == mod_id).unwrap();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(mod_id.to_string());
+                self.problems.push(LoadOrderProblem::DependencyCycle(cycle));
+                return false;
+            }
+            None => {}
+        }
+
+        let dir = mods_dir.join(mod_id);
+        let manifest = if dir.is_dir() {
+            match ModManifest::read_from_dir(&dir) {
+                Ok(manifest) => Some(manifest.unwrap_or(ModManifest::unversioned(mod_id))),
+                Err(err) => {
+                    self.problems.push(LoadOrderProblem::BadManifest(mod_id.into(), format!("{}", err)));
+                    None
+                }
+            }
+        } else {
+            self.problems.push(LoadOrderProblem::MissingMod(mod_id.into()));
+            None
+        };
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                visits.insert(mod_id.into(), Visit::Failed);
+                return false;
+            }
+        };
+
+        visits.insert(mod_id.into(), Visit::InProgress);
+        path.push(mod_id.into());
+        let mut loaded = true;
+        for dependency in &manifest.dependencies {
+            if !self.visit(mods_dir, dependency, visits, path) {
+                self.problems.push(LoadOrderProblem::MissingDependency {
+                    mod_id: mod_id.into(),
+                    dependency: dependency.clone(),
+                });
+                loaded = false;
+            }
+        }
+        path.pop();
+
+        if loaded {
+            self.packages.push(ModPackage {
+                id: mod_id.into(),
+                dir: dir,
+                manifest: manifest,
+            });
+            visits.insert(mod_id.into(), Visit::Loaded);
+        } else {
+            visits.insert(mod_id.into(), Visit::Failed);
+        }
+        loaded
+    }
+}
+
+/// Finds the resources that more than one of the packages replace, sorted by archive and ID
+fn find_conflicts(packages: &[ModPackage]) -> Vec<ModConflict> {
+    let mut replaced_by: HashMap<(DrsKey, u32), Vec<String>> = HashMap::new();
+    for package in packages {
+        for resource in package.replaced_resources() {
+            replaced_by.entry(resource).or_insert(Vec::new()).push(package.id.clone());
+        }
+    }
+    let mut conflicts: Vec<ModConflict> = replaced_by.into_iter()
+        .filter(|&(_, ref mod_ids)| mod_ids.len() > 1)
+        .map(|((drs_key, file_id), mod_ids)| {
+            ModConflict {
+                drs_key: drs_key,
+                file_id: file_id,
+                mod_ids: mod_ids,
+            }
+        })
+        .collect();
+    conflicts.sort_by_key(|conflict| (conflict.drs_key.name(), conflict.file_id));
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use drs_manager::DrsKey;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use super::{LoadOrder, LoadOrderProblem, ModConflict, ModManifest};
+
+    fn write_mod(mods_dir: &Path, mod_id: &str, manifest: Option<&str>, graphics: &[u32]) {
+        let graphics_dir = mods_dir.join(mod_id).join("graphics");
+        fs::create_dir_all(&graphics_dir).unwrap();
+        if let Some(manifest) = manifest {
+            fs::File::create(mods_dir.join(mod_id).join("mod.json"))
+                .unwrap()
+                .write_all(manifest.as_bytes())
+                .unwrap();
+        }
+        for slp_id in graphics {
+            fs::File::create(graphics_dir.join(format!("{}.png", slp_id))).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let file_name = PathBuf::from("mod.json");
+        let manifest = ModManifest::parse(&file_name,
+                                          r#"{"name": "HD Trees", "version": "1.2.0",
+                                              "dependencies": ["hd-base"], "scripts": ["a.rhai"]}"#)
+            .unwrap();
+        assert_eq!("HD Trees", manifest.name);
+        assert_eq!("1.2.0", manifest.version);
+        assert_eq!(vec!["hd-base".to_string()], manifest.dependencies);
+        assert_eq!(vec![PathBuf::from("a.rhai")], manifest.scripts);
+        assert!(manifest.map_scripts.is_empty());
+
+        assert!(ModManifest::parse(&file_name, r#"{"version": "1.0"}"#).is_err());
+        assert!(ModManifest::parse(&file_name, r#"{"name": "x", "dependencies": "y"}"#).is_err());
+        assert!(ModManifest::parse(&file_name, r#"{"name": "x", "scripts": [1]}"#).is_err());
+        assert!(ModManifest::parse(&file_name, "[]").is_err());
+    }
+
+    #[test]
+    fn test_resolve_load_order() {
+        let mods_dir = env::temp_dir().join("chariot_mod_manifest_test");
+        write_mod(&mods_dir, "base", None, &[12, 13]);
+        write_mod(&mods_dir, "trees", Some(r#"{"name": "Trees", "dependencies": ["base"]}"#), &[13]);
+        write_mod(&mods_dir, "broken", Some(r#"{"name": "Broken", "dependencies": ["missing"]}"#), &[]);
+        write_mod(&mods_dir, "first", Some(r#"{"name": "First", "dependencies": ["second"]}"#), &[]);
+        write_mod(&mods_dir, "second", Some(r#"{"name": "Second", "dependencies": ["first"]}"#), &[]);
+
+        let load_order = LoadOrder::resolve(&mods_dir, &["trees", "broken", "first", "base"]);
+        let mod_ids: Vec<&str> = load_order.packages.iter().map(|package| &package.id[..]).collect();
+        assert_eq!(vec!["base", "trees"], mod_ids);
+        assert_eq!("base", load_order.packages[0].manifest.name);
+        assert_eq!(vec![LoadOrderProblem::MissingMod("missing".into()),
+                        LoadOrderProblem::MissingDependency {
+                            mod_id: "broken".into(),
+                            dependency: "missing".into(),
+                        },
+                        LoadOrderProblem::DependencyCycle(vec!["first".into(),
+                                                               "second".into(),
+                                                               "first".into()]),
+                        LoadOrderProblem::MissingDependency {
+                            mod_id: "second".into(),
+                            dependency: "first".into(),
+                        },
+                        LoadOrderProblem::MissingDependency {
+                            mod_id: "first".into(),
+                            dependency: "second".into(),
+                        }],
+                   load_order.problems);
+        assert_eq!(vec![ModConflict {
+                            drs_key: DrsKey::Graphics,
+                            file_id: 13,
+                            mod_ids: vec!["base".into(), "trees".into()],
+                        }],
+                   load_order.conflicts);
+
+        fs::remove_dir_all(&mods_dir).unwrap();
+    }
+}
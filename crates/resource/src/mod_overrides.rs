@@ -0,0 +1,135 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs_manager::DrsKey;
+
+use std::path::{Path, PathBuf};
+
+/// Replacement resources provided by mods, which take priority over the contents of the
+/// original DRS archives. A mod is a directory under the mods directory laid out as
+/// `mods/<name>/<archive>/<resource id>.<extension>`, for example `mods/hd/graphics/12.png`.
+/// When more than one mod replaces the same resource, the one enabled last wins.
+#[derive(Clone, Debug, Default)]
+pub struct ModOverrides {
+    mod_dirs: Vec<PathBuf>,
+}
+
+impl ModOverrides {
+    pub fn new() -> ModOverrides {
+        Default::default()
+    }
+
+    /// Enables the given mods, looking for them in `mods_dir`. Mods that
+    /// don't exist are skipped with a warning.
+    pub fn from_names<P: AsRef<Path>, S: AsRef<str>>(mods_dir: P, mod_names: &[S]) -> ModOverrides {
+        let mut overrides = ModOverrides::new();
+        for mod_name in mod_names {
+            let mod_dir = mods_dir.as_ref().join(mod_name.as_ref());
+            if mod_dir.is_dir() {
+                info!("Enabled mod {:?}", mod_dir);
+                overrides.mod_dirs.push(mod_dir);
+            } else {
+                warn!("Mod directory {:?} doesn't exist; skipping", mod_dir);
+            }
+        }
+        overrides
+    }
+
+    /// Enables the mods in the given directories, which should already be in load order
+    pub fn from_dirs(mod_dirs: Vec<PathBuf>) -> ModOverrides {
+        ModOverrides { mod_dirs: mod_dirs }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mod_dirs.is_empty()
+    }
+
+    pub fn mod_dirs<'a>(&'a self) -> &'a [PathBuf] {
+        &self.mod_dirs
+    }
+
+    /// The archive and resource ID that a file in one of the mods replaces, if it's laid out
+    /// like a replacement; the inverse of `find`
+    pub fn resource_at(&self, path: &Path) -> Option<(DrsKey, u32)> {
+        let mod_dir = match self.mod_dirs.iter().find(|mod_dir| path.starts_with(mod_dir)) {
+            Some(mod_dir) => mod_dir,
+            None => return None,
+        };
+        let relative = path.strip_prefix(mod_dir).unwrap();
+        let components: Vec<_> = relative.components().map(|component| component.as_os_str()).collect();
+        if components.len() != 2 {
+            return None;
+        }
+        let drs_key = match components[0].to_str().and_then(DrsKey::from_name) {
+            Some(drs_key) => drs_key,
+            None => return None,
+        };
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+            .map(|file_id| (drs_key, file_id))
+    }
+
+    /// Finds the replacement for a resource with the given extension, if any mod has one
+    pub fn find(&self, drs_key: DrsKey, file_id: u32, extension: &str) -> Option<PathBuf> {
+        let file_name = format!("{}.{}", file_id, extension);
+        self.mod_dirs
+            .iter()
+            .rev()
+            .map(|mod_dir| mod_dir.join(drs_key.name()).join(&file_name))
+            .find(|path| path.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drs_manager::DrsKey;
+    use std::env;
+    use std::fs;
+    use super::ModOverrides;
+
+    #[test]
+    fn test_later_mods_take_priority() {
+        let mods_dir = env::temp_dir().join(format!("chariot_mod_overrides_test_{}", ::std::process::id()));
+        for mod_name in &["first", "second"] {
+            let graphics_dir = mods_dir.join(mod_name).join("graphics");
+            fs::create_dir_all(&graphics_dir).unwrap();
+            fs::File::create(graphics_dir.join("12.png")).unwrap();
+        }
+        fs::File::create(mods_dir.join("first").join("graphics").join("13.png")).unwrap();
+
+        let overrides = ModOverrides::from_names(&mods_dir, &["first", "second", "missing"]);
+        assert_eq!(Some(mods_dir.join("second").join("graphics").join("12.png")),
+                   overrides.find(DrsKey::Graphics, 12, "png"));
+        assert_eq!(Some(mods_dir.join("first").join("graphics").join("13.png")),
+                   overrides.find(DrsKey::Graphics, 13, "png"));
+        assert_eq!(None, overrides.find(DrsKey::Graphics, 12, "wav"));
+        assert_eq!(None, overrides.find(DrsKey::Terrain, 12, "png"));
+
+        let metadata_path = mods_dir.join("first").join("terrain").join("15001.json");
+        assert_eq!(Some((DrsKey::Terrain, 15001)), overrides.resource_at(&metadata_path));
+        assert_eq!(None, overrides.resource_at(&mods_dir.join("first").join("readme.txt")));
+        assert_eq!(None, overrides.resource_at(&mods_dir.join("first").join("music").join("1.png")));
+        assert_eq!(None, overrides.resource_at(&mods_dir.join("missing").join("graphics").join("1.png")));
+
+        fs::remove_dir_all(&mods_dir).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs::DrsFileType;
+use drs_archive::DrsArchive;
+use drs_manager::DrsKey;
+use edition_assets::{EditionAssets, normalize_palette};
+use error::*;
+use palette::{self, PaletteColor};
+
+use std::fs::File;
+use std::io::{self, Read};
+
+/// The palette every SLP is drawn with. empires.dat doesn't refer to any other: its player
+/// colors and terrain minimap colors are indices into this one.
+pub const MAIN_PALETTE_ID: u32 = 50500;
+
+/// Real time between each step of the color cycling animation
+pub const COLOR_CYCLE_STEP_NANOS: u64 = 1000000000 / 5;
+
+/// A range of palette entries that rotates by one entry every cycle step
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ColorCycle {
+    pub start: u8,
+    pub length: u8,
+}
+
+impl ColorCycle {
+    #[inline]
+    pub fn contains(&self, color_index: u8) -> bool {
+        color_index >= self.start && (color_index as usize) < self.start as usize + self.length as usize
+    }
+}
+
+/// Shoreline water shimmer
+pub const WATER_CYCLE: ColorCycle = ColorCycle {
+    start: 248,
+    length: 7,
+};
+
+/// Glowing lava and embers
+pub const LAVA_CYCLE: ColorCycle = ColorCycle {
+    start: 232,
+    length: 8,
+};
+
+pub const COLOR_CYCLES: [ColorCycle; 2] = [WATER_CYCLE, LAVA_CYCLE];
+
+/// Rotates every color cycle range of the palette forward by the given number of steps
+pub fn cycle_palette(palette: &[u32], step: u32) -> Vec<u32> {
+    let mut cycled = palette.to_vec();
+    for cycle in &COLOR_CYCLES {
+        let (start, length) = (cycle.start as usize, cycle.length as usize);
+        if length == 0 || start + length > palette.len() {
+            continue;
+        }
+        for offset in 0..length {
+            let source = (offset + step as usize) % length;
+            cycled[start + offset] = palette[start + source];
+        }
+    }
+    cycled
+}
+
+/// Fades the colors the way tiles that are explored but out of sight are drawn: a bit toward
+/// gray, and a bit darker. Entries are packed with red in the highest byte and alpha in the lowest.
+pub fn fog_palette(palette: &[u32]) -> Vec<u32> {
+    palette.iter()
+        .map(|&color| {
+            let (red, green, blue) = ((color >> 24) & 0xff, (color >> 16) & 0xff, (color >> 8) & 0xff);
+            let gray = (red * 77 + green * 150 + blue * 29) >> 8;
+            let fade = |channel: u32| (channel * 3 + gray * 2) / 5 * 4 / 5;
+            fade(red) << 24 | fade(green) << 16 | fade(blue) << 8 | (color & 0xff)
+        })
+        .collect()
+}
+
+/// True if the palette index is animated by color cycling
+pub fn is_cycled_color(color_index: u8) -> bool {
+    COLOR_CYCLES.iter().any(|cycle| cycle.contains(color_index))
+}
+
+fn to_pixel_format(colors: &[PaletteColor]) -> Vec<u32> {
+    colors.iter().map(|c: &PaletteColor| -> u32 { (*c).into() }).collect()
+}
+
+/// The palette the game data is drawn with, converted to the pixel format textures are built from
+pub struct PaletteManager {
+    main: Vec<u32>,
+}
+
+impl PaletteManager {
+    pub fn load_from(interfac: &DrsArchive) -> Result<PaletteManager> {
+        let bin_table = try!(interfac.find_table(DrsFileType::Binary)
+            .ok_or(ErrorKind::InterfacBinaryTableMissing));
+        let contents = try!(bin_table.find_file_contents(MAIN_PALETTE_ID)
+            .ok_or(ErrorKind::InterfacMissingPalette));
+        let colors = try!(palette::read_from(&mut io::Cursor::new(contents)));
+
+        Ok(PaletteManager { main: to_pixel_format(&colors) })
+    }
+
+    /// Replaces the palette with a newer edition's version of it, `interfac/50500.pal`, if it
+    /// has one that can be read
+    pub fn apply_edition_palette(&mut self, edition_assets: &EditionAssets) {
+        let path = match edition_assets.find(DrsKey::Interfac, MAIN_PALETTE_ID, "pal") {
+            Some(path) => path,
+            None => return,
+        };
+        let mut text = String::new();
+        if let Err(err) = File::open(&path).and_then(|mut file| file.read_to_string(&mut text)) {
+            warn!("Failed to read palette {:?}: {}", path, err);
+            return;
+        }
+        let colors = normalize_palette(&text)
+            .ok_or(())
+            .and_then(|text| palette::read_from(&mut io::Cursor::new(text.as_bytes())).map_err(|_| ()));
+        match colors {
+            Ok(colors) => self.main = to_pixel_format(&colors),
+            Err(_) => warn!("Palette {:?} isn't a JASC-PAL palette; keeping the original", path),
+        }
+    }
+
+    #[inline]
+    pub fn main<'a>(&'a self) -> &'a [u32] {
+        &self.main
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{COLOR_CYCLES, LAVA_CYCLE, WATER_CYCLE, cycle_palette, fog_palette, is_cycled_color};
+
+    fn test_palette() -> Vec<u32> {
+        (0..256u32).collect()
+    }
+
+    #[test]
+    fn test_cycle_palette_rotates_ranges() {
+        let palette = test_palette();
+        let cycled = cycle_palette(&palette, 1);
+
+        let start = WATER_CYCLE.start as usize;
+        let end = start + WATER_CYCLE.length as usize;
+        for index in start..(end - 1) {
+            assert_eq!(palette[index + 1], cycled[index]);
+        }
+        assert_eq!(palette[start], cycled[end - 1]);
+
+        // Colors outside of the cycles are untouched
+        assert_eq!(palette[0], cycled[0]);
+        assert_eq!(palette[100], cycled[100]);
+    }
+
+    #[test]
+    fn test_cycle_palette_wraps_around() {
+        let palette = test_palette();
+        let full_rotation = COLOR_CYCLES.iter().fold(1, |steps, cycle| steps * cycle.length as u32);
+        assert_eq!(palette, cycle_palette(&palette, 0));
+        assert_eq!(palette, cycle_palette(&palette, full_rotation));
+        assert!(palette != cycle_palette(&palette, full_rotation + 1));
+    }
+
+    #[test]
+    fn test_fog_palette() {
+        let faded = fog_palette(&[0xff0000ff, 0x80808080, 0xffffffff]);
+        // Pure red goes grayer and darker, keeping its alpha
+        assert_eq!(0x921818ff, faded[0]);
+        // Grays only get darker
+        assert_eq!(0x66666680, faded[1]);
+        assert_eq!(0xccccccff, faded[2]);
+    }
+
+    #[test]
+    fn test_is_cycled_color() {
+        assert!(!is_cycled_color(0));
+        assert!(!is_cycled_color(100));
+        assert!(is_cycled_color(WATER_CYCLE.start));
+        assert!(is_cycled_color(LAVA_CYCLE.start + LAVA_CYCLE.length - 1));
+        assert!(!is_cycled_color(WATER_CYCLE.start + WATER_CYCLE.length));
+    }
+}
@@ -0,0 +1,175 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use types::Color;
+
+/// The first palette index of player color 1; each player color has its own run of
+/// eight shades after that, from 16 * color to 16 * color + 7
+const PLAYER_COLOR_START: usize = 16;
+const PLAYER_COLOR_SHADES: usize = 8;
+const PLAYER_COLOR_COUNT: usize = 8;
+
+/// Sets of colors to draw the players in. The alternatives are picked so that players can be
+/// told apart with the common kinds of color blindness.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayerPalette {
+    /// The colors from the game's palette
+    Original,
+    /// Based on the Okabe-Ito palette, which holds up with red-green color blindness
+    ColorBlind,
+    /// Based on Paul Tol's bright scheme, with the colors further apart in brightness too
+    HighContrast,
+}
+
+impl PlayerPalette {
+    pub fn from_name(name: &str) -> Option<PlayerPalette> {
+        match name {
+            "original" => Some(PlayerPalette::Original),
+            "color_blind" => Some(PlayerPalette::ColorBlind),
+            "high_contrast" => Some(PlayerPalette::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PlayerPalette::Original => "original",
+            PlayerPalette::ColorBlind => "color_blind",
+            PlayerPalette::HighContrast => "high_contrast",
+        }
+    }
+
+    /// The main shade of the given player color (1 through 8), for drawing things like
+    /// swatches and minimap dots that aren't taken from the graphics
+    pub fn color(&self, player_color: u8) -> Color {
+        let (r, g, b) = self.base_color(player_color);
+        Color::rgb(r, g, b)
+    }
+
+    fn base_color(&self, player_color: u8) -> (u8, u8, u8) {
+        let colors = match *self {
+            // Approximations of the player colors in the game's palette
+            PlayerPalette::Original => {
+                [(0, 40, 200), (200, 0, 0), (230, 230, 0), (140, 90, 40), (240, 140, 0), (0, 160, 0),
+                 (150, 150, 150), (0, 200, 200)]
+            }
+            PlayerPalette::ColorBlind => {
+                [(0, 114, 178), (213, 94, 0), (240, 228, 66), (204, 121, 167), (230, 159, 0),
+                 (0, 158, 115), (150, 150, 150), (86, 180, 233)]
+            }
+            PlayerPalette::HighContrast => {
+                [(68, 119, 170), (238, 102, 119), (204, 187, 68), (170, 51, 119), (240, 240, 240),
+                 (34, 136, 51), (51, 51, 51), (102, 204, 238)]
+            }
+        };
+        match player_color {
+            1...8 => colors[player_color as usize - 1],
+            _ => (255, 255, 255),
+        }
+    }
+}
+
+/// Replaces the shades of the given player color (1 through 8) in the palette with ones of the
+/// chosen player palette. Each replacement keeps the brightness of the shade it replaces relative
+/// to the brightest of the run, so shading in the graphics still reads the same. The original
+/// player palette and other player colors give back the palette as it is.
+pub fn remap_player_colors(palette: &[u32], player_palette: PlayerPalette, player_color: u8) -> Vec<u32> {
+    let mut remapped = palette.to_vec();
+    if player_palette == PlayerPalette::Original || player_color == 0 ||
+       player_color as usize > PLAYER_COLOR_COUNT {
+        return remapped;
+    }
+
+    let start = PLAYER_COLOR_START * player_color as usize;
+    let end = start + PLAYER_COLOR_SHADES;
+    if end > palette.len() {
+        return remapped;
+    }
+
+    let brightest = palette[start..end].iter().map(|&color| brightness(color)).max().unwrap_or(0);
+    let (r, g, b) = player_palette.base_color(player_color);
+    for index in start..end {
+        let shade = |channel: u8| if brightest == 0 {
+            channel as u32
+        } else {
+            channel as u32 * brightness(palette[index]) / brightest
+        };
+        remapped[index] = shade(r) << 24 | shade(g) << 16 | shade(b) << 8 | (palette[index] & 0xff);
+    }
+    remapped
+}
+
+fn brightness(color: u32) -> u32 {
+    ((color >> 24) & 0xff) * 77 + ((color >> 16) & 0xff) * 150 + ((color >> 8) & 0xff) * 29
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PLAYER_COLOR_START, PlayerPalette, remap_player_colors};
+
+    fn gray(level: u32) -> u32 {
+        level << 24 | level << 16 | level << 8 | 0xff
+    }
+
+    fn test_palette() -> Vec<u32> {
+        (0..256u32).map(|index| gray(index % 16 * 16)).collect()
+    }
+
+    #[test]
+    fn test_original_palette_is_untouched() {
+        let palette = test_palette();
+        for player_color in 0..10 {
+            assert_eq!(palette, remap_player_colors(&palette, PlayerPalette::Original, player_color));
+        }
+        assert_eq!(palette, remap_player_colors(&palette, PlayerPalette::ColorBlind, 0));
+        assert_eq!(palette, remap_player_colors(&palette, PlayerPalette::ColorBlind, 9));
+    }
+
+    #[test]
+    fn test_remap_only_touches_the_player_color() {
+        let palette = test_palette();
+        let remapped = remap_player_colors(&palette, PlayerPalette::ColorBlind, 2);
+        let start = PLAYER_COLOR_START * 2;
+        for index in 0..256 {
+            if index >= start && index < start + 8 {
+                assert!(palette[index] != remapped[index] || palette[index] == 0xff);
+            } else {
+                assert_eq!(palette[index], remapped[index]);
+            }
+        }
+
+        // The brightest shade gets the base color, and the darker ones keep their order
+        let color = PlayerPalette::ColorBlind.color(2);
+        let brightest = (color.r as u32) << 24 | (color.g as u32) << 16 | (color.b as u32) << 8 | 0xff;
+        assert_eq!(brightest, remapped[start + 7]);
+        for index in start..(start + 7) {
+            assert!(remapped[index] >> 24 <= remapped[index + 1] >> 24);
+        }
+    }
+
+    #[test]
+    fn test_palette_names() {
+        for palette in &[PlayerPalette::Original, PlayerPalette::ColorBlind, PlayerPalette::HighContrast] {
+            assert_eq!(Some(*palette), PlayerPalette::from_name(palette.name()));
+        }
+        assert_eq!(None, PlayerPalette::from_name("rainbow"));
+    }
+}
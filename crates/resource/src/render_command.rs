@@ -22,7 +22,7 @@
 use media::Renderer;
 use nalgebra::Vector2;
 use std::cmp::{Ordering, PartialOrd};
-use super::{ShapeKey, ShapeManager};
+use super::{ChunkCache, ChunkKey, ShapeKey, ShapeManager};
 use types::{Color, Rect};
 
 #[derive(Copy, Clone, Debug)]
@@ -30,11 +30,19 @@ pub enum RenderCommand {
     RenderShape(RenderOrder, RenderShapeParams),
     RenderRect(RenderOrder, RenderRectParams),
     RenderLine(RenderOrder, RenderLineParams),
+    /// Stretches a frame of a shape over a rect, such as an interface panel's background, or
+    /// fills the rect with a color if the shape or frame isn't there
+    RenderPanel(RenderOrder, RenderPanelParams),
+    /// Draws a chunk that was baked into the ChunkCache
+    RenderChunk(RenderOrder, ChunkKey),
+    /// Multiplies everything drawn under it by a color, over the whole screen
+    Tint(RenderOrder, Color),
 }
 
 impl RenderCommand {
     pub fn render_all(renderer: &mut Renderer,
                       shape_manager: &mut ShapeManager,
+                      chunk_cache: &mut ChunkCache,
                       commands: &mut Vec<RenderCommand>) {
         use RenderCommand::*;
         commands.sort_by(|a, b| a.order().cmp(b.order()));
@@ -50,12 +58,33 @@ impl RenderCommand {
                                       params.flip_vertical);
                 }
                 RenderRect(_, params) => {
-                    renderer.render_rect(params.rect);
+                    renderer.set_render_color(params.color);
+                    if params.filled {
+                        renderer.fill_rect(params.rect);
+                    } else {
+                        renderer.render_rect(params.rect);
+                    }
                 }
                 RenderLine(_, params) => {
                     renderer.set_render_color(params.color);
                     renderer.render_line(params.points[0], params.points[1]);
                 }
+                RenderPanel(_, params) => {
+                    let (frame, rect, border) = (params.frame_num as usize, params.rect, params.border);
+                    // Missing SLPs would load as the placeholder for missing graphics, stretched
+                    let rendered = shape_manager.has_slp(&params.shape_key) &&
+                                   shape_manager.get(&params.shape_key, renderer).map_or(false, |shape| {
+                                       shape.render_frame_sliced(renderer, frame, &rect, border)
+                                   });
+                    if !rendered {
+                        renderer.set_render_color(params.fallback);
+                        renderer.fill_rect(params.rect);
+                    }
+                }
+                RenderChunk(_, chunk_key) => {
+                    chunk_cache.render(chunk_key, renderer, shape_manager);
+                }
+                Tint(_, color) => renderer.modulate_screen(color),
             }
         }
     }
@@ -88,9 +117,48 @@ impl RenderCommand {
         RenderCommand::RenderLine(order, params)
     }
 
+    pub fn new_panel(layer: u16,
+                     depth: i32,
+                     shape_key: ShapeKey,
+                     frame_num: u16,
+                     rect: Rect,
+                     border: i32,
+                     fallback: Color)
+                     -> RenderCommand {
+        let order = RenderOrder::new(layer, depth, false);
+        let params = RenderPanelParams {
+            shape_key: shape_key,
+            frame_num: frame_num,
+            rect: rect,
+            border: border,
+            fallback: fallback,
+        };
+        RenderCommand::RenderPanel(order, params)
+    }
+
+    pub fn new_chunk(layer: u16, depth: i32, chunk_key: ChunkKey) -> RenderCommand {
+        RenderCommand::RenderChunk(RenderOrder::new(layer, depth, false), chunk_key)
+    }
+
+    pub fn new_tint(layer: u16, depth: i32, color: Color) -> RenderCommand {
+        RenderCommand::Tint(RenderOrder::new(layer, depth, false), color)
+    }
+
+    pub fn new_rect(layer: u16, depth: i32, color: Color, rect: Rect) -> RenderCommand {
+        let order = RenderOrder::new(layer, depth, false);
+        let params = RenderRectParams::new(color, rect, false);
+        RenderCommand::RenderRect(order, params)
+    }
+
+    pub fn new_filled_rect(layer: u16, depth: i32, color: Color, rect: Rect) -> RenderCommand {
+        let order = RenderOrder::new(layer, depth, false);
+        let params = RenderRectParams::new(color, rect, true);
+        RenderCommand::RenderRect(order, params)
+    }
+
     pub fn new_debug_rect(layer: u16, depth: i32, rect: Rect) -> RenderCommand {
         let order = RenderOrder::new(layer, depth, true);
-        let params = RenderRectParams::new(rect);
+        let params = RenderRectParams::new(Color::rgb(255, 255, 255), rect, false);
         RenderCommand::RenderRect(order, params)
     }
 
@@ -111,6 +179,9 @@ impl RenderCommand {
             RenderShape(ref order, _) => order,
             RenderRect(ref order, _) => order,
             RenderLine(ref order, _) => order,
+            RenderPanel(ref order, _) => order,
+            RenderChunk(ref order, _) => order,
+            Tint(ref order, _) => order,
         }
     }
 }
@@ -173,14 +244,30 @@ impl RenderShapeParams {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+pub struct RenderPanelParams {
+    pub shape_key: ShapeKey,
+    pub frame_num: u16,
+    pub rect: Rect,
+    /// How far in from the edges the frame's corners reach, in unscaled pixels
+    pub border: i32,
+    pub fallback: Color,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct RenderRectParams {
+    pub color: Color,
     pub rect: Rect,
+    pub filled: bool,
 }
 
 impl RenderRectParams {
-    pub fn new(rect: Rect) -> RenderRectParams {
-        RenderRectParams { rect: rect }
+    pub fn new(color: Color, rect: Rect, filled: bool) -> RenderRectParams {
+        RenderRectParams {
+            color: color,
+            rect: rect,
+            filled: filled,
+        }
     }
 }
 
@@ -24,27 +24,48 @@ use drs::DrsFileType;
 use drs_manager::{DrsKey, DrsManagerRef};
 use error::*;
 use identifier::{PlayerColorId, SlpFileId};
-use media::{Renderer, Texture, TextureBuilder};
+use lru_cache::LruCache;
+use media::{HardwareCursor, Renderer, Texture, TextureBuilder};
 
 use nalgebra::Vector2;
-use palette::{self, PaletteColor};
+use player_palette::{PlayerPalette, remap_player_colors};
+use png;
+use rustc_serialize::json::Json;
+use palette_manager::{COLOR_CYCLE_STEP_NANOS, PaletteManager, cycle_palette, fog_palette, is_cycled_color};
 use slp::SlpFile;
+use slp_decoder::{self, DecodedSlps};
+use slp_v4::{SlpV4Frame, decode_slp_v4, is_slp_v4};
 
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::io;
+use std::collections::HashSet;
+use std::fs::File;
+use std::i32;
+use std::io::{self, Read};
+use std::path::Path;
 use std::rc::Rc;
 use types::Rect;
 
 const SHAPE_PADDING: i32 = 4;
-const PALETTE_FILE_ID: u32 = 50500;
 const CENTER_CUTOFF: i32 = 100000;
 
+/// Default amount of texture memory (in bytes) to keep decoded shapes in
+pub const DEFAULT_TEXTURE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Most memory (in bytes) the SLPs decoded at startup can take up while they wait to be drawn
+pub const PRELOAD_BUDGET: usize = 128 * 1024 * 1024;
+
+/// Archives whose SLPs get decoded at startup, in order; terrain comes first since every
+/// scenario draws it straight away. Graphics are decoded in the gaia color, which trees,
+/// mines, and wildlife all use.
+const PRELOADED_ARCHIVES: [DrsKey; 4] = [DrsKey::Terrain, DrsKey::Border, DrsKey::Interfac, DrsKey::Graphics];
+
 #[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
 pub struct ShapeKey {
     pub drs_key: DrsKey,
     pub slp_id: SlpFileId,
     pub player_color: PlayerColorId,
+    /// Drawn in the faded colors of what's explored but out of sight
+    pub fogged: bool,
 }
 
 impl ShapeKey {
@@ -53,7 +74,65 @@ impl ShapeKey {
             drs_key: drs_key,
             slp_id: slp_id,
             player_color: player_color,
+            fogged: false,
+        }
+    }
+
+    /// The same shape in the faded colors of what's explored but out of sight
+    pub fn under_fog(mut self) -> ShapeKey {
+        self.fogged = true;
+        self
+    }
+}
+
+/// The palette indices of the part of a shape's texture that's drawn in cycled colors, kept so
+/// that part can be redrawn as the colors move without decoding the SLP again
+struct CycledPixels {
+    rect: Rect,
+    pixels: Vec<u8>,
+    /// The color cycle step the texture was last drawn at
+    step: u32,
+}
+
+impl CycledPixels {
+    /// Copies out the smallest area of the frames, drawn at their rects, that covers every pixel
+    /// in cycled colors, or None if there aren't any
+    fn find(frames: &[(&[u8], Rect)]) -> Option<CycledPixels> {
+        let position = |index: usize, dst_rect: &Rect| {
+            (dst_rect.x + index as i32 % dst_rect.w, dst_rect.y + index as i32 / dst_rect.w)
+        };
+
+        let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+        for &(pixels, dst_rect) in frames {
+            for (index, _) in pixels.iter().enumerate().filter(|&(_, &color)| is_cycled_color(color)) {
+                let (x, y) = position(index, &dst_rect);
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x);
+                bottom = bottom.max(y);
+            }
+        }
+        if left > right {
+            return None;
+        }
+
+        let rect = Rect::of(left, top, right - left + 1, bottom - top + 1);
+        let mut cycled_pixels = vec![0u8; (rect.w * rect.h) as usize];
+        for &(pixels, dst_rect) in frames {
+            for (index, &color) in pixels.iter().enumerate() {
+                let (x, y) = position(index, &dst_rect);
+                let (x, y) = (x - rect.x, y - rect.y);
+                if x >= 0 && y >= 0 && x < rect.w && y < rect.h {
+                    cycled_pixels[(y * rect.w + x) as usize] = color;
+                }
+            }
         }
+
+        Some(CycledPixels {
+            rect: rect,
+            pixels: cycled_pixels,
+            step: 0,
+        })
     }
 }
 
@@ -61,6 +140,9 @@ pub struct Shape {
     texture: Texture,
     frames: Vec<Rect>,
     centers: Vec<Vector2<i32>>,
+    memory_size: usize,
+    cycled_pixels: Option<CycledPixels>,
+    scale: i32,
 }
 
 impl Shape {
@@ -92,13 +174,162 @@ impl Shape {
                                                          dst_rects[index]);
         }
 
+        let frames: Vec<(&[u8], Rect)> =
+            slp.shapes.iter().map(|shape| &shape.pixels[..]).zip(dst_rects.iter().cloned()).collect();
+        let cycled_pixels = CycledPixels::find(&frames);
+
+        Ok(Shape {
+            texture: try!(texture_builder.build(renderer)),
+            frames: dst_rects,
+            centers: centers,
+            memory_size: (total_rect.w * total_rect.h * 4) as usize +
+                         cycled_pixels.as_ref().map_or(0, |cycled| cycled.pixels.len()),
+            cycled_pixels: cycled_pixels,
+            scale: 1,
+        })
+    }
+
+    /// Builds a shape from frames that are already in color, the way the newer editions'
+    /// SLPs decode
+    fn load_from_rgba(frames: &[SlpV4Frame], renderer: &mut Renderer) -> Result<Shape> {
+        let mut total_rect = Rect::new();
+        let mut dst_rects = Vec::new();
+        let mut centers = Vec::new();
+        let mut next_x = 0i32;
+        for frame in frames {
+            let dst_rect = Rect::of(next_x, 0, frame.width as i32, frame.height as i32);
+            dst_rects.push(dst_rect);
+            centers.push(Vector2::new(frame.center_x, frame.center_y));
+
+            total_rect.extend(&dst_rect);
+            next_x += frame.width as i32 + SHAPE_PADDING;
+        }
+
+        let mut texture_builder = try!(TextureBuilder::new(total_rect.w as u32, total_rect.h as u32, &[]));
+        for (index, frame) in frames.iter().enumerate() {
+            let mut rgba = frame.rgba.clone();
+            let src_rect = Rect::of(0, 0, frame.width as i32, frame.height as i32);
+            texture_builder = texture_builder.blit_rgba(&mut rgba, src_rect, dst_rects[index]);
+        }
+
         Ok(Shape {
             texture: try!(texture_builder.build(renderer)),
             frames: dst_rects,
             centers: centers,
+            memory_size: (total_rect.w * total_rect.h * 4) as usize,
+            cycled_pixels: None,
+            scale: 1,
+        })
+    }
+
+    /// Loads a mod's replacement for an SLP from a PNG. The frames are described by a JSON file
+    /// with the same resource ID, found the same way as the PNG, like:
+    ///
+    /// `{ "scale": 2, "frames": [{ "x": 0, "y": 0, "w": 96, "h": 64, "center_x": 48, "center_y": 32 }] }`
+    ///
+    /// where all coordinates are in image pixels, and the image is drawn `scale` times smaller
+    /// so that high resolution graphics line up with the originals. Without the JSON file,
+    /// the whole image is a single frame centered on its middle.
+    fn load_override(image_path: &Path,
+                     metadata_path: Option<&Path>,
+                     renderer: &mut Renderer)
+                     -> Result<Shape> {
+        let (width, height, mut pixels) = try!(read_rgba_png(image_path));
+
+        let (scale, frames, centers) = match metadata_path {
+            Some(metadata_path) => try!(read_override_metadata(metadata_path)),
+            None => {
+                (1,
+                 vec![Rect::of(0, 0, width as i32, height as i32)],
+                 vec![Vector2::new(width as i32 / 2, height as i32 / 2)])
+            }
+        };
+
+        let image_rect = Rect::of(0, 0, width as i32, height as i32);
+        let texture_builder = try!(TextureBuilder::new(width, height, &[]))
+            .blit_rgba(&mut pixels, image_rect, image_rect);
+
+        Ok(Shape {
+            texture: try!(texture_builder.build(renderer)),
+            frames: frames,
+            centers: centers,
+            memory_size: (width * height * 4) as usize,
+            cycled_pixels: None,
+            scale: scale,
         })
     }
 
+    /// Approximate size of the shape's texture in bytes
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// True if the shape is drawn with colors that animate, and changes as they do
+    pub fn cycles_colors(&self) -> bool {
+        self.cycled_pixels.is_some()
+    }
+
+    /// True if the shape's cycled colors were last drawn at another color cycle step
+    fn is_cycled_behind(&self, step: u32) -> bool {
+        self.cycled_pixels.as_ref().map_or(false, |cycled| cycled.step != step)
+    }
+
+    /// Redraws the part of the texture in cycled colors with the palette of the given step
+    fn redraw_cycled_colors(&mut self, palette: &[u32], step: u32) -> Result<()> {
+        let cycled = match self.cycled_pixels {
+            Some(ref mut cycled) => cycled,
+            None => return Ok(()),
+        };
+        let src_rect = Rect::of(0, 0, cycled.rect.w, cycled.rect.h);
+        try!(try!(TextureBuilder::new(cycled.rect.w as u32, cycled.rect.h as u32, palette))
+            .blit_shape(&cycled.pixels, src_rect, src_rect)
+            .update(&mut self.texture, Vector2::new(cycled.rect.x, cycled.rect.y)));
+        cycled.step = step;
+        Ok(())
+    }
+
+    /// Stretches a frame over the rect in nine pieces, the way interface panels and buttons are
+    /// drawn at any size: the corners, `border` pixels across, are drawn as they are, the edges
+    /// are stretched along their length, and the middle over the rest. Returns false without
+    /// drawing anything if the shape has no such frame.
+    pub fn render_frame_sliced(&self,
+                               renderer: &mut Renderer,
+                               frame: usize,
+                               rect: &Rect,
+                               border: i32)
+                               -> bool {
+        if frame >= self.frames.len() {
+            return false;
+        }
+
+        let src_rect = self.frames[frame];
+        let most = (src_rect.w / self.scale).min(src_rect.h / self.scale).min(rect.w).min(rect.h) / 2;
+        let border = border.min(most).max(0);
+        let src_border = border * self.scale;
+        let slices = |src_length: i32, dst_length: i32| {
+            [(0, src_border, 0, border),
+             (src_border, src_length - 2 * src_border, border, dst_length - 2 * border),
+             (src_length - src_border, src_border, dst_length - border, border)]
+        };
+        for &(src_x, src_w, dst_x, dst_w) in &slices(src_rect.w, rect.w) {
+            for &(src_y, src_h, dst_y, dst_h) in &slices(src_rect.h, rect.h) {
+                if src_w <= 0 || src_h <= 0 || dst_w <= 0 || dst_h <= 0 {
+                    continue;
+                }
+                renderer.render_texture(&self.texture,
+                                        Some(Rect::of(src_rect.x + src_x, src_rect.y + src_y, src_w, src_h)),
+                                        Rect::of(rect.x + dst_x, rect.y + dst_y, dst_w, dst_h),
+                                        false,
+                                        false);
+            }
+        }
+        true
+    }
+
     pub fn render_frame(&self,
                         renderer: &mut Renderer,
                         frame: usize,
@@ -113,16 +344,16 @@ impl Shape {
             return;
         }
 
-        let mut dst_rect = Rect::of(0, 0, src_rect.w, src_rect.h);
+        let mut dst_rect = Rect::of(0, 0, src_rect.w / self.scale, src_rect.h / self.scale);
         dst_rect.translate(position.x, position.y);
 
         // Need to mirror the center offset if flipping
-        let (mut offset_x, mut offset_y) = (center.x, center.y);
+        let (mut offset_x, mut offset_y) = (center.x / self.scale, center.y / self.scale);
         if flip_horizontal {
-            offset_x = src_rect.w - offset_x;
+            offset_x = dst_rect.w - offset_x;
         }
         if flip_vertical {
-            offset_y = src_rect.h - offset_y;
+            offset_y = dst_rect.h - offset_y;
         }
         dst_rect.translate(-offset_x, -offset_y);
 
@@ -134,81 +365,312 @@ impl Shape {
     }
 }
 
-enum ShapeCache {
-    Cached(Shape),
-    Failed,
-}
-
+/// Decodes SLPs into textures the first time they're drawn, and keeps the most recently
+/// drawn ones around within a texture memory budget. Shapes that use the animated water
+/// and lava colors have that part of their texture redrawn when they're next drawn after
+/// the color cycle advances.
 pub struct ShapeManager {
     drs_manager: DrsManagerRef,
-    shapes: HashMap<ShapeKey, ShapeCache>,
+    shapes: LruCache<ShapeKey, Shape>,
+    preloaded: DecodedSlps,
+    failed_shapes: HashSet<ShapeKey>,
+    palettes: PaletteManager,
     palette: Vec<u32>,
+    player_palette: PlayerPalette,
+    color_cycle_step: u32,
+    color_cycle_nanos: u64,
 }
 
 pub type ShapeManagerRef = Rc<RefCell<ShapeManager>>;
 
 impl ShapeManager {
-    pub fn new(drs_manager: DrsManagerRef) -> Result<ShapeManagerRef> {
-        let palette = {
-                let borrowed_drs = drs_manager.borrow();
-                let interfac = borrowed_drs.get(DrsKey::Interfac);
-                let bin_table = try!(interfac.find_table(DrsFileType::Binary)
-                    .ok_or(ErrorKind::InterfacBinaryTableMissing));
-                let palette_contents = &try!(bin_table.find_file_contents(PALETTE_FILE_ID)
-                    .ok_or(ErrorKind::InterfacMissingPalette));
-                try!(palette::read_from(&mut io::Cursor::new(palette_contents)))
-            }
-            .iter()
-            .map(|c: &PaletteColor| -> u32 { (*c).into() })
-            .collect();
+    pub fn new(drs_manager: DrsManagerRef, texture_budget: usize) -> Result<ShapeManagerRef> {
+        try!(drs_manager.borrow_mut().load(DrsKey::Interfac));
+        let mut palettes = try!(PaletteManager::load_from(drs_manager.borrow().get(DrsKey::Interfac)));
+        palettes.apply_edition_palette(drs_manager.borrow().edition_assets());
+        let palette = palettes.main().to_vec();
 
         Ok(Rc::new(RefCell::new(ShapeManager {
             drs_manager: drs_manager,
-            shapes: HashMap::new(),
+            shapes: LruCache::new(texture_budget),
+            preloaded: DecodedSlps::new(),
+            failed_shapes: HashSet::new(),
+            palettes: palettes,
             palette: palette,
+            player_palette: PlayerPalette::Original,
+            color_cycle_step: 0,
+            color_cycle_nanos: 0,
         })))
     }
 
     pub fn get<'a>(&'a mut self, shape_key: &ShapeKey, renderer: &mut Renderer) -> Option<&'a Shape> {
-        use self::ShapeCache::*;
+        if self.failed_shapes.contains(shape_key) {
+            return None;
+        }
 
-        let cached = self.shapes.get(&shape_key).is_some();
-        if !cached {
+        if !self.shapes.contains_key(shape_key) {
             match self.load_shape(shape_key, renderer) {
-                Ok(shape) => {
-                    self.shapes.insert(*shape_key, Cached(shape));
+                Ok(mut shape) => {
+                    if let Some(ref mut cycled) = shape.cycled_pixels {
+                        cycled.step = self.color_cycle_step;
+                    }
+                    let memory_size = shape.memory_size();
+                    self.shapes.insert(*shape_key, shape, memory_size);
                 }
                 Err(err) => {
-                    self.shapes.insert(*shape_key, Failed);
-                    println!("Failed to load shape {:?}: {}", shape_key, err);
+                    self.failed_shapes.insert(*shape_key);
+                    warn!("Failed to load shape {:?}: {}", shape_key, err);
                     return None;
                 }
             };
         }
 
-        match *self.shapes.get(&shape_key).unwrap() {
-            Cached(ref shape) => return Some(shape),
-            Failed => return None,
+        self.redraw_cycled_colors(shape_key);
+        self.shapes.get(shape_key)
+    }
+
+    /// Brings a cached shape's cycled colors up to the current color cycle step
+    fn redraw_cycled_colors(&mut self, shape_key: &ShapeKey) {
+        let step = self.color_cycle_step;
+        if !self.shapes.peek_mut(shape_key).map_or(false, |shape| shape.is_cycled_behind(step)) {
+            return;
+        }
+
+        let palette = self.shape_palette(shape_key);
+        if let Some(shape) = self.shapes.peek_mut(shape_key) {
+            if let Err(err) = shape.redraw_cycled_colors(&palette, step) {
+                warn!("Failed to redraw the cycled colors of shape {:?}: {}", shape_key, err);
+            }
+        }
+    }
+
+    /// Decodes the SLPs of the preloaded archives across `threads` threads, so that drawing
+    /// them later only has to build their textures. Calls `on_progress` with the number of
+    /// SLPs done so far and the total.
+    pub fn preload<F: FnMut(usize, usize)>(&mut self, threads: usize, on_progress: F) {
+        let drs_manager = self.drs_manager.borrow();
+        let mut shape_keys = Vec::new();
+        for drs_key in &PRELOADED_ARCHIVES {
+            if !drs_manager.is_loaded(*drs_key) {
+                continue;
+            }
+            if let Some(table) = drs_manager.get(*drs_key).find_table(DrsFileType::Slp) {
+                for entry in table.entries() {
+                    let slp_id: SlpFileId = (entry.file_id as usize).into();
+                    if drs_manager.mod_overrides().find(*drs_key, *slp_id, "png").is_none() {
+                        shape_keys.push(ShapeKey::new(*drs_key, slp_id, 0.into()));
+                    }
+                }
+            }
+        }
+        self.preloaded =
+            slp_decoder::decode_in_parallel(&drs_manager, &shape_keys, threads, PRELOAD_BUDGET, on_progress);
+    }
+
+    /// Forgets everything decoded for an SLP, in every player color, so that it's loaded again
+    /// (from a mod's replacement if there is one now) the next time it's drawn
+    pub fn reload_slp(&mut self, drs_key: DrsKey, slp_id: SlpFileId) {
+        let matches = |shape_key: &ShapeKey| shape_key.drs_key == drs_key && shape_key.slp_id == slp_id;
+        let removed = self.shapes.remove_where(&matches);
+        self.failed_shapes.retain(|shape_key| !matches(shape_key));
+        debug!("Reloading {:?} SLP {:?}; dropped {} cached shapes", drs_key, slp_id, removed);
+    }
+
+    /// Switches the colors players are drawn in. Shapes in player colors are dropped so they
+    /// get rebuilt in the new colors the next time they're drawn.
+    pub fn set_player_palette(&mut self, player_palette: PlayerPalette) {
+        if player_palette == self.player_palette {
+            return;
+        }
+        self.player_palette = player_palette;
+
+        let in_player_color = |shape_key: &ShapeKey| *shape_key.player_color > 0;
+        let removed = self.shapes.remove_where(&in_player_color);
+        self.failed_shapes.retain(|shape_key| !in_player_color(shape_key));
+        debug!("Using the {} player colors; dropped {} cached shapes", player_palette.name(), removed);
+    }
+
+    pub fn set_texture_budget(&mut self, texture_budget: usize) {
+        self.shapes.set_budget(texture_budget);
+    }
+
+    /// Texture memory (in bytes) currently used by decoded shapes
+    pub fn texture_memory_used(&self) -> usize {
+        self.shapes.used()
+    }
+
+    /// How far the color cycling animation has got, which changes whenever the cycled shapes do
+    pub fn color_cycle_step(&self) -> u32 {
+        self.color_cycle_step
+    }
+
+    /// Advances the water/lava color cycling animation by the real time since the last frame.
+    /// Shapes that use the cycled colors catch up the next time they're drawn.
+    pub fn update_color_cycle(&mut self, elapsed_nanos: u64) {
+        self.color_cycle_nanos += elapsed_nanos;
+        if self.color_cycle_nanos < COLOR_CYCLE_STEP_NANOS {
+            return;
+        }
+
+        let steps = self.color_cycle_nanos / COLOR_CYCLE_STEP_NANOS;
+        self.color_cycle_nanos %= COLOR_CYCLE_STEP_NANOS;
+        self.color_cycle_step = self.color_cycle_step.wrapping_add(steps as u32);
+        self.palette = cycle_palette(self.palettes.main(), self.color_cycle_step);
+    }
+
+    /// The current palette in the shape's player color, faded if it's under fog
+    fn shape_palette(&self, shape_key: &ShapeKey) -> Vec<u32> {
+        let palette = if self.player_palette != PlayerPalette::Original && *shape_key.player_color > 0 {
+            remap_player_colors(&self.palette, self.player_palette, *shape_key.player_color)
+        } else {
+            self.palette.clone()
+        };
+
+        if shape_key.fogged {
+            fog_palette(&palette)
+        } else {
+            palette
+        }
+    }
+
+    fn load_shape(&mut self, shape_key: &ShapeKey, renderer: &mut Renderer) -> Result<Shape> {
+        // Mods replace whole SLPs, so the replacement is used for every player color
+        let override_path = self.drs_manager
+            .borrow()
+            .mod_overrides()
+            .find(shape_key.drs_key, *shape_key.slp_id, "png");
+        if let Some(override_path) = override_path {
+            let metadata_path = self.drs_manager
+                .borrow()
+                .find_override(shape_key.drs_key, *shape_key.slp_id, "json");
+            return Shape::load_override(&override_path,
+                                        metadata_path.as_ref().map(|path| path.as_path()),
+                                        renderer);
+        }
+
+        let palette = self.shape_palette(shape_key);
+
+        let edition_path = self.drs_manager
+            .borrow()
+            .edition_assets()
+            .find(shape_key.drs_key, *shape_key.slp_id, "slp");
+        if let Some(edition_path) = edition_path {
+            let mut contents = Vec::new();
+            try!(try!(File::open(edition_path)).read_to_end(&mut contents));
+            if is_slp_v4(&contents) {
+                let frames = try!(decode_slp_v4(&contents, &palette, *shape_key.player_color));
+                return Shape::load_from_rgba(&frames, renderer);
+            }
+            let slp = try!(SlpFile::read_from(&mut io::Cursor::new(&contents[..]), *shape_key.player_color));
+            return Shape::load_from(&slp, &palette, renderer);
+        }
+
+        // Built straight from the SLP decoded at startup, which isn't needed after that
+        if let Some(slp) = self.preloaded.remove(shape_key) {
+            return Shape::load_from(&slp, &palette, renderer);
         }
+
+        let slp = match try!(self.read_drs_slp(shape_key)) {
+            Some(slp) => slp,
+            // Load the "missing" SLP file if we can't find the requested SLP in the DRS archive
+            None => try!(SlpFile::read_from_file("data/nope-64x64.slp", *shape_key.player_color)),
+        };
+
+        Shape::load_from(&slp, &palette, renderer)
+    }
+
+    /// Makes a hardware cursor from a frame of an SLP in the original archives, `scale` times
+    /// as big, with the frame's center as the point that clicks
+    pub fn load_cursor(&self, shape_key: &ShapeKey, frame: usize, scale: u32) -> Result<HardwareCursor> {
+        let slp = try!(try!(self.read_drs_slp(shape_key))
+            .ok_or(ErrorKind::SlpNotFound(shape_key.drs_key, *shape_key.slp_id)));
+        let shape = try!(slp.shapes
+            .get(frame)
+            .ok_or(ErrorKind::FrameNotFound(shape_key.drs_key, *shape_key.slp_id, frame)));
+        let rect = Rect::of(0, 0, shape.header.width as i32, shape.header.height as i32);
+        let hot_spot = Vector2::new(shape.header.center_x, shape.header.center_y);
+        let texture_builder = try!(TextureBuilder::new(rect.w as u32, rect.h as u32, &self.palette));
+        Ok(try!(texture_builder.blit_shape(&shape.pixels, rect, rect).build_cursor(hot_spot, scale)))
+    }
+
+    /// Whether there's an SLP for the shape, from a mod, the edition, or its archive, rather than
+    /// it being drawn as the placeholder for missing graphics
+    pub fn has_slp(&self, shape_key: &ShapeKey) -> bool {
+        let drs_manager = self.drs_manager.borrow();
+        drs_manager.mod_overrides().find(shape_key.drs_key, *shape_key.slp_id, "png").is_some() ||
+        drs_manager.edition_assets().find(shape_key.drs_key, *shape_key.slp_id, "slp").is_some() ||
+        self.preloaded.contains_key(shape_key) ||
+        drs_manager.is_loaded(shape_key.drs_key) &&
+        drs_manager.get(shape_key.drs_key)
+            .find_table(DrsFileType::Slp)
+            .map_or(false, |table| table.find_file_contents(*shape_key.slp_id).is_some())
     }
 
-    fn load_shape(&self, shape_key: &ShapeKey, renderer: &mut Renderer) -> Result<Shape> {
+    /// Reads an SLP from its archive, or None if the archive doesn't have it
+    fn read_drs_slp(&self, shape_key: &ShapeKey) -> Result<Option<SlpFile>> {
+        try!(self.drs_manager.borrow_mut().load(shape_key.drs_key));
         let borrowed_drs = self.drs_manager.borrow();
         let drs_file = borrowed_drs.get(shape_key.drs_key);
 
         let slp_table = try!(drs_file.find_table(DrsFileType::Slp)
             .ok_or(ErrorKind::NoSlpTableInDrs(shape_key.drs_key)));
-        
-        let slp = match slp_table.find_file_contents(*shape_key.slp_id) {
+        match slp_table.find_file_contents(*shape_key.slp_id) {
             Some(slp_contents) => {
-                try!(SlpFile::read_from(&mut io::Cursor::new(slp_contents), *shape_key.player_color))
-            },
-            None => {
-                // Load the "missing" SLP file if we can't find the requested SLP in the DRS archive
-                try!(SlpFile::read_from_file("data/nope-64x64.slp", *shape_key.player_color))
+                let mut reader = io::Cursor::new(slp_contents);
+                Ok(Some(try!(SlpFile::read_from(&mut reader, *shape_key.player_color))))
             }
-        };
+            None => Ok(None),
+        }
+    }
+}
+
+fn read_rgba_png(file_name: &Path) -> Result<(u32, u32, Vec<u8>)> {
+    let decoder = png::Decoder::new(try!(File::open(file_name)));
+    let (info, mut reader) = try!(decoder.read_info());
+    if info.color_type != png::ColorType::RGBA || info.bit_depth != png::BitDepth::Eight {
+        return Err(ErrorKind::UnsupportedOverrideImage(file_name.to_path_buf()).into());
+    }
+
+    let mut pixels = vec![0u8; info.buffer_size()];
+    try!(reader.next_frame(&mut pixels));
+    Ok((info.width, info.height, pixels))
+}
+
+fn read_override_metadata(file_name: &Path) -> Result<(i32, Vec<Rect>, Vec<Vector2<i32>>)> {
+    let bad_metadata = |message: &str| -> Error {
+        ErrorKind::BadOverrideMetadata(file_name.to_path_buf(), message.into()).into()
+    };
+
+    let mut contents = String::new();
+    try!(try!(File::open(file_name)).read_to_string(&mut contents));
+    let metadata = try!(Json::from_str(&contents));
 
-        Shape::load_from(&slp, &self.palette, renderer)
+    let scale = match metadata.find("scale") {
+        Some(scale) => try!(scale.as_i64().ok_or(bad_metadata("scale must be an integer"))) as i32,
+        None => 1,
+    };
+    if scale < 1 {
+        return Err(bad_metadata("scale must be at least 1"));
     }
+
+    let frame_list = try!(metadata.find("frames")
+        .and_then(|frames| frames.as_array())
+        .ok_or(bad_metadata("missing frames list")));
+
+    let (mut frames, mut centers) = (Vec::new(), Vec::new());
+    for frame in frame_list {
+        let field = |name: &str| -> Result<i32> {
+            frame.find(name)
+                .and_then(|value| value.as_i64())
+                .map(|value| value as i32)
+                .ok_or(bad_metadata(&format!("frame is missing \"{}\"", name)))
+        };
+        frames.push(Rect::of(try!(field("x")), try!(field("y")), try!(field("w")), try!(field("h"))));
+        centers.push(Vector2::new(try!(field("center_x")), try!(field("center_y"))));
+    }
+    if frames.is_empty() {
+        return Err(bad_metadata("there must be at least one frame"));
+    }
+
+    Ok((scale, frames, centers))
 }
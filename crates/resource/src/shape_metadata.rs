@@ -69,7 +69,7 @@ impl ShapeMetadataStore {
                 metadata: &mut HashMap<ShapeMetadataKey, ShapeMetadata>) {
         let drs = drs_manager.get(drs_key);
         if let Some(table) = drs.find_table(DrsFileType::Slp) {
-            let keys: Vec<ShapeMetadataKey> = table.entries
+            let keys: Vec<ShapeMetadataKey> = table.entries()
                 .iter()
                 .map(|entry| ShapeMetadataKey::new(drs_key, (entry.file_id as usize).into()))
                 .collect();
@@ -0,0 +1,115 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs::DrsFileType;
+use drs_manager::DrsManager;
+use shape_manager::ShapeKey;
+use slp::SlpFile;
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+/// Decoded SLPs waiting to be turned into textures, keyed by the shape they'll become
+pub type DecodedSlps = HashMap<ShapeKey, SlpFile>;
+
+/// Decodes the given shapes' SLPs on `threads` worker threads, which take them off a shared
+/// queue one at a time so that a few large SLPs don't hold up the rest. Stops keeping the
+/// results once their pixels add up to `budget` bytes. `on_progress` is called on the calling
+/// thread with the number of SLPs done so far and the total, so it can keep a window responsive.
+pub fn decode_in_parallel<F>(drs_manager: &DrsManager,
+                             shape_keys: &[ShapeKey],
+                             threads: usize,
+                             budget: usize,
+                             mut on_progress: F)
+                             -> DecodedSlps
+    where F: FnMut(usize, usize)
+{
+    let mut jobs = VecDeque::new();
+    for shape_key in shape_keys {
+        if !drs_manager.is_loaded(shape_key.drs_key) {
+            continue;
+        }
+        let table = match drs_manager.get(shape_key.drs_key).find_table(DrsFileType::Slp) {
+            Some(table) => table,
+            None => continue,
+        };
+        if let Some(contents) = table.find_file_contents(*shape_key.slp_id) {
+            jobs.push_back((*shape_key, contents.to_vec()));
+        }
+    }
+
+    let total = jobs.len();
+    let queue = Arc::new(Mutex::new(jobs));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|_| {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                loop {
+                    let job = queue.lock().unwrap().pop_front();
+                    let (shape_key, contents) = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let slp = SlpFile::read_from(&mut io::Cursor::new(contents), *shape_key.player_color);
+                    if sender.send((shape_key, slp)).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut decoded = HashMap::new();
+    let mut decoded_bytes = 0;
+    let mut done = 0;
+    on_progress(done, total);
+    for (shape_key, slp) in receiver {
+        done += 1;
+        match slp {
+            Ok(slp) => {
+                let size: usize = slp.shapes.iter().map(|shape| shape.pixels.len()).sum();
+                if decoded_bytes + size <= budget {
+                    decoded_bytes += size;
+                    decoded.insert(shape_key, slp);
+                }
+            }
+            Err(err) => warn!("Failed to decode shape {:?}: {}", shape_key, err),
+        }
+        on_progress(done, total);
+    }
+
+    for worker in workers {
+        if worker.join().is_err() {
+            warn!("An SLP decoding thread panicked");
+        }
+    }
+    info!("Decoded {} of {} SLPs ({} KiB) on {} threads",
+          decoded.len(),
+          total,
+          decoded_bytes / 1024,
+          threads.max(1));
+    decoded
+}
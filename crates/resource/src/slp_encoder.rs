@@ -0,0 +1,412 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use error::*;
+use palette::PaletteColor;
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+const VERSION: &'static [u8; 4] = b"2.0N";
+const COMMENT: &'static [u8] = b"ArtDesk 1.00 SLP Writer";
+const COMMENT_SIZE: usize = 24;
+const HEADER_SIZE: u32 = 32;
+const FRAME_INFO_SIZE: u32 = 32;
+
+/// Outline value that marks a row as completely transparent
+const TRANSPARENT_ROW: u16 = 0x8000;
+
+/// Player colors occupy this range of the palette; the game shifts them per player when drawing
+const PLAYER_COLOR_START: u8 = 16;
+const PLAYER_COLOR_COUNT: u8 = 8;
+
+/// Pixels with an alpha below this are transparent, and at or above it but not
+/// fully opaque are drawn as shadow
+const TRANSPARENT_ALPHA: u8 = 32;
+
+/// Runs of the same color at least this long are written as fills instead of color lists
+const MIN_FILL_LENGTH: usize = 3;
+
+const MAX_SMALL_LENGTH: usize = 63;
+const MAX_BIG_LENGTH: usize = 4095;
+const MAX_NIBBLE_LENGTH: usize = 15;
+const MAX_EXTENDED_LENGTH: usize = 255;
+
+const COMMAND_COLOR_LIST: u8 = 0x00;
+const COMMAND_SKIP: u8 = 0x01;
+const COMMAND_BIG_COLOR_LIST: u8 = 0x02;
+const COMMAND_BIG_SKIP: u8 = 0x03;
+const COMMAND_PLAYER_COLOR_LIST: u8 = 0x06;
+const COMMAND_FILL: u8 = 0x07;
+const COMMAND_PLAYER_COLOR_FILL: u8 = 0x0A;
+const COMMAND_SHADOW: u8 = 0x0B;
+const COMMAND_END_OF_ROW: u8 = 0x0F;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SlpPixel {
+    Transparent,
+    Color(u8),
+
+    /// Offset into the player color range of the palette
+    PlayerColor(u8),
+    Shadow,
+}
+
+/// A single frame to be encoded, already quantized to the palette
+#[derive(Clone, Debug)]
+pub struct SlpFrame {
+    pub width: u32,
+    pub height: u32,
+    pub center_x: i32,
+    pub center_y: i32,
+    pub pixels: Vec<SlpPixel>,
+}
+
+impl SlpFrame {
+    /// Creates a frame from palette indices; index 0 is treated as transparent,
+    /// and indices in the player color range become player color
+    pub fn from_indexed(width: u32, height: u32, center_x: i32, center_y: i32, indices: &[u8]) -> SlpFrame {
+        let pixels = indices.iter().map(|index| indexed_pixel(*index)).collect();
+        SlpFrame {
+            width: width,
+            height: height,
+            center_x: center_x,
+            center_y: center_y,
+            pixels: pixels,
+        }
+    }
+
+    /// Creates a frame from RGBA pixels (4 bytes per pixel), matching each color to the
+    /// closest palette entry. Mostly transparent pixels are skipped, and partially
+    /// transparent pixels become shadow.
+    pub fn from_rgba(width: u32,
+                     height: u32,
+                     center_x: i32,
+                     center_y: i32,
+                     rgba: &[u8],
+                     palette: &[PaletteColor])
+                     -> SlpFrame {
+        let pixels = rgba.chunks(4)
+            .map(|pixel| {
+                if pixel[3] < TRANSPARENT_ALPHA {
+                    SlpPixel::Transparent
+                } else if pixel[3] < 255 {
+                    SlpPixel::Shadow
+                } else {
+                    indexed_pixel(closest_color(palette, pixel[0], pixel[1], pixel[2]))
+                }
+            })
+            .collect();
+        SlpFrame {
+            width: width,
+            height: height,
+            center_x: center_x,
+            center_y: center_y,
+            pixels: pixels,
+        }
+    }
+
+    fn row<'a>(&'a self, y: u32) -> &'a [SlpPixel] {
+        let start = (y * self.width) as usize;
+        &self.pixels[start..start + self.width as usize]
+    }
+}
+
+fn indexed_pixel(index: u8) -> SlpPixel {
+    if index == 0 {
+        SlpPixel::Transparent
+    } else if index >= PLAYER_COLOR_START && index < PLAYER_COLOR_START + PLAYER_COLOR_COUNT {
+        SlpPixel::PlayerColor(index - PLAYER_COLOR_START)
+    } else {
+        SlpPixel::Color(index)
+    }
+}
+
+/// Finds the palette index nearest to the given color, never picking the transparent index 0
+pub fn closest_color(palette: &[PaletteColor], r: u8, g: u8, b: u8) -> u8 {
+    let mut best_index = 0;
+    let mut best_distance = u32::max_value();
+    for (index, color) in palette.iter().enumerate().skip(1).take(255) {
+        let dr = color.r as i32 - r as i32;
+        let dg = color.g as i32 - g as i32;
+        let db = color.b as i32 - b as i32;
+        let distance = (dr * dr + dg * dg + db * db) as u32;
+        if distance < best_distance {
+            best_distance = distance;
+            best_index = index;
+        }
+    }
+    best_index as u8
+}
+
+/// Encodes frames into the SLP format (version 2.0N) used by Age of Empires
+pub struct SlpEncoder {
+    frames: Vec<SlpFrame>,
+}
+
+impl SlpEncoder {
+    pub fn new() -> SlpEncoder {
+        SlpEncoder { frames: Vec::new() }
+    }
+
+    pub fn add_frame(&mut self, frame: SlpFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, file_name: P) -> Result<()> {
+        let mut file = try!(File::create(file_name.as_ref()));
+        try!(self.write_to(&mut file));
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, stream: &mut W) -> Result<()> {
+        let encoded_frames: Vec<EncodedFrame> = self.frames.iter().map(encode_frame).collect();
+
+        try!(stream.write_all(VERSION));
+        try!(write_u32(stream, self.frames.len() as u32));
+        try!(stream.write_all(COMMENT));
+        try!(stream.write_all(&vec![0u8; COMMENT_SIZE - COMMENT.len()]));
+
+        // Frame data follows all of the frame info, one frame after another
+        let mut offset = HEADER_SIZE + FRAME_INFO_SIZE * self.frames.len() as u32;
+        for (frame, encoded) in self.frames.iter().zip(encoded_frames.iter()) {
+            let outline_offset = offset;
+            let command_table_offset = outline_offset + frame.height * 4;
+            try!(write_u32(stream, command_table_offset));
+            try!(write_u32(stream, outline_offset));
+            try!(write_u32(stream, 0)); // palette offset
+            try!(write_u32(stream, 0)); // properties
+            try!(write_u32(stream, frame.width));
+            try!(write_u32(stream, frame.height));
+            try!(write_u32(stream, frame.center_x as u32));
+            try!(write_u32(stream, frame.center_y as u32));
+            offset = command_table_offset + frame.height * 4 + encoded.commands.len() as u32;
+        }
+
+        let mut offset = HEADER_SIZE + FRAME_INFO_SIZE * self.frames.len() as u32;
+        for (frame, encoded) in self.frames.iter().zip(encoded_frames.iter()) {
+            for &(left, right) in &encoded.outlines {
+                try!(write_u16(stream, left));
+                try!(write_u16(stream, right));
+            }
+
+            let commands_offset = offset + frame.height * 8;
+            for row_offset in &encoded.row_offsets {
+                try!(write_u32(stream, commands_offset + row_offset));
+            }
+            try!(stream.write_all(&encoded.commands));
+            offset = commands_offset + encoded.commands.len() as u32;
+        }
+        Ok(())
+    }
+}
+
+struct EncodedFrame {
+    outlines: Vec<(u16, u16)>,
+    row_offsets: Vec<u32>,
+    commands: Vec<u8>,
+}
+
+fn encode_frame(frame: &SlpFrame) -> EncodedFrame {
+    let mut encoded = EncodedFrame {
+        outlines: Vec::new(),
+        row_offsets: Vec::new(),
+        commands: Vec::new(),
+    };
+
+    for y in 0..frame.height {
+        let row = frame.row(y);
+        encoded.row_offsets.push(encoded.commands.len() as u32);
+
+        let first = row.iter().position(|pixel| *pixel != SlpPixel::Transparent);
+        let last = row.iter().rposition(|pixel| *pixel != SlpPixel::Transparent);
+        match (first, last) {
+            (Some(first), Some(last)) => {
+                encoded.outlines.push((first as u16, (row.len() - 1 - last) as u16));
+                encode_row(&row[first..last + 1], &mut encoded.commands);
+            }
+            _ => encoded.outlines.push((TRANSPARENT_ROW, TRANSPARENT_ROW)),
+        }
+        encoded.commands.push(COMMAND_END_OF_ROW);
+    }
+    encoded
+}
+
+/// Encodes the pixels between a row's outlines as run-length commands
+pub fn encode_row(pixels: &[SlpPixel], commands: &mut Vec<u8>) {
+    let mut color_list: Vec<u8> = Vec::new();
+    let mut index = 0;
+    while index < pixels.len() {
+        let pixel = pixels[index];
+        let run_length = pixels[index..].iter().take_while(|p| **p == pixel).count();
+
+        if let SlpPixel::Color(color) = pixel {
+            if run_length < MIN_FILL_LENGTH {
+                color_list.extend((0..run_length).map(|_| color));
+                index += run_length;
+                continue;
+            }
+        }
+
+        write_color_list(&color_list, commands);
+        color_list.clear();
+        match pixel {
+            SlpPixel::Transparent => write_skip(run_length, commands),
+            SlpPixel::Color(color) => write_nibble_command(COMMAND_FILL, run_length, &[color], commands),
+            SlpPixel::PlayerColor(_) => {
+                // Player colors are listed together since they're rarely a solid fill
+                let player_colors: Vec<u8> = pixels[index..]
+                    .iter()
+                    .take_while(|p| match **p {
+                        SlpPixel::PlayerColor(_) => true,
+                        _ => false,
+                    })
+                    .map(|p| match *p {
+                        SlpPixel::PlayerColor(offset) => offset,
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                if run_length == player_colors.len() {
+                    write_nibble_command(COMMAND_PLAYER_COLOR_FILL, run_length, &[player_colors[0]], commands);
+                } else {
+                    for chunk in player_colors.chunks(MAX_EXTENDED_LENGTH) {
+                        write_length(COMMAND_PLAYER_COLOR_LIST, chunk.len(), commands);
+                        commands.extend_from_slice(chunk);
+                    }
+                }
+                index += player_colors.len();
+                continue;
+            }
+            SlpPixel::Shadow => write_nibble_command(COMMAND_SHADOW, run_length, &[], commands),
+        }
+        index += run_length;
+    }
+    write_color_list(&color_list, commands);
+}
+
+fn write_color_list(colors: &[u8], commands: &mut Vec<u8>) {
+    for chunk in colors.chunks(MAX_BIG_LENGTH) {
+        if chunk.len() <= MAX_SMALL_LENGTH {
+            commands.push(COMMAND_COLOR_LIST | (chunk.len() << 2) as u8);
+        } else {
+            commands.push(COMMAND_BIG_COLOR_LIST | ((chunk.len() >> 4) & 0xF0) as u8);
+            commands.push(chunk.len() as u8);
+        }
+        commands.extend_from_slice(chunk);
+    }
+}
+
+fn write_skip(length: usize, commands: &mut Vec<u8>) {
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk = if remaining > MAX_BIG_LENGTH { MAX_BIG_LENGTH } else { remaining };
+        if chunk <= MAX_SMALL_LENGTH {
+            commands.push(COMMAND_SKIP | (chunk << 2) as u8);
+        } else {
+            commands.push(COMMAND_BIG_SKIP | ((chunk >> 4) & 0xF0) as u8);
+            commands.push(chunk as u8);
+        }
+        remaining -= chunk;
+    }
+}
+
+/// Writes a command whose length is in the upper nibble, or in the following byte if it doesn't fit
+fn write_length(command: u8, length: usize, commands: &mut Vec<u8>) {
+    if length <= MAX_NIBBLE_LENGTH {
+        commands.push(command | (length << 4) as u8);
+    } else {
+        commands.push(command);
+        commands.push(length as u8);
+    }
+}
+
+fn write_nibble_command(command: u8, length: usize, arguments: &[u8], commands: &mut Vec<u8>) {
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk = if remaining > MAX_EXTENDED_LENGTH { MAX_EXTENDED_LENGTH } else { remaining };
+        write_length(command, chunk, commands);
+        commands.extend_from_slice(arguments);
+        remaining -= chunk;
+    }
+}
+
+fn write_u16<W: Write>(stream: &mut W, value: u16) -> io::Result<()> {
+    stream.write_all(&[value as u8, (value >> 8) as u8])
+}
+
+fn write_u32<W: Write>(stream: &mut W, value: u32) -> io::Result<()> {
+    stream.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::SlpPixel::*;
+
+    #[test]
+    fn test_encode_row_commands() {
+        let mut commands = Vec::new();
+        encode_row(&[Color(5), Color(6), Transparent, Transparent, Color(9), Color(9), Color(9), Shadow,
+                     PlayerColor(2), PlayerColor(2)],
+                   &mut commands);
+        assert_eq!(vec![0x08, 5, 6, // color list of 2
+                        0x09, // skip 2
+                        0x37, 9, // fill 3
+                        0x1B, // shadow 1
+                        0x2A, 2 /* player color fill 2 */],
+                   commands);
+    }
+
+    #[test]
+    fn test_encode_long_runs() {
+        let mut commands = Vec::new();
+        let pixels: Vec<SlpPixel> = (0..100).map(|_| Transparent).chain((0..20).map(|_| Shadow)).collect();
+        encode_row(&pixels, &mut commands);
+        assert_eq!(vec![0x03, 100, 0x0B, 20], commands);
+    }
+
+    #[test]
+    fn test_encode_frame_outlines() {
+        let frame = SlpFrame::from_indexed(4, 2, 2, 1, &[0, 0, 0, 0, 0, 7, 7, 0]);
+        let encoded = encode_frame(&frame);
+        assert_eq!(vec![(0x8000, 0x8000), (1, 1)], encoded.outlines);
+        assert_eq!(vec![0, 1], encoded.row_offsets);
+        assert_eq!(vec![0x0F, 0x08, 7, 7, 0x0F], encoded.commands);
+    }
+
+    #[test]
+    fn test_slp_encoder_header() {
+        let mut encoder = SlpEncoder::new();
+        encoder.add_frame(SlpFrame::from_indexed(1, 1, 0, 0, &[3]));
+
+        let mut bytes = Vec::new();
+        encoder.write_to(&mut bytes).unwrap();
+        assert_eq!(b"2.0N", &bytes[0..4]);
+        assert_eq!(1, bytes[4]);
+
+        // Outline table right after the frame info, then the command table, then the commands
+        assert_eq!(64 + 4, bytes[32]);
+        assert_eq!(64, bytes[36]);
+        assert_eq!(64 + 8, bytes[68]);
+        assert_eq!(&[0x04, 3, 0x0F], &bytes[72..]);
+    }
+}
@@ -0,0 +1,294 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use error::*;
+
+/// Version prefix of the SLPs the newer editions use. Their frames are usually 32-bit color
+/// rather than indexed, with player colors still indexed into the palette.
+const VERSION_PREFIX: &'static [u8] = b"4.";
+
+const HEADER_SIZE: usize = 32;
+const FRAME_INFO_SIZE: usize = 32;
+
+/// Outline value that marks a row as completely transparent
+const TRANSPARENT_ROW: u16 = 0x8000;
+
+/// Frames with these property bits set hold 32-bit BGRA pixels instead of palette indices
+const FRAME_32_BIT: u32 = 0x07;
+
+const PLAYER_COLOR_START: u32 = 16;
+const SHADOW_RGBA: [u8; 4] = [0, 0, 0, 128];
+
+/// A decoded frame, in R, G, B, A bytes
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlpV4Frame {
+    pub width: u32,
+    pub height: u32,
+    pub center_x: i32,
+    pub center_y: i32,
+    pub rgba: Vec<u8>,
+}
+
+/// True if the contents look like an SLP from one of the newer editions
+pub fn is_slp_v4(contents: &[u8]) -> bool {
+    contents.starts_with(VERSION_PREFIX)
+}
+
+/// Decodes every frame of an SLP 4.x, drawing player colors for the given player. `palette`
+/// is in the same format textures are built from, and is used for player colors and for
+/// frames that are indexed rather than 32-bit.
+pub fn decode_slp_v4(contents: &[u8], palette: &[u32], player_color: u8) -> Result<Vec<SlpV4Frame>> {
+    if !is_slp_v4(contents) || contents.len() < HEADER_SIZE {
+        return Err(bad_slp("missing SLP 4.x header"));
+    }
+    let frame_count = read_u16(contents, 4) as usize;
+    let frame_info_offset = read_u32(contents, 16) as usize;
+
+    let mut frames = Vec::with_capacity(frame_count);
+    for index in 0..frame_count {
+        let info = frame_info_offset + index * FRAME_INFO_SIZE;
+        if info + FRAME_INFO_SIZE > contents.len() {
+            return Err(bad_slp("truncated frame table"));
+        }
+        let (width, height) = (read_u32(contents, info + 16), read_u32(contents, info + 20));
+        let mut decoder = FrameDecoder {
+            contents: contents,
+            palette: palette,
+            player_color: player_color,
+            pixel_size: if read_u32(contents, info + 12) & FRAME_32_BIT == FRAME_32_BIT { 4 } else { 1 },
+            width: width as usize,
+            rgba: vec![0u8; width as usize * height as usize * 4],
+        };
+        let (command_table, outline_table) = (read_u32(contents, info) as usize,
+                                              read_u32(contents, info + 4) as usize);
+        for y in 0..height as usize {
+            try!(decoder.decode_row(y, command_table + y * 4, outline_table + y * 4));
+        }
+        frames.push(SlpV4Frame {
+            width: width,
+            height: height,
+            center_x: read_u32(contents, info + 24) as i32,
+            center_y: read_u32(contents, info + 28) as i32,
+            rgba: decoder.rgba,
+        });
+    }
+    Ok(frames)
+}
+
+struct FrameDecoder<'a> {
+    contents: &'a [u8],
+    palette: &'a [u32],
+    player_color: u8,
+    pixel_size: usize,
+    width: usize,
+    rgba: Vec<u8>,
+}
+
+impl<'a> FrameDecoder<'a> {
+    fn decode_row(&mut self, y: usize, command_offset_at: usize, outline_at: usize) -> Result<()> {
+        if outline_at + 4 > self.contents.len() || command_offset_at + 4 > self.contents.len() {
+            return Err(bad_slp("truncated row tables"));
+        }
+        let (left, right) = (read_u16(self.contents, outline_at), read_u16(self.contents, outline_at + 2));
+        if left & TRANSPARENT_ROW != 0 || right & TRANSPARENT_ROW != 0 {
+            return Ok(());
+        }
+
+        let mut offset = read_u32(self.contents, command_offset_at) as usize;
+        let mut x = left as usize;
+        loop {
+            let command = try!(self.byte(&mut offset));
+            match command & 0x0f {
+                0x00 | 0x04 | 0x08 | 0x0c => {
+                    let count = (command >> 2) as usize;
+                    try!(self.copy_colors(y, &mut x, count, &mut offset));
+                }
+                0x01 | 0x05 | 0x09 | 0x0d => x += (command >> 2) as usize,
+                0x02 => {
+                    let count = ((command as usize & 0xf0) << 4) + try!(self.byte(&mut offset)) as usize;
+                    try!(self.copy_colors(y, &mut x, count, &mut offset));
+                }
+                0x03 => x += ((command as usize & 0xf0) << 4) + try!(self.byte(&mut offset)) as usize,
+                0x06 => {
+                    let count = try!(self.nibble_count(command, &mut offset));
+                    for _ in 0..count {
+                        let index = try!(self.byte(&mut offset));
+                        let rgba = self.player_rgba(index);
+                        self.put(y, &mut x, rgba);
+                    }
+                }
+                0x07 => {
+                    let count = try!(self.nibble_count(command, &mut offset));
+                    let rgba = try!(self.color(&mut offset));
+                    for _ in 0..count {
+                        self.put(y, &mut x, rgba);
+                    }
+                }
+                0x0a => {
+                    let count = try!(self.nibble_count(command, &mut offset));
+                    let rgba = self.player_rgba(try!(self.byte(&mut offset)));
+                    for _ in 0..count {
+                        self.put(y, &mut x, rgba);
+                    }
+                }
+                0x0b => {
+                    for _ in 0..try!(self.nibble_count(command, &mut offset)) {
+                        self.put(y, &mut x, SHADOW_RGBA);
+                    }
+                }
+                0x0e => {
+                    // Outlines drawn where units are behind buildings, which aren't drawn yet
+                    match command >> 4 {
+                        0x04 | 0x06 => x += 1,
+                        0x05 | 0x07 => x += try!(self.byte(&mut offset)) as usize,
+                        _ => {}
+                    }
+                }
+                // 0x0f ends the row
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn copy_colors(&mut self, y: usize, x: &mut usize, count: usize, offset: &mut usize) -> Result<()> {
+        for _ in 0..count {
+            let rgba = try!(self.color(offset));
+            self.put(y, x, rgba);
+        }
+        Ok(())
+    }
+
+    fn nibble_count(&self, command: u8, offset: &mut usize) -> Result<usize> {
+        match command >> 4 {
+            0 => self.byte(offset).map(|count| count as usize),
+            count => Ok(count as usize),
+        }
+    }
+
+    fn byte(&self, offset: &mut usize) -> Result<u8> {
+        match self.contents.get(*offset) {
+            Some(&byte) => {
+                *offset += 1;
+                Ok(byte)
+            }
+            None => Err(bad_slp("a row runs past the end of the file")),
+        }
+    }
+
+    fn color(&self, offset: &mut usize) -> Result<[u8; 4]> {
+        if self.pixel_size == 1 {
+            let index = try!(self.byte(offset));
+            return Ok(palette_rgba(self.palette, index as u32));
+        }
+        if *offset + 4 > self.contents.len() {
+            return Err(bad_slp("a row runs past the end of the file"));
+        }
+        let bgra = &self.contents[*offset..*offset + 4];
+        *offset += 4;
+        Ok([bgra[2], bgra[1], bgra[0], bgra[3]])
+    }
+
+    fn player_rgba(&self, index: u8) -> [u8; 4] {
+        palette_rgba(self.palette,
+                     index as u32 + PLAYER_COLOR_START * self.player_color.max(1) as u32)
+    }
+
+    /// Draws a pixel and moves on to the next one; pixels past the edge of the frame are dropped
+    fn put(&mut self, y: usize, x: &mut usize, rgba: [u8; 4]) {
+        if *x < self.width {
+            let start = (y * self.width + *x) * 4;
+            self.rgba[start..start + 4].copy_from_slice(&rgba);
+        }
+        *x += 1;
+    }
+}
+
+/// Palette entries are packed with red in the highest byte, as textures expect them
+fn palette_rgba(palette: &[u32], index: u32) -> [u8; 4] {
+    match palette.get(index as usize) {
+        Some(&color) => [(color >> 24) as u8, (color >> 16) as u8, (color >> 8) as u8, color as u8],
+        None => [0, 0, 0, 0],
+    }
+}
+
+fn bad_slp(message: &str) -> Error {
+    ErrorKind::BadSlpV4(message.into()).into()
+}
+
+fn read_u16(contents: &[u8], offset: usize) -> u16 {
+    contents[offset] as u16 | (contents[offset + 1] as u16) << 8
+}
+
+fn read_u32(contents: &[u8], offset: usize) -> u32 {
+    read_u16(contents, offset) as u32 | (read_u16(contents, offset + 2) as u32) << 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_slp_v4, is_slp_v4};
+
+    fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+        bytes.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+    }
+
+    /// A 3x2 32-bit frame: a red pixel, then player color 2, then a skipped pixel, with the
+    /// second row left transparent
+    fn slp_bytes() -> Vec<u8> {
+        let mut bytes = b"4.0X".to_vec();
+        bytes.extend_from_slice(&[1, 0, 8, 0, 1, 0, 1, 0]);
+        push_u32(&mut bytes, 0);
+        push_u32(&mut bytes, 32);
+        push_u32(&mut bytes, 0);
+        bytes.extend_from_slice(&[0; 8]);
+
+        let outline_table = 64;
+        let command_table = outline_table + 8;
+        let commands = command_table + 8;
+        for &value in &[command_table, outline_table, 0, 0x07, 3, 2, 1, 1] {
+            push_u32(&mut bytes, value);
+        }
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0x00, 0x80, 0x00, 0x80]);
+        push_u32(&mut bytes, commands);
+        push_u32(&mut bytes, commands);
+        bytes.extend_from_slice(&[0x04, 0, 0, 255, 255, 0x16, 2, 0x05, 0x0f]);
+        bytes
+    }
+
+    #[test]
+    fn test_decode() {
+        assert!(is_slp_v4(&slp_bytes()));
+        let palette: Vec<u32> = (0..64u32).map(|index| index << 24 | 0xff).collect();
+        let frames = decode_slp_v4(&slp_bytes(), &palette, 2).unwrap();
+        assert_eq!(1, frames.len());
+        let frame = &frames[0];
+        assert_eq!((3, 2, 1, 1), (frame.width, frame.height, frame.center_x, frame.center_y));
+        assert_eq!(vec![255, 0, 0, 255, 34, 0, 0, 255, 0, 0, 0, 0], frame.rgba[..12].to_vec());
+        assert!(frame.rgba[12..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut bytes = slp_bytes();
+        bytes.truncate(bytes.len() - 3);
+        assert!(decode_slp_v4(&bytes, &[], 1).is_err());
+        assert!(decode_slp_v4(b"2.0N", &[], 1).is_err());
+    }
+}
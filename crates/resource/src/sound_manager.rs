@@ -0,0 +1,68 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use drs::DrsFileType;
+use drs_manager::{DrsKey, DrsManagerRef};
+use identifier::WavFileId;
+use media::{Audio, Sound};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Decodes the sounds in sounds.drs, or the mods' replacements for them, the first time they're
+/// played. They're all short and few enough to keep around.
+pub struct SoundManager {
+    drs_manager: DrsManagerRef,
+    /// None for the sounds that failed to load, so they're only warned about once
+    sounds: HashMap<WavFileId, Option<Sound>>,
+}
+
+pub type SoundManagerRef = Rc<RefCell<SoundManager>>;
+
+impl SoundManager {
+    pub fn new(drs_manager: DrsManagerRef) -> SoundManagerRef {
+        Rc::new(RefCell::new(SoundManager {
+            drs_manager: drs_manager,
+            sounds: HashMap::new(),
+        }))
+    }
+
+    pub fn get(&mut self, wav_id: WavFileId, audio: &Audio) -> Option<Sound> {
+        if let Some(sound) = self.sounds.get(&wav_id) {
+            return sound.clone();
+        }
+        let sound = self.drs_manager
+            .borrow_mut()
+            .load_contents(DrsKey::Sounds, DrsFileType::Wav, *wav_id)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| audio.decode_wav(&contents).map_err(|err| err.to_string()));
+        let sound = match sound {
+            Ok(sound) => Some(sound),
+            Err(err) => {
+                warn!("Failed to load sound {}: {}", *wav_id, err);
+                None
+            }
+        };
+        self.sounds.insert(wav_id, sound.clone());
+        sound
+    }
+}
@@ -23,7 +23,7 @@ use sdl2;
 
 use std::cmp;
 
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -50,6 +50,16 @@ impl Rect {
         self.y += y;
     }
 
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+
+    /// Whether the two overlap, counting rects that only touch at an edge
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.w && other.x <= self.x + self.w && self.y <= other.y + other.h &&
+        other.y <= self.y + self.h
+    }
+
     pub fn extend(&mut self, other: &Rect) {
         self.x = cmp::min(self.x, other.x);
         self.y = cmp::min(self.y, other.y);
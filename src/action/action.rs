@@ -19,7 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use super::MoveToPositionParams;
+use super::{AttackGroundParams, GarrisonParams, GatherParams, MoveToPositionParams, RestoreParams};
 
 /// Enum of possible actions a unit can undertake
 #[derive(Clone, Debug)]
@@ -27,6 +27,32 @@ pub enum Action {
     /// Clears a unit's action queue
     ClearQueue,
 
+    /// Sets a unit's current and queued actions aside until it's told to resume them, so it
+    /// can be given something else to do in the meantime
+    SuspendQueue,
+
+    /// Replaces whatever a unit is doing with the actions it last had set aside
+    ResumeQueue,
+
     /// Instructs a unit to move to a given position on the map
     MoveToPosition(MoveToPositionParams),
+
+    /// Instructs a villager to gather from a resource, or hunt an animal for its meat, and carry
+    /// what it gathers back to a drop site
+    Gather(GatherParams),
+
+    /// Instructs a siege unit to keep firing at a spot on the ground, hurting whatever is
+    /// there, the unit's own side included
+    AttackGround(AttackGroundParams),
+
+    /// Instructs a priest to heal a living unit, or a villager to repair a building, siege
+    /// weapon or boat, until it's back to full hit points
+    Restore(RestoreParams),
+
+    /// Instructs a unit to keep heading for the nearest big stretch of the map that its player
+    /// hasn't explored yet, until there's none left
+    Explore,
+
+    /// Instructs a unit to walk to a building and stay inside it, out of harm's way
+    Garrison(GarrisonParams),
 }
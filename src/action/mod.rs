@@ -20,7 +20,15 @@
 // SOFTWARE.
 
 mod action;
+mod attack_ground;
+mod garrison;
+mod gather;
 mod move_to_position;
+mod restore;
 
 pub use self::action::Action;
+pub use self::attack_ground::AttackGroundParams;
+pub use self::garrison::GarrisonParams;
+pub use self::gather::GatherParams;
 pub use self::move_to_position::MoveToPositionParams;
+pub use self::restore::RestoreParams;
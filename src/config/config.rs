@@ -0,0 +1,466 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::SelectionClass;
+use logging;
+use media::{FullscreenMode, Key};
+use resource::PlayerPalette;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use super::key_bindings::{KeyAction, KeyBindings};
+use toml::{Table, Value};
+
+pub const CONFIG_FILE_NAME: &'static str = "chariot.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, String),
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref err) => write!(f, "Failed to read {}: {}", path.display(), err),
+            ConfigError::Parse(ref path, ref message) => write!(f, "{}: {}", path.display(), message),
+            ConfigError::InvalidValue(ref key, ref message) => write!(f, "Invalid {}: {}", key, message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::Io(_, _) => "failed to read config file",
+            ConfigError::Parse(_, _) => "failed to parse config file",
+            ConfigError::InvalidValue(_, _) => "invalid config value",
+        }
+    }
+}
+
+/// How the time between frames is evened out before it's fed to the simulation and the camera,
+/// since the measured time jitters from frame to frame even when the display refreshes evenly
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameSmoothing {
+    /// Use each frame's measured time as it is
+    Off,
+    /// Average the last few frames' times
+    Average,
+    /// Round frame times to whole refreshes of the display, carrying the difference over
+    Snap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoConfig {
+    /// Size the window starts at; it can be resized after
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub fullscreen_mode: FullscreenMode,
+    /// How many window pixels each drawn pixel covers; 0 picks it from the window's height
+    pub scale: u32,
+    /// Wait for the display's refresh before showing each frame
+    pub vsync: bool,
+    /// Most frames to draw a second, separately from the simulation's updates; 0 for no limit
+    pub fps_cap: u32,
+    pub frame_smoothing: FrameSmoothing,
+    /// Tint the world for the time of day, or as a scenario asks
+    pub tint: bool,
+    /// Real seconds for a whole day and night to go by; 0 keeps it day
+    pub day_length: u32,
+    /// Have the system draw the cursor, so that it keeps up with the mouse; it's drawn with the
+    /// rest of the frame otherwise, or if the system can't
+    pub hardware_cursor: bool,
+    /// Colors to draw the players in, with alternatives that color blind players can tell apart
+    pub player_colors: PlayerPalette,
+    /// Mark each player with their own shape as well as their color where they're shown as
+    /// dots and swatches
+    pub player_markers: bool,
+    /// Layout file that places the interface panels and picks the graphics they're drawn with;
+    /// empty for the built-in layout
+    pub ui_layout: String,
+}
+
+impl VideoConfig {
+    fn is_structural_change(&self, other: &VideoConfig) -> bool {
+        self.width != other.width || self.height != other.height || self.vsync != other.vsync
+    }
+}
+
+/// Volumes range from 0 (muted) to 1 (full volume)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioConfig {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sound_volume: f32,
+    /// The waves on the shore and the birds in the forests
+    pub ambient_volume: f32,
+    /// Whether the music changes to battle tracks while the player's units are fighting
+    pub battle_music: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlsConfig {
+    /// Which kinds of unit a selection box picks first, when it's dragged over more than one
+    pub selection_priority: Vec<SelectionClass>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutosaveConfig {
+    /// Minutes of game time between saves; 0 turns auto-saving off
+    pub interval_minutes: u32,
+    /// How many auto-saves are kept before the oldest is written over
+    pub slots: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkConfig {
+    /// Port that hosted games listen on
+    pub port: u16,
+}
+
+/// Every setting that isn't chosen per match in the lobby. Settings come from several layers,
+/// each overriding the last: the defaults, then `chariot.toml`, then command line flags, and
+/// finally options changed in game. Structural settings (where the game data comes from, mods,
+/// the starting window size, and network port) only take effect on startup; the rest can be
+/// changed while running.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub data_dir: String,
+    /// More places to look for the game data when it isn't in `data_dir`, such as where the
+    /// game CD gets mounted
+    pub data_search_dirs: Vec<String>,
+    /// Where a newer edition of the game is installed, to draw with its graphics and palettes
+    pub edition_dir: Option<String>,
+    pub mods: Vec<String>,
+    /// Reload mod graphics as they're changed on disk, for quicker modding
+    pub watch_data: bool,
+    pub log_filter: String,
+    pub video: VideoConfig,
+    pub audio: AudioConfig,
+    pub controls: ControlsConfig,
+    pub autosave: AutosaveConfig,
+    pub network: NetworkConfig,
+    pub keys: KeyBindings,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            data_dir: "game".into(),
+            data_search_dirs: Vec::new(),
+            edition_dir: None,
+            mods: Vec::new(),
+            watch_data: false,
+            log_filter: logging::DEFAULT_LOG_FILTER.into(),
+            video: VideoConfig {
+                width: 1024,
+                height: 768,
+                fullscreen: false,
+                fullscreen_mode: FullscreenMode::Borderless,
+                scale: 0,
+                vsync: true,
+                fps_cap: 0,
+                frame_smoothing: FrameSmoothing::Average,
+                tint: true,
+                day_length: 0,
+                hardware_cursor: true,
+                player_colors: PlayerPalette::Original,
+                player_markers: false,
+                ui_layout: String::new(),
+            },
+            audio: AudioConfig {
+                master_volume: 1.0,
+                music_volume: 0.7,
+                sound_volume: 1.0,
+                ambient_volume: 0.8,
+                battle_music: true,
+            },
+            controls: ControlsConfig {
+                selection_priority: vec![SelectionClass::Military,
+                                         SelectionClass::Villagers,
+                                         SelectionClass::Units,
+                                         SelectionClass::Buildings],
+            },
+            autosave: AutosaveConfig {
+                interval_minutes: 5,
+                slots: 3,
+            },
+            network: NetworkConfig { port: 2300 },
+            keys: KeyBindings::new(),
+        }
+    }
+
+    /// Overrides the settings that are present in the given table with its values
+    pub fn apply(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "data_dir" => self.data_dir = try!(as_string(key, value)),
+                "data_search_dirs" => self.data_search_dirs = try!(as_string_list(key, value)),
+                "edition_dir" => self.edition_dir = Some(try!(as_string(key, value))),
+                "mods" => self.mods = try!(as_string_list(key, value)),
+                "watch_data" => self.watch_data = try!(as_bool(key, value)),
+                "log" => self.log_filter = try!(as_string(key, value)),
+                "video" => try!(self.apply_video(try!(as_table(key, value)))),
+                "audio" => try!(self.apply_audio(try!(as_table(key, value)))),
+                "controls" => try!(self.apply_controls(try!(as_table(key, value)))),
+                "autosave" => try!(self.apply_autosave(try!(as_table(key, value)))),
+                "network" => try!(self.apply_network(try!(as_table(key, value)))),
+                "keys" => try!(self.apply_keys(try!(as_table(key, value)))),
+                _ => warn!("Unknown config setting \"{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the settings that differ between the two configs that can't change while running
+    pub fn structural_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.data_dir != other.data_dir || self.data_search_dirs != other.data_search_dirs {
+            changes.push("data_dir");
+        }
+        if self.edition_dir != other.edition_dir {
+            changes.push("edition_dir");
+        }
+        if self.mods != other.mods {
+            changes.push("mods");
+        }
+        if self.video.is_structural_change(&other.video) {
+            changes.push("video");
+        }
+        if self.network != other.network {
+            changes.push("network");
+        }
+        changes
+    }
+
+    /// Takes the settings from the other config that can change while running
+    pub fn apply_non_structural(&mut self, other: &Config) {
+        self.watch_data = other.watch_data;
+        self.log_filter = other.log_filter.clone();
+        self.video.fullscreen = other.video.fullscreen;
+        self.video.fullscreen_mode = other.video.fullscreen_mode;
+        self.video.scale = other.video.scale;
+        self.video.fps_cap = other.video.fps_cap;
+        self.video.frame_smoothing = other.video.frame_smoothing;
+        self.video.tint = other.video.tint;
+        self.video.day_length = other.video.day_length;
+        self.video.hardware_cursor = other.video.hardware_cursor;
+        self.video.player_colors = other.video.player_colors;
+        self.video.player_markers = other.video.player_markers;
+        self.video.ui_layout = other.video.ui_layout.clone();
+        self.audio = other.audio.clone();
+        self.controls = other.controls.clone();
+        self.autosave = other.autosave.clone();
+        self.keys = other.keys.clone();
+    }
+
+    fn apply_video(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "width" => self.video.width = try!(as_integer("video.width", value, 320, 16384)) as u32,
+                "height" => self.video.height = try!(as_integer("video.height", value, 200, 16384)) as u32,
+                "fullscreen" => self.video.fullscreen = try!(as_bool("video.fullscreen", value)),
+                "fullscreen_mode" => {
+                    self.video.fullscreen_mode = try!(as_fullscreen_mode("video.fullscreen_mode", value))
+                }
+                "scale" => self.video.scale = try!(as_integer("video.scale", value, 0, 8)) as u32,
+                "vsync" => self.video.vsync = try!(as_bool("video.vsync", value)),
+                "fps_cap" => self.video.fps_cap = try!(as_integer("video.fps_cap", value, 0, 1000)) as u32,
+                "frame_smoothing" => {
+                    self.video.frame_smoothing = try!(as_frame_smoothing("video.frame_smoothing", value))
+                }
+                "tint" => self.video.tint = try!(as_bool("video.tint", value)),
+                "day_length" => {
+                    self.video.day_length = try!(as_integer("video.day_length", value, 0, 86400)) as u32
+                }
+                "hardware_cursor" => {
+                    self.video.hardware_cursor = try!(as_bool("video.hardware_cursor", value))
+                }
+                "player_colors" => {
+                    self.video.player_colors = try!(as_player_palette("video.player_colors", value))
+                }
+                "player_markers" => self.video.player_markers = try!(as_bool("video.player_markers", value)),
+                "ui_layout" => self.video.ui_layout = try!(as_string("video.ui_layout", value)),
+                _ => warn!("Unknown config setting \"video.{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_audio(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "master_volume" => self.audio.master_volume = try!(as_volume("audio.master_volume", value)),
+                "music_volume" => self.audio.music_volume = try!(as_volume("audio.music_volume", value)),
+                "sound_volume" => self.audio.sound_volume = try!(as_volume("audio.sound_volume", value)),
+                "ambient_volume" => {
+                    self.audio.ambient_volume = try!(as_volume("audio.ambient_volume", value))
+                }
+                "battle_music" => self.audio.battle_music = try!(as_bool("audio.battle_music", value)),
+                _ => warn!("Unknown config setting \"audio.{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_controls(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "selection_priority" => {
+                    self.controls.selection_priority =
+                        try!(as_selection_priority("controls.selection_priority", value))
+                }
+                _ => warn!("Unknown config setting \"controls.{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_autosave(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "interval_minutes" => {
+                    self.autosave.interval_minutes =
+                        try!(as_integer("autosave.interval_minutes", value, 0, 600)) as u32
+                }
+                "slots" => self.autosave.slots = try!(as_integer("autosave.slots", value, 1, 20)) as u32,
+                _ => warn!("Unknown config setting \"autosave.{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_network(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match &key[..] {
+                "port" => self.network.port = try!(as_integer("network.port", value, 1, 65535)) as u16,
+                _ => warn!("Unknown config setting \"network.{}\"", key),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_keys(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (action_name, value) in table {
+            let config_key = format!("keys.{}", action_name);
+            let action = try!(KeyAction::from_name(action_name)
+                .ok_or_else(|| invalid(&config_key, "no such action".into())));
+            let key_name = try!(as_string(&config_key, value));
+            let key = try!(Key::from_name(&key_name)
+                .ok_or_else(|| invalid(&config_key, format!("unknown key \"{}\"", key_name))));
+            self.keys.bind(action, key);
+        }
+        Ok(())
+    }
+}
+
+fn invalid(key: &str, message: String) -> ConfigError {
+    ConfigError::InvalidValue(key.into(), message)
+}
+
+fn as_table<'a>(key: &str, value: &'a Value) -> Result<&'a Table, ConfigError> {
+    match *value {
+        Value::Table(ref table) => Ok(table),
+        _ => Err(invalid(key, "expected a table".into())),
+    }
+}
+
+fn as_string(key: &str, value: &Value) -> Result<String, ConfigError> {
+    match *value {
+        Value::String(ref string) => Ok(string.clone()),
+        _ => Err(invalid(key, "expected a string".into())),
+    }
+}
+
+fn as_bool(key: &str, value: &Value) -> Result<bool, ConfigError> {
+    match *value {
+        Value::Boolean(boolean) => Ok(boolean),
+        _ => Err(invalid(key, "expected true or false".into())),
+    }
+}
+
+fn as_string_list(key: &str, value: &Value) -> Result<Vec<String>, ConfigError> {
+    match *value {
+        Value::Array(ref values) => values.iter().map(|value| as_string(key, value)).collect(),
+        _ => Err(invalid(key, "expected a list of strings".into())),
+    }
+}
+
+fn as_integer(key: &str, value: &Value, min: i64, max: i64) -> Result<i64, ConfigError> {
+    match *value {
+        Value::Integer(integer) if integer >= min && integer <= max => Ok(integer),
+        Value::Integer(integer) => {
+            Err(invalid(key, format!("{} isn't between {} and {}", integer, min, max)))
+        }
+        _ => Err(invalid(key, "expected a whole number".into())),
+    }
+}
+
+fn as_fullscreen_mode(key: &str, value: &Value) -> Result<FullscreenMode, ConfigError> {
+    match &try!(as_string(key, value))[..] {
+        "borderless" => Ok(FullscreenMode::Borderless),
+        "exclusive" => Ok(FullscreenMode::Exclusive),
+        other => Err(invalid(key, format!("\"{}\" isn't borderless or exclusive", other))),
+    }
+}
+
+fn as_frame_smoothing(key: &str, value: &Value) -> Result<FrameSmoothing, ConfigError> {
+    match &try!(as_string(key, value))[..] {
+        "off" => Ok(FrameSmoothing::Off),
+        "average" => Ok(FrameSmoothing::Average),
+        "snap" => Ok(FrameSmoothing::Snap),
+        other => Err(invalid(key, format!("\"{}\" isn't off, average, or snap", other))),
+    }
+}
+
+fn as_player_palette(key: &str, value: &Value) -> Result<PlayerPalette, ConfigError> {
+    let name = try!(as_string(key, value));
+    PlayerPalette::from_name(&name)
+        .ok_or_else(|| invalid(key, format!("\"{}\" isn't original, color_blind, or high_contrast", name)))
+}
+
+fn as_selection_priority(key: &str, value: &Value) -> Result<Vec<SelectionClass>, ConfigError> {
+    let mut order = Vec::new();
+    for name in try!(as_string_list(key, value)) {
+        let class = try!(SelectionClass::from_name(&name).ok_or_else(|| {
+            invalid(key, format!("\"{}\" isn't military, villagers, units, or buildings", name))
+        }));
+        order.push(class);
+    }
+    Ok(order)
+}
+
+fn as_volume(key: &str, value: &Value) -> Result<f32, ConfigError> {
+    let volume = match *value {
+        Value::Float(float) => float,
+        Value::Integer(integer) => integer as f64,
+        _ => return Err(invalid(key, "expected a number".into())),
+    };
+    if volume >= 0.0 && volume <= 1.0 {
+        Ok(volume as f32)
+    } else {
+        Err(invalid(key, format!("{} isn't between 0 and 1", volume)))
+    }
+}
@@ -0,0 +1,144 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use media::{Key, KeyState, KeyStates};
+use std::collections::HashMap;
+
+/// Game actions that can be bound to a key
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum KeyAction {
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    NextIdleVillager,
+    NextIdleMilitary,
+    SpeedUp,
+    SlowDown,
+    OpenChat,
+    ToggleDiplomacy,
+    ToggleMarket,
+    ToggleDebugOverlay,
+    OpenDebugConsole,
+    ToggleRepeatProduction,
+    AddToSelection,
+    NextSubgroup,
+    ToggleScoreScreen,
+    InspectEntity,
+    BuildWall,
+    AttackGround,
+    Explore,
+    RingTownBell,
+    /// Presses the button in a slot of the command card's grid, counting across the rows
+    CommandSlot(u8),
+    /// Hides or shows the command card; its keys work either way
+    ToggleCommandPanel,
+    /// Held while clicking units to add them to the selection or take them out of it
+    ToggleSelected,
+}
+
+/// Every action with its config file name and default key
+const KEY_ACTIONS: [(KeyAction, &'static str, Key); 39] =
+    [(KeyAction::ScrollUp, "scroll_up", Key::Up),
+     (KeyAction::ScrollDown, "scroll_down", Key::Down),
+     (KeyAction::ScrollLeft, "scroll_left", Key::Left),
+     (KeyAction::ScrollRight, "scroll_right", Key::Right),
+     (KeyAction::NextIdleVillager, "next_idle_villager", Key::Period),
+     (KeyAction::NextIdleMilitary, "next_idle_military", Key::Comma),
+     (KeyAction::SpeedUp, "speed_up", Key::Plus),
+     (KeyAction::SlowDown, "slow_down", Key::Minus),
+     (KeyAction::OpenChat, "open_chat", Key::Return),
+     (KeyAction::ToggleDiplomacy, "toggle_diplomacy", Key::F4),
+     (KeyAction::ToggleMarket, "toggle_market", Key::F5),
+     (KeyAction::ToggleDebugOverlay, "toggle_debug_overlay", Key::F3),
+     (KeyAction::OpenDebugConsole, "open_debug_console", Key::Backquote),
+     (KeyAction::ToggleRepeatProduction, "toggle_repeat_production", Key::R),
+     (KeyAction::AddToSelection, "add_to_selection", Key::Shift),
+     (KeyAction::NextSubgroup, "next_subgroup", Key::Tab),
+     (KeyAction::ToggleScoreScreen, "toggle_score_screen", Key::F6),
+     (KeyAction::InspectEntity, "inspect_entity", Key::Ctrl),
+     (KeyAction::BuildWall, "build_wall", Key::W),
+     (KeyAction::AttackGround, "attack_ground", Key::T),
+     (KeyAction::Explore, "explore", Key::X),
+     (KeyAction::RingTownBell, "ring_town_bell", Key::B),
+     (KeyAction::CommandSlot(0), "command_slot_1", Key::Q),
+     (KeyAction::CommandSlot(1), "command_slot_2", Key::W),
+     (KeyAction::CommandSlot(2), "command_slot_3", Key::E),
+     (KeyAction::CommandSlot(3), "command_slot_4", Key::R),
+     (KeyAction::CommandSlot(4), "command_slot_5", Key::T),
+     (KeyAction::CommandSlot(5), "command_slot_6", Key::A),
+     (KeyAction::CommandSlot(6), "command_slot_7", Key::S),
+     (KeyAction::CommandSlot(7), "command_slot_8", Key::D),
+     (KeyAction::CommandSlot(8), "command_slot_9", Key::F),
+     (KeyAction::CommandSlot(9), "command_slot_10", Key::G),
+     (KeyAction::CommandSlot(10), "command_slot_11", Key::Z),
+     (KeyAction::CommandSlot(11), "command_slot_12", Key::X),
+     (KeyAction::CommandSlot(12), "command_slot_13", Key::C),
+     (KeyAction::CommandSlot(13), "command_slot_14", Key::V),
+     (KeyAction::CommandSlot(14), "command_slot_15", Key::B),
+     (KeyAction::ToggleCommandPanel, "toggle_command_panel", Key::F7),
+     (KeyAction::ToggleSelected, "toggle_selected", Key::Ctrl)];
+
+impl KeyAction {
+    pub fn from_name(name: &str) -> Option<KeyAction> {
+        KEY_ACTIONS.iter().find(|&&(_, action_name, _)| action_name == name).map(|&(action, _, _)| action)
+    }
+
+    pub fn all() -> Vec<KeyAction> {
+        KEY_ACTIONS.iter().map(|&(action, _, _)| action).collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        KEY_ACTIONS.iter().find(|&&(action, _, _)| action == *self).map(|&(_, name, _)| name).unwrap()
+    }
+
+    fn default_key(&self) -> Key {
+        KEY_ACTIONS.iter().find(|&&(action, _, _)| action == *self).map(|&(_, _, key)| key).unwrap()
+    }
+}
+
+/// Which key triggers each action; the `[keys]` table of the config file
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: HashMap<KeyAction, Key>,
+}
+
+impl KeyBindings {
+    pub fn new() -> KeyBindings {
+        KeyBindings {
+            bindings: KEY_ACTIONS.iter().map(|&(action, _, key)| (action, key)).collect(),
+        }
+    }
+
+    #[inline]
+    pub fn key(&self, action: KeyAction) -> Key {
+        self.bindings.get(&action).cloned().unwrap_or(action.default_key())
+    }
+
+    /// State of the key that the action is bound to
+    pub fn key_state(&self, key_states: &KeyStates<Key>, action: KeyAction) -> KeyState {
+        key_states.key_state(self.key(action))
+    }
+
+    pub fn bind(&mut self, action: KeyAction, key: Key) {
+        self.bindings.insert(action, key);
+    }
+}
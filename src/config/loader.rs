@@ -0,0 +1,274 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use super::config::{Config, ConfigError};
+use toml::{self, Table, Value};
+
+/// Reads a setting value that was typed in (on the command line or into the debug console).
+/// Anything that isn't valid TOML on its own, like `game/data`, is taken as a plain string.
+pub fn parse_value(text: &str) -> Value {
+    let mut parser = toml::Parser::new(&format!("value = {}", text));
+    parser.parse()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(text.into()))
+}
+
+/// Keeps each layer of settings separately so that the config file can be reread without
+/// losing the player profile's settings, the command line flags, and options set in game
+pub struct ConfigLoader {
+    file_path: PathBuf,
+    file_modified: Option<SystemTime>,
+    file_layer: Table,
+    profile_layer: Table,
+    cli_layer: Table,
+    options_layer: Table,
+}
+
+impl ConfigLoader {
+    pub fn new<P: AsRef<Path>>(file_path: P) -> ConfigLoader {
+        ConfigLoader {
+            file_path: file_path.as_ref().to_path_buf(),
+            file_modified: None,
+            file_layer: Table::new(),
+            profile_layer: Table::new(),
+            cli_layer: Table::new(),
+            options_layer: Table::new(),
+        }
+    }
+
+    pub fn file_path<'a>(&'a self) -> &'a Path {
+        &self.file_path
+    }
+
+    /// Reads the config file. It's fine for the file not to exist; the defaults are used then.
+    pub fn load_file(&mut self) -> Result<(), ConfigError> {
+        let mut text = String::new();
+        let result = match File::open(&self.file_path) {
+            Ok(mut file) => file.read_to_string(&mut text).map(|_| ()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        };
+        try!(result.map_err(|err| ConfigError::Io(self.file_path.clone(), err)));
+        self.file_layer = try!(parse_table(&self.file_path, &text));
+        self.file_modified = self.modified_time();
+        Ok(())
+    }
+
+    /// True if the config file has been written to since it was last read
+    pub fn file_changed(&self) -> bool {
+        self.modified_time() != self.file_modified
+    }
+
+    /// Sets what the player profile overrides in the config file, like its hotkeys. Profiles
+    /// that don't make for a valid config are rejected, leaving the previous layer in place.
+    pub fn set_profile_layer(&mut self, layer: Table) -> Result<(), ConfigError> {
+        let previous_layer = mem::replace(&mut self.profile_layer, layer);
+        if let Err(err) = self.config() {
+            self.profile_layer = previous_layer;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Overrides a setting from the command line, by its dotted name (like `video.width`)
+    pub fn set_cli_override(&mut self, key: &str, value: Value) {
+        set_dotted(&mut self.cli_layer, key, value);
+    }
+
+    /// Overrides a setting from in game, by its dotted name; these take priority over everything.
+    /// Invalid values are rejected so that they don't get in the way of reloading the file later.
+    pub fn set_option(&mut self, key: &str, value: Value) -> Result<(), ConfigError> {
+        let previous_options = self.options_layer.clone();
+        set_dotted(&mut self.options_layer, key, value);
+        if let Err(err) = self.config() {
+            self.options_layer = previous_options;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Merges all of the layers on top of the defaults
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        let mut config = Config::new();
+        for layer in &[&self.file_layer, &self.profile_layer, &self.cli_layer, &self.options_layer] {
+            try!(config.apply(layer));
+        }
+        Ok(config)
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.file_path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+fn parse_table(path: &Path, text: &str) -> Result<Table, ConfigError> {
+    let mut parser = toml::Parser::new(text);
+    match parser.parse() {
+        Some(table) => Ok(table),
+        None => {
+            let messages: Vec<String> = parser.errors
+                .iter()
+                .map(|err| {
+                    let (line, col) = parser.to_linecol(err.lo);
+                    format!("line {}, column {}: {}", line + 1, col + 1, err.desc)
+                })
+                .collect();
+            Err(ConfigError::Parse(path.to_path_buf(), messages.join("; ")))
+        }
+    }
+}
+
+fn set_dotted(table: &mut Table, key: &str, value: Value) {
+    match key.find('.') {
+        Some(index) => {
+            let entry = table.entry(key[..index].to_string()).or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(ref mut inner) = *entry {
+                set_dotted(inner, &key[index + 1..], value);
+            } else {
+                let mut inner = Table::new();
+                set_dotted(&mut inner, &key[index + 1..], value);
+                *entry = Value::Table(inner);
+            }
+        }
+        None => {
+            table.insert(key.into(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::SelectionClass;
+    use media::Key;
+    use std::path::Path;
+    use super::*;
+    use super::parse_table;
+    use super::super::KeyAction;
+    use toml::Value;
+
+    fn loader_with_file(text: &str) -> ConfigLoader {
+        let mut loader = ConfigLoader::new("chariot.toml");
+        loader.file_layer = parse_table(Path::new("chariot.toml"), text).unwrap();
+        loader
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut loader = loader_with_file("data_dir = \"/aoe\"\n\
+                                           [video]\nwidth = 800\nheight = 600\n\
+                                           [audio]\nmusic_volume = 0.25\n\
+                                           [controls]\nselection_priority = [\"villagers\"]\n\
+                                           [autosave]\ninterval_minutes = 10\n\
+                                           [keys]\ntoggle_market = \"F3\"\n");
+        loader.set_cli_override("video.width", parse_value("1280"));
+        loader.set_option("audio.music_volume", parse_value("0")).unwrap();
+        assert!(loader.set_option("audio.music_volume", parse_value("loud")).is_err());
+
+        let config = loader.config().unwrap();
+        assert_eq!("/aoe", config.data_dir);
+        assert_eq!(1280, config.video.width);
+        assert_eq!(600, config.video.height);
+        assert_eq!(0.0, config.audio.music_volume);
+        assert_eq!(1.0, config.audio.sound_volume);
+        assert_eq!(vec![SelectionClass::Villagers], config.controls.selection_priority);
+        assert_eq!(10, config.autosave.interval_minutes);
+        assert_eq!(3, config.autosave.slots);
+        assert_eq!(Key::F3, config.keys.key(KeyAction::ToggleMarket));
+        assert_eq!(Key::F4, config.keys.key(KeyAction::ToggleDiplomacy));
+    }
+
+    #[test]
+    fn test_profile_layer() {
+        let mut loader = loader_with_file("[keys]\ntoggle_market = \"F3\"\n");
+        let profile_layer = |text: &str| parse_table(Path::new("profile.toml"), text).unwrap();
+        loader.set_profile_layer(profile_layer("[keys]\ntoggle_market = \"Home\"\n")).unwrap();
+        assert_eq!(Key::Home, loader.config().unwrap().keys.key(KeyAction::ToggleMarket));
+
+        assert!(loader.set_profile_layer(profile_layer("[keys]\nfly = \"Up\"\n")).is_err());
+        assert_eq!(Key::Home, loader.config().unwrap().keys.key(KeyAction::ToggleMarket));
+
+        loader.set_cli_override("keys.toggle_market", parse_value("\"End\""));
+        assert_eq!(Key::End, loader.config().unwrap().keys.key(KeyAction::ToggleMarket));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(loader_with_file("[video]\nwidth = \"wide\"").config().is_err());
+        assert!(loader_with_file("[audio]\nmaster_volume = 2.0").config().is_err());
+        assert!(loader_with_file("[network]\nport = 70000").config().is_err());
+        assert!(loader_with_file("[controls]\nselection_priority = [\"cavalry\"]").config().is_err());
+        assert!(loader_with_file("[autosave]\nslots = 0").config().is_err());
+        assert!(loader_with_file("[keys]\nopen_chat = \"H\"").config().is_err());
+        assert!(loader_with_file("[keys]\nfly = \"Up\"").config().is_err());
+        assert!(parse_table(Path::new("chariot.toml"), "[video\nwidth = 1").is_err());
+    }
+
+    #[test]
+    fn test_structural_changes() {
+        let old = loader_with_file("").config().unwrap();
+        let mut new = loader_with_file("log = \"debug\"\n[video]\nwidth = 640\n").config().unwrap();
+        assert_eq!(vec!["video"], old.structural_changes(&new));
+
+        let mut running = old.clone();
+        running.apply_non_structural(&new);
+        assert_eq!("debug", running.log_filter);
+        assert_eq!(old.video, running.video);
+
+        new.video = old.video.clone();
+        assert!(old.structural_changes(&new).is_empty());
+    }
+
+    #[test]
+    fn test_window_settings_change_while_running() {
+        let old = loader_with_file("").config().unwrap();
+        let new = loader_with_file("[video]\nfullscreen = true\nfullscreen_mode = \"exclusive\"\nscale = 2\n\
+                                    fps_cap = 144\nframe_smoothing = \"snap\"\ntint = false\n\
+                                    day_length = 600\nhardware_cursor = false\n\
+                                    player_colors = \"color_blind\"\nplayer_markers = true\n\
+                                    ui_layout = \"layout.toml\"\n")
+            .config()
+            .unwrap();
+        assert!(old.structural_changes(&new).is_empty());
+
+        let mut running = old.clone();
+        running.apply_non_structural(&new);
+        assert_eq!(new.video, running.video);
+        assert!(loader_with_file("[video]\nfullscreen_mode = \"windowed\"").config().is_err());
+        assert!(loader_with_file("[video]\nframe_smoothing = \"lots\"").config().is_err());
+        assert!(loader_with_file("[video]\nplayer_colors = \"rainbow\"").config().is_err());
+
+        let vsync_off = loader_with_file("[video]\nvsync = false\n").config().unwrap();
+        assert_eq!(vec!["video"], old.structural_changes(&vsync_off));
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(Value::Integer(1280), parse_value("1280"));
+        assert_eq!(Value::Float(0.5), parse_value("0.5"));
+        assert_eq!(Value::String("F4".into()), parse_value("\"F4\""));
+        assert_eq!(Value::String("game/data".into()), parse_value("game/data"));
+    }
+}
@@ -0,0 +1,314 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Crash reports. While a game is running, the commands applied during the most recent ticks and
+//! a periodic snapshot of the world are kept around, and a panic hook writes them out to the crash
+//! directory along with the panic message and the scenario that was being played. Replaying the
+//! logged commands on top of the snapshot should land in the state the game crashed in. The hook
+//! also writes out the game's latest emergency save, if it's been given one, so that the game can
+//! be picked back up on the next launch.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic::{self, PanicInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, TryLockError};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CRASH_DIR: &'static str = "crashes";
+
+/// How many of the most recent ticks of commands get written to a crash report
+pub const COMMAND_HISTORY_TICKS: usize = 600;
+
+/// How often the world snapshot is retaken. This is shorter than the command history
+/// so that the history always reaches back to before the snapshot.
+pub const SNAPSHOT_INTERVAL_TICKS: usize = 300;
+
+lazy_static! {
+    static ref CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext::new(COMMAND_HISTORY_TICKS));
+}
+
+struct TickCommands {
+    tick: usize,
+    commands: Vec<String>,
+}
+
+/// Everything that goes into a crash report besides the panic itself
+pub struct CrashContext {
+    history_ticks: usize,
+    scenario_path: Option<PathBuf>,
+    tick: usize,
+    history: VecDeque<TickCommands>,
+    snapshot_tick: usize,
+    /// Compressed with `snapshot_dictionary`
+    snapshot: Option<Vec<u8>>,
+    snapshot_dictionary: Option<Vec<u8>>,
+    /// The files of a save of the game kept ready to write out, and where they go; the last
+    /// one is the save itself
+    emergency_save: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl CrashContext {
+    pub fn new(history_ticks: usize) -> CrashContext {
+        CrashContext {
+            history_ticks: history_ticks,
+            scenario_path: None,
+            tick: 0,
+            history: VecDeque::new(),
+            snapshot_tick: 0,
+            snapshot: None,
+            snapshot_dictionary: None,
+            emergency_save: Vec::new(),
+        }
+    }
+
+    pub fn set_scenario<P: AsRef<Path>>(&mut self, scenario_path: P) {
+        self.scenario_path = Some(scenario_path.as_ref().to_path_buf());
+    }
+
+    /// Records the commands applied on a tick, and forgets the ones that are too old to keep
+    pub fn record_tick(&mut self, tick: usize, commands: Vec<String>) {
+        self.tick = tick;
+        if !commands.is_empty() {
+            self.history.push_back(TickCommands {
+                tick: tick,
+                commands: commands,
+            });
+        }
+        while self.history.front().map_or(false, |oldest| oldest.tick + self.history_ticks <= tick) {
+            self.history.pop_front();
+        }
+    }
+
+    /// Sets the dictionary the snapshots are compressed with; the report includes it so that the
+    /// snapshot can be decompressed
+    pub fn set_snapshot_dictionary(&mut self, dictionary: Vec<u8>) {
+        self.snapshot_dictionary = Some(dictionary);
+    }
+
+    /// Replaces the world snapshot. It comes already compressed so that crashing doesn't have to.
+    pub fn set_snapshot(&mut self, tick: usize, compressed_snapshot: Vec<u8>) {
+        self.snapshot = Some(compressed_snapshot);
+        self.snapshot_tick = tick;
+    }
+
+    /// Replaces the save to write out on a crash, or clears it once there's nothing to save
+    pub fn set_emergency_save(&mut self, files: Vec<(PathBuf, Vec<u8>)>) {
+        self.emergency_save = files;
+    }
+
+    /// Writes out the emergency save, if there is one, and returns where it went
+    pub fn write_emergency_save(&self) -> io::Result<Option<PathBuf>> {
+        for &(ref path, ref contents) in &self.emergency_save {
+            if let Some(dir) = path.parent() {
+                try!(fs::create_dir_all(dir));
+            }
+            try!(try!(File::create(path)).write_all(contents));
+        }
+        Ok(self.emergency_save.last().map(|&(ref path, _)| path.clone()))
+    }
+
+    /// Writes a crash report into a new directory under `crash_dir` and returns its path
+    pub fn write_report(&self, crash_dir: &Path, panic_description: &str) -> io::Result<PathBuf> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let report_dir = crash_dir.join(format!("crash-{}-tick-{}", seconds, self.tick));
+        try!(fs::create_dir_all(&report_dir));
+
+        let mut report = try!(File::create(report_dir.join("report.txt")));
+        try!(writeln!(report, "Chariot {} crashed", env!("CARGO_PKG_VERSION")));
+        try!(writeln!(report, "{}", panic_description));
+        try!(writeln!(report, "tick: {}", self.tick));
+        match self.scenario_path {
+            Some(ref path) => try!(writeln!(report, "scenario: {}", path.display())),
+            None => try!(writeln!(report, "scenario: none")),
+        }
+        match self.snapshot {
+            Some(_) => try!(writeln!(report, "snapshot tick: {}", self.snapshot_tick)),
+            None => try!(writeln!(report, "snapshot tick: none")),
+        }
+
+        let mut commands = try!(File::create(report_dir.join("commands.txt")));
+        for tick_commands in &self.history {
+            for command in &tick_commands.commands {
+                try!(writeln!(commands, "{}: {}", tick_commands.tick, command));
+            }
+        }
+
+        if let Some(ref snapshot) = self.snapshot {
+            try!(try!(File::create(report_dir.join("snapshot.txt.zst"))).write_all(snapshot));
+        }
+        if let Some(ref dictionary) = self.snapshot_dictionary {
+            try!(try!(File::create(report_dir.join("snapshot.dict"))).write_all(dictionary));
+        }
+
+        // The scenario is copied so that the report stands on its own
+        if let Some(ref path) = self.scenario_path {
+            if let Some(file_name) = path.file_name() {
+                try!(fs::copy(path, report_dir.join(file_name)));
+            }
+        }
+        Ok(report_dir)
+    }
+}
+
+/// Writes a crash report whenever a thread panics, before the default panic output
+pub fn install_panic_hook<P: AsRef<Path>>(crash_dir: P) {
+    let crash_dir = crash_dir.as_ref().to_path_buf();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let description = describe_panic(info);
+        // The context could be locked by the thread that's panicking, so don't wait for it
+        match CRASH_CONTEXT.try_lock() {
+            Ok(context) => write_crash_files(&context, &crash_dir, &description),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                write_crash_files(&poisoned.into_inner(), &crash_dir, &description)
+            }
+            Err(TryLockError::WouldBlock) => {
+                write_crash_files(&CrashContext::new(0), &crash_dir, &description)
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+fn write_crash_files(context: &CrashContext, crash_dir: &Path, description: &str) {
+    match context.write_report(crash_dir, description) {
+        Ok(report_dir) => println!("Crash report written to {}", report_dir.display()),
+        Err(err) => println!("Failed to write crash report: {}", err),
+    }
+    match context.write_emergency_save() {
+        Ok(Some(path)) => println!("Emergency save written to {}", path.display()),
+        Ok(None) => {}
+        Err(err) => println!("Failed to write the emergency save: {}", err),
+    }
+}
+
+pub fn set_scenario<P: AsRef<Path>>(scenario_path: P) {
+    CRASH_CONTEXT.lock().unwrap().set_scenario(scenario_path);
+}
+
+pub fn record_tick(tick: usize, commands: Vec<String>) {
+    CRASH_CONTEXT.lock().unwrap().record_tick(tick, commands);
+}
+
+pub fn set_snapshot_dictionary(dictionary: Vec<u8>) {
+    CRASH_CONTEXT.lock().unwrap().set_snapshot_dictionary(dictionary);
+}
+
+pub fn set_snapshot(tick: usize, compressed_snapshot: Vec<u8>) {
+    CRASH_CONTEXT.lock().unwrap().set_snapshot(tick, compressed_snapshot);
+}
+
+pub fn set_emergency_save(files: Vec<(PathBuf, Vec<u8>)>) {
+    CRASH_CONTEXT.lock().unwrap().set_emergency_save(files);
+}
+
+fn describe_panic(info: &PanicInfo) -> String {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => {
+            match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown panic".into(),
+            }
+        }
+    };
+    let location = info.location()
+        .map(|location| format!("{}:{}", location.file(), location.line()))
+        .unwrap_or("unknown location".into());
+    format!("thread '{}' panicked at '{}', {}",
+            thread::current().name().unwrap_or("<unnamed>"),
+            message,
+            location)
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::{SnapshotDecoder, SnapshotEncoder};
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Read;
+    use super::CrashContext;
+
+    #[test]
+    fn test_command_history_is_trimmed() {
+        let mut context = CrashContext::new(10);
+        context.record_tick(1, vec!["first".into()]);
+        context.record_tick(5, vec!["second".into(), "third".into()]);
+        context.record_tick(8, Vec::new());
+        assert_eq!(2, context.history.len());
+
+        context.record_tick(11, vec!["fourth".into()]);
+        let ticks: Vec<usize> = context.history.iter().map(|commands| commands.tick).collect();
+        assert_eq!(vec![5, 11], ticks);
+    }
+
+    #[test]
+    fn test_write_report() {
+        let crash_dir = env::temp_dir().join(format!("chariot-crash-test-{}", ::std::process::id()));
+        let mut context = CrashContext::new(10);
+        context.record_tick(3, vec!["entity 4: ClearQueue".into()]);
+        let dictionary = b"tick ".to_vec();
+        let snapshot = SnapshotEncoder::new("tick 2\n".into(), &dictionary, 16).unwrap().finish().unwrap();
+        context.set_snapshot_dictionary(dictionary);
+        context.set_snapshot(2, snapshot);
+
+        let report_dir = context.write_report(&crash_dir, "thread 'main' panicked at 'oops'").unwrap();
+
+        let mut report = String::new();
+        File::open(report_dir.join("report.txt")).unwrap().read_to_string(&mut report).unwrap();
+        assert!(report.contains("panicked at 'oops'"));
+        assert!(report.contains("tick: 3"));
+        assert!(report.contains("snapshot tick: 2"));
+
+        let mut commands = String::new();
+        File::open(report_dir.join("commands.txt")).unwrap().read_to_string(&mut commands).unwrap();
+        assert_eq!("3: entity 4: ClearQueue\n", commands);
+
+        let mut dictionary = Vec::new();
+        File::open(report_dir.join("snapshot.dict")).unwrap().read_to_end(&mut dictionary).unwrap();
+        let snapshot_file = File::open(report_dir.join("snapshot.txt.zst")).unwrap();
+        let snapshot = SnapshotDecoder::new(snapshot_file, &dictionary, 16).unwrap().finish().unwrap();
+        assert_eq!("tick 2\n", snapshot);
+
+        fs::remove_dir_all(&crash_dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_emergency_save() {
+        let save_dir = env::temp_dir().join(format!("chariot-emergency-save-test-{}", ::std::process::id()));
+        let mut context = CrashContext::new(10);
+        assert!(context.write_emergency_save().unwrap().is_none());
+
+        context.set_emergency_save(vec![(save_dir.join("emergency.state"), b"state".to_vec()),
+                                        (save_dir.join("emergency.scn"), b"scenario".to_vec())]);
+        let path = context.write_emergency_save().unwrap().unwrap();
+        assert_eq!(save_dir.join("emergency.scn"), path);
+        let mut save = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut save).unwrap();
+        assert_eq!(b"scenario".to_vec(), save);
+        assert!(save_dir.join("emergency.state").exists());
+
+        fs::remove_dir_all(&save_dir).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{ResourceType, UnitClass};
+use ecs::SortedVecStorage;
+use ecs::resource::path_finder::Path;
+use specs;
+use types::Fixed;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GatherPhase {
+    ToResource,
+    Gathering,
+    ToDropSite,
+    /// Handing over what it carried at the drop site
+    Depositing,
+}
+
+#[derive(Clone, Debug)]
+pub struct GatherActionComponent {
+    /// The resource being gathered from; this changes when it runs out and the gatherer moves on
+    pub target: specs::Entity,
+    /// Where the gatherer last dropped off what it carried, if it's still standing
+    pub drop_site: Option<specs::Entity>,
+    /// Only resources of this type are moved on to, so that the gatherer keeps doing the same job
+    pub resource_type: Option<ResourceType>,
+    /// What kind of thing the resource is being gathered from, like shore fish or a berry bush,
+    /// which picks the gatherer's graphics and which resources it moves on to
+    pub resource_class: Option<UnitClass>,
+    /// Whether the gatherer is hunting, which has it move on to other animals and carcasses
+    pub hunting: bool,
+    pub carrying: Fixed,
+    pub phase: GatherPhase,
+    /// How long the gatherer has been depositing for
+    pub deposit_time: Fixed,
+    pub path: Path,
+}
+
+impl specs::Component for GatherActionComponent {
+    type Storage = SortedVecStorage<GatherActionComponent>;
+}
+
+impl GatherActionComponent {
+    pub fn new(target: specs::Entity) -> GatherActionComponent {
+        GatherActionComponent {
+            target: target,
+            drop_site: None,
+            resource_type: None,
+            resource_class: None,
+            hunting: false,
+            carrying: 0.into(),
+            phase: GatherPhase::ToResource,
+            deposit_time: 0.into(),
+            path: Path::new(),
+        }
+    }
+}
@@ -19,6 +19,16 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod attack_ground;
+mod explore;
+mod garrison;
+mod gather;
 mod move_to_position;
+mod restore;
 
+pub use self::attack_ground::AttackGroundActionComponent;
+pub use self::explore::ExploreActionComponent;
+pub use self::garrison::GarrisonActionComponent;
+pub use self::gather::{GatherActionComponent, GatherPhase};
 pub use self::move_to_position::MoveToPositionActionComponent;
+pub use self::restore::RestoreActionComponent;
@@ -19,20 +19,30 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use ecs::SortedVecStorage;
 use ecs::resource::path_finder::Path;
 use specs;
+use types::Fixed;
 
 #[derive(Clone, Debug)]
 pub struct MoveToPositionActionComponent {
     pub path: Path,
+    /// How long the unit has waited for an idle unit in its way to step aside
+    pub waited: Fixed,
+    /// The idle unit it last asked to step aside, so it only asks once
+    pub asked: Option<specs::Entity>,
 }
 
 impl specs::Component for MoveToPositionActionComponent {
-    type Storage = specs::HashMapStorage<MoveToPositionActionComponent>;
+    type Storage = SortedVecStorage<MoveToPositionActionComponent>;
 }
 
 impl MoveToPositionActionComponent {
     pub fn new(path: Path) -> MoveToPositionActionComponent {
-        MoveToPositionActionComponent { path: path }
+        MoveToPositionActionComponent {
+            path: path,
+            waited: 0.into(),
+            asked: None,
+        }
     }
 }
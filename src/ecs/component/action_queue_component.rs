@@ -31,6 +31,8 @@ pub struct ActionQueueComponent {
     actions: Vec<Action>,
     current_action: Option<Action>,
     current_action_done: bool,
+    /// What the unit was doing before it was told to suspend, current action first
+    suspended: Option<Vec<Action>>,
 }
 
 impl specs::Component for ActionQueueComponent {
@@ -43,6 +45,7 @@ impl ActionQueueComponent {
             actions: Vec::new(),
             current_action: None,
             current_action_done: true,
+            suspended: None,
         }
     }
 
@@ -61,11 +64,73 @@ impl ActionQueueComponent {
     /// This should only ever be called by UnitActionSystem
     pub fn clear(&mut self) {
         self.actions.clear();
+        // A new order replaces anything that was set aside too
+        self.suspended = None;
         // Don't overwrite the current_action; it's needed to remove components
         // in the UnitActionSystem. Instead, set done = true.
         self.current_action_done = true;
     }
 
+    /// Sets the current and queued actions aside until `resume`, leaving the queue empty.
+    /// Suspending again before resuming keeps what was set aside the first time.
+    /// This should only ever be called by UnitActionSystem
+    pub fn suspend(&mut self) {
+        if self.suspended.is_none() {
+            let mut suspended = Vec::new();
+            if !self.current_action_done {
+                suspended.extend(self.current_action.iter().cloned());
+            }
+            suspended.extend(self.actions.drain(..));
+            self.suspended = Some(suspended);
+        }
+        self.actions.clear();
+        self.current_action_done = true;
+    }
+
+    /// Puts back what `suspend` set aside, in place of whatever the unit is doing now.
+    /// This should only ever be called by UnitActionSystem
+    pub fn resume(&mut self) {
+        if let Some(suspended) = self.suspended.take() {
+            self.actions = suspended;
+            self.current_action_done = true;
+        }
+    }
+
+    /// A queue that starts on the given actions, like one that was saved with `orders`
+    pub fn with_orders(orders: Vec<Action>) -> ActionQueueComponent {
+        let mut queue = ActionQueueComponent::new();
+        queue.actions = orders;
+        queue
+    }
+
+    /// What the unit has left to do, current action first, for saving. A suspended unit's orders
+    /// are the ones it set aside, since nothing would be left to resume them in a restored game.
+    pub fn orders(&self) -> Vec<Action> {
+        if let Some(ref suspended) = self.suspended {
+            return suspended.clone();
+        }
+        let mut orders = Vec::new();
+        if !self.current_action_done {
+            orders.extend(self.current_action.iter().cloned());
+        }
+        orders.extend(self.actions.iter().cloned());
+        orders
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// True if the unit has nothing to do
+    pub fn is_idle(&self) -> bool {
+        self.current_action.is_none() && self.actions.is_empty()
+    }
+
+    /// The actions waiting behind the current one
+    pub fn actions<'a>(&'a self) -> &'a [Action] {
+        &self.actions
+    }
+
     /// This should only ever be called by UnitActionSystem
     pub fn current_action_done(&self) -> bool {
         self.current_action_done
@@ -86,3 +151,80 @@ impl ActionQueueComponent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use action::MoveToPositionParams;
+    use super::*;
+    use types::{Fixed, Vector3};
+
+    fn move_to(x: i32) -> Action {
+        Action::MoveToPosition(MoveToPositionParams::new(vec![Vector3::new(x.into(), 0.into(), 0.into())]))
+    }
+
+    fn path_x(action: &Option<Action>) -> Option<Fixed> {
+        match *action {
+            Some(Action::MoveToPosition(ref params)) => Some(params.path[0].x),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_suspend_and_resume() {
+        let mut queue = ActionQueueComponent::new();
+        queue.add(move_to(1));
+        queue.add(move_to(2));
+        queue.next_action();
+
+        queue.suspend();
+        assert!(queue.is_suspended());
+        assert!(queue.current_action_done());
+        queue.next_action();
+        assert!(queue.is_idle());
+
+        queue.add(Action::Explore);
+        queue.next_action();
+        // Suspending again doesn't lose what was set aside
+        queue.suspend();
+        queue.resume();
+        assert!(!queue.is_suspended());
+        queue.next_action();
+        assert_eq!(Some(1.into()), path_x(queue.current_action()));
+        queue.mark_current_done();
+        queue.next_action();
+        assert_eq!(Some(2.into()), path_x(queue.current_action()));
+    }
+
+    #[test]
+    fn test_new_orders_drop_suspended_actions() {
+        let mut queue = ActionQueueComponent::new();
+        queue.add(move_to(1));
+        queue.next_action();
+        queue.suspend();
+        queue.clear();
+        assert!(!queue.is_suspended());
+        queue.resume();
+        queue.next_action();
+        assert!(queue.is_idle());
+    }
+
+    #[test]
+    fn test_orders_start_over() {
+        let mut queue = ActionQueueComponent::new();
+        queue.add(move_to(1));
+        queue.add(move_to(2));
+        queue.next_action();
+
+        let mut restored = ActionQueueComponent::with_orders(queue.orders());
+        assert!(restored.current_action_done());
+        restored.next_action();
+        assert_eq!(Some(1.into()), path_x(restored.current_action()));
+
+        // A suspended unit is saved with what it was doing before
+        queue.suspend();
+        queue.add(Action::Explore);
+        queue.next_action();
+        assert_eq!(2, queue.orders().len());
+        assert_eq!(Some(1.into()), path_x(&queue.orders().into_iter().next()));
+    }
+}
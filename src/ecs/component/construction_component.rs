@@ -0,0 +1,69 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use specs;
+use types::Fixed;
+
+/// Marks a building that's still a foundation: villagers have to build it up before it does
+/// anything. It starts with a single hit point and gains the rest in step with the work done.
+#[derive(Clone, Debug)]
+pub struct ConstructionComponent {
+    /// How much of the building is done, from 0 to 1
+    pub progress: Fixed,
+}
+
+impl specs::Component for ConstructionComponent {
+    type Storage = specs::HashMapStorage<ConstructionComponent>;
+}
+
+impl ConstructionComponent {
+    pub fn new() -> ConstructionComponent {
+        ConstructionComponent { progress: 0.into() }
+    }
+
+    /// Adds the work done towards the building, and returns whether that finished it
+    pub fn advance(&mut self, amount: Fixed) -> bool {
+        self.progress += amount;
+        if self.progress >= 1.into() {
+            self.progress = 1.into();
+        }
+        self.is_finished()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress >= 1.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstructionComponent;
+    use types::Fixed;
+
+    #[test]
+    fn test_advance() {
+        let mut construction = ConstructionComponent::new();
+        assert!(!construction.advance(Fixed::from(1) / 4.into()));
+        assert!(!construction.advance(Fixed::from(1) / 2.into()));
+        assert!(construction.advance(Fixed::from(1) / 2.into()));
+        assert_eq!(Fixed::from(1), construction.progress);
+    }
+}
@@ -0,0 +1,146 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use specs;
+use types::Fixed;
+
+/// Time between a unit's attacks, whether it's fighting, hunting, or firing at the ground
+pub const ATTACK_COOLDOWN: &'static str = "attack";
+
+/// Every cooldown there is, so that a saved one can be started again by its name
+pub const COOLDOWNS: [&'static str; 1] = [ATTACK_COOLDOWN];
+
+/// A named timer counting down to when the unit can do something again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cooldown {
+    pub name: &'static str,
+    /// Seconds left before the cooldown is over
+    pub remaining: Fixed,
+    /// How many seconds the cooldown lasted when it was started
+    pub duration: Fixed,
+}
+
+impl Cooldown {
+    /// How far through the cooldown the unit is, from zero when it was started to one when it's over
+    pub fn progress(&self) -> Fixed {
+        if self.duration > 0.into() {
+            Fixed::from(1) - self.remaining / self.duration
+        } else {
+            1.into()
+        }
+    }
+}
+
+/// Timers for the things a unit has to wait to do again, like attacking or converting, so that
+/// the systems using them don't each keep their own. The CooldownSystem counts them down every
+/// update, and drops them once they're over.
+#[derive(Clone, Debug)]
+pub struct CooldownComponent {
+    cooldowns: Vec<Cooldown>,
+}
+
+impl specs::Component for CooldownComponent {
+    type Storage = specs::VecStorage<CooldownComponent>;
+}
+
+impl CooldownComponent {
+    pub fn new() -> CooldownComponent {
+        CooldownComponent { cooldowns: Vec::new() }
+    }
+
+    /// Cooldowns that were saved partway through, like the ones from `cooldowns`
+    pub fn with_cooldowns(cooldowns: Vec<Cooldown>) -> CooldownComponent {
+        CooldownComponent { cooldowns: cooldowns }
+    }
+
+    /// Starts the named cooldown over from the given number of seconds
+    pub fn start(&mut self, name: &'static str, seconds: Fixed) {
+        self.cooldowns.retain(|cooldown| cooldown.name != name);
+        if seconds > 0.into() {
+            self.cooldowns.push(Cooldown {
+                name: name,
+                remaining: seconds,
+                duration: seconds,
+            });
+        }
+    }
+
+    /// Seconds left on the named cooldown, or zero if it isn't running
+    pub fn remaining(&self, name: &str) -> Fixed {
+        self.cooldowns
+            .iter()
+            .find(|cooldown| cooldown.name == name)
+            .map(|cooldown| cooldown.remaining)
+            .unwrap_or(0.into())
+    }
+
+    pub fn is_ready(&self, name: &str) -> bool {
+        self.remaining(name) <= 0.into()
+    }
+
+    /// The cooldowns that are still running, for showing how long is left on them
+    pub fn cooldowns<'a>(&'a self) -> &'a [Cooldown] {
+        &self.cooldowns
+    }
+
+    pub fn tick(&mut self, time_step: Fixed) {
+        for cooldown in &mut self.cooldowns {
+            cooldown.remaining -= time_step;
+        }
+        self.cooldowns.retain(|cooldown| cooldown.remaining > 0.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Fixed;
+
+    #[test]
+    fn test_cooldowns_run_out() {
+        let mut cooldowns = CooldownComponent::new();
+        assert!(cooldowns.is_ready(ATTACK_COOLDOWN));
+
+        cooldowns.start(ATTACK_COOLDOWN, 2.into());
+        cooldowns.start("convert", 1.into());
+        assert!(!cooldowns.is_ready(ATTACK_COOLDOWN));
+        assert_eq!(Fixed::from(2), cooldowns.remaining(ATTACK_COOLDOWN));
+
+        cooldowns.tick(fixed_const!(1.5));
+        assert_eq!(fixed_const!(0.5), cooldowns.remaining(ATTACK_COOLDOWN));
+        assert_eq!(fixed_const!(0.75), cooldowns.cooldowns()[0].progress());
+        assert!(cooldowns.is_ready("convert"));
+        assert_eq!(1, cooldowns.cooldowns().len());
+
+        cooldowns.tick(fixed_const!(0.5));
+        assert!(cooldowns.is_ready(ATTACK_COOLDOWN));
+        assert!(cooldowns.cooldowns().is_empty());
+    }
+
+    #[test]
+    fn test_restarting_a_cooldown() {
+        let mut cooldowns = CooldownComponent::new();
+        cooldowns.start(ATTACK_COOLDOWN, 1.into());
+        cooldowns.start(ATTACK_COOLDOWN, 3.into());
+        assert_eq!(1, cooldowns.cooldowns().len());
+        assert_eq!(Fixed::from(3), cooldowns.remaining(ATTACK_COOLDOWN));
+    }
+}
@@ -19,6 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use ecs::SortedVecStorage;
 use identifier::{PlayerColorId, SlpFileId};
 use resource::DrsKey;
 use specs;
@@ -34,7 +35,7 @@ pub struct DecalComponent {
 }
 
 impl specs::Component for DecalComponent {
-    type Storage = specs::HashMapStorage<DecalComponent>;
+    type Storage = SortedVecStorage<DecalComponent>;
 }
 
 impl DecalComponent {
@@ -0,0 +1,62 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use specs;
+use types::Fixed;
+
+#[derive(Clone, Debug)]
+pub struct HitPointsComponent {
+    pub hit_points: Fixed,
+}
+
+impl specs::Component for HitPointsComponent {
+    type Storage = specs::VecStorage<HitPointsComponent>;
+}
+
+impl HitPointsComponent {
+    pub fn new(hit_points: Fixed) -> HitPointsComponent {
+        HitPointsComponent { hit_points: hit_points }
+    }
+
+    /// Takes the damage off, and returns whether that destroyed the unit
+    pub fn damage(&mut self, amount: Fixed) -> bool {
+        self.hit_points -= amount;
+        self.is_destroyed()
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.hit_points <= 0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HitPointsComponent;
+
+    #[test]
+    fn test_damage() {
+        let mut hit_points = HitPointsComponent::new(25.into());
+        assert!(!hit_points.damage(10.into()));
+        assert!(!hit_points.damage(14.into()));
+        assert!(hit_points.damage(1.into()));
+        assert!(hit_points.is_destroyed());
+    }
+}
@@ -22,10 +22,20 @@
 mod action;
 mod action_queue_component;
 mod camera_component;
+mod construction_component;
+mod cooldown_component;
 mod decal_component;
+mod decomposition_component;
+mod garrisoned_component;
 mod graphic_component;
+mod hit_points_component;
 mod on_screen_component;
+mod production_queue_component;
+mod projectile_component;
+mod provoked_component;
+mod resource_node_component;
 mod selected_unit_component;
+mod sorted_vec_storage;
 mod transform_component;
 mod unit_component;
 mod velocity_component;
@@ -33,10 +43,21 @@ mod velocity_component;
 pub use self::action::*;
 pub use self::action_queue_component::ActionQueueComponent;
 pub use self::camera_component::CameraComponent;
+pub use self::construction_component::ConstructionComponent;
+pub use self::cooldown_component::{ATTACK_COOLDOWN, COOLDOWNS, Cooldown, CooldownComponent};
 pub use self::decal_component::DecalComponent;
+pub use self::decomposition_component::DecompositionComponent;
+pub use self::garrisoned_component::GarrisonedComponent;
 pub use self::graphic_component::GraphicComponent;
+pub use self::hit_points_component::HitPointsComponent;
 pub use self::on_screen_component::OnScreenComponent;
+pub use self::production_queue_component::{MAX_QUEUED_UNITS, MAX_REPEATED_UNITS,
+                                           ProductionQueueComponent, SavedProductionQueue};
+pub use self::projectile_component::ProjectileComponent;
+pub use self::provoked_component::ProvokedComponent;
+pub use self::resource_node_component::ResourceNodeComponent;
 pub use self::selected_unit_component::SelectedUnitComponent;
+pub use self::sorted_vec_storage::SortedVecStorage;
 pub use self::transform_component::TransformComponent;
 pub use self::unit_component::UnitComponent;
 pub use self::velocity_component::VelocityComponent;
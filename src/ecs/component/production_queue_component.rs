@@ -0,0 +1,291 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::RallyPoint;
+use identifier::{ResearchId, UnitId};
+use specs;
+use std::collections::VecDeque;
+use types::Fixed;
+
+/// Number of units a building can have waiting in its production queue
+pub const MAX_QUEUED_UNITS: usize = 5;
+
+/// How many units repeat production trains before switching itself back off,
+/// so that a forgotten toggle can't quietly drain the stockpile
+pub const MAX_REPEATED_UNITS: u32 = 10;
+
+/// The units a building is training, in order, and the technology it's researching. The
+/// resources for both are paid when they're queued, so everything in here has already been
+/// paid for. A building researches one technology at a time, and trains nothing until it's done.
+#[derive(Clone, Debug)]
+pub struct ProductionQueueComponent {
+    queue: VecDeque<UnitId>,
+    /// Seconds spent training the unit at the front of the queue
+    progress: Fixed,
+    research: Option<ResearchId>,
+    research_progress: Fixed,
+    repeat: bool,
+    last_trained: Option<UnitId>,
+    repeated_count: u32,
+    pub rally_point: Option<RallyPoint>,
+}
+
+impl specs::Component for ProductionQueueComponent {
+    type Storage = specs::HashMapStorage<ProductionQueueComponent>;
+}
+
+/// Everything about a production queue but its rally point, which saves keep apart since it can
+/// point at another entity
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavedProductionQueue {
+    pub queue: Vec<UnitId>,
+    pub progress: Fixed,
+    pub research: Option<ResearchId>,
+    pub research_progress: Fixed,
+    pub repeat: bool,
+    pub last_trained: Option<UnitId>,
+    pub repeated_count: u32,
+}
+
+impl ProductionQueueComponent {
+    pub fn new() -> ProductionQueueComponent {
+        ProductionQueueComponent {
+            queue: VecDeque::new(),
+            progress: 0.into(),
+            research: None,
+            research_progress: 0.into(),
+            repeat: false,
+            last_trained: None,
+            repeated_count: 0,
+            rally_point: None,
+        }
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= MAX_QUEUED_UNITS
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Adds a unit to the back of the queue; returns false if the queue is full
+    pub fn queue_unit(&mut self, unit_id: UnitId) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.queue.push_back(unit_id);
+        true
+    }
+
+    /// The unit currently being trained
+    pub fn current(&self) -> Option<UnitId> {
+        self.queue.front().cloned()
+    }
+
+    #[inline]
+    pub fn progress(&self) -> Fixed {
+        self.progress
+    }
+
+    /// Trains the current unit for another time step. Returns the unit once it has been
+    /// trained for `train_time` seconds, after removing it from the queue.
+    pub fn advance(&mut self, time_step: Fixed, train_time: Fixed) -> Option<UnitId> {
+        let unit_id = match self.current() {
+            Some(unit_id) => unit_id,
+            None => return None,
+        };
+        self.progress = self.progress + time_step;
+        if self.progress < train_time {
+            return None;
+        }
+        self.queue.pop_front();
+        self.progress = 0.into();
+        self.last_trained = Some(unit_id);
+        Some(unit_id)
+    }
+
+    /// Starts researching the technology; returns false if the building already is researching one
+    pub fn queue_research(&mut self, research_id: ResearchId) -> bool {
+        if self.research.is_some() {
+            return false;
+        }
+        self.research = Some(research_id);
+        true
+    }
+
+    /// The technology currently being researched
+    #[inline]
+    pub fn current_research(&self) -> Option<ResearchId> {
+        self.research
+    }
+
+    #[inline]
+    pub fn research_progress(&self) -> Fixed {
+        self.research_progress
+    }
+
+    /// Researches the current technology for another time step. Returns it once it has been
+    /// researched for `research_time` seconds, which frees the building up again.
+    pub fn advance_research(&mut self, time_step: Fixed, research_time: Fixed) -> Option<ResearchId> {
+        let research_id = match self.research {
+            Some(research_id) => research_id,
+            None => return None,
+        };
+        self.research_progress = self.research_progress + time_step;
+        if self.research_progress < research_time {
+            return None;
+        }
+        self.research = None;
+        self.research_progress = 0.into();
+        Some(research_id)
+    }
+
+    #[inline]
+    pub fn repeat(&self) -> bool {
+        self.repeat
+    }
+
+    /// Turns repeat production on or off; either way, the cap starts counting from zero again
+    pub fn toggle_repeat(&mut self) {
+        self.repeat = !self.repeat;
+        self.repeated_count = 0;
+    }
+
+    /// The unit to requeue, if repeat production is on and the queue has run dry
+    pub fn repeat_candidate(&self) -> Option<UnitId> {
+        if self.repeat && self.queue.is_empty() {
+            self.last_trained
+        } else {
+            None
+        }
+    }
+
+    /// Queues the repeat candidate. Returns false once that used up the last of
+    /// `MAX_REPEATED_UNITS`, at which point repeat production has turned itself off.
+    pub fn queue_repeat(&mut self, unit_id: UnitId) -> bool {
+        self.queue_unit(unit_id);
+        self.repeated_count += 1;
+        if self.repeated_count >= MAX_REPEATED_UNITS {
+            self.toggle_repeat();
+            return false;
+        }
+        true
+    }
+
+    /// Units that repeat production will still queue before it turns itself off
+    pub fn repeats_left(&self) -> u32 {
+        if self.repeat {
+            MAX_REPEATED_UNITS - self.repeated_count
+        } else {
+            0
+        }
+    }
+
+    pub fn to_saved(&self) -> SavedProductionQueue {
+        SavedProductionQueue {
+            queue: self.queue.iter().cloned().collect(),
+            progress: self.progress,
+            research: self.research,
+            research_progress: self.research_progress,
+            repeat: self.repeat,
+            last_trained: self.last_trained,
+            repeated_count: self.repeated_count,
+        }
+    }
+
+    /// Puts a queue back the way it was saved, with no rally point
+    pub fn from_saved(saved: &SavedProductionQueue) -> ProductionQueueComponent {
+        ProductionQueueComponent {
+            queue: saved.queue.iter().cloned().collect(),
+            progress: saved.progress,
+            research: saved.research,
+            research_progress: saved.research_progress,
+            repeat: saved.repeat,
+            last_trained: saved.last_trained,
+            repeated_count: saved.repeated_count,
+            rally_point: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Fixed;
+
+    #[test]
+    fn test_advance() {
+        let mut queue = ProductionQueueComponent::new();
+        assert_eq!(None, queue.advance(1.into(), 2.into()));
+
+        assert!(queue.queue_unit(83.into()));
+        assert!(queue.queue_unit(93.into()));
+        assert_eq!(None, queue.advance(1.into(), 2.into()));
+        assert_eq!(Fixed::from(1), queue.progress());
+        assert_eq!(Some(83.into()), queue.advance(1.into(), 2.into()));
+        assert_eq!(Fixed::from(0), queue.progress());
+        assert_eq!(Some(93.into()), queue.current());
+
+        for _ in 1..MAX_QUEUED_UNITS {
+            assert!(queue.queue_unit(83.into()));
+        }
+        assert!(!queue.queue_unit(83.into()));
+    }
+
+    #[test]
+    fn test_advance_research() {
+        let mut queue = ProductionQueueComponent::new();
+        assert_eq!(None, queue.advance_research(1.into(), 2.into()));
+
+        assert!(queue.queue_research(40.into()));
+        assert!(!queue.queue_research(41.into()));
+        assert_eq!(None, queue.advance_research(1.into(), 2.into()));
+        assert_eq!(Fixed::from(1), queue.research_progress());
+        assert_eq!(Some(40.into()), queue.advance_research(1.into(), 2.into()));
+        assert_eq!(None, queue.current_research());
+        assert!(queue.queue_research(41.into()));
+    }
+
+    #[test]
+    fn test_repeat_is_capped() {
+        let mut queue = ProductionQueueComponent::new();
+        queue.toggle_repeat();
+        assert_eq!(None, queue.repeat_candidate());
+
+        queue.queue_unit(83.into());
+        queue.advance(1.into(), 1.into());
+        for repeated in 1..MAX_REPEATED_UNITS {
+            assert_eq!(Some(83.into()), queue.repeat_candidate());
+            assert!(queue.queue_repeat(83.into()));
+            assert_eq!(MAX_REPEATED_UNITS - repeated, queue.repeats_left());
+            queue.advance(1.into(), 1.into());
+        }
+        assert!(!queue.queue_repeat(83.into()));
+        assert!(!queue.repeat());
+        assert_eq!(0, queue.repeats_left());
+
+        queue.advance(1.into(), 1.into());
+        assert_eq!(None, queue.repeat_candidate());
+    }
+}
@@ -0,0 +1,44 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::SortedVecStorage;
+use identifier::PlayerId;
+use specs;
+use types::{Fixed, Vector3};
+
+/// A shot in flight, like a catapult's stone. It flies straight at where it was aimed and
+/// hurts everything within its blast radius of there when it lands.
+#[derive(Clone, Debug)]
+pub struct ProjectileComponent {
+    /// Who fired it
+    pub player_id: PlayerId,
+    /// Where it was fired from, for the elevation it was fired from
+    pub origin: Vector3,
+    pub target: Vector3,
+    pub speed: Fixed,
+    /// The attack of the unit that fired it, as (armor class, amount) pairs
+    pub attacks: Vec<(i16, i16)>,
+    pub blast_radius: Fixed,
+}
+
+impl specs::Component for ProjectileComponent {
+    type Storage = SortedVecStorage<ProjectileComponent>;
+}
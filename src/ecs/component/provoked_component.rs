@@ -0,0 +1,46 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::SortedVecStorage;
+use specs;
+
+/// An animal that's been attacked. Prey runs from whatever attacked it, and animals that can
+/// fight, like elephants, go after it instead. Predators hunting a villager are given one too,
+/// with the villager as the attacker, so they strike it the same way.
+#[derive(Clone, Debug)]
+pub struct ProvokedComponent {
+    pub attacker: specs::Entity,
+    /// Whether prey has already run from the latest attack
+    pub fled: bool,
+}
+
+impl specs::Component for ProvokedComponent {
+    type Storage = SortedVecStorage<ProvokedComponent>;
+}
+
+impl ProvokedComponent {
+    pub fn new(attacker: specs::Entity) -> ProvokedComponent {
+        ProvokedComponent {
+            attacker: attacker,
+            fled: false,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use specs;
+use types::Fixed;
+
+/// What's left in something villagers can gather from, like a tree, a berry bush, or a gold mine
+#[derive(Clone, Debug)]
+pub struct ResourceNodeComponent {
+    pub resource_type: ResourceType,
+    pub amount: Fixed,
+    /// How much is lost a second whether it's gathered or not, like the meat of a carcass
+    /// going off; zero for resources that keep
+    pub decay_rate: Fixed,
+}
+
+impl specs::Component for ResourceNodeComponent {
+    type Storage = specs::VecStorage<ResourceNodeComponent>;
+}
+
+impl ResourceNodeComponent {
+    pub fn new(resource_type: ResourceType, amount: Fixed) -> ResourceNodeComponent {
+        ResourceNodeComponent {
+            resource_type: resource_type,
+            amount: amount,
+            decay_rate: 0.into(),
+        }
+    }
+
+    pub fn decaying(mut self, decay_rate: Fixed) -> ResourceNodeComponent {
+        self.decay_rate = decay_rate;
+        self
+    }
+
+    /// Carcasses are the resources that go off
+    pub fn is_carcass(&self) -> bool {
+        self.decay_rate > 0.into()
+    }
+
+    /// Takes off what's lost over the time
+    pub fn decay(&mut self, seconds: Fixed) {
+        let lost = self.decay_rate * seconds;
+        self.take(lost);
+    }
+
+    /// Takes up to the given amount out of the node, and returns how much was taken
+    pub fn take(&mut self, amount: Fixed) -> Fixed {
+        let taken = if amount < self.amount { amount } else { self.amount };
+        self.amount -= taken;
+        taken
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.amount <= 0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use super::ResourceNodeComponent;
+    use types::Fixed;
+
+    #[test]
+    fn test_take() {
+        let mut node = ResourceNodeComponent::new(ResourceType::Wood, 10.into());
+        assert_eq!(Fixed::from(4), node.take(4.into()));
+        assert!(!node.is_depleted());
+        assert_eq!(Fixed::from(6), node.take(8.into()));
+        assert!(node.is_depleted());
+        assert_eq!(Fixed::from(0), node.take(1.into()));
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut node = ResourceNodeComponent::new(ResourceType::Food, 10.into()).decaying(2.into());
+        assert!(node.is_carcass());
+        node.decay(3.into());
+        assert_eq!(Fixed::from(4), node.amount);
+        node.decay(3.into());
+        assert!(node.is_depleted());
+        assert!(!ResourceNodeComponent::new(ResourceType::Wood, 10.into()).is_carcass());
+    }
+}
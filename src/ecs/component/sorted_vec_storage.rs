@@ -0,0 +1,143 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use specs::{Index, UnprotectedStorage};
+
+/// Keeps components packed in a vector sorted by entity index. Joins visit entities in index
+/// order, so iterating reads the components front to back without skipping over the entities
+/// that don't have one, unlike a VecStorage, or jumping around the heap, unlike a
+/// HashMapStorage. Inserting and removing shift the components after them, which is cheap
+/// for components that only some entities have and that come and go occasionally.
+pub struct SortedVecStorage<T> {
+    components: Vec<T>,
+    /// The entity index of each component, in the same order
+    indices: Vec<Index>,
+    /// Where each entity's component is in `components`, by entity index
+    slots: Vec<usize>,
+}
+
+impl<T> SortedVecStorage<T> {
+    fn renumber_from(&mut self, position: usize) {
+        for slot in position..self.indices.len() {
+            self.slots[self.indices[slot] as usize] = slot;
+        }
+    }
+}
+
+impl<T> UnprotectedStorage<T> for SortedVecStorage<T> {
+    fn new() -> Self {
+        SortedVecStorage {
+            components: Vec::new(),
+            indices: Vec::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    unsafe fn clean<F>(&mut self, _has: F)
+        where F: Fn(Index) -> bool
+    {
+        // Only holds live components, so there's nothing that might not have been initialized
+        self.components.clear();
+        self.indices.clear();
+        self.slots.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &T {
+        &self.components[self.slots[id as usize]]
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut T {
+        let slot = self.slots[id as usize];
+        &mut self.components[slot]
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: T) {
+        let position = match self.indices.binary_search(&id) {
+            Ok(position) => {
+                self.components[position] = value;
+                return;
+            }
+            Err(position) => position,
+        };
+        if self.slots.len() <= id as usize {
+            self.slots.resize(id as usize + 1, 0);
+        }
+        self.indices.insert(position, id);
+        self.components.insert(position, value);
+        self.renumber_from(position);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> T {
+        let position = self.slots[id as usize];
+        self.indices.remove(position);
+        let value = self.components.remove(position);
+        self.renumber_from(position);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::UnprotectedStorage;
+    use super::SortedVecStorage;
+
+    #[test]
+    fn test_components_stay_sorted_by_entity() {
+        let mut storage = SortedVecStorage::<&'static str>::new();
+        unsafe {
+            storage.insert(5, "five");
+            storage.insert(1, "one");
+            storage.insert(3, "three");
+            assert_eq!("one", *storage.get(1));
+            assert_eq!("three", *storage.get(3));
+            assert_eq!("five", *storage.get(5));
+        }
+        assert_eq!(vec!["one", "three", "five"], storage.components);
+    }
+
+    #[test]
+    fn test_remove_keeps_the_rest_reachable() {
+        let mut storage = SortedVecStorage::<u32>::new();
+        unsafe {
+            for id in 0..6 {
+                storage.insert(id, id * 10);
+            }
+            assert_eq!(20, storage.remove(2));
+            *storage.get_mut(4) += 1;
+            assert_eq!(0, *storage.get(0));
+            assert_eq!(30, *storage.get(3));
+            assert_eq!(41, *storage.get(4));
+            assert_eq!(50, *storage.get(5));
+        }
+        assert_eq!(vec![0, 1, 3, 4, 5], storage.indices);
+    }
+
+    #[test]
+    fn test_insert_replaces_an_existing_component() {
+        let mut storage = SortedVecStorage::<u32>::new();
+        unsafe {
+            storage.insert(2, 1);
+            storage.insert(2, 7);
+            assert_eq!(7, *storage.get(2));
+        }
+        assert_eq!(1, storage.components.len());
+    }
+}
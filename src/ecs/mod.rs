@@ -22,8 +22,19 @@
 mod component;
 pub mod resource;
 pub mod render_system;
+mod save;
+mod snapshot;
+mod snapshot_codec;
 pub mod system;
 mod world;
+mod world_query;
 
 pub use self::component::*;
-pub use self::world::{SystemGroup, WorldPlanner, create_world_planner};
+pub use self::save::{SaveState, SavedAction, SavedPlayer, SavedRallyPoint, SavedUnit, restore_save_state,
+                     save_to_scenario};
+pub use self::snapshot::describe_world;
+pub use self::snapshot_codec::{SNAPSHOT_CHUNK_SIZE, SnapshotDecoder, SnapshotEncoder,
+                               train_snapshot_dictionary};
+pub use self::world::{SystemGroup, WorldPlanner, create_headless_world_planner, create_presentation_lane,
+                      create_world_planner, reload_scenario_units, spawn_queued_units};
+pub use self::world_query::{Comparison, QueryContext, WorldQuery};
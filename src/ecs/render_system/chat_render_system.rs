@@ -0,0 +1,78 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{Chat, MAX_MESSAGE_LENGTH, RenderCommands, UiLayout, UiPanel, Viewport};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use ui;
+
+const CHAT_LAYER: u16 = 2000;
+const BOX_HEIGHT: i32 = 16;
+const PADDING: i32 = 3;
+
+/// Draws the chat box where the layout puts it while the local player is typing into it, in the
+/// same text as the widgets'
+pub struct ChatRenderSystem;
+
+impl ChatRenderSystem {
+    pub fn new() -> ChatRenderSystem {
+        ChatRenderSystem
+    }
+}
+
+impl RenderSystem for ChatRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(chat: Chat),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        if !chat.open {
+            return;
+        }
+
+        let top_left = viewport.lerped_top_left(lerp);
+        let box_width = PADDING * 2 + ui::text_width(MAX_MESSAGE_LENGTH);
+        let bounds = ui_layout.place(UiPanel::ChatBox, &Vector2::new(box_width, BOX_HEIGHT), &viewport.size);
+        let x = top_left.x + bounds.x;
+        let y = top_left.y + bounds.y;
+
+        render_commands.push(RenderCommand::new_filled_rect(CHAT_LAYER,
+                                                            0,
+                                                            Color::rgb(20, 20, 20),
+                                                            Rect::of(x, y, box_width, BOX_HEIGHT)));
+        render_commands.push(RenderCommand::new_rect(CHAT_LAYER,
+                                                     1,
+                                                     Color::rgb(200, 200, 200),
+                                                     Rect::of(x, y, box_width, BOX_HEIGHT)));
+        ui::render_text(&mut *render_commands,
+                        CHAT_LAYER,
+                        Color::rgb(255, 255, 255),
+                        chat.draft(),
+                        &Vector2::new(x + PADDING, y + PADDING),
+                        BOX_HEIGHT - PADDING * 2);
+    }
+}
@@ -0,0 +1,244 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::*;
+use input::InputActions;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+
+const PANEL_LAYER: u16 = 2000;
+const BUTTON_BORDER: i32 = 2;
+const PANEL_PADDING: i32 = 4;
+const CROSSHAIR_SIZE: i32 = 10;
+const RETICLE_SIZE: i32 = 12;
+
+/// Draws the command panel's buttons in their slots, with an icon for each order, in the UI theme
+/// over a panel background, unless it's been collapsed, and lights up the one that's targeting.
+/// While one is, a reticle follows the pointer in place of the cursor.
+pub struct CommandPanelRenderSystem;
+
+impl CommandPanelRenderSystem {
+    pub fn new() -> CommandPanelRenderSystem {
+        CommandPanelRenderSystem
+    }
+}
+
+impl RenderSystem for CommandPanelRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(command_panel: CommandPanel),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            resource(town_bell: TownBell),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let top_left = viewport.lerped_top_left(lerp);
+        if command_panel.is_shown() {
+            let bounds = command_panel.bounds(&ui_layout, &viewport.size);
+            let bounds = Rect::of(bounds.x + top_left.x - PANEL_PADDING,
+                              bounds.y + top_left.y - PANEL_PADDING,
+                              bounds.w + PANEL_PADDING * 2,
+                              bounds.h + PANEL_PADDING * 2);
+            render_commands.push(ui_layout.panel_command(PANEL_LAYER, -1, bounds, Color::rgb(24, 20, 16)));
+        }
+        let buttons = if command_panel.collapsed {
+            Vec::new()
+        } else {
+            command_panel.buttons()
+        };
+        for (slot, button) in buttons {
+            let mut rect = command_panel.button(&ui_layout, &viewport.size, slot);
+            rect.translate(top_left.x, top_left.y);
+            let lit = command_panel.targeting == Some(button) ||
+                      (button == CommandButton::TownBell && town_bell.is_ringing(players.local_player_id()));
+            render_commands.push(ui_layout.button_command(PANEL_LAYER, 0, rect, lit, Color::rgb(40, 32, 24)));
+
+            // The themed buttons have borders of their own, but still need to show which is lit
+            if lit || ui_layout.theme.button.is_none() {
+                let border = if lit {
+                    Color::rgb(255, 230, 40)
+                } else {
+                    Color::rgb(90, 72, 54)
+                };
+                for inset in 0..BUTTON_BORDER {
+                    let outline =
+                        Rect::of(rect.x + inset, rect.y + inset, rect.w - inset * 2, rect.h - inset * 2);
+                    render_commands.push(RenderCommand::new_rect(PANEL_LAYER, 1, border, outline));
+                }
+            }
+
+            let center = Vector2::new(rect.x + rect.w / 2, rect.y + rect.h / 2);
+            match button {
+                CommandButton::AttackGround => {
+                    render_crosshair(&mut *render_commands, Color::rgb(220, 60, 60), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::Explore => {
+                    render_compass(&mut *render_commands, Color::rgb(200, 120, 220), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::TownBell => {
+                    render_bell(&mut *render_commands, Color::rgb(230, 190, 60), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::Train(_) => {
+                    render_figure(&mut *render_commands, Color::rgb(120, 200, 120), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::Research(_) => {
+                    render_page(&mut *render_commands, Color::rgb(120, 170, 230), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::BuildMenu | CommandButton::Build(_) => {
+                    render_house(&mut *render_commands, Color::rgb(210, 160, 100), center, CROSSHAIR_SIZE)
+                }
+                CommandButton::Back => {
+                    render_arrow(&mut *render_commands, Color::rgb(200, 200, 200), center, CROSSHAIR_SIZE)
+                }
+            }
+        }
+
+        if command_panel.targeting.is_some() {
+            let pointer = input_actions.pointer + top_left;
+            render_crosshair(&mut *render_commands, Color::rgb(255, 60, 60), pointer, RETICLE_SIZE);
+        }
+    }
+}
+
+/// A head over a body, with arms and legs
+fn render_figure(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let half = size / 2;
+    let line = |render_commands: &mut RenderCommands, from: Vector2<i32>, to: Vector2<i32>| {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER, 2, color, from, to));
+    };
+    render_commands.push(RenderCommand::new_filled_rect(PANEL_LAYER,
+                                                        2,
+                                                        color,
+                                                        Rect::of(center.x - 2, center.y - size, 5, 5)));
+    let (neck, hips) = (center + Vector2::new(0, -half), center + Vector2::new(0, half / 2));
+    line(render_commands, neck, hips);
+    line(render_commands, center + Vector2::new(-half, 0), center + Vector2::new(half, 0));
+    line(render_commands, hips, center + Vector2::new(-half, size));
+    line(render_commands, hips, center + Vector2::new(half, size));
+}
+
+/// A page with lines of writing on it
+fn render_page(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let half = size / 2;
+    let outline = Rect::of(center.x - half - 2, center.y - size, size + 4, size * 2);
+    render_commands.push(RenderCommand::new_rect(PANEL_LAYER, 2, color, outline));
+    for row in 0..4 {
+        let y = center.y - half + row * half / 2 + row;
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER,
+                                                     2,
+                                                     color,
+                                                     Vector2::new(center.x - half + 1, y),
+                                                     Vector2::new(center.x + half - 1, y)));
+    }
+}
+
+/// A box with a pointed roof
+fn render_house(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let half = size / 2;
+    let points = [center + Vector2::new(0, -size),
+                  center + Vector2::new(size, -half / 2),
+                  center + Vector2::new(size, size),
+                  center + Vector2::new(-size, size),
+                  center + Vector2::new(-size, -half / 2)];
+    for index in 0..points.len() {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER,
+                                                     2,
+                                                     color,
+                                                     points[index],
+                                                     points[(index + 1) % points.len()]));
+    }
+    render_commands.push(RenderCommand::new_filled_rect(PANEL_LAYER,
+                                                        2,
+                                                        color,
+                                                        Rect::of(center.x - 2, center.y + half, 5, half)));
+}
+
+/// An arrow pointing left
+fn render_arrow(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let half = size / 2;
+    let line = |render_commands: &mut RenderCommands, from: Vector2<i32>, to: Vector2<i32>| {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER, 2, color, from, to));
+    };
+    let tip = center - Vector2::new(size, 0);
+    line(render_commands, tip, center + Vector2::new(size, 0));
+    line(render_commands, tip, tip + Vector2::new(half, -half));
+    line(render_commands, tip, tip + Vector2::new(half, half));
+}
+
+/// A bell with a clapper hanging under it
+fn render_bell(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let half = size / 2;
+    let points = [center + Vector2::new(-half / 2, -size),
+                  center + Vector2::new(half / 2, -size),
+                  center + Vector2::new(size, half),
+                  center + Vector2::new(-size, half)];
+    for index in 0..points.len() {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER,
+                                                     2,
+                                                     color,
+                                                     points[index],
+                                                     points[(index + 1) % points.len()]));
+    }
+    render_commands.push(RenderCommand::new_filled_rect(PANEL_LAYER,
+                                                        2,
+                                                        color,
+                                                        Rect::of(center.x - 1, center.y + half, 3, 3)));
+}
+
+/// A diamond with a needle pointing up through it
+fn render_compass(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let points = [center + Vector2::new(0, -size),
+                  center + Vector2::new(size, 0),
+                  center + Vector2::new(0, size),
+                  center + Vector2::new(-size, 0)];
+    for index in 0..points.len() {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER,
+                                                     2,
+                                                     color,
+                                                     points[index],
+                                                     points[(index + 1) % points.len()]));
+    }
+    render_commands.push(RenderCommand::new_line(PANEL_LAYER, 2, color, points[2], points[0]));
+}
+
+/// A cross with a box around its middle
+fn render_crosshair(render_commands: &mut RenderCommands, color: Color, center: Vector2<i32>, size: i32) {
+    let line = |render_commands: &mut RenderCommands, from: Vector2<i32>, to: Vector2<i32>| {
+        render_commands.push(RenderCommand::new_line(PANEL_LAYER, 2, color, from, to));
+    };
+    line(render_commands, center - Vector2::new(size, 0), center + Vector2::new(size, 0));
+    line(render_commands, center - Vector2::new(0, size), center + Vector2::new(0, size));
+
+    let half = size / 2;
+    let corners = [center + Vector2::new(-half, -half),
+                   center + Vector2::new(half, -half),
+                   center + Vector2::new(half, half),
+                   center + Vector2::new(-half, half)];
+    for index in 0..corners.len() {
+        line(render_commands, corners[index], corners[(index + 1) % corners.len()]);
+    }
+}
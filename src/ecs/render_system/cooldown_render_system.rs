@@ -0,0 +1,87 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{CooldownComponent, OnScreenComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
+use ecs::resource::{Players, RenderCommands, ViewProjector};
+use resource::RenderCommand;
+use specs::{self, Join};
+use super::RenderSystem;
+use types::{Color, Fixed, Rect, Vector3};
+
+const INDICATOR_LAYER: u16 = 1900;
+const BAR_WIDTH: i32 = 24;
+const BAR_HEIGHT: i32 = 2;
+const GAP: i32 = 2;
+
+/// Draws a thin bar for each running cooldown over the local player's selected units, which
+/// fills up as the cooldown runs out
+pub struct CooldownRenderSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl CooldownRenderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> CooldownRenderSystem {
+        CooldownRenderSystem { empires: empires }
+    }
+}
+
+impl RenderSystem for CooldownRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            components(cooldowns: CooldownComponent),
+            components(on_screen: OnScreenComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(players: Players),
+            resource(projector: ViewProjector),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let local_player_id = players.local_player_id();
+        let items = (&units, &transforms, &cooldowns, &selected_units, &on_screen);
+        for (unit, transform, cooldown, _selected_unit, _on_screen) in items.iter() {
+            if unit.player_id != local_player_id || cooldown.cooldowns().is_empty() {
+                continue;
+            }
+
+            let unit_info = unit.db(&self.empires);
+            let position = transform.lerped_position(lerp);
+            let top = projector.project(&Vector3::new(position.x,
+                                                      position.y,
+                                                      position.z + unit_info.selection_shape_size_z.into()));
+            let x = top.x - BAR_WIDTH / 2;
+            for (index, cooldown) in cooldown.cooldowns().iter().enumerate() {
+                let y = top.y - (index as i32 + 1) * (BAR_HEIGHT + GAP);
+                let filled = i32::from(cooldown.progress() * BAR_WIDTH.into()).min(BAR_WIDTH);
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    0,
+                                                                    Color::rgb(40, 40, 40),
+                                                                    Rect::of(x, y, BAR_WIDTH, BAR_HEIGHT)));
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    1,
+                                                                    Color::rgb(80, 160, 255),
+                                                                    Rect::of(x, y, filled, BAR_HEIGHT)));
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{HitPointsComponent, OnScreenComponent, ResourceNodeComponent, SelectedUnitComponent,
+          TransformComponent, UnitComponent};
+use ecs::resource::{Chat, Cheats, CursorKind, Diplomacy, FogOfWar, MouseCursor, Players, RenderCommands,
+                    Terrain, ViewProjector, Viewport};
+use input::InputActions;
+use resource::RenderCommand;
+use specs::{self, Join};
+use super::RenderSystem;
+use types::Fixed;
+use util::pointer::calculate_mouse_ray;
+use util::unit;
+
+/// Over everything else, the HUD included
+const CURSOR_LAYER: u16 = 3000;
+
+/// Picks the cursor from what's under the pointer and what's selected, and draws it when it
+/// can't be shown as a hardware cursor
+pub struct CursorRenderSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl CursorRenderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> CursorRenderSystem {
+        CursorRenderSystem { empires: empires }
+    }
+}
+
+impl RenderSystem for CursorRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, entities, [
+            components(hit_points: HitPointsComponent),
+            components(on_screen: OnScreenComponent),
+            components(resource_nodes: ResourceNodeComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(chat: Chat),
+            resource(cheats: Cheats),
+            resource(diplomacy: Diplomacy),
+            resource(fog_of_war: FogOfWar),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            resource(terrain: Terrain),
+            resource(view_projector: ViewProjector),
+            resource(viewport: Viewport),
+            mut resource(mouse_cursor: MouseCursor),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let local_player_id = players.local_player_id();
+        let mouse_ray = calculate_mouse_ray(&viewport, &input_actions.pointer, &view_projector, &terrain);
+        let (row, col): (i32, i32) = (mouse_ray.world_coord.y.into(), mouse_ray.world_coord.x.into());
+        let on_map = row >= 0 && col >= 0 && row < terrain.height() && col < terrain.width();
+        let explored = cheats.revealed_map(local_player_id) ||
+                       (on_map && fog_of_war.is_explored(local_player_id, row, col));
+
+        let mut hovered = None;
+        if explored {
+            for (entity, _, unit, transform) in (&entities, &on_screen, &units, &transforms).iter() {
+                let unit_info = self.empires.unit(unit.civilization_id, unit.unit_id);
+                if unit_info.interaction_mode == dat::InteractionMode::NonInteracting {
+                    continue;
+                }
+                let unit_box = unit::selection_box(unit_info, transform);
+                if unit_box.intersects_ray(&mouse_ray.origin, &mouse_ray.direction) {
+                    hovered = Some((entity, unit.clone()));
+                    break;
+                }
+            }
+        }
+
+        let selected: Vec<&dat::Unit> = (&units, &selected_units)
+            .iter()
+            .filter(|&(unit, _)| unit.player_id == local_player_id)
+            .map(|(unit, _)| self.empires.unit(unit.civilization_id, unit.unit_id))
+            .collect();
+        mouse_cursor.kind = match hovered {
+            _ if chat.open => CursorKind::Normal,
+            _ if !explored => CursorKind::Invalid,
+            Some((target, ref target_unit)) => {
+                let target_info = self.empires.unit(target_unit.civilization_id, target_unit.unit_id);
+                let gatherable = resource_nodes.get(target).is_some() ||
+                                 unit::is_huntable(&self.empires, target_unit);
+                let max_hit_points: Fixed = target_info.hit_points().into();
+                let hurt = hit_points.get(target).map_or(false, |points| points.hit_points < max_hit_points);
+                if gatherable &&
+                   selected.iter().any(|unit_info| unit::can_gather_from(unit_info, target_info.class())) {
+                    CursorKind::Gather
+                } else if !target_unit.player_id.is_gaia() &&
+                          diplomacy.is_enemy(local_player_id, target_unit.player_id) &&
+                          selected.iter().any(|unit_info| !unit_info.attacks().is_empty()) {
+                    CursorKind::Attack
+                } else if target_unit.player_id == local_player_id && hurt &&
+                          selected.iter().any(|unit_info| unit::can_restore(unit_info, target_info)) {
+                    CursorKind::Restore
+                } else {
+                    CursorKind::Normal
+                }
+            }
+            None => CursorKind::Normal,
+        };
+
+        if mouse_cursor.software {
+            let pointer = input_actions.pointer + viewport.lerped_top_left(lerp);
+            render_commands.push(RenderCommand::new_shape(CURSOR_LAYER,
+                                                          0,
+                                                          MouseCursor::shape_key(),
+                                                          mouse_cursor.kind.frame() as u16,
+                                                          pointer,
+                                                          false,
+                                                          false));
+        }
+    }
+}
@@ -0,0 +1,308 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::{MoveToPositionActionComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
+use ecs::resource::{CombatLog, DebugOverlay, ElevationModifier, MAX_COMMAND_LENGTH, RenderCommands,
+                    SystemTimings, ViewProjector, Viewport};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use resource::RenderCommand;
+use specs::{self, Join};
+use std::time::{Duration, Instant};
+use super::RenderSystem;
+use types::{Color, Fixed, Rect, Vector3};
+use util::{digits, glyphs};
+
+const OVERLAY_LAYER: u16 = 2000;
+const WORLD_OVERLAY_LAYER: u16 = 1500;
+const MARGIN: i32 = 10;
+const PADDING: i32 = 4;
+const DIGIT_HEIGHT: i32 = 8;
+const ROW_HEIGHT: i32 = 12;
+const PANEL_WIDTH: i32 = 260;
+const LABEL_WIDTH: i32 = 24;
+const MAX_BAR_WIDTH: i32 = 150;
+const MICROS_PER_BAR_PIXEL: u32 = 20;
+const CONSOLE_HEIGHT: i32 = 14;
+const GLYPH_SPACING: i32 = 1;
+
+/// Weight of the latest frame in the smoothed frame time
+const FRAME_TIME_SMOOTHING: f64 = 0.1;
+
+/// Draws the debug overlay: frames per second, entity counts, how long each system took last
+/// time it ran, the latest hits in combat, the grid partition's occupancy, and the paths of the
+/// selected units.
+///
+/// There's no font rendering yet, so system timings are drawn as numbered rows; the row numbers
+/// are logged along with the system names whenever the overlay is opened.
+pub struct DebugOverlayRenderSystem {
+    last_frame: Option<Instant>,
+    frame_time_nanos: f64,
+    was_visible: bool,
+}
+
+impl DebugOverlayRenderSystem {
+    pub fn new() -> DebugOverlayRenderSystem {
+        DebugOverlayRenderSystem {
+            last_frame: None,
+            frame_time_nanos: 0.0,
+            was_visible: false,
+        }
+    }
+
+    fn update_frame_time(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            let frame_nanos = duration_nanos(now.duration_since(last_frame)) as f64;
+            self.frame_time_nanos = if self.frame_time_nanos == 0.0 {
+                frame_nanos
+            } else {
+                self.frame_time_nanos + (frame_nanos - self.frame_time_nanos) * FRAME_TIME_SMOOTHING
+            };
+        }
+        self.last_frame = Some(now);
+    }
+
+    fn fps(&self) -> u32 {
+        if self.frame_time_nanos > 0.0 {
+            (1_000_000_000.0 / self.frame_time_nanos).round() as u32
+        } else {
+            0
+        }
+    }
+}
+
+impl RenderSystem for DebugOverlayRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, entities, [
+            components(move_to_positions: MoveToPositionActionComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(combat_log: CombatLog),
+            resource(debug_overlay: DebugOverlay),
+            resource(grid: GridPartition),
+            resource(projector: ViewProjector),
+            resource(system_timings: SystemTimings),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        self.update_frame_time();
+
+        let timings = system_timings.snapshot();
+        if debug_overlay.visible && !self.was_visible {
+            for (index, &(name, _)) in timings.iter().enumerate() {
+                info!("Debug overlay row {}: {}", index + 1, name);
+            }
+        }
+        self.was_visible = debug_overlay.visible;
+        if !debug_overlay.visible {
+            return;
+        }
+
+        if debug_overlay.show_grid {
+            render_grid_occupancy(&mut *render_commands, &*grid, &*projector);
+        }
+        if debug_overlay.show_paths {
+            let items = (&transforms, &selected_units, &move_to_positions);
+            for (transform, _selected_unit, move_to_position) in items.iter() {
+                let mut from = projector.project(&transform.lerped_position(lerp));
+                for node in &move_to_position.path {
+                    let to = projector.project(node);
+                    render_commands.push(RenderCommand::new_line(WORLD_OVERLAY_LAYER,
+                                                                 1,
+                                                                 Color::rgb(255, 255, 0),
+                                                                 from,
+                                                                 to));
+                    from = to;
+                }
+            }
+        }
+
+        let top_left = viewport.lerped_top_left(lerp);
+        let x = top_left.x + MARGIN;
+        let mut y = top_left.y + MARGIN;
+        let combat_events = combat_log.events();
+        let panel_height = PADDING * 2 + ROW_HEIGHT * (2 + timings.len() as i32 + combat_events.len() as i32);
+        render_commands.push(RenderCommand::new_filled_rect(OVERLAY_LAYER,
+                                                            0,
+                                                            Color::rgb(20, 20, 20),
+                                                            Rect::of(x, y, PANEL_WIDTH, panel_height)));
+        y += PADDING;
+
+        // Frames per second, then the entity, unit, and grid entity counts
+        let white = Color::rgb(255, 255, 255);
+        let fps = self.fps();
+        let fps_color = if fps >= 30 {
+            Color::rgb(60, 220, 60)
+        } else {
+            Color::rgb(220, 60, 60)
+        };
+        render_label(&mut *render_commands, fps_color, Vector2::new(x + PADDING, y));
+        digits::render_number(&mut *render_commands,
+                              OVERLAY_LAYER,
+                              white,
+                              fps,
+                              Vector2::new(x + PADDING + LABEL_WIDTH, y),
+                              DIGIT_HEIGHT);
+        y += ROW_HEIGHT;
+
+        let counts = [((&entities).iter().count(), Color::rgb(200, 200, 200)),
+                      ((&units).iter().count(), Color::rgb(60, 120, 220)),
+                      (grid.entity_count(), Color::rgb(220, 140, 40))];
+        for (index, &(count, color)) in counts.iter().enumerate() {
+            let count_x = x + PADDING + index as i32 * (PANEL_WIDTH / 3);
+            render_label(&mut *render_commands, color, Vector2::new(count_x, y));
+            digits::render_number(&mut *render_commands,
+                                  OVERLAY_LAYER,
+                                  white,
+                                  count as u32,
+                                  Vector2::new(count_x + LABEL_WIDTH, y),
+                                  DIGIT_HEIGHT);
+        }
+        y += ROW_HEIGHT;
+
+        // Row number, a bar as long as the system took, and the time it took in microseconds
+        for (index, &(_, duration)) in timings.iter().enumerate() {
+            let micros = (duration_nanos(duration) / 1000) as u32;
+            digits::render_number(&mut *render_commands,
+                                  OVERLAY_LAYER,
+                                  Color::rgb(160, 160, 160),
+                                  index as u32 + 1,
+                                  Vector2::new(x + PADDING, y),
+                                  DIGIT_HEIGHT);
+            let bar_width = ((micros / MICROS_PER_BAR_PIXEL) as i32).max(1).min(MAX_BAR_WIDTH);
+            render_commands.push(RenderCommand::new_filled_rect(OVERLAY_LAYER,
+                                                                1,
+                                                                heat_color(bar_width, MAX_BAR_WIDTH),
+                                                                Rect::of(x + PADDING + LABEL_WIDTH,
+                                                                         y,
+                                                                         bar_width,
+                                                                         DIGIT_HEIGHT)));
+            digits::render_number(&mut *render_commands,
+                                  OVERLAY_LAYER,
+                                  white,
+                                  micros,
+                                  Vector2::new(x + PADDING * 2 + LABEL_WIDTH + MAX_BAR_WIDTH, y),
+                                  DIGIT_HEIGHT);
+            y += ROW_HEIGHT;
+        }
+
+        // The latest hits, oldest first: the elevation modifier's color, the damage, and the
+        // percentage of it that the modifier let through
+        for event in combat_events {
+            let modifier_color = match event.elevation_modifier {
+                ElevationModifier::Downhill => Color::rgb(60, 220, 60),
+                ElevationModifier::Level => Color::rgb(200, 200, 200),
+                ElevationModifier::Uphill => Color::rgb(220, 60, 60),
+            };
+            render_label(&mut *render_commands, modifier_color, Vector2::new(x + PADDING, y));
+            let damage_color = if event.destroyed {
+                Color::rgb(220, 60, 60)
+            } else {
+                white
+            };
+            digits::render_number(&mut *render_commands,
+                                  OVERLAY_LAYER,
+                                  damage_color,
+                                  event.damage.max(0) as u32,
+                                  Vector2::new(x + PADDING + LABEL_WIDTH, y),
+                                  DIGIT_HEIGHT);
+            digits::render_number(&mut *render_commands,
+                                  OVERLAY_LAYER,
+                                  modifier_color,
+                                  event.elevation_modifier.percent() as u32,
+                                  Vector2::new(x + PADDING + LABEL_WIDTH * 3, y),
+                                  DIGIT_HEIGHT);
+            y += ROW_HEIGHT;
+        }
+
+        if debug_overlay.console_open {
+            render_console(&mut *render_commands, debug_overlay.draft(), Vector2::new(x, y + PADDING));
+        }
+    }
+}
+
+/// Outlines every occupied grid cell on the ground, going from blue to red as the
+/// cell gets more crowded relative to the most crowded cell
+fn render_grid_occupancy(render_commands: &mut RenderCommands,
+                         grid: &GridPartition,
+                         projector: &ViewProjector) {
+    let occupancy = grid.cell_occupancy();
+    let max_count = occupancy.iter().map(|&(_, count)| count).max().unwrap_or(1) as i32;
+    let cell_size = grid.cell_size();
+    for &(col_row, count) in &occupancy {
+        let (left, top) = (col_row.x * cell_size.x, col_row.y * cell_size.y);
+        let (right, bottom) = (left + cell_size.x, top + cell_size.y);
+        let corners = [projector.project(&world_point(left, top)),
+                       projector.project(&world_point(right, top)),
+                       projector.project(&world_point(right, bottom)),
+                       projector.project(&world_point(left, bottom))];
+        let color = heat_color(count as i32, max_count);
+        for index in 0..corners.len() {
+            render_commands.push(RenderCommand::new_line(WORLD_OVERLAY_LAYER,
+                                                         0,
+                                                         color,
+                                                         corners[index],
+                                                         corners[(index + 1) % corners.len()]));
+        }
+    }
+}
+
+fn render_console(render_commands: &mut RenderCommands, draft: &str, top_left: Vector2<i32>) {
+    let width = PADDING * 2 + glyphs::text_width(MAX_COMMAND_LENGTH, GLYPH_SPACING);
+    let rect = Rect::of(top_left.x, top_left.y, width, CONSOLE_HEIGHT);
+    render_commands.push(RenderCommand::new_filled_rect(OVERLAY_LAYER, 0, Color::rgb(10, 10, 40), rect));
+    render_commands.push(RenderCommand::new_rect(OVERLAY_LAYER, 1, Color::rgb(120, 120, 220), rect));
+    let text_top_left = Vector2::new(top_left.x + PADDING,
+                                     top_left.y + (CONSOLE_HEIGHT - glyphs::GLYPH_HEIGHT) / 2);
+    glyphs::render_text(render_commands,
+                        OVERLAY_LAYER,
+                        2,
+                        Color::rgb(255, 255, 255),
+                        draft,
+                        text_top_left,
+                        GLYPH_SPACING);
+}
+
+fn render_label(render_commands: &mut RenderCommands, color: Color, top_left: Vector2<i32>) {
+    render_commands.push(RenderCommand::new_filled_rect(OVERLAY_LAYER,
+                                                        1,
+                                                        color,
+                                                        Rect::of(top_left.x,
+                                                                 top_left.y,
+                                                                 DIGIT_HEIGHT,
+                                                                 DIGIT_HEIGHT)));
+}
+
+fn heat_color(value: i32, max_value: i32) -> Color {
+    let heat = (value.max(0).min(max_value) * 255 / max_value.max(1)) as u8;
+    Color::rgb(heat, 60, 255 - heat)
+}
+
+fn world_point(x: i32, y: i32) -> Vector3 {
+    Vector3::new(x.into(), y.into(), 0.into())
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
@@ -0,0 +1,110 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::resource::*;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+
+const PANEL_LAYER: u16 = 2000;
+
+/// Draws the diplomacy panel where the UI layout puts it, on the theme's panel background.
+/// There's no text rendering yet, so stances are shown as green (ally), yellow (neutral), and
+/// red (enemy) buttons with the current stance filled in, followed by a button for each resource
+/// that can be tributed.
+pub struct DiplomacyPanelRenderSystem;
+
+impl DiplomacyPanelRenderSystem {
+    pub fn new() -> DiplomacyPanelRenderSystem {
+        DiplomacyPanelRenderSystem
+    }
+}
+
+impl RenderSystem for DiplomacyPanelRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(diplomacy: Diplomacy),
+            resource(diplomacy_panel: DiplomacyPanel),
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        if !diplomacy_panel.open {
+            return;
+        }
+
+        // The renderer offsets everything by the camera position, so shift the panel along with it
+        let top_left = viewport.lerped_top_left(lerp);
+        let to_screen = |mut rect: Rect| {
+            rect.translate(top_left.x, top_left.y);
+            rect
+        };
+
+        let local_player_id = players.local_player_id();
+        let row_player_ids = diplomacy_panel.row_player_ids(&players);
+        let bounds = diplomacy_panel.bounds(&ui_layout, &viewport.size, row_player_ids.len());
+        let background = to_screen(bounds);
+        render_commands.push(ui_layout.panel_command(PANEL_LAYER, 0, background, Color::rgb(40, 32, 24)));
+
+        for (row, other_player_id) in row_player_ids.iter().enumerate() {
+            let current_stance = diplomacy.stance(local_player_id, *other_player_id);
+            for (stance_index, stance) in PANEL_STANCES.iter().enumerate() {
+                let rect = to_screen(diplomacy_panel.stance_button(&bounds, row, stance_index));
+                let color = stance_color(*stance);
+                render_commands.push(if *stance == current_stance {
+                    RenderCommand::new_filled_rect(PANEL_LAYER, 1, color, rect)
+                } else {
+                    RenderCommand::new_rect(PANEL_LAYER, 1, color, rect)
+                });
+            }
+
+            for (resource_index, resource_type) in PANEL_TRIBUTE_RESOURCES.iter().enumerate() {
+                let rect = to_screen(diplomacy_panel.tribute_button(&bounds, row, resource_index));
+                render_commands.push(RenderCommand::new_filled_rect(PANEL_LAYER,
+                                                                    1,
+                                                                    resource_color(*resource_type),
+                                                                    rect));
+            }
+        }
+    }
+}
+
+fn stance_color(stance: DiplomaticStance) -> Color {
+    match stance {
+        DiplomaticStance::Ally => Color::rgb(0, 200, 0),
+        DiplomaticStance::Neutral => Color::rgb(220, 200, 0),
+        DiplomaticStance::Enemy => Color::rgb(200, 0, 0),
+    }
+}
+
+pub fn resource_color(resource_type: ResourceType) -> Color {
+    match resource_type {
+        ResourceType::Food => Color::rgb(200, 60, 60),
+        ResourceType::Wood => Color::rgb(130, 80, 30),
+        ResourceType::Stone => Color::rgb(150, 150, 150),
+        ResourceType::Gold => Color::rgb(230, 190, 40),
+        ResourceType::Unknown(_) => Color::rgb(255, 0, 255),
+    }
+}
@@ -0,0 +1,198 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::Action;
+use ecs::TransformComponent;
+use ecs::resource::{EntityInspector, InspectorField, RenderCommands, ViewProjector, Viewport};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs::{self, Join};
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use util::digits;
+
+const INSPECTOR_LAYER: u16 = 2000;
+const WORLD_MARKER_LAYER: u16 = 1500;
+const MARGIN: i32 = 10;
+const PADDING: i32 = 4;
+const DIGIT_HEIGHT: i32 = 8;
+const ROW_HEIGHT: i32 = 12;
+const PANEL_WIDTH: i32 = 160;
+const LABEL_WIDTH: i32 = 24;
+const MARKER_SIZE: i32 = 24;
+
+/// Draws the entity inspector panel on the right side of the screen and marks the inspected
+/// entity on the map.
+///
+/// Each row has its number, a swatch colored by component, and the field's whole number value,
+/// in yellow if the field can be changed with the console's `edit` command. The field names are
+/// logged along with the row numbers when the entity is picked. The unit's orders follow, one
+/// row each with the number of waypoints left: the current order first, outlined once it's done.
+pub struct EntityInspectorRenderSystem;
+
+impl EntityInspectorRenderSystem {
+    pub fn new() -> EntityInspectorRenderSystem {
+        EntityInspectorRenderSystem
+    }
+}
+
+impl RenderSystem for EntityInspectorRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            resource(entity_inspector: EntityInspector),
+            resource(projector: ViewProjector),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let entity_id = match entity_inspector.entity_id() {
+            Some(entity_id) => entity_id,
+            None => return,
+        };
+
+        let marker_color = Color::rgb(0, 230, 230);
+        let inspected = (&entities, &transforms).iter().find(|&(entity, _)| entity.get_id() == entity_id);
+        if let Some((_, transform)) = inspected {
+            let center = projector.project(&transform.lerped_position(lerp));
+            render_commands.push(RenderCommand::new_rect(WORLD_MARKER_LAYER,
+                                                         0,
+                                                         marker_color,
+                                                         Rect::of(center.x - MARKER_SIZE / 2,
+                                                                  center.y - MARKER_SIZE / 2,
+                                                                  MARKER_SIZE,
+                                                                  MARKER_SIZE)));
+        }
+
+        let rows = entity_inspector.rows();
+        let current_order = entity_inspector.current_order();
+        let orders = entity_inspector.orders();
+        let order_count = orders.len() + if current_order.is_some() { 1 } else { 0 };
+        let top_left = viewport.lerped_top_left(lerp);
+        let x = top_left.x + viewport.size.x - MARGIN - PANEL_WIDTH;
+        let mut y = top_left.y + MARGIN;
+        let panel_height = PADDING * 2 + ROW_HEIGHT * (rows.len() + order_count) as i32;
+        let panel = Rect::of(x, y, PANEL_WIDTH, panel_height);
+        render_commands.push(RenderCommand::new_filled_rect(INSPECTOR_LAYER,
+                                                            0,
+                                                            Color::rgb(20, 20, 20),
+                                                            panel));
+        render_commands.push(RenderCommand::new_rect(INSPECTOR_LAYER, 1, marker_color, panel));
+        y += PADDING;
+
+        for (index, &(field, value)) in rows.iter().enumerate() {
+            render_row_number(&mut *render_commands, index + 1, Vector2::new(x + PADDING, y));
+            render_swatch(&mut *render_commands,
+                          field_color(field),
+                          true,
+                          Vector2::new(x + PADDING + LABEL_WIDTH, y));
+            let value_color = if field.is_editable() {
+                Color::rgb(255, 230, 40)
+            } else {
+                Color::rgb(255, 255, 255)
+            };
+            render_value(&mut *render_commands,
+                         value_color,
+                         i32::from(value),
+                         Vector2::new(x + PADDING + LABEL_WIDTH * 2, y));
+            y += ROW_HEIGHT;
+        }
+
+        let current = current_order.iter().map(|order| (order, true));
+        for (order, is_current) in current.chain(orders.iter().map(|order| (order, false))) {
+            let (color, waypoints) = match *order {
+                Action::ClearQueue |
+                Action::SuspendQueue |
+                Action::ResumeQueue => (Color::rgb(160, 160, 160), 0),
+                Action::MoveToPosition(ref params) => (Color::rgb(60, 220, 60), params.path.len()),
+                Action::Gather(_) => (Color::rgb(220, 160, 60), 0),
+                Action::AttackGround(_) => (Color::rgb(220, 60, 60), 0),
+                Action::Restore(_) => (Color::rgb(60, 200, 220), 0),
+                Action::Explore => (Color::rgb(200, 120, 220), 0),
+                Action::Garrison(_) => (Color::rgb(220, 220, 120), 0),
+            };
+            let filled = !is_current || !current_order_done(rows);
+            render_swatch(&mut *render_commands,
+                          color,
+                          filled,
+                          Vector2::new(x + PADDING + LABEL_WIDTH, y));
+            digits::render_number(&mut *render_commands,
+                                  INSPECTOR_LAYER,
+                                  Color::rgb(255, 255, 255),
+                                  waypoints as u32,
+                                  Vector2::new(x + PADDING + LABEL_WIDTH * 2, y),
+                                  DIGIT_HEIGHT);
+            y += ROW_HEIGHT;
+        }
+    }
+}
+
+fn current_order_done(rows: &[(InspectorField, Fixed)]) -> bool {
+    rows.iter().any(|&(field, value)| field == InspectorField::CurrentOrderDone && value != 0.into())
+}
+
+fn render_row_number(render_commands: &mut RenderCommands, row: usize, top_left: Vector2<i32>) {
+    digits::render_number(render_commands,
+                          INSPECTOR_LAYER,
+                          Color::rgb(160, 160, 160),
+                          row as u32,
+                          top_left,
+                          DIGIT_HEIGHT);
+}
+
+fn render_swatch(render_commands: &mut RenderCommands, color: Color, filled: bool, top_left: Vector2<i32>) {
+    let rect = Rect::of(top_left.x, top_left.y, DIGIT_HEIGHT, DIGIT_HEIGHT);
+    render_commands.push(if filled {
+        RenderCommand::new_filled_rect(INSPECTOR_LAYER, 1, color, rect)
+    } else {
+        RenderCommand::new_rect(INSPECTOR_LAYER, 1, color, rect)
+    });
+}
+
+/// Draws a whole number, with a dash in front of it when it's negative
+fn render_value(render_commands: &mut RenderCommands, color: Color, value: i32, top_left: Vector2<i32>) {
+    let mut x = top_left.x;
+    if value < 0 {
+        let dash_width = DIGIT_HEIGHT / 2;
+        let dash = Rect::of(x, top_left.y + DIGIT_HEIGHT / 2, dash_width, 1);
+        render_commands.push(RenderCommand::new_filled_rect(INSPECTOR_LAYER, 1, color, dash));
+        x += dash_width + 2;
+    }
+    digits::render_number(render_commands,
+                          INSPECTOR_LAYER,
+                          color,
+                          value.abs() as u32,
+                          Vector2::new(x, top_left.y),
+                          DIGIT_HEIGHT);
+}
+
+fn field_color(field: InspectorField) -> Color {
+    use ecs::resource::InspectorField::*;
+    match field {
+        PlayerId | CivilizationId | UnitId => Color::rgb(60, 120, 220),
+        PositionX | PositionY | PositionZ | Rotation => Color::rgb(220, 140, 40),
+        VelocityX | VelocityY | VelocityZ => Color::rgb(220, 60, 60),
+        GraphicId | Frame => Color::rgb(180, 80, 220),
+        Selected | OnScreen => Color::rgb(200, 200, 200),
+        QueuedUnits | TrainingProgress | RepeatProduction => Color::rgb(230, 230, 0),
+        QueuedOrders | CurrentOrderDone => Color::rgb(60, 220, 60),
+    }
+}
@@ -0,0 +1,241 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::TransformComponent;
+use dat::ResourceType;
+use ecs::resource::{CombatLog, DepositLog, EffectKind, FeedbackEffects, FogOfWar, Players, RenderCommands,
+                    ViewProjector};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs::{self, Index, Join};
+use std::cmp;
+use std::collections::HashMap;
+use std::time::Instant;
+use super::RenderSystem;
+use types::{Color, Fixed, Vector3};
+use util::digits;
+
+/// With the other markers over the world, so that the tint doesn't darken them
+const FEEDBACK_LAYER: u16 = 1500;
+const FLAG_HEIGHT: i32 = 18;
+const CROSSHAIR_SIZE: i32 = 10;
+const NUMBER_HEIGHT: i32 = 7;
+/// How far a number floats up over its lifetime
+const NUMBER_RISE: f32 = 24.;
+
+/// Draws the short-lived feedback effects, aging them in real time. It works out what to show
+/// from the logs the simulation keeps: crosshairs flash over the units hit since the last frame,
+/// and numbers float up for the damage and for the resources the local player dropped off.
+/// Only hits the local player was part of or can see show, so fights under the fog stay hidden.
+pub struct FeedbackRenderSystem {
+    last_frame: Instant,
+    events_seen: usize,
+    deposits_seen: usize,
+}
+
+impl FeedbackRenderSystem {
+    pub fn new() -> FeedbackRenderSystem {
+        FeedbackRenderSystem {
+            last_frame: Instant::now(),
+            events_seen: 0,
+            deposits_seen: 0,
+        }
+    }
+
+    fn frame_seconds(&mut self) -> f32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_frame);
+        self.last_frame = now;
+        elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.
+    }
+}
+
+fn flag_color(attack: bool) -> Color {
+    if attack {
+        Color::rgb(230, 40, 40)
+    } else {
+        Color::rgb(40, 220, 40)
+    }
+}
+
+fn damage_color() -> Color {
+    Color::rgb(255, 80, 80)
+}
+
+/// Color of the numbers that float up when the resource is dropped off
+fn resource_color(resource_type: ResourceType) -> Color {
+    match resource_type {
+        ResourceType::Food => Color::rgb(230, 90, 70),
+        ResourceType::Wood => Color::rgb(190, 140, 70),
+        ResourceType::Stone => Color::rgb(190, 190, 190),
+        ResourceType::Gold => Color::rgb(250, 210, 60),
+        _ => Color::rgb(255, 255, 255),
+    }
+}
+
+fn line(render_commands: &mut RenderCommands, color: Color, from: Vector2<i32>, to: Vector2<i32>) {
+    render_commands.push(RenderCommand::new_line(FEEDBACK_LAYER, 0, color, from, to));
+}
+
+/// A pole with a pennant that sinks into the ground as the flag runs out
+fn render_flag(render_commands: &mut RenderCommands, color: Color, base: Vector2<i32>, progress: f32) {
+    let height = (FLAG_HEIGHT as f32 * (1. - progress)) as i32;
+    if height < 2 {
+        return;
+    }
+    let top = Vector2::new(base.x, base.y - height);
+    let pennant_height = cmp::min(height, FLAG_HEIGHT / 3);
+    line(render_commands, color, base, top);
+    line(render_commands,
+         color,
+         top,
+         Vector2::new(top.x + 8, top.y + pennant_height / 2));
+    line(render_commands,
+         color,
+         Vector2::new(top.x + 8, top.y + pennant_height / 2),
+         Vector2::new(top.x, top.y + pennant_height));
+}
+
+/// Crosshairs that blink a few times over what was hit
+fn render_crosshair(render_commands: &mut RenderCommands, center: Vector2<i32>, progress: f32) {
+    if (progress * 4.) as i32 % 2 == 1 {
+        return;
+    }
+    let color = flag_color(true);
+    let (size, gap) = (CROSSHAIR_SIZE, CROSSHAIR_SIZE / 3);
+    line(render_commands,
+         color,
+         Vector2::new(center.x - size, center.y),
+         Vector2::new(center.x - gap, center.y));
+    line(render_commands,
+         color,
+         Vector2::new(center.x + gap, center.y),
+         Vector2::new(center.x + size, center.y));
+    line(render_commands,
+         color,
+         Vector2::new(center.x, center.y - size),
+         Vector2::new(center.x, center.y - gap));
+    line(render_commands,
+         color,
+         Vector2::new(center.x, center.y + gap),
+         Vector2::new(center.x, center.y + size));
+}
+
+/// The number with its sign, centered over the position and rising as it ages
+fn render_floating_number(render_commands: &mut RenderCommands,
+                          color: Color,
+                          value: i32,
+                          position: Vector2<i32>,
+                          progress: f32) {
+    let magnitude = value.abs() as u32;
+    let sign_width = NUMBER_HEIGHT / 2 + 2;
+    let width = sign_width + digits::number_width(magnitude, NUMBER_HEIGHT);
+    let top_left = Vector2::new(position.x - width / 2,
+                                position.y - NUMBER_HEIGHT - (NUMBER_RISE * progress) as i32);
+
+    let middle = top_left.y + NUMBER_HEIGHT / 2;
+    let sign_size = NUMBER_HEIGHT / 2;
+    line(render_commands,
+         color,
+         Vector2::new(top_left.x, middle),
+         Vector2::new(top_left.x + sign_size, middle));
+    if value >= 0 {
+        let center = top_left.x + sign_size / 2;
+        line(render_commands,
+             color,
+             Vector2::new(center, middle - sign_size / 2),
+             Vector2::new(center, middle + sign_size / 2));
+    }
+    digits::render_number(render_commands,
+                          FEEDBACK_LAYER,
+                          color,
+                          magnitude,
+                          Vector2::new(top_left.x + sign_width, top_left.y),
+                          NUMBER_HEIGHT);
+}
+
+impl RenderSystem for FeedbackRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, _lerp: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            resource(projector: ViewProjector),
+            resource(combat_log: CombatLog),
+            resource(deposit_log: DepositLog),
+            resource(fog_of_war: FogOfWar),
+            resource(players: Players),
+            mut resource(feedback_effects: FeedbackEffects),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        feedback_effects.advance(self.frame_seconds());
+        let local_player_id = players.local_player_id();
+
+        // Only the latest events are kept in the logs, so a burst of them may have pushed some out
+        let new_events = cmp::min(combat_log.recorded() - self.events_seen, combat_log.events().len());
+        self.events_seen = combat_log.recorded();
+        if new_events > 0 {
+            let positions: HashMap<Index, Vector3> = (&entities, &transforms)
+                .iter()
+                .map(|(entity, transform)| (entity.get_id(), *transform.position()))
+                .collect();
+            let events = combat_log.events();
+            for event in events.iter().skip(events.len() - new_events) {
+                let position = match positions.get(&event.target) {
+                    Some(position) => *position,
+                    None => continue,
+                };
+                let involved = event.attacker_player_id == local_player_id ||
+                               event.target_player_id == local_player_id;
+                if involved || fog_of_war.is_visible(local_player_id, position.y.into(), position.x.into()) {
+                    feedback_effects.target_flash(position);
+                    feedback_effects.floating_number(position, -(event.damage as i32), damage_color());
+                }
+            }
+        }
+
+        let new_deposits = cmp::min(deposit_log.recorded() - self.deposits_seen, deposit_log.events().len());
+        self.deposits_seen = deposit_log.recorded();
+        let deposits = deposit_log.events();
+        for deposit in deposits.iter().skip(deposits.len() - new_deposits) {
+            if deposit.player_id == local_player_id {
+                feedback_effects.floating_number(deposit.position,
+                                                 deposit.amount.into(),
+                                                 resource_color(deposit.resource_type));
+            }
+        }
+
+        for effect in feedback_effects.effects() {
+            let position = projector.project(&effect.position);
+            let progress = effect.progress();
+            match effect.kind {
+                EffectKind::CommandFlag { attack } => {
+                    render_flag(&mut *render_commands, flag_color(attack), position, progress);
+                }
+                EffectKind::TargetFlash => {
+                    render_crosshair(&mut *render_commands, position, progress);
+                }
+                EffectKind::FloatingNumber { value, color } => {
+                    render_floating_number(&mut *render_commands, color, value, position, progress);
+                }
+            }
+        }
+    }
+}
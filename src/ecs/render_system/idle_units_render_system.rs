@@ -0,0 +1,82 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{IdleCategory, IdleUnits, Players, RenderCommands, UiLayout, UiPanel, Viewport};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use util::digits;
+
+const HUD_LAYER: u16 = 2000;
+const ICON_SIZE: i32 = 12;
+const DIGIT_HEIGHT: i32 = 12;
+const COUNTER_WIDTH: i32 = 60;
+
+/// Shows how many idle villagers and idle military units the local player has, where the layout
+/// puts the counters
+pub struct IdleUnitsRenderSystem;
+
+impl IdleUnitsRenderSystem {
+    pub fn new() -> IdleUnitsRenderSystem {
+        IdleUnitsRenderSystem
+    }
+}
+
+impl RenderSystem for IdleUnitsRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(idle_units: IdleUnits),
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let local_player_id = players.local_player().player_id;
+        let counters = [(IdleCategory::Villager, Color::rgb(60, 120, 220)),
+                        (IdleCategory::Military, Color::rgb(220, 60, 60))];
+        let size = Vector2::new(counters.len() as i32 * COUNTER_WIDTH, ICON_SIZE);
+        let bounds = ui_layout.place(UiPanel::IdleUnits, &size, &viewport.size);
+        let top_left = viewport.lerped_top_left(lerp);
+        let y = top_left.y + bounds.y;
+
+        for (index, &(category, color)) in counters.iter().enumerate() {
+            let count = idle_units.count(local_player_id, category);
+            if count == 0 {
+                continue;
+            }
+
+            let x = top_left.x + bounds.x + index as i32 * COUNTER_WIDTH;
+            render_commands.push(RenderCommand::new_filled_rect(HUD_LAYER,
+                                                                0,
+                                                                color,
+                                                                Rect::of(x, y, ICON_SIZE, ICON_SIZE)));
+            digits::render_number(&mut *render_commands,
+                                   HUD_LAYER,
+                                   Color::rgb(255, 255, 255),
+                                   count as u32,
+                                   Vector2::new(x + ICON_SIZE + 4, y),
+                                   DIGIT_HEIGHT);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{RenderCommands, Viewport};
+use input::{InputActions, RADIAL_MENU_ACTIONS};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+
+const HUD_LAYER: u16 = 2000;
+const ENTRY_SIZE: i32 = 24;
+const CURSOR_SIZE: i32 = 8;
+
+/// Draws what's only shown while giving input: the selection box being dragged out, the
+/// gamepad's radial command menu and the gamepad's cursor
+pub struct InputOverlayRenderSystem;
+
+impl InputOverlayRenderSystem {
+    pub fn new() -> InputOverlayRenderSystem {
+        InputOverlayRenderSystem
+    }
+}
+
+impl RenderSystem for InputOverlayRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(input_actions: InputActions),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let top_left = viewport.lerped_top_left(lerp);
+        let translated = |mut rect: Rect| {
+            rect.translate(top_left.x, top_left.y);
+            rect
+        };
+
+        if let Some(drag_box) = input_actions.drag_box {
+            render_commands.push(RenderCommand::new_rect(HUD_LAYER,
+                                                         0,
+                                                         Color::rgb(255, 255, 255),
+                                                         translated(drag_box)));
+        }
+
+        if let Some(ref radial_menu) = input_actions.radial_menu {
+            for index in 0..RADIAL_MENU_ACTIONS.len() {
+                let position = radial_menu.entry_position(index);
+                let entry = Rect::of(position.x - ENTRY_SIZE / 2,
+                                     position.y - ENTRY_SIZE / 2,
+                                     ENTRY_SIZE,
+                                     ENTRY_SIZE);
+                let color = if radial_menu.picked == Some(index) {
+                    Color::rgb(230, 200, 80)
+                } else {
+                    Color::rgb(60, 60, 60)
+                };
+                render_commands.push(RenderCommand::new_filled_rect(HUD_LAYER, 0, color, translated(entry)));
+                render_commands.push(RenderCommand::new_rect(HUD_LAYER,
+                                                             1,
+                                                             Color::rgb(255, 255, 255),
+                                                             translated(entry)));
+            }
+        }
+
+        if input_actions.gamepad_cursor {
+            let pointer = input_actions.pointer + top_left;
+            let cursor_color = Color::rgb(255, 255, 255);
+            render_commands.push(RenderCommand::new_line(HUD_LAYER,
+                                                         2,
+                                                         cursor_color,
+                                                         Vector2::new(pointer.x - CURSOR_SIZE, pointer.y),
+                                                         Vector2::new(pointer.x + CURSOR_SIZE, pointer.y)));
+            render_commands.push(RenderCommand::new_line(HUD_LAYER,
+                                                         2,
+                                                         cursor_color,
+                                                         Vector2::new(pointer.x, pointer.y - CURSOR_SIZE),
+                                                         Vector2::new(pointer.x, pointer.y + CURSOR_SIZE)));
+        }
+    }
+}
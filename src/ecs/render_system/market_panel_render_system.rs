@@ -0,0 +1,98 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::*;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use util::digits;
+
+const PANEL_LAYER: u16 = 2000;
+const DIGIT_HEIGHT: i32 = 12;
+
+/// Draws the market panel on the theme's panel background. Buy buttons are green and sell buttons are red, and each row
+/// lists the local player's buy and sell price for a lot of the commodity.
+pub struct MarketPanelRenderSystem;
+
+impl MarketPanelRenderSystem {
+    pub fn new() -> MarketPanelRenderSystem {
+        MarketPanelRenderSystem
+    }
+}
+
+impl RenderSystem for MarketPanelRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(market: Market),
+            resource(market_panel: MarketPanel),
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        if !market_panel.open {
+            return;
+        }
+
+        let top_left = viewport.lerped_top_left(lerp);
+        let to_screen = |mut rect: Rect| {
+            rect.translate(top_left.x, top_left.y);
+            rect
+        };
+
+        let bounds = market_panel.bounds(&ui_layout, &viewport.size);
+        let background = to_screen(bounds);
+        render_commands.push(ui_layout.panel_command(PANEL_LAYER, 0, background, Color::rgb(40, 32, 24)));
+
+        let local_player_id = players.local_player_id();
+        for (row, resource_type) in COMMODITIES.iter().enumerate() {
+            for (transaction_index, transaction) in MARKET_TRANSACTIONS.iter().enumerate() {
+                let color = match *transaction {
+                    MarketTransaction::Buy => Color::rgb(0, 200, 0),
+                    MarketTransaction::Sell => Color::rgb(200, 0, 0),
+                };
+                let rect = to_screen(market_panel.button(&bounds, row, transaction_index));
+                render_commands.push(RenderCommand::new_filled_rect(PANEL_LAYER, 1, color, rect));
+            }
+
+            let price_position = market_panel.price_position(&bounds, row) + top_left;
+            let buy_price: u32 = market.buy_price(local_player_id, *resource_type).into();
+            let sell_price: u32 = market.sell_price(local_player_id, *resource_type).into();
+            digits::render_number(&mut *render_commands,
+                                  PANEL_LAYER,
+                                  Color::rgb(0, 200, 0),
+                                  buy_price,
+                                  price_position,
+                                  DIGIT_HEIGHT);
+            let sell_position = price_position +
+                                Vector2::new(digits::number_width(buy_price, DIGIT_HEIGHT) + 12, 0);
+            digits::render_number(&mut *render_commands,
+                                  PANEL_LAYER,
+                                  Color::rgb(200, 0, 0),
+                                  sell_price,
+                                  sell_position,
+                                  DIGIT_HEIGHT);
+        }
+    }
+}
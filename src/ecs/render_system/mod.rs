@@ -19,16 +19,52 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod chat_render_system;
+mod command_panel_render_system;
+mod cooldown_render_system;
+mod cursor_render_system;
+mod debug_overlay_render_system;
 mod decal_render_system;
+mod feedback_render_system;
+mod diplomacy_panel_render_system;
+mod entity_inspector_render_system;
 mod graphic_render_system;
+mod idle_units_render_system;
+mod input_overlay_render_system;
+mod market_panel_render_system;
+mod notification_render_system;
+mod production_render_system;
 mod render_system;
+mod score_screen_render_system;
+mod shore_foam_render_system;
 mod terrain_render_system;
 mod tile_debug_render_system;
+mod tint_render_system;
+mod tile_overlay_render_system;
 mod unit_selection_render_system;
+mod wall_placement_render_system;
 
+pub use self::chat_render_system::ChatRenderSystem;
+pub use self::command_panel_render_system::CommandPanelRenderSystem;
+pub use self::cooldown_render_system::CooldownRenderSystem;
+pub use self::cursor_render_system::CursorRenderSystem;
+pub use self::debug_overlay_render_system::DebugOverlayRenderSystem;
 pub use self::decal_render_system::DecalRenderSystem;
+pub use self::feedback_render_system::FeedbackRenderSystem;
+pub use self::diplomacy_panel_render_system::DiplomacyPanelRenderSystem;
+pub use self::entity_inspector_render_system::EntityInspectorRenderSystem;
 pub use self::graphic_render_system::GraphicRenderSystem;
+pub use self::idle_units_render_system::IdleUnitsRenderSystem;
+pub use self::input_overlay_render_system::InputOverlayRenderSystem;
+pub use self::market_panel_render_system::MarketPanelRenderSystem;
+pub use self::notification_render_system::NotificationRenderSystem;
+pub use self::production_render_system::ProductionRenderSystem;
 pub use self::render_system::{RenderSystem, RenderSystemWrapper};
+pub use self::score_screen_render_system::ScoreScreenRenderSystem;
+pub use self::shore_foam_render_system::ShoreFoamRenderSystem;
 pub use self::terrain_render_system::TerrainRenderSystem;
 pub use self::tile_debug_render_system::TileDebugRenderSystem;
+pub use self::tile_overlay_render_system::TileOverlayRenderSystem;
+pub use self::tint_render_system::TintRenderSystem;
 pub use self::unit_selection_render_system::UnitSelectionRenderSystem;
+pub use self::wall_placement_render_system::WallPlacementRenderSystem;
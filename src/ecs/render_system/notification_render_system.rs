@@ -0,0 +1,95 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{Notifications, Players, RenderCommands, UiLayout, UiPanel, Viewport};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use std::time::Instant;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use util::glyphs;
+
+const HUD_LAYER: u16 = 2000;
+const PADDING: i32 = 2;
+const LINE_SPACING: i32 = 3;
+const GLYPH_SPACING: i32 = 1;
+/// Most notifications on screen at once; older ones are still kept until they run out
+const MAX_SHOWN: usize = 5;
+
+/// Lists the local player's latest notifications where the layout puts them, newest at the
+/// bottom, and ages them in real time so that they go away after a while
+pub struct NotificationRenderSystem {
+    last_frame: Instant,
+}
+
+impl NotificationRenderSystem {
+    pub fn new() -> NotificationRenderSystem {
+        NotificationRenderSystem { last_frame: Instant::now() }
+    }
+
+    fn frame_seconds(&mut self) -> f32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_frame);
+        self.last_frame = now;
+        elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.
+    }
+}
+
+impl RenderSystem for NotificationRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(notifications: Notifications),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        notifications.advance(self.frame_seconds());
+
+        let shown = notifications.for_player(players.local_player_id());
+        let shown = &shown[shown.len().saturating_sub(MAX_SHOWN)..];
+        let line_width = |text: &str| glyphs::text_width(text.chars().count(), GLYPH_SPACING) + PADDING * 2;
+        let line_height = glyphs::GLYPH_HEIGHT + PADDING * 2 + LINE_SPACING;
+        let widest = shown.iter().map(|notification| line_width(&notification.text)).max().unwrap_or(0);
+        let size = Vector2::new(widest, shown.len() as i32 * line_height);
+        let bounds = ui_layout.place(UiPanel::Notifications, &size, &viewport.size);
+        let top_left = viewport.lerped_top_left(lerp);
+        for (index, notification) in shown.iter().enumerate() {
+            let x = top_left.x + bounds.x;
+            let y = top_left.y + bounds.y + index as i32 * line_height;
+            let width = line_width(&notification.text);
+            let height = glyphs::GLYPH_HEIGHT + PADDING * 2;
+            render_commands.push(RenderCommand::new_filled_rect(HUD_LAYER,
+                                                                0,
+                                                                Color::rgb(20, 20, 20),
+                                                                Rect::of(x, y, width, height)));
+            glyphs::render_text(&mut *render_commands,
+                                HUD_LAYER,
+                                1,
+                                Color::rgb(255, 230, 120),
+                                &notification.text,
+                                Vector2::new(x + PADDING, y + PADDING),
+                                GLYPH_SPACING);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{OnScreenComponent, ProductionQueueComponent, TransformComponent, UnitComponent};
+use ecs::resource::{Players, RenderCommands, ViewProjector};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs::{self, Join};
+use super::RenderSystem;
+use types::{Color, Fixed, Rect, Vector3};
+use util::digits;
+
+const INDICATOR_LAYER: u16 = 1900;
+const BAR_WIDTH: i32 = 40;
+const BAR_HEIGHT: i32 = 4;
+const DIGIT_HEIGHT: i32 = 7;
+const GAP: i32 = 3;
+
+/// Draws a training progress bar and the queue length over the local player's buildings, or a
+/// blue research progress bar while they're researching, plus a yellow marker with the number of units left before the cap for buildings that
+/// have repeat production turned on
+pub struct ProductionRenderSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl ProductionRenderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> ProductionRenderSystem {
+        ProductionRenderSystem { empires: empires }
+    }
+}
+
+impl RenderSystem for ProductionRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            components(on_screen: OnScreenComponent),
+            components(production_queues: ProductionQueueComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(players: Players),
+            resource(projector: ViewProjector),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let local_player_id = players.local_player_id();
+        let items = (&units, &transforms, &production_queues, &on_screen);
+        for (unit, transform, production_queue, _on_screen) in items.iter() {
+            let researching = production_queue.current_research().is_some();
+            if unit.player_id != local_player_id ||
+               (production_queue.current().is_none() && !researching && !production_queue.repeat()) {
+                continue;
+            }
+
+            let building_info = unit.db(&self.empires);
+            let position = transform.lerped_position(lerp);
+            let top = projector.project(&Vector3::new(position.x,
+                                                      position.y,
+                                                      position.z +
+                                                      building_info.selection_shape_size_z.into()));
+            let x = top.x - BAR_WIDTH / 2;
+            let y = top.y - BAR_HEIGHT - GAP;
+
+            if let Some(research_id) = production_queue.current_research() {
+                let research_time = self.empires.research(research_id).time_seconds;
+                let filled = if research_time > 0 {
+                    let progress = production_queue.research_progress() * BAR_WIDTH.into() /
+                                   research_time.into();
+                    i32::from(progress).min(BAR_WIDTH)
+                } else {
+                    BAR_WIDTH
+                };
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    0,
+                                                                    Color::rgb(40, 40, 40),
+                                                                    Rect::of(x, y, BAR_WIDTH, BAR_HEIGHT)));
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    1,
+                                                                    Color::rgb(70, 130, 230),
+                                                                    Rect::of(x, y, filled, BAR_HEIGHT)));
+            } else if let Some(unit_id) = production_queue.current() {
+                let train_time = self.empires.unit(unit.civilization_id, unit_id).train_time().unwrap_or(0);
+                let filled = if train_time > 0 {
+                    let progress = production_queue.progress() * BAR_WIDTH.into() / train_time.into();
+                    i32::from(progress).min(BAR_WIDTH)
+                } else {
+                    BAR_WIDTH
+                };
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    0,
+                                                                    Color::rgb(40, 40, 40),
+                                                                    Rect::of(x, y, BAR_WIDTH, BAR_HEIGHT)));
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    1,
+                                                                    Color::rgb(60, 220, 60),
+                                                                    Rect::of(x, y, filled, BAR_HEIGHT)));
+                digits::render_number(&mut *render_commands,
+                                      INDICATOR_LAYER,
+                                      Color::rgb(255, 255, 255),
+                                      production_queue.len() as u32,
+                                      Vector2::new(x + BAR_WIDTH + GAP, y + BAR_HEIGHT - DIGIT_HEIGHT),
+                                      DIGIT_HEIGHT);
+            }
+
+            if production_queue.repeat() {
+                let marker_y = y - DIGIT_HEIGHT - GAP;
+                render_commands.push(RenderCommand::new_filled_rect(INDICATOR_LAYER,
+                                                                    0,
+                                                                    Color::rgb(255, 220, 0),
+                                                                    Rect::of(x,
+                                                                             marker_y,
+                                                                             DIGIT_HEIGHT,
+                                                                             DIGIT_HEIGHT)));
+                digits::render_number(&mut *render_commands,
+                                      INDICATOR_LAYER,
+                                      Color::rgb(255, 220, 0),
+                                      production_queue.repeats_left(),
+                                      Vector2::new(x + DIGIT_HEIGHT + GAP, marker_y),
+                                      DIGIT_HEIGHT);
+            }
+        }
+    }
+}
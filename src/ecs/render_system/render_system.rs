@@ -19,7 +19,10 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use ecs::resource::SystemTimings;
+use logging;
 use specs;
+use std::time::Instant;
 use super::super::world::SystemGroup;
 use types::Fixed;
 
@@ -27,18 +30,31 @@ pub trait RenderSystem: Send {
     fn render(&mut self, arg: specs::RunArg, lerp: Fixed);
 }
 
-pub struct RenderSystemWrapper(Box<RenderSystem>);
+pub struct RenderSystemWrapper {
+    render_system: Box<RenderSystem>,
+    name: &'static str,
+    timings: SystemTimings,
+}
 
 impl RenderSystemWrapper {
-    pub fn new(render_system: Box<RenderSystem>) -> RenderSystemWrapper {
-        RenderSystemWrapper(render_system)
+    pub fn new(render_system: Box<RenderSystem>, name: &'static str, timings: SystemTimings) -> RenderSystemWrapper {
+        RenderSystemWrapper {
+            render_system: render_system,
+            name: name,
+            timings: timings,
+        }
     }
 }
 
 impl specs::System<(SystemGroup, Fixed)> for RenderSystemWrapper {
     fn run(&mut self, arg: specs::RunArg, params: (SystemGroup, Fixed)) {
         match params.0 {
-            SystemGroup::Render => self.0.render(arg, params.1),
+            SystemGroup::Render => {
+                let _span = trace_span!("render_system", system = self.name).entered();
+                let start = Instant::now();
+                self.render_system.render(arg, params.1);
+                self.timings.record(self.name, start.elapsed());
+            }
             _ => arg.fetch(|_| {}),
         }
     }
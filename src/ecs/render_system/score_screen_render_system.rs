@@ -0,0 +1,327 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::*;
+use identifier::PlayerId;
+use nalgebra::Vector2;
+use profile::{FAVORITE_UNIT_COUNT, Profile};
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use types::{Color, Fixed, Rect};
+use ui;
+use util::digits;
+use util::markers;
+
+const SCORE_LAYER: u16 = 2000;
+const DIGIT_HEIGHT: i32 = 10;
+const SWATCH_SIZE: i32 = 16;
+const COLUMN_WIDTH: i32 = 90;
+const PADDING: i32 = 8;
+
+/// Draws the score screens. Without text rendering, each player's row starts with a swatch
+/// of their color, in their marker shape when those are on (hollow once they've been defeated),
+/// pages are told apart by the color of their tab, and numbers are drawn as digits.
+///
+/// - Summary: a bar per player split into military (red), economy (yellow), and technology
+///   (blue) scores, followed by the total
+/// - Military: units trained, units standing, score
+/// - Economy: resources spent, tribute sent, tribute received, resources banked, villagers, score
+/// - Technology: technologies researched, score
+/// - Timeline: each player's share of the combined score at every snapshot over the game
+/// - Profile: the local player's name and preferred civilization; games played, won, and lost,
+///   and scenarios completed; units, military units, and technologies, and resources spent over
+///   every game; then a bar for each favorite unit, with its unit ID and how many were trained
+pub struct ScoreScreenRenderSystem;
+
+impl ScoreScreenRenderSystem {
+    pub fn new() -> ScoreScreenRenderSystem {
+        ScoreScreenRenderSystem
+    }
+}
+
+impl RenderSystem for ScoreScreenRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(players: Players),
+            resource(player_colors: PlayerColors),
+            resource(profile: Profile),
+            resource(score_screen: ScoreScreen),
+            resource(statistics: Statistics),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        if !score_screen.open {
+            return;
+        }
+
+        // The renderer offsets everything by the camera position, so shift the screen along with it
+        let top_left = viewport.lerped_top_left(lerp);
+        let to_screen = |mut rect: Rect| {
+            rect.translate(top_left.x, top_left.y);
+            rect
+        };
+
+        let bounds = score_screen.bounds(&ui_layout, &viewport.size);
+        let background = to_screen(bounds);
+        render_commands.push(ui_layout.panel_command(SCORE_LAYER, 0, background, Color::rgb(24, 24, 32)));
+        for (page_index, page) in SCORE_PAGES.iter().enumerate() {
+            let rect = to_screen(score_screen.tab(&bounds, page_index));
+            let color = page_color(*page);
+            render_commands.push(if *page == score_screen.page {
+                RenderCommand::new_filled_rect(SCORE_LAYER, 1, color, rect)
+            } else {
+                RenderCommand::new_rect(SCORE_LAYER, 1, color, rect)
+            });
+        }
+
+        if score_screen.page == ScorePage::Profile {
+            let rows: Vec<Rect> = (0..3 + FAVORITE_UNIT_COUNT)
+                .map(|row| to_screen(score_screen.row(&bounds, row)))
+                .collect();
+            render_profile(&mut *render_commands,
+                           &rows,
+                           &profile,
+                           player_colors.color(players.local_player_id()));
+            return;
+        }
+
+        let player_ids: Vec<PlayerId> = players.player_ids().into_iter().filter(|id| !id.is_gaia()).collect();
+        if score_screen.page == ScorePage::Timeline {
+            let content = to_screen(score_screen.content(&bounds));
+            render_timeline(&mut *render_commands,
+                            &content,
+                            &player_ids,
+                            &player_colors,
+                            statistics.timeline());
+            return;
+        }
+
+        let max_total = player_ids.iter()
+            .map(|player_id| statistics.player(*player_id).total_score())
+            .max()
+            .unwrap_or(0);
+        for (row, player_id) in player_ids.iter().enumerate() {
+            let rect = to_screen(score_screen.row(&bounds, row));
+            markers::render_marker(&mut *render_commands,
+                                   SCORE_LAYER,
+                                   player_colors.color(*player_id),
+                                   player_colors.marker(*player_id),
+                                   Vector2::new(rect.x, rect.y),
+                                   SWATCH_SIZE,
+                                   !statistics.is_defeated(*player_id));
+
+            let player_statistics = statistics.player(*player_id);
+            let x = rect.x + SWATCH_SIZE + PADDING;
+            let y = rect.y + (SWATCH_SIZE - DIGIT_HEIGHT) / 2;
+            let columns = match score_screen.page {
+                ScorePage::Summary => {
+                    let bar_width = rect.w - (x - rect.x) - COLUMN_WIDTH;
+                    render_score_bar(&mut *render_commands,
+                                     &Rect::of(x, rect.y, bar_width, SWATCH_SIZE),
+                                     &player_statistics,
+                                     max_total);
+                    let total = player_statistics.total_score();
+                    render_number(&mut *render_commands, total, Vector2::new(x + bar_width + PADDING, y));
+                    continue;
+                }
+                ScorePage::Military => {
+                    vec![player_statistics.military_units_trained,
+                         player_statistics.military_units,
+                         player_statistics.score(ScoreCategory::Military)]
+                }
+                ScorePage::Economy => {
+                    vec![player_statistics.resources_spent,
+                         player_statistics.tribute_sent,
+                         player_statistics.tribute_received,
+                         player_statistics.resources_banked,
+                         player_statistics.villagers,
+                         player_statistics.score(ScoreCategory::Economy)]
+                }
+                ScorePage::Technology => {
+                    vec![player_statistics.technologies_researched,
+                         player_statistics.score(ScoreCategory::Technology)]
+                }
+                ScorePage::Timeline | ScorePage::Profile => unreachable!(),
+            };
+            for (column, value) in columns.iter().enumerate() {
+                render_number(&mut *render_commands,
+                              *value,
+                              Vector2::new(x + column as i32 * COLUMN_WIDTH, y));
+            }
+        }
+    }
+}
+
+fn render_number(render_commands: &mut RenderCommands, value: u32, top_left: Vector2<i32>) {
+    digits::render_number(render_commands,
+                          SCORE_LAYER,
+                          Color::rgb(255, 255, 255),
+                          value,
+                          top_left,
+                          DIGIT_HEIGHT);
+}
+
+/// Splits a bar into the player's category scores, scaled so that the best total fills it
+fn render_score_bar(render_commands: &mut RenderCommands,
+                    bar: &Rect,
+                    player_statistics: &PlayerStatistics,
+                    max_total: u32) {
+    if max_total == 0 {
+        return;
+    }
+    let mut x = bar.x;
+    for category in &SCORE_CATEGORIES {
+        let width = (player_statistics.score(*category) as i64 * bar.w as i64 / max_total as i64) as i32;
+        if width > 0 {
+            render_commands.push(RenderCommand::new_filled_rect(SCORE_LAYER,
+                                                                1,
+                                                                category_color(*category),
+                                                                Rect::of(x, bar.y, width, bar.h)));
+        }
+        x += width;
+    }
+}
+
+/// Graphs each player's share of the combined score, from the first snapshot on the left
+/// to the latest on the right
+fn render_timeline(render_commands: &mut RenderCommands,
+                   area: &Rect,
+                   player_ids: &[PlayerId],
+                   player_colors: &PlayerColors,
+                   timeline: &[TimelineSnapshot]) {
+    let axis_color = Color::rgb(160, 160, 160);
+    let bottom_left = Vector2::new(area.x, area.y + area.h);
+    render_commands.push(RenderCommand::new_line(SCORE_LAYER,
+                                                 1,
+                                                 axis_color,
+                                                 Vector2::new(area.x, area.y),
+                                                 bottom_left));
+    render_commands.push(RenderCommand::new_line(SCORE_LAYER,
+                                                 1,
+                                                 axis_color,
+                                                 bottom_left,
+                                                 Vector2::new(area.x + area.w, area.y + area.h)));
+
+    let last_seconds = match timeline.last() {
+        Some(snapshot) if timeline.len() > 1 => snapshot.seconds.max(1),
+        _ => return,
+    };
+    digits::render_number(render_commands,
+                          SCORE_LAYER,
+                          axis_color,
+                          last_seconds / 60,
+                          Vector2::new(area.x + area.w - 2 * DIGIT_HEIGHT, area.y + area.h + 4),
+                          DIGIT_HEIGHT);
+
+    let point = |snapshot: &TimelineSnapshot, player_id: PlayerId| {
+        let combined: u32 = snapshot.scores.iter().map(|&(_, score)| score).sum();
+        let score = snapshot.scores
+            .iter()
+            .find(|&&(id, _)| id == player_id)
+            .map(|&(_, score)| score)
+            .unwrap_or(0);
+        let share = if combined > 0 {
+            (score as i64 * area.h as i64 / combined as i64) as i32
+        } else {
+            0
+        };
+        let x = area.x + (snapshot.seconds as i64 * area.w as i64 / last_seconds as i64) as i32;
+        Vector2::new(x, area.y + area.h - share)
+    };
+    for player_id in player_ids {
+        for pair in timeline.windows(2) {
+            render_commands.push(RenderCommand::new_line(SCORE_LAYER,
+                                                         2,
+                                                         player_colors.color(*player_id),
+                                                         point(&pair[0], *player_id),
+                                                         point(&pair[1], *player_id)));
+        }
+    }
+}
+
+/// Lays the profile out a row at a time: the name in the local player's color, then rows of
+/// numbers, then the favorite units
+fn render_profile(render_commands: &mut RenderCommands, rows: &[Rect], profile: &Profile, color: Color) {
+    let name_top_left = Vector2::new(rows[0].x, rows[0].y + (SWATCH_SIZE - DIGIT_HEIGHT) / 2);
+    ui::render_text(render_commands, SCORE_LAYER, color, &profile.name, &name_top_left, DIGIT_HEIGHT);
+    if let Some(civ) = profile.preferred_civ {
+        let x = name_top_left.x + ui::text_width(profile.name.chars().count()) + PADDING;
+        render_number(render_commands, *civ as u32, Vector2::new(x, name_top_left.y));
+    }
+
+    let statistics = &profile.statistics;
+    let columns = [vec![statistics.games_played,
+                        statistics.wins,
+                        statistics.losses,
+                        profile.completed_scenarios.len() as u32],
+                   vec![statistics.units_trained,
+                        statistics.military_units_trained,
+                        statistics.technologies_researched,
+                        statistics.resources_spent]];
+    for (row, values) in rows[1..].iter().zip(columns.iter()) {
+        for (column, value) in values.iter().enumerate() {
+            render_number(render_commands,
+                          *value,
+                          Vector2::new(row.x + column as i32 * COLUMN_WIDTH,
+                                       row.y + (SWATCH_SIZE - DIGIT_HEIGHT) / 2));
+        }
+    }
+
+    let favorites = statistics.favorite_units(FAVORITE_UNIT_COUNT);
+    let most_trained = favorites.first().map(|&(_, trained)| trained).unwrap_or(0);
+    if most_trained == 0 {
+        return;
+    }
+    for (row, &(unit_id, trained)) in rows[3..].iter().zip(favorites.iter()) {
+        let y = row.y + (SWATCH_SIZE - DIGIT_HEIGHT) / 2;
+        render_number(render_commands, *unit_id, Vector2::new(row.x, y));
+        let bar_x = row.x + COLUMN_WIDTH;
+        let bar_width = row.w - 2 * COLUMN_WIDTH;
+        let width = (trained as i64 * bar_width as i64 / most_trained as i64) as i32;
+        render_commands.push(RenderCommand::new_filled_rect(SCORE_LAYER,
+                                                            1,
+                                                            color,
+                                                            Rect::of(bar_x, row.y, width, SWATCH_SIZE)));
+        render_number(render_commands, trained, Vector2::new(bar_x + bar_width + PADDING, y));
+    }
+}
+
+fn page_color(page: ScorePage) -> Color {
+    match page {
+        ScorePage::Summary => Color::rgb(230, 230, 230),
+        ScorePage::Military => category_color(ScoreCategory::Military),
+        ScorePage::Economy => category_color(ScoreCategory::Economy),
+        ScorePage::Technology => category_color(ScoreCategory::Technology),
+        ScorePage::Timeline => Color::rgb(60, 200, 60),
+        ScorePage::Profile => Color::rgb(200, 120, 220),
+    }
+}
+
+fn category_color(category: ScoreCategory) -> Color {
+    match category {
+        ScoreCategory::Military => Color::rgb(200, 40, 40),
+        ScoreCategory::Economy => Color::rgb(230, 190, 40),
+        ScoreCategory::Technology => Color::rgb(60, 100, 220),
+    }
+}
@@ -0,0 +1,169 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{Cheats, FogOfWar, Players, RenderCommands, Terrain, ViewProjector, Viewport};
+use resource::RenderCommand;
+use specs;
+use std::cmp;
+use std::f32::consts::PI;
+use std::time::Instant;
+use super::RenderSystem;
+use types::{Color, Fixed, Vector3};
+
+/// Above the terrain, and under everything standing on it
+const FOAM_LAYER: u16 = 1;
+
+/// Seconds for the surf to wash in and back out again
+const SURF_PERIOD: f32 = 2.5;
+/// How far the surf washes out over the water from the shore, in tiles
+const SURF_REACH: f32 = 0.2;
+
+/// A side of a tile: the neighbor across it, as (row, col) offsets, where the side starts and
+/// ends in the tile, as (x, y) in tiles, and which way the tile is from the side
+struct Side {
+    neighbor: (i32, i32),
+    start: (f32, f32),
+    end: (f32, f32),
+    inward: (f32, f32),
+}
+
+const SIDES: [Side; 4] = [Side {
+                              neighbor: (-1, 0),
+                              start: (0., 0.),
+                              end: (1., 0.),
+                              inward: (0., 1.),
+                          },
+                          Side {
+                              neighbor: (1, 0),
+                              start: (0., 1.),
+                              end: (1., 1.),
+                              inward: (0., -1.),
+                          },
+                          Side {
+                              neighbor: (0, -1),
+                              start: (0., 0.),
+                              end: (0., 1.),
+                              inward: (1., 0.),
+                          },
+                          Side {
+                              neighbor: (0, 1),
+                              start: (1., 0.),
+                              end: (1., 1.),
+                              inward: (-1., 0.),
+                          }];
+
+/// Draws foam along the shore wherever explored water meets land, with a line of surf that
+/// washes out over the water and back. It's drawn fresh every frame on top of the terrain
+/// rather than baked into its chunks, so that the surf moving doesn't make them bake again.
+pub struct ShoreFoamRenderSystem {
+    started: Instant,
+}
+
+impl ShoreFoamRenderSystem {
+    pub fn new() -> ShoreFoamRenderSystem {
+        ShoreFoamRenderSystem { started: Instant::now() }
+    }
+
+    fn seconds(&self) -> f32 {
+        let elapsed = self.started.elapsed();
+        elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.
+    }
+}
+
+impl RenderSystem for ShoreFoamRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, _lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(cheats: Cheats),
+            resource(fog_of_war: FogOfWar),
+            resource(players: Players),
+            resource(projector: ViewProjector),
+            resource(terrain: Terrain),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let area = projector.calculate_visible_world_coords(&viewport, &*terrain);
+        let local_player_id = players.local_player_id();
+        let revealed_map = cheats.revealed_map(local_player_id);
+        let seconds = self.seconds();
+
+        for row in cmp::max(0, area.y)..cmp::min(terrain.height(), area.y + area.h) {
+            for col in cmp::max(0, area.x)..cmp::min(terrain.width(), area.x + area.w) {
+                let tile = terrain.tile_at_row_col(row, col);
+                if !tile.is_water() || !(revealed_map || fog_of_war.is_explored(local_player_id, row, col)) {
+                    continue;
+                }
+                let fogged = !revealed_map && !fog_of_war.is_visible(local_player_id, row, col);
+                let (foam_color, surf_color) = foam_colors(fogged);
+
+                // Neighboring tiles wash in a little after one another, so the surf ripples
+                // along the shore
+                let phase = (seconds / SURF_PERIOD + (row * 3 + col * 5) as f32 / 16.) * 2. * PI;
+                let reach = (1. - phase.cos()) / 2. * SURF_REACH;
+
+                for side in &SIDES {
+                    let (neighbor_row, neighbor_col) = (row + side.neighbor.0, col + side.neighbor.1);
+                    if neighbor_row < 0 || neighbor_row >= terrain.height() || neighbor_col < 0 ||
+                       neighbor_col >= terrain.width() ||
+                       terrain.tile_at_row_col(neighbor_row, neighbor_col).is_water() {
+                        continue;
+                    }
+
+                    let point = |x: f32, y: f32| {
+                        projector.project(&Vector3::new(Fixed::from(col as f32 + x),
+                                                        Fixed::from(row as f32 + y),
+                                                        Fixed::from(tile.elevation as i32)))
+                    };
+                    let (start, end) = (point(side.start.0, side.start.1), point(side.end.0, side.end.1));
+                    render_commands.push(RenderCommand::new_line(FOAM_LAYER,
+                                                                 start.y,
+                                                                 foam_color,
+                                                                 start,
+                                                                 end));
+
+                    // The surf is a bit shorter than the shore, so it doesn't run into the
+                    // surf off the next side around a corner
+                    let (inward_x, inward_y) = (side.inward.0 * reach, side.inward.1 * reach);
+                    let (along_x, along_y) = ((side.end.0 - side.start.0) * reach,
+                                              (side.end.1 - side.start.1) * reach);
+                    let surf_start = point(side.start.0 + inward_x + along_x,
+                                           side.start.1 + inward_y + along_y);
+                    let surf_end = point(side.end.0 + inward_x - along_x,
+                                         side.end.1 + inward_y - along_y);
+                    render_commands.push(RenderCommand::new_line(FOAM_LAYER,
+                                                                 surf_start.y,
+                                                                 surf_color,
+                                                                 surf_start,
+                                                                 surf_end));
+                }
+            }
+        }
+    }
+}
+
+/// The foam and surf colors, faded like the terrain when the water is out of sight
+fn foam_colors(fogged: bool) -> (Color, Color) {
+    if fogged {
+        (Color::rgb(172, 176, 178), Color::rgb(136, 150, 158))
+    } else {
+        (Color::rgb(236, 244, 248), Color::rgb(178, 208, 226))
+    }
+}
@@ -21,21 +21,29 @@
 
 
 use dat;
-use ecs::resource::{RenderCommands, Terrain, ViewProjector, Viewport};
+use ecs::resource::{Cheats, FogOfWar, Players, RenderCommands, Terrain, ViewProjector, Viewport};
 use ecs::resource::terrain::{BlendInfo, BorderMatch, ElevationGraphic, ElevationMatch};
-use identifier::{SlpFileId, TerrainBorderId, TerrainId};
+use identifier::{PlayerId, SlpFileId, TerrainBorderId, TerrainId};
 
 use nalgebra::Vector2;
-use resource::{DrsKey, RenderCommand, ShapeKey};
+use resource::{ChunkKey, DrsKey, RenderCommand, ShapeKey};
 use specs;
 use std::cmp;
 
 use std::collections::HashMap;
 use super::RenderSystem;
-use types::{Fixed, Rect, Vector3};
+use types::{Fixed, Rect};
 
 const TERRAIN_LAYER: u16 = 0;
 
+/// Terrain is baked into textures this many tiles on a side, which keeps a chunk's tiles within
+/// the 64 bits of the masks in its ChunkState
+const CHUNK_TILES: i32 = 8;
+
+/// The most that a tile gets raised by its elevation, in half tile heights, for working out
+/// how much room a chunk's texture needs above its tiles
+const MAX_RENDER_OFFSET_Y: f32 = 8.;
+
 lazy_static! {
     static ref DEFAULT_ELEVATION: ElevationMatch =
         ElevationMatch::new(0, ElevationGraphic::new(0, 0.));
@@ -64,15 +72,34 @@ impl<T> TileKey<T> {
     }
 }
 
+/// What a chunk was baked from: it needs baking again once one of its tiles changes, or comes
+/// into or goes out of sight. The masks have a bit for each of the chunk's tiles, in the order
+/// that chunk_tiles lists them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct ChunkState {
+    revision: u32,
+    explored: u64,
+    visible: u64,
+}
+
+/// Draws the explored terrain, with its blending and elevation, as chunks of tiles that are
+/// baked into textures once and then redrawn with a single copy each, instead of drawing every
+/// tile every frame. Tiles that are explored but out of sight are drawn in faded colors. Chunks
+/// are baked again when their tiles change or their fog does, and are let go of once they've
+/// scrolled well out of view.
 pub struct TerrainRenderSystem {
     empires: dat::EmpiresDbRef,
     tiles: HashMap<TileKey<TerrainId>, Tile<TerrainId>>,
     borders: HashMap<TileKey<TerrainBorderId>, Tile<TerrainBorderId>>,
+    chunks: HashMap<ChunkKey, ChunkState>,
 }
 
 impl RenderSystem for TerrainRenderSystem {
     fn render(&mut self, arg: specs::RunArg, _lerp: Fixed) {
         fetch_components!(arg, _entities, [
+            resource(cheats: Cheats),
+            resource(fog_of_war: FogOfWar),
+            resource(players: Players),
             resource(projector: ViewProjector),
             resource(viewport: Viewport),
             mut resource(terrain: Terrain),
@@ -80,6 +107,8 @@ impl RenderSystem for TerrainRenderSystem {
         ]);
 
         let area = projector.calculate_visible_world_coords(&viewport, &*terrain);
+        let local_player_id = players.local_player_id();
+        let revealed_map = cheats.revealed_map(local_player_id);
 
         let (tile_half_width, tile_half_height) = self.empires.tile_half_sizes();
         let (tile_width, tile_height) = (tile_half_width * 2, tile_half_height * 2);
@@ -87,33 +116,138 @@ impl RenderSystem for TerrainRenderSystem {
         let mut bounds = Rect::new();
         bounds.x = viewport.top_left_i32().x - tile_width;
         bounds.y = viewport.top_left_i32().y - tile_height;
-        bounds.w = bounds.x + viewport.size.x + 2 * tile_width;
-        bounds.h = bounds.y + viewport.size.y + 2 * tile_height;
-
-        for row in area.y..(area.y + area.h) {
-            for col in (area.x..(area.x + area.w)).rev() {
-                if row >= 0 && row < terrain.width() && col >= 0 && col < terrain.height() {
-                    let pos = projector.project(&Vector3::new(col.into(), row.into(), 0.into()));
-                    if pos.x > bounds.x && pos.y > bounds.y && pos.x < bounds.w && pos.y < bounds.h {
-                        self.blend_and_render_tile(&mut *render_commands, row, col, &mut terrain);
+        bounds.w = viewport.size.x + 2 * tile_width;
+        bounds.h = viewport.size.y + 2 * tile_height;
+
+        let first_row = cmp::max(0, area.y) / CHUNK_TILES;
+        let last_row = (cmp::min(terrain.height(), area.y + area.h) - 1) / CHUNK_TILES;
+        let first_col = cmp::max(0, area.x) / CHUNK_TILES;
+        let last_col = (cmp::min(terrain.width(), area.x + area.w) - 1) / CHUNK_TILES;
+
+        // Chunks a little way out of view are kept so that scrolling back and forth doesn't
+        // keep baking them
+        let dropped: Vec<ChunkKey> = self.chunks
+            .keys()
+            .filter(|&&(row, col)| {
+                row < first_row - 1 || row > last_row + 1 || col < first_col - 1 || col > last_col + 1
+            })
+            .cloned()
+            .collect();
+        for chunk_key in dropped {
+            self.chunks.remove(&chunk_key);
+            render_commands.drop_chunk(chunk_key);
+        }
+
+        for chunk_row in first_row..(last_row + 1) {
+            for chunk_col in first_col..(last_col + 1) {
+                let chunk_key = (chunk_row, chunk_col);
+                let state = chunk_state(chunk_key, &*terrain, &*fog_of_war, local_player_id, revealed_map);
+                let chunk_bounds = self.chunk_bounds(chunk_key, &*terrain);
+                if state.explored == 0 || !overlaps(&chunk_bounds, &bounds) {
+                    continue;
+                }
+
+                if self.chunks.get(&chunk_key) != Some(&state) {
+                    let mut contents = Vec::new();
+                    for (index, (row, col)) in chunk_tiles(chunk_key, &*terrain).into_iter().enumerate() {
+                        if state.explored & (1 << index) != 0 {
+                            let start = contents.len();
+                            self.blend_and_render_tile(&mut contents, row, col, &mut terrain);
+                            if state.visible & (1 << index) == 0 {
+                                for command in &mut contents[start..] {
+                                    fade_into_fog(command);
+                                }
+                            }
+                        }
                     }
+                    render_commands.bake_chunk(chunk_key, chunk_bounds, contents);
+                    self.chunks.insert(chunk_key, state);
                 }
+                render_commands.push(RenderCommand::new_chunk(TERRAIN_LAYER, chunk_bounds.y, chunk_key));
             }
         }
     }
 }
 
+/// The tiles in the chunk that are on the map, as (row, col)
+fn chunk_tiles(chunk_key: ChunkKey, terrain: &Terrain) -> Vec<(i32, i32)> {
+    let (first_row, first_col) = (chunk_key.0 * CHUNK_TILES, chunk_key.1 * CHUNK_TILES);
+    let last_row = cmp::min(first_row + CHUNK_TILES, terrain.height());
+    let last_col = cmp::min(first_col + CHUNK_TILES, terrain.width());
+    let mut tiles = Vec::new();
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            tiles.push((row, col));
+        }
+    }
+    tiles
+}
+
+fn chunk_state(chunk_key: ChunkKey,
+               terrain: &Terrain,
+               fog_of_war: &FogOfWar,
+               player_id: PlayerId,
+               revealed_map: bool)
+               -> ChunkState {
+    let mut state = ChunkState {
+        revision: 0,
+        explored: 0,
+        visible: 0,
+    };
+    for (index, (row, col)) in chunk_tiles(chunk_key, terrain).into_iter().enumerate() {
+        // Revisions only go up, so the newest one changes whenever any of the tiles do
+        state.revision = cmp::max(state.revision, terrain.tile_at_row_col(row, col).revision());
+        if revealed_map || fog_of_war.is_explored(player_id, row, col) {
+            state.explored |= 1 << index;
+        }
+        if revealed_map || fog_of_war.is_visible(player_id, row, col) {
+            state.visible |= 1 << index;
+        }
+    }
+    state
+}
+
+fn fade_into_fog(command: &mut RenderCommand) {
+    if let RenderCommand::RenderShape(_, ref mut params) = *command {
+        params.shape_key = params.shape_key.under_fog();
+    }
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
 impl TerrainRenderSystem {
     pub fn new(empires: dat::EmpiresDbRef) -> TerrainRenderSystem {
         TerrainRenderSystem {
             empires: empires,
             tiles: HashMap::new(),
             borders: HashMap::new(),
+            chunks: HashMap::new(),
         }
     }
 
+    /// Where the chunk's texture goes, with a tile's room on every side for the tile graphics
+    /// that stick out over their neighbors, and more above for elevation
+    fn chunk_bounds(&self, chunk_key: ChunkKey, terrain: &Terrain) -> Rect {
+        let (tile_half_width, tile_half_height) = self.empires.tile_half_sizes();
+        let (tile_width, tile_height) = (tile_half_width * 2, tile_half_height * 2);
+        let (first_row, first_col) = (chunk_key.0 * CHUNK_TILES, chunk_key.1 * CHUNK_TILES);
+        let last_row = cmp::min(first_row + CHUNK_TILES, terrain.height()) - 1;
+        let last_col = cmp::min(first_col + CHUNK_TILES, terrain.width()) - 1;
+
+        let (left, _) = self.project_row_col(first_row, first_col, 0.);
+        let (right, _) = self.project_row_col(last_row, last_col, 0.);
+        let (_, top) = self.project_row_col(first_row, last_col, MAX_RENDER_OFFSET_Y);
+        let (_, bottom) = self.project_row_col(last_row, first_col, 0.);
+        Rect::of(left - tile_width,
+                 top - tile_height,
+                 right - left + 3 * tile_width,
+                 bottom - top + 3 * tile_height)
+    }
+
     fn blend_and_render_tile(&mut self,
-                             render_commands: &mut RenderCommands,
+                             render_commands: &mut Vec<RenderCommand>,
                              row: i32,
                              col: i32,
                              terrain: &mut Terrain) {
@@ -155,7 +289,7 @@ impl TerrainRenderSystem {
     }
 
     fn render_tile<T>(&self,
-                      render_commands: &mut RenderCommands,
+                      render_commands: &mut Vec<RenderCommand>,
                       drs_key: DrsKey,
                       tile: &Tile<T>,
                       render_offset_y: f32,
@@ -174,7 +308,7 @@ impl TerrainRenderSystem {
     }
 
     fn render_borders(&mut self,
-                      render_commands: &mut RenderCommands,
+                      render_commands: &mut Vec<RenderCommand>,
                       border_id: TerrainBorderId,
                       border_indices: &'static [u16],
                       elevation_index: u8,
@@ -209,8 +343,7 @@ impl TerrainRenderSystem {
         match ElevationMatch::find_match(blended_tile.elevation_matrix) {
             Some(elevation_match) => elevation_match,
             None => {
-                println!("Elevation match failed:\n{:?}",
-                         blended_tile.elevation_matrix);
+                warn!("Elevation match failed:\n{:?}", blended_tile.elevation_matrix);
                 &DEFAULT_ELEVATION
             }
         }
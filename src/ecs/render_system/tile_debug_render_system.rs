@@ -54,11 +54,11 @@ impl RenderSystem for TileDebugRenderSystem {
             let col: i32 = tile_pos.x.round().into();
             let actual_tile = *terrain.tile_at(tile_pos);
             let blend_info = *terrain.blend_at(row, col);
-            println!("\nTile under cursor ({}, {}):\n{:?}\n{:#?}\n",
-                     row,
-                     col,
-                     actual_tile,
-                     blend_info);
+            info!("Tile under cursor ({}, {}):\n{:?}\n{:#?}",
+                  row,
+                  col,
+                  actual_tile,
+                  blend_info);
         }
 
         // Draw a cactus at the tile's position
@@ -0,0 +1,145 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{SelectedUnitComponent, UnitComponent};
+use ecs::resource::{DebugOverlay, OccupiedTiles, PathFinder, RenderCommands, Terrain, ViewProjector,
+                    Viewport};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs::{self, Join};
+use std::cmp;
+use super::RenderSystem;
+use types::{Color, Fixed, Vector3};
+use util::digits;
+
+const TILE_OVERLAY_LAYER: u16 = 1500;
+const DIGIT_HEIGHT: i32 = 5;
+const DIGIT_GAP: i32 = 3;
+
+/// Outlines every visible tile, colored by whether the first selected unit can walk over it,
+/// and labels each tile with its row and column, with its elevation underneath. Tiles are gray
+/// while nothing is selected and orange while something is standing on them.
+/// Toggled with the debug console's `tiles` command.
+pub struct TileOverlayRenderSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl TileOverlayRenderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> TileOverlayRenderSystem {
+        TileOverlayRenderSystem { empires: empires }
+    }
+}
+
+impl RenderSystem for TileOverlayRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, _lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            components(selected_units: SelectedUnitComponent),
+            components(units: UnitComponent),
+            resource(debug_overlay: DebugOverlay),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(projector: ViewProjector),
+            resource(terrain: Terrain),
+            resource(viewport: Viewport),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        if !debug_overlay.show_tiles {
+            return;
+        }
+
+        let restriction_id = (&units, &selected_units)
+            .iter()
+            .map(|(unit, _)| self.empires.unit(unit.civilization_id, unit.unit_id).terrain_restriction)
+            .next();
+
+        // The visible area is approximate, so pad it by a tile on every side
+        let visible = projector.calculate_visible_world_coords(&viewport, &*terrain);
+        let (start_col, start_row) = (cmp::max(0, visible.x - 1), cmp::max(0, visible.y - 1));
+        let end_col = cmp::min(terrain.width(), visible.x + visible.w + 2);
+        let end_row = cmp::min(terrain.height(), visible.y + visible.h + 2);
+
+        for row in start_row..end_row {
+            for col in start_col..end_col {
+                let tile = terrain.tile_at_row_col(row, col);
+                let color = if occupied_tiles.tiles.contains(&(row, col)) {
+                    Color::rgb(255, 150, 0)
+                } else {
+                    match restriction_id {
+                        Some(restriction_id) if path_finder.passable(restriction_id, tile.terrain_id) => {
+                            Color::rgb(60, 220, 60)
+                        }
+                        Some(_) => Color::rgb(220, 40, 40),
+                        None => Color::rgb(160, 160, 160),
+                    }
+                };
+
+                let elevation = tile.elevation as i32;
+                let corners = [projector.project(&tile_point(col, row, elevation)),
+                               projector.project(&tile_point(col + 1, row, elevation)),
+                               projector.project(&tile_point(col + 1, row + 1, elevation)),
+                               projector.project(&tile_point(col, row + 1, elevation))];
+                for index in 0..corners.len() {
+                    render_commands.push(RenderCommand::new_line(TILE_OVERLAY_LAYER,
+                                                                 0,
+                                                                 color,
+                                                                 corners[index],
+                                                                 corners[(index + 1) % corners.len()]));
+                }
+
+                let center = projector.project(&Vector3::new(Fixed::from(col) + fixed_const!(0.5),
+                                                             Fixed::from(row) + fixed_const!(0.5),
+                                                             elevation.into()));
+                let white = Color::rgb(255, 255, 255);
+                let coordinates_width = digits::number_width(row as u32, DIGIT_HEIGHT) + DIGIT_GAP +
+                                        digits::number_width(col as u32, DIGIT_HEIGHT);
+                let coordinates_x = center.x - coordinates_width / 2;
+                let coordinates_y = center.y - DIGIT_HEIGHT - 1;
+                digits::render_number(&mut *render_commands,
+                                      TILE_OVERLAY_LAYER,
+                                      white,
+                                      row as u32,
+                                      Vector2::new(coordinates_x, coordinates_y),
+                                      DIGIT_HEIGHT);
+                digits::render_number(&mut *render_commands,
+                                      TILE_OVERLAY_LAYER,
+                                      white,
+                                      col as u32,
+                                      Vector2::new(coordinates_x + coordinates_width -
+                                                   digits::number_width(col as u32, DIGIT_HEIGHT),
+                                                   coordinates_y),
+                                      DIGIT_HEIGHT);
+                let elevation_width = digits::number_width(elevation as u32, DIGIT_HEIGHT);
+                digits::render_number(&mut *render_commands,
+                                      TILE_OVERLAY_LAYER,
+                                      Color::rgb(255, 255, 0),
+                                      elevation as u32,
+                                      Vector2::new(center.x - elevation_width / 2, center.y + 1),
+                                      DIGIT_HEIGHT);
+            }
+        }
+    }
+}
+
+fn tile_point(col: i32, row: i32, elevation: i32) -> Vector3 {
+    Vector3::new(col.into(), row.into(), elevation.into())
+}
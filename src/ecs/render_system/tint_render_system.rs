@@ -0,0 +1,64 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{RenderCommands, SceneTint};
+use resource::RenderCommand;
+use specs;
+use std::time::Instant;
+use super::RenderSystem;
+use types::{Color, Fixed};
+
+/// Over the world, and under the overlays and the HUD, so that they keep their colors
+const TINT_LAYER: u16 = 1000;
+
+/// Tints the world for the time of day or the scenario. The day goes by in real time, apart
+/// from the simulation, so it carries on through pauses and changes of game speed.
+pub struct TintRenderSystem {
+    started: Instant,
+}
+
+impl TintRenderSystem {
+    pub fn new() -> TintRenderSystem {
+        TintRenderSystem { started: Instant::now() }
+    }
+
+    fn seconds(&self) -> f32 {
+        let elapsed = self.started.elapsed();
+        elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.
+    }
+}
+
+impl RenderSystem for TintRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, _lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(scene_tint: SceneTint),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        match scene_tint.color(self.seconds()) {
+            // Multiplying by white wouldn't change anything
+            Some(color) if color != Color::rgb(255, 255, 255) => {
+                render_commands.push(RenderCommand::new_tint(TINT_LAYER, 0, color));
+            }
+            _ => {}
+        }
+    }
+}
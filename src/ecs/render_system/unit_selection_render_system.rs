@@ -21,7 +21,7 @@
 
 use dat;
 use ecs::{OnScreenComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
-use ecs::resource::{RenderCommands, ViewProjector};
+use ecs::resource::{RenderCommands, SelectionSubgroup, ViewProjector};
 use resource::RenderCommand;
 use specs::{self, Join};
 use super::RenderSystem;
@@ -46,6 +46,7 @@ impl RenderSystem for UnitSelectionRenderSystem {
             components(on_screen: OnScreenComponent),
             components(selected_units: SelectedUnitComponent),
             resource(projector: ViewProjector),
+            resource(selection_subgroup: SelectionSubgroup),
             mut resource(render_commands: RenderCommands),
         ]);
 
@@ -55,7 +56,12 @@ impl RenderSystem for UnitSelectionRenderSystem {
             let unit_box = unit::selection_box(unit_info, transform);
             let position = projector.project(&transform.lerped_position(lerp));
 
-            let color = Color::rgb(255, 255, 255);
+            // Units outside the subgroup that Tab picked are dimmed since commands skip them
+            let color = if selection_subgroup.contains(unit.unit_id) {
+                Color::rgb(255, 255, 255)
+            } else {
+                Color::rgb(120, 120, 120)
+            };
             let points: [Vector3; 4] = [unit_box.min,
                                         Vector3::new(unit_box.max.x, unit_box.min.y, unit_box.min.z),
                                         Vector3::new(unit_box.max.x, unit_box.max.y, unit_box.min.z),
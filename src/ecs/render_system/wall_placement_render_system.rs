@@ -0,0 +1,98 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::*;
+use input::InputActions;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use specs;
+use super::RenderSystem;
+use super::diplomacy_panel_render_system::resource_color;
+use types::{Color, Fixed, Vector3};
+use util::digits;
+
+const WALL_PLACEMENT_LAYER: u16 = 1500;
+const DIGIT_HEIGHT: i32 = 8;
+const COST_OFFSET: i32 = 16;
+const COST_SPACING: i32 = 10;
+
+/// Outlines the tiles of the wall being placed, green for the segments it's going to build and
+/// red for the blocked tiles it skips, and lists what it costs next to the pointer
+pub struct WallPlacementRenderSystem;
+
+impl WallPlacementRenderSystem {
+    pub fn new() -> WallPlacementRenderSystem {
+        WallPlacementRenderSystem
+    }
+}
+
+impl RenderSystem for WallPlacementRenderSystem {
+    fn render(&mut self, arg: specs::RunArg, lerp: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(input_actions: InputActions),
+            resource(projector: ViewProjector),
+            resource(terrain: Terrain),
+            resource(viewport: Viewport),
+            resource(wall_placement: WallPlacement),
+            mut resource(render_commands: RenderCommands),
+        ]);
+
+        let plan = match wall_placement.plan {
+            Some(ref plan) => plan,
+            None => return,
+        };
+
+        let outlines = plan.segments
+            .iter()
+            .map(|segment| (segment.tile, Color::rgb(60, 220, 60)))
+            .chain(plan.skipped.iter().map(|&tile| (tile, Color::rgb(220, 40, 40))));
+        for ((row, col), color) in outlines {
+            let elevation = terrain.tile_at_row_col(row, col).elevation as i32;
+            let corner = |col: i32, row: i32| {
+                projector.project(&Vector3::new(col.into(), row.into(), elevation.into()))
+            };
+            let corners = [corner(col, row),
+                           corner(col + 1, row),
+                           corner(col + 1, row + 1),
+                           corner(col, row + 1)];
+            for index in 0..corners.len() {
+                render_commands.push(RenderCommand::new_line(WALL_PLACEMENT_LAYER,
+                                                             0,
+                                                             color,
+                                                             corners[index],
+                                                             corners[(index + 1) % corners.len()]));
+            }
+        }
+
+        let mut cost_position = input_actions.pointer + viewport.lerped_top_left(lerp) +
+                                Vector2::new(COST_OFFSET, COST_OFFSET);
+        for &(resource_type, amount) in &wall_placement.cost {
+            let amount: u32 = amount.into();
+            digits::render_number(&mut *render_commands,
+                                  WALL_PLACEMENT_LAYER,
+                                  resource_color(resource_type),
+                                  amount,
+                                  cost_position,
+                                  DIGIT_HEIGHT);
+            cost_position.x += digits::number_width(amount, DIGIT_HEIGHT) + COST_SPACING;
+        }
+    }
+}
@@ -0,0 +1,261 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{SoundGroupId, TerrainId};
+use std::cmp::{self, Ordering};
+use super::Terrain;
+use types::{Fixed, Vector3};
+
+/// The map is split into squares this many tiles across, and each gets an emitter for each kind of
+/// feature in it
+const EMITTER_CELL_SIZE: i32 = 8;
+
+/// A square with fewer tiles of a feature than this is too little of it to be heard
+const MIN_EMITTER_TILES: u32 = 3;
+
+/// An emitter with this many tiles is as loud as they get
+const FULL_EMITTER_TILES: u32 = 16;
+
+/// Emitters are heard at full volume up to this far from the middle of the view, in tiles, and
+/// fade out from there until they can't be heard at all at `HEARING_DISTANCE`
+const NEAR_DISTANCE: f32 = 6.0;
+const HEARING_DISTANCE: f32 = 20.0;
+
+/// How far off to the side, in tiles, an emitter has to be to be heard from just one speaker
+const PAN_DISTANCE: f32 = 16.0;
+
+/// Only the loudest few are heard, so that the map doesn't drown out everything else
+pub const MAX_AUDIBLE_EMITTERS: usize = 4;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AmbientKind {
+    /// Water that meets the land, where the waves wash in
+    Shore,
+    Forest,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmbientEmitter {
+    pub kind: AmbientKind,
+    pub voice: SoundGroupId,
+    /// The middle of the tiles it's heard from
+    pub position: Vector3,
+    pub tile_count: u32,
+}
+
+/// How an emitter is heard from where the player is looking
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AmbientMix {
+    /// Index into the emitters
+    pub emitter: usize,
+    pub volume: f32,
+    /// From -1 (left) to 1 (right)
+    pub pan: f32,
+}
+
+/// The sounds of the map itself, like waves on the shore and birds in the forests. The emitters
+/// are placed when the map is loaded, each with the sound of the terrain under it.
+pub struct AmbientSounds {
+    emitters: Vec<AmbientEmitter>,
+}
+
+impl AmbientSounds {
+    /// Places emitters along the shores and in the forests; `terrain_sound` gives the sound of a
+    /// terrain, for those that have one
+    pub fn from_terrain<F>(terrain: &Terrain, terrain_sound: F) -> AmbientSounds
+        where F: Fn(TerrainId) -> Option<SoundGroupId>
+    {
+        let mut emitters = Vec::new();
+        let cells = |tiles: i32| (tiles + EMITTER_CELL_SIZE - 1) / EMITTER_CELL_SIZE;
+        for cell_row in 0..cells(terrain.height()) {
+            for cell_col in 0..cells(terrain.width()) {
+                // The kind, sound, sums of the columns and rows, and count of the tiles of each
+                // feature in the square
+                let mut features: Vec<(AmbientKind, SoundGroupId, f32, f32, u32)> = Vec::new();
+                let first_row = cell_row * EMITTER_CELL_SIZE;
+                let first_col = cell_col * EMITTER_CELL_SIZE;
+                for row in first_row..cmp::min(terrain.height(), first_row + EMITTER_CELL_SIZE) {
+                    for col in first_col..cmp::min(terrain.width(), first_col + EMITTER_CELL_SIZE) {
+                        let tile = terrain.tile_at_row_col(row, col);
+                        let kind = if tile.is_water() && is_shore(terrain, row, col) {
+                            AmbientKind::Shore
+                        } else if tile.is_forest() {
+                            AmbientKind::Forest
+                        } else {
+                            continue;
+                        };
+                        let voice = match terrain_sound(tile.terrain_id) {
+                            Some(voice) => voice,
+                            None => continue,
+                        };
+                        if let Some(index) = features.iter().position(|f| f.0 == kind && f.1 == voice) {
+                            features[index].2 += col as f32;
+                            features[index].3 += row as f32;
+                            features[index].4 += 1;
+                        } else {
+                            features.push((kind, voice, col as f32, row as f32, 1));
+                        }
+                    }
+                }
+
+                for (kind, voice, col_sum, row_sum, count) in features {
+                    if count < MIN_EMITTER_TILES {
+                        continue;
+                    }
+                    let middle = |sum: f32| Fixed::from(sum / count as f32 + 0.5);
+                    emitters.push(AmbientEmitter {
+                        kind: kind,
+                        voice: voice,
+                        position: Vector3::new(middle(col_sum), middle(row_sum), 0.into()),
+                        tile_count: count,
+                    });
+                }
+            }
+        }
+        AmbientSounds { emitters: emitters }
+    }
+
+    pub fn emitters<'a>(&'a self) -> &'a [AmbientEmitter] {
+        &self.emitters
+    }
+
+    /// The emitters that can be heard from the listener, loudest first
+    pub fn mix(&self, listener: &Vector3) -> Vec<AmbientMix> {
+        let (listener_x, listener_y): (f32, f32) = (listener.x.into(), listener.y.into());
+        let mut heard: Vec<AmbientMix> = self.emitters
+            .iter()
+            .enumerate()
+            .filter_map(|(index, emitter)| {
+                let (x, y): (f32, f32) = (emitter.position.x.into(), emitter.position.y.into());
+                let (dx, dy) = (x - listener_x, y - listener_y);
+                let distance = (dx * dx + dy * dy).sqrt();
+                let fade = (distance - NEAR_DISTANCE) / (HEARING_DISTANCE - NEAR_DISTANCE);
+                let falloff = 1.0 - fade.max(0.0);
+                if falloff <= 0.0 {
+                    return None;
+                }
+                let size = (emitter.tile_count as f32 / FULL_EMITTER_TILES as f32).min(1.0);
+
+                // The screen's x axis runs along x + y in the world
+                let pan = ((dx + dy) / PAN_DISTANCE).max(-1.0).min(1.0);
+                Some(AmbientMix {
+                    emitter: index,
+                    volume: falloff * size,
+                    pan: pan,
+                })
+            })
+            .collect();
+        heard.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap_or(Ordering::Equal));
+        heard.truncate(MAX_AUDIBLE_EMITTERS);
+        heard
+    }
+}
+
+fn is_shore(terrain: &Terrain, row: i32, col: i32) -> bool {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|&(row_offset, col_offset)| {
+        let (row, col) = (row + row_offset, col + col_offset);
+        row >= 0 && col >= 0 && row < terrain.height() && col < terrain.width() &&
+        !terrain.tile_at_row_col(row, col).is_water()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{EmpiresDb, EmpiresDbRef};
+    use ecs::resource::{Terrain, Tile};
+    use identifier::{SoundGroupId, TerrainId};
+    use super::*;
+    use types::{Fixed, Vector3};
+
+    const WATER: usize = 1;
+    const GRASS: usize = 0;
+
+    /// Water on the left half and grass on the right
+    fn coast(size: i32) -> Terrain {
+        let tiles = (0..size * size)
+            .map(|index| Tile::new((if index % size < size / 2 { WATER } else { GRASS }).into(), 0))
+            .collect();
+        Terrain::new(size, size, tiles, EmpiresDbRef::new(EmpiresDb::new()))
+    }
+
+    fn water_sound(terrain_id: TerrainId) -> Option<SoundGroupId> {
+        if *terrain_id as usize == WATER {
+            Some(5usize.into())
+        } else {
+            None
+        }
+    }
+
+    fn emitter(x: f32, y: f32, tile_count: u32) -> AmbientEmitter {
+        AmbientEmitter {
+            kind: AmbientKind::Forest,
+            voice: 0usize.into(),
+            position: Vector3::new(Fixed::from(x), Fixed::from(y), 0.into()),
+            tile_count: tile_count,
+        }
+    }
+
+    fn at(x: f32, y: f32) -> Vector3 {
+        Vector3::new(Fixed::from(x), Fixed::from(y), 0.into())
+    }
+
+    #[test]
+    fn test_emitters_follow_the_shore() {
+        let ambient = AmbientSounds::from_terrain(&coast(8), water_sound);
+        assert_eq!(vec![AmbientEmitter {
+                            kind: AmbientKind::Shore,
+                            voice: 5usize.into(),
+                            position: at(3.5, 4.0),
+                            tile_count: 8,
+                        }],
+                   ambient.emitters().to_vec());
+    }
+
+    #[test]
+    fn test_terrain_without_sounds_is_quiet() {
+        let ambient = AmbientSounds::from_terrain(&coast(16), |_| None);
+        assert!(ambient.emitters().is_empty());
+    }
+
+    #[test]
+    fn test_emitters_fade_with_distance() {
+        let ambient = AmbientSounds { emitters: vec![emitter(10.0, 10.0, 16), emitter(100.0, 100.0, 16)] };
+        assert_eq!(vec![AmbientMix {
+                            emitter: 0,
+                            volume: 1.0,
+                            pan: 0.0,
+                        }],
+                   ambient.mix(&at(10.0, 10.0)));
+
+        let heard = ambient.mix(&at(2.0, 2.0));
+        assert_eq!(1, heard.len());
+        assert!(heard[0].volume > 0.0 && heard[0].volume < 1.0);
+        assert_eq!(1.0, heard[0].pan);
+    }
+
+    #[test]
+    fn test_only_the_loudest_are_heard() {
+        let emitters = (0..6).map(|tile_count| emitter(10.0, 10.0, tile_count * 3)).collect();
+        let ambient = AmbientSounds { emitters: emitters };
+        let heard: Vec<usize> = ambient.mix(&at(10.0, 10.0)).iter().map(|mix| mix.emitter).collect();
+        assert_eq!(vec![5, 4, 3, 2], heard);
+    }
+}
@@ -0,0 +1,95 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use nalgebra::Vector2;
+use partition::GridPartition;
+use std::collections::{HashMap, HashSet};
+
+/// A rectangle of tiles, including both corners
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Area {
+    pub start: Vector2<i32>,
+    pub end: Vector2<i32>,
+}
+
+impl Area {
+    pub fn new(start: Vector2<i32>, end: Vector2<i32>) -> Area {
+        Area {
+            start: Vector2::new(start.x.min(end.x), start.y.min(end.y)),
+            end: Vector2::new(start.x.max(end.x), start.y.max(end.y)),
+        }
+    }
+}
+
+/// Remembers which entities the grid partition found in an area, so that triggers and AI
+/// scripts asking about the same areas each tick only query the grid once for each of them.
+/// Everything is forgotten as soon as anything moves.
+pub struct AreaQueryCache {
+    generation: u64,
+    areas: HashMap<Area, HashSet<u32>>,
+}
+
+impl AreaQueryCache {
+    pub fn new() -> AreaQueryCache {
+        AreaQueryCache {
+            generation: 0,
+            areas: HashMap::new(),
+        }
+    }
+
+    /// IDs of the entities in the area
+    pub fn entities_in<'a>(&'a mut self, grid: &GridPartition, area: &Area) -> &'a HashSet<u32> {
+        if grid.generation() != self.generation {
+            self.areas.clear();
+            self.generation = grid.generation();
+        }
+        self.areas.entry(*area).or_insert_with(|| grid.query(&area.start, &area.end))
+    }
+
+    pub fn cached_count(&self) -> usize {
+        self.areas.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+    use partition::GridPartition;
+    use super::{Area, AreaQueryCache};
+
+    #[test]
+    fn test_results_are_cached_until_something_moves() {
+        let mut grid = GridPartition::new(4, 4);
+        grid.update_entity(1, &Vector2::new(2, 2));
+        grid.update_entity(2, &Vector2::new(9, 9));
+        let area = Area::new(Vector2::new(5, 5), Vector2::new(0, 0));
+
+        let mut cache = AreaQueryCache::new();
+        assert_eq!(1, cache.entities_in(&grid, &area).len());
+        assert_eq!(1, cache.entities_in(&grid, &area).len());
+        assert_eq!(1, grid.query_stats().queries);
+        assert_eq!(1, cache.cached_count());
+
+        grid.update_entity(2, &Vector2::new(3, 3));
+        assert_eq!(2, cache.entities_in(&grid, &area).len());
+        assert_eq!(2, grid.query_stats().queries);
+    }
+}
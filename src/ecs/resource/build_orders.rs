@@ -0,0 +1,66 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{CivilizationId, PlayerId, UnitId};
+use specs::Index;
+use std::mem;
+use types::Vector3;
+
+/// One building in a build order
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BuildSite {
+    /// Where the building goes, lined up with the tiles
+    pub position: Vector3,
+    /// The frame of the graphic to start on, for the pieces of a wall
+    pub frame: u16,
+}
+
+/// Foundations for a player to lay down and the villagers to build them
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildOrder {
+    pub player_id: PlayerId,
+    pub civilization_id: CivilizationId,
+    pub unit_id: UnitId,
+    pub sites: Vec<BuildSite>,
+    /// The villagers that go and build the foundations, in the order they were laid down
+    pub builders: Vec<Index>,
+}
+
+/// Orders to build buildings, carried out by the BuildOrderSystem on its next update
+pub struct BuildOrders {
+    orders: Vec<BuildOrder>,
+}
+
+impl BuildOrders {
+    pub fn new() -> BuildOrders {
+        BuildOrders { orders: Vec::new() }
+    }
+
+    pub fn queue_order(&mut self, order: BuildOrder) {
+        self.orders.push(order);
+    }
+
+    pub fn consume_orders(&mut self) -> Vec<BuildOrder> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.orders);
+        consumed
+    }
+}
@@ -0,0 +1,118 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use std::mem;
+
+/// Longest message that can be typed into the chat box
+pub const MAX_MESSAGE_LENGTH: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    pub player_id: PlayerId,
+    pub text: String,
+}
+
+impl ChatMessage {
+    pub fn new(player_id: PlayerId, text: String) -> ChatMessage {
+        ChatMessage {
+            player_id: player_id,
+            text: text,
+        }
+    }
+}
+
+/// The local player's chat box, and every message sent so far
+pub struct Chat {
+    pub open: bool,
+    draft: String,
+    messages: Vec<ChatMessage>,
+}
+
+impl Chat {
+    pub fn new() -> Chat {
+        Chat {
+            open: false,
+            draft: String::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// The message currently being typed
+    pub fn draft<'a>(&'a self) -> &'a str {
+        &self.draft
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        for character in text.chars() {
+            if self.draft.chars().count() >= MAX_MESSAGE_LENGTH {
+                break;
+            }
+            self.draft.push(character);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.draft.pop();
+    }
+
+    /// Closes the chat box and returns what was typed into it
+    pub fn take_draft(&mut self) -> String {
+        self.open = false;
+        let mut draft = String::new();
+        mem::swap(&mut draft, &mut self.draft);
+        draft
+    }
+
+    pub fn add_message(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+    }
+
+    pub fn messages<'a>(&'a self) -> &'a [ChatMessage] {
+        &self.messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_draft_editing() {
+        let mut chat = Chat::new();
+        chat.open = true;
+        chat.type_text("coinagex");
+        chat.backspace();
+        assert_eq!("coinage", chat.draft());
+
+        assert_eq!("coinage", chat.take_draft());
+        assert!(!chat.open);
+        assert_eq!("", chat.draft());
+    }
+
+    #[test]
+    fn test_chat_draft_length_limit() {
+        let mut chat = Chat::new();
+        let long_text: String = (0..MAX_MESSAGE_LENGTH + 10).map(|_| 'a').collect();
+        chat.type_text(&long_text);
+        assert_eq!(MAX_MESSAGE_LENGTH, chat.draft().len());
+    }
+}
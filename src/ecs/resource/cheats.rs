@@ -0,0 +1,249 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::{PlayerId, UnitId};
+use std::collections::HashSet;
+use std::mem;
+use types::Fixed;
+
+/// Amount of a resource granted by the resource cheats
+pub const CHEAT_RESOURCE_AMOUNT: Fixed = fixed_const!(1000);
+
+/// The car with a rocket launcher; it's an ordinary unit in empires.dat that's just never trained
+const BIG_DADDY_UNIT_ID: usize = 748;
+
+pub fn big_daddy_unit_id() -> UnitId {
+    BIG_DADDY_UNIT_ID.into()
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cheat {
+    GrantResource(ResourceType),
+    InstantBuild,
+    RevealMap,
+    BigDaddy,
+}
+
+impl Cheat {
+    /// Matches a chat message against the original cheat codes, ignoring case and surrounding whitespace
+    pub fn parse(text: &str) -> Option<Cheat> {
+        use self::Cheat::*;
+        Some(match &text.trim().to_lowercase()[..] {
+            "pepperoni pizza" => GrantResource(ResourceType::Food),
+            "woodstock" => GrantResource(ResourceType::Wood),
+            "quarry" => GrantResource(ResourceType::Stone),
+            "coinage" => GrantResource(ResourceType::Gold),
+            "steroids" => InstantBuild,
+            "reveal map" => RevealMap,
+            "bigdaddy" => BigDaddy,
+            _ => return None,
+        })
+    }
+
+    /// The code that's typed in for the cheat, which `parse` reads back
+    pub fn code(&self) -> &'static str {
+        use self::Cheat::*;
+        match *self {
+            GrantResource(ResourceType::Food) => "pepperoni pizza",
+            GrantResource(ResourceType::Wood) => "woodstock",
+            GrantResource(ResourceType::Stone) => "quarry",
+            GrantResource(_) => "coinage",
+            InstantBuild => "steroids",
+            RevealMap => "reveal map",
+            BigDaddy => "bigdaddy",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CheatRequest {
+    pub player_id: PlayerId,
+    pub cheat: Cheat,
+}
+
+impl CheatRequest {
+    pub fn new(player_id: PlayerId, cheat: Cheat) -> CheatRequest {
+        CheatRequest {
+            player_id: player_id,
+            cheat: cheat,
+        }
+    }
+}
+
+/// A cheat that was applied, and the simulation tick it was applied on
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CheatRecord {
+    pub tick: usize,
+    pub request: CheatRequest,
+}
+
+pub struct Cheats {
+    allowed: bool,
+    requests: Vec<CheatRequest>,
+    history: Vec<CheatRecord>,
+    instant_build: HashSet<PlayerId>,
+    revealed_map: HashSet<PlayerId>,
+}
+
+impl Cheats {
+    pub fn new(allowed: bool) -> Cheats {
+        Cheats {
+            allowed: allowed,
+            requests: Vec::new(),
+            history: Vec::new(),
+            instant_build: HashSet::new(),
+            revealed_map: HashSet::new(),
+        }
+    }
+
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Queues a cheat to be applied on the next update; ignored if the lobby didn't allow cheats
+    pub fn queue_cheat(&mut self, request: CheatRequest) -> bool {
+        if self.allowed {
+            self.requests.push(request);
+        }
+        self.allowed
+    }
+
+    pub fn consume_requests(&mut self) -> Vec<CheatRequest> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.requests);
+        consumed
+    }
+
+    pub fn record(&mut self, tick: usize, request: CheatRequest) {
+        self.history.push(CheatRecord {
+            tick: tick,
+            request: request,
+        });
+    }
+
+    /// Every cheat applied so far, in order; replays and saves store these so that playback can
+    /// be verified
+    pub fn history<'a>(&'a self) -> &'a [CheatRecord] {
+        &self.history
+    }
+
+    /// Puts back the cheats of a saved game, turning back on the ones that were left on. What the
+    /// others did, like the resources they granted, was saved along with the rest of the game.
+    pub fn restore_history(&mut self, history: Vec<CheatRecord>) {
+        for record in &history {
+            match record.request.cheat {
+                Cheat::InstantBuild => self.toggle_instant_build(record.request.player_id),
+                Cheat::RevealMap => self.toggle_revealed_map(record.request.player_id),
+                Cheat::GrantResource(_) | Cheat::BigDaddy => {}
+            }
+        }
+        self.history = history;
+    }
+
+    pub fn toggle_instant_build(&mut self, player_id: PlayerId) {
+        if !self.instant_build.remove(&player_id) {
+            self.instant_build.insert(player_id);
+        }
+    }
+
+    pub fn instant_build(&self, player_id: PlayerId) -> bool {
+        self.instant_build.contains(&player_id)
+    }
+
+    pub fn toggle_revealed_map(&mut self, player_id: PlayerId) {
+        if !self.revealed_map.remove(&player_id) {
+            self.revealed_map.insert(player_id);
+        }
+    }
+
+    pub fn revealed_map(&self, player_id: PlayerId) -> bool {
+        self.revealed_map.contains(&player_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use super::*;
+
+    #[test]
+    fn test_cheat_parse() {
+        assert_eq!(Some(Cheat::GrantResource(ResourceType::Gold)),
+                   Cheat::parse("coinage"));
+        assert_eq!(Some(Cheat::GrantResource(ResourceType::Food)),
+                   Cheat::parse("  PEPPERONI Pizza "));
+        assert_eq!(Some(Cheat::RevealMap), Cheat::parse("reveal map"));
+        assert_eq!(None, Cheat::parse("gg"));
+    }
+
+    #[test]
+    fn test_cheat_code_parses_back() {
+        let cheats = [Cheat::GrantResource(ResourceType::Food),
+                      Cheat::GrantResource(ResourceType::Wood),
+                      Cheat::GrantResource(ResourceType::Stone),
+                      Cheat::GrantResource(ResourceType::Gold),
+                      Cheat::InstantBuild,
+                      Cheat::RevealMap,
+                      Cheat::BigDaddy];
+        for cheat in &cheats {
+            assert_eq!(Some(*cheat), Cheat::parse(cheat.code()));
+        }
+    }
+
+    #[test]
+    fn test_restored_history_turns_toggles_back_on() {
+        let history = vec![CheatRecord {
+                               tick: 10,
+                               request: CheatRequest::new(1.into(), Cheat::InstantBuild),
+                           },
+                           CheatRecord {
+                               tick: 20,
+                               request: CheatRequest::new(1.into(), Cheat::RevealMap),
+                           },
+                           CheatRecord {
+                               tick: 30,
+                               request: CheatRequest::new(1.into(), Cheat::RevealMap),
+                           }];
+        let mut cheats = Cheats::new(true);
+        cheats.restore_history(history.clone());
+        assert!(cheats.instant_build(1.into()));
+        assert!(!cheats.revealed_map(1.into()));
+        assert_eq!(&history[..], cheats.history());
+    }
+
+    #[test]
+    fn test_cheats_disallowed() {
+        let mut cheats = Cheats::new(false);
+        assert!(!cheats.queue_cheat(CheatRequest::new(1.into(), Cheat::InstantBuild)));
+        assert!(cheats.consume_requests().is_empty());
+    }
+
+    #[test]
+    fn test_cheats_toggles() {
+        let mut cheats = Cheats::new(true);
+        cheats.toggle_revealed_map(1.into());
+        assert!(cheats.revealed_map(1.into()));
+        assert!(!cheats.revealed_map(2.into()));
+        cheats.toggle_revealed_map(1.into());
+        assert!(!cheats.revealed_map(1.into()));
+    }
+}
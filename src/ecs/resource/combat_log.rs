@@ -0,0 +1,147 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use specs::Index;
+use std::collections::VecDeque;
+
+/// How many of the latest combat events are kept for the debug overlay
+pub const MAX_COMBAT_EVENTS: usize = 8;
+
+/// How the ground between an attacker and its target changes the damage, like the original's
+/// bonus for attacking downhill and penalty for attacking uphill
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ElevationModifier {
+    Downhill,
+    Level,
+    Uphill,
+}
+
+impl ElevationModifier {
+    pub fn between(attacker_elevation: u8, target_elevation: u8) -> ElevationModifier {
+        if attacker_elevation > target_elevation {
+            ElevationModifier::Downhill
+        } else if attacker_elevation < target_elevation {
+            ElevationModifier::Uphill
+        } else {
+            ElevationModifier::Level
+        }
+    }
+
+    /// Percentage of the damage that gets through
+    pub fn percent(&self) -> i32 {
+        match *self {
+            ElevationModifier::Downhill => 125,
+            ElevationModifier::Level => 100,
+            ElevationModifier::Uphill => 75,
+        }
+    }
+
+    /// Scales the damage, rounding down, but never below the one point every hit does
+    pub fn apply(&self, damage: i16) -> i16 {
+        ((damage as i32 * self.percent() / 100) as i16).max(1)
+    }
+}
+
+/// A hit on a unit
+#[derive(Clone, Debug, PartialEq)]
+pub struct CombatEvent {
+    pub attacker_player_id: PlayerId,
+    /// ID of the entity that was hit
+    pub target: Index,
+    pub target_player_id: PlayerId,
+    /// Damage after the elevation modifier
+    pub damage: i16,
+    pub elevation_modifier: ElevationModifier,
+    pub destroyed: bool,
+}
+
+/// The latest hits, for showing how combat played out on the debug overlay
+pub struct CombatLog {
+    events: VecDeque<CombatEvent>,
+    recorded: usize,
+}
+
+impl CombatLog {
+    pub fn new() -> CombatLog {
+        CombatLog {
+            events: VecDeque::new(),
+            recorded: 0,
+        }
+    }
+
+    pub fn record(&mut self, event: CombatEvent) {
+        trace!(entity = event.target,
+               damage = event.damage,
+               modifier = event.elevation_modifier.percent(),
+               "Hit a unit");
+        if self.events.len() >= MAX_COMBAT_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+        self.recorded += 1;
+    }
+
+    /// How many events have been recorded in all, so that the ones since can be told apart
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// The latest events, oldest first
+    pub fn events<'a>(&'a self) -> &'a VecDeque<CombatEvent> {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elevation_modifier() {
+        assert_eq!(ElevationModifier::Downhill, ElevationModifier::between(2, 1));
+        assert_eq!(ElevationModifier::Level, ElevationModifier::between(1, 1));
+        assert_eq!(ElevationModifier::Uphill, ElevationModifier::between(0, 3));
+
+        assert_eq!(12, ElevationModifier::Downhill.apply(10));
+        assert_eq!(10, ElevationModifier::Level.apply(10));
+        assert_eq!(7, ElevationModifier::Uphill.apply(10));
+        assert_eq!(1, ElevationModifier::Uphill.apply(1));
+    }
+
+    #[test]
+    fn test_keeps_latest_events() {
+        let mut combat_log = CombatLog::new();
+        for target in 0..(MAX_COMBAT_EVENTS as Index + 2) {
+            combat_log.record(CombatEvent {
+                attacker_player_id: 1.into(),
+                target: target,
+                target_player_id: 2.into(),
+                damage: 5,
+                elevation_modifier: ElevationModifier::Level,
+                destroyed: false,
+            });
+        }
+        assert_eq!(MAX_COMBAT_EVENTS, combat_log.events().len());
+        assert_eq!(MAX_COMBAT_EVENTS + 2, combat_log.recorded());
+        assert_eq!(Some(2), combat_log.events().front().map(|event| event.target));
+    }
+}
@@ -0,0 +1,44 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::mem;
+
+/// Descriptions of the player commands that were applied during the current update. They're
+/// collected after every update so that crash reports can list what led up to a crash.
+pub struct CommandLog {
+    commands: Vec<String>,
+}
+
+impl CommandLog {
+    pub fn new() -> CommandLog {
+        CommandLog { commands: Vec::new() }
+    }
+
+    pub fn record(&mut self, command: String) {
+        self.commands.push(command);
+    }
+
+    pub fn consume_commands(&mut self) -> Vec<String> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.commands);
+        consumed
+    }
+}
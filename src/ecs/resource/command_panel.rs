@@ -0,0 +1,211 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{ResearchId, UnitId};
+use nalgebra::Vector2;
+use super::{UiLayout, UiPanel};
+use types::Rect;
+
+const BUTTON_SIZE: i32 = 32;
+const BUTTON_SPACING: i32 = 4;
+const BUTTON_COLUMNS: usize = 5;
+
+/// The grid has three rows of five, like the keys from Q to T, A to G, and Z to B that press them
+pub const COMMAND_SLOTS: usize = 15;
+
+/// An order that the command panel has a button for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CommandButton {
+    /// Aims the selected siege units at a spot on the ground
+    AttackGround,
+    /// Sends the selected units off to explore
+    Explore,
+    /// Rings the town bell, or rings it again for the all clear
+    TownBell,
+    /// Queues the unit in the selected buildings that train it
+    Train(UnitId),
+    /// Starts researching the technology in a selected building that isn't busy researching
+    Research(ResearchId),
+    /// Turns the card to the buildings the selected villagers can build
+    BuildMenu,
+    /// Places the building wherever the map is clicked next
+    Build(UnitId),
+    /// Turns the card back from the build menu
+    Back,
+}
+
+/// Which set of buttons the command panel shows
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CommandPage {
+    Main,
+    Build,
+}
+
+/// Screen-space layout and state of the command panel, which has a button for each order the
+/// local player's selection can be given, each in a slot of a grid of five columns. The UI layout
+/// places it, in the bottom left corner of the screen by default.
+pub struct CommandPanel {
+    slots: [Option<CommandButton>; COMMAND_SLOTS],
+    pub page: CommandPage,
+    /// Whether the card is hidden; its hotkeys still work
+    pub collapsed: bool,
+    /// The order waiting on a click on the map, which the cursor shows
+    pub targeting: Option<CommandButton>,
+}
+
+impl CommandPanel {
+    pub fn new() -> CommandPanel {
+        CommandPanel {
+            slots: [None; COMMAND_SLOTS],
+            page: CommandPage::Main,
+            collapsed: false,
+            targeting: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots = [None; COMMAND_SLOTS];
+    }
+
+    /// Puts the button in its slot, or the first free one after it if that's taken, so that
+    /// a unit always has its button in the same place. Returns whether there was room.
+    pub fn place(&mut self, button: CommandButton, slot: usize) -> bool {
+        if self.contains(button) {
+            return true;
+        }
+        let free = (0..COMMAND_SLOTS)
+            .map(|offset| (slot + offset) % COMMAND_SLOTS)
+            .find(|&slot| self.slots[slot].is_none());
+        match free {
+            Some(free) => {
+                self.slots[free] = Some(button);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn slot(&self, slot: usize) -> Option<CommandButton> {
+        self.slots.get(slot).and_then(|button| *button)
+    }
+
+    /// Each button with its slot
+    pub fn buttons(&self) -> Vec<(usize, CommandButton)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, button)| button.map(|button| (slot, button)))
+            .collect()
+    }
+
+    pub fn contains(&self, button: CommandButton) -> bool {
+        self.slots.contains(&Some(button))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|button| button.is_none())
+    }
+
+    /// Whether the card is drawn, and so whether it can be clicked
+    pub fn is_shown(&self) -> bool {
+        !self.collapsed && !self.is_empty()
+    }
+
+    /// The area the buttons cover, down to the last row with a button in it
+    pub fn bounds(&self, layout: &UiLayout, viewport_size: &Vector2<i32>) -> Rect {
+        let step = BUTTON_SIZE + BUTTON_SPACING;
+        let rows = self.buttons().last().map_or(0, |&(slot, _)| slot / BUTTON_COLUMNS + 1) as i32;
+        let columns = if rows > 0 { BUTTON_COLUMNS as i32 } else { 0 };
+        let size = Vector2::new(columns * step - BUTTON_SPACING, rows * step - BUTTON_SPACING);
+        layout.place(UiPanel::CommandPanel, &size, viewport_size)
+    }
+
+    pub fn button(&self, layout: &UiLayout, viewport_size: &Vector2<i32>, slot: usize) -> Rect {
+        let step = BUTTON_SIZE + BUTTON_SPACING;
+        let row = (slot / BUTTON_COLUMNS) as i32;
+        let col = (slot % BUTTON_COLUMNS) as i32;
+        let bounds = self.bounds(layout, viewport_size);
+        Rect::of(bounds.x + col * step,
+                 bounds.y + row * step,
+                 BUTTON_SIZE,
+                 BUTTON_SIZE)
+    }
+
+    /// Finds which button is under the given screen position
+    pub fn button_at(&self,
+                     layout: &UiLayout,
+                     viewport_size: &Vector2<i32>,
+                     position: &Vector2<i32>)
+                     -> Option<CommandButton> {
+        if self.collapsed {
+            return None;
+        }
+        self.buttons()
+            .into_iter()
+            .find(|&(slot, _)| self.button(layout, viewport_size, slot).contains(position.x, position.y))
+            .map(|(_, button)| button)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::UiLayout;
+    use nalgebra::Vector2;
+    use super::*;
+
+    #[test]
+    fn test_button_at() {
+        let layout = UiLayout::new();
+        let viewport_size = Vector2::new(640, 480);
+        let mut panel = CommandPanel::new();
+        let corner = Vector2::new(11, viewport_size.y - 11);
+        assert_eq!(None, panel.button_at(&layout, &viewport_size, &corner));
+
+        panel.place(CommandButton::AttackGround, 0);
+        assert_eq!(Some(CommandButton::AttackGround),
+                   panel.button_at(&layout, &viewport_size, &corner));
+        assert_eq!(None, panel.button_at(&layout, &viewport_size, &Vector2::new(320, 240)));
+
+        // Collapsed, the card can't be clicked
+        panel.collapsed = true;
+        assert_eq!(None, panel.button_at(&layout, &viewport_size, &corner));
+    }
+
+    #[test]
+    fn test_buttons_keep_to_their_slots() {
+        let mut panel = CommandPanel::new();
+        assert!(panel.place(CommandButton::Train(83usize.into()), 3));
+        assert!(panel.place(CommandButton::Train(73usize.into()), 3));
+        assert!(panel.place(CommandButton::BuildMenu, COMMAND_SLOTS - 1));
+        assert!(panel.place(CommandButton::Train(83usize.into()), 7));
+        assert_eq!(vec![(3, CommandButton::Train(83usize.into())),
+                        (4, CommandButton::Train(73usize.into())),
+                        (COMMAND_SLOTS - 1, CommandButton::BuildMenu)],
+                   panel.buttons());
+
+        // A full grid wraps around to the start, and then there's no room
+        for unit_id in 0..(COMMAND_SLOTS - 3) {
+            assert!(panel.place(CommandButton::Build(unit_id.into()), COMMAND_SLOTS - 1));
+        }
+        assert_eq!(Some(CommandButton::Build(0usize.into())), panel.slot(0));
+        assert!(!panel.place(CommandButton::Back, 0));
+    }
+}
@@ -0,0 +1,277 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::WorldQuery;
+use identifier::UnitId;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use types::Fixed;
+use util::parse::parse_number;
+
+/// Longest command that can be typed into the debug console
+pub const MAX_COMMAND_LENGTH: usize = 64;
+
+/// How long each system took the last time it ran. The system wrappers record into this from
+/// the planner's worker threads, so it's shared rather than fetched like other resources.
+#[derive(Clone)]
+pub struct SystemTimings {
+    timings: Arc<Mutex<HashMap<&'static str, Duration>>>,
+}
+
+impl SystemTimings {
+    pub fn new() -> SystemTimings {
+        SystemTimings { timings: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn record(&self, system_name: &'static str, duration: Duration) {
+        self.timings.lock().unwrap().insert(system_name, duration);
+    }
+
+    /// The latest timings, sorted by system name so that their order is stable between frames
+    pub fn snapshot(&self) -> Vec<(&'static str, Duration)> {
+        let mut snapshot: Vec<(&'static str, Duration)> =
+            self.timings.lock().unwrap().iter().map(|(name, duration)| (*name, *duration)).collect();
+        snapshot.sort_by(|a, b| a.0.cmp(b.0));
+        snapshot
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConsoleCommand {
+    /// Spawns units of the given type for the local player under the mouse cursor
+    Spawn(UnitId, usize),
+    /// Queues units of the given type across the local player's selected buildings
+    Train(UnitId, usize),
+    /// Toggles the fog of war for the local player
+    ToggleFog,
+    ToggleGrid,
+    TogglePaths,
+    /// Toggles the tile grid with coordinates, elevation, and passability
+    ToggleTiles,
+    /// Toggles the numbers that float up for resources dropped off and damage done
+    ToggleNumbers,
+    /// Replaces the log filter directives
+    SetLogFilter(String),
+    /// Changes a setting by its dotted config name, like `audio.music_volume`
+    SetOption(String, String),
+    /// Changes a field of the inspected entity, by its row number in the inspector
+    EditField(usize, Fixed),
+    /// Logs the answer to a world query, as triggers and AI scripts would ask it
+    Query(WorldQuery),
+    /// Loads the running scripts from their files again
+    ReloadScripts,
+    /// Toggles logging the rules that scripts fire, with the queries they were decided on
+    ToggleScriptTrace,
+}
+
+impl ConsoleCommand {
+    pub fn parse(text: &str) -> Result<ConsoleCommand, String> {
+        use self::ConsoleCommand::*;
+        let mut words = text.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command.to_lowercase(),
+            None => return Err("empty command".into()),
+        };
+        let arguments: Vec<&str> = words.collect();
+        match (&command[..], arguments.len()) {
+            ("spawn", 1) | ("spawn", 2) | ("train", 1) | ("train", 2) => {
+                let unit_id = try!(parse_number(arguments[0], "unit id"));
+                let count = if arguments.len() == 2 {
+                    try!(parse_number(arguments[1], "count"))
+                } else {
+                    1
+                };
+                if command == "spawn" {
+                    Ok(Spawn(unit_id.into(), count))
+                } else {
+                    Ok(Train(unit_id.into(), count))
+                }
+            }
+            ("spawn", _) => Err("usage: spawn <unit id> [count]".into()),
+            ("train", _) => Err("usage: train <unit id> [count]".into()),
+            ("fog", 0) => Ok(ToggleFog),
+            ("grid", 0) => Ok(ToggleGrid),
+            ("paths", 0) => Ok(TogglePaths),
+            ("tiles", 0) => Ok(ToggleTiles),
+            ("numbers", 0) => Ok(ToggleNumbers),
+            ("log", 1) => Ok(SetLogFilter(arguments[0].into())),
+            ("log", _) => Err("usage: log <filter>".into()),
+            ("set", count) if count >= 2 => Ok(SetOption(arguments[0].into(), arguments[1..].join(" "))),
+            ("set", _) => Err("usage: set <setting> <value>".into()),
+            ("edit", 2) => {
+                let row = try!(parse_number(arguments[0], "row"));
+                match arguments[1].parse::<f64>() {
+                    Ok(value) => Ok(EditField(row, value.into())),
+                    Err(_) => Err(format!("invalid value \"{}\"", arguments[1])),
+                }
+            }
+            ("edit", _) => Err("usage: edit <row> <value>".into()),
+            ("query", count) if count >= 1 => WorldQuery::parse(&arguments.join(" ")).map(Query),
+            ("query", _) => Err("usage: query <query>".into()),
+            ("script", 1) if arguments[0].to_lowercase() == "reload" => Ok(ReloadScripts),
+            ("script", 1) if arguments[0].to_lowercase() == "trace" => Ok(ToggleScriptTrace),
+            ("script", _) => Err("usage: script <reload|trace>".into()),
+            _ => Err(format!("unknown command \"{}\"", text.trim())),
+        }
+    }
+}
+
+/// Developer overlay toggled with F3, and the command console that is opened with ` while
+/// the overlay is showing
+pub struct DebugOverlay {
+    pub visible: bool,
+    pub show_grid: bool,
+    pub show_paths: bool,
+    pub show_tiles: bool,
+    /// Whether scripts log the rules they fire
+    pub trace_scripts: bool,
+    /// Set when the scripts should be reloaded, until the game state has done so
+    pub reload_scripts: bool,
+    pub console_open: bool,
+    draft: String,
+    commands: Vec<ConsoleCommand>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            visible: false,
+            show_grid: true,
+            show_paths: true,
+            show_tiles: false,
+            trace_scripts: false,
+            reload_scripts: false,
+            console_open: false,
+            draft: String::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        if !self.visible {
+            self.take_draft();
+        }
+    }
+
+    /// The command currently being typed
+    pub fn draft<'a>(&'a self) -> &'a str {
+        &self.draft
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        for character in text.chars() {
+            if self.draft.chars().count() >= MAX_COMMAND_LENGTH {
+                break;
+            }
+            self.draft.push(character);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.draft.pop();
+    }
+
+    /// Closes the console and returns what was typed into it
+    pub fn take_draft(&mut self) -> String {
+        self.console_open = false;
+        let mut draft = String::new();
+        mem::swap(&mut draft, &mut self.draft);
+        draft
+    }
+
+    pub fn queue_command(&mut self, command: ConsoleCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn consume_commands(&mut self) -> Vec<ConsoleCommand> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.commands);
+        consumed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use types::Fixed;
+
+    #[test]
+    fn test_parse_console_commands() {
+        use super::ConsoleCommand::*;
+        assert_eq!(Ok(Spawn(83.into(), 1)), ConsoleCommand::parse("spawn 83"));
+        assert_eq!(Ok(Spawn(83.into(), 10)), ConsoleCommand::parse("  SPAWN 83 10 "));
+        assert_eq!(Ok(Train(83.into(), 3)), ConsoleCommand::parse("train 83 3"));
+        assert_eq!(Ok(ToggleFog), ConsoleCommand::parse("fog"));
+        assert_eq!(Ok(ToggleGrid), ConsoleCommand::parse("grid"));
+        assert_eq!(Ok(TogglePaths), ConsoleCommand::parse("paths"));
+        assert_eq!(Ok(ToggleTiles), ConsoleCommand::parse("tiles"));
+        assert_eq!(Ok(ToggleNumbers), ConsoleCommand::parse("numbers"));
+        assert_eq!(Ok(SetLogFilter("info,chariot=trace".into())),
+                   ConsoleCommand::parse("log info,chariot=trace"));
+        assert_eq!(Ok(SetOption("keys.open_chat".into(), "\"F5\"".into())),
+                   ConsoleCommand::parse("set keys.open_chat \"F5\""));
+        assert_eq!(Ok(EditField(3, Fixed::from(-12.5))), ConsoleCommand::parse("edit 3 -12.5"));
+        assert_eq!(Ok(Query(WorldQuery::parse("count owner 2").unwrap())),
+                   ConsoleCommand::parse("query count owner 2"));
+        assert_eq!(Ok(ReloadScripts), ConsoleCommand::parse("script Reload"));
+        assert_eq!(Ok(ToggleScriptTrace), ConsoleCommand::parse("script trace"));
+
+        assert!(ConsoleCommand::parse("").is_err());
+        assert!(ConsoleCommand::parse("spawn").is_err());
+        assert!(ConsoleCommand::parse("spawn militia").is_err());
+        assert!(ConsoleCommand::parse("train 83 3 4").is_err());
+        assert!(ConsoleCommand::parse("fog on").is_err());
+        assert!(ConsoleCommand::parse("set audio.music_volume").is_err());
+        assert!(ConsoleCommand::parse("edit 3").is_err());
+        assert!(ConsoleCommand::parse("edit 3 fast").is_err());
+        assert!(ConsoleCommand::parse("query").is_err());
+        assert!(ConsoleCommand::parse("script").is_err());
+        assert!(ConsoleCommand::parse("script run").is_err());
+        assert!(ConsoleCommand::parse("teleport").is_err());
+    }
+
+    #[test]
+    fn test_hiding_overlay_closes_console() {
+        let mut overlay = DebugOverlay::new();
+        overlay.toggle();
+        overlay.console_open = true;
+        overlay.type_text("spawn 8");
+        overlay.toggle();
+        assert!(!overlay.visible);
+        assert!(!overlay.console_open);
+        assert_eq!("", overlay.draft());
+    }
+
+    #[test]
+    fn test_system_timings_snapshot_is_sorted() {
+        let timings = SystemTimings::new();
+        timings.record("VelocitySystem", Duration::new(0, 300));
+        timings.record("AnimationSystem", Duration::new(0, 100));
+        timings.record("VelocitySystem", Duration::new(0, 200));
+        assert_eq!(vec![("AnimationSystem", Duration::new(0, 100)),
+                        ("VelocitySystem", Duration::new(0, 200))],
+                   timings.snapshot());
+    }
+}
@@ -0,0 +1,72 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::PlayerId;
+use std::collections::VecDeque;
+use types::{Fixed, Vector3};
+
+/// How many of the latest deposits are kept for the presentation to pick up
+pub const MAX_DEPOSIT_EVENTS: usize = 16;
+
+/// Resources a gatherer dropped off
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepositEvent {
+    pub player_id: PlayerId,
+    /// Where the gatherer was when it dropped them off
+    pub position: Vector3,
+    pub resource_type: ResourceType,
+    pub amount: Fixed,
+}
+
+/// The latest resources dropped off, so that what's shown of them can be worked out outside of
+/// the simulation
+pub struct DepositLog {
+    events: VecDeque<DepositEvent>,
+    recorded: usize,
+}
+
+impl DepositLog {
+    pub fn new() -> DepositLog {
+        DepositLog {
+            events: VecDeque::new(),
+            recorded: 0,
+        }
+    }
+
+    pub fn record(&mut self, event: DepositEvent) {
+        if self.events.len() >= MAX_DEPOSIT_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+        self.recorded += 1;
+    }
+
+    /// How many events have been recorded in all, so that the ones since can be told apart
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// The latest events, oldest first
+    pub fn events<'a>(&'a self) -> &'a VecDeque<DepositEvent> {
+        &self.events
+    }
+}
@@ -0,0 +1,341 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::PlayerId;
+use scn::Scenario;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use types::Fixed;
+
+/// How one player regards another. Stances are one-directional; two players
+/// are only allied when they both consider each other allies.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DiplomaticStance {
+    Ally,
+    Neutral,
+    Enemy,
+}
+
+impl DiplomaticStance {
+    pub fn from_scn(value: u32) -> DiplomaticStance {
+        match value {
+            0 => DiplomaticStance::Ally,
+            1 => DiplomaticStance::Neutral,
+            _ => DiplomaticStance::Enemy,
+        }
+    }
+
+    pub fn to_scn(&self) -> u32 {
+        match *self {
+            DiplomaticStance::Ally => 0,
+            DiplomaticStance::Neutral => 1,
+            DiplomaticStance::Enemy => 3,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StanceChange {
+    pub player_id: PlayerId,
+    pub other_player_id: PlayerId,
+    pub stance: DiplomaticStance,
+}
+
+impl StanceChange {
+    pub fn new(player_id: PlayerId, other_player_id: PlayerId, stance: DiplomaticStance) -> StanceChange {
+        StanceChange {
+            player_id: player_id,
+            other_player_id: other_player_id,
+            stance: stance,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Tribute {
+    pub from_player_id: PlayerId,
+    pub to_player_id: PlayerId,
+    pub resource_type: ResourceType,
+    pub amount: Fixed,
+}
+
+impl Tribute {
+    pub fn new(from_player_id: PlayerId,
+               to_player_id: PlayerId,
+               resource_type: ResourceType,
+               amount: Fixed)
+               -> Tribute {
+        Tribute {
+            from_player_id: from_player_id,
+            to_player_id: to_player_id,
+            resource_type: resource_type,
+            amount: amount,
+        }
+    }
+
+    /// How much of the tribute actually arrives after the market takes its cut
+    pub fn amount_received(&self, tribute_penalty: Fixed) -> Fixed {
+        self.amount - self.amount * tribute_penalty
+    }
+}
+
+/// Tracks the stances between all of the players. Stance changes and tributes are
+/// queued up here and then applied by the DiplomacySystem, similar to how unit actions
+/// flow through the ActionBatcher.
+pub struct Diplomacy {
+    stances: HashMap<(PlayerId, PlayerId), DiplomaticStance>,
+    allied_victory: HashSet<PlayerId>,
+    shared_vision: HashSet<PlayerId>,
+    tribute_fees: HashMap<PlayerId, Fixed>,
+    stance_changes: Vec<StanceChange>,
+    tributes: Vec<Tribute>,
+}
+
+impl Diplomacy {
+    pub fn new() -> Diplomacy {
+        Diplomacy {
+            stances: HashMap::new(),
+            allied_victory: HashSet::new(),
+            shared_vision: HashSet::new(),
+            tribute_fees: HashMap::new(),
+            stance_changes: Vec::new(),
+            tributes: Vec::new(),
+        }
+    }
+
+    pub fn from_scenario(scenario: &Scenario) -> Diplomacy {
+        let mut diplomacy = Diplomacy::new();
+        let player_ids = scenario.player_ids();
+        for player_id in &player_ids {
+            let player_index = **player_id as usize;
+            for other_player_id in &player_ids {
+                if player_id != other_player_id {
+                    let other_player_index = **other_player_id as usize;
+                    let stance = scenario.player_data.diplomacy.stance(player_index, other_player_index);
+                    diplomacy.set_stance(*player_id, *other_player_id, DiplomaticStance::from_scn(stance));
+                }
+            }
+            let allied_victory = scenario.player_data.allied_victory[player_index] != 0;
+            diplomacy.set_allied_victory(*player_id, allied_victory);
+        }
+        diplomacy
+    }
+
+    /// Stance of the player towards the other player. Players are always allied with themselves,
+    /// and everyone starts out as enemies if the scenario doesn't say otherwise.
+    pub fn stance(&self, player_id: PlayerId, other_player_id: PlayerId) -> DiplomaticStance {
+        if player_id == other_player_id {
+            return DiplomaticStance::Ally;
+        }
+        match self.stances.get(&(player_id, other_player_id)) {
+            Some(stance) => *stance,
+            None => DiplomaticStance::Enemy,
+        }
+    }
+
+    pub fn set_stance(&mut self, player_id: PlayerId, other_player_id: PlayerId, stance: DiplomaticStance) {
+        if player_id != other_player_id {
+            self.stances.insert((player_id, other_player_id), stance);
+        }
+    }
+
+    /// True if both players consider each other allies
+    pub fn is_allied(&self, player_id: PlayerId, other_player_id: PlayerId) -> bool {
+        self.stance(player_id, other_player_id) == DiplomaticStance::Ally &&
+        self.stance(other_player_id, player_id) == DiplomaticStance::Ally
+    }
+
+    /// True if the player's units should attack the other player's units
+    pub fn is_enemy(&self, player_id: PlayerId, other_player_id: PlayerId) -> bool {
+        self.stance(player_id, other_player_id) == DiplomaticStance::Enemy
+    }
+
+    pub fn allied_victory(&self, player_id: PlayerId) -> bool {
+        self.allied_victory.contains(&player_id)
+    }
+
+    pub fn set_allied_victory(&mut self, player_id: PlayerId, allied_victory: bool) {
+        if allied_victory {
+            self.allied_victory.insert(player_id);
+        } else {
+            self.allied_victory.remove(&player_id);
+        }
+    }
+
+    /// Given the players that are still in the game, determine whether they have all won together.
+    /// That requires everyone left to be mutually allied and to have opted into allied victory.
+    pub fn is_allied_victory(&self, remaining_player_ids: &[PlayerId]) -> bool {
+        if remaining_player_ids.len() < 2 {
+            return false;
+        }
+        for player_id in remaining_player_ids {
+            if !self.allied_victory(*player_id) {
+                return false;
+            }
+            for other_player_id in remaining_player_ids {
+                if !self.is_allied(*player_id, *other_player_id) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Lets the player see everything their allies see, from the start with the allied vision
+    /// setting, or once they've researched Writing
+    pub fn enable_shared_vision(&mut self, player_id: PlayerId) {
+        self.shared_vision.insert(player_id);
+    }
+
+    /// True if the viewing player gets to see what the other player's units see
+    pub fn shares_vision(&self, viewer_player_id: PlayerId, other_player_id: PlayerId) -> bool {
+        viewer_player_id == other_player_id ||
+        (self.shared_vision.contains(&viewer_player_id) && self.is_allied(viewer_player_id, other_player_id))
+    }
+
+    /// Fraction of every tribute the player sends that is kept as a fee. Until research has
+    /// changed it, the fee is the civilization's starting tribute penalty.
+    pub fn tribute_fee(&self, player_id: PlayerId, starting_penalty: Fixed) -> Fixed {
+        match self.tribute_fees.get(&player_id) {
+            Some(fee) => *fee,
+            None => starting_penalty,
+        }
+    }
+
+    /// Changed by research that changes the fee; see `apply_research_effects`
+    pub fn set_tribute_fee(&mut self, player_id: PlayerId, fee: Fixed) {
+        self.tribute_fees.insert(player_id, fee);
+    }
+
+    pub fn queue_stance_change(&mut self, stance_change: StanceChange) {
+        self.stance_changes.push(stance_change);
+    }
+
+    pub fn queue_tribute(&mut self, tribute: Tribute) {
+        self.tributes.push(tribute);
+    }
+
+    pub fn consume_stance_changes(&mut self) -> Vec<StanceChange> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.stance_changes);
+        consumed
+    }
+
+    pub fn consume_tributes(&mut self) -> Vec<Tribute> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.tributes);
+        consumed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use identifier::PlayerId;
+    use super::{Diplomacy, DiplomaticStance, Tribute};
+    use types::Fixed;
+
+    fn id(val: usize) -> PlayerId {
+        val.into()
+    }
+
+    fn ally(diplomacy: &mut Diplomacy, a: usize, b: usize) {
+        diplomacy.set_stance(id(a), id(b), DiplomaticStance::Ally);
+        diplomacy.set_stance(id(b), id(a), DiplomaticStance::Ally);
+    }
+
+    #[test]
+    fn test_stance_defaults() {
+        let diplomacy = Diplomacy::new();
+        assert_eq!(DiplomaticStance::Ally, diplomacy.stance(id(1), id(1)));
+        assert_eq!(DiplomaticStance::Enemy, diplomacy.stance(id(1), id(2)));
+        assert!(diplomacy.is_allied(id(1), id(1)));
+        assert!(diplomacy.is_enemy(id(1), id(2)));
+    }
+
+    #[test]
+    fn test_alliance_is_mutual() {
+        let mut diplomacy = Diplomacy::new();
+        diplomacy.set_stance(id(1), id(2), DiplomaticStance::Ally);
+        assert!(!diplomacy.is_allied(id(1), id(2)));
+        assert!(!diplomacy.is_enemy(id(1), id(2)));
+        assert!(diplomacy.is_enemy(id(2), id(1)));
+
+        diplomacy.set_stance(id(2), id(1), DiplomaticStance::Ally);
+        assert!(diplomacy.is_allied(id(1), id(2)));
+        assert!(diplomacy.is_allied(id(2), id(1)));
+    }
+
+    #[test]
+    fn test_allied_victory() {
+        let mut diplomacy = Diplomacy::new();
+        ally(&mut diplomacy, 1, 2);
+        ally(&mut diplomacy, 2, 3);
+        diplomacy.set_allied_victory(id(1), true);
+        diplomacy.set_allied_victory(id(2), true);
+        diplomacy.set_allied_victory(id(3), true);
+
+        assert!(!diplomacy.is_allied_victory(&[id(1)]));
+        assert!(diplomacy.is_allied_victory(&[id(1), id(2)]));
+        assert!(diplomacy.is_allied_victory(&[id(2), id(3)]));
+
+        // 1 and 3 aren't allied with each other
+        assert!(!diplomacy.is_allied_victory(&[id(1), id(2), id(3)]));
+
+        ally(&mut diplomacy, 1, 3);
+        assert!(diplomacy.is_allied_victory(&[id(1), id(2), id(3)]));
+
+        diplomacy.set_allied_victory(id(3), false);
+        assert!(!diplomacy.is_allied_victory(&[id(1), id(2), id(3)]));
+    }
+
+    #[test]
+    fn test_shared_vision() {
+        let mut diplomacy = Diplomacy::new();
+        ally(&mut diplomacy, 1, 2);
+        assert!(diplomacy.shares_vision(id(1), id(1)));
+        assert!(!diplomacy.shares_vision(id(1), id(2)));
+
+        diplomacy.enable_shared_vision(id(1));
+        assert!(diplomacy.shares_vision(id(1), id(2)));
+        assert!(!diplomacy.shares_vision(id(2), id(1)));
+        assert!(!diplomacy.shares_vision(id(1), id(3)));
+    }
+
+    #[test]
+    fn test_tribute_amount_received() {
+        let tribute = Tribute::new(id(1), id(2), ResourceType::Gold, 100.into());
+        assert_eq!(Fixed::from(75), tribute.amount_received(fixed_const!(0.25)));
+        assert_eq!(Fixed::from(100), tribute.amount_received(0.into()));
+    }
+
+    #[test]
+    fn test_tribute_fee() {
+        let mut diplomacy = Diplomacy::new();
+        let starting_penalty = fixed_const!(0.25);
+        assert_eq!(starting_penalty, diplomacy.tribute_fee(id(1), starting_penalty));
+
+        diplomacy.set_tribute_fee(id(1), 0.into());
+        assert_eq!(Fixed::from(0), diplomacy.tribute_fee(id(1), starting_penalty));
+        assert_eq!(starting_penalty, diplomacy.tribute_fee(id(2), starting_penalty));
+    }
+}
@@ -0,0 +1,116 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::PlayerId;
+use nalgebra::Vector2;
+use super::{DiplomaticStance, Players, UiLayout, UiPanel};
+use types::Rect;
+
+const ROW_HEIGHT: i32 = 24;
+const BUTTON_SIZE: i32 = 20;
+const BUTTON_SPACING: i32 = 4;
+const GROUP_SPACING: i32 = 16;
+
+pub const PANEL_STANCES: [DiplomaticStance; 3] =
+    [DiplomaticStance::Ally, DiplomaticStance::Neutral, DiplomaticStance::Enemy];
+
+pub const PANEL_TRIBUTE_RESOURCES: [ResourceType; 4] =
+    [ResourceType::Food, ResourceType::Wood, ResourceType::Stone, ResourceType::Gold];
+
+/// Screen-space layout and open/closed state of the diplomacy panel, which the UI layout places.
+/// Each row belongs to another player and has a button per stance followed by
+/// a button per resource that can be sent as tribute.
+pub struct DiplomacyPanel {
+    pub open: bool,
+}
+
+impl DiplomacyPanel {
+    pub fn new() -> DiplomacyPanel {
+        DiplomacyPanel { open: false }
+    }
+
+    /// Players that get a row in the panel; gaia and the local player don't
+    pub fn row_player_ids(&self, players: &Players) -> Vec<PlayerId> {
+        let local_player_id = players.local_player_id();
+        players.player_ids()
+            .into_iter()
+            .filter(|id| !id.is_gaia() && *id != local_player_id)
+            .collect()
+    }
+
+    /// Where the panel is with the given number of rows; the buttons are placed within it
+    pub fn bounds(&self, layout: &UiLayout, viewport_size: &Vector2<i32>, row_count: usize) -> Rect {
+        let button_count = (PANEL_STANCES.len() + PANEL_TRIBUTE_RESOURCES.len()) as i32;
+        let width = button_count * (BUTTON_SIZE + BUTTON_SPACING) + GROUP_SPACING + BUTTON_SPACING;
+        let height = row_count as i32 * ROW_HEIGHT + BUTTON_SPACING;
+        layout.place(UiPanel::DiplomacyPanel, &Vector2::new(width, height), viewport_size)
+    }
+
+    pub fn stance_button(&self, bounds: &Rect, row: usize, stance_index: usize) -> Rect {
+        self.button(bounds, row, stance_index as i32, 0)
+    }
+
+    pub fn tribute_button(&self, bounds: &Rect, row: usize, resource_index: usize) -> Rect {
+        self.button(bounds,
+                    row,
+                    (PANEL_STANCES.len() + resource_index) as i32,
+                    GROUP_SPACING)
+    }
+
+    /// Finds which stance button is under the given screen position
+    pub fn stance_button_at(&self,
+                            bounds: &Rect,
+                            row_count: usize,
+                            position: &Vector2<i32>)
+                            -> Option<(usize, usize)> {
+        for row in 0..row_count {
+            for stance_index in 0..PANEL_STANCES.len() {
+                if self.stance_button(bounds, row, stance_index).contains(position.x, position.y) {
+                    return Some((row, stance_index));
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds which tribute button is under the given screen position
+    pub fn tribute_button_at(&self,
+                             bounds: &Rect,
+                             row_count: usize,
+                             position: &Vector2<i32>)
+                             -> Option<(usize, usize)> {
+        for row in 0..row_count {
+            for resource_index in 0..PANEL_TRIBUTE_RESOURCES.len() {
+                if self.tribute_button(bounds, row, resource_index).contains(position.x, position.y) {
+                    return Some((row, resource_index));
+                }
+            }
+        }
+        None
+    }
+
+    fn button(&self, bounds: &Rect, row: usize, column: i32, extra_offset: i32) -> Rect {
+        let x = bounds.x + BUTTON_SPACING + column * (BUTTON_SIZE + BUTTON_SPACING) + extra_offset;
+        let y = bounds.y + BUTTON_SPACING + row as i32 * ROW_HEIGHT;
+        Rect::of(x, y, BUTTON_SIZE, BUTTON_SIZE)
+    }
+}
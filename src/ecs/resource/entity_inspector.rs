@@ -0,0 +1,233 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::Action;
+use specs::Index;
+use std::mem;
+use types::Fixed;
+
+/// A numeric field of one of the inspected entity's components
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InspectorField {
+    PlayerId,
+    CivilizationId,
+    UnitId,
+    PositionX,
+    PositionY,
+    PositionZ,
+    Rotation,
+    VelocityX,
+    VelocityY,
+    VelocityZ,
+    GraphicId,
+    Frame,
+    Selected,
+    OnScreen,
+    QueuedUnits,
+    TrainingProgress,
+    RepeatProduction,
+    QueuedOrders,
+    CurrentOrderDone,
+}
+
+impl InspectorField {
+    /// The component and field name, used when logging the inspector's rows
+    pub fn name(&self) -> &'static str {
+        use self::InspectorField::*;
+        match *self {
+            PlayerId => "unit.player_id",
+            CivilizationId => "unit.civilization_id",
+            UnitId => "unit.unit_id",
+            PositionX => "transform.x",
+            PositionY => "transform.y",
+            PositionZ => "transform.z",
+            Rotation => "transform.rotation",
+            VelocityX => "velocity.x",
+            VelocityY => "velocity.y",
+            VelocityZ => "velocity.z",
+            GraphicId => "graphic.graphic_id",
+            Frame => "graphic.frame",
+            Selected => "selected",
+            OnScreen => "on_screen",
+            QueuedUnits => "production_queue.len",
+            TrainingProgress => "production_queue.progress",
+            RepeatProduction => "production_queue.repeat",
+            QueuedOrders => "action_queue.len",
+            CurrentOrderDone => "action_queue.current_done",
+        }
+    }
+
+    /// Whether the field can be changed with the console's `edit` command. Setting
+    /// `action_queue.current_done` to anything but zero marks the current order as done,
+    /// which gets a stuck unit moving on to its next order.
+    pub fn is_editable(&self) -> bool {
+        use self::InspectorField::*;
+        match *self {
+            PositionX | PositionY | PositionZ | Rotation | VelocityX | VelocityY | VelocityZ | Frame |
+            CurrentOrderDone => true,
+            _ => false,
+        }
+    }
+}
+
+/// The entity picked for inspection with the debug overlay open, along with its fields and
+/// orders as of the last update
+pub struct EntityInspector {
+    entity_id: Option<Index>,
+    rows: Vec<(InspectorField, Fixed)>,
+    current_order: Option<Action>,
+    orders: Vec<Action>,
+    edits: Vec<(usize, Fixed)>,
+    logged: bool,
+}
+
+impl EntityInspector {
+    pub fn new() -> EntityInspector {
+        EntityInspector {
+            entity_id: None,
+            rows: Vec::new(),
+            current_order: None,
+            orders: Vec::new(),
+            edits: Vec::new(),
+            logged: false,
+        }
+    }
+
+    #[inline]
+    pub fn entity_id(&self) -> Option<Index> {
+        self.entity_id
+    }
+
+    /// Starts inspecting another entity, or closes the inspector if there isn't one
+    pub fn inspect(&mut self, entity_id: Option<Index>) {
+        self.entity_id = entity_id;
+        self.rows.clear();
+        self.current_order = None;
+        self.orders.clear();
+        self.edits.clear();
+        self.logged = false;
+    }
+
+    pub fn close(&mut self) {
+        self.inspect(None);
+    }
+
+    #[inline]
+    pub fn rows<'a>(&'a self) -> &'a [(InspectorField, Fixed)] {
+        &self.rows
+    }
+
+    #[inline]
+    pub fn current_order<'a>(&'a self) -> &'a Option<Action> {
+        &self.current_order
+    }
+
+    #[inline]
+    pub fn orders<'a>(&'a self) -> &'a [Action] {
+        &self.orders
+    }
+
+    /// Replaces the inspected entity's fields and orders. Returns true the first time after
+    /// picking an entity, so that its rows can be logged once.
+    pub fn refresh(&mut self,
+                   rows: Vec<(InspectorField, Fixed)>,
+                   current_order: Option<Action>,
+                   orders: Vec<Action>)
+                   -> bool {
+        self.rows = rows;
+        self.current_order = current_order;
+        self.orders = orders;
+        let first_refresh = !self.logged;
+        self.logged = true;
+        first_refresh
+    }
+
+    /// Queues a change to the field in the given row, numbered from one like on screen
+    pub fn queue_edit(&mut self, row: usize, value: Fixed) {
+        self.edits.push((row, value));
+    }
+
+    /// Takes the queued edits, resolving their rows to fields. Rows that are out of range or
+    /// read only come back as errors so they can be reported.
+    pub fn consume_edits(&mut self) -> Vec<Result<(InspectorField, Fixed), String>> {
+        let mut edits = Vec::new();
+        mem::swap(&mut edits, &mut self.edits);
+        edits.into_iter()
+            .map(|(row, value)| {
+                match self.rows.get(row.wrapping_sub(1)) {
+                    Some(&(field, _)) if field.is_editable() => Ok((field, value)),
+                    Some(&(field, _)) => Err(format!("{} is read only", field.name())),
+                    None => Err(format!("no row {} in the inspector", row)),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inspector_with_rows() -> EntityInspector {
+        let mut inspector = EntityInspector::new();
+        inspector.inspect(Some(7));
+        inspector.refresh(vec![(InspectorField::PlayerId, 1.into()), (InspectorField::PositionX, 12.into())],
+                          None,
+                          Vec::new());
+        inspector
+    }
+
+    #[test]
+    fn test_refresh_logs_once_per_entity() {
+        let mut inspector = EntityInspector::new();
+        inspector.inspect(Some(7));
+        assert!(inspector.refresh(Vec::new(), None, Vec::new()));
+        assert!(!inspector.refresh(Vec::new(), None, Vec::new()));
+        inspector.inspect(Some(8));
+        assert!(inspector.refresh(Vec::new(), None, Vec::new()));
+    }
+
+    #[test]
+    fn test_consume_edits() {
+        let mut inspector = inspector_with_rows();
+        inspector.queue_edit(2, 30.into());
+        inspector.queue_edit(1, 2.into());
+        inspector.queue_edit(0, 2.into());
+        inspector.queue_edit(3, 2.into());
+
+        let edits = inspector.consume_edits();
+        assert_eq!(Ok((InspectorField::PositionX, 30.into())), edits[0]);
+        assert!(edits[1].is_err());
+        assert!(edits[2].is_err());
+        assert!(edits[3].is_err());
+        assert!(inspector.consume_edits().is_empty());
+    }
+
+    #[test]
+    fn test_inspecting_drops_pending_edits() {
+        let mut inspector = inspector_with_rows();
+        inspector.queue_edit(2, 30.into());
+        inspector.close();
+        assert_eq!(None, inspector.entity_id());
+        assert!(inspector.rows().is_empty());
+        assert!(inspector.consume_edits().is_empty());
+    }
+}
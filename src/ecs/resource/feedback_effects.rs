@@ -0,0 +1,193 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use types::{Color, Vector3};
+
+/// Most effects shown at once; spawning another past this replaces the one closest to running out
+pub const MAX_EFFECTS: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EffectKind {
+    /// Where the player gave a command: green where units were sent, red where they were set on
+    /// a target
+    CommandFlag { attack: bool },
+    /// Crosshairs flashing over a unit that was hit
+    TargetFlash,
+    /// A number floating up from where something happened, like "+15" in the color of food when
+    /// it's dropped off; negative numbers show with a minus
+    FloatingNumber { value: i32, color: Color },
+}
+
+impl EffectKind {
+    /// Seconds that the effect is shown for
+    pub fn lifetime(&self) -> f32 {
+        match *self {
+            EffectKind::CommandFlag { .. } => 1.,
+            EffectKind::TargetFlash => 0.4,
+            EffectKind::FloatingNumber { .. } => 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effect {
+    pub kind: EffectKind,
+    /// Where in the world it's shown
+    pub position: Vector3,
+    /// Seconds since it was spawned
+    pub age: f32,
+}
+
+impl Effect {
+    /// How far through its lifetime it is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        (self.age / self.kind.lifetime()).min(1.)
+    }
+}
+
+/// Short-lived visual feedback on what's happening, kept in a pool of slots that are reused as
+/// the effects in them run out. It's only for show, and ages in real time as frames are drawn.
+pub struct FeedbackEffects {
+    slots: Vec<Option<Effect>>,
+    /// Whether numbers float up for resources dropped off and damage done, for debugging
+    pub show_numbers: bool,
+}
+
+impl FeedbackEffects {
+    pub fn new() -> FeedbackEffects {
+        FeedbackEffects {
+            slots: (0..MAX_EFFECTS).map(|_| None).collect(),
+            show_numbers: false,
+        }
+    }
+
+    pub fn spawn(&mut self, kind: EffectKind, position: Vector3) {
+        let effect = Effect {
+            kind: kind,
+            position: position,
+            age: 0.,
+        };
+        let free_slot = self.slots.iter().position(|slot| slot.is_none());
+        let slot = match free_slot {
+            Some(slot) => slot,
+            None => {
+                let closest_to_running_out = self.slots
+                    .iter()
+                    .enumerate()
+                    .max_by(|&(_, a), &(_, b)| {
+                        let (a, b) = (a.as_ref().unwrap().progress(), b.as_ref().unwrap().progress());
+                        a.partial_cmp(&b).unwrap()
+                    })
+                    .map(|(slot, _)| slot);
+                closest_to_running_out.unwrap()
+            }
+        };
+        self.slots[slot] = Some(effect);
+    }
+
+    pub fn command_flag(&mut self, position: Vector3, attack: bool) {
+        self.spawn(EffectKind::CommandFlag { attack: attack }, position);
+    }
+
+    pub fn target_flash(&mut self, position: Vector3) {
+        self.spawn(EffectKind::TargetFlash, position);
+    }
+
+    /// Floats a number up from the position, when the numbers are shown
+    pub fn floating_number(&mut self, position: Vector3, value: i32, color: Color) {
+        if self.show_numbers {
+            self.spawn(EffectKind::FloatingNumber {
+                           value: value,
+                           color: color,
+                       },
+                       position);
+        }
+    }
+
+    /// Ages the effects, freeing the slots of those that have run out
+    pub fn advance(&mut self, seconds: f32) {
+        for slot in &mut self.slots {
+            let expired = match *slot {
+                Some(ref mut effect) => {
+                    effect.age += seconds;
+                    effect.age >= effect.kind.lifetime()
+                }
+                None => false,
+            };
+            if expired {
+                *slot = None;
+            }
+        }
+    }
+
+    pub fn effects<'a>(&'a self) -> Vec<&'a Effect> {
+        self.slots.iter().filter_map(|slot| slot.as_ref()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Color, Vector3};
+
+    fn origin() -> Vector3 {
+        Vector3::new(0.into(), 0.into(), 0.into())
+    }
+
+    #[test]
+    fn test_effects_run_out() {
+        let mut effects = FeedbackEffects::new();
+        effects.command_flag(origin(), false);
+        effects.target_flash(origin());
+        effects.advance(0.5);
+        assert_eq!(vec![EffectKind::CommandFlag { attack: false }],
+                   effects.effects().iter().map(|effect| effect.kind.clone()).collect::<Vec<_>>());
+        effects.advance(0.5);
+        assert!(effects.effects().is_empty());
+    }
+
+    #[test]
+    fn test_full_pool_replaces_the_closest_to_running_out() {
+        let mut effects = FeedbackEffects::new();
+        effects.command_flag(origin(), true);
+        effects.advance(0.1);
+        for _ in 1..MAX_EFFECTS {
+            effects.command_flag(origin(), false);
+        }
+        assert_eq!(MAX_EFFECTS, effects.effects().len());
+
+        effects.command_flag(origin(), false);
+        assert_eq!(MAX_EFFECTS, effects.effects().len());
+        let replaced = EffectKind::CommandFlag { attack: true };
+        assert!(effects.effects().iter().all(|effect| effect.kind != replaced));
+    }
+
+    #[test]
+    fn test_numbers_only_when_shown() {
+        let mut effects = FeedbackEffects::new();
+        effects.floating_number(origin(), 15, Color::rgb(255, 0, 0));
+        assert!(effects.effects().is_empty());
+
+        effects.show_numbers = true;
+        effects.floating_number(origin(), 15, Color::rgb(255, 0, 0));
+        assert_eq!(1, effects.effects().len());
+    }
+}
@@ -0,0 +1,560 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// The tiles that the search for unexplored regions spreads to from each tile
+const NEIGHBORS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// A circle of tiles that a unit can see, in tile coordinates
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Sight {
+    pub row: i32,
+    pub col: i32,
+    pub radius: i32,
+}
+
+impl Sight {
+    pub fn new(row: i32, col: i32, radius: i32) -> Sight {
+        Sight {
+            row: row,
+            col: col,
+            radius: radius,
+        }
+    }
+}
+
+/// The bounding box, in tiles, of everything whose visibility changed for a player
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DirtyRegion {
+    pub min_row: i32,
+    pub min_col: i32,
+    pub max_row: i32,
+    pub max_col: i32,
+}
+
+impl DirtyRegion {
+    fn tile(row: i32, col: i32) -> DirtyRegion {
+        DirtyRegion {
+            min_row: row,
+            min_col: col,
+            max_row: row,
+            max_col: col,
+        }
+    }
+
+    fn include(&mut self, row: i32, col: i32) {
+        self.min_row = cmp::min(self.min_row, row);
+        self.min_col = cmp::min(self.min_col, col);
+        self.max_row = cmp::max(self.max_row, row);
+        self.max_col = cmp::max(self.max_col, col);
+    }
+}
+
+struct PlayerVision {
+    /// How many of the player's own units see each tile
+    own_count: Vec<u16>,
+    /// Every tile the player's own units have ever seen
+    own_explored: Vec<bool>,
+
+    /// How many of the players this player shares vision with (including itself) see each tile
+    visible_count: Vec<u8>,
+    /// Every tile this player or anyone sharing vision with them has seen
+    explored: Vec<bool>,
+    /// What changed for this player since the region was last taken
+    dirty: Option<DirtyRegion>,
+}
+
+impl PlayerVision {
+    fn new(tile_count: usize) -> PlayerVision {
+        PlayerVision {
+            own_count: vec![0; tile_count],
+            own_explored: vec![false; tile_count],
+            visible_count: vec![0; tile_count],
+            explored: vec![false; tile_count],
+            dirty: None,
+        }
+    }
+
+    fn mark_dirty(&mut self, row: i32, col: i32) {
+        match self.dirty {
+            Some(ref mut region) => region.include(row, col),
+            None => self.dirty = Some(DirtyRegion::tile(row, col)),
+        }
+    }
+}
+
+/// Which tiles each player can see and has explored. Every player's own sight is kept as a
+/// count of the units seeing each tile, and only the units whose sight changed since the last
+/// update get added and taken away, so units standing still cost nothing. Tiles that come into
+/// or go out of a player's own sight are passed on to the allies sharing vision with them, so
+/// their maps never have to be merged from scratch unless who shares with whom changes.
+pub struct FogOfWar {
+    width: i32,
+    height: i32,
+    players: BTreeMap<PlayerId, PlayerVision>,
+    /// For each viewing player, the players whose sight they get (always including themselves)
+    sharing: BTreeMap<PlayerId, Vec<PlayerId>>,
+    /// The owner and sight each unit was last counted with, by entity ID
+    unit_sights: HashMap<u32, (PlayerId, Sight)>,
+}
+
+impl FogOfWar {
+    pub fn new(width: i32, height: i32) -> FogOfWar {
+        FogOfWar {
+            width: width,
+            height: height,
+            players: BTreeMap::new(),
+            sharing: BTreeMap::new(),
+            unit_sights: HashMap::new(),
+        }
+    }
+
+    pub fn is_visible(&self, player_id: PlayerId, row: i32, col: i32) -> bool {
+        match (self.players.get(&player_id), self.index(row, col)) {
+            (Some(vision), Some(index)) => vision.visible_count[index] > 0,
+            _ => false,
+        }
+    }
+
+    pub fn is_explored(&self, player_id: PlayerId, row: i32, col: i32) -> bool {
+        match (self.players.get(&player_id), self.index(row, col)) {
+            (Some(vision), Some(index)) => vision.explored[index],
+            _ => false,
+        }
+    }
+
+    /// Searches outward from the tile through what the player has explored, and returns the
+    /// nearest unexplored tile on the edge of it that has at least `min_unexplored` unexplored
+    /// tiles within `radius` of it, or failing that, the edge tile with the most. Tiles that
+    /// `skip` returns true for aren't picked, such as ones a unit has failed to reach already.
+    /// None once there's nothing left to explore.
+    pub fn nearest_unexplored_region<F>(&self,
+                                        player_id: PlayerId,
+                                        row: i32,
+                                        col: i32,
+                                        radius: i32,
+                                        min_unexplored: usize,
+                                        skip: F)
+                                        -> Option<(i32, i32)>
+        where F: Fn(i32, i32) -> bool
+    {
+        let (vision, start) = match (self.players.get(&player_id), self.index(row, col)) {
+            (Some(vision), Some(start)) => (vision, start),
+            _ => return None,
+        };
+
+        let mut seen = vec![false; self.tile_count()];
+        let mut queue = VecDeque::new();
+        seen[start] = true;
+        queue.push_back((row, col));
+        let mut best: Option<((i32, i32), usize)> = None;
+        while let Some((row, col)) = queue.pop_front() {
+            if !vision.explored[(row * self.width + col) as usize] {
+                if !skip(row, col) {
+                    let unexplored = self.count_unexplored(vision, row, col, radius);
+                    if unexplored >= min_unexplored {
+                        return Some((row, col));
+                    }
+                    if best.map_or(true, |(_, most)| unexplored > most) {
+                        best = Some(((row, col), unexplored));
+                    }
+                }
+                // The search only spreads through what's been explored
+                continue;
+            }
+            for &(d_row, d_col) in &NEIGHBORS {
+                let (next_row, next_col) = (row + d_row, col + d_col);
+                if let Some(index) = self.index(next_row, next_col) {
+                    if !seen[index] {
+                        seen[index] = true;
+                        queue.push_back((next_row, next_col));
+                    }
+                }
+            }
+        }
+        best.map(|(tile, _)| tile)
+    }
+
+    /// The tiles the player's own units have explored, and the tiles the player has explored
+    /// along with the allies sharing vision with them, for saves
+    pub fn explored_tiles(&self, player_id: PlayerId) -> Option<(Vec<bool>, Vec<bool>)> {
+        self.players.get(&player_id).map(|vision| (vision.own_explored.clone(), vision.explored.clone()))
+    }
+
+    /// Puts back what the player had explored in a saved game, before any units are counted.
+    /// Returns false if the tiles don't fit the map.
+    pub fn restore_explored(&mut self,
+                            player_id: PlayerId,
+                            own_explored: Vec<bool>,
+                            explored: Vec<bool>)
+                            -> bool {
+        let tile_count = self.tile_count();
+        if own_explored.len() != tile_count || explored.len() != tile_count {
+            return false;
+        }
+        let (width, height) = (self.width, self.height);
+        let vision = self.vision_mut(player_id);
+        vision.own_explored = own_explored;
+        vision.explored = explored;
+        if width > 0 && height > 0 {
+            vision.mark_dirty(0, 0);
+            vision.mark_dirty(height - 1, width - 1);
+        }
+        true
+    }
+
+    /// Hands over the region of the player's map that changed since the last call, so that
+    /// anything drawn from the map only has to redo that part of it
+    pub fn take_dirty_region(&mut self, player_id: PlayerId) -> Option<DirtyRegion> {
+        self.players.get_mut(&player_id).and_then(|vision| vision.dirty.take())
+    }
+
+    /// Sets whose sight the viewing player gets. When that changes, the viewer's map is rebuilt
+    /// from the other players' own maps once, which is also when explored areas are shared.
+    pub fn set_sharing(&mut self, viewer_id: PlayerId, mut owner_ids: Vec<PlayerId>) {
+        if !owner_ids.contains(&viewer_id) {
+            owner_ids.push(viewer_id);
+        }
+        owner_ids.sort();
+        if self.sharing.get(&viewer_id) == Some(&owner_ids) {
+            return;
+        }
+
+        let tile_count = self.tile_count();
+        let mut visible_count = vec![0u8; tile_count];
+        let mut explored = vec![false; tile_count];
+        for owner_id in &owner_ids {
+            if let Some(owner) = self.players.get(owner_id) {
+                for (count, &own_count) in visible_count.iter_mut().zip(owner.own_count.iter()) {
+                    if own_count > 0 {
+                        *count += 1;
+                    }
+                }
+                for (tile_explored, &own_explored) in explored.iter_mut().zip(owner.own_explored.iter()) {
+                    *tile_explored = *tile_explored || own_explored;
+                }
+            }
+        }
+
+        let (width, height) = (self.width, self.height);
+        let viewer = self.vision_mut(viewer_id);
+        viewer.visible_count = visible_count;
+        for (tile_explored, &already_explored) in explored.iter_mut().zip(viewer.explored.iter()) {
+            *tile_explored = *tile_explored || already_explored;
+        }
+        viewer.explored = explored;
+        if width > 0 && height > 0 {
+            viewer.mark_dirty(0, 0);
+            viewer.mark_dirty(height - 1, width - 1);
+        }
+        self.sharing.insert(viewer_id, owner_ids);
+    }
+
+    /// Updates what a unit sees, or stops counting it with None. Does nothing if the unit's
+    /// owner and sight are the same as last time, which is the case for most units most ticks.
+    pub fn set_unit_sight(&mut self, entity_id: u32, owner_id: PlayerId, sight: Option<Sight>) {
+        let previous = self.unit_sights.get(&entity_id).cloned();
+        let current = sight.map(|sight| (owner_id, sight));
+        if previous == current {
+            return;
+        }
+
+        // Adding before taking away keeps the tiles both sights cover from going out of sight
+        // and straight back in
+        if let Some((owner_id, sight)) = current {
+            self.add_sight(owner_id, &sight, true);
+            self.unit_sights.insert(entity_id, (owner_id, sight));
+        } else {
+            self.unit_sights.remove(&entity_id);
+        }
+        if let Some((owner_id, sight)) = previous {
+            self.add_sight(owner_id, &sight, false);
+        }
+    }
+
+    /// Stops counting the units the function returns false for, such as ones that have died
+    pub fn retain_units<F: Fn(u32) -> bool>(&mut self, keep: F) {
+        let removed: Vec<(PlayerId, Sight)> = self.unit_sights
+            .iter()
+            .filter(|&(entity_id, _)| !keep(*entity_id))
+            .map(|(_, owner_and_sight)| *owner_and_sight)
+            .collect();
+        if removed.is_empty() {
+            return;
+        }
+        self.unit_sights.retain(|entity_id, _| keep(*entity_id));
+        for (owner_id, sight) in removed {
+            self.add_sight(owner_id, &sight, false);
+        }
+    }
+
+    /// Adds or takes away one unit's sight from its owner, and passes the tiles that came into
+    /// or went out of the owner's sight on to everyone sharing the owner's vision
+    fn add_sight(&mut self, owner_id: PlayerId, sight: &Sight, adding: bool) {
+        if !self.sharing.contains_key(&owner_id) {
+            self.set_sharing(owner_id, Vec::new());
+        }
+
+        let mut changed = Vec::new();
+        {
+            let (width, height) = (self.width, self.height);
+            let owner = self.vision_mut(owner_id);
+            for_each_tile_in_sight(sight, width, height, |index| {
+                let count = &mut owner.own_count[index];
+                if adding {
+                    *count += 1;
+                    if *count == 1 {
+                        owner.own_explored[index] = true;
+                        changed.push(index);
+                    }
+                } else {
+                    *count -= 1;
+                    if *count == 0 {
+                        changed.push(index);
+                    }
+                }
+            });
+        }
+        if changed.is_empty() {
+            return;
+        }
+
+        let viewer_ids: Vec<PlayerId> = self.sharing
+            .iter()
+            .filter(|&(_, owner_ids)| owner_ids.contains(&owner_id))
+            .map(|(viewer_id, _)| *viewer_id)
+            .collect();
+        let width = self.width;
+        for viewer_id in viewer_ids {
+            let viewer = self.vision_mut(viewer_id);
+            for &index in &changed {
+                if adding {
+                    viewer.visible_count[index] += 1;
+                    viewer.explored[index] = true;
+                } else {
+                    viewer.visible_count[index] -= 1;
+                }
+                let crossed = if adding {
+                    viewer.visible_count[index] == 1
+                } else {
+                    viewer.visible_count[index] == 0
+                };
+                if crossed {
+                    viewer.mark_dirty(index as i32 / width, index as i32 % width);
+                }
+            }
+        }
+    }
+
+    /// How many tiles on the map within the square of the given radius around the tile the
+    /// player hasn't explored
+    fn count_unexplored(&self, vision: &PlayerVision, row: i32, col: i32, radius: i32) -> usize {
+        let mut count = 0;
+        for row in cmp::max(0, row - radius)..cmp::min(self.height, row + radius + 1) {
+            for col in cmp::max(0, col - radius)..cmp::min(self.width, col + radius + 1) {
+                if !vision.explored[(row * self.width + col) as usize] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn tile_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    fn index(&self, row: i32, col: i32) -> Option<usize> {
+        if row < 0 || col < 0 || row >= self.height || col >= self.width {
+            None
+        } else {
+            Some((row * self.width + col) as usize)
+        }
+    }
+
+    fn vision_mut<'a>(&'a mut self, player_id: PlayerId) -> &'a mut PlayerVision {
+        let tile_count = self.tile_count();
+        self.players.entry(player_id).or_insert_with(|| PlayerVision::new(tile_count))
+    }
+}
+
+/// Calls the function with the index of every tile on the map within the sight's radius
+fn for_each_tile_in_sight<F: FnMut(usize)>(sight: &Sight, width: i32, height: i32, mut f: F) {
+    let radius_squared = sight.radius * sight.radius;
+    for row in cmp::max(0, sight.row - sight.radius)..cmp::min(height, sight.row + sight.radius + 1) {
+        for col in cmp::max(0, sight.col - sight.radius)..cmp::min(width, sight.col + sight.radius + 1) {
+            let (d_row, d_col) = (row - sight.row, col - sight.col);
+            if d_row * d_row + d_col * d_col <= radius_squared {
+                f((row * width + col) as usize);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use identifier::PlayerId;
+    use super::{DirtyRegion, FogOfWar, Sight};
+
+    fn id(val: usize) -> PlayerId {
+        val.into()
+    }
+
+    #[test]
+    fn test_sight_is_tracked_per_player() {
+        let mut fog = FogOfWar::new(10, 10);
+        fog.set_sharing(id(1), vec![]);
+        fog.set_sharing(id(2), vec![]);
+        fog.set_unit_sight(1, id(1), Some(Sight::new(2, 2, 1)));
+
+        assert!(fog.is_visible(id(1), 2, 2));
+        assert!(fog.is_visible(id(1), 1, 2));
+        assert!(!fog.is_visible(id(1), 1, 1));
+        assert!(!fog.is_visible(id(2), 2, 2));
+
+        fog.set_unit_sight(1, id(1), Some(Sight::new(7, 7, 0)));
+        assert!(!fog.is_visible(id(1), 2, 2));
+        assert!(fog.is_explored(id(1), 2, 2));
+        assert!(fog.is_visible(id(1), 7, 7));
+        assert!(!fog.is_explored(id(2), 7, 7));
+    }
+
+    #[test]
+    fn test_overlapping_units() {
+        let mut fog = FogOfWar::new(10, 10);
+        fog.set_unit_sight(1, id(1), Some(Sight::new(2, 2, 1)));
+        fog.set_unit_sight(2, id(1), Some(Sight::new(2, 3, 1)));
+
+        fog.set_unit_sight(1, id(1), None);
+        assert!(fog.is_visible(id(1), 2, 2));
+        assert!(!fog.is_visible(id(1), 2, 1));
+
+        fog.retain_units(|entity_id| entity_id != 2);
+        assert!(!fog.is_visible(id(1), 2, 3));
+        assert!(fog.is_explored(id(1), 2, 1));
+    }
+
+    #[test]
+    fn test_changing_owner_moves_the_sight() {
+        let mut fog = FogOfWar::new(10, 10);
+        fog.set_unit_sight(1, id(1), Some(Sight::new(4, 4, 0)));
+        fog.set_unit_sight(1, id(2), Some(Sight::new(4, 4, 0)));
+        assert!(!fog.is_visible(id(1), 4, 4));
+        assert!(fog.is_visible(id(2), 4, 4));
+    }
+
+    #[test]
+    fn test_dirty_region_covers_the_changes() {
+        let mut fog = FogOfWar::new(10, 10);
+        fog.set_sharing(id(1), vec![]);
+        fog.take_dirty_region(id(1));
+
+        fog.set_unit_sight(1, id(1), Some(Sight::new(2, 2, 0)));
+        fog.set_unit_sight(1, id(1), Some(Sight::new(3, 5, 0)));
+        assert_eq!(Some(DirtyRegion {
+                       min_row: 2,
+                       min_col: 2,
+                       max_row: 3,
+                       max_col: 5,
+                   }),
+                   fog.take_dirty_region(id(1)));
+
+        // Nothing changes for a unit that stays put
+        fog.set_unit_sight(1, id(1), Some(Sight::new(3, 5, 0)));
+        assert_eq!(None, fog.take_dirty_region(id(1)));
+    }
+
+    #[test]
+    fn test_shared_vision() {
+        let mut fog = FogOfWar::new(10, 10);
+        fog.set_sharing(id(1), vec![]);
+        fog.set_sharing(id(2), vec![]);
+        fog.set_unit_sight(1, id(1), Some(Sight::new(2, 2, 0)));
+        fog.set_unit_sight(2, id(2), Some(Sight::new(5, 5, 0)));
+        fog.set_unit_sight(3, id(2), Some(Sight::new(2, 2, 0)));
+        fog.set_unit_sight(2, id(2), Some(Sight::new(6, 6, 0)));
+
+        // Sharing hands over everything the ally has explored so far
+        fog.set_sharing(id(1), vec![id(2)]);
+        assert!(fog.is_explored(id(1), 5, 5));
+        assert!(fog.is_visible(id(1), 6, 6));
+        assert!(!fog.is_visible(id(2), 0, 0));
+
+        // and from then on keeps up with the ally's sight as it changes
+        fog.set_unit_sight(2, id(2), Some(Sight::new(8, 8, 0)));
+        assert!(!fog.is_visible(id(1), 6, 6));
+        assert!(fog.is_visible(id(1), 8, 8));
+        fog.set_unit_sight(2, id(2), None);
+        fog.set_unit_sight(3, id(2), None);
+        assert!(fog.is_visible(id(1), 2, 2));
+        fog.set_unit_sight(1, id(1), None);
+        assert!(!fog.is_visible(id(1), 2, 2));
+        assert!(fog.is_explored(id(1), 8, 8));
+
+        fog.set_sharing(id(1), vec![]);
+        fog.set_unit_sight(2, id(2), Some(Sight::new(9, 9, 0)));
+        assert!(!fog.is_visible(id(1), 9, 9));
+        assert!(!fog.is_explored(id(1), 9, 9));
+    }
+
+    #[test]
+    fn test_nearest_unexplored_region() {
+        // A strip of ten tiles with the middle five explored, leaving two unexplored tiles on
+        // the left and three on the right
+        let mut fog = FogOfWar::new(10, 1);
+        for col in 2..7 {
+            fog.set_unit_sight(col as u32, id(1), Some(Sight::new(0, col, 0)));
+        }
+        fn never(_row: i32, _col: i32) -> bool {
+            false
+        }
+
+        assert_eq!(Some((0, 1)), fog.nearest_unexplored_region(id(1), 0, 3, 2, 1, never));
+        // The farther edge is taken when the nearer one isn't big enough
+        assert_eq!(Some((0, 7)), fog.nearest_unexplored_region(id(1), 0, 3, 2, 3, never));
+        // and the biggest one when none is
+        assert_eq!(Some((0, 7)), fog.nearest_unexplored_region(id(1), 0, 3, 2, 5, never));
+        assert_eq!(Some((0, 1)),
+                   fog.nearest_unexplored_region(id(1), 0, 3, 2, 3, |_, col| col == 7));
+
+        fog.set_unit_sight(1, id(1), Some(Sight::new(0, 5, 5)));
+        assert_eq!(None, fog.nearest_unexplored_region(id(1), 0, 3, 2, 1, never));
+        assert_eq!(None, fog.nearest_unexplored_region(id(2), 0, 3, 2, 1, never));
+    }
+
+    #[test]
+    fn test_restored_tiles_stay_explored() {
+        let mut saved = FogOfWar::new(4, 4);
+        saved.set_unit_sight(1, id(1), Some(Sight::new(1, 1, 1)));
+        let (own_explored, explored) = saved.explored_tiles(id(1)).unwrap();
+
+        let mut fog = FogOfWar::new(4, 4);
+        assert!(!fog.restore_explored(id(1), vec![true; 4], vec![true; 4]));
+        assert!(fog.restore_explored(id(1), own_explored, explored));
+        fog.set_sharing(id(1), vec![]);
+        assert!(fog.is_explored(id(1), 1, 2));
+        assert!(!fog.is_visible(id(1), 1, 2));
+        assert!(!fog.is_explored(id(1), 3, 3));
+    }
+}
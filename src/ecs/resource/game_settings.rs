@@ -0,0 +1,49 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use types::Fixed;
+
+/// Options chosen in the lobby before the game starts
+#[derive(Clone, Debug)]
+pub struct GameSettings {
+    pub allow_cheats: bool,
+
+    /// Allies see what each other see from the start, without having to research Writing
+    pub allied_vision: bool,
+
+    /// Speed to start the game at; can be changed in-game afterwards
+    pub game_speed: Fixed,
+
+    /// Every random number in the game comes from this; replaying a game with the same seed and
+    /// commands reproduces it exactly
+    pub random_seed: u64,
+}
+
+impl GameSettings {
+    pub fn new() -> GameSettings {
+        GameSettings {
+            allow_cheats: false,
+            allied_vision: false,
+            game_speed: 1.into(),
+            random_seed: 0,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use std::collections::HashMap;
+use types::Fixed;
+
+/// The speeds the game can be run at, from slowest to fastest
+pub const SPEED_MULTIPLIERS: [Fixed; 6] = [fixed_const!(0.5),
+                                           fixed_const!(1),
+                                           fixed_const!(1.5),
+                                           fixed_const!(2),
+                                           fixed_const!(4),
+                                           fixed_const!(8)];
+
+const NORMAL_SPEED_INDEX: usize = 1;
+
+/// Simulation speed. Changing it only changes how often the fixed time step is run,
+/// so it never affects the outcome of the simulation.
+pub struct GameSpeed {
+    speed_index: usize,
+    proposals: HashMap<PlayerId, usize>,
+}
+
+impl GameSpeed {
+    pub fn new() -> GameSpeed {
+        GameSpeed::with_multiplier(SPEED_MULTIPLIERS[NORMAL_SPEED_INDEX])
+    }
+
+    /// Starts at the closest available speed to the given multiplier
+    pub fn with_multiplier(multiplier: Fixed) -> GameSpeed {
+        let mut speed_index = 0;
+        for (index, speed) in SPEED_MULTIPLIERS.iter().enumerate() {
+            if (*speed - multiplier).abs() < (SPEED_MULTIPLIERS[speed_index] - multiplier).abs() {
+                speed_index = index;
+            }
+        }
+        GameSpeed {
+            speed_index: speed_index,
+            proposals: HashMap::new(),
+        }
+    }
+
+    pub fn multiplier(&self) -> Fixed {
+        SPEED_MULTIPLIERS[self.speed_index]
+    }
+
+    pub fn speed_index(&self) -> usize {
+        self.speed_index
+    }
+
+    pub fn faster_index(&self) -> usize {
+        if self.speed_index + 1 < SPEED_MULTIPLIERS.len() {
+            self.speed_index + 1
+        } else {
+            self.speed_index
+        }
+    }
+
+    pub fn slower_index(&self) -> usize {
+        if self.speed_index > 0 {
+            self.speed_index - 1
+        } else {
+            self.speed_index
+        }
+    }
+
+    /// Records a player's vote to change to the given speed. The speed only changes once
+    /// every human player has voted for it, which in single player is immediately.
+    /// Returns true if the speed was changed.
+    pub fn propose(&mut self, player_id: PlayerId, speed_index: usize, voters: &[PlayerId]) -> bool {
+        if speed_index >= SPEED_MULTIPLIERS.len() {
+            return false;
+        }
+
+        self.proposals.insert(player_id, speed_index);
+        let agreed = voters.iter().all(|voter| self.proposals.get(voter) == Some(&speed_index));
+        if agreed {
+            self.speed_index = speed_index;
+            self.proposals.clear();
+        }
+        agreed
+    }
+
+    /// The speed a player has voted for that hasn't been agreed upon yet
+    pub fn proposal(&self, player_id: PlayerId) -> Option<usize> {
+        self.proposals.get(&player_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Fixed;
+
+    #[test]
+    fn test_game_speed_single_player() {
+        let mut game_speed = GameSpeed::new();
+        assert_eq!(Fixed::from(1), game_speed.multiplier());
+
+        let faster = game_speed.faster_index();
+        assert!(game_speed.propose(1.into(), faster, &[1.into()]));
+        assert_eq!(fixed_const!(1.5), game_speed.multiplier());
+    }
+
+    #[test]
+    fn test_game_speed_limits() {
+        let game_speed = GameSpeed::with_multiplier(100.into());
+        assert_eq!(Fixed::from(8), game_speed.multiplier());
+        assert_eq!(game_speed.speed_index(), game_speed.faster_index());
+
+        let game_speed = GameSpeed::with_multiplier(0.into());
+        assert_eq!(fixed_const!(0.5), game_speed.multiplier());
+        assert_eq!(0, game_speed.slower_index());
+    }
+
+    #[test]
+    fn test_game_speed_closest_multiplier() {
+        assert_eq!(fixed_const!(1.5), GameSpeed::with_multiplier(fixed_const!(1.4)).multiplier());
+        assert_eq!(Fixed::from(4), GameSpeed::with_multiplier(fixed_const!(3.5)).multiplier());
+        assert_eq!(Fixed::from(8), GameSpeed::with_multiplier(7.into()).multiplier());
+    }
+
+    #[test]
+    fn test_game_speed_requires_consensus() {
+        let mut game_speed = GameSpeed::new();
+        let voters = [1.into(), 2.into()];
+
+        assert!(!game_speed.propose(1.into(), 3, &voters));
+        assert_eq!(Fixed::from(1), game_speed.multiplier());
+        assert_eq!(Some(3), game_speed.proposal(1.into()));
+
+        assert!(!game_speed.propose(2.into(), 4, &voters));
+        assert!(game_speed.propose(1.into(), 4, &voters));
+        assert_eq!(Fixed::from(4), game_speed.multiplier());
+        assert_eq!(None, game_speed.proposal(2.into()));
+    }
+}
@@ -0,0 +1,119 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use specs;
+use std::collections::HashMap;
+use std::mem;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum IdleCategory {
+    Villager,
+    Military,
+}
+
+/// Keeps track of each player's idle villagers and idle military units.
+/// The UnitActionSystem reports whenever a unit's action queue runs dry or gets
+/// new work, and the IdleUnitSystem sorts those reports into the lists here.
+pub struct IdleUnits {
+    changes: Vec<(specs::Entity, bool)>,
+    idle: HashMap<(PlayerId, IdleCategory), Vec<specs::Entity>>,
+    cycle_positions: HashMap<IdleCategory, usize>,
+}
+
+impl IdleUnits {
+    pub fn new() -> IdleUnits {
+        IdleUnits {
+            changes: Vec::new(),
+            idle: HashMap::new(),
+            cycle_positions: HashMap::new(),
+        }
+    }
+
+    /// Called when the entity's action queue becomes empty
+    pub fn report_idle(&mut self, entity: specs::Entity) {
+        self.changes.push((entity, true));
+    }
+
+    /// Called when the entity starts performing an action
+    pub fn report_busy(&mut self, entity: specs::Entity) {
+        self.changes.push((entity, false));
+    }
+
+    pub fn consume_changes(&mut self) -> Vec<(specs::Entity, bool)> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.changes);
+        consumed
+    }
+
+    pub fn insert(&mut self, player_id: PlayerId, category: IdleCategory, entity: specs::Entity) {
+        let list = self.idle.entry((player_id, category)).or_insert_with(Vec::new);
+        if !list.contains(&entity) {
+            list.push(entity);
+        }
+    }
+
+    pub fn remove(&mut self, entity: specs::Entity) {
+        for list in self.idle.values_mut() {
+            list.retain(|e| *e != entity);
+        }
+    }
+
+    pub fn count(&self, player_id: PlayerId, category: IdleCategory) -> usize {
+        match self.idle.get(&(player_id, category)) {
+            Some(list) => list.len(),
+            None => 0,
+        }
+    }
+
+    /// Returns the next idle unit in the category, wrapping around once the end of the list is reached
+    pub fn cycle(&mut self, player_id: PlayerId, category: IdleCategory) -> Option<specs::Entity> {
+        let list = match self.idle.get(&(player_id, category)) {
+            Some(list) if !list.is_empty() => list,
+            _ => return None,
+        };
+        let position = self.cycle_positions.entry(category).or_insert(0);
+        let entity = list[*position % list.len()];
+        *position = (*position + 1) % list.len();
+        Some(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs;
+    use super::{IdleCategory, IdleUnits};
+
+    #[test]
+    fn test_cycle_starts_at_the_first_unit() {
+        let mut world = specs::World::new();
+        let first = world.create_now().build();
+        let second = world.create_now().build();
+        let mut idle_units = IdleUnits::new();
+        idle_units.insert(1.into(), IdleCategory::Villager, first);
+        idle_units.insert(1.into(), IdleCategory::Villager, second);
+
+        assert_eq!(Some(first), idle_units.cycle(1.into(), IdleCategory::Villager));
+        assert_eq!(Some(second), idle_units.cycle(1.into(), IdleCategory::Villager));
+        assert_eq!(Some(first), idle_units.cycle(1.into(), IdleCategory::Villager));
+        assert_eq!(None, idle_units.cycle(1.into(), IdleCategory::Military));
+    }
+}
@@ -19,7 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use media::{Key, KeyStates, MouseButton};
+use media::{Key, KeyStates, MouseButton, Touch};
 
 use nalgebra::Vector2;
 
@@ -28,6 +28,17 @@ use std::collections::HashMap;
 pub type KeyboardKeyStates = KeyStates<Key>;
 pub type MouseKeyStates = KeyStates<MouseButton>;
 
+/// Text typed since the last update
+pub struct TextInput {
+    pub text: String,
+}
+
+impl TextInput {
+    pub fn new() -> TextInput {
+        TextInput { text: String::new() }
+    }
+}
+
 pub struct MouseState {
     pub position: Vector2<i32>,
     pub key_states: MouseKeyStates,
@@ -41,3 +52,14 @@ impl MouseState {
         }
     }
 }
+
+/// Fingers that are on the screen
+pub struct TouchInput {
+    pub touches: Vec<Touch>,
+}
+
+impl TouchInput {
+    pub fn new() -> TouchInput {
+        TouchInput { touches: Vec::new() }
+    }
+}
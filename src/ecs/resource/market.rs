@@ -0,0 +1,253 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::PlayerId;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use super::Stockpile;
+use types::Fixed;
+
+/// Amount of a commodity bought or sold per transaction
+pub const TRADE_LOT: Fixed = fixed_const!(100);
+
+/// Commodities that can be traded for gold
+pub const COMMODITIES: [ResourceType; 3] = [ResourceType::Food, ResourceType::Wood, ResourceType::Stone];
+
+/// Price (in gold per trade lot) that commodities start at and drift back towards
+const BASE_PRICE: Fixed = fixed_const!(100);
+const MIN_PRICE: Fixed = fixed_const!(20);
+const MAX_PRICE: Fixed = fixed_const!(9999);
+
+/// How much each transaction moves a commodity's price
+const PRICE_STEP: Fixed = fixed_const!(3);
+
+/// How quickly prices drift back towards the base price, in gold per second
+const PRICE_RECOVERY_RATE: Fixed = fixed_const!(0.5);
+
+/// Fraction of the price kept by the market on every transaction until research lowers it
+const DEFAULT_EXCHANGE_FEE: Fixed = fixed_const!(0.3);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MarketTransaction {
+    Buy,
+    Sell,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MarketOrder {
+    pub player_id: PlayerId,
+    pub resource_type: ResourceType,
+    pub transaction: MarketTransaction,
+}
+
+impl MarketOrder {
+    pub fn new(player_id: PlayerId,
+               resource_type: ResourceType,
+               transaction: MarketTransaction)
+               -> MarketOrder {
+        MarketOrder {
+            player_id: player_id,
+            resource_type: resource_type,
+            transaction: transaction,
+        }
+    }
+}
+
+/// Commodity prices shared by every player. Buying a commodity makes it more expensive
+/// and selling it makes it cheaper; left alone, prices slowly return to where they started.
+pub struct Market {
+    prices: BTreeMap<ResourceType, Fixed>,
+    exchange_fees: HashMap<PlayerId, Fixed>,
+    orders: Vec<MarketOrder>,
+}
+
+impl Market {
+    pub fn new() -> Market {
+        let mut prices = BTreeMap::new();
+        for resource_type in &COMMODITIES {
+            prices.insert(*resource_type, BASE_PRICE);
+        }
+        Market {
+            prices: prices,
+            exchange_fees: HashMap::new(),
+            orders: Vec::new(),
+        }
+    }
+
+    /// Price of a trade lot before the exchange fee is applied
+    pub fn price(&self, resource_type: ResourceType) -> Fixed {
+        match self.prices.get(&resource_type) {
+            Some(price) => *price,
+            None => BASE_PRICE,
+        }
+    }
+
+    pub fn exchange_fee(&self, player_id: PlayerId) -> Fixed {
+        match self.exchange_fees.get(&player_id) {
+            Some(fee) => *fee,
+            None => DEFAULT_EXCHANGE_FEE,
+        }
+    }
+
+    /// Changed by research that changes the fee; see `apply_research_effects`
+    pub fn set_exchange_fee(&mut self, player_id: PlayerId, fee: Fixed) {
+        self.exchange_fees.insert(player_id, fee);
+    }
+
+    /// Gold it costs the player to buy a trade lot of the commodity
+    pub fn buy_price(&self, player_id: PlayerId, resource_type: ResourceType) -> Fixed {
+        let price = self.price(resource_type);
+        (price + price * self.exchange_fee(player_id)).round()
+    }
+
+    /// Gold the player gets for selling a trade lot of the commodity
+    pub fn sell_price(&self, player_id: PlayerId, resource_type: ResourceType) -> Fixed {
+        let price = self.price(resource_type);
+        (price - price * self.exchange_fee(player_id)).round()
+    }
+
+    /// Performs the transaction against the player's stockpile. Returns false if
+    /// the player couldn't afford it or the resource can't be traded.
+    pub fn trade(&mut self, order: &MarketOrder, stockpile: &mut Stockpile) -> bool {
+        if !self.prices.contains_key(&order.resource_type) {
+            return false;
+        }
+
+        match order.transaction {
+            MarketTransaction::Buy => {
+                let cost = self.buy_price(order.player_id, order.resource_type);
+                if !stockpile.take(ResourceType::Gold, cost) {
+                    return false;
+                }
+                stockpile.add(order.resource_type, TRADE_LOT);
+                self.adjust_price(order.resource_type, PRICE_STEP);
+            }
+            MarketTransaction::Sell => {
+                if !stockpile.take(order.resource_type, TRADE_LOT) {
+                    return false;
+                }
+                stockpile.add(ResourceType::Gold,
+                              self.sell_price(order.player_id, order.resource_type));
+                self.adjust_price(order.resource_type, -PRICE_STEP);
+            }
+        }
+        true
+    }
+
+    /// Puts a commodity's price back to what it was in a saved game
+    pub fn set_price(&mut self, resource_type: ResourceType, price: Fixed) {
+        if let Some(current) = self.prices.get_mut(&resource_type) {
+            *current = cmp::min(MAX_PRICE, cmp::max(MIN_PRICE, price));
+        }
+    }
+
+    /// Moves all of the prices back towards the base price
+    pub fn recover_prices(&mut self, time_step: Fixed) {
+        let recovery = PRICE_RECOVERY_RATE * time_step;
+        for price in self.prices.values_mut() {
+            if *price > BASE_PRICE {
+                *price = cmp::max(BASE_PRICE, *price - recovery);
+            } else if *price < BASE_PRICE {
+                *price = cmp::min(BASE_PRICE, *price + recovery);
+            }
+        }
+    }
+
+    pub fn queue_order(&mut self, order: MarketOrder) {
+        self.orders.push(order);
+    }
+
+    pub fn consume_orders(&mut self) -> Vec<MarketOrder> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.orders);
+        consumed
+    }
+
+    fn adjust_price(&mut self, resource_type: ResourceType, amount: Fixed) {
+        if let Some(price) = self.prices.get_mut(&resource_type) {
+            *price = cmp::min(MAX_PRICE, cmp::max(MIN_PRICE, *price + amount));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::resource::Stockpile;
+    use super::{Market, MarketOrder, MarketTransaction};
+    use types::Fixed;
+
+    fn order(resource_type: ResourceType, transaction: MarketTransaction) -> MarketOrder {
+        MarketOrder::new(1.into(), resource_type, transaction)
+    }
+
+    #[test]
+    fn test_buy_and_sell() {
+        let mut market = Market::new();
+        let mut stockpile = Stockpile::new();
+        stockpile.set(ResourceType::Gold, 200.into());
+
+        assert_eq!(Fixed::from(130), market.buy_price(1.into(), ResourceType::Wood));
+        assert!(market.trade(&order(ResourceType::Wood, MarketTransaction::Buy), &mut stockpile));
+        assert_eq!(Fixed::from(70), stockpile.amount(ResourceType::Gold));
+        assert_eq!(Fixed::from(100), stockpile.amount(ResourceType::Wood));
+        assert_eq!(Fixed::from(103), market.price(ResourceType::Wood));
+
+        // Can't afford a second lot
+        assert!(!market.trade(&order(ResourceType::Wood, MarketTransaction::Buy), &mut stockpile));
+        assert_eq!(Fixed::from(103), market.price(ResourceType::Wood));
+
+        assert!(market.trade(&order(ResourceType::Wood, MarketTransaction::Sell), &mut stockpile));
+        assert_eq!(Fixed::from(0), stockpile.amount(ResourceType::Wood));
+        assert_eq!(Fixed::from(70 + 72), stockpile.amount(ResourceType::Gold));
+        assert_eq!(Fixed::from(100), market.price(ResourceType::Wood));
+
+        // Gold itself isn't a commodity
+        assert!(!market.trade(&order(ResourceType::Gold, MarketTransaction::Sell), &mut stockpile));
+    }
+
+    #[test]
+    fn test_exchange_fee() {
+        let mut market = Market::new();
+        market.set_exchange_fee(1.into(), fixed_const!(0.15));
+        assert_eq!(Fixed::from(115), market.buy_price(1.into(), ResourceType::Food));
+        assert_eq!(Fixed::from(85), market.sell_price(1.into(), ResourceType::Food));
+        assert_eq!(Fixed::from(130), market.buy_price(2.into(), ResourceType::Food));
+    }
+
+    #[test]
+    fn test_price_limits_and_recovery() {
+        let mut market = Market::new();
+        let mut stockpile = Stockpile::new();
+        stockpile.set(ResourceType::Stone, 100000.into());
+        for _ in 0..100 {
+            market.trade(&order(ResourceType::Stone, MarketTransaction::Sell), &mut stockpile);
+        }
+        assert_eq!(Fixed::from(20), market.price(ResourceType::Stone));
+
+        market.recover_prices(10.into());
+        assert_eq!(Fixed::from(25), market.price(ResourceType::Stone));
+        market.recover_prices(1000.into());
+        assert_eq!(Fixed::from(100), market.price(ResourceType::Stone));
+    }
+}
@@ -0,0 +1,79 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use nalgebra::Vector2;
+use super::{COMMODITIES, MarketTransaction, UiLayout, UiPanel};
+use types::Rect;
+
+const ROW_HEIGHT: i32 = 24;
+const BUTTON_SIZE: i32 = 20;
+const BUTTON_SPACING: i32 = 4;
+const PRICE_WIDTH: i32 = 120;
+
+pub const MARKET_TRANSACTIONS: [MarketTransaction; 2] = [MarketTransaction::Buy, MarketTransaction::Sell];
+
+/// Screen-space layout and open/closed state of the market panel, which the UI layout places,
+/// in the top right corner of the screen by default. Each commodity has a row with a buy and a
+/// sell button, followed by the current buy and sell prices.
+pub struct MarketPanel {
+    pub open: bool,
+}
+
+impl MarketPanel {
+    pub fn new() -> MarketPanel {
+        MarketPanel { open: false }
+    }
+
+    /// Where the panel is; the buttons and prices are placed within it
+    pub fn bounds(&self, layout: &UiLayout, viewport_size: &Vector2<i32>) -> Rect {
+        let width = self.width();
+        let height = COMMODITIES.len() as i32 * ROW_HEIGHT + BUTTON_SPACING;
+        layout.place(UiPanel::MarketPanel, &Vector2::new(width, height), viewport_size)
+    }
+
+    pub fn button(&self, bounds: &Rect, row: usize, transaction_index: usize) -> Rect {
+        let x = bounds.x + BUTTON_SPACING + transaction_index as i32 * (BUTTON_SIZE + BUTTON_SPACING);
+        let y = bounds.y + BUTTON_SPACING + row as i32 * ROW_HEIGHT;
+        Rect::of(x, y, BUTTON_SIZE, BUTTON_SIZE)
+    }
+
+    /// Top left of where the prices for the row should be drawn
+    pub fn price_position(&self, bounds: &Rect, row: usize) -> Vector2<i32> {
+        let last_button = self.button(bounds, row, MARKET_TRANSACTIONS.len() - 1);
+        Vector2::new(last_button.x + BUTTON_SIZE + BUTTON_SPACING * 2, last_button.y + 4)
+    }
+
+    /// Finds which commodity row and transaction button is under the given screen position
+    pub fn button_at(&self, bounds: &Rect, position: &Vector2<i32>) -> Option<(usize, usize)> {
+        for row in 0..COMMODITIES.len() {
+            for transaction_index in 0..MARKET_TRANSACTIONS.len() {
+                if self.button(bounds, row, transaction_index).contains(position.x, position.y) {
+                    return Some((row, transaction_index));
+                }
+            }
+        }
+        None
+    }
+
+    fn width(&self) -> i32 {
+        MARKET_TRANSACTIONS.len() as i32 * (BUTTON_SIZE + BUTTON_SPACING) + PRICE_WIDTH
+    }
+}
@@ -20,21 +20,109 @@
 // SOFTWARE.
 
 mod action_batcher;
+mod ambient_sounds;
+mod area_query_cache;
+mod build_orders;
+mod chat;
+mod cheats;
+mod combat_log;
+mod command_log;
+mod command_panel;
+mod debug_overlay;
+mod deposit_log;
+mod diplomacy;
+mod diplomacy_panel;
+mod entity_inspector;
+mod feedback_effects;
+mod fog_of_war;
+mod game_settings;
+mod game_speed;
+mod idle_units;
 mod input;
+mod market;
+mod market_panel;
 pub mod path_finder;
+mod path_scheduler;
+mod mouse_cursor;
+mod notifications;
 mod occupied_tiles;
+mod option_changes;
+mod player_colors;
 mod players;
+mod production_orders;
+mod random_numbers;
 mod render;
+mod scene_tint;
+mod score_screen;
+mod selection_priority;
+mod selection_subgroup;
+mod simulation_lod;
+mod simulation_tick;
+mod statistics;
+mod stockpile;
 pub mod terrain;
+mod technologies;
+mod tick_arena;
+mod town_bell;
+mod ui_layout;
+mod unit_index;
+mod unit_spawner;
+mod unit_voices;
 mod view_projector;
 mod viewport;
+mod wall_placement;
 
 pub use self::action_batcher::ActionBatcher;
-pub use self::input::{KeyboardKeyStates, MouseState};
-pub use self::occupied_tiles::OccupiedTiles;
-pub use self::path_finder::PathFinder;
+pub use self::ambient_sounds::{AmbientEmitter, AmbientKind, AmbientMix, AmbientSounds, MAX_AUDIBLE_EMITTERS};
+pub use self::area_query_cache::{Area, AreaQueryCache};
+pub use self::build_orders::{BuildOrder, BuildOrders, BuildSite};
+pub use self::chat::{Chat, ChatMessage, MAX_MESSAGE_LENGTH};
+pub use self::cheats::{CHEAT_RESOURCE_AMOUNT, Cheat, CheatRecord, CheatRequest, Cheats, big_daddy_unit_id};
+pub use self::combat_log::{CombatEvent, CombatLog, ElevationModifier, MAX_COMBAT_EVENTS};
+pub use self::command_log::CommandLog;
+pub use self::command_panel::{COMMAND_SLOTS, CommandButton, CommandPage, CommandPanel};
+pub use self::deposit_log::{DepositEvent, DepositLog, MAX_DEPOSIT_EVENTS};
+pub use self::debug_overlay::{ConsoleCommand, DebugOverlay, MAX_COMMAND_LENGTH, SystemTimings};
+pub use self::diplomacy::{Diplomacy, DiplomaticStance, StanceChange, Tribute};
+pub use self::diplomacy_panel::{DiplomacyPanel, PANEL_STANCES, PANEL_TRIBUTE_RESOURCES};
+pub use self::entity_inspector::{EntityInspector, InspectorField};
+pub use self::feedback_effects::{Effect, EffectKind, FeedbackEffects, MAX_EFFECTS};
+pub use self::fog_of_war::{DirtyRegion, FogOfWar, Sight};
+pub use self::game_settings::GameSettings;
+pub use self::game_speed::{GameSpeed, SPEED_MULTIPLIERS};
+pub use self::idle_units::{IdleCategory, IdleUnits};
+pub use self::input::{KeyboardKeyStates, MouseState, TextInput, TouchInput};
+pub use self::market::{COMMODITIES, Market, MarketOrder, MarketTransaction, TRADE_LOT};
+pub use self::market_panel::{MARKET_TRANSACTIONS, MarketPanel};
+pub use self::mouse_cursor::{CursorKind, MouseCursor};
+pub use self::notifications::{MAX_NOTIFICATIONS, NOTIFICATION_SECONDS, Notification, Notifications};
+pub use self::occupied_tiles::{OccupiedTile, OccupiedTiles};
+pub use self::option_changes::OptionChanges;
+pub use self::path_finder::{PassabilityProvider, PathFinder, PathSearch};
+pub use self::path_scheduler::{FinishedPath, IMMEDIATE_EXPANSIONS, PATH_EXPANSION_BUDGET, PathScheduler};
+pub use self::player_colors::PlayerColors;
 pub use self::players::{Player, Players};
+pub use self::production_orders::{ProductionOrder, ProductionOrderKind, ProductionOrders, SharedTrainOrder};
+pub use self::random_numbers::{RANDOM_STREAMS, RandomNumbers, RandomStream};
 pub use self::render::RenderCommands;
+pub use self::scene_tint::SceneTint;
+pub use self::score_screen::{SCORE_PAGES, ScorePage, ScoreScreen};
+pub use self::selection_priority::{MAX_SELECTED_UNITS, SelectionCandidate, SelectionClass,
+                                   SelectionPriority};
+pub use self::selection_subgroup::SelectionSubgroup;
+pub use self::simulation_lod::{FAR_UPDATE_INTERVAL, SimulationLod};
+pub use self::simulation_tick::SimulationTick;
+pub use self::statistics::{PlayerStatistics, SCORE_CATEGORIES, ScoreCategory, Statistics, TimelineSnapshot};
+pub use self::stockpile::Stockpile;
 pub use self::terrain::{Terrain, Tile};
+pub use self::technologies::{Technologies, apply_research_effects, research_effects};
+pub use self::tick_arena::{Reusable, Scratch, TickArena, TickArenaStats};
+pub use self::town_bell::TownBell;
+pub use self::ui_layout::{Anchor, DEFAULT_LAYOUT, PanelPlacement, Skin, UiLayout, UiPanel, UiTheme};
+pub use self::unit_index::{UnitIndex, UnitTag};
+pub use self::unit_spawner::{RallyPoint, SpawnRequest, UnitSpawner};
+pub use self::unit_voices::{Acknowledgment, UnitVoices};
 pub use self::view_projector::ViewProjector;
 pub use self::viewport::Viewport;
+pub use self::wall_placement::{WALL_UNIT_ID, WallPiece, WallPlacement, WallPlan, WallSegment, WallTile,
+                               wall_line};
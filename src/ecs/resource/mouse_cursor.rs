@@ -0,0 +1,73 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use resource::{DrsKey, ShapeKey};
+
+/// The original game's cursors, in interfac.drs
+const CURSOR_SLP_ID: u32 = 51000;
+
+/// What the cursor shows about what clicking would do where it's pointing
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CursorKind {
+    Normal,
+    /// Over an enemy, with units selected that can fight
+    Attack,
+    /// Over something the selected units can gather from
+    Gather,
+    /// Over one of the player's own units that the selected units can heal or repair
+    Restore,
+    /// Over the unexplored part of the map
+    Invalid,
+}
+
+impl CursorKind {
+    /// The frame of the cursor SLP that shows it
+    pub fn frame(&self) -> usize {
+        match *self {
+            CursorKind::Normal => 0,
+            CursorKind::Attack => 2,
+            CursorKind::Gather => 3,
+            CursorKind::Restore => 4,
+            CursorKind::Invalid => 6,
+        }
+    }
+}
+
+/// Which cursor to show, worked out each frame by the CursorRenderSystem from what's under the
+/// pointer. The game state shows it as a hardware cursor when it can; otherwise `software` is
+/// set, and the CursorRenderSystem draws it with the rest of the frame.
+pub struct MouseCursor {
+    pub kind: CursorKind,
+    pub software: bool,
+}
+
+impl MouseCursor {
+    pub fn new() -> MouseCursor {
+        MouseCursor {
+            kind: CursorKind::Normal,
+            software: false,
+        }
+    }
+
+    pub fn shape_key() -> ShapeKey {
+        ShapeKey::new(DrsKey::Interfac, CURSOR_SLP_ID.into(), 0.into())
+    }
+}
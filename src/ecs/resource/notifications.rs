@@ -0,0 +1,116 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+
+/// Seconds a notification stays up before it goes away
+pub const NOTIFICATION_SECONDS: f32 = 6.;
+
+/// Most notifications kept at once; sending another past this drops the oldest
+pub const MAX_NOTIFICATIONS: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub player_id: PlayerId,
+    pub text: String,
+    /// Seconds since it was sent
+    pub age: f32,
+}
+
+/// Messages telling players about things that need their attention, like villagers that have
+/// run out of work. They're shown on the HUD, and age in real time as it's drawn until they
+/// run out.
+pub struct Notifications {
+    notifications: Vec<Notification>,
+}
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        Notifications { notifications: Vec::new() }
+    }
+
+    pub fn notify(&mut self, player_id: PlayerId, text: String) {
+        info!(player_id = *player_id, "{}", text);
+        if self.notifications.len() >= MAX_NOTIFICATIONS {
+            self.notifications.remove(0);
+        }
+        self.notifications.push(Notification {
+            player_id: player_id,
+            text: text,
+            age: 0.,
+        });
+    }
+
+    /// Ages the notifications, dropping those that have been up long enough
+    pub fn advance(&mut self, seconds: f32) {
+        for notification in &mut self.notifications {
+            notification.age += seconds;
+        }
+        self.notifications.retain(|notification| notification.age < NOTIFICATION_SECONDS);
+    }
+
+    /// The notifications sent to a player, oldest first
+    pub fn for_player<'a>(&'a self, player_id: PlayerId) -> Vec<&'a Notification> {
+        self.notifications.iter().filter(|notification| notification.player_id == player_id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_NOTIFICATIONS, NOTIFICATION_SECONDS, Notifications};
+
+    #[test]
+    fn test_for_player() {
+        let mut notifications = Notifications::new();
+        notifications.notify(1.into(), "first".into());
+        notifications.notify(2.into(), "other".into());
+        notifications.notify(1.into(), "second".into());
+
+        let texts: Vec<&str> = notifications.for_player(1.into()).iter().map(|n| &n.text[..]).collect();
+        assert_eq!(vec!["first", "second"], texts);
+        assert!(notifications.for_player(3.into()).is_empty());
+    }
+
+    #[test]
+    fn test_notifications_run_out() {
+        let mut notifications = Notifications::new();
+        notifications.notify(1.into(), "first".into());
+        notifications.advance(NOTIFICATION_SECONDS / 2.);
+        notifications.notify(1.into(), "second".into());
+        notifications.advance(NOTIFICATION_SECONDS / 2.);
+
+        let texts: Vec<&str> = notifications.for_player(1.into()).iter().map(|n| &n.text[..]).collect();
+        assert_eq!(vec!["second"], texts);
+        notifications.advance(NOTIFICATION_SECONDS);
+        assert!(notifications.for_player(1.into()).is_empty());
+    }
+
+    #[test]
+    fn test_oldest_dropped_when_full() {
+        let mut notifications = Notifications::new();
+        for index in 0..(MAX_NOTIFICATIONS + 1) {
+            notifications.notify(1.into(), index.to_string());
+        }
+        let player_notifications = notifications.for_player(1.into());
+        assert_eq!(MAX_NOTIFICATIONS, player_notifications.len());
+        assert_eq!("1", player_notifications[0].text);
+    }
+}
@@ -19,17 +19,132 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::collections::HashSet;
+use specs::{self, Index};
+use std::collections::{HashMap, HashSet};
 
 /// (row, col) tile position
 pub type OccupiedTile = (i32, i32);
 
+/// The tiles that units can't walk through because something stands on them, like a building
+/// or a tree. Each blocking entity's footprint is kept, so that when one comes or goes only
+/// its own tiles change, and the tiles that changed are kept until they're taken, for whatever
+/// has to catch up with them.
 pub struct OccupiedTiles {
     pub tiles: HashSet<OccupiedTile>,
+    footprints: HashMap<Index, Vec<OccupiedTile>>,
+    /// How many footprints cover each tile, since they can overlap
+    coverage: HashMap<OccupiedTile, u32>,
+    changed: Vec<OccupiedTile>,
+    /// Units standing idle, by the tile they're on. They don't block the tile, but paths go
+    /// around them when that's not much longer, and units walking into them ask them to move.
+    idle_units: HashMap<OccupiedTile, specs::Entity>,
 }
 
 impl OccupiedTiles {
     pub fn new() -> OccupiedTiles {
-        OccupiedTiles { tiles: HashSet::new() }
+        OccupiedTiles {
+            tiles: HashSet::new(),
+            footprints: HashMap::new(),
+            coverage: HashMap::new(),
+            changed: Vec::new(),
+            idle_units: HashMap::new(),
+        }
+    }
+
+    /// Sets the tiles that the entity blocks, replacing any it blocked before
+    pub fn occupy(&mut self, entity_id: Index, footprint: Vec<OccupiedTile>) {
+        if self.footprints.get(&entity_id) == Some(&footprint) {
+            return;
+        }
+        self.vacate(entity_id);
+        for &tile in &footprint {
+            let coverage = self.coverage.entry(tile).or_insert(0);
+            *coverage += 1;
+            if *coverage == 1 {
+                self.tiles.insert(tile);
+                self.changed.push(tile);
+            }
+        }
+        self.footprints.insert(entity_id, footprint);
+    }
+
+    /// Frees the tiles that the entity blocked, other than the ones something else blocks too
+    pub fn vacate(&mut self, entity_id: Index) {
+        let footprint = match self.footprints.remove(&entity_id) {
+            Some(footprint) => footprint,
+            None => return,
+        };
+        for tile in footprint {
+            let uncovered = match self.coverage.get_mut(&tile) {
+                Some(coverage) => {
+                    *coverage -= 1;
+                    *coverage == 0
+                }
+                None => false,
+            };
+            if uncovered {
+                self.coverage.remove(&tile);
+                self.tiles.remove(&tile);
+                self.changed.push(tile);
+            }
+        }
+    }
+
+    /// The entities that block tiles
+    pub fn occupants(&self) -> Vec<Index> {
+        self.footprints.keys().cloned().collect()
+    }
+
+    pub fn set_idle_units(&mut self, idle_units: HashMap<OccupiedTile, specs::Entity>) {
+        self.idle_units = idle_units;
+    }
+
+    pub fn idle_unit_at(&self, tile: OccupiedTile) -> Option<specs::Entity> {
+        self.idle_units.get(&tile).cloned()
+    }
+
+    /// The tiles that have become blocked or free since the last time they were taken
+    pub fn take_changes(&mut self) -> Vec<OccupiedTile> {
+        let mut changed: Vec<OccupiedTile> = self.changed.drain(..).collect();
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OccupiedTiles;
+
+    #[test]
+    fn test_overlapping_footprints() {
+        let mut occupied_tiles = OccupiedTiles::new();
+        occupied_tiles.occupy(1, vec![(0, 0), (0, 1)]);
+        occupied_tiles.occupy(2, vec![(0, 1), (0, 2)]);
+        assert_eq!(3, occupied_tiles.tiles.len());
+        assert_eq!(vec![(0, 0), (0, 1), (0, 2)], occupied_tiles.take_changes());
+
+        // The tile both cover stays blocked until both are gone
+        occupied_tiles.vacate(1);
+        assert!(!occupied_tiles.tiles.contains(&(0, 0)));
+        assert!(occupied_tiles.tiles.contains(&(0, 1)));
+        assert_eq!(vec![(0, 0)], occupied_tiles.take_changes());
+
+        occupied_tiles.vacate(2);
+        assert!(occupied_tiles.tiles.is_empty());
+        assert_eq!(vec![(0, 1), (0, 2)], occupied_tiles.take_changes());
+    }
+
+    #[test]
+    fn test_unchanged_footprint_changes_nothing() {
+        let mut occupied_tiles = OccupiedTiles::new();
+        occupied_tiles.occupy(1, vec![(4, 4)]);
+        occupied_tiles.take_changes();
+        occupied_tiles.occupy(1, vec![(4, 4)]);
+        assert!(occupied_tiles.take_changes().is_empty());
+
+        occupied_tiles.occupy(1, vec![(4, 5)]);
+        assert_eq!(vec![(4, 4), (4, 5)], occupied_tiles.take_changes());
+        assert_eq!(vec![1], occupied_tiles.occupants());
     }
 }
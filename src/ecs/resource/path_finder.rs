@@ -30,6 +30,10 @@ use types::{Fixed, ToFixed, Vector3};
 
 const PASSABILITY_THRESHOLD: f32 = 0.999;
 
+/// What stepping onto a tile with an idle unit on it costs, in tiles walked; enough to go a
+/// few tiles around it, but not all the way around a crowd
+const IDLE_UNIT_COST: i32 = 6;
+
 pub type PathNode = Vector3;
 pub type Path = Vec<PathNode>;
 
@@ -39,6 +43,8 @@ type TilePath = Vec<TileNode>;
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct TilePathCandidate {
     path: TilePath,
+    /// What walking the path costs so far
+    cost: i32,
     heuristic: i32,
     dist_from_target: i32,
     direction: (i32, i32),
@@ -46,12 +52,14 @@ struct TilePathCandidate {
 
 impl TilePathCandidate {
     fn new(path: TilePath,
+           cost: i32,
            heuristic: i32,
            dist_from_target: i32,
            direction: (i32, i32))
            -> TilePathCandidate {
         TilePathCandidate {
             path: path,
+            cost: cost,
             heuristic: heuristic,
             dist_from_target: dist_from_target,
             direction: direction,
@@ -71,8 +79,8 @@ impl PartialOrd for TilePathCandidate {
     }
 }
 
-/// Trait for faking out the Empires DB for tests
-trait PassabilityProvider: Send + Sync {
+/// Trait for faking out the Empires DB for tests and benchmarks
+pub trait PassabilityProvider: Send + Sync {
     fn passable(&self, restriction_id: UnitTerrainRestrictionId, terrain_id: TerrainId) -> bool;
 }
 
@@ -93,6 +101,24 @@ impl PassabilityProvider for EmpiresPassabilityProvider {
     }
 }
 
+/// A path search that can be spread across several calls, for when there's only so much time
+/// to spend on it at once
+pub struct PathSearch {
+    /// Clamped to the map
+    to_tile: TileNode,
+    destination_tile: TileNode,
+    destination: Vector3,
+    restriction_id: UnitTerrainRestrictionId,
+
+    // For tracking the path that comes closest to the target in case it's not possible to reach the target
+    closest: TilePathCandidate,
+
+    // For tracking nodes whose neighbors we've already pushed onto the queue
+    visited: HashSet<TileNode>,
+
+    path_queue: BinaryHeap<TilePathCandidate>,
+}
+
 pub struct PathFinder {
     passability_provider: Box<PassabilityProvider>,
 }
@@ -102,11 +128,15 @@ impl PathFinder {
         PathFinder { passability_provider: Box::new(EmpiresPassabilityProvider::new(empires)) }
     }
 
-    #[cfg(test)]
-    fn new_with(passability_provider: Box<PassabilityProvider>) -> PathFinder {
+    pub fn new_with(passability_provider: Box<PassabilityProvider>) -> PathFinder {
         PathFinder { passability_provider: passability_provider }
     }
 
+    /// Whether units with the given terrain restriction can move over the given terrain
+    pub fn passable(&self, restriction_id: UnitTerrainRestrictionId, terrain_id: TerrainId) -> bool {
+        self.passability_provider.passable(restriction_id, terrain_id)
+    }
+
     pub fn find_path(&self,
                      terrain: &Terrain,
                      occupied_tiles: &OccupiedTiles,
@@ -114,26 +144,52 @@ impl PathFinder {
                      to: &Vector3,
                      restriction_id: UnitTerrainRestrictionId)
                      -> Path {
+        let mut search = self.start_search(terrain, from, to, restriction_id);
+        let mut budget = usize::max_value();
+        self.continue_search(&mut search, terrain, occupied_tiles, &mut budget).unwrap()
+    }
+
+    pub fn start_search(&self,
+                        terrain: &Terrain,
+                        from: &Vector3,
+                        to: &Vector3,
+                        restriction_id: UnitTerrainRestrictionId)
+                        -> PathSearch {
         let from_tile: (i32, i32) = (from.y.into(), from.x.into());
         let to_tile: (i32, i32) = (to.y.into(), to.x.into());
-        let tile_path = self.find_tile_path(terrain, occupied_tiles, from_tile, to_tile, restriction_id);
-
-        let mut position_path: Vec<PathNode> = Vec::new();
-        for tile_node in tile_path.iter().skip(1) {
-            let tile = terrain.tile_at_row_col(tile_node.0, tile_node.1);
-            let position = Vector3::new(tile_node.1.to_fixed() + fixed_const!(0.5),
-                                        tile_node.0.to_fixed() + fixed_const!(0.5),
-                                        tile.elevation.to_fixed());
-            position_path.push(position);
-        }
-        if *tile_path.last().unwrap() == to_tile {
-            position_path.pop(); // Remove the tile center for the last tile
-            position_path.push(*to);
-        }
+        let mut search = new_tile_search(terrain, from_tile, to_tile, restriction_id);
+        search.destination_tile = to_tile;
+        search.destination = *to;
+        search
+    }
+
+    /// Expands up to `budget` more nodes of the search, taking the ones it expands off of the
+    /// budget, and returns the path once the search is finished
+    pub fn continue_search(&self,
+                           search: &mut PathSearch,
+                           terrain: &Terrain,
+                           occupied_tiles: &OccupiedTiles,
+                           budget: &mut usize)
+                           -> Option<Path> {
+        let tile_path = match self.continue_tile_search(search, terrain, occupied_tiles, budget) {
+            Some(tile_path) => tile_path,
+            None => return None,
+        };
+        Some(to_position_path(terrain, &tile_path, search.destination_tile, &search.destination))
+    }
+
+    /// Whether the search has already looked at the tile, either by going through it or by
+    /// going next to it, so that the tile becoming blocked or free could change the path found
+    pub fn has_looked_at(&self, search: &PathSearch, row: i32, col: i32) -> bool {
+        (-1..2).any(|dr| (-1..2).any(|dc| search.visited.contains(&(row + dr, col + dc))))
+    }
 
-        position_path
+    /// The path to wherever an unfinished search has come closest to its destination so far
+    pub fn partial_path(&self, search: &PathSearch, terrain: &Terrain) -> Path {
+        to_position_path(terrain, &search.closest.path, search.destination_tile, &search.destination)
     }
 
+    #[cfg(test)]
     fn find_tile_path(&self,
                       terrain: &Terrain,
                       occupied_tiles: &OccupiedTiles,
@@ -141,65 +197,118 @@ impl PathFinder {
                       to: TileNode,
                       restriction_id: UnitTerrainRestrictionId)
                       -> TilePath {
-        let (width, height) = (terrain.width(), terrain.height());
-        let (from, to) = (clamp(from, width, height), clamp(to, width, height));
-        if from == to {
-            return vec![to];
-        }
-
-        // For tracking the path that comes closest to the target in case it's not possible to reach the target
-        let mut closest = {
-            let distance = dist(&from, &to);
-            TilePathCandidate::new(vec![from], 1 + distance, distance, (0, 0))
-        };
-
-        // For tracking nodes whose neighbors we've already pushed onto the queue
-        let mut visited: HashSet<TileNode> = HashSet::new();
-        visited.insert(from);
+        let mut search = new_tile_search(terrain, from, to, restriction_id);
+        let mut budget = usize::max_value();
+        self.continue_tile_search(&mut search, terrain, occupied_tiles, &mut budget).unwrap()
+    }
 
-        let mut path_queue: BinaryHeap<TilePathCandidate> = BinaryHeap::new();
-        path_queue.push(closest.clone());
+    fn continue_tile_search(&self,
+                            search: &mut PathSearch,
+                            terrain: &Terrain,
+                            occupied_tiles: &OccupiedTiles,
+                            budget: &mut usize)
+                            -> Option<TilePath> {
+        let (width, height) = (terrain.width(), terrain.height());
+        let to = search.to_tile;
 
         // Breadth-first search with priority queue and heuristic; also known as A*
-        while !path_queue.is_empty() {
-            let next = path_queue.pop().unwrap();
+        while *budget > 0 {
+            let next = match search.path_queue.pop() {
+                Some(next) => next,
+                None => return Some(search.closest.path.clone()),
+            };
+            *budget -= 1;
+
             let last_node = *next.path.last().unwrap();
             if last_node == to {
-                return next.path;
+                return Some(next.path);
             } else {
                 // Setup future exploration of neighbors
                 for neighbor in neighbors(&last_node, width, height).into_iter() {
                     let tile = terrain.tile_at_row_col(neighbor.0, neighbor.1);
-                    if !visited.contains(neighbor) && !occupied_tiles.tiles.contains(neighbor) &&
-                       self.passability_provider.passable(restriction_id, tile.terrain_id) {
+                    if !search.visited.contains(neighbor) && !occupied_tiles.tiles.contains(neighbor) &&
+                       self.passability_provider.passable(search.restriction_id, tile.terrain_id) {
                         let mut neighbor_path = next.path.clone();
                         neighbor_path.push(*neighbor);
 
                         let neighbor_direction = (neighbor.0 - last_node.0, neighbor.1 - last_node.1);
                         let neighbor_dist = dist(neighbor, &to);
-                        let neighbor_heuristic = heuristic(&neighbor_path,
+                        // Idle units don't block, but it's better to go around them
+                        let neighbor_cost = if occupied_tiles.idle_unit_at(*neighbor).is_some() {
+                            next.cost + 1 + IDLE_UNIT_COST
+                        } else {
+                            next.cost + 1
+                        };
+                        let neighbor_heuristic = heuristic(neighbor_cost,
                                                            neighbor_dist,
                                                            next.direction != neighbor_direction);
                         let neighbor_candidate = TilePathCandidate::new(neighbor_path,
+                                                                        neighbor_cost,
                                                                         neighbor_heuristic,
                                                                         neighbor_dist,
                                                                         neighbor_direction);
-                        path_queue.push(neighbor_candidate);
+                        search.path_queue.push(neighbor_candidate);
                     }
-                    visited.insert(*neighbor);
+                    search.visited.insert(*neighbor);
                 }
 
                 // Keep track of the closest path
-                if closest.dist_from_target > next.dist_from_target {
-                    closest = next;
+                if search.closest.dist_from_target > next.dist_from_target {
+                    search.closest = next;
                 }
             }
         }
 
-        closest.path
+        None
     }
 }
 
+fn new_tile_search(terrain: &Terrain,
+                   from: TileNode,
+                   to: TileNode,
+                   restriction_id: UnitTerrainRestrictionId)
+                   -> PathSearch {
+    let (width, height) = (terrain.width(), terrain.height());
+    let (from, to) = (clamp(from, width, height), clamp(to, width, height));
+    let closest = {
+        let distance = dist(&from, &to);
+        TilePathCandidate::new(vec![from], 1, 1 + distance, distance, (0, 0))
+    };
+
+    let mut visited: HashSet<TileNode> = HashSet::new();
+    visited.insert(from);
+
+    let mut path_queue: BinaryHeap<TilePathCandidate> = BinaryHeap::new();
+    path_queue.push(closest.clone());
+
+    PathSearch {
+        to_tile: to,
+        destination_tile: to,
+        destination: Vector3::new(0.into(), 0.into(), 0.into()),
+        restriction_id: restriction_id,
+        closest: closest,
+        visited: visited,
+        path_queue: path_queue,
+    }
+}
+
+fn to_position_path(terrain: &Terrain, tile_path: &TilePath, to_tile: TileNode, to: &Vector3) -> Path {
+    let mut position_path: Vec<PathNode> = Vec::new();
+    for tile_node in tile_path.iter().skip(1) {
+        let tile = terrain.tile_at_row_col(tile_node.0, tile_node.1);
+        let position = Vector3::new(tile_node.1.to_fixed() + fixed_const!(0.5),
+                                    tile_node.0.to_fixed() + fixed_const!(0.5),
+                                    tile.elevation.to_fixed());
+        position_path.push(position);
+    }
+    if *tile_path.last().unwrap() == to_tile {
+        position_path.pop(); // Remove the tile center for the last tile
+        position_path.push(*to);
+    }
+
+    position_path
+}
+
 fn neighbors(node: &TileNode, width: i32, height: i32) -> [TileNode; 8] {
     [clamp((node.0 - 1, node.1), width, height),
      clamp((node.0 + 1, node.1), width, height),
@@ -211,9 +320,8 @@ fn neighbors(node: &TileNode, width: i32, height: i32) -> [TileNode; 8] {
      clamp((node.0 + 1, node.1 + 1), width, height)]
 }
 
-fn heuristic(path: &TilePath, dist_to_goal: i32, direction_change: bool) -> i32 {
-    let length = path.len() as i32;
-    length + dist_to_goal + (direction_change as i32)
+fn heuristic(cost: i32, dist_to_goal: i32, direction_change: bool) -> i32 {
+    cost + dist_to_goal + (direction_change as i32)
 }
 
 fn dist(from: &TileNode, to: &TileNode) -> i32 {
@@ -232,6 +340,8 @@ mod tests {
     use dat::{EmpiresDb, EmpiresDbRef};
     use ecs::resource::{OccupiedTiles, Terrain, Tile};
     use identifier::{TerrainId, UnitTerrainRestrictionId};
+    use specs;
+    use std::collections::HashMap;
     use super::*;
     use super::PassabilityProvider;
 
@@ -304,4 +414,66 @@ mod tests {
              (6, 0),
              vec![(2, 4), (3, 5), (4, 4), (5, 3), (6, 2), (7, 1), (6, 0)]);
     }
+
+    #[test]
+    fn test_paths_go_around_idle_units() {
+        let width = 5;
+        let map = vec![1; 15];
+        let (terrain, path_finder) = make_terrain_and_path_finder(map, width);
+        let mut occupied_tiles = OccupiedTiles::new();
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let straight = path_finder.find_tile_path(&terrain, &occupied_tiles, (1, 0), (1, 4), restriction_id);
+        assert_eq!(vec![(1, 0), (1, 1), (1, 2), (1, 3), (1, 4)], straight);
+
+        let mut world = specs::World::new();
+        let mut idle_units = HashMap::new();
+        idle_units.insert((1, 2), world.create_now().build());
+        occupied_tiles.set_idle_units(idle_units);
+        let around = path_finder.find_tile_path(&terrain, &occupied_tiles, (1, 0), (1, 4), restriction_id);
+        assert_eq!(straight.len(), around.len());
+        assert!(!around.contains(&(1, 2)));
+
+        // but not when there's no way around
+        let mut idle_units = HashMap::new();
+        for row in 0..3 {
+            idle_units.insert((row, 2), world.create_now().build());
+        }
+        occupied_tiles.set_idle_units(idle_units);
+        let through = path_finder.find_tile_path(&terrain, &occupied_tiles, (1, 0), (1, 4), restriction_id);
+        assert_eq!(Some(&(1, 4)), through.last());
+    }
+
+    #[test]
+    fn test_search_across_calls() {
+        let width = 5;
+        let map = vec![
+            1, 1, 1, 1, 1,
+            1, 0, 0, 0, 1,
+            1, 1, 1, 0, 1,
+            0, 0, 1, 0, 1,
+            1, 1, 1, 0, 1,
+        ];
+        let (terrain, path_finder) = make_terrain_and_path_finder(map, width);
+        let occupied_tiles = OccupiedTiles::new();
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let from = Vector3::new(0.5.into(), 4.5.into(), 0.into());
+        let to = Vector3::new(4.5.into(), 4.5.into(), 0.into());
+
+        let expected = path_finder.find_path(&terrain, &occupied_tiles, &from, &to, restriction_id);
+
+        let mut search = path_finder.start_search(&terrain, &from, &to, restriction_id);
+        let mut calls = 0;
+        let path = loop {
+            calls += 1;
+            let mut budget = 2;
+            let path = path_finder.continue_search(&mut search, &terrain, &occupied_tiles, &mut budget);
+            if let Some(path) = path {
+                break path;
+            }
+            assert_eq!(0, budget);
+            assert!(!path_finder.partial_path(&search, &terrain).is_empty());
+        };
+        assert!(calls > 1);
+        assert_eq!(expected, path);
+    }
 }
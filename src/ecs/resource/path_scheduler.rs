@@ -0,0 +1,286 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{OccupiedTile, OccupiedTiles, PathFinder, PathSearch, Terrain};
+use ecs::resource::path_finder::{Path, PathNode};
+use identifier::UnitTerrainRestrictionId;
+use specs::Index;
+use std::collections::VecDeque;
+use types::Vector3;
+
+/// How many nodes the queued path searches get to expand between them each tick
+pub const PATH_EXPANSION_BUDGET: usize = 4000;
+
+/// How many nodes a path search gets to expand right away, before the rest of it is queued;
+/// most short paths finish within this
+pub const IMMEDIATE_EXPANSIONS: usize = 48;
+
+struct QueuedSearch {
+    entity_id: Index,
+    search: PathSearch,
+    from: Vector3,
+    to: Vector3,
+    restriction_id: UnitTerrainRestrictionId,
+    /// Where the partial path that the unit was given ends
+    partial_end: PathNode,
+}
+
+/// A path that a queued search finished, for a unit that was given a partial path to follow
+/// until then
+pub struct FinishedPath {
+    pub entity_id: Index,
+    pub path: Path,
+    pub partial_end: PathNode,
+}
+
+/// Caps how much path finding gets done each tick, so that ordering a big group of units around
+/// doesn't cause a frame spike. Units are given a partial path right away so that they can start
+/// moving, and the rest of the search is queued and spread across the following ticks.
+pub struct PathScheduler {
+    budget_per_tick: usize,
+    queue: VecDeque<QueuedSearch>,
+}
+
+impl PathScheduler {
+    pub fn new() -> PathScheduler {
+        PathScheduler::with_budget(PATH_EXPANSION_BUDGET)
+    }
+
+    pub fn with_budget(budget_per_tick: usize) -> PathScheduler {
+        PathScheduler {
+            budget_per_tick: budget_per_tick,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Finds as much of a path for the entity as a small search allows. If that doesn't finish,
+    /// the rest of the search is queued, and `run` hands back the full path once it's found.
+    /// Any search still queued for the entity is dropped, since this one replaces it.
+    pub fn find_path(&mut self,
+                     path_finder: &PathFinder,
+                     terrain: &Terrain,
+                     occupied_tiles: &OccupiedTiles,
+                     entity_id: Index,
+                     from: &Vector3,
+                     to: &Vector3,
+                     restriction_id: UnitTerrainRestrictionId)
+                     -> Path {
+        self.cancel(entity_id);
+
+        let mut search = path_finder.start_search(terrain, from, to, restriction_id);
+        let mut budget = IMMEDIATE_EXPANSIONS;
+        if let Some(path) = path_finder.continue_search(&mut search, terrain, occupied_tiles, &mut budget) {
+            return path;
+        }
+
+        let path = path_finder.partial_path(&search, terrain);
+        self.queue.push_back(QueuedSearch {
+            entity_id: entity_id,
+            search: search,
+            from: *from,
+            to: *to,
+            restriction_id: restriction_id,
+            partial_end: path.last().cloned().unwrap_or(*from),
+        });
+        path
+    }
+
+    pub fn cancel(&mut self, entity_id: Index) {
+        self.queue.retain(|queued| queued.entity_id != entity_id);
+    }
+
+    /// Starts over the queued searches that have already looked at any of the changed tiles,
+    /// since those tiles have become blocked or free and the search so far might be wrong. The
+    /// units keep following their partial paths until the new search finishes.
+    pub fn restart_searches(&mut self,
+                            path_finder: &PathFinder,
+                            terrain: &Terrain,
+                            changed: &[OccupiedTile]) {
+        for queued in &mut self.queue {
+            let stale = changed.iter().any(|&(row, col)| path_finder.has_looked_at(&queued.search, row, col));
+            if stale {
+                queued.search =
+                    path_finder.start_search(terrain, &queued.from, &queued.to, queued.restriction_id);
+            }
+        }
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Continues the queued searches, oldest first, until this tick's budget runs out, and
+    /// returns the paths that got finished
+    pub fn run(&mut self,
+               path_finder: &PathFinder,
+               terrain: &Terrain,
+               occupied_tiles: &OccupiedTiles)
+               -> Vec<FinishedPath> {
+        let mut finished = Vec::new();
+        let mut budget = self.budget_per_tick;
+        while budget > 0 {
+            let path = match self.queue.front_mut() {
+                Some(queued) => {
+                    path_finder.continue_search(&mut queued.search, terrain, occupied_tiles, &mut budget)
+                }
+                None => break,
+            };
+            if let Some(path) = path {
+                let queued = self.queue.pop_front().unwrap();
+                finished.push(FinishedPath {
+                    entity_id: queued.entity_id,
+                    path: path,
+                    partial_end: queued.partial_end,
+                });
+            }
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{EmpiresDb, EmpiresDbRef};
+    use ecs::resource::{OccupiedTiles, PassabilityProvider, PathFinder, Terrain, Tile};
+    use identifier::{TerrainId, UnitTerrainRestrictionId};
+    use specs::Index;
+    use super::PathScheduler;
+    use types::Vector3;
+
+    struct AllPassable;
+
+    impl PassabilityProvider for AllPassable {
+        fn passable(&self, _restriction_id: UnitTerrainRestrictionId, _terrain_id: TerrainId) -> bool {
+            true
+        }
+    }
+
+    fn setup(size: i32) -> (Terrain, PathFinder, OccupiedTiles) {
+        let empires = EmpiresDbRef::new(EmpiresDb::new());
+        let tiles = (0..size * size).map(|_| Tile::new((1 as usize).into(), 0)).collect();
+        let terrain = Terrain::new(size, size, tiles, empires);
+        (terrain, PathFinder::new_with(Box::new(AllPassable)), OccupiedTiles::new())
+    }
+
+    fn position(x: f64, y: f64) -> Vector3 {
+        Vector3::new(x.into(), y.into(), 0.into())
+    }
+
+    #[test]
+    fn test_short_paths_finish_right_away() {
+        let (terrain, path_finder, occupied_tiles) = setup(16);
+        let mut scheduler = PathScheduler::new();
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let path = scheduler.find_path(&path_finder,
+                                       &terrain,
+                                       &occupied_tiles,
+                                       1,
+                                       &position(1.5, 1.5),
+                                       &position(3.5, 1.5),
+                                       restriction_id);
+        assert_eq!(Some(&position(3.5, 1.5)), path.last());
+        assert_eq!(0, scheduler.queued_count());
+    }
+
+    #[test]
+    fn test_long_searches_are_spread_across_ticks() {
+        let (terrain, path_finder, mut occupied_tiles) = setup(64);
+        // A wall across most of the map makes the search expand a lot of nodes
+        for row in 0..60 {
+            occupied_tiles.tiles.insert((row, 32));
+        }
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let (from, to) = (position(20.5, 20.5), position(44.5, 20.5));
+        let full_path = path_finder.find_path(&terrain, &occupied_tiles, &from, &to, restriction_id);
+
+        let mut scheduler = PathScheduler::with_budget(100);
+        let partial_path =
+            scheduler.find_path(&path_finder, &terrain, &occupied_tiles, 7, &from, &to, restriction_id);
+        assert!(!partial_path.is_empty());
+        assert!(partial_path.last() != Some(&to));
+        assert_eq!(1, scheduler.queued_count());
+
+        let mut ticks = 0;
+        let finished = loop {
+            ticks += 1;
+            let mut finished = scheduler.run(&path_finder, &terrain, &occupied_tiles);
+            if !finished.is_empty() {
+                break finished.remove(0);
+            }
+        };
+        assert!(ticks > 1);
+        assert_eq!(7, finished.entity_id);
+        assert_eq!(full_path, finished.path);
+        assert_eq!(partial_path.last(), Some(&finished.partial_end));
+        assert_eq!(0, scheduler.queued_count());
+    }
+
+    #[test]
+    fn test_new_requests_replace_queued_ones() {
+        let (terrain, path_finder, mut occupied_tiles) = setup(64);
+        for row in 0..60 {
+            occupied_tiles.tiles.insert((row, 32));
+        }
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let mut scheduler = PathScheduler::with_budget(100);
+        for _ in 0..3 {
+            scheduler.find_path(&path_finder,
+                                &terrain,
+                                &occupied_tiles,
+                                7,
+                                &position(20.5, 20.5),
+                                &position(44.5, 20.5),
+                                restriction_id);
+        }
+        assert_eq!(1, scheduler.queued_count());
+        scheduler.cancel(7);
+        assert_eq!(0, scheduler.queued_count());
+    }
+
+    #[test]
+    fn test_searches_restart_when_tiles_change() {
+        let (terrain, path_finder, mut occupied_tiles) = setup(64);
+        for row in 0..60 {
+            occupied_tiles.occupy(row as Index, vec![(row, 32)]);
+        }
+        occupied_tiles.take_changes();
+        let restriction_id = UnitTerrainRestrictionId::Flying;
+        let (from, to) = (position(20.5, 20.5), position(44.5, 20.5));
+        let mut scheduler = PathScheduler::with_budget(100);
+        scheduler.find_path(&path_finder, &terrain, &occupied_tiles, 7, &from, &to, restriction_id);
+        scheduler.run(&path_finder, &terrain, &occupied_tiles);
+        assert_eq!(1, scheduler.queued_count());
+
+        // Opening a gap in the wall right next to the start makes the old search wrong
+        occupied_tiles.vacate(20);
+        let changed = occupied_tiles.take_changes();
+        scheduler.restart_searches(&path_finder, &terrain, &changed);
+
+        let full_path = path_finder.find_path(&terrain, &occupied_tiles, &from, &to, restriction_id);
+        let finished = loop {
+            let mut finished = scheduler.run(&path_finder, &terrain, &occupied_tiles);
+            if !finished.is_empty() {
+                break finished.remove(0);
+            }
+        };
+        assert_eq!(full_path, finished.path);
+    }
+}
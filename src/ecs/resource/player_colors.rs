@@ -0,0 +1,60 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use resource::PlayerPalette;
+use types::Color;
+use util::markers::{self, Marker};
+
+/// How players are told apart where they're drawn as swatches and dots rather than with the
+/// graphics, following the options. The graphics get their player colors from the shape manager.
+pub struct PlayerColors {
+    pub palette: PlayerPalette,
+    /// Whether to give each player their own marker shape as well as their color
+    pub markers: bool,
+}
+
+impl PlayerColors {
+    pub fn new() -> PlayerColors {
+        PlayerColors {
+            palette: PlayerPalette::Original,
+            markers: false,
+        }
+    }
+
+    pub fn configure(&mut self, palette: PlayerPalette, markers: bool) {
+        self.palette = palette;
+        self.markers = markers;
+    }
+
+    pub fn color(&self, player_id: PlayerId) -> Color {
+        self.palette.color(*player_id)
+    }
+
+    /// The shape to draw the player's swatches and dots in
+    pub fn marker(&self, player_id: PlayerId) -> Marker {
+        if self.markers {
+            markers::player_marker(player_id)
+        } else {
+            Marker::Square
+        }
+    }
+}
@@ -19,15 +19,21 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use dat::ResourceType;
 use identifier::{CivilizationId, PlayerColorId, PlayerId};
 use scn::Scenario;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use super::Stockpile;
+use types::Fixed;
 
 pub struct Player {
     pub name: String,
     pub player_id: PlayerId,
     pub player_color_id: PlayerColorId,
     pub civ_id: CivilizationId,
+    pub computer: bool,
+    pub stockpile: Stockpile,
+    carry_bonuses: BTreeMap<ResourceType, Fixed>,
 }
 
 impl Player {
@@ -41,8 +47,22 @@ impl Player {
             player_id: player_id,
             player_color_id: player_color_id,
             civ_id: civ_id,
+            computer: false,
+            stockpile: Stockpile::new(),
+            carry_bonuses: BTreeMap::new(),
         }
     }
+
+    /// How much more of the resource the player's villagers carry at once than the game data
+    /// says they do
+    pub fn carry_bonus(&self, resource_type: ResourceType) -> Fixed {
+        self.carry_bonuses.get(&resource_type).cloned().unwrap_or(0.into())
+    }
+
+    /// Changed by research that lets villagers carry more; see `apply_research_effects`
+    pub fn set_carry_bonus(&mut self, resource_type: ResourceType, bonus: Fixed) {
+        self.carry_bonuses.insert(resource_type, bonus);
+    }
 }
 
 pub struct Players {
@@ -62,10 +82,19 @@ impl Players {
         let mut players = Players::new();
         for player_id in scenario.player_ids() {
             let name = scenario.player_data.player_names[*player_id as usize].clone();
-            let civ_id = scenario.player_data.player_civs[*player_id as usize].civilization_id;
+            let player_civ = &scenario.player_data.player_civs[*player_id as usize];
             let color_id = player_id.into();
             let local = player_id == local_player_id;
-            players.add_player(Player::new(name, player_id, color_id, civ_id), local);
+
+            let mut player = Player::new(name, player_id, color_id, player_civ.civilization_id);
+            player.computer = !local && !player_civ.is_human();
+            if let Some(resources) = scenario.player_resources(player_id) {
+                player.stockpile.set(ResourceType::Food, resources.food.into());
+                player.stockpile.set(ResourceType::Wood, resources.wood.into());
+                player.stockpile.set(ResourceType::Stone, resources.stone.into());
+                player.stockpile.set(ResourceType::Gold, resources.gold.into());
+            }
+            players.add_player(player, local);
         }
         players
     }
@@ -81,4 +110,23 @@ impl Players {
         let local_player_id = self.local_player_id;
         &self.players[&local_player_id]
     }
+
+    pub fn local_player_id(&self) -> PlayerId {
+        self.local_player_id
+    }
+
+    pub fn player<'a>(&'a self, player_id: PlayerId) -> Option<&'a Player> {
+        self.players.get(&player_id)
+    }
+
+    pub fn player_mut<'a>(&'a mut self, player_id: PlayerId) -> Option<&'a mut Player> {
+        self.players.get_mut(&player_id)
+    }
+
+    /// Returns the IDs of all players, in ascending order
+    pub fn player_ids(&self) -> Vec<PlayerId> {
+        let mut player_ids: Vec<PlayerId> = self.players.keys().cloned().collect();
+        player_ids.sort();
+        player_ids
+    }
 }
@@ -0,0 +1,93 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{ResearchId, UnitId};
+use specs::Index;
+use std::mem;
+use super::RallyPoint;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProductionOrderKind {
+    /// Pays for the unit and adds it to the building's production queue
+    Train(UnitId),
+    /// Pays for the technology and starts researching it in the building
+    Research(ResearchId),
+    /// Turns requeuing the last trained unit on or off
+    ToggleRepeat,
+    /// Changes or clears where trained units go
+    SetRallyPoint(Option<RallyPoint>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProductionOrder {
+    pub entity_id: Index,
+    pub kind: ProductionOrderKind,
+}
+
+/// A unit to train in whichever of several buildings has the shortest queue
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SharedTrainOrder {
+    pub entity_ids: Vec<Index>,
+    pub unit_id: UnitId,
+}
+
+/// Production orders for buildings, carried out by the ProductionSystem on its next update
+pub struct ProductionOrders {
+    orders: Vec<ProductionOrder>,
+    shared_orders: Vec<SharedTrainOrder>,
+}
+
+impl ProductionOrders {
+    pub fn new() -> ProductionOrders {
+        ProductionOrders {
+            orders: Vec::new(),
+            shared_orders: Vec::new(),
+        }
+    }
+
+    pub fn queue_order(&mut self, entity_id: Index, kind: ProductionOrderKind) {
+        self.orders.push(ProductionOrder {
+            entity_id: entity_id,
+            kind: kind,
+        });
+    }
+
+    pub fn consume_orders(&mut self) -> Vec<ProductionOrder> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.orders);
+        consumed
+    }
+
+    /// Trains the unit in whichever of the buildings that can train it has the fewest units
+    /// queued, so that training a batch from a multi-building selection spreads it out
+    pub fn queue_shared_training(&mut self, entity_ids: Vec<Index>, unit_id: UnitId) {
+        self.shared_orders.push(SharedTrainOrder {
+            entity_ids: entity_ids,
+            unit_id: unit_id,
+        });
+    }
+
+    pub fn consume_shared_orders(&mut self) -> Vec<SharedTrainOrder> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.shared_orders);
+        consumed
+    }
+}
@@ -0,0 +1,180 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// The independent streams random numbers are drawn from. Each system draws from its own stream,
+/// so that drawing more or fewer numbers in one (particularly the graphics-only one, which each
+/// player's client draws from differently) never changes what another gets.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RandomStream {
+    Combat,
+    Ai,
+    MapGen,
+    Wildlife,
+    /// For randomness that only affects what's drawn; never use it for anything that changes
+    /// the simulation
+    Graphics,
+}
+
+pub const RANDOM_STREAMS: [RandomStream; 5] = [RandomStream::Combat,
+                                               RandomStream::Ai,
+                                               RandomStream::MapGen,
+                                               RandomStream::Wildlife,
+                                               RandomStream::Graphics];
+
+impl RandomStream {
+    fn index(&self) -> usize {
+        RANDOM_STREAMS.iter().position(|stream| stream == self).unwrap()
+    }
+}
+
+/// Seeded random numbers, split into a deterministic stream per system. Every stream is derived
+/// from the game's seed, so a replay that starts from the recorded seed draws the same numbers.
+pub struct RandomNumbers {
+    seed: u64,
+    states: [u64; 5],
+}
+
+impl RandomNumbers {
+    pub fn new(seed: u64) -> RandomNumbers {
+        let mut states = [0u64; 5];
+        for (index, state) in states.iter_mut().enumerate() {
+            *state = split_mix(seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15)));
+            // Xorshift gets stuck on zero
+            if *state == 0 {
+                *state = 1;
+            }
+        }
+        RandomNumbers {
+            seed: seed,
+            states: states,
+        }
+    }
+
+    /// Picks the streams up where a saved game left them, given in the order of `RANDOM_STREAMS`.
+    /// Streams that weren't saved start over from the seed.
+    pub fn resume(seed: u64, stream_states: &[u64]) -> RandomNumbers {
+        let mut random = RandomNumbers::new(seed);
+        for (state, &saved_state) in random.states.iter_mut().zip(stream_states.iter()) {
+            if saved_state != 0 {
+                *state = saved_state;
+            }
+        }
+        random
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Where each stream is at, for checking that two games haven't diverged
+    pub fn stream_state(&self, stream: RandomStream) -> u64 {
+        self.states[stream.index()]
+    }
+
+    pub fn next_u32(&mut self, stream: RandomStream) -> u32 {
+        (self.next_u64(stream) >> 32) as u32
+    }
+
+    /// A number in `0..bound`
+    pub fn below(&mut self, stream: RandomStream, bound: u32) -> u32 {
+        assert!(bound > 0, "bound must be positive");
+        ((self.next_u32(stream) as u64 * bound as u64) >> 32) as u32
+    }
+
+    /// True one in `chance` times
+    pub fn one_in(&mut self, stream: RandomStream, chance: u32) -> bool {
+        self.below(stream, chance) == 0
+    }
+
+    /// Xorshift64*
+    fn next_u64(&mut self, stream: RandomStream) -> u64 {
+        let state = &mut self.states[stream.index()];
+        let mut x = *state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        *state = x;
+        x.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+}
+
+/// Scrambles the seed so that nearby seeds (and the stream offsets) give unrelated streams
+fn split_mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RANDOM_STREAMS, RandomNumbers, RandomStream};
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let mut first = RandomNumbers::new(1234);
+        let mut second = RandomNumbers::new(1234);
+        for _ in 0..100 {
+            assert_eq!(first.next_u32(RandomStream::Combat), second.next_u32(RandomStream::Combat));
+        }
+        assert!(RandomNumbers::new(1235).next_u32(RandomStream::Combat) !=
+                RandomNumbers::new(1234).next_u32(RandomStream::Combat));
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let mut with_graphics = RandomNumbers::new(42);
+        let mut without_graphics = RandomNumbers::new(42);
+        for _ in 0..10 {
+            with_graphics.next_u32(RandomStream::Graphics);
+            assert_eq!(with_graphics.next_u32(RandomStream::Wildlife),
+                       without_graphics.next_u32(RandomStream::Wildlife));
+        }
+        assert!(with_graphics.stream_state(RandomStream::Graphics) !=
+                without_graphics.stream_state(RandomStream::Graphics));
+        assert_eq!(with_graphics.stream_state(RandomStream::Combat),
+                   without_graphics.stream_state(RandomStream::Combat));
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut random = RandomNumbers::new(7);
+        random.next_u32(RandomStream::Combat);
+        random.next_u32(RandomStream::Wildlife);
+        let states: Vec<u64> = RANDOM_STREAMS.iter().map(|stream| random.stream_state(*stream)).collect();
+        let mut resumed = RandomNumbers::resume(random.seed(), &states);
+        for stream in &RANDOM_STREAMS {
+            assert_eq!(random.next_u32(*stream), resumed.next_u32(*stream));
+        }
+    }
+
+    #[test]
+    fn test_below() {
+        let mut random = RandomNumbers::new(0);
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let value = random.below(RandomStream::Ai, 5);
+            assert!(value < 5);
+            seen[value as usize] = true;
+        }
+        assert_eq!([true; 5], seen);
+    }
+}
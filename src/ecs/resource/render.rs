@@ -20,23 +20,52 @@
 // SOFTWARE.
 
 use media::Renderer;
-use resource::{RenderCommand, ShapeManager};
+use resource::{ChunkCache, ChunkKey, RenderCommand, ShapeManager};
+use types::Rect;
 
 pub struct RenderCommands {
     commands: Vec<RenderCommand>,
+    /// Chunks to draw into the chunk cache before anything is rendered
+    bakes: Vec<(ChunkKey, Rect, Vec<RenderCommand>)>,
+    /// Chunks that aren't going to be drawn again until they're baked again
+    dropped_chunks: Vec<ChunkKey>,
 }
 
 impl RenderCommands {
     pub fn new() -> RenderCommands {
-        RenderCommands { commands: Vec::new() }
+        RenderCommands {
+            commands: Vec::new(),
+            bakes: Vec::new(),
+            dropped_chunks: Vec::new(),
+        }
     }
 
     pub fn push(&mut self, render_command: RenderCommand) {
         self.commands.push(render_command);
     }
 
-    pub fn execute(&mut self, renderer: &mut Renderer, shape_manager: &mut ShapeManager) {
-        RenderCommand::render_all(renderer, shape_manager, &mut self.commands);
+    /// Has the commands baked into the chunk, covering the bounds, for RenderChunk commands to
+    /// draw from this frame on
+    pub fn bake_chunk(&mut self, chunk_key: ChunkKey, bounds: Rect, contents: Vec<RenderCommand>) {
+        self.bakes.push((chunk_key, bounds, contents));
+    }
+
+    /// Lets the chunk cache free the chunk's texture
+    pub fn drop_chunk(&mut self, chunk_key: ChunkKey) {
+        self.dropped_chunks.push(chunk_key);
+    }
+
+    pub fn execute(&mut self,
+                   renderer: &mut Renderer,
+                   shape_manager: &mut ShapeManager,
+                   chunk_cache: &mut ChunkCache) {
+        for chunk_key in self.dropped_chunks.drain(..) {
+            chunk_cache.remove(chunk_key);
+        }
+        for (chunk_key, bounds, contents) in self.bakes.drain(..) {
+            chunk_cache.bake(chunk_key, bounds, contents, renderer, shape_manager);
+        }
+        RenderCommand::render_all(renderer, shape_manager, chunk_cache, &mut self.commands);
     }
 
     pub fn clear_rendered(&mut self) {
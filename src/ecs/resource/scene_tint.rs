@@ -0,0 +1,142 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use types::Color;
+
+/// Hour of the day that games start at, so that they start in daylight
+const START_HOUR: f32 = 9.;
+
+/// How the light changes over a day, as hours and the tint from then on, blending into the
+/// next one; the last one blends back into the first
+const DAY_CYCLE: [(f32, (u8, u8, u8)); 7] = [(0., (110, 120, 180)),
+                                              (5., (110, 120, 180)),
+                                              (7., (235, 205, 190)),
+                                              (9., (255, 255, 255)),
+                                              (17., (255, 255, 255)),
+                                              (19., (250, 180, 140)),
+                                              (21., (110, 120, 180))];
+
+/// How the world is tinted once it's drawn, for the time of day or for the mood of a scenario
+/// (such as a mission at dusk). It's only for show: nothing in the simulation reads it, so it
+/// can't change how a game plays out.
+pub struct SceneTint {
+    /// Whether to tint at all, from the options
+    pub enabled: bool,
+    /// Real seconds for a whole day and night to go by; 0 keeps it day
+    pub day_length: u32,
+    /// Tint a scenario asked for, which takes the place of the day and night
+    scenario_tint: Option<Color>,
+}
+
+impl SceneTint {
+    pub fn new() -> SceneTint {
+        SceneTint {
+            enabled: true,
+            day_length: 0,
+            scenario_tint: None,
+        }
+    }
+
+    pub fn configure(&mut self, enabled: bool, day_length: u32) {
+        self.enabled = enabled;
+        self.day_length = day_length;
+    }
+
+    pub fn scenario_tint(&self) -> Option<Color> {
+        self.scenario_tint
+    }
+
+    pub fn set_scenario_tint(&mut self, tint: Option<Color>) {
+        self.scenario_tint = tint;
+    }
+
+    /// The color to multiply the world by, the given number of seconds after the game started;
+    /// there's none when nothing should be tinted
+    pub fn color(&self, seconds: f32) -> Option<Color> {
+        if !self.enabled {
+            return None;
+        }
+        if self.scenario_tint.is_some() {
+            return self.scenario_tint;
+        }
+        if self.day_length == 0 {
+            return None;
+        }
+        let hour = (START_HOUR + seconds / self.day_length as f32 * 24.) % 24.;
+        Some(time_of_day_color(hour))
+    }
+}
+
+/// Tint of the light at the given hour, from 0 up to 24
+fn time_of_day_color(hour: f32) -> Color {
+    let next_index = DAY_CYCLE.iter().position(|&(start, _)| start > hour).unwrap_or(0);
+    let (start, from) = DAY_CYCLE[(next_index + DAY_CYCLE.len() - 1) % DAY_CYCLE.len()];
+    let (mut end, to) = DAY_CYCLE[next_index];
+    if end <= start {
+        end += 24.;
+    }
+    let t = (hour - start) / (end - start);
+    let blend = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+    Color::rgb(blend(from.0, to.0), blend(from.1, to.1), blend(from.2, to.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Color;
+
+    #[test]
+    fn test_time_of_day_color() {
+        assert_eq!(Color::rgb(255, 255, 255), time_of_day_color(12.));
+        assert_eq!(Color::rgb(110, 120, 180), time_of_day_color(3.));
+        assert_eq!(Color::rgb(250, 180, 140), time_of_day_color(19.));
+        assert_eq!(Color::rgb(180, 150, 160), time_of_day_color(20.));
+        // Between the last hour and midnight it blends back into the first
+        assert_eq!(Color::rgb(110, 120, 180), time_of_day_color(23.5));
+    }
+
+    #[test]
+    fn test_day_cycles() {
+        let mut tint = SceneTint::new();
+        assert_eq!(None, tint.color(100.));
+
+        tint.configure(true, 240);
+        assert_eq!(Some(Color::rgb(255, 255, 255)), tint.color(0.));
+        // 10 seconds to the hour, so 10 hours later it's 19:00
+        assert_eq!(Some(Color::rgb(250, 180, 140)), tint.color(100.));
+        assert_eq!(tint.color(100.), tint.color(340.));
+    }
+
+    #[test]
+    fn test_scenario_tint_replaces_the_day() {
+        let mut tint = SceneTint::new();
+        tint.configure(true, 240);
+        tint.set_scenario_tint(Some(Color::rgb(250, 180, 140)));
+        assert_eq!(Some(Color::rgb(250, 180, 140)), tint.color(0.));
+
+        tint.configure(false, 240);
+        assert_eq!(None, tint.color(0.));
+
+        tint.configure(true, 240);
+        tint.set_scenario_tint(None);
+        assert_eq!(Some(Color::rgb(255, 255, 255)), tint.color(0.));
+    }
+}
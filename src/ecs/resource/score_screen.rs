@@ -0,0 +1,111 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use nalgebra::Vector2;
+use super::{UiLayout, UiPanel};
+use types::Rect;
+
+const SCREEN_MARGIN: i32 = 40;
+const PADDING: i32 = 10;
+const TAB_SIZE: i32 = 20;
+const TAB_SPACING: i32 = 6;
+const ROW_HEIGHT: i32 = 24;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScorePage {
+    /// Every player's score, broken down by category
+    Summary,
+    Military,
+    Economy,
+    Technology,
+    /// Relative scores over the course of the game
+    Timeline,
+    /// The local player's profile and lifetime statistics, over every game they've finished
+    Profile,
+}
+
+pub const SCORE_PAGES: [ScorePage; 6] = [ScorePage::Summary,
+                                         ScorePage::Military,
+                                         ScorePage::Economy,
+                                         ScorePage::Technology,
+                                         ScorePage::Timeline,
+                                         ScorePage::Profile];
+
+/// Screen-space layout, open/closed state, and current page of the score screens. They come up
+/// by themselves when the game ends, and can be opened at any time to compare scores. A row of
+/// tabs along the top switches between the pages.
+pub struct ScoreScreen {
+    pub open: bool,
+    pub page: ScorePage,
+    shown_game_over: bool,
+}
+
+impl ScoreScreen {
+    pub fn new() -> ScoreScreen {
+        ScoreScreen {
+            open: false,
+            page: ScorePage::Summary,
+            shown_game_over: false,
+        }
+    }
+
+    /// Opens the summary the first time this is called
+    pub fn show_game_over(&mut self) {
+        if !self.shown_game_over {
+            self.shown_game_over = true;
+            self.open = true;
+            self.page = ScorePage::Summary;
+        }
+    }
+
+    /// Where the screen is drawn, which the UI layout decides; it covers most of the screen by
+    /// default. The tabs and pages are placed within it.
+    pub fn bounds(&self, layout: &UiLayout, screen_size: &Vector2<i32>) -> Rect {
+        let size = Vector2::new(screen_size.x - 2 * SCREEN_MARGIN, screen_size.y - 2 * SCREEN_MARGIN);
+        layout.place(UiPanel::ScoreScreen, &size, screen_size)
+    }
+
+    pub fn tab(&self, bounds: &Rect, page_index: usize) -> Rect {
+        Rect::of(bounds.x + PADDING + page_index as i32 * (TAB_SIZE + TAB_SPACING),
+                 bounds.y + PADDING,
+                 TAB_SIZE,
+                 TAB_SIZE)
+    }
+
+    /// Finds which tab is under the given screen position
+    pub fn tab_at(&self, bounds: &Rect, position: &Vector2<i32>) -> Option<usize> {
+        (0..SCORE_PAGES.len()).find(|index| self.tab(bounds, *index).contains(position.x, position.y))
+    }
+
+    /// The area under the tabs that the page is drawn in
+    pub fn content(&self, bounds: &Rect) -> Rect {
+        let top = TAB_SIZE + 2 * PADDING;
+        Rect::of(bounds.x + PADDING,
+                 bounds.y + top,
+                 bounds.w - 2 * PADDING,
+                 bounds.h - top - PADDING)
+    }
+
+    pub fn row(&self, bounds: &Rect, row: usize) -> Rect {
+        let content = self.content(bounds);
+        Rect::of(content.x, content.y + row as i32 * ROW_HEIGHT, content.w, ROW_HEIGHT - 4)
+    }
+}
@@ -0,0 +1,184 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use identifier::PlayerId;
+
+/// The most units a selection holds, as in the original game
+pub const MAX_SELECTED_UNITS: usize = 25;
+
+/// The kinds of unit that a selection box picks between
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SelectionClass {
+    Military,
+    Villagers,
+    /// Everything else that moves, like priests, boats and animals
+    Units,
+    Buildings,
+}
+
+impl SelectionClass {
+    pub fn from_name(name: &str) -> Option<SelectionClass> {
+        match name {
+            "military" => Some(SelectionClass::Military),
+            "villagers" => Some(SelectionClass::Villagers),
+            "units" => Some(SelectionClass::Units),
+            "buildings" => Some(SelectionClass::Buildings),
+            _ => None,
+        }
+    }
+
+    /// The class the unit is selected as, or None for what a selection box doesn't pick up at
+    /// all, like trees and gold mines
+    pub fn of(unit_info: &dat::Unit) -> Option<SelectionClass> {
+        match unit_info.interaction_mode {
+            dat::InteractionMode::Building => Some(SelectionClass::Buildings),
+            dat::InteractionMode::Movable => {
+                let class = unit_info.class();
+                if class.is_military() {
+                    Some(SelectionClass::Military)
+                } else if class.is_villager() {
+                    Some(SelectionClass::Villagers)
+                } else {
+                    Some(SelectionClass::Units)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Something inside a selection box, for the priority to pick from
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SelectionCandidate<T> {
+    pub id: T,
+    pub player_id: PlayerId,
+    pub class: SelectionClass,
+}
+
+/// Which of the units inside a selection box get selected. A selection only ever holds one
+/// player's units: the local player's own whenever there are any in the box, and otherwise the
+/// player whose unit ranks highest. Of those, only the highest ranked class in the box is
+/// selected, so a box over an army and the villagers behind it picks up the army. Classes left
+/// out of the order rank below the rest, all together; an empty order selects everything.
+pub struct SelectionPriority {
+    order: Vec<SelectionClass>,
+}
+
+impl SelectionPriority {
+    pub fn new() -> SelectionPriority {
+        SelectionPriority {
+            order: vec![SelectionClass::Military,
+                        SelectionClass::Villagers,
+                        SelectionClass::Units,
+                        SelectionClass::Buildings],
+        }
+    }
+
+    pub fn configure(&mut self, order: &[SelectionClass]) {
+        self.order = order.to_vec();
+    }
+
+    /// Lower ranks are picked first
+    fn rank(&self, class: SelectionClass) -> usize {
+        self.order.iter().position(|&ordered| ordered == class).unwrap_or(self.order.len())
+    }
+
+    pub fn pick<T: Copy>(&self,
+                         candidates: &[SelectionCandidate<T>],
+                         local_player_id: PlayerId)
+                         -> Vec<T> {
+        let owner = if candidates.iter().any(|candidate| candidate.player_id == local_player_id) {
+            local_player_id
+        } else {
+            match candidates.iter().min_by_key(|candidate| self.rank(candidate.class)) {
+                Some(candidate) => candidate.player_id,
+                None => return Vec::new(),
+            }
+        };
+        let owned: Vec<_> = candidates.iter().filter(|candidate| candidate.player_id == owner).collect();
+        let best_rank = owned.iter().map(|candidate| self.rank(candidate.class)).min().unwrap_or(0);
+        owned.into_iter()
+            .filter(|candidate| self.rank(candidate.class) == best_rank)
+            .map(|candidate| candidate.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: u32, player_id: u8, class: SelectionClass) -> SelectionCandidate<u32> {
+        SelectionCandidate {
+            id: id,
+            player_id: player_id.into(),
+            class: class,
+        }
+    }
+
+    #[test]
+    fn test_prefers_military_over_villagers_over_buildings() {
+        let priority = SelectionPriority::new();
+        let mut candidates = vec![candidate(1, 1, SelectionClass::Buildings),
+                                  candidate(2, 1, SelectionClass::Villagers),
+                                  candidate(3, 1, SelectionClass::Military),
+                                  candidate(4, 1, SelectionClass::Villagers),
+                                  candidate(5, 1, SelectionClass::Military)];
+        assert_eq!(vec![3, 5], priority.pick(&candidates, 1.into()));
+
+        candidates.retain(|candidate| candidate.class != SelectionClass::Military);
+        assert_eq!(vec![2, 4], priority.pick(&candidates, 1.into()));
+        candidates.retain(|candidate| candidate.class != SelectionClass::Villagers);
+        assert_eq!(vec![1], priority.pick(&candidates, 1.into()));
+    }
+
+    #[test]
+    fn test_never_mixes_players() {
+        let priority = SelectionPriority::new();
+        let candidates = vec![candidate(1, 2, SelectionClass::Military),
+                              candidate(2, 0, SelectionClass::Units),
+                              candidate(3, 1, SelectionClass::Buildings)];
+        // The player's own building beats the enemy's army
+        assert_eq!(vec![3], priority.pick(&candidates, 1.into()));
+        // Without any of their own units, it's whoever's unit ranks highest
+        assert_eq!(vec![1], priority.pick(&candidates[..2], 1.into()));
+        assert_eq!(vec![2], priority.pick(&candidates[1..2], 1.into()));
+        assert!(priority.pick::<u32>(&[], 1.into()).is_empty());
+    }
+
+    #[test]
+    fn test_configured_order() {
+        let mut priority = SelectionPriority::new();
+        let candidates = vec![candidate(1, 1, SelectionClass::Military),
+                              candidate(2, 1, SelectionClass::Villagers),
+                              candidate(3, 1, SelectionClass::Buildings)];
+        priority.configure(&[SelectionClass::Villagers]);
+        assert_eq!(vec![2], priority.pick(&candidates, 1.into()));
+
+        // The classes left out tie with each other
+        let without_villagers = [candidates[0], candidates[2]];
+        assert_eq!(vec![1, 3], priority.pick(&without_villagers, 1.into()));
+
+        priority.configure(&[]);
+        assert_eq!(vec![1, 2, 3], priority.pick(&candidates, 1.into()));
+    }
+}
@@ -0,0 +1,93 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::UnitId;
+
+/// The unit type in a mixed selection that building commands go to. Tab cycles through the
+/// types that are selected; while no type is picked, commands go to the whole selection.
+pub struct SelectionSubgroup {
+    unit_id: Option<UnitId>,
+}
+
+impl SelectionSubgroup {
+    pub fn new() -> SelectionSubgroup {
+        SelectionSubgroup { unit_id: None }
+    }
+
+    #[inline]
+    pub fn unit_id(&self) -> Option<UnitId> {
+        self.unit_id
+    }
+
+    /// Goes back to commanding the whole selection; called whenever the selection changes
+    pub fn clear(&mut self) {
+        self.unit_id = None;
+    }
+
+    /// Moves on to the next of the selected unit types in id order, and back to the
+    /// whole selection after the last one
+    pub fn cycle(&mut self, selected_unit_ids: &[UnitId]) {
+        let mut unit_ids = selected_unit_ids.to_vec();
+        unit_ids.sort();
+        unit_ids.dedup();
+        if unit_ids.len() < 2 {
+            self.unit_id = None;
+            return;
+        }
+
+        self.unit_id = match self.unit_id {
+            Some(current) => unit_ids.into_iter().find(|unit_id| *unit_id > current),
+            None => Some(unit_ids[0]),
+        };
+    }
+
+    /// Whether selected units of the given type receive commands
+    pub fn contains(&self, unit_id: UnitId) -> bool {
+        self.unit_id.map(|subgroup_id| subgroup_id == unit_id).unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SelectionSubgroup;
+
+    #[test]
+    fn test_cycle() {
+        let mut subgroup = SelectionSubgroup::new();
+        assert!(subgroup.contains(83.into()));
+
+        let selected = [109.into(), 83.into(), 109.into(), 12.into()];
+        subgroup.cycle(&selected);
+        assert_eq!(Some(12.into()), subgroup.unit_id());
+        assert!(!subgroup.contains(83.into()));
+        subgroup.cycle(&selected);
+        assert_eq!(Some(83.into()), subgroup.unit_id());
+        subgroup.cycle(&selected);
+        assert_eq!(Some(109.into()), subgroup.unit_id());
+        subgroup.cycle(&selected);
+        assert_eq!(None, subgroup.unit_id());
+        assert!(subgroup.contains(83.into()));
+
+        // A selection of a single type has no subgroups to cycle through
+        subgroup.cycle(&[109.into(), 109.into()]);
+        assert_eq!(None, subgroup.unit_id());
+    }
+}
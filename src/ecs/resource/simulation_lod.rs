@@ -0,0 +1,112 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashSet;
+use types::Fixed;
+
+/// Far entities get updated once every this many ticks
+pub const FAR_UPDATE_INTERVAL: u64 = 4;
+
+/// Which entities are far from the camera and from any fighting, and have nothing to do, so
+/// that systems can update them less often. Only systems that don't affect the simulation
+/// should use it, since the camera is different for every player.
+pub struct SimulationLod {
+    tick: u64,
+    far: HashSet<u32>,
+}
+
+impl SimulationLod {
+    pub fn new() -> SimulationLod {
+        SimulationLod {
+            tick: 0,
+            far: HashSet::new(),
+        }
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn advance_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn set_far(&mut self, far: HashSet<u32>) {
+        self.far = far;
+    }
+
+    pub fn is_far(&self, entity_id: u32) -> bool {
+        self.far.contains(&entity_id)
+    }
+
+    pub fn far_count(&self) -> usize {
+        self.far.len()
+    }
+
+    /// Near entities update every tick. Far ones take turns by entity ID so that they don't
+    /// all come due on the same tick.
+    pub fn updates_this_tick(&self, entity_id: u32) -> bool {
+        !self.is_far(entity_id) || (self.tick + entity_id as u64) % FAR_UPDATE_INTERVAL == 0
+    }
+
+    /// How much time the entity should be updated for on this tick, if it should be updated
+    /// at all; far entities make up for the ticks they skipped
+    pub fn time_step(&self, entity_id: u32, time_step: Fixed) -> Option<Fixed> {
+        if !self.is_far(entity_id) {
+            Some(time_step)
+        } else if self.updates_this_tick(entity_id) {
+            Some(time_step * FAR_UPDATE_INTERVAL.into())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use types::Fixed;
+
+    #[test]
+    fn test_far_entities_take_turns() {
+        let mut lod = SimulationLod::new();
+        let mut far = HashSet::new();
+        far.insert(5);
+        far.insert(6);
+        lod.set_far(far);
+
+        let step: Fixed = 1.into();
+        let mut updates = Vec::new();
+        for _ in 0..(FAR_UPDATE_INTERVAL * 2) {
+            lod.advance_tick();
+            updates.push((lod.time_step(5, step), lod.time_step(6, step), lod.time_step(7, step)));
+        }
+
+        let far_step = Some(Fixed::from(FAR_UPDATE_INTERVAL));
+        assert_eq!(2, updates.iter().filter(|update| update.0 == far_step).count());
+        assert_eq!(2, updates.iter().filter(|update| update.1 == far_step).count());
+        assert!(updates.iter().all(|update| update.0 == far_step || update.0.is_none()));
+        assert!(updates.iter().all(|update| update.2 == Some(step)));
+        assert!(updates.iter().all(|update| update.0.is_none() || update.1.is_none()));
+    }
+}
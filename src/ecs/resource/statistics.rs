@@ -0,0 +1,231 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{PlayerId, UnitId};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScoreCategory {
+    Military,
+    Economy,
+    Technology,
+}
+
+pub const SCORE_CATEGORIES: [ScoreCategory; 3] =
+    [ScoreCategory::Military, ScoreCategory::Economy, ScoreCategory::Technology];
+
+/// Running totals for one player, plus counts that are sampled from the world every update
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlayerStatistics {
+    pub units_trained: u32,
+    pub military_units_trained: u32,
+    pub resources_spent: u32,
+    pub tribute_sent: u32,
+    pub tribute_received: u32,
+    pub technologies_researched: u32,
+    /// How many of each kind of unit were trained
+    pub trained_by_unit: BTreeMap<UnitId, u32>,
+
+    pub military_units: u32,
+    pub villagers: u32,
+    pub buildings: u32,
+    pub resources_banked: u32,
+}
+
+impl PlayerStatistics {
+    /// Military: 5 points per military unit trained and 1 per military unit still standing.
+    /// Economy: 1 point per 10 resources spent or sent as tribute, 1 per 100 banked, and 2 per
+    /// villager. Technology: 20 points per technology researched.
+    pub fn score(&self, category: ScoreCategory) -> u32 {
+        match category {
+            ScoreCategory::Military => self.military_units_trained * 5 + self.military_units,
+            ScoreCategory::Economy => {
+                self.resources_spent / 10 + self.tribute_sent / 10 + self.resources_banked / 100 +
+                self.villagers * 2
+            }
+            ScoreCategory::Technology => self.technologies_researched * 20,
+        }
+    }
+
+    pub fn total_score(&self) -> u32 {
+        SCORE_CATEGORIES.iter().map(|category| self.score(*category)).sum()
+    }
+}
+
+/// Every player's total score at one point in the game
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimelineSnapshot {
+    pub seconds: u32,
+    pub scores: Vec<(PlayerId, u32)>,
+}
+
+/// Statistics collected over the game for the post-game score screens. The StatisticsSystem
+/// samples the world and takes the timeline snapshots; other systems record what happened
+/// as they carry out player commands.
+pub struct Statistics {
+    players: BTreeMap<PlayerId, PlayerStatistics>,
+    timeline: Vec<TimelineSnapshot>,
+    /// Players that have had units at some point; only they can be defeated
+    contenders: BTreeSet<PlayerId>,
+    defeated: BTreeSet<PlayerId>,
+    game_over: bool,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics {
+            players: BTreeMap::new(),
+            timeline: Vec::new(),
+            contenders: BTreeSet::new(),
+            defeated: BTreeSet::new(),
+            game_over: false,
+        }
+    }
+
+    pub fn player(&self, player_id: PlayerId) -> PlayerStatistics {
+        self.players.get(&player_id).cloned().unwrap_or_default()
+    }
+
+    pub fn player_mut<'a>(&'a mut self, player_id: PlayerId) -> &'a mut PlayerStatistics {
+        self.players.entry(player_id).or_insert_with(PlayerStatistics::default)
+    }
+
+    pub fn record_trained(&mut self, player_id: PlayerId, unit_id: UnitId, military: bool) {
+        let player = self.player_mut(player_id);
+        player.units_trained += 1;
+        *player.trained_by_unit.entry(unit_id).or_insert(0) += 1;
+        if military {
+            player.military_units_trained += 1;
+        }
+    }
+
+    pub fn record_spent(&mut self, player_id: PlayerId, amount: u32) {
+        self.player_mut(player_id).resources_spent += amount;
+    }
+
+    pub fn record_tribute(&mut self,
+                          from_player_id: PlayerId,
+                          to_player_id: PlayerId,
+                          sent: u32,
+                          received: u32) {
+        self.player_mut(from_player_id).tribute_sent += sent;
+        self.player_mut(to_player_id).tribute_received += received;
+    }
+
+    pub fn record_research(&mut self, player_id: PlayerId) {
+        self.player_mut(player_id).technologies_researched += 1;
+    }
+
+    pub fn take_snapshot(&mut self, seconds: u32, player_ids: &[PlayerId]) {
+        let scores = player_ids.iter()
+            .map(|player_id| (*player_id, self.player(*player_id).total_score()))
+            .collect();
+        self.timeline.push(TimelineSnapshot {
+            seconds: seconds,
+            scores: scores,
+        });
+    }
+
+    pub fn timeline<'a>(&'a self) -> &'a [TimelineSnapshot] {
+        &self.timeline
+    }
+
+    /// Notes which players still have units; a player who had units and lost all of them is
+    /// defeated. The game is over once at most one player, or one group of players who won
+    /// together, is left.
+    pub fn update_standings<F>(&mut self, player_ids_with_units: &BTreeSet<PlayerId>, won_together: F)
+        where F: Fn(&[PlayerId]) -> bool
+    {
+        if self.game_over {
+            return;
+        }
+        for player_id in player_ids_with_units {
+            self.contenders.insert(*player_id);
+        }
+        for player_id in &self.contenders {
+            if !player_ids_with_units.contains(player_id) {
+                self.defeated.insert(*player_id);
+            }
+        }
+
+        let remaining: Vec<PlayerId> = self.contenders.difference(&self.defeated).cloned().collect();
+        if self.contenders.len() > 1 && (remaining.len() <= 1 || won_together(&remaining)) {
+            self.game_over = true;
+        }
+    }
+
+    pub fn is_defeated(&self, player_id: PlayerId) -> bool {
+        self.defeated.contains(&player_id)
+    }
+
+    #[inline]
+    pub fn game_over(&self) -> bool {
+        self.game_over
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use identifier::PlayerId;
+    use std::collections::BTreeSet;
+    use super::{ScoreCategory, Statistics};
+
+    fn ids(vals: &[usize]) -> BTreeSet<PlayerId> {
+        vals.iter().map(|val| (*val).into()).collect()
+    }
+
+    #[test]
+    fn test_scores() {
+        let mut statistics = Statistics::new();
+        statistics.record_trained(1.into(), 75.into(), true);
+        statistics.record_trained(1.into(), 83.into(), false);
+        statistics.record_spent(1.into(), 150);
+        statistics.record_tribute(1.into(), 2.into(), 100, 75);
+        statistics.player_mut(1.into()).villagers = 3;
+        statistics.record_research(1.into());
+
+        let player = statistics.player(1.into());
+        assert_eq!(5, player.score(ScoreCategory::Military));
+        assert_eq!(15 + 10 + 6, player.score(ScoreCategory::Economy));
+        assert_eq!(20, player.score(ScoreCategory::Technology));
+        assert_eq!(56, player.total_score());
+        assert_eq!(75, statistics.player(2.into()).tribute_received);
+        assert_eq!(Some(&1), player.trained_by_unit.get(&83.into()));
+
+        statistics.take_snapshot(30, &[1.into(), 2.into()]);
+        assert_eq!(vec![(1.into(), 56), (2.into(), 0)], statistics.timeline()[0].scores);
+    }
+
+    #[test]
+    fn test_game_over() {
+        let mut statistics = Statistics::new();
+        statistics.update_standings(&ids(&[1, 2, 3]), |_| false);
+        assert!(!statistics.game_over());
+
+        statistics.update_standings(&ids(&[1, 3]), |_| false);
+        assert!(statistics.is_defeated(2.into()));
+        assert!(!statistics.game_over());
+
+        statistics.update_standings(&ids(&[1, 3]), |remaining| remaining.len() == 2);
+        assert!(statistics.game_over());
+        assert!(!statistics.is_defeated(1.into()));
+    }
+}
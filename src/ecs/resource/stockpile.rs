@@ -0,0 +1,114 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use std::collections::BTreeMap;
+use types::Fixed;
+
+/// The resources a player has banked
+#[derive(Clone, Debug)]
+pub struct Stockpile {
+    amounts: BTreeMap<ResourceType, Fixed>,
+}
+
+impl Stockpile {
+    pub fn new() -> Stockpile {
+        Stockpile { amounts: BTreeMap::new() }
+    }
+
+    pub fn amount(&self, resource_type: ResourceType) -> Fixed {
+        match self.amounts.get(&resource_type) {
+            Some(amount) => *amount,
+            None => 0.into(),
+        }
+    }
+
+    pub fn set(&mut self, resource_type: ResourceType, amount: Fixed) {
+        self.amounts.insert(resource_type, amount);
+    }
+
+    pub fn add(&mut self, resource_type: ResourceType, amount: Fixed) {
+        let current = self.amount(resource_type);
+        self.set(resource_type, current + amount);
+    }
+
+    /// Removes the given amount of the resource if there is enough of it.
+    /// Returns false (and leaves the stockpile alone) if there isn't.
+    pub fn take(&mut self, resource_type: ResourceType, amount: Fixed) -> bool {
+        let current = self.amount(resource_type);
+        if current < amount {
+            return false;
+        }
+        self.set(resource_type, current - amount);
+        true
+    }
+
+    /// Removes all of the given amounts if every one of them can be paid.
+    /// Returns false (and leaves the stockpile alone) if any can't.
+    pub fn take_all(&mut self, costs: &[(ResourceType, Fixed)]) -> bool {
+        if costs.iter().any(|&(resource_type, amount)| self.amount(resource_type) < amount) {
+            return false;
+        }
+        for &(resource_type, amount) in costs {
+            self.take(resource_type, amount);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use super::Stockpile;
+    use types::Fixed;
+
+    #[test]
+    fn test_take() {
+        let mut stockpile = Stockpile::new();
+        stockpile.add(ResourceType::Wood, 100.into());
+
+        assert!(!stockpile.take(ResourceType::Wood, 150.into()));
+        assert_eq!(Fixed::from(100), stockpile.amount(ResourceType::Wood));
+
+        assert!(stockpile.take(ResourceType::Wood, 60.into()));
+        assert_eq!(Fixed::from(40), stockpile.amount(ResourceType::Wood));
+
+        assert!(!stockpile.take(ResourceType::Gold, 1.into()));
+        assert!(stockpile.take(ResourceType::Gold, 0.into()));
+    }
+
+    #[test]
+    fn test_take_all() {
+        let mut stockpile = Stockpile::new();
+        stockpile.add(ResourceType::Food, 100.into());
+        stockpile.add(ResourceType::Gold, 20.into());
+
+        let costs = [(ResourceType::Food, 60.into()), (ResourceType::Gold, 40.into())];
+        assert!(!stockpile.take_all(&costs));
+        assert_eq!(Fixed::from(100), stockpile.amount(ResourceType::Food));
+        assert_eq!(Fixed::from(20), stockpile.amount(ResourceType::Gold));
+
+        let costs = [(ResourceType::Food, 60.into()), (ResourceType::Gold, 20.into())];
+        assert!(stockpile.take_all(&costs));
+        assert_eq!(Fixed::from(40), stockpile.amount(ResourceType::Food));
+        assert_eq!(Fixed::from(0), stockpile.amount(ResourceType::Gold));
+    }
+}
@@ -0,0 +1,234 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResearchEffect, ResearchEffectValue, UnitAttributeId};
+use identifier::{PlayerId, ResearchId};
+use std::collections::{BTreeSet, HashMap};
+use super::{Diplomacy, Market, Players};
+use types::Fixed;
+
+/// Civilization header value for the fee the market keeps on each trade. The game's own data
+/// doesn't have research that changes it, but the later games' data uses the same index for
+/// their market's fee, so data with a trading tech can lower it.
+const TRADE_FEE_HEADER: i16 = 78;
+
+/// Civilization header value for the fraction of each tribute lost as a fee
+const TRIBUTE_FEE_HEADER: i16 = 46;
+
+/// Civilization header value that Writing sets, which shares allies' vision with the player
+const REVEAL_ALLY_HEADER: i16 = 50;
+
+/// The technologies each player has finished researching
+#[derive(Clone)]
+pub struct Technologies {
+    researched: HashMap<PlayerId, BTreeSet<ResearchId>>,
+}
+
+impl Technologies {
+    pub fn new() -> Technologies {
+        Technologies { researched: HashMap::new() }
+    }
+
+    pub fn has_researched(&self, player_id: PlayerId, research_id: ResearchId) -> bool {
+        self.researched.get(&player_id).map_or(false, |researched| researched.contains(&research_id))
+    }
+
+    /// Everything the player has researched, ordered by ID
+    pub fn researched(&self, player_id: PlayerId) -> Vec<ResearchId> {
+        self.researched.get(&player_id).map_or(Vec::new(), |researched| researched.iter().cloned().collect())
+    }
+
+    /// Whether the player can start on the research: their civ's tech tree has it, they haven't
+    /// researched it already, and they have researched everything it requires
+    pub fn can_research(&self,
+                        player_id: PlayerId,
+                        civ: &dat::Civilization,
+                        research: &dat::Research)
+                        -> bool {
+        civ.research_available(research.id) && !self.has_researched(player_id, research.id) &&
+        research.required_techs
+            .iter()
+            .filter(|&&required| required >= 0)
+            .all(|&required| self.has_researched(player_id, (required as usize).into()))
+    }
+
+    pub fn complete(&mut self, player_id: PlayerId, research_id: ResearchId) {
+        self.researched.entry(player_id).or_insert_with(BTreeSet::new).insert(research_id);
+    }
+}
+
+/// The effects researching something has: those of the effect group it names, which the data
+/// calls an age
+pub fn research_effects<'a>(empires: &'a dat::EmpiresDb, research: &dat::Research) -> &'a [ResearchEffect] {
+    match research.age_id {
+        Some(age_id) if (*age_id as usize) < empires.ages().len() => &empires.age(age_id).effects,
+        _ => &[],
+    }
+}
+
+/// Applies the effects of research the player has finished to the values the simulation keeps
+/// for each player. Apart from how much villagers carry, effects on unit stats, upgrades, and
+/// enabling units aren't applied, since the units all share their civ's game data.
+pub fn apply_research_effects(player_id: PlayerId,
+                              civ: &dat::Civilization,
+                              effects: &[ResearchEffect],
+                              market: &mut Market,
+                              diplomacy: &mut Diplomacy,
+                              players: &mut Players) {
+    for effect in effects {
+        match *effect {
+            ResearchEffect::UnitAttribute { target_unit_id,
+                                            target_unit_class_id,
+                                            attribute_id: UnitAttributeId::ResourceCarryCapacity,
+                                            ref effect } => {
+                let player = match players.player_mut(player_id) {
+                    Some(player) => player,
+                    None => continue,
+                };
+                // The villagers that gather each resource carry the same amount of it, so the
+                // first unit targeted that gathers it sets the bonus for all of them
+                let mut raised = BTreeSet::new();
+                for unit_info in civ.units() {
+                    let targeted = match (target_unit_id, target_unit_class_id) {
+                        (Some(unit_id), _) => unit_info.id == unit_id,
+                        (None, Some(class_id)) => unit_info.is_in_class(class_id),
+                        (None, None) => false,
+                    };
+                    if !targeted {
+                        continue;
+                    }
+                    for resource_type in unit_info.gathered_resources() {
+                        if raised.insert(resource_type) {
+                            let base = Fixed::from(unit_info.resource_capacity());
+                            let capacity = apply_effect(base + player.carry_bonus(resource_type), effect);
+                            player.set_carry_bonus(resource_type, capacity - base);
+                        }
+                    }
+                }
+            }
+            ResearchEffect::CivHeader { target_civ_header_id, ref effect } => {
+                match target_civ_header_id {
+                    TRADE_FEE_HEADER => {
+                        let fee = apply_effect(market.exchange_fee(player_id), effect);
+                        market.set_exchange_fee(player_id, fee);
+                    }
+                    TRIBUTE_FEE_HEADER => {
+                        let fee = diplomacy.tribute_fee(player_id, civ.tribute_penalty().into());
+                        diplomacy.set_tribute_fee(player_id, apply_effect(fee, effect));
+                    }
+                    REVEAL_ALLY_HEADER => {
+                        if apply_effect(0.into(), effect) > 0.into() {
+                            diplomacy.enable_shared_vision(player_id);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The value once the effect has changed it, in fixed point so that every player gets the same
+fn apply_effect(value: Fixed, effect: &ResearchEffectValue) -> Fixed {
+    match *effect {
+        ResearchEffectValue::SetTo(amount) => Fixed::from(amount),
+        ResearchEffectValue::Add(amount) => value + Fixed::from(amount),
+        ResearchEffectValue::MultiplyBy(amount) => value * Fixed::from(amount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{self, ResearchEffect, ResearchEffectValue};
+    use ecs::resource::{Diplomacy, DiplomaticStance, Market, Players};
+    use identifier::ResearchId;
+    use super::*;
+    use types::Fixed;
+
+    fn apply(effects: &[ResearchEffect], market: &mut Market, diplomacy: &mut Diplomacy) {
+        let civ = dat::Civilization::default();
+        apply_research_effects(1.into(), &civ, effects, market, diplomacy, &mut Players::new());
+    }
+
+    #[test]
+    fn test_can_research() {
+        let civ = dat::Civilization::default();
+        let mut research = dat::Research::default();
+        research.id = 5.into();
+        research.required_techs = vec![3, -1];
+
+        let mut technologies = Technologies::new();
+        assert!(!technologies.can_research(1.into(), &civ, &research));
+        technologies.complete(1.into(), 3.into());
+        assert!(technologies.can_research(1.into(), &civ, &research));
+        assert!(!technologies.can_research(2.into(), &civ, &research));
+
+        technologies.complete(1.into(), 5.into());
+        assert!(!technologies.can_research(1.into(), &civ, &research));
+        let researched: Vec<ResearchId> = vec![3.into(), 5.into()];
+        assert_eq!(researched, technologies.researched(1.into()));
+    }
+
+    #[test]
+    fn test_research_lowers_exchange_fee() {
+        let mut market = Market::new();
+        let effects = [ResearchEffect::CivHeader {
+                           target_civ_header_id: TRADE_FEE_HEADER,
+                           effect: ResearchEffectValue::MultiplyBy(0.5),
+                       },
+                       ResearchEffect::CivHeader {
+                           target_civ_header_id: 47,
+                           effect: ResearchEffectValue::Add(0.25),
+                       }];
+        apply(&effects, &mut market, &mut Diplomacy::new());
+        assert_eq!(fixed_const!(0.15), market.exchange_fee(1.into()));
+        assert_eq!(fixed_const!(0.3), market.exchange_fee(2.into()));
+    }
+
+    #[test]
+    fn test_writing_shares_vision() {
+        let mut diplomacy = Diplomacy::new();
+        diplomacy.set_stance(1.into(), 2.into(), DiplomaticStance::Ally);
+        diplomacy.set_stance(2.into(), 1.into(), DiplomaticStance::Ally);
+        let effects = [ResearchEffect::CivHeader {
+                           target_civ_header_id: REVEAL_ALLY_HEADER,
+                           effect: ResearchEffectValue::SetTo(1.),
+                       }];
+        assert!(!diplomacy.shares_vision(1.into(), 2.into()));
+        apply(&effects, &mut Market::new(), &mut diplomacy);
+        assert!(diplomacy.shares_vision(1.into(), 2.into()));
+        assert!(!diplomacy.shares_vision(2.into(), 1.into()));
+    }
+
+    #[test]
+    fn test_research_changes_tribute_fee() {
+        let mut diplomacy = Diplomacy::new();
+        let effects = [ResearchEffect::CivHeader {
+                           target_civ_header_id: TRIBUTE_FEE_HEADER,
+                           effect: ResearchEffectValue::Add(0.25),
+                       }];
+        apply(&effects, &mut Market::new(), &mut diplomacy);
+        apply(&effects, &mut Market::new(), &mut diplomacy);
+        assert_eq!(fixed_const!(0.5), diplomacy.tribute_fee(1.into(), 0.into()));
+        assert_eq!(Fixed::from(0), diplomacy.tribute_fee(2.into(), 0.into()));
+    }
+}
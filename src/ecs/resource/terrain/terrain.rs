@@ -28,11 +28,19 @@ use super::dir;
 use super::elevation::ElevationMatrix;
 use types::Vector3;
 
+/// Water and deep water, which boats sail on and the surf washes in from
+const WATER_TERRAIN_IDS: [u8; 2] = [1, 22];
+
+/// Forest, palm desert, pine forest, and jungle, the ground that the woods grow on
+const FOREST_TERRAIN_IDS: [u8; 4] = [10, 13, 19, 20];
+
 #[derive(Debug, Copy, Clone)]
 pub struct Tile {
     pub terrain_id: TerrainId,
     pub elevation: u8,
     blend_cache_index: Option<u32>,
+    /// The terrain's revision when the tile or one of its neighbors last changed
+    revision: u32,
 }
 
 impl Tile {
@@ -42,6 +50,7 @@ impl Tile {
             terrain_id: terrain_id,
             elevation: elevation,
             blend_cache_index: None,
+            revision: 0,
         }
     }
 
@@ -50,8 +59,24 @@ impl Tile {
             terrain_id: scn_tile.terrain_id,
             elevation: scn_tile.elevation,
             blend_cache_index: None,
+            revision: 0,
         }
     }
+
+    pub fn is_water(&self) -> bool {
+        WATER_TERRAIN_IDS.contains(&*self.terrain_id)
+    }
+
+    pub fn is_forest(&self) -> bool {
+        FOREST_TERRAIN_IDS.contains(&*self.terrain_id)
+    }
+
+    /// Goes up every time the tile's blending might have changed, so that whatever was drawn
+    /// from it can tell when it has to be drawn again
+    #[inline]
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -70,6 +95,7 @@ pub struct Terrain {
     tiles: Vec<Tile>,
     empires: dat::EmpiresDbRef,
     blend_cache: Vec<BlendInfo>,
+    revision: u32,
 }
 
 impl Terrain {
@@ -81,6 +107,7 @@ impl Terrain {
             tiles: tiles,
             empires: empires,
             blend_cache: Vec::new(),
+            revision: 0,
         }
     }
 
@@ -91,6 +118,56 @@ impl Terrain {
             tiles: scn_map.tiles.iter().map(|t| Tile::from(t)).collect(),
             empires: empires,
             blend_cache: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    /// Brings the terrain in line with the map after it's been edited, only touching the
+    /// tiles that changed so that everything else keeps its revision
+    pub fn update_from(&mut self, scn_map: &scn::Map) {
+        if scn_map.width as i32 != self.width || scn_map.height as i32 != self.height {
+            self.width = scn_map.width as i32;
+            self.height = scn_map.height as i32;
+            self.tiles = scn_map.tiles.iter().map(|t| Tile::from(t)).collect();
+            self.blend_cache.clear();
+            self.revision += 1;
+            for tile in &mut self.tiles {
+                tile.revision = self.revision;
+            }
+            return;
+        }
+        for (index, scn_tile) in scn_map.tiles.iter().enumerate() {
+            let tile = self.tiles[index];
+            if tile.terrain_id != scn_tile.terrain_id || tile.elevation != scn_tile.elevation {
+                let (row, col) = (index as i32 / self.width, index as i32 % self.width);
+                self.set_tile(row, col, scn_tile.terrain_id, scn_tile.elevation);
+            }
+        }
+    }
+
+    /// Changes the tile's terrain and elevation, and blends it and its neighbors again
+    pub fn set_tile(&mut self, row: i32, col: i32, terrain_id: TerrainId, elevation: u8) {
+        if row < 0 || row >= self.height || col < 0 || col >= self.width {
+            return;
+        }
+        let index = self.tile_index(row, col);
+        self.tiles[index].terrain_id = terrain_id;
+        self.tiles[index].elevation = elevation;
+
+        self.revision += 1;
+        for neighbor_row in (row - 1)..(row + 2) {
+            for neighbor_col in (col - 1)..(col + 2) {
+                if neighbor_row < 0 || neighbor_row >= self.height || neighbor_col < 0 ||
+                   neighbor_col >= self.width {
+                    continue;
+                }
+                let index = self.tile_index(neighbor_row, neighbor_col);
+                self.tiles[index].revision = self.revision;
+                if let Some(blend_cache_index) = self.tiles[index].blend_cache_index {
+                    let blend_info = self.blend_at_no_cache(neighbor_row, neighbor_col);
+                    self.blend_cache[blend_cache_index as usize] = blend_info;
+                }
+            }
         }
     }
 
@@ -211,3 +288,56 @@ impl Terrain {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dat::{EmpiresDb, EmpiresDbRef};
+    use super::{Terrain, Tile};
+
+    fn terrain(size: i32) -> Terrain {
+        let empires = EmpiresDbRef::new(EmpiresDb::new());
+        let tiles = (0..size * size).map(|_| Tile::new((1 as usize).into(), 0)).collect();
+        Terrain::new(size, size, tiles, empires)
+    }
+
+    #[test]
+    fn test_set_tile_changes_neighbor_revisions() {
+        let mut terrain = terrain(8);
+        terrain.set_tile(0, 0, (2 as usize).into(), 1);
+        assert_eq!(2, *terrain.tile_at_row_col(0, 0).terrain_id);
+        assert_eq!(1, terrain.tile_at_row_col(0, 0).elevation);
+        assert_eq!(1, terrain.tile_at_row_col(1, 1).revision());
+        assert_eq!(0, terrain.tile_at_row_col(2, 2).revision());
+
+        terrain.set_tile(3, 3, (2 as usize).into(), 0);
+        assert_eq!(2, terrain.tile_at_row_col(2, 2).revision());
+        assert_eq!(2, terrain.tile_at_row_col(4, 4).revision());
+        assert_eq!(1, terrain.tile_at_row_col(0, 0).revision());
+    }
+
+    #[test]
+    fn test_is_water() {
+        assert!(Tile::new((1 as usize).into(), 0).is_water());
+        assert!(Tile::new((22 as usize).into(), 0).is_water());
+        assert!(!Tile::new((4 as usize).into(), 0).is_water());
+    }
+
+    #[test]
+    fn test_is_forest() {
+        assert!(Tile::new((10 as usize).into(), 0).is_forest());
+        assert!(Tile::new((20 as usize).into(), 0).is_forest());
+        assert!(!Tile::new((0 as usize).into(), 0).is_forest());
+    }
+
+    #[test]
+    fn test_set_tile_off_the_map() {
+        let mut terrain = terrain(4);
+        terrain.set_tile(-1, 2, (2 as usize).into(), 0);
+        terrain.set_tile(2, 4, (2 as usize).into(), 0);
+        for row in 0..4 {
+            for col in 0..4 {
+                assert_eq!(0, terrain.tile_at_row_col(row, col).revision());
+            }
+        }
+    }
+}
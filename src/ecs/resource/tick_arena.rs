@@ -0,0 +1,256 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::any::{Any, TypeId};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Containers the TickArena can hand out; clearing one keeps its capacity for the next user
+pub trait Reusable: Default + Send + 'static {
+    fn clear_for_reuse(&mut self);
+}
+
+impl<T: Send + 'static> Reusable for Vec<T> {
+    fn clear_for_reuse(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Eq + Hash + Send + 'static> Reusable for HashSet<T> {
+    fn clear_for_reuse(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K: Eq + Hash + Send + 'static, V: Send + 'static> Reusable for HashMap<K, V> {
+    fn clear_for_reuse(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Ord + Send + 'static> Reusable for BinaryHeap<T> {
+    fn clear_for_reuse(&mut self) {
+        self.clear();
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct TickArenaStats {
+    /// Containers handed out this tick
+    pub taken: usize,
+    /// How many of those were already allocated by an earlier user
+    pub reused: usize,
+}
+
+trait ErasedPool: Send {
+    fn end_tick(&mut self);
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+struct Pool<T> {
+    free: Vec<T>,
+    outstanding: usize,
+    peak_outstanding: usize,
+}
+
+impl<T: Reusable> ErasedPool for Pool<T> {
+    /// Drops the containers that more than covered this tick's busiest moment, so that one
+    /// unusual tick doesn't hold on to memory for the rest of the game
+    fn end_tick(&mut self) {
+        let keep = self.peak_outstanding.saturating_sub(self.outstanding);
+        self.free.truncate(keep);
+        self.peak_outstanding = self.outstanding;
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
+
+struct Pools {
+    pools: HashMap<TypeId, Box<ErasedPool>>,
+    stats: TickArenaStats,
+}
+
+impl Pools {
+    fn pool<T: Reusable>(&mut self) -> &mut Pool<T> {
+        self.pools
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Pool::<T> {
+                    free: Vec::new(),
+                    outstanding: 0,
+                    peak_outstanding: 0,
+                })
+            })
+            .as_any_mut()
+            .downcast_mut::<Pool<T>>()
+            .unwrap()
+    }
+}
+
+/// Scratch containers for the temporary collections systems build every tick. They keep their
+/// capacity between ticks, so the hot loop stops going back to the allocator for them. A
+/// container returns to the arena when its Scratch is dropped, from whichever thread that is.
+pub struct TickArena {
+    pools: Arc<Mutex<Pools>>,
+    last_tick_stats: TickArenaStats,
+}
+
+impl TickArena {
+    pub fn new() -> TickArena {
+        TickArena {
+            pools: Arc::new(Mutex::new(Pools {
+                pools: HashMap::new(),
+                stats: TickArenaStats::default(),
+            })),
+            last_tick_stats: TickArenaStats::default(),
+        }
+    }
+
+    /// Hands out an empty container, reusing one from an earlier user if there is one free
+    pub fn take<T: Reusable>(&self) -> Scratch<T> {
+        let mut pools = self.pools.lock().unwrap();
+        let reused = {
+            let pool = pools.pool::<T>();
+            pool.outstanding += 1;
+            pool.peak_outstanding = pool.peak_outstanding.max(pool.outstanding);
+            pool.free.pop()
+        };
+        pools.stats.taken += 1;
+        if reused.is_some() {
+            pools.stats.reused += 1;
+        }
+        Scratch {
+            value: Some(reused.unwrap_or_default()),
+            pools: self.pools.clone(),
+        }
+    }
+
+    /// Resets the arena for the next tick and trims any containers this tick didn't need
+    pub fn end_tick(&mut self) {
+        let mut pools = self.pools.lock().unwrap();
+        for pool in pools.pools.values_mut() {
+            pool.end_tick();
+        }
+        self.last_tick_stats = pools.stats;
+        pools.stats = TickArenaStats::default();
+    }
+
+    pub fn last_tick_stats(&self) -> TickArenaStats {
+        self.last_tick_stats
+    }
+}
+
+/// A container on loan from the TickArena
+pub struct Scratch<T: Reusable> {
+    value: Option<T>,
+    pools: Arc<Mutex<Pools>>,
+}
+
+impl<T: Reusable> Deref for Scratch<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T: Reusable> DerefMut for Scratch<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T: Reusable> Drop for Scratch<T> {
+    fn drop(&mut self) {
+        if let Some(mut value) = self.value.take() {
+            value.clear_for_reuse();
+            if let Ok(mut pools) = self.pools.lock() {
+                let pool = pools.pool::<T>();
+                pool.outstanding -= 1;
+                pool.free.push(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::{TickArena, TickArenaStats};
+
+    #[test]
+    fn test_containers_come_back_empty_with_their_capacity() {
+        let mut arena = TickArena::new();
+        {
+            let mut ids = arena.take::<Vec<u32>>();
+            ids.extend(0..100);
+        }
+        arena.end_tick();
+
+        let ids = arena.take::<Vec<u32>>();
+        assert!(ids.is_empty());
+        assert!(ids.capacity() >= 100);
+    }
+
+    #[test]
+    fn test_types_get_separate_pools() {
+        let mut arena = TickArena::new();
+        {
+            let _ids = arena.take::<Vec<u32>>();
+            let _set = arena.take::<HashSet<u32>>();
+        }
+        arena.end_tick();
+        assert_eq!(TickArenaStats { taken: 2, reused: 0 }, arena.last_tick_stats());
+
+        {
+            let _ids = arena.take::<Vec<u32>>();
+            let _positions = arena.take::<Vec<i32>>();
+        }
+        arena.end_tick();
+        assert_eq!(TickArenaStats { taken: 2, reused: 1 }, arena.last_tick_stats());
+    }
+
+    #[test]
+    fn test_end_tick_trims_to_the_busiest_moment() {
+        let mut arena = TickArena::new();
+        {
+            let _scratches: Vec<_> = (0..4).map(|_| arena.take::<Vec<u32>>()).collect();
+        }
+        arena.end_tick();
+
+        // Only one is needed at a time this tick, so three get dropped at the end of it
+        for _ in 0..4 {
+            let _scratch = arena.take::<Vec<u32>>();
+        }
+        arena.end_tick();
+        assert_eq!(TickArenaStats { taken: 4, reused: 4 }, arena.last_tick_stats());
+        {
+            let _first = arena.take::<Vec<u32>>();
+            let _second = arena.take::<Vec<u32>>();
+        }
+        arena.end_tick();
+        assert_eq!(TickArenaStats { taken: 2, reused: 1 }, arena.last_tick_stats());
+    }
+}
@@ -0,0 +1,88 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::PlayerId;
+use specs::Index;
+use std::collections::BTreeMap;
+use std::mem;
+
+/// Each player's town bell. Ringing it sends the player's villagers to take shelter in the
+/// nearest town centers and towers with room, setting aside whatever they were doing, and
+/// ringing it again sends them back to it. The TownBellSystem acts on the rings.
+pub struct TownBell {
+    /// The players who rang since the TownBellSystem last looked
+    rung: Vec<PlayerId>,
+    /// The villagers each player's ringing bell sent into shelter, by entity ID
+    sheltering: BTreeMap<PlayerId, Vec<Index>>,
+}
+
+impl TownBell {
+    pub fn new() -> TownBell {
+        TownBell {
+            rung: Vec::new(),
+            sheltering: BTreeMap::new(),
+        }
+    }
+
+    pub fn ring(&mut self, player_id: PlayerId) {
+        self.rung.push(player_id);
+    }
+
+    pub fn take_rung(&mut self) -> Vec<PlayerId> {
+        mem::replace(&mut self.rung, Vec::new())
+    }
+
+    /// Whether the player's villagers are in shelter, or on their way to it
+    pub fn is_ringing(&self, player_id: PlayerId) -> bool {
+        self.sheltering.contains_key(&player_id)
+    }
+
+    /// Starts the player's bell ringing, with the villagers it sent into shelter
+    pub fn start(&mut self, player_id: PlayerId, villagers: Vec<Index>) {
+        self.sheltering.insert(player_id, villagers);
+    }
+
+    /// Stops the player's bell, handing back the villagers to send back to work
+    pub fn stop(&mut self, player_id: PlayerId) -> Option<Vec<Index>> {
+        self.sheltering.remove(&player_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ringing_twice_hands_back_the_villagers() {
+        let player_id: PlayerId = 1.into();
+        let mut town_bell = TownBell::new();
+        town_bell.ring(player_id);
+        assert_eq!(vec![player_id], town_bell.take_rung());
+        assert!(town_bell.take_rung().is_empty());
+
+        town_bell.start(player_id, vec![4, 7]);
+        assert!(town_bell.is_ringing(player_id));
+        assert!(!town_bell.is_ringing(2.into()));
+        assert_eq!(Some(vec![4, 7]), town_bell.stop(player_id));
+        assert!(!town_bell.is_ringing(player_id));
+        assert_eq!(None, town_bell.stop(player_id));
+    }
+}
@@ -0,0 +1,499 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::SlpFileId;
+use nalgebra::Vector2;
+use resource::{DrsKey, RenderCommand, ShapeKey};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use toml::{self, Table, Value};
+use types::{Color, Rect};
+
+/// The layout the game ships with, for when there's no layout file or it can't be read
+pub const DEFAULT_LAYOUT: &'static str = r#"
+[[panel]]
+name = "command_panel"
+anchor = "bottom_left"
+x = 10
+y = 10
+
+[[panel]]
+name = "diplomacy_panel"
+anchor = "top_left"
+x = 10
+y = 10
+
+[[panel]]
+name = "market_panel"
+anchor = "top_right"
+x = 10
+y = 10
+
+[[panel]]
+name = "score_screen"
+anchor = "fill"
+x = 40
+y = 40
+
+[[panel]]
+name = "chat_box"
+anchor = "bottom"
+y = 40
+
+[[panel]]
+name = "idle_units"
+anchor = "bottom_left"
+x = 10
+y = 10
+
+[[panel]]
+name = "notifications"
+anchor = "top_left"
+x = 10
+y = 10
+
+# With room to spare, the market sits in the middle of the top instead of over the corner
+[[panel]]
+name = "market_panel"
+min_width = 1280
+anchor = "top"
+y = 10
+
+# Wide screens keep the scores to a readable width
+[[panel]]
+name = "score_screen"
+min_width = 1280
+anchor = "fill"
+x = 160
+y = 60
+
+[theme]
+panel_slp = 50746
+panel_frame = 0
+panel_border = 8
+button_slp = 50721
+button_frame = 0
+button_lit_frame = 1
+button_border = 2
+"#;
+
+/// The interface panels that are placed by the layout
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UiPanel {
+    CommandPanel,
+    DiplomacyPanel,
+    MarketPanel,
+    ScoreScreen,
+    ChatBox,
+    IdleUnits,
+    Notifications,
+}
+
+impl UiPanel {
+    pub fn from_name(name: &str) -> Option<UiPanel> {
+        match name {
+            "command_panel" => Some(UiPanel::CommandPanel),
+            "diplomacy_panel" => Some(UiPanel::DiplomacyPanel),
+            "market_panel" => Some(UiPanel::MarketPanel),
+            "score_screen" => Some(UiPanel::ScoreScreen),
+            "chat_box" => Some(UiPanel::ChatBox),
+            "idle_units" => Some(UiPanel::IdleUnits),
+            "notifications" => Some(UiPanel::Notifications),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of the screen a panel keeps to. Its offset is measured in from the edges it's
+/// against, and moves it right and down from the middle otherwise.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    /// Covers the screen, with the offset as the margin on each side
+    Fill,
+}
+
+impl Anchor {
+    pub fn from_name(name: &str) -> Option<Anchor> {
+        match name {
+            "top_left" => Some(Anchor::TopLeft),
+            "top" => Some(Anchor::Top),
+            "top_right" => Some(Anchor::TopRight),
+            "left" => Some(Anchor::Left),
+            "center" => Some(Anchor::Center),
+            "right" => Some(Anchor::Right),
+            "bottom_left" => Some(Anchor::BottomLeft),
+            "bottom" => Some(Anchor::Bottom),
+            "bottom_right" => Some(Anchor::BottomRight),
+            "fill" => Some(Anchor::Fill),
+            _ => None,
+        }
+    }
+}
+
+/// Where a panel goes on screens at least `min_size` big
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PanelPlacement {
+    pub panel: UiPanel,
+    pub anchor: Anchor,
+    pub offset: Vector2<i32>,
+    pub min_size: Vector2<i32>,
+}
+
+impl PanelPlacement {
+    fn fits(&self, screen_size: &Vector2<i32>) -> bool {
+        screen_size.x >= self.min_size.x && screen_size.y >= self.min_size.y
+    }
+
+    /// Position of a panel of the given size, which fill panels ignore
+    pub fn place(&self, size: &Vector2<i32>, screen_size: &Vector2<i32>) -> Rect {
+        let place_along = |alignment: i32, offset: i32, length: i32, screen_length: i32| {
+            match alignment {
+                0 => offset,
+                1 => (screen_length - length) / 2 + offset,
+                _ => screen_length - length - offset,
+            }
+        };
+        let (horizontal, vertical) = match self.anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+            Anchor::Fill => {
+                return Rect::of(self.offset.x,
+                                self.offset.y,
+                                screen_size.x - 2 * self.offset.x,
+                                screen_size.y - 2 * self.offset.y);
+            }
+        };
+        Rect::of(place_along(horizontal, self.offset.x, size.x, screen_size.x),
+                 place_along(vertical, self.offset.y, size.y, screen_size.y),
+                 size.x,
+                 size.y)
+    }
+}
+
+/// A frame of an interface SLP that widgets are drawn with, stretched to their size
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Skin {
+    pub slp_id: SlpFileId,
+    pub frame: u16,
+    pub lit_frame: u16,
+    /// How far in from the edges the frame's corners reach; they aren't stretched
+    pub border: i32,
+}
+
+impl Skin {
+    fn render_command(&self,
+                      layer: u16,
+                      depth: i32,
+                      rect: Rect,
+                      lit: bool,
+                      fallback: Color)
+                      -> RenderCommand {
+        let shape_key = ShapeKey::new(DrsKey::Interfac, self.slp_id, 0.into());
+        let frame = if lit { self.lit_frame } else { self.frame };
+        RenderCommand::new_panel(layer, depth, shape_key, frame, rect, self.border, fallback)
+    }
+}
+
+/// The graphics from interfac.drs that panels and buttons are drawn with. Without a skin, or
+/// when the data hasn't got its SLP, they're drawn in flat colors.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct UiTheme {
+    pub panel: Option<Skin>,
+    pub button: Option<Skin>,
+}
+
+/// Where the interface panels go and what they're drawn with, from a layout file so that the
+/// screens aren't laid out for one resolution only. A layout looks like this:
+///
+/// ```toml
+/// [[panel]]
+/// name = "market_panel"
+/// anchor = "top_right"
+/// x = 10
+/// y = 10
+///
+/// # Placements with a minimum screen size take over from earlier ones once the screen is as big
+/// [[panel]]
+/// name = "market_panel"
+/// min_width = 1280
+/// anchor = "top"
+/// y = 10
+///
+/// [theme]
+/// panel_slp = 50746
+/// panel_frame = 0
+/// panel_border = 8
+/// button_slp = 50721
+/// button_frame = 0
+/// button_lit_frame = 1
+/// button_border = 2
+/// ```
+///
+/// Anchors are `top_left`, `top`, `top_right`, `left`, `center`, `right`, `bottom_left`,
+/// `bottom`, `bottom_right`, and `fill`. Of the placements that fit the screen, the last one
+/// for each panel is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiLayout {
+    placements: Vec<PanelPlacement>,
+    pub theme: UiTheme,
+}
+
+impl UiLayout {
+    pub fn new() -> UiLayout {
+        UiLayout::parse(DEFAULT_LAYOUT).expect("The default layout is invalid")
+    }
+
+    pub fn parse(text: &str) -> Result<UiLayout, String> {
+        let mut layout = UiLayout {
+            placements: Vec::new(),
+            theme: UiTheme::default(),
+        };
+        try!(layout.merge(text));
+        Ok(layout)
+    }
+
+    /// Reads a layout file on top of the default layout: its placements take over from the
+    /// default ones wherever they fit the screen, and its theme, if it has one, replaces the
+    /// default theme
+    pub fn read_from_file(path: &Path) -> Result<UiLayout, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("{}", err)));
+        let mut layout = UiLayout::new();
+        try!(layout.merge(&text));
+        Ok(layout)
+    }
+
+    /// The layout from the file, or the default layout if there's no file or it can't be read
+    pub fn load(path: &str) -> UiLayout {
+        if path.is_empty() {
+            return UiLayout::new();
+        }
+        UiLayout::read_from_file(Path::new(path)).unwrap_or_else(|err| {
+            warn!("Using the default UI layout, since {} couldn't be read: {}", path, err);
+            UiLayout::new()
+        })
+    }
+
+    fn merge(&mut self, text: &str) -> Result<(), String> {
+        let mut parser = toml::Parser::new(text);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let messages: Vec<String> = parser.errors
+                    .iter()
+                    .map(|err| {
+                        let (line, col) = parser.to_linecol(err.lo);
+                        format!("line {}, column {}: {}", line + 1, col + 1, err.desc)
+                    })
+                    .collect();
+                return Err(messages.join("; "));
+            }
+        };
+
+        for (key, value) in table {
+            match (&key[..], value) {
+                ("panel", Value::Array(entries)) => {
+                    for entry in entries {
+                        match entry {
+                            Value::Table(entry) => self.placements.push(try!(parse_placement(&entry))),
+                            _ => return Err("\"panel\" must be a list of [[panel]] tables".into()),
+                        }
+                    }
+                }
+                ("panel", _) => return Err("\"panel\" must be a list of [[panel]] tables".into()),
+                ("theme", Value::Table(theme)) => self.theme = try!(parse_theme(&theme)),
+                ("theme", _) => return Err("\"theme\" must be a table".into()),
+                (other, _) => return Err(format!("unknown table \"{}\"; use [[panel]] or [theme]", other)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Where a panel of the given size goes on a screen of the given size
+    pub fn place(&self, panel: UiPanel, size: &Vector2<i32>, screen_size: &Vector2<i32>) -> Rect {
+        self.placements
+            .iter()
+            .rev()
+            .find(|placement| placement.panel == panel && placement.fits(screen_size))
+            .map(|placement| placement.place(size, screen_size))
+            .unwrap_or_else(|| Rect::of(0, 0, size.x, size.y))
+    }
+
+    /// The background of a panel, in the theme's panel skin or the fallback color
+    pub fn panel_command(&self, layer: u16, depth: i32, rect: Rect, fallback: Color) -> RenderCommand {
+        match self.theme.panel {
+            Some(skin) => skin.render_command(layer, depth, rect, false, fallback),
+            None => RenderCommand::new_filled_rect(layer, depth, fallback, rect),
+        }
+    }
+
+    /// The face of a button, lit when it's pressed or active, in the theme's button skin or
+    /// the fallback color
+    pub fn button_command(&self,
+                          layer: u16,
+                          depth: i32,
+                          rect: Rect,
+                          lit: bool,
+                          fallback: Color)
+                          -> RenderCommand {
+        match self.theme.button {
+            Some(skin) => skin.render_command(layer, depth, rect, lit, fallback),
+            None => RenderCommand::new_filled_rect(layer, depth, fallback, rect),
+        }
+    }
+}
+
+fn parse_placement(entry: &Table) -> Result<PanelPlacement, String> {
+    let name = try!(string_field(entry, "name"));
+    let panel = try!(UiPanel::from_name(&name).ok_or_else(|| format!("unknown panel \"{}\"", name)));
+    let anchor = match entry.get("anchor") {
+        Some(_) => {
+            let anchor_name = try!(string_field(entry, "anchor"));
+            try!(Anchor::from_name(&anchor_name).ok_or_else(|| format!("unknown anchor \"{}\"", anchor_name)))
+        }
+        None => Anchor::TopLeft,
+    };
+    for key in entry.keys() {
+        match &key[..] {
+            "name" | "anchor" | "x" | "y" | "min_width" | "min_height" => {}
+            _ => return Err(format!("unknown setting \"{}\" for panel \"{}\"", key, name)),
+        }
+    }
+    Ok(PanelPlacement {
+        panel: panel,
+        anchor: anchor,
+        offset: Vector2::new(try!(integer_field(entry, "x", 0)), try!(integer_field(entry, "y", 0))),
+        min_size: Vector2::new(try!(integer_field(entry, "min_width", 0)),
+                               try!(integer_field(entry, "min_height", 0))),
+    })
+}
+
+fn parse_theme(theme: &Table) -> Result<UiTheme, String> {
+    for key in theme.keys() {
+        match &key[..] {
+            "panel_slp" | "panel_frame" | "panel_border" | "button_slp" | "button_frame" |
+            "button_lit_frame" | "button_border" => {}
+            _ => return Err(format!("unknown theme setting \"{}\"", key)),
+        }
+    }
+    let skin = |prefix: &str| -> Result<Option<Skin>, String> {
+        let slp_key = format!("{}_slp", prefix);
+        if theme.get(&slp_key).is_none() {
+            return Ok(None);
+        }
+        let frame = try!(integer_field(theme, &format!("{}_frame", prefix), 0)) as u16;
+        Ok(Some(Skin {
+            slp_id: (try!(integer_field(theme, &slp_key, 0)) as u32).into(),
+            frame: frame,
+            lit_frame: try!(integer_field(theme, &format!("{}_lit_frame", prefix), frame as i32)) as u16,
+            border: try!(integer_field(theme, &format!("{}_border", prefix), 0)),
+        }))
+    };
+    Ok(UiTheme {
+        panel: try!(skin("panel")),
+        button: try!(skin("button")),
+    })
+}
+
+fn string_field(entry: &Table, key: &str) -> Result<String, String> {
+    match entry.get(key) {
+        Some(&Value::String(ref value)) => Ok(value.clone()),
+        Some(_) => Err(format!("\"{}\" must be a string", key)),
+        None => Err(format!("\"{}\" is missing", key)),
+    }
+}
+
+fn integer_field(entry: &Table, key: &str, default: i32) -> Result<i32, String> {
+    match entry.get(key) {
+        Some(&Value::Integer(value)) if value >= 0 && value <= 65535 => Ok(value as i32),
+        Some(_) => Err(format!("\"{}\" must be a whole number from 0 to 65535", key)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+    use super::*;
+    use types::Rect;
+
+    #[test]
+    fn test_default_layout() {
+        let layout = UiLayout::new();
+        let size = Vector2::new(100, 50);
+        let screen = Vector2::new(800, 600);
+        assert_eq!(Rect::of(10, 540, 100, 50), layout.place(UiPanel::CommandPanel, &size, &screen));
+        assert_eq!(Rect::of(690, 10, 100, 50), layout.place(UiPanel::MarketPanel, &size, &screen));
+        assert_eq!(Rect::of(40, 40, 720, 520), layout.place(UiPanel::ScoreScreen, &size, &screen));
+        assert_eq!(Rect::of(350, 510, 100, 50), layout.place(UiPanel::ChatBox, &size, &screen));
+        assert_eq!(Rect::of(10, 540, 100, 50), layout.place(UiPanel::IdleUnits, &size, &screen));
+        assert_eq!(Rect::of(10, 10, 100, 50), layout.place(UiPanel::Notifications, &size, &screen));
+        assert!(layout.theme.panel.is_some());
+        assert!(layout.theme.button.is_some());
+    }
+
+    #[test]
+    fn test_larger_screens_rearrange_panels() {
+        let layout = UiLayout::new();
+        let size = Vector2::new(100, 50);
+        let wide = Vector2::new(1920, 1080);
+        assert_eq!(Rect::of(910, 10, 100, 50), layout.place(UiPanel::MarketPanel, &size, &wide));
+        assert_eq!(Rect::of(160, 60, 1600, 960), layout.place(UiPanel::ScoreScreen, &size, &wide));
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        let layout = UiLayout::parse("[[panel]]\nname = \"command_panel\"\nanchor = \"bottom_right\"\n\
+                                      x = 5\ny = 6\n\n[theme]\nbutton_slp = 50721\nbutton_frame = 3\n")
+            .unwrap();
+        let size = Vector2::new(20, 10);
+        assert_eq!(Rect::of(295, 184, 20, 10),
+                   layout.place(UiPanel::CommandPanel, &size, &Vector2::new(320, 200)));
+        assert_eq!(None, layout.theme.panel);
+        let button = layout.theme.button.unwrap();
+        assert_eq!((3, 3, 0), (button.frame, button.lit_frame, button.border));
+
+        assert!(UiLayout::parse("[[panel]]\nname = \"minimap\"\n").is_err());
+        assert!(UiLayout::parse("[[panel]]\nname = \"market_panel\"\nanchor = \"middle\"\n").is_err());
+        assert!(UiLayout::parse("[[panel]]\nname = \"market_panel\"\nwidth = 10\n").is_err());
+        assert!(UiLayout::parse("[theme]\npanel_color = 1\n").is_err());
+        assert!(UiLayout::parse("[[widget]]\n").is_err());
+    }
+}
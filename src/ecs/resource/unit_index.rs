@@ -0,0 +1,146 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::PlayerId;
+use specs;
+use std::collections::{BTreeMap, HashMap};
+
+/// A kind of unit that systems look up all of a player's units of
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum UnitTag {
+    Villager,
+    Military,
+    Building,
+    /// A building that gatherers can drop resources off at
+    DropSite,
+    /// Something holding a resource for gatherers, like a tree or a gold mine
+    Resource(ResourceType),
+    /// A wild animal that hunters can kill for the food on its carcass
+    Huntable,
+}
+
+/// Every unit, by player and by what kind of unit it is, so that systems looking for, say, all of
+/// a player's drop sites don't have to go through the whole world. Units are added when they're
+/// spawned and removed when they die.
+pub struct UnitIndex {
+    tagged: BTreeMap<(PlayerId, UnitTag), Vec<specs::Entity>>,
+    entries: HashMap<specs::Index, (specs::Entity, PlayerId, Vec<UnitTag>)>,
+}
+
+impl UnitIndex {
+    pub fn new() -> UnitIndex {
+        UnitIndex {
+            tagged: BTreeMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Adds the unit under each of its tags, replacing whatever it was indexed under before
+    pub fn insert(&mut self, entity: specs::Entity, player_id: PlayerId, tags: Vec<UnitTag>) {
+        self.remove(entity);
+        for tag in &tags {
+            self.tagged.entry((player_id, *tag)).or_insert_with(Vec::new).push(entity);
+        }
+        self.entries.insert(entity.get_id(), (entity, player_id, tags));
+    }
+
+    pub fn remove(&mut self, entity: specs::Entity) {
+        if let Some((_, player_id, tags)) = self.entries.remove(&entity.get_id()) {
+            for tag in tags {
+                if let Some(list) = self.tagged.get_mut(&(player_id, tag)) {
+                    list.retain(|e| *e != entity);
+                }
+            }
+        }
+    }
+
+    /// The player's units with the tag, in the order they were spawned
+    pub fn entities<'a>(&'a self, player_id: PlayerId, tag: UnitTag) -> &'a [specs::Entity] {
+        match self.tagged.get(&(player_id, tag)) {
+            Some(list) => list,
+            None => &[],
+        }
+    }
+
+    /// Every player's units with the tag, ordered by player
+    pub fn all_entities(&self, tag: UnitTag) -> Vec<specs::Entity> {
+        self.tagged
+            .iter()
+            .filter(|&(&(_, list_tag), _)| list_tag == tag)
+            .flat_map(|(_, list)| list.iter().cloned())
+            .collect()
+    }
+
+    pub fn tags<'a>(&'a self, entity: specs::Entity) -> &'a [UnitTag] {
+        match self.entries.get(&entity.get_id()) {
+            Some(&(_, _, ref tags)) => tags,
+            None => &[],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use specs;
+    use super::{UnitIndex, UnitTag};
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut world = specs::World::new();
+        let town_center = world.create_now().build();
+        let villager = world.create_now().build();
+        let tree = world.create_now().build();
+
+        let mut index = UnitIndex::new();
+        index.insert(town_center, 1.into(), vec![UnitTag::Building, UnitTag::DropSite]);
+        index.insert(villager, 1.into(), vec![UnitTag::Villager]);
+        index.insert(tree, 0.into(), vec![UnitTag::Resource(ResourceType::Wood)]);
+        assert_eq!(3, index.len());
+        assert_eq!(&[town_center], index.entities(1.into(), UnitTag::DropSite));
+        assert!(index.entities(2.into(), UnitTag::DropSite).is_empty());
+        assert_eq!(vec![tree], index.all_entities(UnitTag::Resource(ResourceType::Wood)));
+        assert!(index.all_entities(UnitTag::Resource(ResourceType::Gold)).is_empty());
+
+        index.remove(town_center);
+        assert!(index.entities(1.into(), UnitTag::Building).is_empty());
+        assert!(index.tags(town_center).is_empty());
+        assert_eq!(&[UnitTag::Villager], index.tags(villager));
+        assert_eq!(2, index.len());
+    }
+
+    #[test]
+    fn test_reinserting_moves_the_unit() {
+        let mut world = specs::World::new();
+        let villager = world.create_now().build();
+
+        let mut index = UnitIndex::new();
+        index.insert(villager, 1.into(), vec![UnitTag::Villager]);
+        index.insert(villager, 2.into(), vec![UnitTag::Villager]);
+        assert!(index.entities(1.into(), UnitTag::Villager).is_empty());
+        assert_eq!(&[villager], index.entities(2.into(), UnitTag::Villager));
+    }
+}
@@ -0,0 +1,100 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::component::ProjectileComponent;
+use identifier::{CivilizationId, PlayerId, UnitId};
+use specs::Index;
+use std::mem;
+use types::{Fixed, Vector3};
+
+/// Where a production building sends the units it trains
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RallyPoint {
+    Position(Vector3),
+    /// Follows another entity; where it is gets looked up when the unit spawns
+    Unit(Index),
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnRequest {
+    pub player_id: PlayerId,
+    pub civilization_id: CivilizationId,
+    pub unit_id: UnitId,
+    pub position: Vector3,
+    pub rotation: Fixed,
+    pub rally_point: Option<RallyPoint>,
+    /// The frame of the graphic to start on, for graphics whose frames are different pieces
+    /// rather than an animation, like walls
+    pub frame: u16,
+    /// Set when the unit is a shot in flight rather than a unit of its own
+    pub projectile: Option<ProjectileComponent>,
+    /// Set for what's left behind when a unit dies or decays, which doesn't take part in the
+    /// fighting
+    pub remains: bool,
+    /// Set for a building that's laid down as a foundation, for villagers to build
+    pub foundation: bool,
+    /// The villagers that go and build the foundation
+    pub builders: Vec<Index>,
+}
+
+impl SpawnRequest {
+    pub fn new(player_id: PlayerId,
+               civilization_id: CivilizationId,
+               unit_id: UnitId,
+               position: Vector3)
+               -> SpawnRequest {
+        SpawnRequest {
+            player_id: player_id,
+            civilization_id: civilization_id,
+            unit_id: unit_id,
+            position: position,
+            rotation: 0.into(),
+            rally_point: None,
+            frame: 0,
+            projectile: None,
+            remains: false,
+            foundation: false,
+            builders: Vec::new(),
+        }
+    }
+}
+
+/// Systems can't create entities with all of their components while the world is being dispatched,
+/// so new units are queued up here and created once the update is over.
+pub struct UnitSpawner {
+    requests: Vec<SpawnRequest>,
+}
+
+impl UnitSpawner {
+    pub fn new() -> UnitSpawner {
+        UnitSpawner { requests: Vec::new() }
+    }
+
+    pub fn queue_spawn(&mut self, request: SpawnRequest) {
+        self.requests.push(request);
+    }
+
+    pub fn consume_spawns(&mut self) -> Vec<SpawnRequest> {
+        let mut consumed = Vec::new();
+        mem::swap(&mut consumed, &mut self.requests);
+        consumed
+    }
+}
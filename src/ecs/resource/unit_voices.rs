@@ -0,0 +1,175 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{SoundEffectGroup, Unit};
+use identifier::{SoundGroupId, WavFileId};
+use std::collections::HashMap;
+
+/// No line starts sooner than this after the last one, so a burst of clicks comes down to one
+const MIN_LINE_INTERVAL_NANOS: u64 = 250000000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Acknowledgment {
+    Selected,
+    Moved,
+    Attacked,
+}
+
+/// The local player's units answering when they're selected or given orders. Each unit type
+/// has its own voice in the game data, and so can each civ's version of it; each voice has a few
+/// lines, which take turns. Only one line plays at a time, and they're rate limited, so that
+/// clicking away doesn't pile up a chorus of the same few words.
+pub struct UnitVoices {
+    /// The newest acknowledgment asked for since a line was last played
+    pending: Option<SoundGroupId>,
+    /// The line each voice says next
+    next_lines: HashMap<SoundGroupId, usize>,
+    last_started_nanos: Option<u64>,
+}
+
+impl UnitVoices {
+    pub fn new() -> UnitVoices {
+        UnitVoices {
+            pending: None,
+            next_lines: HashMap::new(),
+            last_started_nanos: None,
+        }
+    }
+
+    /// Has the unit answer, if it has a voice for it
+    pub fn acknowledge(&mut self, unit: &Unit, acknowledgment: Acknowledgment) {
+        let voice = match acknowledgment {
+            Acknowledgment::Selected => unit.selection_sound_id(),
+            Acknowledgment::Moved => unit.move_sound_id(),
+            Acknowledgment::Attacked => unit.attack_sound_id(),
+        };
+        if let Some(voice) = voice {
+            self.request(voice);
+        }
+    }
+
+    /// Asks for a line from the voice, in place of any earlier request that hasn't played yet
+    pub fn request(&mut self, voice: SoundGroupId) {
+        self.pending = Some(voice);
+    }
+
+    /// The voice and sound of the line to play, once it's time for one. `speaking` is the voice of
+    /// the line that's still playing, if there is one: asking for that voice again is dropped,
+    /// rather than starting it over, while any other voice cuts it off.
+    pub fn next_line(&mut self,
+                     voices: &[SoundEffectGroup],
+                     now_nanos: u64,
+                     speaking: Option<SoundGroupId>)
+                     -> Option<(SoundGroupId, WavFileId)> {
+        let voice = match self.pending {
+            Some(voice) => voice,
+            None => return None,
+        };
+        if speaking == Some(voice) {
+            self.pending = None;
+            return None;
+        }
+        if let Some(last_started_nanos) = self.last_started_nanos {
+            if now_nanos < last_started_nanos + MIN_LINE_INTERVAL_NANOS {
+                return None;
+            }
+        }
+        self.pending = None;
+
+        let lines: Vec<WavFileId> = match voices.get(*voice as usize) {
+            Some(group) => group.sound_effects.iter().filter_map(|effect| effect.resource_id).collect(),
+            None => return None,
+        };
+        if lines.is_empty() {
+            return None;
+        }
+        let next_line = self.next_lines.entry(voice).or_insert(0);
+        let line = lines[*next_line % lines.len()];
+        *next_line = (*next_line + 1) % lines.len();
+        self.last_started_nanos = Some(now_nanos);
+        Some((voice, line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{SoundEffect, SoundEffectGroup};
+    use identifier::SoundGroupId;
+    use super::*;
+
+    const SECOND: u64 = 1000000000;
+
+    fn voices() -> Vec<SoundEffectGroup> {
+        (0usize..2)
+            .map(|voice| {
+                SoundEffectGroup {
+                    id: voice.into(),
+                    sound_effects: (0usize..3)
+                        .map(|line| {
+                            SoundEffect {
+                                resource_id: Some((voice * 10 + line).into()),
+                                ..Default::default()
+                            }
+                        })
+                        .collect(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lines_take_turns() {
+        let voices = voices();
+        let mut unit_voices = UnitVoices::new();
+        let lines: Vec<u32> = (0..4)
+            .map(|second| {
+                unit_voices.request(0usize.into());
+                *unit_voices.next_line(&voices, second * SECOND, None).unwrap().1
+            })
+            .collect();
+        assert_eq!(vec![0, 1, 2, 0], lines);
+    }
+
+    #[test]
+    fn test_the_same_voice_isnt_started_over() {
+        let voices = voices();
+        let voice: SoundGroupId = 1usize.into();
+        let mut unit_voices = UnitVoices::new();
+        unit_voices.request(voice);
+        assert!(unit_voices.next_line(&voices, SECOND, Some(voice)).is_none());
+        assert!(unit_voices.next_line(&voices, 2 * SECOND, None).is_none());
+    }
+
+    #[test]
+    fn test_bursts_come_down_to_the_newest_request() {
+        let voices = voices();
+        let mut unit_voices = UnitVoices::new();
+        unit_voices.request(0usize.into());
+        assert!(unit_voices.next_line(&voices, 0, None).is_some());
+
+        unit_voices.request(0usize.into());
+        unit_voices.request(1usize.into());
+        assert!(unit_voices.next_line(&voices, SECOND / 10, None).is_none());
+        let (voice, line) = unit_voices.next_line(&voices, SECOND / 2, None).unwrap();
+        assert_eq!((1, 10), (*voice, *line));
+    }
+}
@@ -0,0 +1,247 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use identifier::UnitId;
+use types::Fixed;
+
+/// The small wall, the first wall there is to build
+pub const WALL_UNIT_ID: usize = 72;
+
+/// (row, col) tile position
+pub type WallTile = (i32, i32);
+
+/// Which way a wall segment joins up with the segments next to it, which picks the shape its
+/// graphic is drawn with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WallPiece {
+    /// Stands on its own
+    Post,
+    /// Runs along the columns
+    AlongX,
+    /// Runs along the rows
+    AlongY,
+    /// Runs with the column and row going up together
+    Diagonal,
+    /// Runs with the column going up as the row goes down
+    AntiDiagonal,
+    /// Turns from one direction into another
+    Corner,
+}
+
+impl WallPiece {
+    /// The shape the piece's frame is in the wall graphics
+    pub fn shape_index(&self) -> u16 {
+        match *self {
+            WallPiece::Post => 0,
+            WallPiece::AlongX => 1,
+            WallPiece::AlongY => 2,
+            WallPiece::Diagonal => 3,
+            WallPiece::AntiDiagonal => 4,
+            WallPiece::Corner => 5,
+        }
+    }
+
+    fn joining(previous: Option<WallTile>, tile: WallTile, next: Option<WallTile>) -> WallPiece {
+        let direction = |other: WallTile| (other.0 - tile.0, other.1 - tile.1);
+        match (previous.map(&direction), next.map(&direction)) {
+            (None, None) => WallPiece::Post,
+            (Some(only), None) | (None, Some(only)) => WallPiece::along(only),
+            (Some(first), Some(second)) if first == (-second.0, -second.1) => WallPiece::along(first),
+            _ => WallPiece::Corner,
+        }
+    }
+
+    fn along((row_step, col_step): (i32, i32)) -> WallPiece {
+        match (row_step, col_step) {
+            (0, _) => WallPiece::AlongX,
+            (_, 0) => WallPiece::AlongY,
+            _ if row_step == col_step => WallPiece::Diagonal,
+            _ => WallPiece::AntiDiagonal,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WallSegment {
+    pub tile: WallTile,
+    pub piece: WallPiece,
+}
+
+/// The segments of a wall dragged from one tile to another, snapped to the tiles along the
+/// line between them. Tiles that are blocked are skipped, which breaks the wall there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WallPlan {
+    pub segments: Vec<WallSegment>,
+    pub skipped: Vec<WallTile>,
+}
+
+impl WallPlan {
+    pub fn new<F: Fn(WallTile) -> bool>(start: WallTile, end: WallTile, blocked: F) -> WallPlan {
+        let (segment_tiles, skipped): (Vec<_>, Vec<_>) =
+            wall_line(start, end).into_iter().partition(|&tile| !blocked(tile));
+        let adjacent = |first: WallTile, second: WallTile| {
+            (first.0 - second.0).abs() <= 1 && (first.1 - second.1).abs() <= 1
+        };
+
+        let segments = segment_tiles.iter()
+            .enumerate()
+            .map(|(index, &tile)| {
+                let previous = if index > 0 { Some(segment_tiles[index - 1]) } else { None };
+                let next = segment_tiles.get(index + 1).cloned();
+                WallSegment {
+                    tile: tile,
+                    piece: WallPiece::joining(previous.filter(|&other| adjacent(other, tile)),
+                                              tile,
+                                              next.filter(|&other| adjacent(other, tile))),
+                }
+            })
+            .collect();
+        WallPlan {
+            segments: segments,
+            skipped: skipped,
+        }
+    }
+
+    /// What the whole wall costs, given what one segment does
+    pub fn cost(&self, segment_cost: &[(ResourceType, Fixed)]) -> Vec<(ResourceType, Fixed)> {
+        let count = Fixed::from(self.segments.len());
+        segment_cost.iter().map(|&(resource_type, amount)| (resource_type, amount * count)).collect()
+    }
+}
+
+/// The tiles on the line from one tile to another, both included, each touching the one
+/// before it along a side or a corner
+pub fn wall_line(start: WallTile, end: WallTile) -> Vec<WallTile> {
+    let (row_distance, col_distance) = ((end.0 - start.0).abs(), (end.1 - start.1).abs());
+    let (row_step, col_step) = ((end.0 - start.0).signum(), (end.1 - start.1).signum());
+    let mut tiles = Vec::with_capacity(row_distance.max(col_distance) as usize + 1);
+    let (mut row, mut col) = start;
+    let mut error = col_distance - row_distance;
+    loop {
+        tiles.push((row, col));
+        if (row, col) == end {
+            return tiles;
+        }
+        let doubled = 2 * error;
+        if doubled > -row_distance {
+            error -= row_distance;
+            col += col_step;
+        }
+        if doubled < col_distance {
+            error += col_distance;
+            row += row_step;
+        }
+    }
+}
+
+/// A wall being laid out by the local player: turned on with the build wall key, or the wall's
+/// button on the command panel, while a villager is selected, then dragged out from one tile
+/// to another and built when it's let go
+pub struct WallPlacement {
+    /// The wall being built, while placing one
+    pub unit_id: Option<UnitId>,
+    /// The tile the drag started on
+    pub start: Option<WallTile>,
+    pub plan: Option<WallPlan>,
+    /// What the planned wall costs, all of its segments together
+    pub cost: Vec<(ResourceType, Fixed)>,
+}
+
+impl WallPlacement {
+    pub fn new() -> WallPlacement {
+        WallPlacement {
+            unit_id: None,
+            start: None,
+            plan: None,
+            cost: Vec::new(),
+        }
+    }
+
+    pub fn is_placing(&self) -> bool {
+        self.unit_id.is_some()
+    }
+
+    pub fn begin(&mut self, unit_id: UnitId) {
+        *self = WallPlacement::new();
+        self.unit_id = Some(unit_id);
+    }
+
+    pub fn cancel(&mut self) {
+        *self = WallPlacement::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_snaps_to_tiles() {
+        assert_eq!(vec![(2, 1), (2, 2), (2, 3), (2, 4)], wall_line((2, 1), (2, 4)));
+        assert_eq!(vec![(0, 0), (1, 1), (2, 2)], wall_line((0, 0), (2, 2)));
+        assert_eq!(vec![(3, 3)], wall_line((3, 3), (3, 3)));
+
+        let shallow = wall_line((4, 0), (2, 4));
+        assert_eq!(Some(&(4, 0)), shallow.first());
+        assert_eq!(Some(&(2, 4)), shallow.last());
+        assert_eq!(5, shallow.len());
+        for pair in shallow.windows(2) {
+            assert!((pair[0].0 - pair[1].0).abs() <= 1 && (pair[0].1 - pair[1].1).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_blocked_tiles_are_skipped() {
+        let plan = WallPlan::new((0, 0), (0, 4), |tile| tile == (0, 2));
+        assert_eq!(vec![(0, 2)], plan.skipped);
+        let pieces: Vec<_> = plan.segments.iter().map(|segment| (segment.tile, segment.piece)).collect();
+        assert_eq!(vec![((0, 0), WallPiece::AlongX),
+                        ((0, 1), WallPiece::AlongX),
+                        ((0, 3), WallPiece::AlongX),
+                        ((0, 4), WallPiece::AlongX)],
+                   pieces);
+
+        let lone = WallPlan::new((0, 0), (0, 2), |tile| tile == (0, 1));
+        assert_eq!(vec![WallPiece::Post, WallPiece::Post],
+                   lone.segments.iter().map(|segment| segment.piece).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_corner_pieces() {
+        // Going two across for every one down steps straight, then diagonally, then straight
+        let plan = WallPlan::new((0, 0), (1, 2), |_| false);
+        let pieces: Vec<_> = plan.segments.iter().map(|segment| segment.piece).collect();
+        assert_eq!(vec![WallPiece::AlongX, WallPiece::Corner, WallPiece::Diagonal], pieces);
+
+        let plan = WallPlan::new((3, 0), (0, 3), |_| false);
+        assert!(plan.segments.iter().all(|segment| segment.piece == WallPiece::AntiDiagonal));
+        let plan = WallPlan::new((0, 5), (3, 5), |_| false);
+        assert!(plan.segments.iter().all(|segment| segment.piece == WallPiece::AlongY));
+    }
+
+    #[test]
+    fn test_cost_is_per_segment() {
+        let plan = WallPlan::new((0, 0), (0, 4), |tile| tile == (0, 2));
+        assert_eq!(vec![(ResourceType::Stone, Fixed::from(20))],
+                   plan.cost(&[(ResourceType::Stone, 5.into())]));
+    }
+}
@@ -0,0 +1,1028 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, AttackGroundParams, GarrisonParams, GatherParams, MoveToPositionParams, RestoreParams};
+use dat::{self, ResourceType};
+use ecs::component::*;
+use ecs::resource::{COMMODITIES, Cheat, CheatRecord, CheatRequest, Cheats, Diplomacy, FogOfWar, Market,
+                    Players, RANDOM_STREAMS, RallyPoint, RandomNumbers, SimulationTick, Technologies,
+                    apply_research_effects, research_effects};
+use identifier::{PlayerId, ResearchId, UnitId};
+use scn;
+use specs::{self, Join};
+use std::collections::HashMap;
+use std::iter;
+use toml::{self, Table, Value};
+use types::{Fixed, Vector3};
+
+/// The resources a player's stockpile is saved with, and their keys in the save state
+const SAVED_RESOURCES: [(ResourceType, &'static str); 4] = [(ResourceType::Food, "food"),
+                                                             (ResourceType::Wood, "wood"),
+                                                             (ResourceType::Stone, "stone"),
+                                                             (ResourceType::Gold, "gold")];
+
+/// Everything a save keeps that a scenario has no room for: the game clock, where the random
+/// streams are, the market's prices, each player's exact stockpile, research and explored tiles,
+/// the units' hit points, orders and cooldowns, what's left in each resource, how far along each
+/// foundation is, what each building is training and researching, the cheats that were used, and
+/// which of the scripts' `fire_once` rules have fired. Fixed point values are kept as they are.
+///
+/// Units start their current order over, losing things like the path they were partway along,
+/// and shots in flight and where the birds were circling aren't kept, so a restored game can play
+/// out differently from the saved one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveState {
+    pub tick: usize,
+    pub random_seed: u64,
+    /// In the order of `RANDOM_STREAMS`
+    pub random_streams: Vec<u64>,
+    /// The price of each commodity at the market
+    pub market_prices: Vec<(ResourceType, Fixed)>,
+    pub players: Vec<SavedPlayer>,
+    pub units: Vec<SavedUnit>,
+    /// Every cheat used so far, which also says which of the toggled ones were left on
+    pub cheats: Vec<CheatRecord>,
+    /// The rules each script has fired, by the path the script was loaded from
+    pub scripts: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedPlayer {
+    pub player_id: PlayerId,
+    /// In the order of `SAVED_RESOURCES`
+    pub stockpile: [Fixed; 4],
+    pub researched: Vec<ResearchId>,
+    /// The tiles the player's own units have explored, and the ones they've explored along with
+    /// their allies, one per tile; see `FogOfWar::explored_tiles`
+    pub explored: Option<(Vec<bool>, Vec<bool>)>,
+}
+
+/// A unit in the saved scenario, found again by its player and where it comes in that player's
+/// units
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedUnit {
+    pub player_id: PlayerId,
+    pub index: usize,
+    pub unit_id: UnitId,
+    pub frame: u16,
+    pub hit_points: Option<Fixed>,
+    pub resource_amount: Option<Fixed>,
+    pub construction_progress: Option<Fixed>,
+    pub production: Option<SavedProductionQueue>,
+    pub rally_point: Option<SavedRallyPoint>,
+    /// Current order first
+    pub orders: Vec<SavedAction>,
+    pub cooldowns: Vec<Cooldown>,
+}
+
+/// A building's rally point, with the unit it follows found by its player and index like a
+/// `SavedUnit`, since entity IDs aren't the same in the restored game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SavedRallyPoint {
+    Position(Vector3),
+    Unit(PlayerId, usize),
+}
+
+/// An order a unit was given, with the unit it's aimed at found by its player and index like a
+/// `SavedRallyPoint`
+#[derive(Clone, Debug, PartialEq)]
+pub enum SavedAction {
+    MoveToPosition(Vec<Vector3>),
+    Gather(PlayerId, usize),
+    AttackGround(Vector3),
+    Restore(PlayerId, usize),
+    Explore,
+    Garrison(PlayerId, usize),
+}
+
+/// Writes the world's units, what each player has banked, and the stances between the players
+/// into the scenario in place of what it started with, so that playing the scenario again picks
+/// the game up from there. The rest of what's needed to pick it up where it was is returned, to
+/// be restored with `restore_save_state`. Shots in flight and remains are left out.
+pub fn save_to_scenario(world: &specs::World, scenario: &mut scn::Scenario) -> SaveState {
+    let mut state = SaveState::default();
+    state.tick = world.read_resource::<SimulationTick>().get();
+
+    let random = world.read_resource::<RandomNumbers>();
+    state.random_seed = random.seed();
+    state.random_streams = RANDOM_STREAMS.iter().map(|stream| random.stream_state(*stream)).collect();
+
+    let market = world.read_resource::<Market>();
+    state.market_prices = COMMODITIES.iter()
+        .map(|resource_type| (*resource_type, market.price(*resource_type)))
+        .collect();
+
+    state.cheats = world.read_resource::<Cheats>().history().to_vec();
+
+    let player_ids = scenario.player_ids();
+    for player_id in &player_ids {
+        scenario.player_units_mut(*player_id).clear();
+    }
+
+    let diplomacy = world.read_resource::<Diplomacy>();
+    for player_id in &player_ids {
+        let player_index = **player_id as usize;
+        for other_player_id in &player_ids {
+            if player_id != other_player_id {
+                let stance = diplomacy.stance(*player_id, *other_player_id).to_scn();
+                scenario.player_data.diplomacy.set_stance(player_index, **other_player_id as usize, stance);
+            }
+        }
+        scenario.player_data.allied_victory[player_index] = diplomacy.allied_victory(*player_id) as u32;
+    }
+
+    let players = world.read_resource::<Players>();
+    let technologies = world.read_resource::<Technologies>();
+    let fog_of_war = world.read_resource::<FogOfWar>();
+    for player_id in &player_ids {
+        let player = match players.player(*player_id) {
+            Some(player) => player,
+            None => continue,
+        };
+        if let Some(resources) = scenario.player_resources_mut(*player_id) {
+            resources.food = player.stockpile.amount(ResourceType::Food).into();
+            resources.wood = player.stockpile.amount(ResourceType::Wood).into();
+            resources.stone = player.stockpile.amount(ResourceType::Stone).into();
+            resources.gold = player.stockpile.amount(ResourceType::Gold).into();
+        }
+        let mut stockpile = [Fixed::from(0); 4];
+        for (amount, &(resource_type, _)) in stockpile.iter_mut().zip(SAVED_RESOURCES.iter()) {
+            *amount = player.stockpile.amount(resource_type);
+        }
+        state.players.push(SavedPlayer {
+            player_id: *player_id,
+            stockpile: stockpile,
+            researched: technologies.researched(*player_id),
+            explored: fog_of_war.explored_tiles(*player_id),
+        });
+    }
+
+    let entities = world.entities();
+    let units = world.read::<UnitComponent>();
+    let transforms = world.read::<TransformComponent>();
+    let graphics = world.read::<GraphicComponent>();
+    let hit_points = world.read::<HitPointsComponent>();
+    let resource_nodes = world.read::<ResourceNodeComponent>();
+    let constructions = world.read::<ConstructionComponent>();
+    let projectiles = world.read::<ProjectileComponent>();
+    let decompositions = world.read::<DecompositionComponent>();
+    let production_queues = world.read::<ProductionQueueComponent>();
+    let action_queues = world.read::<ActionQueueComponent>();
+    let cooldowns = world.read::<CooldownComponent>();
+    let mut unit_counts: HashMap<PlayerId, usize> = HashMap::new();
+    // Where each saved unit comes in its player's units, by entity ID, for the rally points and
+    // orders
+    let mut saved_indices = HashMap::new();
+    let mut rally_points = Vec::new();
+    let mut orders = Vec::new();
+    for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+        if projectiles.get(entity).is_some() || decompositions.get(entity).is_some() ||
+           !player_ids.contains(&unit.player_id) {
+            continue;
+        }
+        let position = transform.position();
+        scenario.add_unit(unit.player_id,
+                          scn::PlayerUnit {
+                              position_x: position.x.into(),
+                              position_y: position.y.into(),
+                              position_z: position.z.into(),
+                              unit_id: unit.unit_id,
+                              rotation: transform.rotation.into(),
+                              ..Default::default()
+                          });
+
+        let count = unit_counts.entry(unit.player_id).or_insert(0);
+        saved_indices.insert(entity.get_id(), (unit.player_id, *count));
+        if let Some(rally_point) = production_queues.get(entity).and_then(|queue| queue.rally_point) {
+            rally_points.push((state.units.len(), rally_point));
+        }
+        if let Some(action_queue) = action_queues.get(entity) {
+            orders.push((state.units.len(), action_queue.orders()));
+        }
+        state.units.push(SavedUnit {
+            player_id: unit.player_id,
+            index: *count,
+            unit_id: unit.unit_id,
+            frame: graphics.get(entity).map_or(0, |graphic| graphic.frame),
+            hit_points: hit_points.get(entity).map(|hit_points| hit_points.hit_points),
+            resource_amount: resource_nodes.get(entity).map(|node| node.amount),
+            construction_progress: constructions.get(entity).map(|construction| construction.progress),
+            production: production_queues.get(entity).map(|queue| queue.to_saved()),
+            rally_point: None,
+            orders: Vec::new(),
+            cooldowns: cooldowns.get(entity).map_or(Vec::new(), |cooldowns| cooldowns.cooldowns().to_vec()),
+        });
+        *count += 1;
+    }
+
+    // Rally points on units that weren't saved, like remains, are dropped
+    for (unit_index, rally_point) in rally_points {
+        state.units[unit_index].rally_point = match rally_point {
+            RallyPoint::Position(position) => Some(SavedRallyPoint::Position(position)),
+            RallyPoint::Unit(target_id) => {
+                saved_indices.get(&target_id)
+                    .map(|&(player_id, index)| SavedRallyPoint::Unit(player_id, index))
+            }
+        };
+    }
+
+    // As are orders aimed at them
+    for (unit_index, actions) in orders {
+        let aimed_at = |target: specs::Entity, order: fn(PlayerId, usize) -> SavedAction| {
+            saved_indices.get(&target.get_id()).map(|&(player_id, index)| order(player_id, index))
+        };
+        state.units[unit_index].orders = actions.iter()
+            .filter_map(|action| match *action {
+                Action::MoveToPosition(ref params) => Some(SavedAction::MoveToPosition(params.path.clone())),
+                Action::Gather(ref params) => aimed_at(params.target, SavedAction::Gather),
+                Action::AttackGround(ref params) => Some(SavedAction::AttackGround(params.position)),
+                Action::Restore(ref params) => aimed_at(params.target, SavedAction::Restore),
+                Action::Explore => Some(SavedAction::Explore),
+                Action::Garrison(ref params) => aimed_at(params.target, SavedAction::Garrison),
+                Action::ClearQueue | Action::SuspendQueue | Action::ResumeQueue => None,
+            })
+            .collect();
+    }
+    state
+}
+
+/// Puts back what the scenario a game was saved to couldn't hold, on a world that was just
+/// created from that scenario. Units that don't match what was saved, such as ones the game data
+/// doesn't have, are left as the scenario made them.
+pub fn restore_save_state(world: &mut specs::World, empires: &dat::EmpiresDb, state: &SaveState) {
+    world.write_resource::<SimulationTick>().set(state.tick);
+    if !state.random_streams.is_empty() {
+        *world.write_resource::<RandomNumbers>() = RandomNumbers::resume(state.random_seed,
+                                                                         &state.random_streams);
+    }
+    world.write_resource::<Cheats>().restore_history(state.cheats.clone());
+
+    {
+        let mut players = world.write_resource::<Players>();
+        let mut technologies = world.write_resource::<Technologies>();
+        let mut market = world.write_resource::<Market>();
+        let mut diplomacy = world.write_resource::<Diplomacy>();
+        let mut fog_of_war = world.write_resource::<FogOfWar>();
+        for &(resource_type, price) in &state.market_prices {
+            market.set_price(resource_type, price);
+        }
+        for saved_player in &state.players {
+            let civ_id = match players.player_mut(saved_player.player_id) {
+                Some(player) => {
+                    for (amount, &(resource_type, _)) in saved_player.stockpile
+                        .iter()
+                        .zip(SAVED_RESOURCES.iter()) {
+                        player.stockpile.set(resource_type, *amount);
+                    }
+                    player.civ_id
+                }
+                None => continue,
+            };
+            if let Some((ref own_explored, ref explored)) = saved_player.explored {
+                let player_id = saved_player.player_id;
+                if !fog_of_war.restore_explored(player_id, own_explored.clone(), explored.clone()) {
+                    warn!("Player {}'s explored tiles in the save don't fit the map",
+                          *saved_player.player_id);
+                }
+            }
+            for &research_id in &saved_player.researched {
+                if *research_id as usize >= empires.all_research().len() ||
+                   technologies.has_researched(saved_player.player_id, research_id) {
+                    continue;
+                }
+                technologies.complete(saved_player.player_id, research_id);
+                apply_research_effects(saved_player.player_id,
+                                       empires.civilization(civ_id),
+                                       research_effects(empires, empires.research(research_id)),
+                                       &mut *market,
+                                       &mut *diplomacy,
+                                       &mut *players);
+            }
+        }
+    }
+
+    let mut scenario_units = HashMap::new();
+    {
+        let entities = world.entities();
+        let units = world.read::<UnitComponent>();
+        let mut unit_counts: HashMap<PlayerId, usize> = HashMap::new();
+        for (entity, unit) in (&entities, &units).iter() {
+            let count = unit_counts.entry(unit.player_id).or_insert(0);
+            scenario_units.insert((unit.player_id, *count), (entity, unit.civilization_id, unit.unit_id));
+            *count += 1;
+        }
+    }
+
+    let mut graphics = world.write::<GraphicComponent>();
+    let mut hit_points = world.write::<HitPointsComponent>();
+    let mut resource_nodes = world.write::<ResourceNodeComponent>();
+    let mut constructions = world.write::<ConstructionComponent>();
+    let mut production_queues = world.write::<ProductionQueueComponent>();
+    let mut action_queues = world.write::<ActionQueueComponent>();
+    let mut cooldowns = world.write::<CooldownComponent>();
+    for saved_unit in &state.units {
+        let scenario_unit = scenario_units.get(&(saved_unit.player_id, saved_unit.index));
+        let (entity, civ_id) = match scenario_unit {
+            Some(&(entity, civ_id, unit_id)) if unit_id == saved_unit.unit_id => (entity, civ_id),
+            _ => {
+                warn!("Player {}'s unit {} in the save doesn't match the scenario's",
+                      *saved_unit.player_id,
+                      saved_unit.index);
+                continue;
+            }
+        };
+        if let Some(amount) = saved_unit.hit_points {
+            hit_points.insert(entity, HitPointsComponent::new(amount));
+        }
+        if let (Some(amount), Some(node)) = (saved_unit.resource_amount, resource_nodes.get_mut(entity)) {
+            node.amount = amount;
+        }
+        if let Some(graphic) = graphics.get_mut(entity) {
+            graphic.frame = saved_unit.frame;
+            if let Some(progress) = saved_unit.construction_progress {
+                // Wall pieces are told apart by their frame, which only the standing graphic has
+                if saved_unit.frame == 0 {
+                    let unit_info = empires.unit(civ_id, saved_unit.unit_id);
+                    graphic.set_graphic(unit_info.construction_graphic().or(unit_info.standing_graphic));
+                }
+                constructions.insert(entity, ConstructionComponent { progress: progress });
+            }
+        }
+        if let Some(ref production) = saved_unit.production {
+            let mut production_queue = ProductionQueueComponent::from_saved(production);
+            production_queue.rally_point = match saved_unit.rally_point {
+                Some(SavedRallyPoint::Position(position)) => Some(RallyPoint::Position(position)),
+                Some(SavedRallyPoint::Unit(player_id, index)) => {
+                    scenario_units.get(&(player_id, index))
+                        .map(|&(target, _, _)| RallyPoint::Unit(target.get_id()))
+                }
+                None => None,
+            };
+            production_queues.insert(entity, production_queue);
+        }
+        if action_queues.get(entity).is_some() {
+            let target = |player_id: PlayerId, index: usize| {
+                scenario_units.get(&(player_id, index)).map(|&(target, _, _)| target)
+            };
+            let orders = saved_unit.orders
+                .iter()
+                .filter_map(|order| match *order {
+                    SavedAction::MoveToPosition(ref path) => {
+                        Some(Action::MoveToPosition(MoveToPositionParams::new(path.clone())))
+                    }
+                    SavedAction::Gather(player_id, index) => {
+                        target(player_id, index).map(|target| Action::Gather(GatherParams::new(target)))
+                    }
+                    SavedAction::AttackGround(position) => {
+                        Some(Action::AttackGround(AttackGroundParams::new(position)))
+                    }
+                    SavedAction::Restore(player_id, index) => {
+                        target(player_id, index).map(|target| Action::Restore(RestoreParams::new(target)))
+                    }
+                    SavedAction::Explore => Some(Action::Explore),
+                    SavedAction::Garrison(player_id, index) => {
+                        target(player_id, index).map(|target| Action::Garrison(GarrisonParams::new(target)))
+                    }
+                })
+                .collect();
+            action_queues.insert(entity, ActionQueueComponent::with_orders(orders));
+        }
+        if cooldowns.get(entity).is_some() {
+            cooldowns.insert(entity, CooldownComponent::with_cooldowns(saved_unit.cooldowns.clone()));
+        }
+    }
+}
+
+impl SaveState {
+    /// The save state as the contents of a TOML file
+    pub fn to_toml(&self) -> String {
+        let mut table = Table::new();
+        table.insert("tick".into(), Value::Integer(self.tick as i64));
+        // The random state is kept bit for bit, which TOML only has signed integers for
+        table.insert("random_seed".into(), Value::Integer(self.random_seed as i64));
+        let streams = self.random_streams.iter().map(|state| Value::Integer(*state as i64)).collect();
+        table.insert("random_streams".into(), Value::Array(streams));
+
+        let mut market_table = Table::new();
+        for &(resource_type, price) in &self.market_prices {
+            let saved_resource = SAVED_RESOURCES.iter().find(|&&(saved_type, _)| saved_type == resource_type);
+            if let Some(&(_, key)) = saved_resource {
+                market_table.insert(key.into(), Value::Integer(price.scaled));
+            }
+        }
+        table.insert("market".into(), Value::Table(market_table));
+
+        let players = self.players
+            .iter()
+            .map(|player| {
+                let mut player_table = Table::new();
+                player_table.insert("player".into(), Value::Integer(*player.player_id as i64));
+                for (amount, &(_, key)) in player.stockpile.iter().zip(SAVED_RESOURCES.iter()) {
+                    player_table.insert(key.into(), Value::Integer(amount.scaled));
+                }
+                let researched = player.researched.iter().map(|id| Value::Integer(**id as i64)).collect();
+                player_table.insert("researched".into(), Value::Array(researched));
+                if let Some((ref own_explored, ref explored)) = player.explored {
+                    player_table.insert("own_explored".into(), run_lengths(own_explored));
+                    player_table.insert("explored".into(), run_lengths(explored));
+                }
+                Value::Table(player_table)
+            })
+            .collect();
+        table.insert("players".into(), Value::Array(players));
+
+        let units = self.units
+            .iter()
+            .map(|unit| {
+                let mut unit_table = Table::new();
+                unit_table.insert("player".into(), Value::Integer(*unit.player_id as i64));
+                unit_table.insert("index".into(), Value::Integer(unit.index as i64));
+                unit_table.insert("unit".into(), Value::Integer(*unit.unit_id as i64));
+                unit_table.insert("frame".into(), Value::Integer(unit.frame as i64));
+                let optional = [("hit_points", unit.hit_points),
+                                ("resource", unit.resource_amount),
+                                ("construction", unit.construction_progress)];
+                for &(key, value) in &optional {
+                    if let Some(value) = value {
+                        unit_table.insert(key.into(), Value::Integer(value.scaled));
+                    }
+                }
+                if let Some(ref production) = unit.production {
+                    unit_table.insert("production".into(), Value::Table(production_table(production)));
+                }
+                if let Some(rally_point) = unit.rally_point {
+                    unit_table.insert("rally_point".into(), Value::Table(rally_point_table(rally_point)));
+                }
+                if !unit.orders.is_empty() {
+                    let orders = unit.orders.iter().map(|order| Value::Table(order_table(order))).collect();
+                    unit_table.insert("orders".into(), Value::Array(orders));
+                }
+                if !unit.cooldowns.is_empty() {
+                    let cooldowns = unit.cooldowns
+                        .iter()
+                        .map(|cooldown| {
+                            let mut cooldown_table = Table::new();
+                            cooldown_table.insert("name".into(), Value::String(cooldown.name.into()));
+                            cooldown_table.insert("remaining".into(),
+                                                  Value::Integer(cooldown.remaining.scaled));
+                            cooldown_table.insert("duration".into(),
+                                                  Value::Integer(cooldown.duration.scaled));
+                            Value::Table(cooldown_table)
+                        })
+                        .collect();
+                    unit_table.insert("cooldowns".into(), Value::Array(cooldowns));
+                }
+                Value::Table(unit_table)
+            })
+            .collect();
+        table.insert("units".into(), Value::Array(units));
+
+        let cheats = self.cheats
+            .iter()
+            .map(|record| {
+                let mut cheat_table = Table::new();
+                cheat_table.insert("tick".into(), Value::Integer(record.tick as i64));
+                cheat_table.insert("player".into(), Value::Integer(*record.request.player_id as i64));
+                cheat_table.insert("cheat".into(), Value::String(record.request.cheat.code().into()));
+                Value::Table(cheat_table)
+            })
+            .collect();
+        table.insert("cheats".into(), Value::Array(cheats));
+
+        let scripts = self.scripts
+            .iter()
+            .map(|&(ref path, ref fired)| {
+                let mut script_table = Table::new();
+                script_table.insert("path".into(), Value::String(path.clone()));
+                let fired = fired.iter().map(|rule| Value::String(rule.clone())).collect();
+                script_table.insert("fired".into(), Value::Array(fired));
+                Value::Table(script_table)
+            })
+            .collect();
+        table.insert("scripts".into(), Value::Array(scripts));
+
+        Value::Table(table).to_string()
+    }
+
+    pub fn from_toml(text: &str) -> Result<SaveState, String> {
+        let table = try!(toml::Parser::new(text).parse().ok_or_else(|| "not valid TOML".to_string()));
+        let mut state = SaveState::default();
+        state.tick = try!(unsigned(&table, "tick")) as usize;
+
+        // Saves from before the random state was kept start the streams over
+        if table.contains_key("random_seed") {
+            state.random_seed = try!(integer(&table, "random_seed")) as u64;
+        }
+        if let Some(streams) = table.get("random_streams") {
+            state.random_streams = match *streams {
+                Value::Array(ref streams) => {
+                    try!(streams.iter()
+                        .map(|stream| match *stream {
+                            Value::Integer(state) => Ok(state as u64),
+                            _ => Err("random_streams has an invalid state".to_string()),
+                        })
+                        .collect())
+                }
+                _ => return Err("random_streams has an invalid value".into()),
+            };
+        }
+
+        match table.get("market") {
+            Some(&Value::Table(ref market_table)) => {
+                for &(resource_type, key) in &SAVED_RESOURCES {
+                    if market_table.contains_key(key) {
+                        let price = Fixed { scaled: try!(integer(market_table, key)) };
+                        state.market_prices.push((resource_type, price));
+                    }
+                }
+            }
+            Some(_) => return Err("market has an invalid value".into()),
+            None => {}
+        }
+
+        for player_table in try!(tables(&table, "players")) {
+            let mut stockpile = [Fixed::from(0); 4];
+            for (amount, &(_, key)) in stockpile.iter_mut().zip(SAVED_RESOURCES.iter()) {
+                *amount = Fixed { scaled: try!(integer(player_table, key)) };
+            }
+            let researched: Vec<ResearchId> = match player_table.get("researched") {
+                Some(&Value::Array(ref ids)) => {
+                    try!(ids.iter()
+                        .map(|id| match *id {
+                            Value::Integer(id) if id >= 0 => Ok((id as usize).into()),
+                            _ => Err("researched has an invalid ID".to_string()),
+                        })
+                        .collect())
+                }
+                _ => return Err("a player has no researched list".into()),
+            };
+            let explored = if player_table.contains_key("explored") {
+                Some((try!(from_run_lengths(player_table, "own_explored")),
+                      try!(from_run_lengths(player_table, "explored"))))
+            } else {
+                None
+            };
+            state.players.push(SavedPlayer {
+                player_id: try!(player_id(player_table)),
+                stockpile: stockpile,
+                researched: researched,
+                explored: explored,
+            });
+        }
+
+        for unit_table in try!(tables(&table, "units")) {
+            let optional = |key: &str| match unit_table.get(key) {
+                Some(&Value::Integer(scaled)) => Ok(Some(Fixed { scaled: scaled })),
+                Some(_) => Err(format!("{} has an invalid value", key)),
+                None => Ok(None),
+            };
+            let mut orders = Vec::new();
+            for order_table in try!(tables(unit_table, "orders")) {
+                orders.push(try!(saved_order(order_table)));
+            }
+            let mut cooldowns = Vec::new();
+            for cooldown_table in try!(tables(unit_table, "cooldowns")) {
+                cooldowns.push(try!(saved_cooldown(cooldown_table)));
+            }
+            state.units.push(SavedUnit {
+                player_id: try!(player_id(unit_table)),
+                index: try!(unsigned(unit_table, "index")) as usize,
+                unit_id: (try!(unsigned(unit_table, "unit")) as usize).into(),
+                frame: try!(unsigned(unit_table, "frame")) as u16,
+                hit_points: try!(optional("hit_points")),
+                resource_amount: try!(optional("resource")),
+                construction_progress: try!(optional("construction")),
+                production: match unit_table.get("production") {
+                    Some(&Value::Table(ref production)) => Some(try!(saved_production(production))),
+                    Some(_) => return Err("production has an invalid value".into()),
+                    None => None,
+                },
+                rally_point: match unit_table.get("rally_point") {
+                    Some(&Value::Table(ref rally_point)) => Some(try!(saved_rally_point(rally_point))),
+                    Some(_) => return Err("rally_point has an invalid value".into()),
+                    None => None,
+                },
+                orders: orders,
+                cooldowns: cooldowns,
+            });
+        }
+
+        for cheat_table in try!(tables(&table, "cheats")) {
+            let cheat = match cheat_table.get("cheat") {
+                Some(&Value::String(ref code)) => {
+                    try!(Cheat::parse(code).ok_or_else(|| format!("unknown cheat {}", code)))
+                }
+                _ => return Err("a cheat has no code".into()),
+            };
+            state.cheats.push(CheatRecord {
+                tick: try!(unsigned(cheat_table, "tick")) as usize,
+                request: CheatRequest::new(try!(player_id(cheat_table)), cheat),
+            });
+        }
+
+        for script_table in try!(tables(&table, "scripts")) {
+            let path = match script_table.get("path") {
+                Some(&Value::String(ref path)) => path.clone(),
+                _ => return Err("a script has no path".into()),
+            };
+            let fired: Vec<String> = match script_table.get("fired") {
+                Some(&Value::Array(ref rules)) => {
+                    try!(rules.iter()
+                        .map(|rule| match *rule {
+                            Value::String(ref rule) => Ok(rule.clone()),
+                            _ => Err("fired has an invalid rule".to_string()),
+                        })
+                        .collect())
+                }
+                _ => return Err(format!("script {} has no fired list", path)),
+            };
+            state.scripts.push((path, fired));
+        }
+        Ok(state)
+    }
+}
+
+fn production_table(production: &SavedProductionQueue) -> Table {
+    let mut table = Table::new();
+    let queue = production.queue.iter().map(|id| Value::Integer(**id as i64)).collect();
+    table.insert("queue".into(), Value::Array(queue));
+    table.insert("progress".into(), Value::Integer(production.progress.scaled));
+    if let Some(research_id) = production.research {
+        table.insert("research".into(), Value::Integer(*research_id as i64));
+    }
+    table.insert("research_progress".into(),
+                 Value::Integer(production.research_progress.scaled));
+    table.insert("repeat".into(), Value::Boolean(production.repeat));
+    if let Some(unit_id) = production.last_trained {
+        table.insert("last_trained".into(), Value::Integer(*unit_id as i64));
+    }
+    table.insert("repeated".into(), Value::Integer(production.repeated_count as i64));
+    table
+}
+
+fn saved_production(table: &Table) -> Result<SavedProductionQueue, String> {
+    let queue = match table.get("queue") {
+        Some(&Value::Array(ref ids)) => {
+            try!(ids.iter()
+                .map(|id| match *id {
+                    Value::Integer(id) if id >= 0 => Ok((id as usize).into()),
+                    _ => Err("queue has an invalid ID".to_string()),
+                })
+                .collect())
+        }
+        _ => return Err("a production queue has no queue".into()),
+    };
+    let optional_id = |key: &str| if table.contains_key(key) {
+        unsigned(table, key).map(|id| Some(id as usize))
+    } else {
+        Ok(None)
+    };
+    Ok(SavedProductionQueue {
+        queue: queue,
+        progress: Fixed { scaled: try!(integer(table, "progress")) },
+        research: try!(optional_id("research")).map(|id| id.into()),
+        research_progress: Fixed { scaled: try!(integer(table, "research_progress")) },
+        repeat: match table.get("repeat") {
+            Some(&Value::Boolean(repeat)) => repeat,
+            _ => return Err("a production queue has no repeat".into()),
+        },
+        last_trained: try!(optional_id("last_trained")).map(|id| id.into()),
+        repeated_count: try!(unsigned(table, "repeated")),
+    })
+}
+
+fn rally_point_table(rally_point: SavedRallyPoint) -> Table {
+    let mut table = Table::new();
+    match rally_point {
+        SavedRallyPoint::Position(position) => {
+            table.insert("x".into(), Value::Integer(position.x.scaled));
+            table.insert("y".into(), Value::Integer(position.y.scaled));
+            table.insert("z".into(), Value::Integer(position.z.scaled));
+        }
+        SavedRallyPoint::Unit(player_id, index) => {
+            table.insert("player".into(), Value::Integer(*player_id as i64));
+            table.insert("index".into(), Value::Integer(index as i64));
+        }
+    }
+    table
+}
+
+fn saved_rally_point(table: &Table) -> Result<SavedRallyPoint, String> {
+    if table.contains_key("player") {
+        return Ok(SavedRallyPoint::Unit(try!(player_id(table)), try!(unsigned(table, "index")) as usize));
+    }
+    let coordinate = |key: &str| integer(table, key).map(|scaled| Fixed { scaled: scaled });
+    Ok(SavedRallyPoint::Position(Vector3::new(try!(coordinate("x")),
+                                              try!(coordinate("y")),
+                                              try!(coordinate("z")))))
+}
+
+fn order_table(order: &SavedAction) -> Table {
+    let mut table = Table::new();
+    let (action, target) = match *order {
+        SavedAction::MoveToPosition(ref path) => {
+            let path = path.iter().map(|&position| position_value(position)).collect();
+            table.insert("path".into(), Value::Array(path));
+            ("move", None)
+        }
+        SavedAction::Gather(player_id, index) => ("gather", Some((player_id, index))),
+        SavedAction::AttackGround(position) => {
+            table.insert("position".into(), position_value(position));
+            ("attack_ground", None)
+        }
+        SavedAction::Restore(player_id, index) => ("restore", Some((player_id, index))),
+        SavedAction::Explore => ("explore", None),
+        SavedAction::Garrison(player_id, index) => ("garrison", Some((player_id, index))),
+    };
+    table.insert("action".into(), Value::String(action.into()));
+    if let Some((player_id, index)) = target {
+        table.insert("player".into(), Value::Integer(*player_id as i64));
+        table.insert("index".into(), Value::Integer(index as i64));
+    }
+    table
+}
+
+fn saved_order(table: &Table) -> Result<SavedAction, String> {
+    let target = || -> Result<(PlayerId, usize), String> {
+        Ok((try!(player_id(table)), try!(unsigned(table, "index")) as usize))
+    };
+    match table.get("action") {
+        Some(&Value::String(ref action)) => {
+            match &action[..] {
+                "move" => {
+                    match table.get("path") {
+                        Some(&Value::Array(ref path)) => {
+                            let path = try!(path.iter().map(from_position_value).collect());
+                            Ok(SavedAction::MoveToPosition(path))
+                        }
+                        _ => Err("a move order has no path".into()),
+                    }
+                }
+                "gather" => target().map(|(player_id, index)| SavedAction::Gather(player_id, index)),
+                "attack_ground" => {
+                    match table.get("position") {
+                        Some(position) => from_position_value(position).map(SavedAction::AttackGround),
+                        None => Err("an attack ground order has no position".into()),
+                    }
+                }
+                "restore" => target().map(|(player_id, index)| SavedAction::Restore(player_id, index)),
+                "explore" => Ok(SavedAction::Explore),
+                "garrison" => target().map(|(player_id, index)| SavedAction::Garrison(player_id, index)),
+                _ => Err(format!("unknown order {}", action)),
+            }
+        }
+        _ => Err("an order has no action".into()),
+    }
+}
+
+fn saved_cooldown(table: &Table) -> Result<Cooldown, String> {
+    let name = match table.get("name") {
+        Some(&Value::String(ref name)) => {
+            try!(COOLDOWNS.iter()
+                .cloned()
+                .find(|known| *known == &name[..])
+                .ok_or_else(|| format!("unknown cooldown {}", name)))
+        }
+        _ => return Err("a cooldown has no name".into()),
+    };
+    Ok(Cooldown {
+        name: name,
+        remaining: Fixed { scaled: try!(integer(table, "remaining")) },
+        duration: Fixed { scaled: try!(integer(table, "duration")) },
+    })
+}
+
+fn position_value(position: Vector3) -> Value {
+    Value::Array(vec![Value::Integer(position.x.scaled),
+                      Value::Integer(position.y.scaled),
+                      Value::Integer(position.z.scaled)])
+}
+
+fn from_position_value(value: &Value) -> Result<Vector3, String> {
+    match *value {
+        Value::Array(ref coordinates) if coordinates.len() == 3 => {
+            let mut scaled = [0; 3];
+            for (scaled, coordinate) in scaled.iter_mut().zip(coordinates.iter()) {
+                *scaled = match *coordinate {
+                    Value::Integer(value) => value,
+                    _ => return Err("a position has an invalid coordinate".into()),
+                };
+            }
+            Ok(Vector3::new(Fixed { scaled: scaled[0] },
+                            Fixed { scaled: scaled[1] },
+                            Fixed { scaled: scaled[2] }))
+        }
+        _ => Err("an order has an invalid position".into()),
+    }
+}
+
+/// Tiles as the lengths of the runs of unexplored and explored tiles in turn, starting with
+/// unexplored ones, which keeps a whole map's worth down to a few numbers
+fn run_lengths(tiles: &[bool]) -> Value {
+    let mut runs = Vec::new();
+    let mut explored = false;
+    let mut length = 0;
+    for &tile in tiles {
+        if tile != explored {
+            runs.push(Value::Integer(length));
+            explored = tile;
+            length = 0;
+        }
+        length += 1;
+    }
+    runs.push(Value::Integer(length));
+    Value::Array(runs)
+}
+
+fn from_run_lengths(table: &Table, key: &str) -> Result<Vec<bool>, String> {
+    let runs = match table.get(key) {
+        Some(&Value::Array(ref runs)) => runs,
+        Some(_) => return Err(format!("{} has an invalid value", key)),
+        None => return Err(format!("no {}", key)),
+    };
+    let mut tiles = Vec::new();
+    for (index, run) in runs.iter().enumerate() {
+        match *run {
+            Value::Integer(length) if length >= 0 => {
+                tiles.extend(iter::repeat(index % 2 == 1).take(length as usize));
+            }
+            _ => return Err(format!("{} has an invalid run", key)),
+        }
+    }
+    Ok(tiles)
+}
+
+fn integer(table: &Table, key: &str) -> Result<i64, String> {
+    match table.get(key) {
+        Some(&Value::Integer(value)) => Ok(value),
+        Some(_) => Err(format!("{} has an invalid value", key)),
+        None => Err(format!("no {}", key)),
+    }
+}
+
+fn unsigned(table: &Table, key: &str) -> Result<u32, String> {
+    let value = try!(integer(table, key));
+    if value < 0 || value > u32::max_value() as i64 {
+        return Err(format!("{} is out of range", key));
+    }
+    Ok(value as u32)
+}
+
+fn player_id(table: &Table) -> Result<PlayerId, String> {
+    let value = try!(unsigned(table, "player"));
+    if value > u8::max_value() as u32 {
+        return Err("player is out of range".into());
+    }
+    Ok((value as usize).into())
+}
+
+fn tables<'a>(table: &'a Table, key: &str) -> Result<Vec<&'a Table>, String> {
+    match table.get(key) {
+        Some(&Value::Array(ref values)) => {
+            values.iter()
+                .map(|value| match *value {
+                    Value::Table(ref table) => Ok(table),
+                    _ => Err(format!("{} has an invalid entry", key)),
+                })
+                .collect()
+        }
+        Some(_) => Err(format!("{} has an invalid value", key)),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::component::{ATTACK_COOLDOWN, Cooldown, SavedProductionQueue};
+    use ecs::resource::{Cheat, CheatRecord, CheatRequest};
+    use super::{SaveState, SavedAction, SavedPlayer, SavedRallyPoint, SavedUnit};
+    use types::{Fixed, Vector3};
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let path = vec![Vector3::new(1.into(), fixed_const!(2.5), 0.into()),
+                        Vector3::new(3.into(), 4.into(), 0.into())];
+        let target = Vector3::new(5.into(), 6.into(), 0.into());
+        let state = SaveState {
+            tick: 5400,
+            random_seed: 0xdead_beef_0000_0001,
+            random_streams: vec![1, 0xffff_ffff_ffff_fffe, 3, 4, 5],
+            market_prices: vec![(ResourceType::Food, 103.into()), (ResourceType::Stone, 20.into())],
+            players: vec![SavedPlayer {
+                              player_id: 1.into(),
+                              stockpile: [Fixed::from(200) / 3.into(), 150.into(), 0.into(), 75.into()],
+                              researched: vec![101.into(), 102.into()],
+                              explored: Some((vec![false, true, true, false],
+                                              vec![true, true, true, false])),
+                          }],
+            units: vec![SavedUnit {
+                            player_id: 0.into(),
+                            index: 3,
+                            unit_id: 59.into(),
+                            frame: 0,
+                            hit_points: None,
+                            resource_amount: Some(Fixed::from(125) / 7.into()),
+                            construction_progress: None,
+                            production: None,
+                            rally_point: None,
+                            orders: Vec::new(),
+                            cooldowns: Vec::new(),
+                        },
+                        SavedUnit {
+                            player_id: 1.into(),
+                            index: 0,
+                            unit_id: 117.into(),
+                            frame: 4,
+                            hit_points: Some(12.into()),
+                            resource_amount: None,
+                            construction_progress: Some(Fixed::from(1) / 3.into()),
+                            production: None,
+                            rally_point: Some(SavedRallyPoint::Unit(0.into(), 3)),
+                            orders: vec![SavedAction::MoveToPosition(path),
+                                         SavedAction::Gather(0.into(), 3),
+                                         SavedAction::AttackGround(target),
+                                         SavedAction::Explore,
+                                         SavedAction::Garrison(1.into(), 1)],
+                            cooldowns: vec![Cooldown {
+                                                name: ATTACK_COOLDOWN,
+                                                remaining: Fixed::from(2) / 3.into(),
+                                                duration: 2.into(),
+                                            }],
+                        },
+                        SavedUnit {
+                            player_id: 1.into(),
+                            index: 1,
+                            unit_id: 109.into(),
+                            frame: 0,
+                            hit_points: Some(600.into()),
+                            resource_amount: None,
+                            construction_progress: None,
+                            production: Some(SavedProductionQueue {
+                                queue: vec![83.into(), 83.into()],
+                                progress: Fixed::from(10) / 3.into(),
+                                research: Some(22.into()),
+                                research_progress: 5.into(),
+                                repeat: true,
+                                last_trained: Some(83.into()),
+                                repeated_count: 2,
+                            }),
+                            rally_point: Some(SavedRallyPoint::Position(Vector3::new(fixed_const!(4.5),
+                                                                                     7.into(),
+                                                                                     0.into()))),
+                            orders: Vec::new(),
+                            cooldowns: Vec::new(),
+                        }],
+            cheats: vec![CheatRecord {
+                             tick: 120,
+                             request: CheatRequest::new(1.into(), Cheat::GrantResource(ResourceType::Gold)),
+                         },
+                         CheatRecord {
+                             tick: 300,
+                             request: CheatRequest::new(2.into(), Cheat::InstantBuild),
+                         }],
+            scripts: vec![("scenarios/c1s1.rhai".into(), vec!["reinforcements".into()])],
+        };
+        assert_eq!(Ok(state.clone()), SaveState::from_toml(&state.to_toml()));
+        assert!(SaveState::from_toml("tick = -1").is_err());
+        assert!(SaveState::from_toml("tick = 0\n[[units]]\nplayer = 1").is_err());
+        assert!(SaveState::from_toml("tick = 0\n[[units]]\nplayer = 1\nindex = 0\nunit = 83\nframe = 0\n\
+                                      [[units.orders]]\naction = \"dance\"")
+            .is_err());
+    }
+
+    #[test]
+    fn test_older_save_states_still_load() {
+        let state = SaveState::from_toml("tick = 60\n[[players]]\nplayer = 1\nfood = 0\nwood = 0\n\
+                                          stone = 0\ngold = 0\nresearched = []")
+            .unwrap();
+        assert!(state.random_streams.is_empty());
+        assert!(state.market_prices.is_empty());
+        assert!(state.cheats.is_empty());
+        assert_eq!(None, state.players[0].explored);
+
+        let state = SaveState::from_toml("tick = 60\n[[units]]\nplayer = 1\nindex = 0\nunit = 83\n\
+                                          frame = 0")
+            .unwrap();
+        assert!(state.units[0].orders.is_empty());
+        assert!(state.units[0].cooldowns.is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::component::*;
+use ecs::resource::{Cheats, Diplomacy, GameSettings, GameSpeed, Players, RANDOM_STREAMS, RandomNumbers,
+                    Technologies};
+use specs::{self, Join};
+use std::fmt::Write;
+
+/// Describes the simulation state of the world in plain text: the lobby settings, every player,
+/// and every unit along with its transform, velocity, and actions. Crash reports include this
+/// so that the state a crash happened in can be inspected and recreated.
+pub fn describe_world(world: &specs::World, tick: usize) -> String {
+    let mut out = String::new();
+    writeln!(out, "tick {}", tick).unwrap();
+    writeln!(out, "{:?}", *world.read_resource::<GameSettings>()).unwrap();
+    writeln!(out, "game speed {:?}", world.read_resource::<GameSpeed>().multiplier()).unwrap();
+    writeln!(out, "cheat history {:?}", world.read_resource::<Cheats>().history()).unwrap();
+    let random = world.read_resource::<RandomNumbers>();
+    for stream in &RANDOM_STREAMS {
+        writeln!(out, "random stream {:?} at {:#x}", stream, random.stream_state(*stream)).unwrap();
+    }
+
+    let players = world.read_resource::<Players>();
+    let diplomacy = world.read_resource::<Diplomacy>();
+    let technologies = world.read_resource::<Technologies>();
+    for player_id in players.player_ids() {
+        if let Some(player) = players.player(player_id) {
+            writeln!(out,
+                     "player {:?} ({}) {:?} computer={} {:?}",
+                     player.player_id,
+                     player.name,
+                     player.civ_id,
+                     player.computer,
+                     player.stockpile)
+                .unwrap();
+            writeln!(out, "    researched {:?}", technologies.researched(player_id)).unwrap();
+            for other_id in players.player_ids() {
+                if other_id != player_id {
+                    writeln!(out, "    towards {:?}: {:?}", other_id, diplomacy.stance(player_id, other_id))
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    let entities = world.entities();
+    let units = world.read::<UnitComponent>();
+    let transforms = world.read::<TransformComponent>();
+    let velocities = world.read::<VelocityComponent>();
+    let action_queues = world.read::<ActionQueueComponent>();
+    for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+        writeln!(out,
+                 "entity {}: {:?} {:?} {:?} at {:?} rotation {:?}",
+                 entity.get_id(),
+                 unit.player_id,
+                 unit.civilization_id,
+                 unit.unit_id,
+                 transform.position(),
+                 transform.rotation)
+            .unwrap();
+        if let Some(velocity) = velocities.get(entity) {
+            writeln!(out, "    velocity {:?}", velocity.velocity).unwrap();
+        }
+        if let Some(action_queue) = action_queues.get(entity) {
+            writeln!(out, "    {:?}", action_queue).unwrap();
+        }
+    }
+    out
+}
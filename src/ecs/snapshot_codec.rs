@@ -0,0 +1,199 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Compression for world snapshots. Snapshots are mostly the same few line layouts repeated
+//! for every entity, so they're compressed with a zstd dictionary trained on those lines, and in
+//! chunks so that a large snapshot can be spread over several frames instead of stalling one.
+
+use std::io::{self, Read, Write};
+use std::mem;
+use zstd;
+
+/// How much of the snapshot gets compressed or decompressed by each step
+pub const SNAPSHOT_CHUNK_SIZE: usize = 64 * 1024;
+
+const COMPRESSION_LEVEL: i32 = 3;
+const DICTIONARY_SIZE: usize = 16 * 1024;
+
+/// Training needs a reasonable number of samples; below this, the snapshot itself is used as
+/// a raw dictionary instead
+const MIN_TRAINING_SAMPLES: usize = 8;
+
+/// Trains a dictionary on the entity descriptions in `snapshot`. Each entity's lines, as written
+/// by `describe_world`, make up one sample.
+pub fn train_snapshot_dictionary(snapshot: &str) -> Vec<u8> {
+    let mut samples: Vec<String> = Vec::new();
+    for line in snapshot.lines() {
+        if line.starts_with(' ') {
+            if let Some(sample) = samples.last_mut() {
+                sample.push_str(line);
+                sample.push('\n');
+                continue;
+            }
+        }
+        samples.push(format!("{}\n", line));
+    }
+
+    if samples.len() >= MIN_TRAINING_SAMPLES {
+        if let Ok(dictionary) = zstd::dict::from_samples(&samples, DICTIONARY_SIZE) {
+            return dictionary;
+        }
+    }
+    // zstd treats a dictionary without its magic number as raw content to match against
+    let end = snapshot.len().min(DICTIONARY_SIZE);
+    snapshot.as_bytes()[..end].to_vec()
+}
+
+/// Compresses a snapshot one chunk per `step`
+pub struct SnapshotEncoder {
+    snapshot: Vec<u8>,
+    position: usize,
+    chunk_size: usize,
+    encoder: zstd::stream::Encoder<Vec<u8>>,
+}
+
+impl SnapshotEncoder {
+    pub fn new(snapshot: String, dictionary: &[u8], chunk_size: usize) -> io::Result<SnapshotEncoder> {
+        Ok(SnapshotEncoder {
+            snapshot: snapshot.into_bytes(),
+            position: 0,
+            chunk_size: chunk_size,
+            encoder: try!(zstd::stream::Encoder::with_dictionary(Vec::new(), COMPRESSION_LEVEL, dictionary)),
+        })
+    }
+
+    /// Compresses the next chunk, and returns true once the whole snapshot has been compressed
+    pub fn step(&mut self) -> io::Result<bool> {
+        let end = (self.position + self.chunk_size).min(self.snapshot.len());
+        try!(self.encoder.write_all(&self.snapshot[self.position..end]));
+        self.position = end;
+        Ok(self.is_done())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position == self.snapshot.len()
+    }
+
+    /// Compresses whatever is left and returns the compressed snapshot
+    pub fn finish(mut self) -> io::Result<Vec<u8>> {
+        while !try!(self.step()) {}
+        self.encoder.finish()
+    }
+}
+
+/// Decompresses a snapshot one chunk per `step`, as its compressed bytes come in from `reader`
+pub struct SnapshotDecoder<R: Read> {
+    decoder: zstd::stream::Decoder<R>,
+    snapshot: Vec<u8>,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<R: Read> SnapshotDecoder<R> {
+    pub fn new(reader: R, dictionary: &[u8], chunk_size: usize) -> io::Result<SnapshotDecoder<R>> {
+        Ok(SnapshotDecoder {
+            decoder: try!(zstd::stream::Decoder::with_dictionary(reader, dictionary)),
+            snapshot: Vec::new(),
+            chunk_size: chunk_size,
+            done: false,
+        })
+    }
+
+    /// Decompresses up to a chunk more, and returns true once the end of the snapshot is reached
+    pub fn step(&mut self) -> io::Result<bool> {
+        if !self.done {
+            let start = self.snapshot.len();
+            self.snapshot.resize(start + self.chunk_size, 0);
+            let mut filled = 0;
+            while filled < self.chunk_size {
+                match self.decoder.read(&mut self.snapshot[start + filled..]) {
+                    Ok(0) => {
+                        self.done = true;
+                        break;
+                    }
+                    Ok(count) => filled += count,
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            self.snapshot.truncate(start + filled);
+        }
+        Ok(self.done)
+    }
+
+    /// Decompresses whatever is left and returns the snapshot
+    pub fn finish(mut self) -> io::Result<String> {
+        while !try!(self.step()) {}
+        let snapshot = mem::replace(&mut self.snapshot, Vec::new());
+        String::from_utf8(snapshot).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SnapshotDecoder, SnapshotEncoder, train_snapshot_dictionary};
+
+    fn sample_snapshot(entity_count: usize) -> String {
+        let mut snapshot = String::from("tick 300\ngame speed 1.5\n");
+        for id in 0..entity_count {
+            snapshot.push_str(&format!("entity {}: PlayerId({}) CivilizationId(1) UnitId(83) at \
+                                        Vector3 {{ x: {}, y: {}, z: 0 }} rotation 0\n",
+                                       id,
+                                       id % 4,
+                                       id * 7 % 100,
+                                       id * 13 % 100));
+            if id % 3 == 0 {
+                snapshot.push_str(&format!("    velocity Vector3 {{ x: 0.{}, y: 0, z: 0 }}\n", id));
+            }
+        }
+        snapshot
+    }
+
+    #[test]
+    fn test_round_trip_in_chunks() {
+        let snapshot = sample_snapshot(2000);
+        let dictionary = train_snapshot_dictionary(&sample_snapshot(200));
+
+        let mut encoder = SnapshotEncoder::new(snapshot.clone(), &dictionary, 1024).unwrap();
+        let mut steps = 1;
+        while !encoder.step().unwrap() {
+            steps += 1;
+        }
+        assert!(steps > 1);
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < snapshot.len() / 4);
+
+        let mut decoder = SnapshotDecoder::new(&compressed[..], &dictionary, 1024).unwrap();
+        assert!(!decoder.step().unwrap());
+        assert_eq!(snapshot, decoder.finish().unwrap());
+    }
+
+    #[test]
+    fn test_small_snapshots_fall_back_to_a_raw_dictionary() {
+        let snapshot = sample_snapshot(2);
+        let dictionary = train_snapshot_dictionary(&snapshot);
+        assert_eq!(snapshot.as_bytes(), &dictionary[..]);
+
+        let compressed = SnapshotEncoder::new(snapshot.clone(), &dictionary, 16).unwrap().finish().unwrap();
+        let decoder = SnapshotDecoder::new(&compressed[..], &dictionary, 16).unwrap();
+        assert_eq!(snapshot, decoder.finish().unwrap());
+    }
+}
@@ -0,0 +1,176 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::component::*;
+use ecs::resource::{Notifications, OccupiedTiles, PathFinder, PathScheduler, SpawnRequest, Terrain,
+                    UnitSpawner};
+use specs::{self, Join};
+use super::super::System;
+use types::{Fixed, Vector3};
+use util::steering::{WAYPOINT_THRESHOLD, next_direction, within};
+
+/// Used for shots whose unit doesn't say how fast it flies
+const DEFAULT_PROJECTILE_SPEED: Fixed = fixed_const!(5);
+
+const OUT_OF_RANGE: &'static str = "A siege unit can't get in range of where it was told to attack";
+const TOO_CLOSE: &'static str = "A siege unit is too close to fire where it was told to attack";
+
+/// Units with no reload time in the game data still wait this long between shots
+const MIN_RELOAD_SECONDS: Fixed = fixed_const!(0.5);
+
+/// Walks siege units into range of the spot they were told to attack, then keeps firing at it,
+/// facing it with their attack animation, for as long as the order stands. Shots are spawned as
+/// projectiles that the ProjectileSystem flies to the spot. Units that are too close to fire, or
+/// that can't get into range, give up with a notification to their player.
+pub struct AttackGroundActionSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl AttackGroundActionSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> AttackGroundActionSystem {
+        AttackGroundActionSystem { empires: empires }
+    }
+}
+
+impl System for AttackGroundActionSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(units: UnitComponent),
+            mut components(attack_grounds: AttackGroundActionComponent),
+            mut components(cooldowns: CooldownComponent),
+            mut components(transforms: TransformComponent),
+            mut components(velocities: VelocityComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(action_queues: ActionQueueComponent),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(notifications: Notifications),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+
+        let items = (&entities,
+                     &units,
+                     &mut attack_grounds,
+                     &mut cooldowns,
+                     &mut transforms,
+                     &mut velocities,
+                     &mut graphics,
+                     &mut action_queues);
+        for (entity, unit, attack, cooldown, transform, velocity, graphic, action_queue) in items.iter() {
+            let unit_info = unit.db(&self.empires);
+            let position = *transform.position();
+            let max_range: Fixed = unit_info.max_range().into();
+            let min_range: Fixed = unit_info.min_range().into();
+            let mut give_up = None;
+            let mut graphic_id = unit_info.standing_graphic;
+
+            if min_range > 0.into() && within(&position, &attack.target, min_range) {
+                give_up = Some(TOO_CLOSE);
+            } else if !within(&position, &attack.target, max_range) {
+                if attack.path.is_empty() {
+                    attack.path = path_scheduler.find_path(&*path_finder,
+                                                           &*terrain,
+                                                           &*occupied_tiles,
+                                                           entity.get_id(),
+                                                           &position,
+                                                           &attack.target,
+                                                           unit_info.terrain_restriction);
+                    match attack.path.last() {
+                        Some(end) if !within(end, &position, WAYPOINT_THRESHOLD) => {}
+                        _ => give_up = Some(OUT_OF_RANGE),
+                    }
+                }
+                if give_up.is_none() {
+                    match (next_direction(&mut attack.path, &position), unit_info.motion_params.as_ref()) {
+                        (Some(direction), Some(params)) => {
+                            let speed: Fixed = params.speed.into();
+                            velocity.velocity = direction * speed;
+                            graphic_id = params.walking_graphics[0].or(graphic_id);
+                        }
+                        // A unit that can't move gets no closer
+                        (Some(_), None) => give_up = Some(OUT_OF_RANGE),
+                        // Waits for a new path next update
+                        (None, _) => stop(velocity),
+                    }
+                }
+            } else {
+                stop(velocity);
+                attack.path.clear();
+                transform.rotation = {
+                    // Only used for drawing, like the rotation from the VelocitySystem
+                    let x: f32 = (attack.target.x - position.x).into();
+                    let y: f32 = (attack.target.y - position.y).into();
+                    y.atan2(x).into()
+                };
+                graphic_id = unit_info.attack_graphic().or(graphic_id);
+
+                if cooldown.is_ready(ATTACK_COOLDOWN) {
+                    let reload_time: Fixed = unit_info.reload_time().into();
+                    cooldown.start(ATTACK_COOLDOWN,
+                                   if reload_time > MIN_RELOAD_SECONDS {
+                                       reload_time
+                                   } else {
+                                       MIN_RELOAD_SECONDS
+                                   });
+                    if let Some(projectile_unit_id) = unit_info.projectile_unit_id() {
+                        let projectile_info = self.empires.unit(unit.civilization_id, projectile_unit_id);
+                        let speed = projectile_info.motion_params
+                            .as_ref()
+                            .map(|params| Fixed::from(params.speed))
+                            .and_then(|speed| if speed > 0.into() { Some(speed) } else { None })
+                            .unwrap_or(DEFAULT_PROJECTILE_SPEED);
+                        let mut request = SpawnRequest::new(unit.player_id,
+                                                            unit.civilization_id,
+                                                            projectile_unit_id,
+                                                            position);
+                        request.rotation = transform.rotation;
+                        request.projectile = Some(ProjectileComponent {
+                            player_id: unit.player_id,
+                            origin: position,
+                            target: attack.target,
+                            speed: speed,
+                            attacks: unit_info.attacks().to_vec(),
+                            blast_radius: unit_info.blast_width().into(),
+                        });
+                        unit_spawner.queue_spawn(request);
+                    }
+                }
+            }
+
+            if let Some(text) = give_up {
+                stop(velocity);
+                graphic_id = unit_info.standing_graphic;
+                action_queue.mark_current_done();
+                notifications.notify(unit.player_id, text.into());
+            }
+            if graphic.graphic_id != graphic_id {
+                graphic.set_graphic(graphic_id);
+            }
+        }
+    }
+}
+
+fn stop(velocity: &mut VelocityComponent) {
+    velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+}
@@ -0,0 +1,158 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::component::*;
+use ecs::resource::{FogOfWar, Notifications, OccupiedTiles, PathFinder, PathScheduler, Terrain};
+use specs::{self, Join};
+use super::super::System;
+use types::{Fixed, Vector3};
+use util::steering::{WAYPOINT_THRESHOLD, next_direction, within};
+
+/// How far around an unexplored tile, in tiles, counts towards how big its region is
+const REGION_RADIUS: i32 = 4;
+
+/// How many unexplored tiles around a tile make it worth heading for over a nearer one
+const MIN_REGION_TILES: usize = 24;
+
+const NOTHING_LEFT: &'static str = "There's nothing left on the map for a unit to explore";
+
+/// Sends exploring units to the nearest big unexplored region, as found by searching outward
+/// through the player's explored map, and picks a new one as soon as the one it was heading
+/// for gets explored, by the unit or anyone sharing vision with its player. Regions the unit
+/// can't find a way to are left alone. The unit stops once nothing is left to explore.
+pub struct ExploreActionSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl ExploreActionSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> ExploreActionSystem {
+        ExploreActionSystem { empires: empires }
+    }
+}
+
+impl System for ExploreActionSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(action_queues: ActionQueueComponent),
+            mut components(explores: ExploreActionComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(velocities: VelocityComponent),
+            resource(fog_of_war: FogOfWar),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(notifications: Notifications),
+            mut resource(path_scheduler: PathScheduler),
+        ]);
+
+        let items = (&entities,
+                     &transforms,
+                     &units,
+                     &mut explores,
+                     &mut velocities,
+                     &mut graphics,
+                     &mut action_queues);
+        for (entity, transform, unit, explore, velocity, graphic, action_queue) in items.iter() {
+            let unit_info = unit.db(&self.empires);
+            let position = *transform.position();
+            let mut graphic_id = unit_info.standing_graphic;
+
+            let explored = explore.target
+                .map_or(false, |(row, col)| fog_of_war.is_explored(unit.player_id, row, col));
+            if explored {
+                explore.target = None;
+                explore.path.clear();
+            }
+
+            if explore.target.is_none() {
+                let unreachable = &explore.unreachable;
+                let near_unreachable = |row: i32, col: i32| {
+                    unreachable.iter().any(|&(unreachable_row, unreachable_col)| {
+                        (row - unreachable_row).abs() <= REGION_RADIUS &&
+                        (col - unreachable_col).abs() <= REGION_RADIUS
+                    })
+                };
+                explore.target = fog_of_war.nearest_unexplored_region(unit.player_id,
+                                                                      position.y.into(),
+                                                                      position.x.into(),
+                                                                      REGION_RADIUS,
+                                                                      MIN_REGION_TILES,
+                                                                      near_unreachable);
+                if explore.target.is_none() {
+                    stop(velocity);
+                    if graphic.graphic_id != graphic_id {
+                        graphic.set_graphic(graphic_id);
+                    }
+                    action_queue.mark_current_done();
+                    notifications.notify(unit.player_id, NOTHING_LEFT.into());
+                    continue;
+                }
+            }
+
+            if explore.path.is_empty() {
+                let (row, col) = explore.target.unwrap();
+                let destination = tile_center(row, col);
+                explore.path = path_scheduler.find_path(&*path_finder,
+                                                        &*terrain,
+                                                        &*occupied_tiles,
+                                                        entity.get_id(),
+                                                        &position,
+                                                        &destination,
+                                                        unit_info.terrain_restriction);
+                let gets_closer = match explore.path.last() {
+                    Some(end) => !within(end, &position, WAYPOINT_THRESHOLD),
+                    None => false,
+                };
+                if !gets_closer {
+                    // Tries somewhere else next update
+                    explore.unreachable.push((row, col));
+                    explore.target = None;
+                    explore.path.clear();
+                }
+            }
+
+            match (next_direction(&mut explore.path, &position), unit_info.motion_params.as_ref()) {
+                (Some(direction), Some(params)) => {
+                    let speed: Fixed = params.speed.into();
+                    velocity.velocity = direction * speed;
+                    graphic_id = params.walking_graphics[0].or(graphic_id);
+                }
+                // Waits for a new path or target next update
+                _ => stop(velocity),
+            }
+            if graphic.graphic_id != graphic_id {
+                graphic.set_graphic(graphic_id);
+            }
+        }
+    }
+}
+
+fn tile_center(row: i32, col: i32) -> Vector3 {
+    let half: Fixed = fixed_const!(0.5);
+    Vector3::new(Fixed::from(col) + half, Fixed::from(row) + half, 0.into())
+}
+
+fn stop(velocity: &mut VelocityComponent) {
+    velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+}
@@ -0,0 +1,218 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::component::*;
+use ecs::resource::{Notifications, OccupiedTiles, PathFinder, PathScheduler, Terrain};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use specs::{self, Join};
+use std::collections::HashMap;
+use super::super::System;
+use types::{Fixed, Vector3};
+use util::steering::{WAYPOINT_THRESHOLD, next_direction, within};
+use util::unit;
+
+/// How close a unit has to get to the edge of a building or transport to go in
+const REACH: Fixed = fixed_const!(0.5);
+
+/// How far from the edge of a building or transport units come back out
+const EXIT_GAP: Fixed = fixed_const!(0.5);
+
+const UNREACHABLE: &'static str = "A unit can't reach what it was told to go inside";
+const FULL: &'static str = "There's no room left in what a unit was told to go inside";
+
+/// Walks units to the building or transport they were told to garrison in and takes them off
+/// the map once they're inside, as long as it's their own and it has room. They stay in until
+/// they're told to do something else, or what they're in is destroyed, and then come back out
+/// beside it.
+pub struct GarrisonActionSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl GarrisonActionSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> GarrisonActionSystem {
+        GarrisonActionSystem { empires: empires }
+    }
+}
+
+impl System for GarrisonActionSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(constructions: ConstructionComponent),
+            components(units: UnitComponent),
+            mut components(action_queues: ActionQueueComponent),
+            mut components(garrisons: GarrisonActionComponent),
+            mut components(garrisoned: GarrisonedComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(selected_units: SelectedUnitComponent),
+            mut components(transforms: TransformComponent),
+            mut components(velocities: VelocityComponent),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(grid: GridPartition),
+            mut resource(notifications: Notifications),
+            mut resource(path_scheduler: PathScheduler),
+        ]);
+
+        // Count who's staying in each building, and let out everyone who isn't
+        let mut occupants: HashMap<specs::Index, usize> = HashMap::new();
+        let mut leaving = Vec::new();
+        for (entity, inside) in (&entities, &garrisoned).iter() {
+            let told_to_stay = garrisons.get(entity)
+                .map_or(false, |garrison| garrison.target == inside.building);
+            if told_to_stay && transforms.get(inside.building).is_some() {
+                *occupants.entry(inside.building.get_id()).or_insert(0) += 1;
+            } else {
+                leaving.push(entity);
+            }
+        }
+        for entity in leaving {
+            let inside = garrisoned.remove(entity).unwrap();
+            let mut transform = inside.transform;
+            if let (Some(building_transform), Some(building)) = (transforms.get(inside.building),
+                                                                 units.get(inside.building)) {
+                let building_position = *building_transform.position();
+                let size_y: Fixed = building.db(&self.empires).collision_size_y.into();
+                let exit = Vector3::new(building_position.x,
+                                        building_position.y + size_y + EXIT_GAP,
+                                        building_position.z);
+                transform = TransformComponent::new(exit, transform.rotation);
+            }
+            let position = *transform.position();
+            grid.update_entity(entity.get_id(), &Vector2::new(position.x.into(), position.y.into()));
+            transforms.insert(entity, transform);
+
+            // A unit whose building was destroyed is done sheltering
+            if garrisons.get(entity).is_some() {
+                if let Some(action_queue) = action_queues.get_mut(entity) {
+                    action_queue.mark_current_done();
+                }
+            }
+        }
+
+        let mut entering = Vec::new();
+        {
+            let items = (&entities,
+                         &units,
+                         &transforms,
+                         &mut garrisons,
+                         &mut velocities,
+                         &mut graphics,
+                         &mut action_queues);
+            for (entity, unit, transform, garrison, velocity, graphic, action_queue) in items.iter() {
+                let unit_info = unit.db(&self.empires);
+                let position = *transform.position();
+                let mut graphic_id = unit_info.standing_graphic;
+                let mut give_up = None;
+
+                let target = garrison.target;
+                let building = match (transforms.get(target), units.get(target)) {
+                    (Some(target_transform), Some(target_unit)) => {
+                        Some((*target_transform.position(), target_unit))
+                    }
+                    _ => None,
+                };
+                let (target_position, target_info) = match building {
+                    Some((target_position, target_unit)) if target_unit.player_id == unit.player_id &&
+                                                            constructions.get(target).is_none() => {
+                        (target_position, target_unit.db(&self.empires))
+                    }
+                    // The building is gone, isn't theirs any more, or is still a foundation
+                    _ => {
+                        stop(velocity);
+                        if graphic.graphic_id != graphic_id {
+                            graphic.set_graphic(graphic_id);
+                        }
+                        action_queue.mark_current_done();
+                        continue;
+                    }
+                };
+
+                let size: Fixed = target_info.collision_size_x.max(target_info.collision_size_y).into();
+                if within(&position, &target_position, size + REACH) {
+                    stop(velocity);
+                    let occupants = occupants.entry(target.get_id()).or_insert(0);
+                    if *occupants < unit::garrison_capacity(target_info) {
+                        *occupants += 1;
+                        path_scheduler.cancel(entity.get_id());
+                        entering.push((entity, target));
+                    } else {
+                        give_up = Some(FULL);
+                    }
+                } else {
+                    if garrison.path.is_empty() {
+                        garrison.path = path_scheduler.find_path(&*path_finder,
+                                                                 &*terrain,
+                                                                 &*occupied_tiles,
+                                                                 entity.get_id(),
+                                                                 &position,
+                                                                 &target_position,
+                                                                 unit_info.terrain_restriction);
+                        match garrison.path.last() {
+                            Some(end) if !within(end, &position, WAYPOINT_THRESHOLD) => {}
+                            _ => give_up = Some(UNREACHABLE),
+                        }
+                    }
+                    if give_up.is_none() {
+                        let direction = next_direction(&mut garrison.path, &position);
+                        match (direction, unit_info.motion_params.as_ref()) {
+                            (Some(direction), Some(params)) => {
+                                let speed: Fixed = params.speed.into();
+                                velocity.velocity = direction * speed;
+                                graphic_id = params.walking_graphics[0].or(graphic_id);
+                            }
+                            (Some(_), None) => give_up = Some(UNREACHABLE),
+                            // Waits for a new path next update
+                            (None, _) => stop(velocity),
+                        }
+                    }
+                }
+
+                if let Some(text) = give_up {
+                    stop(velocity);
+                    graphic_id = unit_info.standing_graphic;
+                    action_queue.mark_current_done();
+                    notifications.notify(unit.player_id, text.into());
+                }
+                if graphic.graphic_id != graphic_id {
+                    graphic.set_graphic(graphic_id);
+                }
+            }
+        }
+
+        for (entity, building) in entering {
+            if let Some(transform) = transforms.remove(entity) {
+                selected_units.remove(entity);
+                garrisoned.insert(entity,
+                                  GarrisonedComponent {
+                                      building: building,
+                                      transform: transform,
+                                  });
+            }
+        }
+    }
+}
+
+fn stop(velocity: &mut VelocityComponent) {
+    velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+}
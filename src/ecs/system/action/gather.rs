@@ -0,0 +1,961 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResourceType, UnitClass};
+use ecs::component::*;
+use ecs::resource::{CombatEvent, CombatLog, DepositEvent, DepositLog, ElevationModifier, Notifications,
+                    OccupiedTiles, PathFinder, PathScheduler, Player, Players, Terrain, UnitIndex, UnitTag};
+use ecs::resource::path_finder::Path;
+use identifier::{GraphicId, PlayerId, UnitId, UnitTerrainRestrictionId};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use specs::{self, Join};
+use std::collections::{BTreeSet, HashMap};
+use super::super::System;
+use types::{Fixed, Vector3};
+use util::steering::{WAYPOINT_THRESHOLD, next_direction, within};
+use util::unit;
+
+/// How close a gatherer has to get to the edge of a resource or drop site to use it
+const REACH: Fixed = fixed_const!(0.5);
+
+/// How much further a gatherer reaches out to something on terrain it can't walk on, like a
+/// villager fishing from the shore
+const SHORE_REACH: Fixed = fixed_const!(1);
+
+/// How long a gatherer stands at the drop site handing over what it carried
+const DEPOSIT_SECONDS: Fixed = fixed_const!(0.5);
+
+/// Something a gatherer walks up to: a resource or a drop site
+#[derive(Copy, Clone, Debug)]
+struct Target {
+    entity: specs::Entity,
+    position: Vector3,
+    /// How far the edge of the target is from its position
+    size: Fixed,
+    /// Whether it's a live animal, which hunters strike at from as far off as they can rather
+    /// than gather from
+    alive: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    resource_type: ResourceType,
+    class: UnitClass,
+    /// Whether it's an animal or a carcass, which hunters move on to
+    hunted: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct DropSite {
+    player_id: PlayerId,
+    unit_id: UnitId,
+}
+
+/// Where the gatherer is and what it can walk on
+struct Gatherer {
+    entity_id: specs::Index,
+    position: Vector3,
+    restriction_id: UnitTerrainRestrictionId,
+    /// How far off the gatherer can strike at an animal it's hunting
+    range: Fixed,
+}
+
+struct PathContext<'a> {
+    path_finder: &'a PathFinder,
+    terrain: &'a Terrain,
+    occupied_tiles: &'a OccupiedTiles,
+}
+
+/// What a gatherer looks like it's doing
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum GatherAnimation {
+    Standing,
+    Walking,
+    /// Walking back to a drop site with a load
+    Carrying,
+    Working,
+}
+
+impl GatherAnimation {
+    fn of(phase: GatherPhase, carrying: Fixed, moving: bool) -> GatherAnimation {
+        match phase {
+            GatherPhase::Gathering => GatherAnimation::Working,
+            GatherPhase::Depositing => GatherAnimation::Standing,
+            _ if !moving => GatherAnimation::Standing,
+            _ if carrying > 0.into() => GatherAnimation::Carrying,
+            _ => GatherAnimation::Walking,
+        }
+    }
+
+    /// The gatherer's graphic for the animation, falling back on walking or standing when the
+    /// game data doesn't have one for the resource
+    fn graphic(&self,
+               unit_info: &dat::Unit,
+               resource_type: Option<ResourceType>,
+               resource_class: Option<UnitClass>)
+               -> Option<GraphicId> {
+        let walking = unit_info.motion_params.as_ref().and_then(|params| params.walking_graphics[0]);
+        let graphic = match (*self, resource_type) {
+            (GatherAnimation::Standing, _) => None,
+            (GatherAnimation::Walking, _) => walking,
+            (GatherAnimation::Carrying, Some(resource_type)) => {
+                unit_info.carrying_graphic(resource_type, resource_class).or(walking)
+            }
+            (GatherAnimation::Carrying, None) => walking,
+            (GatherAnimation::Working, Some(resource_type)) => {
+                unit_info.gathering_graphic(resource_type, resource_class)
+            }
+            (GatherAnimation::Working, None) => None,
+        };
+        graphic.or(unit_info.standing_graphic)
+    }
+}
+
+enum Walk {
+    /// Still on the way; the direction is zero while waiting on a new path
+    Heading(Vector3),
+    Arrived,
+    Unreachable,
+}
+
+/// Walks villagers back and forth between the resource they're gathering and a drop site,
+/// working with the resource's tool, walking back with the load in hand, and standing at the
+/// drop site for a moment to hand it over. How much they carry at once goes up with their
+/// player's research. When the resource runs out or the drop site is gone, the villager moves
+/// on to the nearest one it can reach instead, and only goes idle, with a notification to its
+/// player, when there isn't one. Fishing boats work the same way, sailing between fish and
+/// their player's docks, and villagers fish from the shore next to fish close enough to it.
+/// Villagers hunt animals by striking at them until they drop, and then gather from the
+/// carcass, which goes off as it waits.
+pub struct GatherActionSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl GatherActionSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> GatherActionSystem {
+        GatherActionSystem { empires: empires }
+    }
+}
+
+impl System for GatherActionSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(constructions: ConstructionComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(gathers: GatherActionComponent),
+            mut components(velocities: VelocityComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(action_queues: ActionQueueComponent),
+            mut components(cooldowns: CooldownComponent),
+            mut components(resource_nodes: ResourceNodeComponent),
+            mut components(hit_points: HitPointsComponent),
+            mut components(provoked: ProvokedComponent),
+            resource(grid: GridPartition),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            resource(unit_index: UnitIndex),
+            mut resource(combat_log: CombatLog),
+            mut resource(deposit_log: DepositLog),
+            mut resource(notifications: Notifications),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(players: Players),
+        ]);
+
+        if (&entities, &gathers).iter().next().is_none() {
+            return;
+        }
+
+        // Only the kinds of resources that someone is gathering and the drop sites of the players
+        // gathering them are looked at, going by the UnitIndex rather than the whole world. A
+        // gatherer that hasn't found out what its target holds yet is after an animal's meat.
+        let mut resource_types = BTreeSet::new();
+        let mut gathering_players = BTreeSet::new();
+        for (gather, unit) in (&gathers, &units).iter() {
+            let resource_type = gather.resource_type
+                .or_else(|| resource_nodes.get(gather.target).map(|node| node.resource_type));
+            resource_types.insert(resource_type.unwrap_or(ResourceType::Food));
+            gathering_players.insert(unit.player_id);
+        }
+        let mut candidates = Vec::new();
+        for resource_type in &resource_types {
+            let tag = UnitTag::Resource(*resource_type);
+            candidates.extend_from_slice(unit_index.entities(PlayerId::gaia(), tag));
+        }
+        if resource_types.contains(&ResourceType::Food) {
+            candidates.extend_from_slice(unit_index.entities(PlayerId::gaia(), UnitTag::Huntable));
+        }
+        for player_id in &gathering_players {
+            candidates.extend_from_slice(unit_index.entities(*player_id, UnitTag::DropSite));
+        }
+
+        // Everything that can be walked up to, by entity ID, so that grid query results can be
+        // looked up
+        let mut nodes = HashMap::new();
+        let mut drop_sites = HashMap::new();
+        for entity in candidates {
+            let (transform, unit) = match (transforms.get(entity), units.get(entity)) {
+                (Some(transform), Some(unit)) => (transform, unit),
+                _ => continue,
+            };
+            let unit_info = unit.db(&self.empires);
+            let alive = hit_points.get(entity).map_or(false, |hit_points| !hit_points.is_destroyed());
+            let mut target = Target {
+                entity: entity,
+                position: *transform.position(),
+                size: unit_info.collision_size_x.max(unit_info.collision_size_y).into(),
+                alive: false,
+            };
+            if alive && unit::is_huntable(&self.empires, unit) {
+                target.alive = true;
+                let node = Node {
+                    resource_type: ResourceType::Food,
+                    class: unit_info.class(),
+                    hunted: true,
+                };
+                nodes.insert(entity.get_id(), (target, node));
+            } else if let Some(node) = resource_nodes.get(entity) {
+                if !node.is_depleted() {
+                    let node = Node {
+                        resource_type: node.resource_type,
+                        class: unit_info.class(),
+                        hunted: node.is_carcass(),
+                    };
+                    nodes.insert(entity.get_id(), (target, node));
+                }
+            } else if unit_info.interaction_mode == dat::InteractionMode::Building &&
+                      constructions.get(entity).is_none() {
+                // Foundations only take resources once they're built
+                let drop_site = DropSite {
+                    player_id: unit.player_id,
+                    unit_id: unit.unit_id,
+                };
+                drop_sites.insert(entity.get_id(), (target, drop_site));
+            }
+        }
+
+        let context = PathContext {
+            path_finder: &*path_finder,
+            terrain: &*terrain,
+            occupied_tiles: &*occupied_tiles,
+        };
+
+        let items = (&entities,
+                     &transforms,
+                     &units,
+                     &mut gathers,
+                     &mut velocities,
+                     &mut graphics,
+                     &mut action_queues);
+        for (entity, transform, unit, gather, velocity, graphic, action_queue) in items.iter() {
+            let unit_info = unit.db(&self.empires);
+            let gatherer = Gatherer {
+                entity_id: entity.get_id(),
+                position: *transform.position(),
+                restriction_id: unit_info.terrain_restriction,
+                range: unit_info.max_range().into(),
+            };
+            let mut give_up = None;
+            let mut waiting = false;
+
+            if gather.resource_type.is_none() {
+                if is_current(&nodes, gather.target) {
+                    let node = nodes[&gather.target.get_id()].1;
+                    gather.resource_type = Some(node.resource_type);
+                    gather.resource_class = Some(node.class);
+                    gather.hunting = node.hunted;
+                } else {
+                    gather.resource_type = resource_nodes.get(gather.target).map(|node| node.resource_type);
+                }
+            }
+            let resource_type = gather.resource_type;
+            let resource_class = gather.resource_class;
+            let hunting = gather.hunting;
+            let name = gatherer_name(unit_info);
+            let attack_ready = cooldowns.get(entity)
+                .map_or(true, |cooldown| cooldown.is_ready(ATTACK_COOLDOWN));
+
+            let accept_node = |target: &Target, node: &Node, carcasses_only: bool| {
+                Some(node.resource_type) == resource_type && same_job(resource_class, hunting, node) &&
+                !(carcasses_only && target.alive) && unit::can_gather_from(unit_info, node.class) &&
+                reachable(target, &gatherer, &context)
+            };
+            let accepted = unit_info.drop_sites();
+            let accept_drop_site = |target: &Target, drop_site: &DropSite| {
+                takes_drop_off(drop_site, unit.player_id, &accepted) && reachable(target, &gatherer, &context)
+            };
+            match retarget(gather,
+                           unit.player_id,
+                           &grid,
+                           &nodes,
+                           &drop_sites,
+                           &gatherer.position,
+                           attack_ready,
+                           accept_node,
+                           accept_drop_site) {
+                Retarget::Ready => {}
+                Retarget::WaitingForCarcass => {
+                    stop(velocity);
+                    waiting = true;
+                }
+                Retarget::OutOfResources => {
+                    give_up = Some(format!("{} has run out of {} to gather nearby",
+                                           name,
+                                           resource_name(resource_type)));
+                }
+                Retarget::NoDropSite => {
+                    give_up = Some(format!("{} has nowhere to drop off its {}",
+                                           name,
+                                           resource_name(resource_type)));
+                }
+            }
+
+            if give_up.is_none() && !waiting {
+                match gather.phase {
+                    GatherPhase::ToResource => {
+                        let target = nodes[&gather.target.get_id()].0;
+                        match walk(&mut gather.path, &target, &gatherer, &context, &mut *path_scheduler) {
+                            Walk::Heading(direction) => head(direction, velocity, unit_info),
+                            Walk::Arrived => {
+                                stop(velocity);
+                                gather.phase = GatherPhase::Gathering;
+                            }
+                            Walk::Unreachable => give_up = Some(format!("{} can't reach its resource", name)),
+                        }
+                    }
+                    GatherPhase::Gathering if nodes[&gather.target.get_id()].0.alive => {
+                        let target = nodes[&gather.target.get_id()].0;
+                        let reach = reach(&target, &gatherer, &context);
+                        if !within(&gatherer.position, &target.position, reach) {
+                            // The animal has got away, so go after it
+                            gather.phase = GatherPhase::ToResource;
+                            gather.path.clear();
+                        } else if attack_ready {
+                            if let Some(cooldown) = cooldowns.get_mut(entity) {
+                                cooldown.start(ATTACK_COOLDOWN, unit::reload_time(unit_info));
+                            }
+                            let target_unit = units.get(gather.target).unwrap();
+                            let armors = target_unit.db(&self.empires).armors();
+                            let elevation = terrain.tile_at(gatherer.position).elevation;
+                            let target_elevation = terrain.tile_at(target.position).elevation;
+                            let modifier = ElevationModifier::between(elevation, target_elevation);
+                            let amount = modifier.apply(unit::damage(unit_info.attacks(), armors));
+                            let target_hit_points = hit_points.get_mut(gather.target).unwrap();
+                            let destroyed = target_hit_points.damage(amount.into());
+                            combat_log.record(CombatEvent {
+                                attacker_player_id: unit.player_id,
+                                target: gather.target.get_id(),
+                                target_player_id: target_unit.player_id,
+                                damage: amount,
+                                elevation_modifier: modifier,
+                                destroyed: destroyed,
+                            });
+
+                            // The animal runs or fights back, whichever it does
+                            let already_provoked = provoked.get_mut(gather.target)
+                                .map(|provocation| {
+                                    provocation.attacker = entity;
+                                    provocation.fled = false;
+                                })
+                                .is_some();
+                            if !already_provoked {
+                                provoked.insert(gather.target, ProvokedComponent::new(entity));
+                            }
+                        }
+                    }
+                    GatherPhase::Gathering => {
+                        let player = players.player(unit.player_id);
+                        let capacity = carry_capacity(unit_info, player, resource_type);
+                        let work_rate: Fixed = unit_info.work_rate().into();
+                        let work = work_rate * time_step;
+                        let room = capacity - gather.carrying;
+                        let node = resource_nodes.get_mut(gather.target).unwrap();
+                        gather.carrying += node.take(if work < room { work } else { room });
+                        if gather.carrying >= capacity || node.is_depleted() {
+                            gather.phase = GatherPhase::ToDropSite;
+                            gather.path.clear();
+                        }
+                    }
+                    GatherPhase::ToDropSite => {
+                        let target = gather.drop_site
+                            .and_then(|drop_site| drop_sites.get(&drop_site.get_id()))
+                            .map(|&(target, _)| target);
+                        let walked = target.map(|target| {
+                            walk(&mut gather.path, &target, &gatherer, &context, &mut *path_scheduler)
+                        });
+                        match walked {
+                            Some(Walk::Heading(direction)) => head(direction, velocity, unit_info),
+                            Some(Walk::Arrived) => {
+                                stop(velocity);
+                                gather.phase = GatherPhase::Depositing;
+                                gather.deposit_time = 0.into();
+                            }
+                            Some(Walk::Unreachable) => {
+                                give_up = Some(format!("{} can't reach a drop site", name))
+                            }
+                            None => {
+                                give_up = Some(format!("{} has nowhere to drop off its {}",
+                                                       name,
+                                                       resource_name(resource_type)))
+                            }
+                        }
+                    }
+                    GatherPhase::Depositing => {
+                        gather.deposit_time += time_step;
+                        if gather.deposit_time >= DEPOSIT_SECONDS {
+                            if let (Some(resource_type), Some(player)) =
+                                   (resource_type, players.player_mut(unit.player_id)) {
+                                player.stockpile.add(resource_type, gather.carrying);
+                                deposit_log.record(DepositEvent {
+                                    player_id: unit.player_id,
+                                    position: *transform.position(),
+                                    resource_type: resource_type,
+                                    amount: gather.carrying,
+                                });
+                            }
+                            gather.carrying = 0.into();
+                            gather.phase = GatherPhase::ToResource;
+                        }
+                    }
+                }
+            }
+
+            let animation = if let Some(text) = give_up {
+                stop(velocity);
+                action_queue.mark_current_done();
+                notifications.notify(unit.player_id, text);
+                GatherAnimation::Standing
+            } else {
+                let moving = velocity.velocity != Vector3::new(0.into(), 0.into(), 0.into());
+                GatherAnimation::of(gather.phase, gather.carrying, moving)
+            };
+            let graphic_id = animation.graphic(unit_info, resource_type, resource_class);
+            if graphic.graphic_id != graphic_id {
+                graphic.set_graphic(graphic_id);
+            }
+        }
+    }
+}
+
+/// What a gatherer is left to do once `retarget` has sent it on
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Retarget {
+    /// It has a resource or drop site to go to
+    Ready,
+    /// A hunter waiting for the carcass of the animal it has just killed to turn up
+    WaitingForCarcass,
+    OutOfResources,
+    NoDropSite,
+}
+
+/// Moves the gatherer on to the nearest node that's accepted if its own has run out, and sends
+/// it to the nearest drop site that's accepted when it's taking its load back and isn't headed
+/// for one of its player's that's still standing. Whatever was already gathered still gets taken
+/// back when there's nothing left to gather. Hunters go for a carcass before another animal,
+/// and wait for the carcass of the animal they've just killed to turn up rather than going after
+/// another one; `accept_node` is told whether only carcasses will do.
+fn retarget<N, D>(gather: &mut GatherActionComponent,
+                  player_id: PlayerId,
+                  grid: &GridPartition,
+                  nodes: &HashMap<specs::Index, (Target, Node)>,
+                  drop_sites: &HashMap<specs::Index, (Target, DropSite)>,
+                  position: &Vector3,
+                  attack_ready: bool,
+                  accept_node: N,
+                  accept_drop_site: D)
+                  -> Retarget
+    where N: Fn(&Target, &Node, bool) -> bool,
+          D: Fn(&Target, &DropSite) -> bool
+{
+    let returning = |phase| phase == GatherPhase::ToDropSite || phase == GatherPhase::Depositing;
+    if !returning(gather.phase) && !is_current(nodes, gather.target) {
+        let hunting = gather.hunting;
+        let mut next = find_nearest(grid, nodes, position, |target, node| accept_node(target, node, hunting));
+        let waiting_for_carcass = hunting && !attack_ready;
+        if next.is_none() && !waiting_for_carcass {
+            next = find_nearest(grid, nodes, position, |target, node| accept_node(target, node, false));
+        }
+        match next {
+            Some(target) => {
+                let node = nodes[&target.entity.get_id()].1;
+                gather.target = target.entity;
+                // A carcass keeps the graphics of the hunt that got it
+                if !node.hunted || target.alive {
+                    gather.resource_class = Some(node.class);
+                }
+                gather.phase = GatherPhase::ToResource;
+                gather.path.clear();
+            }
+            None if waiting_for_carcass => return Retarget::WaitingForCarcass,
+            None if gather.carrying > 0.into() => {
+                gather.phase = GatherPhase::ToDropSite;
+                gather.path.clear();
+            }
+            None => return Retarget::OutOfResources,
+        }
+    }
+
+    // Looked at again, since running out of resources can have just sent the gatherer back
+    if returning(gather.phase) {
+        let still_standing = gather.drop_site.map_or(false, |drop_site| {
+            is_current(drop_sites, drop_site) && drop_sites[&drop_site.get_id()].1.player_id == player_id
+        });
+        if !still_standing {
+            let next = find_nearest(grid, drop_sites, position, accept_drop_site);
+            gather.drop_site = next.map(|target| target.entity);
+            gather.phase = GatherPhase::ToDropSite;
+            gather.path.clear();
+            if next.is_none() {
+                return Retarget::NoDropSite;
+            }
+        }
+    }
+    Retarget::Ready
+}
+
+/// Whether the gatherer can drop off at the site: it has to be one of its player's, and one of
+/// the buildings the game data lets it drop off at
+fn takes_drop_off(drop_site: &DropSite, player_id: PlayerId, accepted: &[UnitId]) -> bool {
+    drop_site.player_id == player_id && accepted.contains(&drop_site.unit_id)
+}
+
+/// Whether the entity is still one of the candidates, rather than gone or used up
+fn is_current<T>(candidates: &HashMap<specs::Index, (Target, T)>, entity: specs::Entity) -> bool {
+    candidates.get(&entity.get_id()).map_or(false, |&(ref target, _)| target.entity == entity)
+}
+
+/// Finds the closest candidate that's accepted, searching the grid outwards from the position.
+/// Ties go to the lowest entity ID, so that every client picks the same one.
+fn find_nearest<T, F>(grid: &GridPartition,
+                      candidates: &HashMap<specs::Index, (Target, T)>,
+                      position: &Vector3,
+                      accept: F)
+                      -> Option<Target>
+    where F: Fn(&Target, &T) -> bool
+{
+    let center: Vector2<i32> = Vector2::new(position.x.into(), position.y.into());
+    let accepted = |entity_id| {
+        candidates.get(&entity_id).map_or(false, |&(ref target, ref candidate)| accept(target, candidate))
+    };
+    grid.query_k_nearest(&center, 1, accepted).first().map(|entity_id| candidates[entity_id].0)
+}
+
+/// Whether the gatherer could stand next to the target: one of the tiles around it has to be
+/// on the map, free, and passable for the gatherer
+fn reachable(target: &Target, gatherer: &Gatherer, context: &PathContext) -> bool {
+    let terrain = context.terrain;
+    let row: i32 = target.position.y.into();
+    let col: i32 = target.position.x.into();
+    let radius = i32::from(target.size) + 1;
+    for tile_row in (row - radius)..(row + radius + 1) {
+        for tile_col in (col - radius)..(col + radius + 1) {
+            let on_edge = (tile_row - row).abs() == radius || (tile_col - col).abs() == radius;
+            if !on_edge || tile_row < 0 || tile_col < 0 || tile_row >= terrain.height() ||
+               tile_col >= terrain.width() ||
+               context.occupied_tiles.tiles.contains(&(tile_row, tile_col)) {
+                continue;
+            }
+            let terrain_id = terrain.tile_at_row_col(tile_row, tile_col).terrain_id;
+            if context.path_finder.passable(gatherer.restriction_id, terrain_id) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Works out which way the gatherer heads next to get within reach of the target, finding a
+/// new path there whenever it has run out of one
+fn walk(path: &mut Path,
+        target: &Target,
+        gatherer: &Gatherer,
+        context: &PathContext,
+        path_scheduler: &mut PathScheduler)
+        -> Walk {
+    if within(&gatherer.position, &target.position, reach(target, gatherer, context)) {
+        path.clear();
+        return Walk::Arrived;
+    }
+
+    if path.is_empty() {
+        *path = path_scheduler.find_path(context.path_finder,
+                                         context.terrain,
+                                         context.occupied_tiles,
+                                         gatherer.entity_id,
+                                         &gatherer.position,
+                                         &approach_point(target, &gatherer.position),
+                                         gatherer.restriction_id);
+
+        // A path that doesn't get the gatherer anywhere means the target is cut off
+        match path.last() {
+            Some(end) if !within(end, &gatherer.position, WAYPOINT_THRESHOLD) => {}
+            _ => return Walk::Unreachable,
+        }
+    }
+
+    let stopped = Vector3::new(0.into(), 0.into(), 0.into());
+    Walk::Heading(next_direction(path, &gatherer.position).unwrap_or(stopped))
+}
+
+/// How close the gatherer has to get to the target to use it: within reach of its edge, a tile
+/// further when it's on terrain the gatherer can't walk on, like fish off the shore, and as far
+/// as the gatherer's range for animals it's hunting
+fn reach(target: &Target, gatherer: &Gatherer, context: &PathContext) -> Fixed {
+    let tile = context.terrain.tile_at_row_col(target.position.y.into(), target.position.x.into());
+    let mut reach = target.size + REACH;
+    if !context.path_finder.passable(gatherer.restriction_id, tile.terrain_id) {
+        reach += SHORE_REACH;
+    }
+    if target.alive {
+        reach += gatherer.range;
+    }
+    reach
+}
+
+/// The point at the edge of the target's reach that's closest to the gatherer
+fn approach_point(target: &Target, from: &Vector3) -> Vector3 {
+    let reach = target.size + REACH / Fixed::from(2);
+    let clamp = |offset: Fixed| {
+        if offset > reach {
+            reach
+        } else if offset < -reach {
+            -reach
+        } else {
+            offset
+        }
+    };
+    Vector3::new(target.position.x + clamp(from.x - target.position.x),
+                 target.position.y + clamp(from.y - target.position.y),
+                 target.position.z)
+}
+
+fn head(direction: Vector3, velocity: &mut VelocityComponent, unit_info: &dat::Unit) {
+    match unit_info.motion_params {
+        Some(ref params) => {
+            let speed: Fixed = params.speed.into();
+            velocity.velocity = direction * speed;
+        }
+        None => stop(velocity),
+    }
+}
+
+fn stop(velocity: &mut VelocityComponent) {
+    velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+}
+
+/// How much of the resource the gatherer can carry at once, with what its player's research
+/// adds to it
+fn carry_capacity(unit_info: &dat::Unit,
+                  player: Option<&Player>,
+                  resource_type: Option<ResourceType>)
+                  -> Fixed {
+    let bonus = match (player, resource_type) {
+        (Some(player), Some(resource_type)) => player.carry_bonus(resource_type),
+        _ => 0.into(),
+    };
+    Fixed::from(unit_info.resource_capacity()) + bonus
+}
+
+/// Whether moving on to the node keeps the gatherer at the same job: any fish will do for
+/// fishing, and any animal or carcass for hunting
+fn same_job(resource_class: Option<UnitClass>, hunting: bool, node: &Node) -> bool {
+    if hunting || node.hunted {
+        return hunting && node.hunted;
+    }
+    match resource_class {
+        Some(resource_class) => {
+            resource_class == node.class || (resource_class.is_fish() && node.class.is_fish())
+        }
+        None => true,
+    }
+}
+
+fn gatherer_name(unit_info: &dat::Unit) -> &'static str {
+    if unit_info.class() == UnitClass::FishingBoat {
+        "A fishing boat"
+    } else {
+        "A villager"
+    }
+}
+
+fn resource_name(resource_type: Option<ResourceType>) -> &'static str {
+    match resource_type {
+        Some(ResourceType::Food) => "food",
+        Some(ResourceType::Wood) => "wood",
+        Some(ResourceType::Stone) => "stone",
+        Some(ResourceType::Gold) => "gold",
+        _ => "resources",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{self, ResourceType, UnitClass};
+    use ecs::{GatherActionComponent, GatherPhase};
+    use ecs::resource::Player;
+    use nalgebra::Vector2;
+    use partition::GridPartition;
+    use specs;
+    use std::collections::HashMap;
+    use super::{DropSite, GatherAnimation, Node, Retarget, Target, approach_point, carry_capacity,
+                find_nearest, is_current, retarget, same_job, takes_drop_off};
+    use types::{Fixed, Vector3};
+    use util::steering::within;
+
+    fn target(world: &mut specs::World, x: i32, y: i32) -> Target {
+        Target {
+            entity: world.create_now().build(),
+            position: Vector3::new(Fixed::from(x) + fixed_const!(0.5),
+                                   Fixed::from(y) + fixed_const!(0.5),
+                                   0.into()),
+            size: fixed_const!(0.5),
+            alive: false,
+        }
+    }
+
+    #[test]
+    fn test_find_nearest() {
+        let mut world = specs::World::new();
+        let mut grid = GridPartition::new(4, 4);
+        let mut candidates = HashMap::new();
+        for &(x, y, wood) in &[(3, 3, false), (6, 2, true), (2, 9, true), (40, 40, true)] {
+            let target = target(&mut world, x, y);
+            grid.update_entity(target.entity.get_id(), &Vector2::new(x, y));
+            candidates.insert(target.entity.get_id(), (target, wood));
+        }
+
+        let position = Vector3::new(2.into(), 2.into(), 0.into());
+        let nearest = find_nearest(&grid, &candidates, &position, |_, &wood| wood).unwrap();
+        assert_eq!((6, 2), (i32::from(nearest.position.x), i32::from(nearest.position.y)));
+
+        // Searches keep widening until they find something
+        let far = find_nearest(&grid, &candidates, &position, |target, _| {
+            i32::from(target.position.x) == 40
+        });
+        assert!(far.is_some());
+        assert!(find_nearest(&grid, &candidates, &position, |_, _| false).is_none());
+    }
+
+    #[test]
+    fn test_gatherers_find_another_drop_site_when_theirs_is_destroyed() {
+        let mut world = specs::World::new();
+        let mut grid = GridPartition::new(4, 4);
+        let mut drop_sites = HashMap::new();
+        let town_center = 109.into();
+        let sites = [(3, 2, 1, town_center),
+                     (5, 2, 2, town_center),
+                     (9, 9, 1, 68.into()),
+                     (20, 20, 1, town_center)];
+        let mut entities = Vec::new();
+        for &(x, y, player_id, unit_id) in &sites {
+            let target = target(&mut world, x, y);
+            grid.update_entity(target.entity.get_id(), &Vector2::new(x, y));
+            let drop_site = DropSite {
+                player_id: player_id.into(),
+                unit_id: unit_id,
+            };
+            drop_sites.insert(target.entity.get_id(), (target, drop_site));
+            entities.push(target.entity);
+        }
+
+        // The nearest one goes, so the gatherer has to look past another player's site and a
+        // building it can't drop off at for the next one of its own
+        let destroyed = entities[0];
+        drop_sites.remove(&destroyed.get_id());
+        grid.remove(destroyed.get_id());
+        assert!(!is_current(&drop_sites, destroyed));
+
+        let position = Vector3::new(2.into(), 2.into(), 0.into());
+        let accepted = vec![town_center];
+        let next = find_nearest(&grid, &drop_sites, &position, |_, drop_site| {
+            takes_drop_off(drop_site, 1.into(), &accepted)
+        });
+        assert_eq!(Some(entities[3]), next.map(|target| target.entity));
+
+        // With none of its own left, there's nowhere to go
+        drop_sites.remove(&entities[3].get_id());
+        assert!(find_nearest(&grid, &drop_sites, &position, |_, drop_site| {
+                takes_drop_off(drop_site, 1.into(), &accepted)
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_gatherers_move_on_when_their_resource_runs_out() {
+        let mut world = specs::World::new();
+        let mut grid = GridPartition::new(4, 4);
+        let mut nodes = HashMap::new();
+        let mut entities = Vec::new();
+        for &(x, y, resource_type) in &[(3, 3, ResourceType::Wood),
+                                         (4, 3, ResourceType::Gold),
+                                         (8, 8, ResourceType::Wood)] {
+            let target = target(&mut world, x, y);
+            grid.update_entity(target.entity.get_id(), &Vector2::new(x, y));
+            let node = Node {
+                resource_type: resource_type,
+                class: UnitClass::Tree,
+                hunted: false,
+            };
+            nodes.insert(target.entity.get_id(), (target, node));
+            entities.push(target.entity);
+        }
+
+        // Used up resources drop out of the candidates, and the next nearest of the same kind
+        // is taken instead of the closer gold
+        nodes.remove(&entities[0].get_id());
+        let position = Vector3::new(3.into(), 3.into(), 0.into());
+        let next = find_nearest(&grid, &nodes, &position, |_, node| {
+            node.resource_type == ResourceType::Wood && same_job(Some(UnitClass::Tree), false, node)
+        });
+        assert_eq!(Some(entities[2]), next.map(|target| target.entity));
+    }
+
+    #[test]
+    fn test_loaded_gatherers_take_their_load_back_when_nothing_is_left() {
+        let mut world = specs::World::new();
+        let mut grid = GridPartition::new(4, 4);
+        let used_up = target(&mut world, 3, 3);
+        let site = target(&mut world, 9, 9);
+        grid.update_entity(site.entity.get_id(), &Vector2::new(9, 9));
+        let nodes = HashMap::new();
+        let mut drop_sites = HashMap::new();
+        let drop_site = DropSite {
+            player_id: 1.into(),
+            unit_id: 109.into(),
+        };
+        drop_sites.insert(site.entity.get_id(), (site, drop_site));
+
+        // The resource ran out partway through a load, with nothing else of its kind around
+        let mut gather = GatherActionComponent::new(used_up.entity);
+        gather.phase = GatherPhase::Gathering;
+        gather.carrying = 4.into();
+        let position = Vector3::new(3.into(), 3.into(), 0.into());
+        let outcome = retarget(&mut gather,
+                               1.into(),
+                               &grid,
+                               &nodes,
+                               &drop_sites,
+                               &position,
+                               true,
+                               |_, _, _| true,
+                               |_, _| true);
+        assert_eq!(Retarget::Ready, outcome);
+        assert_eq!(GatherPhase::ToDropSite, gather.phase);
+        assert_eq!(Some(site.entity), gather.drop_site);
+
+        // Without a drop site to go to, the gatherer gives up rather than heading for nothing
+        let mut gather = GatherActionComponent::new(used_up.entity);
+        gather.phase = GatherPhase::Gathering;
+        gather.carrying = 4.into();
+        let outcome = retarget(&mut gather,
+                               1.into(),
+                               &grid,
+                               &nodes,
+                               &HashMap::new(),
+                               &position,
+                               true,
+                               |_, _, _| true,
+                               |_, _| true);
+        assert_eq!(Retarget::NoDropSite, outcome);
+        assert_eq!(None, gather.drop_site);
+
+        // Empty handed, there's nothing to take back
+        let mut gather = GatherActionComponent::new(used_up.entity);
+        gather.phase = GatherPhase::Gathering;
+        let outcome = retarget(&mut gather,
+                               1.into(),
+                               &grid,
+                               &nodes,
+                               &drop_sites,
+                               &position,
+                               true,
+                               |_, _, _| true,
+                               |_, _| true);
+        assert_eq!(Retarget::OutOfResources, outcome);
+    }
+
+    #[test]
+    fn test_approach_point() {
+        let mut world = specs::World::new();
+        let target = target(&mut world, 10, 10);
+        let from = Vector3::new(100.into(), Fixed::from(10) + fixed_const!(0.5), 0.into());
+        let point = approach_point(&target, &from);
+        assert!(within(&point, &target.position, target.size + fixed_const!(0.5)));
+        assert!(point.x > target.position.x);
+        assert_eq!(target.position.y, point.y);
+    }
+
+    #[test]
+    fn test_gather_animation() {
+        let loaded: Fixed = 10.into();
+        assert_eq!(GatherAnimation::Walking,
+                   GatherAnimation::of(GatherPhase::ToResource, 0.into(), true));
+        assert_eq!(GatherAnimation::Working,
+                   GatherAnimation::of(GatherPhase::Gathering, loaded, false));
+        assert_eq!(GatherAnimation::Carrying,
+                   GatherAnimation::of(GatherPhase::ToDropSite, loaded, true));
+        assert_eq!(GatherAnimation::Standing,
+                   GatherAnimation::of(GatherPhase::ToDropSite, loaded, false));
+        assert_eq!(GatherAnimation::Standing,
+                   GatherAnimation::of(GatherPhase::Depositing, loaded, false));
+
+        // Units without graphics for the job keep standing
+        let mut unit_info = dat::Unit::default();
+        unit_info.standing_graphic = Some(7.into());
+        assert_eq!(Some(7.into()),
+                   GatherAnimation::Carrying.graphic(&unit_info, Some(ResourceType::Wood), None));
+    }
+
+    #[test]
+    fn test_gatherers_move_on_to_the_same_job() {
+        let node = |class, hunted| {
+            Node {
+                resource_type: ResourceType::Food,
+                class: class,
+                hunted: hunted,
+            }
+        };
+        let bush = node(UnitClass::BerryBush, false);
+        assert!(same_job(None, false, &bush));
+        assert!(same_job(Some(UnitClass::BerryBush), false, &bush));
+        assert!(!same_job(Some(UnitClass::ShoreFish), false, &bush));
+        assert!(same_job(Some(UnitClass::SeaFish), false, &node(UnitClass::DeepSeaFish, false)));
+
+        // Hunters go from animals to carcasses and back, but not to berries
+        let carcass = node(UnitClass::Other, true);
+        assert!(same_job(Some(UnitClass::PreyAnimal), true, &carcass));
+        assert!(same_job(Some(UnitClass::Other), true, &node(UnitClass::PreyAnimal, true)));
+        assert!(!same_job(Some(UnitClass::PreyAnimal), true, &bush));
+        assert!(!same_job(Some(UnitClass::BerryBush), false, &carcass));
+    }
+
+    #[test]
+    fn test_carry_capacity_goes_up_with_research() {
+        let unit_info = dat::Unit::default();
+        let mut player = Player::new("Player".to_string(), 1.into(), 1.into(), 1.into());
+        player.set_carry_bonus(ResourceType::Wood, 5.into());
+        assert_eq!(Fixed::from(5), carry_capacity(&unit_info, Some(&player), Some(ResourceType::Wood)));
+        assert_eq!(Fixed::from(0), carry_capacity(&unit_info, Some(&player), Some(ResourceType::Gold)));
+        assert_eq!(Fixed::from(0), carry_capacity(&unit_info, None, Some(ResourceType::Wood)));
+    }
+}
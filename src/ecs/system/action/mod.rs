@@ -19,6 +19,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod attack_ground;
+mod explore;
+mod garrison;
+mod gather;
 mod move_to_position;
 
+pub use self::attack_ground::AttackGroundActionSystem;
+pub use self::explore::ExploreActionSystem;
+pub use self::garrison::GarrisonActionSystem;
+pub use self::gather::GatherActionSystem;
 pub use self::move_to_position::MoveToPositionActionSystem;
@@ -19,14 +19,28 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use action::{Action, MoveToPositionParams};
 use dat::EmpiresDbRef;
 use ecs::component::*;
+use ecs::resource::{ActionBatcher, OccupiedTile, OccupiedTiles, PathFinder, Terrain};
 use specs::{self, Join};
 use super::super::System;
-use types::{Fixed, Norm, Vector3};
+use types::{Fixed, Norm, ToFixed, ToPrimitive, Vector3};
+use util::steering::WAYPOINT_THRESHOLD;
 
-const THRESHOLD: Fixed = fixed_const!(0.1);
+/// How close a unit has to be to a waypoint with an idle unit on it to ask it to step aside
+const STEP_ASIDE_DISTANCE: Fixed = fixed_const!(1.5);
 
+/// How long a unit waits for an idle unit to step aside before walking through it anyway, so
+/// that units that can't move out of the way don't hold anyone up for good
+const PUSH_THROUGH_SECONDS: Fixed = fixed_const!(1.5);
+
+/// The tiles around a tile that an idle unit can step aside to
+const NEIGHBORS: [(i32, i32); 8] = [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// Walks units along their paths. A unit coming up to a tile where one of its own player's
+/// units stands idle waits and asks it to step aside, and walks through it if it hasn't
+/// moved after a little while.
 pub struct MoveToPositionActionSystem {
     empires: EmpiresDbRef,
 }
@@ -38,30 +52,83 @@ impl MoveToPositionActionSystem {
 }
 
 impl System for MoveToPositionActionSystem {
-    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
-        fetch_components!(arg, _entities, [
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
             components(transforms: TransformComponent),
             components(units: UnitComponent),
             mut components(mtps: MoveToPositionActionComponent),
             mut components(velocities: VelocityComponent),
             mut components(graphics: GraphicComponent),
             mut components(action_queues: ActionQueueComponent),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(action_batcher: ActionBatcher),
         ]);
 
-        let items = (&mut velocities, &transforms, &units, &mut graphics, &mut mtps, &mut action_queues);
-        for (mut velocity, transform, unit, mut graphic, mut mtps, mut action_queue) in items.iter() {
-            let mut done = false;
-            if !mtps.path.is_empty() {
+        let items = (&entities,
+                     &mut velocities,
+                     &transforms,
+                     &units,
+                     &mut graphics,
+                     &mut mtps,
+                     &mut action_queues);
+        for (entity, mut velocity, transform, unit, mut graphic, mut mtps, mut action_queue) in items.iter() {
+            let unit_info = unit.db(&self.empires);
+            let done = if !mtps.path.is_empty() {
                 let target = *mtps.path.first().unwrap();
                 let mut direction = target - *transform.position();
                 let distance = direction.normalize();
 
-                let done = if distance <= THRESHOLD {
+                if distance <= WAYPOINT_THRESHOLD {
                     mtps.path.remove(0);
+                    mtps.waited = 0.into();
                     mtps.path.is_empty()
                 } else {
-                    match unit.db(&self.empires).motion_params {
-                        Some(ref params) => {
+                    let target_tile = (target.y.to_i32().unwrap(), target.x.to_i32().unwrap());
+                    let blocker = match occupied_tiles.idle_unit_at(target_tile) {
+                        Some(blocker) if blocker != entity && distance < STEP_ASIDE_DISTANCE &&
+                                         mtps.waited < PUSH_THROUGH_SECONDS => {
+                            units.get(blocker).and_then(|blocker_unit| {
+                                if blocker_unit.player_id == unit.player_id {
+                                    Some((blocker, blocker_unit))
+                                } else {
+                                    None
+                                }
+                            })
+                        }
+                        _ => None,
+                    };
+
+                    match (blocker, unit_info.motion_params.as_ref()) {
+                        (Some((blocker, blocker_unit)), _) => {
+                            if mtps.asked != Some(blocker) {
+                                mtps.asked = Some(blocker);
+                                let restriction_id = blocker_unit.db(&self.empires).terrain_restriction;
+                                let aside = step_aside_tile(target_tile, &direction, &mtps.path, |tile| {
+                                    let terrain_id = terrain.tile_at_row_col(tile.0, tile.1).terrain_id;
+                                    !occupied_tiles.tiles.contains(&tile) &&
+                                    occupied_tiles.idle_unit_at(tile).is_none() &&
+                                    path_finder.passable(restriction_id, terrain_id)
+                                });
+                                if let Some((row, col)) = aside {
+                                    let elevation = terrain.tile_at_row_col(row, col).elevation;
+                                    let position = Vector3::new(col.to_fixed() + fixed_const!(0.5),
+                                                                row.to_fixed() + fixed_const!(0.5),
+                                                                elevation.to_fixed());
+                                    let params = MoveToPositionParams::new(vec![position]);
+                                    action_batcher.queue_for_entity(blocker.get_id(),
+                                                                    Action::MoveToPosition(params));
+                                }
+                            }
+                            mtps.waited += time_step;
+                            velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+                            if graphic.graphic_id != unit_info.standing_graphic {
+                                graphic.set_graphic(unit_info.standing_graphic);
+                            }
+                            false
+                        }
+                        (None, Some(params)) => {
                             if params.walking_graphics[0].is_some() &&
                                graphic.graphic_id != params.walking_graphics[0] {
                                 graphic.set_graphic(params.walking_graphics[0])
@@ -70,15 +137,14 @@ impl System for MoveToPositionActionSystem {
                             velocity.velocity = direction * speed;
                             false
                         }
-                        None => true,
+                        (None, None) => true,
                     }
-                };
+                }
             } else {
-                done = true;
-            }
+                true
+            };
 
             if done {
-                let unit_info = unit.db(&self.empires);
                 graphic.set_graphic(unit_info.standing_graphic);
                 velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
                 action_queue.mark_current_done();
@@ -86,3 +152,47 @@ impl System for MoveToPositionActionSystem {
         }
     }
 }
+
+/// A free tile next to the one in the way for the unit standing there to step aside to, off to
+/// the side of the way the other unit is heading and off the rest of its path
+fn step_aside_tile<F>(tile: OccupiedTile,
+                      heading: &Vector3,
+                      path: &[Vector3],
+                      free: F)
+                      -> Option<OccupiedTile>
+    where F: Fn(OccupiedTile) -> bool
+{
+    let on_path = |candidate: OccupiedTile| {
+        path.iter().any(|node| (node.y.to_i32().unwrap(), node.x.to_i32().unwrap()) == candidate)
+    };
+    let mut candidates: Vec<(Fixed, OccupiedTile)> = NEIGHBORS.iter()
+        .map(|&(d_row, d_col)| {
+            let along = heading.y * Fixed::from(d_row) + heading.x * Fixed::from(d_col);
+            (along.abs(), (tile.0 + d_row, tile.1 + d_col))
+        })
+        .filter(|&(_, candidate)| !on_path(candidate) && free(candidate))
+        .collect();
+    // Straight out to the side first
+    candidates.sort_by_key(|&(along, _)| along);
+    candidates.first().map(|&(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_aside_tile() {
+        let heading = Vector3::new(1.into(), 0.into(), 0.into());
+        let path = vec![Vector3::new(fixed_const!(4.5), fixed_const!(3.5), 0.into())];
+        let anywhere = |_: OccupiedTile| true;
+        let aside = step_aside_tile((3, 3), &heading, &path, &anywhere).unwrap();
+        assert_eq!(3, aside.1);
+        assert!(aside.0 == 2 || aside.0 == 4);
+
+        let aside = step_aside_tile((3, 3), &heading, &path, |tile: OccupiedTile| tile.1 != 3).unwrap();
+        assert!(aside != (3, 4));
+        assert!(aside.1 == 2 || aside.1 == 4);
+        assert_eq!(None, step_aside_tile((3, 3), &heading, &path, |_| false));
+    }
+}
@@ -21,10 +21,11 @@
 
 use dat;
 use ecs::{GraphicComponent, TransformComponent};
+use ecs::resource::SimulationLod;
 use resource::{DrsKey, ShapeMetadataKey, ShapeMetadataStoreRef};
-use specs::{self, Join};
+use specs::Join;
 use std::ops::Rem;
-use super::System;
+use super::{LaneArg, PresentationSystem, SystemAccess};
 use types::Fixed;
 
 pub struct AnimationSystem {
@@ -69,18 +70,29 @@ impl AnimationSystem {
     }
 }
 
-impl System for AnimationSystem {
-    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
-        fetch_components!(arg, _entities, [
+impl PresentationSystem for AnimationSystem {
+    fn access(&self) -> SystemAccess {
+        system_access!([
             components(transforms: TransformComponent),
             mut components(graphics: GraphicComponent),
+            resource(simulation_lod: SimulationLod),
+        ])
+    }
+
+    fn update(&mut self, arg: LaneArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            mut components(graphics: GraphicComponent),
+            resource(simulation_lod: SimulationLod),
         ]);
 
-        for (transform, graphic) in (&transforms, &mut graphics).iter() {
+        for (entity, transform, graphic) in (&entities, &transforms, &mut graphics).iter() {
             if let Some(graphic_id) = graphic.graphic_id {
                 let graphic_info = self.empires.graphic(graphic_id);
                 if graphic_info.frame_count > 1 {
-                    self.update_graphic(graphic, transform.rotation, graphic_info, time_step);
+                    if let Some(time_step) = simulation_lod.time_step(entity.get_id(), time_step) {
+                        self.update_graphic(graphic, transform.rotation, graphic_info, time_step);
+                    }
                 }
             }
         }
@@ -0,0 +1,91 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, UnitClass};
+use ecs::{TransformComponent, UnitComponent, VelocityComponent};
+use specs::{self, Join};
+use std::collections::{HashMap, HashSet};
+use super::System;
+use types::{Fixed, Norm, Vector3};
+
+/// Radius (in tiles) of the loop that birds fly around
+const WANDER_RADIUS: Fixed = fixed_const!(3);
+
+/// Speed (in tiles per second) that birds fly at
+const WANDER_SPEED: Fixed = fixed_const!(1.5);
+
+/// Birds are only there for atmosphere. They circle around wherever they were first seen,
+/// without going through the ActionBatcher. Since they move like every other unit, the system
+/// runs in the simulation planner and only goes by simulation state, so that every player's
+/// birds are in the same places. For the same reason every bird is steered on every tick,
+/// rather than less often when `SimulationLod` says it's far from the camera.
+pub struct BirdWanderSystem {
+    empires: dat::EmpiresDbRef,
+    wander_centers: HashMap<u32, Vector3>,
+}
+
+impl BirdWanderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> BirdWanderSystem {
+        BirdWanderSystem {
+            empires: empires,
+            wander_centers: HashMap::new(),
+        }
+    }
+}
+
+impl System for BirdWanderSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(velocities: VelocityComponent),
+        ]);
+
+        let mut birds = HashSet::new();
+        for (entity, unit, transform, velocity) in (&entities, &units, &transforms, &mut velocities).iter() {
+            if !unit.player_id.is_gaia() || unit.db(&self.empires).class() != UnitClass::Bird {
+                continue;
+            }
+            birds.insert(entity.get_id());
+
+            let position = *transform.position();
+            let center = *self.wander_centers
+                .entry(entity.get_id())
+                .or_insert_with(|| position - Vector3::new(WANDER_RADIUS, 0.into(), 0.into()));
+
+            let mut offset = position - center;
+            offset.z = 0.into();
+            let distance = offset.length();
+            if distance == 0.into() {
+                velocity.velocity = Vector3::new(WANDER_SPEED, 0.into(), 0.into());
+                continue;
+            }
+
+            // Fly along the circle, and steer back towards it if drifting off
+            let tangent = Vector3::new(-offset.y, offset.x, 0.into()) / distance;
+            let correction = offset / distance * (WANDER_RADIUS - distance);
+            velocity.velocity = tangent * WANDER_SPEED + correction;
+        }
+
+        // Forget the birds that are gone
+        self.wander_centers.retain(|entity_id, _| birds.contains(entity_id));
+    }
+}
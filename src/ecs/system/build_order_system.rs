@@ -0,0 +1,135 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::Action;
+use dat;
+use ecs::UnitComponent;
+use ecs::resource::*;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, ToPrimitive};
+use util::placement;
+
+/// Lays down the foundations that players order built, and sends their villagers to build
+/// them. The sites are checked again here, since the ground can have changed since the order
+/// was given, and the ones still clear are paid for all at once; if the player can't afford
+/// them all, nothing gets built. Players with the instant build cheat on get the buildings
+/// finished straight away, and their villagers carry on with what they were doing.
+pub struct BuildOrderSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl BuildOrderSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> BuildOrderSystem {
+        BuildOrderSystem { empires: empires }
+    }
+}
+
+impl System for BuildOrderSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(units: UnitComponent),
+            resource(cheats: Cheats),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(action_batcher: ActionBatcher),
+            mut resource(build_orders: BuildOrders),
+            mut resource(command_log: CommandLog),
+            mut resource(notifications: Notifications),
+            mut resource(players: Players),
+            mut resource(statistics: Statistics),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+
+        for order in build_orders.consume_orders() {
+            command_log.record(format!("{:?}", order));
+            let civ = self.empires.civilization(order.civilization_id);
+            if !civ.has_unit(order.unit_id) || !civ.unit_available(order.unit_id) {
+                warn!("Player {} can't build unit {}", *order.player_id, *order.unit_id);
+                continue;
+            }
+            let building_info = civ.unit(order.unit_id);
+
+            // Only the player's own villagers build for them
+            let builders: Vec<_> = (&entities, &units)
+                .iter()
+                .filter(|&(entity, unit)| {
+                    order.builders.contains(&entity.get_id()) && unit.player_id == order.player_id &&
+                    unit.db(&self.empires).class().is_villager()
+                })
+                .map(|(entity, _)| entity.get_id())
+                .collect();
+
+            let passable = |terrain_id| path_finder.passable(building_info.terrain_restriction, terrain_id);
+            let sites: Vec<_> = order.sites
+                .iter()
+                .filter(|site| {
+                    placement::check_game_placement(&terrain,
+                                                    &occupied_tiles,
+                                                    building_info,
+                                                    site.position.x.to_f32().unwrap(),
+                                                    site.position.y.to_f32().unwrap(),
+                                                    &passable)
+                        .is_allowed()
+                })
+                .collect();
+            if sites.is_empty() {
+                notifications.notify(order.player_id, "Can't build there".to_string());
+                continue;
+            }
+
+            let costs: Vec<_> = building_info.train_costs()
+                .iter()
+                .filter(|cost| cost.enabled && cost.amount > 0)
+                .map(|cost| (cost.resource_type, Fixed::from(cost.amount as i32 * sites.len() as i32)))
+                .collect();
+            let paid = match players.player_mut(order.player_id) {
+                Some(player) => player.stockpile.take_all(&costs),
+                None => false,
+            };
+            if !paid {
+                notifications.notify(order.player_id,
+                                     format!("Not enough resources to build the {}", building_info.name()));
+                continue;
+            }
+            let total: i32 = costs.iter().map(|&(_, amount)| i32::from(amount)).sum();
+            statistics.record_spent(order.player_id, total as u32);
+
+            let instant = cheats.instant_build(order.player_id);
+            if !instant {
+                for &builder_id in &builders {
+                    action_batcher.queue_for_entity(builder_id, Action::ClearQueue);
+                }
+            }
+            for site in sites {
+                let mut request =
+                    SpawnRequest::new(order.player_id, order.civilization_id, order.unit_id, site.position);
+                request.frame = site.frame;
+                if !instant {
+                    request.foundation = true;
+                    request.builders = builders.clone();
+                }
+                unit_spawner.queue_spawn(request);
+            }
+        }
+    }
+}
@@ -20,8 +20,7 @@
 // SOFTWARE.
 
 use ecs::{CameraComponent, VelocityComponent};
-use ecs::resource::KeyboardKeyStates;
-use media::Key;
+use input::InputActions;
 use specs::{self, Join};
 use super::System;
 use types::{Fixed, Norm, Vector3};
@@ -40,33 +39,29 @@ impl CameraInputSystem {
 }
 
 impl System for CameraInputSystem {
-    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
         fetch_components!(arg, _entities, [
             components(cameras: CameraComponent),
             mut components(velocities: VelocityComponent),
-            resource(keyboard_key_states: KeyboardKeyStates),
+            resource(input_actions: InputActions),
         ]);
 
         for (velocity, _camera) in (&mut velocities, &cameras).iter() {
-            let mut new_velocity = Vector3::new(0.into(), 0.into(), 0.into());
+            let mut new_velocity = Vector3::new(input_actions.scroll.x, input_actions.scroll.y, 0.into());
 
-            if keyboard_key_states.key_state(Key::Up).is_down() {
-                new_velocity.y = (-1).into();
-            } else if keyboard_key_states.key_state(Key::Down).is_down() {
-                new_velocity.y = 1.into();
-            }
-
-            if keyboard_key_states.key_state(Key::Left).is_down() {
-                new_velocity.x = (-1).into();
-            } else if keyboard_key_states.key_state(Key::Right).is_down() {
-                new_velocity.x = 1.into();
+            // Scrolling diagonally isn't any faster, but a stick pushed part way scrolls slower
+            if new_velocity.length_squared() > Fixed::from(1) {
+                new_velocity.normalize();
             }
+            new_velocity = new_velocity * CAMERA_SPEED;
 
-            if new_velocity.length_squared() > ZERO_THRESHOLD {
-                new_velocity.normalize();
+            // Panning drags the map along with the fingers, so the camera goes the other way
+            if time_step > ZERO_THRESHOLD {
+                new_velocity.x = new_velocity.x - Fixed::from(input_actions.pan.x) / time_step;
+                new_velocity.y = new_velocity.y - Fixed::from(input_actions.pan.y) / time_step;
             }
 
-            velocity.velocity = new_velocity * CAMERA_SPEED;
+            velocity.velocity = new_velocity;
         }
     }
 }
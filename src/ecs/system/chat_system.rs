@@ -0,0 +1,87 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use ecs::resource::*;
+use input::InputActions;
+use media::{Key, KeyState};
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Handles typing into the chat box. Sent messages that match a cheat code are
+/// turned into cheat requests when the lobby allows them, and are otherwise sent as chat.
+pub struct ChatSystem;
+
+impl ChatSystem {
+    pub fn new() -> ChatSystem {
+        ChatSystem
+    }
+}
+
+impl System for ChatSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(keyboard_key_states: KeyboardKeyStates),
+            resource(players: Players),
+            resource(text_input: TextInput),
+            mut resource(chat: Chat),
+            mut resource(cheats: Cheats),
+        ]);
+
+        if !chat.open {
+            // Return also sends debug console commands, so the chat box stays shut while the
+            // console is being typed into
+            if !debug_overlay.console_open &&
+               input_actions.action_state(KeyAction::OpenChat) == KeyState::TransitionDown {
+                chat.open = true;
+            }
+            return;
+        }
+
+        if keyboard_key_states.key_state(Key::Escape) == KeyState::TransitionDown {
+            chat.take_draft();
+            return;
+        }
+        if keyboard_key_states.key_state(Key::Backspace) == KeyState::TransitionDown {
+            chat.backspace();
+        }
+        chat.type_text(&text_input.text);
+
+        if keyboard_key_states.key_state(Key::Return) == KeyState::TransitionDown {
+            let text = chat.take_draft();
+            if text.trim().is_empty() {
+                return;
+            }
+
+            let player_id = players.local_player_id();
+            let sent_cheat = match Cheat::parse(&text) {
+                Some(cheat) => cheats.queue_cheat(CheatRequest::new(player_id, cheat)),
+                None => false,
+            };
+            if !sent_cheat {
+                chat.add_message(ChatMessage::new(player_id, text));
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{TransformComponent, UnitComponent};
+use ecs::resource::*;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, Vector3};
+
+/// Applies queued cheats and keeps a record of them for replays and saves
+pub struct CheatSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl CheatSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> CheatSystem {
+        CheatSystem { empires: empires }
+    }
+}
+
+impl System for CheatSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(simulation_tick: SimulationTick),
+            mut resource(cheats: Cheats),
+            mut resource(command_log: CommandLog),
+            mut resource(players: Players),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+
+        let tick = simulation_tick.get();
+        for request in cheats.consume_requests() {
+            let player_id = request.player_id;
+            let civ_id = match players.player(player_id) {
+                Some(player) => player.civ_id,
+                None => continue,
+            };
+
+            match request.cheat {
+                Cheat::GrantResource(resource_type) => {
+                    if let Some(player) = players.player_mut(player_id) {
+                        player.stockpile.add(resource_type, CHEAT_RESOURCE_AMOUNT);
+                    }
+                }
+                Cheat::InstantBuild => cheats.toggle_instant_build(player_id),
+                Cheat::RevealMap => cheats.toggle_revealed_map(player_id),
+                Cheat::BigDaddy if self.empires.civilization(civ_id).has_unit(big_daddy_unit_id()) => {
+                    // Park the car next to one of the player's units
+                    let position = (&entities, &units, &transforms)
+                        .iter()
+                        .filter(|&(_, unit, _)| unit.player_id == player_id)
+                        .map(|(_, _, transform)| *transform.position())
+                        .next();
+                    if let Some(position) = position {
+                        let offset = Vector3::new(1.into(), 1.into(), 0.into());
+                        unit_spawner.queue_spawn(SpawnRequest::new(player_id,
+                                                                   civ_id,
+                                                                   big_daddy_unit_id(),
+                                                                   position + offset));
+                    }
+                }
+                Cheat::BigDaddy => continue,
+            }
+            command_log.record(format!("{:?}", request));
+            cheats.record(tick, request);
+        }
+    }
+}
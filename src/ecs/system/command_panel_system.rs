@@ -0,0 +1,409 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, AttackGroundParams};
+use config::{KeyAction, KeyBindings};
+use dat;
+use ecs::{ConstructionComponent, ProductionQueueComponent, SelectedUnitComponent, UnitComponent};
+use ecs::resource::*;
+use identifier::{CivilizationId, ResearchId, UnitId};
+use input::InputActions;
+use media::KeyState;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, ToFixed, ToPrimitive, Vector3};
+use util::{placement, unit};
+
+/// Where the buttons that aren't in the game data go, in the slots their default keys press
+const ATTACK_GROUND_SLOT: usize = 4;
+const EXPLORE_SLOT: usize = 11;
+const TOWN_BELL_SLOT: usize = 14;
+const BUILD_MENU_SLOT: usize = 14;
+
+/// Handles input for the command panel. Its buttons follow what the local player's selection
+/// can do, laid out on a grid the way the game data places them: the units a building trains
+/// and the technologies it can research, or the build menu, whose keys then pick a building.
+/// Clicking a button, or pressing its key, gives the order, or for orders that need a spot on the
+/// map starts targeting, and the next click on the map gives the order there. Command calls the
+/// targeting off. The keys work while the panel is collapsed too, and take the press from
+/// whatever else is on the same key. Clicks on the panel and while targeting are taken for the
+/// panel, so it has to run before the selection system.
+pub struct CommandPanelSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl CommandPanelSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> CommandPanelSystem {
+        CommandPanelSystem { empires: empires }
+    }
+}
+
+impl System for CommandPanelSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(constructions: ConstructionComponent),
+            components(production_queues: ProductionQueueComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(units: UnitComponent),
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(key_bindings: KeyBindings),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(players: Players),
+            resource(selection_subgroup: SelectionSubgroup),
+            resource(technologies: Technologies),
+            resource(terrain: Terrain),
+            resource(ui_layout: UiLayout),
+            resource(view_projector: ViewProjector),
+            resource(viewport: Viewport),
+            mut resource(action_batcher: ActionBatcher),
+            mut resource(build_orders: BuildOrders),
+            mut resource(command_panel: CommandPanel),
+            mut resource(feedback_effects: FeedbackEffects),
+            mut resource(input_actions: InputActions),
+            mut resource(notifications: Notifications),
+            mut resource(production_orders: ProductionOrders),
+            mut resource(town_bell: TownBell),
+            mut resource(wall_placement: WallPlacement),
+        ]);
+        let local_player_id = players.local_player_id();
+
+        let typing = chat.open || debug_overlay.console_open;
+        if !typing && input_actions.action_state(KeyAction::ToggleCommandPanel) == KeyState::TransitionDown {
+            command_panel.collapsed = !command_panel.collapsed;
+        }
+
+        let (mut siege_units, mut explorers, mut trainers) = (Vec::new(), Vec::new(), Vec::new());
+        let mut shelter_selected = false;
+        let mut trained_units = Vec::new();
+        let mut research_sites = Vec::new();
+        let mut builder = None;
+        let mut villagers = Vec::new();
+        let researching: Vec<ResearchId> = (&units, &production_queues)
+            .iter()
+            .filter(|&(unit, _)| unit.player_id == local_player_id)
+            .filter_map(|(_, production_queue)| production_queue.current_research())
+            .collect();
+        for (entity, unit, _) in (&entities, &units, &selected_units).iter() {
+            if unit.player_id != local_player_id {
+                continue;
+            }
+            let unit_info = unit.db(&self.empires);
+            if unit::can_attack_ground(unit_info) {
+                siege_units.push(entity);
+            }
+            if unit::can_explore(unit_info) {
+                explorers.push(entity);
+            }
+            if unit::garrison_capacity(unit_info) > 0 {
+                shelter_selected = true;
+            }
+            if unit_info.class().is_villager() {
+                builder = builder.or(Some(unit.civilization_id));
+                villagers.push(entity.get_id());
+            }
+            // Foundations don't train or research anything until they're built
+            let production_queue = match constructions.get(entity) {
+                Some(_) => None,
+                None => production_queues.get(entity),
+            };
+            if let Some(production_queue) = production_queue {
+                if selection_subgroup.contains(unit.unit_id) {
+                    trainers.push(entity.get_id());
+                    trained_units.extend(self.trained_at(unit.civilization_id, unit.unit_id));
+                    if production_queue.current_research().is_none() {
+                        let researchable = self.researched_at(unit, &technologies)
+                            .into_iter()
+                            .filter(|&(research_id, _)| !researching.contains(&research_id))
+                            .map(|(research_id, slot)| (research_id, slot, entity.get_id()));
+                        research_sites.extend(researchable);
+                    }
+                }
+            }
+        }
+
+        if builder.is_none() {
+            command_panel.page = CommandPage::Main;
+        }
+        command_panel.clear();
+        match command_panel.page {
+            CommandPage::Main => {
+                if !siege_units.is_empty() {
+                    command_panel.place(CommandButton::AttackGround, ATTACK_GROUND_SLOT);
+                }
+                if !explorers.is_empty() {
+                    command_panel.place(CommandButton::Explore, EXPLORE_SLOT);
+                }
+                if shelter_selected {
+                    command_panel.place(CommandButton::TownBell, TOWN_BELL_SLOT);
+                }
+                for &(unit_id, slot) in &trained_units {
+                    command_panel.place(CommandButton::Train(unit_id), slot);
+                }
+                for &(research_id, slot, _) in &research_sites {
+                    command_panel.place(CommandButton::Research(research_id), slot);
+                }
+                if builder.is_some() {
+                    command_panel.place(CommandButton::BuildMenu, BUILD_MENU_SLOT);
+                }
+            }
+            CommandPage::Build => {
+                command_panel.place(CommandButton::Back, COMMAND_SLOTS - 1);
+                if let Some(civilization_id) = builder {
+                    for (unit_id, slot) in self.buildable(civilization_id) {
+                        command_panel.place(CommandButton::Build(unit_id), slot);
+                    }
+                }
+            }
+        }
+        let still_available = command_panel.targeting
+            .map_or(true, |targeting| command_panel.contains(targeting));
+        if !still_available || wall_placement.is_placing() {
+            command_panel.targeting = None;
+        }
+        if command_panel.is_empty() || wall_placement.is_placing() {
+            return;
+        }
+
+        let mut pressed = None;
+        if !typing {
+            let keys = [(KeyAction::AttackGround, CommandButton::AttackGround),
+                        (KeyAction::Explore, CommandButton::Explore),
+                        (KeyAction::RingTownBell, CommandButton::TownBell)];
+            for &(key_action, button) in &keys {
+                if command_panel.contains(button) &&
+                   input_actions.action_state(key_action) == KeyState::TransitionDown {
+                    pressed = Some(button);
+                }
+            }
+            for (slot, button) in command_panel.buttons() {
+                let slot_action = KeyAction::CommandSlot(slot as u8);
+                if input_actions.action_state(slot_action) == KeyState::TransitionDown {
+                    pressed = Some(button);
+                    release_shared_key(&mut input_actions, &key_bindings, slot_action);
+                }
+            }
+        }
+
+        let pointer = input_actions.pointer;
+        let button_under_pointer = command_panel.button_at(&ui_layout, &viewport.size, &pointer);
+        if let Some(button) = button_under_pointer {
+            if input_actions.select == KeyState::TransitionUp {
+                pressed = Some(button);
+            }
+            if input_actions.select != KeyState::Up {
+                consume_input(&mut input_actions);
+            }
+        }
+
+        match pressed {
+            Some(CommandButton::Explore) => {
+                command_panel.targeting = None;
+                give_order(&mut action_batcher, &explorers, Action::Explore);
+            }
+            Some(CommandButton::TownBell) => {
+                command_panel.targeting = None;
+                town_bell.ring(local_player_id);
+            }
+            Some(CommandButton::Train(unit_id)) => {
+                production_orders.queue_shared_training(trainers.clone(), unit_id);
+            }
+            Some(CommandButton::Research(research_id)) => {
+                let site = research_sites.iter().find(|&&(site_id, _, _)| site_id == research_id);
+                if let Some(&(_, _, entity_id)) = site {
+                    production_orders.queue_order(entity_id, ProductionOrderKind::Research(research_id));
+                }
+            }
+            Some(CommandButton::BuildMenu) => {
+                command_panel.targeting = None;
+                command_panel.page = CommandPage::Build;
+            }
+            Some(CommandButton::Back) => {
+                command_panel.targeting = None;
+                command_panel.page = CommandPage::Main;
+            }
+            Some(CommandButton::Build(unit_id)) if unit_id == WALL_UNIT_ID.into() => {
+                command_panel.targeting = None;
+                command_panel.page = CommandPage::Main;
+                wall_placement.begin(unit_id);
+            }
+            Some(button) => toggle(&mut command_panel, button),
+            None => {}
+        }
+        if button_under_pointer.is_some() {
+            return;
+        }
+
+        let targeting = match command_panel.targeting {
+            Some(targeting) => targeting,
+            None => return,
+        };
+        if input_actions.command == KeyState::TransitionUp {
+            command_panel.targeting = None;
+            consume_input(&mut input_actions);
+            return;
+        }
+
+        // Letting go at the end of a drag finishes a drag box rather than clicking
+        let clicked = input_actions.select == KeyState::TransitionUp ||
+                      input_actions.finished_drag_box.is_some();
+        if clicked {
+            let target = view_projector.unproject(&(input_actions.pointer + viewport.top_left_i32()),
+                                                  &*terrain);
+            match targeting {
+                CommandButton::AttackGround => {
+                    let order = Action::AttackGround(AttackGroundParams::new(target));
+                    give_order(&mut action_batcher, &siege_units, order);
+                    feedback_effects.command_flag(target, true);
+                    command_panel.targeting = None;
+                }
+                CommandButton::Build(unit_id) => {
+                    // The build menu is only up while there's a villager selected
+                    let civilization_id = builder.unwrap();
+                    let building_info = self.empires.unit(civilization_id, unit_id);
+                    let site = placement::check_game_placement(&terrain,
+                                                               &occupied_tiles,
+                                                               building_info,
+                                                               target.x.to_f32().unwrap(),
+                                                               target.y.to_f32().unwrap(),
+                                                               |terrain_id| {
+                        path_finder.passable(building_info.terrain_restriction, terrain_id)
+                    });
+                    if !site.is_allowed() {
+                        notifications.notify(local_player_id, "Can't build there".to_string());
+                    } else {
+                        // The BuildOrderSystem pays for it and lays the foundation down
+                        let (x, y) = (site.x.to_fixed(), site.y.to_fixed());
+                        let (row, col): (i32, i32) = (y.into(), x.into());
+                        let position = Vector3::new(x, y, terrain.tile_at_row_col(row, col).elevation.into());
+                        build_orders.queue_order(BuildOrder {
+                            player_id: local_player_id,
+                            civilization_id: civilization_id,
+                            unit_id: unit_id,
+                            sites: vec![BuildSite {
+                                            position: position,
+                                            frame: 0,
+                                        }],
+                            builders: villagers.clone(),
+                        });
+                        command_panel.targeting = None;
+                        command_panel.page = CommandPage::Main;
+                    }
+                }
+                // Given as soon as they're pressed
+                _ => command_panel.targeting = None,
+            }
+        }
+        consume_input(&mut input_actions);
+    }
+}
+
+impl CommandPanelSystem {
+    /// The units the building trains, with the slots the game data puts their buttons in
+    fn trained_at(&self, civilization_id: CivilizationId, building_id: UnitId) -> Vec<(UnitId, usize)> {
+        let civ = self.empires.civilization(civilization_id);
+        civ.units()
+            .into_iter()
+            .filter(|unit_info| {
+                unit_info.train_location_id() == Some(building_id) && civ.unit_available(unit_info.id)
+            })
+            .map(|unit_info| (unit_info.id, data_slot(unit_info.button_id())))
+            .collect()
+    }
+
+    /// The technologies the player can research at the building, with the slots the game data
+    /// puts their buttons in
+    fn researched_at(&self,
+                     building: &UnitComponent,
+                     technologies: &Technologies)
+                     -> Vec<(ResearchId, usize)> {
+        let civ = self.empires.civilization(building.civilization_id);
+        self.empires
+            .all_research()
+            .iter()
+            .filter(|research| {
+                research.location == Some(building.unit_id) &&
+                technologies.can_research(building.player_id, civ, research)
+            })
+            .map(|research| (research.id, data_slot(Some(research.button_id))))
+            .collect()
+    }
+
+    /// The buildings the civilization's villagers can build, with their slots on the build menu
+    fn buildable(&self, civilization_id: CivilizationId) -> Vec<(UnitId, usize)> {
+        let civ = self.empires.civilization(civilization_id);
+        civ.units()
+            .into_iter()
+            .filter(|unit_info| {
+                let built_by_villagers = unit_info.train_location_id()
+                    .map_or(false, |id| civ.has_unit(id) && civ.unit(id).class().is_villager());
+                unit_info.interaction_mode == dat::InteractionMode::Building && built_by_villagers &&
+                civ.unit_available(unit_info.id)
+            })
+            .map(|unit_info| (unit_info.id, data_slot(unit_info.button_id())))
+            .collect()
+    }
+}
+
+/// The slot for a button the game data has at the given button ID; buttons the data doesn't
+/// place take the first free one
+fn data_slot(button_id: Option<i8>) -> usize {
+    match button_id {
+        Some(button_id) if button_id > 0 && (button_id as usize) <= COMMAND_SLOTS => button_id as usize - 1,
+        _ => 0,
+    }
+}
+
+/// Takes the press away from the other actions on the same key, so that a building's key on
+/// the panel doesn't also do what the key does elsewhere
+fn release_shared_key(input_actions: &mut InputActions, key_bindings: &KeyBindings, used: KeyAction) {
+    let key = key_bindings.key(used);
+    for action in KeyAction::all() {
+        if action != used && key_bindings.key(action) == key {
+            input_actions.release_action(action);
+        }
+    }
+}
+
+/// Replaces whatever the units were doing with the order
+fn give_order(action_batcher: &mut ActionBatcher, entities: &[specs::Entity], order: Action) {
+    for entity in entities {
+        action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+        action_batcher.queue_for_entity(entity.get_id(), order.clone());
+    }
+}
+
+fn toggle(command_panel: &mut CommandPanel, button: CommandButton) {
+    command_panel.targeting = if command_panel.targeting == Some(button) {
+        None
+    } else {
+        Some(button)
+    };
+}
+
+/// Keeps the selection system from acting on the input that went to the panel
+fn consume_input(input_actions: &mut InputActions) {
+    input_actions.select = KeyState::Up;
+    input_actions.command = KeyState::Up;
+    input_actions.drag_box = None;
+    input_actions.finished_drag_box = None;
+}
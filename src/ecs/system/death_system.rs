@@ -0,0 +1,102 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::component::*;
+use ecs::resource::{IdleUnits, PathScheduler, SpawnRequest, UnitIndex, UnitSpawner};
+use partition::GridPartition;
+use specs::{self, Join};
+use super::System;
+use types::Fixed;
+
+/// Takes units that have run out of hit points and resources that have been emptied off the
+/// map, and has remains decay once their time is up, or once the food on them has gone off for
+/// carcasses. Either way, what's left behind is whatever the game data names as the unit's dead
+/// unit, in the same spot and facing the same way, so a soldier leaves a corpse that leaves a
+/// skeleton, a chopped tree leaves a stump, a gazelle leaves a carcass, and a fishing ship
+/// leaves debris, until a unit with no dead unit ends the chain. Units that go are taken out of
+/// the unit index, the idle lists and the grid, and their queued path searches are dropped.
+pub struct DeathSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl DeathSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> DeathSystem {
+        DeathSystem { empires: empires }
+    }
+}
+
+impl System for DeathSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(hit_points: HitPointsComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(decompositions: DecompositionComponent),
+            mut components(resource_nodes: ResourceNodeComponent),
+            mut resource(grid: GridPartition),
+            mut resource(idle_units: IdleUnits),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(unit_index: UnitIndex),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+
+        let mut gone = Vec::new();
+        for (entity, transform, unit) in (&entities, &transforms, &units).iter() {
+            let destroyed = hit_points.get(entity).map_or(false, |hit_points| hit_points.is_destroyed());
+            let emptied = match resource_nodes.get_mut(entity) {
+                Some(node) => {
+                    node.decay(time_step);
+                    node.is_depleted()
+                }
+                None => false,
+            };
+            let decayed = match decompositions.get_mut(entity) {
+                Some(decomposition) => {
+                    decomposition.remaining -= time_step;
+                    decomposition.remaining <= 0.into()
+                }
+                None => false,
+            };
+            if destroyed || emptied || decayed {
+                gone.push((entity, transform.clone(), unit.clone()));
+            }
+        }
+
+        for (entity, transform, unit) in gone {
+            // Nothing should cycle to, find or path for the unit once it's gone
+            unit_index.remove(entity);
+            idle_units.remove(entity);
+            grid.remove(entity.get_id());
+            path_scheduler.cancel(entity.get_id());
+            arg.delete(entity);
+            if let Some(dead_unit_id) = unit.db(&self.empires).dead_unit_id() {
+                let mut request = SpawnRequest::new(unit.player_id,
+                                                    unit.civilization_id,
+                                                    dead_unit_id,
+                                                    *transform.position());
+                request.rotation = transform.rotation;
+                request.remains = true;
+                unit_spawner.queue_spawn(request);
+            }
+        }
+    }
+}
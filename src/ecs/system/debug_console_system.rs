@@ -0,0 +1,169 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::{self, KeyAction};
+use ecs::{QueryContext, SelectedUnitComponent, UnitComponent};
+use partition::GridPartition;
+use ecs::resource::*;
+use input::InputActions;
+use logging;
+use media::{Key, KeyState};
+use specs::{self, Join};
+use super::System;
+use types::Fixed;
+
+/// Toggles the debug overlay, handles typing into its console, and runs the typed commands.
+/// Console commands skip the lobby's cheat setting since they're only meant for development.
+pub struct DebugConsoleSystem;
+
+impl DebugConsoleSystem {
+    pub fn new() -> DebugConsoleSystem {
+        DebugConsoleSystem
+    }
+}
+
+impl System for DebugConsoleSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(selected_units: SelectedUnitComponent),
+            components(units: UnitComponent),
+            resource(chat: Chat),
+            resource(grid: GridPartition),
+            resource(input_actions: InputActions),
+            resource(keyboard_key_states: KeyboardKeyStates),
+            resource(players: Players),
+            resource(selection_subgroup: SelectionSubgroup),
+            resource(technologies: Technologies),
+            resource(terrain: Terrain),
+            resource(text_input: TextInput),
+            resource(view_projector: ViewProjector),
+            resource(viewport: Viewport),
+            mut resource(area_query_cache: AreaQueryCache),
+            mut resource(cheats: Cheats),
+            mut resource(command_log: CommandLog),
+            mut resource(debug_overlay: DebugOverlay),
+            mut resource(entity_inspector: EntityInspector),
+            mut resource(feedback_effects: FeedbackEffects),
+            mut resource(option_changes: OptionChanges),
+            mut resource(production_orders: ProductionOrders),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        for command in debug_overlay.consume_commands() {
+            command_log.record(format!("Debug console: {:?}", command));
+            let local_player = players.local_player();
+            match command {
+                ConsoleCommand::Spawn(unit_id, count) => {
+                    let mouse_position = input_actions.pointer + viewport.top_left_i32();
+                    let position = view_projector.unproject(&mouse_position, &*terrain);
+                    for _ in 0..count {
+                        unit_spawner.queue_spawn(SpawnRequest::new(local_player.player_id,
+                                                                   local_player.civ_id,
+                                                                   unit_id,
+                                                                   position));
+                    }
+                }
+                ConsoleCommand::Train(unit_id, count) => {
+                    let building_ids: Vec<_> = (&entities, &units, &selected_units)
+                        .iter()
+                        .filter(|&(_, unit, _)| {
+                            unit.player_id == local_player.player_id &&
+                            selection_subgroup.contains(unit.unit_id)
+                        })
+                        .map(|(entity, _, _)| entity.get_id())
+                        .collect();
+                    for _ in 0..count {
+                        production_orders.queue_shared_training(building_ids.clone(), unit_id);
+                    }
+                }
+                ConsoleCommand::ToggleFog => cheats.toggle_revealed_map(local_player.player_id),
+                ConsoleCommand::ToggleGrid => debug_overlay.show_grid = !debug_overlay.show_grid,
+                ConsoleCommand::TogglePaths => debug_overlay.show_paths = !debug_overlay.show_paths,
+                ConsoleCommand::ToggleTiles => debug_overlay.show_tiles = !debug_overlay.show_tiles,
+                ConsoleCommand::ToggleNumbers => {
+                    feedback_effects.show_numbers = !feedback_effects.show_numbers;
+                }
+                ConsoleCommand::SetLogFilter(filter) => {
+                    if let Err(err) = logging::set_filter(&filter) {
+                        warn!("Failed to set log filter \"{}\": {}", filter, err);
+                    }
+                }
+                ConsoleCommand::SetOption(key, value) => {
+                    option_changes.queue_change(key, config::parse_value(&value))
+                }
+                ConsoleCommand::EditField(row, value) => entity_inspector.queue_edit(row, value),
+                ConsoleCommand::Query(query) => {
+                    let mut context = QueryContext {
+                        players: &*players,
+                        technologies: &*technologies,
+                        grid: &*grid,
+                        area_cache: &mut *area_query_cache,
+                    };
+                    let queried = (&entities, &units).iter().map(|(entity, unit)| (entity.get_id(), unit));
+                    info!("Debug console: {:?} = {}", query, query.evaluate(&mut context, queried));
+                }
+                ConsoleCommand::ReloadScripts => debug_overlay.reload_scripts = true,
+                ConsoleCommand::ToggleScriptTrace => {
+                    debug_overlay.trace_scripts = !debug_overlay.trace_scripts;
+                    info!("Debug console: script tracing {}",
+                          if debug_overlay.trace_scripts { "on" } else { "off" });
+                }
+            }
+        }
+
+        if !debug_overlay.console_open &&
+           key_state(KeyAction::ToggleDebugOverlay) == KeyState::TransitionDown {
+            debug_overlay.toggle();
+            return;
+        }
+        if !debug_overlay.visible || chat.open {
+            return;
+        }
+
+        if !debug_overlay.console_open {
+            if key_state(KeyAction::OpenDebugConsole) == KeyState::TransitionDown {
+                debug_overlay.console_open = true;
+            }
+            return;
+        }
+
+        if keyboard_key_states.key_state(Key::Escape) == KeyState::TransitionDown {
+            debug_overlay.take_draft();
+            return;
+        }
+        if keyboard_key_states.key_state(Key::Backspace) == KeyState::TransitionDown {
+            debug_overlay.backspace();
+        }
+        debug_overlay.type_text(&text_input.text);
+
+        if keyboard_key_states.key_state(Key::Return) == KeyState::TransitionDown {
+            let text = debug_overlay.take_draft();
+            match ConsoleCommand::parse(&text) {
+                Ok(command) => {
+                    info!("Debug console: {}", text.trim());
+                    debug_overlay.queue_command(command);
+                }
+                Err(err) => warn!("Debug console: {}", err),
+            }
+        }
+    }
+}
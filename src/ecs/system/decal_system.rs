@@ -21,8 +21,8 @@
 
 use ecs::DecalComponent;
 use resource::{ShapeMetadataKey, ShapeMetadataStoreRef};
-use specs::{self, Join};
-use super::System;
+use specs::Join;
+use super::{LaneArg, PresentationSystem, SystemAccess};
 use types::Fixed;
 
 // Hardcoded framerate for now
@@ -38,8 +38,12 @@ impl DecalSystem {
     }
 }
 
-impl System for DecalSystem {
-    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+impl PresentationSystem for DecalSystem {
+    fn access(&self) -> SystemAccess {
+        system_access!([ mut components(decals: DecalComponent), ])
+    }
+
+    fn update(&mut self, arg: LaneArg, time_step: Fixed) {
         fetch_components!(arg, entities, [ mut components(decals: DecalComponent), ]);
 
         for (entity, decal) in (&entities, &mut decals).iter() {
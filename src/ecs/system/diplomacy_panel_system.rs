@@ -0,0 +1,95 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use ecs::resource::*;
+use input::InputActions;
+use media::KeyState;
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Amount of a resource sent per click on a tribute button
+const TRIBUTE_INCREMENT: Fixed = fixed_const!(100);
+
+/// Amount sent when the add-to-selection modifier is held while clicking a tribute button
+const LARGE_TRIBUTE_INCREMENT: Fixed = fixed_const!(500);
+
+/// Handles input for the diplomacy panel, turning clicks into queued stance changes and tributes
+pub struct DiplomacyPanelSystem;
+
+impl DiplomacyPanelSystem {
+    pub fn new() -> DiplomacyPanelSystem {
+        DiplomacyPanelSystem
+    }
+}
+
+impl System for DiplomacyPanelSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(diplomacy: Diplomacy),
+            mut resource(diplomacy_panel: DiplomacyPanel),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        let typing = chat.open || debug_overlay.console_open;
+        if !typing && key_state(KeyAction::ToggleDiplomacy) == KeyState::TransitionUp {
+            diplomacy_panel.open = !diplomacy_panel.open;
+        }
+
+        if !diplomacy_panel.open || input_actions.select != KeyState::TransitionUp {
+            return;
+        }
+
+        let local_player_id = players.local_player_id();
+        let row_player_ids = diplomacy_panel.row_player_ids(&players);
+        let row_count = row_player_ids.len();
+        let bounds = diplomacy_panel.bounds(&ui_layout, &viewport.size, row_count);
+
+        if let Some((row, stance_index)) = diplomacy_panel.stance_button_at(&bounds,
+                                                                           row_count,
+                                                                           &input_actions.pointer) {
+            diplomacy.queue_stance_change(StanceChange::new(local_player_id,
+                                                            row_player_ids[row],
+                                                            PANEL_STANCES[stance_index]));
+        }
+
+        if let Some((row, resource_index)) = diplomacy_panel.tribute_button_at(&bounds,
+                                                                              row_count,
+                                                                              &input_actions.pointer) {
+            let amount = if !typing && key_state(KeyAction::AddToSelection).is_down() {
+                LARGE_TRIBUTE_INCREMENT
+            } else {
+                TRIBUTE_INCREMENT
+            };
+            diplomacy.queue_tribute(Tribute::new(local_player_id,
+                                                 row_player_ids[row],
+                                                 PANEL_TRIBUTE_RESOURCES[resource_index],
+                                                 amount));
+        }
+    }
+}
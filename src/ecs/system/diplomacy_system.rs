@@ -0,0 +1,178 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResourceType};
+use ecs::resource::{CommandLog, Diplomacy, DiplomaticStance, Notifications, Players, StanceChange, Statistics,
+                    Tribute};
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Minimum tribute (after the market tax) that will convince
+/// a hostile computer player to back down to neutral
+const APPEASEMENT_TRIBUTE_AMOUNT: Fixed = fixed_const!(100);
+
+/// Applies queued stance changes and tributes, and has computer players react to them.
+/// Both sides of a tribute hear about it: the recipient when it arrives, and the sender
+/// when they couldn't afford it.
+pub struct DiplomacySystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl DiplomacySystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> DiplomacySystem {
+        DiplomacySystem { empires: empires }
+    }
+
+    fn apply_tribute(&self,
+                     players: &mut Players,
+                     diplomacy: &mut Diplomacy,
+                     statistics: &mut Statistics,
+                     notifications: &mut Notifications,
+                     tribute: &Tribute) {
+        if tribute.from_player_id == tribute.to_player_id || players.player(tribute.to_player_id).is_none() {
+            return;
+        }
+
+        let (tribute_fee, sender_name) = match players.player_mut(tribute.from_player_id) {
+            Some(sender) => {
+                if !sender.stockpile.take(tribute.resource_type, tribute.amount) {
+                    notifications.notify(tribute.from_player_id,
+                                         format!("Not enough {} to send a tribute",
+                                                 resource_name(tribute.resource_type)));
+                    return;
+                }
+                let starting_penalty = self.empires.civilization(sender.civ_id).tribute_penalty().into();
+                (diplomacy.tribute_fee(tribute.from_player_id, starting_penalty), sender.name.clone())
+            }
+            None => return,
+        };
+
+        let amount_received = tribute.amount_received(tribute_fee);
+        let recipient = players.player_mut(tribute.to_player_id).unwrap();
+        recipient.stockpile.add(tribute.resource_type, amount_received);
+        notifications.notify(tribute.to_player_id,
+                             received_message(&sender_name, tribute.resource_type, amount_received));
+        statistics.record_tribute(tribute.from_player_id,
+                                  tribute.to_player_id,
+                                  i32::from(tribute.amount) as u32,
+                                  i32::from(amount_received) as u32);
+
+        if recipient.computer && amount_received >= APPEASEMENT_TRIBUTE_AMOUNT &&
+           diplomacy.is_enemy(tribute.to_player_id, tribute.from_player_id) {
+            diplomacy.set_stance(tribute.to_player_id,
+                                 tribute.from_player_id,
+                                 DiplomaticStance::Neutral);
+        }
+    }
+}
+
+impl System for DiplomacySystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            mut resource(command_log: CommandLog),
+            mut resource(diplomacy: Diplomacy),
+            mut resource(notifications: Notifications),
+            mut resource(players: Players),
+            mut resource(statistics: Statistics),
+        ]);
+
+        for stance_change in diplomacy.consume_stance_changes() {
+            command_log.record(format!("{:?}", stance_change));
+            diplomacy.set_stance(stance_change.player_id,
+                                 stance_change.other_player_id,
+                                 stance_change.stance);
+
+            let other_is_computer = match players.player(stance_change.other_player_id) {
+                Some(other_player) => other_player.computer,
+                None => false,
+            };
+            if other_is_computer {
+                react_to_stance_change(&mut diplomacy, &stance_change);
+            }
+        }
+
+        for tribute in diplomacy.consume_tributes() {
+            command_log.record(format!("{:?}", tribute));
+            self.apply_tribute(&mut players,
+                               &mut diplomacy,
+                               &mut statistics,
+                               &mut notifications,
+                               &tribute);
+        }
+    }
+}
+
+fn resource_name(resource_type: ResourceType) -> String {
+    format!("{:?}", resource_type).to_lowercase()
+}
+
+/// What the recipient of a tribute is told once it arrives
+fn received_message(sender_name: &str, resource_type: ResourceType, amount_received: Fixed) -> String {
+    format!("{} sent you {} {}",
+            sender_name,
+            i32::from(amount_received),
+            resource_name(resource_type))
+}
+
+/// Computer players answer hostility with hostility, and warm up one step
+/// at a time to players that offer them a better stance.
+fn react_to_stance_change(diplomacy: &mut Diplomacy, stance_change: &StanceChange) {
+    let computer_id = stance_change.other_player_id;
+    let player_id = stance_change.player_id;
+    let current = diplomacy.stance(computer_id, player_id);
+    let reaction = match (stance_change.stance, current) {
+        (DiplomaticStance::Enemy, _) => DiplomaticStance::Enemy,
+        (DiplomaticStance::Neutral, DiplomaticStance::Enemy) => DiplomaticStance::Neutral,
+        (DiplomaticStance::Ally, DiplomaticStance::Enemy) => DiplomaticStance::Neutral,
+        (DiplomaticStance::Ally, DiplomaticStance::Neutral) => DiplomaticStance::Ally,
+        (_, current) => current,
+    };
+    diplomacy.set_stance(computer_id, player_id, reaction);
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::resource::{Diplomacy, DiplomaticStance, StanceChange};
+    use super::{react_to_stance_change, received_message};
+
+    fn react(diplomacy: &mut Diplomacy, stance: DiplomaticStance) -> DiplomaticStance {
+        react_to_stance_change(diplomacy, &StanceChange::new(1.into(), 2.into(), stance));
+        diplomacy.stance(2.into(), 1.into())
+    }
+
+    #[test]
+    fn test_computer_reactions() {
+        let mut diplomacy = Diplomacy::new();
+        assert_eq!(DiplomaticStance::Neutral, react(&mut diplomacy, DiplomaticStance::Ally));
+        assert_eq!(DiplomaticStance::Ally, react(&mut diplomacy, DiplomaticStance::Ally));
+        assert_eq!(DiplomaticStance::Ally, react(&mut diplomacy, DiplomaticStance::Neutral));
+        assert_eq!(DiplomaticStance::Enemy, react(&mut diplomacy, DiplomaticStance::Enemy));
+        assert_eq!(DiplomaticStance::Neutral, react(&mut diplomacy, DiplomaticStance::Neutral));
+    }
+
+    #[test]
+    fn test_received_message() {
+        assert_eq!("Ramses sent you 75 gold",
+                   received_message("Ramses", ResourceType::Gold, 75.into()));
+    }
+}
@@ -0,0 +1,178 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::{ActionQueueComponent, GraphicComponent, OnScreenComponent, ProductionQueueComponent,
+          SelectedUnitComponent, TransformComponent, UnitComponent, VelocityComponent};
+use ecs::resource::{DebugOverlay, EntityInspector, InspectorField};
+use specs::{self, Join};
+use std::u16;
+use super::System;
+use types::Fixed;
+
+/// Applies the edits typed into the debug console to the inspected entity, then reads its
+/// fields and orders back into the inspector. The rows are logged with their names when an
+/// entity is first picked, since the inspector panel can only draw numbers.
+pub struct EntityInspectorSystem;
+
+impl EntityInspectorSystem {
+    pub fn new() -> EntityInspectorSystem {
+        EntityInspectorSystem
+    }
+}
+
+impl System for EntityInspectorSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(on_screen: OnScreenComponent),
+            components(production_queues: ProductionQueueComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(units: UnitComponent),
+            mut components(action_queues: ActionQueueComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(transforms: TransformComponent),
+            mut components(velocities: VelocityComponent),
+            resource(debug_overlay: DebugOverlay),
+            mut resource(entity_inspector: EntityInspector),
+        ]);
+
+        let entity_id = match entity_inspector.entity_id() {
+            Some(entity_id) => entity_id,
+            None => return,
+        };
+        if !debug_overlay.visible {
+            entity_inspector.close();
+            return;
+        }
+        let entity = match (&entities).iter().find(|entity| entity.get_id() == entity_id) {
+            Some(entity) => entity,
+            None => {
+                info!("Entity inspector: entity {} no longer exists", entity_id);
+                entity_inspector.close();
+                return;
+            }
+        };
+
+        for edit in entity_inspector.consume_edits() {
+            let (field, value) = match edit {
+                Ok(edit) => edit,
+                Err(err) => {
+                    warn!("Entity inspector: {}", err);
+                    continue;
+                }
+            };
+            let applied = match field {
+                InspectorField::PositionX | InspectorField::PositionY | InspectorField::PositionZ => {
+                    transforms.get_mut(entity).map(|transform| {
+                        let mut position = *transform.position();
+                        match field {
+                            InspectorField::PositionX => position.x = value,
+                            InspectorField::PositionY => position.y = value,
+                            _ => position.z = value,
+                        }
+                        transform.set_position(position);
+                    })
+                }
+                InspectorField::Rotation => {
+                    transforms.get_mut(entity).map(|transform| transform.rotation = value)
+                }
+                InspectorField::VelocityX | InspectorField::VelocityY | InspectorField::VelocityZ => {
+                    velocities.get_mut(entity).map(|velocity| {
+                        match field {
+                            InspectorField::VelocityX => velocity.velocity.x = value,
+                            InspectorField::VelocityY => velocity.velocity.y = value,
+                            _ => velocity.velocity.z = value,
+                        }
+                    })
+                }
+                InspectorField::Frame => {
+                    let frame = i32::from(value).max(0).min(u16::MAX as i32) as u16;
+                    graphics.get_mut(entity).map(|graphic| graphic.frame = frame)
+                }
+                InspectorField::CurrentOrderDone if value != 0.into() => {
+                    action_queues.get_mut(entity).map(|action_queue| action_queue.mark_current_done())
+                }
+                _ => Some(()),
+            };
+            match applied {
+                Some(()) => {
+                    info!("Entity inspector: set {} of entity {} to {}", field.name(), entity_id, value)
+                }
+                None => warn!("Entity inspector: entity {} no longer has {}", entity_id, field.name()),
+            }
+        }
+
+        let mut rows: Vec<(InspectorField, Fixed)> = Vec::new();
+        if let Some(unit) = units.get(entity) {
+            rows.push((InspectorField::PlayerId, (*unit.player_id).into()));
+            rows.push((InspectorField::CivilizationId, (*unit.civilization_id).into()));
+            rows.push((InspectorField::UnitId, (*unit.unit_id).into()));
+        }
+        if let Some(transform) = transforms.get(entity) {
+            rows.push((InspectorField::PositionX, transform.position().x));
+            rows.push((InspectorField::PositionY, transform.position().y));
+            rows.push((InspectorField::PositionZ, transform.position().z));
+            rows.push((InspectorField::Rotation, transform.rotation));
+        }
+        if let Some(velocity) = velocities.get(entity) {
+            rows.push((InspectorField::VelocityX, velocity.velocity.x));
+            rows.push((InspectorField::VelocityY, velocity.velocity.y));
+            rows.push((InspectorField::VelocityZ, velocity.velocity.z));
+        }
+        if let Some(graphic) = graphics.get(entity) {
+            // Graphics that aren't showing anything get -1 rather than being left out
+            let graphic_id = graphic.graphic_id
+                .map(|graphic_id| Fixed::from(*graphic_id))
+                .unwrap_or((-1).into());
+            rows.push((InspectorField::GraphicId, graphic_id));
+            rows.push((InspectorField::Frame, graphic.frame.into()));
+        }
+        rows.push((InspectorField::Selected, flag(selected_units.get(entity).is_some())));
+        rows.push((InspectorField::OnScreen, flag(on_screen.get(entity).is_some())));
+        if let Some(production_queue) = production_queues.get(entity) {
+            rows.push((InspectorField::QueuedUnits, production_queue.len().into()));
+            rows.push((InspectorField::TrainingProgress, production_queue.progress()));
+            rows.push((InspectorField::RepeatProduction, flag(production_queue.repeat())));
+        }
+
+        let (current_order, orders) = match action_queues.get(entity) {
+            Some(action_queue) => {
+                rows.push((InspectorField::QueuedOrders, action_queue.actions().len().into()));
+                rows.push((InspectorField::CurrentOrderDone, flag(action_queue.current_action_done())));
+                (action_queue.current_action().clone(), action_queue.actions().to_vec())
+            }
+            None => (None, Vec::new()),
+        };
+
+        if entity_inspector.refresh(rows, current_order, orders) {
+            info!("Entity inspector: entity {}", entity_id);
+            for (index, &(field, value)) in entity_inspector.rows().iter().enumerate() {
+                info!("Entity inspector row {}: {} = {}", index + 1, field.name(), value);
+            }
+            info!("Entity inspector: current order {:?}, queued orders {:?}",
+                  entity_inspector.current_order(),
+                  entity_inspector.orders());
+        }
+    }
+}
+
+fn flag(value: bool) -> Fixed {
+    if value { 1.into() } else { 0.into() }
+}
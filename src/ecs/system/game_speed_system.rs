@@ -0,0 +1,71 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use ecs::resource::*;
+use identifier::PlayerId;
+use input::InputActions;
+use media::KeyState;
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Handles the +/- hotkeys for changing the game speed
+pub struct GameSpeedSystem;
+
+impl GameSpeedSystem {
+    pub fn new() -> GameSpeedSystem {
+        GameSpeedSystem
+    }
+}
+
+impl System for GameSpeedSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            mut resource(game_speed: GameSpeed),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        if chat.open || debug_overlay.console_open {
+            return;
+        }
+
+        let speed_index = if key_state(KeyAction::SpeedUp) == KeyState::TransitionDown {
+            game_speed.faster_index()
+        } else if key_state(KeyAction::SlowDown) == KeyState::TransitionDown {
+            game_speed.slower_index()
+        } else {
+            return;
+        };
+
+        // Every human player has to agree on a speed change
+        let voters: Vec<PlayerId> = players.player_ids()
+            .into_iter()
+            .filter(|player_id| players.player(*player_id).map(|player| !player.computer).unwrap_or(false))
+            .filter(|player_id| !player_id.is_gaia())
+            .collect();
+        game_speed.propose(players.local_player_id(), speed_index, &voters);
+    }
+}
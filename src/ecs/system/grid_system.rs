@@ -24,32 +24,68 @@ use ecs::resource::{Terrain, ViewProjector, Viewport};
 use nalgebra::Vector2;
 use partition::GridPartition;
 use specs::{self, Join};
+use std::collections::HashSet;
 use super::System;
 use types::Fixed;
 
-/// System the updates the grid partition with the latest entity positions
-pub struct GridSystem;
+/// How often the grid's cell size is checked against the queries made since the last check
+const TUNING_SECONDS: Fixed = fixed_const!(10);
+
+/// System that updates the grid partition with the latest entity positions, and rebuilds it
+/// with a better cell size when the queries it's getting call for one. Its own query for what's
+/// on screen is left out of the stats, so that the cell size follows the simulation's queries.
+pub struct GridSystem {
+    time_since_tuning: Fixed,
+}
 
 impl GridSystem {
     pub fn new() -> GridSystem {
-        GridSystem
+        GridSystem { time_since_tuning: 0.into() }
+    }
+
+    fn tune(&mut self, grid: &mut GridPartition) {
+        let cell_size = grid.cell_size();
+        if let Some(tuned_size) = grid.tuned_cell_size() {
+            // Only rebuild when the size is way off, so that it doesn't flip back and forth
+            if is_way_off(cell_size.x, tuned_size.x) || is_way_off(cell_size.y, tuned_size.y) {
+                let stats = grid.query_stats();
+                info!("Rebuilding the grid partition with {}x{} cells instead of {}x{}; queries visited {} \
+                       cells and checked {} entities to find {} on average",
+                      tuned_size.x,
+                      tuned_size.y,
+                      cell_size.x,
+                      cell_size.y,
+                      stats.average_cells_visited(),
+                      stats.average_entities_checked(),
+                      stats.average_entities_found());
+                grid.rebuild_with_cell_size(tuned_size.x, tuned_size.y);
+            }
+        }
+        grid.reset_query_stats();
     }
 }
 
 impl System for GridSystem {
-    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
         fetch_components!(arg, entities, [
             mut components(on_screen: OnScreenComponent),
             resource(viewport: Viewport),
             resource(projector: ViewProjector),
-            resource(grid: GridPartition),
             resource(terrain: Terrain),
+            mut resource(grid: GridPartition),
         ]);
 
+        self.time_since_tuning += time_step;
+        if self.time_since_tuning >= TUNING_SECONDS {
+            self.time_since_tuning = 0.into();
+            self.tune(&mut *grid);
+        }
+
         let visible_region = projector.calculate_visible_world_coords(&viewport, &*terrain);
         let start_region = Vector2::new(visible_region.x, visible_region.y);
         let end_region = start_region + Vector2::new(visible_region.w, visible_region.h);
-        let visible_entities = grid.query(&start_region, &end_region);
+        let mut visible_entities = HashSet::new();
+        grid.query_into_untracked(&start_region, &end_region, &mut visible_entities);
 
         on_screen.clear();
         for entity in (&entities).iter() {
@@ -59,3 +95,7 @@ impl System for GridSystem {
         }
     }
 }
+
+fn is_way_off(cell_size: i32, tuned_size: i32) -> bool {
+    tuned_size >= cell_size * 2 || tuned_size * 2 <= cell_size
+}
@@ -0,0 +1,132 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use dat;
+use ecs::{ActionQueueComponent, CameraComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
+use ecs::resource::*;
+use input::InputActions;
+use media::KeyState;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, Vector3};
+
+/// Sorts idle units into villagers and military as their action queues change,
+/// and selects and centers the camera on the next idle unit when its hotkey is pressed.
+pub struct IdleUnitSystem {
+    empires: dat::EmpiresDbRef,
+    seeded: bool,
+}
+
+impl IdleUnitSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> IdleUnitSystem {
+        IdleUnitSystem {
+            empires: empires,
+            seeded: false,
+        }
+    }
+
+    fn track(&self, idle_units: &mut IdleUnits, entity: specs::Entity, unit: &UnitComponent) {
+        if let Some(category) = idle_category(unit.db(&self.empires)) {
+            idle_units.insert(unit.player_id, category, entity);
+        }
+    }
+}
+
+impl System for IdleUnitSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(action_queues: ActionQueueComponent),
+            components(cameras: CameraComponent),
+            components(units: UnitComponent),
+            mut components(selected_units: SelectedUnitComponent),
+            mut components(transforms: TransformComponent),
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            resource(view_projector: ViewProjector),
+            resource(viewport: Viewport),
+            mut resource(idle_units: IdleUnits),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        // Units start out idle without their action queues ever changing, so pick those up once
+        if !self.seeded {
+            for (entity, unit, action_queue) in (&entities, &units, &action_queues).iter() {
+                if action_queue.is_idle() {
+                    self.track(&mut idle_units, entity, unit);
+                }
+            }
+            self.seeded = true;
+        }
+
+        for (entity, idle) in idle_units.consume_changes() {
+            idle_units.remove(entity);
+            if idle {
+                if let Some(unit) = units.get(entity) {
+                    self.track(&mut idle_units, entity, unit);
+                }
+            }
+        }
+
+        if chat.open || debug_overlay.console_open {
+            return;
+        }
+        let category = if key_state(KeyAction::NextIdleVillager) == KeyState::TransitionDown {
+            IdleCategory::Villager
+        } else if key_state(KeyAction::NextIdleMilitary) == KeyState::TransitionDown {
+            IdleCategory::Military
+        } else {
+            return;
+        };
+
+        let local_player_id = players.local_player().player_id;
+        if let Some(entity) = idle_units.cycle(local_player_id, category) {
+            let unit_position = match transforms.get(entity) {
+                Some(transform) => *transform.position(),
+                None => return,
+            };
+
+            selected_units.clear();
+            selected_units.insert(entity, SelectedUnitComponent);
+
+            let screen_position = view_projector.project(&unit_position);
+            let camera_position = Vector3::new((screen_position.x - viewport.size.x / 2).into(),
+                                               (screen_position.y - viewport.size.y / 2).into(),
+                                               0.into());
+            for (transform, _camera) in (&mut transforms, &cameras).iter() {
+                transform.set_position(camera_position);
+            }
+        }
+    }
+}
+
+fn idle_category(unit_info: &dat::Unit) -> Option<IdleCategory> {
+    let class = unit_info.class();
+    if class.is_villager() {
+        Some(IdleCategory::Villager)
+    } else if class.is_military() {
+        Some(IdleCategory::Military)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,64 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyBindings;
+use ecs::resource::{KeyboardKeyStates, MouseState, TouchInput, Viewport};
+use input::{InputActions, InputMapper, RawInput};
+use media::GamepadState;
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Maps this update's keyboard, mouse, gamepad and touch input onto the `InputActions` that
+/// the rest of the systems read, so it has to run before any of them
+pub struct InputMappingSystem {
+    mapper: InputMapper,
+}
+
+impl InputMappingSystem {
+    pub fn new() -> InputMappingSystem {
+        InputMappingSystem { mapper: InputMapper::new() }
+    }
+}
+
+impl System for InputMappingSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(gamepad: GamepadState),
+            resource(key_bindings: KeyBindings),
+            resource(keyboard_key_states: KeyboardKeyStates),
+            resource(mouse_state: MouseState),
+            resource(touch_input: TouchInput),
+            resource(viewport: Viewport),
+            mut resource(input_actions: InputActions),
+        ]);
+
+        let raw = RawInput {
+            keys: &keyboard_key_states,
+            mouse_position: mouse_state.position,
+            mouse_buttons: &mouse_state.key_states,
+            gamepad: &gamepad,
+            touches: &touch_input.touches,
+            viewport_size: viewport.size,
+        };
+        self.mapper.update(&raw, &key_bindings, time_step.into(), &mut input_actions);
+    }
+}
@@ -0,0 +1,69 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use ecs::resource::*;
+use input::InputActions;
+use media::KeyState;
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Handles input for the market panel, turning clicks into queued market orders
+pub struct MarketPanelSystem;
+
+impl MarketPanelSystem {
+    pub fn new() -> MarketPanelSystem {
+        MarketPanelSystem
+    }
+}
+
+impl System for MarketPanelSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(players: Players),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(market: Market),
+            mut resource(market_panel: MarketPanel),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        let typing = chat.open || debug_overlay.console_open;
+        if !typing && key_state(KeyAction::ToggleMarket) == KeyState::TransitionUp {
+            market_panel.open = !market_panel.open;
+        }
+
+        if !market_panel.open || input_actions.select != KeyState::TransitionUp {
+            return;
+        }
+
+        let bounds = market_panel.bounds(&ui_layout, &viewport.size);
+        if let Some((row, transaction_index)) = market_panel.button_at(&bounds, &input_actions.pointer) {
+            market.queue_order(MarketOrder::new(players.local_player_id(),
+                                                COMMODITIES[row],
+                                                MARKET_TRANSACTIONS[transaction_index]));
+        }
+    }
+}
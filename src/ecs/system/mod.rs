@@ -21,24 +21,80 @@
 
 mod action;
 mod animation_system;
+mod bird_wander_system;
+mod build_order_system;
 mod camera_input_system;
 mod camera_position_system;
+mod chat_system;
+mod cheat_system;
+mod command_panel_system;
+mod cooldown_system;
+mod debug_console_system;
+mod death_system;
 mod decal_system;
+mod diplomacy_panel_system;
+mod diplomacy_system;
+mod entity_inspector_system;
+mod game_speed_system;
 mod grid_system;
+mod idle_unit_system;
+mod input_mapping_system;
+mod market_panel_system;
+mod market_system;
 mod occupied_tile_system;
+mod path_scheduler_system;
+mod presentation_lane;
+mod production_system;
+mod projectile_system;
+mod restoration_system;
+mod score_screen_system;
+mod simulation_lod_system;
+mod statistics_system;
 mod system;
+mod town_bell_system;
 mod unit_action_system;
 mod unit_selection_system;
 mod velocity_system;
+mod visibility_system;
+mod wall_placement_system;
+mod wildlife_system;
 
 pub use self::action::*;
 pub use self::animation_system::AnimationSystem;
+pub use self::bird_wander_system::BirdWanderSystem;
+pub use self::build_order_system::BuildOrderSystem;
 pub use self::camera_input_system::CameraInputSystem;
 pub use self::camera_position_system::CameraPositionSystem;
+pub use self::chat_system::ChatSystem;
+pub use self::cheat_system::CheatSystem;
+pub use self::command_panel_system::CommandPanelSystem;
+pub use self::cooldown_system::CooldownSystem;
+pub use self::debug_console_system::DebugConsoleSystem;
+pub use self::death_system::DeathSystem;
 pub use self::decal_system::DecalSystem;
+pub use self::diplomacy_panel_system::DiplomacyPanelSystem;
+pub use self::diplomacy_system::DiplomacySystem;
+pub use self::entity_inspector_system::EntityInspectorSystem;
+pub use self::game_speed_system::GameSpeedSystem;
 pub use self::grid_system::GridSystem;
+pub use self::idle_unit_system::IdleUnitSystem;
+pub use self::input_mapping_system::InputMappingSystem;
+pub use self::market_panel_system::MarketPanelSystem;
+pub use self::market_system::MarketSystem;
 pub use self::occupied_tile_system::OccupiedTileSystem;
-pub use self::system::{System, SystemWrapper};
+pub use self::path_scheduler_system::PathSchedulerSystem;
+pub use self::presentation_lane::{CheckAccess, LaneArg, PresentationLane, PresentationSystem, SystemAccess};
+pub use self::production_system::ProductionSystem;
+pub use self::projectile_system::ProjectileSystem;
+pub use self::restoration_system::RestorationSystem;
+pub use self::score_screen_system::ScoreScreenSystem;
+pub use self::simulation_lod_system::SimulationLodSystem;
+pub use self::statistics_system::StatisticsSystem;
+pub use self::system::{System, SystemScope, SystemWrapper};
+pub use self::town_bell_system::TownBellSystem;
 pub use self::unit_action_system::UnitActionSystem;
 pub use self::unit_selection_system::UnitSelectionSystem;
 pub use self::velocity_system::VelocitySystem;
+pub use self::visibility_system::VisibilitySystem;
+pub use self::wall_placement_system::WallPlacementSystem;
+pub use self::wildlife_system::WildlifeSystem;
@@ -20,13 +20,18 @@
 // SOFTWARE.
 
 use dat;
-use ecs::{TransformComponent, UnitComponent};
+use ecs::{ActionQueueComponent, ResourceNodeComponent, TransformComponent, UnitComponent};
 use ecs::resource::OccupiedTiles;
 use specs::{self, Join};
+use std::collections::{HashMap, HashSet};
 use super::System;
 use types::{Fixed, ToPrimitive};
 use util::unit;
 
+/// Keeps the tiles that buildings and resources stand on blocked. Resources only block while
+/// they still hold something, so a forest opens up a tile at a time as its trees are chopped
+/// down to stumps. Only the footprints of entities that came, went or moved change. Units with
+/// nothing to do are kept track of too, for paths to go around.
 pub struct OccupiedTileSystem {
     empires: dat::EmpiresDbRef,
 }
@@ -39,39 +44,49 @@ impl OccupiedTileSystem {
 
 impl System for OccupiedTileSystem {
     fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
-        fetch_components!(arg, _entities, [
+        fetch_components!(arg, entities, [
+            components(action_queues: ActionQueueComponent),
+            components(resource_nodes: ResourceNodeComponent),
             components(transforms: TransformComponent),
             components(units: UnitComponent),
             mut resource(occupied_tiles: OccupiedTiles),
         ]);
 
-        occupied_tiles.tiles.clear();
-        for (transform, unit) in (&transforms, &units).iter() {
+        let mut occupants = HashSet::new();
+        let mut idle_units = HashMap::new();
+        for (entity, transform, unit) in (&entities, &transforms, &units).iter() {
             let unit_info = self.empires.unit(unit.civilization_id, unit.unit_id);
+            let idle = action_queues.get(entity).map_or(false, |action_queue| action_queue.is_idle());
+            if idle && unit_info.interaction_mode == dat::InteractionMode::Movable {
+                let position = transform.position();
+                idle_units.insert((position.y.to_i32().unwrap(), position.x.to_i32().unwrap()), entity);
+            }
+
             let unit_blocks_tiles = match unit_info.interaction_mode {
                 dat::InteractionMode::Building => true,
                 dat::InteractionMode::Resource => {
-                    match unit_info.motion_params {
+                    let standing_still = match unit_info.motion_params {
                         Some(ref params) => params.speed < 0.001f32,
                         None => true,
-                    }
+                    };
+                    standing_still && resource_nodes.get(entity).map_or(false, |node| !node.is_depleted())
                 }
                 _ => false,
             };
 
             if unit_blocks_tiles {
-                let collision_box = unit::collision_box(unit_info, transform);
+                let footprint = unit::footprint(&unit::collision_box(unit_info, transform));
+                occupied_tiles.occupy(entity.get_id(), footprint);
+                occupants.insert(entity.get_id());
+            }
+        }
 
-                let (start_row, end_row) = (collision_box.min.y.to_i32().unwrap(),
-                                            collision_box.max.y.to_i32().unwrap());
-                let (start_col, end_col) = (collision_box.min.x.to_i32().unwrap(),
-                                            collision_box.max.x.to_i32().unwrap());
-                for row in start_row..(end_row + 1) {
-                    for col in start_col..(end_col + 1) {
-                        occupied_tiles.tiles.insert((row, col));
-                    }
-                }
+        // What's gone, or has stopped blocking, frees its tiles
+        for entity_id in occupied_tiles.occupants() {
+            if !occupants.contains(&entity_id) {
+                occupied_tiles.vacate(entity_id);
             }
         }
+        occupied_tiles.set_idle_units(idle_units);
     }
 }
@@ -0,0 +1,105 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, MoveToPositionParams};
+use ecs::{ActionQueueComponent, MoveToPositionActionComponent, TransformComponent};
+use ecs::resource::{ActionBatcher, OccupiedTiles, PathFinder, PathScheduler, Terrain};
+use specs::{self, Join};
+use std::collections::HashMap;
+use super::System;
+use types::{Fixed, Norm};
+
+/// How close an idle unit has to be to the end of its partial path to still count as following it
+const ARRIVED_DISTANCE: Fixed = fixed_const!(1);
+
+/// Spends the tick's path finding budget on the queued searches, and swaps a unit's partial path
+/// for the full one once its search finishes
+pub struct PathSchedulerSystem;
+
+impl PathSchedulerSystem {
+    pub fn new() -> PathSchedulerSystem {
+        PathSchedulerSystem
+    }
+}
+
+impl System for PathSchedulerSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(action_queues: ActionQueueComponent),
+            components(move_to_positions: MoveToPositionActionComponent),
+            components(transforms: TransformComponent),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(action_batcher: ActionBatcher),
+            mut resource(occupied_tiles: OccupiedTiles),
+            mut resource(path_scheduler: PathScheduler),
+        ]);
+
+        // Trees getting chopped down and buildings going up can make the queued searches wrong
+        let changed = occupied_tiles.take_changes();
+        if !changed.is_empty() {
+            path_scheduler.restart_searches(&path_finder, &terrain, &changed);
+        }
+
+        if path_scheduler.queued_count() == 0 {
+            return;
+        }
+        let finished: HashMap<_, _> = path_scheduler.run(&path_finder, &terrain, &occupied_tiles)
+            .into_iter()
+            .map(|finished| (finished.entity_id, finished))
+            .collect();
+        if finished.is_empty() {
+            return;
+        }
+
+        for (entity, transform, action_queue) in (&entities, &transforms, &action_queues).iter() {
+            let finished = match finished.get(&entity.get_id()) {
+                Some(finished) => finished,
+                None => continue,
+            };
+
+            // The unit might have been given some other order while its path was being found
+            let position = *transform.position();
+            let following_partial_path = match move_to_positions.get(entity) {
+                Some(move_to_position) => move_to_position.path.last() == Some(&finished.partial_end),
+                None => {
+                    action_queue.is_idle() && (finished.partial_end - position).length() < ARRIVED_DISTANCE
+                }
+            };
+            if !following_partial_path {
+                continue;
+            }
+
+            // The full path starts from where the unit was when it was ordered to move, so skip
+            // ahead to the part of it nearest to where the unit has gotten to since
+            let nearest = finished.path
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, node)| (*node - position).length_squared())
+                .map_or(0, |(index, _)| index);
+            let path = finished.path[nearest..].to_vec();
+
+            action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+            action_batcher.queue_for_entity(entity.get_id(),
+                                            Action::MoveToPosition(MoveToPositionParams::new(path)));
+        }
+    }
+}
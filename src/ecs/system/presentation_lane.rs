@@ -0,0 +1,273 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::SystemTimings;
+use logging;
+use rayon;
+use specs;
+use std::any::{Any, TypeId};
+use std::time::Instant;
+use types::Fixed;
+
+/// The components and resources that a presentation system reads and writes
+#[derive(Clone, Debug, Default)]
+pub struct SystemAccess {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+}
+
+impl SystemAccess {
+    pub fn new() -> SystemAccess {
+        SystemAccess::default()
+    }
+
+    pub fn read<T: Any>(mut self) -> SystemAccess {
+        self.reads.push(TypeId::of::<T>());
+        self
+    }
+
+    pub fn write<T: Any>(mut self) -> SystemAccess {
+        self.writes.push(TypeId::of::<T>());
+        self
+    }
+
+    fn declares_read(&self, type_id: TypeId) -> bool {
+        self.reads.contains(&type_id) || self.writes.contains(&type_id)
+    }
+
+    fn declares_write(&self, type_id: TypeId) -> bool {
+        self.writes.contains(&type_id)
+    }
+
+    /// Whether either system writes something that the other one uses
+    pub fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        self.writes.iter().any(|type_id| other.reads.contains(type_id) || other.writes.contains(type_id)) ||
+        other.writes.iter().any(|type_id| self.reads.contains(type_id))
+    }
+}
+
+/// Stands in for the `specs::RunArg` that the planner gives to systems, so that presentation
+/// systems can use `fetch_components!` too
+pub struct LaneArg<'a> {
+    world: &'a specs::World,
+    name: &'static str,
+    access: &'a SystemAccess,
+}
+
+impl<'a> LaneArg<'a> {
+    pub fn fetch<U, F: FnOnce(&'a specs::World) -> U>(&self, f: F) -> U {
+        f(self.world)
+    }
+
+    /// Deletes the entity once the world is next maintained, the same as `RunArg::delete`
+    pub fn delete(&self, entity: specs::Entity) {
+        self.world.delete_later(entity);
+    }
+}
+
+/// What `fetch_components!` checks each fetch against before making it. Presentation systems
+/// panic in debug builds when they fetch something their `access` didn't declare, since the
+/// lane would otherwise run them alongside systems they race with. The planner runs its
+/// systems one at a time, so it has nothing to check.
+pub trait CheckAccess {
+    fn check_read<T: Any>(&self) {}
+    fn check_write<T: Any>(&self) {}
+}
+
+impl CheckAccess for specs::RunArg {}
+
+impl<'a> CheckAccess for LaneArg<'a> {
+    fn check_read<T: Any>(&self) {
+        debug_assert!(self.access.declares_read(TypeId::of::<T>()),
+                      "{} reads something that its access doesn't list",
+                      self.name);
+    }
+
+    fn check_write<T: Any>(&self) {
+        debug_assert!(self.access.declares_write(TypeId::of::<T>()),
+                      "{} writes something that its access doesn't list as written",
+                      self.name);
+    }
+}
+
+/// A system whose results only change what players see, like animations, and which doesn't
+/// create entities. Entities it deletes, like decals that have played out, go along with the
+/// planner's deletions.
+pub trait PresentationSystem: Send {
+    /// Has to list everything that `update` fetches; see `system_access!`
+    fn access(&self) -> SystemAccess;
+
+    fn update(&mut self, arg: LaneArg, time_step: Fixed);
+}
+
+struct LaneSystem {
+    name: &'static str,
+    access: SystemAccess,
+    system: Box<PresentationSystem>,
+}
+
+/// Runs the presentation systems on a thread pool after the simulation systems are done with
+/// a tick. The simulation stays on the planner, which runs its systems one at a time in the
+/// order they were added, so that every player's game plays out the same way.
+///
+/// Systems are split into batches: each system goes in the batch after the last one with a
+/// system it conflicts with, so systems that share data still run in the order they were
+/// added, and everything within a batch runs at the same time.
+pub struct PresentationLane {
+    pool: rayon::ThreadPool,
+    batches: Vec<Vec<LaneSystem>>,
+    timings: SystemTimings,
+}
+
+impl PresentationLane {
+    pub fn new(num_threads: usize, timings: SystemTimings) -> PresentationLane {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|index| format!("presentation-{}", index))
+            .build()
+            .expect("Failed to create the presentation thread pool");
+        PresentationLane {
+            pool: pool,
+            batches: Vec::new(),
+            timings: timings,
+        }
+    }
+
+    pub fn add_system(&mut self, system: Box<PresentationSystem>, name: &'static str) {
+        let access = system.access();
+        let batch_index = self.batches
+            .iter()
+            .rposition(|batch| batch.iter().any(|lane_system| lane_system.access.conflicts_with(&access)))
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        if batch_index == self.batches.len() {
+            self.batches.push(Vec::new());
+        }
+        self.batches[batch_index].push(LaneSystem {
+            name: name,
+            access: access,
+            system: system,
+        });
+    }
+
+    pub fn run(&mut self, world: &specs::World, time_step: Fixed) {
+        let timings = &self.timings;
+        for batch in &mut self.batches {
+            self.pool.scope(|scope| {
+                for lane_system in batch.iter_mut() {
+                    scope.spawn(move |_| {
+                        let _span = trace_span!("system",
+                                                system = lane_system.name,
+                                                tick = logging::current_tick())
+                            .entered();
+                        let start = Instant::now();
+                        let arg = LaneArg {
+                            world: world,
+                            name: lane_system.name,
+                            access: &lane_system.access,
+                        };
+                        lane_system.system.update(arg, time_step);
+                        timings.record(lane_system.name, start.elapsed());
+                    });
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::SystemTimings;
+    use super::*;
+    use types::Fixed;
+
+    struct Graphic;
+    struct Transform;
+    struct Fog;
+
+    struct TestSystem(SystemAccess);
+
+    impl PresentationSystem for TestSystem {
+        fn access(&self) -> SystemAccess {
+            self.0.clone()
+        }
+
+        fn update(&mut self, _arg: LaneArg, _time_step: Fixed) {}
+    }
+
+    #[test]
+    fn test_access_conflicts() {
+        let reads_transform = SystemAccess::new().read::<Transform>();
+        let writes_transform = SystemAccess::new().write::<Transform>();
+        let writes_graphic = SystemAccess::new().read::<Transform>().write::<Graphic>();
+        assert!(!reads_transform.conflicts_with(&reads_transform));
+        assert!(reads_transform.conflicts_with(&writes_transform));
+        assert!(writes_transform.conflicts_with(&reads_transform));
+        assert!(writes_transform.conflicts_with(&writes_transform));
+        assert!(!reads_transform.conflicts_with(&writes_graphic));
+    }
+
+    #[test]
+    fn test_systems_are_batched_in_order() {
+        let mut lane = PresentationLane::new(2, SystemTimings::new());
+        let systems = [("Animation", SystemAccess::new().read::<Transform>().write::<Graphic>()),
+                       ("Visibility", SystemAccess::new().read::<Transform>().write::<Fog>()),
+                       ("Movement", SystemAccess::new().write::<Transform>()),
+                       ("Flicker", SystemAccess::new().write::<Graphic>())];
+        for &(name, ref access) in &systems {
+            lane.add_system(Box::new(TestSystem(access.clone())), name);
+        }
+
+        let batches: Vec<Vec<&str>> = lane.batches
+            .iter()
+            .map(|batch| batch.iter().map(|lane_system| lane_system.name).collect())
+            .collect();
+        assert_eq!(vec![vec!["Animation", "Visibility"], vec!["Movement", "Flicker"]], batches);
+    }
+
+    #[test]
+    fn test_declared_fetches_pass_the_check() {
+        let world = specs::World::new();
+        let access = SystemAccess::new().read::<Transform>().write::<Graphic>();
+        let arg = LaneArg {
+            world: &world,
+            name: "Animation",
+            access: &access,
+        };
+        arg.check_read::<Transform>();
+        arg.check_read::<Graphic>();
+        arg.check_write::<Graphic>();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Animation writes something")]
+    fn test_undeclared_writes_fail_the_check() {
+        let world = specs::World::new();
+        let access = SystemAccess::new().read::<Transform>().write::<Graphic>();
+        let arg = LaneArg {
+            world: &world,
+            name: "Animation",
+            access: &access,
+        };
+        arg.check_write::<Transform>();
+    }
+}
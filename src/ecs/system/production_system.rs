@@ -0,0 +1,313 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use dat;
+use ecs::{ConstructionComponent, MAX_QUEUED_UNITS, ProductionQueueComponent, SelectedUnitComponent,
+          TransformComponent, UnitComponent};
+use ecs::resource::*;
+use identifier::{PlayerId, ResearchId, UnitId};
+use input::InputActions;
+use media::KeyState;
+use specs::{self, Join};
+use std::collections::HashSet;
+use super::System;
+use types::{Fixed, Vector3};
+
+/// Pays for and trains the units queued in production buildings, and keeps requeuing
+/// the last trained unit for buildings that have repeat production turned on. Also pays for
+/// and researches technologies, which hold up training in their building until they're done,
+/// and applies what they do once they are.
+pub struct ProductionSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl ProductionSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> ProductionSystem {
+        ProductionSystem { empires: empires }
+    }
+}
+
+impl System for ProductionSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(constructions: ConstructionComponent),
+            components(selected_units: SelectedUnitComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(production_queues: ProductionQueueComponent),
+            resource(chat: Chat),
+            resource(cheats: Cheats),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(selection_subgroup: SelectionSubgroup),
+            mut resource(command_log: CommandLog),
+            mut resource(diplomacy: Diplomacy),
+            mut resource(market: Market),
+            mut resource(notifications: Notifications),
+            mut resource(players: Players),
+            mut resource(production_orders: ProductionOrders),
+            mut resource(statistics: Statistics),
+            mut resource(technologies: Technologies),
+            mut resource(unit_spawner: UnitSpawner),
+        ]);
+
+        if !chat.open && !debug_overlay.console_open &&
+           input_actions.action_state(KeyAction::ToggleRepeatProduction) == KeyState::TransitionDown {
+            let local_player_id = players.local_player_id();
+            let items = (&entities, &units, &selected_units, &production_queues);
+            for (entity, unit, _selected_unit, _production_queue) in items.iter() {
+                if unit.player_id == local_player_id && selection_subgroup.contains(unit.unit_id) {
+                    production_orders.queue_order(entity.get_id(), ProductionOrderKind::ToggleRepeat);
+                }
+            }
+        }
+
+        let mut orders = production_orders.consume_orders();
+        for shared_order in production_orders.consume_shared_orders() {
+            let unit_id = shared_order.unit_id;
+            let emptiest = (&entities, &units, &production_queues)
+                .iter()
+                .filter(|&(entity, unit, _)| {
+                    shared_order.entity_ids.contains(&entity.get_id()) && self.can_train(unit, unit_id)
+                })
+                .map(|(entity, _, production_queue)| {
+                    let entity_id = entity.get_id();
+                    let ordered = orders.iter().filter(|order| order.entity_id == entity_id).count();
+                    (production_queue.len() + ordered, entity_id)
+                })
+                .filter(|&(queued, _)| queued < MAX_QUEUED_UNITS)
+                .min();
+            match emptiest {
+                Some((_, entity_id)) => {
+                    orders.push(ProductionOrder {
+                        entity_id: entity_id,
+                        kind: ProductionOrderKind::Train(unit_id),
+                    })
+                }
+                None => info!("No selected building can queue unit {}", *unit_id),
+            }
+        }
+
+        // A player researches each technology in one building at a time
+        let mut researching: HashSet<(PlayerId, ResearchId)> = (&units, &production_queues)
+            .iter()
+            .filter_map(|(unit, production_queue)| {
+                production_queue.current_research().map(|research_id| (unit.player_id, research_id))
+            })
+            .collect();
+
+        for (entity, unit, production_queue) in (&entities, &units, &mut production_queues).iter() {
+            for order in orders.iter().filter(|order| order.entity_id == entity.get_id()) {
+                command_log.record(format!("{:?}", order));
+                if constructions.get(entity).is_some() {
+                    info!(entity = entity.get_id(), "Foundations can't train or research");
+                    continue;
+                }
+                match order.kind {
+                    ProductionOrderKind::Train(unit_id) => {
+                        self.train(unit, unit_id, production_queue, &mut *players, &mut *statistics)
+                    }
+                    ProductionOrderKind::Research(research_id) => {
+                        if researching.contains(&(unit.player_id, research_id)) {
+                            info!("Research {} is already under way", *research_id);
+                        } else if self.research(unit,
+                                                research_id,
+                                                production_queue,
+                                                &*technologies,
+                                                &mut *players,
+                                                &mut *statistics) {
+                            researching.insert((unit.player_id, research_id));
+                        }
+                    }
+                    ProductionOrderKind::ToggleRepeat => production_queue.toggle_repeat(),
+                    ProductionOrderKind::SetRallyPoint(rally_point) => {
+                        production_queue.rally_point = rally_point
+                    }
+                }
+            }
+        }
+
+        let items = (&entities, &units, &transforms, &mut production_queues);
+        for (entity, unit, transform, production_queue) in items.iter() {
+            if let Some(unit_id) = production_queue.repeat_candidate() {
+                // Wait for the resources to come in rather than giving up on the repeat
+                if self.pay(unit, unit_id, &mut *players, &mut *statistics) &&
+                   !production_queue.queue_repeat(unit_id) {
+                    info!(entity = entity.get_id(), "Repeat production reached its cap");
+                }
+            }
+
+            if let Some(research_id) = production_queue.current_research() {
+                let research_time = if cheats.instant_build(unit.player_id) {
+                    0.into()
+                } else {
+                    Fixed::from(self.empires.research(research_id).time_seconds)
+                };
+                if let Some(research_id) = production_queue.advance_research(time_step, research_time) {
+                    let research = self.empires.research(research_id);
+                    technologies.complete(unit.player_id, research_id);
+                    statistics.record_research(unit.player_id);
+                    apply_research_effects(unit.player_id,
+                                           self.empires.civilization(unit.civilization_id),
+                                           research_effects(&self.empires, research),
+                                           &mut *market,
+                                           &mut *diplomacy,
+                                           &mut *players);
+                    notifications.notify(unit.player_id, format!("{} researched", research.name));
+                }
+                continue;
+            }
+
+            let train_time = match production_queue.current() {
+                Some(unit_id) => self.empires.unit(unit.civilization_id, unit_id).train_time(),
+                None => continue,
+            };
+            let train_time = if cheats.instant_build(unit.player_id) {
+                0.into()
+            } else {
+                Fixed::from(train_time.unwrap_or(0))
+            };
+
+            if let Some(unit_id) = production_queue.advance(time_step, train_time) {
+                let military = self.empires.unit(unit.civilization_id, unit_id).class().is_military();
+                statistics.record_trained(unit.player_id, unit_id, military);
+
+                // Trained units show up just past the building's south corner
+                let building_info = unit.db(&self.empires);
+                let offset_x = Fixed::from(building_info.collision_size_x) + 1.into();
+                let offset_y = Fixed::from(building_info.collision_size_y) + 1.into();
+                let position = transform.position();
+                let position = Vector3::new(position.x + offset_x, position.y + offset_y, position.z);
+                let mut request = SpawnRequest::new(unit.player_id, unit.civilization_id, unit_id, position);
+                request.rally_point = production_queue.rally_point;
+                unit_spawner.queue_spawn(request);
+            }
+        }
+    }
+}
+
+impl ProductionSystem {
+    fn train(&self,
+             building: &UnitComponent,
+             unit_id: UnitId,
+             production_queue: &mut ProductionQueueComponent,
+             players: &mut Players,
+             statistics: &mut Statistics) {
+        if !self.can_train(building, unit_id) {
+            warn!("Unit {} can't be trained at unit {}", *unit_id, *building.unit_id);
+            return;
+        }
+        if production_queue.is_full() {
+            warn!("Production queue is full");
+            return;
+        }
+        if self.pay(building, unit_id, players, statistics) {
+            production_queue.queue_unit(unit_id);
+        } else {
+            info!("Not enough resources to train unit {}", *unit_id);
+        }
+    }
+
+    /// Pays for the research and starts it in the building; returns whether it was started
+    fn research(&self,
+                building: &UnitComponent,
+                research_id: ResearchId,
+                production_queue: &mut ProductionQueueComponent,
+                technologies: &Technologies,
+                players: &mut Players,
+                statistics: &mut Statistics)
+                -> bool {
+        if !self.can_research(building, research_id, technologies) {
+            warn!("Research {} can't be done at unit {}", *research_id, *building.unit_id);
+            return false;
+        }
+        if production_queue.current_research().is_some() {
+            info!("The building is already researching");
+            return false;
+        }
+        let civ = self.empires.civilization(building.civilization_id);
+        let costs: Vec<_> = civ.research_costs(self.empires.research(research_id))
+            .iter()
+            .filter(|cost| cost.enabled && cost.amount > 0)
+            .map(|cost| (cost.resource_type, Fixed::from(cost.amount)))
+            .collect();
+        if !pay_costs(building.player_id, &costs, players, statistics) {
+            info!("Not enough resources to research {}", *research_id);
+            return false;
+        }
+        production_queue.queue_research(research_id)
+    }
+
+    fn can_research(&self,
+                    building: &UnitComponent,
+                    research_id: ResearchId,
+                    technologies: &Technologies)
+                    -> bool {
+        if *research_id as usize >= self.empires.all_research().len() {
+            return false;
+        }
+        let research = self.empires.research(research_id);
+        let civ = self.empires.civilization(building.civilization_id);
+        research.location == Some(building.unit_id) &&
+        technologies.can_research(building.player_id, civ, research)
+    }
+
+    /// What the research does once it's finished
+    fn can_train(&self, building: &UnitComponent, unit_id: UnitId) -> bool {
+        let civ = self.empires.civilization(building.civilization_id);
+        civ.unit_available(unit_id) && civ.unit(unit_id).train_location_id() == Some(building.unit_id)
+    }
+
+    /// Takes the unit's training costs from the building owner's stockpile, if they can afford it
+    fn pay(&self,
+           building: &UnitComponent,
+           unit_id: UnitId,
+           players: &mut Players,
+           statistics: &mut Statistics)
+           -> bool {
+        let costs: Vec<_> = self.empires
+            .unit(building.civilization_id, unit_id)
+            .train_costs()
+            .iter()
+            .filter(|cost| cost.enabled && cost.amount > 0)
+            .map(|cost| (cost.resource_type, Fixed::from(cost.amount as i32)))
+            .collect();
+        pay_costs(building.player_id, &costs, players, statistics)
+    }
+}
+
+/// Takes the costs from the player's stockpile, if they can afford them
+fn pay_costs(player_id: PlayerId,
+             costs: &[(dat::ResourceType, Fixed)],
+             players: &mut Players,
+             statistics: &mut Statistics)
+             -> bool {
+    let paid = match players.player_mut(player_id) {
+        Some(player) => player.stockpile.take_all(costs),
+        None => false,
+    };
+    if paid {
+        let total: i32 = costs.iter().map(|&(_, amount)| i32::from(amount)).sum();
+        statistics.record_spent(player_id, total as u32);
+    }
+    paid
+}
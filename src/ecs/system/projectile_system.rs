@@ -0,0 +1,122 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::component::*;
+use ecs::resource::{CombatEvent, CombatLog, ElevationModifier, Terrain};
+use identifier::PlayerId;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, Norm, Vector3};
+use util::steering::within;
+use util::unit;
+
+/// Shots land on anything at least this close, so that ones with the smallest blast still hit
+/// what they're aimed at
+const MIN_BLAST_RADIUS: Fixed = fixed_const!(0.5);
+
+/// A shot that has landed
+struct Impact {
+    player_id: PlayerId,
+    /// Elevation of the tile the shot was fired from
+    origin_elevation: u8,
+    position: Vector3,
+    attacks: Vec<(i16, i16)>,
+    blast_radius: Fixed,
+}
+
+/// Flies projectiles straight at the spot they were fired at. When one gets there, every unit
+/// within its blast radius takes its damage, whichever side the unit is on, and the projectile
+/// is gone; units left without hit points are taken away by the DeathSystem. Shots fired from higher ground
+/// than the unit they hit do more damage, and shots fired uphill do less; every hit goes into
+/// the combat log with the modifier it got.
+pub struct ProjectileSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl ProjectileSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> ProjectileSystem {
+        ProjectileSystem { empires: empires }
+    }
+}
+
+impl System for ProjectileSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(projectiles: ProjectileComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(hit_points: HitPointsComponent),
+            mut components(velocities: VelocityComponent),
+            resource(terrain: Terrain),
+            mut resource(combat_log: CombatLog),
+        ]);
+
+        let mut impacts = Vec::new();
+        for (entity, projectile, transform, velocity) in
+            (&entities, &projectiles, &transforms, &mut velocities).iter() {
+            let position = *transform.position();
+            let step = projectile.speed * time_step;
+            if within(&position, &projectile.target, step) {
+                velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+                impacts.push(Impact {
+                    player_id: projectile.player_id,
+                    origin_elevation: terrain.tile_at(projectile.origin).elevation,
+                    position: projectile.target,
+                    attacks: projectile.attacks.clone(),
+                    blast_radius: if projectile.blast_radius > MIN_BLAST_RADIUS {
+                        projectile.blast_radius
+                    } else {
+                        MIN_BLAST_RADIUS
+                    },
+                });
+                arg.delete(entity);
+            } else {
+                let mut direction = projectile.target - position;
+                direction.normalize();
+                velocity.velocity = direction * projectile.speed;
+            }
+        }
+
+        for impact in &impacts {
+            for (entity, transform, unit, unit_hit_points) in
+                (&entities, &transforms, &units, &mut hit_points).iter() {
+                let in_blast = within(transform.position(), &impact.position, impact.blast_radius);
+                if !in_blast || unit_hit_points.is_destroyed() {
+                    continue;
+                }
+                let target_elevation = terrain.tile_at(*transform.position()).elevation;
+                let modifier = ElevationModifier::between(impact.origin_elevation, target_elevation);
+                let amount = modifier.apply(unit::damage(&impact.attacks, unit.db(&self.empires).armors()));
+                let destroyed = unit_hit_points.damage(amount.into());
+                combat_log.record(CombatEvent {
+                    attacker_player_id: impact.player_id,
+                    target: entity.get_id(),
+                    target_player_id: unit.player_id,
+                    damage: amount,
+                    elevation_modifier: modifier,
+                    destroyed: destroyed,
+                });
+            }
+        }
+    }
+}
+
@@ -0,0 +1,313 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResourceType};
+use ecs::component::*;
+use ecs::resource::{Cheats, Notifications, OccupiedTiles, PathFinder, PathScheduler, Players, Terrain};
+use identifier::GraphicId;
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, Vector3};
+use util::steering::{WAYPOINT_THRESHOLD, next_direction, within};
+
+/// How close a unit has to get to the edge of what it's restoring to work on it
+const REACH: Fixed = fixed_const!(0.5);
+
+/// Hit points a second restored by units whose work rate in the game data is zero
+const DEFAULT_RESTORE_RATE: Fixed = fixed_const!(1);
+
+/// Repairing a unit all the way back from nothing costs this much of what it cost to make
+const REPAIR_COST_FRACTION: Fixed = fixed_const!(0.5);
+
+const SECONDS_PER_MINUTE: Fixed = fixed_const!(60);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Restoration {
+    Healing,
+    Repairing,
+    Building,
+}
+
+impl Restoration {
+    fn of(unit_info: &dat::Unit, target_info: &dat::Unit, under_construction: bool) -> Restoration {
+        if unit_info.can_heal() && target_info.class().is_organic() {
+            Restoration::Healing
+        } else if under_construction {
+            Restoration::Building
+        } else {
+            Restoration::Repairing
+        }
+    }
+
+    fn graphic(&self, unit_info: &dat::Unit) -> Option<GraphicId> {
+        let graphic = match *self {
+            Restoration::Healing => unit_info.healing_graphic(),
+            Restoration::Repairing => unit_info.repairing_graphic(),
+            Restoration::Building => unit_info.building_graphic(),
+        };
+        graphic.or(unit_info.standing_graphic)
+    }
+
+    fn unreachable_text(&self) -> &'static str {
+        match *self {
+            Restoration::Healing => "A priest can't reach the unit it was told to heal",
+            Restoration::Repairing => "A villager can't reach what it was told to repair",
+            Restoration::Building => "A villager can't reach what it was told to build",
+        }
+    }
+}
+
+/// Gives units back their hit points, all in one place so that the ways of doing it work the
+/// same: priests walk up to living units and heal them, villagers walk up to buildings, siege
+/// weapons and boats and repair them, paying their player for it as they go in proportion to
+/// what the unit cost to make, and units that the game data says regenerate heal themselves
+/// over time. Villagers also build foundations up the same way, for nothing, at the pace the
+/// building's train time sets, or all at once for players with the instant build cheat on.
+/// Healers and repairers stop once the target is back to full, and give up with a notification
+/// when they can't reach it or their player runs out of resources.
+pub struct RestorationSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl RestorationSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> RestorationSystem {
+        RestorationSystem { empires: empires }
+    }
+}
+
+impl System for RestorationSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(action_queues: ActionQueueComponent),
+            mut components(constructions: ConstructionComponent),
+            mut components(graphics: GraphicComponent),
+            mut components(hit_points: HitPointsComponent),
+            mut components(restores: RestoreActionComponent),
+            mut components(velocities: VelocityComponent),
+            resource(cheats: Cheats),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(notifications: Notifications),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(players: Players),
+        ]);
+
+        for (unit, unit_hit_points) in (&units, &mut hit_points).iter() {
+            let unit_info = unit.db(&self.empires);
+            let rate: Fixed = unit_info.regeneration_rate().into();
+            if rate > 0.into() && !unit_hit_points.is_destroyed() {
+                restore(unit_hit_points, unit_info, rate / SECONDS_PER_MINUTE * time_step);
+            }
+        }
+
+        let mut built = Vec::new();
+        let items = (&entities,
+                     &transforms,
+                     &units,
+                     &mut restores,
+                     &mut velocities,
+                     &mut graphics,
+                     &mut action_queues);
+        for (entity, transform, unit, restore_action, velocity, graphic, action_queue) in items.iter() {
+            let unit_info = unit.db(&self.empires);
+            let position = *transform.position();
+            let target = restore_action.target;
+            let (target_position, target_info) = match (transforms.get(target), units.get(target)) {
+                (Some(target_transform), Some(target_unit)) if hit_points.get(target).is_some() => {
+                    (*target_transform.position(), target_unit.db(&self.empires))
+                }
+                // Whatever was being restored is gone
+                _ => {
+                    finish(velocity, graphic, unit_info, action_queue);
+                    continue;
+                }
+            };
+            let restoration = Restoration::of(unit_info, target_info, constructions.get(target).is_some());
+            let mut give_up = None;
+
+            let size: Fixed = target_info.collision_size_x.max(target_info.collision_size_y).into();
+            if !within(&position, &target_position, size + REACH) {
+                if restore_action.path.is_empty() {
+                    restore_action.path = path_scheduler.find_path(&*path_finder,
+                                                                   &*terrain,
+                                                                   &*occupied_tiles,
+                                                                   entity.get_id(),
+                                                                   &position,
+                                                                   &target_position,
+                                                                   unit_info.terrain_restriction);
+                    match restore_action.path.last() {
+                        Some(end) if !within(end, &position, WAYPOINT_THRESHOLD) => {}
+                        _ => give_up = Some(restoration.unreachable_text().to_string()),
+                    }
+                }
+                if give_up.is_none() {
+                    let direction = next_direction(&mut restore_action.path, &position);
+                    match (direction, unit_info.motion_params.as_ref()) {
+                        (Some(direction), Some(params)) => {
+                            let speed: Fixed = params.speed.into();
+                            velocity.velocity = direction * speed;
+                            let walking = params.walking_graphics[0].or(unit_info.standing_graphic);
+                            if graphic.graphic_id != walking {
+                                graphic.set_graphic(walking);
+                            }
+                        }
+                        (Some(_), None) => give_up = Some(restoration.unreachable_text().to_string()),
+                        // Waits for a new path next update
+                        (None, _) => stop(velocity),
+                    }
+                }
+            } else {
+                stop(velocity);
+                restore_action.path.clear();
+
+                let target_hit_points = hit_points.get_mut(target).unwrap();
+                let max_hit_points: Fixed = target_info.hit_points().into();
+
+                if restoration == Restoration::Building {
+                    let construction = constructions.get_mut(target).unwrap();
+                    let train_time: Fixed = target_info.train_time().unwrap_or(0).into();
+                    let work = if train_time > 0.into() && !cheats.instant_build(unit.player_id) {
+                        time_step / train_time
+                    } else {
+                        1.into()
+                    };
+                    let before = construction.progress;
+                    let finished = construction.advance(work);
+                    let gained = (construction.progress - before) * max_hit_points;
+                    restore(target_hit_points, target_info, gained);
+                    let graphic_id = restoration.graphic(unit_info);
+                    if graphic.graphic_id != graphic_id {
+                        graphic.set_graphic(graphic_id);
+                    }
+                    if finished {
+                        built.push(target);
+                        finish(velocity, graphic, unit_info, action_queue);
+                    }
+                    continue;
+                }
+
+                let room = max_hit_points - target_hit_points.hit_points;
+                let work_rate: Fixed = unit_info.work_rate().into();
+                let rate = if work_rate > 0.into() { work_rate } else { DEFAULT_RESTORE_RATE };
+                let work = rate * time_step;
+                let amount = if work < room { work } else { room };
+
+                let paid = restoration == Restoration::Healing || amount <= 0.into() || {
+                    let cost = repair_cost(target_info, amount);
+                    players.player_mut(unit.player_id)
+                        .map_or(false, |player| player.stockpile.take_all(&cost))
+                };
+                if paid {
+                    restore(target_hit_points, target_info, amount);
+                    let graphic_id = restoration.graphic(unit_info);
+                    if graphic.graphic_id != graphic_id {
+                        graphic.set_graphic(graphic_id);
+                    }
+                } else {
+                    give_up = Some("Not enough resources to keep repairing".to_string());
+                }
+
+                if target_hit_points.hit_points >= max_hit_points {
+                    finish(velocity, graphic, unit_info, action_queue);
+                    continue;
+                }
+            }
+
+            if let Some(text) = give_up {
+                finish(velocity, graphic, unit_info, action_queue);
+                notifications.notify(unit.player_id, text);
+            }
+        }
+
+        // Several villagers can finish the same foundation on the same update
+        for target in built {
+            if constructions.remove(target).is_some() {
+                let target_info = units.get(target).unwrap().db(&self.empires);
+                match graphics.get_mut(target) {
+                    Some(target_graphic) if target_graphic.graphic_id != target_info.standing_graphic => {
+                        target_graphic.set_graphic(target_info.standing_graphic);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Adds to the unit's hit points, up to what it has at full health
+fn restore(hit_points: &mut HitPointsComponent, unit_info: &dat::Unit, amount: Fixed) {
+    let max_hit_points: Fixed = unit_info.hit_points().into();
+    hit_points.hit_points += amount;
+    if hit_points.hit_points > max_hit_points {
+        hit_points.hit_points = max_hit_points;
+    }
+}
+
+/// What repairing the given hit points of the unit costs
+fn repair_cost(unit_info: &dat::Unit, restored: Fixed) -> Vec<(ResourceType, Fixed)> {
+    let share = repair_share(restored, unit_info.hit_points().into());
+    unit_info.train_costs()
+        .iter()
+        .filter(|cost| cost.enabled && cost.amount > 0)
+        .map(|cost| (cost.resource_type, Fixed::from(cost.amount as i32) * share))
+        .collect()
+}
+
+/// How much of what the unit cost to make goes into repairing it by the given hit points, in
+/// proportion to how much of its hit points they are
+fn repair_share(restored: Fixed, max_hit_points: Fixed) -> Fixed {
+    if max_hit_points <= 0.into() {
+        0.into()
+    } else {
+        restored / max_hit_points * REPAIR_COST_FRACTION
+    }
+}
+
+fn finish(velocity: &mut VelocityComponent,
+          graphic: &mut GraphicComponent,
+          unit_info: &dat::Unit,
+          action_queue: &mut ActionQueueComponent) {
+    stop(velocity);
+    if graphic.graphic_id != unit_info.standing_graphic {
+        graphic.set_graphic(unit_info.standing_graphic);
+    }
+    action_queue.mark_current_done();
+}
+
+fn stop(velocity: &mut VelocityComponent) {
+    velocity.velocity = Vector3::new(0.into(), 0.into(), 0.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_share() {
+        // A quarter of the hit points back costs an eighth of what the unit cost
+        assert_eq!(Fixed::from(15), Fixed::from(120) * repair_share(25.into(), 100.into()));
+        assert_eq!(REPAIR_COST_FRACTION, repair_share(100.into(), 100.into()));
+        assert_eq!(Fixed::from(0), repair_share(10.into(), 0.into()));
+    }
+}
@@ -0,0 +1,69 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use ecs::resource::*;
+use input::InputActions;
+use media::KeyState;
+use specs;
+use super::System;
+use types::Fixed;
+
+/// Opens the score screens when the game ends or their hotkey is pressed, and switches pages
+/// when their tabs are clicked
+pub struct ScoreScreenSystem;
+
+impl ScoreScreenSystem {
+    pub fn new() -> ScoreScreenSystem {
+        ScoreScreenSystem
+    }
+}
+
+impl System for ScoreScreenSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(input_actions: InputActions),
+            resource(statistics: Statistics),
+            resource(ui_layout: UiLayout),
+            resource(viewport: Viewport),
+            mut resource(score_screen: ScoreScreen),
+        ]);
+        let key_state = |action| input_actions.action_state(action);
+
+        if statistics.game_over() {
+            score_screen.show_game_over();
+        }
+        let typing = chat.open || debug_overlay.console_open;
+        if !typing && key_state(KeyAction::ToggleScoreScreen) == KeyState::TransitionUp {
+            score_screen.open = !score_screen.open;
+        }
+
+        if !score_screen.open || input_actions.select != KeyState::TransitionUp {
+            return;
+        }
+        let bounds = score_screen.bounds(&ui_layout, &viewport.size);
+        if let Some(page_index) = score_screen.tab_at(&bounds, &input_actions.pointer) {
+            score_screen.page = SCORE_PAGES[page_index];
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{ActionQueueComponent, TransformComponent, UnitComponent};
+use ecs::resource::{SimulationLod, Terrain, TickArena, ViewProjector, Viewport};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use specs::Join;
+use std::collections::HashSet;
+use super::{LaneArg, PresentationSystem, SystemAccess};
+use types::Fixed;
+
+/// Entities are sorted into near and far once every this many ticks
+const CLASSIFY_INTERVAL: u64 = 8;
+
+/// How far past the edges of the screen (in tiles) entities still count as near, so that
+/// they're up to date by the time the camera scrolls over to them
+const CAMERA_MARGIN: i32 = 8;
+
+/// Distance (in tiles) from a military unit carrying out an order that entities count as near
+const HOTSPOT_RADIUS: i32 = 10;
+
+/// Counts ticks and uses the grid partition to work out which entities are far from the
+/// camera and from any military units on the move. Far entities that are idle get put in
+/// the SimulationLod resource, which the animation and bird systems use to update them less
+/// often.
+pub struct SimulationLodSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl SimulationLodSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> SimulationLodSystem {
+        SimulationLodSystem { empires: empires }
+    }
+}
+
+impl PresentationSystem for SimulationLodSystem {
+    fn access(&self) -> SystemAccess {
+        system_access!([
+            components(action_queues: ActionQueueComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(grid: GridPartition),
+            resource(projector: ViewProjector),
+            resource(terrain: Terrain),
+            resource(tick_arena: TickArena),
+            resource(viewport: Viewport),
+            mut resource(simulation_lod: SimulationLod),
+        ])
+    }
+
+    fn update(&mut self, arg: LaneArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(action_queues: ActionQueueComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(grid: GridPartition),
+            resource(projector: ViewProjector),
+            resource(terrain: Terrain),
+            resource(tick_arena: TickArena),
+            resource(viewport: Viewport),
+            mut resource(simulation_lod: SimulationLod),
+        ]);
+
+        simulation_lod.advance_tick();
+        if simulation_lod.tick() % CLASSIFY_INTERVAL != 0 {
+            return;
+        }
+
+        let visible_region = projector.calculate_visible_world_coords(&viewport, &*terrain);
+        let margin = Vector2::new(CAMERA_MARGIN, CAMERA_MARGIN);
+        let visible_start = Vector2::new(visible_region.x, visible_region.y);
+        let visible_end = visible_start + Vector2::new(visible_region.w, visible_region.h);
+        let mut near = tick_arena.take::<HashSet<u32>>();
+        // The lane's queries are left out of the stats that the grid's cell size is tuned by
+        grid.query_into_untracked(&(visible_start - margin), &(visible_end + margin), &mut near);
+
+        let radius = Vector2::new(HOTSPOT_RADIUS, HOTSPOT_RADIUS);
+        for (unit, transform, action_queue) in (&units, &transforms, &action_queues).iter() {
+            if action_queue.is_idle() || unit.player_id.is_gaia() ||
+               !unit.db(&self.empires).class().is_military() {
+                continue;
+            }
+            let position = transform.position();
+            let center: Vector2<i32> = Vector2::new(position.x.into(), position.y.into());
+            grid.query_into_untracked(&(center - radius), &(center + radius), &mut near);
+        }
+
+        let far: HashSet<u32> = (&entities, &transforms)
+            .iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| {
+                !near.contains(&entity.get_id()) &&
+                action_queues.get(*entity).map(|action_queue| action_queue.is_idle()).unwrap_or(true)
+            })
+            .map(|entity| entity.get_id())
+            .collect();
+        simulation_lod.set_far(far);
+    }
+}
@@ -0,0 +1,114 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResourceType};
+use ecs::UnitComponent;
+use ecs::resource::{Diplomacy, Players, Statistics};
+use specs::{self, Join};
+use std::collections::BTreeSet;
+use super::System;
+use types::Fixed;
+
+/// Seconds of game time between the score snapshots that make up the timeline
+const TIMELINE_INTERVAL_SECONDS: Fixed = fixed_const!(30);
+
+const BANKED_RESOURCES: [ResourceType; 4] =
+    [ResourceType::Food, ResourceType::Wood, ResourceType::Stone, ResourceType::Gold];
+
+/// Counts every player's units and banked resources for the score screens, takes the timeline
+/// snapshots, and works out who has been defeated
+pub struct StatisticsSystem {
+    empires: dat::EmpiresDbRef,
+    elapsed: Fixed,
+    since_snapshot: Fixed,
+}
+
+impl StatisticsSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> StatisticsSystem {
+        StatisticsSystem {
+            empires: empires,
+            elapsed: 0.into(),
+            since_snapshot: 0.into(),
+        }
+    }
+}
+
+impl System for StatisticsSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, _entities, [
+            components(units: UnitComponent),
+            resource(diplomacy: Diplomacy),
+            resource(players: Players),
+            mut resource(statistics: Statistics),
+        ]);
+
+        // Leave the numbers as they were at the end for the score screens
+        if statistics.game_over() {
+            return;
+        }
+
+        let player_ids = players.player_ids();
+        for player_id in &player_ids {
+            let banked: i32 = match players.player(*player_id) {
+                Some(player) => {
+                    BANKED_RESOURCES.iter()
+                        .map(|resource_type| i32::from(player.stockpile.amount(*resource_type)))
+                        .sum()
+                }
+                None => 0,
+            };
+            let player_statistics = statistics.player_mut(*player_id);
+            player_statistics.military_units = 0;
+            player_statistics.villagers = 0;
+            player_statistics.buildings = 0;
+            player_statistics.resources_banked = banked as u32;
+        }
+
+        let mut player_ids_with_units = BTreeSet::new();
+        for unit in (&units).iter() {
+            if unit.player_id.is_gaia() {
+                continue;
+            }
+            player_ids_with_units.insert(unit.player_id);
+
+            let unit_info = unit.db(&self.empires);
+            let player_statistics = statistics.player_mut(unit.player_id);
+            if unit_info.class().is_military() {
+                player_statistics.military_units += 1;
+            } else if unit_info.class().is_villager() {
+                player_statistics.villagers += 1;
+            } else if unit_info.interaction_mode == dat::InteractionMode::Building {
+                player_statistics.buildings += 1;
+            }
+        }
+        statistics.update_standings(&player_ids_with_units,
+                                    |remaining| diplomacy.is_allied_victory(remaining));
+
+        self.elapsed += time_step;
+        self.since_snapshot += time_step;
+        if statistics.timeline().is_empty() || self.since_snapshot >= TIMELINE_INTERVAL_SECONDS ||
+           statistics.game_over() {
+            self.since_snapshot = 0.into();
+            let non_gaia_ids: Vec<_> = player_ids.into_iter().filter(|id| !id.is_gaia()).collect();
+            statistics.take_snapshot(i32::from(self.elapsed) as u32, &non_gaia_ids);
+        }
+    }
+}
@@ -19,27 +19,63 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use ecs::resource::SystemTimings;
+use logging;
 use specs;
+use std::time::Instant;
 use super::super::world::SystemGroup;
 use types::Fixed;
 
+/// Which of the planner's groups a system runs in
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SystemScope {
+    Game,
+    /// Also runs in the scenario editor, where nothing but the camera should move
+    GameAndEditor,
+}
+
 pub trait System: Send {
     fn update(&mut self, arg: specs::RunArg, time_step: Fixed);
 }
 
-pub struct SystemWrapper(Box<System>);
+pub struct SystemWrapper {
+    system: Box<System>,
+    name: &'static str,
+    scope: SystemScope,
+    timings: SystemTimings,
+}
 
 impl SystemWrapper {
-    pub fn new(system: Box<System>) -> SystemWrapper {
-        SystemWrapper(system)
+    pub fn new(system: Box<System>,
+               name: &'static str,
+               scope: SystemScope,
+               timings: SystemTimings)
+               -> SystemWrapper {
+        SystemWrapper {
+            system: system,
+            name: name,
+            scope: scope,
+            timings: timings,
+        }
+    }
+
+    fn run_system(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        let _span = trace_span!("system", system = self.name, tick = logging::current_tick()).entered();
+        let start = Instant::now();
+        self.system.update(arg, time_step);
+        self.timings.record(self.name, start.elapsed());
     }
 }
 
 impl specs::System<(SystemGroup, Fixed)> for SystemWrapper {
     fn run(&mut self, arg: specs::RunArg, params: (SystemGroup, Fixed)) {
         match params.0 {
-            SystemGroup::Normal => self.0.update(arg, params.1),
+            SystemGroup::Normal => self.run_system(arg, params.1),
+            SystemGroup::Editor if self.scope == SystemScope::GameAndEditor => {
+                self.run_system(arg, params.1)
+            }
             _ => arg.fetch(|_| {}),
         }
     }
 }
+
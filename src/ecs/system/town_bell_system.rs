@@ -0,0 +1,132 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, GarrisonParams};
+use dat;
+use ecs::{GarrisonActionComponent, TransformComponent, UnitComponent};
+use ecs::resource::{ActionBatcher, Notifications, TownBell};
+use specs::{self, Join};
+use std::collections::HashMap;
+use super::System;
+use types::{Fixed, Vector3};
+use util::unit;
+
+const ALL_CLEAR: &'static str = "All clear; the villagers are going back to work";
+const NO_SHELTER: &'static str = "There's nowhere with room for the villagers to take shelter";
+
+struct Shelter {
+    entity: specs::Entity,
+    position: Vector3,
+    /// How many more villagers fit inside
+    room: usize,
+}
+
+/// Rings the town bells that players rang. A bell sends each of its player's villagers to the
+/// nearest of the player's town centers and towers that still has room, setting aside what
+/// they were doing, and ringing it again has them pick that back up, coming out of shelter.
+/// Villagers given another order in the meantime keep to it instead.
+pub struct TownBellSystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl TownBellSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> TownBellSystem {
+        TownBellSystem { empires: empires }
+    }
+}
+
+impl System for TownBellSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(garrisons: GarrisonActionComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut resource(action_batcher: ActionBatcher),
+            mut resource(notifications: Notifications),
+            mut resource(town_bell: TownBell),
+        ]);
+
+        for player_id in town_bell.take_rung() {
+            if let Some(villagers) = town_bell.stop(player_id) {
+                for entity_id in villagers {
+                    action_batcher.queue_for_entity(entity_id, Action::ResumeQueue);
+                }
+                notifications.notify(player_id, ALL_CLEAR.into());
+                continue;
+            }
+
+            // Units already inside or on their way in take up room too
+            let mut taken: HashMap<specs::Index, usize> = HashMap::new();
+            for garrison in garrisons.iter() {
+                *taken.entry(garrison.target.get_id()).or_insert(0) += 1;
+            }
+            let mut shelters = Vec::new();
+            for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+                if unit.player_id != player_id {
+                    continue;
+                }
+                let capacity = unit::garrison_capacity(unit.db(&self.empires));
+                let inside = taken.get(&entity.get_id()).cloned().unwrap_or(0);
+                if capacity > inside {
+                    shelters.push(Shelter {
+                        entity: entity,
+                        position: *transform.position(),
+                        room: capacity - inside,
+                    });
+                }
+            }
+
+            let mut sheltering = Vec::new();
+            for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+                if unit.player_id != player_id || !unit.db(&self.empires).class().is_villager() ||
+                   garrisons.get(entity).is_some() {
+                    continue;
+                }
+                let position = *transform.position();
+                let nearest = shelters.iter_mut()
+                    .filter(|shelter| shelter.room > 0)
+                    .min_by_key(|shelter| distance(&position, &shelter.position));
+                if let Some(shelter) = nearest {
+                    shelter.room -= 1;
+                    action_batcher.queue_for_entity(entity.get_id(), Action::SuspendQueue);
+                    action_batcher.queue_for_entity(entity.get_id(),
+                                                    Action::Garrison(GarrisonParams::new(shelter.entity)));
+                    sheltering.push(entity.get_id());
+                }
+            }
+
+            if sheltering.is_empty() {
+                notifications.notify(player_id, NO_SHELTER.into());
+            } else {
+                notifications.notify(player_id,
+                                     format!("The town bell rings; {} villagers are taking shelter",
+                                             sheltering.len()));
+                town_bell.start(player_id, sheltering);
+            }
+        }
+    }
+}
+
+/// How far apart two positions are, going along the rows and columns, which is close enough
+/// to pick the nearest shelter and can't overflow however far apart they are
+fn distance(a: &Vector3, b: &Vector3) -> Fixed {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
@@ -21,7 +21,7 @@
 
 use action::Action;
 use ecs::component::*;
-use ecs::resource::ActionBatcher;
+use ecs::resource::{ActionBatcher, CommandLog, IdleUnits};
 use specs::{self, Join};
 use super::System;
 use types::Fixed;
@@ -31,20 +31,42 @@ use types::Fixed;
 const TURN_LENGTH_SECONDS: Fixed = fixed_const!(0.1);
 
 macro_rules! detach_action_component {
-    ($action:expr, $entity:expr, $mtps:expr) => {
+    ($action:expr, $entity:expr, $mtps:expr, $gathers:expr, $attack_grounds:expr, $restores:expr,
+     $explores:expr, $garrisons:expr) => {
         match $action {
             Action::MoveToPosition(_) => { $mtps.remove($entity); }
+            Action::Gather(_) => { $gathers.remove($entity); }
+            Action::AttackGround(_) => { $attack_grounds.remove($entity); }
+            Action::Restore(_) => { $restores.remove($entity); }
+            Action::Explore => { $explores.remove($entity); }
+            Action::Garrison(_) => { $garrisons.remove($entity); }
             _ => panic!("Failed to detach unknown action: {:?}", $action)
         }
     }
 }
 
 macro_rules! attach_action_component {
-    ($action:expr, $entity:expr, $mtps:expr) => {
+    ($action:expr, $entity:expr, $mtps:expr, $gathers:expr, $attack_grounds:expr, $restores:expr,
+     $explores:expr, $garrisons:expr) => {
         match $action {
             Action::MoveToPosition(ref params) => {
                 $mtps.insert($entity, MoveToPositionActionComponent::new(params.path.clone()));
             }
+            Action::Gather(ref params) => {
+                $gathers.insert($entity, GatherActionComponent::new(params.target));
+            }
+            Action::AttackGround(ref params) => {
+                $attack_grounds.insert($entity, AttackGroundActionComponent::new(params.position));
+            }
+            Action::Restore(ref params) => {
+                $restores.insert($entity, RestoreActionComponent::new(params.target));
+            }
+            Action::Explore => {
+                $explores.insert($entity, ExploreActionComponent::new());
+            }
+            Action::Garrison(ref params) => {
+                $garrisons.insert($entity, GarrisonActionComponent::new(params.target));
+            }
             _ => panic!("Failed to attach unknown action: {:?}", $action)
         }
     }
@@ -67,8 +89,15 @@ impl System for UnitActionSystem {
     fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
         fetch_components!(arg, entities, [
             mut components(action_queues: ActionQueueComponent),
+            mut components(attack_grounds: AttackGroundActionComponent),
+            mut components(explores: ExploreActionComponent),
+            mut components(garrisons: GarrisonActionComponent),
+            mut components(gathers: GatherActionComponent),
             mut components(mtps: MoveToPositionActionComponent),
+            mut components(restores: RestoreActionComponent),
             mut resource(action_batcher: ActionBatcher),
+            mut resource(command_log: CommandLog),
+            mut resource(idle_units: IdleUnits),
         ]);
 
         self.turn_accumulator += time_step;
@@ -79,8 +108,11 @@ impl System for UnitActionSystem {
             for (entity, action_queue) in (&entities, &mut action_queues).iter() {
                 if let Some(actions) = action_batch.get(&entity.get_id()) {
                     for action in actions {
+                        command_log.record(format!("entity {}: {:?}", entity.get_id(), action));
                         match *action {
                             Action::ClearQueue => action_queue.clear(),
+                            Action::SuspendQueue => action_queue.suspend(),
+                            Action::ResumeQueue => action_queue.resume(),
                             _ => action_queue.add(action.clone()),
                         }
                     }
@@ -94,13 +126,35 @@ impl System for UnitActionSystem {
             // by creating a component on that entity for the action type.
             // Handle the actual action via separate systems.
             if action_queue.current_action_done() {
+                let was_idle = action_queue.current_action().is_none();
                 if let &Some(ref action) = action_queue.current_action() {
-                    detach_action_component!(*action, entity, &mut mtps);
+                    detach_action_component!(*action,
+                                             entity,
+                                             &mut mtps,
+                                             &mut gathers,
+                                             &mut attack_grounds,
+                                             &mut restores,
+                                             &mut explores,
+                                             &mut garrisons);
                 }
                 action_queue.next_action();
 
                 if let &Some(ref action) = action_queue.current_action() {
-                    attach_action_component!(*action, entity, &mut mtps);
+                    attach_action_component!(*action,
+                                             entity,
+                                             &mut mtps,
+                                             &mut gathers,
+                                             &mut attack_grounds,
+                                             &mut restores,
+                                             &mut explores,
+                                             &mut garrisons);
+                }
+
+                let is_idle = action_queue.current_action().is_none();
+                if is_idle && !was_idle {
+                    idle_units.report_idle(entity);
+                } else if !is_idle && was_idle {
+                    idle_units.report_busy(entity);
                 }
             }
         }
@@ -19,118 +19,408 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use action::{Action, MoveToPositionParams};
+use action::{Action, GatherParams, MoveToPositionParams, RestoreParams};
+use config::KeyAction;
 use dat;
-use ecs::{DecalComponent, OnScreenComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
+use ecs::{DecalComponent, HitPointsComponent, OnScreenComponent, ProductionQueueComponent,
+          ResourceNodeComponent, SelectedUnitComponent, TransformComponent, UnitComponent};
 use ecs::resource::*;
-use media::{KeyState, MouseButton};
+use identifier::UnitTerrainRestrictionId;
+use input::InputActions;
+use media::KeyState;
 use resource::DrsKey;
 use specs::{self, Join};
 use super::System;
-use types::{Fixed, Vector3};
+use types::{AABox, Fixed, Rect, Vector3};
+use util::formation::{self, FormationTile};
+use util::pointer::calculate_mouse_ray;
 use util::unit;
 
+/// Clicking the same unit twice within this many seconds selects every unit of its type on screen,
+/// up to what a selection holds
+const DOUBLE_CLICK_SECONDS: Fixed = fixed_const!(0.4);
+
+const TILE_MIDDLE: Fixed = fixed_const!(0.5);
+
 pub struct UnitSelectionSystem {
     empires: dat::EmpiresDbRef,
+    last_clicked: Option<specs::Index>,
+    time_since_click: Fixed,
 }
 
 impl UnitSelectionSystem {
     pub fn new(empires: dat::EmpiresDbRef) -> UnitSelectionSystem {
-        UnitSelectionSystem { empires: empires }
+        UnitSelectionSystem {
+            empires: empires,
+            last_clicked: None,
+            time_since_click: 0.into(),
+        }
     }
 }
 
 impl System for UnitSelectionSystem {
-    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
         fetch_components!(arg, entities, [
+            components(hit_points: HitPointsComponent),
             components(on_screen: OnScreenComponent),
+            components(production_queues: ProductionQueueComponent),
+            components(resource_nodes: ResourceNodeComponent),
             components(units: UnitComponent),
             mut components(decals: DecalComponent),
             mut components(selected_units: SelectedUnitComponent),
             mut components(transforms: TransformComponent),
-            resource(mouse_state: MouseState),
+            resource(chat: Chat),
+            resource(debug_overlay: DebugOverlay),
+            resource(diplomacy: Diplomacy),
+            resource(input_actions: InputActions),
             resource(path_finder: PathFinder),
             resource(players: Players),
+            resource(selection_priority: SelectionPriority),
             resource(view_projector: ViewProjector),
             resource(viewport: Viewport),
             resource(occupied_tiles: OccupiedTiles),
             resource(terrain: Terrain),
+            resource(unit_index: UnitIndex),
             mut resource(action_batcher: ActionBatcher),
+            mut resource(entity_inspector: EntityInspector),
+            mut resource(feedback_effects: FeedbackEffects),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(production_orders: ProductionOrders),
+            mut resource(selection_subgroup: SelectionSubgroup),
+            mut resource(unit_voices: UnitVoices),
         ]);
+        let key_state = |action| input_actions.action_state(action);
+        let typing = chat.open || debug_overlay.console_open;
+        self.time_since_click += time_step;
 
-        if mouse_state.key_states.key_state(MouseButton::Left) == KeyState::TransitionUp {
-            selected_units.clear();
+        if !typing && key_state(KeyAction::NextSubgroup) == KeyState::TransitionDown {
+            let unit_ids: Vec<_> = (&units, &selected_units).iter().map(|(unit, _)| unit.unit_id).collect();
+            selection_subgroup.cycle(&unit_ids);
+        }
 
-            let mouse_ray = calculate_mouse_ray(&viewport, &mouse_state, &view_projector, &terrain);
+        // A selection is only ever one player's units
+        let selected_player_id = (&units, &selected_units).iter().map(|(unit, _)| unit.player_id).next();
+
+        // A finished selection box picks what's drawn inside it, the kinds of unit the selection
+        // priority puts first
+        if let Some(selection_box) = input_actions.finished_drag_box {
+            let local_player_id = players.local_player().player_id;
+            let viewport_pos = viewport.top_left_i32();
+            let mut candidates = Vec::new();
             for (entity, _, unit, transform) in (&entities, &on_screen, &units, &transforms).iter() {
                 let unit_info = self.empires.unit(unit.civilization_id, unit.unit_id);
-                if unit_info.interaction_mode != dat::InteractionMode::NonInteracting {
-                    let unit_box = unit::selection_box(unit_info, transform);
+                let class = match SelectionClass::of(unit_info) {
+                    Some(class) => class,
+                    None => continue,
+                };
+                let mut bounds = screen_bounds(&view_projector, &unit::selection_box(unit_info, transform));
+                bounds.translate(-viewport_pos.x, -viewport_pos.y);
+                if selection_box.intersects(&bounds) {
+                    candidates.push(SelectionCandidate {
+                        id: entity,
+                        player_id: unit.player_id,
+                        class: class,
+                    });
+                }
+            }
+            let mut picked = selection_priority.pick(&candidates, local_player_id);
+            let picked_player_id =
+                picked.first().and_then(|&entity| units.get(entity)).map(|unit| unit.player_id);
+
+            let adding = !typing && key_state(KeyAction::AddToSelection).is_down();
+            if !adding || (picked_player_id.is_some() && picked_player_id != selected_player_id) {
+                selected_units.clear();
+            }
+            selection_subgroup.clear();
+            picked.truncate(MAX_SELECTED_UNITS.saturating_sub((&selected_units).iter().count()));
+            for &entity in &picked {
+                selected_units.insert(entity, SelectedUnitComponent);
+            }
+            if picked_player_id == Some(local_player_id) && !picked.is_empty() {
+                let unit = units.get(picked[0]).unwrap();
+                unit_voices.acknowledge(unit.db(&self.empires), Acknowledgment::Selected);
+            }
+            self.last_clicked = None;
+            return;
+        }
+
+        let left_clicked = input_actions.select == KeyState::TransitionUp;
+        let right_clicked = input_actions.command == KeyState::TransitionUp;
+        if !left_clicked && !right_clicked {
+            return;
+        }
+
+        let mut clicked = None;
+        let mouse_ray = calculate_mouse_ray(&viewport, &input_actions.pointer, &view_projector, &terrain);
+        for (entity, _, unit, transform) in (&entities, &on_screen, &units, &transforms).iter() {
+            let unit_info = self.empires.unit(unit.civilization_id, unit.unit_id);
+            if unit_info.interaction_mode != dat::InteractionMode::NonInteracting {
+                let unit_box = unit::selection_box(unit_info, transform);
 
-                    // Cast a ray from the mouse position through to the terrain and select any unit
-                    // whose axis-aligned box intersects the ray.
-                    if unit_box.intersects_ray(&mouse_ray.origin, &mouse_ray.direction) {
+                // Cast a ray from the mouse position through to the terrain and pick any unit
+                // whose axis-aligned box intersects the ray.
+                if unit_box.intersects_ray(&mouse_ray.origin, &mouse_ray.direction) {
+                    clicked = Some((entity, unit.clone()));
+                    break;
+                }
+            }
+        }
+
+        // With the debug overlay open, holding the inspect key picks what to inspect instead of
+        // changing the selection; clicking the ground closes the inspector
+        if left_clicked && debug_overlay.visible && !typing &&
+           key_state(KeyAction::InspectEntity).is_down() {
+            entity_inspector.inspect(clicked.as_ref().map(|&(entity, _)| entity.get_id()));
+            return;
+        }
+
+        if left_clicked {
+            let double_click = clicked.is_some() && self.time_since_click < DOUBLE_CLICK_SECONDS &&
+                               self.last_clicked == clicked.as_ref().map(|&(entity, _)| entity.get_id());
+            // Add to the selection holds onto it, toggle picks units out of it or back in, and
+            // both together pick up every unit of the type, like a double click
+            let toggle_held = !typing && key_state(KeyAction::ToggleSelected).is_down();
+            let add_held = !typing && key_state(KeyAction::AddToSelection).is_down();
+            let select_type = clicked.is_some() &&
+                              ((toggle_held && add_held) || (double_click && !toggle_held));
+            let toggling = toggle_held && !select_type;
+            let adding = toggle_held || add_held;
+            let other_player = match clicked {
+                Some((_, ref clicked_unit)) => {
+                    selected_player_id.map_or(false, |player_id| player_id != clicked_unit.player_id)
+                }
+                None => false,
+            };
+            if !adding || other_player {
+                selected_units.clear();
+            }
+            selection_subgroup.clear();
+
+            match clicked {
+                // Select the player's units of the same type on screen, like every barracks, the
+                // ones nearest the clicked one first if there are more than the selection holds
+                Some((clicked_entity, ref clicked_unit)) if select_type => {
+                    let class_tags = [UnitTag::Villager, UnitTag::Military, UnitTag::Building];
+                    let indexed = unit::tags(&self.empires, clicked_unit)
+                        .into_iter()
+                        .find(|tag| class_tags.contains(tag))
+                        .map(|tag| unit_index.entities(clicked_unit.player_id, tag).to_vec());
+                    // Units in none of the index's classes, like priests and fishing boats, are
+                    // looked for among everything on screen
+                    let candidates = indexed.unwrap_or_else(|| {
+                        (&entities, &on_screen).iter().map(|(entity, _)| entity).collect()
+                    });
+                    let center = transforms.get(clicked_entity).unwrap().position();
+                    let mut same_type: Vec<_> = candidates.into_iter()
+                        .filter(|&entity| {
+                            on_screen.get(entity).is_some() && selected_units.get(entity).is_none()
+                        })
+                        .filter_map(|entity| match (units.get(entity), transforms.get(entity)) {
+                            (Some(unit), Some(transform)) if unit.player_id == clicked_unit.player_id &&
+                                                             unit.unit_id == clicked_unit.unit_id => {
+                                let offset = *transform.position() - *center;
+                                Some((offset.x * offset.x + offset.y * offset.y, entity))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    same_type.sort_by_key(|&(distance, entity)| (distance, entity.get_id()));
+                    let room = MAX_SELECTED_UNITS.saturating_sub((&selected_units).iter().count());
+                    for &(_, entity) in same_type.iter().take(room) {
+                        selected_units.insert(entity, SelectedUnitComponent);
+                    }
+                }
+                Some((entity, _)) if toggling && selected_units.get(entity).is_some() => {
+                    selected_units.remove(entity);
+                }
+                Some((entity, _)) => {
+                    if (&selected_units).iter().count() < MAX_SELECTED_UNITS {
                         selected_units.insert(entity, SelectedUnitComponent);
-                        break;
                     }
                 }
+                None => {}
+            }
+            // The player's own units answer when they're picked
+            if let Some((entity, ref clicked_unit)) = clicked {
+                if clicked_unit.player_id == players.local_player().player_id &&
+                   selected_units.get(entity).is_some() {
+                    unit_voices.acknowledge(clicked_unit.db(&self.empires), Acknowledgment::Selected);
+                }
             }
+            self.last_clicked = clicked.as_ref().map(|&(entity, _)| entity.get_id());
+            self.time_since_click = 0.into();
         }
 
-        if mouse_state.key_states.key_state(MouseButton::Right) == KeyState::TransitionUp {
-            let mouse_ray = calculate_mouse_ray(&viewport, &mouse_state, &view_projector, &terrain);
-            let mut moving_unit = false;
-            for (entity, transform, unit, _selected_unit) in (&entities, &transforms, &units, &selected_units).iter() {
+        if right_clicked {
+            let rally_point = match clicked {
+                Some((target, _)) => RallyPoint::Unit(target.get_id()),
+                None => RallyPoint::Position(mouse_ray.world_coord),
+            };
+            // Animals with meat on them get hunted
+            let gather_target = match clicked {
+                Some((target, ref target_unit)) if resource_nodes.get(target).is_some() ||
+                                                   unit::is_huntable(&self.empires, target_unit) => {
+                    Some((target, target_unit.db(&self.empires).class()))
+                }
+                _ => None,
+            };
+            // The player's own units that are hurt or damaged can be healed or repaired
+            let local_player_id = players.local_player().player_id;
+            let restore_target = match clicked {
+                Some((target, ref target_unit)) if target_unit.player_id == local_player_id => {
+                    let target_info = target_unit.db(&self.empires);
+                    let max_hit_points: Fixed = target_info.hit_points().into();
+                    match hit_points.get(target) {
+                        Some(target_hit_points) if target_hit_points.hit_points < max_hit_points => {
+                            Some((target, target_info))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            let mut movers = Vec::new();
+            let mut ordered = false;
+            let mut answering = None;
+            let selected = (&entities, &transforms, &units, &selected_units);
+            for (entity, transform, unit, _selected_unit) in selected.iter() {
                 if unit.player_id != players.local_player().player_id {
                     continue;
                 }
 
+                // Buildings stay put; right-clicking sets where their trained units go instead,
+                // and right-clicking the building itself clears that
+                if production_queues.get(entity).is_some() {
+                    let rally_point = match clicked {
+                        Some((target, _)) if target == entity => None,
+                        _ => Some(rally_point),
+                    };
+                    production_orders.queue_order(entity.get_id(),
+                                                  ProductionOrderKind::SetRallyPoint(rally_point));
+                    continue;
+                }
+
                 let unit_info = self.empires.unit(unit.civilization_id, unit.unit_id);
-                let path = path_finder.find_path(&*terrain,
+                answering = answering.or(Some(unit_info));
+                if let Some((target, target_class)) = gather_target {
+                    if unit::can_gather_from(unit_info, target_class) {
+                        action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+                        action_batcher.queue_for_entity(entity.get_id(),
+                                                        Action::Gather(GatherParams::new(target)));
+                        ordered = true;
+                        continue;
+                    }
+                }
+                if let Some((target, target_info)) = restore_target {
+                    if target != entity && unit::can_restore(unit_info, target_info) {
+                        action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+                        action_batcher.queue_for_entity(entity.get_id(),
+                                                        Action::Restore(RestoreParams::new(target)));
+                        ordered = true;
+                        continue;
+                    }
+                }
+                movers.push((entity, *transform.position(), unit_info.terrain_restriction));
+            }
+
+            let destinations = formation_destinations(&movers,
+                                                      &mouse_ray.world_coord,
+                                                      &path_finder,
+                                                      &terrain,
+                                                      &occupied_tiles);
+            for (&(entity, ref position, restriction_id), destination) in movers.iter().zip(destinations) {
+                let path = path_scheduler.find_path(&*path_finder,
+                                                    &*terrain,
                                                     &*occupied_tiles,
-                                                    transform.position(),
-                                                    &mouse_ray.world_coord,
-                                                    unit_info.terrain_restriction);
+                                                    entity.get_id(),
+                                                    position,
+                                                    &destination,
+                                                    restriction_id);
                 action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
                 action_batcher.queue_for_entity(entity.get_id(),
                                                 Action::MoveToPosition(MoveToPositionParams::new(path)));
-                moving_unit = true;
             }
 
-            if moving_unit {
-                let decal = arg.create();
-                transforms.insert(decal,
-                                  TransformComponent::new(mouse_ray.world_coord, 0.into()));
-                decals.insert(decal,
-                              DecalComponent::new(0.into(), DrsKey::Interfac, 50405.into()));
+            // A red flag goes on an enemy that units were sent at, and the game's own flag decal
+            // wherever else; the first of the units answers for the group
+            if ordered || !movers.is_empty() {
+                let acknowledgment = match clicked {
+                    Some((target, ref target_unit)) if !target_unit.player_id.is_gaia() &&
+                                                       diplomacy.is_enemy(local_player_id,
+                                                                          target_unit.player_id) => {
+                        let position = *transforms.get(target).unwrap().position();
+                        feedback_effects.command_flag(position, true);
+                        Acknowledgment::Attacked
+                    }
+                    _ => {
+                        let decal = arg.create();
+                        transforms.insert(decal, TransformComponent::new(mouse_ray.world_coord, 0.into()));
+                        decals.insert(decal, DecalComponent::new(0.into(), DrsKey::Interfac, 50405.into()));
+                        Acknowledgment::Moved
+                    }
+                };
+                if let Some(unit_info) = answering {
+                    unit_voices.acknowledge(unit_info, acknowledgment);
+                }
             }
         }
     }
 }
 
-struct MouseRay {
-    world_coord: Vector3,
-    origin: Vector3,
-    direction: Vector3,
+/// The area of the screen that the box covers, around all eight of its corners
+fn screen_bounds(view_projector: &ViewProjector, world_box: &AABox) -> Rect {
+    let start = view_projector.project(&world_box.min);
+    let mut bounds = Rect::of(start.x, start.y, 0, 0);
+    for &x in &[world_box.min.x, world_box.max.x] {
+        for &y in &[world_box.min.y, world_box.max.y] {
+            for &z in &[world_box.min.z, world_box.max.z] {
+                let corner = view_projector.project(&Vector3::new(x, y, z));
+                bounds.extend(&Rect::of(corner.x, corner.y, 0, 0));
+            }
+        }
+    }
+    bounds
 }
 
-fn calculate_mouse_ray(viewport: &Viewport,
-                       mouse_state: &MouseState,
-                       view_projector: &ViewProjector,
-                       terrain: &Terrain)
-                       -> MouseRay {
-    let viewport_pos = viewport.top_left_i32();
-    let mouse_pos = mouse_state.position + viewport_pos;
-
-    // "Origin elevation" just needs to be a bit taller than the max terrain elevation
-    let origin_elevation: Fixed = Fixed::from(terrain.elevation_range().1) * 2.into();
-    let world_coord = view_projector.unproject(&mouse_pos, &*terrain);
-    let origin = view_projector.unproject_at_elevation(&mouse_pos, origin_elevation);
-    let direction = world_coord - origin;
-
-    MouseRay {
-        world_coord: world_coord,
-        origin: origin,
-        direction: direction,
+/// Where each of the moving units goes: one unit goes right where it was sent, and a group
+/// spreads out into a box around it, one unit to a tile
+fn formation_destinations(movers: &[(specs::Entity, Vector3, UnitTerrainRestrictionId)],
+                          target: &Vector3,
+                          path_finder: &PathFinder,
+                          terrain: &Terrain,
+                          occupied_tiles: &OccupiedTiles)
+                          -> Vec<Vector3> {
+    if movers.len() < 2 {
+        return movers.iter().map(|_| *target).collect();
     }
+
+    let tile_of = |position: &Vector3| -> FormationTile { (position.y.into(), position.x.into()) };
+    let unit_tiles: Vec<FormationTile> = movers.iter()
+        .map(|&(_, ref position, _)| tile_of(position))
+        .collect();
+    let center = tile_of(target);
+    let (row_sum, col_sum) = unit_tiles.iter().fold((0, 0), |sum, tile| (sum.0 + tile.0, sum.1 + tile.1));
+    let count = unit_tiles.len() as i32;
+    let heading = (center.0 - row_sum / count, center.1 - col_sum / count);
+
+    // Every unit in the group has to be able to stand on every tile, since any of them could
+    // be given it
+    let free = |(row, col): FormationTile| {
+        if row < 0 || col < 0 || row >= terrain.height() || col >= terrain.width() ||
+           occupied_tiles.tiles.contains(&(row, col)) {
+            return false;
+        }
+        let terrain_id = terrain.tile_at_row_col(row, col).terrain_id;
+        movers.iter().all(|&(_, _, restriction_id)| path_finder.passable(restriction_id, terrain_id))
+    };
+    let tiles = formation::formation_tiles(center, movers.len(), heading, free);
+    formation::assign_tiles(&unit_tiles, &tiles)
+        .into_iter()
+        .map(|tile| match tile {
+            Some((row, col)) => {
+                Vector3::new(Fixed::from(col) + TILE_MIDDLE, Fixed::from(row) + TILE_MIDDLE, target.z)
+            }
+            None => *target,
+        })
+        .collect()
 }
@@ -0,0 +1,78 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::{TransformComponent, UnitComponent};
+use ecs::resource::{Diplomacy, FogOfWar, Players, Sight, TickArena};
+use specs::{self, Join};
+use std::collections::HashSet;
+use super::System;
+use types::Fixed;
+
+/// Keeps track of what every player's units can see, and whose sight each player gets to share.
+/// Players share their allies' vision once they have researched Writing, or from the start
+/// if the game was set up with allied vision. It runs with the simulation, since explore orders
+/// go by what each player has explored.
+pub struct VisibilitySystem {
+    empires: dat::EmpiresDbRef,
+}
+
+impl VisibilitySystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> VisibilitySystem {
+        VisibilitySystem { empires: empires }
+    }
+}
+
+impl System for VisibilitySystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            resource(diplomacy: Diplomacy),
+            resource(players: Players),
+            resource(tick_arena: TickArena),
+            mut resource(fog_of_war: FogOfWar),
+        ]);
+
+        let player_ids = players.player_ids();
+        for viewer_id in &player_ids {
+            let owner_ids = player_ids.iter()
+                .cloned()
+                .filter(|owner_id| diplomacy.shares_vision(*viewer_id, *owner_id))
+                .collect();
+            fog_of_war.set_sharing(*viewer_id, owner_ids);
+        }
+
+        // Only the units that moved to another tile, changed hands, or appeared get recounted
+        let mut sighted = tick_arena.take::<HashSet<u32>>();
+        for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+            let line_of_sight = unit.db(&self.empires).line_of_sight();
+            if line_of_sight <= 0. || !player_ids.contains(&unit.player_id) {
+                continue;
+            }
+            let position = transform.position();
+            let sight = Sight::new(position.y.into(), position.x.into(), line_of_sight.ceil() as i32);
+            fog_of_war.set_unit_sight(entity.get_id(), unit.player_id, Some(sight));
+            sighted.insert(entity.get_id());
+        }
+        fog_of_war.retain_units(|entity_id| sighted.contains(&entity_id));
+    }
+}
@@ -0,0 +1,202 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use dat;
+use ecs::{SelectedUnitComponent, UnitComponent};
+use ecs::resource::*;
+use input::InputActions;
+use media::KeyState;
+use resource::{DrsKey, ShapeMetadataKey, ShapeMetadataStoreRef};
+use specs::{self, Join};
+use super::System;
+use types::{Fixed, Vector3};
+use util::placement;
+
+const TILE_MIDDLE: Fixed = fixed_const!(0.5);
+
+/// Lays out walls for the local player. The build wall key turns it on while one of their
+/// villagers is selected; dragging with select then plans a wall along the tiles under the
+/// drag, and letting go orders every segment built by the selected villagers. Command or the build wall
+/// key again calls it off. While it's on, it takes the select and command input for itself,
+/// so it has to run before the selection system.
+pub struct WallPlacementSystem {
+    empires: dat::EmpiresDbRef,
+    shape_metadata: ShapeMetadataStoreRef,
+}
+
+impl WallPlacementSystem {
+    pub fn new(empires: dat::EmpiresDbRef, shape_metadata: ShapeMetadataStoreRef) -> WallPlacementSystem {
+        WallPlacementSystem {
+            empires: empires,
+            shape_metadata: shape_metadata,
+        }
+    }
+}
+
+impl System for WallPlacementSystem {
+    fn update(&mut self, arg: specs::RunArg, _time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(selected_units: SelectedUnitComponent),
+            components(units: UnitComponent),
+            resource(chat: Chat),
+            resource(command_panel: CommandPanel),
+            resource(debug_overlay: DebugOverlay),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            resource(view_projector: ViewProjector),
+            resource(viewport: Viewport),
+            resource(players: Players),
+            mut resource(build_orders: BuildOrders),
+            mut resource(input_actions: InputActions),
+            mut resource(notifications: Notifications),
+            mut resource(wall_placement: WallPlacement),
+        ]);
+        let local_player_id = players.local_player_id();
+
+        // Placing for a civilization's own wall, so it goes with the first selected villager
+        let villagers: Vec<_> = (&entities, &units, &selected_units)
+            .iter()
+            .filter(|&(_, unit, _)| {
+                unit.player_id == local_player_id &&
+                self.empires.unit(unit.civilization_id, unit.unit_id).class().is_villager()
+            })
+            .map(|(entity, unit, _)| (entity.get_id(), unit))
+            .collect();
+        let builder = villagers.first().map(|&(_, unit)| unit.clone());
+
+        // On the command panel's build menu the key may well be a building's instead
+        let typing = chat.open || debug_overlay.console_open;
+        let on_build_menu = command_panel.page == CommandPage::Build;
+        if !typing && !on_build_menu &&
+           input_actions.action_state(KeyAction::BuildWall) == KeyState::TransitionDown {
+            if wall_placement.is_placing() {
+                wall_placement.cancel();
+            } else if let Some(ref builder) = builder {
+                if self.empires.civilization(builder.civilization_id).unit_available(WALL_UNIT_ID.into()) {
+                    wall_placement.begin(WALL_UNIT_ID.into());
+                } else {
+                    notifications.notify(local_player_id, "Walls can't be built".to_string());
+                }
+            }
+        }
+
+        let (builder, wall_unit_id) = match (builder, wall_placement.unit_id) {
+            (Some(builder), Some(wall_unit_id)) => (builder, wall_unit_id),
+            _ => {
+                // Losing the villager from the selection calls the wall off
+                wall_placement.cancel();
+                return;
+            }
+        };
+
+        if input_actions.command == KeyState::TransitionUp {
+            wall_placement.cancel();
+            consume_input(&mut input_actions);
+            return;
+        }
+
+        let wall_info = self.empires.unit(builder.civilization_id, wall_unit_id);
+        let pointer = view_projector.unproject(&(input_actions.pointer + viewport.top_left_i32()), &*terrain);
+        let pointer_tile: WallTile = (pointer.y.into(), pointer.x.into());
+        if input_actions.select == KeyState::TransitionDown {
+            wall_placement.start = Some(pointer_tile);
+        }
+
+        if let Some(start) = wall_placement.start {
+            let plan = WallPlan::new(start, pointer_tile, |(row, col)| {
+                !placement::check_game_placement(&terrain,
+                                                 &occupied_tiles,
+                                                 wall_info,
+                                                 col as f32 + 0.5,
+                                                 row as f32 + 0.5,
+                                                 |terrain_id| {
+                                                     path_finder.passable(wall_info.terrain_restriction,
+                                                                          terrain_id)
+                                                 })
+                    .is_allowed()
+            });
+            let segment_cost: Vec<_> = wall_info.train_costs()
+                .iter()
+                .filter(|cost| cost.enabled && cost.amount > 0)
+                .map(|cost| (cost.resource_type, Fixed::from(cost.amount as i32)))
+                .collect();
+            wall_placement.cost = plan.cost(&segment_cost);
+            wall_placement.plan = Some(plan);
+        }
+
+        // Letting go at the end of a drag finishes a drag box rather than clicking
+        let released = input_actions.select == KeyState::TransitionUp ||
+                       input_actions.finished_drag_box.is_some();
+        consume_input(&mut input_actions);
+        if !released {
+            return;
+        }
+
+        let plan = match wall_placement.plan.take() {
+            Some(plan) => plan,
+            None => return,
+        };
+        wall_placement.start = None;
+        if plan.segments.is_empty() {
+            return;
+        }
+        let shape_count = wall_info.standing_graphic
+            .and_then(|graphic_id| self.empires.graphic(graphic_id).slp_id)
+            .and_then(|slp_id| self.shape_metadata.get(&ShapeMetadataKey::new(DrsKey::Graphics, slp_id)))
+            .map(|shape_metadata| shape_metadata.shape_count as u16)
+            .unwrap_or(0);
+        let sites = plan.segments
+            .iter()
+            .map(|segment| {
+                let (row, col) = segment.tile;
+                let elevation = terrain.tile_at_row_col(row, col).elevation;
+                // Graphics without a shape for the piece fall back on their first one
+                let shape_index = segment.piece.shape_index();
+                BuildSite {
+                    position: Vector3::new(Fixed::from(col) + TILE_MIDDLE,
+                                           Fixed::from(row) + TILE_MIDDLE,
+                                           elevation.into()),
+                    frame: if shape_index < shape_count { shape_index } else { 0 },
+                }
+            })
+            .collect();
+        // The BuildOrderSystem pays for every segment and lays them all down as foundations
+        build_orders.queue_order(BuildOrder {
+            player_id: local_player_id,
+            civilization_id: builder.civilization_id,
+            unit_id: wall_unit_id,
+            sites: sites,
+            builders: villagers.iter().map(|&(entity_id, _)| entity_id).collect(),
+        });
+        info!(segments = plan.segments.len(), "Ordered a wall");
+        wall_placement.cancel();
+    }
+}
+
+/// Keeps the selection system from acting on the input that went into placing the wall
+fn consume_input(input_actions: &mut InputActions) {
+    input_actions.select = KeyState::Up;
+    input_actions.command = KeyState::Up;
+    input_actions.drag_box = None;
+    input_actions.finished_drag_box = None;
+}
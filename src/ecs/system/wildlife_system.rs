@@ -0,0 +1,344 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, MoveToPositionParams};
+use dat::{self, UnitClass};
+use ecs::{ATTACK_COOLDOWN, ActionQueueComponent, CooldownComponent, HitPointsComponent, ProvokedComponent,
+          TransformComponent, UnitComponent};
+use ecs::resource::{ActionBatcher, CombatEvent, CombatLog, ElevationModifier, OccupiedTiles, PathFinder,
+                    PathScheduler, RandomNumbers, RandomStream, Terrain};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use specs::{self, Join};
+use std::collections::HashMap;
+use super::System;
+use types::{Fixed, Norm, Vector3};
+use util::steering::within;
+use util::unit;
+
+/// Animals don't need to react every frame, so only think a couple of times a second
+const THINK_INTERVAL: Fixed = fixed_const!(0.5);
+
+/// Distance (in tiles) at which prey notices soldiers and runs from them
+const FLEE_RADIUS: i32 = 4;
+
+/// How far prey runs once it gets spooked
+const FLEE_DISTANCE: Fixed = fixed_const!(6);
+
+/// Distance (in tiles) at which predators go after villagers
+const AGGRO_RADIUS: i32 = 6;
+
+/// How far an animal that fights back chases whatever attacked it, or a predator chases its
+/// prey, before giving up
+const CHASE_DISTANCE: Fixed = fixed_const!(10);
+
+/// How close to the edge of its attacker an animal has to get to strike it
+const STRIKE_REACH: Fixed = fixed_const!(0.5);
+
+/// Fish drift around within this distance of where they are
+const SCHOOL_RADIUS: i32 = 2;
+
+/// One in this many idle fish will move on each think
+const SCHOOL_MOVE_CHANCE: u32 = 8;
+
+#[derive(Copy, Clone)]
+struct NearbyUnit {
+    position: Vector3,
+    villager: bool,
+}
+
+/// Drives the gaia animals: prey flees from nearby soldiers and from hunters that hit it,
+/// predators hunt down villagers, animals that can fight, like elephants, strike back at whatever
+/// attacked them, and fish idly drift around. All of their movement goes through the
+/// ActionBatcher like any other unit order.
+pub struct WildlifeSystem {
+    empires: dat::EmpiresDbRef,
+    think_accumulator: Fixed,
+}
+
+impl WildlifeSystem {
+    pub fn new(empires: dat::EmpiresDbRef) -> WildlifeSystem {
+        WildlifeSystem {
+            empires: empires,
+            think_accumulator: 0.into(),
+        }
+    }
+}
+
+impl System for WildlifeSystem {
+    fn update(&mut self, arg: specs::RunArg, time_step: Fixed) {
+        fetch_components!(arg, entities, [
+            components(action_queues: ActionQueueComponent),
+            components(transforms: TransformComponent),
+            components(units: UnitComponent),
+            mut components(cooldowns: CooldownComponent),
+            mut components(hit_points: HitPointsComponent),
+            mut components(provoked: ProvokedComponent),
+            resource(grid: GridPartition),
+            resource(occupied_tiles: OccupiedTiles),
+            resource(path_finder: PathFinder),
+            resource(terrain: Terrain),
+            mut resource(action_batcher: ActionBatcher),
+            mut resource(combat_log: CombatLog),
+            mut resource(path_scheduler: PathScheduler),
+            mut resource(random: RandomNumbers),
+        ]);
+
+        self.think_accumulator += time_step;
+        if self.think_accumulator < THINK_INTERVAL {
+            return;
+        }
+        self.think_accumulator -= THINK_INTERVAL;
+
+        // Units that animals care about, by entity ID, so that grid query results can be looked up
+        let mut nearby_units = HashMap::new();
+        let mut nearby_entities = HashMap::new();
+        for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+            if !unit.player_id.is_gaia() {
+                let class = unit.db(&self.empires).class();
+                if class.is_villager() || class.is_military() {
+                    nearby_entities.insert(entity.get_id(), entity);
+                    nearby_units.insert(entity.get_id(),
+                                        NearbyUnit {
+                                            position: *transform.position(),
+                                            villager: class.is_villager(),
+                                        });
+                }
+            }
+        }
+
+        let mut calmed = Vec::new();
+        let items = (&entities, &units, &transforms, &action_queues);
+        for (entity, unit, transform, action_queue) in items.iter() {
+            if !unit.player_id.is_gaia() {
+                continue;
+            }
+
+            let position = *transform.position();
+            let unit_info = unit.db(&self.empires);
+
+            // Animals that have been attacked deal with their attacker before anything else, and
+            // predators that have picked out a villager keep after it the same way
+            let mut reacted = false;
+            let mut reaction_destination = None;
+            if let Some(provocation) = provoked.get_mut(entity) {
+                let attacker = provocation.attacker;
+                let attacker_alive = hit_points.get(attacker)
+                    .map_or(false, |hit_points| !hit_points.is_destroyed());
+                match (transforms.get(attacker), units.get(attacker)) {
+                    (Some(attacker_transform), Some(attacker_unit)) if attacker_alive => {
+                        let attacker_position = *attacker_transform.position();
+                        let attacker_info = attacker_unit.db(&self.empires);
+                        if !unit_info.attacks().is_empty() {
+                            reacted = true;
+                            let reach = size(unit_info) + size(attacker_info) + STRIKE_REACH +
+                                        Fixed::from(unit_info.max_range());
+                            if !within(&position, &attacker_position, reach) {
+                                if within(&position, &attacker_position, CHASE_DISTANCE) {
+                                    reaction_destination = Some(attacker_position);
+                                } else {
+                                    calmed.push(entity);
+                                }
+                            } else {
+                                if !action_queue.is_idle() {
+                                    action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+                                }
+                                let ready = cooldowns.get(entity)
+                                    .map_or(false, |cooldown| cooldown.is_ready(ATTACK_COOLDOWN));
+                                if ready {
+                                    cooldowns.get_mut(entity)
+                                        .unwrap()
+                                        .start(ATTACK_COOLDOWN, unit::reload_time(unit_info));
+                                    let elevation = terrain.tile_at(position).elevation;
+                                    let attacker_elevation = terrain.tile_at(attacker_position).elevation;
+                                    let modifier = ElevationModifier::between(elevation, attacker_elevation);
+                                    let damage = unit::damage(unit_info.attacks(), attacker_info.armors());
+                                    let amount = modifier.apply(damage);
+                                    let attacker_hit_points = hit_points.get_mut(attacker).unwrap();
+                                    let destroyed = attacker_hit_points.damage(amount.into());
+                                    combat_log.record(CombatEvent {
+                                        attacker_player_id: unit.player_id,
+                                        target: attacker.get_id(),
+                                        target_player_id: attacker_unit.player_id,
+                                        damage: amount,
+                                        elevation_modifier: modifier,
+                                        destroyed: destroyed,
+                                    });
+                                }
+                            }
+                        } else if !provocation.fled {
+                            reacted = true;
+                            provocation.fled = true;
+                            reaction_destination = Some(flee(&position, &attacker_position));
+                        }
+                    }
+                    _ => calmed.push(entity),
+                }
+            }
+            let destination = match unit_info.class() {
+                _ if reacted => reaction_destination,
+                UnitClass::PreyAnimal => {
+                    find_nearest(&grid, &nearby_units, &position, FLEE_RADIUS, |nearby| !nearby.villager)
+                        .map(|(_, threat)| flee(&position, &threat))
+                }
+                UnitClass::PredatorAnimal if action_queue.is_idle() => {
+                    // The predator goes after its prey like an attacker, so it strikes once it
+                    // catches up and gives up if the villager gets away
+                    find_nearest(&grid, &nearby_units, &position, AGGRO_RADIUS, |nearby| nearby.villager)
+                        .map(|(prey_id, prey_position)| {
+                            provoked.insert(entity, ProvokedComponent::new(nearby_entities[&prey_id]));
+                            prey_position
+                        })
+                }
+                UnitClass::SeaFish |
+                UnitClass::DeepSeaFish |
+                UnitClass::ShoreFish if action_queue.is_idle() => {
+                    // Every client has to agree where the fish go, so this comes from a
+                    // simulation stream
+                    if random.one_in(RandomStream::Wildlife, SCHOOL_MOVE_CHANCE) {
+                        let span = (SCHOOL_RADIUS * 2 + 1) as u32;
+                        let offset_x = random.below(RandomStream::Wildlife, span) as i32 - SCHOOL_RADIUS;
+                        let offset_y = random.below(RandomStream::Wildlife, span) as i32 - SCHOOL_RADIUS;
+                        Some(position + Vector3::new(offset_x.into(), offset_y.into(), 0.into()))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+
+            if let Some(destination) = destination {
+                let destination = clamp_to_terrain(&terrain, destination);
+                let path = path_scheduler.find_path(&*path_finder,
+                                                    &*terrain,
+                                                    &*occupied_tiles,
+                                                    entity.get_id(),
+                                                    &position,
+                                                    &destination,
+                                                    unit_info.terrain_restriction);
+                action_batcher.queue_for_entity(entity.get_id(), Action::ClearQueue);
+                action_batcher.queue_for_entity(entity.get_id(),
+                                                Action::MoveToPosition(MoveToPositionParams::new(path)));
+            }
+        }
+
+        for entity in calmed {
+            provoked.remove(entity);
+        }
+    }
+}
+
+/// Finds the entity ID and position of the closest unit of interest within the radius (in tiles)
+/// of the position. The grid hands back its results in no particular order, so ties go to the
+/// lowest entity ID to make every client pick the same unit.
+fn find_nearest<F>(grid: &GridPartition,
+                   nearby_units: &HashMap<u32, NearbyUnit>,
+                   position: &Vector3,
+                   radius: i32,
+                   interesting: F)
+                   -> Option<(u32, Vector3)>
+    where F: Fn(&NearbyUnit) -> bool
+{
+    let center: Vector2<i32> = Vector2::new(position.x.into(), position.y.into());
+    let start = center - Vector2::new(radius, radius);
+    let end = center + Vector2::new(radius, radius);
+    let radius_squared: Fixed = (radius * radius).into();
+
+    let mut nearest: Option<(Fixed, u32, Vector3)> = None;
+    for entity_id in grid.query(&start, &end) {
+        if let Some(nearby_unit) = nearby_units.get(&entity_id) {
+            if !interesting(nearby_unit) {
+                continue;
+            }
+            let mut offset = nearby_unit.position - *position;
+            offset.z = 0.into();
+            let distance_squared = offset.length_squared();
+            let closer = nearest.map_or(true, |n| {
+                distance_squared < n.0 || distance_squared == n.0 && entity_id < n.1
+            });
+            if distance_squared <= radius_squared && closer {
+                nearest = Some((distance_squared, entity_id, nearby_unit.position));
+            }
+        }
+    }
+    nearest.map(|n| (n.1, n.2))
+}
+
+/// Where prey heads to get away from the threat
+fn flee(position: &Vector3, threat: &Vector3) -> Vector3 {
+    let mut away = *position - *threat;
+    away.z = 0.into();
+    if away.length_squared() == 0.into() {
+        away.x = 1.into();
+    }
+    *position + away.normalized() * FLEE_DISTANCE
+}
+
+fn size(unit_info: &dat::Unit) -> Fixed {
+    unit_info.collision_size_x.max(unit_info.collision_size_y).into()
+}
+
+fn clamp_to_terrain(terrain: &Terrain, position: Vector3) -> Vector3 {
+    let clamp = |value: Fixed, max: i32| {
+        let max: Fixed = Fixed::from(max) - fixed_const!(0.5);
+        if value < fixed_const!(0.5) {
+            fixed_const!(0.5)
+        } else if value > max {
+            max
+        } else {
+            value
+        }
+    };
+    Vector3::new(clamp(position.x, terrain.width()),
+                 clamp(position.y, terrain.height()),
+                 position.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NearbyUnit, find_nearest};
+    use nalgebra::Vector2;
+    use partition::GridPartition;
+    use std::collections::HashMap;
+    use types::Vector3;
+
+    #[test]
+    fn test_find_nearest_breaks_ties_on_entity_id() {
+        let mut grid = GridPartition::new(4, 4);
+        let mut nearby_units = HashMap::new();
+        for &(entity_id, x) in &[(9u32, 3), (4, 7), (6, 3), (2, 8)] {
+            grid.update_entity(entity_id, &Vector2::new(x, 5));
+            nearby_units.insert(entity_id,
+                                NearbyUnit {
+                                    position: Vector3::new(x.into(), 5.into(), 0.into()),
+                                    villager: entity_id != 2,
+                                });
+        }
+
+        // Entities 9 and 6 are both two tiles away, and 4 is just as far the other way
+        let position = Vector3::new(5.into(), 5.into(), 0.into());
+        let nearest = find_nearest(&grid, &nearby_units, &position, 4, |nearby| nearby.villager);
+        assert_eq!(Some((4, Vector3::new(7.into(), 5.into(), 0.into()))), nearest);
+        let nearest = find_nearest(&grid, &nearby_units, &position, 4, |nearby| !nearby.villager);
+        assert_eq!(Some((2, Vector3::new(8.into(), 5.into(), 0.into()))), nearest);
+        assert_eq!(None, find_nearest(&grid, &nearby_units, &position, 1, |_| true));
+    }
+}
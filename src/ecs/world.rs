@@ -19,21 +19,39 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use dat::EmpiresDbRef;
+use action::{Action, GarrisonParams, GatherParams, MoveToPositionParams, RestoreParams};
+use config::KeyBindings;
+use dat::{self, EmpiresDbRef};
 use ecs::render_system::*;
 use ecs::resource::*;
 use ecs::system::*;
-use media::MediaRef;
+use identifier::{CivilizationId, UnitId};
+use input::InputActions;
+use media::{GamepadState, MediaRef};
+use nalgebra::Vector2;
 use partition::GridPartition;
+use profile::Profile;
 use resource::ShapeMetadataStoreRef;
 use scn;
-use specs;
+use specs::{self, Join};
 use std::collections::HashMap;
 use super::component::*;
 use types::{Fixed, Vector3};
+use util::unit;
 
-const NUM_THREADS: usize = 4;
-const GRID_CELL_SIZE: i32 = 10; // in tiles
+/// The planner only gets one thread so that the simulation systems always run one after the
+/// other in the order they were added, which keeps every player's game in sync; systems that
+/// only affect what's shown go on the presentation lane's threads instead
+const PLANNER_THREADS: usize = 1;
+const PRESENTATION_THREADS: usize = 4;
+/// The grid starts with cells this many times smaller than the map's longest side, in tiles,
+/// and then the GridSystem tunes them as the game goes
+const GRID_CELLS_ACROSS_MAP: i32 = 16;
+const MIN_GRID_CELL_SIZE: i32 = 4;
+/// Size the viewport is given when there's no window, which only matters to the camera
+const HEADLESS_VIEWPORT_SIZE: (u32, u32) = (1024, 768);
+/// Food a second that goes off on a carcass, so hunters have to get to work on it quickly
+const CARCASS_DECAY_RATE: Fixed = fixed_const!(0.25);
 
 pub type WorldPlanner = specs::Planner<(SystemGroup, Fixed)>;
 
@@ -41,57 +59,272 @@ pub type WorldPlanner = specs::Planner<(SystemGroup, Fixed)>;
 pub enum SystemGroup {
     Normal,
     Render,
+    /// Only the camera's systems, for the scenario editor's preview of the map
+    Editor,
 }
 
 pub fn create_world_planner(media: MediaRef,
                             empires: EmpiresDbRef,
                             shape_metadata: ShapeMetadataStoreRef,
-                            scenario: &scn::Scenario)
+                            scenario: &scn::Scenario,
+                            settings: &GameSettings)
                             -> WorldPlanner {
+    let viewport_size = media.borrow().viewport_size();
+    build_world_planner(viewport_size, empires, shape_metadata, scenario, settings)
+}
+
+/// Creates a world planner without a window, for running the simulation by itself
+pub fn create_headless_world_planner(empires: EmpiresDbRef,
+                                     shape_metadata: ShapeMetadataStoreRef,
+                                     scenario: &scn::Scenario,
+                                     settings: &GameSettings)
+                                     -> WorldPlanner {
+    let viewport_size = Vector2::new(HEADLESS_VIEWPORT_SIZE.0, HEADLESS_VIEWPORT_SIZE.1);
+    build_world_planner(viewport_size, empires, shape_metadata, scenario, settings)
+}
+
+fn build_world_planner(viewport_size: Vector2<u32>,
+                       empires: EmpiresDbRef,
+                       shape_metadata: ShapeMetadataStoreRef,
+                       scenario: &scn::Scenario,
+                       settings: &GameSettings)
+                       -> WorldPlanner {
     let mut world = specs::World::new();
     register_components(&mut world);
-    add_resources(&mut world, &media, &empires, scenario);
+    add_resources(&mut world, viewport_size, &empires, scenario, settings);
+
+    let timings = SystemTimings::new();
+    world.add_resource(timings.clone());
 
-    // Create entities for each unit in the SCN
+    create_scenario_units(&mut world, &empires, scenario);
+
+    let mut planner = WorldPlanner::new(world, PLANNER_THREADS);
+    attach_systems(&mut planner, &timings, &empires, &shape_metadata);
+    attach_render_systems(&mut planner, &timings, &empires);
+    planner
+}
+
+/// Swaps the world's units for the scenario's, so the scenario editor's preview can follow its
+/// edits without building a new planner
+pub fn reload_scenario_units(world: &mut specs::World, empires: &EmpiresDbRef, scenario: &scn::Scenario) {
+    let units: Vec<specs::Entity> = (&world.entities(), &world.read::<UnitComponent>())
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in units {
+        world.write_resource::<UnitIndex>().remove(entity);
+        world.write_resource::<IdleUnits>().remove(entity);
+        world.write_resource::<GridPartition>().remove(entity.get_id());
+        world.delete_now(entity);
+    }
+    create_scenario_units(world, empires, scenario);
+}
+
+/// Creates entities for each unit in the SCN
+fn create_scenario_units(world: &mut specs::World, empires: &EmpiresDbRef, scenario: &scn::Scenario) {
     for player_id in scenario.player_ids() {
         let units = scenario.player_units(player_id);
         let civ_id = scenario.player_civilization_id(player_id);
         for unit in units {
-            let transform_component = TransformComponent::new(Vector3::new(unit.position_x.into(),
-                                                                           unit.position_y.into(),
-                                                                           unit.position_z.into()),
-                                                              unit.rotation.into());
-
-            let unit_info = empires.unit(civ_id, unit.unit_id);
-
-            let mut graphic_component = GraphicComponent::new();
-            graphic_component.player_color_id = player_id.into();
-            graphic_component.graphic_id = unit_info.standing_graphic;
-
-            // TODO: Use the bulk creation iterator for better performance
-            world.create_now()
-                .with(ActionQueueComponent::new())
-                .with(transform_component)
-                .with(graphic_component)
-                .with(UnitComponent::new(player_id, civ_id, unit.unit_id))
-                .with(VelocityComponent::new())
-                .build();
+            if !has_unit(&empires, civ_id, unit.unit_id) {
+                warn!("Skipping player {}'s unit {}: the game data has no such unit for civilization {}",
+                      *player_id,
+                      *unit.unit_id,
+                      *civ_id);
+                continue;
+            }
+            let position = Vector3::new(unit.position_x.into(),
+                                        unit.position_y.into(),
+                                        unit.position_z.into());
+            let mut request = SpawnRequest::new(player_id, civ_id, unit.unit_id, position);
+            request.rotation = unit.rotation.into();
+            create_unit(world, empires, &request);
         }
     }
+}
 
-    let mut planner = WorldPlanner::new(world, NUM_THREADS);
-    attach_systems(&mut planner, &empires, &shape_metadata);
-    attach_render_systems(&mut planner, &empires);
-    planner
+/// Creates the lane for the systems that only affect what's shown, which run after the
+/// planner's systems every tick
+pub fn create_presentation_lane(planner: &mut WorldPlanner,
+                                empires: EmpiresDbRef,
+                                shape_metadata: ShapeMetadataStoreRef)
+                                -> PresentationLane {
+    let timings = planner.mut_world().read_resource::<SystemTimings>().clone();
+    let mut lane = PresentationLane::new(PRESENTATION_THREADS, timings);
+    lane.add_system(Box::new(SimulationLodSystem::new(empires.clone())), "SimulationLodSystem");
+    lane.add_system(Box::new(DecalSystem::new(shape_metadata.clone())), "DecalSystem");
+    lane.add_system(Box::new(AnimationSystem::new(empires.clone(), shape_metadata)),
+                    "AnimationSystem");
+    lane
+}
+
+/// Creates the units that systems queued up in the UnitSpawner during the last update
+pub fn spawn_queued_units(world: &mut specs::World, empires: &EmpiresDbRef) {
+    let requests = world.write_resource::<UnitSpawner>().consume_spawns();
+    for request in &requests {
+        if !has_unit(empires, request.civilization_id, request.unit_id) {
+            warn!("Not spawning unit {}: the game data has no such unit for civilization {}",
+                  *request.unit_id,
+                  *request.civilization_id);
+            continue;
+        }
+        let entity = create_unit(world, empires, request);
+        if let Some(rally_point) = request.rally_point {
+            send_to_rally_point(world, empires, entity, request, rally_point);
+        }
+        if request.foundation {
+            // The builders were told to drop what they were doing when the order went out, so
+            // every foundation from the same order lines up behind the first one
+            let mut action_batcher = world.write_resource::<ActionBatcher>();
+            for &builder_id in &request.builders {
+                action_batcher.queue_for_entity(builder_id, Action::Restore(RestoreParams::new(entity)));
+            }
+        }
+    }
+}
+
+/// Queues up the walk to the rally point of the building that trained the unit. Rally points on
+/// other entities are looked up now, so the unit heads to wherever the target has got to, gets
+/// to work on it when it's a resource, or boards it when it's a transport.
+fn send_to_rally_point(world: &specs::World,
+                       empires: &EmpiresDbRef,
+                       entity: specs::Entity,
+                       request: &SpawnRequest,
+                       rally_point: RallyPoint) {
+    let unit_info = empires.unit(request.civilization_id, request.unit_id);
+    let target = match rally_point {
+        RallyPoint::Position(position) => position,
+        RallyPoint::Unit(target_id) => {
+            let entities = world.entities();
+            let transforms = world.read::<TransformComponent>();
+            match (&entities, &transforms).iter().find(|&(target, _)| target.get_id() == target_id) {
+                // Villagers and fishing boats rallied to a resource get straight to work on it,
+                // and villagers rallied to an animal go hunting
+                Some((target, _)) if world.read::<UnitComponent>().get(target).map_or(false, |target_unit| {
+                    let gatherable = world.read::<ResourceNodeComponent>().get(target).is_some() ||
+                                     unit::is_huntable(empires, target_unit);
+                    gatherable && unit::can_gather_from(unit_info, target_unit.db(empires).class())
+                }) => {
+                    world.write_resource::<ActionBatcher>()
+                        .queue_for_entity(entity.get_id(), Action::Gather(GatherParams::new(target)));
+                    return;
+                }
+                // Units rallied to one of their player's transports board it
+                Some((target, _)) if world.read::<UnitComponent>().get(target).map_or(false, |target_unit| {
+                    target_unit.player_id == request.player_id &&
+                    unit::can_board(unit_info, target_unit.db(empires))
+                }) => {
+                    world.write_resource::<ActionBatcher>()
+                        .queue_for_entity(entity.get_id(), Action::Garrison(GarrisonParams::new(target)));
+                    return;
+                }
+                Some((_, transform)) => *transform.position(),
+                None => return,
+            }
+        }
+    };
+
+    // Units rallied to anything else just walk up to it
+    let path = world.write_resource::<PathScheduler>().find_path(&*world.read_resource::<PathFinder>(),
+                                                                 &*world.read_resource::<Terrain>(),
+                                                                 &*world.read_resource::<OccupiedTiles>(),
+                                                                 entity.get_id(),
+                                                                 &request.position,
+                                                                 &target,
+                                                                 unit_info.terrain_restriction);
+    world.write_resource::<ActionBatcher>()
+        .queue_for_entity(entity.get_id(), Action::MoveToPosition(MoveToPositionParams::new(path)));
+}
+
+/// Whether the game data has the unit for the civilization; scenarios made for other data sets,
+/// such as the full game's when playing with the demo's, can use units it doesn't
+fn has_unit(empires: &EmpiresDbRef, civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+    *civilization_id >= 1 && *civilization_id as usize <= empires.civilizations().len() &&
+    empires.civilization(civilization_id).has_unit(unit_id)
+}
+
+fn create_unit(world: &mut specs::World, empires: &EmpiresDbRef, request: &SpawnRequest) -> specs::Entity {
+    let transform_component = TransformComponent::new(request.position, request.rotation);
+
+    let unit_info = empires.unit(request.civilization_id, request.unit_id);
+
+    let mut graphic_component = GraphicComponent::new();
+    graphic_component.player_color_id = request.player_id.into();
+    // Wall pieces are told apart by their frame, which only the standing graphic has
+    graphic_component.graphic_id = if request.foundation && request.frame == 0 {
+        unit_info.construction_graphic().or(unit_info.standing_graphic)
+    } else {
+        unit_info.standing_graphic
+    };
+    graphic_component.frame = request.frame;
+
+    // TODO: Use the bulk creation iterator for better performance
+    let entity = world.create_now()
+        .with(ActionQueueComponent::new())
+        .with(CooldownComponent::new())
+        .with(transform_component)
+        .with(graphic_component)
+        .with(UnitComponent::new(request.player_id, request.civilization_id, request.unit_id))
+        .with(VelocityComponent::new())
+        .build();
+    if unit_info.interaction_mode == dat::InteractionMode::Building {
+        world.write::<ProductionQueueComponent>().insert(entity, ProductionQueueComponent::new());
+    }
+    if let Some(ref projectile) = request.projectile {
+        world.write::<ProjectileComponent>().insert(entity, projectile.clone());
+    } else if request.remains && unit_info.stored_resource().is_none() {
+        // Remains with food on them, like a carcass, go once the food has gone off instead
+        if let Some(remaining) = unit::remains_lifetime(empires, unit_info) {
+            world.write::<DecompositionComponent>().insert(entity, DecompositionComponent::new(remaining));
+        }
+    } else if request.foundation {
+        world.write::<HitPointsComponent>().insert(entity, HitPointsComponent::new(1.into()));
+        world.write::<ConstructionComponent>().insert(entity, ConstructionComponent::new());
+    } else if unit_info.hit_points() > 0 {
+        world.write::<HitPointsComponent>()
+            .insert(entity, HitPointsComponent::new(unit_info.hit_points().into()));
+    }
+    if request.player_id.is_gaia() {
+        if let Some((resource_type, amount)) = unit_info.stored_resource() {
+            let mut node = ResourceNodeComponent::new(resource_type, amount.into());
+            if request.remains {
+                node = node.decaying(CARCASS_DECAY_RATE);
+            }
+            world.write::<ResourceNodeComponent>().insert(entity, node);
+        }
+    }
+    if request.projectile.is_none() {
+        let unit = UnitComponent::new(request.player_id, request.civilization_id, request.unit_id);
+        world.write_resource::<UnitIndex>().insert(entity, request.player_id, unit::tags(empires, &unit));
+    }
+    trace!(entity = entity.get_id(),
+           unit_id = *request.unit_id,
+           player_id = *request.player_id,
+           "Spawned unit");
+    entity
 }
 
 fn register_components(world: &mut specs::World) {
     world.register::<ActionQueueComponent>();
+    world.register::<AttackGroundActionComponent>();
     world.register::<CameraComponent>();
+    world.register::<ConstructionComponent>();
+    world.register::<CooldownComponent>();
     world.register::<DecalComponent>();
+    world.register::<DecompositionComponent>();
+    world.register::<ExploreActionComponent>();
+    world.register::<GarrisonActionComponent>();
+    world.register::<GarrisonedComponent>();
+    world.register::<GatherActionComponent>();
     world.register::<GraphicComponent>();
+    world.register::<HitPointsComponent>();
     world.register::<MoveToPositionActionComponent>();
     world.register::<OnScreenComponent>();
+    world.register::<ProductionQueueComponent>();
+    world.register::<ProjectileComponent>();
+    world.register::<ProvokedComponent>();
+    world.register::<ResourceNodeComponent>();
+    world.register::<RestoreActionComponent>();
     world.register::<SelectedUnitComponent>();
     world.register::<TransformComponent>();
     world.register::<UnitComponent>();
@@ -99,20 +332,42 @@ fn register_components(world: &mut specs::World) {
 }
 
 fn add_resources(world: &mut specs::World,
-                 media: &MediaRef,
+                 viewport_size: Vector2<u32>,
                  empires: &EmpiresDbRef,
-                 scenario: &scn::Scenario) {
-    let viewport_size = media.borrow().viewport_size();
+                 scenario: &scn::Scenario,
+                 settings: &GameSettings) {
     let (tile_half_width, tile_half_height) = empires.tile_half_sizes();
 
     // Input resources
+    world.add_resource(KeyBindings::new());
     world.add_resource(KeyboardKeyStates::new(HashMap::new()));
     world.add_resource(MouseState::new());
+    world.add_resource(TextInput::new());
+    world.add_resource(TouchInput::new());
+    world.add_resource(GamepadState::new());
+    world.add_resource(InputActions::new());
+
+    // Lobby resources
+    world.add_resource(settings.clone());
+    world.add_resource(Chat::new());
+    world.add_resource(Cheats::new(settings.allow_cheats));
+    world.add_resource(GameSpeed::with_multiplier(settings.game_speed));
+    world.add_resource(RandomNumbers::new(settings.random_seed));
+    world.add_resource(DebugOverlay::new());
+    world.add_resource(EntityInspector::new());
+    world.add_resource(OptionChanges::new());
+
+    // Scratch memory for temporary collections, reset at the end of every tick
+    world.add_resource(TickArena::new());
 
     // Render resources
     world.add_resource(RenderCommands::new());
+    world.add_resource(SceneTint::new());
+    world.add_resource(PlayerColors::new());
+    world.add_resource(UiLayout::new());
+    world.add_resource(MouseCursor::new());
+    world.add_resource(FeedbackEffects::new());
     world.add_resource(ViewProjector::new(tile_half_width, tile_half_height));
-    world.add_resource(GridPartition::new(GRID_CELL_SIZE, GRID_CELL_SIZE));
 
     // Camera resources and entity
     world.add_resource(Viewport::new(viewport_size.x as i32, viewport_size.y as i32));
@@ -125,78 +380,285 @@ fn add_resources(world: &mut specs::World,
     // Players
     // TODO: Local player currently hardcoded to 1; should be determined in menu
     world.add_resource(Players::from_scenario(scenario, 1.into()));
+    let mut diplomacy = Diplomacy::from_scenario(scenario);
+    if settings.allied_vision {
+        for player_id in scenario.player_ids() {
+            diplomacy.enable_shared_vision(player_id);
+        }
+    }
+    world.add_resource(diplomacy);
+    world.add_resource(DiplomacyPanel::new());
+    world.add_resource(Market::new());
+    world.add_resource(MarketPanel::new());
+    world.add_resource(ScoreScreen::new());
+    world.add_resource(Statistics::new());
+    world.add_resource(Technologies::new());
+    world.add_resource(Profile::for_current_user());
+    world.add_resource(SimulationTick::new());
 
     // Unit resources
     world.add_resource(ActionBatcher::new());
+    world.add_resource(BuildOrders::new());
+    world.add_resource(CommandLog::new());
+    world.add_resource(IdleUnits::new());
+    world.add_resource(Notifications::new());
+    world.add_resource(ProductionOrders::new());
+    world.add_resource(SelectionPriority::new());
+    world.add_resource(SelectionSubgroup::new());
+    world.add_resource(UnitIndex::new());
+    world.add_resource(UnitSpawner::new());
+    world.add_resource(WallPlacement::new());
+    world.add_resource(CommandPanel::new());
+    world.add_resource(CombatLog::new());
+    world.add_resource(DepositLog::new());
+    world.add_resource(TownBell::new());
+    world.add_resource(UnitVoices::new());
 
     // Terrain resources
     world.add_resource(OccupiedTiles::new());
     world.add_resource(PathFinder::new(empires.clone()));
-    world.add_resource(Terrain::from(&scenario.map, empires.clone()));
+    world.add_resource(PathScheduler::new());
+    let terrain = Terrain::from(&scenario.map, empires.clone());
+    let map_size = terrain.width().max(terrain.height());
+    let grid_cell_size = (map_size / GRID_CELLS_ACROSS_MAP).max(MIN_GRID_CELL_SIZE);
+    world.add_resource(AreaQueryCache::new());
+    world.add_resource::<GridPartition>(GridPartition::bounded(grid_cell_size,
+                                                               grid_cell_size,
+                                                               terrain.width(),
+                                                               terrain.height()));
+    world.add_resource(SimulationLod::new());
+    world.add_resource(FogOfWar::new(terrain.width(), terrain.height()));
+    world.add_resource(AmbientSounds::from_terrain(&terrain, |terrain_id| {
+        if empires.terrain_block().has_terrain(terrain_id) {
+            empires.terrain(terrain_id).sound_group_id()
+        } else {
+            None
+        }
+    }));
+    world.add_resource(terrain);
 }
 
 macro_rules! system {
-    ($planner:expr, $typ:ident, $priority:expr) => {
-        $planner.add_system(SystemWrapper::new(Box::new($typ::new())), stringify!($typ), $priority);
+    (editor $planner:expr, $timings:expr, $typ:ident, $priority:expr) => {
+        $planner.add_system(SystemWrapper::new(Box::new($typ::new()),
+                                               stringify!($typ),
+                                               SystemScope::GameAndEditor,
+                                               $timings.clone()),
+                            stringify!($typ),
+                            $priority);
     };
-    ($planner:expr, $typ:ident, $inst:expr, $priority:expr) => {
-        $planner.add_system(SystemWrapper::new(Box::new($inst)), stringify!($typ), $priority);
+    ($planner:expr, $timings:expr, $typ:ident, $priority:expr) => {
+        $planner.add_system(SystemWrapper::new(Box::new($typ::new()),
+                                               stringify!($typ),
+                                               SystemScope::Game,
+                                               $timings.clone()),
+                            stringify!($typ),
+                            $priority);
+    };
+    ($planner:expr, $timings:expr, $typ:ident, $inst:expr, $priority:expr) => {
+        $planner.add_system(SystemWrapper::new(Box::new($inst),
+                                               stringify!($typ),
+                                               SystemScope::Game,
+                                               $timings.clone()),
+                            stringify!($typ),
+                            $priority);
     };
 }
 
 fn attach_systems(planner: &mut WorldPlanner,
+                  timings: &SystemTimings,
                   empires: &EmpiresDbRef,
                   shape_metadata: &ShapeMetadataStoreRef) {
-    system!(planner, VelocitySystem, 1000);
-    system!(planner, CameraInputSystem, 1000);
-    system!(planner, CameraPositionSystem, 1000);
-    system!(planner, CameraPositionSystem, 1000);
-    system!(planner, GridSystem, 1000);
+    system!(editor planner, timings, InputMappingSystem, 1000);
+    system!(editor planner, timings, VelocitySystem, 1000);
+    system!(editor planner, timings, CameraInputSystem, 1000);
+    system!(editor planner, timings, CameraPositionSystem, 1000);
+    system!(editor planner, timings, CameraPositionSystem, 1000);
+    system!(planner, timings, GridSystem, 1000);
+    system!(planner, timings, UnitActionSystem, UnitActionSystem::new(), 1000);
+    system!(planner,
+            timings,
+            WallPlacementSystem,
+            WallPlacementSystem::new(empires.clone(), shape_metadata.clone()),
+            1000);
     system!(planner,
-            DecalSystem,
-            DecalSystem::new(shape_metadata.clone()),
+            timings,
+            CommandPanelSystem,
+            CommandPanelSystem::new(empires.clone()),
             1000);
     system!(planner,
-            AnimationSystem,
-            AnimationSystem::new(empires.clone(), shape_metadata.clone()),
+            timings,
+            BuildOrderSystem,
+            BuildOrderSystem::new(empires.clone()),
             1000);
-    system!(planner, UnitActionSystem, UnitActionSystem::new(), 1000);
     system!(planner,
+            timings,
+            TownBellSystem,
+            TownBellSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
             UnitSelectionSystem,
             UnitSelectionSystem::new(empires.clone()),
             1000);
+    system!(planner, timings, PathSchedulerSystem, 1000);
+    system!(planner, timings, CooldownSystem, 1000);
     system!(planner,
+            timings,
             MoveToPositionActionSystem,
             MoveToPositionActionSystem::new(empires.clone()),
             1000);
     system!(planner,
+            timings,
+            GatherActionSystem,
+            GatherActionSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            AttackGroundActionSystem,
+            AttackGroundActionSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            ProjectileSystem,
+            ProjectileSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            RestorationSystem,
+            RestorationSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            VisibilitySystem,
+            VisibilitySystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            ExploreActionSystem,
+            ExploreActionSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            GarrisonActionSystem,
+            GarrisonActionSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            DeathSystem,
+            DeathSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
             OccupiedTileSystem,
             OccupiedTileSystem::new(empires.clone()),
             1000);
+    system!(planner,
+            timings,
+            IdleUnitSystem,
+            IdleUnitSystem::new(empires.clone()),
+            1000);
+    system!(planner, timings, DiplomacyPanelSystem, 1000);
+    system!(planner,
+            timings,
+            DiplomacySystem,
+            DiplomacySystem::new(empires.clone()),
+            1000);
+    system!(planner, timings, MarketPanelSystem, 1000);
+    system!(planner, timings, MarketSystem, 1000);
+    system!(planner,
+            timings,
+            ProductionSystem,
+            ProductionSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            StatisticsSystem,
+            StatisticsSystem::new(empires.clone()),
+            1000);
+    system!(planner, timings, ScoreScreenSystem, 1000);
+    system!(planner, timings, ChatSystem, 1000);
+    system!(planner, timings, DebugConsoleSystem, 1000);
+    system!(planner, timings, EntityInspectorSystem, 1000);
+    system!(planner, timings, GameSpeedSystem, 1000);
+    system!(planner, timings, CheatSystem, CheatSystem::new(empires.clone()), 1000);
+    system!(planner,
+            timings,
+            WildlifeSystem,
+            WildlifeSystem::new(empires.clone()),
+            1000);
+    system!(planner,
+            timings,
+            BirdWanderSystem,
+            BirdWanderSystem::new(empires.clone()),
+            1000);
 }
 
 macro_rules! render_system {
-    ($planner:expr, $typ:ident, $priority:expr) => {
-        $planner.add_system(RenderSystemWrapper::new(Box::new($typ::new())), stringify!($typ), $priority);
+    ($planner:expr, $timings:expr, $typ:ident, $priority:expr) => {
+        $planner.add_system(RenderSystemWrapper::new(Box::new($typ::new()),
+                                                     stringify!($typ),
+                                                     $timings.clone()),
+                            stringify!($typ),
+                            $priority);
     };
-    ($planner:expr, $typ:ident, $inst:expr, $priority:expr) => {
-        $planner.add_system(RenderSystemWrapper::new(Box::new($inst)), stringify!($typ), $priority);
+    ($planner:expr, $timings:expr, $typ:ident, $inst:expr, $priority:expr) => {
+        $planner.add_system(RenderSystemWrapper::new(Box::new($inst), stringify!($typ), $timings.clone()),
+                            stringify!($typ),
+                            $priority);
     };
 }
 
-fn attach_render_systems(planner: &mut WorldPlanner, empires: &EmpiresDbRef) {
+fn attach_render_systems(planner: &mut WorldPlanner, timings: &SystemTimings, empires: &EmpiresDbRef) {
     render_system!(planner,
+                   timings,
                    TerrainRenderSystem,
                    TerrainRenderSystem::new(empires.clone()),
                    1000);
-    render_system!(planner, DecalRenderSystem, 1000);
+    render_system!(planner, timings, ShoreFoamRenderSystem, 1000);
+    render_system!(planner, timings, DecalRenderSystem, 1000);
     render_system!(planner,
+                   timings,
                    GraphicRenderSystem,
                    GraphicRenderSystem::new(empires.clone()),
                    1000);
     render_system!(planner,
+                   timings,
                    UnitSelectionRenderSystem,
                    UnitSelectionRenderSystem::new(empires.clone()),
                    1000);
-    render_system!(planner, TileDebugRenderSystem, 1000);
+    render_system!(planner, timings, FeedbackRenderSystem, 1000);
+    render_system!(planner, timings, TintRenderSystem, 1000);
+    render_system!(planner, timings, TileDebugRenderSystem, 1000);
+    render_system!(planner,
+                   timings,
+                   TileOverlayRenderSystem,
+                   TileOverlayRenderSystem::new(empires.clone()),
+                   1000);
+    render_system!(planner, timings, WallPlacementRenderSystem, 1000);
+    render_system!(planner, timings, CommandPanelRenderSystem, 1000);
+    render_system!(planner, timings, DiplomacyPanelRenderSystem, 1000);
+    render_system!(planner, timings, IdleUnitsRenderSystem, 1000);
+    render_system!(planner, timings, NotificationRenderSystem, 1000);
+    render_system!(planner,
+                   timings,
+                   ProductionRenderSystem,
+                   ProductionRenderSystem::new(empires.clone()),
+                   1000);
+    render_system!(planner,
+                   timings,
+                   CooldownRenderSystem,
+                   CooldownRenderSystem::new(empires.clone()),
+                   1000);
+    render_system!(planner, timings, MarketPanelRenderSystem, 1000);
+    render_system!(planner, timings, ScoreScreenRenderSystem, 1000);
+    render_system!(planner, timings, ChatRenderSystem, 1000);
+    render_system!(planner, timings, InputOverlayRenderSystem, 1000);
+    render_system!(planner, timings, DebugOverlayRenderSystem, 1000);
+    render_system!(planner, timings, EntityInspectorRenderSystem, 1000);
+    render_system!(planner,
+                   timings,
+                   CursorRenderSystem,
+                   CursorRenderSystem::new(empires.clone()),
+                   1000);
 }
@@ -0,0 +1,283 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::UnitComponent;
+use ecs::resource::{Area, AreaQueryCache, Players, Technologies};
+use identifier::{PlayerId, ResearchId, UnitId};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use specs::Index;
+use types::Fixed;
+use util::parse::parse_number;
+
+/// How a query's answer is compared against a value, by triggers and test plans
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Less,
+    AtMost,
+    Equal,
+    AtLeast,
+    Greater,
+}
+
+impl Comparison {
+    pub fn parse(text: &str) -> Option<Comparison> {
+        match text {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::AtMost),
+            "==" | "=" => Some(Comparison::Equal),
+            ">=" => Some(Comparison::AtLeast),
+            ">" => Some(Comparison::Greater),
+            _ => None,
+        }
+    }
+
+    pub fn operator(&self) -> &'static str {
+        match *self {
+            Comparison::Less => "<",
+            Comparison::AtMost => "<=",
+            Comparison::Equal => "==",
+            Comparison::AtLeast => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+
+    /// Whether `left <comparison> right` is true
+    pub fn holds(&self, left: i64, right: i64) -> bool {
+        match *self {
+            Comparison::Less => left < right,
+            Comparison::AtMost => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::AtLeast => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// A question about the state of the world, in the form that scenario triggers and AI scripts
+/// ask it. Queries can be written out as text:
+///
+/// * `count [type <unit id>] [owner <player id>] [in <x1> <y1> <x2> <y2>]` counts the units
+///   matching every condition given
+/// * `stockpile <player id> <food|wood|stone|gold>` is how much of a resource a player has
+/// * `researched <player id> <research id>` is 1 once the player has finished the research, and
+///   0 until then
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WorldQuery {
+    UnitCount {
+        unit_id: Option<UnitId>,
+        player_id: Option<PlayerId>,
+        area: Option<Area>,
+    },
+    Stockpile(PlayerId, ResourceType),
+    Researched(PlayerId, ResearchId),
+}
+
+/// What a query needs to look at to answer it
+pub struct QueryContext<'a> {
+    pub players: &'a Players,
+    pub technologies: &'a Technologies,
+    pub grid: &'a GridPartition,
+    pub area_cache: &'a mut AreaQueryCache,
+}
+
+impl WorldQuery {
+    pub fn parse(text: &str) -> Result<WorldQuery, String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.first().map(|word| word.to_lowercase()) {
+            Some(ref word) if word == "count" => parse_unit_count(&words[1..]),
+            Some(ref word) if word == "stockpile" => {
+                if words.len() != 3 {
+                    return Err("usage: stockpile <player id> <food|wood|stone|gold>".into());
+                }
+                let player_id = try!(parse_number(words[1], "player id"));
+                let resource_type = match &words[2].to_lowercase()[..] {
+                    "food" => ResourceType::Food,
+                    "wood" => ResourceType::Wood,
+                    "stone" => ResourceType::Stone,
+                    "gold" => ResourceType::Gold,
+                    _ => return Err(format!("unknown resource \"{}\"", words[2])),
+                };
+                Ok(WorldQuery::Stockpile((player_id as usize).into(), resource_type))
+            }
+            Some(ref word) if word == "researched" => {
+                if words.len() != 3 {
+                    return Err("usage: researched <player id> <research id>".into());
+                }
+                let player_id = try!(parse_number(words[1], "player id"));
+                let research_id = try!(parse_number(words[2], "research id"));
+                Ok(WorldQuery::Researched(player_id.into(), research_id.into()))
+            }
+            Some(_) => Err(format!("unknown query \"{}\"", text.trim())),
+            None => Err("empty query".into()),
+        }
+    }
+
+    /// Answers the query; `units` are the IDs and unit components of the entities to count
+    pub fn evaluate<'a, I>(&self, context: &mut QueryContext, units: I) -> Fixed
+        where I: IntoIterator<Item = (Index, &'a UnitComponent)>
+    {
+        match *self {
+            WorldQuery::UnitCount { unit_id, player_id, area } => {
+                let in_area = match area {
+                    Some(ref area) => Some(context.area_cache.entities_in(context.grid, area)),
+                    None => None,
+                };
+                let count = units.into_iter()
+                    .filter(|&(entity_id, unit)| {
+                        unit_id.map_or(true, |unit_id| unit.unit_id == unit_id) &&
+                        player_id.map_or(true, |player_id| unit.player_id == player_id) &&
+                        in_area.map_or(true, |in_area| in_area.contains(&entity_id))
+                    })
+                    .count();
+                (count as i32).into()
+            }
+            WorldQuery::Stockpile(player_id, resource_type) => {
+                context.players
+                    .player(player_id)
+                    .map_or(0.into(), |player| player.stockpile.amount(resource_type))
+            }
+            WorldQuery::Researched(player_id, research_id) => {
+                if context.technologies.has_researched(player_id, research_id) {
+                    1.into()
+                } else {
+                    0.into()
+                }
+            }
+        }
+    }
+}
+
+fn parse_unit_count(words: &[&str]) -> Result<WorldQuery, String> {
+    const USAGE: &'static str = "usage: count [type <unit id>] [owner <player id>] [in <x1> <y1> <x2> <y2>]";
+    let mut unit_id: Option<UnitId> = None;
+    let mut player_id: Option<PlayerId> = None;
+    let mut area = None;
+    let mut index = 0;
+    while index < words.len() {
+        let argument_count = match &words[index].to_lowercase()[..] {
+            "type" | "owner" => 1,
+            "in" => 4,
+            _ => return Err(USAGE.into()),
+        };
+        if index + argument_count >= words.len() {
+            return Err(USAGE.into());
+        }
+        let arguments = &words[index + 1..index + 1 + argument_count];
+        match &words[index].to_lowercase()[..] {
+            "type" => unit_id = Some(try!(parse_number(arguments[0], "unit id")).into()),
+            "owner" => player_id = Some(try!(parse_number(arguments[0], "player id")).into()),
+            _ => {
+                let mut corners = [0i32; 4];
+                for (corner, argument) in corners.iter_mut().zip(arguments) {
+                    *corner = try!(argument.parse()
+                        .map_err(|_| format!("invalid coordinate \"{}\"", argument)));
+                }
+                area = Some(Area::new(Vector2::new(corners[0], corners[1]),
+                                      Vector2::new(corners[2], corners[3])));
+            }
+        }
+        index += 1 + argument_count;
+    }
+    Ok(WorldQuery::UnitCount {
+        unit_id: unit_id,
+        player_id: player_id,
+        area: area,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::UnitComponent;
+    use ecs::resource::{Area, AreaQueryCache, Player, Players, Technologies};
+    use nalgebra::Vector2;
+    use partition::GridPartition;
+    use super::{QueryContext, WorldQuery};
+    use types::Fixed;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(WorldQuery::UnitCount {
+                       unit_id: Some(83.into()),
+                       player_id: Some(1.into()),
+                       area: Some(Area::new(Vector2::new(0, 0), Vector2::new(10, 20))),
+                   }),
+                   WorldQuery::parse("count type 83 owner 1 in 10 20 0 0"));
+        assert_eq!(Ok(WorldQuery::UnitCount {
+                       unit_id: None,
+                       player_id: None,
+                       area: None,
+                   }),
+                   WorldQuery::parse("Count"));
+        assert_eq!(Ok(WorldQuery::Stockpile(2.into(), ResourceType::Gold)),
+                   WorldQuery::parse("stockpile 2 gold"));
+        assert_eq!(Ok(WorldQuery::Researched(1.into(), 101.into())),
+                   WorldQuery::parse("researched 1 101"));
+        assert!(WorldQuery::parse("researched 1").is_err());
+        assert!(WorldQuery::parse("count in 1 2 3").is_err());
+        assert!(WorldQuery::parse("count owner").is_err());
+        assert!(WorldQuery::parse("stockpile 2 silver").is_err());
+        assert!(WorldQuery::parse("").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let units = vec![(1, UnitComponent::new(1.into(), 1.into(), 83.into())),
+                         (2, UnitComponent::new(1.into(), 1.into(), 83.into())),
+                         (3, UnitComponent::new(2.into(), 1.into(), 83.into())),
+                         (4, UnitComponent::new(1.into(), 1.into(), 4.into()))];
+        let mut grid = GridPartition::new(8, 8);
+        grid.update_entity(1, &Vector2::new(2, 2));
+        grid.update_entity(2, &Vector2::new(30, 30));
+        grid.update_entity(3, &Vector2::new(3, 3));
+        grid.update_entity(4, &Vector2::new(4, 4));
+
+        let mut players = Players::new();
+        let mut player = Player::new("Player 1".into(), 1.into(), 1.into(), 1.into());
+        player.stockpile.set(ResourceType::Food, 200.into());
+        players.add_player(player, true);
+
+        let mut technologies = Technologies::new();
+        technologies.complete(1.into(), 101.into());
+
+        let mut area_cache = AreaQueryCache::new();
+        let mut context = QueryContext {
+            players: &players,
+            technologies: &technologies,
+            grid: &grid,
+            area_cache: &mut area_cache,
+        };
+        let mut evaluate = |text| {
+            let units = units.iter().map(|&(entity_id, ref unit)| (entity_id, unit));
+            WorldQuery::parse(text).unwrap().evaluate(&mut context, units)
+        };
+        assert_eq!(Fixed::from(4), evaluate("count"));
+        assert_eq!(Fixed::from(2), evaluate("count type 83 owner 1"));
+        assert_eq!(Fixed::from(1), evaluate("count type 83 owner 1 in 0 0 10 10"));
+        assert_eq!(Fixed::from(3), evaluate("count in 0 0 10 10"));
+        assert_eq!(Fixed::from(200), evaluate("stockpile 1 food"));
+        assert_eq!(Fixed::from(0), evaluate("stockpile 3 food"));
+        assert_eq!(Fixed::from(1), evaluate("researched 1 101"));
+        assert_eq!(Fixed::from(0), evaluate("researched 2 101"));
+    }
+}
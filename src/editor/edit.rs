@@ -0,0 +1,369 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{PlayerId, TerrainId};
+use scn::{self, PlayerUnit};
+use std::cmp;
+use super::scenario_editor::EditorChange;
+use super::trigger::EditorTrigger;
+
+/// How many edits can be undone
+const MAX_UNDO_STEPS: usize = 200;
+
+/// The parts of a map tile that the editor changes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TileState {
+    pub terrain_id: TerrainId,
+    pub elevation: u8,
+}
+
+impl TileState {
+    pub fn of(tile: &scn::MapTile) -> TileState {
+        TileState {
+            terrain_id: tile.terrain_id,
+            elevation: tile.elevation,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TileChange {
+    /// Index of the tile in the map, `y * width + x`
+    pub index: usize,
+    pub before: TileState,
+    pub after: TileState,
+}
+
+/// One change to the scenario. Every edit knows how to undo itself, so the editor's history
+/// is a stack of these.
+#[derive(Clone, Debug)]
+pub enum EditorEdit {
+    Tiles(Vec<TileChange>),
+    AddUnit {
+        player_id: PlayerId,
+        index: usize,
+        unit: PlayerUnit,
+    },
+    RemoveUnit {
+        player_id: PlayerId,
+        index: usize,
+        unit: PlayerUnit,
+    },
+    ChangeUnit {
+        player_id: PlayerId,
+        index: usize,
+        before: PlayerUnit,
+        after: PlayerUnit,
+    },
+    AddTrigger {
+        index: usize,
+        trigger: EditorTrigger,
+    },
+    RemoveTrigger {
+        index: usize,
+        trigger: EditorTrigger,
+    },
+    ReplaceTrigger {
+        index: usize,
+        before: EditorTrigger,
+        after: EditorTrigger,
+    },
+    /// Moves a trigger to another place in the list, shifting the ones in between
+    MoveTrigger {
+        from: usize,
+        to: usize,
+    },
+    /// Edits that are undone together, such as everything painted in one drag
+    Group(Vec<EditorEdit>),
+}
+
+impl EditorEdit {
+    pub fn apply(&self, scenario: &mut scn::Scenario, triggers: &mut Vec<EditorTrigger>) -> EditorChange {
+        use self::EditorEdit::*;
+        match *self {
+            Tiles(ref changes) => {
+                for change in changes {
+                    let tile = &mut scenario.map.tiles[change.index];
+                    tile.terrain_id = change.after.terrain_id;
+                    tile.elevation = change.after.elevation;
+                }
+                EditorChange::Map
+            }
+            AddUnit { player_id, index, ref unit } => {
+                scenario.player_units_mut(player_id).insert(index, unit.clone());
+                EditorChange::Units
+            }
+            RemoveUnit { player_id, index, .. } => {
+                scenario.player_units_mut(player_id).remove(index);
+                EditorChange::Units
+            }
+            ChangeUnit { player_id, index, ref after, .. } => {
+                scenario.player_units_mut(player_id)[index] = after.clone();
+                EditorChange::Units
+            }
+            AddTrigger { index, ref trigger } => {
+                triggers.insert(index, trigger.clone());
+                EditorChange::Settings
+            }
+            RemoveTrigger { index, .. } => {
+                triggers.remove(index);
+                EditorChange::Settings
+            }
+            ReplaceTrigger { index, ref after, .. } => {
+                triggers[index] = after.clone();
+                EditorChange::Settings
+            }
+            MoveTrigger { from, to } => {
+                let trigger = triggers.remove(from);
+                triggers.insert(to, trigger);
+                EditorChange::Settings
+            }
+            Group(ref edits) => {
+                edits.iter()
+                    .map(|edit| edit.apply(scenario, triggers))
+                    .fold(EditorChange::Nothing, cmp::max)
+            }
+        }
+    }
+
+    /// The edit that undoes this one
+    pub fn inverse(&self) -> EditorEdit {
+        use self::EditorEdit::*;
+        match *self {
+            Tiles(ref changes) => {
+                Tiles(changes.iter()
+                    .rev()
+                    .map(|change| {
+                        TileChange {
+                            index: change.index,
+                            before: change.after,
+                            after: change.before,
+                        }
+                    })
+                    .collect())
+            }
+            AddUnit { player_id, index, ref unit } => {
+                RemoveUnit {
+                    player_id: player_id,
+                    index: index,
+                    unit: unit.clone(),
+                }
+            }
+            RemoveUnit { player_id, index, ref unit } => {
+                AddUnit {
+                    player_id: player_id,
+                    index: index,
+                    unit: unit.clone(),
+                }
+            }
+            ChangeUnit { player_id, index, ref before, ref after } => {
+                ChangeUnit {
+                    player_id: player_id,
+                    index: index,
+                    before: after.clone(),
+                    after: before.clone(),
+                }
+            }
+            AddTrigger { index, ref trigger } => {
+                RemoveTrigger {
+                    index: index,
+                    trigger: trigger.clone(),
+                }
+            }
+            RemoveTrigger { index, ref trigger } => {
+                AddTrigger {
+                    index: index,
+                    trigger: trigger.clone(),
+                }
+            }
+            ReplaceTrigger { index, ref before, ref after } => {
+                ReplaceTrigger {
+                    index: index,
+                    before: after.clone(),
+                    after: before.clone(),
+                }
+            }
+            MoveTrigger { from, to } => MoveTrigger { from: to, to: from },
+            Group(ref edits) => Group(edits.iter().rev().map(EditorEdit::inverse).collect()),
+        }
+    }
+}
+
+/// The edits that can be undone and redone. Edits recorded between `begin_group` and
+/// `end_group` are undone as one.
+pub struct EditHistory {
+    undo: Vec<EditorEdit>,
+    redo: Vec<EditorEdit>,
+    group: Option<Vec<EditorEdit>>,
+}
+
+impl EditHistory {
+    pub fn new() -> EditHistory {
+        EditHistory {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            group: None,
+        }
+    }
+
+    /// Adds an edit that has just been applied; anything that was undone can't be redone
+    /// after this
+    pub fn record(&mut self, edit: EditorEdit) {
+        self.redo.clear();
+        match self.group {
+            Some(ref mut group) => group.push(edit),
+            None => self.push_undo(edit),
+        }
+    }
+
+    pub fn begin_group(&mut self) {
+        self.end_group();
+        self.group = Some(Vec::new());
+    }
+
+    pub fn end_group(&mut self) {
+        match self.group.take() {
+            Some(mut group) => {
+                match group.len() {
+                    0 => {}
+                    1 => self.push_undo(group.pop().unwrap()),
+                    _ => self.push_undo(EditorEdit::Group(group)),
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn push_undo(&mut self, edit: EditorEdit) {
+        self.undo.push(edit);
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Takes the latest edit to undo it; the caller applies its inverse
+    pub fn take_undo(&mut self) -> Option<EditorEdit> {
+        self.end_group();
+        let edit = self.undo.pop();
+        if let Some(ref edit) = edit {
+            self.redo.push(edit.clone());
+        }
+        edit
+    }
+
+    /// Takes the latest undone edit to apply it again
+    pub fn take_redo(&mut self) -> Option<EditorEdit> {
+        self.end_group();
+        let edit = self.redo.pop();
+        if let Some(ref edit) = edit {
+            self.undo.push(edit.clone());
+        }
+        edit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile_change(index: usize, before: u8, after: u8) -> EditorEdit {
+        let state = |elevation| {
+            TileState {
+                terrain_id: 0.into(),
+                elevation: elevation,
+            }
+        };
+        EditorEdit::Tiles(vec![TileChange {
+                                   index: index,
+                                   before: state(before),
+                                   after: state(after),
+                               }])
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut scenario = scn::Scenario::new(2, 2);
+        let mut triggers = Vec::new();
+        let trigger = EditorTrigger::parse("when count owner 1 == 0 then message Defeat").unwrap();
+        let edit = EditorEdit::Group(vec![tile_change(3, 0, 2),
+                                          EditorEdit::AddUnit {
+                                              player_id: 1.into(),
+                                              index: 0,
+                                              unit: Default::default(),
+                                          },
+                                          EditorEdit::AddTrigger {
+                                              index: 0,
+                                              trigger: trigger,
+                                          }]);
+        assert_eq!(EditorChange::Units, edit.apply(&mut scenario, &mut triggers));
+        assert_eq!(2, scenario.map.tiles[3].elevation);
+        assert_eq!(1, scenario.player_units(1.into()).len());
+        assert_eq!(1, triggers.len());
+
+        edit.inverse().apply(&mut scenario, &mut triggers);
+        assert_eq!(0, scenario.map.tiles[3].elevation);
+        assert!(scenario.player_units(1.into()).is_empty());
+        assert!(triggers.is_empty());
+    }
+
+    #[test]
+    fn test_move_trigger() {
+        let mut scenario = scn::Scenario::new(1, 1);
+        let mut triggers: Vec<EditorTrigger> = ["0", "1", "2"]
+            .iter()
+            .map(|value| EditorTrigger::parse(&format!("when count == {} then message Hi", value)).unwrap())
+            .collect();
+        let original = triggers.clone();
+        let edit = EditorEdit::MoveTrigger { from: 0, to: 2 };
+        edit.apply(&mut scenario, &mut triggers);
+        assert_eq!(vec![original[1].clone(), original[2].clone(), original[0].clone()], triggers);
+        edit.inverse().apply(&mut scenario, &mut triggers);
+        assert_eq!(original, triggers);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = EditHistory::new();
+        history.begin_group();
+        history.record(tile_change(0, 0, 1));
+        history.record(tile_change(1, 0, 1));
+        history.end_group();
+        history.record(tile_change(2, 0, 1));
+
+        match history.take_undo() {
+            Some(EditorEdit::Tiles(ref changes)) => assert_eq!(2, changes[0].index),
+            other => panic!("unexpected undo {:?}", other),
+        }
+        match history.take_undo() {
+            Some(EditorEdit::Group(ref edits)) => assert_eq!(2, edits.len()),
+            other => panic!("unexpected undo {:?}", other),
+        }
+        assert!(history.take_undo().is_none());
+        assert!(history.take_redo().is_some());
+
+        // A new edit drops whatever could have been redone
+        history.record(tile_change(3, 0, 1));
+        assert!(history.take_redo().is_none());
+        assert!(history.take_undo().is_some());
+        assert!(history.take_undo().is_some());
+        assert!(history.take_undo().is_none());
+    }
+}
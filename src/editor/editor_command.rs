@@ -0,0 +1,452 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::resource::DiplomaticStance;
+use identifier::PlayerId;
+use super::map_tools::{Brush, BrushShape};
+use super::scenario_editor::{EditorChange, EditorTool, MAX_BRUSH_RADIUS, MAX_ELEVATION, ScenarioEditor};
+use super::trigger::EditorTrigger;
+use super::validation::{EditorData, has_errors};
+use util::parse::parse_number;
+
+/// Something typed into the editor's command box; this is how the parts of a scenario that
+/// aren't painted on the map get set
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditorCommand {
+    SetTool(EditorTool),
+    SetBrush(Brush),
+    /// Picks the player that placed units belong to
+    SetPlayer(PlayerId),
+    SetStartingResource(PlayerId, ResourceType, f32),
+    SetStance(PlayerId, PlayerId, DiplomaticStance),
+    AddTrigger(EditorTrigger),
+    RemoveTrigger(usize),
+    ReplaceTrigger(usize, EditorTrigger),
+    /// Moves a trigger from one number to another
+    MoveTrigger(usize, usize),
+    /// Logs the scenario's triggers with their numbers
+    ListTriggers,
+    /// Logs the problems the trigger validation finds
+    CheckTriggers,
+    /// Logs the IDs and names of the current player's units, optionally only those whose name
+    /// contains some text, for looking up the IDs that triggers and the unit tool take
+    ListUnits(Option<String>),
+    /// Logs the IDs and names of research, optionally only those whose name contains some text
+    ListResearch(Option<String>),
+    /// Copies the map between two corner tiles, for the paste tool
+    CopyRegion(i32, i32, i32, i32),
+    Undo,
+    Redo,
+    Save,
+}
+
+impl EditorCommand {
+    pub fn parse(text: &str) -> Result<EditorCommand, String> {
+        use self::EditorCommand::*;
+        let mut words = text.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command.to_lowercase(),
+            None => return Err("empty command".into()),
+        };
+        let arguments: Vec<&str> = words.collect();
+        match (&command[..], arguments.len()) {
+            ("terrain", 1) => {
+                let terrain_id = try!(parse_number_up_to(arguments[0], "terrain id", u8::MAX as usize));
+                Ok(SetTool(EditorTool::Terrain(terrain_id.into())))
+            }
+            ("terrain", _) => Err("usage: terrain <terrain id>".into()),
+            ("elevation", 1) => {
+                let elevation = try!(parse_number_up_to(arguments[0], "elevation", MAX_ELEVATION as usize));
+                Ok(SetTool(EditorTool::Elevation(elevation as u8)))
+            }
+            ("elevation", _) => Err("usage: elevation <0-6>".into()),
+            ("unit", 1) => {
+                let unit_id = try!(parse_number_up_to(arguments[0], "unit id", u32::MAX as usize));
+                Ok(SetTool(EditorTool::Unit(unit_id.into())))
+            }
+            ("unit", _) => Err("usage: unit <unit id>".into()),
+            ("fill", 1) => {
+                let terrain_id = try!(parse_number_up_to(arguments[0], "terrain id", u8::MAX as usize));
+                Ok(SetTool(EditorTool::Fill(terrain_id.into())))
+            }
+            ("fill", _) => Err("usage: fill <terrain id>".into()),
+            ("erase", 0) => Ok(SetTool(EditorTool::Erase)),
+            ("brush", 1) | ("brush", 2) => {
+                let radius = try!(parse_number_up_to(arguments[0],
+                                                     "brush radius",
+                                                     MAX_BRUSH_RADIUS as usize));
+                let shape = match arguments.get(1).map(|shape| shape.to_lowercase()) {
+                    None => BrushShape::Square,
+                    Some(ref shape) if shape == "square" => BrushShape::Square,
+                    Some(ref shape) if shape == "round" => BrushShape::Round,
+                    Some(_) => return Err(format!("unknown brush shape \"{}\"", arguments[1])),
+                };
+                Ok(SetBrush(Brush {
+                    radius: radius as u32,
+                    shape: shape,
+                }))
+            }
+            ("brush", _) => Err("usage: brush <radius> [square|round]".into()),
+            ("copy", 4) => {
+                let mut corners = [0; 4];
+                for (corner, argument) in corners.iter_mut().zip(&arguments) {
+                    *corner = try!(parse_number_up_to(argument, "tile coordinate", i32::MAX as usize)) as i32;
+                }
+                Ok(CopyRegion(corners[0], corners[1], corners[2], corners[3]))
+            }
+            ("copy", _) => Err("usage: copy <x1> <y1> <x2> <y2>".into()),
+            ("paste", 0) => Ok(SetTool(EditorTool::Paste)),
+            ("undo", 0) => Ok(Undo),
+            ("redo", 0) => Ok(Redo),
+            ("player", 1) => Ok(SetPlayer(try!(parse_player_id(arguments[0])))),
+            ("player", _) => Err("usage: player <player id>".into()),
+            ("resources", 3) => {
+                let player_id = try!(parse_player_id(arguments[0]));
+                let resource_type = try!(parse_resource_type(arguments[1]));
+                match arguments[2].parse::<f32>() {
+                    Ok(amount) if amount >= 0.0 => {
+                        Ok(SetStartingResource(player_id, resource_type, amount))
+                    }
+                    _ => Err(format!("invalid amount \"{}\"", arguments[2])),
+                }
+            }
+            ("resources", _) => Err("usage: resources <player id> <food|wood|stone|gold> <amount>".into()),
+            ("stance", 3) => {
+                let player_id = try!(parse_player_id(arguments[0]));
+                let other_player_id = try!(parse_player_id(arguments[1]));
+                let stance = match &arguments[2].to_lowercase()[..] {
+                    "ally" => DiplomaticStance::Ally,
+                    "neutral" => DiplomaticStance::Neutral,
+                    "enemy" => DiplomaticStance::Enemy,
+                    _ => return Err(format!("unknown stance \"{}\"", arguments[2])),
+                };
+                Ok(SetStance(player_id, other_player_id, stance))
+            }
+            ("stance", _) => Err("usage: stance <player id> <other player id> <ally|neutral|enemy>".into()),
+            ("trigger", _) => EditorTrigger::parse(&arguments.join(" ")).map(AddTrigger),
+            ("untrigger", 1) => Ok(RemoveTrigger(try!(parse_number(arguments[0], "trigger number")))),
+            ("untrigger", _) => Err("usage: untrigger <trigger number>".into()),
+            ("edittrigger", count) if count >= 2 => {
+                let index = try!(parse_number(arguments[0], "trigger number"));
+                EditorTrigger::parse(&arguments[1..].join(" ")).map(|trigger| ReplaceTrigger(index, trigger))
+            }
+            ("edittrigger", _) => Err("usage: edittrigger <trigger number> when ...".into()),
+            ("movetrigger", 2) => {
+                let from = try!(parse_number(arguments[0], "trigger number"));
+                let to = try!(parse_number(arguments[1], "trigger number"));
+                Ok(MoveTrigger(from, to))
+            }
+            ("movetrigger", _) => Err("usage: movetrigger <trigger number> <new trigger number>".into()),
+            ("triggers", 0) => Ok(ListTriggers),
+            ("check", 0) => Ok(CheckTriggers),
+            ("units", _) => Ok(ListUnits(parse_filter(&arguments))),
+            ("techs", _) => Ok(ListResearch(parse_filter(&arguments))),
+            ("save", 0) => Ok(Save),
+            _ => Err(format!("unknown command \"{}\"", text.trim())),
+        }
+    }
+
+    /// Carries the command out on the scenario, returning what it changed and a line to show
+    /// for it; `data` is where units and research are looked up
+    pub fn apply(self,
+                 editor: &mut ScenarioEditor,
+                 data: &EditorData)
+                 -> Result<(EditorChange, String), String> {
+        use self::EditorCommand::*;
+        match self {
+            SetTool(tool) => {
+                try!(editor.set_tool(tool));
+                Ok((EditorChange::Nothing, format!("Tool: {:?}", editor.tool())))
+            }
+            SetBrush(brush) => {
+                editor.set_brush(brush);
+                let brush = editor.brush();
+                Ok((EditorChange::Nothing, format!("Brush: {:?}, radius {}", brush.shape, brush.radius)))
+            }
+            SetPlayer(player_id) => {
+                try!(editor.set_player_id(player_id));
+                Ok((EditorChange::Nothing, format!("Placing units for player {}", *player_id)))
+            }
+            SetStartingResource(player_id, resource_type, amount) => {
+                try!(editor.set_starting_resource(player_id, resource_type, amount));
+                Ok((EditorChange::Settings,
+                    format!("Player {} starts with {} {:?}", *player_id, amount, resource_type)))
+            }
+            SetStance(player_id, other_player_id, stance) => {
+                try!(editor.set_stance(player_id, other_player_id, stance));
+                Ok((EditorChange::Settings,
+                    format!("Player {} is now {:?} towards player {}", *player_id, stance, *other_player_id)))
+            }
+            AddTrigger(trigger) => {
+                let text = trigger.to_text();
+                editor.add_trigger(trigger);
+                Ok((EditorChange::Settings, format!("Trigger {}: {}", editor.triggers().len() - 1, text)))
+            }
+            RemoveTrigger(index) => {
+                let trigger = try!(editor.remove_trigger(index));
+                Ok((EditorChange::Settings, format!("Removed trigger: {}", trigger.to_text())))
+            }
+            ReplaceTrigger(index, trigger) => {
+                let text = trigger.to_text();
+                try!(editor.replace_trigger(index, trigger));
+                Ok((EditorChange::Settings, format!("Trigger {}: {}", index, text)))
+            }
+            MoveTrigger(from, to) => {
+                try!(editor.move_trigger(from, to));
+                Ok((EditorChange::Settings, format!("Moved trigger {} to {}", from, to)))
+            }
+            ListTriggers => {
+                let lines: Vec<String> = editor.triggers()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, trigger)| format!("{}: {}", index, trigger.to_text()))
+                    .collect();
+                if lines.is_empty() {
+                    Ok((EditorChange::Nothing, "The scenario has no triggers".into()))
+                } else {
+                    Ok((EditorChange::Nothing, lines.join("\n")))
+                }
+            }
+            CheckTriggers => {
+                let lines: Vec<String> =
+                    editor.check_triggers(data).iter().map(|problem| problem.to_string()).collect();
+                if lines.is_empty() {
+                    Ok((EditorChange::Nothing, "No problems found in the triggers".into()))
+                } else {
+                    Ok((EditorChange::Nothing, lines.join("\n")))
+                }
+            }
+            ListUnits(filter) => {
+                let civilization_id = editor.scenario().player_civilization_id(editor.player_id());
+                let units = data.units(civilization_id)
+                    .into_iter()
+                    .map(|(unit_id, name)| if data.is_gated(civilization_id, unit_id) {
+                        (*unit_id as usize, format!("{} (not in this data set)", name))
+                    } else {
+                        (*unit_id as usize, name)
+                    });
+                list_matching(units, filter, "units")
+            }
+            ListResearch(filter) => {
+                let research = data.research()
+                    .into_iter()
+                    .map(|(research_id, name)| (*research_id as usize, name));
+                list_matching(research, filter, "research")
+            }
+            CopyRegion(x1, y1, x2, y2) => {
+                let (width, height) = try!(editor.copy_region(x1, y1, x2, y2));
+                Ok((EditorChange::Nothing,
+                    format!("Copied {}x{} tiles; use paste to put them down", width, height)))
+            }
+            Undo => {
+                let change = try!(editor.undo().ok_or("there's nothing to undo".to_string()));
+                Ok((change, "Undone".into()))
+            }
+            Redo => {
+                let change = try!(editor.redo().ok_or("there's nothing to redo".to_string()));
+                Ok((change, "Redone".into()))
+            }
+            Save => {
+                let problems = editor.check_triggers(data);
+                let mut lines: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+                if has_errors(&problems) {
+                    lines.insert(0, "not saved; fix the trigger errors first:".into());
+                    return Err(lines.join("\n"));
+                }
+                try!(editor.save());
+                lines.insert(0, format!("Saved {}", editor.path().display()));
+                Ok((EditorChange::Nothing, lines.join("\n")))
+            }
+        }
+    }
+}
+
+/// Parses a number for a field that can't hold more than `max`, rather than letting it wrap
+/// around when it's cast down
+fn parse_number_up_to(text: &str, what: &str, max: usize) -> Result<usize, String> {
+    match try!(parse_number(text, what)) {
+        number if number <= max => Ok(number),
+        _ => Err(format!("{} {} is out of range; it can't be over {}", what, text, max)),
+    }
+}
+
+fn parse_player_id(text: &str) -> Result<PlayerId, String> {
+    parse_number_up_to(text, "player id", u8::MAX as usize).map(|player_id| player_id.into())
+}
+
+fn parse_filter(arguments: &[&str]) -> Option<String> {
+    if arguments.is_empty() {
+        None
+    } else {
+        Some(arguments.join(" ").to_lowercase())
+    }
+}
+
+/// Lines of "<id>: <name>" for the entries whose name contains the (lowercase) filter
+fn list_matching<I>(entries: I, filter: Option<String>, what: &str) -> Result<(EditorChange, String), String>
+    where I: Iterator<Item = (usize, String)>
+{
+    let lines: Vec<String> = entries.filter(|&(_, ref name)| match filter {
+            Some(ref filter) => name.to_lowercase().contains(&filter[..]),
+            None => true,
+        })
+        .map(|(id, name)| format!("{}: {}", id, name))
+        .collect();
+    if lines.is_empty() {
+        return Err(format!("no {} match", what));
+    }
+    Ok((EditorChange::Nothing, lines.join("\n")))
+}
+
+fn parse_resource_type(text: &str) -> Result<ResourceType, String> {
+    match &text.to_lowercase()[..] {
+        "food" => Ok(ResourceType::Food),
+        "wood" => Ok(ResourceType::Wood),
+        "stone" => Ok(ResourceType::Stone),
+        "gold" => Ok(ResourceType::Gold),
+        _ => Err(format!("unknown resource \"{}\"", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::resource::DiplomaticStance;
+    use editor::{Brush, BrushShape, EditorChange, EditorData, EditorTool, PlacementRules, ScenarioEditor};
+    use identifier::{CivilizationId, ResearchId, TerrainId, UnitId};
+    use scn;
+    use std::env;
+    use super::EditorCommand;
+
+    struct FakeData;
+
+    impl EditorData for FakeData {
+        fn units(&self, _civilization_id: CivilizationId) -> Vec<(UnitId, String)> {
+            vec![(83.into(), "Villager".into()), (125.into(), "Priest".into()), (299.into(), "Scout".into())]
+        }
+
+        fn has_unit(&self, _civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+            *unit_id == 83 || *unit_id == 125 || *unit_id == 299
+        }
+
+        fn is_gated(&self, _civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+            *unit_id == 125
+        }
+
+        fn research(&self) -> Vec<(ResearchId, String)> {
+            vec![(0.into(), "Woodworking".into())]
+        }
+
+        fn passable(&self, _civilization_id: CivilizationId, _unit_id: UnitId, _terrain: TerrainId) -> bool {
+            true
+        }
+
+        fn placement_rules(&self,
+                           _civilization_id: CivilizationId,
+                           _unit_id: UnitId)
+                           -> Option<PlacementRules> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(EditorCommand::SetTool(EditorTool::Terrain(2.into()))),
+                   EditorCommand::parse("terrain 2"));
+        assert_eq!(Ok(EditorCommand::SetTool(EditorTool::Erase)),
+                   EditorCommand::parse("Erase"));
+        assert_eq!(Ok(EditorCommand::SetStartingResource(1.into(), ResourceType::Wood, 200.0)),
+                   EditorCommand::parse("resources 1 wood 200"));
+        assert_eq!(Ok(EditorCommand::SetStance(1.into(), 2.into(), DiplomaticStance::Ally)),
+                   EditorCommand::parse("stance 1 2 ally"));
+        assert_eq!(Ok(EditorCommand::RemoveTrigger(3)), EditorCommand::parse("untrigger 3"));
+        assert_eq!(Ok(EditorCommand::SetBrush(Brush {
+                       radius: 3,
+                       shape: BrushShape::Round,
+                   })),
+                   EditorCommand::parse("brush 3 round"));
+        assert_eq!(Ok(EditorCommand::CopyRegion(4, 5, 0, 1)), EditorCommand::parse("copy 4 5 0 1"));
+        assert_eq!(Ok(EditorCommand::MoveTrigger(2, 0)), EditorCommand::parse("movetrigger 2 0"));
+        assert_eq!(Ok(EditorCommand::ListUnits(Some("war elephant".into()))),
+                   EditorCommand::parse("units War Elephant"));
+        assert_eq!(Ok(EditorCommand::ListResearch(None)), EditorCommand::parse("techs"));
+        assert!(EditorCommand::parse("edittrigger 0").is_err());
+        assert!(EditorCommand::parse("brush 3 triangle").is_err());
+        assert!(EditorCommand::parse("resources 1 wood -5").is_err());
+        assert!(EditorCommand::parse("stance 1 2 friendly").is_err());
+        assert!(EditorCommand::parse("trigger when nothing").is_err());
+        assert!(EditorCommand::parse("terrain").is_err());
+        assert!(EditorCommand::parse("terrain 256").is_err());
+        assert!(EditorCommand::parse("elevation 7").is_err());
+        assert!(EditorCommand::parse("player 257").is_err());
+        assert!(EditorCommand::parse("").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let path = env::temp_dir().join("chariot_editor_command_unsaved.scn");
+        let mut editor = ScenarioEditor::new(scn::Scenario::new(2, 2), path).unwrap();
+        let apply = |editor: &mut ScenarioEditor, text: &str| {
+            EditorCommand::parse(text).unwrap().apply(editor, &FakeData)
+        };
+
+        assert_eq!(EditorChange::Nothing, apply(&mut editor, "unit 83").unwrap().0);
+        assert_eq!(EditorTool::Unit(83.into()), editor.tool());
+        assert_eq!(EditorChange::Settings,
+                   apply(&mut editor, "trigger when count owner 1 == 0 then message Defeat").unwrap().0);
+        assert_eq!(1, editor.triggers().len());
+        assert!(apply(&mut editor, "untrigger 1").is_err());
+        apply(&mut editor, "untrigger 0").unwrap();
+        assert!(editor.triggers().is_empty());
+        assert!(apply(&mut editor, "player 10").is_err());
+        assert_eq!(EditorChange::Settings, apply(&mut editor, "undo").unwrap().0);
+        assert_eq!(1, editor.triggers().len());
+        assert!(apply(&mut editor, "redo").is_ok());
+        assert!(apply(&mut editor, "redo").is_err());
+        assert!(apply(&mut editor, "paste").is_err());
+    }
+
+    #[test]
+    fn test_trigger_editing() {
+        let path = env::temp_dir().join("chariot_editor_command_triggers.scn");
+        let mut editor = ScenarioEditor::new(scn::Scenario::new(2, 2), path).unwrap();
+        let apply = |editor: &mut ScenarioEditor, text: &str| {
+            EditorCommand::parse(text).unwrap().apply(editor, &FakeData)
+        };
+
+        apply(&mut editor, "trigger when count == 0 then message First").unwrap();
+        apply(&mut editor, "trigger when count == 0 then spawn 1 83 0 0").unwrap();
+        apply(&mut editor, "movetrigger 1 0").unwrap();
+        assert_eq!("when count == 0 then spawn 1 83 0 0", editor.triggers()[0].to_text());
+        apply(&mut editor, "edittrigger 0 when count == 0 then spawn 1 84 0 0").unwrap();
+        assert_eq!("when count == 0 then spawn 1 84 0 0", editor.triggers()[0].to_text());
+        assert!(apply(&mut editor, "edittrigger 2 when count == 0 then message Hi").is_err());
+
+        assert!(apply(&mut editor, "check").unwrap().1.contains("trigger 0: error"));
+        assert!(apply(&mut editor, "save").is_err());
+        assert!(!editor.path().exists());
+
+        assert_eq!("299: Scout", apply(&mut editor, "units scout").unwrap().1);
+        assert_eq!("125: Priest (not in this data set)", apply(&mut editor, "units priest").unwrap().1);
+        assert!(apply(&mut editor, "units cavalry").is_err());
+    }
+}
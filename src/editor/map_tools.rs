@@ -0,0 +1,399 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{PlayerId, TerrainId};
+use scn::{self, PlayerUnit};
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+use super::edit::{TileChange, TileState};
+use super::scenario_editor::MAX_ELEVATION;
+
+/// Offsets to the four tiles that share an edge with a tile
+const EDGE_NEIGHBORS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+/// Offsets to all eight tiles around a tile
+const ALL_NEIGHBORS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BrushShape {
+    Square,
+    Round,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Brush {
+    /// How many tiles out from the center tile the brush reaches
+    pub radius: u32,
+    pub shape: BrushShape,
+}
+
+impl Brush {
+    /// The tiles on the map under the brush when it's centered on the given tile
+    pub fn tiles(&self, map: &scn::Map, center_x: i32, center_y: i32) -> Vec<(i32, i32)> {
+        let radius = self.radius as i32;
+        let mut tiles = Vec::new();
+        for y in cmp::max(0, center_y - radius)..cmp::min(map.height as i32, center_y + radius + 1) {
+            for x in cmp::max(0, center_x - radius)..cmp::min(map.width as i32, center_x + radius + 1) {
+                let (dx, dy) = (x - center_x, y - center_y);
+                // Rounding the radius out by half a tile keeps small round brushes from
+                // looking like plus signs
+                if self.shape == BrushShape::Square || (dx * dx + dy * dy) * 4 <= (2 * radius + 1).pow(2) {
+                    tiles.push((x, y));
+                }
+            }
+        }
+        tiles
+    }
+}
+
+/// Changes to a map's tiles that haven't been applied yet, remembering how each tile was
+struct PendingTiles<'a> {
+    map: &'a scn::Map,
+    changes: BTreeMap<usize, TileChange>,
+}
+
+impl<'a> PendingTiles<'a> {
+    fn new(map: &'a scn::Map) -> PendingTiles<'a> {
+        PendingTiles {
+            map: map,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.map.width as i32 && y < self.map.height as i32
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.map.width as i32 + x) as usize
+    }
+
+    fn get(&self, x: i32, y: i32) -> TileState {
+        let index = self.index(x, y);
+        match self.changes.get(&index) {
+            Some(change) => change.after,
+            None => TileState::of(&self.map.tiles[index]),
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, state: TileState) {
+        let index = self.index(x, y);
+        let before = TileState::of(&self.map.tiles[index]);
+        self.changes.insert(index,
+                            TileChange {
+                                index: index,
+                                before: before,
+                                after: state,
+                            });
+    }
+
+    fn into_changes(self) -> Vec<TileChange> {
+        self.changes
+            .into_iter()
+            .map(|(_, change)| change)
+            .filter(|change| change.before != change.after)
+            .collect()
+    }
+}
+
+/// Paints the tiles with a terrain. Terrain borders are drawn on one tile from only one of its
+/// neighbors, so a tile left without any edge neighbor of its own terrain can't blend into the
+/// terrain around it; those tiles around the paint are painted over too.
+pub fn paint_terrain(map: &scn::Map, tiles: &[(i32, i32)], terrain_id: TerrainId) -> Vec<TileChange> {
+    let mut pending = PendingTiles::new(map);
+    for &(x, y) in tiles {
+        let state = pending.get(x, y);
+        pending.set(x,
+                    y,
+                    TileState {
+                        terrain_id: terrain_id,
+                        elevation: state.elevation,
+                    });
+    }
+
+    // Covering a tile can leave its own neighbors cut off, so they get checked in turn
+    let mut unchecked: VecDeque<(i32, i32)> = tiles.iter()
+        .flat_map(|&(x, y)| EDGE_NEIGHBORS.iter().map(move |&(dx, dy)| (x + dx, y + dy)))
+        .collect();
+    while let Some((x, y)) = unchecked.pop_front() {
+        if !pending.contains(x, y) {
+            continue;
+        }
+        let state = pending.get(x, y);
+        if state.terrain_id == terrain_id {
+            continue;
+        }
+        let isolated = EDGE_NEIGHBORS.iter()
+            .map(|&(dx, dy)| (x + dx, y + dy))
+            .filter(|&(nx, ny)| pending.contains(nx, ny))
+            .all(|(nx, ny)| pending.get(nx, ny).terrain_id != state.terrain_id);
+        if isolated {
+            pending.set(x,
+                        y,
+                        TileState {
+                            terrain_id: terrain_id,
+                            elevation: state.elevation,
+                        });
+            unchecked.extend(EDGE_NEIGHBORS.iter().map(|&(dx, dy)| (x + dx, y + dy)));
+        }
+    }
+    pending.into_changes()
+}
+
+/// Raises or lowers the tiles to an elevation. Elevation graphics only have slopes that go
+/// up or down by one level, so the tiles around are ramped until no two neighbors are more
+/// than a level apart.
+pub fn paint_elevation(map: &scn::Map, tiles: &[(i32, i32)], elevation: u8) -> Vec<TileChange> {
+    let elevation = cmp::min(elevation, MAX_ELEVATION);
+    let mut pending = PendingTiles::new(map);
+    let mut queue = VecDeque::new();
+    for &(x, y) in tiles {
+        let state = pending.get(x, y);
+        pending.set(x,
+                    y,
+                    TileState {
+                        terrain_id: state.terrain_id,
+                        elevation: elevation,
+                    });
+        queue.push_back((x, y));
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let tile_elevation = pending.get(x, y).elevation as i32;
+        for &(dx, dy) in &ALL_NEIGHBORS {
+            let (nx, ny) = (x + dx, y + dy);
+            if !pending.contains(nx, ny) {
+                continue;
+            }
+            let neighbor = pending.get(nx, ny);
+            let difference = neighbor.elevation as i32 - tile_elevation;
+            if difference.abs() > 1 {
+                let ramped = tile_elevation + difference.signum();
+                pending.set(nx,
+                            ny,
+                            TileState {
+                                terrain_id: neighbor.terrain_id,
+                                elevation: ramped as u8,
+                            });
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    pending.into_changes()
+}
+
+/// Paints the terrain over the area of same-terrain tiles connected to the given tile
+pub fn fill_terrain(map: &scn::Map, x: i32, y: i32, terrain_id: TerrainId) -> Vec<TileChange> {
+    let mut pending = PendingTiles::new(map);
+    if !pending.contains(x, y) {
+        return Vec::new();
+    }
+    let replaced = pending.get(x, y).terrain_id;
+    if replaced == terrain_id {
+        return Vec::new();
+    }
+
+    let mut filled = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((x, y));
+    while let Some((x, y)) = queue.pop_front() {
+        if !pending.contains(x, y) || pending.get(x, y).terrain_id != replaced {
+            continue;
+        }
+        let state = pending.get(x, y);
+        pending.set(x,
+                    y,
+                    TileState {
+                        terrain_id: terrain_id,
+                        elevation: state.elevation,
+                    });
+        filled.push((x, y));
+        for &(dx, dy) in &EDGE_NEIGHBORS {
+            queue.push_back((x + dx, y + dy));
+        }
+    }
+    let map = pending.map;
+    paint_terrain(map, &filled, terrain_id)
+}
+
+/// A rectangle of a map copied out, with the units that were on it
+#[derive(Clone, Debug)]
+pub struct MapRegion {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row, like the map's own tiles
+    pub tiles: Vec<TileState>,
+    /// Units with their positions relative to the region's top left corner
+    pub units: Vec<(PlayerId, PlayerUnit)>,
+}
+
+impl MapRegion {
+    /// Copies the tiles from one corner to the other, both included, and the units on them
+    pub fn copy(scenario: &scn::Scenario, x1: i32, y1: i32, x2: i32, y2: i32) -> Result<MapRegion, String> {
+        let map = &scenario.map;
+        let (left, right) = (cmp::min(x1, x2), cmp::max(x1, x2));
+        let (top, bottom) = (cmp::min(y1, y2), cmp::max(y1, y2));
+        if left < 0 || top < 0 || right >= map.width as i32 || bottom >= map.height as i32 {
+            return Err(format!("the region has to be within the {}x{} map", map.width, map.height));
+        }
+
+        let mut tiles = Vec::new();
+        for y in top..(bottom + 1) {
+            for x in left..(right + 1) {
+                tiles.push(TileState::of(&map.tiles[(y * map.width as i32 + x) as usize]));
+            }
+        }
+        let mut units = Vec::new();
+        for player_id in scenario.player_ids() {
+            for unit in scenario.player_units(player_id) {
+                let (x, y) = (unit.position_x.floor() as i32, unit.position_y.floor() as i32);
+                if x >= left && x <= right && y >= top && y <= bottom {
+                    let mut copy = unit.clone();
+                    copy.position_x -= left as f32;
+                    copy.position_y -= top as f32;
+                    copy.spawn_id = None;
+                    units.push((player_id, copy));
+                }
+            }
+        }
+        Ok(MapRegion {
+            width: (right - left + 1) as u32,
+            height: (bottom - top + 1) as u32,
+            tiles: tiles,
+            units: units,
+        })
+    }
+
+    /// The changes that put the region's tiles on the map with its top left corner at the
+    /// given tile; whatever would fall off the map is left out
+    pub fn paste_tiles(&self, map: &scn::Map, left: i32, top: i32) -> Vec<TileChange> {
+        let mut pending = PendingTiles::new(map);
+        for region_y in 0..self.height as i32 {
+            for region_x in 0..self.width as i32 {
+                let (x, y) = (left + region_x, top + region_y);
+                if pending.contains(x, y) {
+                    pending.set(x, y, self.tiles[(region_y * self.width as i32 + region_x) as usize]);
+                }
+            }
+        }
+        pending.into_changes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_with_terrain(width: u32, height: u32, terrain_ids: &[u8]) -> scn::Scenario {
+        let mut scenario = scn::Scenario::new(width, height);
+        for (tile, &terrain_id) in scenario.map.tiles.iter_mut().zip(terrain_ids) {
+            tile.terrain_id = (terrain_id as usize).into();
+        }
+        scenario
+    }
+
+    fn apply(map: &mut scn::Map, changes: &[TileChange]) {
+        for change in changes {
+            map.tiles[change.index].terrain_id = change.after.terrain_id;
+            map.tiles[change.index].elevation = change.after.elevation;
+        }
+    }
+
+    #[test]
+    fn test_brush_shapes() {
+        let scenario = scn::Scenario::new(10, 10);
+        let square = Brush {
+            radius: 1,
+            shape: BrushShape::Square,
+        };
+        assert_eq!(9, square.tiles(&scenario.map, 5, 5).len());
+        assert_eq!(4, square.tiles(&scenario.map, 0, 0).len());
+        let round = Brush {
+            radius: 2,
+            shape: BrushShape::Round,
+        };
+        let tiles = round.tiles(&scenario.map, 5, 5);
+        assert_eq!(21, tiles.len());
+        assert!(!tiles.contains(&(3, 3)));
+    }
+
+    #[test]
+    fn test_paint_terrain_covers_slivers() {
+        // The 1 at the right edge would be left with no neighbor of its own terrain
+        let scenario = map_with_terrain(4, 3, &[0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]);
+        let changes = paint_terrain(&scenario.map, &[(1, 1)], 2.into());
+        let indices: Vec<usize> = changes.iter().map(|change| change.index).collect();
+        assert_eq!(vec![5, 6], indices);
+        assert!(changes.iter().all(|change| change.after.terrain_id == 2.into()));
+    }
+
+    #[test]
+    fn test_paint_elevation_ramps() {
+        let mut scenario = scn::Scenario::new(7, 1);
+        let changes = paint_elevation(&scenario.map, &[(0, 0)], 4);
+        apply(&mut scenario.map, &changes);
+        let elevations: Vec<u8> = scenario.map.tiles.iter().map(|tile| tile.elevation).collect();
+        assert_eq!(vec![4, 3, 2, 1, 0, 0, 0], elevations);
+
+        let changes = paint_elevation(&scenario.map, &[(0, 0)], 0);
+        apply(&mut scenario.map, &changes);
+        let elevations: Vec<u8> = scenario.map.tiles.iter().map(|tile| tile.elevation).collect();
+        assert_eq!(vec![0, 1, 2, 1, 0, 0, 0], elevations);
+    }
+
+    #[test]
+    fn test_fill_terrain() {
+        let scenario = map_with_terrain(3, 3, &[0, 0, 1, 1, 0, 1, 0, 1, 1]);
+        let changes = fill_terrain(&scenario.map, 0, 0, 3.into());
+        let indices: Vec<usize> = changes.iter().map(|change| change.index).collect();
+        // The 1 on the left edge is cut off from its own terrain by the fill, and then so is
+        // the 0 below it, so both get covered
+        assert_eq!(vec![0, 1, 3, 4, 6], indices);
+        assert!(fill_terrain(&scenario.map, 2, 0, 1.into()).is_empty());
+        assert!(fill_terrain(&scenario.map, 5, 0, 3.into()).is_empty());
+    }
+
+    #[test]
+    fn test_copy_and_paste() {
+        let mut scenario = map_with_terrain(3, 3, &[1, 2, 0, 3, 4, 0, 0, 0, 0]);
+        scenario.add_unit(1.into(),
+                          PlayerUnit {
+                              position_x: 1.5,
+                              position_y: 0.5,
+                              ..Default::default()
+                          });
+        scenario.add_unit(1.into(),
+                          PlayerUnit {
+                              position_x: 2.5,
+                              position_y: 2.5,
+                              ..Default::default()
+                          });
+        let region = MapRegion::copy(&scenario, 1, 1, 0, 0).unwrap();
+        assert_eq!((2, 2), (region.width, region.height));
+        assert_eq!(1, region.units.len());
+        assert_eq!(1.5, region.units[0].1.position_x);
+        assert!(MapRegion::copy(&scenario, 0, 0, 3, 1).is_err());
+
+        let changes = region.paste_tiles(&scenario.map, 2, 1);
+        apply(&mut scenario.map, &changes);
+        let terrain_ids: Vec<u8> = scenario.map.tiles.iter().map(|tile| *tile.terrain_id).collect();
+        assert_eq!(vec![1, 2, 0, 3, 4, 1, 0, 0, 3], terrain_ids);
+    }
+}
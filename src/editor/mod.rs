@@ -0,0 +1,36 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+mod edit;
+mod editor_command;
+mod map_tools;
+mod scenario_editor;
+mod trigger;
+mod validation;
+
+pub use self::edit::{EditHistory, EditorEdit, TileChange, TileState};
+pub use self::editor_command::EditorCommand;
+pub use self::map_tools::{Brush, BrushShape, MapRegion};
+pub use util::placement::{FootprintTile, Placement, PlacementRules, check_placement};
+pub use self::scenario_editor::{EditorChange, EditorTool, MAX_BRUSH_RADIUS, MAX_ELEVATION, ScenarioEditor};
+pub use self::trigger::{EditorTrigger, TriggerEffect, triggers_from_script};
+pub use self::validation::{EditorData, EmpiresEditorData, Severity, TriggerProblem, has_errors,
+                           validate_triggers};
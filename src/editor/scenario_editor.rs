@@ -0,0 +1,700 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::resource::DiplomaticStance;
+use identifier::{PlayerId, SpawnId, TerrainId, UnitId};
+use scn::{self, PlayerUnit};
+use script::ScenarioScript;
+use std::cmp;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use super::edit::{EditHistory, EditorEdit, TileChange};
+use super::map_tools::{self, Brush, BrushShape, MapRegion};
+use super::trigger::{self, EditorTrigger};
+use super::validation::{self, EditorData, TriggerProblem};
+use util::placement::{self, Placement};
+
+/// Highest elevation the terrain can be raised to
+pub const MAX_ELEVATION: u8 = 6;
+pub const MAX_BRUSH_RADIUS: u32 = 8;
+
+/// Scenarios hold units for gaia (player 0) and up to 8 players
+const PLAYER_SLOTS: usize = 9;
+/// How close to a unit, in tiles, a click has to be to pick it
+const PICK_RADIUS: f32 = 0.75;
+const ROTATION_STEP: f32 = PI / 4.0;
+
+/// What a click on the map does
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditorTool {
+    Terrain(TerrainId),
+    Elevation(u8),
+    /// Paints a terrain over the connected area of the clicked tile's terrain
+    Fill(TerrainId),
+    Unit(UnitId),
+    Erase,
+    /// Puts the copied region down with its top left corner on the clicked tile
+    Paste,
+}
+
+/// What part of the scenario an edit changed, so the preview only rebuilds what it has to.
+/// Later variants take in the earlier ones: rebuilding for units rebuilds the map as well.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum EditorChange {
+    Nothing,
+    /// Player data, triggers, and other things the preview doesn't show
+    Settings,
+    Map,
+    Units,
+}
+
+/// A scenario being edited, along with the editor's current tool and player. Positions are in
+/// tiles, with x being the map column and y the row. Every change to the map, its units, and
+/// the triggers goes through the edit history, so it can be undone.
+pub struct ScenarioEditor {
+    scenario: scn::Scenario,
+    path: PathBuf,
+    tool: EditorTool,
+    player_id: PlayerId,
+    brush: Brush,
+    triggers: Vec<EditorTrigger>,
+    history: EditHistory,
+    clipboard: Option<MapRegion>,
+    /// Set when the scenario already has a script the editor didn't write, which saving would
+    /// otherwise replace
+    handwritten_script: bool,
+}
+
+impl ScenarioEditor {
+    /// Starts editing a scenario that will be saved to the given path, picking up the triggers
+    /// from its script if the editor wrote it
+    pub fn new<P: Into<PathBuf>>(scenario: scn::Scenario, path: P) -> Result<ScenarioEditor, String> {
+        let path = path.into();
+        let mut triggers = Vec::new();
+        let mut handwritten_script = false;
+        let script_path = ScenarioScript::sidecar_path(&path);
+        if script_path.is_file() {
+            let mut source = String::new();
+            try!(File::open(&script_path)
+                .and_then(|mut file| file.read_to_string(&mut source))
+                .map_err(|err| format!("failed to read {}: {}", script_path.display(), err)));
+            match trigger::triggers_from_script(&source) {
+                Some(result) => {
+                    triggers = try!(result.map_err(|err| format!("{}: {}", script_path.display(), err)))
+                }
+                None => handwritten_script = true,
+            }
+        }
+
+        Ok(ScenarioEditor {
+            scenario: scenario,
+            path: path,
+            tool: EditorTool::Terrain(0.into()),
+            player_id: 1.into(),
+            brush: Brush {
+                radius: 0,
+                shape: BrushShape::Square,
+            },
+            triggers: triggers,
+            history: EditHistory::new(),
+            clipboard: None,
+            handwritten_script: handwritten_script,
+        })
+    }
+
+    pub fn scenario<'a>(&'a self) -> &'a scn::Scenario {
+        &self.scenario
+    }
+
+    pub fn path<'a>(&'a self) -> &'a Path {
+        &self.path
+    }
+
+    pub fn tool(&self) -> EditorTool {
+        self.tool
+    }
+
+    pub fn set_tool(&mut self, tool: EditorTool) -> Result<(), String> {
+        self.tool = match tool {
+            EditorTool::Elevation(elevation) => EditorTool::Elevation(cmp::min(elevation, MAX_ELEVATION)),
+            EditorTool::Paste if self.clipboard.is_none() => return Err("nothing has been copied".into()),
+            tool => tool,
+        };
+        Ok(())
+    }
+
+    /// The player that placed units belong to
+    pub fn player_id(&self) -> PlayerId {
+        self.player_id
+    }
+
+    pub fn set_player_id(&mut self, player_id: PlayerId) -> Result<(), String> {
+        try!(check_player_id(player_id));
+        self.player_id = player_id;
+        Ok(())
+    }
+
+    /// What terrain and elevation get painted with
+    pub fn brush(&self) -> Brush {
+        self.brush
+    }
+
+    pub fn set_brush(&mut self, brush: Brush) {
+        self.brush = Brush {
+            radius: cmp::min(brush.radius, MAX_BRUSH_RADIUS),
+            shape: brush.shape,
+        };
+    }
+
+    /// Starts a stroke: everything the tools do until `end_stroke` is undone in one step
+    pub fn begin_stroke(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.history.end_group();
+    }
+
+    /// Uses the current tool at the given position; `data` is where the rules for placing
+    /// buildings are looked up
+    pub fn apply_tool(&mut self, x: f32, y: f32, data: &EditorData) -> EditorChange {
+        let (tile_x, tile_y) = (x.floor() as i32, y.floor() as i32);
+        match self.tool {
+            EditorTool::Terrain(terrain_id) => {
+                let tiles = self.brush.tiles(&self.scenario.map, tile_x, tile_y);
+                let changes = map_tools::paint_terrain(&self.scenario.map, &tiles, terrain_id);
+                self.perform_tile_changes(changes)
+            }
+            EditorTool::Elevation(elevation) => {
+                let tiles = self.brush.tiles(&self.scenario.map, tile_x, tile_y);
+                let changes = map_tools::paint_elevation(&self.scenario.map, &tiles, elevation);
+                self.perform_tile_changes(changes)
+            }
+            EditorTool::Fill(terrain_id) => {
+                let changes = map_tools::fill_terrain(&self.scenario.map, tile_x, tile_y, terrain_id);
+                self.perform_tile_changes(changes)
+            }
+            EditorTool::Unit(unit_id) => {
+                let (x, y) = match self.check_placement(data, unit_id, x, y) {
+                    Some(ref placement) if !placement.is_allowed() => return EditorChange::Nothing,
+                    Some(placement) => (placement.x, placement.y),
+                    None => (x, y),
+                };
+                let player_id = self.player_id;
+                self.place_unit(player_id, unit_id, x, y);
+                EditorChange::Units
+            }
+            EditorTool::Erase => self.delete_unit_near(x, y),
+            EditorTool::Paste => self.paste(tile_x, tile_y),
+        }
+    }
+
+    /// Applies an edit and adds it to the history
+    fn perform(&mut self, edit: EditorEdit) -> EditorChange {
+        let change = edit.apply(&mut self.scenario, &mut self.triggers);
+        self.history.record(edit);
+        change
+    }
+
+    fn perform_tile_changes(&mut self, changes: Vec<TileChange>) -> EditorChange {
+        if changes.is_empty() {
+            return EditorChange::Nothing;
+        }
+        self.perform(EditorEdit::Tiles(changes))
+    }
+
+    pub fn undo(&mut self) -> Option<EditorChange> {
+        self.history
+            .take_undo()
+            .map(|edit| edit.inverse().apply(&mut self.scenario, &mut self.triggers))
+    }
+
+    pub fn redo(&mut self) -> Option<EditorChange> {
+        self.history
+            .take_redo()
+            .map(|edit| edit.apply(&mut self.scenario, &mut self.triggers))
+    }
+
+    /// Where the unit tool would put a building, lined up with the tiles, and whether the
+    /// ground there allows it; None for units that aren't buildings, which go anywhere
+    pub fn check_placement(&self, data: &EditorData, unit_id: UnitId, x: f32, y: f32) -> Option<Placement> {
+        let civilization_id = self.scenario.player_civilization_id(self.player_id);
+        data.placement_rules(civilization_id, unit_id).map(|rules| {
+            placement::check_placement(&self.scenario.map, &rules, x, y, |terrain_id| {
+                data.passable(civilization_id, unit_id, terrain_id)
+            })
+        })
+    }
+
+    pub fn place_unit(&mut self, player_id: PlayerId, unit_id: UnitId, x: f32, y: f32) -> SpawnId {
+        let elevation = self.elevation_at(x, y);
+        let unit = PlayerUnit {
+            position_x: x,
+            position_y: y,
+            position_z: elevation as f32,
+            unit_id: unit_id,
+            ..Default::default()
+        };
+        self.add_unit(player_id, unit)
+    }
+
+    fn add_unit(&mut self, player_id: PlayerId, mut unit: PlayerUnit) -> SpawnId {
+        let spawn_id = self.scenario.next_spawn_id();
+        unit.spawn_id = Some(spawn_id);
+        let index = self.scenario.player_units(player_id).len();
+        self.perform(EditorEdit::AddUnit {
+            player_id: player_id,
+            index: index,
+            unit: unit,
+        });
+        spawn_id
+    }
+
+    fn elevation_at(&self, x: f32, y: f32) -> u8 {
+        let map = &self.scenario.map;
+        if x < 0.0 || y < 0.0 || x >= map.width as f32 || y >= map.height as f32 {
+            return 0;
+        }
+        map.tiles[y as usize * map.width as usize + x as usize].elevation
+    }
+
+    /// The player and index of the closest unit within picking distance of the position
+    fn unit_near(&self, x: f32, y: f32) -> Option<(PlayerId, usize)> {
+        let mut closest: Option<(f32, PlayerId, usize)> = None;
+        for player_id in self.scenario.player_ids() {
+            for (index, unit) in self.scenario.player_units(player_id).iter().enumerate() {
+                let (dx, dy) = (unit.position_x - x, unit.position_y - y);
+                let distance = (dx * dx + dy * dy).sqrt();
+                let closer = closest.map(|(closest, _, _)| distance < closest).unwrap_or(true);
+                if distance <= PICK_RADIUS && closer {
+                    closest = Some((distance, player_id, index));
+                }
+            }
+        }
+        closest.map(|(_, player_id, index)| (player_id, index))
+    }
+
+    /// Deletes the unit closest to the position, whichever player it belongs to
+    pub fn delete_unit_near(&mut self, x: f32, y: f32) -> EditorChange {
+        match self.unit_near(x, y) {
+            Some((player_id, index)) => {
+                let unit = self.scenario.player_units(player_id)[index].clone();
+                self.perform(EditorEdit::RemoveUnit {
+                    player_id: player_id,
+                    index: index,
+                    unit: unit,
+                })
+            }
+            None => EditorChange::Nothing,
+        }
+    }
+
+    /// Turns the unit closest to the position an eighth of the way around
+    pub fn rotate_unit_near(&mut self, x: f32, y: f32) -> EditorChange {
+        match self.unit_near(x, y) {
+            Some((player_id, index)) => {
+                let before = self.scenario.player_units(player_id)[index].clone();
+                let mut after = before.clone();
+                after.rotation = (after.rotation + ROTATION_STEP) % (2.0 * PI);
+                self.perform(EditorEdit::ChangeUnit {
+                    player_id: player_id,
+                    index: index,
+                    before: before,
+                    after: after,
+                })
+            }
+            None => EditorChange::Nothing,
+        }
+    }
+
+    /// Copies the tiles from one corner to the other, both included, and the units on them
+    pub fn copy_region(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> Result<(u32, u32), String> {
+        let region = try!(MapRegion::copy(&self.scenario, x1, y1, x2, y2));
+        let size = (region.width, region.height);
+        self.clipboard = Some(region);
+        Ok(size)
+    }
+
+    /// Puts the copied region down with its top left corner on the given tile; the pasted
+    /// units get new spawn IDs
+    pub fn paste(&mut self, left: i32, top: i32) -> EditorChange {
+        let region = match self.clipboard {
+            Some(ref region) => region.clone(),
+            None => return EditorChange::Nothing,
+        };
+        self.history.begin_group();
+        let mut change = self.perform_tile_changes(region.paste_tiles(&self.scenario.map, left, top));
+        let (width, height) = (self.scenario.map.width as f32, self.scenario.map.height as f32);
+        for (player_id, mut unit) in region.units {
+            unit.position_x += left as f32;
+            unit.position_y += top as f32;
+            if unit.position_x < width && unit.position_y < height && unit.position_x >= 0.0 &&
+               unit.position_y >= 0.0 {
+                unit.position_z = self.elevation_at(unit.position_x, unit.position_y) as f32;
+                self.add_unit(player_id, unit);
+                change = EditorChange::Units;
+            }
+        }
+        self.history.end_group();
+        change
+    }
+
+    pub fn set_starting_resource(&mut self,
+                                 player_id: PlayerId,
+                                 resource_type: ResourceType,
+                                 amount: f32)
+                                 -> Result<(), String> {
+        let resources = try!(self.scenario
+            .player_resources_mut(player_id)
+            .ok_or(format!("player {} can't be given starting resources", *player_id)));
+        match resource_type {
+            ResourceType::Food => resources.food = amount,
+            ResourceType::Wood => resources.wood = amount,
+            ResourceType::Stone => resources.stone = amount,
+            ResourceType::Gold => resources.gold = amount,
+            _ => return Err(format!("{:?} isn't a starting resource", resource_type)),
+        }
+        Ok(())
+    }
+
+    /// Sets how the player regards the other player; stances are one-directional
+    pub fn set_stance(&mut self,
+                      player_id: PlayerId,
+                      other_player_id: PlayerId,
+                      stance: DiplomaticStance)
+                      -> Result<(), String> {
+        try!(check_player_id(player_id));
+        try!(check_player_id(other_player_id));
+        self.scenario
+            .player_data
+            .diplomacy
+            .set_stance(*player_id as usize, *other_player_id as usize, stance.to_scn());
+        Ok(())
+    }
+
+    pub fn triggers<'a>(&'a self) -> &'a [EditorTrigger] {
+        &self.triggers
+    }
+
+    pub fn add_trigger(&mut self, trigger: EditorTrigger) {
+        let index = self.triggers.len();
+        self.perform(EditorEdit::AddTrigger {
+            index: index,
+            trigger: trigger,
+        });
+    }
+
+    pub fn remove_trigger(&mut self, index: usize) -> Result<EditorTrigger, String> {
+        try!(self.check_trigger_index(index));
+        let trigger = self.triggers[index].clone();
+        self.perform(EditorEdit::RemoveTrigger {
+            index: index,
+            trigger: trigger.clone(),
+        });
+        Ok(trigger)
+    }
+
+    pub fn replace_trigger(&mut self, index: usize, trigger: EditorTrigger) -> Result<(), String> {
+        try!(self.check_trigger_index(index));
+        let before = self.triggers[index].clone();
+        self.perform(EditorEdit::ReplaceTrigger {
+            index: index,
+            before: before,
+            after: trigger,
+        });
+        Ok(())
+    }
+
+    /// Moves a trigger to another place in the list; triggers are checked in order, so this
+    /// decides which of two triggers that fire on the same tick happens first
+    pub fn move_trigger(&mut self, from: usize, to: usize) -> Result<(), String> {
+        try!(self.check_trigger_index(from));
+        try!(self.check_trigger_index(to));
+        if from != to {
+            self.perform(EditorEdit::MoveTrigger { from: from, to: to });
+        }
+        Ok(())
+    }
+
+    fn check_trigger_index(&self, index: usize) -> Result<(), String> {
+        if index >= self.triggers.len() {
+            return Err(format!("there's no trigger {}; the scenario has {}", index, self.triggers.len()));
+        }
+        Ok(())
+    }
+
+    /// Checks the triggers against the scenario and the game data
+    pub fn check_triggers(&self, data: &EditorData) -> Vec<TriggerProblem> {
+        validation::validate_triggers(&self.scenario, &self.triggers, data)
+    }
+
+    /// Writes the scenario out, along with its triggers as the scenario's script
+    pub fn save(&mut self) -> Result<(), String> {
+        let script_path = ScenarioScript::sidecar_path(&self.path);
+        if self.handwritten_script && !self.triggers.is_empty() {
+            return Err(format!("{} wasn't written by the editor, so it won't be replaced with the triggers; \
+                                move it out of the way first",
+                               script_path.display()));
+        }
+        try!(self.scenario
+            .write_to_file(&self.path)
+            .map_err(|err| format!("failed to write {}: {}", self.path.display(), err)));
+        if !self.handwritten_script {
+            let script = trigger::triggers_to_script(&self.triggers);
+            try!(File::create(&script_path)
+                .and_then(|mut file| file.write_all(script.as_bytes()))
+                .map_err(|err| format!("failed to write {}: {}", script_path.display(), err)));
+        }
+        Ok(())
+    }
+}
+
+fn check_player_id(player_id: PlayerId) -> Result<(), String> {
+    if *player_id as usize >= PLAYER_SLOTS {
+        return Err(format!("there's no player {}; scenarios have players 0 (gaia) to {}",
+                           *player_id,
+                           PLAYER_SLOTS - 1));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use editor::PlacementRules;
+    use identifier::{CivilizationId, ResearchId};
+    use std::env;
+    use std::fs;
+    use super::*;
+
+    /// Unit 109 is a two by two building that has to stand on terrain 0; everything else goes
+    /// anywhere
+    struct FakeData;
+
+    impl EditorData for FakeData {
+        fn units(&self, _civilization_id: CivilizationId) -> Vec<(UnitId, String)> {
+            Vec::new()
+        }
+
+        fn has_unit(&self, _civilization_id: CivilizationId, _unit_id: UnitId) -> bool {
+            true
+        }
+
+        fn is_gated(&self, _civilization_id: CivilizationId, _unit_id: UnitId) -> bool {
+            false
+        }
+
+        fn research(&self) -> Vec<(ResearchId, String)> {
+            Vec::new()
+        }
+
+        fn passable(&self, _civilization_id: CivilizationId, unit_id: UnitId, terrain_id: TerrainId) -> bool {
+            *unit_id != 109 || *terrain_id == 0
+        }
+
+        fn placement_rules(&self,
+                           _civilization_id: CivilizationId,
+                           unit_id: UnitId)
+                           -> Option<PlacementRules> {
+            if *unit_id != 109 {
+                return None;
+            }
+            Some(PlacementRules {
+                half_size: (1.0, 1.0),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn editor(width: u32, height: u32) -> ScenarioEditor {
+        let path = env::temp_dir().join("chariot_scenario_editor_unsaved.scn");
+        ScenarioEditor::new(scn::Scenario::new(width, height), path).unwrap()
+    }
+
+    #[test]
+    fn test_paint_terrain_and_elevation() {
+        let mut editor = editor(4, 3);
+        editor.set_tool(EditorTool::Terrain(2.into())).unwrap();
+        editor.set_brush(Brush {
+            radius: 1,
+            shape: BrushShape::Square,
+        });
+        assert_eq!(EditorChange::Map, editor.apply_tool(0.5, 0.5, &FakeData));
+        assert_eq!(EditorChange::Nothing, editor.apply_tool(0.2, 0.7, &FakeData));
+        let terrain_ids: Vec<u8> = editor.scenario().map.tiles.iter().map(|tile| *tile.terrain_id).collect();
+        assert_eq!(vec![2, 2, 0, 0, 2, 2, 0, 0, 0, 0, 0, 0], terrain_ids);
+
+        editor.set_tool(EditorTool::Elevation(10)).unwrap();
+        assert_eq!(EditorTool::Elevation(MAX_ELEVATION), editor.tool());
+        editor.set_brush(Brush {
+            radius: 0,
+            shape: BrushShape::Round,
+        });
+        editor.apply_tool(3.5, 2.5, &FakeData);
+        assert_eq!(MAX_ELEVATION, editor.scenario().map.tiles[11].elevation);
+        assert_eq!(MAX_ELEVATION - 1, editor.scenario().map.tiles[10].elevation);
+        assert_eq!(EditorChange::Nothing, editor.apply_tool(-5.0, 10.0, &FakeData));
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut editor = editor(3, 3);
+        editor.begin_stroke();
+        editor.set_tool(EditorTool::Elevation(1)).unwrap();
+        editor.apply_tool(0.5, 0.5, &FakeData);
+        editor.apply_tool(1.5, 0.5, &FakeData);
+        editor.end_stroke();
+        editor.place_unit(1.into(), 83.into(), 2.5, 2.5);
+        editor.add_trigger(EditorTrigger::parse("when count owner 1 == 0 then message Defeat").unwrap());
+
+        assert_eq!(Some(EditorChange::Settings), editor.undo());
+        assert!(editor.triggers().is_empty());
+        assert_eq!(Some(EditorChange::Units), editor.undo());
+        assert!(editor.scenario().player_units(1.into()).is_empty());
+        // The whole stroke comes undone at once
+        assert_eq!(Some(EditorChange::Map), editor.undo());
+        assert!(editor.scenario().map.tiles.iter().all(|tile| tile.elevation == 0));
+        assert_eq!(None, editor.undo());
+
+        assert_eq!(Some(EditorChange::Map), editor.redo());
+        assert_eq!(1, editor.scenario().map.tiles[1].elevation);
+        assert_eq!(Some(EditorChange::Units), editor.redo());
+        assert_eq!(1, editor.scenario().player_units(1.into()).len());
+    }
+
+    #[test]
+    fn test_copy_and_paste() {
+        let mut editor = editor(6, 6);
+        assert!(editor.set_tool(EditorTool::Paste).is_err());
+        editor.set_tool(EditorTool::Fill(4.into())).unwrap();
+        editor.apply_tool(0.5, 0.5, &FakeData);
+        editor.place_unit(1.into(), 83.into(), 0.5, 0.5);
+        assert!(editor.scenario().map.tiles.iter().all(|tile| tile.terrain_id == 4.into()));
+
+        editor.set_tool(EditorTool::Terrain(1.into())).unwrap();
+        editor.apply_tool(1.5, 1.5, &FakeData);
+        assert_eq!(Ok((2, 2)), editor.copy_region(0, 0, 1, 1));
+        editor.set_tool(EditorTool::Paste).unwrap();
+        assert_eq!(EditorChange::Units, editor.apply_tool(4.5, 4.5, &FakeData));
+        assert_eq!(1.into(), editor.scenario().map.tiles[5 * 6 + 5].terrain_id);
+        let units = editor.scenario().player_units(1.into());
+        assert_eq!(2, units.len());
+        assert_eq!(4.5, units[1].position_x);
+        assert!(units[0].spawn_id != units[1].spawn_id);
+
+        // Pasting is undone in one step
+        editor.undo();
+        assert_eq!(1, editor.scenario().player_units(1.into()).len());
+        assert_eq!(4.into(), editor.scenario().map.tiles[5 * 6 + 5].terrain_id);
+    }
+
+    #[test]
+    fn test_place_rotate_and_delete_units() {
+        let mut editor = editor(8, 8);
+        editor.set_player_id(2.into()).unwrap();
+        assert!(editor.set_player_id(9.into()).is_err());
+        editor.set_tool(EditorTool::Unit(83.into())).unwrap();
+        editor.apply_tool(2.5, 3.5, &FakeData);
+        editor.place_unit(0.into(), 59.into(), 6.0, 6.0);
+        assert_eq!(83.into(), editor.scenario().player_units(2.into())[0].unit_id);
+        assert_eq!(59.into(), editor.scenario().player_units(0.into())[0].unit_id);
+
+        assert_eq!(EditorChange::Units, editor.rotate_unit_near(2.2, 3.7));
+        assert!(editor.scenario().player_units(2.into())[0].rotation > 0.0);
+        assert_eq!(EditorChange::Nothing, editor.rotate_unit_near(4.0, 4.0));
+
+        editor.set_tool(EditorTool::Erase).unwrap();
+        assert_eq!(EditorChange::Units, editor.apply_tool(6.3, 5.8, &FakeData));
+        assert!(editor.scenario().player_units(0.into()).is_empty());
+        assert_eq!(1, editor.scenario().player_units(2.into()).len());
+    }
+
+    #[test]
+    fn test_building_placement() {
+        let mut editor = editor(6, 6);
+        editor.set_tool(EditorTool::Fill(1.into())).unwrap();
+        editor.apply_tool(0.5, 0.5, &FakeData);
+        editor.set_tool(EditorTool::Terrain(0.into())).unwrap();
+        editor.set_brush(Brush {
+            radius: 1,
+            shape: BrushShape::Square,
+        });
+        editor.apply_tool(3.5, 3.5, &FakeData);
+
+        // Buildings only go where the ground under all of them allows it, lined up with the tiles
+        editor.set_tool(EditorTool::Unit(109.into())).unwrap();
+        assert_eq!(EditorChange::Nothing, editor.apply_tool(2.2, 2.2, &FakeData));
+        let placement = editor.check_placement(&FakeData, 109.into(), 3.8, 3.6).unwrap();
+        assert!(placement.is_allowed());
+        assert_eq!(4, placement.tiles.len());
+        assert_eq!(EditorChange::Units, editor.apply_tool(3.8, 3.6, &FakeData));
+        let units = editor.scenario().player_units(1.into());
+        assert_eq!((4.0, 4.0), (units[0].position_x, units[0].position_y));
+
+        // Other units are placed wherever they're put
+        assert!(editor.check_placement(&FakeData, 83.into(), 0.5, 0.5).is_none());
+        editor.set_tool(EditorTool::Unit(83.into())).unwrap();
+        assert_eq!(EditorChange::Units, editor.apply_tool(0.5, 0.5, &FakeData));
+    }
+
+    #[test]
+    fn test_player_settings() {
+        let mut editor = editor(2, 2);
+        editor.set_starting_resource(3.into(), ResourceType::Gold, 250.0).unwrap();
+        assert_eq!(250.0, editor.scenario().player_resources(3.into()).unwrap().gold);
+        assert!(editor.set_starting_resource(12.into(), ResourceType::Gold, 1.0).is_err());
+
+        editor.set_stance(1.into(), 2.into(), DiplomaticStance::Neutral).unwrap();
+        assert_eq!(1, editor.scenario().player_data.diplomacy.stance(1, 2));
+        assert_eq!(0, editor.scenario().player_data.diplomacy.stance(2, 1));
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let dir = env::temp_dir().join("chariot_scenario_editor_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let scenario_path = dir.join("test.scn");
+
+        let mut editor = ScenarioEditor::new(scn::Scenario::new(4, 4), &scenario_path).unwrap();
+        editor.place_unit(1.into(), 83.into(), 1.5, 1.5);
+        editor.add_trigger(EditorTrigger::parse("when count owner 1 == 0 then message Defeat").unwrap());
+        editor.save().unwrap();
+
+        let scenario = scn::Scenario::read_from_file(&scenario_path).unwrap();
+        assert_eq!(1, scenario.player_units(1.into()).len());
+        let editor = ScenarioEditor::new(scenario, &scenario_path).unwrap();
+        assert_eq!(1, editor.triggers().len());
+
+        // Scripts the editor didn't write are left alone
+        let script_path = dir.join("test.rhai");
+        File::create(&script_path).unwrap().write_all(b"fn on_start() {}").unwrap();
+        let mut editor = ScenarioEditor::new(scn::Scenario::new(4, 4), &scenario_path).unwrap();
+        assert!(editor.triggers().is_empty());
+        editor.save().unwrap();
+        editor.add_trigger(EditorTrigger::parse("when count owner 1 == 0 then message Defeat").unwrap());
+        assert!(editor.save().is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,301 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::{Comparison, WorldQuery};
+use std::fmt::Write;
+
+/// First line of the scripts the editor writes, so that it can tell them apart from scripts
+/// written by hand, which it won't overwrite
+pub const GENERATED_SCRIPT_HEADER: &'static str = "// Triggers written by the scenario editor; changes to \
+                                                   this file are lost when the scenario is saved";
+
+/// Comment prefix that each trigger is kept under in the generated script, so it can be read back
+const TRIGGER_COMMENT: &'static str = "// trigger: ";
+
+/// How often, in ticks, the generated script checks the trigger conditions
+const TRIGGER_CHECK_INTERVAL: usize = 30;
+
+/// What happens when a trigger fires
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TriggerEffect {
+    Message(String),
+    Spawn {
+        player_id: usize,
+        unit_id: usize,
+        x: i32,
+        y: i32,
+    },
+    Give {
+        player_id: usize,
+        resource: String,
+        amount: i32,
+    },
+    /// Finishes research for the player
+    Research {
+        player_id: usize,
+        research_id: usize,
+    },
+}
+
+/// A classic scenario trigger: once a world query compares true against a value, the effect
+/// happens, once. The editor saves triggers as the scenario's script. Triggers are written as
+/// `when <query> <comparison> <value> then <effect>`, where the query is one the debug console
+/// takes and the effect is `message <text>`, `spawn <player> <unit id> <x> <y>`,
+/// `give <player> <food|wood|stone|gold> <amount>`, or `research <player> <research id>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EditorTrigger {
+    query: String,
+    comparison: Comparison,
+    value: i64,
+    effect: TriggerEffect,
+}
+
+impl EditorTrigger {
+    pub fn parse(text: &str) -> Result<EditorTrigger, String> {
+        const USAGE: &'static str = "usage: when <query> <comparison> <value> then <effect>";
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.first().map(|word| word.to_lowercase()) != Some("when".into()) {
+            return Err(USAGE.into());
+        }
+        let then_index = match words.iter().position(|word| word.to_lowercase() == "then") {
+            Some(index) if index >= 4 => index,
+            _ => return Err(USAGE.into()),
+        };
+        let comparison = try!(Comparison::parse(words[then_index - 2])
+            .ok_or(format!("unknown comparison \"{}\"; use <, <=, ==, >=, or >", words[then_index - 2])));
+        let value = try!(words[then_index - 1]
+            .parse()
+            .map_err(|_| format!("invalid value \"{}\"", words[then_index - 1])));
+        let query = words[1..then_index - 2].join(" ");
+        try!(WorldQuery::parse(&query));
+
+        Ok(EditorTrigger {
+            query: query,
+            comparison: comparison,
+            value: value,
+            effect: try!(parse_effect(&words[then_index + 1..])),
+        })
+    }
+
+    /// The world query the condition asks
+    pub fn query(&self) -> WorldQuery {
+        // The text was checked when the trigger was parsed
+        WorldQuery::parse(&self.query).unwrap()
+    }
+
+    pub fn comparison(&self) -> Comparison {
+        self.comparison
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub fn effect<'a>(&'a self) -> &'a TriggerEffect {
+        &self.effect
+    }
+
+    /// The effect written out the way `parse` reads it
+    pub fn effect_text(&self) -> String {
+        match self.effect {
+            TriggerEffect::Message(ref text) => format!("message {}", text),
+            TriggerEffect::Spawn { player_id, unit_id, x, y } => {
+                format!("spawn {} {} {} {}", player_id, unit_id, x, y)
+            }
+            TriggerEffect::Give { player_id, ref resource, amount } => {
+                format!("give {} {} {}", player_id, resource, amount)
+            }
+            TriggerEffect::Research { player_id, research_id } => {
+                format!("research {} {}", player_id, research_id)
+            }
+        }
+    }
+
+    /// The trigger written out the way `parse` reads it
+    pub fn to_text(&self) -> String {
+        format!("when {} {} {} then {}",
+                self.query,
+                self.comparison.operator(),
+                self.value,
+                self.effect_text())
+    }
+}
+
+fn parse_effect(words: &[&str]) -> Result<TriggerEffect, String> {
+    const USAGE: &'static str = "the effect must be message <text>, spawn <player> <unit id> <x> <y>, \
+                                 give <player> <resource> <amount>, or research <player> <research id>";
+    let number = |index: usize, what: &str, min: i64, max: i64| -> Result<i64, String> {
+        let word = try!(words.get(index).ok_or(USAGE.to_string()));
+        match word.parse() {
+            Ok(number) if number >= min && number <= max => Ok(number),
+            Ok(_) => Err(format!("{} {} is out of range; it must be from {} to {}", what, word, min, max)),
+            Err(_) => Err(format!("invalid {} \"{}\"", what, word)),
+        }
+    };
+    let player_id = || number(1, "player id", 0, u8::MAX as i64);
+    match words.first().map(|word| word.to_lowercase()) {
+        Some(ref word) if word == "message" && words.len() > 1 => {
+            Ok(TriggerEffect::Message(words[1..].join(" ")))
+        }
+        Some(ref word) if word == "spawn" && words.len() == 5 => {
+            Ok(TriggerEffect::Spawn {
+                player_id: try!(player_id()) as usize,
+                unit_id: try!(number(2, "unit id", 0, u32::MAX as i64)) as usize,
+                x: try!(number(3, "x", 0, i32::MAX as i64)) as i32,
+                y: try!(number(4, "y", 0, i32::MAX as i64)) as i32,
+            })
+        }
+        Some(ref word) if word == "give" && words.len() == 4 => {
+            let resource = words[2].to_lowercase();
+            if !["food", "wood", "stone", "gold"].contains(&&resource[..]) {
+                return Err(format!("unknown resource \"{}\"", words[2]));
+            }
+            Ok(TriggerEffect::Give {
+                player_id: try!(player_id()) as usize,
+                resource: resource,
+                amount: try!(number(3, "amount", i32::MIN as i64, i32::MAX as i64)) as i32,
+            })
+        }
+        Some(ref word) if word == "research" && words.len() == 3 => {
+            Ok(TriggerEffect::Research {
+                player_id: try!(player_id()) as usize,
+                research_id: try!(number(2, "research id", 0, u32::MAX as i64)) as usize,
+            })
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Writes the triggers out as a scenario script
+pub fn triggers_to_script(triggers: &[EditorTrigger]) -> String {
+    let mut script = String::new();
+    writeln!(script, "{}", GENERATED_SCRIPT_HEADER).unwrap();
+    for trigger in triggers {
+        writeln!(script, "{}{}", TRIGGER_COMMENT, trigger.to_text()).unwrap();
+    }
+    writeln!(script, "\nevery({}, \"check_triggers\");\n", TRIGGER_CHECK_INTERVAL).unwrap();
+    writeln!(script, "fn check_triggers() {{").unwrap();
+    for (index, trigger) in triggers.iter().enumerate() {
+        writeln!(script,
+                 "    if query(\"{}\") {} {} && fire_once(\"trigger {}\") {{",
+                 escape(&trigger.query),
+                 trigger.comparison.operator(),
+                 trigger.value,
+                 index)
+            .unwrap();
+        let effect = match trigger.effect {
+            TriggerEffect::Message(ref text) => format!("show_message(\"{}\");", escape(text)),
+            TriggerEffect::Spawn { player_id, unit_id, x, y } => {
+                format!("spawn_unit({}, {}, {}, {});", player_id, unit_id, x, y)
+            }
+            TriggerEffect::Give { player_id, ref resource, amount } => {
+                format!("add_stockpile({}, \"{}\", {});", player_id, resource, amount)
+            }
+            TriggerEffect::Research { player_id, research_id } => {
+                format!("complete_research({}, {});", player_id, research_id)
+            }
+        };
+        writeln!(script, "        {}\n    }}", effect).unwrap();
+    }
+    writeln!(script, "}}").unwrap();
+    script
+}
+
+/// Reads the triggers back out of a script the editor wrote. Returns None for scripts that
+/// weren't written by the editor.
+pub fn triggers_from_script(script: &str) -> Option<Result<Vec<EditorTrigger>, String>> {
+    if !script.starts_with(GENERATED_SCRIPT_HEADER) {
+        return None;
+    }
+    Some(script.lines()
+        .filter(|line| line.starts_with(TRIGGER_COMMENT))
+        .map(|line| EditorTrigger::parse(&line[TRIGGER_COMMENT.len()..]))
+        .collect())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::resource::{Player, Players};
+    use script::{ScenarioScript, ScriptEffect, ScriptView};
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let trigger = EditorTrigger::parse("when stockpile 1 gold >= 500 then message A \"golden\" age")
+            .unwrap();
+        assert_eq!(Comparison::AtLeast, trigger.comparison);
+        assert_eq!(500, trigger.value);
+        assert_eq!(TriggerEffect::Message("A \"golden\" age".into()), trigger.effect);
+        assert_eq!(Ok(trigger.clone()), EditorTrigger::parse(&trigger.to_text()));
+
+        assert_eq!(Ok(TriggerEffect::Spawn {
+                       player_id: 2,
+                       unit_id: 83,
+                       x: 10,
+                       y: 12,
+                   }),
+                   EditorTrigger::parse("when count owner 1 == 0 then spawn 2 83 10 12").map(|t| t.effect));
+        assert!(EditorTrigger::parse("when count owner 1 ~ 0 then message hi").is_err());
+        assert_eq!(Ok(TriggerEffect::Research {
+                       player_id: 1,
+                       research_id: 101,
+                   }),
+                   EditorTrigger::parse("when researched 2 101 == 1 then research 1 101").map(|t| t.effect));
+        assert!(EditorTrigger::parse("when count owner 1 >= 0 then give 1 silver 5").is_err());
+        assert!(EditorTrigger::parse("when count owner 1 >= 0 then give 256 food 5").is_err());
+        assert!(EditorTrigger::parse("when nonsense >= 0 then message hi").is_err());
+        assert!(EditorTrigger::parse("count owner 1 >= 0 then message hi").is_err());
+        assert!(EditorTrigger::parse("when >= 0 then message hi").is_err());
+    }
+
+    #[test]
+    fn test_script_round_trip() {
+        let triggers = vec![EditorTrigger::parse("when stockpile 1 gold >= 500 then give 1 food 100"),
+                            EditorTrigger::parse("when stockpile 1 gold > 100 then message \"Rich\"")]
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let script_text = triggers_to_script(&triggers);
+        assert_eq!(Some(Ok(triggers)), triggers_from_script(&script_text));
+        assert_eq!(None, triggers_from_script("fn on_start() {}"));
+
+        let view = || {
+            let mut players = Players::new();
+            let mut player = Player::new("Player 1".into(), 1.into(), 1.into(), 1.into());
+            player.stockpile.set(ResourceType::Gold, 600.into());
+            players.add_player(player, true);
+            ScriptView::with_players(players)
+        };
+        let mut script = ScenarioScript::compile(&script_text).unwrap();
+        assert!(script.start(view()).unwrap().is_empty());
+        let effects = script.tick(30, view()).unwrap();
+        assert_eq!(vec![ScriptEffect::AddToStockpile(1.into(), ResourceType::Food, 100.into()),
+                        ScriptEffect::Message("\"Rich\"".into())],
+                   effects);
+        // Triggers only fire once
+        assert!(script.tick(60, view()).unwrap().is_empty());
+    }
+}
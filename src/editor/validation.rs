@@ -0,0 +1,421 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::WorldQuery;
+use ecs::resource::{Area, PathFinder};
+use game::ContentGate;
+use identifier::{CivilizationId, PlayerId, ResearchId, TerrainId, UnitId};
+use scn;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use super::trigger::{EditorTrigger, TriggerEffect};
+use util::placement::PlacementRules;
+
+/// The game data the editor looks units and research up in, for listing them and checking
+/// that triggers refer to things that exist
+pub trait EditorData {
+    /// The units the civilization has, by ID and name, ordered by ID
+    fn units(&self, civilization_id: CivilizationId) -> Vec<(UnitId, String)>;
+
+    fn has_unit(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool;
+
+    /// Whether the game data has the unit but the data set, like the demo's, can't draw it
+    fn is_gated(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool;
+
+    /// All research, by ID and name, ordered by ID
+    fn research(&self) -> Vec<(ResearchId, String)>;
+
+    /// Whether the unit can move over the terrain; for buildings, whether they can stand on it
+    fn passable(&self, civilization_id: CivilizationId, unit_id: UnitId, terrain_id: TerrainId) -> bool;
+
+    /// What the ground has to be for the unit to be placed; None for units that aren't buildings
+    fn placement_rules(&self, civilization_id: CivilizationId, unit_id: UnitId) -> Option<PlacementRules>;
+}
+
+pub struct EmpiresEditorData {
+    empires: dat::EmpiresDbRef,
+    content_gate: ContentGate,
+    path_finder: PathFinder,
+}
+
+impl EmpiresEditorData {
+    pub fn new(empires: dat::EmpiresDbRef, content_gate: ContentGate) -> EmpiresEditorData {
+        EmpiresEditorData {
+            empires: empires.clone(),
+            content_gate: content_gate,
+            path_finder: PathFinder::new(empires),
+        }
+    }
+}
+
+impl EditorData for EmpiresEditorData {
+    fn units(&self, civilization_id: CivilizationId) -> Vec<(UnitId, String)> {
+        self.empires
+            .civilization(civilization_id)
+            .units()
+            .iter()
+            .map(|unit| (unit.id, unit.name().to_string()))
+            .collect()
+    }
+
+    fn has_unit(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+        self.empires.civilization(civilization_id).has_unit(unit_id)
+    }
+
+    fn is_gated(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+        self.content_gate.is_unit_gated(civilization_id, unit_id)
+    }
+
+    fn research(&self) -> Vec<(ResearchId, String)> {
+        self.empires
+            .all_research()
+            .iter()
+            .enumerate()
+            .map(|(index, research)| (index.into(), research.name.clone()))
+            .collect()
+    }
+
+    fn passable(&self, civilization_id: CivilizationId, unit_id: UnitId, terrain_id: TerrainId) -> bool {
+        let unit = self.empires.unit(civilization_id, unit_id);
+        self.path_finder.passable(unit.terrain_restriction, terrain_id)
+    }
+
+    fn placement_rules(&self, civilization_id: CivilizationId, unit_id: UnitId) -> Option<PlacementRules> {
+        PlacementRules::of(self.empires.unit(civilization_id, unit_id))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// The trigger works, but probably not the way it was meant to
+    Warning,
+    /// The trigger can't work; the scenario isn't saved while there are any of these
+    Error,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TriggerProblem {
+    pub trigger_index: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl TriggerProblem {
+    fn new(trigger_index: usize, severity: Severity, message: String) -> TriggerProblem {
+        TriggerProblem {
+            trigger_index: trigger_index,
+            severity: severity,
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for TriggerProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "trigger {}: {}: {}", self.trigger_index, severity, self.message)
+    }
+}
+
+/// Looks for triggers that refer to players, units, research, or tiles the scenario or game data
+/// doesn't have, and for units spawned where they can't move to the rest of their player's units
+pub fn validate_triggers(scenario: &scn::Scenario,
+                         triggers: &[EditorTrigger],
+                         data: &EditorData)
+                         -> Vec<TriggerProblem> {
+    let mut problems = Vec::new();
+    for (index, trigger) in triggers.iter().enumerate() {
+        let mut checker = TriggerChecker {
+            scenario: scenario,
+            data: data,
+            index: index,
+            problems: &mut problems,
+        };
+        checker.check_query(&trigger.query());
+        checker.check_effect(trigger.effect());
+    }
+    problems
+}
+
+pub fn has_errors(problems: &[TriggerProblem]) -> bool {
+    problems.iter().any(|problem| problem.severity == Severity::Error)
+}
+
+struct TriggerChecker<'a> {
+    scenario: &'a scn::Scenario,
+    data: &'a EditorData,
+    index: usize,
+    problems: &'a mut Vec<TriggerProblem>,
+}
+
+impl<'a> TriggerChecker<'a> {
+    fn report(&mut self, severity: Severity, message: String) {
+        self.problems.push(TriggerProblem::new(self.index, severity, message));
+    }
+
+    fn check_query(&mut self, query: &WorldQuery) {
+        match *query {
+            WorldQuery::UnitCount { unit_id, player_id, area } => {
+                let player_exists = player_id.map(|player_id| self.check_player(player_id)).unwrap_or(true);
+                if let (Some(unit_id), true) = (unit_id, player_exists) {
+                    let civilization_ids: Vec<CivilizationId> = match player_id {
+                        Some(player_id) => vec![self.scenario.player_civilization_id(player_id)],
+                        None => {
+                            self.scenario
+                                .player_ids()
+                                .into_iter()
+                                .map(|player_id| self.scenario.player_civilization_id(player_id))
+                                .collect()
+                        }
+                    };
+                    let data = self.data;
+                    if !civilization_ids.iter().any(|&civ_id| data.has_unit(civ_id, unit_id)) {
+                        self.report(Severity::Error,
+                                    format!("counts unit {}, which isn't in the game data", *unit_id));
+                    }
+                }
+                if let Some(area) = area {
+                    self.check_area(area);
+                }
+            }
+            WorldQuery::Stockpile(player_id, _) => {
+                self.check_player(player_id);
+            }
+            WorldQuery::Researched(player_id, research_id) => {
+                self.check_player(player_id);
+                self.check_research(research_id, "asks about");
+            }
+        }
+    }
+
+    fn check_effect(&mut self, effect: &TriggerEffect) {
+        match *effect {
+            TriggerEffect::Message(_) => {}
+            TriggerEffect::Spawn { player_id, unit_id, x, y } => {
+                let player_id: PlayerId = player_id.into();
+                if !self.check_player(player_id) {
+                    return;
+                }
+                let unit_id: UnitId = unit_id.into();
+                let civilization_id = self.scenario.player_civilization_id(player_id);
+                if !self.data.has_unit(civilization_id, unit_id) {
+                    self.report(Severity::Error,
+                                format!("spawns unit {}, which player {}'s civilization doesn't have",
+                                        *unit_id,
+                                        *player_id));
+                    return;
+                }
+                if self.data.is_gated(civilization_id, unit_id) {
+                    self.report(Severity::Warning,
+                                format!("spawns unit {}, whose graphics aren't in this data set", *unit_id));
+                }
+                if !self.on_map(x, y) {
+                    self.report(Severity::Error, format!("spawns at ({}, {}), which is off the map", x, y));
+                    return;
+                }
+                self.check_reachable(player_id, unit_id, x, y);
+            }
+            TriggerEffect::Give { player_id, .. } => {
+                self.check_player(player_id.into());
+            }
+            TriggerEffect::Research { player_id, research_id } => {
+                self.check_player(player_id.into());
+                self.check_research(research_id.into(), "completes");
+            }
+        }
+    }
+
+    fn check_research(&mut self, research_id: ResearchId, verb: &str) {
+        if !self.data.research().iter().any(|&(id, _)| id == research_id) {
+            self.report(Severity::Error,
+                        format!("{} research {}, which isn't in the game data", verb, *research_id));
+        }
+    }
+
+    /// Reports the player if the scenario doesn't have them, returning whether it does
+    fn check_player(&mut self, player_id: PlayerId) -> bool {
+        let player_count = self.scenario.player_ids().len();
+        if *player_id as usize >= player_count {
+            self.report(Severity::Error,
+                        format!("refers to player {}; the scenario has players 0 to {}",
+                                *player_id,
+                                player_count - 1));
+            return false;
+        }
+        true
+    }
+
+    fn check_area(&mut self, area: Area) {
+        if !self.on_map(area.start.x, area.start.y) || !self.on_map(area.end.x, area.end.y) {
+            let map = &self.scenario.map;
+            let message = format!("looks at tiles ({}, {}) to ({}, {}), past the edge of the {}x{} map",
+                                  area.start.x,
+                                  area.start.y,
+                                  area.end.x,
+                                  area.end.y,
+                                  map.width,
+                                  map.height);
+            self.report(Severity::Error, message);
+        }
+    }
+
+    fn on_map(&self, x: i32, y: i32) -> bool {
+        let map = &self.scenario.map;
+        x >= 0 && y >= 0 && (x as u32) < map.width && (y as u32) < map.height
+    }
+
+    /// Warns when a spawned unit can't stand where it's spawned, or can't get from there to any
+    /// of the units its player starts with, such as when it's spawned on an island
+    fn check_reachable(&mut self, player_id: PlayerId, unit_id: UnitId, x: i32, y: i32) {
+        let (scenario, data) = (self.scenario, self.data);
+        let map = &scenario.map;
+        let civilization_id = scenario.player_civilization_id(player_id);
+        let passable = |x: i32, y: i32| {
+            let tile = &map.tiles[(y as u32 * map.width + x as u32) as usize];
+            data.passable(civilization_id, unit_id, tile.terrain_id)
+        };
+        if !passable(x, y) {
+            self.report(Severity::Warning,
+                        format!("spawns unit {} at ({}, {}), on terrain it can't move over", *unit_id, x, y));
+            return;
+        }
+
+        let targets: HashSet<(i32, i32)> = scenario.player_units(player_id)
+            .iter()
+            .map(|unit| (unit.position_x.floor() as i32, unit.position_y.floor() as i32))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert((x, y));
+        queue.push_back((x, y));
+        while let Some((tile_x, tile_y)) = queue.pop_front() {
+            if targets.contains(&(tile_x, tile_y)) {
+                return;
+            }
+            for dy in -1..2 {
+                for dx in -1..2 {
+                    let (next_x, next_y) = (tile_x + dx, tile_y + dy);
+                    if self.on_map(next_x, next_y) && !visited.contains(&(next_x, next_y)) &&
+                       passable(next_x, next_y) {
+                        visited.insert((next_x, next_y));
+                        queue.push_back((next_x, next_y));
+                    }
+                }
+            }
+        }
+        self.report(Severity::Warning,
+                    format!("spawns unit {} at ({}, {}), where it can't reach any of player {}'s units",
+                            *unit_id,
+                            x,
+                            y,
+                            *player_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use editor::EditorTrigger;
+    use identifier::{CivilizationId, ResearchId, TerrainId, UnitId};
+    use scn::{self, PlayerUnit};
+    use super::{EditorData, PlacementRules, Severity, validate_triggers};
+
+    /// Units 1 and 2 exist; 1 walks on terrain 0 and 2 walks on anything. Research 4 exists.
+    struct FakeData;
+
+    impl EditorData for FakeData {
+        fn units(&self, _civilization_id: CivilizationId) -> Vec<(UnitId, String)> {
+            vec![(1.into(), "Villager".into()), (2.into(), "Scout".into()), (3.into(), "Priest".into())]
+        }
+
+        fn has_unit(&self, _civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+            *unit_id >= 1 && *unit_id <= 3
+        }
+
+        fn is_gated(&self, _civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+            *unit_id == 3
+        }
+
+        fn research(&self) -> Vec<(ResearchId, String)> {
+            vec![(4.into(), "Wheel".into())]
+        }
+
+        fn passable(&self, _civilization_id: CivilizationId, unit_id: UnitId, terrain_id: TerrainId) -> bool {
+            *unit_id == 2 || *terrain_id == 0
+        }
+
+        fn placement_rules(&self,
+                           _civilization_id: CivilizationId,
+                           _unit_id: UnitId)
+                           -> Option<PlacementRules> {
+            None
+        }
+    }
+
+    fn validate(scenario: &scn::Scenario, trigger: &str) -> Vec<Severity> {
+        let triggers = vec![EditorTrigger::parse(trigger).unwrap()];
+        validate_triggers(scenario, &triggers, &FakeData).iter().map(|problem| problem.severity).collect()
+    }
+
+    #[test]
+    fn test_references() {
+        let scenario = scn::Scenario::new(4, 4);
+        assert!(validate(&scenario, "when count type 1 owner 1 == 0 then give 1 wood 100").is_empty());
+        assert_eq!(vec![Severity::Error], validate(&scenario, "when count type 5 == 0 then message Hi"));
+        assert_eq!(vec![Severity::Error], validate(&scenario, "when stockpile 12 gold > 5 then message Hi"));
+        assert_eq!(vec![Severity::Error],
+                   validate(&scenario, "when count in 0 0 4 2 == 0 then message Hi"));
+        assert_eq!(vec![Severity::Error], validate(&scenario, "when count == 0 then spawn 1 7 0 0"));
+        assert_eq!(vec![Severity::Error], validate(&scenario, "when count == 0 then spawn 1 1 0 9"));
+        assert_eq!(vec![Severity::Warning], validate(&scenario, "when count == 0 then spawn 1 3 0 0"));
+        assert!(validate(&scenario, "when researched 1 4 == 1 then research 1 4").is_empty());
+        assert_eq!(vec![Severity::Error, Severity::Error],
+                   validate(&scenario, "when researched 1 5 == 1 then research 1 6"));
+    }
+
+    #[test]
+    fn test_reachability() {
+        // A wall of terrain 1 down the middle column cuts the map in two
+        let mut scenario = scn::Scenario::new(3, 3);
+        for y in 0..3 {
+            scenario.map.tiles[y * 3 + 1].terrain_id = 1.into();
+        }
+        scenario.add_unit(1.into(),
+                          PlayerUnit {
+                              position_x: 0.5,
+                              position_y: 1.5,
+                              unit_id: 1.into(),
+                              ..Default::default()
+                          });
+
+        assert!(validate(&scenario, "when count == 0 then spawn 1 1 0 0").is_empty());
+        assert_eq!(vec![Severity::Warning], validate(&scenario, "when count == 0 then spawn 1 1 2 0"));
+        assert_eq!(vec![Severity::Warning], validate(&scenario, "when count == 0 then spawn 1 1 1 0"));
+        assert!(validate(&scenario, "when count == 0 then spawn 1 2 2 0").is_empty());
+    }
+}
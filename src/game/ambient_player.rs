@@ -0,0 +1,91 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::EmpiresDb;
+use ecs::resource::AmbientSounds;
+use media::{Audio, AudioChannel, Media, PlaybackId};
+use resource::SoundManager;
+use std::collections::HashMap;
+use types::Vector3;
+
+/// Keeps a loop going for each ambient emitter that's in earshot, following it in volume and pan
+/// as the view moves
+pub struct AmbientPlayer {
+    /// Keyed by the emitter's index
+    playing: HashMap<usize, PlaybackId>,
+}
+
+impl AmbientPlayer {
+    pub fn new() -> AmbientPlayer {
+        AmbientPlayer { playing: HashMap::new() }
+    }
+
+    /// Called once a frame with where the player is looking
+    pub fn update(&mut self,
+                  ambient_sounds: &AmbientSounds,
+                  listener: &Vector3,
+                  media: &mut Media,
+                  sound_manager: &mut SoundManager,
+                  empires: &EmpiresDb) {
+        let audio = media.audio();
+        let heard = ambient_sounds.mix(listener);
+        let out_of_earshot: Vec<usize> = self.playing
+            .keys()
+            .filter(|emitter| !heard.iter().any(|mix| mix.emitter == **emitter))
+            .cloned()
+            .collect();
+        for emitter in out_of_earshot {
+            if let Some(playback) = self.playing.remove(&emitter) {
+                audio.stop(playback);
+            }
+        }
+
+        for mix in &heard {
+            if let Some(&playback) = self.playing.get(&mix.emitter) {
+                audio.set_mix(playback, mix.volume, mix.pan);
+                continue;
+            }
+            let emitter = &ambient_sounds.emitters()[mix.emitter];
+            let lines: Vec<_> = match empires.sound_effect_groups().get(*emitter.voice as usize) {
+                Some(group) => group.sound_effects.iter().filter_map(|effect| effect.resource_id).collect(),
+                None => continue,
+            };
+            if lines.is_empty() {
+                continue;
+            }
+
+            // Neighboring emitters of the same sound pick different lines from it, so they
+            // don't sound like an echo
+            let wav_id = lines[mix.emitter % lines.len()];
+            if let Some(sound) = sound_manager.get(wav_id, audio) {
+                let playback = audio.play(&sound, AudioChannel::Ambient, mix.volume, mix.pan, true);
+                self.playing.insert(mix.emitter, playback);
+            }
+        }
+    }
+
+    /// Stops all of the loops, like when the game is left
+    pub fn stop(&mut self, audio: &mut Audio) {
+        for (_, playback) in self.playing.drain() {
+            audio.stop(playback);
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Auto-saves, and picking an unfinished game back up. While a game is being played, a session
+//! file in the save directory says which scenario it is and how it was set up, and the game is
+//! saved next to it every so often, and on a crash. The session file is only taken away once the
+//! game is over, so finding it on launch means the last game wasn't finished.
+
+use config::AutosaveConfig;
+use ecs::SaveState;
+use ecs::resource::GameSettings;
+use media;
+use profile;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml::{self, Table, Value};
+
+const SAVE_DIR_NAME: &'static str = "saves";
+const SESSION_FILE_NAME: &'static str = "session.toml";
+const AUTOSAVE_PREFIX: &'static str = "autosave_";
+const SAVE_EXTENSION: &'static str = "scn";
+const EMERGENCY_SAVE_NAME: &'static str = "emergency.scn";
+const STATE_EXTENSION: &'static str = "state";
+
+/// Ticks of game time in a minute; the game always steps at 60 ticks a second, however fast
+/// it's running
+const TICKS_PER_MINUTE: usize = 60 * 60;
+
+/// How often the save kept for crashes is brought up to date between auto-saves. Encoding a save
+/// takes a while on a big map, so it's done once a minute of game time, which is as much as a
+/// crash can lose.
+const EMERGENCY_SAVE_INTERVAL_TICKS: usize = TICKS_PER_MINUTE;
+
+/// Where the saves go, in the user's config directory
+pub fn save_dir() -> PathBuf {
+    profile::user_config_dir().join(SAVE_DIR_NAME)
+}
+
+/// Saves are scenarios with the units where they were when the game was saved, written with
+/// `ecs::save_to_scenario`, each with its `ecs::SaveState` in a file next to it
+fn is_save(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    file_name == EMERGENCY_SAVE_NAME ||
+    (file_name.starts_with(AUTOSAVE_PREFIX) && file_name.ends_with(&format!(".{}", SAVE_EXTENSION)))
+}
+
+fn saves_in(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => {
+            entries.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| is_save(path))
+                .collect()
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The game being played: the scenario it was started from, whose script and name carry on
+/// into a restored game, and its lobby settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub scenario_path: PathBuf,
+    pub allow_cheats: bool,
+    pub allied_vision: bool,
+    pub game_speed: f64,
+}
+
+impl Session {
+    pub fn new<P: AsRef<Path>>(scenario_path: P, settings: &GameSettings) -> Session {
+        Session {
+            scenario_path: scenario_path.as_ref().to_path_buf(),
+            allow_cheats: settings.allow_cheats,
+            allied_vision: settings.allied_vision,
+            game_speed: settings.game_speed.into(),
+        }
+    }
+
+    /// Reads the unfinished session in the given directory, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Session>, String> {
+        let path = dir.join(SESSION_FILE_NAME);
+        let mut text = String::new();
+        match File::open(&path) {
+            Ok(mut file) => {
+                try!(file.read_to_string(&mut text)
+                    .map_err(|err| format!("Failed to read {}: {}", path.display(), err)));
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("Failed to open {}: {}", path.display(), err)),
+        }
+        let table = try!(toml::Parser::new(&text)
+            .parse()
+            .ok_or_else(|| format!("Failed to parse {}", path.display())));
+        Session::from_table(&table)
+            .map(Some)
+            .map_err(|err| format!("Invalid session {}: {}", path.display(), err))
+    }
+
+    /// The lobby settings to play the session with again. The seed is picked anew, though a save
+    /// with its state puts the random streams back where they were.
+    pub fn settings(&self, random_seed: u64) -> GameSettings {
+        let mut settings = GameSettings::new();
+        settings.allow_cheats = self.allow_cheats;
+        settings.allied_vision = self.allied_vision;
+        settings.game_speed = self.game_speed.into();
+        settings.random_seed = random_seed;
+        settings
+    }
+
+    /// Marks the start of a game, clearing away the saves of the game before it
+    pub fn start(&self, dir: &Path) -> Result<(), String> {
+        try!(remove_saves(dir));
+        try!(fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err)));
+        let path = dir.join(SESSION_FILE_NAME);
+        File::create(&path)
+            .and_then(|mut file| file.write_all(Value::Table(self.to_table()).to_string().as_bytes()))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    }
+
+    /// Marks the game as over, so there's nothing to restore on the next launch
+    pub fn end(dir: &Path) -> Result<(), String> {
+        let path = dir.join(SESSION_FILE_NAME);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("Failed to remove {}: {}", path.display(), err)),
+        }
+        remove_saves(dir)
+    }
+
+    /// The newest save in the directory, from the auto-saves and the emergency save
+    pub fn latest_save(dir: &Path) -> Option<PathBuf> {
+        let modified = |path: &PathBuf| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        saves_in(dir).into_iter().filter(|path| modified(path).is_some()).max_by_key(|path| modified(path))
+    }
+
+    fn from_table(table: &Table) -> Result<Session, String> {
+        let mut session = Session::new("", &GameSettings::new());
+        let mut has_scenario = false;
+        for (key, value) in table {
+            match (&key[..], value) {
+                ("scenario", &Value::String(ref path)) => {
+                    session.scenario_path = PathBuf::from(path);
+                    has_scenario = true;
+                }
+                ("allow_cheats", &Value::Boolean(allow_cheats)) => session.allow_cheats = allow_cheats,
+                ("allied_vision", &Value::Boolean(allied_vision)) => session.allied_vision = allied_vision,
+                ("game_speed", &Value::Float(game_speed)) if game_speed > 0.0 => {
+                    session.game_speed = game_speed
+                }
+                ("game_speed", &Value::Integer(game_speed)) if game_speed > 0 => {
+                    session.game_speed = game_speed as f64
+                }
+                ("scenario", _) | ("allow_cheats", _) | ("allied_vision", _) | ("game_speed", _) => {
+                    return Err(format!("{} has an invalid value", key));
+                }
+                _ => warn!("Unknown session setting \"{}\"", key),
+            }
+        }
+        if !has_scenario {
+            return Err("no scenario".into());
+        }
+        Ok(session)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("scenario".into(), Value::String(self.scenario_path.to_string_lossy().into_owned()));
+        table.insert("allow_cheats".into(), Value::Boolean(self.allow_cheats));
+        table.insert("allied_vision".into(), Value::Boolean(self.allied_vision));
+        table.insert("game_speed".into(), Value::Float(self.game_speed));
+        table
+    }
+}
+
+fn remove_saves(dir: &Path) -> Result<(), String> {
+    for path in saves_in(dir) {
+        try!(fs::remove_file(&path).map_err(|err| format!("Failed to remove {}: {}", path.display(), err)));
+        let state_path = state_path(&path);
+        match fs::remove_file(&state_path) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("Failed to remove {}: {}", state_path.display(), err)),
+        }
+    }
+    Ok(())
+}
+
+/// Where the state that goes with a save is kept
+fn state_path(save_path: &Path) -> PathBuf {
+    save_path.with_extension(STATE_EXTENSION)
+}
+
+/// A save of the game as it's written out: the scenario, and the state that goes with it
+#[derive(Clone, Debug)]
+pub struct Save {
+    pub scenario: Vec<u8>,
+    pub state: String,
+}
+
+impl Save {
+    /// The files the save is written as when it goes to the given path, for writing out with no
+    /// time to spare, as on a crash
+    pub fn files(&self, path: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        vec![(state_path(path), self.state.as_bytes().to_vec()), (path.to_path_buf(), self.scenario.clone())]
+    }
+}
+
+/// Reads the state that goes with a save. Saves written before the state was kept don't have it,
+/// and are restored with only what's in their scenario.
+pub fn read_save_state(save_path: &Path) -> Result<Option<SaveState>, String> {
+    let path = state_path(save_path);
+    let mut text = String::new();
+    match File::open(&path) {
+        Ok(mut file) => {
+            try!(file.read_to_string(&mut text)
+                .map_err(|err| format!("Failed to read {}: {}", path.display(), err)));
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("Failed to open {}: {}", path.display(), err)),
+    }
+    SaveState::from_toml(&text)
+        .map(Some)
+        .map_err(|err| format!("Invalid save state {}: {}", path.display(), err))
+}
+
+/// Decides when the game is saved, counting in game time so that pausing or slowing the game
+/// down doesn't bring the next save any sooner. Saves take turns between a fixed number of
+/// files, the oldest being written over each time.
+pub struct AutoSaver {
+    dir: PathBuf,
+    interval_ticks: usize,
+    slots: usize,
+    next_slot: usize,
+    ticks_since_save: usize,
+    ticks_since_emergency_save: usize,
+}
+
+impl AutoSaver {
+    pub fn new(dir: PathBuf) -> AutoSaver {
+        AutoSaver {
+            dir: dir,
+            interval_ticks: 0,
+            slots: 1,
+            next_slot: 0,
+            ticks_since_save: 0,
+            ticks_since_emergency_save: 0,
+        }
+    }
+
+    pub fn configure(&mut self, config: &AutosaveConfig) {
+        self.interval_ticks = config.interval_minutes as usize * TICKS_PER_MINUTE;
+        self.slots = config.slots.max(1) as usize;
+        self.next_slot %= self.slots;
+    }
+
+    /// Counts off a tick, returning the file to save to when a save is due. An auto-save also
+    /// serves as the emergency save.
+    pub fn tick(&mut self) -> Option<PathBuf> {
+        self.ticks_since_save += 1;
+        self.ticks_since_emergency_save += 1;
+        if self.interval_ticks == 0 || self.ticks_since_save < self.interval_ticks {
+            return None;
+        }
+        self.ticks_since_save = 0;
+        self.ticks_since_emergency_save = 0;
+        let path = self.dir.join(format!("{}{}.{}", AUTOSAVE_PREFIX, self.next_slot + 1, SAVE_EXTENSION));
+        self.next_slot = (self.next_slot + 1) % self.slots;
+        Some(path)
+    }
+
+    /// Whether the emergency save is due to be brought up to date, with no auto-save due this tick
+    pub fn emergency_save_due(&mut self) -> bool {
+        if self.ticks_since_emergency_save < EMERGENCY_SAVE_INTERVAL_TICKS {
+            return false;
+        }
+        self.ticks_since_emergency_save = 0;
+        true
+    }
+
+    /// Where the save that's written on a crash goes
+    pub fn emergency_path(&self) -> PathBuf {
+        self.dir.join(EMERGENCY_SAVE_NAME)
+    }
+}
+
+/// Asks whether to pick the last game back up, if it wasn't finished and was saved, returning
+/// its session and the save to start it from if so
+pub fn offer_to_restore(dir: &Path) -> Option<(Session, PathBuf)> {
+    let session = match Session::load(dir) {
+        Ok(Some(session)) => session,
+        Ok(None) => return None,
+        Err(err) => {
+            warn!("{}", err);
+            return None;
+        }
+    };
+    let save_path = match Session::latest_save(dir) {
+        Some(save_path) => save_path,
+        None => return None,
+    };
+    let message = format!("The last game of {} wasn't finished. Pick it up from where it was last \
+                           saved? Units start what they were doing over, and shots in the air are lost.",
+                          session.scenario_path.display());
+    if media::show_choice_dialog("Restore last session", &message, "Restore", "New Game") {
+        info!("Restoring {} from {}", session.scenario_path.display(), save_path.display());
+        Some((session, save_path))
+    } else {
+        None
+    }
+}
+
+/// Writes each of a save's files to a temporary file first, so that a failed write can't lose the
+/// save that was there before. The scenario goes last, since it's what marks the save as there.
+pub fn write_save(path: &Path, save: &Save) -> Result<(), String> {
+    let files = [(state_path(path), save.state.as_bytes()), (path.to_path_buf(), &save.scenario[..])];
+    for &(ref file_path, contents) in &files {
+        let mut temp_path = file_path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        try!(File::create(&temp_path)
+            .and_then(|mut file| file.write_all(contents))
+            .and_then(|_| fs::rename(&temp_path, file_path))
+            .map_err(|err| format!("Failed to write {}: {}", file_path.display(), err)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use config::AutosaveConfig;
+    use ecs::resource::GameSettings;
+    use std::env;
+    use std::fs;
+    use ecs::SaveState;
+    use super::{AutoSaver, Save, Session, read_save_state, write_save};
+
+    #[test]
+    fn test_autosave_slots() {
+        let mut auto_saver = AutoSaver::new("saves".into());
+        assert_eq!(None, (0..100000).filter_map(|_| auto_saver.tick()).next());
+        assert!(auto_saver.emergency_save_due());
+        assert!(!auto_saver.emergency_save_due());
+
+        auto_saver.configure(&AutosaveConfig {
+            interval_minutes: 1,
+            slots: 2,
+        });
+        let saves: Vec<String> = (0..3600 * 3)
+            .filter_map(|_| auto_saver.tick())
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(vec!["autosave_1.scn", "autosave_2.scn", "autosave_1.scn"], saves);
+        // The auto-saves kept the emergency save up to date
+        assert!(!auto_saver.emergency_save_due());
+        assert_eq!("emergency.scn", auto_saver.emergency_path().file_name().unwrap());
+    }
+
+    #[test]
+    fn test_session_lifetime() {
+        let dir = env::temp_dir().join(format!("chariot-session-test-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(None, Session::load(&dir).unwrap());
+
+        let mut settings = GameSettings::new();
+        settings.allied_vision = true;
+        let session = Session::new("scenarios/c1s1.scn", &settings);
+        session.start(&dir).unwrap();
+        assert_eq!(Some(session.clone()), Session::load(&dir).unwrap());
+        assert!(session.settings(7).allied_vision);
+        assert_eq!(None, Session::latest_save(&dir));
+
+        let mut state = SaveState::default();
+        state.tick = 3600;
+        let save = Save {
+            scenario: b"save".to_vec(),
+            state: state.to_toml(),
+        };
+        write_save(&dir.join("autosave_1.scn"), &save).unwrap();
+        fs::File::create(dir.join("notes.txt")).unwrap();
+        assert_eq!(Some(dir.join("autosave_1.scn")), Session::latest_save(&dir));
+        assert_eq!(Some(state), read_save_state(&dir.join("autosave_1.scn")).unwrap());
+        assert_eq!(None, read_save_state(&dir.join("autosave_2.scn")).unwrap());
+
+        // Starting another game clears away the last one's saves
+        session.start(&dir).unwrap();
+        assert_eq!(None, Session::latest_save(&dir));
+
+        write_save(&dir.join("emergency.scn"), &save).unwrap();
+        Session::end(&dir).unwrap();
+        assert_eq!(None, Session::load(&dir).unwrap());
+        assert_eq!(None, Session::latest_save(&dir));
+        assert!(!dir.join("emergency.state").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::EmpiresDb;
+use identifier::{CivilizationId, PlayerId, SlpFileId, UnitId};
+use scn;
+use std::collections::HashSet;
+
+/// Every civilization has a town center in its own style of buildings; when the data set can't
+/// draw it, the civilization's buildings are missing and it can't be played
+const TOWN_CENTER_UNIT_ID: u32 = 109;
+
+/// What the data set has enough of to use. The freely distributable demo ships the full
+/// game data tables but leaves most of the graphics out of its archives, so units, and whole
+/// civilizations, that the tables know about can be missing what they're drawn with.
+#[derive(Clone, Debug, Default)]
+pub struct ContentGate {
+    civilization_count: usize,
+    /// Units the tables have that can be drawn, by civilization
+    available_units: HashSet<(CivilizationId, UnitId)>,
+    /// Units the tables have that are missing their graphics
+    gated_units: HashSet<(CivilizationId, UnitId)>,
+    gated_civilizations: HashSet<CivilizationId>,
+}
+
+impl ContentGate {
+    /// Works out what's usable by looking up each unit's standing graphic; `has_slp` says
+    /// whether a graphic's SLP can be loaded
+    pub fn check<F>(empires: &EmpiresDb, mut has_slp: F) -> ContentGate
+        where F: FnMut(SlpFileId) -> bool
+    {
+        let mut gate = ContentGate::new(empires.civilizations().len());
+        for civilization in empires.civilizations() {
+            for unit in civilization.units() {
+                let slp_id = unit.standing_graphic
+                    .and_then(|graphic_id| empires.graphics().get(*graphic_id as usize))
+                    .and_then(|graphic| graphic.slp_id);
+                let drawable = slp_id.map(|slp_id| has_slp(slp_id)).unwrap_or(true);
+                gate.add_unit(civilization.id(), unit.id, drawable);
+            }
+        }
+        gate
+    }
+
+    fn new(civilization_count: usize) -> ContentGate {
+        ContentGate { civilization_count: civilization_count, ..Default::default() }
+    }
+
+    fn add_unit(&mut self, civilization_id: CivilizationId, unit_id: UnitId, drawable: bool) {
+        if drawable {
+            self.available_units.insert((civilization_id, unit_id));
+        } else {
+            self.gated_units.insert((civilization_id, unit_id));
+            if *unit_id == TOWN_CENTER_UNIT_ID {
+                self.gated_civilizations.insert(civilization_id);
+            }
+        }
+    }
+
+    /// Whether anything the tables know about is missing from the data set, as it is with
+    /// the demo's
+    pub fn is_reduced(&self) -> bool {
+        !self.gated_units.is_empty()
+    }
+
+    pub fn gated_unit_count(&self) -> usize {
+        self.gated_units.len()
+    }
+
+    /// Whether the tables have the unit for the civilization but the data set can't draw it
+    pub fn is_unit_gated(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+        self.gated_units.contains(&(civilization_id, unit_id))
+    }
+
+    pub fn is_unit_available(&self, civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+        self.available_units.contains(&(civilization_id, unit_id))
+    }
+
+    pub fn is_civilization_available(&self, civilization_id: CivilizationId) -> bool {
+        *civilization_id >= 1 && *civilization_id as usize <= self.civilization_count &&
+        !self.gated_civilizations.contains(&civilization_id)
+    }
+
+    /// Takes the units the data set can't show out of the scenario, returning a line for each
+    /// kind left out. A scenario with a player whose civilization can't be played can't be
+    /// started at all, and the error says why.
+    pub fn gate_scenario(&self, scenario: &mut scn::Scenario) -> Result<Vec<String>, String> {
+        let player_ids: Vec<PlayerId> = scenario.player_ids();
+        for &player_id in &player_ids {
+            let civilization_id = scenario.player_civilization_id(player_id);
+            let has_units = !scenario.player_units(player_id).is_empty();
+            if has_units && !self.is_civilization_available(civilization_id) {
+                return Err(format!("player {} plays civilization {}, which isn't in this data set",
+                                   *player_id,
+                                   *civilization_id));
+            }
+        }
+
+        let mut left_out = Vec::new();
+        for player_id in player_ids {
+            let civilization_id = scenario.player_civilization_id(player_id);
+            let mut removed: Vec<UnitId> = Vec::new();
+            scenario.player_units_mut(player_id).retain(|unit| {
+                let available = self.is_unit_available(civilization_id, unit.unit_id);
+                if !available {
+                    removed.push(unit.unit_id);
+                }
+                available
+            });
+            removed.sort();
+            removed.dedup();
+            for unit_id in removed {
+                let reason = if self.is_unit_gated(civilization_id, unit_id) {
+                    "its graphics aren't in this data set"
+                } else {
+                    "the game data has no such unit"
+                };
+                left_out.push(format!("left out player {}'s unit {}: {}", *player_id, *unit_id, reason));
+            }
+        }
+        Ok(left_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scn;
+    use scn::PlayerUnit;
+    use super::ContentGate;
+
+    fn demo_gate() -> ContentGate {
+        let mut gate = ContentGate::new(3);
+        gate.add_unit(1.into(), 109.into(), true);
+        gate.add_unit(1.into(), 83.into(), true);
+        gate.add_unit(1.into(), 299.into(), false);
+        gate.add_unit(2.into(), 109.into(), false);
+        gate
+    }
+
+    fn scenario_with_units(civilization_id: usize, unit_ids: &[usize]) -> scn::Scenario {
+        let mut scenario = scn::Scenario::new(16, 16);
+        scenario.player_data.player_civs[1].civilization_id = civilization_id.into();
+        for &unit_id in unit_ids {
+            scenario.add_unit(1.into(), PlayerUnit { unit_id: unit_id.into(), ..Default::default() });
+        }
+        scenario
+    }
+
+    #[test]
+    fn test_availability() {
+        let gate = demo_gate();
+        assert!(gate.is_reduced());
+        assert_eq!(2, gate.gated_unit_count());
+        assert!(gate.is_unit_available(1.into(), 83.into()));
+        assert!(gate.is_unit_gated(1.into(), 299.into()));
+        assert!(!gate.is_unit_available(1.into(), 299.into()));
+        assert!(!gate.is_unit_gated(1.into(), 500.into()));
+        assert!(gate.is_civilization_available(1.into()));
+        assert!(!gate.is_civilization_available(2.into()));
+        assert!(gate.is_civilization_available(3.into()));
+        assert!(!gate.is_civilization_available(0.into()));
+        assert!(!gate.is_civilization_available(4.into()));
+    }
+
+    #[test]
+    fn test_full_data_set_is_not_reduced() {
+        let mut gate = ContentGate::new(1);
+        gate.add_unit(1.into(), 83.into(), true);
+        assert!(!gate.is_reduced());
+    }
+
+    #[test]
+    fn test_gate_scenario_leaves_out_missing_units() {
+        let mut scenario = scenario_with_units(1, &[83, 299, 299, 500, 83]);
+        let left_out = demo_gate().gate_scenario(&mut scenario).unwrap();
+        assert_eq!(vec!["left out player 1's unit 299: its graphics aren't in this data set".to_string(),
+                        "left out player 1's unit 500: the game data has no such unit".to_string()],
+                   left_out);
+        let unit_ids: Vec<u32> = scenario.player_units(1.into()).iter().map(|unit| *unit.unit_id).collect();
+        assert_eq!(vec![83, 83], unit_ids);
+    }
+
+    #[test]
+    fn test_gate_scenario_refuses_missing_civilization() {
+        let mut scenario = scenario_with_units(2, &[83]);
+        assert_eq!(Err("player 1 plays civilization 2, which isn't in this data set".to_string()),
+                   demo_gate().gate_scenario(&mut scenario));
+        assert_eq!(1, scenario.player_units(1.into()).len());
+    }
+}
@@ -0,0 +1,122 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{CursorKind, MouseCursor};
+use media::{HardwareCursor, Media};
+use resource::ShapeManager;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Shown {
+    /// The system's own pointer
+    System,
+    Hardware(CursorKind),
+    /// Drawn with the rest of the frame, with the system's pointer hidden
+    Software,
+}
+
+/// Shows the cursor that the MouseCursor resource asks for. Hardware cursors are made from the
+/// cursor SLP the first time each is needed; when one can't be made, or they're turned off, the
+/// cursor is drawn with the frame instead. Without the cursor graphics, the system's pointer stays.
+pub struct Cursors {
+    hardware: bool,
+    /// Each hardware cursor made, or None for those that couldn't be
+    cursors: HashMap<CursorKind, Option<HardwareCursor>>,
+    /// Whether the cursor SLP is there, once it's been looked for
+    has_graphics: Option<bool>,
+    /// Render scale the hardware cursors were made at, since they're drawn by the system unscaled
+    scale: u32,
+    shown: Option<Shown>,
+}
+
+impl Cursors {
+    pub fn new() -> Cursors {
+        Cursors {
+            hardware: true,
+            cursors: HashMap::new(),
+            has_graphics: None,
+            scale: 0,
+            shown: None,
+        }
+    }
+
+    /// Whether to use hardware cursors when they can be made
+    pub fn set_hardware(&mut self, hardware: bool) {
+        self.hardware = hardware;
+    }
+
+    /// Called once a frame, after the render systems have picked the cursor
+    pub fn update(&mut self,
+                  mouse_cursor: &mut MouseCursor,
+                  media: &mut Media,
+                  shape_manager: &mut ShapeManager) {
+        let scale = media.renderer().scale();
+        if scale != self.scale {
+            self.cursors.clear();
+            self.scale = scale;
+            self.shown = None;
+        }
+
+        if self.has_graphics.is_none() {
+            self.has_graphics = Some(shape_manager.has_slp(&MouseCursor::shape_key()));
+        }
+
+        let kind = mouse_cursor.kind;
+        let has_graphics = self.has_graphics == Some(true) &&
+                           shape_manager.get(&MouseCursor::shape_key(), media.renderer())
+                               .map_or(false, |shape| kind.frame() < shape.frame_count());
+        let wanted = if !has_graphics {
+            Shown::System
+        } else if self.hardware && self.make_hardware_cursor(kind, shape_manager) {
+            Shown::Hardware(kind)
+        } else {
+            Shown::Software
+        };
+        mouse_cursor.software = wanted == Shown::Software;
+        if self.shown == Some(wanted) {
+            return;
+        }
+
+        match wanted {
+            Shown::System => media.set_cursor(None),
+            Shown::Hardware(kind) => media.set_cursor(self.cursors[&kind].as_ref()),
+            Shown::Software => media.show_cursor(false),
+        }
+        self.shown = Some(wanted);
+    }
+
+    /// Makes the hardware cursor if it hasn't been tried yet, returning whether there is one
+    fn make_hardware_cursor(&mut self, kind: CursorKind, shape_manager: &ShapeManager) -> bool {
+        let scale = self.scale;
+        self.cursors
+            .entry(kind)
+            .or_insert_with(|| {
+                match shape_manager.load_cursor(&MouseCursor::shape_key(), kind.frame(), scale) {
+                    Ok(cursor) => Some(cursor),
+                    Err(err) => {
+                        warn!("Drawing the {:?} cursor, since it can't be a hardware cursor: {}", kind, err);
+                        None
+                    }
+                }
+            })
+            .is_some()
+    }
+}
@@ -0,0 +1,264 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{EmpiresDb, PatchValue, ResearchPatch, UnitPatch};
+use resource::ModPackage;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use toml::{self, Table, Value};
+
+/// Directory in a mod that holds its data patches, as `.toml` files
+pub const DATA_PATCH_DIR: &'static str = "data";
+
+/// Changes to the game data tables from one patch file, which looks like this:
+///
+/// ```toml
+/// [[unit]]
+/// id = 83
+/// hit_points = 30
+/// "attack.4" = 5
+///
+/// # A new unit, copied from unit 83, for civilization 2 only
+/// [[unit]]
+/// id = 400
+/// base = 83
+/// civilization = 2
+/// name = "Veteran Villager"
+///
+/// [[research]]
+/// id = 12
+/// time_seconds = 20
+/// "cost.food" = 150
+/// ```
+///
+/// Every other key is a field name, as taken by `Unit::set_field` and `Research::set_field`.
+#[derive(Debug, Default, PartialEq)]
+pub struct DataPatches {
+    pub units: Vec<UnitPatch>,
+    pub research: Vec<ResearchPatch>,
+}
+
+impl DataPatches {
+    pub fn parse(text: &str) -> Result<DataPatches, String> {
+        let mut parser = toml::Parser::new(text);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let messages: Vec<String> = parser.errors
+                    .iter()
+                    .map(|err| {
+                        let (line, col) = parser.to_linecol(err.lo);
+                        format!("line {}, column {}: {}", line + 1, col + 1, err.desc)
+                    })
+                    .collect();
+                return Err(messages.join("; "));
+            }
+        };
+
+        let mut patches = DataPatches::default();
+        for (key, value) in table {
+            let entries = match value {
+                Value::Array(entries) => entries,
+                _ => return Err(format!("\"{}\" must be a list of [[{}]] tables", key, key)),
+            };
+            for entry in entries {
+                let entry = match entry {
+                    Value::Table(entry) => entry,
+                    _ => return Err(format!("\"{}\" must be a list of [[{}]] tables", key, key)),
+                };
+                match &key[..] {
+                    "unit" => patches.units.push(try!(parse_unit_patch(entry))),
+                    "research" => patches.research.push(try!(parse_research_patch(entry))),
+                    _ => return Err(format!("unknown table \"{}\"; use [[unit]] or [[research]]", key)),
+                }
+            }
+        }
+        Ok(patches)
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<DataPatches, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("{}", err)));
+        DataPatches::parse(&text)
+    }
+
+    /// Applies the patches, skipping any that fail with a warning
+    pub fn apply(&self, empires: &mut EmpiresDb, source: &Path) {
+        for patch in &self.units {
+            if let Err(err) = empires.apply_unit_patch(patch) {
+                warn!("Skipping patch to unit {} from {}: {}", *patch.unit_id, source.display(), err);
+            }
+        }
+        for patch in &self.research {
+            if let Err(err) = empires.apply_research_patch(patch) {
+                warn!("Skipping patch to research {} from {}: {}",
+                      *patch.research_id,
+                      source.display(),
+                      err);
+            }
+        }
+    }
+}
+
+/// The patch files in the mods' data directories, in load order, and sorted by name within a mod
+pub fn data_patch_files(packages: &[ModPackage]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for package in packages {
+        let entries = match fs::read_dir(package.dir.join(DATA_PATCH_DIR)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut mod_files: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |extension| extension == "toml"))
+            .collect();
+        mod_files.sort();
+        files.extend(mod_files);
+    }
+    files
+}
+
+/// Merges the mods' data patches over the tables read from empires.dat
+pub fn apply_data_patches(empires: &mut EmpiresDb, packages: &[ModPackage]) {
+    for path in data_patch_files(packages) {
+        match DataPatches::read_from_file(&path) {
+            Ok(patches) => {
+                info!("Applying data patches from {}", path.display());
+                patches.apply(empires, &path);
+            }
+            Err(err) => warn!("Skipping data patches in {}: {}", path.display(), err),
+        }
+    }
+}
+
+fn parse_unit_patch(mut entry: Table) -> Result<UnitPatch, String> {
+    let unit_id = match try!(take_id(&mut entry, "id", "unit")) {
+        Some(unit_id) => unit_id,
+        None => return Err("every [[unit]] needs an id".into()),
+    };
+    Ok(UnitPatch {
+        unit_id: unit_id,
+        civilization_id: try!(take_id(&mut entry, "civilization", "unit")),
+        base_unit_id: try!(take_id(&mut entry, "base", "unit")),
+        fields: try!(patch_fields(entry)),
+    })
+}
+
+fn parse_research_patch(mut entry: Table) -> Result<ResearchPatch, String> {
+    let research_id = match try!(take_id(&mut entry, "id", "research")) {
+        Some(research_id) => research_id,
+        None => return Err("every [[research]] needs an id".into()),
+    };
+    Ok(ResearchPatch {
+        research_id: research_id,
+        base_research_id: try!(take_id(&mut entry, "base", "research")),
+        fields: try!(patch_fields(entry)),
+    })
+}
+
+fn take_id<T>(entry: &mut Table, key: &str, table_name: &str) -> Result<Option<T>, String>
+    where usize: Into<T>
+{
+    match entry.remove(key) {
+        Some(Value::Integer(id)) if id >= 0 => Ok(Some((id as usize).into())),
+        Some(_) => Err(format!("\"{}\" in a [[{}]] must be an ID", key, table_name)),
+        None => Ok(None),
+    }
+}
+
+fn patch_fields(entry: Table) -> Result<Vec<(String, PatchValue)>, String> {
+    entry.into_iter()
+        .map(|(field, value)| {
+            let value = match value {
+                Value::Integer(value) => PatchValue::Integer(value),
+                Value::Float(value) => PatchValue::Float(value),
+                Value::Boolean(value) => PatchValue::Boolean(value),
+                Value::String(value) => PatchValue::String(value),
+                _ => return Err(format!("\"{}\" must be a number, boolean, or string", field)),
+            };
+            Ok((field, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::{PatchValue, ResearchPatch, UnitPatch};
+    use super::DataPatches;
+
+    #[test]
+    fn test_parse() {
+        let patches = DataPatches::parse(r#"
+            [[unit]]
+            id = 83
+            hit_points = 30
+            "attack.4" = 5
+
+            [[unit]]
+            id = 400
+            base = 83
+            civilization = 2
+            name = "Veteran Villager"
+            speed = 1.5
+
+            [[research]]
+            id = 12
+            "cost.food" = 150
+        "#)
+            .unwrap();
+        assert_eq!(DataPatches {
+                       units: vec![UnitPatch {
+                                       unit_id: 83.into(),
+                                       civilization_id: None,
+                                       base_unit_id: None,
+                                       fields: vec![("attack.4".into(), PatchValue::Integer(5)),
+                                                    ("hit_points".into(), PatchValue::Integer(30))],
+                                   },
+                                   UnitPatch {
+                                       unit_id: 400.into(),
+                                       civilization_id: Some(2.into()),
+                                       base_unit_id: Some(83.into()),
+                                       fields: vec![("name".into(),
+                                                     PatchValue::String("Veteran Villager".into())),
+                                                    ("speed".into(), PatchValue::Float(1.5))],
+                                   }],
+                       research: vec![ResearchPatch {
+                                          research_id: 12.into(),
+                                          base_research_id: None,
+                                          fields: vec![("cost.food".into(), PatchValue::Integer(150))],
+                                      }],
+                   },
+                   patches);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(DataPatches::parse("[[unit]]\nhit_points = 30").is_err());
+        assert!(DataPatches::parse("[[unit]]\nid = -1").is_err());
+        assert!(DataPatches::parse("[[unit]]\nid = 83\nattacks = [1, 2]").is_err());
+        assert!(DataPatches::parse("[[building]]\nid = 83").is_err());
+        assert!(DataPatches::parse("[unit]\nid = 83").is_err());
+        assert!(DataPatches::parse("[[unit]\nid = 83").is_err());
+    }
+}
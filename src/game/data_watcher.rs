@@ -0,0 +1,72 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use resource::{DrsManagerRef, FileWatcher, ShapeManagerRef};
+use std::path::PathBuf;
+
+/// Watches the enabled mods for changed replacement graphics and reloads them while the game
+/// is running. The game data tables and the mods' patches to them are watched too, but every
+/// system holds on to the unit stats it was created with, so those only get a warning to restart.
+pub struct DataWatcher {
+    drs_manager: DrsManagerRef,
+    shape_manager: ShapeManagerRef,
+    mods: FileWatcher,
+    data_tables: FileWatcher,
+}
+
+impl DataWatcher {
+    pub fn new(drs_manager: DrsManagerRef,
+               shape_manager: ShapeManagerRef,
+               data_tables: &[PathBuf])
+               -> DataWatcher {
+        let mut mods = FileWatcher::new();
+        for mod_dir in drs_manager.borrow().mod_overrides().mod_dirs() {
+            mods.watch(mod_dir);
+        }
+        let mut data_table_watcher = FileWatcher::new();
+        for path in data_tables {
+            data_table_watcher.watch(path);
+        }
+        DataWatcher {
+            drs_manager: drs_manager,
+            shape_manager: shape_manager,
+            mods: mods,
+            data_tables: data_table_watcher,
+        }
+    }
+
+    pub fn poll(&mut self) {
+        for path in self.mods.poll() {
+            let resource = self.drs_manager.borrow().mod_overrides().resource_at(&path);
+            match resource {
+                // The PNG and its JSON frame layout both make up the replacement
+                Some((drs_key, file_id)) => {
+                    info!("Reloading {}", path.display());
+                    self.shape_manager.borrow_mut().reload_slp(drs_key, (file_id as usize).into());
+                }
+                None => debug!("Ignoring change to {}, which doesn't replace a resource", path.display()),
+            }
+        }
+        for path in self.data_tables.poll() {
+            warn!("{} changed; restart the game for the changes to take effect", path.display());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::FrameSmoothing;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+use time;
+
+/// How many of the latest frame times are averaged
+const AVERAGED_FRAMES: usize = 8;
+
+/// Frame times further than this from a whole number of refreshes are left as they are
+const SNAP_TOLERANCE_NANOS: i64 = 1500000;
+
+/// A frame that takes longer than this is a hitch, like loading or dragging the window, that
+/// shouldn't be spread over the frames after it
+const HITCH_NANOS: u64 = 250000000;
+
+/// Sleeping can overshoot by about this much, so the end of a wait is spent yielding instead
+const SPIN_NANOS: u64 = 1000000;
+
+/// The most update cycles run in one frame. At high game speeds a slow frame would otherwise
+/// owe more cycles, making the next frame slower still, so past this the game falls behind instead.
+const MAX_STEPS_PER_FRAME: u64 = 16;
+
+/// How many time steps are due out of the accumulated time, and the time left over afterwards.
+/// Time owed beyond `MAX_STEPS_PER_FRAME` steps is dropped, keeping only the fraction of a step
+/// that's left over for interpolation.
+pub fn steps_due(accumulator: u64, time_step_nanos: u64) -> (u64, u64) {
+    let time_step_nanos = time_step_nanos.max(1);
+    let steps = accumulator / time_step_nanos;
+    (steps.min(MAX_STEPS_PER_FRAME), accumulator % time_step_nanos)
+}
+
+/// Evens out the time measured between frames, and holds frames back to the frame rate cap.
+/// The measured time jitters around the display's refresh interval, which shows as judder when
+/// the camera scrolls on high refresh displays, since each frame moves it a different amount.
+pub struct FramePacer {
+    smoothing: FrameSmoothing,
+    fps_cap: u32,
+    refresh_nanos: Option<u64>,
+    recent: VecDeque<u64>,
+    /// Time that snapping has taken from or added to the frames so far, which goes back into
+    /// the next one so the game doesn't drift from real time
+    snap_residual: i64,
+}
+
+impl FramePacer {
+    pub fn new(smoothing: FrameSmoothing, fps_cap: u32, refresh_rate: Option<u32>) -> FramePacer {
+        let mut pacer = FramePacer {
+            smoothing: smoothing,
+            fps_cap: fps_cap,
+            refresh_nanos: None,
+            recent: VecDeque::new(),
+            snap_residual: 0,
+        };
+        pacer.set_refresh_rate(refresh_rate);
+        pacer
+    }
+
+    pub fn configure(&mut self, smoothing: FrameSmoothing, fps_cap: u32) {
+        if smoothing != self.smoothing {
+            self.reset();
+        }
+        self.smoothing = smoothing;
+        self.fps_cap = fps_cap;
+    }
+
+    /// The refresh rate changes when the window is moved to another display or goes fullscreen
+    pub fn set_refresh_rate(&mut self, refresh_rate: Option<u32>) {
+        let refresh_nanos = refresh_rate.map(|rate| 1000000000 / rate as u64);
+        if refresh_nanos != self.refresh_nanos {
+            self.refresh_nanos = refresh_nanos;
+            self.reset();
+        }
+    }
+
+    /// Turns the measured time since the last frame into the time to advance the game by
+    pub fn frame_time(&mut self, elapsed_nanos: u64) -> u64 {
+        if elapsed_nanos >= HITCH_NANOS {
+            self.reset();
+            return elapsed_nanos;
+        }
+        match self.smoothing {
+            FrameSmoothing::Off => elapsed_nanos,
+            FrameSmoothing::Average => {
+                self.recent.push_back(elapsed_nanos);
+                if self.recent.len() > AVERAGED_FRAMES {
+                    self.recent.pop_front();
+                }
+                self.recent.iter().sum::<u64>() / self.recent.len() as u64
+            }
+            FrameSmoothing::Snap => {
+                let refresh_nanos = match self.refresh_nanos {
+                    Some(refresh_nanos) => refresh_nanos as i64,
+                    None => return elapsed_nanos,
+                };
+                let time = elapsed_nanos as i64 + self.snap_residual;
+                let refreshes = (time + refresh_nanos / 2) / refresh_nanos;
+                let snapped = refreshes * refresh_nanos;
+                if refreshes > 0 && (time - snapped).abs() <= SNAP_TOLERANCE_NANOS {
+                    self.snap_residual = time - snapped;
+                    snapped as u64
+                } else {
+                    self.snap_residual = 0;
+                    elapsed_nanos
+                }
+            }
+        }
+    }
+
+    /// How much longer the frame that started at `frame_start` has to wait to keep under the
+    /// frame rate cap
+    pub fn wait_nanos(&self, frame_start: u64, now: u64) -> u64 {
+        if self.fps_cap == 0 {
+            return 0;
+        }
+        let min_frame_nanos = 1000000000 / self.fps_cap as u64;
+        (frame_start + min_frame_nanos).saturating_sub(now)
+    }
+
+    /// Blocks until the frame that started at `frame_start` is allowed to end
+    pub fn wait(&self, frame_start: u64) {
+        loop {
+            let wait_nanos = self.wait_nanos(frame_start, time::precise_time_ns());
+            if wait_nanos == 0 {
+                return;
+            } else if wait_nanos > SPIN_NANOS {
+                let sleep_nanos = wait_nanos - SPIN_NANOS;
+                thread::sleep(Duration::new(sleep_nanos / 1000000000, (sleep_nanos % 1000000000) as u32));
+            } else {
+                thread::yield_now();
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.recent.clear();
+        self.snap_residual = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::FrameSmoothing;
+    use super::{FramePacer, MAX_STEPS_PER_FRAME, steps_due};
+
+    const MS: u64 = 1000000;
+
+    #[test]
+    fn test_average_evens_out_jitter() {
+        let mut pacer = FramePacer::new(FrameSmoothing::Average, 0, None);
+        assert_eq!(8 * MS, pacer.frame_time(8 * MS));
+        assert_eq!(7 * MS, pacer.frame_time(6 * MS));
+        assert_eq!(8 * MS, pacer.frame_time(10 * MS));
+
+        // A hitch goes through as it is and starts the average over
+        assert_eq!(300 * MS, pacer.frame_time(300 * MS));
+        assert_eq!(5 * MS, pacer.frame_time(5 * MS));
+    }
+
+    #[test]
+    fn test_snap_to_refreshes_without_drifting() {
+        let mut pacer = FramePacer::new(FrameSmoothing::Snap, 0, Some(100));
+        assert_eq!(10 * MS, pacer.frame_time(11 * MS));
+        assert_eq!(10 * MS, pacer.frame_time(9 * MS + MS / 2));
+        assert_eq!(20 * MS, pacer.frame_time(19 * MS));
+
+        // Too far from a whole number of refreshes to snap
+        assert_eq!(15 * MS, pacer.frame_time(15 * MS));
+
+        let mut total = 0;
+        for elapsed in &[11 * MS, 9 * MS, 11 * MS, 9 * MS] {
+            total += pacer.frame_time(*elapsed);
+        }
+        assert_eq!(40 * MS, total);
+    }
+
+    #[test]
+    fn test_snap_needs_the_refresh_rate() {
+        let mut pacer = FramePacer::new(FrameSmoothing::Snap, 0, None);
+        assert_eq!(11 * MS, pacer.frame_time(11 * MS));
+    }
+
+    #[test]
+    fn test_fps_cap() {
+        let mut pacer = FramePacer::new(FrameSmoothing::Off, 0, None);
+        assert_eq!(0, pacer.wait_nanos(0, MS));
+
+        pacer.configure(FrameSmoothing::Off, 100);
+        assert_eq!(6 * MS, pacer.wait_nanos(0, 4 * MS));
+        assert_eq!(0, pacer.wait_nanos(0, 12 * MS));
+    }
+
+    #[test]
+    fn test_steps_due() {
+        assert_eq!((0, 5 * MS), steps_due(5 * MS, 10 * MS));
+        assert_eq!((3, 2 * MS), steps_due(32 * MS, 10 * MS));
+
+        // A long frame at 8x speed owes far more steps than it can run; the rest is dropped
+        let time_step_nanos = 1000000000 / 60 / 8;
+        let (steps, remaining) = steps_due(200 * MS, time_step_nanos);
+        assert_eq!(MAX_STEPS_PER_FRAME, steps);
+        assert!(remaining < time_step_nanos);
+    }
+}
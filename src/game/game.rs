@@ -19,64 +19,152 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use config::{Config, ConfigLoader, ConfigValue};
 use dat::{EmpiresDb, EmpiresDbRef};
+use drs::DrsFileType;
 use media::{self, MediaRef};
-use resource::{DrsManager, DrsManagerRef, GameDir, ShapeManager, ShapeManagerRef, ShapeMetadataStore,
-               ShapeMetadataStoreRef};
+use resource::{self, DEFAULT_TEXTURE_BUDGET, DrsKey, DrsManager, DrsManagerRef, EditionAssets, GameDir,
+               LoadOrder, ModOverrides, ModPackage, ShapeManager, ShapeManagerRef, ShapeMetadataStore,
+               ShapeMetadataStoreRef, SoundManager, SoundManagerRef};
+use logging;
+use profile::{self, Profile};
+use rayon;
+use scn;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use super::content_gate::ContentGate;
+use super::data_patches;
+use super::data_watcher::DataWatcher;
+use super::frame_pacer::{self, FramePacer};
+use super::loader::{LoadProgress, Loader};
+use super::loading_screen::{LoadStage, LoadingScreen};
 use super::state::GameState;
 use time;
 use types::Fixed;
 
 const WINDOW_TITLE: &'static str = "Chariot";
-const WINDOW_WIDTH: u32 = 1024;
-const WINDOW_HEIGHT: u32 = 768;
+const MODS_DIR: &'static str = "mods";
+
+/// How often to check whether the config file, and with `watch_data` the mods, have been changed
+const CONFIG_POLL_NANOS: u64 = 1000000000;
 
 pub struct Game {
+    config_loader: ConfigLoader,
+    config: Config,
+    profile: Profile,
+    profile_path: PathBuf,
     game_dir: GameDir,
     drs_manager: DrsManagerRef,
     shape_manager: ShapeManagerRef,
     shape_metadata: ShapeMetadataStoreRef,
+    sound_manager: SoundManagerRef,
     empires: EmpiresDbRef,
+    empires_path: PathBuf,
+    content_gate: ContentGate,
+    data_watcher: Option<DataWatcher>,
+    mod_packages: Vec<ModPackage>,
     media: MediaRef,
+    frame_pacer: FramePacer,
     states: Vec<Box<GameState>>,
 }
 
+/// What's loaded on the loading thread; everything else is tied to the renderer, or shared
+/// in ways that keep it on the main thread
+struct LoadedData {
+    drs_manager: DrsManager,
+    shape_metadata: ShapeMetadataStore,
+    empires: EmpiresDb,
+    empires_path: PathBuf,
+    content_gate: ContentGate,
+    mod_packages: Vec<ModPackage>,
+}
+
 impl Game {
-    pub fn new(game_data_dir: &str) -> Game {
-        let game_dir = GameDir::new(game_data_dir).unwrap_or_else(|err| {
+    /// Loads the game, or returns None if the loading was cancelled
+    pub fn new(config_loader: ConfigLoader) -> Option<Game> {
+        Game::new_with(config_loader, |_| Ok(())).map(|(game, ())| game)
+    }
+
+    /// Loads the game along with whatever else `load_extra` loads, such as the scenario to
+    /// start with. Everything but the graphics, whose textures belong to the renderer, is loaded
+    /// on a loading thread while the window shows the progress, and the game is only returned
+    /// once all of it has loaded, so the first state can start straight away. Closing the window
+    /// or pressing Escape while the loading thread runs cancels it, and None is returned; with
+    /// no menu to go back to yet, that quits the game.
+    pub fn new_with<T, F>(mut config_loader: ConfigLoader, load_extra: F) -> Option<(Game, T)>
+        where T: Send + 'static,
+              F: FnOnce(&LoadProgress) -> Result<T, String> + Send + 'static
+    {
+        let profile_path = profile::default_profile_path();
+        let profile = load_profile(&profile_path, &mut config_loader);
+        let config = config_loader.config().unwrap_or_else(|err| {
             unrecoverable!("{}", err);
         });
 
-        let drs_manager = DrsManager::new(&game_dir);
-        if let Err(err) = drs_manager.borrow_mut().preload() {
-            unrecoverable!("Failed to preload DRS archives: {}", err);
-        }
+        let game_dir = find_game_dir(&config, config_loader.file_path());
 
-        let shape_manager = ShapeManager::new(drs_manager.clone()).unwrap_or_else(|err| {
-            unrecoverable!("Failed to initialize the shape manager: {}", err);
-        });
+        // The window comes first so that there's somewhere to show the loading progress
+        let video = &config.video;
+        let media = media::create_media(video.width, video.height, video.vsync, WINDOW_TITLE)
+            .unwrap_or_else(|err| {
+                unrecoverable!("Failed to create media window: {}", err);
+            });
+        apply_window_settings(&media, &config);
+        apply_audio_settings(&media, &config);
+        let mut loading_screen = LoadingScreen::new(media.clone());
 
-        let shape_metadata = ShapeMetadataStoreRef::new(ShapeMetadataStore::load(&*drs_manager.borrow()));
+        let (loader_config, loader_game_dir) = (config.clone(), game_dir.clone());
+        let loader = Loader::spawn(move |progress| {
+            let data = try!(load_data(&loader_config, &loader_game_dir, progress));
+            let extra = try!(load_extra(progress));
+            Ok((data, extra))
+        });
+        let (data, extra) = match loading_screen.wait_for(loader) {
+            Some(Ok(loaded)) => loaded,
+            Some(Err(err)) => {
+                unrecoverable!("{}", err);
+            }
+            None => return None,
+        };
 
-        let empires_dat_location = game_dir.find_file("data/empires.dat").unwrap();
-        let empires = EmpiresDbRef::new(EmpiresDb::read_from_file(empires_dat_location)
+        loading_screen.begin(LoadStage::Graphics);
+        let drs_manager: DrsManagerRef = Rc::new(RefCell::new(data.drs_manager));
+        let shape_manager = ShapeManager::new(drs_manager.clone(), DEFAULT_TEXTURE_BUDGET)
             .unwrap_or_else(|err| {
-                unrecoverable!("Failed to load empires.dat: {}", err);
-            }));
-
-        let media = media::create_media(WINDOW_WIDTH, WINDOW_HEIGHT, WINDOW_TITLE).unwrap_or_else(|err| {
-            unrecoverable!("Failed to create media window: {}", err);
+                unrecoverable!("Failed to initialize the shape manager: {}", err);
+            });
+        shape_manager.borrow_mut().set_player_palette(config.video.player_colors);
+        shape_manager.borrow_mut().preload(rayon::current_num_threads(), |done, total| {
+            loading_screen.progress(LoadStage::Graphics, done, total);
         });
+        loading_screen.finish(LoadStage::Graphics);
+        let sound_manager = SoundManager::new(drs_manager.clone());
+
+        let refresh_rate = media.borrow_mut().renderer().refresh_rate();
+        let frame_pacer = FramePacer::new(config.video.frame_smoothing, config.video.fps_cap, refresh_rate);
 
-        Game {
+        let mut game = Game {
+            config_loader: config_loader,
+            config: config,
+            profile: profile,
+            profile_path: profile_path,
             game_dir: game_dir,
             drs_manager: drs_manager,
             shape_manager: shape_manager,
-            shape_metadata: shape_metadata,
-            empires: empires,
+            shape_metadata: ShapeMetadataStoreRef::new(data.shape_metadata),
+            sound_manager: sound_manager,
+            empires: EmpiresDbRef::new(data.empires),
+            empires_path: data.empires_path,
+            content_gate: data.content_gate,
+            data_watcher: None,
+            mod_packages: data.mod_packages,
             media: media,
+            frame_pacer: frame_pacer,
             states: Vec::new(),
-        }
+        };
+        game.update_data_watcher();
+        Some((game, extra))
     }
 
     pub fn push_state(&mut self, mut state: Box<GameState>) {
@@ -88,30 +176,143 @@ impl Game {
     }
 
     pub fn game_loop(&mut self) {
-        let time_step_nanos = 1000000000 / 60u64;
+        let base_time_step_nanos = 1000000000 / 60u64;
         let time_step_seconds = Fixed::from(1) / Fixed::from(60);
 
         let mut accumulator: u64 = 0;
         let mut last_time = time::precise_time_ns();
+        let mut last_config_poll = last_time;
 
         while self.media.borrow().is_open() {
             self.media.borrow_mut().renderer().present();
 
             let new_time = time::precise_time_ns();
-            accumulator += new_time - last_time;
+            let elapsed_nanos = self.frame_pacer.frame_time(new_time - last_time);
+            accumulator += elapsed_nanos;
             last_time = new_time;
 
-            while accumulator >= time_step_nanos {
+            self.shape_manager.borrow_mut().update_color_cycle(elapsed_nanos);
+
+            if new_time - last_config_poll >= CONFIG_POLL_NANOS {
+                last_config_poll = new_time;
+                let refresh_rate = self.media.borrow_mut().renderer().refresh_rate();
+                self.frame_pacer.set_refresh_rate(refresh_rate);
+                self.reload_config_if_changed();
+                if let Some(ref mut data_watcher) = self.data_watcher {
+                    data_watcher.poll();
+                }
+            }
+
+            let time_step_nanos = self.time_step_nanos(base_time_step_nanos);
+            let (steps, remaining) = frame_pacer::steps_due(accumulator, time_step_nanos);
+            for _ in 0..steps {
                 self.media.borrow_mut().update();
+                self.keep_fullscreen_toggle();
                 self.update(time_step_seconds);
-                accumulator -= time_step_nanos;
+                self.apply_option_changes();
             }
+            accumulator = remaining;
 
             let lerp = Fixed::from(accumulator as f64 / time_step_nanos as f64);
             if let Some(state) = self.current_state() {
                 state.render(lerp);
             }
+            self.frame_pacer.wait(last_time);
+        }
+    }
+
+    /// Real time between update cycles; running faster just runs the same fixed time step more often
+    fn time_step_nanos(&mut self, base_time_step_nanos: u64) -> u64 {
+        let multiplier: f64 = match self.current_state() {
+            Some(state) => state.speed_multiplier().into(),
+            None => 1f64,
+        };
+        (base_time_step_nanos as f64 / multiplier) as u64
+    }
+
+    fn reload_config_if_changed(&mut self) {
+        if !self.config_loader.file_changed() {
+            return;
+        }
+        info!("Reloading {}", self.config_loader.file_path().display());
+        match self.config_loader.load_file() {
+            Ok(()) => self.refresh_config(),
+            Err(err) => warn!("{}", err),
+        }
+    }
+
+    /// Alt+Enter is handled by the window, so the setting is brought in line with it afterwards,
+    /// which keeps the window from being put back when the config is next reloaded
+    fn keep_fullscreen_toggle(&mut self) {
+        let fullscreen = self.media.borrow().is_fullscreen();
+        if fullscreen == self.config.video.fullscreen {
+            return;
+        }
+        let value = ConfigValue::Boolean(fullscreen);
+        if let Err(err) = self.config_loader.set_option("video.fullscreen", value) {
+            warn!("{}", err);
+        }
+        self.refresh_config();
+    }
+
+    fn apply_option_changes(&mut self) {
+        let changes = match self.current_state() {
+            Some(state) => state.take_option_changes(),
+            None => return,
+        };
+        if changes.is_empty() {
+            return;
+        }
+        for (key, value) in changes {
+            if let Err(err) = self.config_loader.set_option(&key, value) {
+                warn!("{}", err);
+            }
+        }
+        self.refresh_config();
+    }
+
+    /// Applies the latest settings, except for those that only take effect on startup
+    fn refresh_config(&mut self) {
+        let new_config = match self.config_loader.config() {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("{}", err);
+                return;
+            }
+        };
+        for setting in self.config.structural_changes(&new_config) {
+            warn!("The {} settings changed; restart the game for them to take effect", setting);
+        }
+        self.config.apply_non_structural(&new_config);
+        apply_window_settings(&self.media, &self.config);
+        apply_audio_settings(&self.media, &self.config);
+        self.frame_pacer.configure(self.config.video.frame_smoothing, self.config.video.fps_cap);
+        self.shape_manager.borrow_mut().set_player_palette(self.config.video.player_colors);
+
+        if let Err(err) = logging::set_filter(&self.config.log_filter) {
+            warn!("Invalid log filter \"{}\": {}", self.config.log_filter, err);
+        }
+        for state in &mut self.states {
+            state.apply_config(&self.config);
+        }
+        self.update_data_watcher();
+    }
+
+    /// Starts or stops watching the data files, following the `watch_data` setting
+    fn update_data_watcher(&mut self) {
+        if self.config.watch_data == self.data_watcher.is_some() {
+            return;
         }
+        self.data_watcher = if self.config.watch_data {
+            info!("Watching the mods and game data for changes");
+            let mut data_tables = vec![self.empires_path.clone()];
+            data_tables.extend(self.mod_packages
+                .iter()
+                .map(|package| package.dir.join(data_patches::DATA_PATCH_DIR)));
+            Some(DataWatcher::new(self.drs_manager.clone(), self.shape_manager.clone(), &data_tables))
+        } else {
+            None
+        };
     }
 
     fn pop_state(&mut self) {
@@ -150,10 +351,29 @@ impl Game {
         }
     }
 
+    pub fn config<'a>(&'a self) -> &'a Config {
+        &self.config
+    }
+
+    /// The player profile as it was when the game started
+    pub fn profile<'a>(&'a self) -> &'a Profile {
+        &self.profile
+    }
+
+    /// Where the player profile is saved, in the user's config directory
+    pub fn profile_path<'a>(&'a self) -> &'a Path {
+        &self.profile_path
+    }
+
     pub fn game_dir<'a>(&'a self) -> &'a GameDir {
         &self.game_dir
     }
 
+    /// The enabled mods, in the order they were loaded
+    pub fn mod_packages<'a>(&'a self) -> &'a [ModPackage] {
+        &self.mod_packages
+    }
+
     pub fn drs_manager(&self) -> DrsManagerRef {
         self.drs_manager.clone()
     }
@@ -166,11 +386,146 @@ impl Game {
         self.shape_metadata.clone()
     }
 
+    pub fn sound_manager(&self) -> SoundManagerRef {
+        self.sound_manager.clone()
+    }
+
     pub fn empires_db(&self) -> EmpiresDbRef {
         self.empires.clone()
     }
 
+    /// What the game data has enough of to use, for data sets like the demo's
+    pub fn content_gate<'a>(&'a self) -> &'a ContentGate {
+        &self.content_gate
+    }
+
+    /// Takes the units the data set can't show out of the scenario before it's started. A
+    /// scenario for a civilization that isn't in the data set can't be played at all, which is
+    /// told in a dialog before exiting rather than left to fail on the first missing graphic.
+    pub fn gate_scenario(&self, scenario: &mut scn::Scenario) {
+        match self.content_gate.gate_scenario(scenario) {
+            Ok(left_out) => {
+                for line in left_out {
+                    warn!("Scenario: {}", line);
+                }
+            }
+            Err(err) => {
+                let message = format!("This scenario needs content that isn't in the game data: {}. \
+                                       It can only be played with the full game's data.",
+                                      err);
+                media::show_error_dialog("Scenario not available", &message);
+                unrecoverable!("{}", message);
+            }
+        }
+    }
+
     pub fn media(&self) -> MediaRef {
         self.media.clone()
     }
 }
+
+/// Looks for the game data in the configured directory, then wherever it's usually installed.
+/// If it isn't anywhere, the player is told where it was looked for and how to point the game
+/// at it, and can search again after putting the CD in.
+/// Opens the archives and reads the game data, on the loading thread
+fn load_data(config: &Config, game_dir: &GameDir, progress: &LoadProgress) -> Result<LoadedData, String> {
+    try!(progress.begin(LoadStage::Archives));
+    let mut drs_manager = DrsManager::open(game_dir);
+    let load_order = LoadOrder::resolve(MODS_DIR, &config.mods);
+    for problem in &load_order.problems {
+        warn!("Skipping mod: {}", problem);
+    }
+    for conflict in &load_order.conflicts {
+        info!("Mod conflict: {}", conflict);
+    }
+    for package in &load_order.packages {
+        info!("Enabled mod {} {} from {:?}",
+              package.manifest.name,
+              package.manifest.version,
+              package.dir);
+    }
+    drs_manager.set_mod_overrides(ModOverrides::from_dirs(load_order.mod_dirs()));
+    if let Some(ref edition_dir) = config.edition_dir {
+        drs_manager.set_edition_assets(EditionAssets::open(edition_dir));
+    }
+    try!(drs_manager.preload().map_err(|err| format!("Failed to preload DRS archives: {}", err)));
+    progress.finish(LoadStage::Archives);
+
+    try!(progress.begin(LoadStage::ShapeMetadata));
+    let shape_metadata = ShapeMetadataStore::load(&drs_manager);
+    progress.finish(LoadStage::ShapeMetadata);
+
+    try!(progress.begin(LoadStage::GameData));
+    let empires_path = try!(game_dir.find_file("data/empires.dat")
+        .map_err(|err| format!("Failed to find empires.dat: {}", err)));
+    let mut empires = try!(EmpiresDb::read_from_file(&empires_path)
+        .map_err(|err| format!("Failed to load empires.dat: {}", err)));
+    empires.apply_civ_bonuses();
+    data_patches::apply_data_patches(&mut empires, &load_order.packages);
+    let content_gate = ContentGate::check(&empires, |slp_id| {
+        drs_manager.has_file(DrsKey::Graphics, DrsFileType::Slp, *slp_id)
+    });
+    if content_gate.is_reduced() {
+        info!("The game data is a reduced set, such as the demo's; {} units, and the scenarios that use \
+               them, aren't available",
+              content_gate.gated_unit_count());
+    }
+    progress.finish(LoadStage::GameData);
+
+    Ok(LoadedData {
+        drs_manager: drs_manager,
+        shape_metadata: shape_metadata,
+        empires: empires,
+        empires_path: empires_path,
+        content_gate: content_gate,
+        mod_packages: load_order.packages,
+    })
+}
+
+fn apply_window_settings(media: &MediaRef, config: &Config) {
+    let mut media = media.borrow_mut();
+    media.set_render_scale(config.video.scale);
+    media.set_fullscreen(config.video.fullscreen, config.video.fullscreen_mode);
+}
+
+fn apply_audio_settings(media: &MediaRef, config: &Config) {
+    let audio = &config.audio;
+    media.borrow_mut()
+        .audio()
+        .set_volumes(audio.master_volume, audio.sound_volume, audio.ambient_volume, audio.music_volume);
+}
+
+/// Reads the player profile and layers its settings over the config file's. A profile that can't
+/// be read is started over rather than keeping the game from starting.
+fn load_profile(path: &Path, config_loader: &mut ConfigLoader) -> Profile {
+    let profile = Profile::load(path).unwrap_or_else(|err| {
+        warn!("{}; starting a new profile", err);
+        Profile::for_current_user()
+    });
+    if let Err(err) = config_loader.set_profile_layer(profile.config_layer()) {
+        warn!("Ignoring the settings in {}: {}", path.display(), err);
+    }
+    profile
+}
+
+fn find_game_dir(config: &Config, config_path: &Path) -> GameDir {
+    let search_dirs: Vec<PathBuf> = config.data_search_dirs.iter().map(PathBuf::from).collect();
+    loop {
+        let rejected = match resource::locate_game_dir(Path::new(&config.data_dir), &search_dirs) {
+            Ok(game_dir) => return game_dir,
+            Err(rejected) => rejected,
+        };
+        let looked_in: Vec<String> = rejected.iter().map(|rejected| format!("  {}", rejected)).collect();
+        let message = format!("Chariot needs the data from the original Age of Empires, and couldn't find \
+                               it in any of these places:\n\n{}\n\nIf the game is installed or its CD \
+                               is mounted somewhere else, set data_dir in {} to the directory with \
+                               empires.exe, language.dll, and the data directory in it, or pass \
+                               --game-data-dir.",
+                              looked_in.join("\n"),
+                              config_path.display());
+        warn!("{}", message);
+        if !media::show_retry_dialog("Chariot: Missing game data", &message, "Search Again") {
+            unrecoverable!("Couldn't find the game data");
+        }
+    }
+}
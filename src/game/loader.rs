@@ -0,0 +1,194 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use super::loading_screen::LoadStage;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StageEvent {
+    Began(LoadStage),
+    Progress(LoadStage, usize, usize),
+    Finished(LoadStage),
+}
+
+/// Handed to the job on the loading thread, to report how far along it is and to find out
+/// whether it's been cancelled
+pub struct LoadProgress {
+    events: Sender<StageEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl LoadProgress {
+    /// Starts a stage, unless loading has been cancelled in the meantime; that gives the job
+    /// a chance to stop between stages
+    pub fn begin(&self, stage: LoadStage) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err("Loading was cancelled".to_string());
+        }
+        self.send(StageEvent::Began(stage));
+        Ok(())
+    }
+
+    pub fn progress(&self, stage: LoadStage, done: usize, total: usize) {
+        self.send(StageEvent::Progress(stage, done, total));
+    }
+
+    pub fn finish(&self, stage: LoadStage) {
+        self.send(StageEvent::Finished(stage));
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn send(&self, event: StageEvent) {
+        // Nobody is listening once the loading has been given up on, which is fine
+        let _ = self.events.send(event);
+    }
+}
+
+pub enum LoadUpdate<T> {
+    Stage(StageEvent),
+    /// The job is done; this is the last update
+    Done(Result<T, String>),
+}
+
+/// Runs a loading job on its own thread, so that the window stays responsive while it runs
+pub struct Loader<T> {
+    events: Receiver<StageEvent>,
+    result: Receiver<Result<T, String>>,
+    /// The result, once it's come in, held back until the events before it have been handed out
+    done: Option<Result<T, String>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T: Send + 'static> Loader<T> {
+    pub fn spawn<F>(job: F) -> Loader<T>
+        where F: FnOnce(&LoadProgress) -> Result<T, String> + Send + 'static
+    {
+        let (event_sender, events) = mpsc::channel();
+        let (result_sender, result) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = LoadProgress {
+            events: event_sender,
+            cancelled: cancelled.clone(),
+        };
+        thread::Builder::new()
+            .name("loader".to_string())
+            .spawn(move || {
+                let _ = result_sender.send(job(&progress));
+            })
+            .unwrap_or_else(|err| {
+                unrecoverable!("Failed to start the loading thread: {}", err);
+            });
+        Loader {
+            events: events,
+            result: result,
+            done: None,
+            cancelled: cancelled,
+        }
+    }
+
+    /// The job's next stage event, or its result once it has reported everything, waiting up
+    /// to the timeout for either. A job that panicked is reported as having failed.
+    pub fn next(&mut self, timeout: Duration) -> Option<LoadUpdate<T>> {
+        // The job sends its result after all of its events, so once the result is in, so are
+        // the events
+        if self.done.is_none() {
+            self.done = match self.result.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => Some(Err("The loading thread crashed".to_string())),
+            };
+        }
+        if let Ok(event) = self.events.try_recv() {
+            return Some(LoadUpdate::Stage(event));
+        }
+        if let Some(result) = self.done.take() {
+            return Some(LoadUpdate::Done(result));
+        }
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(LoadUpdate::Stage(event)),
+            Err(RecvTimeoutError::Timeout) |
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Asks the job to stop at its next stage. The thread is left to wind down on its own, and
+    /// whatever it was loading is thrown away.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::LoadStage;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use super::{LoadUpdate, Loader, StageEvent};
+
+    fn run<T: Send + 'static>(mut loader: Loader<T>) -> (Vec<StageEvent>, Result<T, String>) {
+        let mut events = Vec::new();
+        loop {
+            match loader.next(Duration::from_millis(1)) {
+                Some(LoadUpdate::Stage(event)) => events.push(event),
+                Some(LoadUpdate::Done(result)) => return (events, result),
+                None => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_come_before_the_result() {
+        let loader = Loader::spawn(|progress| {
+            try!(progress.begin(LoadStage::GameData));
+            progress.progress(LoadStage::GameData, 1, 2);
+            progress.finish(LoadStage::GameData);
+            Ok(42)
+        });
+        assert_eq!((vec![StageEvent::Began(LoadStage::GameData),
+                         StageEvent::Progress(LoadStage::GameData, 1, 2),
+                         StageEvent::Finished(LoadStage::GameData)],
+                    Ok(42)),
+                   run(loader));
+    }
+
+    #[test]
+    fn test_cancelling_stops_at_the_next_stage() {
+        let (go_sender, go) = mpsc::channel::<()>();
+        let loader = Loader::spawn(move |progress| {
+            try!(progress.begin(LoadStage::Archives));
+            go.recv().unwrap();
+            try!(progress.begin(LoadStage::GameData));
+            Ok(())
+        });
+        loader.cancel();
+        let _ = go_sender.send(());
+        let (events, result) = run(loader);
+        assert!(result.is_err());
+        assert!(!events.contains(&StageEvent::Began(LoadStage::GameData)));
+    }
+}
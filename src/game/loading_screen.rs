@@ -0,0 +1,168 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use media::{Key, KeyState, MediaRef};
+use nalgebra::Vector2;
+use std::time::Duration;
+use super::loader::{LoadUpdate, Loader, StageEvent};
+use time;
+use types::{Color, Rect};
+
+/// Don't redraw the loading screen more often than this; presenting waits for vsync,
+/// which would otherwise slow down loading
+const REDRAW_INTERVAL_NANOS: u64 = 50000000;
+
+/// How long to wait for word from the loading thread before checking the window again
+const POLL_INTERVAL_MILLIS: u64 = 10;
+
+const BAR_HEIGHT: i32 = 16;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LoadStage {
+    Archives,
+    ShapeMetadata,
+    GameData,
+    Scenario,
+    Graphics,
+}
+
+pub const LOAD_STAGES: [LoadStage; 5] = [LoadStage::Archives,
+                                         LoadStage::ShapeMetadata,
+                                         LoadStage::GameData,
+                                         LoadStage::Scenario,
+                                         LoadStage::Graphics];
+
+impl LoadStage {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LoadStage::Archives => "archives",
+            LoadStage::ShapeMetadata => "shape metadata",
+            LoadStage::GameData => "game data",
+            LoadStage::Scenario => "scenario",
+            LoadStage::Graphics => "graphics",
+        }
+    }
+
+    fn index(&self) -> usize {
+        LOAD_STAGES.iter().position(|stage| stage == self).unwrap()
+    }
+}
+
+/// Draws a progress bar while the game data loads, split into one section per stage. Closing
+/// the window or pressing Escape asks for the loading to be cancelled.
+pub struct LoadingScreen {
+    media: MediaRef,
+    stage_started_nanos: u64,
+    last_draw_nanos: u64,
+    cancel_requested: bool,
+}
+
+impl LoadingScreen {
+    pub fn new(media: MediaRef) -> LoadingScreen {
+        LoadingScreen {
+            media: media,
+            stage_started_nanos: 0,
+            last_draw_nanos: 0,
+            cancel_requested: false,
+        }
+    }
+
+    /// Shows the progress of a job on the loading thread until it's done, returning its result,
+    /// or None if the loading was cancelled
+    pub fn wait_for<T: Send + 'static>(&mut self, mut loader: Loader<T>) -> Option<Result<T, String>> {
+        loop {
+            self.pump();
+            if self.cancel_requested {
+                info!("Loading was cancelled");
+                loader.cancel();
+                return None;
+            }
+            match loader.next(Duration::from_millis(POLL_INTERVAL_MILLIS)) {
+                Some(LoadUpdate::Stage(StageEvent::Began(stage))) => self.begin(stage),
+                Some(LoadUpdate::Stage(StageEvent::Progress(stage, done, total))) => {
+                    self.progress(stage, done, total)
+                }
+                Some(LoadUpdate::Stage(StageEvent::Finished(stage))) => self.finish(stage),
+                Some(LoadUpdate::Done(result)) => return Some(result),
+                None => {}
+            }
+        }
+    }
+
+    pub fn begin(&mut self, stage: LoadStage) {
+        info!("Loading {}...", stage.name());
+        self.stage_started_nanos = time::precise_time_ns();
+        self.draw(stage, 0, 1);
+    }
+
+    /// Shows how far along the stage is, at most once every REDRAW_INTERVAL_NANOS
+    pub fn progress(&mut self, stage: LoadStage, done: usize, total: usize) {
+        if time::precise_time_ns() - self.last_draw_nanos >= REDRAW_INTERVAL_NANOS {
+            self.draw(stage, done, total);
+        }
+    }
+
+    pub fn finish(&mut self, stage: LoadStage) {
+        let elapsed_nanos = time::precise_time_ns() - self.stage_started_nanos;
+        info!("Loaded {} in {} ms", stage.name(), elapsed_nanos / 1000000);
+        self.draw(stage, 1, 1);
+    }
+
+    fn draw(&mut self, stage: LoadStage, done: usize, total: usize) {
+        self.last_draw_nanos = time::precise_time_ns();
+
+        self.pump();
+        let mut media = self.media.borrow_mut();
+        let viewport_size = media.viewport_size();
+        let renderer = media.renderer();
+
+        let bar_width = viewport_size.x as i32 / 2;
+        let bar = Rect::of(viewport_size.x as i32 / 4,
+                           (viewport_size.y as i32 - BAR_HEIGHT) / 2,
+                           bar_width,
+                           BAR_HEIGHT);
+        let stage_count = LOAD_STAGES.len() as i32;
+        let stage_fraction = if total > 0 { done as f64 / total as f64 } else { 1f64 };
+        let filled = ((stage.index() as f64 + stage_fraction) * bar_width as f64 / stage_count as f64) as i32;
+
+        renderer.set_render_color(Color::rgb(24, 24, 32));
+        renderer.fill_rect(bar);
+        renderer.set_render_color(Color::rgb(200, 160, 60));
+        renderer.fill_rect(Rect::of(bar.x, bar.y, filled, bar.h));
+        renderer.set_render_color(Color::rgb(90, 90, 100));
+        for index in 1..stage_count {
+            let x = bar.x + index * bar_width / stage_count;
+            renderer.render_line(Vector2::new(x, bar.y), Vector2::new(x, bar.y + bar.h - 1));
+        }
+        renderer.set_render_color(Color::rgb(230, 230, 230));
+        renderer.render_rect(bar);
+        renderer.present();
+    }
+
+    /// Keeps the window responsive, noting whether it's been asked to close
+    fn pump(&mut self) {
+        let mut media = self.media.borrow_mut();
+        media.update();
+        if !media.is_open() || media.key_states().key_state(Key::Escape) == KeyState::TransitionDown {
+            self.cancel_requested = true;
+        }
+    }
+}
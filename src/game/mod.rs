@@ -19,8 +19,30 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod ambient_player;
+mod autosave;
+mod content_gate;
+mod cursors;
+mod data_patches;
+mod data_watcher;
+mod frame_pacer;
 mod game;
+mod loader;
+mod loading_screen;
+mod music_player;
 mod state;
+mod voice_player;
 
+pub use self::ambient_player::AmbientPlayer;
+pub use self::autosave::{AutoSaver, Save, Session, offer_to_restore, read_save_state, save_dir, write_save};
+pub use self::content_gate::ContentGate;
+pub use self::cursors::Cursors;
+pub use self::data_patches::{DATA_PATCH_DIR, DataPatches, apply_data_patches, data_patch_files};
+pub use self::data_watcher::DataWatcher;
+pub use self::frame_pacer::FramePacer;
 pub use self::game::Game;
-pub use self::state::{GameState, ScenarioGameState};
+pub use self::loader::{LoadProgress, LoadUpdate, Loader, StageEvent};
+pub use self::loading_screen::{LOAD_STAGES, LoadStage, LoadingScreen};
+pub use self::music_player::MusicPlayer;
+pub use self::state::{EditorGameState, GameState, ScenarioGameState};
+pub use self::voice_player::VoicePlayer;
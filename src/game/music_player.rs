@@ -0,0 +1,274 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{CombatEvent, CombatLog};
+use identifier::PlayerId;
+use media::{Audio, AudioChannel, Media, PlaybackId, Sound};
+use resource::{GameDir, ModPackage};
+use std::ascii::AsciiExt;
+use std::cmp;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const MUSIC_DIR: &'static str = "music";
+
+/// Tracks whose file names start with this are played during fights
+const BATTLE_TRACK_PREFIX: &'static str = "battle";
+
+/// The intensity is about how many hits there have been over this many seconds
+const INTENSITY_SECONDS: f32 = 5.0;
+
+/// A fight starts at this intensity, and it has to drop below the lower one to be over, so that
+/// a lull of a hit or two doesn't keep switching the music back and forth
+const BATTLE_START_INTENSITY: f32 = 6.0;
+const BATTLE_END_INTENSITY: f32 = 2.0;
+
+/// The least a fight lasts for, once it's started
+const MIN_BATTLE_SECONDS: f32 = 20.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Mood {
+    Peaceful,
+    Battle,
+}
+
+/// How much fighting the player's units are in
+struct CombatIntensity {
+    intensity: f32,
+    battle: bool,
+    battle_seconds: f32,
+}
+
+impl CombatIntensity {
+    fn new() -> CombatIntensity {
+        CombatIntensity {
+            intensity: 0.0,
+            battle: false,
+            battle_seconds: 0.0,
+        }
+    }
+
+    /// Adds the hits since the last update, and tells whether there's a fight going on
+    fn update(&mut self, hits: usize, seconds: f32) -> bool {
+        self.intensity = self.intensity * (-seconds / INTENSITY_SECONDS).exp() + hits as f32;
+        if self.battle {
+            self.battle_seconds += seconds;
+            if self.intensity < BATTLE_END_INTENSITY && self.battle_seconds >= MIN_BATTLE_SECONDS {
+                self.battle = false;
+            }
+        } else if self.intensity >= BATTLE_START_INTENSITY {
+            self.battle = true;
+            self.battle_seconds = 0.0;
+        }
+        self.battle
+    }
+}
+
+/// Plays the music tracks one after another, changing to the battle tracks while the local
+/// player's units are fighting another player's
+pub struct MusicPlayer {
+    peaceful_tracks: Vec<PathBuf>,
+    battle_tracks: Vec<PathBuf>,
+    next_peaceful: usize,
+    next_battle: usize,
+    battle_music: bool,
+    intensity: CombatIntensity,
+    events_seen: usize,
+    /// The track that's playing, and the mood it was picked for
+    playing: Option<(PlaybackId, Mood)>,
+}
+
+impl MusicPlayer {
+    /// Finds the tracks in the game data's music directory and in the mods'
+    pub fn new(game_dir: &GameDir, mod_packages: &[ModPackage]) -> MusicPlayer {
+        let mut music_dirs: Vec<PathBuf> = game_dir.find_file(MUSIC_DIR).into_iter().collect();
+        music_dirs.extend(mod_packages.iter().map(|package| package.dir.join(MUSIC_DIR)));
+
+        let (mut peaceful_tracks, mut battle_tracks) = (Vec::new(), Vec::new());
+        for track in music_dirs.iter().flat_map(|dir| find_tracks(dir)) {
+            if is_battle_track(&track) {
+                battle_tracks.push(track);
+            } else {
+                peaceful_tracks.push(track);
+            }
+        }
+        MusicPlayer {
+            peaceful_tracks: peaceful_tracks,
+            battle_tracks: battle_tracks,
+            next_peaceful: 0,
+            next_battle: 0,
+            battle_music: true,
+            intensity: CombatIntensity::new(),
+            events_seen: 0,
+            playing: None,
+        }
+    }
+
+    /// Whether to change to the battle tracks during fights
+    pub fn set_battle_music(&mut self, battle_music: bool) {
+        self.battle_music = battle_music;
+    }
+
+    /// Called every tick, with its length in seconds
+    pub fn update(&mut self,
+                  combat_log: &CombatLog,
+                  local_player_id: PlayerId,
+                  seconds: f32,
+                  media: &mut Media) {
+        let new_events = cmp::min(combat_log.recorded() - self.events_seen, combat_log.events().len());
+        self.events_seen = combat_log.recorded();
+        let events = combat_log.events();
+        let hits = events.iter()
+            .skip(events.len() - new_events)
+            .filter(|event| is_players_fight(event, local_player_id))
+            .count();
+        let battle = self.intensity.update(hits, seconds);
+        let mood = if battle && self.battle_music && !self.battle_tracks.is_empty() {
+            Mood::Battle
+        } else {
+            Mood::Peaceful
+        };
+
+        let audio = media.audio();
+        match self.playing {
+            Some((playback, playing_mood)) if playing_mood == mood && audio.is_playing(playback) => return,
+            Some((playback, _)) => audio.stop(playback),
+            None => {}
+        }
+        self.playing = None;
+
+        // Tracks that can't be played are dropped, so they're only warned about once
+        loop {
+            let (tracks, next) = match mood {
+                Mood::Peaceful => (&mut self.peaceful_tracks, &mut self.next_peaceful),
+                Mood::Battle => (&mut self.battle_tracks, &mut self.next_battle),
+            };
+            if tracks.is_empty() {
+                return;
+            }
+            let index = *next % tracks.len();
+            match load_track(&tracks[index], audio) {
+                Ok(sound) => {
+                    *next = index + 1;
+                    self.playing = Some((audio.play(&sound, AudioChannel::Music, 1.0, 0.0, false), mood));
+                    return;
+                }
+                Err(err) => {
+                    warn!("Skipping music track {}: {}", tracks[index].display(), err);
+                    tracks.remove(index);
+                }
+            }
+        }
+    }
+
+    pub fn stop(&mut self, audio: &mut Audio) {
+        if let Some((playback, _)) = self.playing.take() {
+            audio.stop(playback);
+        }
+    }
+}
+
+/// A hit between the local player's units and another player's; fights with the wildlife don't
+/// count
+fn is_players_fight(event: &CombatEvent, local_player_id: PlayerId) -> bool {
+    !event.attacker_player_id.is_gaia() && !event.target_player_id.is_gaia() &&
+    (event.attacker_player_id == local_player_id || event.target_player_id == local_player_id)
+}
+
+fn is_battle_track(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| {
+        let name = name.to_string_lossy().to_ascii_lowercase();
+        name.starts_with(BATTLE_TRACK_PREFIX)
+    })
+}
+
+/// The WAV files in the directory, in order by name
+fn find_tracks(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut tracks: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension.to_string_lossy().eq_ignore_ascii_case("wav"))
+        })
+        .collect();
+    tracks.sort();
+    tracks
+}
+
+fn load_track(path: &Path, audio: &Audio) -> Result<Sound, String> {
+    let mut contents = Vec::new();
+    try!(File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .map_err(|err| err.to_string()));
+    audio.decode_wav(&contents).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::{CombatEvent, ElevationModifier};
+    use std::path::Path;
+    use super::{CombatIntensity, is_battle_track, is_players_fight};
+
+    fn event(attacker_player_id: u8, target_player_id: u8) -> CombatEvent {
+        CombatEvent {
+            attacker_player_id: (attacker_player_id as usize).into(),
+            target: 0,
+            target_player_id: (target_player_id as usize).into(),
+            damage: 1,
+            elevation_modifier: ElevationModifier::Level,
+            destroyed: false,
+        }
+    }
+
+    #[test]
+    fn test_battle_starts_and_ends_with_a_margin() {
+        let mut intensity = CombatIntensity::new();
+        assert!(!intensity.update(4, 1.0));
+        assert!(intensity.update(4, 1.0));
+
+        // It's only over once it's both died down and gone on for long enough
+        assert!(intensity.update(0, 5.0));
+        assert!(intensity.update(0, 10.0));
+        assert!(!intensity.update(0, 10.0));
+    }
+
+    #[test]
+    fn test_wildlife_fights_dont_count() {
+        let local_player_id = 1usize.into();
+        assert!(is_players_fight(&event(1, 2), local_player_id));
+        assert!(is_players_fight(&event(3, 1), local_player_id));
+        assert!(!is_players_fight(&event(2, 3), local_player_id));
+        assert!(!is_players_fight(&event(1, 0), local_player_id));
+        assert!(!is_players_fight(&event(0, 1), local_player_id));
+    }
+
+    #[test]
+    fn test_battle_tracks_go_by_name() {
+        assert!(is_battle_track(Path::new("mods/music/Battle2.wav")));
+        assert!(!is_battle_track(Path::new("music/peace.wav")));
+        assert!(!is_battle_track(Path::new("battle/march.wav")));
+    }
+}
@@ -0,0 +1,619 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::{Config, ConfigValue, KeyBindings};
+use dat::EmpiresDbRef;
+use ecs;
+use ecs::resource::{Cheats, GameSettings, KeyboardKeyStates, MouseCursor, MouseState, OptionChanges,
+                    Players, RenderCommands, Terrain, TextInput, TickArena, TouchInput, UiLayout,
+                    ViewProjector, Viewport};
+use editor::{EditorChange, EditorCommand, EditorTool, EmpiresEditorData, ScenarioEditor, Severity,
+             TriggerProblem};
+use game::{Cursors, Game, GameState};
+use identifier::CivilizationId;
+use media::{GamepadState, Key, KeyState, MediaRef, MouseButton};
+use nalgebra::{Vector2, convert};
+use resource::{ChunkCache, RenderCommand, ShapeManagerRef, ShapeMetadataStoreRef};
+use specs;
+use super::trigger_form::TriggerForm;
+use types::{Color, Fixed, Rect, Vector3};
+use ui::{Form, ListItem, NavKey, UiEvent, UiInput, Widget, text_width};
+
+const COMMAND_BOX_LAYER: u16 = 2000;
+const PLACEMENT_GHOST_LAYER: u16 = 1500;
+const COMMAND_BOX_HEIGHT: i32 = 16;
+const COMMAND_BOX_MARGIN: i32 = 40;
+const PADDING: i32 = 3;
+const MAX_COMMAND_LENGTH: usize = 120;
+const TRIGGER_PANEL_MARGIN: i32 = 10;
+const TRIGGER_ROW_HEIGHT: i32 = 14;
+const TRIGGER_STATUS_WIDTH: i32 = 8;
+/// How much of each trigger's text the panel has room for
+const TRIGGER_PANEL_GLYPHS: usize = 60;
+
+/// Edits a scenario on a live preview of its map. The left mouse button uses the current
+/// tool, the right one deletes the unit under the cursor, R turns it, and Ctrl+Z and Ctrl+Y
+/// undo and redo. Everything else is typed into the command box, opened with Return; see
+/// `EditorCommand` for what it takes. Tab opens the trigger panel, which lists the triggers
+/// marked by what the validation found in them, over a `TriggerForm` for writing one, with
+/// the condition and effect picked from dropdowns of the game's units, resources, and
+/// research. Clicking a trigger, or picking it
+/// with the arrow keys and Return, fills the form in with it, ready to be edited. With a
+/// building picked for the unit tool, the tiles it would cover are outlined under the cursor,
+/// red where it can't be placed. The command box and the panel are written in the pixel font
+/// from `util::glyphs`, and the results of commands go to the log.
+pub struct EditorGameState {
+    media: MediaRef,
+    empires: EmpiresDbRef,
+    editor_data: EmpiresEditorData,
+    shape_manager: ShapeManagerRef,
+    chunk_cache: ChunkCache,
+    cursors: Cursors,
+    editor: ScenarioEditor,
+    planner: ecs::WorldPlanner,
+    presentation_lane: ecs::system::PresentationLane,
+    command_box: Form,
+    command_box_open: bool,
+    /// The theme the command box and trigger panel are drawn in
+    ui_layout: UiLayout,
+    /// The tile the current drag last painted, so holding the button doesn't repaint it; None
+    /// when there's no drag going on
+    last_painted_tile: Option<(i32, i32)>,
+    trigger_panel: Form,
+    trigger_form: TriggerForm,
+    trigger_panel_open: bool,
+    /// What the validation found in the triggers, kept up to date while the panel is open
+    trigger_problems: Vec<TriggerProblem>,
+    /// The trigger the form was filled in with, which it replaces when it's submitted; None when
+    /// it adds a new one
+    editing_trigger: Option<usize>,
+}
+
+impl EditorGameState {
+    pub fn new(g: &Game, editor: ScenarioEditor) -> EditorGameState {
+        let (planner, presentation_lane) = create_preview(g.media(),
+                                                          g.empires_db(),
+                                                          g.shape_metadata(),
+                                                          &editor);
+        let mut state = EditorGameState {
+            media: g.media(),
+            empires: g.empires_db(),
+            editor_data: EmpiresEditorData::new(g.empires_db(), g.content_gate().clone()),
+            shape_manager: g.shape_manager(),
+            chunk_cache: ChunkCache::new(),
+            cursors: Cursors::new(),
+            editor: editor,
+            planner: planner,
+            presentation_lane: presentation_lane,
+            command_box: command_box(),
+            command_box_open: false,
+            ui_layout: UiLayout::new(),
+            last_painted_tile: None,
+            trigger_panel: trigger_panel(),
+            trigger_form: TriggerForm::new(),
+            trigger_panel_open: false,
+            trigger_problems: Vec::new(),
+            editing_trigger: None,
+        };
+        state.apply_config(g.config());
+        state
+    }
+
+    /// Brings the preview up to date with an edit
+    fn refresh_preview(&mut self, change: EditorChange) {
+        if change != EditorChange::Nothing && self.trigger_panel_open {
+            self.trigger_problems = self.editor.check_triggers(&self.editor_data);
+        }
+        if change == EditorChange::Settings {
+            // The trigger being edited may have been moved or removed
+            self.editing_trigger = None;
+        }
+        match change {
+            EditorChange::Nothing | EditorChange::Settings => {}
+            EditorChange::Map => {
+                self.planner.mut_world().write_resource::<Terrain>().update_from(&self.editor.scenario().map);
+            }
+            EditorChange::Units => {
+                ecs::reload_scenario_units(self.planner.mut_world(), &self.empires, self.editor.scenario());
+            }
+        }
+    }
+
+    /// Runs an editor command, returning whether it worked
+    fn run_command(&mut self, text: &str) -> bool {
+        let result = {
+            let (editor, data) = (&mut self.editor, &self.editor_data);
+            EditorCommand::parse(text).and_then(|command| command.apply(editor, data))
+        };
+        match result {
+            Ok((change, message)) => {
+                for line in message.lines() {
+                    info!("Editor: {}", line);
+                }
+                self.refresh_preview(change);
+                true
+            }
+            Err(err) => {
+                for line in err.lines() {
+                    warn!("Editor: {}", line);
+                }
+                false
+            }
+        }
+    }
+
+    /// Tab opens and closes the trigger panel while nothing in it has the focus, and moves
+    /// between its widgets otherwise; Escape hands the focus back. Returns whether it opened or
+    /// closed the panel.
+    fn update_trigger_panel_key(&mut self) -> bool {
+        if self.trigger_panel.focused().is_some() ||
+           self.media.borrow().key_states().key_state(Key::Tab) != KeyState::TransitionDown {
+            return false;
+        }
+        self.trigger_panel_open = !self.trigger_panel_open;
+        if self.trigger_panel_open {
+            self.trigger_problems = self.editor.check_triggers(&self.editor_data);
+        } else {
+            self.trigger_panel.clear_focus();
+        }
+        true
+    }
+
+    /// Keeps the trigger panel's list up to date and works it, returning whether it has the
+    /// keyboard
+    fn update_trigger_panel(&mut self, input: &UiInput, viewport_size: &Vector2<i32>) -> bool {
+        if !self.trigger_panel_open {
+            return false;
+        }
+        let items = self.editor
+            .triggers()
+            .iter()
+            .enumerate()
+            .map(|(index, trigger)| {
+                ListItem {
+                    text: trigger.to_text(),
+                    color: match self.trigger_severity(index) {
+                        None => Color::rgb(60, 160, 60),
+                        Some(Severity::Warning) => Color::rgb(210, 170, 40),
+                        Some(Severity::Error) => Color::rgb(200, 50, 50),
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        self.layout_trigger_panel(items.len(), viewport_size);
+        self.trigger_panel.list_mut("triggers").unwrap().set_items(items);
+
+        for event in self.trigger_panel.update(input) {
+            match event {
+                UiEvent::Activated(_, index) => self.fill_trigger_form(index),
+                UiEvent::Submitted(id, text) => {
+                    // Return empties the field, but what's in it stays until the trigger's written
+                    self.trigger_panel.text_field_mut(id).unwrap().set_text(&text);
+                    let editing = self.editing_trigger;
+                    self.submit_trigger_form(editing);
+                }
+                UiEvent::Pressed("add") => self.submit_trigger_form(None),
+                UiEvent::Pressed("replace") => {
+                    let editing = self.editing_trigger;
+                    self.submit_trigger_form(editing);
+                }
+                UiEvent::Cancelled(_) => self.trigger_panel.clear_focus(),
+                _ => {}
+            }
+        }
+        let civilization_id = self.civilization_id();
+        self.trigger_form.refresh(&mut self.trigger_panel, &self.editor_data, civilization_id);
+        self.trigger_panel.focused().is_some()
+    }
+
+    /// Puts the list at the top of the panel, stopping short of the command box and scrolling if
+    /// there are more triggers than fit, with the form under it
+    fn layout_trigger_panel(&mut self, trigger_count: usize, viewport_size: &Vector2<i32>) {
+        let form_height = TriggerForm::height(COMMAND_BOX_HEIGHT);
+        let room = viewport_size.y - TRIGGER_PANEL_MARGIN * 2 - COMMAND_BOX_MARGIN - COMMAND_BOX_HEIGHT -
+                   form_height;
+        let rows = (trigger_count.max(1) as i32).min((room / TRIGGER_ROW_HEIGHT).max(1));
+        let (x, width) = (TRIGGER_PANEL_MARGIN, trigger_panel_width());
+        let list = Rect::of(x, TRIGGER_PANEL_MARGIN, width, rows * TRIGGER_ROW_HEIGHT);
+
+        self.trigger_panel.widget_mut("triggers").unwrap().rect = list;
+        TriggerForm::layout(&mut self.trigger_panel,
+                            x,
+                            list.y + list.h + PADDING,
+                            width,
+                            COMMAND_BOX_HEIGHT);
+        self.trigger_panel.widget_mut("replace").unwrap().enabled = self.editing_trigger.is_some();
+    }
+
+    /// Fills the form in with a trigger, so that it can be changed and put back in its place
+    fn fill_trigger_form(&mut self, index: usize) {
+        let civilization_id = self.civilization_id();
+        self.trigger_form.fill(&mut self.trigger_panel,
+                               &self.editor_data,
+                               civilization_id,
+                               &self.editor.triggers()[index]);
+        self.editing_trigger = Some(index);
+        self.trigger_panel.set_focus("condition");
+    }
+
+    /// Adds the trigger the form describes, or puts it in place of the given one. The form is
+    /// emptied once the trigger's been written; when it doesn't parse, the fields are left as
+    /// they are to be fixed.
+    fn submit_trigger_form(&mut self, replacing: Option<usize>) {
+        let text = match self.trigger_form.text(&self.trigger_panel) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Editor: {}", err);
+                return;
+            }
+        };
+        let command = match replacing {
+            Some(index) => format!("edittrigger {} {}", index, text),
+            None => format!("trigger {}", text),
+        };
+        if self.run_command(&command) {
+            TriggerForm::clear(&mut self.trigger_panel);
+            self.editing_trigger = None;
+        }
+    }
+
+    /// The civilization of the player being edited, whose units the trigger form lists
+    fn civilization_id(&self) -> CivilizationId {
+        self.editor.scenario().player_civilization_id(self.editor.player_id())
+    }
+
+    /// The worst problem validation found in a trigger, if any
+    fn trigger_severity(&self, index: usize) -> Option<Severity> {
+        self.trigger_problems
+            .iter()
+            .filter(|problem| problem.trigger_index == index)
+            .map(|problem| problem.severity)
+            .max()
+    }
+
+    /// Works the command box and the trigger panel, returning whether they have the keyboard.
+    /// While the command box is open, it takes all of the keys, though the panel can still be
+    /// clicked.
+    fn update_ui(&mut self) -> bool {
+        let (mut input, viewport_size) = {
+            let media = self.media.borrow();
+            let size = media.viewport_size();
+            (UiInput::from_media(&**media), Vector2::new(size.x as i32, size.y as i32))
+        };
+        let box_width = (PADDING * 2 + text_width(MAX_COMMAND_LENGTH)).min(viewport_size.x);
+        self.command_box.widget_mut("command").unwrap().rect =
+            Rect::of((viewport_size.x - box_width) / 2,
+                     viewport_size.y - COMMAND_BOX_MARGIN - COMMAND_BOX_HEIGHT,
+                     box_width,
+                     COMMAND_BOX_HEIGHT);
+
+        if self.command_box_open {
+            self.command_box.set_focus("command");
+            for event in self.command_box.update(&input) {
+                match event {
+                    UiEvent::Submitted(_, text) => {
+                        self.command_box_open = false;
+                        if !text.trim().is_empty() {
+                            self.run_command(&text);
+                        }
+                    }
+                    UiEvent::Cancelled(_) => self.command_box_open = false,
+                    _ => {}
+                }
+            }
+            input.keys.clear();
+            input.text.clear();
+            input.composition.clear();
+            self.update_trigger_panel(&input, &viewport_size);
+            return true;
+        }
+
+        self.update_undo_keys();
+        if self.update_trigger_panel_key() {
+            // The Tab that opened the panel doesn't also focus its first widget
+            input.keys.retain(|&key| key != NavKey::Tab);
+        }
+        let browsing = self.update_trigger_panel(&input, &viewport_size);
+        if !self.command_box_open && !browsing && input.pressed(NavKey::Return) {
+            self.open_command_box("");
+        }
+        browsing || self.command_box_open
+    }
+
+    fn open_command_box(&mut self, text: &str) {
+        self.command_box.text_field_mut("command").unwrap().set_text(text);
+        self.command_box_open = true;
+    }
+
+    /// Whether the command box or the trigger panel is under the given screen position
+    fn ui_at(&self, position: &Vector2<i32>) -> bool {
+        (self.command_box_open && self.command_box.widget_at(position).is_some()) ||
+        (self.trigger_panel_open && self.trigger_panel.widget_at(position).is_some())
+    }
+
+    /// Uses the tools on the map under the mouse cursor
+    fn update_mouse_tools(&mut self, keyboard_free: bool) {
+        let (left, right, rotate) = {
+            let media = self.media.borrow();
+            (media.mouse_button_states().key_state(MouseButton::Left),
+             media.mouse_button_states().key_state(MouseButton::Right),
+             keyboard_free && media.key_states().key_state(Key::R) == KeyState::TransitionDown)
+        };
+        if !left.is_down() && self.last_painted_tile.take().is_some() {
+            self.editor.end_stroke();
+        }
+        if !left.is_down() && right != KeyState::TransitionDown && !rotate {
+            return;
+        }
+        if left == KeyState::TransitionDown {
+            let mouse_position = self.media.borrow().mouse_position();
+            if self.ui_at(&mouse_position) {
+                return;
+            }
+        }
+
+        let position = self.mouse_world_position();
+        let (x, y): (f32, f32) = (position.x.into(), position.y.into());
+        let tile = (x.floor() as i32, y.floor() as i32);
+        let change = if right == KeyState::TransitionDown {
+            self.editor.delete_unit_near(x, y)
+        } else if rotate {
+            self.editor.rotate_unit_near(x, y)
+        } else if left == KeyState::TransitionDown {
+            self.editor.begin_stroke();
+            self.last_painted_tile = Some(tile);
+            self.editor.apply_tool(x, y, &self.editor_data)
+        } else if self.paints_while_held() && self.last_painted_tile != Some(tile) {
+            self.last_painted_tile = Some(tile);
+            self.editor.apply_tool(x, y, &self.editor_data)
+        } else {
+            EditorChange::Nothing
+        };
+        self.refresh_preview(change);
+    }
+
+    /// Terrain and elevation get painted along a drag; the other tools only work per click
+    fn paints_while_held(&self) -> bool {
+        use editor::EditorTool::*;
+        match self.editor.tool() {
+            Terrain(_) | Elevation(_) => true,
+            Fill(_) | Unit(_) | Erase | Paste => false,
+        }
+    }
+
+    /// Ctrl+Z undoes the last edit, and Ctrl+Y redoes it
+    fn update_undo_keys(&mut self) {
+        let (undo, redo) = {
+            let media = self.media.borrow();
+            let key_states = media.key_states();
+            let ctrl = key_states.key_state(Key::Ctrl).is_down();
+            (ctrl && key_states.key_state(Key::Z) == KeyState::TransitionDown,
+             ctrl && key_states.key_state(Key::Y) == KeyState::TransitionDown)
+        };
+        let change = if undo {
+            self.editor.undo()
+        } else if redo {
+            self.editor.redo()
+        } else {
+            return;
+        };
+        self.refresh_preview(change.unwrap_or(EditorChange::Nothing));
+    }
+
+    fn mouse_world_position(&mut self) -> Vector3 {
+        let mouse_position = self.media.borrow().mouse_position();
+        let world = self.planner.mut_world();
+        let viewport = world.read_resource::<Viewport>();
+        let view_projector = world.read_resource::<ViewProjector>();
+        let terrain = world.read_resource::<Terrain>();
+        view_projector.unproject(&(mouse_position + viewport.top_left_i32()), &*terrain)
+    }
+
+    fn update_input_resources(&mut self, keyboard_free: bool) {
+        let world = self.planner.mut_world();
+        let (mut keys, mut mouse_state, mut text_input, mut viewport, mut gamepad, mut touch_input) = {
+            (world.write_resource::<KeyboardKeyStates>(),
+             world.write_resource::<MouseState>(),
+             world.write_resource::<TextInput>(),
+             world.write_resource::<Viewport>(),
+             world.write_resource::<GamepadState>(),
+             world.write_resource::<TouchInput>())
+        };
+
+        let media = self.media.borrow();
+        *keys = if keyboard_free {
+            media.key_states().clone()
+        } else {
+            KeyboardKeyStates::new(Default::default())
+        };
+        (*text_input).text = String::new();
+        (*mouse_state).position = media.mouse_position().clone();
+        (*mouse_state).key_states = media.mouse_button_states().clone();
+        *gamepad = media.gamepad().clone();
+        (*touch_input).touches = media.touches().to_vec();
+        // The window can be resized, and the HUD is laid out against its edges
+        let viewport_size = media.viewport_size();
+        viewport.size = Vector2::new(viewport_size.x as i32, viewport_size.y as i32);
+    }
+
+    fn update_viewport(&mut self, lerp: Fixed) {
+        let viewport = self.planner.mut_world().read_resource::<Viewport>();
+        let top_left: Vector2<i32> = convert(viewport.lerped_top_left(lerp));
+        self.media.borrow_mut().renderer().set_camera_position(&top_left);
+    }
+
+    /// Outlines the tiles a building would cover under the cursor while the unit tool is picked,
+    /// in green where the ground allows it and red where it doesn't
+    fn render_placement_ghost(&mut self) {
+        let unit_id = match self.editor.tool() {
+            EditorTool::Unit(unit_id) => unit_id,
+            _ => return,
+        };
+        let position = self.mouse_world_position();
+        let placement = match self.editor.check_placement(&self.editor_data,
+                                                          unit_id,
+                                                          position.x.into(),
+                                                          position.y.into()) {
+            Some(placement) => placement,
+            None => return,
+        };
+
+        let world = self.planner.mut_world();
+        let view_projector = world.read_resource::<ViewProjector>();
+        let mut render_commands = world.write_resource::<RenderCommands>();
+        let blocked_by_side_terrain = !placement.has_side_terrain;
+        for tile in &placement.tiles {
+            let color = if tile.allowed && !blocked_by_side_terrain {
+                Color::rgb(60, 220, 60)
+            } else {
+                Color::rgb(220, 50, 50)
+            };
+            let corner = |dx: i32, dy: i32| {
+                view_projector.project(&Vector3::new((tile.x + dx).into(), (tile.y + dy).into(), position.z))
+            };
+            let corners = [corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)];
+            for index in 0..corners.len() {
+                render_commands.push(RenderCommand::new_line(PLACEMENT_GHOST_LAYER,
+                                                             0,
+                                                             color,
+                                                             corners[index],
+                                                             corners[(index + 1) % corners.len()]));
+            }
+        }
+    }
+
+    fn render_trigger_panel(&mut self, lerp: Fixed) {
+        if self.trigger_panel_open {
+            render_form(&self.trigger_panel, self.planner.mut_world(), &self.ui_layout, lerp);
+        }
+    }
+
+    /// Draws the command box while it's open, in the same style as the chat box
+    fn render_command_box(&mut self, lerp: Fixed) {
+        if self.command_box_open {
+            render_form(&self.command_box, self.planner.mut_world(), &self.ui_layout, lerp);
+        }
+    }
+}
+
+fn command_box() -> Form {
+    let mut form = Form::new();
+    form.add(Widget::text_input("command", Rect::new(), MAX_COMMAND_LENGTH));
+    form
+}
+
+/// A list of the triggers, each led by a block coloured by what the validation found in it,
+/// and the form for writing one
+fn trigger_panel() -> Form {
+    let mut form = Form::new();
+    form.add(Widget::list("triggers", Rect::new(), TRIGGER_ROW_HEIGHT));
+    TriggerForm::add_widgets(&mut form, TRIGGER_ROW_HEIGHT);
+    form
+}
+
+fn render_form(form: &Form, world: &specs::World, layout: &UiLayout, lerp: Fixed) {
+    let viewport = world.read_resource::<Viewport>();
+    let mut render_commands = world.write_resource::<RenderCommands>();
+    form.render(&mut render_commands, layout, COMMAND_BOX_LAYER, &viewport.lerped_top_left(lerp));
+}
+
+/// Room for the status blocks, the text, and the scroll bar
+fn trigger_panel_width() -> i32 {
+    PADDING * 4 + TRIGGER_STATUS_WIDTH + text_width(TRIGGER_PANEL_GLYPHS)
+}
+
+/// Builds a world from the scenario being edited, with the whole map revealed
+fn create_preview(media: MediaRef,
+                  empires: EmpiresDbRef,
+                  shape_metadata: ShapeMetadataStoreRef,
+                  editor: &ScenarioEditor)
+                  -> (ecs::WorldPlanner, ecs::system::PresentationLane) {
+    let mut planner = ecs::create_world_planner(media,
+                                                empires.clone(),
+                                                shape_metadata.clone(),
+                                                editor.scenario(),
+                                                &GameSettings::new());
+    let presentation_lane = ecs::create_presentation_lane(&mut planner, empires, shape_metadata);
+    {
+        let world = planner.mut_world();
+        let local_player_id = world.read_resource::<Players>().local_player_id();
+        world.write_resource::<Cheats>().toggle_revealed_map(local_player_id);
+    }
+    (planner, presentation_lane)
+}
+
+impl GameState for EditorGameState {
+    fn start(&mut self) {
+        info!("Editing {}; press Return to type editor commands", self.editor.path().display());
+    }
+
+    fn stop(&mut self) {}
+
+    fn update(&mut self, time_step: Fixed) -> bool {
+        let keyboard_taken = self.update_ui();
+        let typing = self.command_box_open;
+        self.update_mouse_tools(!typing);
+        self.update_input_resources(!keyboard_taken);
+
+        {
+            let world = self.planner.mut_world();
+            let mut render_commands = world.write_resource::<RenderCommands>();
+            render_commands.clear_debug();
+        }
+
+        self.planner.dispatch((ecs::SystemGroup::Editor, time_step));
+        self.planner.wait();
+        self.presentation_lane.run(self.planner.mut_world(), time_step);
+        self.planner.mut_world().write_resource::<TickArena>().end_tick();
+
+        true
+    }
+
+    fn render(&mut self, lerp: Fixed) {
+        self.update_viewport(lerp);
+
+        self.planner.dispatch((ecs::SystemGroup::Render, lerp));
+        self.planner.wait();
+        self.render_placement_ghost();
+        self.render_trigger_panel(lerp);
+        self.render_command_box(lerp);
+
+        let world = self.planner.mut_world();
+        self.cursors.update(&mut *world.write_resource::<MouseCursor>(),
+                            &mut **self.media.borrow_mut(),
+                            &mut *self.shape_manager.borrow_mut());
+        let mut render_commands = world.write_resource::<RenderCommands>();
+        render_commands.execute(self.media.borrow_mut().renderer(),
+                                &mut *self.shape_manager.borrow_mut(),
+                                &mut self.chunk_cache);
+        render_commands.clear_rendered();
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        *self.planner.mut_world().write_resource::<KeyBindings>() = config.keys.clone();
+        self.cursors.set_hardware(config.video.hardware_cursor);
+        self.ui_layout = UiLayout::load(&config.video.ui_layout);
+    }
+
+    fn take_option_changes(&mut self) -> Vec<(String, ConfigValue)> {
+        self.planner.mut_world().write_resource::<OptionChanges>().consume_changes()
+    }
+}
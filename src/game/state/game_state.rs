@@ -19,6 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use config::{Config, ConfigValue};
 use types::Fixed;
 
 pub trait GameState {
@@ -35,4 +36,18 @@ pub trait GameState {
     /// occur between render calls, so an interpolator (lerp) is passed in
     /// so that smooth rendering can be achieved.
     fn render(&mut self, lerp: Fixed);
+
+    /// How many update cycles run per real-time update interval; the time step
+    /// passed to update stays the same regardless, only the tick rate changes
+    fn speed_multiplier(&mut self) -> Fixed {
+        1.into()
+    }
+
+    /// Called on startup and whenever settings that can change while running are changed
+    fn apply_config(&mut self, _config: &Config) {}
+
+    /// Options that were changed in game since the last call, as dotted setting names and values
+    fn take_option_changes(&mut self) -> Vec<(String, ConfigValue)> {
+        Vec::new()
+    }
 }
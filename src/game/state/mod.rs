@@ -19,8 +19,11 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod editor_game_state;
 mod game_state;
 mod scenario_game_state;
+mod trigger_form;
 
+pub use self::editor_game_state::EditorGameState;
 pub use self::game_state::GameState;
 pub use self::scenario_game_state::ScenarioGameState;
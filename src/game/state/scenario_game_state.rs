@@ -19,28 +19,126 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use config::{Config, ConfigValue, KeyBindings};
+use crash;
+use dat::EmpiresDbRef;
 use ecs;
-use ecs::resource::{KeyboardKeyStates, MouseState, RenderCommands, Viewport};
-use game::{Game, GameState};
-use media::MediaRef;
+use ecs::resource::{AmbientSounds, CombatLog, CommandLog, DebugOverlay, GameSettings, GameSpeed,
+                    KeyboardKeyStates, MouseCursor, MouseState, OptionChanges, PlayerColors, Players,
+                    RenderCommands, SceneTint, SelectionPriority, SimulationTick, Statistics, Terrain,
+                    TextInput, TickArena, TouchInput, UiLayout, UnitVoices, ViewProjector, Viewport};
+use game::{self, AmbientPlayer, AutoSaver, Cursors, Game, GameState, MusicPlayer, Session, VoicePlayer};
+use logging;
+use media::{GamepadState, MediaRef};
 use nalgebra::{Vector2, convert};
-use resource::ShapeManagerRef;
+use profile::{self, Profile};
+use resource::{ChunkCache, ShapeManagerRef, SoundManagerRef};
 use scn;
+use script::{ScenarioScript, ScriptEffect, ScriptView, apply_script_effects};
+use std::mem;
+use std::path::PathBuf;
 use types::Fixed;
 
 pub struct ScenarioGameState {
     media: MediaRef,
+    empires: EmpiresDbRef,
     shape_manager: ShapeManagerRef,
+    sound_manager: SoundManagerRef,
+    chunk_cache: ChunkCache,
+    cursors: Cursors,
+    voice_player: VoicePlayer,
+    ambient_player: AmbientPlayer,
+    music_player: MusicPlayer,
     planner: ecs::WorldPlanner,
+    presentation_lane: ecs::system::PresentationLane,
+    tick: usize,
+    snapshot_dictionary: Option<Vec<u8>>,
+    /// The crash report snapshot being compressed, a chunk per tick, and the tick it was taken on
+    pending_snapshot: Option<(usize, ecs::SnapshotEncoder)>,
+    /// The enabled mods' scripts, in load order, followed by the scenario's own
+    scripts: Vec<ScenarioScript>,
+    /// Scripts that failed, kept so that reloading them can bring them back
+    stopped_scripts: Vec<ScenarioScript>,
+    /// What the scenario is called in the profile's campaign progress
+    scenario_name: String,
+    profile_path: PathBuf,
+    recorded_profile: bool,
+    /// The scenario as it was started, which saves are written into
+    scenario: scn::Scenario,
+    save_dir: PathBuf,
+    auto_saver: AutoSaver,
+    /// For a game picked back up from a save, the rules each script had fired, by the path it
+    /// was loaded from; the scripts resume instead of starting over
+    resumed_scripts: Option<Vec<(String, Vec<String>)>>,
 }
 
 impl ScenarioGameState {
-    pub fn new(g: &Game, scenario: scn::Scenario) -> ScenarioGameState {
-        ScenarioGameState {
+    pub fn new(g: &Game,
+               scenario: scn::Scenario,
+               session: Session,
+               settings: GameSettings,
+               script: Option<ScenarioScript>,
+               save_state: Option<ecs::SaveState>)
+               -> ScenarioGameState {
+        let mut planner = ecs::create_world_planner(g.media(),
+                                                    g.empires_db(),
+                                                    g.shape_metadata(),
+                                                    &scenario,
+                                                    &settings);
+        let mut tick = 0;
+        let mut resumed_scripts = None;
+        if let Some(save_state) = save_state {
+            ecs::restore_save_state(planner.mut_world(), &g.empires_db(), &save_state);
+            // The saved tick had already run
+            tick = save_state.tick + 1;
+            resumed_scripts = Some(save_state.scripts);
+        }
+        let presentation_lane =
+            ecs::create_presentation_lane(&mut planner, g.empires_db(), g.shape_metadata());
+        let mut scripts = Vec::new();
+        for package in g.mod_packages() {
+            for script_path in package.script_paths() {
+                match ScenarioScript::load_file(&script_path) {
+                    Ok(script) => scripts.push(script),
+                    Err(err) => warn!("Skipping script from mod \"{}\": {}", package.id, err),
+                }
+            }
+        }
+        scripts.extend(script);
+
+        let save_dir = game::save_dir();
+        if let Err(err) = session.start(&save_dir) {
+            warn!("Auto-saving won't be able to pick this game back up: {}", err);
+        }
+
+        let mut state = ScenarioGameState {
             media: g.media(),
+            empires: g.empires_db(),
             shape_manager: g.shape_manager(),
-            planner: ecs::create_world_planner(g.media(), g.empires_db(), g.shape_metadata(), &scenario),
-        }
+            sound_manager: g.sound_manager(),
+            chunk_cache: ChunkCache::new(),
+            cursors: Cursors::new(),
+            voice_player: VoicePlayer::new(),
+            ambient_player: AmbientPlayer::new(),
+            music_player: MusicPlayer::new(g.game_dir(), g.mod_packages()),
+            planner: planner,
+            presentation_lane: presentation_lane,
+            tick: tick,
+            snapshot_dictionary: None,
+            pending_snapshot: None,
+            scripts: scripts,
+            stopped_scripts: Vec::new(),
+            scenario_name: profile::scenario_name(&session.scenario_path),
+            profile_path: g.profile_path().to_path_buf(),
+            recorded_profile: false,
+            scenario: scenario,
+            auto_saver: AutoSaver::new(save_dir.clone()),
+            save_dir: save_dir,
+            resumed_scripts: resumed_scripts,
+        };
+        *state.planner.mut_world().write_resource::<Profile>() = g.profile().clone();
+        state.apply_config(g.config());
+        state
     }
 
     fn update_viewport(&mut self, lerp: Fixed) {
@@ -49,25 +147,285 @@ impl ScenarioGameState {
         self.media.borrow_mut().renderer().set_camera_position(&top_left);
     }
 
+    /// Hands the tick's commands, and every so often a snapshot of the world, to crash reporting
+    fn update_crash_context(&mut self) {
+        let world = self.planner.mut_world();
+        let tick = world.read_resource::<SimulationTick>().get();
+        let commands = world.write_resource::<CommandLog>().consume_commands();
+        crash::record_tick(tick, commands);
+        if tick % crash::SNAPSHOT_INTERVAL_TICKS == 0 {
+            let snapshot = ecs::describe_world(world, tick);
+            if self.snapshot_dictionary.is_none() {
+                let dictionary = ecs::train_snapshot_dictionary(&snapshot);
+                crash::set_snapshot_dictionary(dictionary.clone());
+                self.snapshot_dictionary = Some(dictionary);
+            }
+            // An unfinished snapshot is abandoned for the newer one
+            let dictionary = self.snapshot_dictionary.as_ref().unwrap();
+            match ecs::SnapshotEncoder::new(snapshot, dictionary, ecs::SNAPSHOT_CHUNK_SIZE) {
+                Ok(encoder) => self.pending_snapshot = Some((tick, encoder)),
+                Err(err) => warn!("Failed to start compressing the crash report snapshot: {}", err),
+            }
+        }
+
+        let done = match self.pending_snapshot {
+            Some((_, ref mut encoder)) => encoder.step(),
+            None => return,
+        };
+        match done {
+            Ok(false) => {}
+            Ok(true) => {
+                let (snapshot_tick, encoder) = self.pending_snapshot.take().unwrap();
+                match encoder.finish() {
+                    Ok(compressed) => crash::set_snapshot(snapshot_tick, compressed),
+                    Err(err) => warn!("Failed to compress the crash report snapshot: {}", err),
+                }
+            }
+            Err(err) => {
+                warn!("Failed to compress the crash report snapshot: {}", err);
+                self.pending_snapshot = None;
+            }
+        }
+    }
+
+    /// Runs whatever part of the scripts is due this tick. A script that fails is turned off,
+    /// rather than failing again every tick.
+    fn update_scripts(&mut self) {
+        let (reload, tracing) = {
+            let mut debug_overlay = self.planner.mut_world().write_resource::<DebugOverlay>();
+            (mem::replace(&mut debug_overlay.reload_scripts, false), debug_overlay.trace_scripts)
+        };
+        if reload {
+            self.reload_scripts();
+        }
+
+        let tick = self.tick;
+        let mut index = 0;
+        while index < self.scripts.len() {
+            self.scripts[index].set_tracing(tracing);
+            let result = if self.scripts[index].runs_on_tick(tick) {
+                let view = ScriptView::from_world(self.planner.mut_world());
+                self.scripts[index].tick(tick, view)
+            } else {
+                Ok(Vec::new())
+            };
+            for trace in self.scripts[index].take_traces() {
+                info!("Script trace: {}", trace);
+            }
+            if self.apply_script_result(result) {
+                index += 1;
+            } else {
+                let script = self.scripts.remove(index);
+                self.stopped_scripts.push(script);
+            }
+        }
+    }
+
+    /// Loads every script from its file again, the stopped ones included; a script that
+    /// doesn't reload keeps running as it was
+    fn reload_scripts(&mut self) {
+        let stopped = mem::replace(&mut self.stopped_scripts, Vec::new());
+        let mut scripts = mem::replace(&mut self.scripts, Vec::new());
+        let was_running = scripts.len();
+        scripts.extend(stopped);
+        for (index, mut script) in scripts.into_iter().enumerate() {
+            let view = ScriptView::from_world(self.planner.mut_world());
+            let result = script.reload(view);
+            let name = script.path().map(|path| path.display().to_string()).unwrap_or("script".into());
+            let running = match result {
+                Ok(effects) => {
+                    info!("Reloaded {}", name);
+                    apply_script_effects(self.planner.mut_world(), &self.empires, effects);
+                    true
+                }
+                Err(err) => {
+                    warn!("Failed to reload {}: {}", name, err);
+                    index < was_running
+                }
+            };
+            if running {
+                self.scripts.push(script);
+            } else {
+                self.stopped_scripts.push(script);
+            }
+        }
+    }
+
+    /// Applies what a script asked for, returning false if the script failed
+    fn apply_script_result(&mut self, result: Result<Vec<ScriptEffect>, String>) -> bool {
+        match result {
+            Ok(effects) => {
+                apply_script_effects(self.planner.mut_world(), &self.empires, effects);
+                true
+            }
+            Err(err) => {
+                warn!("Scenario script failed and has been stopped: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Saves the game every so often, and keeps a save ready for crash reporting to write out if
+    /// the game crashes. Nothing's saved once the game is over.
+    fn update_autosave(&mut self) {
+        if self.recorded_profile {
+            return;
+        }
+        let autosave_path = self.auto_saver.tick();
+        if autosave_path.is_none() && !self.auto_saver.emergency_save_due() {
+            return;
+        }
+        let save = match self.encode_save() {
+            Ok(save) => save,
+            Err(err) => {
+                warn!("Failed to save the game: {}", err);
+                return;
+            }
+        };
+        if let Some(path) = autosave_path {
+            match game::write_save(&path, &save) {
+                Ok(()) => info!("Auto-saved to {}", path.display()),
+                Err(err) => warn!("{}", err),
+            }
+        }
+        crash::set_emergency_save(save.files(&self.auto_saver.emergency_path()));
+    }
+
+    /// Writes the game as a scenario that starts where the game is now, along with the state that
+    /// picks it up from there; see `ecs::SaveState` for what's kept
+    fn encode_save(&mut self) -> Result<game::Save, String> {
+        let mut state = ecs::save_to_scenario(self.planner.mut_world(), &mut self.scenario);
+        for script in self.scripts.iter().chain(self.stopped_scripts.iter()) {
+            if let Some(path) = script.path() {
+                state.scripts.push((path.to_string_lossy().into_owned(), script.fired_rules()));
+            }
+        }
+        let mut scenario = Vec::new();
+        try!(self.scenario.write_to_stream(&mut scenario).map_err(|err| err.to_string()));
+        Ok(game::Save {
+            scenario: scenario,
+            state: state.to_toml(),
+        })
+    }
+
+    /// Adds the game to the profile's lifetime statistics once it's over, and saves the profile.
+    /// Games that are quit before they're decided aren't counted. The session ends along with
+    /// the game, so it isn't offered to be restored.
+    fn update_profile(&mut self) {
+        if self.recorded_profile {
+            return;
+        }
+        let world = self.planner.mut_world();
+        let statistics = world.read_resource::<Statistics>();
+        if !statistics.game_over() {
+            return;
+        }
+        self.recorded_profile = true;
+
+        let local_player_id = world.read_resource::<Players>().local_player_id();
+        let won = !statistics.is_defeated(local_player_id);
+        let mut profile = world.write_resource::<Profile>();
+        profile.record_game(&self.scenario_name, won, &statistics.player(local_player_id));
+        match profile.save(&self.profile_path) {
+            Ok(()) => info!("Saved the profile to {}", self.profile_path.display()),
+            Err(err) => warn!("{}", err),
+        }
+
+        crash::set_emergency_save(Vec::new());
+        if let Err(err) = Session::end(&self.save_dir) {
+            warn!("{}", err);
+        }
+    }
+
+    /// Plays the units' answers, the sounds of the map around where the player is looking, and
+    /// the music to suit the game
+    fn update_audio(&mut self, time_step: Fixed) {
+        let world = self.planner.mut_world();
+        let listener = {
+            let viewport = world.read_resource::<Viewport>();
+            let center = viewport.top_left_i32() + Vector2::new(viewport.size.x / 2, viewport.size.y / 2);
+            world.read_resource::<ViewProjector>().unproject(&center, &*world.read_resource::<Terrain>())
+        };
+        let mut media = self.media.borrow_mut();
+        let mut sound_manager = self.sound_manager.borrow_mut();
+        self.voice_player.update(&mut *world.write_resource::<UnitVoices>(),
+                                 &mut **media,
+                                 &mut *sound_manager,
+                                 &self.empires);
+        self.ambient_player.update(&*world.read_resource::<AmbientSounds>(),
+                                   &listener,
+                                   &mut **media,
+                                   &mut *sound_manager,
+                                   &self.empires);
+        self.music_player.update(&*world.read_resource::<CombatLog>(),
+                                 world.read_resource::<Players>().local_player_id(),
+                                 time_step.into(),
+                                 &mut **media);
+    }
+
     fn update_input_resources(&mut self) {
         let world = self.planner.mut_world();
-        let (mut keys, mut mouse_state) = {
-            (world.write_resource::<KeyboardKeyStates>(), world.write_resource::<MouseState>())
+        let (mut keys, mut mouse_state, mut text_input, mut viewport, mut gamepad, mut touch_input) = {
+            (world.write_resource::<KeyboardKeyStates>(),
+             world.write_resource::<MouseState>(),
+             world.write_resource::<TextInput>(),
+             world.write_resource::<Viewport>(),
+             world.write_resource::<GamepadState>(),
+             world.write_resource::<TouchInput>())
         };
 
         let media = self.media.borrow();
         *keys = media.key_states().clone();
+        (*text_input).text = media.text_input().to_string();
         (*mouse_state).position = media.mouse_position().clone();
         (*mouse_state).key_states = media.mouse_button_states().clone();
+        *gamepad = media.gamepad().clone();
+        (*touch_input).touches = media.touches().to_vec();
+        // The window can be resized, and the HUD is laid out against its edges
+        let viewport_size = media.viewport_size();
+        viewport.size = Vector2::new(viewport_size.x as i32, viewport_size.y as i32);
     }
 }
 
 impl GameState for ScenarioGameState {
-    fn start(&mut self) {}
+    fn start(&mut self) {
+        let resumed_scripts = self.resumed_scripts.take();
+        let mut index = 0;
+        while index < self.scripts.len() {
+            let view = ScriptView::from_world(self.planner.mut_world());
+            let saved = resumed_scripts.as_ref().and_then(|resumed_scripts| {
+                let path = match self.scripts[index].path() {
+                    Some(path) => path.to_string_lossy().into_owned(),
+                    None => return None,
+                };
+                resumed_scripts.iter().find(|&&(ref saved_path, _)| *saved_path == path)
+            });
+            // Scripts the saved game didn't have, like a newly enabled mod's, start afresh
+            let result = match saved {
+                Some(&(_, ref fired)) => {
+                    self.scripts[index].resume(self.tick, fired, view).map(|()| Vec::new())
+                }
+                None => self.scripts[index].start(view),
+            };
+            if self.apply_script_result(result) {
+                index += 1;
+            } else {
+                let script = self.scripts.remove(index);
+                self.stopped_scripts.push(script);
+            }
+        }
+    }
 
-    fn stop(&mut self) {}
+    fn stop(&mut self) {
+        let mut media = self.media.borrow_mut();
+        self.ambient_player.stop(media.audio());
+        self.music_player.stop(media.audio());
+    }
 
     fn update(&mut self, time_step: Fixed) -> bool {
+        logging::set_tick(self.tick);
+        self.planner.mut_world().write_resource::<SimulationTick>().set(self.tick);
+        self.tick += 1;
         self.update_input_resources();
 
         {
@@ -78,6 +436,15 @@ impl GameState for ScenarioGameState {
 
         self.planner.dispatch((ecs::SystemGroup::Normal, time_step));
         self.planner.wait();
+        self.presentation_lane.run(self.planner.mut_world(), time_step);
+
+        self.update_scripts();
+        ecs::spawn_queued_units(self.planner.mut_world(), &self.empires);
+        self.update_crash_context();
+        self.update_autosave();
+        self.update_profile();
+        self.update_audio(time_step);
+        self.planner.mut_world().write_resource::<TickArena>().end_tick();
 
         true
     }
@@ -89,9 +456,34 @@ impl GameState for ScenarioGameState {
         self.planner.wait();
 
         let world = self.planner.mut_world();
+        self.cursors.update(&mut *world.write_resource::<MouseCursor>(),
+                            &mut **self.media.borrow_mut(),
+                            &mut *self.shape_manager.borrow_mut());
         let mut render_commands = world.write_resource::<RenderCommands>();
         render_commands.execute(self.media.borrow_mut().renderer(),
-                                &mut *self.shape_manager.borrow_mut());
+                                &mut *self.shape_manager.borrow_mut(),
+                                &mut self.chunk_cache);
         render_commands.clear_rendered();
     }
+
+    fn speed_multiplier(&mut self) -> Fixed {
+        self.planner.mut_world().read_resource::<GameSpeed>().multiplier()
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        let world = self.planner.mut_world();
+        *world.write_resource::<KeyBindings>() = config.keys.clone();
+        world.write_resource::<SceneTint>().configure(config.video.tint, config.video.day_length);
+        world.write_resource::<PlayerColors>()
+            .configure(config.video.player_colors, config.video.player_markers);
+        *world.write_resource::<UiLayout>() = UiLayout::load(&config.video.ui_layout);
+        world.write_resource::<SelectionPriority>().configure(&config.controls.selection_priority);
+        self.cursors.set_hardware(config.video.hardware_cursor);
+        self.music_player.set_battle_music(config.audio.battle_music);
+        self.auto_saver.configure(&config.autosave);
+    }
+
+    fn take_option_changes(&mut self) -> Vec<(String, ConfigValue)> {
+        self.planner.mut_world().write_resource::<OptionChanges>().consume_changes()
+    }
 }
@@ -0,0 +1,493 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::{Comparison, WorldQuery};
+use editor::{EditorData, EditorTrigger, TriggerEffect};
+use identifier::{CivilizationId, ResearchId, UnitId};
+use types::{Color, Rect};
+use ui::{Form, ListItem, Widget, WidgetKind, text_width};
+
+const PADDING: i32 = 3;
+const ROWS: i32 = 5;
+/// Room for the longest kind's name and the dropdown's arrow
+const KIND_GLYPHS: usize = 11;
+const PLAYER_GLYPHS: usize = 6;
+const COMPARISON_GLYPHS: usize = 3;
+const VALUE_GLYPHS: usize = 10;
+/// Room for the longest button label
+const BUTTON_GLYPHS: usize = 7;
+const MAX_ARGUMENT_LENGTH: usize = 100;
+
+const CONDITIONS: [&'static str; 3] = ["count", "stockpile", "researched"];
+const COMPARISONS: [Comparison; 5] =
+    [Comparison::Less, Comparison::AtMost, Comparison::Equal, Comparison::AtLeast, Comparison::Greater];
+const EFFECTS: [&'static str; 4] = ["message", "spawn", "give", "research"];
+const RESOURCES: [(&'static str, ResourceType); 4] = [("food", ResourceType::Food),
+                                                      ("wood", ResourceType::Wood),
+                                                      ("stone", ResourceType::Stone),
+                                                      ("gold", ResourceType::Gold)];
+
+/// The fields the form's text is emptied from once a trigger's written
+const TEXT_FIELDS: [&'static str; 5] =
+    ["condition_player", "area", "value", "effect_player", "effect_argument"];
+
+/// What an item of the subject dropdowns stands for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Subject {
+    AnyUnit,
+    Unit(UnitId),
+    Resource(&'static str),
+    Research(ResearchId),
+}
+
+/// The part of the editor's trigger panel that a trigger is written in. Each part of the
+/// condition and the effect has a field of its own: the kinds of condition and effect, the
+/// comparison, and the units, resources, and research they refer to are picked from dropdowns
+/// filled from the game data, while players, numbers, and text are typed. The widgets are kept
+/// in the panel's form, so that Tab moves between them and the trigger list.
+pub struct TriggerForm {
+    condition_subjects: Vec<Subject>,
+    effect_subjects: Vec<Subject>,
+    /// The kinds of condition and effect, and the civilization, that the subject dropdowns
+    /// were filled for
+    subjects_for: Option<(usize, usize, CivilizationId)>,
+}
+
+impl TriggerForm {
+    pub fn new() -> TriggerForm {
+        TriggerForm {
+            condition_subjects: Vec::new(),
+            effect_subjects: Vec::new(),
+            subjects_for: None,
+        }
+    }
+
+    /// Adds the widgets to the form; they're placed by `layout`
+    pub fn add_widgets(form: &mut Form, row_height: i32) {
+        form.add(Widget::dropdown("condition", Rect::new(), row_height));
+        form.add(Widget::text_input("condition_player", Rect::new(), PLAYER_GLYPHS).with_label("player"));
+        form.add(Widget::dropdown("condition_subject", Rect::new(), row_height));
+        form.add(Widget::text_input("area", Rect::new(), MAX_ARGUMENT_LENGTH).with_label("area x1 y1 x2 y2"));
+        form.add(Widget::dropdown("comparison", Rect::new(), row_height));
+        form.add(Widget::text_input("value", Rect::new(), VALUE_GLYPHS).with_label("value"));
+        form.add(Widget::dropdown("effect", Rect::new(), row_height));
+        form.add(Widget::text_input("effect_player", Rect::new(), PLAYER_GLYPHS).with_label("player"));
+        form.add(Widget::dropdown("effect_subject", Rect::new(), row_height));
+        form.add(Widget::text_input("effect_argument", Rect::new(), MAX_ARGUMENT_LENGTH));
+        form.add(Widget::button("add", Rect::new(), "Add"));
+        form.add(Widget::button("replace", Rect::new(), "Replace"));
+
+        set_items(form, "condition", CONDITIONS.iter().map(|name| name.to_string()).collect());
+        set_items(form,
+                  "comparison",
+                  COMPARISONS.iter().map(|comparison| comparison.operator().to_string()).collect());
+        set_items(form, "effect", EFFECTS.iter().map(|name| name.to_string()).collect());
+        for &id in &["condition", "comparison", "effect"] {
+            form.select(id, 0);
+        }
+    }
+
+    /// How tall the form is with rows of the given height
+    pub fn height(row_height: i32) -> i32 {
+        (row_height + PADDING) * ROWS
+    }
+
+    /// Places the widgets in rows from the top left
+    pub fn layout(form: &mut Form, x: i32, y: i32, width: i32, row_height: i32) {
+        let row = row_height + PADDING;
+        let field = |glyphs: usize| PADDING * 2 + text_width(glyphs);
+        let (kind_width, player_width) = (field(KIND_GLYPHS), field(PLAYER_GLYPHS));
+        let (comparison_width, value_width) = (field(COMPARISON_GLYPHS), field(VALUE_GLYPHS));
+        let subject_x = x + kind_width + player_width + PADDING * 2;
+        let subject_width = x + width - subject_x;
+        let area_width = width - comparison_width - value_width - PADDING * 2;
+        let button_width = field(BUTTON_GLYPHS);
+        let rects = [("condition", Rect::of(x, y, kind_width, row_height)),
+                     ("condition_player",
+                      Rect::of(x + kind_width + PADDING, y, player_width, row_height)),
+                     ("condition_subject", Rect::of(subject_x, y, subject_width, row_height)),
+                     ("area", Rect::of(x, y + row, area_width, row_height)),
+                     ("comparison",
+                      Rect::of(x + area_width + PADDING, y + row, comparison_width, row_height)),
+                     ("value", Rect::of(x + width - value_width, y + row, value_width, row_height)),
+                     ("effect", Rect::of(x, y + row * 2, kind_width, row_height)),
+                     ("effect_player",
+                      Rect::of(x + kind_width + PADDING, y + row * 2, player_width, row_height)),
+                     ("effect_subject", Rect::of(subject_x, y + row * 2, subject_width, row_height)),
+                     ("effect_argument", Rect::of(x, y + row * 3, width, row_height)),
+                     ("add", Rect::of(x, y + row * 4, button_width, row_height)),
+                     ("replace",
+                      Rect::of(x + button_width + PADDING, y + row * 4, button_width, row_height))];
+        for &(id, rect) in &rects {
+            form.widget_mut(id).unwrap().rect = rect;
+        }
+    }
+
+    /// Keeps the subject dropdowns filled for the kinds of condition and effect picked, with
+    /// the units of the given civilization, and turns off the fields the kinds don't use
+    pub fn refresh(&mut self, form: &mut Form, data: &EditorData, civilization_id: CivilizationId) {
+        let condition = selected(form, "condition");
+        let effect = selected(form, "effect");
+        if self.subjects_for != Some((condition, effect, civilization_id)) {
+            self.subjects_for = Some((condition, effect, civilization_id));
+            let units = data.units(civilization_id)
+                .into_iter()
+                .map(|(unit_id, name)| {
+                    let color = if data.is_gated(civilization_id, unit_id) {
+                        missing_color()
+                    } else {
+                        item_color()
+                    };
+                    (Subject::Unit(unit_id), format!("{}: {}", *unit_id, name), color)
+                })
+                .collect::<Vec<_>>();
+            let research = || {
+                data.research()
+                    .into_iter()
+                    .map(|(research_id, name)| {
+                        (Subject::Research(research_id), format!("{}: {}", *research_id, name), item_color())
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let resources = || {
+                RESOURCES.iter()
+                    .map(|&(name, _)| (Subject::Resource(name), name.to_string(), item_color()))
+                    .collect::<Vec<_>>()
+            };
+            let condition_subjects = match condition {
+                0 => {
+                    Some((Subject::AnyUnit, "any unit".to_string(), item_color()))
+                        .into_iter()
+                        .chain(units.clone())
+                        .collect()
+                }
+                1 => resources(),
+                _ => research(),
+            };
+            let effect_subjects = match effect {
+                0 => Vec::new(),
+                1 => units,
+                2 => resources(),
+                _ => research(),
+            };
+            self.condition_subjects =
+                set_subjects(form, "condition_subject", &self.condition_subjects, condition_subjects);
+            self.effect_subjects =
+                set_subjects(form, "effect_subject", &self.effect_subjects, effect_subjects);
+        }
+
+        enable(form, "area", condition == 0);
+        enable(form, "effect_player", effect != 0);
+        enable(form, "effect_subject", effect != 0);
+        enable(form, "effect_argument", effect != 3);
+        let argument_label = match effect {
+            0 => "message text",
+            1 => "x y",
+            2 => "amount",
+            _ => "",
+        };
+        let widget = form.widget_mut("effect_argument").unwrap();
+        if widget.label != argument_label {
+            widget.label = argument_label.to_string();
+        }
+    }
+
+    /// Fills the form in with the trigger
+    pub fn fill(&mut self,
+                form: &mut Form,
+                data: &EditorData,
+                civilization_id: CivilizationId,
+                trigger: &EditorTrigger) {
+        let (condition, condition_player, condition_subject, area) = match trigger.query() {
+            WorldQuery::UnitCount { unit_id, player_id, area } => {
+                (0,
+                 player_id.map_or(String::new(), |player_id| (*player_id).to_string()),
+                 unit_id.map_or(Subject::AnyUnit, Subject::Unit),
+                 area.map_or(String::new(), |area| {
+                     format!("{} {} {} {}", area.start.x, area.start.y, area.end.x, area.end.y)
+                 }))
+            }
+            WorldQuery::Stockpile(player_id, resource_type) => {
+                (1, (*player_id).to_string(), Subject::Resource(resource_name(resource_type)), String::new())
+            }
+            WorldQuery::Researched(player_id, research_id) => {
+                (2, (*player_id).to_string(), Subject::Research(research_id), String::new())
+            }
+        };
+        let (effect, effect_player, effect_subject, argument) = match *trigger.effect() {
+            TriggerEffect::Message(ref text) => (0, String::new(), None, text.clone()),
+            TriggerEffect::Spawn { player_id, unit_id, x, y } => {
+                (1, player_id.to_string(), Some(Subject::Unit(unit_id.into())), format!("{} {}", x, y))
+            }
+            TriggerEffect::Give { player_id, ref resource, amount } => {
+                let subject = RESOURCES.iter()
+                    .find(|&&(name, _)| name == resource.as_str())
+                    .map(|&(name, _)| Subject::Resource(name));
+                (2, player_id.to_string(), subject, amount.to_string())
+            }
+            TriggerEffect::Research { player_id, research_id } => {
+                (3, player_id.to_string(), Some(Subject::Research(research_id.into())), String::new())
+            }
+        };
+
+        form.select("condition", condition);
+        form.select("effect", effect);
+        let comparison = COMPARISONS.iter()
+            .position(|&comparison| comparison == trigger.comparison())
+            .unwrap();
+        form.select("comparison", comparison);
+        self.refresh(form, data, civilization_id);
+        pick_subject(form, "condition_subject", &mut self.condition_subjects, condition_subject);
+        if let Some(effect_subject) = effect_subject {
+            pick_subject(form, "effect_subject", &mut self.effect_subjects, effect_subject);
+        }
+        let fields = [("condition_player", condition_player),
+                      ("area", area),
+                      ("value", trigger.value().to_string()),
+                      ("effect_player", effect_player),
+                      ("effect_argument", argument)];
+        for &(id, ref text) in &fields {
+            form.text_field_mut(id).unwrap().set_text(text);
+        }
+    }
+
+    /// Empties the typed fields, leaving what was picked in the dropdowns
+    pub fn clear(form: &mut Form) {
+        for id in &TEXT_FIELDS {
+            form.text_field_mut(id).unwrap().set_text("");
+        }
+    }
+
+    /// The trigger the form describes, written the way `EditorTrigger::parse` reads it, which
+    /// checks the rest of it
+    pub fn text(&self, form: &Form) -> Result<String, String> {
+        let condition_player = field_text(form, "condition_player");
+        let condition_subject = self.subject(form, "condition_subject", &self.condition_subjects);
+        let query = match (selected(form, "condition"), condition_subject) {
+            (0, subject) => {
+                let mut query = "count".to_string();
+                if let Some(Subject::Unit(unit_id)) = subject {
+                    query.push_str(&format!(" type {}", *unit_id));
+                }
+                if !condition_player.is_empty() {
+                    query.push_str(&format!(" owner {}", condition_player));
+                }
+                let area = field_text(form, "area");
+                if !area.is_empty() {
+                    query.push_str(&format!(" in {}", area));
+                }
+                query
+            }
+            (1, Some(Subject::Resource(resource))) => format!("stockpile {} {}", condition_player, resource),
+            (2, Some(Subject::Research(research_id))) => {
+                format!("researched {} {}", condition_player, *research_id)
+            }
+            (1, _) => return Err("pick the resource the condition looks at".into()),
+            _ => return Err("pick the research the condition looks at".into()),
+        };
+
+        let effect_player = field_text(form, "effect_player");
+        let argument = field_text(form, "effect_argument");
+        let effect_subject = self.subject(form, "effect_subject", &self.effect_subjects);
+        let effect = match (selected(form, "effect"), effect_subject) {
+            (0, _) => format!("message {}", argument),
+            (1, Some(Subject::Unit(unit_id))) => format!("spawn {} {} {}", effect_player, *unit_id, argument),
+            (2, Some(Subject::Resource(resource))) => {
+                format!("give {} {} {}", effect_player, resource, argument)
+            }
+            (3, Some(Subject::Research(research_id))) => {
+                format!("research {} {}", effect_player, *research_id)
+            }
+            (1, _) => return Err("pick the unit to spawn".into()),
+            (2, _) => return Err("pick the resource to give".into()),
+            _ => return Err("pick the research to complete".into()),
+        };
+
+        let comparison = COMPARISONS[selected(form, "comparison")].operator();
+        Ok(format!("when {} {} {} then {}", query, comparison, field_text(form, "value"), effect))
+    }
+
+    fn subject(&self, form: &Form, id: &str, subjects: &[Subject]) -> Option<Subject> {
+        form.list(id).and_then(|list| list.selected()).and_then(|index| subjects.get(index).cloned())
+    }
+}
+
+fn item_color() -> Color {
+    Color::rgb(90, 90, 90)
+}
+
+/// Marks units that the content gate keeps out, and things the data doesn't have
+fn missing_color() -> Color {
+    Color::rgb(200, 50, 50)
+}
+
+fn resource_name(resource_type: ResourceType) -> &'static str {
+    RESOURCES.iter().find(|&&(_, other)| other == resource_type).map_or("food", |&(name, _)| name)
+}
+
+/// Fills a subject dropdown, keeping what was picked in it if it's still there, and returns
+/// what its items stand for
+fn set_subjects(form: &mut Form,
+                id: &str,
+                old_subjects: &[Subject],
+                subjects: Vec<(Subject, String, Color)>)
+                -> Vec<Subject> {
+    let picked = form.list(id)
+        .and_then(|list| list.selected())
+        .and_then(|index| old_subjects.get(index).cloned());
+    let items = subjects.iter()
+        .map(|&(_, ref text, color)| {
+            ListItem {
+                text: text.clone(),
+                color: color,
+            }
+        })
+        .collect();
+    form.list_mut(id).unwrap().set_items(items);
+    let subjects: Vec<Subject> = subjects.into_iter().map(|(subject, _, _)| subject).collect();
+    let index = picked.and_then(|picked| subjects.iter().position(|&subject| subject == picked)).unwrap_or(0);
+    form.select(id, index);
+    subjects
+}
+
+/// Picks the item of a subject dropdown standing for the subject, adding one if the data
+/// doesn't have it, so that a trigger referring to something missing isn't changed to refer
+/// to something else
+fn pick_subject(form: &mut Form, id: &str, subjects: &mut Vec<Subject>, subject: Subject) {
+    let index = match subjects.iter().position(|&other| other == subject) {
+        Some(index) => index,
+        None => {
+            let text = match subject {
+                Subject::AnyUnit => "any unit".to_string(),
+                Subject::Unit(unit_id) => format!("{}: missing", *unit_id),
+                Subject::Resource(name) => name.to_string(),
+                Subject::Research(research_id) => format!("{}: missing", *research_id),
+            };
+            let list = form.list_mut(id).unwrap();
+            let mut items = list.items().to_vec();
+            items.push(ListItem {
+                text: text,
+                color: missing_color(),
+            });
+            list.set_items(items);
+            subjects.push(subject);
+            subjects.len() - 1
+        }
+    };
+    form.select(id, index);
+}
+
+fn set_items(form: &mut Form, id: &str, texts: Vec<String>) {
+    let items = texts.into_iter()
+        .map(|text| {
+            ListItem {
+                text: text,
+                color: item_color(),
+            }
+        })
+        .collect();
+    form.list_mut(id).unwrap().set_items(items);
+}
+
+fn selected(form: &Form, id: &str) -> usize {
+    form.list(id).and_then(|list| list.selected()).unwrap_or(0)
+}
+
+fn enable(form: &mut Form, id: &str, enabled: bool) {
+    form.widget_mut(id).unwrap().enabled = enabled;
+}
+
+fn field_text(form: &Form, id: &str) -> String {
+    match form.widget(id).map(|widget| &widget.kind) {
+        Some(&WidgetKind::TextInput(ref field)) => field.text().trim().to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use editor::{EditorData, EditorTrigger, PlacementRules};
+    use identifier::{CivilizationId, ResearchId, TerrainId, UnitId};
+    use super::TriggerForm;
+    use ui::Form;
+
+    /// Units 1 and 2, and research 4
+    struct FakeData;
+
+    impl EditorData for FakeData {
+        fn units(&self, _civilization_id: CivilizationId) -> Vec<(UnitId, String)> {
+            vec![(1.into(), "Villager".into()), (2.into(), "Scout".into())]
+        }
+
+        fn has_unit(&self, _civilization_id: CivilizationId, unit_id: UnitId) -> bool {
+            *unit_id == 1 || *unit_id == 2
+        }
+
+        fn is_gated(&self, _civilization_id: CivilizationId, _unit_id: UnitId) -> bool {
+            false
+        }
+
+        fn research(&self) -> Vec<(ResearchId, String)> {
+            vec![(4.into(), "Wheel".into())]
+        }
+
+        fn passable(&self,
+                    _civilization_id: CivilizationId,
+                    _unit_id: UnitId,
+                    _terrain_id: TerrainId)
+                    -> bool {
+            true
+        }
+
+        fn placement_rules(&self,
+                           _civilization_id: CivilizationId,
+                           _unit_id: UnitId)
+                           -> Option<PlacementRules> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_fill_and_write_back() {
+        let mut form = Form::new();
+        TriggerForm::add_widgets(&mut form, 14);
+        let mut trigger_form = TriggerForm::new();
+        let civilization_id = CivilizationId::default();
+        for text in &["when count type 2 owner 1 in 0 0 5 5 >= 3 then message Scouts out",
+                      "when count == 0 then spawn 1 1 4 5",
+                      "when stockpile 2 gold < 100 then give 2 gold 50",
+                      "when researched 1 4 == 1 then research 2 4",
+                      "when count type 9 > 0 then spawn 1 7 0 0"] {
+            let trigger = EditorTrigger::parse(text).unwrap();
+            trigger_form.fill(&mut form, &FakeData, civilization_id, &trigger);
+            assert_eq!(Ok(text.to_string()), trigger_form.text(&form));
+        }
+
+        TriggerForm::clear(&mut form);
+        form.select("effect", 1);
+        trigger_form.refresh(&mut form, &FakeData, civilization_id);
+        assert!(form.widget("effect_player").unwrap().enabled);
+        form.select("effect", 3);
+        trigger_form.refresh(&mut form, &FakeData, civilization_id);
+        assert!(!form.widget("effect_argument").unwrap().enabled);
+    }
+}
@@ -0,0 +1,69 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::EmpiresDb;
+use ecs::resource::UnitVoices;
+use identifier::SoundGroupId;
+use media::{AudioChannel, Media, PlaybackId};
+use resource::SoundManager;
+use time;
+
+/// Plays the units' acknowledgments, one line at a time
+pub struct VoicePlayer {
+    /// The line that's playing, and the voice it's from
+    speaking: Option<(PlaybackId, SoundGroupId)>,
+}
+
+impl VoicePlayer {
+    pub fn new() -> VoicePlayer {
+        VoicePlayer { speaking: None }
+    }
+
+    /// Called once a frame, after the systems have asked for any acknowledgments
+    pub fn update(&mut self,
+                  unit_voices: &mut UnitVoices,
+                  media: &mut Media,
+                  sound_manager: &mut SoundManager,
+                  empires: &EmpiresDb) {
+        let audio = media.audio();
+        if let Some((playback, _)) = self.speaking {
+            if !audio.is_playing(playback) {
+                self.speaking = None;
+            }
+        }
+
+        let speaking = self.speaking.map(|(_, voice)| voice);
+        let (voice, wav_id) =
+            match unit_voices.next_line(empires.sound_effect_groups(), time::precise_time_ns(), speaking) {
+                Some(line) => line,
+                None => return,
+            };
+
+        // Another unit answering cuts off whoever was talking
+        if let Some((playback, _)) = self.speaking.take() {
+            audio.stop(playback);
+        }
+        if let Some(sound) = sound_manager.get(wav_id, audio) {
+            let playback = audio.play(&sound, AudioChannel::Sounds, 1.0, 0.0, false);
+            self.speaking = Some((playback, voice));
+        }
+    }
+}
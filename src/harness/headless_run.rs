@@ -0,0 +1,275 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use action::{Action, MoveToPositionParams};
+use dat::{EmpiresDb, EmpiresDbRef};
+use drs::DrsFileType;
+use ecs;
+use ecs::{TransformComponent, UnitComponent};
+use ecs::resource::{ActionBatcher, GameSettings, OccupiedTiles, PathFinder, PathScheduler, ProductionOrders,
+                    RenderCommands, SimulationTick, Terrain, TickArena};
+use harness::{PlanAction, PlanChecker, PlanReport, ReplayAnalysis, TestPlan};
+use game::ContentGate;
+use identifier::{CivilizationId, PlayerId, UnitId};
+use logging;
+use nalgebra::Vector2;
+use resource::{DrsKey, DrsManager, GameDir, ShapeMetadataStore, ShapeMetadataStoreRef};
+use scn;
+use script::{ScenarioScript, ScriptEffect, ScriptView, apply_script_effects};
+use specs::{Index, Join};
+use types::{Fixed, Vector3};
+
+/// The game data a headless run needs, which is everything but the graphics themselves
+pub struct HeadlessData {
+    pub empires: EmpiresDbRef,
+    pub shape_metadata: ShapeMetadataStoreRef,
+    /// Which units the archives have the graphics for, as the game works it out
+    pub content_gate: ContentGate,
+}
+
+impl HeadlessData {
+    /// Loads the original game's data without any mods, so that runs only depend on the
+    /// scenario and the plan
+    pub fn load(game_dir: &GameDir) -> Result<HeadlessData, String> {
+        let drs_manager = DrsManager::new(game_dir);
+        try!(drs_manager.borrow_mut()
+            .preload()
+            .map_err(|err| format!("Failed to preload DRS archives: {}", err)));
+        let shape_metadata = ShapeMetadataStoreRef::new(ShapeMetadataStore::load(&*drs_manager.borrow()));
+
+        let empires_dat_location = try!(game_dir.find_file("data/empires.dat")
+            .map_err(|err| format!("Failed to find empires.dat: {}", err)));
+        let mut empires = try!(EmpiresDb::read_from_file(&empires_dat_location)
+            .map_err(|err| format!("Failed to load empires.dat: {}", err)));
+        empires.apply_civ_bonuses();
+        let content_gate = ContentGate::check(&empires, |slp_id| {
+            drs_manager.borrow_mut().has_file(DrsKey::Graphics, DrsFileType::Slp, *slp_id)
+        });
+        Ok(HeadlessData {
+            empires: EmpiresDbRef::new(empires),
+            shape_metadata: shape_metadata,
+            content_gate: content_gate,
+        })
+    }
+}
+
+/// A scenario running without a window or any input, a tick at a time, as fast as it'll go.
+/// Ticks go through the same systems in the same order as in `ScenarioGameState`, minus the
+/// rendering.
+pub struct HeadlessRun {
+    empires: EmpiresDbRef,
+    planner: ecs::WorldPlanner,
+    presentation_lane: ecs::system::PresentationLane,
+    tick: usize,
+    script: Option<ScenarioScript>,
+    time_step: Fixed,
+    errors: Vec<String>,
+}
+
+impl HeadlessRun {
+    pub fn new(data: &HeadlessData,
+               scenario: &scn::Scenario,
+               settings: &GameSettings,
+               script: Option<ScenarioScript>)
+               -> HeadlessRun {
+        let mut planner = ecs::create_headless_world_planner(data.empires.clone(),
+                                                             data.shape_metadata.clone(),
+                                                             scenario,
+                                                             settings);
+        let presentation_lane = ecs::create_presentation_lane(&mut planner,
+                                                              data.empires.clone(),
+                                                              data.shape_metadata.clone());
+        let mut run = HeadlessRun {
+            empires: data.empires.clone(),
+            planner: planner,
+            presentation_lane: presentation_lane,
+            tick: 0,
+            script: script,
+            time_step: Fixed::from(1) / Fixed::from(60),
+            errors: Vec::new(),
+        };
+        if run.script.is_some() {
+            let view = ScriptView::from_world(run.planner.mut_world());
+            let result = run.script.as_mut().unwrap().start(view);
+            run.apply_script_result(result);
+        }
+        run
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.tick
+    }
+
+    /// Advances the world by one tick
+    pub fn tick(&mut self) {
+        logging::set_tick(self.tick);
+        self.planner.mut_world().write_resource::<SimulationTick>().set(self.tick);
+        self.tick += 1;
+
+        self.planner.dispatch((ecs::SystemGroup::Normal, self.time_step));
+        self.planner.wait();
+        self.presentation_lane.run(self.planner.mut_world(), self.time_step);
+
+        let tick = self.tick;
+        let result = match self.script {
+            Some(ref mut script) if script.runs_on_tick(tick) => {
+                let view = ScriptView::from_world(self.planner.mut_world());
+                script.tick(tick, view)
+            }
+            _ => Ok(Vec::new()),
+        };
+        self.apply_script_result(result);
+        ecs::spawn_queued_units(self.planner.mut_world(), &self.empires);
+
+        let world = self.planner.mut_world();
+        // Nothing draws the debug shapes that systems queue, so they'd pile up otherwise
+        world.write_resource::<RenderCommands>().clear_debug();
+        world.write_resource::<TickArena>().end_tick();
+    }
+
+    /// Runs the scenario for as long as the plan lasts, checking each tick's expectations
+    /// against the world before carrying out that tick's actions
+    pub fn run_plan(self, plan: &TestPlan) -> PlanReport {
+        self.run(plan, None)
+    }
+
+    /// Runs the plan like `run_plan`, playing it back as a replay for the analysis to observe
+    /// every tick of
+    pub fn analyze_plan(self, plan: &TestPlan, analysis: &mut ReplayAnalysis) -> PlanReport {
+        self.run(plan, Some(analysis))
+    }
+
+    fn run(mut self, plan: &TestPlan, mut analysis: Option<&mut ReplayAnalysis>) -> PlanReport {
+        let mut checker = PlanChecker::new(plan);
+        let mut actions = plan.actions.iter().peekable();
+        loop {
+            let tick = self.tick;
+            if checker.is_due(tick) {
+                let mut view = ScriptView::from_world(self.planner.mut_world());
+                checker.check(tick, |query| view.evaluate(query));
+            }
+            if let Some(ref mut analysis) = analysis {
+                analysis.observe(tick, self.planner.mut_world(), &self.empires);
+            }
+            while actions.peek().map_or(false, |&&(action_tick, _)| action_tick == tick) {
+                let &(_, ref action) = actions.next().unwrap();
+                if let Some(ref mut analysis) = analysis {
+                    match *action {
+                        PlanAction::Move { player_id, .. } |
+                        PlanAction::Train { player_id, .. } => analysis.record_command(player_id),
+                        PlanAction::Effect(_) => {}
+                    }
+                }
+                self.apply_action(action);
+            }
+            if tick >= plan.ticks {
+                break;
+            }
+            self.tick();
+        }
+        checker.finish(self.errors)
+    }
+
+    fn apply_action(&mut self, action: &PlanAction) {
+        match *action {
+            PlanAction::Effect(ref effect) => {
+                apply_script_effects(self.planner.mut_world(), &self.empires, vec![effect.clone()]);
+            }
+            PlanAction::Move { player_id, unit_id, tile } => self.move_units(player_id, unit_id, tile),
+            PlanAction::Train { player_id, building_id, unit_id, count } => {
+                let buildings: Vec<Index> = self.find_units(player_id, building_id)
+                    .into_iter()
+                    .map(|(entity_id, _, _)| entity_id)
+                    .collect();
+                if buildings.is_empty() {
+                    let error = format!("tick {}: player {} has no buildings of type {} to train {} at",
+                                        self.tick,
+                                        *player_id,
+                                        *building_id,
+                                        *unit_id);
+                    self.errors.push(error);
+                    return;
+                }
+                let world = self.planner.mut_world();
+                let mut production_orders = world.write_resource::<ProductionOrders>();
+                for _ in 0..count {
+                    production_orders.queue_shared_training(buildings.clone(), unit_id);
+                }
+            }
+        }
+    }
+
+    fn move_units(&mut self, player_id: PlayerId, unit_id: UnitId, tile: Vector2<i32>) {
+        let units = self.find_units(player_id, unit_id);
+        if units.is_empty() {
+            let error = format!("tick {}: player {} has no units of type {} to move",
+                                self.tick,
+                                *player_id,
+                                *unit_id);
+            self.errors.push(error);
+            return;
+        }
+
+        let world = self.planner.mut_world();
+        let target = Vector3::new(tile.x.into(), tile.y.into(), 0.into());
+        let (path_finder, terrain, occupied_tiles) = (world.read_resource::<PathFinder>(),
+                                                      world.read_resource::<Terrain>(),
+                                                      world.read_resource::<OccupiedTiles>());
+        let mut path_scheduler = world.write_resource::<PathScheduler>();
+        let mut action_batcher = world.write_resource::<ActionBatcher>();
+        for (entity_id, civilization_id, position) in units {
+            let restriction_id = self.empires.unit(civilization_id, unit_id).terrain_restriction;
+            let path = path_scheduler.find_path(&*path_finder,
+                                                &*terrain,
+                                                &*occupied_tiles,
+                                                entity_id,
+                                                &position,
+                                                &target,
+                                                restriction_id);
+            action_batcher.queue_for_entity(entity_id, Action::ClearQueue);
+            action_batcher.queue_for_entity(entity_id,
+                                            Action::MoveToPosition(MoveToPositionParams::new(path)));
+        }
+    }
+
+    /// The IDs, civilizations, and positions of the player's units of a type
+    fn find_units(&mut self, player_id: PlayerId, unit_id: UnitId) -> Vec<(Index, CivilizationId, Vector3)> {
+        let world = self.planner.mut_world();
+        let entities = world.entities();
+        let (units, transforms) = (world.read::<UnitComponent>(), world.read::<TransformComponent>());
+        (&entities, &units, &transforms)
+            .iter()
+            .filter(|&(_, unit, _)| unit.player_id == player_id && unit.unit_id == unit_id)
+            .map(|(entity, unit, transform)| (entity.get_id(), unit.civilization_id, *transform.position()))
+            .collect()
+    }
+
+    /// Records a failing script as an error in the run, and stops it
+    fn apply_script_result(&mut self, result: Result<Vec<ScriptEffect>, String>) {
+        match result {
+            Ok(effects) => apply_script_effects(self.planner.mut_world(), &self.empires, effects),
+            Err(err) => {
+                self.errors.push(format!("tick {}: scenario script failed: {}", self.tick, err));
+                self.script = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+mod headless_run;
+mod replay_analysis;
+mod scenario_smoke_test;
+mod test_plan;
+
+pub use self::headless_run::{HeadlessData, HeadlessRun};
+pub use self::replay_analysis::{BuildOrderEntry, Engagement, EngagementSide, PlayerSample, ReplayAnalysis,
+                                Sample};
+pub use self::scenario_smoke_test::{DEFAULT_SMOKE_TEST_TICKS, SmokeProblem, SmokeTestReport, SmokeTestResult,
+                                    scenario_files, smoke_test_dir, smoke_test_scenario};
+pub use self::test_plan::{Deadline, Expectation, Outcome, PlanAction, PlanChecker, PlanReport, TestPlan};
@@ -0,0 +1,536 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, EmpiresDbRef, ResourceType};
+use ecs::UnitComponent;
+use ecs::resource::{CheatRecord, Cheats, CombatLog, Players};
+use identifier::{PlayerId, UnitId};
+use specs::{self, Index, Join};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Headless runs tick at the same fixed rate as the game
+pub const TICKS_PER_SECOND: usize = 60;
+
+/// How often the resource and army curves are sampled; every five seconds of game time
+pub const SAMPLE_INTERVAL_TICKS: usize = 300;
+
+/// Hits that come closer together than this, between the same players, are one engagement
+pub const ENGAGEMENT_GAP_TICKS: usize = 600;
+
+const RESOURCES: [ResourceType; 4] = [ResourceType::Food, ResourceType::Wood, ResourceType::Stone,
+                                      ResourceType::Gold];
+
+/// One player's economy and army at a point in the game
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerSample {
+    pub player_id: PlayerId,
+    /// Food, wood, stone, and gold in the stockpile
+    pub stockpile: [i32; 4],
+    pub villagers: u32,
+    pub military_units: u32,
+    /// What the standing military units cost to train, in all resources together
+    pub army_value: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub tick: usize,
+    pub players: Vec<PlayerSample>,
+}
+
+/// A unit or building that a player got during the game, in the order they appeared
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildOrderEntry {
+    pub tick: usize,
+    pub player_id: PlayerId,
+    pub unit_id: UnitId,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EngagementSide {
+    pub damage_dealt: u32,
+    pub units_lost: u32,
+}
+
+/// A run of hits between players, with how each of them came out of it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Engagement {
+    pub start_tick: usize,
+    pub end_tick: usize,
+    pub sides: BTreeMap<PlayerId, EngagementSide>,
+}
+
+impl Engagement {
+    /// The player who lost the fewest units, when nobody else lost as few
+    pub fn winner(&self) -> Option<PlayerId> {
+        let fewest = self.sides.values().map(|side| side.units_lost).min();
+        let mut least_lost = self.sides.iter().filter(|&(_, side)| Some(side.units_lost) == fewest);
+        match (least_lost.next(), least_lost.next()) {
+            (Some((player_id, _)), None) if self.sides.len() > 1 => Some(*player_id),
+            _ => None,
+        }
+    }
+
+    fn involves(&self, player_id: PlayerId) -> bool {
+        self.sides.contains_key(&player_id)
+    }
+}
+
+/// Analytics gathered while a headless run plays back a game: actions per minute, resource
+/// and army curves, build orders, the cheats used, and combat engagements. They're written out
+/// as JSON for stat tools, or the curves alone as CSV.
+pub struct ReplayAnalysis {
+    ticks: usize,
+    samples: Vec<Sample>,
+    build_order: Vec<BuildOrderEntry>,
+    cheats: Vec<CheatRecord>,
+    engagements: Vec<Engagement>,
+    commands: BTreeMap<PlayerId, u32>,
+    /// Who owns each unit as of the last observed tick, so that hits on units destroyed since
+    /// can still be put down to a player
+    owners: HashMap<Index, PlayerId>,
+    events_seen: usize,
+}
+
+impl ReplayAnalysis {
+    pub fn new() -> ReplayAnalysis {
+        ReplayAnalysis {
+            ticks: 0,
+            samples: Vec::new(),
+            build_order: Vec::new(),
+            cheats: Vec::new(),
+            engagements: Vec::new(),
+            commands: BTreeMap::new(),
+            owners: HashMap::new(),
+            events_seen: 0,
+        }
+    }
+
+    pub fn samples<'a>(&'a self) -> &'a [Sample] {
+        &self.samples
+    }
+
+    pub fn build_order<'a>(&'a self) -> &'a [BuildOrderEntry] {
+        &self.build_order
+    }
+
+    pub fn cheats<'a>(&'a self) -> &'a [CheatRecord] {
+        &self.cheats
+    }
+
+    pub fn engagements<'a>(&'a self) -> &'a [Engagement] {
+        &self.engagements
+    }
+
+    /// Counts an order the player gave
+    pub fn record_command(&mut self, player_id: PlayerId) {
+        *self.commands.entry(player_id).or_insert(0) += 1;
+    }
+
+    /// The player's orders per minute of game time
+    pub fn actions_per_minute(&self, player_id: PlayerId) -> f32 {
+        let minutes = self.ticks as f32 / (TICKS_PER_SECOND * 60) as f32;
+        if minutes == 0. {
+            return 0.;
+        }
+        self.commands.get(&player_id).cloned().unwrap_or(0) as f32 / minutes
+    }
+
+    /// Puts a hit between two players into the engagement it's part of, or starts a new one
+    pub fn record_hit(&mut self,
+                      tick: usize,
+                      attacker_player_id: PlayerId,
+                      target_player_id: PlayerId,
+                      damage: u32,
+                      destroyed: bool) {
+        let ongoing = self.engagements.iter().rposition(|engagement| {
+            tick <= engagement.end_tick + ENGAGEMENT_GAP_TICKS &&
+            (engagement.involves(attacker_player_id) || engagement.involves(target_player_id))
+        });
+        let index = match ongoing {
+            Some(index) => index,
+            None => {
+                self.engagements.push(Engagement {
+                    start_tick: tick,
+                    end_tick: tick,
+                    sides: BTreeMap::new(),
+                });
+                self.engagements.len() - 1
+            }
+        };
+        let engagement = &mut self.engagements[index];
+        engagement.end_tick = tick;
+        {
+            let attacker_side = engagement.sides.entry(attacker_player_id).or_insert_with(Default::default);
+            attacker_side.damage_dealt += damage;
+        }
+        let target_side = engagement.sides.entry(target_player_id).or_insert_with(Default::default);
+        if destroyed {
+            target_side.units_lost += 1;
+        }
+    }
+
+    /// Takes in what happened in the world up to the tick. Should be called on every tick,
+    /// starting with tick 0; the units there at the start aren't part of the build order.
+    pub fn observe(&mut self, tick: usize, world: &specs::World, empires: &EmpiresDbRef) {
+        self.ticks = tick;
+        self.observe_hits(tick, &*world.read_resource::<CombatLog>());
+        let cheats_seen = self.cheats.len();
+        self.cheats.extend(world.read_resource::<Cheats>().history().iter().skip(cheats_seen).cloned());
+
+        let entities = world.entities();
+        let units = world.read::<UnitComponent>();
+        let mut owners = HashMap::new();
+        for (entity, unit) in (&entities, &units).iter() {
+            owners.insert(entity.get_id(), unit.player_id);
+            if tick > 0 && !unit.player_id.is_gaia() && !self.owners.contains_key(&entity.get_id()) {
+                self.build_order.push(BuildOrderEntry {
+                    tick: tick,
+                    player_id: unit.player_id,
+                    unit_id: unit.unit_id,
+                    name: unit.db(empires).name().to_string(),
+                });
+            }
+        }
+        self.owners = owners;
+
+        if tick % SAMPLE_INTERVAL_TICKS == 0 {
+            let sample = sample(tick, &*world.read_resource::<Players>(), (&units).iter(), empires);
+            self.samples.push(sample);
+        }
+    }
+
+    fn observe_hits(&mut self, tick: usize, combat_log: &CombatLog) {
+        let new_events = (combat_log.recorded() - self.events_seen).min(combat_log.events().len());
+        self.events_seen = combat_log.recorded();
+        let events = combat_log.events();
+        for event in events.iter().skip(events.len() - new_events) {
+            let target_player_id = match self.owners.get(&event.target) {
+                Some(player_id) => *player_id,
+                None => continue,
+            };
+            // Hunting and animals fighting back aren't battles
+            if event.attacker_player_id.is_gaia() || target_player_id.is_gaia() {
+                continue;
+            }
+            self.record_hit(tick,
+                            event.attacker_player_id,
+                            target_player_id,
+                            event.damage.max(0) as u32,
+                            event.destroyed);
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        json.push_str(&format!("  \"ticks\": {},\n  \"seconds\": {},\n", self.ticks, seconds(self.ticks)));
+
+        let players: Vec<String> = self.player_ids()
+            .iter()
+            .map(|player_id| {
+                format!("    {{\"player\": {}, \"commands\": {}, \"apm\": {:.1}}}",
+                        **player_id,
+                        self.commands.get(player_id).cloned().unwrap_or(0),
+                        self.actions_per_minute(*player_id))
+            })
+            .collect();
+        push_array(&mut json, "players", &players, true);
+
+        let samples: Vec<String> = self.samples
+            .iter()
+            .map(|sample| {
+                let players: Vec<String> = sample.players
+                    .iter()
+                    .map(|player| {
+                        format!("{{\"player\": {}, \"food\": {}, \"wood\": {}, \"stone\": {}, \"gold\": {}, \
+                                 \"villagers\": {}, \"military_units\": {}, \"army_value\": {}}}",
+                                *player.player_id,
+                                player.stockpile[0],
+                                player.stockpile[1],
+                                player.stockpile[2],
+                                player.stockpile[3],
+                                player.villagers,
+                                player.military_units,
+                                player.army_value)
+                    })
+                    .collect();
+                format!("    {{\"seconds\": {}, \"players\": [{}]}}",
+                        seconds(sample.tick),
+                        players.join(", "))
+            })
+            .collect();
+        push_array(&mut json, "samples", &samples, true);
+
+        let build_order: Vec<String> = self.build_order
+            .iter()
+            .map(|entry| {
+                format!("    {{\"seconds\": {}, \"player\": {}, \"unit_id\": {}, \"name\": {}}}",
+                        seconds(entry.tick),
+                        *entry.player_id,
+                        *entry.unit_id,
+                        json_string(&entry.name))
+            })
+            .collect();
+        push_array(&mut json, "build_order", &build_order, true);
+
+        let cheats: Vec<String> = self.cheats
+            .iter()
+            .map(|record| {
+                format!("    {{"seconds": {}, "player": {}, "cheat": {}}}",
+                        seconds(record.tick),
+                        *record.request.player_id,
+                        json_string(record.request.cheat.code()))
+            })
+            .collect();
+        push_array(&mut json, "cheats", &cheats, true);
+
+        let engagements: Vec<String> = self.engagements
+            .iter()
+            .map(|engagement| {
+                let sides: Vec<String> = engagement.sides
+                    .iter()
+                    .map(|(player_id, side)| {
+                        format!("{{\"player\": {}, \"damage_dealt\": {}, \"units_lost\": {}}}",
+                                **player_id,
+                                side.damage_dealt,
+                                side.units_lost)
+                    })
+                    .collect();
+                let winner = match engagement.winner() {
+                    Some(player_id) => (*player_id).to_string(),
+                    None => "null".to_string(),
+                };
+                format!("    {{\"start\": {}, \"end\": {}, \"sides\": [{}], \"winner\": {}}}",
+                        seconds(engagement.start_tick),
+                        seconds(engagement.end_tick),
+                        sides.join(", "),
+                        winner)
+            })
+            .collect();
+        push_array(&mut json, "engagements", &engagements, false);
+        json.push_str("}\n");
+        json
+    }
+
+    /// The resource and army curves, a row per player per sample
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("seconds,player,food,wood,stone,gold,villagers,military_units,\
+                                    army_value\n");
+        for sample in &self.samples {
+            for player in &sample.players {
+                csv.push_str(&format!("{},{},{},{},{},{},{},{},{}\n",
+                                      seconds(sample.tick),
+                                      *player.player_id,
+                                      player.stockpile[0],
+                                      player.stockpile[1],
+                                      player.stockpile[2],
+                                      player.stockpile[3],
+                                      player.villagers,
+                                      player.military_units,
+                                      player.army_value));
+            }
+        }
+        csv
+    }
+
+    /// Writes the curves as CSV if the file name ends in `.csv`, and everything as JSON otherwise
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let csv = path.extension()
+            .map_or(false, |extension| extension.to_string_lossy().to_lowercase() == "csv");
+        let text = if csv { self.to_csv() } else { self.to_json() };
+        File::create(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|err| format!("failed to write {}: {}", path.display(), err))
+    }
+
+    /// Everyone but gaia that shows up in the samples or gave orders
+    fn player_ids(&self) -> Vec<PlayerId> {
+        let mut player_ids: Vec<PlayerId> = self.commands.keys().cloned().collect();
+        for player in self.samples.iter().flat_map(|sample| sample.players.iter()) {
+            if !player_ids.contains(&player.player_id) {
+                player_ids.push(player.player_id);
+            }
+        }
+        player_ids.sort();
+        player_ids
+    }
+}
+
+fn sample<'a, I>(tick: usize, players: &Players, units: I, empires: &EmpiresDbRef) -> Sample
+    where I: Iterator<Item = &'a UnitComponent>
+{
+    let mut player_samples: BTreeMap<PlayerId, PlayerSample> = BTreeMap::new();
+    for player_id in players.player_ids().into_iter().filter(|player_id| !player_id.is_gaia()) {
+        let mut stockpile = [0; 4];
+        if let Some(player) = players.player(player_id) {
+            for (amount, resource_type) in stockpile.iter_mut().zip(RESOURCES.iter()) {
+                *amount = i32::from(player.stockpile.amount(*resource_type));
+            }
+        }
+        player_samples.insert(player_id,
+                              PlayerSample {
+                                  player_id: player_id,
+                                  stockpile: stockpile,
+                                  villagers: 0,
+                                  military_units: 0,
+                                  army_value: 0,
+                              });
+    }
+    for unit in units {
+        let player_sample = match player_samples.get_mut(&unit.player_id) {
+            Some(player_sample) => player_sample,
+            None => continue,
+        };
+        let unit_info = unit.db(empires);
+        if unit_info.class().is_military() {
+            player_sample.military_units += 1;
+            player_sample.army_value += train_cost(unit_info);
+        } else if unit_info.class().is_villager() {
+            player_sample.villagers += 1;
+        }
+    }
+    Sample {
+        tick: tick,
+        players: player_samples.into_iter().map(|(_, player_sample)| player_sample).collect(),
+    }
+}
+
+fn train_cost(unit_info: &dat::Unit) -> u32 {
+    unit_info.train_costs()
+        .iter()
+        .filter(|cost| cost.enabled && cost.amount > 0 && RESOURCES.contains(&cost.resource_type))
+        .map(|cost| cost.amount as u32)
+        .sum()
+}
+
+fn seconds(tick: usize) -> f32 {
+    tick as f32 / TICKS_PER_SECOND as f32
+}
+
+fn push_array(json: &mut String, name: &str, items: &[String], more: bool) {
+    if items.is_empty() {
+        json.push_str(&format!("  \"{}\": []", name));
+    } else {
+        json.push_str(&format!("  \"{}\": [\n{}\n  ]", name, items.join(",\n")));
+    }
+    json.push_str(if more { ",\n" } else { "\n" });
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::{Cheat, CheatRequest};
+    use identifier::PlayerId;
+    use super::*;
+
+    fn id(player_id: usize) -> PlayerId {
+        player_id.into()
+    }
+
+    #[test]
+    fn test_engagements() {
+        let mut analysis = ReplayAnalysis::new();
+        analysis.record_hit(100, id(1), id(2), 6, false);
+        analysis.record_hit(200, id(2), id(1), 4, false);
+        analysis.record_hit(300, id(1), id(2), 6, true);
+        // Too long after the last hit to be part of the same fight
+        analysis.record_hit(300 + ENGAGEMENT_GAP_TICKS + 1, id(2), id(1), 4, true);
+        // Players that weren't in it start a fight of their own
+        analysis.record_hit(300 + ENGAGEMENT_GAP_TICKS + 1, id(3), id(4), 1, false);
+
+        let engagements = analysis.engagements();
+        assert_eq!(3, engagements.len());
+        assert_eq!((100, 300), (engagements[0].start_tick, engagements[0].end_tick));
+        assert_eq!(12, engagements[0].sides[&id(1)].damage_dealt);
+        assert_eq!(1, engagements[0].sides[&id(2)].units_lost);
+        assert_eq!(Some(id(1)), engagements[0].winner());
+        assert_eq!(Some(id(2)), engagements[1].winner());
+        assert_eq!(None, engagements[2].winner());
+    }
+
+    #[test]
+    fn test_actions_per_minute() {
+        let mut analysis = ReplayAnalysis::new();
+        assert_eq!(0., analysis.actions_per_minute(id(1)));
+        for _ in 0..30 {
+            analysis.record_command(id(1));
+        }
+        analysis.ticks = TICKS_PER_SECOND * 120;
+        assert_eq!(15., analysis.actions_per_minute(id(1)));
+        assert_eq!(0., analysis.actions_per_minute(id(2)));
+    }
+
+    #[test]
+    fn test_export() {
+        let mut analysis = ReplayAnalysis::new();
+        analysis.samples.push(Sample {
+            tick: SAMPLE_INTERVAL_TICKS,
+            players: vec![PlayerSample {
+                              player_id: id(1),
+                              stockpile: [200, 150, 0, 5],
+                              villagers: 4,
+                              military_units: 1,
+                              army_value: 50,
+                          }],
+        });
+        analysis.build_order.push(BuildOrderEntry {
+            tick: 90,
+            player_id: id(1),
+            unit_id: 83.into(),
+            name: "Villager \"Bob\"".into(),
+        });
+        analysis.cheats.push(CheatRecord {
+            tick: 60,
+            request: CheatRequest::new(id(2), Cheat::RevealMap),
+        });
+        assert_eq!("seconds,player,food,wood,stone,gold,villagers,military_units,army_value\n\
+                    5,1,200,150,0,5,4,1,50\n",
+                   analysis.to_csv());
+
+        let json = analysis.to_json();
+        assert!(json.contains("\"players\": [\n    {\"player\": 1, \"commands\": 0, \"apm\": 0.0}\n  ]"));
+        assert!(json.contains("{\"seconds\": 1.5, \"player\": 1, \"unit_id\": 83, \
+                               \"name\": \"Villager \\\"Bob\\\"\"}"));
+        assert!(json.contains("\"cheats\": [\n    {\"seconds\": 1, \"player\": 2, \
+                               \"cheat\": \"reveal map\"}\n  ]"));
+        assert!(json.ends_with("\"engagements\": []\n}\n"));
+    }
+}
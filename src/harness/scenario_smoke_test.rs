@@ -0,0 +1,278 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::GameSettings;
+use editor::{self, EmpiresEditorData, Severity};
+use harness::{HeadlessData, HeadlessRun, TestPlan};
+use identifier::TerrainId;
+use scn;
+use script::ScenarioScript;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// How long each scenario runs for when no tick count is given; ten seconds of game time
+pub const DEFAULT_SMOKE_TEST_TICKS: usize = 600;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SmokeProblem {
+    /// The file couldn't be read as a scenario
+    Parse(String),
+    /// The scenario refers to something that isn't in the game data
+    MissingAsset(String),
+    /// A trigger that refers to something the scenario or game data doesn't have, or a
+    /// scenario script that failed to load or run
+    Trigger(String),
+    /// The run panicked
+    Crash(String),
+}
+
+impl fmt::Display for SmokeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SmokeProblem::Parse(ref message) => write!(f, "parse error: {}", message),
+            SmokeProblem::MissingAsset(ref message) => write!(f, "missing asset: {}", message),
+            SmokeProblem::Trigger(ref message) => write!(f, "trigger error: {}", message),
+            SmokeProblem::Crash(ref message) => write!(f, "crash: {}", message),
+        }
+    }
+}
+
+pub struct SmokeTestResult {
+    pub path: PathBuf,
+    pub problems: Vec<SmokeProblem>,
+}
+
+impl SmokeTestResult {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+pub struct SmokeTestReport {
+    pub results: Vec<SmokeTestResult>,
+}
+
+impl SmokeTestReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed())
+    }
+}
+
+impl fmt::Display for SmokeTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut passed_count = 0;
+        for result in &self.results {
+            if result.passed() {
+                passed_count += 1;
+                try!(writeln!(f, "{}: ok", result.path.display()));
+            } else {
+                try!(writeln!(f, "{}: FAILED", result.path.display()));
+                for problem in &result.problems {
+                    try!(writeln!(f, "    {}", problem));
+                }
+            }
+        }
+        write!(f, "{} of {} scenarios passed", passed_count, self.results.len())
+    }
+}
+
+/// The scenario files in the directory, not counting subdirectories, in order by name
+pub fn scenario_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, String> {
+    let dir = dir.as_ref();
+    let entries = try!(fs::read_dir(dir).map_err(|err| format!("failed to read {}: {}", dir.display(), err)));
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_scenario_file(path))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn is_scenario_file(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => {
+            let extension = extension.to_string_lossy().to_lowercase();
+            extension == "scn" || extension == "scx"
+        }
+        None => false,
+    }
+}
+
+/// Loads each scenario in the directory the way the game would and runs it for a number of
+/// ticks without anyone giving orders, to shake out scenarios that can't be played
+pub fn smoke_test_dir<P: AsRef<Path>>(data: &HeadlessData,
+                                      dir: P,
+                                      ticks: usize)
+                                      -> Result<SmokeTestReport, String> {
+    let paths = try!(scenario_files(dir));
+    let results = paths.into_iter()
+        .map(|path| {
+            info!("Smoke testing {}", path.display());
+            let problems = smoke_test_scenario(data, &path, ticks);
+            SmokeTestResult {
+                path: path,
+                problems: problems,
+            }
+        })
+        .collect();
+    Ok(SmokeTestReport { results: results })
+}
+
+pub fn smoke_test_scenario(data: &HeadlessData, path: &Path, ticks: usize) -> Vec<SmokeProblem> {
+    let mut scenario = match scn::Scenario::read_from_file(path) {
+        Ok(scenario) => scenario,
+        Err(err) => return vec![SmokeProblem::Parse(err.to_string())],
+    };
+
+    let mut problems = Vec::new();
+    let unknown_terrain = unknown_terrain(data, &scenario);
+    for (terrain_id, count) in &unknown_terrain {
+        problems.push(SmokeProblem::MissingAsset(format!("{} tiles of terrain {}, which the game data \
+                                                          doesn't have",
+                                                         count,
+                                                         **terrain_id)));
+    }
+    problems.extend(check_triggers(data, &scenario, path));
+    let script = match ScenarioScript::load_sidecar(path) {
+        Ok(script) => script,
+        Err(err) => {
+            problems.push(SmokeProblem::Trigger(err));
+            None
+        }
+    };
+
+    // Left out the same way the game leaves them out, and a scenario the game won't start isn't run
+    match data.content_gate.gate_scenario(&mut scenario) {
+        Ok(left_out) => problems.extend(left_out.into_iter().map(SmokeProblem::MissingAsset)),
+        Err(err) => {
+            problems.push(SmokeProblem::MissingAsset(err));
+            return problems;
+        }
+    }
+    if !unknown_terrain.is_empty() {
+        return problems;
+    }
+
+    let plan = TestPlan {
+        actions: Vec::new(),
+        expectations: Vec::new(),
+        ticks: ticks,
+        seed: None,
+    };
+    let settings = GameSettings::new();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        HeadlessRun::new(data, &scenario, &settings, script).run_plan(&plan)
+    }));
+    match outcome {
+        Ok(report) => problems.extend(report.errors.into_iter().map(SmokeProblem::Trigger)),
+        Err(payload) => {
+            let message = match payload.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => payload.downcast_ref::<String>().cloned().unwrap_or("unknown panic".into()),
+            };
+            problems.push(SmokeProblem::Crash(message));
+        }
+    }
+    problems
+}
+
+/// Tile counts of the terrain IDs the game data doesn't have
+fn unknown_terrain(data: &HeadlessData, scenario: &scn::Scenario) -> BTreeMap<TerrainId, usize> {
+    let terrain_block = data.empires.terrain_block();
+    let mut unknown = BTreeMap::new();
+    for tile in &scenario.map.tiles {
+        if !terrain_block.has_terrain(tile.terrain_id) {
+            *unknown.entry(tile.terrain_id).or_insert(0) += 1;
+        }
+    }
+    unknown
+}
+
+/// The errors the editor's check finds in the triggers of a script the editor wrote
+fn check_triggers(data: &HeadlessData, scenario: &scn::Scenario, path: &Path) -> Vec<SmokeProblem> {
+    let script_path = ScenarioScript::sidecar_path(path);
+    if !script_path.is_file() {
+        return Vec::new();
+    }
+    let mut source = String::new();
+    if let Err(err) = File::open(&script_path).and_then(|mut file| file.read_to_string(&mut source)) {
+        return vec![SmokeProblem::Trigger(format!("failed to read {}: {}", script_path.display(), err))];
+    }
+    let triggers = match editor::triggers_from_script(&source) {
+        Some(Ok(triggers)) => triggers,
+        Some(Err(err)) => return vec![SmokeProblem::Trigger(format!("{}: {}", script_path.display(), err))],
+        // Handwritten scripts are only checked by running them
+        None => return Vec::new(),
+    };
+    let editor_data = EmpiresEditorData::new(data.empires.clone(), data.content_gate.clone());
+    editor::validate_triggers(scenario, &triggers, &editor_data)
+        .into_iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .map(|problem| SmokeProblem::Trigger(problem.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use super::*;
+
+    #[test]
+    fn test_scenario_files() {
+        let dir = env::temp_dir().join("chariot_smoke_test_scenarios");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested.scn")).unwrap();
+        for name in &["b.scn", "A.SCX", "notes.txt", "b.scn.rhai"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let names: Vec<String> = scenario_files(&dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec!["A.SCX".to_string(), "b.scn".to_string()], names);
+        assert!(scenario_files(&dir).is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let report = SmokeTestReport {
+            results: vec![SmokeTestResult {
+                              path: "a.scn".into(),
+                              problems: Vec::new(),
+                          },
+                          SmokeTestResult {
+                              path: "b.scn".into(),
+                              problems: vec![SmokeProblem::Parse("unexpected end of file".into())],
+                          }],
+        };
+        assert!(!report.passed());
+        assert_eq!("a.scn: ok\n\
+                    b.scn: FAILED\n    parse error: unexpected end of file\n\
+                    1 of 2 scenarios passed",
+                   report.to_string());
+    }
+}
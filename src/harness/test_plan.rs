@@ -0,0 +1,490 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::ResourceType;
+use ecs::{Comparison, WorldQuery};
+use identifier::{PlayerId, UnitId};
+use nalgebra::Vector2;
+use script::ScriptEffect;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use util::parse::parse_number;
+
+/// Something a test plan does to the world on a given tick
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlanAction {
+    /// Carried out the same way as when a scenario script asks for it
+    Effect(ScriptEffect),
+    /// Orders every unit of a type that a player has to walk to a tile
+    Move {
+        player_id: PlayerId,
+        unit_id: UnitId,
+        tile: Vector2<i32>,
+    },
+    /// Queues units for training across every building of a type that a player has
+    Train {
+        player_id: PlayerId,
+        building_id: UnitId,
+        unit_id: UnitId,
+        count: usize,
+    },
+}
+
+/// When an expectation has to be true
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Deadline {
+    /// On exactly this tick
+    At(usize),
+    /// On any tick up to and including this one
+    By(usize),
+}
+
+impl Deadline {
+    pub fn tick(&self) -> usize {
+        match *self {
+            Deadline::At(tick) | Deadline::By(tick) => tick,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expectation {
+    /// The plan line it's written on, counting from 1
+    pub line: usize,
+    pub deadline: Deadline,
+    pub query: WorldQuery,
+    pub comparison: Comparison,
+    pub value: i64,
+    /// The line as it's written in the plan
+    pub text: String,
+}
+
+/// A script of actions and expectations for running a scenario without a window, to catch
+/// regressions end to end. Plans are text, one step per line, with `#` starting a comment:
+///
+/// * `at <tick> spawn <player> <unit id> <x> <y>` spawns a unit on a tile
+/// * `at <tick> give <player> <food|wood|stone|gold> <amount>` adds to a player's stockpile
+/// * `at <tick> move <player> <unit id> <x> <y>` walks the player's units of a type to a tile
+/// * `at <tick> train <player> <building unit id> <unit id> [count]` trains units at the
+///   player's buildings of a type
+/// * `at <tick> expect <query> <comparison> <value>` has to hold on that tick, and
+///   `by <tick> expect ...` on any tick up to it; queries are the ones scenario triggers use
+/// * `seed <number>` sets the seed for the game's random numbers, and `run <ticks>` makes the
+///   run last at least that long
+///
+/// The world starts on tick 0. A tick's expectations are checked before its actions happen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestPlan {
+    /// In the order they happen
+    pub actions: Vec<(usize, PlanAction)>,
+    pub expectations: Vec<Expectation>,
+    /// The tick the run ends on
+    pub ticks: usize,
+    pub seed: Option<u64>,
+}
+
+impl TestPlan {
+    pub fn parse(text: &str) -> Result<TestPlan, String> {
+        let mut plan = TestPlan {
+            actions: Vec::new(),
+            expectations: Vec::new(),
+            ticks: 0,
+            seed: None,
+        };
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            try!(plan.parse_line(index + 1, line).map_err(|err| format!("line {}: {}", index + 1, err)));
+        }
+        // Sorting is stable, so actions on the same tick keep the order they're written in
+        plan.actions.sort_by_key(|&(tick, _)| tick);
+        let last_step = plan.actions
+            .iter()
+            .map(|&(tick, _)| tick)
+            .chain(plan.expectations.iter().map(|expectation| expectation.deadline.tick()))
+            .max()
+            .unwrap_or(0);
+        plan.ticks = plan.ticks.max(last_step);
+        Ok(plan)
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<TestPlan, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("failed to read {}: {}", path.display(), err)));
+        TestPlan::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let keyword = words[0].to_lowercase();
+        match (&keyword[..], words.len()) {
+            ("seed", 2) => {
+                let seed = try!(words[1].parse().map_err(|_| format!("invalid seed \"{}\"", words[1])));
+                self.seed = Some(seed);
+                return Ok(());
+            }
+            ("seed", _) => return Err("usage: seed <number>".into()),
+            ("run", 2) => {
+                self.ticks = try!(parse_number(words[1], "tick count"));
+                return Ok(());
+            }
+            ("run", _) => return Err("usage: run <ticks>".into()),
+            ("at", count) | ("by", count) if count >= 3 => {}
+            _ => return Err(format!("unknown step \"{}\"", line)),
+        }
+
+        let tick = try!(parse_number(words[1], "tick"));
+        let verb = words[2].to_lowercase();
+        let arguments = &words[3..];
+        if verb == "expect" {
+            let deadline = if keyword == "at" {
+                Deadline::At(tick)
+            } else {
+                Deadline::By(tick)
+            };
+            let expectation = try!(parse_expectation(line_number, deadline, arguments, line));
+            self.expectations.push(expectation);
+            return Ok(());
+        }
+        if keyword == "by" {
+            return Err("only expectations can be given a deadline with \"by\"".into());
+        }
+        let action = try!(parse_action(&verb, arguments));
+        self.actions.push((tick, action));
+        Ok(())
+    }
+}
+
+fn parse_expectation(line_number: usize,
+                     deadline: Deadline,
+                     arguments: &[&str],
+                     line: &str)
+                     -> Result<Expectation, String> {
+    const USAGE: &'static str = "usage: at|by <tick> expect <query> <comparison> <value>";
+    if arguments.len() < 3 {
+        return Err(USAGE.into());
+    }
+    let (query_words, rest) = arguments.split_at(arguments.len() - 2);
+    let comparison = try!(Comparison::parse(rest[0])
+        .ok_or(format!("unknown comparison \"{}\"; use <, <=, ==, >=, or >", rest[0])));
+    let value = try!(rest[1].parse().map_err(|_| format!("invalid value \"{}\"", rest[1])));
+    Ok(Expectation {
+        line: line_number,
+        deadline: deadline,
+        query: try!(WorldQuery::parse(&query_words.join(" "))),
+        comparison: comparison,
+        value: value,
+        text: line.to_string(),
+    })
+}
+
+fn parse_action(verb: &str, arguments: &[&str]) -> Result<PlanAction, String> {
+    match (verb, arguments.len()) {
+        ("spawn", 4) | ("move", 4) => {
+            let player_id = try!(parse_number(arguments[0], "player id"));
+            let unit_id = try!(parse_number(arguments[1], "unit id"));
+            let mut tile = [0i32; 2];
+            for (coordinate, argument) in tile.iter_mut().zip(&arguments[2..]) {
+                *coordinate = try!(argument.parse()
+                    .map_err(|_| format!("invalid coordinate \"{}\"", argument)));
+            }
+            let tile = Vector2::new(tile[0], tile[1]);
+            if verb == "spawn" {
+                Ok(PlanAction::Effect(ScriptEffect::Spawn {
+                    player_id: player_id.into(),
+                    unit_id: unit_id.into(),
+                    position: tile,
+                }))
+            } else {
+                Ok(PlanAction::Move {
+                    player_id: player_id.into(),
+                    unit_id: unit_id.into(),
+                    tile: tile,
+                })
+            }
+        }
+        ("spawn", _) => Err("usage: at <tick> spawn <player> <unit id> <x> <y>".into()),
+        ("move", _) => Err("usage: at <tick> move <player> <unit id> <x> <y>".into()),
+        ("give", 3) => {
+            let player_id = try!(parse_number(arguments[0], "player id"));
+            let resource_type = match &arguments[1].to_lowercase()[..] {
+                "food" => ResourceType::Food,
+                "wood" => ResourceType::Wood,
+                "stone" => ResourceType::Stone,
+                "gold" => ResourceType::Gold,
+                _ => return Err(format!("unknown resource \"{}\"", arguments[1])),
+            };
+            let amount: i32 = try!(arguments[2].parse()
+                .map_err(|_| format!("invalid amount \"{}\"", arguments[2])));
+            Ok(PlanAction::Effect(ScriptEffect::AddToStockpile(player_id.into(),
+                                                               resource_type,
+                                                               amount.into())))
+        }
+        ("give", _) => Err("usage: at <tick> give <player> <food|wood|stone|gold> <amount>".into()),
+        ("train", 3) | ("train", 4) => {
+            let count = match arguments.get(3) {
+                Some(count) => try!(parse_number(count, "count")),
+                None => 1,
+            };
+            Ok(PlanAction::Train {
+                player_id: try!(parse_number(arguments[0], "player id")).into(),
+                building_id: try!(parse_number(arguments[1], "building unit id")).into(),
+                unit_id: try!(parse_number(arguments[2], "unit id")).into(),
+                count: count,
+            })
+        }
+        ("train", _) => Err("usage: at <tick> train <player> <building unit id> <unit id> [count]".into()),
+        _ => Err(format!("unknown action \"{}\"", verb)),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Passed { tick: usize },
+    /// What the query last answered before the deadline passed
+    Failed { tick: usize, actual: i64 },
+}
+
+/// Keeps track of which of a plan's expectations have been decided over a run
+pub struct PlanChecker {
+    expectations: Vec<Expectation>,
+    outcomes: Vec<Option<Outcome>>,
+}
+
+impl PlanChecker {
+    pub fn new(plan: &TestPlan) -> PlanChecker {
+        PlanChecker {
+            expectations: plan.expectations.clone(),
+            outcomes: vec![None; plan.expectations.len()],
+        }
+    }
+
+    /// Whether any expectation has to be checked on the tick, so the caller can skip
+    /// looking at the world when none does
+    pub fn is_due(&self, tick: usize) -> bool {
+        (0..self.expectations.len()).any(|index| self.is_due_at(index, tick))
+    }
+
+    fn is_due_at(&self, index: usize, tick: usize) -> bool {
+        if self.outcomes[index].is_some() {
+            return false;
+        }
+        match self.expectations[index].deadline {
+            Deadline::At(deadline) => tick == deadline,
+            Deadline::By(deadline) => tick <= deadline,
+        }
+    }
+
+    /// Decides the expectations that are due on the tick; `evaluate` answers queries about
+    /// the world as it is on that tick
+    pub fn check<F>(&mut self, tick: usize, mut evaluate: F)
+        where F: FnMut(&WorldQuery) -> i64
+    {
+        for index in 0..self.expectations.len() {
+            if !self.is_due_at(index, tick) {
+                continue;
+            }
+            let expectation = &self.expectations[index];
+            let actual = evaluate(&expectation.query);
+            if expectation.comparison.holds(actual, expectation.value) {
+                self.outcomes[index] = Some(Outcome::Passed { tick: tick });
+            } else if tick == expectation.deadline.tick() {
+                self.outcomes[index] = Some(Outcome::Failed {
+                    tick: tick,
+                    actual: actual,
+                });
+            }
+        }
+    }
+
+    /// Whether every expectation has been decided
+    pub fn is_done(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.is_some())
+    }
+
+    /// The outcomes of the run; `errors` are the problems that came up in it, such as a
+    /// failing scenario script, which fail the run by themselves
+    pub fn finish(self, errors: Vec<String>) -> PlanReport {
+        let results = self.expectations
+            .into_iter()
+            .zip(self.outcomes)
+            .map(|(expectation, outcome)| {
+                // Only possible if the run stopped early
+                let outcome = outcome.unwrap_or(Outcome::Failed {
+                    tick: expectation.deadline.tick(),
+                    actual: 0,
+                });
+                (expectation, outcome)
+            })
+            .collect();
+        PlanReport {
+            results: results,
+            errors: errors,
+        }
+    }
+}
+
+pub struct PlanReport {
+    pub results: Vec<(Expectation, Outcome)>,
+    pub errors: Vec<String>,
+}
+
+impl PlanReport {
+    pub fn passed(&self) -> bool {
+        self.errors.is_empty() &&
+        self.results.iter().all(|&(_, outcome)| match outcome {
+            Outcome::Passed { .. } => true,
+            Outcome::Failed { .. } => false,
+        })
+    }
+}
+
+impl fmt::Display for PlanReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut passed_count = 0;
+        for &(ref expectation, outcome) in &self.results {
+            match outcome {
+                Outcome::Passed { tick } => {
+                    passed_count += 1;
+                    try!(writeln!(f,
+                                  "line {}: {}: passed on tick {}",
+                                  expectation.line,
+                                  expectation.text,
+                                  tick));
+                }
+                Outcome::Failed { tick, actual } => {
+                    try!(writeln!(f,
+                                  "line {}: {}: FAILED on tick {}, where the query was {}",
+                                  expectation.line,
+                                  expectation.text,
+                                  tick,
+                                  actual));
+                }
+            }
+        }
+        for error in &self.errors {
+            try!(writeln!(f, "error: {}", error));
+        }
+        write!(f, "{} of {} expectations passed", passed_count, self.results.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::WorldQuery;
+    use nalgebra::Vector2;
+    use script::ScriptEffect;
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let plan = TestPlan::parse("
+            # Player 2 should expand
+            seed 7
+            at 10 train 2 109 83 3
+            at 0 spawn 2 109 10 12
+            at 10 move 2 83 20 -1  # off the map on purpose
+            by 3000 expect count type 109 owner 2 >= 2
+            at 50 expect stockpile 1 food < 100
+        ")
+            .unwrap();
+        assert_eq!(Some(7), plan.seed);
+        assert_eq!(3000, plan.ticks);
+        assert_eq!(vec![(0,
+                         PlanAction::Effect(ScriptEffect::Spawn {
+                             player_id: 2.into(),
+                             unit_id: 109.into(),
+                             position: Vector2::new(10, 12),
+                         })),
+                        (10,
+                         PlanAction::Train {
+                             player_id: 2.into(),
+                             building_id: 109.into(),
+                             unit_id: 83.into(),
+                             count: 3,
+                         }),
+                        (10,
+                         PlanAction::Move {
+                             player_id: 2.into(),
+                             unit_id: 83.into(),
+                             tile: Vector2::new(20, -1),
+                         })],
+                   plan.actions);
+        assert_eq!(2, plan.expectations.len());
+        assert_eq!(7, plan.expectations[0].line);
+        assert_eq!(Deadline::By(3000), plan.expectations[0].deadline);
+        assert_eq!(WorldQuery::Stockpile(1.into(), ResourceType::Food),
+                   plan.expectations[1].query);
+
+        assert_eq!(5000, TestPlan::parse("run 5000\nat 10 give 1 gold 5").unwrap().ticks);
+        assert!(TestPlan::parse("by 10 give 1 gold 5").is_err());
+        assert!(TestPlan::parse("at 10 expect count owner 1 ~ 5").is_err());
+        assert!(TestPlan::parse("at 10 expect nothing == 5").is_err());
+        assert!(TestPlan::parse("at ten give 1 gold 5").is_err());
+        assert!(TestPlan::parse("at 1 give 1 silver 5").unwrap_err().starts_with("line 1:"));
+    }
+
+    #[test]
+    fn test_checker() {
+        let plan = TestPlan::parse("
+            by 20 expect stockpile 1 food >= 100
+            at 10 expect stockpile 1 food == 0
+            by 5 expect stockpile 1 wood > 0
+        ")
+            .unwrap();
+        let mut checker = PlanChecker::new(&plan);
+        for tick in 0..plan.ticks + 1 {
+            let food = tick as i64 * 10;
+            if checker.is_due(tick) {
+                checker.check(tick, |query| match *query {
+                    WorldQuery::Stockpile(_, ResourceType::Food) => food,
+                    _ => 0,
+                });
+            }
+        }
+        assert!(checker.is_done());
+        let report = checker.finish(Vec::new());
+        assert_eq!(vec![Outcome::Passed { tick: 10 },
+                        Outcome::Failed {
+                            tick: 10,
+                            actual: 100,
+                        },
+                        Outcome::Failed { tick: 5, actual: 0 }],
+                   report.results.iter().map(|&(_, outcome)| outcome).collect::<Vec<_>>());
+        assert!(!report.passed());
+        assert!(format!("{}", report).ends_with("1 of 3 expectations passed"));
+
+        let plan = TestPlan::parse("at 0 expect count == 0").unwrap();
+        let mut checker = PlanChecker::new(&plan);
+        checker.check(0, |_| 0);
+        assert!(checker.finish(Vec::new()).passed());
+        let checker = PlanChecker::new(&plan);
+        assert!(!checker.finish(vec!["the script failed".into()]).passed());
+    }
+}
@@ -0,0 +1,142 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use media::KeyState;
+use nalgebra::Vector2;
+use types::Rect;
+
+/// How far, in viewport pixels, the pointer has to move while select is held for it to drag
+/// out a box rather than click
+pub const DRAG_THRESHOLD: i32 = 6;
+
+/// Turns holding select down and moving the pointer into a selection box
+pub struct DragSelect {
+    start: Option<Vector2<i32>>,
+    dragging: bool,
+}
+
+/// What holding select did this update
+#[derive(Clone, Debug, PartialEq)]
+pub struct DragOutput {
+    /// The select button's state, except that letting go at the end of a drag isn't a click
+    pub click: KeyState,
+    pub drag_box: Option<Rect>,
+    pub finished_drag_box: Option<Rect>,
+}
+
+impl DragOutput {
+    pub fn none() -> DragOutput {
+        DragOutput {
+            click: KeyState::Up,
+            drag_box: None,
+            finished_drag_box: None,
+        }
+    }
+}
+
+impl DragSelect {
+    pub fn new() -> DragSelect {
+        DragSelect {
+            start: None,
+            dragging: false,
+        }
+    }
+
+    pub fn update(&mut self, select: KeyState, position: Vector2<i32>) -> DragOutput {
+        if select == KeyState::TransitionDown {
+            self.start = Some(position);
+            self.dragging = false;
+        }
+
+        let mut output = DragOutput {
+            click: select,
+            drag_box: None,
+            finished_drag_box: None,
+        };
+        if let Some(start) = self.start {
+            let moved = position - start;
+            if moved.x.abs() > DRAG_THRESHOLD || moved.y.abs() > DRAG_THRESHOLD {
+                self.dragging = true;
+            }
+            if self.dragging {
+                let selection_box = box_between(start, position);
+                if select.is_down() {
+                    output.drag_box = Some(selection_box);
+                } else {
+                    output.finished_drag_box = Some(selection_box);
+                    output.click = KeyState::Up;
+                }
+            }
+        }
+        if select.is_up() {
+            self.cancel();
+        }
+        output
+    }
+
+    /// Forgets the drag, for when a gesture turns out to be something else
+    pub fn cancel(&mut self) {
+        self.start = None;
+        self.dragging = false;
+    }
+}
+
+/// The box with the two points at opposite corners
+pub fn box_between(first: Vector2<i32>, second: Vector2<i32>) -> Rect {
+    Rect::of(first.x.min(second.x),
+             first.y.min(second.y),
+             (first.x - second.x).abs(),
+             (first.y - second.y).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use media::KeyState;
+    use nalgebra::Vector2;
+    use super::DragSelect;
+    use types::Rect;
+
+    #[test]
+    fn test_click_without_moving() {
+        let mut drag = DragSelect::new();
+        drag.update(KeyState::TransitionDown, Vector2::new(10, 10));
+        drag.update(KeyState::Down, Vector2::new(12, 11));
+        let output = drag.update(KeyState::TransitionUp, Vector2::new(12, 11));
+        assert_eq!(KeyState::TransitionUp, output.click);
+        assert_eq!(None, output.finished_drag_box);
+    }
+
+    #[test]
+    fn test_drag_out_a_box() {
+        let mut drag = DragSelect::new();
+        drag.update(KeyState::TransitionDown, Vector2::new(50, 40));
+        let output = drag.update(KeyState::Down, Vector2::new(20, 60));
+        assert_eq!(Some(Rect::of(20, 40, 30, 20)), output.drag_box);
+
+        let output = drag.update(KeyState::TransitionUp, Vector2::new(10, 60));
+        assert_eq!(KeyState::Up, output.click);
+        assert_eq!(None, output.drag_box);
+        assert_eq!(Some(Rect::of(10, 40, 40, 20)), output.finished_drag_box);
+
+        let output = drag.update(KeyState::Up, Vector2::new(10, 60));
+        assert_eq!(None, output.finished_drag_box);
+    }
+}
@@ -0,0 +1,148 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use media::{GamepadButton, GamepadState, KeyState};
+use nalgebra::Vector2;
+use super::{DragOutput, DragSelect, RadialMenu};
+
+/// Viewport pixels a second that the cursor moves with the left stick pushed all the way over
+const CURSOR_SPEED: f32 = 600.0;
+
+/// Button that holds the radial command menu open
+const RADIAL_MENU_BUTTON: GamepadButton = GamepadButton::Y;
+
+/// Buttons that work like the keys for these actions
+const BUTTON_ACTIONS: [(GamepadButton, KeyAction); 4] =
+    [(GamepadButton::RightShoulder, KeyAction::AddToSelection),
+     (GamepadButton::LeftShoulder, KeyAction::NextSubgroup),
+     (GamepadButton::X, KeyAction::NextIdleVillager),
+     (GamepadButton::Start, KeyAction::ToggleScoreScreen)];
+
+/// Drives the game with a gamepad: the left stick moves a cursor, A selects (and drags out a
+/// selection box when held), B orders the selection, the right stick and d-pad scroll, and
+/// holding Y opens a radial menu of commands to pick from with the left stick
+pub struct GamepadScheme {
+    cursor: Vector2<f32>,
+    drag: DragSelect,
+    radial_menu: Option<RadialMenu>,
+}
+
+/// What the gamepad did this update
+pub struct GamepadOutput {
+    /// Whether the gamepad moved the cursor, which the pointer then follows
+    pub cursor_moved: bool,
+    pub cursor: Vector2<i32>,
+    pub select: DragOutput,
+    pub command: KeyState,
+    pub scroll: Vector2<f32>,
+    pub actions: Vec<(KeyAction, KeyState)>,
+    /// An action picked from the radial menu by letting go of it
+    pub picked_action: Option<KeyAction>,
+    pub radial_menu: Option<RadialMenu>,
+}
+
+impl GamepadScheme {
+    pub fn new() -> GamepadScheme {
+        GamepadScheme {
+            cursor: Vector2::new(0.0, 0.0),
+            drag: DragSelect::new(),
+            radial_menu: None,
+        }
+    }
+
+    /// `pointer` is where the pointer was left, so that the cursor carries on from wherever
+    /// the mouse or a touch last put it
+    pub fn update(&mut self,
+                  gamepad: &GamepadState,
+                  pointer: Vector2<i32>,
+                  viewport_size: Vector2<i32>,
+                  seconds: f32)
+                  -> GamepadOutput {
+        if rounded(self.cursor) != pointer {
+            self.cursor = Vector2::new(pointer.x as f32, pointer.y as f32);
+        }
+        let mut output = GamepadOutput {
+            cursor_moved: false,
+            cursor: pointer,
+            select: DragOutput::none(),
+            command: KeyState::Up,
+            scroll: Vector2::new(0.0, 0.0),
+            actions: Vec::new(),
+            picked_action: None,
+            radial_menu: None,
+        };
+        if !gamepad.connected {
+            self.drag.cancel();
+            self.radial_menu = None;
+            return output;
+        }
+
+        let button = |button| gamepad.buttons.key_state(button);
+        let menu_button = button(RADIAL_MENU_BUTTON);
+        if menu_button == KeyState::TransitionDown {
+            self.drag.cancel();
+            self.radial_menu = Some(RadialMenu::new(pointer));
+        }
+        if let Some(mut radial_menu) = self.radial_menu.take() {
+            radial_menu.point(gamepad.left_stick);
+            if menu_button.is_down() {
+                self.radial_menu = Some(radial_menu.clone());
+                output.radial_menu = Some(radial_menu);
+            } else {
+                output.picked_action = radial_menu.picked_action();
+            }
+        } else {
+            let stick = gamepad.left_stick;
+            if stick.x != 0.0 || stick.y != 0.0 {
+                self.cursor.x = (self.cursor.x + stick.x * CURSOR_SPEED * seconds)
+                    .max(0.0)
+                    .min(viewport_size.x as f32 - 1.0);
+                self.cursor.y = (self.cursor.y + stick.y * CURSOR_SPEED * seconds)
+                    .max(0.0)
+                    .min(viewport_size.y as f32 - 1.0);
+                output.cursor_moved = true;
+                output.cursor = rounded(self.cursor);
+            }
+            output.select = self.drag.update(button(GamepadButton::A), output.cursor);
+            output.command = button(GamepadButton::B);
+        }
+
+        output.scroll = gamepad.right_stick;
+        for &(button_name, x, y) in &[(GamepadButton::DPadUp, 0.0, -1.0),
+                                      (GamepadButton::DPadDown, 0.0, 1.0),
+                                      (GamepadButton::DPadLeft, -1.0, 0.0),
+                                      (GamepadButton::DPadRight, 1.0, 0.0)] {
+            if button(button_name).is_down() {
+                output.scroll.x += x;
+                output.scroll.y += y;
+            }
+        }
+        output.actions = BUTTON_ACTIONS.iter()
+            .map(|&(button_name, action)| (action, button(button_name)))
+            .collect();
+        output
+    }
+}
+
+fn rounded(position: Vector2<f32>) -> Vector2<i32> {
+    Vector2::new(position.x.round() as i32, position.y.round() as i32)
+}
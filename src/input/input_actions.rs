@@ -0,0 +1,106 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use media::KeyState;
+use nalgebra::Vector2;
+use std::collections::HashMap;
+use super::RadialMenu;
+use types::{Fixed, Rect};
+
+/// What the player asked for this update, whichever of the keyboard and mouse, a gamepad, or
+/// the touch screen it was asked with. Systems read this instead of the devices so that they
+/// all drive the same selections and orders.
+pub struct InputActions {
+    action_states: HashMap<KeyAction, KeyState>,
+    /// Where selections and orders are aimed, in viewport pixels
+    pub pointer: Vector2<i32>,
+    /// Selects what's under the pointer on TransitionUp, like the left mouse button
+    pub select: KeyState,
+    /// Orders the selected units to what's under the pointer on TransitionUp, like the right
+    /// mouse button
+    pub command: KeyState,
+    /// A selection box that's being dragged out, in viewport pixels
+    pub drag_box: Option<Rect>,
+    /// Set for the update that a selection box is let go on, to select what's in it
+    pub finished_drag_box: Option<Rect>,
+    /// How fast to scroll the camera, from -1 to 1 on each axis
+    pub scroll: Vector2<Fixed>,
+    /// Viewport pixels to move the camera by, from panning with two fingers
+    pub pan: Vector2<i32>,
+    /// The gamepad's radial command menu, while it's open
+    pub radial_menu: Option<RadialMenu>,
+    /// Whether the pointer is the gamepad's cursor, which gets drawn since there's no mouse
+    /// cursor where it is
+    pub gamepad_cursor: bool,
+}
+
+impl InputActions {
+    pub fn new() -> InputActions {
+        InputActions {
+            action_states: HashMap::new(),
+            pointer: Vector2::new(0, 0),
+            select: KeyState::Up,
+            command: KeyState::Up,
+            drag_box: None,
+            finished_drag_box: None,
+            scroll: Vector2::new(0.into(), 0.into()),
+            pan: Vector2::new(0, 0),
+            radial_menu: None,
+            gamepad_cursor: false,
+        }
+    }
+
+    pub fn action_state(&self, action: KeyAction) -> KeyState {
+        self.action_states.get(&action).cloned().unwrap_or(KeyState::Up)
+    }
+
+    /// Combines the state with the one the action already has this update, so that pressing
+    /// it on any device counts
+    pub fn press_action(&mut self, action: KeyAction, state: KeyState) {
+        let combined = combine_states(self.action_state(action), state);
+        self.action_states.insert(action, combined);
+    }
+
+    /// Takes back a press, for when another action that shares its key has used it
+    pub fn release_action(&mut self, action: KeyAction) {
+        self.action_states.remove(&action);
+    }
+
+    /// Clears everything for the next update
+    pub fn reset(&mut self) {
+        let pointer = self.pointer;
+        *self = InputActions::new();
+        self.pointer = pointer;
+    }
+}
+
+/// The state of two buttons that do the same thing: down if either is, and changing whenever
+/// either of them changes
+pub fn combine_states(first: KeyState, second: KeyState) -> KeyState {
+    use media::KeyState::*;
+    match (first, second) {
+        (TransitionDown, _) | (_, TransitionDown) => TransitionDown,
+        (Down, _) | (_, Down) => Down,
+        (TransitionUp, _) | (_, TransitionUp) => TransitionUp,
+        (Up, Up) => Up,
+    }
+}
@@ -0,0 +1,251 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::{KeyAction, KeyBindings};
+use media::{GamepadState, Key, KeyState, KeyStates, MouseButton, Touch};
+use nalgebra::Vector2;
+use super::{DragSelect, GamepadScheme, InputActions, TouchScheme, combine_states};
+
+/// Everything the devices reported this update
+pub struct RawInput<'a> {
+    pub keys: &'a KeyStates<Key>,
+    pub mouse_position: Vector2<i32>,
+    pub mouse_buttons: &'a KeyStates<MouseButton>,
+    pub gamepad: &'a GamepadState,
+    pub touches: &'a [Touch],
+    pub viewport_size: Vector2<i32>,
+}
+
+/// Turns keyboard and mouse, gamepad and touch input into the same `InputActions`, so that the
+/// game doesn't care which one is being played with
+pub struct InputMapper {
+    mouse_drag: DragSelect,
+    gamepad: GamepadScheme,
+    touch: TouchScheme,
+    last_mouse_position: Option<Vector2<i32>>,
+    /// Actions picked from the radial menu last update, which are let go of this update
+    releasing: Vec<KeyAction>,
+}
+
+impl InputMapper {
+    pub fn new() -> InputMapper {
+        InputMapper {
+            mouse_drag: DragSelect::new(),
+            gamepad: GamepadScheme::new(),
+            touch: TouchScheme::new(),
+            last_mouse_position: None,
+            releasing: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self,
+                  raw: &RawInput,
+                  key_bindings: &KeyBindings,
+                  seconds: f32,
+                  actions: &mut InputActions) {
+        actions.reset();
+        for action in KeyAction::all() {
+            actions.press_action(action, key_bindings.key_state(raw.keys, action));
+        }
+        for action in self.releasing.drain(..) {
+            actions.press_action(action, KeyState::TransitionUp);
+        }
+
+        let mut scroll = Vector2::new(0.0, 0.0);
+        for &(action, x, y) in &[(KeyAction::ScrollUp, 0.0, -1.0),
+                                 (KeyAction::ScrollDown, 0.0, 1.0),
+                                 (KeyAction::ScrollLeft, -1.0, 0.0),
+                                 (KeyAction::ScrollRight, 1.0, 0.0)] {
+            if actions.action_state(action).is_down() {
+                scroll.x += x;
+                scroll.y += y;
+            }
+        }
+
+        // The mouse only takes the pointer back once it's moved, so that a mouse sitting
+        // still doesn't fight the gamepad cursor or a touch
+        if self.last_mouse_position != Some(raw.mouse_position) {
+            self.last_mouse_position = Some(raw.mouse_position);
+            actions.pointer = raw.mouse_position;
+            actions.gamepad_cursor = false;
+        }
+        let mouse = self.mouse_drag.update(raw.mouse_buttons.key_state(MouseButton::Left), actions.pointer);
+        actions.select = mouse.click;
+        actions.drag_box = mouse.drag_box;
+        actions.finished_drag_box = mouse.finished_drag_box;
+        actions.command = raw.mouse_buttons.key_state(MouseButton::Right);
+
+        let gamepad = self.gamepad.update(raw.gamepad, actions.pointer, raw.viewport_size, seconds);
+        if gamepad.cursor_moved {
+            actions.pointer = gamepad.cursor;
+            actions.gamepad_cursor = true;
+        }
+        actions.select = combine_states(actions.select, gamepad.select.click);
+        actions.drag_box = actions.drag_box.or(gamepad.select.drag_box);
+        actions.finished_drag_box = actions.finished_drag_box.or(gamepad.select.finished_drag_box);
+        actions.command = combine_states(actions.command, gamepad.command);
+        scroll.x += gamepad.scroll.x;
+        scroll.y += gamepad.scroll.y;
+        for (action, state) in gamepad.actions {
+            actions.press_action(action, state);
+        }
+        if let Some(action) = gamepad.picked_action {
+            actions.press_action(action, KeyState::TransitionDown);
+            self.releasing.push(action);
+        }
+        actions.radial_menu = gamepad.radial_menu;
+
+        let touch = self.touch.update(raw.touches);
+        if let Some(pointer) = touch.pointer {
+            actions.pointer = pointer;
+            actions.gamepad_cursor = false;
+        }
+        actions.select = combine_states(actions.select, touch.select.click);
+        actions.drag_box = actions.drag_box.or(touch.select.drag_box);
+        actions.finished_drag_box = actions.finished_drag_box.or(touch.select.finished_drag_box);
+        actions.command = combine_states(actions.command, touch.command);
+        actions.pan = touch.pan;
+
+        actions.scroll = Vector2::new(scroll.x.max(-1.0).min(1.0).into(),
+                                      scroll.y.max(-1.0).min(1.0).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{KeyAction, KeyBindings};
+    use input::InputActions;
+    use media::{GamepadButton, GamepadState, Key, KeyState, KeyStates, MouseButton, Touch};
+    use nalgebra::Vector2;
+    use std::collections::HashMap;
+    use super::{InputMapper, RawInput};
+    use types::Fixed;
+
+    struct Devices {
+        keys: KeyStates<Key>,
+        mouse_position: Vector2<i32>,
+        mouse_buttons: KeyStates<MouseButton>,
+        gamepad: GamepadState,
+        touches: Vec<Touch>,
+    }
+
+    impl Devices {
+        fn new() -> Devices {
+            Devices {
+                keys: KeyStates::new(HashMap::new()),
+                mouse_position: Vector2::new(0, 0),
+                mouse_buttons: KeyStates::new(HashMap::new()),
+                gamepad: GamepadState::new(),
+                touches: Vec::new(),
+            }
+        }
+
+        fn update(&self, mapper: &mut InputMapper, actions: &mut InputActions) {
+            let raw = RawInput {
+                keys: &self.keys,
+                mouse_position: self.mouse_position,
+                mouse_buttons: &self.mouse_buttons,
+                gamepad: &self.gamepad,
+                touches: &self.touches,
+                viewport_size: Vector2::new(800, 600),
+            };
+            mapper.update(&raw, &KeyBindings::new(), 0.1, actions);
+        }
+    }
+
+    #[test]
+    fn test_keys_trigger_their_actions() {
+        let mut devices = Devices::new();
+        let (mut mapper, mut actions) = (InputMapper::new(), InputActions::new());
+        let bindings = KeyBindings::new();
+        devices.keys.0.insert(bindings.key(KeyAction::ToggleMarket), KeyState::TransitionDown);
+        devices.keys.0.insert(bindings.key(KeyAction::ScrollLeft), KeyState::Down);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(KeyState::TransitionDown, actions.action_state(KeyAction::ToggleMarket));
+        assert_eq!(Vector2::new(Fixed::from(-1), 0.into()), actions.scroll);
+    }
+
+    #[test]
+    fn test_gamepad_and_mouse_share_the_pointer() {
+        let mut devices = Devices::new();
+        let (mut mapper, mut actions) = (InputMapper::new(), InputActions::new());
+        devices.mouse_position = Vector2::new(100, 100);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(100, 100), actions.pointer);
+
+        devices.gamepad.connected = true;
+        devices.gamepad.left_stick = Vector2::new(1.0, 0.0);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(160, 100), actions.pointer);
+        assert!(actions.gamepad_cursor);
+
+        devices.gamepad.left_stick = Vector2::new(0.0, 0.0);
+        devices.gamepad.buttons.0.insert(GamepadButton::A, KeyState::TransitionUp);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(160, 100), actions.pointer);
+        assert_eq!(KeyState::TransitionUp, actions.select);
+
+        devices.gamepad.buttons.0.clear();
+        devices.mouse_position = Vector2::new(50, 50);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(50, 50), actions.pointer);
+        assert!(!actions.gamepad_cursor);
+    }
+
+    #[test]
+    fn test_radial_menu_picks_an_action() {
+        let mut devices = Devices::new();
+        let (mut mapper, mut actions) = (InputMapper::new(), InputActions::new());
+        devices.gamepad.connected = true;
+        devices.gamepad.buttons.0.insert(GamepadButton::Y, KeyState::TransitionDown);
+        devices.update(&mut mapper, &mut actions);
+        assert!(actions.radial_menu.is_some());
+
+        devices.gamepad.buttons.0.insert(GamepadButton::Y, KeyState::Down);
+        devices.gamepad.left_stick = Vector2::new(0.0, -1.0);
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(0, 0), actions.pointer);
+
+        devices.gamepad.buttons.0.insert(GamepadButton::Y, KeyState::TransitionUp);
+        devices.update(&mut mapper, &mut actions);
+        assert!(actions.radial_menu.is_none());
+        assert_eq!(KeyState::TransitionDown, actions.action_state(KeyAction::NextIdleVillager));
+
+        devices.gamepad.buttons.0.clear();
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(KeyState::TransitionUp, actions.action_state(KeyAction::NextIdleVillager));
+    }
+
+    #[test]
+    fn test_touch_tap_selects_where_it_was() {
+        let mut devices = Devices::new();
+        let (mut mapper, mut actions) = (InputMapper::new(), InputActions::new());
+        devices.touches.push(Touch {
+            id: 1,
+            position: Vector2::new(300, 200),
+        });
+        devices.update(&mut mapper, &mut actions);
+        devices.touches.clear();
+        devices.update(&mut mapper, &mut actions);
+        assert_eq!(Vector2::new(300, 200), actions.pointer);
+        assert_eq!(KeyState::TransitionUp, actions.select);
+    }
+}
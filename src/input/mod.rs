@@ -0,0 +1,34 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+mod drag_select;
+mod gamepad_scheme;
+mod input_actions;
+mod input_mapper;
+mod radial_menu;
+mod touch_scheme;
+
+pub use self::drag_select::{DRAG_THRESHOLD, DragOutput, DragSelect, box_between};
+pub use self::gamepad_scheme::{GamepadOutput, GamepadScheme};
+pub use self::input_actions::{InputActions, combine_states};
+pub use self::input_mapper::{InputMapper, RawInput};
+pub use self::radial_menu::{RADIAL_MENU_ACTIONS, RADIAL_MENU_RADIUS, RadialMenu};
+pub use self::touch_scheme::{TouchOutput, TouchScheme};
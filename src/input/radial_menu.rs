@@ -0,0 +1,117 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config::KeyAction;
+use nalgebra::Vector2;
+use std::f32::consts::PI;
+
+/// What the gamepad's radial command menu offers, clockwise from the top
+pub const RADIAL_MENU_ACTIONS: [KeyAction; 6] = [KeyAction::NextIdleVillager,
+                                                 KeyAction::NextIdleMilitary,
+                                                 KeyAction::NextSubgroup,
+                                                 KeyAction::ToggleRepeatProduction,
+                                                 KeyAction::ToggleMarket,
+                                                 KeyAction::ToggleDiplomacy];
+
+/// Distance, in viewport pixels, from the middle of the menu to its entries
+pub const RADIAL_MENU_RADIUS: i32 = 60;
+
+/// How far the stick has to be pushed over to pick an entry
+const PICK_THRESHOLD: f32 = 0.5;
+
+/// The radial command menu, opened around the gamepad's cursor. Pushing the stick towards an
+/// entry picks it, and it stays picked when the stick is let go, so letting go of the menu's
+/// button afterwards still does it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialMenu {
+    pub center: Vector2<i32>,
+    pub picked: Option<usize>,
+}
+
+impl RadialMenu {
+    pub fn new(center: Vector2<i32>) -> RadialMenu {
+        RadialMenu {
+            center: center,
+            picked: None,
+        }
+    }
+
+    pub fn point(&mut self, stick: Vector2<f32>) {
+        if (stick.x * stick.x + stick.y * stick.y).sqrt() >= PICK_THRESHOLD {
+            self.picked = Some(entry_towards(stick, RADIAL_MENU_ACTIONS.len()));
+        }
+    }
+
+    pub fn picked_action(&self) -> Option<KeyAction> {
+        self.picked.map(|index| RADIAL_MENU_ACTIONS[index])
+    }
+
+    /// Middle of where the entry is drawn
+    pub fn entry_position(&self, index: usize) -> Vector2<i32> {
+        let angle = index as f32 * 2.0 * PI / RADIAL_MENU_ACTIONS.len() as f32;
+        let radius = RADIAL_MENU_RADIUS as f32;
+        Vector2::new(self.center.x + (angle.sin() * radius).round() as i32,
+                     self.center.y - (angle.cos() * radius).round() as i32)
+    }
+}
+
+/// Which of `count` entries, evenly spaced clockwise from the top, the direction points at;
+/// down is positive like screen coordinates
+fn entry_towards(direction: Vector2<f32>, count: usize) -> usize {
+    let angle = direction.x.atan2(-direction.y);
+    let turns = (angle / (2.0 * PI) + 1.0) % 1.0;
+    let entry_turns = 1.0 / count as f32;
+    ((turns + entry_turns / 2.0) / entry_turns) as usize % count
+}
+
+#[cfg(test)]
+mod tests {
+    use config::KeyAction;
+    use nalgebra::Vector2;
+    use super::{RADIAL_MENU_RADIUS, RadialMenu, entry_towards};
+
+    #[test]
+    fn test_entry_towards() {
+        assert_eq!(0, entry_towards(Vector2::new(0.0, -1.0), 4));
+        assert_eq!(1, entry_towards(Vector2::new(1.0, 0.0), 4));
+        assert_eq!(2, entry_towards(Vector2::new(0.0, 1.0), 4));
+        assert_eq!(3, entry_towards(Vector2::new(-1.0, 0.0), 4));
+        assert_eq!(0, entry_towards(Vector2::new(-0.1, -1.0), 4));
+        assert_eq!(1, entry_towards(Vector2::new(0.5, -0.3), 6));
+    }
+
+    #[test]
+    fn test_pick_stays_after_letting_go_of_the_stick() {
+        let mut menu = RadialMenu::new(Vector2::new(100, 100));
+        menu.point(Vector2::new(0.1, 0.1));
+        assert_eq!(None, menu.picked_action());
+        menu.point(Vector2::new(0.0, -0.9));
+        menu.point(Vector2::new(0.0, 0.0));
+        assert_eq!(Some(KeyAction::NextIdleVillager), menu.picked_action());
+    }
+
+    #[test]
+    fn test_entry_position() {
+        let menu = RadialMenu::new(Vector2::new(100, 100));
+        assert_eq!(Vector2::new(100, 100 - RADIAL_MENU_RADIUS), menu.entry_position(0));
+        assert_eq!(Vector2::new(100, 100 + RADIAL_MENU_RADIUS), menu.entry_position(3));
+    }
+}
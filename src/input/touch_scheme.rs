@@ -0,0 +1,209 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use media::{KeyState, Touch};
+use nalgebra::Vector2;
+use super::{DRAG_THRESHOLD, DragOutput, DragSelect};
+
+enum Gesture {
+    Idle,
+    OneFinger { position: Vector2<i32> },
+    TwoFingers {
+        start_centroid: Vector2<i32>,
+        last_centroid: Vector2<i32>,
+        panning: bool,
+    },
+    /// A two finger gesture is over but a finger is still down; nothing happens until it's
+    /// lifted
+    Finished,
+}
+
+/// Drives the game with touches: tapping selects, dragging one finger draws a selection box,
+/// dragging two fingers pans the camera and tapping with two fingers orders the selection
+pub struct TouchScheme {
+    gesture: Gesture,
+    drag: DragSelect,
+}
+
+/// What the touches did this update
+pub struct TouchOutput {
+    /// Where the gesture is aimed, while there is one
+    pub pointer: Option<Vector2<i32>>,
+    pub select: DragOutput,
+    pub command: KeyState,
+    pub pan: Vector2<i32>,
+}
+
+impl TouchScheme {
+    pub fn new() -> TouchScheme {
+        TouchScheme {
+            gesture: Gesture::Idle,
+            drag: DragSelect::new(),
+        }
+    }
+
+    pub fn update(&mut self, touches: &[Touch]) -> TouchOutput {
+        let mut output = TouchOutput {
+            pointer: None,
+            select: DragOutput::none(),
+            command: KeyState::Up,
+            pan: Vector2::new(0, 0),
+        };
+
+        let gesture = match self.gesture {
+            Gesture::Idle | Gesture::OneFinger { .. } if touches.len() >= 2 => {
+                self.drag.cancel();
+                let centroid = centroid(touches);
+                Gesture::TwoFingers {
+                    start_centroid: centroid,
+                    last_centroid: centroid,
+                    panning: false,
+                }
+            }
+            Gesture::Idle if touches.len() == 1 => {
+                let position = touches[0].position;
+                output.pointer = Some(position);
+                output.select = self.drag.update(KeyState::TransitionDown, position);
+                Gesture::OneFinger { position: position }
+            }
+            Gesture::Idle => Gesture::Idle,
+            Gesture::OneFinger { position } => {
+                if touches.is_empty() {
+                    output.pointer = Some(position);
+                    output.select = self.drag.update(KeyState::TransitionUp, position);
+                    Gesture::Idle
+                } else {
+                    let position = touches[0].position;
+                    output.pointer = Some(position);
+                    output.select = self.drag.update(KeyState::Down, position);
+                    Gesture::OneFinger { position: position }
+                }
+            }
+            Gesture::TwoFingers { start_centroid, last_centroid, mut panning } => {
+                if touches.len() >= 2 {
+                    let centroid = centroid(touches);
+                    let moved = centroid - start_centroid;
+                    if moved.x.abs() > DRAG_THRESHOLD || moved.y.abs() > DRAG_THRESHOLD {
+                        panning = true;
+                    }
+                    if panning {
+                        output.pan = centroid - last_centroid;
+                    }
+                    output.pointer = Some(centroid);
+                    Gesture::TwoFingers {
+                        start_centroid: start_centroid,
+                        last_centroid: centroid,
+                        panning: panning,
+                    }
+                } else {
+                    if !panning {
+                        output.pointer = Some(last_centroid);
+                        output.command = KeyState::TransitionUp;
+                    }
+                    if touches.is_empty() {
+                        Gesture::Idle
+                    } else {
+                        Gesture::Finished
+                    }
+                }
+            }
+            Gesture::Finished => {
+                if touches.is_empty() {
+                    Gesture::Idle
+                } else {
+                    Gesture::Finished
+                }
+            }
+        };
+        self.gesture = gesture;
+        output
+    }
+}
+
+fn centroid(touches: &[Touch]) -> Vector2<i32> {
+    let count = touches.len() as i32;
+    let x: i32 = touches.iter().map(|touch| touch.position.x).sum();
+    let y: i32 = touches.iter().map(|touch| touch.position.y).sum();
+    Vector2::new(x / count, y / count)
+}
+
+#[cfg(test)]
+mod tests {
+    use media::{KeyState, Touch};
+    use nalgebra::Vector2;
+    use super::TouchScheme;
+    use types::Rect;
+
+    fn touch(id: i64, x: i32, y: i32) -> Touch {
+        Touch {
+            id: id,
+            position: Vector2::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_tap_selects() {
+        let mut scheme = TouchScheme::new();
+        scheme.update(&[touch(1, 100, 100)]);
+        scheme.update(&[touch(1, 101, 100)]);
+        let output = scheme.update(&[]);
+        assert_eq!(KeyState::TransitionUp, output.select.click);
+        assert_eq!(Some(Vector2::new(101, 100)), output.pointer);
+    }
+
+    #[test]
+    fn test_drag_draws_a_selection_box() {
+        let mut scheme = TouchScheme::new();
+        scheme.update(&[touch(1, 100, 100)]);
+        let output = scheme.update(&[touch(1, 150, 130)]);
+        assert_eq!(Some(Rect::of(100, 100, 50, 30)), output.select.drag_box);
+        let output = scheme.update(&[]);
+        assert_eq!(KeyState::Up, output.select.click);
+        assert_eq!(Some(Rect::of(100, 100, 50, 30)), output.select.finished_drag_box);
+    }
+
+    #[test]
+    fn test_two_finger_drag_pans() {
+        let mut scheme = TouchScheme::new();
+        scheme.update(&[touch(1, 100, 100), touch(2, 200, 100)]);
+        let output = scheme.update(&[touch(1, 102, 100), touch(2, 202, 100)]);
+        assert_eq!(Vector2::new(0, 0), output.pan);
+        let output = scheme.update(&[touch(1, 120, 100), touch(2, 220, 100)]);
+        assert_eq!(Vector2::new(18, 0), output.pan);
+
+        let output = scheme.update(&[touch(2, 220, 100)]);
+        assert_eq!(KeyState::Up, output.command);
+        let output = scheme.update(&[touch(2, 180, 100)]);
+        assert_eq!(None, output.select.drag_box);
+        assert_eq!(Vector2::new(0, 0), output.pan);
+    }
+
+    #[test]
+    fn test_two_finger_tap_commands() {
+        let mut scheme = TouchScheme::new();
+        scheme.update(&[touch(1, 100, 100)]);
+        scheme.update(&[touch(1, 100, 100), touch(2, 200, 120)]);
+        let output = scheme.update(&[]);
+        assert_eq!(KeyState::TransitionUp, output.command);
+        assert_eq!(KeyState::Up, output.select.click);
+        assert_eq!(Some(Vector2::new(150, 110)), output.pointer);
+    }
+}
@@ -0,0 +1,69 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+extern crate chariot_drs as drs;
+extern crate chariot_slp as slp;
+extern crate chariot_palette as palette;
+extern crate chariot_dat as dat;
+extern crate chariot_language as language;
+extern crate chariot_scn as scn;
+extern crate chariot_media as media;
+extern crate chariot_resource as resource;
+extern crate chariot_identifier as identifier;
+
+#[macro_use]
+extern crate chariot_types as types;
+
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate tracing;
+extern crate tracing_chrome;
+extern crate tracing_subscriber;
+
+extern crate nalgebra;
+extern crate num;
+extern crate rayon;
+extern crate rhai;
+extern crate specs;
+extern crate time;
+extern crate toml;
+extern crate zstd;
+
+#[macro_use]
+mod macros;
+
+pub mod action;
+pub mod config;
+pub mod crash;
+pub mod ecs;
+pub mod editor;
+pub mod game;
+pub mod harness;
+pub mod input;
+pub mod logging;
+pub mod net;
+pub mod partition;
+pub mod profile;
+pub mod script;
+pub mod ui;
+pub mod util;
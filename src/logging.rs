@@ -0,0 +1,103 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Log output goes through `tracing`. Every system runs inside a span tagged with the system's
+//! name and the simulation tick, so log records from inside systems carry both. Filtering uses
+//! `RUST_LOG`-style directives (like `info,chariot::ecs=debug,chariot_resource=warn`) and can be
+//! changed while the game is running.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Mutex;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+use tracing_subscriber::prelude::*;
+
+pub const DEFAULT_LOG_FILTER: &'static str = "info";
+
+static CURRENT_TICK: AtomicUsize = ATOMIC_USIZE_INIT;
+
+lazy_static! {
+    static ref FILTER_HANDLE: Mutex<Option<reload::Handle<EnvFilter, Registry>>> = Mutex::new(None);
+}
+
+/// Keeps the chrome trace (if any) open; the trace is flushed when this is dropped
+pub struct LoggingGuard {
+    _chrome_guard: Option<FlushGuard>,
+}
+
+/// Sets up logging for the whole process. If `chrome_trace_file` is given, spans are also
+/// recorded there in the chrome tracing format (viewable in chrome://tracing or Perfetto).
+/// System spans are at the trace level, so profiling needs a filter like `info,chariot=trace`.
+pub fn init(filter: &str, chrome_trace_file: Option<&str>) -> LoggingGuard {
+    let (filter_layer, filter_handle) = reload::Layer::new(parse_filter(filter));
+
+    let (chrome_layer, chrome_guard) = match chrome_trace_file {
+        Some(file_name) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .writer(BufWriter::new(File::create(file_name).unwrap_or_else(|err| {
+                    unrecoverable!("Failed to create trace file \"{}\": {}", file_name, err);
+                })))
+                .include_args(true)
+                .build();
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(chrome_layer)
+        .with(fmt::layer().with_target(true))
+        .init();
+
+    *FILTER_HANDLE.lock().unwrap() = Some(filter_handle);
+    LoggingGuard { _chrome_guard: chrome_guard }
+}
+
+/// Replaces the log filter directives at runtime
+pub fn set_filter(filter: &str) -> Result<(), String> {
+    let new_filter = try!(EnvFilter::try_new(filter).map_err(|err| format!("{}", err)));
+    match *FILTER_HANDLE.lock().unwrap() {
+        Some(ref handle) => handle.reload(new_filter).map_err(|err| format!("{}", err)),
+        None => Err("logging hasn't been initialized".into()),
+    }
+}
+
+fn parse_filter(filter: &str) -> EnvFilter {
+    EnvFilter::try_new(filter).unwrap_or_else(|err| {
+        println!("Invalid log filter \"{}\" ({}); using \"{}\"", filter, err, DEFAULT_LOG_FILTER);
+        EnvFilter::new(DEFAULT_LOG_FILTER)
+    })
+}
+
+/// Called at the start of every simulation tick so that systems can tag their spans with it.
+/// It's shared by every world in the process, so it's only for logging; the simulation reads
+/// the world's SimulationTick instead.
+pub fn set_tick(tick: usize) {
+    CURRENT_TICK.store(tick, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn current_tick() -> usize {
+    CURRENT_TICK.load(Ordering::Relaxed)
+}
@@ -20,6 +20,7 @@
 // SOFTWARE.
 
 // The messaging is a little more user-friendly than panic
+#[macro_export]
 macro_rules! unrecoverable {
     ( $fmt:expr, $($args:expr),* ) => {
         use std::process;
@@ -39,6 +40,10 @@ macro_rules! fetch_components {
             $( mut resource($mut_res_name:ident: $mut_res_typ:path), )*
         ]
     ) => {
+        $( $crate::ecs::system::CheckAccess::check_read::<$typ>(&$arg); )*
+        $( $crate::ecs::system::CheckAccess::check_read::<$res_typ>(&$arg); )*
+        $( $crate::ecs::system::CheckAccess::check_write::<$mut_typ>(&$arg); )*
+        $( $crate::ecs::system::CheckAccess::check_write::<$mut_res_typ>(&$arg); )*
         let (
             $entities,
             $( $name, )*
@@ -56,3 +61,23 @@ macro_rules! fetch_components {
         });
     };
 }
+
+/// Lists what a presentation system fetches, so that the presentation lane knows which systems
+/// can run at the same time. Takes the same list as `fetch_components!`, and has to match it, which
+/// `fetch_components!` checks in debug builds.
+macro_rules! system_access {
+    (
+        [
+            $( components($name:ident: $typ:path), )*
+            $( mut components($mut_name:ident: $mut_typ:path), )*
+            $( resource($res_name:ident: $res_typ:path), )*
+            $( mut resource($mut_res_name:ident: $mut_res_typ:path), )*
+        ]
+    ) => {
+        $crate::ecs::system::SystemAccess::new()
+            $( .read::<$typ>() )*
+            $( .read::<$res_typ>() )*
+            $( .write::<$mut_typ>() )*
+            $( .write::<$mut_res_typ>() )*
+    };
+}
@@ -19,65 +19,360 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-extern crate chariot_drs as drs;
-extern crate chariot_slp as slp;
-extern crate chariot_palette as palette;
-extern crate chariot_dat as dat;
-extern crate chariot_language as language;
-extern crate chariot_scn as scn;
-extern crate chariot_media as media;
-extern crate chariot_resource as resource;
-extern crate chariot_identifier as identifier;
-
-#[macro_use]
-extern crate chariot_types as types;
-
 #[macro_use]
-extern crate lazy_static;
+extern crate chariot;
+extern crate chariot_resource as resource;
+extern crate chariot_scn as scn;
 
 extern crate clap;
-extern crate nalgebra;
-extern crate num;
-extern crate specs;
-extern crate time;
 
-#[macro_use]
-mod macros;
+use chariot::config::{CONFIG_FILE_NAME, Config, ConfigLoader, ConfigValue};
+use chariot::crash;
+use chariot::ecs::resource::GameSettings;
+use chariot::editor::ScenarioEditor;
+use chariot::game::{self, EditorGameState, Game, GameState, LoadProgress, LoadStage, ScenarioGameState,
+                    Session};
+use chariot::harness::{DEFAULT_SMOKE_TEST_TICKS, HeadlessData, HeadlessRun, ReplayAnalysis, TestPlan,
+                       smoke_test_dir};
+use chariot::logging;
+use chariot::script::ScenarioScript;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-mod action;
-mod ecs;
-mod game;
-mod partition;
-mod util;
-
-use game::{Game, GameState, ScenarioGameState};
+/// Width and height, in tiles, of the map of a scenario created in the editor; the size of a
+/// medium map
+const NEW_SCENARIO_MAP_SIZE: u32 = 72;
 
 fn main() {
     let arg_matches = clap::App::new("Chariot")
         .about("An open source reimplementation of Age of Empires (1997)")
+        .setting(clap::AppSettings::SubcommandsNegateReqs)
+        .arg(clap::Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .value_name("FILE")
+            .help("Sets the config file to read settings from. Defaults to \"chariot.toml\"; settings \
+                   given on the command line take priority over the ones in it.")
+            .takes_value(true))
         .arg(clap::Arg::with_name("game_data_dir")
             .short("d")
             .long("game-data-dir")
             .value_name("GAME_DATA_DIR")
             .help("Sets the directory to look in for game data. Defaults to \"game\".")
             .takes_value(true))
+        .arg(clap::Arg::with_name("resolution")
+            .long("resolution")
+            .value_name("WIDTHxHEIGHT")
+            .help("Sets the window size. Defaults to 1024x768.")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("fullscreen")
+            .long("fullscreen")
+            .help("Starts in fullscreen; Alt+Enter switches between fullscreen and a window."))
         .arg(clap::Arg::with_name("SCENARIO")
             .required(true)
             .help("Scenario file to load (temporary while there's no menu)"))
+        .arg(clap::Arg::with_name("editor")
+            .long("editor")
+            .help("Opens the scenario in the scenario editor instead of playing it; a new scenario is \
+                   created if the file doesn't exist yet"))
+        .arg(clap::Arg::with_name("allow_cheats")
+            .long("allow-cheats")
+            .help("Allows cheat codes to be entered into the chat (temporary while there's no lobby)"))
+        .arg(clap::Arg::with_name("allied_vision")
+            .long("allied-vision")
+            .help("Lets allies share vision from the start (temporary while there's no lobby)"))
+        .arg(clap::Arg::with_name("game_speed")
+            .long("game-speed")
+            .value_name("MULTIPLIER")
+            .help("Sets the starting game speed, from 0.5 to 8. Defaults to 1.")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Sets the seed for the game's random numbers, to replay a game exactly. Defaults to \
+                   one based on the current time.")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("mod")
+            .long("mod")
+            .value_name("MOD_NAME")
+            .help("Enables a mod from the mods directory; replacement graphics and sounds in it \
+                   are used instead of the ones in the DRS archives. Can be given more than once, \
+                   with later mods taking priority.")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(clap::Arg::with_name("watch_data")
+            .long("watch-data")
+            .help("Reloads mods' replacement graphics while the game is running whenever they change"))
+        .arg(clap::Arg::with_name("test_plan")
+            .long("test-plan")
+            .value_name("FILE")
+            .help("Plays the scenario without a window, following the given test plan, then exits with \
+                   a non-zero status if any of its expectations failed")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("analyze")
+            .long("analyze")
+            .value_name("FILE")
+            .help("Plays back the test plan like a replay and writes analytics of the game to the given \
+                   file: actions per minute, resource and army curves, build orders, and engagements as \
+                   JSON, or only the curves as CSV if the file name ends in .csv")
+            .takes_value(true)
+            .requires("test_plan"))
+        .arg(clap::Arg::with_name("log")
+            .long("log")
+            .value_name("FILTER")
+            .help("Sets which log records to show, such as \"info,chariot::ecs=debug\". Defaults to \
+                   RUST_LOG if it's set, or the config file's log setting otherwise.")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("trace_file")
+            .long("trace-file")
+            .value_name("FILE")
+            .help("Records a chrome tracing profile of the session to the given file")
+            .takes_value(true))
+        .subcommand(clap::SubCommand::with_name("test-scenarios")
+            .about("Loads every scenario in a directory without a window and runs each for a while with \
+                    nobody giving orders, then reports the ones that failed to parse, refer to things \
+                    the game data doesn't have, have broken triggers, or crash, exiting with a non-zero \
+                    status if there were any")
+            .arg(clap::Arg::with_name("DIR")
+                .required(true)
+                .help("Directory of scenarios to test"))
+            .arg(clap::Arg::with_name("ticks")
+                .long("ticks")
+                .value_name("TICKS")
+                .help("Sets how many ticks each scenario runs for. Defaults to 600, ten seconds of game \
+                       time.")
+                .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("verify-data")
+            .about("Reads every required game data file through to work out its checksum, which the \
+                    startup check doesn't, then prints the report and the checksums found, exiting \
+                    with a non-zero status if there were problems")
+            .arg(clap::Arg::with_name("CHECKSUMS")
+                .help("Table of known checksums, as printed by an earlier run, for the files to match")))
         .get_matches();
 
-    let game_data_dir = arg_matches.value_of("game_data_dir").unwrap_or("game");
+    let mut config_loader = ConfigLoader::new(arg_matches.value_of("config").unwrap_or(CONFIG_FILE_NAME));
+    if let Err(err) = config_loader.load_file() {
+        unrecoverable!("{}", err);
+    }
+    if let Some(game_data_dir) = arg_matches.value_of("game_data_dir") {
+        config_loader.set_cli_override("data_dir", ConfigValue::String(game_data_dir.into()));
+    }
+    if let Some(mod_names) = arg_matches.values_of("mod") {
+        let mod_names = mod_names.map(|name| ConfigValue::String(name.into())).collect();
+        config_loader.set_cli_override("mods", ConfigValue::Array(mod_names));
+    }
+    if arg_matches.is_present("watch_data") {
+        config_loader.set_cli_override("watch_data", ConfigValue::Boolean(true));
+    }
+    if let Some(resolution) = arg_matches.value_of("resolution") {
+        let (width, height) = parse_resolution(resolution).unwrap_or_else(|| {
+            unrecoverable!("Invalid resolution \"{}\"; expected something like 1024x768", resolution);
+        });
+        config_loader.set_cli_override("video.width", ConfigValue::Integer(width));
+        config_loader.set_cli_override("video.height", ConfigValue::Integer(height));
+    }
+    if arg_matches.is_present("fullscreen") {
+        config_loader.set_cli_override("video.fullscreen", ConfigValue::Boolean(true));
+    }
+    let log_filter = arg_matches.value_of("log").map(|filter| filter.to_string()).or(env::var("RUST_LOG").ok());
+    if let Some(log_filter) = log_filter {
+        config_loader.set_cli_override("log", ConfigValue::String(log_filter));
+    }
+
+    let config = config_loader.config().unwrap_or_else(|err| {
+        unrecoverable!("{}", err);
+    });
+    let _logging_guard = logging::init(&config.log_filter, arg_matches.value_of("trace_file"));
+
+    // Panics are caught and reported per scenario, rather than written out as crash reports
+    if let Some(test_matches) = arg_matches.subcommand_matches("test-scenarios") {
+        let ticks = match test_matches.value_of("ticks") {
+            Some(ticks) => {
+                ticks.parse().unwrap_or_else(|_| {
+                    unrecoverable!("Invalid tick count \"{}\"", ticks);
+                })
+            }
+            None => DEFAULT_SMOKE_TEST_TICKS,
+        };
+        let data = load_headless_data(&config);
+        let dir = test_matches.value_of("DIR").unwrap();
+        let report = smoke_test_dir(&data, dir, ticks).unwrap_or_else(|err| {
+            unrecoverable!("{}", err);
+        });
+        println!("{}", report);
+        process::exit(if report.passed() { 0 } else { 1 });
+    }
+    if let Some(verify_matches) = arg_matches.subcommand_matches("verify-data") {
+        process::exit(verify_data(&config, verify_matches.value_of("CHECKSUMS")));
+    }
+    crash::install_panic_hook(crash::CRASH_DIR);
+
     let scenario_file_name = arg_matches.value_of("SCENARIO").unwrap();
 
-    let scenario = scn::Scenario::read_from_file(scenario_file_name).unwrap_or_else(|err| {
-        unrecoverable!("Failed to load scenario \"{}\": {}",
-                       scenario_file_name,
-                       err);
+    crash::set_scenario(scenario_file_name);
+    if arg_matches.is_present("editor") {
+        let file_name = scenario_file_name.to_string();
+        let loaded = Game::new_with(config_loader, move |progress| {
+            if Path::new(&file_name).exists() {
+                load_scenario(&file_name, progress)
+            } else {
+                Ok(scn::Scenario::new(NEW_SCENARIO_MAP_SIZE, NEW_SCENARIO_MAP_SIZE))
+            }
+        });
+        let (mut game, scenario) = match loaded {
+            Some(loaded) => loaded,
+            None => return,
+        };
+        let editor = ScenarioEditor::new(scenario, scenario_file_name).unwrap_or_else(|err| {
+            unrecoverable!("Failed to open the scenario in the editor: {}", err);
+        });
+        let initial_state = Box::new(EditorGameState::new(&game, editor));
+        game.push_state(initial_state as Box<GameState>);
+        game.game_loop();
+        return;
+    }
+
+    let script = ScenarioScript::load_sidecar(scenario_file_name).unwrap_or_else(|err| {
+        unrecoverable!("Failed to load the scenario script: {}", err);
+    });
+
+    let mut settings = GameSettings::new();
+    settings.allow_cheats = arg_matches.is_present("allow_cheats");
+    settings.allied_vision = arg_matches.is_present("allied_vision");
+    if let Some(game_speed) = arg_matches.value_of("game_speed") {
+        let game_speed: f64 = game_speed.parse().unwrap_or_else(|_| {
+            unrecoverable!("Invalid game speed \"{}\"", game_speed);
+        });
+        settings.game_speed = game_speed.into();
+    }
+    let seed: Option<u64> = arg_matches.value_of("seed").map(|seed| {
+        seed.parse().unwrap_or_else(|_| {
+            unrecoverable!("Invalid seed \"{}\"", seed);
+        })
+    });
+
+    if let Some(plan_file_name) = arg_matches.value_of("test_plan") {
+        let plan = TestPlan::read_from_file(plan_file_name).unwrap_or_else(|err| {
+            unrecoverable!("Failed to load the test plan: {}", err);
+        });
+        // Test runs have to be repeatable, so they never take their seed from the clock
+        settings.random_seed = plan.seed.or(seed).unwrap_or(0);
+        let scenario = read_scenario(scenario_file_name).unwrap_or_else(|err| {
+            unrecoverable!("{}", err);
+        });
+        let data = load_headless_data(&config);
+        let run = HeadlessRun::new(&data, &scenario, &settings, script);
+        let report = match arg_matches.value_of("analyze") {
+            Some(analysis_file_name) => {
+                let mut analysis = ReplayAnalysis::new();
+                let report = run.analyze_plan(&plan, &mut analysis);
+                analysis.write_to_file(analysis_file_name).unwrap_or_else(|err| {
+                    unrecoverable!("Failed to write the analysis: {}", err);
+                });
+                report
+            }
+            None => run.run_plan(&plan),
+        };
+        println!("{}", report);
+        process::exit(if report.passed() { 0 } else { 1 });
+    }
+
+    settings.random_seed = seed.unwrap_or_else(|| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0));
+        now.as_secs() ^ (now.subsec_nanos() as u64) << 32
     });
 
-    let mut game = Game::new(game_data_dir);
-    let initial_state = Box::new(ScenarioGameState::new(&game, scenario));
+    // An unfinished last game can be picked up from its latest save, which is played as the
+    // scenario in place of the one that was asked for
+    let mut session = Session::new(scenario_file_name, &settings);
+    let mut file_name = scenario_file_name.to_string();
+    let mut script = script;
+    let mut save_state = None;
+    if let Some((last_session, save_path)) = game::offer_to_restore(&game::save_dir()) {
+        settings = last_session.settings(settings.random_seed);
+        save_state = game::read_save_state(&save_path).unwrap_or_else(|err| {
+            println!("{}; the game is picked up from its scenario alone", err);
+            None
+        });
+        script = ScenarioScript::load_sidecar(&last_session.scenario_path).unwrap_or_else(|err| {
+            unrecoverable!("Failed to load the scenario script: {}", err);
+        });
+        file_name = save_path.to_string_lossy().into_owned();
+        crash::set_scenario(&save_path);
+        session = last_session;
+    }
+
+    let loaded = Game::new_with(config_loader, move |progress| load_scenario(&file_name, progress));
+    let (mut game, mut scenario) = match loaded {
+        Some(loaded) => loaded,
+        None => return,
+    };
+    game.gate_scenario(&mut scenario);
+    let initial_state =
+        Box::new(ScenarioGameState::new(&game, scenario, session, settings, script, save_state));
     game.push_state(initial_state as Box<GameState>);
 
     game.game_loop();
 }
+
+fn read_scenario(file_name: &str) -> Result<scn::Scenario, String> {
+    scn::Scenario::read_from_file(file_name)
+        .map_err(|err| format!("Failed to load scenario \"{}\": {}", file_name, err))
+}
+
+/// Reads the scenario on the loading thread, alongside the game data
+fn load_scenario(file_name: &str, progress: &LoadProgress) -> Result<scn::Scenario, String> {
+    try!(progress.begin(LoadStage::Scenario));
+    let scenario = try!(read_scenario(file_name));
+    progress.finish(LoadStage::Scenario);
+    Ok(scenario)
+}
+
+/// There's nobody to ask where the data is for runs without a window, so it's either found or
+/// the run fails
+fn load_headless_data(config: &Config) -> HeadlessData {
+    HeadlessData::load(&locate_game_dir(config)).unwrap_or_else(|err| {
+        unrecoverable!("{}", err);
+    })
+}
+
+fn locate_game_dir(config: &Config) -> resource::GameDir {
+    let search_dirs: Vec<PathBuf> = config.data_search_dirs.iter().map(PathBuf::from).collect();
+    resource::locate_game_dir(Path::new(&config.data_dir), &search_dirs).unwrap_or_else(|rejected| {
+        let looked_in: Vec<String> = rejected.iter().map(|rejected| rejected.to_string()).collect();
+        unrecoverable!("Couldn't find the game data in any of:\n{}", looked_in.join("\n"));
+    })
+}
+
+/// Checks the game data through to the files' checksums, comparing them with the table of known
+/// ones when there is one, and returns the status to exit with
+fn verify_data(config: &Config, checksums_file: Option<&str>) -> i32 {
+    let known = match checksums_file {
+        Some(checksums_file) => {
+            resource::KnownChecksums::load(checksums_file).unwrap_or_else(|err| {
+                unrecoverable!("{}", err);
+            })
+        }
+        None => resource::KnownChecksums::default(),
+    };
+    let report = resource::AssetReport::generate_with_checksums(&locate_game_dir(config), &known);
+    println!("{}", report);
+    print!("{}", report.checksum_table());
+    if report.is_ok() { 0 } else { 1 }
+}
+
+fn parse_resolution(resolution: &str) -> Option<(i64, i64)> {
+    let mut parts = resolution.split('x');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => {
+            match (width.parse(), height.parse()) {
+                (Ok(width), Ok(height)) => Some((width, height)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
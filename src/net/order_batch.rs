@@ -0,0 +1,405 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use identifier::{PlayerId, UnitId};
+use specs::Index;
+use std::fmt;
+use types::{Fixed, Vector3};
+
+/// Packets stay under this many bytes, which leaves room for IP and UDP headers within the
+/// usual 1500 byte MTU, and below the minimum that IPv6 guarantees
+pub const MAX_PACKET_SIZE: usize = 1200;
+
+const PROTOCOL_VERSION: u8 = 1;
+
+const ORDER_STOP: u8 = 0;
+const ORDER_MOVE_TO: u8 = 1;
+const ORDER_TRAIN: u8 = 2;
+
+/// An order a player gives to units, as it's sent to the other players for a lockstep turn.
+/// Orders name their destination rather than the path there, since every player works out
+/// the same path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnitOrder {
+    Stop,
+    MoveTo(Vector3),
+    Train(UnitId),
+}
+
+/// One order and every unit it was given to
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderGroup {
+    pub order: UnitOrder,
+    /// Sorted, without duplicates
+    pub entity_ids: Vec<Index>,
+}
+
+/// The orders one player gave during one turn. The same order given to a whole selection
+/// is only encoded once, with the list of units it went to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderBatch {
+    pub turn: u32,
+    pub player_id: PlayerId,
+    pub groups: Vec<OrderGroup>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    Truncated,
+    UnknownVersion(u8),
+    UnknownOrder(u8),
+    /// A turn number, unit type or entity ID too big for 32 bits
+    OutOfRange,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated => write!(f, "the packet ended early"),
+            DecodeError::UnknownVersion(version) => write!(f, "unknown protocol version {}", version),
+            DecodeError::UnknownOrder(tag) => write!(f, "unknown order type {}", tag),
+            DecodeError::OutOfRange => write!(f, "a number was too big for 32 bits"),
+        }
+    }
+}
+
+impl OrderBatch {
+    /// Groups identical orders together, keeping the groups in the order they were first given
+    pub fn coalesce(turn: u32, player_id: PlayerId, orders: &[(Index, UnitOrder)]) -> OrderBatch {
+        let mut groups: Vec<OrderGroup> = Vec::new();
+        for &(entity_id, ref order) in orders {
+            match groups.iter().position(|group| group.order == *order) {
+                Some(index) => groups[index].entity_ids.push(entity_id),
+                None => {
+                    groups.push(OrderGroup {
+                        order: order.clone(),
+                        entity_ids: vec![entity_id],
+                    })
+                }
+            }
+        }
+        for group in &mut groups {
+            group.entity_ids.sort();
+            group.entity_ids.dedup();
+        }
+        OrderBatch {
+            turn: turn,
+            player_id: player_id,
+            groups: groups,
+        }
+    }
+
+    /// Encodes the batch into as few packets of at most `max_size` bytes as it fits in. A
+    /// group with too many units for one packet is split across several.
+    pub fn encode_packets(&self, max_size: usize) -> Vec<Vec<u8>> {
+        let mut header = Vec::new();
+        header.push(PROTOCOL_VERSION);
+        write_varint(&mut header, self.turn as u64);
+        header.push(*self.player_id);
+        // Leaves room for the group count
+        let body_size = max_size.saturating_sub(header.len() + 2);
+
+        let mut encoded_groups = Vec::new();
+        for group in &self.groups {
+            encode_group(&group.order, &group.entity_ids, body_size, &mut encoded_groups);
+        }
+
+        let mut packets = Vec::new();
+        let mut body = Vec::new();
+        let mut group_count = 0;
+        for encoded_group in encoded_groups {
+            if group_count > 0 && body.len() + encoded_group.len() > body_size {
+                packets.push(finish_packet(&header, group_count, &body));
+                body.clear();
+                group_count = 0;
+            }
+            body.extend_from_slice(&encoded_group);
+            group_count += 1;
+        }
+        if group_count > 0 || packets.is_empty() {
+            packets.push(finish_packet(&header, group_count, &body));
+        }
+        packets
+    }
+
+    /// Decodes one packet. A batch sent in several packets comes back as several batches
+    /// for the same turn.
+    pub fn decode(packet: &[u8]) -> Result<OrderBatch, DecodeError> {
+        let mut reader = Reader {
+            bytes: packet,
+            position: 0,
+        };
+        let version = try!(reader.byte());
+        if version != PROTOCOL_VERSION {
+            return Err(DecodeError::UnknownVersion(version));
+        }
+        let turn = try!(reader.varint_u32());
+        let player_id: PlayerId = (try!(reader.byte()) as usize).into();
+
+        let group_count = try!(reader.varint());
+        let mut groups = Vec::new();
+        for _ in 0..group_count {
+            let order = match try!(reader.byte()) {
+                ORDER_STOP => UnitOrder::Stop,
+                ORDER_MOVE_TO => {
+                    let x = Fixed { scaled: unzigzag(try!(reader.varint())) };
+                    let y = Fixed { scaled: unzigzag(try!(reader.varint())) };
+                    let z = Fixed { scaled: unzigzag(try!(reader.varint())) };
+                    UnitOrder::MoveTo(Vector3::new(x, y, z))
+                }
+                ORDER_TRAIN => UnitOrder::Train((try!(reader.varint_u32()) as usize).into()),
+                tag => return Err(DecodeError::UnknownOrder(tag)),
+            };
+
+            let id_count = try!(reader.varint());
+            let mut entity_ids = Vec::new();
+            let mut last_id = 0;
+            for index in 0..id_count {
+                let delta: Index = try!(reader.varint_u32());
+                last_id = if index == 0 {
+                    delta
+                } else {
+                    try!(last_id.checked_add(delta).ok_or(DecodeError::OutOfRange))
+                };
+                entity_ids.push(last_id);
+            }
+            groups.push(OrderGroup {
+                order: order,
+                entity_ids: entity_ids,
+            });
+        }
+        Ok(OrderBatch {
+            turn: turn,
+            player_id: player_id,
+            groups: groups,
+        })
+    }
+}
+
+/// Encodes the order followed by its entity IDs, each as the difference from the one before,
+/// which for the units of one selection are usually small enough to take a single byte
+fn encode_group(order: &UnitOrder, entity_ids: &[Index], max_size: usize, out: &mut Vec<Vec<u8>>) {
+    let mut encoded = Vec::new();
+    match *order {
+        UnitOrder::Stop => encoded.push(ORDER_STOP),
+        UnitOrder::MoveTo(ref position) => {
+            encoded.push(ORDER_MOVE_TO);
+            write_varint(&mut encoded, zigzag(position.x.scaled));
+            write_varint(&mut encoded, zigzag(position.y.scaled));
+            write_varint(&mut encoded, zigzag(position.z.scaled));
+        }
+        UnitOrder::Train(unit_id) => {
+            encoded.push(ORDER_TRAIN);
+            write_varint(&mut encoded, *unit_id as u64);
+        }
+    }
+    write_varint(&mut encoded, entity_ids.len() as u64);
+    let mut last_id = 0;
+    for (index, &entity_id) in entity_ids.iter().enumerate() {
+        let delta = if index == 0 { entity_id } else { entity_id - last_id };
+        write_varint(&mut encoded, delta as u64);
+        last_id = entity_id;
+    }
+
+    if encoded.len() > max_size && entity_ids.len() > 1 {
+        let (first, second) = entity_ids.split_at(entity_ids.len() / 2);
+        encode_group(order, first, max_size, out);
+        encode_group(order, second, max_size, out);
+    } else {
+        out.push(encoded);
+    }
+}
+
+fn finish_packet(header: &[u8], group_count: usize, body: &[u8]) -> Vec<u8> {
+    let mut packet = header.to_vec();
+    write_varint(&mut packet, group_count as u64);
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Little-endian base 128: seven bits per byte, with the top bit set on all but the last byte
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Maps small negative numbers to small positive ones, so that they stay short as varints
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = try!(self.bytes.get(self.position).cloned().ok_or(DecodeError::Truncated));
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = try!(self.byte());
+            if shift >= 64 {
+                return Err(DecodeError::Truncated);
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    /// A varint that has to fit in 32 bits, like turn numbers and entity IDs
+    fn varint_u32(&mut self) -> Result<u32, DecodeError> {
+        let value = try!(self.varint());
+        if value > u32::max_value() as u64 {
+            Err(DecodeError::OutOfRange)
+        } else {
+            Ok(value as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use identifier::PlayerId;
+    use types::{Fixed, Vector3};
+    use super::{DecodeError, MAX_PACKET_SIZE, ORDER_STOP, OrderBatch, PROTOCOL_VERSION, UnitOrder, unzigzag,
+                write_varint, zigzag};
+
+    fn move_to(x: f64, y: f64) -> UnitOrder {
+        UnitOrder::MoveTo(Vector3::new(Fixed::from(x), Fixed::from(y), Fixed::from(0)))
+    }
+
+    fn player() -> PlayerId {
+        2.into()
+    }
+
+    #[test]
+    fn test_identical_orders_are_grouped() {
+        let orders = vec![(7, move_to(3.5, 4.)),
+                          (3, move_to(3.5, 4.)),
+                          (5, UnitOrder::Stop),
+                          (4, move_to(3.5, 4.))];
+        let batch = OrderBatch::coalesce(10, player(), &orders);
+        assert_eq!(2, batch.groups.len());
+        assert_eq!(move_to(3.5, 4.), batch.groups[0].order);
+        assert_eq!(vec![3, 4, 7], batch.groups[0].entity_ids);
+        assert_eq!(vec![5], batch.groups[1].entity_ids);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let orders = vec![(300, move_to(-12.25, 40.)),
+                          (301, move_to(-12.25, 40.)),
+                          (2, UnitOrder::Train(83.into()))];
+        let batch = OrderBatch::coalesce(123456, player(), &orders);
+        let packets = batch.encode_packets(MAX_PACKET_SIZE);
+        assert_eq!(1, packets.len());
+        assert_eq!(Ok(batch), OrderBatch::decode(&packets[0]));
+    }
+
+    #[test]
+    fn test_selection_ids_take_a_byte_each() {
+        let orders: Vec<_> = (1000..1050).map(|id| (id, UnitOrder::Stop)).collect();
+        let packets = OrderBatch::coalesce(1, player(), &orders).encode_packets(MAX_PACKET_SIZE);
+        // Header, group count, order tag, ID count, first ID, then 49 single byte deltas
+        assert_eq!(3 + 1 + 1 + 1 + 2 + 49, packets[0].len());
+    }
+
+    #[test]
+    fn test_large_batches_are_split_into_packets() {
+        let orders: Vec<_> = (0..2000).map(|id| (id * 1000, move_to(id as f64, 0.))).collect();
+        let batch = OrderBatch::coalesce(1, player(), &orders);
+        let packets = batch.encode_packets(MAX_PACKET_SIZE);
+        assert!(packets.len() > 1);
+
+        let mut groups = Vec::new();
+        for packet in &packets {
+            assert!(packet.len() <= MAX_PACKET_SIZE);
+            groups.extend(OrderBatch::decode(packet).unwrap().groups);
+        }
+        assert_eq!(batch.groups, groups);
+    }
+
+    #[test]
+    fn test_big_selections_are_split_across_packets() {
+        let orders: Vec<_> = (0..1000).map(|id| (id * 100000, UnitOrder::Stop)).collect();
+        let packets = OrderBatch::coalesce(1, player(), &orders).encode_packets(MAX_PACKET_SIZE);
+        assert!(packets.len() > 1);
+        let entity_count: usize = packets.iter()
+            .flat_map(|packet| OrderBatch::decode(packet).unwrap().groups)
+            .map(|group| group.entity_ids.len())
+            .sum();
+        assert_eq!(1000, entity_count);
+    }
+
+    #[test]
+    fn test_truncated_packets_are_rejected() {
+        let orders = vec![(1, move_to(1., 1.))];
+        let packet = OrderBatch::coalesce(1, player(), &orders).encode_packets(MAX_PACKET_SIZE).remove(0);
+        assert_eq!(Err(DecodeError::Truncated), OrderBatch::decode(&packet[..packet.len() - 1]));
+    }
+
+    #[test]
+    fn test_out_of_range_numbers_are_rejected() {
+        let header = |turn: u64| {
+            let mut packet = vec![PROTOCOL_VERSION];
+            write_varint(&mut packet, turn);
+            packet.push(2);
+            packet
+        };
+        assert_eq!(Err(DecodeError::OutOfRange),
+                   OrderBatch::decode(&header(u32::max_value() as u64 + 1)));
+
+        // IDs that add up past the largest one
+        let mut packet = header(1);
+        write_varint(&mut packet, 1);
+        packet.push(ORDER_STOP);
+        write_varint(&mut packet, 2);
+        write_varint(&mut packet, u32::max_value() as u64);
+        write_varint(&mut packet, 1);
+        assert_eq!(Err(DecodeError::OutOfRange), OrderBatch::decode(&packet));
+    }
+
+    #[test]
+    fn test_zigzag() {
+        for value in &[0i64, 1, -1, 63, -64, i64::max_value(), i64::min_value()] {
+            assert_eq!(*value, unzigzag(zigzag(*value)));
+        }
+        assert_eq!(1, zigzag(-1));
+        assert_eq!(2, zigzag(1));
+    }
+}
@@ -22,6 +22,11 @@
 use nalgebra::Vector2;
 
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Smallest cell size that `tuned_cell_size` will suggest
+const MIN_TUNED_CELL_SIZE: i32 = 2;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 struct CellKey {
@@ -38,39 +43,39 @@ impl CellKey {
     }
 }
 
-struct Cell {
-    entities: Vec<GridEntity>,
+struct Cell<K> {
+    entities: Vec<GridEntity<K>>,
 }
 
-impl Cell {
-    fn new() -> Cell {
+impl<K: Copy + Eq> Cell<K> {
+    fn new() -> Cell<K> {
         Cell { entities: Vec::new() }
     }
 
-    fn add(&mut self, entity: GridEntity) {
+    fn add(&mut self, entity: GridEntity<K>) {
         self.entities.push(entity);
     }
 
-    fn remove(&mut self, entity_id: u32) {
+    fn remove(&mut self, entity_id: K) {
         if let Some(index) = self.entities.iter().position(|entity| entity.entity_id == entity_id) {
             self.entities.swap_remove(index);
         }
     }
 
     #[inline]
-    fn entities<'a>(&'a self) -> &Vec<GridEntity> {
+    fn entities<'a>(&'a self) -> &Vec<GridEntity<K>> {
         &self.entities
     }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct GridEntity {
-    entity_id: u32,
+pub struct GridEntity<K> {
+    entity_id: K,
     position: Vector2<i32>,
 }
 
-impl GridEntity {
-    fn new(entity_id: u32, position: Vector2<i32>) -> GridEntity {
+impl<K> GridEntity<K> {
+    fn new(entity_id: K, position: Vector2<i32>) -> GridEntity<K> {
         GridEntity {
             entity_id: entity_id,
             position: position,
@@ -78,46 +83,275 @@ impl GridEntity {
     }
 }
 
-pub struct GridPartition {
+/// Totals over the queries made since the stats were last reset
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct QueryStats {
+    pub queries: usize,
+    pub cells_visited: usize,
+    /// Entities in the visited cells, whether or not they were inside the query bounds
+    pub entities_checked: usize,
+    pub entities_found: usize,
+    pub total_query_width: usize,
+    pub total_query_height: usize,
+}
+
+impl QueryStats {
+    pub fn average_cells_visited(&self) -> usize {
+        self.cells_visited / self.queries.max(1)
+    }
+
+    pub fn average_entities_checked(&self) -> usize {
+        self.entities_checked / self.queries.max(1)
+    }
+
+    pub fn average_entities_found(&self) -> usize {
+        self.entities_found / self.queries.max(1)
+    }
+
+    pub fn average_query_size(&self) -> Vector2<i32> {
+        Vector2::new((self.total_query_width / self.queries.max(1)) as i32,
+                     (self.total_query_height / self.queries.max(1)) as i32)
+    }
+}
+
+/// Query stats are recorded from `query`, which only borrows the grid, and the grid is shared
+/// between systems that can run on different threads
+struct QueryCounters {
+    queries: AtomicUsize,
+    cells_visited: AtomicUsize,
+    entities_checked: AtomicUsize,
+    entities_found: AtomicUsize,
+    total_query_width: AtomicUsize,
+    total_query_height: AtomicUsize,
+}
+
+impl QueryCounters {
+    fn new() -> QueryCounters {
+        QueryCounters {
+            queries: AtomicUsize::new(0),
+            cells_visited: AtomicUsize::new(0),
+            entities_checked: AtomicUsize::new(0),
+            entities_found: AtomicUsize::new(0),
+            total_query_width: AtomicUsize::new(0),
+            total_query_height: AtomicUsize::new(0),
+        }
+    }
+
+    fn record(&self, query: &QueryStats) {
+        self.queries.fetch_add(query.queries, Ordering::Relaxed);
+        self.cells_visited.fetch_add(query.cells_visited, Ordering::Relaxed);
+        self.entities_checked.fetch_add(query.entities_checked, Ordering::Relaxed);
+        self.entities_found.fetch_add(query.entities_found, Ordering::Relaxed);
+        self.total_query_width.fetch_add(query.total_query_width, Ordering::Relaxed);
+        self.total_query_height.fetch_add(query.total_query_height, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> QueryStats {
+        QueryStats {
+            queries: self.queries.load(Ordering::Relaxed),
+            cells_visited: self.cells_visited.load(Ordering::Relaxed),
+            entities_checked: self.entities_checked.load(Ordering::Relaxed),
+            entities_found: self.entities_found.load(Ordering::Relaxed),
+            total_query_width: self.total_query_width.load(Ordering::Relaxed),
+            total_query_height: self.total_query_height.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = QueryCounters::new();
+    }
+}
+
+/// Bounded grids keep their cells in a flat vector so that looking one up doesn't take any
+/// hashing; infinite grids only have the cells that have been used, in a map
+enum CellStorage<K> {
+    Sparse(HashMap<CellKey, Cell<K>>),
+    Dense { cols: i32, rows: i32, cells: Vec<Cell<K>> },
+}
+
+impl<K: Copy + Eq> CellStorage<K> {
+    fn new(dimensions: Option<Vector2<i32>>) -> CellStorage<K> {
+        match dimensions {
+            Some(dimensions) => {
+                CellStorage::Dense {
+                    cols: dimensions.x,
+                    rows: dimensions.y,
+                    cells: (0..dimensions.x * dimensions.y).map(|_| Cell::new()).collect(),
+                }
+            }
+            None => CellStorage::Sparse(HashMap::new()),
+        }
+    }
+
+    #[inline]
+    fn get<'a>(&'a self, cell_key: CellKey) -> Option<&'a Cell<K>> {
+        match *self {
+            CellStorage::Sparse(ref cells) => cells.get(&cell_key),
+            CellStorage::Dense { cols, rows, ref cells } => {
+                if cell_key.col < 0 || cell_key.col >= cols || cell_key.row < 0 || cell_key.row >= rows {
+                    None
+                } else {
+                    cells.get(dense_index(cols, cell_key))
+                }
+            }
+        }
+    }
+
+    fn get_mut<'a>(&'a mut self, cell_key: CellKey) -> &'a mut Cell<K> {
+        match *self {
+            CellStorage::Sparse(ref mut cells) => cells.entry(cell_key).or_insert_with(Cell::new),
+            CellStorage::Dense { cols, ref mut cells, .. } => &mut cells[dense_index(cols, cell_key)],
+        }
+    }
+
+    fn occupied<'a>(&'a self) -> Vec<(CellKey, &'a Cell<K>)> {
+        match *self {
+            CellStorage::Sparse(ref cells) => {
+                cells.iter()
+                    .filter(|&(_, cell)| !cell.entities().is_empty())
+                    .map(|(key, cell)| (*key, cell))
+                    .collect()
+            }
+            CellStorage::Dense { cols, ref cells, .. } => {
+                cells.iter()
+                    .enumerate()
+                    .filter(|&(_, cell)| !cell.entities().is_empty())
+                    .map(|(index, cell)| (CellKey::new(index as i32 / cols, index as i32 % cols), cell))
+                    .collect()
+            }
+        }
+    }
+
+    /// Moves a column and row that's off the edge of a bounded grid onto its nearest edge cell
+    fn clamp(&self, col_row: Vector2<i32>) -> Vector2<i32> {
+        match *self {
+            CellStorage::Sparse(_) => col_row,
+            CellStorage::Dense { cols, rows, .. } => {
+                Vector2::new(col_row.x.max(0).min(cols - 1), col_row.y.max(0).min(rows - 1))
+            }
+        }
+    }
+}
+
+#[inline]
+fn dense_index(cols: i32, cell_key: CellKey) -> usize {
+    (cell_key.row * cols + cell_key.col) as usize
+}
+
+/// Grid spatial partition, either infinite or covering a bounded area like a map. Entries are
+/// keyed by whatever identifies them, which for the world's grid is the entity ID; anything
+/// `Copy + Eq + Hash`, like a projectile or decal handle, works just as well.
+pub struct GridPartition<K = u32> {
     cell_width: i32,
     cell_height: i32,
-    entities: HashMap<u32, CellKey>,
-    cells: HashMap<CellKey, Cell>,
+    /// Width and height of the bounded area, or None for an infinite grid
+    bounds: Option<Vector2<i32>>,
+    entities: HashMap<K, CellKey>,
+    cells: CellStorage<K>,
+    query_counters: QueryCounters,
+    /// Goes up whenever an entity moves, so that cached query results can tell they're stale
+    generation: u64,
 }
 
-/// Infinite grid spatial partition
-impl GridPartition {
-    pub fn new(cell_width: i32, cell_height: i32) -> GridPartition {
+impl<K: Copy + Eq + Hash> GridPartition<K> {
+    pub fn new(cell_width: i32, cell_height: i32) -> GridPartition<K> {
+        GridPartition {
+            cell_width: cell_width,
+            cell_height: cell_height,
+            bounds: None,
+            entities: HashMap::new(),
+            cells: CellStorage::new(None),
+            query_counters: QueryCounters::new(),
+            generation: 0,
+        }
+    }
+
+    /// Creates a grid that covers the area from the origin to the given width and height.
+    /// Entities outside of that area go into the nearest edge cell, so they can still be found.
+    pub fn bounded(cell_width: i32, cell_height: i32, width: i32, height: i32) -> GridPartition<K> {
+        let bounds = Vector2::new(width, height);
         GridPartition {
             cell_width: cell_width,
             cell_height: cell_height,
+            bounds: Some(bounds),
             entities: HashMap::new(),
-            cells: HashMap::new(),
+            cells: CellStorage::new(Some(cell_dimensions(&bounds, cell_width, cell_height))),
+            query_counters: QueryCounters::new(),
+            generation: 0,
         }
     }
 
     /// Tells the grid where an entity is so that it can be queried later
-    pub fn update_entity(&mut self, entity_id: u32, position: &Vector2<i32>) {
+    pub fn update_entity(&mut self, entity_id: K, position: &Vector2<i32>) {
         let cell_key = self.cell_key(&position);
-        if !self.entities.contains_key(&entity_id) {
-            self.entities.insert(entity_id, cell_key);
-        } else {
-            let old_cell_key = *self.entities.get(&entity_id).unwrap();
+        if let Some(old_cell_key) = self.entities.insert(entity_id, cell_key) {
             self.remove_from_cell(old_cell_key, entity_id);
         }
         self.add_to_cell(cell_key, GridEntity::new(entity_id, *position));
+        self.generation += 1;
+    }
+
+    /// Takes the entity out of the grid, for when it's gone from the world
+    pub fn remove(&mut self, entity_id: K) {
+        if let Some(cell_key) = self.entities.remove(&entity_id) {
+            self.remove_from_cell(cell_key, entity_id);
+            self.generation += 1;
+        }
+    }
+
+    /// Changes every time an entity is updated; query results taken at the same generation
+    /// are still current
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     /// Returns the entity IDs that lie in the cells overlapped by the given bounds
     /// Note: the returned entity IDs can lie outside of the bounds
-    pub fn query(&self, start_position: &Vector2<i32>, end_position: &Vector2<i32>) -> HashSet<u32> {
+    pub fn query(&self, start_position: &Vector2<i32>, end_position: &Vector2<i32>) -> HashSet<K> {
+        let mut entities = HashSet::new();
+        self.query_into(start_position, end_position, &mut entities);
+        entities
+    }
+
+    /// Same as query, but adds the entity IDs to an existing set so that callers can reuse it
+    pub fn query_into(&self,
+                      start_position: &Vector2<i32>,
+                      end_position: &Vector2<i32>,
+                      entities: &mut HashSet<K>) {
+        let stats = self.query_cells(start_position, end_position, entities);
+        self.query_counters.record(&stats);
+    }
+
+    /// Same as query_into, but left out of the query stats. For queries that follow the camera
+    /// rather than the units, whose size has nothing to do with how the units are spread out and
+    /// would otherwise pull the tuned cell size towards the size of the screen.
+    pub fn query_into_untracked(&self,
+                                start_position: &Vector2<i32>,
+                                end_position: &Vector2<i32>,
+                                entities: &mut HashSet<K>) {
+        self.query_cells(start_position, end_position, entities);
+    }
+
+    fn query_cells(&self,
+                   start_position: &Vector2<i32>,
+                   end_position: &Vector2<i32>,
+                   entities: &mut HashSet<K>)
+                   -> QueryStats {
         let start = self.row_col(start_position);
         let end = self.row_col(end_position);
 
-        let mut entities = HashSet::new();
+        let mut stats = QueryStats {
+            queries: 1,
+            total_query_width: (end_position.x - start_position.x).max(0) as usize,
+            total_query_height: (end_position.y - start_position.y).max(0) as usize,
+            ..QueryStats::default()
+        };
+        let already_found = entities.len();
         for row in start.y..(end.y + 1) {
             for col in start.x..(end.x + 1) {
+                stats.cells_visited += 1;
                 if let Some(cell) = self.cell(CellKey::new(row, col)) {
+                    stats.entities_checked += cell.entities().len();
                     for entity in cell.entities() {
                         if entity.position.x >= start_position.x && entity.position.x <= end_position.x &&
                            entity.position.y >= start_position.y &&
@@ -128,31 +362,156 @@ impl GridPartition {
                 }
             }
         }
-        entities
+        stats.entities_found = entities.len() - already_found;
+        stats
+    }
+
+    /// Up to `k` of the entities that pass the filter, nearest to the position first, with ties
+    /// going to the lower key so that every client picks the same ones. The search works
+    /// outwards a ring of cells at a time, and only calls the filter, nearest first, on entities
+    /// that nothing in the unsearched rings could be closer than.
+    pub fn query_k_nearest<F>(&self, position: &Vector2<i32>, k: usize, mut filter: F) -> Vec<K>
+        where K: Ord,
+              F: FnMut(K) -> bool
+    {
+        let mut nearest = Vec::new();
+        if k == 0 {
+            return nearest;
+        }
+        let center = self.row_col(position);
+        let mut pending: Vec<(i64, K)> = Vec::new();
+        let mut checked = 0;
+        let mut ring = 0;
+        loop {
+            for row in (center.y - ring)..(center.y + ring + 1) {
+                for col in (center.x - ring)..(center.x + ring + 1) {
+                    let on_ring = (row - center.y).abs() == ring || (col - center.x).abs() == ring;
+                    if !on_ring {
+                        continue;
+                    }
+                    if let Some(cell) = self.cell(CellKey::new(row, col)) {
+                        checked += cell.entities().len();
+                        for entity in cell.entities() {
+                            let dx = (entity.position.x - position.x) as i64;
+                            let dy = (entity.position.y - position.y) as i64;
+                            pending.push((dx * dx + dy * dy, entity.entity_id));
+                        }
+                    }
+                }
+            }
+
+            // Nothing outside the searched block of cells can be closer than its nearest edge
+            let exhausted = checked >= self.entities.len();
+            let reach = self.ring_reach(position, &center, ring) as i64;
+            pending.sort();
+            let settled = if exhausted {
+                pending.len()
+            } else {
+                pending.iter().take_while(|&&(distance_squared, _)| distance_squared < reach * reach).count()
+            };
+            for (_, entity_id) in pending.drain(..settled) {
+                if filter(entity_id) {
+                    nearest.push(entity_id);
+                    if nearest.len() == k {
+                        return nearest;
+                    }
+                }
+            }
+            if exhausted {
+                return nearest;
+            }
+            ring += 1;
+        }
+    }
+
+    /// How far the position is from the nearest edge of the block of cells within the ring of
+    /// the center cell, or zero if it isn't inside that block
+    fn ring_reach(&self, position: &Vector2<i32>, center: &Vector2<i32>, ring: i32) -> i32 {
+        let left = (center.x - ring) * self.cell_width;
+        let right = (center.x + ring + 1) * self.cell_width;
+        let top = (center.y - ring) * self.cell_height;
+        let bottom = (center.y + ring + 1) * self.cell_height;
+        (position.x - left).min(right - position.x).min(position.y - top).min(bottom - position.y).max(0)
+    }
+
+    /// How much work the queries have done since the grid was built or the stats were reset
+    pub fn query_stats(&self) -> QueryStats {
+        self.query_counters.snapshot()
+    }
+
+    pub fn reset_query_stats(&mut self) {
+        self.query_counters.reset();
+    }
+
+    /// Cell size suited to the queries made since the stats were last reset, or None if there
+    /// haven't been any. Cells half the size of the average query keep each query to about
+    /// nine cells, while staying small enough that most of the entities checked are in bounds.
+    pub fn tuned_cell_size(&self) -> Option<Vector2<i32>> {
+        let stats = self.query_stats();
+        if stats.queries == 0 {
+            return None;
+        }
+        let query_size = stats.average_query_size();
+        Some(Vector2::new((query_size.x / 2).max(MIN_TUNED_CELL_SIZE),
+                          (query_size.y / 2).max(MIN_TUNED_CELL_SIZE)))
+    }
+
+    /// Moves every entity into cells of the new size. The query stats start over, since they
+    /// describe how the old cells performed.
+    pub fn rebuild_with_cell_size(&mut self, cell_width: i32, cell_height: i32) {
+        let grid_entities: Vec<GridEntity<K>> =
+            self.cells.occupied().iter().flat_map(|&(_, cell)| cell.entities().iter().cloned()).collect();
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self.entities.clear();
+        let dimensions = self.bounds.map(|bounds| cell_dimensions(&bounds, cell_width, cell_height));
+        self.cells = CellStorage::new(dimensions);
+        for grid_entity in grid_entities {
+            self.update_entity(grid_entity.entity_id, &grid_entity.position);
+        }
+        self.reset_query_stats();
     }
 
-    pub fn contains(&self, entity_id: u32) -> bool {
+    pub fn contains(&self, entity_id: K) -> bool {
         self.entities.contains_key(&entity_id)
     }
 
-    fn add_to_cell(&mut self, cell_key: CellKey, entity: GridEntity) {
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Width and height of each cell
+    pub fn cell_size(&self) -> Vector2<i32> {
+        Vector2::new(self.cell_width, self.cell_height)
+    }
+
+    /// Returns the column/row of every occupied cell along with how many entities are in it,
+    /// sorted by row and then column
+    pub fn cell_occupancy(&self) -> Vec<(Vector2<i32>, usize)> {
+        let mut occupancy: Vec<(Vector2<i32>, usize)> = self.cells
+            .occupied()
+            .iter()
+            .map(|&(key, cell)| (Vector2::new(key.col, key.row), cell.entities().len()))
+            .collect();
+        occupancy.sort_by_key(|&(col_row, _)| (col_row.y, col_row.x));
+        occupancy
+    }
+
+    fn add_to_cell(&mut self, cell_key: CellKey, entity: GridEntity<K>) {
         self.cell_mut(cell_key).add(entity);
     }
 
-    fn remove_from_cell(&mut self, cell_key: CellKey, entity_id: u32) {
+    fn remove_from_cell(&mut self, cell_key: CellKey, entity_id: K) {
         self.cell_mut(cell_key).remove(entity_id);
     }
 
     #[inline]
-    fn cell<'a>(&'a self, cell_key: CellKey) -> Option<&'a Cell> {
-        self.cells.get(&cell_key)
+    fn cell<'a>(&'a self, cell_key: CellKey) -> Option<&'a Cell<K>> {
+        self.cells.get(cell_key)
     }
 
-    fn cell_mut<'a>(&'a mut self, cell_key: CellKey) -> &'a mut Cell {
-        if !self.cells.contains_key(&cell_key) {
-            self.cells.insert(cell_key, Cell::new());
-        }
-        self.cells.get_mut(&cell_key).unwrap()
+    fn cell_mut<'a>(&'a mut self, cell_key: CellKey) -> &'a mut Cell<K> {
+        self.cells.get_mut(cell_key)
     }
 
     fn cell_key(&self, position: &Vector2<i32>) -> CellKey {
@@ -160,16 +519,24 @@ impl GridPartition {
         CellKey::new(row_col.y, row_col.x)
     }
 
+    /// Column and row of the cell containing the position, clamped to the edge cells of a
+    /// bounded grid
     fn row_col(&self, position: &Vector2<i32>) -> Vector2<i32> {
-        Vector2::new(position.x / self.cell_width, position.y / self.cell_height)
+        self.cells.clamp(Vector2::new(position.x / self.cell_width, position.y / self.cell_height))
     }
 }
 
+/// Number of columns and rows needed to cover the bounds, rounding partial cells up
+fn cell_dimensions(bounds: &Vector2<i32>, cell_width: i32, cell_height: i32) -> Vector2<i32> {
+    Vector2::new(((bounds.x + cell_width - 1) / cell_width).max(1),
+                 ((bounds.y + cell_height - 1) / cell_height).max(1))
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::Vector2;
     use std::collections::HashSet;
-    use super::{Cell, CellKey, GridEntity, GridPartition};
+    use super::{Cell, CellKey, GridEntity, GridPartition, QueryStats};
 
     macro_rules! ids {
         [ $($id:expr),* ] => {
@@ -207,7 +574,7 @@ mod tests {
 
     #[test]
     fn test_cell_key_from_position() {
-        let grid = GridPartition::new(10, 10);
+        let grid: GridPartition = GridPartition::new(10, 10);
         assert_eq!(CellKey::new(0, 0), grid.cell_key(&v(0, 0)));
         assert_eq!(CellKey::new(0, 0), grid.cell_key(&v(5, 5)));
         assert_eq!(CellKey::new(0, 1), grid.cell_key(&v(10, 5)));
@@ -232,6 +599,32 @@ mod tests {
         assert!(grid.cell_mut(CellKey::new(0, 0)).entities().is_empty());
         assert_eq!(&vec![entity2], grid.cell_mut(CellKey::new(0, 1)).entities());
         assert_eq!(&vec![entity1], grid.cell_mut(CellKey::new(1, 2)).entities());
+
+        // Moving again has to take the entity out of the cell it most recently moved to
+        grid.update_entity(1, &v(35, 35));
+        let entity1 = GridEntity::new(1, Vector2::new(35, 35));
+
+        assert!(grid.cell_mut(CellKey::new(1, 2)).entities().is_empty());
+        assert_eq!(&vec![entity1], grid.cell_mut(CellKey::new(3, 3)).entities());
+    }
+
+    #[test]
+    fn test_grid_remove() {
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(6, 5));
+        let generation = grid.generation();
+
+        grid.remove(1);
+        assert!(!grid.contains(1));
+        assert_eq!(1, grid.entity_count());
+        assert_eq!(ids![2], grid.query(&v(0, 0), &v(9, 9)));
+        assert!(grid.generation() > generation);
+
+        // Removing what isn't there leaves the grid alone
+        let generation = grid.generation();
+        grid.remove(1);
+        assert_eq!(generation, grid.generation());
     }
 
     #[test]
@@ -247,4 +640,161 @@ mod tests {
         assert_eq!(ids![3], grid.query(&v(9, 0), &v(20, 10)));
         assert_eq!(ids![3], grid.query(&v(10, 0), &v(20, 10)));
     }
+
+    #[test]
+    fn test_grid_cell_occupancy() {
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(6, 5));
+        grid.update_entity(3, &v(15, 5));
+        grid.update_entity(4, &v(5, 15));
+        assert_eq!(4, grid.entity_count());
+        assert_eq!(vec![(v(0, 0), 2), (v(1, 0), 1), (v(0, 1), 1)], grid.cell_occupancy());
+
+        // Cells that have been emptied out aren't reported
+        grid.update_entity(3, &v(5, 5));
+        assert_eq!(vec![(v(0, 0), 3), (v(0, 1), 1)], grid.cell_occupancy());
+    }
+
+    #[test]
+    fn test_grid_query_stats() {
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(6, 5));
+        grid.update_entity(3, &v(15, 5));
+        assert_eq!(QueryStats::default(), grid.query_stats());
+
+        grid.query(&v(1, 1), &v(9, 9));
+        grid.query(&v(0, 0), &v(29, 9));
+        let stats = grid.query_stats();
+        assert_eq!(2, stats.queries);
+        assert_eq!(4, stats.cells_visited);
+        assert_eq!(5, stats.entities_checked);
+        assert_eq!(5, stats.entities_found);
+        assert_eq!(v(18, 8), stats.average_query_size());
+        assert_eq!(Some(v(9, 4)), grid.tuned_cell_size());
+
+        grid.reset_query_stats();
+        assert_eq!(None, grid.tuned_cell_size());
+
+        let mut found = HashSet::new();
+        grid.query_into_untracked(&v(0, 0), &v(29, 9), &mut found);
+        assert_eq!(ids![1, 2, 3], found);
+        assert_eq!(QueryStats::default(), grid.query_stats());
+    }
+
+    #[test]
+    fn test_grid_rebuild_with_cell_size() {
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(6, 5));
+        grid.update_entity(3, &v(15, 5));
+        grid.update_entity(4, &v(5, 15));
+        grid.query(&v(0, 0), &v(20, 20));
+
+        grid.rebuild_with_cell_size(20, 20);
+        assert_eq!(v(20, 20), grid.cell_size());
+        assert_eq!(4, grid.entity_count());
+        assert_eq!(vec![(v(0, 0), 4)], grid.cell_occupancy());
+        assert_eq!(ids![1, 2, 3, 4], grid.query(&v(0, 0), &v(20, 20)));
+        assert_eq!(1, grid.query_stats().queries);
+
+        // Entities still move out of the right cell after the rebuild
+        grid.update_entity(1, &v(45, 5));
+        assert_eq!(vec![(v(0, 0), 3), (v(2, 0), 1)], grid.cell_occupancy());
+    }
+
+    #[test]
+    fn test_bounded_grid() {
+        let mut grid = GridPartition::bounded(10, 10, 25, 20);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(24, 19));
+        grid.update_entity(3, &v(15, 5));
+        assert_eq!(vec![(v(0, 0), 1), (v(1, 0), 1), (v(2, 1), 1)], grid.cell_occupancy());
+        assert_eq!(ids![1, 3], grid.query(&v(0, 0), &v(15, 9)));
+        assert_eq!(ids![2, 3], grid.query(&v(10, 0), &v(100, 100)));
+
+        grid.update_entity(3, &v(5, 15));
+        assert_eq!(vec![(v(0, 0), 1), (v(0, 1), 1), (v(2, 1), 1)], grid.cell_occupancy());
+    }
+
+    #[test]
+    fn test_bounded_grid_clamps_to_edge_cells() {
+        let mut grid = GridPartition::bounded(10, 10, 20, 20);
+        grid.update_entity(1, &v(-5, 5));
+        grid.update_entity(2, &v(35, 45));
+        assert_eq!(vec![(v(0, 0), 1), (v(1, 1), 1)], grid.cell_occupancy());
+
+        // Only the edge cells get visited for queries that reach past the bounds
+        assert_eq!(ids![1], grid.query(&v(-10, 0), &v(0, 9)));
+        assert_eq!(ids![2], grid.query(&v(30, 30), &v(1000, 1000)));
+        assert_eq!(2, grid.query_stats().cells_visited);
+    }
+
+    #[test]
+    fn test_grid_query_k_nearest() {
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(1, &v(50, 50));
+        grid.update_entity(2, &v(52, 50));
+        grid.update_entity(3, &v(50, 35));
+        grid.update_entity(4, &v(48, 50));
+        grid.update_entity(5, &v(5, 95));
+        grid.update_entity(6, &v(59, 50));
+
+        let center = v(50, 50);
+        assert_eq!(vec![1, 2, 4], grid.query_k_nearest(&center, 3, |_| true));
+        assert_eq!(vec![6, 3, 5], grid.query_k_nearest(&center, 3, |id| id != 2 && id != 4 && id != 1));
+        assert_eq!(vec![1, 2, 4, 6, 3, 5], grid.query_k_nearest(&center, 10, |_| true));
+        assert!(grid.query_k_nearest(&center, 0, |_| true).is_empty());
+        assert!(grid.query_k_nearest(&center, 2, |_| false).is_empty());
+
+        // The filter is only asked about entities nearest first, and not past the k found
+        let mut asked = Vec::new();
+        grid.query_k_nearest(&center, 2, |id| {
+            asked.push(id);
+            id != 2
+        });
+        assert_eq!(vec![1, 2, 4], asked);
+    }
+
+    #[test]
+    fn test_bounded_grid_query_k_nearest() {
+        let mut grid = GridPartition::bounded(10, 10, 40, 40);
+        grid.update_entity(1, &v(-20, 5));
+        grid.update_entity(2, &v(30, 30));
+        grid.update_entity(3, &v(12, 5));
+        assert_eq!(vec![3, 1, 2], grid.query_k_nearest(&v(5, 5), 3, |_| true));
+    }
+
+    #[test]
+    fn test_grid_with_other_keys() {
+        #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+        enum PickTarget {
+            Minimap,
+            Button(u8),
+        }
+
+        let mut grid = GridPartition::new(10, 10);
+        grid.update_entity(PickTarget::Minimap, &v(5, 5));
+        grid.update_entity(PickTarget::Button(1), &v(15, 5));
+        grid.update_entity(PickTarget::Button(2), &v(25, 5));
+        grid.update_entity(PickTarget::Button(1), &v(5, 15));
+
+        let found = grid.query(&v(0, 0), &v(20, 20));
+        assert_eq!(2, found.len());
+        assert!(found.contains(&PickTarget::Minimap) && found.contains(&PickTarget::Button(1)));
+        assert!(grid.contains(PickTarget::Button(2)));
+    }
+
+    #[test]
+    fn test_bounded_grid_rebuild_with_cell_size() {
+        let mut grid = GridPartition::bounded(10, 10, 40, 40);
+        grid.update_entity(1, &v(5, 5));
+        grid.update_entity(2, &v(35, 35));
+
+        grid.rebuild_with_cell_size(20, 20);
+        assert_eq!(vec![(v(0, 0), 1), (v(1, 1), 1)], grid.cell_occupancy());
+        assert_eq!(ids![1, 2], grid.query(&v(0, 0), &v(40, 40)));
+        assert_eq!(4, grid.query_stats().cells_visited);
+    }
 }
@@ -21,4 +21,4 @@
 
 mod grid;
 
-pub use self::grid::GridPartition;
+pub use self::grid::{GridPartition, QueryStats};
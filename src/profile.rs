@@ -0,0 +1,368 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! The local player profile: who's playing, how they like to play, and how they've done. It's
+//! kept as a TOML file in the user's config directory, apart from the game's own config file, so
+//! it follows the player from one game install to the next. The profile's hotkeys are layered on
+//! top of the config file's, and each finished game is added to its lifetime statistics.
+
+use ecs::resource::PlayerStatistics;
+use identifier::{CivilizationId, UnitId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml::{self, Table, Value};
+
+pub const PROFILE_FILE_NAME: &'static str = "profile.toml";
+
+/// How many of the most trained units are shown as favorites
+pub const FAVORITE_UNIT_COUNT: usize = 5;
+
+const DEFAULT_NAME: &'static str = "Player";
+
+/// Where per-user files go: `%APPDATA%\chariot` on Windows, and `$XDG_CONFIG_HOME/chariot`
+/// or `~/.config/chariot` elsewhere. Falls back to the working directory if none of those
+/// are set.
+pub fn user_config_dir() -> PathBuf {
+    let var = |name| env::var_os(name).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        var("APPDATA")
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    };
+    match base {
+        Some(base) => base.join("chariot"),
+        None => PathBuf::from("."),
+    }
+}
+
+pub fn default_profile_path() -> PathBuf {
+    user_config_dir().join(PROFILE_FILE_NAME)
+}
+
+/// What a scenario is called in the campaign progress: its file name without the extension
+pub fn scenario_name<P: AsRef<Path>>(scenario_path: P) -> String {
+    let path = scenario_path.as_ref();
+    path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Totals over every game played with the profile that was played to the end
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LifetimeStatistics {
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub units_trained: u32,
+    pub military_units_trained: u32,
+    pub technologies_researched: u32,
+    pub resources_spent: u32,
+    pub trained_by_unit: BTreeMap<UnitId, u32>,
+}
+
+impl LifetimeStatistics {
+    /// The most trained units, most first; ties go to the lower unit ID
+    pub fn favorite_units(&self, count: usize) -> Vec<(UnitId, u32)> {
+        let mut units: Vec<(UnitId, u32)> = self.trained_by_unit
+            .iter()
+            .map(|(unit_id, trained)| (*unit_id, *trained))
+            .collect();
+        units.sort_by(|a, b| (b.1, a.0).cmp(&(a.1, b.0)));
+        units.truncate(count);
+        units
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub preferred_civ: Option<CivilizationId>,
+    /// Hotkey layout, as the `[keys]` table of the config file takes it
+    pub keys: Table,
+    /// Campaign progress: the scenarios that have been won, by file name without the extension
+    pub completed_scenarios: BTreeSet<String>,
+    pub statistics: LifetimeStatistics,
+}
+
+impl Profile {
+    pub fn new<S: Into<String>>(name: S) -> Profile {
+        Profile {
+            name: name.into(),
+            preferred_civ: None,
+            keys: Table::new(),
+            completed_scenarios: BTreeSet::new(),
+            statistics: LifetimeStatistics::default(),
+        }
+    }
+
+    /// A new profile named after the user that's logged in
+    pub fn for_current_user() -> Profile {
+        let name = env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or(DEFAULT_NAME.into());
+        Profile::new(name)
+    }
+
+    /// Reads the profile from the given file, or starts a new one if there isn't one yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Profile, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                try!(file.read_to_string(&mut text)
+                    .map_err(|err| format!("Failed to read {}: {}", path.display(), err)));
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Profile::for_current_user()),
+            Err(err) => return Err(format!("Failed to open {}: {}", path.display(), err)),
+        }
+        let mut parser = toml::Parser::new(&text);
+        match parser.parse() {
+            Some(table) => {
+                Profile::from_table(&table)
+                    .map_err(|err| format!("Invalid profile {}: {}", path.display(), err))
+            }
+            None => {
+                let messages: Vec<String> = parser.errors
+                    .iter()
+                    .map(|err| {
+                        let (line, col) = parser.to_linecol(err.lo);
+                        format!("line {}, column {}: {}", line + 1, col + 1, err.desc)
+                    })
+                    .collect();
+                Err(format!("Failed to parse {}: {}", path.display(), messages.join("; ")))
+            }
+        }
+    }
+
+    /// Writes the profile out, creating the directory if it's needed. It's written to a
+    /// temporary file first so that a failed write can't lose the old profile.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir)
+                .map_err(|err| format!("Failed to create {}: {}", dir.display(), err)));
+        }
+        let temp_path = path.with_extension("toml.tmp");
+        let result = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(Value::Table(self.to_table()).to_string().as_bytes()))
+            .and_then(|_| fs::rename(&temp_path, path));
+        result.map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    }
+
+    /// Adds a finished game to the lifetime statistics. Winning a scenario counts it as
+    /// completed for the campaign progress.
+    pub fn record_game(&mut self, scenario_name: &str, won: bool, player: &PlayerStatistics) {
+        let statistics = &mut self.statistics;
+        statistics.games_played += 1;
+        if won {
+            statistics.wins += 1;
+            self.completed_scenarios.insert(scenario_name.into());
+        } else {
+            statistics.losses += 1;
+        }
+        statistics.units_trained += player.units_trained;
+        statistics.military_units_trained += player.military_units_trained;
+        statistics.technologies_researched += player.technologies_researched;
+        statistics.resources_spent += player.resources_spent;
+        for (unit_id, trained) in &player.trained_by_unit {
+            *statistics.trained_by_unit.entry(*unit_id).or_insert(0) += *trained;
+        }
+    }
+
+    /// The settings the profile adds to the config, as a config layer
+    pub fn config_layer(&self) -> Table {
+        let mut layer = Table::new();
+        if !self.keys.is_empty() {
+            layer.insert("keys".into(), Value::Table(self.keys.clone()));
+        }
+        layer
+    }
+
+    fn from_table(table: &Table) -> Result<Profile, String> {
+        let mut profile = Profile::new(DEFAULT_NAME);
+        for (key, value) in table {
+            match &key[..] {
+                "name" => profile.name = try!(as_string(key, value)),
+                "preferred_civ" => {
+                    let civ = try!(as_count(key, value));
+                    if civ > u8::max_value() as u32 {
+                        return Err(format!("{}: {} isn't a civilization", key, civ));
+                    }
+                    profile.preferred_civ = Some((civ as usize).into());
+                }
+                "keys" => profile.keys = try!(as_table(key, value)).clone(),
+                "completed_scenarios" => {
+                    let values = match *value {
+                        Value::Array(ref values) => values,
+                        _ => return Err(format!("{}: expected a list of scenario names", key)),
+                    };
+                    for value in values {
+                        profile.completed_scenarios.insert(try!(as_string(key, value)));
+                    }
+                }
+                "statistics" => profile.statistics = try!(statistics_from_table(try!(as_table(key, value)))),
+                _ => warn!("Unknown profile setting \"{}\"", key),
+            }
+        }
+        Ok(profile)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.name.clone()));
+        if let Some(civ) = self.preferred_civ {
+            table.insert("preferred_civ".into(), Value::Integer(*civ as i64));
+        }
+        let completed = self.completed_scenarios.iter().map(|name| Value::String(name.clone())).collect();
+        table.insert("completed_scenarios".into(), Value::Array(completed));
+        table.insert("keys".into(), Value::Table(self.keys.clone()));
+
+        let statistics = &self.statistics;
+        let mut statistics_table = Table::new();
+        for &(key, count) in &[("games_played", statistics.games_played),
+                               ("wins", statistics.wins),
+                               ("losses", statistics.losses),
+                               ("units_trained", statistics.units_trained),
+                               ("military_units_trained", statistics.military_units_trained),
+                               ("technologies_researched", statistics.technologies_researched),
+                               ("resources_spent", statistics.resources_spent)] {
+            statistics_table.insert(key.into(), Value::Integer(count as i64));
+        }
+        let trained = statistics.trained_by_unit
+            .iter()
+            .map(|(unit_id, trained)| ((**unit_id).to_string(), Value::Integer(*trained as i64)))
+            .collect();
+        statistics_table.insert("trained".into(), Value::Table(trained));
+        table.insert("statistics".into(), Value::Table(statistics_table));
+        table
+    }
+}
+
+fn statistics_from_table(table: &Table) -> Result<LifetimeStatistics, String> {
+    let mut statistics = LifetimeStatistics::default();
+    for (key, value) in table {
+        match &key[..] {
+            "games_played" => statistics.games_played = try!(as_count(key, value)),
+            "wins" => statistics.wins = try!(as_count(key, value)),
+            "losses" => statistics.losses = try!(as_count(key, value)),
+            "units_trained" => statistics.units_trained = try!(as_count(key, value)),
+            "military_units_trained" => statistics.military_units_trained = try!(as_count(key, value)),
+            "technologies_researched" => statistics.technologies_researched = try!(as_count(key, value)),
+            "resources_spent" => statistics.resources_spent = try!(as_count(key, value)),
+            "trained" => {
+                for (unit_key, trained) in try!(as_table(key, value)) {
+                    let unit_id: usize = try!(unit_key.parse()
+                        .map_err(|_| format!("statistics.trained: \"{}\" isn't a unit ID", unit_key)));
+                    statistics.trained_by_unit.insert(unit_id.into(), try!(as_count(unit_key, trained)));
+                }
+            }
+            _ => warn!("Unknown profile statistic \"{}\"", key),
+        }
+    }
+    Ok(statistics)
+}
+
+fn as_string(key: &str, value: &Value) -> Result<String, String> {
+    match *value {
+        Value::String(ref string) => Ok(string.clone()),
+        _ => Err(format!("{}: expected a string", key)),
+    }
+}
+
+fn as_table<'a>(key: &str, value: &'a Value) -> Result<&'a Table, String> {
+    match *value {
+        Value::Table(ref table) => Ok(table),
+        _ => Err(format!("{}: expected a table", key)),
+    }
+}
+
+fn as_count(key: &str, value: &Value) -> Result<u32, String> {
+    match *value {
+        Value::Integer(integer) if integer >= 0 && integer <= u32::max_value() as i64 => Ok(integer as u32),
+        _ => Err(format!("{}: expected a whole number that isn't negative", key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecs::resource::PlayerStatistics;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use super::{FAVORITE_UNIT_COUNT, Profile, scenario_name};
+    use toml::Value;
+
+    fn sample_game(trained: &[(usize, u32)]) -> PlayerStatistics {
+        let mut player = PlayerStatistics::default();
+        for &(unit_id, count) in trained {
+            player.units_trained += count;
+            player.trained_by_unit.insert(unit_id.into(), count);
+        }
+        player.resources_spent = 500;
+        player
+    }
+
+    #[test]
+    fn test_record_game() {
+        let mut profile = Profile::new("Tester");
+        profile.record_game("c1s1", true, &sample_game(&[(83, 10), (4, 3)]));
+        profile.record_game("c1s2", false, &sample_game(&[(4, 8), (5, 1)]));
+
+        let statistics = &profile.statistics;
+        assert_eq!((2, 1, 1), (statistics.games_played, statistics.wins, statistics.losses));
+        assert_eq!(22, statistics.units_trained);
+        assert_eq!(1000, statistics.resources_spent);
+        assert_eq!(vec!["c1s1".to_string()], profile.completed_scenarios.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(vec![(4.into(), 11), (83.into(), 10), (5.into(), 1)],
+                   statistics.favorite_units(FAVORITE_UNIT_COUNT));
+        assert_eq!(1, statistics.favorite_units(1).len());
+        assert_eq!("c1s1", scenario_name("campaign/c1s1.scn"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir().join("chariot_profile_test").join("profile.toml");
+        let _ = fs::remove_file(&path);
+        let mut profile = Profile::new("Tester");
+        profile.preferred_civ = Some(3.into());
+        profile.keys.insert("toggle_market".into(), Value::String("F3".into()));
+        profile.record_game("c1s1", true, &sample_game(&[(83, 10)]));
+        profile.save(&path).unwrap();
+
+        assert_eq!(profile, Profile::load(&path).unwrap());
+        assert!(profile.config_layer().contains_key("keys"));
+        assert!(Profile::new("Tester").config_layer().is_empty());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        let path = env::temp_dir().join("chariot_profile_invalid_test.toml");
+        for text in &["name = 3",
+                      "preferred_civ = 300",
+                      "[statistics]\nwins = -1",
+                      "[statistics.trained]\nbow = 2"] {
+            File::create(&path).and_then(|mut file| file.write_all(text.as_bytes())).unwrap();
+            assert!(Profile::load(&path).is_err(), "{}", text);
+        }
+        let _ = fs::remove_file(&path);
+        assert_eq!(0, Profile::load(&path).unwrap().statistics.games_played);
+    }
+}
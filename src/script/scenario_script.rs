@@ -0,0 +1,799 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat::{self, ResourceType};
+use ecs::{QueryContext, TransformComponent, UnitComponent, WorldQuery};
+use ecs::resource::{AreaQueryCache, Chat, ChatMessage, Diplomacy, Market, Player, Players, SceneTint,
+                    SpawnRequest, Technologies, UnitSpawner, apply_research_effects, research_effects};
+use identifier::{PlayerId, ResearchId, UnitId};
+use nalgebra::Vector2;
+use partition::GridPartition;
+use rhai::{self, AST, CallFnOptions, Engine, EvalAltResult, ImmutableString, Scope};
+use specs::{self, Index, Join};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use types::{Color, Fixed, Vector3};
+
+/// Extension of the script file that sits next to a scenario and goes with it
+const SIDECAR_EXTENSION: &'static str = "rhai";
+
+/// How much work a script gets to do per call before it's cut off, so that a runaway loop
+/// in a scenario can't hang the game
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 4096;
+const MAX_COLLECTION_SIZE: usize = 4096;
+
+/// Cell size of the grid rebuilt for the area queries of a script view
+const VIEW_GRID_CELL_SIZE: i32 = 8;
+
+/// Something a script asked to happen to the world; these are collected while the script runs,
+/// and the game state applies them once it's done.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptEffect {
+    /// Spawns a unit at a tile position
+    Spawn {
+        player_id: PlayerId,
+        unit_id: UnitId,
+        position: Vector2<i32>,
+    },
+    AddToStockpile(PlayerId, ResourceType, Fixed),
+    SetStockpile(PlayerId, ResourceType, Fixed),
+    /// Shows a message to every player
+    Message(String),
+    /// Tints the world, in place of the time of day, or goes back to it with None
+    SetTint(Option<Color>),
+    /// Finishes the research for the player, as if they'd researched it
+    CompleteResearch(PlayerId, ResearchId),
+}
+
+/// What a script sees of the world while it runs. Script functions can't borrow the world,
+/// so the parts they query are copied out before a call that needs them.
+pub struct ScriptView {
+    units: Vec<(Index, UnitComponent)>,
+    players: Players,
+    technologies: Technologies,
+    grid: GridPartition,
+    area_cache: AreaQueryCache,
+}
+
+impl ScriptView {
+    pub fn new() -> ScriptView {
+        ScriptView {
+            units: Vec::new(),
+            players: Players::new(),
+            technologies: Technologies::new(),
+            grid: GridPartition::new(VIEW_GRID_CELL_SIZE, VIEW_GRID_CELL_SIZE),
+            area_cache: AreaQueryCache::new(),
+        }
+    }
+
+    /// A view of no units, with the given players
+    pub fn with_players(players: Players) -> ScriptView {
+        let mut view = ScriptView::new();
+        view.players = players;
+        view
+    }
+
+    pub fn from_world(world: &specs::World) -> ScriptView {
+        let mut view = ScriptView::new();
+        let entities = world.entities();
+        let units = world.read::<UnitComponent>();
+        let transforms = world.read::<TransformComponent>();
+        for (entity, unit, transform) in (&entities, &units, &transforms).iter() {
+            let position = transform.position();
+            view.grid.update_entity(entity.get_id(), &Vector2::new(position.x.into(), position.y.into()));
+            view.units.push((entity.get_id(), unit.clone()));
+        }
+
+        let players = world.read_resource::<Players>();
+        for player_id in players.player_ids() {
+            let player = players.player(player_id).unwrap();
+            let mut copy = Player::new(player.name.clone(),
+                                       player.player_id,
+                                       player.player_color_id,
+                                       player.civ_id);
+            copy.computer = player.computer;
+            copy.stockpile = player.stockpile.clone();
+            view.players.add_player(copy, player_id == players.local_player_id());
+        }
+        view.technologies = world.read_resource::<Technologies>().clone();
+        view
+    }
+
+    pub fn evaluate(&mut self, query: &WorldQuery) -> i64 {
+        let mut context = QueryContext {
+            players: &self.players,
+            technologies: &self.technologies,
+            grid: &self.grid,
+            area_cache: &mut self.area_cache,
+        };
+        query.evaluate(&mut context, self.units.iter().map(|&(entity_id, ref unit)| (entity_id, unit)))
+            .into()
+    }
+}
+
+/// A rule that fired while tracing was on: a name passed to `fire_once` for the first time,
+/// along with what the script had asked about the world in that call before it did, which is
+/// what the rule's condition was decided on
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleTrace {
+    pub tick: usize,
+    /// The script function that was running
+    pub function_name: String,
+    pub rule: String,
+    /// Queries and their answers
+    pub facts: Vec<(String, i64)>,
+}
+
+impl fmt::Display for RuleTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "tick {}, {}: \"{}\" fired", self.tick, self.function_name, self.rule));
+        for (index, &(ref query, value)) in self.facts.iter().enumerate() {
+            let separator = if index == 0 { " with" } else { "," };
+            try!(write!(f, "{} {} = {}", separator, query, value));
+        }
+        Ok(())
+    }
+}
+
+/// A function the script asked to have called every so many ticks
+struct TickHook {
+    interval: usize,
+    function_name: String,
+}
+
+/// State shared between the script host and the functions it exposes to the script
+struct SharedState {
+    effects: Vec<ScriptEffect>,
+    hooks: Vec<TickHook>,
+    view: Option<ScriptView>,
+    /// Names passed to `fire_once` so far
+    fired: HashSet<String>,
+    tracing: bool,
+    /// The tick and function of the call that's running, for traces
+    tick: usize,
+    function_name: String,
+    /// What's been queried so far in the running call, while tracing
+    facts: Vec<(String, i64)>,
+    traces: Vec<RuleTrace>,
+}
+
+type SharedStateRef = Rc<RefCell<SharedState>>;
+
+/// A scenario's script. The script is run once when the scenario starts, which is where it
+/// registers its tick hooks with `every(ticks, "function name")`; it can also define
+/// `on_start()` and `on_tick(tick)`, which get called if they exist.
+///
+/// Scripts only get to the world through the functions registered here:
+///
+/// * `spawn_unit(player, unit id, x, y)` spawns a unit on a tile
+/// * `add_stockpile(player, resource, amount)` and `set_stockpile(player, resource, amount)`
+///   change a player's resources
+/// * `stockpile(player, resource)` and `query(text)` ask about the world, the latter taking the
+///   same queries as the debug console's `query` command
+/// * `show_message(text)` shows a message to every player
+/// * `fire_once(name)` is true the first time it's called with a name, and false after that,
+///   since script functions can't keep state of their own between calls
+///
+/// The engine is sandboxed: there's no module loading or `eval`, and every call is limited in
+/// how much work it can do.
+///
+/// A script loaded from a file can be reloaded while the game runs, and while tracing is on it
+/// records which `fire_once` rules fired and what they were decided on.
+pub struct ScenarioScript {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: SharedStateRef,
+    has_on_start: bool,
+    has_on_tick: bool,
+    /// Where the script was loaded from, if it was loaded from a file
+    path: Option<PathBuf>,
+}
+
+impl ScenarioScript {
+    pub fn compile(source: &str) -> Result<ScenarioScript, String> {
+        let state = Rc::new(RefCell::new(SharedState {
+            effects: Vec::new(),
+            hooks: Vec::new(),
+            view: None,
+            fired: HashSet::new(),
+            tracing: false,
+            tick: 0,
+            function_name: String::new(),
+            facts: Vec::new(),
+            traces: Vec::new(),
+        }));
+        let engine = create_engine(&state);
+        let ast = try!(engine.compile(source).map_err(|err| format!("{}", err)));
+        let (has_on_start, has_on_tick) = {
+            let has_function = |name: &str, param_count: usize| {
+                ast.iter_functions()
+                    .any(|function| function.name == name && function.params.len() == param_count)
+            };
+            (has_function("on_start", 0), has_function("on_tick", 1))
+        };
+        Ok(ScenarioScript {
+            engine: engine,
+            ast: ast,
+            scope: Scope::new(),
+            state: state,
+            has_on_start: has_on_start,
+            has_on_tick: has_on_tick,
+            path: None,
+        })
+    }
+
+    pub fn load_file<P: AsRef<Path>>(script_path: P) -> Result<ScenarioScript, String> {
+        let script_path = script_path.as_ref();
+        let mut source = String::new();
+        try!(File::open(script_path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|err| format!("failed to read {}: {}", script_path.display(), err)));
+        let mut script = try!(ScenarioScript::compile(&source)
+            .map_err(|err| format!("{}: {}", script_path.display(), err)));
+        script.path = Some(script_path.to_path_buf());
+        Ok(script)
+    }
+
+    pub fn path<'a>(&'a self) -> Option<&'a Path> {
+        self.path.as_ref().map(|path| path.as_path())
+    }
+
+    /// Loads the script's file again and runs its top level, which registers its tick hooks
+    /// anew; `on_start` isn't called again. Rules that already fired stay fired. If the new
+    /// version doesn't load or its top level fails, the script is left as it was.
+    pub fn reload(&mut self, view: ScriptView) -> Result<Vec<ScriptEffect>, String> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Err("the script wasn't loaded from a file".into()),
+        };
+        let mut reloaded = try!(ScenarioScript::load_file(&path));
+        {
+            let state = self.state.borrow();
+            let mut reloaded_state = reloaded.state.borrow_mut();
+            reloaded_state.fired = state.fired.clone();
+            reloaded_state.tracing = state.tracing;
+            reloaded_state.tick = state.tick;
+        }
+        let effects = try!(reloaded.run_top_level(view)
+            .map_err(|err| format!("{}: {}", path.display(), err)));
+        *self = reloaded;
+        Ok(effects)
+    }
+
+    pub fn set_tracing(&mut self, tracing: bool) {
+        let mut state = self.state.borrow_mut();
+        state.tracing = tracing;
+        if !tracing {
+            state.traces.clear();
+        }
+    }
+
+    /// The rules that fired since the last time this was called, while tracing was on
+    pub fn take_traces(&mut self) -> Vec<RuleTrace> {
+        self.state.borrow_mut().traces.drain(..).collect()
+    }
+
+    /// Where a scenario's own script goes: next to the scenario file, with the same name and a
+    /// `.rhai` extension
+    pub fn sidecar_path<P: AsRef<Path>>(scenario_path: P) -> PathBuf {
+        scenario_path.as_ref().with_extension(SIDECAR_EXTENSION)
+    }
+
+    /// Loads the scenario's own script. Returns None if the scenario doesn't have one.
+    pub fn load_sidecar<P: AsRef<Path>>(scenario_path: P) -> Result<Option<ScenarioScript>, String> {
+        let script_path = ScenarioScript::sidecar_path(scenario_path);
+        if !script_path.is_file() {
+            return Ok(None);
+        }
+        ScenarioScript::load_file(&script_path).map(Some)
+    }
+
+    /// Runs the script's top level and its `on_start` function
+    pub fn start(&mut self, view: ScriptView) -> Result<Vec<ScriptEffect>, String> {
+        self.state.borrow_mut().view = Some(view);
+        let mut result = self.run_ast();
+        if result.is_ok() && self.has_on_start {
+            result = self.call("on_start", ());
+        }
+        self.finish_call(result)
+    }
+
+    /// Picks the script of a saved game back up: its top level is run again to register its tick
+    /// hooks, and the rules the saved game had fired are marked fired. `on_start` isn't called,
+    /// and what the top level asks for is dropped, since what they did is already in the save.
+    pub fn resume(&mut self, tick: usize, fired: &[String], view: ScriptView) -> Result<(), String> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.fired.extend(fired.iter().cloned());
+            state.tick = tick;
+        }
+        self.run_top_level(view).map(|_| ())
+    }
+
+    /// The names passed to `fire_once` that have fired, sorted, for saving the game
+    pub fn fired_rules(&self) -> Vec<String> {
+        let mut fired: Vec<String> = self.state.borrow().fired.iter().cloned().collect();
+        fired.sort();
+        fired
+    }
+
+    fn run_top_level(&mut self, view: ScriptView) -> Result<Vec<ScriptEffect>, String> {
+        self.state.borrow_mut().view = Some(view);
+        let result = self.run_ast();
+        self.finish_call(result)
+    }
+
+    fn run_ast(&mut self) -> Result<(), String> {
+        self.begin_call("top level");
+        self.engine
+            .run_ast_with_scope(&mut self.scope, &self.ast)
+            .map_err(|err| format!("{}", err))
+    }
+
+    fn begin_call(&mut self, function_name: &str) {
+        let mut state = self.state.borrow_mut();
+        state.function_name = function_name.to_string();
+        state.facts.clear();
+    }
+
+    /// Whether anything in the script runs on the given tick, so that the caller can skip making
+    /// a view of the world for it when nothing does
+    pub fn runs_on_tick(&self, tick: usize) -> bool {
+        self.has_on_tick || self.state.borrow().hooks.iter().any(|hook| tick % hook.interval == 0)
+    }
+
+    /// Calls `on_tick` and the tick hooks that are due
+    pub fn tick(&mut self, tick: usize, view: ScriptView) -> Result<Vec<ScriptEffect>, String> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.view = Some(view);
+            state.tick = tick;
+        }
+        let due: Vec<String> = self.state
+            .borrow()
+            .hooks
+            .iter()
+            .filter(|hook| tick % hook.interval == 0)
+            .map(|hook| hook.function_name.clone())
+            .collect();
+
+        let mut result = Ok(());
+        if self.has_on_tick {
+            result = self.call("on_tick", (tick as i64,));
+        }
+        for function_name in &due {
+            if result.is_err() {
+                break;
+            }
+            result = self.call(function_name, ());
+        }
+        self.finish_call(result)
+    }
+
+    fn call<A: rhai::FuncArgs>(&mut self, function_name: &str, args: A) -> Result<(), String> {
+        self.begin_call(function_name);
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options::<rhai::Dynamic>(options, &mut self.scope, &self.ast, function_name, args)
+            .map(|_| ())
+            .map_err(|err| format!("{}: {}", function_name, err))
+    }
+
+    /// Hands back what the script asked for, even if it failed partway through
+    fn finish_call(&mut self, result: Result<(), String>) -> Result<Vec<ScriptEffect>, String> {
+        let mut state = self.state.borrow_mut();
+        state.view = None;
+        let effects = state.effects.drain(..).collect();
+        result.map(|_| effects)
+    }
+}
+
+/// Carries out what a script asked for
+pub fn apply_script_effects(world: &mut specs::World, empires: &dat::EmpiresDb, effects: Vec<ScriptEffect>) {
+    for effect in effects {
+        match effect {
+            ScriptEffect::Spawn { player_id, unit_id, position } => {
+                let civ_id = match world.read_resource::<Players>().player(player_id) {
+                    Some(player) => player.civ_id,
+                    None => {
+                        warn!("Script tried to spawn a unit for missing player {:?}", player_id);
+                        continue;
+                    }
+                };
+                let position = Vector3::new(position.x.into(), position.y.into(), 0.into());
+                world.write_resource::<UnitSpawner>()
+                    .queue_spawn(SpawnRequest::new(player_id, civ_id, unit_id, position));
+            }
+            ScriptEffect::AddToStockpile(player_id, resource_type, amount) => {
+                if let Some(player) = world.write_resource::<Players>().player_mut(player_id) {
+                    player.stockpile.add(resource_type, amount);
+                }
+            }
+            ScriptEffect::SetStockpile(player_id, resource_type, amount) => {
+                if let Some(player) = world.write_resource::<Players>().player_mut(player_id) {
+                    player.stockpile.set(resource_type, amount);
+                }
+            }
+            ScriptEffect::Message(text) => {
+                info!("Scenario message: {}", text);
+                world.write_resource::<Chat>().add_message(ChatMessage::new(0.into(), text));
+            }
+            ScriptEffect::SetTint(tint) => world.write_resource::<SceneTint>().set_scenario_tint(tint),
+            ScriptEffect::CompleteResearch(player_id, research_id) => {
+                complete_research(world, empires, player_id, research_id)
+            }
+        }
+    }
+}
+
+fn complete_research(world: &mut specs::World,
+                     empires: &dat::EmpiresDb,
+                     player_id: PlayerId,
+                     research_id: ResearchId) {
+    let mut players = world.write_resource::<Players>();
+    let civ_id = match players.player(player_id) {
+        Some(player) => player.civ_id,
+        None => {
+            warn!("Script tried to complete research for missing player {:?}", player_id);
+            return;
+        }
+    };
+    if *research_id as usize >= empires.all_research().len() {
+        warn!("Script tried to complete research {}, which isn't in the game data", *research_id);
+        return;
+    }
+    let mut technologies = world.write_resource::<Technologies>();
+    if technologies.has_researched(player_id, research_id) {
+        return;
+    }
+    technologies.complete(player_id, research_id);
+    apply_research_effects(player_id,
+                           empires.civilization(civ_id),
+                           research_effects(empires, empires.research(research_id)),
+                           &mut *world.write_resource::<Market>(),
+                           &mut *world.write_resource::<Diplomacy>(),
+                           &mut *players);
+}
+
+fn create_engine(state: &SharedStateRef) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.on_print(|text| info!("Scenario script: {}", text));
+    engine.on_debug(|text, _, position| debug!("Scenario script at {}: {}", position, text));
+
+    let shared = state.clone();
+    engine.register_fn("every", move |ticks: i64, function_name: ImmutableString| {
+        if ticks <= 0 {
+            return Err(script_error(format!("every: interval must be positive, not {}", ticks)));
+        }
+        shared.borrow_mut().hooks.push(TickHook {
+            interval: ticks as usize,
+            function_name: function_name.to_string(),
+        });
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("spawn_unit", move |player: i64, unit: i64, x: i64, y: i64| {
+        shared.borrow_mut().effects.push(ScriptEffect::Spawn {
+            player_id: (player as usize).into(),
+            unit_id: (unit as usize).into(),
+            position: Vector2::new(x as i32, y as i32),
+        });
+    });
+
+    let shared = state.clone();
+    engine.register_fn("add_stockpile",
+                       move |player: i64, resource: ImmutableString, amount: i64| {
+        let resource_type = try!(parse_resource_type(&resource));
+        let effect = ScriptEffect::AddToStockpile((player as usize).into(),
+                                                  resource_type,
+                                                  (amount as i32).into());
+        shared.borrow_mut().effects.push(effect);
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("set_stockpile",
+                       move |player: i64, resource: ImmutableString, amount: i64| {
+        let resource_type = try!(parse_resource_type(&resource));
+        let effect = ScriptEffect::SetStockpile((player as usize).into(),
+                                                resource_type,
+                                                (amount as i32).into());
+        shared.borrow_mut().effects.push(effect);
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("complete_research", move |player: i64, research: i64| {
+        if research < 0 {
+            return Err(script_error(format!("complete_research: there's no research {}", research)));
+        }
+        let effect = ScriptEffect::CompleteResearch((player as usize).into(), (research as usize).into());
+        shared.borrow_mut().effects.push(effect);
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("show_message", move |text: ImmutableString| {
+        shared.borrow_mut().effects.push(ScriptEffect::Message(text.to_string()));
+    });
+
+    let shared = state.clone();
+    engine.register_fn("set_tint", move |red: i64, green: i64, blue: i64| {
+        let channel = |value: i64| if value >= 0 && value <= 255 {
+            Ok(value as u8)
+        } else {
+            Err(script_error(format!("set_tint: {} isn't between 0 and 255", value)))
+        };
+        let color = Color::rgb(try!(channel(red)), try!(channel(green)), try!(channel(blue)));
+        shared.borrow_mut().effects.push(ScriptEffect::SetTint(Some(color)));
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("clear_tint", move || {
+        shared.borrow_mut().effects.push(ScriptEffect::SetTint(None));
+    });
+
+    let shared = state.clone();
+    engine.register_fn("fire_once", move |name: ImmutableString| {
+        let mut state = shared.borrow_mut();
+        let first_time = state.fired.insert(name.to_string());
+        if first_time && state.tracing {
+            let trace = RuleTrace {
+                tick: state.tick,
+                function_name: state.function_name.clone(),
+                rule: name.to_string(),
+                facts: state.facts.clone(),
+            };
+            state.traces.push(trace);
+        }
+        first_time
+    });
+
+    let shared = state.clone();
+    engine.register_fn("stockpile", move |player: i64, resource: ImmutableString| {
+        let query = WorldQuery::Stockpile((player as usize).into(), try!(parse_resource_type(&resource)));
+        evaluate(&shared, &query, || format!("stockpile {} {}", player, resource))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("query", move |text: ImmutableString| {
+        let query = try!(WorldQuery::parse(&text).map_err(script_error));
+        evaluate(&shared, &query, || text.trim().to_string())
+    });
+
+    engine
+}
+
+/// Answers a query for the script, noting it down for traces under the description
+fn evaluate<F>(state: &SharedStateRef, query: &WorldQuery, describe: F) -> Result<i64, Box<EvalAltResult>>
+    where F: FnOnce() -> String
+{
+    let mut state = state.borrow_mut();
+    let value = match state.view {
+        Some(ref mut view) => view.evaluate(query),
+        None => return Err(script_error("the world can't be queried outside of a script call".into())),
+    };
+    if state.tracing {
+        state.facts.push((describe(), value));
+    }
+    Ok(value)
+}
+
+fn parse_resource_type(name: &str) -> Result<ResourceType, Box<EvalAltResult>> {
+    match &name.to_lowercase()[..] {
+        "food" => Ok(ResourceType::Food),
+        "wood" => Ok(ResourceType::Wood),
+        "stone" => Ok(ResourceType::Stone),
+        "gold" => Ok(ResourceType::Gold),
+        _ => Err(script_error(format!("unknown resource \"{}\"", name))),
+    }
+}
+
+fn script_error(message: String) -> Box<EvalAltResult> {
+    message.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use dat::ResourceType;
+    use ecs::resource::{Player, Players};
+    use nalgebra::Vector2;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+    use super::{RuleTrace, ScenarioScript, ScriptEffect, ScriptView};
+    use types::Color;
+
+    fn view_with_food(food: i32) -> ScriptView {
+        let mut players = Players::new();
+        let mut player = Player::new("Player 1".into(), 1.into(), 1.into(), 1.into());
+        player.stockpile.set(ResourceType::Food, food.into());
+        players.add_player(player, true);
+        ScriptView::with_players(players)
+    }
+
+    #[test]
+    fn test_start_effects() {
+        let mut script = ScenarioScript::compile(r#"
+            spawn_unit(1, 83, 10, 12);
+            fn on_start() {
+                add_stockpile(1, "food", stockpile(1, "food"));
+                show_message("The scenario has started");
+            }
+        "#)
+            .unwrap();
+        let effects = script.start(view_with_food(150)).unwrap();
+        assert_eq!(vec![ScriptEffect::Spawn {
+                            player_id: 1.into(),
+                            unit_id: 83.into(),
+                            position: Vector2::new(10, 12),
+                        },
+                        ScriptEffect::AddToStockpile(1.into(), ResourceType::Food, 150.into()),
+                        ScriptEffect::Message("The scenario has started".into())],
+                   effects);
+    }
+
+    #[test]
+    fn test_tint() {
+        let mut script = ScenarioScript::compile(r#"
+            set_tint(250, 180, 140);
+            fn on_start() { clear_tint(); }
+        "#)
+            .unwrap();
+        assert_eq!(vec![ScriptEffect::SetTint(Some(Color::rgb(250, 180, 140))), ScriptEffect::SetTint(None)],
+                   script.start(ScriptView::new()).unwrap());
+
+        let mut script = ScenarioScript::compile("set_tint(256, 0, 0);").unwrap();
+        assert!(script.start(ScriptView::new()).is_err());
+    }
+
+    #[test]
+    fn test_research() {
+        let mut script = ScenarioScript::compile(r#"
+            fn on_start() {
+                if query("researched 1 101") == 0 { complete_research(1, 101); }
+            }
+        "#)
+            .unwrap();
+        assert_eq!(vec![ScriptEffect::CompleteResearch(1.into(), 101.into())],
+                   script.start(ScriptView::new()).unwrap());
+
+        let mut script = ScenarioScript::compile("complete_research(1, -1);").unwrap();
+        assert!(script.start(ScriptView::new()).is_err());
+    }
+
+    #[test]
+    fn test_tick_hooks() {
+        let mut script = ScenarioScript::compile(r#"
+            every(10, "reinforce");
+            fn reinforce() { set_stockpile(1, "gold", query("count owner 1")); }
+        "#)
+            .unwrap();
+        assert!(script.start(ScriptView::new()).unwrap().is_empty());
+        assert!(!script.runs_on_tick(5));
+        assert!(script.runs_on_tick(20));
+        assert_eq!(vec![ScriptEffect::SetStockpile(1.into(), ResourceType::Gold, 0.into())],
+                   script.tick(20, ScriptView::new()).unwrap());
+    }
+
+    #[test]
+    fn test_rule_traces() {
+        const SOURCE: &'static str = r#"
+            fn on_tick(tick) {
+                if stockpile(1, "food") >= 100 && fire_once("feast") { show_message("Feast"); }
+            }
+        "#;
+        let mut script = ScenarioScript::compile(SOURCE).unwrap();
+        script.start(view_with_food(150)).unwrap();
+        script.tick(1, view_with_food(150)).unwrap();
+        assert!(script.take_traces().is_empty());
+
+        let mut script = ScenarioScript::compile(SOURCE).unwrap();
+        script.set_tracing(true);
+        script.start(view_with_food(150)).unwrap();
+        script.tick(4, view_with_food(50)).unwrap();
+        script.tick(5, view_with_food(150)).unwrap();
+        script.tick(6, view_with_food(150)).unwrap();
+        assert_eq!(vec![RuleTrace {
+                            tick: 5,
+                            function_name: "on_tick".into(),
+                            rule: "feast".into(),
+                            facts: vec![("stockpile 1 food".into(), 150)],
+                        }],
+                   script.take_traces());
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = env::temp_dir().join("chariot_scenario_script_reload.rhai");
+        let write = |source: &str| File::create(&path).unwrap().write_all(source.as_bytes()).unwrap();
+
+        write("every(10, \"greet\"); fn greet() { if fire_once(\"hello\") { show_message(\"Hello\"); } }");
+        let mut script = ScenarioScript::load_file(&path).unwrap();
+        script.start(ScriptView::new()).unwrap();
+        assert_eq!(vec![ScriptEffect::Message("Hello".into())],
+                   script.tick(10, ScriptView::new()).unwrap());
+
+        write("every(5, \"greet\"); fn greet() { if fire_once(\"hello\") { show_message(\"Hi\"); } }");
+        assert!(script.reload(ScriptView::new()).unwrap().is_empty());
+        assert!(script.runs_on_tick(5));
+        assert!(script.tick(5, ScriptView::new()).unwrap().is_empty());
+
+        write("fn greet( {");
+        assert!(script.reload(ScriptView::new()).is_err());
+        assert!(script.runs_on_tick(5));
+        assert!(ScenarioScript::compile("").unwrap().reload(ScriptView::new()).is_err());
+    }
+
+    #[test]
+    fn test_resume() {
+        let source = r#"
+            spawn_unit(1, 83, 10, 12);
+            every(10, "check");
+            fn on_start() { show_message("Started"); }
+            fn check() {
+                if fire_once("first") { show_message("First"); }
+                if fire_once("second") { show_message("Second"); }
+            }
+        "#;
+        let mut script = ScenarioScript::compile(source).unwrap();
+        script.start(ScriptView::new()).unwrap();
+        script.tick(10, ScriptView::new()).unwrap();
+        assert_eq!(vec!["first".to_string(), "second".to_string()], script.fired_rules());
+
+        let mut resumed = ScenarioScript::compile(source).unwrap();
+        resumed.resume(10, &["first".to_string()], ScriptView::new()).unwrap();
+        assert!(resumed.runs_on_tick(20));
+        assert_eq!(vec![ScriptEffect::Message("Second".into())],
+                   resumed.tick(20, ScriptView::new()).unwrap());
+    }
+
+    #[test]
+    fn test_sandbox_limits() {
+        let mut script = ScenarioScript::compile("fn on_tick(tick) { loop { } }").unwrap();
+        script.start(ScriptView::new()).unwrap();
+        assert!(script.runs_on_tick(1));
+        assert!(script.tick(1, ScriptView::new()).is_err());
+
+        assert!(ScenarioScript::compile("eval(\"1 + 1\")").is_err());
+        let mut script = ScenarioScript::compile("import \"other\" as other;").unwrap();
+        assert!(script.start(ScriptView::new()).is_err());
+        let mut script = ScenarioScript::compile("add_stockpile(1, \"silver\", 5);").unwrap();
+        assert!(script.start(ScriptView::new()).is_err());
+    }
+}
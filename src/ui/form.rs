@@ -0,0 +1,807 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{ListState, TextField, UiEvent, Widget, WidgetKind};
+use super::widget::dropdown_list_rect;
+use ecs::resource::{RenderCommands, UiLayout};
+use media::{Key, KeyState, Media, MouseButton};
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use types::{Color, Rect};
+use util::glyphs;
+
+/// How many screen pixels across each of the font's pixels are drawn, at most; text in rows too
+/// short for it is drawn smaller
+const TEXT_SCALE: i32 = 2;
+pub const GLYPH_WIDTH: i32 = glyphs::GLYPH_WIDTH * TEXT_SCALE;
+pub const GLYPH_SPACING: i32 = TEXT_SCALE;
+const PADDING: i32 = 3;
+const CHECKBOX_SIZE: i32 = 10;
+const SLIDER_KNOB_WIDTH: i32 = 6;
+const SCROLL_BAR_WIDTH: i32 = 4;
+
+/// The keys that work the widgets
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NavKey {
+    Tab,
+    /// Shift+Tab
+    BackTab,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Backspace,
+    Delete,
+    Return,
+    Space,
+    Escape,
+}
+
+const NAV_KEYS: [(Key, NavKey); 14] = [(Key::Tab, NavKey::Tab),
+                                       (Key::Up, NavKey::Up),
+                                       (Key::Down, NavKey::Down),
+                                       (Key::Left, NavKey::Left),
+                                       (Key::Right, NavKey::Right),
+                                       (Key::PageUp, NavKey::PageUp),
+                                       (Key::PageDown, NavKey::PageDown),
+                                       (Key::Home, NavKey::Home),
+                                       (Key::End, NavKey::End),
+                                       (Key::Backspace, NavKey::Backspace),
+                                       (Key::Delete, NavKey::Delete),
+                                       (Key::Return, NavKey::Return),
+                                       (Key::Space, NavKey::Space),
+                                       (Key::Escape, NavKey::Escape)];
+
+/// A frame's worth of input for a form, in screen coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiInput {
+    pub pointer: Vector2<i32>,
+    /// The state of the button that clicks widgets
+    pub select: KeyState,
+    /// The keys pressed this frame
+    pub keys: Vec<NavKey>,
+    /// What was typed this frame
+    pub text: String,
+    /// What's being composed with an input method, if anything
+    pub composition: String,
+}
+
+impl UiInput {
+    pub fn new() -> UiInput {
+        UiInput {
+            pointer: Vector2::new(0, 0),
+            select: KeyState::Up,
+            keys: Vec::new(),
+            text: String::new(),
+            composition: String::new(),
+        }
+    }
+
+    pub fn from_media(media: &Media) -> UiInput {
+        let key_states = media.key_states();
+        let shift = key_states.key_state(Key::Shift).is_down();
+        let keys = NAV_KEYS.iter()
+            .filter(|&&(key, _)| key_states.key_state(key) == KeyState::TransitionDown)
+            .map(|&(_, nav_key)| if nav_key == NavKey::Tab && shift {
+                NavKey::BackTab
+            } else {
+                nav_key
+            })
+            .collect();
+        UiInput {
+            pointer: media.mouse_position(),
+            select: media.mouse_button_states().key_state(MouseButton::Left),
+            keys: keys,
+            text: media.text_input().to_string(),
+            composition: media.text_composition().to_string(),
+        }
+    }
+
+    pub fn pressed(&self, key: NavKey) -> bool {
+        self.keys.contains(&key)
+    }
+}
+
+/// A set of widgets kept from frame to frame, which tracks the focus between them. Tab and
+/// Shift+Tab move the focus, and clicking a widget focuses it as well as using it. The keyboard
+/// goes to the focused widget: Return and Space press buttons, tick checkboxes, and open and close
+/// dropdowns, the arrow keys move sliders and list and dropdown selections (along with Page Up,
+/// Page Down, Home and End), and text inputs take what's typed. Escape or Return on a text input
+/// hands it back, and Escape closes an open dropdown.
+pub struct Form {
+    widgets: Vec<Widget>,
+    focus: Option<usize>,
+    /// The slider being dragged
+    dragging: Option<usize>,
+}
+
+impl Form {
+    pub fn new() -> Form {
+        Form {
+            widgets: Vec::new(),
+            focus: None,
+            dragging: None,
+        }
+    }
+
+    pub fn add(&mut self, widget: Widget) {
+        self.widgets.push(widget);
+    }
+
+    pub fn widgets<'a>(&'a self) -> &'a [Widget] {
+        &self.widgets
+    }
+
+    pub fn widget<'a>(&'a self, id: &str) -> Option<&'a Widget> {
+        self.widgets.iter().find(|widget| widget.id == id)
+    }
+
+    pub fn widget_mut<'a>(&'a mut self, id: &str) -> Option<&'a mut Widget> {
+        self.widgets.iter_mut().find(|widget| widget.id == id)
+    }
+
+    /// The items of a list or a dropdown
+    pub fn list<'a>(&'a self, id: &str) -> Option<&'a ListState> {
+        match self.widget(id) {
+            Some(&Widget { kind: WidgetKind::List(ref list), .. }) |
+            Some(&Widget { kind: WidgetKind::Dropdown { ref list, .. }, .. }) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn list_mut<'a>(&'a mut self, id: &str) -> Option<&'a mut ListState> {
+        match self.widget_mut(id) {
+            Some(&mut Widget { kind: WidgetKind::List(ref mut list), .. }) |
+            Some(&mut Widget { kind: WidgetKind::Dropdown { ref mut list, .. }, .. }) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Picks an item of a list or a dropdown, scrolling it so that the item shows
+    pub fn select(&mut self, id: &str, index: usize) {
+        if let Some(widget) = self.widget_mut(id) {
+            match widget.kind {
+                WidgetKind::List(ref mut list) => list.select(index, &widget.rect),
+                WidgetKind::Dropdown { ref mut list, .. } => {
+                    let list_rect = dropdown_list_rect(&widget.rect, list);
+                    list.select(index, &list_rect);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn text_field_mut<'a>(&'a mut self, id: &str) -> Option<&'a mut TextField> {
+        match self.widget_mut(id) {
+            Some(&mut Widget { kind: WidgetKind::TextInput(ref mut field), .. }) => Some(field),
+            _ => None,
+        }
+    }
+
+    pub fn focused<'a>(&'a self) -> Option<&'a Widget> {
+        self.focus.map(|index| &self.widgets[index])
+    }
+
+    pub fn set_focus(&mut self, id: &str) {
+        self.focus = self.widgets.iter().position(|widget| widget.id == id && widget.enabled);
+    }
+
+    pub fn clear_focus(&mut self) {
+        self.focus = None;
+    }
+
+    /// Whether the focused widget is taking the keyboard
+    pub fn has_keyboard(&self) -> bool {
+        self.focused().map_or(false, |widget| widget.takes_keyboard())
+    }
+
+    /// The enabled widget at the given screen position
+    pub fn widget_at<'a>(&'a self, position: &Vector2<i32>) -> Option<&'a Widget> {
+        self.widget_index_at(position).map(|index| &self.widgets[index])
+    }
+
+    fn widget_index_at(&self, position: &Vector2<i32>) -> Option<usize> {
+        // An open dropdown's list is drawn over the widgets under it, so it's looked at first
+        let at_position = |widget: &Widget| widget.enabled && widget.contains(position.x, position.y);
+        self.widgets
+            .iter()
+            .position(|widget| at_position(widget) && widget.open_list_rect().is_some())
+            .or_else(|| self.widgets.iter().position(|widget| at_position(widget)))
+    }
+
+    /// Closes the open dropdowns other than the given one
+    fn close_dropdowns(&mut self, except: Option<usize>) {
+        for (index, widget) in self.widgets.iter_mut().enumerate() {
+            if let WidgetKind::Dropdown { ref mut open, .. } = widget.kind {
+                if Some(index) != except {
+                    *open = false;
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self, input: &UiInput) -> Vec<UiEvent> {
+        let mut events = Vec::new();
+        self.update_pointer(input, &mut events);
+        if input.pressed(NavKey::Tab) {
+            self.move_focus(1);
+        } else if input.pressed(NavKey::BackTab) {
+            self.move_focus(-1);
+        } else if let Some(index) = self.focus {
+            update_focused(&mut self.widgets[index], input, &mut events);
+        }
+        events
+    }
+
+    fn update_pointer(&mut self, input: &UiInput, events: &mut Vec<UiEvent>) {
+        if !input.select.is_down() {
+            self.dragging = None;
+            return;
+        }
+        if input.select != KeyState::TransitionDown {
+            if let Some(index) = self.dragging {
+                drag_slider(&mut self.widgets[index], input.pointer.x, events);
+            }
+            return;
+        }
+
+        self.focus = self.widget_index_at(&input.pointer);
+        let focus = self.focus;
+        self.close_dropdowns(focus);
+        let index = match self.focus {
+            Some(index) => index,
+            None => return,
+        };
+        let widget = &mut self.widgets[index];
+        let id = widget.id;
+        match widget.kind {
+            WidgetKind::Button => events.push(UiEvent::Pressed(id)),
+            WidgetKind::Checkbox { ref mut checked } => {
+                *checked = !*checked;
+                events.push(UiEvent::Toggled(id, *checked));
+            }
+            WidgetKind::Slider { .. } => {
+                self.dragging = Some(index);
+                drag_slider(widget, input.pointer.x, events);
+            }
+            WidgetKind::List(ref mut list) => {
+                if let Some(item) = list.item_at(&widget.rect, input.pointer.x, input.pointer.y) {
+                    if list.selected() != Some(item) {
+                        list.select(item, &widget.rect);
+                        events.push(UiEvent::Selected(id, item));
+                    }
+                    events.push(UiEvent::Activated(id, item));
+                }
+            }
+            WidgetKind::Dropdown { ref mut list, ref mut open } => {
+                // Clicking an item picks it, and clicking anywhere on the dropdown closes it
+                if *open {
+                    let list_rect = dropdown_list_rect(&widget.rect, list);
+                    if let Some(item) = list.item_at(&list_rect, input.pointer.x, input.pointer.y) {
+                        if list.selected() != Some(item) {
+                            list.select(item, &list_rect);
+                            events.push(UiEvent::Selected(id, item));
+                        }
+                    }
+                }
+                *open = !*open;
+            }
+            WidgetKind::TextInput(_) => {}
+        }
+    }
+
+    /// Moves the focus forwards or backwards to the next enabled widget, wrapping around
+    fn move_focus(&mut self, direction: i32) {
+        let count = self.widgets.len() as i32;
+        let start = match self.focus {
+            Some(index) => index as i32,
+            None if direction > 0 => -1,
+            None => count,
+        };
+        for step in 1..count + 1 {
+            let index = ((start + direction * step) % count + count) % count;
+            if self.widgets[index as usize].enabled {
+                self.focus = Some(index as usize);
+                self.close_dropdowns(Some(index as usize));
+                return;
+            }
+        }
+    }
+
+    /// Draws the widgets, with screen positions moved by `origin` into where the render
+    /// commands are drawn
+    pub fn render(&self,
+                  render_commands: &mut RenderCommands,
+                  layout: &UiLayout,
+                  layer: u16,
+                  origin: &Vector2<i32>) {
+        for (index, widget) in self.widgets.iter().enumerate() {
+            let mut rect = widget.rect;
+            rect.translate(origin.x, origin.y);
+            let focused = self.focus == Some(index);
+            render_widget(render_commands, layout, layer, widget, rect, focused);
+            if focused {
+                render_commands.push(RenderCommand::new_rect(layer, 4, focus_color(), rect));
+            }
+        }
+    }
+}
+
+fn update_focused(widget: &mut Widget, input: &UiInput, events: &mut Vec<UiEvent>) {
+    let id = widget.id;
+    if let WidgetKind::Dropdown { ref mut open, .. } = widget.kind {
+        if *open && input.pressed(NavKey::Escape) {
+            *open = false;
+            return;
+        }
+    }
+    if input.pressed(NavKey::Escape) {
+        events.push(UiEvent::Cancelled(id));
+        return;
+    }
+    let activate = input.pressed(NavKey::Return) || input.pressed(NavKey::Space);
+    match widget.kind {
+        WidgetKind::Button => {
+            if activate {
+                events.push(UiEvent::Pressed(id));
+            }
+        }
+        WidgetKind::Checkbox { ref mut checked } => {
+            if activate {
+                *checked = !*checked;
+                events.push(UiEvent::Toggled(id, *checked));
+            }
+        }
+        WidgetKind::Slider { ref mut value, min, max, step } => {
+            let old_value = *value;
+            for key in &input.keys {
+                *value = match *key {
+                    NavKey::Left | NavKey::Down => *value - step,
+                    NavKey::Right | NavKey::Up => *value + step,
+                    NavKey::Home => min,
+                    NavKey::End => max,
+                    _ => *value,
+                }
+                .max(min)
+                .min(max);
+            }
+            if *value != old_value {
+                events.push(UiEvent::Changed(id, *value));
+            }
+        }
+        WidgetKind::List(ref mut list) => {
+            move_list_selection(id, list, &widget.rect, input, events);
+            if let Some(selected) = list.selected() {
+                if input.pressed(NavKey::Return) {
+                    events.push(UiEvent::Activated(id, selected));
+                }
+            }
+        }
+        WidgetKind::Dropdown { ref mut list, ref mut open } => {
+            let list_rect = dropdown_list_rect(&widget.rect, list);
+            move_list_selection(id, list, &list_rect, input, events);
+            if activate {
+                *open = !*open;
+            }
+        }
+        WidgetKind::TextInput(ref mut field) => {
+            for key in &input.keys {
+                match *key {
+                    NavKey::Left => field.move_left(),
+                    NavKey::Right => field.move_right(),
+                    NavKey::Home => field.move_home(),
+                    NavKey::End => field.move_end(),
+                    NavKey::Backspace => field.backspace(),
+                    NavKey::Delete => field.delete(),
+                    _ => {}
+                }
+            }
+            // Space comes through as typed text, so only Return submits
+            if input.pressed(NavKey::Return) {
+                events.push(UiEvent::Submitted(id, field.take_text()));
+            } else {
+                field.insert(&input.text);
+                field.set_composition(&input.composition);
+            }
+        }
+    }
+}
+
+/// Moves the selection by the arrow keys, Page Up, Page Down, Home and End, scrolling the list
+/// drawn in the rect to keep it showing
+fn move_list_selection(id: &'static str,
+                       list: &mut ListState,
+                       rect: &Rect,
+                       input: &UiInput,
+                       events: &mut Vec<UiEvent>) {
+    let old_selection = list.selected();
+    let page = list.visible_rows(rect) as i32;
+    for key in &input.keys {
+        match *key {
+            NavKey::Up => list.move_selection(-1, rect),
+            NavKey::Down => list.move_selection(1, rect),
+            NavKey::PageUp => list.move_selection(-page, rect),
+            NavKey::PageDown => list.move_selection(page, rect),
+            NavKey::Home => list.select(0, rect),
+            NavKey::End => list.select(usize::max_value(), rect),
+            _ => {}
+        }
+    }
+    if let Some(selected) = list.selected() {
+        if Some(selected) != old_selection {
+            events.push(UiEvent::Selected(id, selected));
+        }
+    }
+}
+
+fn drag_slider(widget: &mut Widget, x: i32, events: &mut Vec<UiEvent>) {
+    let new_value = match widget.slider_value_at(x) {
+        Some(new_value) => new_value,
+        None => return,
+    };
+    if let WidgetKind::Slider { ref mut value, .. } = widget.kind {
+        if *value != new_value {
+            *value = new_value;
+            events.push(UiEvent::Changed(widget.id, new_value));
+        }
+    }
+}
+
+fn render_widget(render_commands: &mut RenderCommands,
+                 layout: &UiLayout,
+                 layer: u16,
+                 widget: &Widget,
+                 rect: Rect,
+                 focused: bool) {
+    let text_color = if widget.enabled {
+        Color::rgb(255, 255, 255)
+    } else {
+        Color::rgb(120, 120, 120)
+    };
+    let glyph_height = (rect.h - PADDING * 2).max(1);
+    match widget.kind {
+        WidgetKind::Button => {
+            render_commands.push(layout.button_command(layer, 0, rect, focused, Color::rgb(60, 60, 60)));
+            let width = text_width(widget.label.chars().count());
+            render_text(render_commands,
+                        layer,
+                        text_color,
+                        &widget.label,
+                        &Vector2::new(rect.x + (rect.w - width) / 2, rect.y + PADDING),
+                        glyph_height);
+        }
+        WidgetKind::Checkbox { checked } => {
+            let size = CHECKBOX_SIZE.min(rect.h);
+            let check = Rect::of(rect.x, rect.y + (rect.h - size) / 2, size, size);
+            render_commands.push(RenderCommand::new_filled_rect(layer, 0, Color::rgb(20, 20, 20), check));
+            render_commands.push(RenderCommand::new_rect(layer, 1, Color::rgb(200, 200, 200), check));
+            if checked {
+                let tick = Rect::of(check.x + 2, check.y + 2, size - 4, size - 4);
+                render_commands.push(RenderCommand::new_filled_rect(layer, 2, text_color, tick));
+            }
+            render_text(render_commands,
+                        layer,
+                        text_color,
+                        &widget.label,
+                        &Vector2::new(rect.x + size + PADDING * 2, rect.y + PADDING),
+                        glyph_height);
+        }
+        WidgetKind::Slider { value, min, max, .. } => {
+            let middle = rect.y + rect.h / 2;
+            render_commands.push(RenderCommand::new_line(layer,
+                                                         0,
+                                                         Color::rgb(200, 200, 200),
+                                                         Vector2::new(rect.x, middle),
+                                                         Vector2::new(rect.x + rect.w, middle)));
+            let along = if max > min {
+                (rect.w - SLIDER_KNOB_WIDTH) * (value - min) / (max - min)
+            } else {
+                0
+            };
+            let knob = Rect::of(rect.x + along, rect.y, SLIDER_KNOB_WIDTH, rect.h);
+            render_commands.push(layout.button_command(layer, 1, knob, focused, Color::rgb(160, 160, 160)));
+        }
+        WidgetKind::List(ref list) => render_list(render_commands, layout, layer, list, rect, text_color),
+        WidgetKind::Dropdown { ref list, open } => {
+            render_commands.push(RenderCommand::new_filled_rect(layer, 0, Color::rgb(20, 20, 20), rect));
+            render_commands.push(RenderCommand::new_rect(layer, 1, Color::rgb(200, 200, 200), rect));
+            let glyph_advance = GLYPH_WIDTH + GLYPH_SPACING;
+            let arrow_x = rect.x + rect.w - PADDING - GLYPH_WIDTH;
+            let room = ((arrow_x - rect.x - PADDING) / glyph_advance).max(0) as usize;
+            let text: String = list.selected()
+                .map_or("", |selected| &list.items()[selected].text[..])
+                .chars()
+                .take(room)
+                .collect();
+            render_text(render_commands,
+                        layer,
+                        text_color,
+                        &text,
+                        &Vector2::new(rect.x + PADDING, rect.y + PADDING),
+                        glyph_height);
+            render_text(render_commands,
+                        layer,
+                        text_color,
+                        "v",
+                        &Vector2::new(arrow_x, rect.y + PADDING),
+                        glyph_height);
+            if open {
+                // A layer up, so the list covers the widgets under it
+                let list_rect = dropdown_list_rect(&rect, list);
+                render_list(render_commands, layout, layer + 1, list, list_rect, text_color);
+            }
+        }
+        WidgetKind::TextInput(ref field) => {
+            render_commands.push(RenderCommand::new_filled_rect(layer, 0, Color::rgb(20, 20, 20), rect));
+            render_commands.push(RenderCommand::new_rect(layer, 1, Color::rgb(200, 200, 200), rect));
+            let top_left = Vector2::new(rect.x + PADDING, rect.y + PADDING);
+            if field.text().is_empty() && field.composition().is_empty() {
+                // The label says what goes in the input until something's typed
+                render_text(render_commands,
+                            layer,
+                            Color::rgb(120, 120, 120),
+                            &widget.label,
+                            &top_left,
+                            glyph_height);
+            }
+            render_text(render_commands, layer, text_color, field.text(), &top_left, glyph_height);
+            let cursor_x = top_left.x + text_width(field.cursor());
+            // What's being composed shows at the cursor as outlines, since it isn't typed yet
+            for index in 0..field.composition().chars().count() {
+                let glyph_x = cursor_x + index as i32 * (GLYPH_WIDTH + GLYPH_SPACING);
+                render_commands.push(RenderCommand::new_rect(layer,
+                                                             2,
+                                                             text_color,
+                                                             Rect::of(glyph_x,
+                                                                      top_left.y,
+                                                                      GLYPH_WIDTH,
+                                                                      glyph_height)));
+            }
+            if focused {
+                let x = cursor_x - GLYPH_SPACING / 2 - 1;
+                render_commands.push(RenderCommand::new_line(layer,
+                                                             3,
+                                                             focus_color(),
+                                                             Vector2::new(x, top_left.y),
+                                                             Vector2::new(x, top_left.y + glyph_height)));
+            }
+        }
+    }
+}
+
+/// Draws the rows of a list that show, each led by a block in its item's color, along with a
+/// scroll bar if not all of them fit
+fn render_list(render_commands: &mut RenderCommands,
+               layout: &UiLayout,
+               layer: u16,
+               list: &ListState,
+               rect: Rect,
+               text_color: Color) {
+    render_commands.push(layout.panel_command(layer, 0, rect, Color::rgb(20, 20, 20)));
+    render_commands.push(RenderCommand::new_rect(layer, 1, Color::rgb(200, 200, 200), rect));
+    let rows = list.visible_rows(&rect);
+    let glyph_height = (list.row_height() - PADDING).max(1);
+    for (row, item) in list.items().iter().enumerate().skip(list.scroll()).take(rows) {
+        let row_y = rect.y + (row - list.scroll()) as i32 * list.row_height();
+        if list.selected() == Some(row) {
+            render_commands.push(RenderCommand::new_filled_rect(layer,
+                                                                1,
+                                                                Color::rgb(70, 70, 110),
+                                                                Rect::of(rect.x + 1,
+                                                                         row_y,
+                                                                         rect.w - 2,
+                                                                         list.row_height())));
+        }
+        let block_y = row_y + PADDING / 2;
+        render_commands.push(RenderCommand::new_filled_rect(layer,
+                                                            2,
+                                                            item.color,
+                                                            Rect::of(rect.x + PADDING,
+                                                                     block_y,
+                                                                     GLYPH_WIDTH + GLYPH_SPACING + 1,
+                                                                     glyph_height)));
+        let text_x = rect.x + PADDING * 2 + GLYPH_WIDTH + GLYPH_SPACING + 1;
+        let room = ((rect.x + rect.w - SCROLL_BAR_WIDTH - text_x) / (GLYPH_WIDTH + GLYPH_SPACING)).max(0);
+        let text: String = item.text.chars().take(room as usize).collect();
+        render_text(render_commands,
+                    layer,
+                    text_color,
+                    &text,
+                    &Vector2::new(text_x, block_y),
+                    glyph_height);
+    }
+
+    let count = list.items().len();
+    if count > rows {
+        let height = (rect.h * rows as i32 / count as i32).max(2);
+        let y = rect.y + (rect.h - height) * list.scroll() as i32 / (count - rows) as i32;
+        render_commands.push(RenderCommand::new_filled_rect(layer,
+                                                            2,
+                                                            Color::rgb(160, 160, 160),
+                                                            Rect::of(rect.x + rect.w - SCROLL_BAR_WIDTH,
+                                                                     y,
+                                                                     SCROLL_BAR_WIDTH,
+                                                                     height)));
+    }
+}
+
+/// How wide the given number of characters are drawn
+pub fn text_width(chars: usize) -> i32 {
+    chars as i32 * (GLYPH_WIDTH + GLYPH_SPACING)
+}
+
+/// Draws text in the pixel font from `util::glyphs`, as big as fits in the given height and
+/// centered in it, with the characters the same distance apart whatever the size
+pub fn render_text(render_commands: &mut RenderCommands,
+                   layer: u16,
+                   color: Color,
+                   text: &str,
+                   top_left: &Vector2<i32>,
+                   glyph_height: i32) {
+    let scale = (glyph_height / glyphs::GLYPH_HEIGHT).max(1).min(TEXT_SCALE);
+    let y = top_left.y + (glyph_height - glyphs::GLYPH_HEIGHT * scale) / 2;
+    glyphs::render_scaled_text(render_commands,
+                               layer,
+                               2,
+                               color,
+                               text,
+                               Vector2::new(top_left.x, y),
+                               GLYPH_WIDTH + GLYPH_SPACING,
+                               scale);
+}
+
+fn focus_color() -> Color {
+    Color::rgb(230, 200, 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use media::KeyState;
+    use nalgebra::Vector2;
+    use types::{Color, Rect};
+    use ui::{ListItem, UiEvent, Widget};
+
+    fn keys(keys: &[NavKey]) -> UiInput {
+        let mut input = UiInput::new();
+        input.keys = keys.to_vec();
+        input
+    }
+
+    fn click(x: i32, y: i32) -> UiInput {
+        let mut input = UiInput::new();
+        input.pointer = Vector2::new(x, y);
+        input.select = KeyState::TransitionDown;
+        input
+    }
+
+    fn form() -> Form {
+        let mut form = Form::new();
+        form.add(Widget::button("ok", Rect::of(0, 0, 50, 10), "OK"));
+        let mut disabled = Widget::checkbox("fog", Rect::of(0, 20, 50, 10), "Fog", false);
+        disabled.enabled = false;
+        form.add(disabled);
+        form.add(Widget::checkbox("sound", Rect::of(0, 40, 50, 10), "Sound", true));
+        form.add(Widget::slider("speed", Rect::of(0, 60, 100, 10), 1, 5, 1, 3));
+        form
+    }
+
+    #[test]
+    fn test_tab_skips_disabled_widgets_and_wraps() {
+        let mut form = form();
+        let mut focus_order = Vec::new();
+        for _ in 0..4 {
+            form.update(&keys(&[NavKey::Tab]));
+            focus_order.push(form.focused().unwrap().id);
+        }
+        assert_eq!(vec!["ok", "sound", "speed", "ok"], focus_order);
+        form.update(&keys(&[NavKey::BackTab]));
+        assert_eq!("speed", form.focused().unwrap().id);
+    }
+
+    #[test]
+    fn test_keyboard_works_the_focused_widget() {
+        let mut form = form();
+        form.set_focus("sound");
+        assert_eq!(vec![UiEvent::Toggled("sound", false)], form.update(&keys(&[NavKey::Space])));
+
+        form.set_focus("speed");
+        assert_eq!(vec![UiEvent::Changed("speed", 5)],
+                   form.update(&keys(&[NavKey::Right, NavKey::Right, NavKey::Right])));
+        assert_eq!(vec![UiEvent::Changed("speed", 1)], form.update(&keys(&[NavKey::Home])));
+        assert!(form.update(&keys(&[NavKey::Left])).is_empty());
+    }
+
+    #[test]
+    fn test_clicks_focus_and_use_widgets() {
+        let mut form = form();
+        assert_eq!(vec![UiEvent::Pressed("ok")], form.update(&click(10, 5)));
+        assert_eq!("ok", form.focused().unwrap().id);
+        assert!(form.update(&click(10, 25)).is_empty());
+        assert!(form.focused().is_none());
+        assert_eq!(vec![UiEvent::Changed("speed", 5)], form.update(&click(99, 65)));
+    }
+
+    #[test]
+    fn test_lists_and_text_inputs() {
+        let mut form = Form::new();
+        form.add(Widget::list("maps", Rect::of(0, 0, 100, 20), 10));
+        form.add(Widget::text_input("name", Rect::of(0, 30, 100, 10), 10));
+        let items = (0..5)
+            .map(|index| {
+                ListItem {
+                    text: format!("map {}", index),
+                    color: Color::rgb(0, 0, 0),
+                }
+            })
+            .collect();
+        form.list_mut("maps").unwrap().set_items(items);
+
+        assert_eq!(vec![UiEvent::Selected("maps", 1), UiEvent::Activated("maps", 1)],
+                   form.update(&click(5, 15)));
+        assert_eq!(vec![UiEvent::Selected("maps", 4), UiEvent::Activated("maps", 4)],
+                   form.update(&keys(&[NavKey::End, NavKey::Return])));
+        assert_eq!(3, form.list_mut("maps").unwrap().scroll());
+
+        form.update(&keys(&[NavKey::Tab]));
+        assert!(form.has_keyboard());
+        let mut typing = keys(&[NavKey::Space]);
+        typing.text = "a b".to_string();
+        assert!(form.update(&typing).is_empty());
+        assert_eq!(vec![UiEvent::Submitted("name", "a b".to_string())],
+                   form.update(&keys(&[NavKey::Return])));
+    }
+
+    #[test]
+    fn test_dropdowns() {
+        let mut form = Form::new();
+        form.add(Widget::dropdown("civ", Rect::of(0, 0, 100, 10), 10));
+        form.add(Widget::button("ok", Rect::of(0, 20, 50, 10), "OK"));
+        let items = ["Egyptian", "Greek", "Babylonian"]
+            .iter()
+            .map(|name| {
+                ListItem {
+                    text: name.to_string(),
+                    color: Color::rgb(0, 0, 0),
+                }
+            })
+            .collect();
+        form.list_mut("civ").unwrap().set_items(items);
+
+        // The open list covers the button, so clicking there picks an item
+        assert!(form.update(&click(5, 5)).is_empty());
+        assert!(form.widget("civ").unwrap().open_list_rect().is_some());
+        assert_eq!(vec![UiEvent::Selected("civ", 1)], form.update(&click(5, 25)));
+        assert!(form.widget("civ").unwrap().open_list_rect().is_none());
+        assert_eq!(Some(1), form.list("civ").unwrap().selected());
+
+        assert_eq!(vec![UiEvent::Selected("civ", 2)], form.update(&keys(&[NavKey::Down])));
+        form.update(&keys(&[NavKey::Space]));
+        assert!(form.widget("civ").unwrap().open_list_rect().is_some());
+        assert!(form.update(&keys(&[NavKey::Escape])).is_empty());
+        assert!(form.widget("civ").unwrap().open_list_rect().is_none());
+        assert_eq!(vec![UiEvent::Pressed("ok")], form.update(&click(5, 25)));
+    }
+}
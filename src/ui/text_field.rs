@@ -0,0 +1,187 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// What's typed into a text box, with a cursor. Everything is counted in characters rather than
+/// bytes, so that editing never splits one, and text still being composed with an input method
+/// is kept apart from the text until it comes through as typed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextField {
+    text: String,
+    /// Characters before the cursor
+    cursor: usize,
+    composition: String,
+    max_chars: usize,
+}
+
+impl TextField {
+    pub fn new(max_chars: usize) -> TextField {
+        TextField {
+            text: String::new(),
+            cursor: 0,
+            composition: String::new(),
+            max_chars: max_chars,
+        }
+    }
+
+    pub fn text<'a>(&'a self) -> &'a str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn composition<'a>(&'a self) -> &'a str {
+        &self.composition
+    }
+
+    /// Replaces the text, with the cursor at its end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().take(self.max_chars).collect();
+        self.cursor = self.text.chars().count();
+        self.composition.clear();
+    }
+
+    /// Empties the field, returning what was in it
+    pub fn take_text(&mut self) -> String {
+        let text = self.text.clone();
+        self.set_text("");
+        text
+    }
+
+    /// Types text in at the cursor, dropping what doesn't fit. Control characters are left out,
+    /// since the keys that make them (Return, Tab, and so on) are handled as keys.
+    pub fn insert(&mut self, typed: &str) {
+        let room = self.max_chars.saturating_sub(self.text.chars().count());
+        let typed: Vec<char> = typed.chars().filter(|character| !character.is_control()).take(room).collect();
+        let at = self.byte_index(self.cursor);
+        let tail = self.text.split_off(at);
+        self.text.extend(typed.iter());
+        self.text.push_str(&tail);
+        self.cursor += typed.len();
+        self.composition.clear();
+    }
+
+    /// Sets the text that's being composed, which is shown at the cursor until it's typed
+    pub fn set_composition(&mut self, composition: &str) {
+        self.composition = composition.to_string();
+    }
+
+    /// Deletes the character before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.remove_at_cursor();
+        }
+    }
+
+    /// Deletes the character after the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            self.remove_at_cursor();
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    fn remove_at_cursor(&mut self) {
+        let at = self.byte_index(self.cursor);
+        self.text.remove(at);
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text.char_indices().nth(char_index).map_or(self.text.len(), |(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextField;
+
+    #[test]
+    fn test_editing_at_the_cursor() {
+        let mut field = TextField::new(20);
+        field.insert("helo");
+        field.move_left();
+        field.insert("l");
+        assert_eq!("hello", field.text());
+        assert_eq!(4, field.cursor());
+
+        field.move_home();
+        field.delete();
+        field.insert("j");
+        field.move_end();
+        field.backspace();
+        assert_eq!("jell", field.text());
+        assert_eq!(4, field.cursor());
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        let mut field = TextField::new(20);
+        field.insert("日本語");
+        field.move_left();
+        field.backspace();
+        assert_eq!("日語", field.text());
+        field.move_right();
+        field.move_right();
+        field.insert("é");
+        assert_eq!("日語é", field.text());
+        assert_eq!(3, field.cursor());
+    }
+
+    #[test]
+    fn test_composition_isnt_typed() {
+        let mut field = TextField::new(20);
+        field.set_composition("にほ");
+        assert_eq!("", field.text());
+        assert_eq!("にほ", field.composition());
+
+        field.insert("日本");
+        assert_eq!("日本", field.text());
+        assert_eq!("", field.composition());
+    }
+
+    #[test]
+    fn test_length_limit_and_control_characters() {
+        let mut field = TextField::new(4);
+        field.insert("ab\tc\rdef");
+        assert_eq!("abcd", field.text());
+        field.insert("x");
+        assert_eq!("abcd", field.text());
+        assert_eq!("abcd", field.take_text());
+        assert_eq!(("", 0), (field.text(), field.cursor()));
+    }
+}
@@ -0,0 +1,306 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::TextField;
+use types::{Color, Rect};
+
+/// How many of a dropdown's items show at once while it's open
+const DROPDOWN_ROWS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListItem {
+    pub text: String,
+    /// Marks the item, in a block ahead of its text
+    pub color: Color,
+}
+
+/// The items of a list, which one is picked, and how far it's scrolled
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListState {
+    items: Vec<ListItem>,
+    selected: Option<usize>,
+    /// The first item showing
+    scroll: usize,
+    row_height: i32,
+}
+
+impl ListState {
+    pub fn new(row_height: i32) -> ListState {
+        ListState {
+            items: Vec::new(),
+            selected: None,
+            scroll: 0,
+            row_height: row_height,
+        }
+    }
+
+    pub fn items<'a>(&'a self) -> &'a [ListItem] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn row_height(&self) -> i32 {
+        self.row_height
+    }
+
+    /// Replaces the items, keeping the selection and scroll where they still fit
+    pub fn set_items(&mut self, items: Vec<ListItem>) {
+        self.items = items;
+        if self.items.is_empty() {
+            self.selected = None;
+        } else if let Some(selected) = self.selected {
+            self.selected = Some(selected.min(self.items.len() - 1));
+        }
+        self.scroll = self.scroll.min(self.items.len().saturating_sub(1));
+    }
+
+    /// How many rows fit in a list of the given size
+    pub fn visible_rows(&self, rect: &Rect) -> usize {
+        (rect.h / self.row_height).max(1) as usize
+    }
+
+    /// Picks an item, scrolling the list so that it shows
+    pub fn select(&mut self, index: usize, rect: &Rect) {
+        if self.items.is_empty() {
+            return;
+        }
+        let index = index.min(self.items.len() - 1);
+        let rows = self.visible_rows(rect);
+        if index < self.scroll {
+            self.scroll = index;
+        } else if index >= self.scroll + rows {
+            self.scroll = index + 1 - rows;
+        }
+        self.selected = Some(index);
+    }
+
+    /// Moves the selection by the given number of items, starting from the first one if there
+    /// wasn't a selection
+    pub fn move_selection(&mut self, offset: i32, rect: &Rect) {
+        let index = match self.selected {
+            Some(selected) => (selected as i32 + offset).max(0) as usize,
+            None => 0,
+        };
+        self.select(index, rect);
+    }
+
+    /// The item shown at the given position, if there is one
+    pub fn item_at(&self, rect: &Rect, x: i32, y: i32) -> Option<usize> {
+        if !rect.contains(x, y) {
+            return None;
+        }
+        let index = self.scroll + ((y - rect.y) / self.row_height) as usize;
+        if index < self.items.len() && index < self.scroll + self.visible_rows(rect) {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WidgetKind {
+    Button,
+    Checkbox { checked: bool },
+    Slider { value: i32, min: i32, max: i32, step: i32 },
+    List(ListState),
+    /// A list that only shows the selected item until it's opened, below the widget
+    Dropdown { list: ListState, open: bool },
+    TextInput(TextField),
+}
+
+/// One control of a form. The id is what its events are reported by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Widget {
+    pub id: &'static str,
+    pub rect: Rect,
+    pub label: String,
+    pub enabled: bool,
+    pub kind: WidgetKind,
+}
+
+impl Widget {
+    pub fn new(id: &'static str, rect: Rect, kind: WidgetKind) -> Widget {
+        Widget {
+            id: id,
+            rect: rect,
+            label: String::new(),
+            enabled: true,
+            kind: kind,
+        }
+    }
+
+    pub fn button(id: &'static str, rect: Rect, label: &str) -> Widget {
+        Widget::new(id, rect, WidgetKind::Button).with_label(label)
+    }
+
+    pub fn checkbox(id: &'static str, rect: Rect, label: &str, checked: bool) -> Widget {
+        Widget::new(id, rect, WidgetKind::Checkbox { checked: checked }).with_label(label)
+    }
+
+    pub fn slider(id: &'static str, rect: Rect, min: i32, max: i32, step: i32, value: i32) -> Widget {
+        let kind = WidgetKind::Slider {
+            value: value.max(min).min(max),
+            min: min,
+            max: max,
+            step: step.max(1),
+        };
+        Widget::new(id, rect, kind)
+    }
+
+    pub fn list(id: &'static str, rect: Rect, row_height: i32) -> Widget {
+        Widget::new(id, rect, WidgetKind::List(ListState::new(row_height)))
+    }
+
+    pub fn dropdown(id: &'static str, rect: Rect, row_height: i32) -> Widget {
+        Widget::new(id,
+                    rect,
+                    WidgetKind::Dropdown {
+                        list: ListState::new(row_height),
+                        open: false,
+                    })
+    }
+
+    pub fn text_input(id: &'static str, rect: Rect, max_chars: usize) -> Widget {
+        Widget::new(id, rect, WidgetKind::TextInput(TextField::new(max_chars)))
+    }
+
+    pub fn with_label(mut self, label: &str) -> Widget {
+        self.label = label.to_string();
+        self
+    }
+
+    /// Whether the widget takes the keyboard while it has the focus, so that typing into it
+    /// doesn't also work the game's hotkeys
+    pub fn takes_keyboard(&self) -> bool {
+        match self.kind {
+            WidgetKind::TextInput(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Where an open dropdown's list is drawn, under the widget; None for anything else
+    pub fn open_list_rect(&self) -> Option<Rect> {
+        match self.kind {
+            WidgetKind::Dropdown { ref list, open: true } => Some(dropdown_list_rect(&self.rect, list)),
+            _ => None,
+        }
+    }
+
+    /// Whether the widget, or its open list, is at the given position
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.rect.contains(x, y) || self.open_list_rect().map_or(false, |rect| rect.contains(x, y))
+    }
+
+    /// The slider's value at the given horizontal position along it
+    pub fn slider_value_at(&self, x: i32) -> Option<i32> {
+        match self.kind {
+            WidgetKind::Slider { min, max, step, .. } => {
+                let along = (x - self.rect.x).max(0).min(self.rect.w);
+                let width = self.rect.w.max(1);
+                let raw = min + ((max - min) * along + width / 2) / width;
+                let snapped = min + (raw - min + step / 2) / step * step;
+                Some(snapped.max(min).min(max))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Room for as many of the items as fit in the dropdown's rows
+pub fn dropdown_list_rect(rect: &Rect, list: &ListState) -> Rect {
+    let rows = list.items().len().max(1).min(DROPDOWN_ROWS) as i32;
+    Rect::of(rect.x, rect.y + rect.h, rect.w, rows * list.row_height())
+}
+
+/// What happened to a form's widgets during an update
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiEvent {
+    Pressed(&'static str),
+    Toggled(&'static str, bool),
+    Changed(&'static str, i32),
+    /// The list's selection moved to the item
+    Selected(&'static str, usize),
+    /// The list's item was clicked, or Return was pressed on it
+    Activated(&'static str, usize),
+    /// Return was pressed in the text input, which is emptied
+    Submitted(&'static str, String),
+    /// Escape was pressed with the widget focused
+    Cancelled(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Color, Rect};
+
+    fn items(count: usize) -> Vec<ListItem> {
+        (0..count)
+            .map(|index| {
+                ListItem {
+                    text: index.to_string(),
+                    color: Color::rgb(0, 0, 0),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_scrolls_to_the_selection() {
+        let rect = Rect::of(0, 0, 100, 30);
+        let mut list = ListState::new(10);
+        list.set_items(items(10));
+        list.select(5, &rect);
+        assert_eq!((Some(5), 3), (list.selected(), list.scroll()));
+        list.move_selection(-4, &rect);
+        assert_eq!((Some(1), 1), (list.selected(), list.scroll()));
+        list.move_selection(100, &rect);
+        assert_eq!((Some(9), 7), (list.selected(), list.scroll()));
+
+        assert_eq!(Some(8), list.item_at(&rect, 50, 15));
+        assert_eq!(None, list.item_at(&rect, 50, 35));
+
+        list.set_items(items(4));
+        assert_eq!((Some(3), 3), (list.selected(), list.scroll()));
+    }
+
+    #[test]
+    fn test_slider_snaps_to_steps() {
+        let slider = Widget::slider("volume", Rect::of(10, 0, 100, 10), 0, 100, 10, 250);
+        assert_eq!(WidgetKind::Slider {
+                       value: 100,
+                       min: 0,
+                       max: 100,
+                       step: 10,
+                   },
+                   slider.kind);
+        assert_eq!(Some(0), slider.slider_value_at(0));
+        assert_eq!(Some(40), slider.slider_value_at(47));
+        assert_eq!(Some(100), slider.slider_value_at(500));
+    }
+}
@@ -0,0 +1,106 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Draws numbers out of lines, seven-segment display style, since there's no font rendering yet
+
+use ecs::resource::RenderCommands;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use types::Color;
+
+// Bit N is set if segment N is lit. Segments are numbered clockwise
+// starting at the top, with the middle segment last.
+const DIGIT_SEGMENTS: [u8; 10] = [0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101,
+                                  0b1111101, 0b0000111, 0b1111111, 0b1101111];
+
+fn digit_width(digit_height: i32) -> i32 {
+    digit_height / 2
+}
+
+fn digit_spacing(digit_height: i32) -> i32 {
+    digit_height / 4 + 1
+}
+
+/// Width in pixels that the given number will take up
+pub fn number_width(value: u32, digit_height: i32) -> i32 {
+    let digit_count = value.to_string().len() as i32;
+    digit_count * digit_width(digit_height) + (digit_count - 1) * digit_spacing(digit_height)
+}
+
+/// Returns the line segments needed to draw the given number
+pub fn number_lines(value: u32, top_left: Vector2<i32>, digit_height: i32) -> Vec<[Vector2<i32>; 2]> {
+    let (w, h, hh) = (digit_width(digit_height), digit_height, digit_height / 2);
+    let segments = [[(0, 0), (w, 0)],
+                    [(w, 0), (w, hh)],
+                    [(w, hh), (w, h)],
+                    [(0, h), (w, h)],
+                    [(0, hh), (0, h)],
+                    [(0, 0), (0, hh)],
+                    [(0, hh), (w, hh)]];
+
+    let mut lines = Vec::new();
+    let mut x = top_left.x;
+    for digit in value.to_string().bytes().map(|b| (b - b'0') as usize) {
+        for (index, segment) in segments.iter().enumerate() {
+            if DIGIT_SEGMENTS[digit] & (1 << index) != 0 {
+                lines.push([Vector2::new(x + (segment[0].0), top_left.y + (segment[0].1)),
+                            Vector2::new(x + (segment[1].0), top_left.y + (segment[1].1))]);
+            }
+        }
+        x += w + digit_spacing(digit_height);
+    }
+    lines
+}
+
+pub fn render_number(render_commands: &mut RenderCommands,
+                     layer: u16,
+                     color: Color,
+                     value: u32,
+                     top_left: Vector2<i32>,
+                     digit_height: i32) {
+    for line in number_lines(value, top_left, digit_height) {
+        render_commands.push(RenderCommand::new_line(layer, 0, color, line[0], line[1]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+    use super::{number_lines, number_width};
+
+    #[test]
+    fn test_number_lines() {
+        assert_eq!(2, number_lines(1, Vector2::new(0, 0), 10).len());
+        assert_eq!(7, number_lines(8, Vector2::new(0, 0), 10).len());
+        assert_eq!(6 + 2, number_lines(10, Vector2::new(0, 0), 10).len());
+
+        // The second digit should start after the first digit and the spacing
+        let lines = number_lines(11, Vector2::new(5, 5), 10);
+        assert_eq!([Vector2::new(10, 5), Vector2::new(10, 10)], lines[0]);
+        assert_eq!([Vector2::new(18, 5), Vector2::new(18, 10)], lines[2]);
+    }
+
+    #[test]
+    fn test_number_width() {
+        assert_eq!(5, number_width(0, 10));
+        assert_eq!(13, number_width(42, 10));
+    }
+}
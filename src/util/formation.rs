@@ -0,0 +1,172 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Box formations for moving a group of units, so that each one gets a tile of its own to go to
+//! instead of all of them piling up on the one that was clicked.
+
+use std::cmp;
+
+/// (row, col) tile position
+pub type FormationTile = (i32, i32);
+
+/// Furthest from the box that tiles are looked for when too much of it is blocked
+const MAX_SPREAD: i32 = 8;
+
+/// Tiles across the front of the box and tiles deep for `count` units; the box is about square,
+/// with any extra width across the front
+pub fn box_size(count: usize) -> (i32, i32) {
+    let across = cmp::max(1, (count as f64).sqrt().ceil() as i32);
+    let deep = cmp::max(1, (count as i32 + across - 1) / across);
+    (across, deep)
+}
+
+/// Picks `count` tiles around `center` in a box that faces `heading`, the (row, col) direction
+/// the group is moving in, leaving out tiles that `free` says can't be stood on. Blocked tiles
+/// are made up for with the nearest free ones around the box, and there can still be fewer than
+/// `count` tiles when the area is too crowded.
+pub fn formation_tiles<F>(center: FormationTile,
+                          count: usize,
+                          heading: (i32, i32),
+                          free: F)
+                          -> Vec<FormationTile>
+    where F: Fn(FormationTile) -> bool
+{
+    let (across, deep) = box_size(count);
+    // The front runs crosswise to the way the group is heading
+    let (rows, cols) = if heading.1.abs() >= heading.0.abs() {
+        (across, deep)
+    } else {
+        (deep, across)
+    };
+
+    let top_left = (center.0 - (rows - 1) / 2, center.1 - (cols - 1) / 2);
+    let mut tiles = Vec::new();
+    for row in top_left.0..(top_left.0 + rows) {
+        for col in top_left.1..(top_left.1 + cols) {
+            if free((row, col)) {
+                tiles.push((row, col));
+            }
+        }
+    }
+    sort_by_distance(&mut tiles, center);
+    tiles.truncate(count);
+
+    let mut spread = 1;
+    while tiles.len() < count && spread <= MAX_SPREAD {
+        let (first_row, last_row) = (top_left.0 - spread, top_left.0 + rows + spread - 1);
+        let (first_col, last_col) = (top_left.1 - spread, top_left.1 + cols + spread - 1);
+        let mut ring = Vec::new();
+        for row in first_row..(last_row + 1) {
+            for col in first_col..(last_col + 1) {
+                let on_ring = row == first_row || row == last_row || col == first_col || col == last_col;
+                if on_ring && free((row, col)) {
+                    ring.push((row, col));
+                }
+            }
+        }
+        sort_by_distance(&mut ring, center);
+        let missing = count - tiles.len();
+        tiles.extend(ring.into_iter().take(missing));
+        spread += 1;
+    }
+    tiles
+}
+
+/// Gives each unit, by its current tile, one of the formation's tiles; the closest pairs are
+/// matched first so units don't cross over each other on the way. The result lines up with
+/// `units`, and units left over when there are fewer tiles than units get `None`.
+pub fn assign_tiles(units: &[FormationTile], tiles: &[FormationTile]) -> Vec<Option<FormationTile>> {
+    let mut pairs = Vec::with_capacity(units.len() * tiles.len());
+    for (unit_index, unit) in units.iter().enumerate() {
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            pairs.push((distance_squared(*unit, *tile), unit_index, tile_index));
+        }
+    }
+    pairs.sort();
+
+    let mut assigned = vec![None; units.len()];
+    let mut taken = vec![false; tiles.len()];
+    for (_, unit_index, tile_index) in pairs {
+        if assigned[unit_index].is_none() && !taken[tile_index] {
+            assigned[unit_index] = Some(tiles[tile_index]);
+            taken[tile_index] = true;
+        }
+    }
+    assigned
+}
+
+fn distance_squared(first: FormationTile, second: FormationTile) -> i32 {
+    let (rows, cols) = (first.0 - second.0, first.1 - second.1);
+    rows * rows + cols * cols
+}
+
+fn sort_by_distance(tiles: &mut Vec<FormationTile>, center: FormationTile) {
+    tiles.sort_by_key(|&tile| (distance_squared(tile, center), tile));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::{assign_tiles, box_size, formation_tiles};
+
+    #[test]
+    fn test_box_size() {
+        assert_eq!((1, 1), box_size(1));
+        assert_eq!((2, 1), box_size(2));
+        assert_eq!((3, 2), box_size(5));
+        assert_eq!((3, 3), box_size(9));
+        assert_eq!((4, 3), box_size(10));
+    }
+
+    #[test]
+    fn test_box_faces_the_heading() {
+        // Heading right, so the front of the box runs up and down
+        let tiles = formation_tiles((10, 10), 6, (0, 5), |_| true);
+        assert_eq!(6, tiles.len());
+        let rows: HashSet<i32> = tiles.iter().map(|tile| tile.0).collect();
+        let cols: HashSet<i32> = tiles.iter().map(|tile| tile.1).collect();
+        assert_eq!((3, 2), (rows.len(), cols.len()));
+        assert!(tiles.contains(&(10, 10)));
+
+        let tiles = formation_tiles((10, 10), 6, (-5, 1), |_| true);
+        let rows: HashSet<i32> = tiles.iter().map(|tile| tile.0).collect();
+        assert_eq!(2, rows.len());
+    }
+
+    #[test]
+    fn test_blocked_tiles_are_made_up_for() {
+        let tiles = formation_tiles((10, 10), 4, (0, 1), |tile| tile.1 != 10);
+        assert_eq!(4, tiles.len());
+        assert!(tiles.iter().all(|tile| tile.1 != 10));
+        let unique: HashSet<_> = tiles.iter().collect();
+        assert_eq!(4, unique.len());
+
+        let tiles = formation_tiles((10, 10), 4, (0, 1), |tile| tile == (10, 10));
+        assert_eq!(vec![(10, 10)], tiles);
+    }
+
+    #[test]
+    fn test_assign_closest_first() {
+        let units = [(0, 0), (0, 5), (9, 9)];
+        let tiles = [(1, 5), (1, 0)];
+        assert_eq!(vec![Some((1, 0)), Some((1, 5)), None], assign_tiles(&units, &tiles));
+    }
+}
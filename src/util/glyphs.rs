@@ -0,0 +1,219 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Draws text out of a tiny pixel font, for the places that need to be readable before there's
+//! font rendering (letters are drawn as capitals)
+
+use ecs::resource::RenderCommands;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use types::{Color, Rect};
+
+pub const GLYPH_WIDTH: i32 = 3;
+pub const GLYPH_HEIGHT: i32 = 5;
+
+/// Drawn for characters that the font doesn't have
+const MISSING_GLYPH: [u8; 5] = [0b111, 0b111, 0b111, 0b111, 0b111];
+
+// Each row is three pixels wide, with the leftmost pixel in the highest bit
+fn glyph(character: char) -> Option<[u8; 5]> {
+    let rows = match character.to_uppercase().next().unwrap_or(character) {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// Width in pixels that the given number of characters take up
+pub fn text_width(chars: usize, spacing: i32) -> i32 {
+    (chars as i32 * (GLYPH_WIDTH + spacing) - spacing).max(0)
+}
+
+/// Returns the rectangles that light up the text's pixels, one for each run of lit pixels
+/// in a row of a glyph
+pub fn text_rects(text: &str, top_left: Vector2<i32>, spacing: i32) -> Vec<Rect> {
+    scaled_text_rects(text, top_left, GLYPH_WIDTH + spacing, 1)
+}
+
+/// Like `text_rects`, with each of the font's pixels drawn `scale` pixels square, and each
+/// character `advance` screen pixels on from the one before it
+pub fn scaled_text_rects(text: &str, top_left: Vector2<i32>, advance: i32, scale: i32) -> Vec<Rect> {
+    let mut rects = Vec::new();
+    for (index, character) in text.chars().enumerate() {
+        if character.is_whitespace() {
+            continue;
+        }
+        let x = top_left.x + index as i32 * advance;
+        let rows = glyph(character).unwrap_or(MISSING_GLYPH);
+        for (row_index, row) in rows.iter().enumerate() {
+            let y = top_left.y + row_index as i32 * scale;
+            let mut run_start = None;
+            for column in 0..GLYPH_WIDTH + 1 {
+                let lit = column < GLYPH_WIDTH && row & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                match (lit, run_start) {
+                    (true, None) => run_start = Some(column),
+                    (false, Some(start)) => {
+                        rects.push(Rect::of(x + start * scale, y, (column - start) * scale, scale));
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    rects
+}
+
+pub fn render_text(render_commands: &mut RenderCommands,
+                   layer: u16,
+                   depth: i32,
+                   color: Color,
+                   text: &str,
+                   top_left: Vector2<i32>,
+                   spacing: i32) {
+    for rect in text_rects(text, top_left, spacing) {
+        render_commands.push(RenderCommand::new_filled_rect(layer, depth, color, rect));
+    }
+}
+
+/// Draws text bigger than the font's own size, for text that's read at a glance, like the chat
+/// box's and the widgets'
+pub fn render_scaled_text(render_commands: &mut RenderCommands,
+                          layer: u16,
+                          depth: i32,
+                          color: Color,
+                          text: &str,
+                          top_left: Vector2<i32>,
+                          advance: i32,
+                          scale: i32) {
+    for rect in scaled_text_rects(text, top_left, advance, scale) {
+        render_commands.push(RenderCommand::new_filled_rect(layer, depth, color, rect));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+    use super::{scaled_text_rects, text_rects, text_width};
+    use types::Rect;
+
+    #[test]
+    fn test_text_rects() {
+        // Each row of a glyph is a run, so an I has its bar, its stem, and its bar again
+        let rects = text_rects("I", Vector2::new(10, 20), 1);
+        assert_eq!(vec![Rect::of(10, 20, 3, 1),
+                        Rect::of(11, 21, 1, 1),
+                        Rect::of(11, 22, 1, 1),
+                        Rect::of(11, 23, 1, 1),
+                        Rect::of(10, 24, 3, 1)],
+                   rects);
+
+        // Rows with a gap in the middle are two runs, and letters are drawn as capitals
+        assert_eq!(text_rects("H", Vector2::new(0, 0), 1), text_rects("h", Vector2::new(0, 0), 1));
+        assert_eq!(2, text_rects("H", Vector2::new(0, 0), 1).iter().filter(|r| r.y == 0).count());
+    }
+
+    #[test]
+    fn test_text_rects_spacing() {
+        // Spaces take up room without drawing anything
+        let rects = text_rects("- -", Vector2::new(0, 0), 1);
+        assert_eq!(vec![Rect::of(0, 2, 3, 1), Rect::of(8, 2, 3, 1)], rects);
+    }
+
+    #[test]
+    fn test_scaled_text_rects() {
+        let rects = scaled_text_rects("--", Vector2::new(10, 20), 8, 2);
+        assert_eq!(vec![Rect::of(10, 24, 6, 2), Rect::of(18, 24, 6, 2)], rects);
+    }
+
+    #[test]
+    fn test_missing_glyphs_are_blocks() {
+        let rects = text_rects("\u{e9}", Vector2::new(0, 0), 1);
+        assert_eq!(5, rects.len());
+        assert!(rects.iter().all(|rect| rect.w == 3));
+    }
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(0, text_width(0, 1));
+        assert_eq!(3, text_width(1, 1));
+        assert_eq!(11, text_width(3, 1));
+    }
+}
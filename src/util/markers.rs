@@ -0,0 +1,141 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Shapes that tell players apart without relying on their color, for color blind players
+
+use ecs::resource::RenderCommands;
+use identifier::PlayerId;
+use nalgebra::Vector2;
+use resource::RenderCommand;
+use types::Color;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Marker {
+    Square,
+    Circle,
+    Diamond,
+    TriangleUp,
+    TriangleDown,
+    TriangleLeft,
+    TriangleRight,
+    Hexagon,
+}
+
+const MARKERS: [Marker; 8] = [Marker::Square,
+                              Marker::Circle,
+                              Marker::Diamond,
+                              Marker::TriangleUp,
+                              Marker::Hexagon,
+                              Marker::TriangleDown,
+                              Marker::TriangleLeft,
+                              Marker::TriangleRight];
+
+/// The marker of the given (non-gaia) player; players 1 through 8 each get a different one
+pub fn player_marker(player_id: PlayerId) -> Marker {
+    MARKERS[(*player_id as usize + MARKERS.len() - 1) % MARKERS.len()]
+}
+
+/// The first and last column covered by each row of the marker, in a square `size` pixels across.
+/// All of the markers are convex, so each row is a single run.
+pub fn marker_spans(marker: Marker, size: i32) -> Vec<(i32, i32)> {
+    let last = (size - 1) as f32;
+    (0..size)
+        .map(|y| {
+            // How far down the row is, and how far from the middle row, both from 0 to 1
+            let down = (y as f32 + 0.5) / size as f32;
+            let off_middle = (down - 0.5).abs() * 2.;
+            let (left, right) = match marker {
+                Marker::Square => (0., 1.),
+                Marker::Circle => {
+                    let half = (1. - off_middle * off_middle).sqrt() / 2.;
+                    (0.5 - half, 0.5 + half)
+                }
+                Marker::Diamond => (off_middle / 2., 1. - off_middle / 2.),
+                Marker::TriangleUp => (0.5 - down / 2., 0.5 + down / 2.),
+                Marker::TriangleDown => (down / 2., 1. - down / 2.),
+                Marker::TriangleLeft => (off_middle, 1.),
+                Marker::TriangleRight => (0., 1. - off_middle),
+                Marker::Hexagon => {
+                    let inset = (off_middle - 0.5).max(0.) / 2.;
+                    (inset, 1. - inset)
+                }
+            };
+            ((left * last).round() as i32, (right * last).round() as i32)
+        })
+        .collect()
+}
+
+/// Draws the marker in a square `size` pixels across, filled in or as just its outline
+pub fn render_marker(render_commands: &mut RenderCommands,
+                     layer: u16,
+                     color: Color,
+                     marker: Marker,
+                     top_left: Vector2<i32>,
+                     size: i32,
+                     filled: bool) {
+    let spans = marker_spans(marker, size);
+    let point = |x: i32, y: usize| Vector2::new(top_left.x + x, top_left.y + y as i32);
+    for (y, &(left, right)) in spans.iter().enumerate() {
+        let edge_row = y == 0 || y + 1 == spans.len();
+        if filled || edge_row {
+            render_commands.push(RenderCommand::new_line(layer, 1, color, point(left, y), point(right, y)));
+        } else {
+            // The sides join up with the row above, so that steep steps don't leave gaps
+            let (above_left, above_right) = spans[y - 1];
+            for &(above, x) in &[(above_left, left), (above_right, right)] {
+                let (from, to) = (point(above, y - 1), point(x, y));
+                render_commands.push(RenderCommand::new_line(layer, 1, color, from, to));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MARKERS, marker_spans, player_marker};
+
+    #[test]
+    fn test_marker_spans_stay_inside() {
+        for marker in &MARKERS {
+            let spans = marker_spans(*marker, 16);
+            assert_eq!(16, spans.len());
+            for &(left, right) in &spans {
+                assert!(0 <= left && left <= right && right < 16, "{:?}: {} {}", marker, left, right);
+            }
+        }
+    }
+
+    #[test]
+    fn test_markers_differ() {
+        for (index, marker) in MARKERS.iter().enumerate() {
+            for other in MARKERS.iter().skip(index + 1) {
+                assert!(marker_spans(*marker, 16) != marker_spans(*other, 16), "{:?} {:?}", marker, other);
+            }
+        }
+    }
+
+    #[test]
+    fn test_player_markers() {
+        assert_eq!(MARKERS[0], player_marker(1.into()));
+        assert_eq!(MARKERS[7], player_marker(8.into()));
+        assert_eq!(MARKERS[0], player_marker(9.into()));
+    }
+}
@@ -19,4 +19,12 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod digits;
+pub mod formation;
+pub mod glyphs;
+pub mod markers;
+pub mod parse;
+pub mod placement;
+pub mod pointer;
+pub mod steering;
 pub mod unit;
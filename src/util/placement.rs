@@ -0,0 +1,253 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use dat;
+use ecs::resource::{OccupiedTiles, Terrain};
+use identifier::TerrainId;
+use scn;
+
+/// The ground buildings are placed on, looked up by tile
+pub trait PlacementGround {
+    /// The terrain of the tile, or None if it's off the map
+    fn terrain_at(&self, x: i32, y: i32) -> Option<TerrainId>;
+}
+
+impl PlacementGround for scn::Map {
+    fn terrain_at(&self, x: i32, y: i32) -> Option<TerrainId> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            None
+        } else {
+            Some(self.tiles[(y * self.width as i32 + x) as usize].terrain_id)
+        }
+    }
+}
+
+impl PlacementGround for Terrain {
+    fn terrain_at(&self, x: i32, y: i32) -> Option<TerrainId> {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            None
+        } else {
+            Some(self.tile_at_row_col(y, x).terrain_id)
+        }
+    }
+}
+
+/// What the ground has to be for a building to be placed, from its entry in the game data
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlacementRules {
+    /// Half the width and height, in tiles, of the ground the building covers
+    pub half_size: (f32, f32),
+    /// When there are any, every tile under the building has to be one of these, like water
+    /// for a dock
+    pub terrain_ids: Vec<TerrainId>,
+    /// When there are any, one of the tiles around the building has to be one of these, like
+    /// the shore a dock is built against
+    pub side_terrain_ids: Vec<TerrainId>,
+}
+
+impl PlacementRules {
+    /// The rules for placing the unit, or None for units that aren't buildings, which go anywhere
+    pub fn of(unit_info: &dat::Unit) -> Option<PlacementRules> {
+        if unit_info.interaction_mode != dat::InteractionMode::Building {
+            return None;
+        }
+        Some(PlacementRules {
+            half_size: (unit_info.collision_size_x, unit_info.collision_size_y),
+            terrain_ids: unit_info.placement_terrain_ids(),
+            side_terrain_ids: unit_info.placement_side_terrain_ids(),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FootprintTile {
+    pub x: i32,
+    pub y: i32,
+    pub allowed: bool,
+}
+
+/// Where a building would go and which of the tiles under it stop it going there
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    /// The building's position, moved so that it lines up with the tiles
+    pub x: f32,
+    pub y: f32,
+    pub tiles: Vec<FootprintTile>,
+    pub has_side_terrain: bool,
+}
+
+impl Placement {
+    pub fn is_allowed(&self) -> bool {
+        self.has_side_terrain && self.tiles.iter().all(|tile| tile.allowed)
+    }
+}
+
+/// Lines a building up with the tiles nearest the position and checks the ground it would cover:
+/// each tile has to be on the map, be a terrain the building's terrain restriction allows
+/// (`passable`), and be one of its placement terrains. The scenario editor and the game both
+/// place buildings with it, so that they agree on where buildings can go.
+pub fn check_placement<G, F>(ground: &G, rules: &PlacementRules, x: f32, y: f32, passable: F) -> Placement
+    where G: PlacementGround,
+          F: Fn(TerrainId) -> bool
+{
+    let terrain_at = |tile_x: i32, tile_y: i32| ground.terrain_at(tile_x, tile_y);
+
+    let (left, width) = footprint_span(x, rules.half_size.0);
+    let (top, height) = footprint_span(y, rules.half_size.1);
+    let mut tiles = Vec::new();
+    for tile_y in top..(top + height) {
+        for tile_x in left..(left + width) {
+            let allowed = terrain_at(tile_x, tile_y).map_or(false, |terrain_id| {
+                passable(terrain_id) &&
+                (rules.terrain_ids.is_empty() || rules.terrain_ids.contains(&terrain_id))
+            });
+            tiles.push(FootprintTile {
+                x: tile_x,
+                y: tile_y,
+                allowed: allowed,
+            });
+        }
+    }
+
+    // The ring of tiles just outside the footprint
+    let mut has_side_terrain = rules.side_terrain_ids.is_empty();
+    for tile_y in (top - 1)..(top + height + 1) {
+        for tile_x in (left - 1)..(left + width + 1) {
+            let inside = tile_x >= left && tile_x < left + width && tile_y >= top && tile_y < top + height;
+            let side_terrain = terrain_at(tile_x, tile_y)
+                .map_or(false, |terrain_id| rules.side_terrain_ids.contains(&terrain_id));
+            if !inside && side_terrain {
+                has_side_terrain = true;
+            }
+        }
+    }
+
+    Placement {
+        x: left as f32 + width as f32 / 2.0,
+        y: top as f32 + height as f32 / 2.0,
+        tiles: tiles,
+        has_side_terrain: has_side_terrain,
+    }
+}
+
+/// Checks the placement of a unit in the game. On top of the ground it would cover, none of its
+/// tiles can have something standing on them already.
+pub fn check_game_placement<F>(terrain: &Terrain,
+                               occupied_tiles: &OccupiedTiles,
+                               unit_info: &dat::Unit,
+                               x: f32,
+                               y: f32,
+                               passable: F)
+                               -> Placement
+    where F: Fn(TerrainId) -> bool
+{
+    let rules = PlacementRules::of(unit_info).unwrap_or_else(|| {
+        PlacementRules {
+            half_size: (unit_info.collision_size_x, unit_info.collision_size_y),
+            terrain_ids: Vec::new(),
+            side_terrain_ids: Vec::new(),
+        }
+    });
+    let mut placement = check_placement(terrain, &rules, x, y, passable);
+    for tile in &mut placement.tiles {
+        if occupied_tiles.tiles.contains(&(tile.y, tile.x)) {
+            tile.allowed = false;
+        }
+    }
+    placement
+}
+
+/// The first tile a building covers along one axis, and how many tiles it covers
+fn footprint_span(center: f32, half_size: f32) -> (i32, i32) {
+    let count = ((half_size * 2.0).round() as i32).max(1);
+    ((center - count as f32 / 2.0).round() as i32, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use identifier::TerrainId;
+    use scn;
+    use super::{PlacementRules, check_placement};
+
+    const GRASS: u8 = 0;
+    const WATER: u8 = 1;
+    const SHALLOWS: u8 = 4;
+
+    /// Water on the left half of the map, with shallows in its top corner, and grass on the right
+    fn map() -> scn::Map {
+        let mut scenario = scn::Scenario::new(8, 8);
+        for y in 0..8 {
+            for x in 0..4 {
+                let terrain_id = if x == 3 && y == 0 { SHALLOWS } else { WATER };
+                scenario.map.tiles[y * 8 + x].terrain_id = TerrainId(terrain_id);
+            }
+        }
+        scenario.map
+    }
+
+    fn land(terrain_id: TerrainId) -> bool {
+        *terrain_id == GRASS
+    }
+
+    #[test]
+    fn test_footprint_lines_up_with_tiles() {
+        let rules = PlacementRules {
+            half_size: (1.5, 1.5),
+            ..Default::default()
+        };
+        let placement = check_placement(&map(), &rules, 5.3, 4.8, land);
+        assert_eq!((5.5, 4.5), (placement.x, placement.y));
+        assert_eq!(9, placement.tiles.len());
+        assert_eq!((4, 3), (placement.tiles[0].x, placement.tiles[0].y));
+        assert!(placement.is_allowed());
+
+        // Half on the water, and hanging off the map
+        assert!(!check_placement(&map(), &rules, 3.5, 4.5, land).is_allowed());
+        assert!(!check_placement(&map(), &rules, 7.5, 4.5, land).is_allowed());
+    }
+
+    #[test]
+    fn test_dock_straddles_the_coastline() {
+        let rules = PlacementRules {
+            half_size: (1.0, 1.0),
+            terrain_ids: vec![TerrainId(WATER)],
+            side_terrain_ids: vec![TerrainId(GRASS)],
+        };
+        let on_water = |terrain_id: TerrainId| *terrain_id != GRASS;
+        assert!(check_placement(&map(), &rules, 3.0, 4.0, on_water).is_allowed());
+
+        // Out in open water, there's no shore next to it
+        let open_water = check_placement(&map(), &rules, 1.0, 4.0, on_water);
+        assert!(open_water.tiles.iter().all(|tile| tile.allowed));
+        assert!(!open_water.has_side_terrain);
+        assert!(!open_water.is_allowed());
+    }
+
+    #[test]
+    fn test_tower_stays_off_shallows() {
+        let rules = PlacementRules {
+            half_size: (0.5, 0.5),
+            ..Default::default()
+        };
+        assert!(!check_placement(&map(), &rules, 3.5, 0.5, land).is_allowed());
+        assert!(check_placement(&map(), &rules, 4.5, 0.5, land).is_allowed());
+    }
+}
@@ -0,0 +1,53 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ecs::resource::{Terrain, ViewProjector, Viewport};
+use nalgebra::Vector2;
+use types::{Fixed, Vector3};
+
+/// A ray from above the map down through where the pointer is, for finding what's under it
+pub struct MouseRay {
+    /// Where the ray meets the terrain
+    pub world_coord: Vector3,
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+pub fn calculate_mouse_ray(viewport: &Viewport,
+                           pointer: &Vector2<i32>,
+                           view_projector: &ViewProjector,
+                           terrain: &Terrain)
+                           -> MouseRay {
+    let viewport_pos = viewport.top_left_i32();
+    let mouse_pos = *pointer + viewport_pos;
+
+    // "Origin elevation" just needs to be a bit taller than the max terrain elevation
+    let origin_elevation: Fixed = Fixed::from(terrain.elevation_range().1) * 2.into();
+    let world_coord = view_projector.unproject(&mouse_pos, &*terrain);
+    let origin = view_projector.unproject_at_elevation(&mouse_pos, origin_elevation);
+    let direction = world_coord - origin;
+
+    MouseRay {
+        world_coord: world_coord,
+        origin: origin,
+        direction: direction,
+    }
+}
@@ -0,0 +1,80 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Helpers for walking units along the paths that the PathFinder finds for them
+
+use types::{Fixed, Norm, Vector3};
+
+/// How close a unit has to get to a waypoint before heading for the next one
+pub const WAYPOINT_THRESHOLD: Fixed = fixed_const!(0.1);
+
+/// Which way to head for the next waypoint on the path, dropping the ones already reached;
+/// none once the path runs out
+pub fn next_direction(path: &mut Vec<Vector3>, position: &Vector3) -> Option<Vector3> {
+    while let Some(&waypoint) = path.first() {
+        let mut direction = waypoint - *position;
+        if direction.normalize() > WAYPOINT_THRESHOLD {
+            return Some(direction);
+        }
+        path.remove(0);
+    }
+    None
+}
+
+/// Whether two positions are within the distance of each other across the ground
+pub fn within(a: &Vector3, b: &Vector3, distance: Fixed) -> bool {
+    let offset_x = (a.x - b.x).abs();
+    let offset_y = (a.y - b.y).abs();
+    offset_x <= distance && offset_y <= distance &&
+    offset_x * offset_x + offset_y * offset_y <= distance * distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_direction, within};
+    use types::{Fixed, Vector3};
+
+    fn point(x: i32, y: i32) -> Vector3 {
+        Vector3::new(x.into(), y.into(), 0.into())
+    }
+
+    #[test]
+    fn test_next_direction_drops_reached_waypoints() {
+        let mut path = vec![point(1, 1), point(1, 3)];
+        let near_first = Vector3::new(1.into(), fixed_const!(1.05), 0.into());
+        let direction = next_direction(&mut path, &near_first).unwrap();
+        assert_eq!(vec![point(1, 3)], path);
+        assert_eq!(Fixed::from(0), direction.x);
+        assert!(direction.y > fixed_const!(0.99));
+
+        let near_last = Vector3::new(1.into(), fixed_const!(3.05), 0.into());
+        assert_eq!(None, next_direction(&mut path, &near_last));
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn test_within() {
+        let distance: Fixed = 5.into();
+        assert!(within(&point(0, 0), &point(3, 4), distance));
+        assert!(!within(&point(0, 0), &point(4, 4), distance));
+        assert!(!within(&point(0, 0), &point(6, 0), distance));
+    }
+}
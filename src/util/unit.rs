@@ -21,10 +21,11 @@
 
 
 use dat;
-use ecs::TransformComponent;
+use ecs::{TransformComponent, UnitComponent};
+use ecs::resource::UnitTag;
 
 use nalgebra::Vector3;
-use types::AABox;
+use types::{AABox, Fixed, ToPrimitive};
 
 pub fn selection_box(unit_info: &dat::Unit, transform: &TransformComponent) -> AABox {
     let position = transform.position();
@@ -37,7 +38,11 @@ pub fn selection_box(unit_info: &dat::Unit, transform: &TransformComponent) -> A
 }
 
 pub fn collision_box(unit_info: &dat::Unit, transform: &TransformComponent) -> AABox {
-    let position = transform.position();
+    collision_box_at(unit_info, &transform.position())
+}
+
+/// The collision box the unit would have standing at the position
+pub fn collision_box_at(unit_info: &dat::Unit, position: &Vector3<Fixed>) -> AABox {
     AABox::new(Vector3::new(position.x - unit_info.collision_size_x.into(),
                             position.y - unit_info.collision_size_y.into(),
                             position.z + unit_info.collision_size_z.into()),
@@ -45,3 +50,204 @@ pub fn collision_box(unit_info: &dat::Unit, transform: &TransformComponent) -> A
                             position.y + unit_info.collision_size_y.into(),
                             position.z))
 }
+
+/// The (row, col) tiles the box stands on, counting the tiles its edges are on
+pub fn footprint(collision_box: &AABox) -> Vec<(i32, i32)> {
+    let (start_row, end_row) = (collision_box.min.y.to_i32().unwrap(), collision_box.max.y.to_i32().unwrap());
+    let (start_col, end_col) = (collision_box.min.x.to_i32().unwrap(), collision_box.max.x.to_i32().unwrap());
+    let mut footprint = Vec::new();
+    for row in start_row..(end_row + 1) {
+        for col in start_col..(end_col + 1) {
+            footprint.push((row, col));
+        }
+    }
+    footprint
+}
+
+/// Whether the unit can gather resources and carry them to a drop site, like villagers and
+/// fishing boats
+pub fn can_gather(unit_info: &dat::Unit) -> bool {
+    let class = unit_info.class();
+    (class.is_villager() || class == dat::UnitClass::FishingBoat) && unit_info.resource_capacity() > 0 &&
+    !unit_info.drop_sites().is_empty()
+}
+
+/// Whether the unit can gather from a resource of the given class: fishing boats only fish,
+/// and villagers gather everything but the fish they can't reach from the shore
+pub fn can_gather_from(unit_info: &dat::Unit, target_class: dat::UnitClass) -> bool {
+    if !can_gather(unit_info) {
+        return false;
+    }
+    match unit_info.class() {
+        dat::UnitClass::FishingBoat => target_class.is_fish(),
+        class if class.is_villager() => !target_class.is_fish() || target_class == dat::UnitClass::ShoreFish,
+        _ => false,
+    }
+}
+
+/// Whether the unit is a wild animal that villagers can hunt for the food on its carcass
+pub fn is_huntable(empires: &dat::EmpiresDbRef, unit: &UnitComponent) -> bool {
+    let civilization = empires.civilization(unit.civilization_id);
+    let unit_info = unit.db(empires);
+    unit_info.class().is_animal() &&
+    unit_info.dead_unit_id().map_or(false, |dead_unit_id| {
+        civilization.has_unit(dead_unit_id) &&
+        civilization.unit(dead_unit_id)
+            .stored_resource()
+            .map_or(false, |(resource_type, _)| resource_type == dat::ResourceType::Food)
+    })
+}
+
+/// How much an attack takes off a unit with the armor, both as (armor class, amount) pairs.
+/// Only the classes that the unit has armor against count, each for however much it beats the
+/// armor by, but every hit does at least one point.
+pub fn damage(attacks: &[(i16, i16)], armors: &[(i16, i16)]) -> i16 {
+    let mut total = 0;
+    for &(class, amount) in attacks {
+        if let Some(&(_, armor)) = armors.iter().find(|&&(armor_class, _)| armor_class == class) {
+            total += (amount - armor).max(0);
+        }
+    }
+    total.max(1)
+}
+
+/// Seconds between the unit's attacks, or a second when the game data doesn't say
+pub fn reload_time(unit_info: &dat::Unit) -> Fixed {
+    let reload_time: Fixed = unit_info.reload_time().into();
+    if reload_time > 0.into() {
+        reload_time
+    } else {
+        1.into()
+    }
+}
+
+/// Whether the unit can be told to attack the ground: it has to fire shots that hurt everything
+/// where they land, like a catapult
+pub fn can_attack_ground(unit_info: &dat::Unit) -> bool {
+    unit_info.blast_width() > 0. && !unit_info.attacks().is_empty() &&
+    unit_info.projectile_unit_id().is_some()
+}
+
+/// Whether the unit can be sent off to explore the map, which any unit that moves can
+pub fn can_explore(unit_info: &dat::Unit) -> bool {
+    unit_info.motion_params.as_ref().map_or(false, |params| params.speed > 0.)
+}
+
+/// Whether the unit can heal or repair the target: priests heal living units, and villagers
+/// repair buildings, siege weapons and boats
+pub fn can_restore(unit_info: &dat::Unit, target_info: &dat::Unit) -> bool {
+    let target_class = target_info.class();
+    (unit_info.can_heal() && target_class.is_organic()) ||
+    (unit_info.can_repair() && target_class.is_repairable())
+}
+
+/// Whether the unit can board the target, which has to be a transport with room for units in
+/// the game data; anything but another boat can go aboard
+pub fn can_board(unit_info: &dat::Unit, target_info: &dat::Unit) -> bool {
+    use dat::UnitClass::*;
+    let is_boat = match unit_info.class() {
+        TradeBoat | TransportBoat | FishingBoat | WarBoat => true,
+        _ => false,
+    };
+    target_info.class() == TransportBoat && target_info.garrison_capacity() > 0 && !is_boat
+}
+
+/// The town center, in each of the civilizations' building styles
+const TOWN_CENTER_UNIT_IDS: [u32; 4] = [71, 109, 141, 142];
+
+/// How many villagers fit in a town center or a tower when the game data gives no capacity
+const TOWN_CENTER_CAPACITY: usize = 15;
+const TOWER_CAPACITY: usize = 5;
+
+/// How many units can take shelter in the unit when the town bell rings: what the game data
+/// says, or else fifteen for town centers and five for towers
+pub fn garrison_capacity(unit_info: &dat::Unit) -> usize {
+    if unit_info.garrison_capacity() > 0 {
+        unit_info.garrison_capacity()
+    } else if TOWN_CENTER_UNIT_IDS.contains(&*unit_info.id) {
+        TOWN_CENTER_CAPACITY
+    } else if unit_info.interaction_mode == dat::InteractionMode::Building &&
+              !unit_info.attacks().is_empty() {
+        TOWER_CAPACITY
+    } else {
+        0
+    }
+}
+
+/// How long the unit lasts as remains before it decays: the decay time from the game data, or
+/// else as long as its animation runs. Remains with neither stay for good.
+pub fn remains_lifetime(empires: &dat::EmpiresDbRef, unit_info: &dat::Unit) -> Option<Fixed> {
+    let animation_time = unit_info.standing_graphic.map_or(0., |graphic_id| {
+        let graphic = empires.graphic(graphic_id);
+        // Frames last half of the frame rate, as the AnimationSystem plays them
+        graphic.frame_count as f32 * graphic.frame_rate / 2.
+    });
+    let lifetime = if unit_info.decay_time() > 0. {
+        unit_info.decay_time()
+    } else {
+        animation_time
+    };
+    if lifetime > 0. {
+        Some(lifetime.into())
+    } else {
+        None
+    }
+}
+
+/// What the unit is indexed under in the UnitIndex. Only gaia holds resources for gathering and
+/// animals for hunting, and a building is a drop site when any of its civilization's units drop resources off at it.
+pub fn tags(empires: &dat::EmpiresDbRef, unit: &UnitComponent) -> Vec<UnitTag> {
+    let unit_info = unit.db(empires);
+    let class = unit_info.class();
+    let mut tags = Vec::new();
+    if class.is_villager() {
+        tags.push(UnitTag::Villager);
+    }
+    if class.is_military() {
+        tags.push(UnitTag::Military);
+    }
+    if unit_info.interaction_mode == dat::InteractionMode::Building {
+        tags.push(UnitTag::Building);
+        let civilization = empires.civilization(unit.civilization_id);
+        if civilization.units().iter().any(|other| other.drop_sites().contains(&unit.unit_id)) {
+            tags.push(UnitTag::DropSite);
+        }
+    }
+    if unit.player_id.is_gaia() {
+        if let Some((resource_type, _)) = unit_info.stored_resource() {
+            tags.push(UnitTag::Resource(resource_type));
+        }
+        if is_huntable(empires, unit) {
+            tags.push(UnitTag::Huntable);
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use super::{damage, footprint};
+    use types::{AABox, Fixed};
+
+    #[test]
+    fn test_damage() {
+        // Pierce against a unit with pierce and melee armor
+        assert_eq!(7, damage(&[(3, 10)], &[(3, 3), (4, 1)]));
+        // Classes the unit has no armor against don't count
+        assert_eq!(12, damage(&[(3, 10), (4, 3), (11, 50)], &[(3, 0), (4, 1)]));
+        // Armor that outdoes the attack still lets one point through
+        assert_eq!(1, damage(&[(4, 2)], &[(4, 8)]));
+        assert_eq!(1, damage(&[], &[(4, 8)]));
+    }
+
+    #[test]
+    fn test_footprint() {
+        let size: Fixed = 1.into();
+        let center = Vector3::new(4.5.into(), 2.into(), 0.into());
+        let collision_box = AABox::new(center - Vector3::new(size, size, 0.into()),
+                                       center + Vector3::new(size, size, 0.into()));
+        assert_eq!(vec![(1, 3), (1, 4), (1, 5), (2, 3), (2, 4), (2, 5), (3, 3), (3, 4), (3, 5)],
+                   footprint(&collision_box));
+    }
+}
@@ -0,0 +1,277 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+extern crate chariot_dat as dat;
+
+extern crate clap;
+extern crate rustc_serialize;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rustc_serialize::json::{self, Json, ToJson};
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::process;
+
+/// A single row of a table: a unit, a tech, or a civ. Records are flattened into named
+/// fields so that every table can be printed as JSON or CSV, and compared field by field.
+struct Record {
+    key: String,
+    fields: Vec<(&'static str, Json)>,
+}
+
+impl Record {
+    fn new(key: String) -> Record {
+        Record {
+            key: key,
+            fields: Vec::new(),
+        }
+    }
+
+    fn field<T: ToJson>(mut self, name: &'static str, value: T) -> Record {
+        self.fields.push((name, value.to_json()));
+        self
+    }
+
+    fn to_json(&self) -> Json {
+        Json::Object(self.fields.iter().map(|&(name, ref value)| (name.to_string(), value.clone())).collect())
+    }
+}
+
+fn fail<D: Display>(message: D) -> ! {
+    println!("{}", message);
+    process::exit(1);
+}
+
+fn load_dat(file_name: &str) -> dat::EmpiresDb {
+    dat::EmpiresDb::read_from_file(file_name)
+        .unwrap_or_else(|err| fail(format!("Failed to load \"{}\": {}", file_name, err)))
+}
+
+fn pairs_to_json(pairs: &[(i16, i16)]) -> Json {
+    Json::Array(pairs.iter().map(|&(class, amount)| vec![class, amount].to_json()).collect())
+}
+
+fn costs_to_json<T: Copy + ToJson, E: Copy>(costs: &[dat::ResourceCost<T, E>]) -> Json {
+    Json::Object(costs.iter()
+        .map(|cost| (format!("{:?}", cost.resource_type).to_lowercase(), cost.amount.to_json()))
+        .collect())
+}
+
+fn unit_records(empires: &dat::EmpiresDb, civ_filter: Option<u8>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for civ in empires.civilizations() {
+        if civ_filter.map(|id| id != *civ.id()).unwrap_or(false) {
+            continue;
+        }
+        for unit in civ.units() {
+            records.push(Record::new(format!("civ{}/unit{}", *civ.id(), *unit.id))
+                .field("civ_id", *civ.id())
+                .field("id", *unit.id)
+                .field("name", unit.name().to_string())
+                .field("class", format!("{:?}", unit.class()))
+                .field("enabled", unit.enabled)
+                .field("hit_points", unit.hit_points())
+                .field("line_of_sight", unit.line_of_sight())
+                .field("speed", unit.motion_params.as_ref().map(|p| p.speed))
+                .field("attacks", pairs_to_json(unit.attacks()))
+                .field("armors", pairs_to_json(unit.armors()))
+                .field("max_range", unit.max_range())
+                .field("reload_time", unit.reload_time())
+                .field("train_time", unit.train_time())
+                .field("train_costs", costs_to_json(unit.train_costs())));
+        }
+    }
+    records
+}
+
+fn tech_records(empires: &dat::EmpiresDb) -> Vec<Record> {
+    empires.all_research()
+        .iter()
+        .map(|research| {
+            Record::new(format!("tech{}", *research.id))
+                .field("id", *research.id)
+                .field("name", research.name.clone())
+                .field("age_id", research.age_id.map(|id| *id))
+                .field("location", research.location.map(|id| *id))
+                .field("time", research.time_seconds)
+                .field("required_techs",
+                       research.required_techs.iter().filter(|id| **id >= 0).cloned().collect::<Vec<i16>>())
+                .field("costs", costs_to_json(&research.resource_costs))
+        })
+        .collect()
+}
+
+fn civ_records(empires: &dat::EmpiresDb) -> Vec<Record> {
+    empires.civilizations()
+        .iter()
+        .map(|civ| {
+            let bonuses: Vec<String> = civ.bonus_effects_id()
+                .map(|id| empires.age(id).effects.iter().map(|effect| format!("{:?}", effect)).collect())
+                .unwrap_or(Vec::new());
+            Record::new(format!("civ{}", *civ.id()))
+                .field("id", *civ.id())
+                .field("name", civ.name().to_string())
+                .field("enabled", civ.enabled())
+                .field("icon_set", civ.icon_set())
+                .field("tribute_penalty", civ.tribute_penalty())
+                .field("bonuses", bonuses)
+        })
+        .collect()
+}
+
+fn records_for(table: &str, empires: &dat::EmpiresDb, args: &ArgMatches) -> Vec<Record> {
+    match table {
+        "units" => {
+            let civ_filter = args.value_of("civ").map(|civ| {
+                civ.parse().unwrap_or_else(|_| fail(format!("Invalid civ ID \"{}\"", civ)))
+            });
+            unit_records(empires, civ_filter)
+        }
+        "techs" => tech_records(empires),
+        "civs" => civ_records(empires),
+        _ => unreachable!(),
+    }
+}
+
+fn csv_value(value: &Json) -> String {
+    let text = match *value {
+        Json::String(ref text) => text.clone(),
+        Json::Null => String::new(),
+        ref other => other.to_string(),
+    };
+    if text.contains(',') || text.contains('"') || text.contains('\n') {
+        format!("\"{}\"", text.replace("\"", "\"\""))
+    } else {
+        text
+    }
+}
+
+fn print_records(records: &[Record], format: &str) {
+    match format {
+        "csv" => {
+            if let Some(first) = records.first() {
+                println!("{}", first.fields.iter().map(|&(name, _)| name).collect::<Vec<_>>().join(","));
+            }
+            for record in records {
+                println!("{}",
+                         record.fields.iter().map(|&(_, ref value)| csv_value(value)).collect::<Vec<_>>().join(","));
+            }
+        }
+        _ => {
+            let list = Json::Array(records.iter().map(|record| record.to_json()).collect());
+            println!("{}", json::as_pretty_json(&list));
+        }
+    }
+}
+
+/// Prints every record and field that differs between the two files. Exits with a
+/// non-zero status if anything differs, so it can be used from scripts.
+fn diff(args: &ArgMatches) {
+    let (old_name, new_name) = (args.value_of("OLD").unwrap(), args.value_of("NEW").unwrap());
+    let (old_dat, new_dat) = (load_dat(old_name), load_dat(new_name));
+
+    let mut difference_count = 0;
+    for table in &["civs", "techs", "units"] {
+        let to_map = |records: Vec<Record>| -> BTreeMap<String, Record> {
+            records.into_iter().map(|record| (record.key.clone(), record)).collect()
+        };
+        let old_records = to_map(records_for(table, &old_dat, args));
+        let new_records = to_map(records_for(table, &new_dat, args));
+
+        for (key, old_record) in &old_records {
+            match new_records.get(key) {
+                None => {
+                    println!("- {}", key);
+                    difference_count += 1;
+                }
+                Some(new_record) => {
+                    for (&(name, ref old_value), &(_, ref new_value)) in old_record.fields
+                        .iter()
+                        .zip(new_record.fields.iter()) {
+                        if old_value != new_value {
+                            println!("~ {}.{}: {} -> {}", key, name, old_value, new_value);
+                            difference_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        for key in new_records.keys().filter(|key| !old_records.contains_key(*key)) {
+            println!("+ {}", key);
+            difference_count += 1;
+        }
+    }
+
+    println!("{} differences between {} and {}", difference_count, old_name, new_name);
+    if difference_count > 0 {
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let format_arg = Arg::with_name("format")
+        .short("f")
+        .long("format")
+        .value_name("FORMAT")
+        .possible_values(&["json", "csv"])
+        .help("Output format. Defaults to json.")
+        .takes_value(true);
+    let civ_arg = Arg::with_name("civ")
+        .long("civ")
+        .value_name("CIV_ID")
+        .help("Only include units of the given civilization")
+        .takes_value(true);
+    let dat_arg = Arg::with_name("DAT").required(true).index(1).help("Path to empires.dat");
+
+    let matches = App::new("chariot-dat")
+        .version("1.0")
+        .about("Dumps and compares the contents of Age of Empires (1997) empires.dat files")
+        .subcommand(SubCommand::with_name("units")
+            .about("Dumps every civilization's units")
+            .arg(dat_arg.clone())
+            .arg(civ_arg.clone())
+            .arg(format_arg.clone()))
+        .subcommand(SubCommand::with_name("techs")
+            .about("Dumps all research")
+            .arg(dat_arg.clone())
+            .arg(format_arg.clone()))
+        .subcommand(SubCommand::with_name("civs")
+            .about("Dumps civilizations along with their bonuses")
+            .arg(dat_arg.clone())
+            .arg(format_arg.clone()))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Lists the units, techs, and civs that differ between two files")
+            .arg(Arg::with_name("OLD").required(true).index(1))
+            .arg(Arg::with_name("NEW").required(true).index(2))
+            .arg(civ_arg.clone()))
+        .get_matches();
+
+    match matches.subcommand() {
+        ("diff", Some(args)) => diff(args),
+        (table, Some(args)) => {
+            let empires = load_dat(args.value_of("DAT").unwrap());
+            print_records(&records_for(table, &empires, args),
+                          args.value_of("format").unwrap_or("json"));
+        }
+        _ => fail(matches.usage()),
+    }
+}
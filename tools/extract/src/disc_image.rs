@@ -0,0 +1,177 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::ascii::AsciiExt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const ISO_SECTOR_SIZE: u64 = 2048;
+const PRIMARY_VOLUME_DESCRIPTOR_SECTOR: u64 = 16;
+const ROOT_RECORD_OFFSET: usize = 156;
+
+/// How the 2048 bytes of data in each sector are laid out in an image file: plain ISO images
+/// have nothing else, while raw BIN dumps keep each sector's sync, header, and error correction
+const SECTOR_LAYOUTS: [(u64, u64); 3] = [(2048, 0), (2352, 16), (2352, 24)];
+
+#[derive(Clone, Debug)]
+pub struct DiscEntry {
+    /// Without the `;1` version ISO 9660 adds to file names
+    pub name: String,
+    pub is_dir: bool,
+    extent: u32,
+    size: u32,
+}
+
+/// Reads files out of an ISO 9660 CD image without mounting it
+pub struct DiscImage {
+    file: File,
+    sector_size: u64,
+    data_offset: u64,
+    root: DiscEntry,
+}
+
+impl DiscImage {
+    /// Opens an image, working out its sector layout from where the volume descriptor is
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<DiscImage> {
+        let file = try!(File::open(path));
+        for &(sector_size, data_offset) in &SECTOR_LAYOUTS {
+            let mut image = DiscImage {
+                file: try!(file.try_clone()),
+                sector_size: sector_size,
+                data_offset: data_offset,
+                root: DiscEntry {
+                    name: String::new(),
+                    is_dir: true,
+                    extent: 0,
+                    size: 0,
+                },
+            };
+            let descriptor = match image.read_sectors(PRIMARY_VOLUME_DESCRIPTOR_SECTOR as u32, 1) {
+                Ok(descriptor) => descriptor,
+                Err(_) => continue,
+            };
+            if descriptor[0] == 1 && &descriptor[1..6] == b"CD001" {
+                if let Some((root, _)) = parse_record(&descriptor[ROOT_RECORD_OFFSET..]) {
+                    image.root = root;
+                    return Ok(image);
+                }
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "not an ISO 9660 CD image"))
+    }
+
+    pub fn root(&self) -> DiscEntry {
+        self.root.clone()
+    }
+
+    pub fn read_dir(&mut self, dir: &DiscEntry) -> io::Result<Vec<DiscEntry>> {
+        let data = try!(self.read_entry(dir));
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            if data[offset] == 0 {
+                // Records don't cross sectors, so the rest of this one is padding
+                offset = (offset / ISO_SECTOR_SIZE as usize + 1) * ISO_SECTOR_SIZE as usize;
+                continue;
+            }
+            match parse_record(&data[offset..]) {
+                Some((entry, length)) => {
+                    // The first two records are the directory itself and its parent
+                    if entry.name != "\u{0}" && entry.name != "\u{1}" {
+                        entries.push(entry);
+                    }
+                    offset += length;
+                }
+                None => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Finds an entry by its path from the root, ignoring case
+    pub fn find(&mut self, path: &str) -> io::Result<Option<DiscEntry>> {
+        let mut entry = self.root();
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            if !entry.is_dir {
+                return Ok(None);
+            }
+            let found = try!(self.read_dir(&entry))
+                .into_iter()
+                .find(|child| child.name.eq_ignore_ascii_case(component));
+            entry = match found {
+                Some(child) => child,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(entry))
+    }
+
+    pub fn read_entry(&mut self, entry: &DiscEntry) -> io::Result<Vec<u8>> {
+        let sector_count = (entry.size as u64 + ISO_SECTOR_SIZE - 1) / ISO_SECTOR_SIZE;
+        let mut data = try!(self.read_sectors(entry.extent, sector_count as u32));
+        data.truncate(entry.size as usize);
+        Ok(data)
+    }
+
+    fn read_sectors(&mut self, first_sector: u32, count: u32) -> io::Result<Vec<u8>> {
+        let mut data = vec![0u8; (count as u64 * ISO_SECTOR_SIZE) as usize];
+        if self.sector_size == ISO_SECTOR_SIZE {
+            try!(self.file.seek(SeekFrom::Start(first_sector as u64 * ISO_SECTOR_SIZE)));
+            try!(self.file.read_exact(&mut data));
+        } else {
+            for (index, chunk) in data.chunks_mut(ISO_SECTOR_SIZE as usize).enumerate() {
+                let sector = first_sector as u64 + index as u64;
+                try!(self.file.seek(SeekFrom::Start(sector * self.sector_size + self.data_offset)));
+                try!(self.file.read_exact(chunk));
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Reads a directory record, returning it along with how long it is
+fn parse_record(data: &[u8]) -> Option<(DiscEntry, usize)> {
+    let length = *data.first().unwrap_or(&0) as usize;
+    if length < 34 || data.len() < length {
+        return None;
+    }
+    let name_length = data[32] as usize;
+    if 33 + name_length > length {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&data[33..33 + name_length]).into_owned();
+    let name = match name.find(';') {
+        Some(index) => name[..index].trim_right_matches('.').to_string(),
+        None => name,
+    };
+    let entry = DiscEntry {
+        name: name,
+        is_dir: data[25] & 2 != 0,
+        extent: read_u32(&data[2..6]),
+        size: read_u32(&data[10..14]),
+    };
+    Some((entry, length))
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+}
@@ -0,0 +1,128 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use disc_image::DiscImage;
+
+use std::ascii::AsciiExt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Somewhere the original game's files can be copied from: a directory, such as a mounted CD
+/// or where an installer put the game, or a CD image. The archives inside installers, like the
+/// trial version's, can't be read.
+pub enum InstallSource {
+    Dir(PathBuf),
+    Image(DiscImage),
+}
+
+impl InstallSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<InstallSource> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(InstallSource::Dir(path.to_path_buf()));
+        }
+        let mut magic = [0; 4];
+        let read = try!(File::open(path).and_then(|mut file| file.read(&mut magic)));
+        if magic[..read].starts_with(b"MZ") || magic[..read].starts_with(b"MSCF") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "installers and their archives can't be read; run the installer and \
+                                       give the directory it installed the game to"));
+        }
+        DiscImage::open(path).map(InstallSource::Image)
+    }
+
+    /// Reads a file by its path from the top of the source, ignoring case. Files that aren't
+    /// there are `None` rather than an error.
+    pub fn read(&mut self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        match *self {
+            InstallSource::Dir(ref dir) => {
+                match find_in_dir(dir, path) {
+                    Some(ref file_path) if file_path.is_file() => {
+                        let mut contents = Vec::new();
+                        try!(File::open(file_path).and_then(|mut file| file.read_to_end(&mut contents)));
+                        Ok(Some(contents))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            InstallSource::Image(ref mut image) => {
+                match try!(image.find(path)) {
+                    Some(ref entry) if !entry.is_dir => image.read_entry(entry).map(Some),
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// The names of the files directly in a directory, or nothing if there's no such directory
+    pub fn file_names(&mut self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = match *self {
+            InstallSource::Dir(ref dir) => {
+                let mut names = Vec::new();
+                if let Some(dir) = find_in_dir(dir, path) {
+                    if dir.is_dir() {
+                        for entry in try!(fs::read_dir(dir)) {
+                            let entry = try!(entry);
+                            if entry.path().is_file() {
+                                names.push(entry.file_name().to_string_lossy().into_owned());
+                            }
+                        }
+                    }
+                }
+                names
+            }
+            InstallSource::Image(ref mut image) => {
+                match try!(image.find(path)) {
+                    Some(ref entry) if entry.is_dir => {
+                        try!(image.read_dir(entry))
+                            .into_iter()
+                            .filter(|child| !child.is_dir)
+                            .map(|child| child.name)
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        };
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// Joins a path onto a directory, matching each part of it regardless of case, since CDs are
+/// all upper case and the game asks for lower case names
+fn find_in_dir(dir: &Path, path: &str) -> Option<PathBuf> {
+    let mut full_path = dir.to_path_buf();
+    for component in path.split('/').filter(|component| !component.is_empty()) {
+        let entries = match fs::read_dir(&full_path) {
+            Ok(entries) => entries,
+            Err(_) => return None,
+        };
+        let found = entries.filter_map(|entry| entry.ok())
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(component));
+        match found {
+            Some(entry) => full_path = entry.path(),
+            None => return None,
+        }
+    }
+    Some(full_path)
+}
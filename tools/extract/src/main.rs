@@ -0,0 +1,434 @@
+// Chariot: An open source reimplementation of Age of Empires (1997)
+// Copyright (c) 2016 Kevin Fuller
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+extern crate chariot_drs as drs;
+extern crate chariot_slp as slp;
+extern crate chariot_palette as palette;
+extern crate chariot_scn as scn;
+extern crate chariot_resource as resource;
+
+extern crate png;
+extern crate rustc_serialize;
+
+#[macro_use(value_t)]
+extern crate clap;
+
+mod disc_image;
+mod install_source;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use install_source::InstallSource;
+use png::HasParameters;
+use resource::{GameDir, REQUIRED_FILES};
+use rustc_serialize::json::{self, Json, ToJson};
+
+use std::ascii::AsciiExt;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+const MAIN_PALETTE_ID: u32 = 50500;
+const SPRITE_SHEET_PADDING: u32 = 4;
+const SWATCH_SIZE: u32 = 16;
+
+/// Directories that aren't needed to start the game, but hold everything there is to play
+const OPTIONAL_DIRS: [&'static str; 2] = ["campaign", "scenario"];
+
+const FILE_TYPES: [(drs::DrsFileType, &'static str); 4] = [(drs::DrsFileType::Binary, "bin"),
+                                                          (drs::DrsFileType::Shape, "shp"),
+                                                          (drs::DrsFileType::Slp, "slp"),
+                                                          (drs::DrsFileType::Wav, "wav")];
+
+fn fail<D: Display>(message: D) -> ! {
+    println!("{}", message);
+    process::exit(1);
+}
+
+fn load_drs(file_name: &str) -> drs::DrsFile {
+    drs::DrsFile::read_from_file(file_name)
+        .unwrap_or_else(|err| fail(format!("Failed to load DRS \"{}\": {}", file_name, err)))
+}
+
+fn find_contents<'a>(drs: &'a drs::DrsFile, file_type: drs::DrsFileType, file_id: u32) -> Option<&'a [u8]> {
+    drs.find_table(file_type).and_then(|table| table.find_file_contents(file_id)).map(|contents| &contents[..])
+}
+
+/// Finds a resource in any of the archive's tables, along with the extension for its type
+fn find_resource<'a>(drs: &'a drs::DrsFile, file_id: u32) -> Option<(&'static str, &'a [u8])> {
+    FILE_TYPES.iter()
+        .filter_map(|&(file_type, extension)| {
+            find_contents(drs, file_type, file_id).map(|contents| (extension, contents))
+        })
+        .next()
+}
+
+fn load_palette(interfac_name: &str, palette_id: u32) -> palette::Palette {
+    let interfac = load_drs(interfac_name);
+    let contents = find_contents(&interfac, drs::DrsFileType::Binary, palette_id)
+        .unwrap_or_else(|| fail(format!("Couldn't find palette {} in {}", palette_id, interfac_name)));
+    palette::read_from(&mut io::Cursor::new(contents))
+        .unwrap_or_else(|err| fail(format!("Failed to read palette {}: {}", palette_id, err)))
+}
+
+fn create_file<P: AsRef<Path>>(file_name: P) -> File {
+    File::create(file_name.as_ref())
+        .unwrap_or_else(|err| fail(format!("Failed to create {:?}: {}", file_name.as_ref(), err)))
+}
+
+fn write_png<P: AsRef<Path>>(file_name: P, width: u32, height: u32, rgba: &[u8]) {
+    let mut encoder = png::Encoder::new(create_file(&file_name), width, height);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    let result = encoder.write_header().and_then(|mut writer| writer.write_image_data(rgba));
+    if let Err(err) = result {
+        fail(format!("Failed to write {:?}: {}", file_name.as_ref(), err));
+    }
+}
+
+fn write_json<P: AsRef<Path>>(file_name: Option<P>, value: &Json) {
+    let text = format!("{}\n", json::as_pretty_json(value));
+    let result = match file_name {
+        Some(file_name) => create_file(file_name).write_all(text.as_bytes()),
+        None => io::stdout().write_all(text.as_bytes()),
+    };
+    if let Err(err) = result {
+        fail(format!("Failed to write JSON: {}", err));
+    }
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect::<BTreeMap<_, _>>())
+}
+
+fn list(args: &ArgMatches) {
+    let drs_name = args.value_of("DRS").unwrap();
+    let drs = load_drs(drs_name);
+    println!("{:<6} {:>8} {:>10}", "type", "id", "size");
+    for &(file_type, extension) in &FILE_TYPES {
+        if let Some(table) = drs.find_table(file_type) {
+            for entry in &table.entries {
+                let size = table.find_file_contents(entry.file_id).map(|c| c.len()).unwrap_or(0);
+                println!("{:<6} {:>8} {:>10}", extension, entry.file_id, size);
+            }
+        }
+    }
+}
+
+fn dump(args: &ArgMatches) {
+    let drs_name = args.value_of("DRS").unwrap();
+    let file_id = value_t!(args, "ID", u32).unwrap_or_else(|e| e.exit());
+    let drs = load_drs(drs_name);
+    let (extension, contents) = find_resource(&drs, file_id)
+        .unwrap_or_else(|| fail(format!("Couldn't find resource {} in {}", file_id, drs_name)));
+
+    let output_name = args.value_of("output")
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("{}.{}", file_id, extension));
+    if let Err(err) = create_file(&output_name).write_all(contents) {
+        fail(format!("Failed to write {}: {}", output_name, err));
+    }
+    println!("Wrote {} bytes to {}", contents.len(), output_name);
+}
+
+/// Lays the frames out left to right, and writes the metadata in the same format the game
+/// reads mod overrides in, so the output can be edited and dropped into a mod directly
+fn slp_to_png(args: &ArgMatches) {
+    let drs_name = args.value_of("DRS").unwrap();
+    let slp_id = value_t!(args, "ID", u32).unwrap_or_else(|e| e.exit());
+    let player = if args.is_present("player") {
+        value_t!(args, "player", u8).unwrap_or_else(|e| e.exit())
+    } else {
+        1
+    };
+    let palette = load_palette(args.value_of("interfac").unwrap(), MAIN_PALETTE_ID);
+
+    let drs = load_drs(drs_name);
+    let contents = find_contents(&drs, drs::DrsFileType::Slp, slp_id)
+        .unwrap_or_else(|| fail(format!("Couldn't find SLP {} in {}", slp_id, drs_name)));
+    let slp = slp::SlpFile::read_from(&mut io::Cursor::new(contents), player)
+        .unwrap_or_else(|err| fail(format!("Failed to read SLP {}: {}", slp_id, err)));
+
+    let width = slp.shapes.iter().map(|s| s.header.width + SPRITE_SHEET_PADDING).sum::<u32>();
+    let height = slp.shapes.iter().map(|s| s.header.height).max().unwrap_or(0);
+    let mut rgba = vec![0u8; (width * height * 4) as usize];
+
+    let mut frames = Vec::new();
+    let mut next_x = 0;
+    for shape in &slp.shapes {
+        let (shape_width, shape_height) = (shape.header.width, shape.header.height);
+        for y in 0..shape_height {
+            for x in 0..shape_width {
+                let color_index = shape.pixels[(y * shape_width + x) as usize] as usize;
+                if color_index == 0 {
+                    continue;
+                }
+                let color = &palette[color_index];
+                let dst = (((y * width) + next_x + x) * 4) as usize;
+                rgba[dst..(dst + 4)].copy_from_slice(&[color.r, color.g, color.b, 255]);
+            }
+        }
+        frames.push(object(vec![("x", next_x.to_json()),
+                                ("y", 0.to_json()),
+                                ("w", shape_width.to_json()),
+                                ("h", shape_height.to_json()),
+                                ("center_x", shape.header.center_x.to_json()),
+                                ("center_y", shape.header.center_y.to_json())]));
+        next_x += shape_width + SPRITE_SHEET_PADDING;
+    }
+
+    let output_name = args.value_of("output")
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("{}.png", slp_id));
+    write_png(&output_name, width, height, &rgba);
+    write_json(Some(Path::new(&output_name).with_extension("json")),
+               &object(vec![("scale", 1.to_json()), ("frames", Json::Array(frames))]));
+    println!("Wrote {} frames to {}", slp.shapes.len(), output_name);
+}
+
+fn export_palette(args: &ArgMatches) {
+    let palette_id = if args.is_present("ID") {
+        value_t!(args, "ID", u32).unwrap_or_else(|e| e.exit())
+    } else {
+        MAIN_PALETTE_ID
+    };
+    let palette = load_palette(args.value_of("INTERFAC").unwrap(), palette_id);
+    let output_name = args.value_of("output")
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("{}.gpl", palette_id));
+
+    if output_name.to_lowercase().ends_with(".png") {
+        // 16x16 grid of swatches, in palette order from left to right and top to bottom
+        let size = 16 * SWATCH_SIZE;
+        let mut rgba = vec![0u8; (size * size * 4) as usize];
+        for y in 0..size {
+            for x in 0..size {
+                let color_index = ((y / SWATCH_SIZE) * 16 + x / SWATCH_SIZE) as usize;
+                if let Some(color) = palette.get(color_index) {
+                    let dst = ((y * size + x) * 4) as usize;
+                    rgba[dst..(dst + 4)].copy_from_slice(&[color.r, color.g, color.b, 255]);
+                }
+            }
+        }
+        write_png(&output_name, size, size, &rgba);
+    } else {
+        let mut gpl = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", palette_id);
+        for (index, color) in palette.iter().enumerate() {
+            gpl.push_str(&format!("{:3} {:3} {:3}\tIndex {}\n", color.r, color.g, color.b, index));
+        }
+        if let Err(err) = create_file(&output_name).write_all(gpl.as_bytes()) {
+            fail(format!("Failed to write {}: {}", output_name, err));
+        }
+    }
+    println!("Wrote palette {} to {}", palette_id, output_name);
+}
+
+fn dump_scenario(args: &ArgMatches) {
+    let scenario_name = args.value_of("SCENARIO").unwrap();
+    let scenario = scn::Scenario::read_from_file(scenario_name)
+        .unwrap_or_else(|err| fail(format!("Failed to load scenario \"{}\": {}", scenario_name, err)));
+    let data = &scenario.player_data;
+
+    let players = scenario.player_ids()
+        .iter()
+        .map(|&player_id| {
+            let index = *player_id as usize;
+            let mut fields = vec![("id", index.to_json()),
+                                  ("civilization_id", (*scenario.player_civilization_id(player_id)).to_json())];
+            if index > 0 {
+                fields.push(("name", data.player_names[index - 1].to_json()));
+            }
+            if let Some(resources) = scenario.player_resources(player_id) {
+                fields.push(("resources",
+                             object(vec![("food", resources.food.to_json()),
+                                         ("wood", resources.wood.to_json()),
+                                         ("gold", resources.gold.to_json()),
+                                         ("stone", resources.stone.to_json())])));
+            }
+            let units = scenario.player_units(player_id)
+                .iter()
+                .map(|unit| {
+                    object(vec![("unit_id", (*unit.unit_id).to_json()),
+                                ("spawn_id", unit.spawn_id.map(|id| *id).to_json()),
+                                ("position", vec![unit.position_x, unit.position_y, unit.position_z].to_json()),
+                                ("rotation", unit.rotation.to_json()),
+                                ("state", unit.state.to_json())])
+                })
+                .collect();
+            fields.push(("units", Json::Array(units)));
+            object(fields)
+        })
+        .collect();
+
+    let scenario_json = object(vec![("header_version", scenario.header_version().to_json()),
+                                    ("data_version", scenario.data_version().to_json()),
+                                    ("original_file_name", data.original_file_name.to_json()),
+                                    ("instructions", data.instructions.to_json()),
+                                    ("hints", data.hints.to_json()),
+                                    ("victory", data.victory.to_json()),
+                                    ("loss", data.loss.to_json()),
+                                    ("history", data.history.to_json()),
+                                    ("map_width", scenario.map.width.to_json()),
+                                    ("map_height", scenario.map.height.to_json()),
+                                    ("players", Json::Array(players))]);
+    write_json(args.value_of("output"), &scenario_json);
+}
+
+/// Where the game's own files start in the source: the CD keeps them in a `game` directory,
+/// while installs have them at the top
+fn find_game_root(source: &mut InstallSource) -> Option<&'static str> {
+    for &root in &["", "game/"] {
+        let names = source.file_names(&format!("{}data", root))
+            .unwrap_or_else(|err| fail(format!("Failed to read the source: {}", err)));
+        if names.iter().any(|name| name.eq_ignore_ascii_case("empires.dat")) {
+            return Some(root);
+        }
+    }
+    None
+}
+
+fn write_installed(output_dir: &Path, file_name: &str, contents: &[u8]) {
+    let path = output_dir.join(file_name);
+    if let Some(parent) = path.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            fail(format!("Failed to create {:?}: {}", parent, err));
+        }
+    }
+    if let Err(err) = create_file(&path).write_all(contents) {
+        fail(format!("Failed to write {:?}: {}", path, err));
+    }
+}
+
+/// Copies the files the game needs out of the original CD, an image of it, or an installed
+/// copy, then checks them the same way the game does when it starts
+fn install(args: &ArgMatches) {
+    let source_name = args.value_of("SOURCE").unwrap();
+    let output_dir = Path::new(args.value_of("output").unwrap_or("game"));
+    let mut source = InstallSource::open(source_name).unwrap_or_else(|err| {
+        fail(format!("Failed to open \"{}\": {}. The source has to be a directory or an ISO or BIN CD \
+                      image.",
+                     source_name,
+                     err))
+    });
+    let game_root = find_game_root(&mut source)
+        .unwrap_or_else(|| fail(format!("Couldn't find data/empires.dat in \"{}\"", source_name)));
+
+    let mut missing = Vec::new();
+    for file_name in REQUIRED_FILES.iter() {
+        let contents = source.read(&format!("{}{}", game_root, file_name))
+            .unwrap_or_else(|err| fail(format!("Failed to read {}: {}", file_name, err)));
+        match contents {
+            Some(contents) => write_installed(output_dir, file_name, &contents),
+            None => missing.push(*file_name),
+        }
+    }
+    if !missing.is_empty() {
+        fail(format!("\"{}\" is missing {}", source_name, missing.join(", ")));
+    }
+
+    let mut optional_count = 0;
+    for dir in &OPTIONAL_DIRS {
+        let dir_path = format!("{}{}", game_root, dir);
+        let names = source.file_names(&dir_path)
+            .unwrap_or_else(|err| fail(format!("Failed to read the {} directory: {}", dir, err)));
+        for name in names {
+            let contents = source.read(&format!("{}/{}", dir_path, name))
+                .unwrap_or_else(|err| fail(format!("Failed to read {}/{}: {}", dir, name, err)));
+            if let Some(contents) = contents {
+                write_installed(output_dir, &format!("{}/{}", dir, name.to_lowercase()), &contents);
+                optional_count += 1;
+            }
+        }
+    }
+
+    if let Err(err) = GameDir::new(output_dir) {
+        fail(format!("The copied game data isn't usable: {}", err));
+    }
+    println!("Installed {} game files and {} scenarios and campaigns to {:?}",
+             REQUIRED_FILES.len(),
+             optional_count,
+             output_dir);
+}
+
+fn main() {
+    let output_arg = Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("FILE")
+        .takes_value(true);
+
+    let matches = App::new("chariot-extract")
+        .version("1.0")
+        .about("Inspects and extracts assets from Age of Empires (1997)")
+        .subcommand(SubCommand::with_name("list")
+            .about("Lists every resource in a DRS archive")
+            .arg(Arg::with_name("DRS").required(true).index(1)))
+        .subcommand(SubCommand::with_name("dump")
+            .about("Writes out the raw contents of a resource in a DRS archive")
+            .arg(Arg::with_name("DRS").required(true).index(1))
+            .arg(Arg::with_name("ID").required(true).index(2))
+            .arg(output_arg.clone().help("Defaults to <ID>.<type>")))
+        .subcommand(SubCommand::with_name("slp")
+            .about("Converts an SLP to a PNG sprite sheet, with its frames described in a JSON file \
+                    next to it")
+            .arg(Arg::with_name("DRS").required(true).index(1))
+            .arg(Arg::with_name("ID").required(true).index(2))
+            .arg(Arg::with_name("interfac")
+                .short("i")
+                .long("interfac")
+                .value_name("INTERFAC")
+                .help("Location of interfac.drs (to get the palette from)")
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::with_name("player")
+                .short("p")
+                .long("player")
+                .value_name("PLAYER")
+                .help("Player color base index, from 1 to 8. Defaults to 1.")
+                .takes_value(true))
+            .arg(output_arg.clone().help("Defaults to <ID>.png")))
+        .subcommand(SubCommand::with_name("palette")
+            .about("Exports a palette as a GIMP palette, or a PNG of swatches if the output ends in .png")
+            .arg(Arg::with_name("INTERFAC").required(true).index(1))
+            .arg(Arg::with_name("ID").index(2).help("Defaults to the main palette, 50500"))
+            .arg(output_arg.clone().help("Defaults to <ID>.gpl")))
+        .subcommand(SubCommand::with_name("scenario")
+            .about("Dumps a scenario to JSON")
+            .arg(Arg::with_name("SCENARIO").required(true).index(1))
+            .arg(output_arg.clone().help("Defaults to standard output")))
+        .subcommand(SubCommand::with_name("install")
+            .about("Copies the game data Chariot needs out of the original CD, an ISO or BIN image of \
+                    it, or an installed copy")
+            .arg(Arg::with_name("SOURCE").required(true).index(1))
+            .arg(output_arg.clone().value_name("DIR").help("Defaults to \"game\"")))
+        .get_matches();
+
+    match matches.subcommand() {
+        ("list", Some(args)) => list(args),
+        ("dump", Some(args)) => dump(args),
+        ("slp", Some(args)) => slp_to_png(args),
+        ("palette", Some(args)) => export_palette(args),
+        ("scenario", Some(args)) => dump_scenario(args),
+        ("install", Some(args)) => install(args),
+        _ => fail(matches.usage()),
+    }
+}